@@ -122,7 +122,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let auto_save_status = editor_plugin.get_auto_save_status(session_id).await?;
     println!(
         "✓ Auto-save status: enabled={}, dirty={}",
-        auto_save_status.enabled, auto_save_status.is_dirty
+        auto_save_status.is_enabled(),
+        auto_save_status.is_dirty()
     );
 
     // Close session
@@ -75,74 +75,92 @@ impl CursorManager {
         self.rendered_position
     }
 
+    /// Rendered position showing `raw_pos`, interpolated between the
+    /// nearest mapped positions; none before anything was rendered
     pub fn map_raw_to_rendered(&self, raw_pos: usize) -> Option<usize> {
-        if let Some(mapping) = self
-            .position_mappings
-            .iter()
-            .find(|m| m.raw_position == raw_pos)
-        {
-            return Some(mapping.rendered_position);
+        if self.rendered_content_length == 0 {
+            return None;
         }
 
-        for element_mapping in self.element_mappings.values() {
-            if element_mapping.raw_range.contains(raw_pos) {
-                let relative_pos = raw_pos - element_mapping.raw_range.start;
-                let raw_element_length = element_mapping.raw_range.len();
-                let rendered_element_length = element_mapping.rendered_range.len();
-
-                if raw_element_length == 0 {
-                    return Some(element_mapping.rendered_range.start);
-                }
-
-                let ratio = relative_pos as f64 / raw_element_length as f64;
-                let rendered_offset = (ratio * rendered_element_length as f64) as usize;
+        let anchors = self.anchors();
+        let Some(next) = anchors.iter().position(|anchor| anchor.0 >= raw_pos) else {
+            return anchors.last().map(|anchor| anchor.1);
+        };
+        let (raw_end, rendered_end) = anchors[next];
+        if raw_end == raw_pos || next == 0 {
+            return Some(rendered_end);
+        }
+        let (raw_start, rendered_start) = anchors[next - 1];
+        Some(
+            rendered_start
+                + scale(
+                    raw_pos - raw_start,
+                    rendered_end - rendered_start,
+                    raw_end - raw_start,
+                ),
+        )
+    }
 
-                return Some(element_mapping.rendered_range.start + rendered_offset);
-            }
+    /// First raw position shown at `rendered_pos`, so that mapping it back
+    /// gives `rendered_pos` again
+    pub fn map_rendered_to_raw(&self, rendered_pos: usize) -> usize {
+        if self.rendered_content_length == 0 {
+            return rendered_pos.min(self.raw_content_length);
         }
 
-        if self.raw_content_length > 0 && self.rendered_content_length > 0 {
-            let ratio = raw_pos as f64 / self.raw_content_length as f64;
-            let estimated_pos = (ratio * self.rendered_content_length as f64) as usize;
-            Some(estimated_pos.min(self.rendered_content_length))
-        } else {
-            None
+        let anchors = self.anchors();
+        let Some(next) = anchors.iter().position(|anchor| anchor.1 >= rendered_pos) else {
+            return anchors.last().map_or(0, |anchor| anchor.0);
+        };
+        let (raw_end, rendered_end) = anchors[next];
+        if rendered_end == rendered_pos || next == 0 {
+            return raw_end;
         }
+        let (raw_start, rendered_start) = anchors[next - 1];
+        let rendered_span = rendered_end - rendered_start;
+        let raw_offset = scale(
+            rendered_pos - rendered_start,
+            raw_end - raw_start,
+            rendered_span,
+        );
+        // Rounded up, the first raw position mapping to `rendered_pos`
+        let raw_offset = if scale(raw_offset, rendered_span, raw_end - raw_start)
+            < rendered_pos - rendered_start
+        {
+            raw_offset + 1
+        } else {
+            raw_offset
+        };
+        raw_start + raw_offset
     }
 
-    pub fn map_rendered_to_raw(&self, rendered_pos: usize) -> usize {
-        if let Some(mapping) = self
+    /// Mapped positions as `(raw, rendered)` pairs, from the start to the end
+    /// of the content, increasing in both
+    ///
+    /// Elements may overlap or be rendered out of order; the mappings that
+    /// would make the positions go back, or map one raw position twice, are
+    /// left out so that mapping stays monotonic and invertible.
+    fn anchors(&self) -> Vec<(usize, usize)> {
+        let mut anchors: Vec<(usize, usize)> = Vec::new();
+        let mappings = self
             .position_mappings
             .iter()
-            .find(|m| m.rendered_position == rendered_pos)
-        {
-            return mapping.raw_position;
-        }
-
-        for element_mapping in self.element_mappings.values() {
-            if element_mapping.rendered_range.contains(rendered_pos) {
-                let relative_pos = rendered_pos - element_mapping.rendered_range.start;
-                let rendered_element_length = element_mapping.rendered_range.len();
-                let raw_element_length = element_mapping.raw_range.len();
-
-                if rendered_element_length == 0 {
-                    return element_mapping.raw_range.start;
+            .map(|mapping| (mapping.raw_position, mapping.rendered_position))
+            .chain([(self.raw_content_length, self.rendered_content_length)]);
+        for (raw, rendered) in mappings {
+            if raw > self.raw_content_length || rendered > self.rendered_content_length {
+                continue;
+            }
+            match anchors.last() {
+                Some(&(last_raw, last_rendered)) if raw <= last_raw || rendered < last_rendered => {
+                    continue
                 }
-
-                let ratio = relative_pos as f64 / rendered_element_length as f64;
-                let raw_offset = (ratio * raw_element_length as f64) as usize;
-
-                return element_mapping.raw_range.start + raw_offset;
+                None if raw > 0 => anchors.push((0, 0)),
+                _ => {}
             }
+            anchors.push((raw, rendered));
         }
-
-        if self.rendered_content_length > 0 && self.raw_content_length > 0 {
-            let ratio = rendered_pos as f64 / self.rendered_content_length as f64;
-            let estimated_pos = (ratio * self.raw_content_length as f64) as usize;
-            estimated_pos.min(self.raw_content_length)
-        } else {
-            rendered_pos.min(self.raw_content_length)
-        }
+        anchors
     }
 
     pub fn update_element_mappings(
@@ -198,21 +216,24 @@ impl CursorManager {
         _old_content: &str,
         new_content: &str,
     ) {
-        let change_delta =
-            new_content.len() as i32 - (change_range.end - change_range.start) as i32;
-        self.raw_content_length = (self.raw_content_length as i32 + change_delta) as usize;
+        // Positions after the change move with the text following it
+        let inserted_end = change_range.start + new_content.len();
+        let shift = |position: usize| position - change_range.end + inserted_end;
+        self.raw_content_length = (self.raw_content_length + new_content.len())
+            .saturating_sub(change_range.len())
+            .max(inserted_end);
 
         if self.raw_position >= change_range.end {
-            self.raw_position = (self.raw_position as i32 + change_delta) as usize;
+            self.raw_position = shift(self.raw_position);
         } else if self.raw_position >= change_range.start {
-            self.raw_position = change_range.start + new_content.len();
+            self.raw_position = inserted_end;
         }
 
         let mut updated_mappings = HashMap::new();
         for (id, mut mapping) in self.element_mappings.drain() {
             if mapping.raw_range.start >= change_range.end {
-                mapping.raw_range.start = (mapping.raw_range.start as i32 + change_delta) as usize;
-                mapping.raw_range.end = (mapping.raw_range.end as i32 + change_delta) as usize;
+                mapping.raw_range.start = shift(mapping.raw_range.start);
+                mapping.raw_range.end = shift(mapping.raw_range.end);
                 updated_mappings.insert(id, mapping);
             } else if mapping.raw_range.end <= change_range.start {
                 updated_mappings.insert(id, mapping);
@@ -222,7 +243,7 @@ impl CursorManager {
 
         self.position_mappings.retain_mut(|mapping| {
             if mapping.raw_position >= change_range.end {
-                mapping.raw_position = (mapping.raw_position as i32 + change_delta) as usize;
+                mapping.raw_position = shift(mapping.raw_position);
                 true
             } else {
                 mapping.raw_position < change_range.start
@@ -316,6 +337,15 @@ impl Default for CursorManager {
         Self::new()
     }
 }
+
+/// `value * numerator / denominator`, rounded down, without overflowing
+fn scale(value: usize, numerator: usize, denominator: usize) -> usize {
+    if denominator == 0 {
+        return 0;
+    }
+    (value as u128 * numerator as u128 / denominator as u128) as usize
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -430,4 +460,161 @@ mod tests {
         assert_eq!(stats.raw_content_length, 100);
         assert_eq!(stats.rendered_content_length, 150);
     }
+
+    mod properties {
+        use super::*;
+        use crate::editor_state::CursorPosition;
+        use crate::inline_renderer::{InlineRenderer, MarkdownInlineRenderer};
+        use crate::syntax_parser::{MarkdownSyntaxParser, SyntaxParser};
+        use proptest::prelude::*;
+
+        fn document() -> impl Strategy<Value = String> {
+            let piece = prop_oneof![
+                "[a-z]{1,8}",
+                Just(" ".to_string()),
+                Just("\n".to_string()),
+                "\\*\\*[a-z]{1,6}\\*\\*",
+                "\\*[a-z]{1,6}\\*",
+                "`[a-z]{1,6}`",
+                "\\[[a-z]{1,6}\\]\\(https://[a-z]{1,6}\\)",
+                "\n#{1,3} [a-z ]{1,10}\n",
+                "\n- [a-z ]{1,10}\n",
+            ];
+            prop::collection::vec(piece, 0..24).prop_map(|pieces| pieces.concat())
+        }
+
+        /// Edits as (start, end, inserted text), with positions as fractions
+        /// of the length of the document at the time of the edit
+        fn edits() -> impl Strategy<Value = Vec<(f64, f64, String)>> {
+            prop::collection::vec((0.0..=1.0f64, 0.0..=1.0f64, "[a-z*# ]{0,6}"), 1..8)
+        }
+
+        /// Manager mapped the way the live editor maps a document
+        fn mapped(content: &str) -> CursorManager {
+            let parser = MarkdownSyntaxParser::new();
+            let renderer = MarkdownInlineRenderer::new();
+            let elements = parser.parse_document(content);
+            let rendered =
+                renderer.render_elements_with_cursor(&elements, &CursorPosition::new(0, 0, 0));
+            let html: String = rendered.iter().map(|r| r.html.as_str()).collect();
+
+            let mut manager = CursorManager::new();
+            manager.update_element_mappings(&elements, &rendered, content, &html);
+            manager
+        }
+
+        /// Every raw position maps into the rendered content, monotonically,
+        /// and back to the first raw position showing the same rendered one
+        fn assert_consistent(manager: &CursorManager) {
+            if manager.rendered_content_length == 0 {
+                return;
+            }
+            let mut previous = 0;
+            for raw in 0..=manager.raw_content_length {
+                let rendered = manager.map_raw_to_rendered(raw).unwrap();
+                assert!(rendered <= manager.rendered_content_length);
+                assert!(rendered >= previous, "mapping goes back at {}", raw);
+                previous = rendered;
+
+                let back = manager.map_rendered_to_raw(rendered);
+                assert!(back <= raw, "{} maps back to {}", raw, back);
+                assert_eq!(manager.map_raw_to_rendered(back), Some(rendered));
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn mapping_of_rendered_documents_is_invertible(content in document()) {
+                assert_consistent(&mapped(&content));
+            }
+
+            #[test]
+            fn mapping_of_arbitrary_elements_is_invertible(
+                raw_length in 0usize..200,
+                rendered_length in 0usize..400,
+                ranges in prop::collection::vec(
+                    (0usize..250, 0usize..50, 0usize..450, 0usize..90),
+                    0..12,
+                ),
+            ) {
+                // Overlapping, out of order or out of bounds, as a renderer may
+                // report them
+                let (syntax, rendered): (Vec<_>, Vec<_>) = ranges
+                    .iter()
+                    .map(|&(raw, raw_span, html, html_span)| {
+                        (
+                            SyntaxElement::new(
+                                SyntaxElementType::Bold,
+                                PositionRange::new(raw, raw + raw_span),
+                                String::new(),
+                                String::new(),
+                            ),
+                            RenderedElement::new(
+                                String::new(),
+                                vec![],
+                                String::new(),
+                                (html, html + html_span),
+                            ),
+                        )
+                    })
+                    .unzip();
+                let mut manager = CursorManager::new();
+                manager.update_element_mappings(
+                    &syntax,
+                    &rendered,
+                    &"x".repeat(raw_length),
+                    &"x".repeat(rendered_length),
+                );
+                assert_consistent(&manager);
+            }
+
+            #[test]
+            fn mapping_stays_consistent_through_edits(
+                content in document(),
+                edits in edits(),
+            ) {
+                let mut manager = mapped(&content);
+                let mut content = content;
+                manager.raw_position = content.len() / 2;
+
+                for (from, to, text) in edits {
+                    let (from, to) = (from.min(to), from.max(to));
+                    let change = PositionRange::new(
+                        (from * content.len() as f64) as usize,
+                        (to * content.len() as f64) as usize,
+                    );
+                    let before = manager.clone();
+                    let anchors = before.anchors();
+                    let old = content[change.start..change.end].to_string();
+                    content.replace_range(change.start..change.end, &text);
+                    manager.handle_content_change(&change, &old, &text);
+
+                    prop_assert_eq!(manager.raw_content_length, content.len());
+                    prop_assert!(manager.raw_position <= content.len());
+                    assert_consistent(&manager);
+
+                    // Mapping before the edit is kept, and after it moves with
+                    // the text; only the span around the edit is interpolated anew
+                    if let Some(kept) = anchors.iter().rev().find(|a| a.0 < change.start) {
+                        for raw in 0..=kept.0 {
+                            prop_assert_eq!(
+                                manager.map_raw_to_rendered(raw),
+                                before.map_raw_to_rendered(raw)
+                            );
+                        }
+                    }
+                    // The start of the content stays put, whatever is inserted there
+                    if let Some(moved) = anchors.iter().find(|a| a.0 >= change.end.max(1)) {
+                        for raw in moved.0..=before.raw_content_length {
+                            let shifted = raw - change.end + change.start + text.len();
+                            prop_assert_eq!(
+                                manager.map_raw_to_rendered(shifted),
+                                before.map_raw_to_rendered(raw)
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,110 @@
+//! Windows of documents too large to load
+//!
+//! A session of a document at least as large as the `large_files` threshold
+//! does not load it. The page reads it a [`DocumentWindow`] at a time as it
+//! scrolls instead, each starting where the previous one ended. Windows end
+//! with a whole line, or, for a line longer than the window, with a whole
+//! character, so every window is text of its own.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// A piece of a document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentWindow {
+    /// Byte offset of the window in the document
+    pub offset: u64,
+    /// Byte offset the window ends at, where the next one starts
+    pub end: u64,
+    /// Size of the whole document in bytes
+    pub total_bytes: u64,
+    pub text: String,
+}
+
+impl DocumentWindow {
+    /// Whether the window reaches the end of the document
+    pub fn is_last(&self) -> bool {
+        self.end >= self.total_bytes
+    }
+}
+
+/// Whether `byte` continues a UTF-8 character
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+/// Read up to `max_bytes` of the file at `path` from `offset`
+///
+/// An offset inside a character moves on to the start of the next one.
+pub fn read_window(path: &Path, offset: u64, max_bytes: usize) -> io::Result<DocumentWindow> {
+    let mut file = File::open(path)?;
+    let total_bytes = file.metadata()?.len();
+    let offset = offset.min(total_bytes);
+    file.seek(SeekFrom::Start(offset))?;
+    let mut bytes = Vec::with_capacity(max_bytes);
+    file.take(max_bytes as u64).read_to_end(&mut bytes)?;
+
+    let start = bytes
+        .iter()
+        .take_while(|&&byte| is_continuation(byte))
+        .count();
+    let end = if offset + bytes.len() as u64 >= total_bytes {
+        bytes.len()
+    } else {
+        match bytes[start..].iter().rposition(|&byte| byte == b'\n') {
+            Some(newline) => start + newline + 1,
+            // A line longer than the window is cut between characters
+            None => match std::str::from_utf8(&bytes[start..]) {
+                Err(e) if e.error_len().is_none() => start + e.valid_up_to(),
+                _ => bytes.len(),
+            },
+        }
+    };
+
+    Ok(DocumentWindow {
+        offset: offset + start as u64,
+        end: offset + end as u64,
+        total_bytes,
+        text: String::from_utf8_lossy(&bytes[start..end]).into_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_windows_follow_each_other_on_line_boundaries() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("big.log");
+        let document = "first line\nsecond line\nthird line\n".repeat(100) + "last";
+        std::fs::write(&path, &document).unwrap();
+
+        let mut offset = 0;
+        let mut text = String::new();
+        loop {
+            let window = read_window(&path, offset, 64).unwrap();
+            assert_eq!(window.offset, offset);
+            assert!(window.text.ends_with('\n') || window.is_last());
+            text.push_str(&window.text);
+            offset = window.end;
+            if window.is_last() {
+                break;
+            }
+        }
+        assert_eq!(text, document);
+
+        // A line longer than the window is cut between characters
+        std::fs::write(&path, "é".repeat(100) + "\nshort\n").unwrap();
+        let window = read_window(&path, 0, 7).unwrap();
+        assert_eq!(window.text, "ééé");
+        let next = read_window(&path, window.end, 7).unwrap();
+        assert_eq!((next.offset, next.text.as_str()), (6, "ééé"));
+        // and an offset inside a character moves on to the next one
+        let window = read_window(&path, 1, 7).unwrap();
+        assert_eq!((window.offset, window.text.as_str()), (2, "ééé"));
+    }
+}
@@ -1,6 +1,7 @@
 //! Editor state management with cursor tracking and dirty state
 
 use serde::{Deserialize, Serialize};
+use std::ops::Range;
 use std::time::SystemTime;
 use uuid::Uuid;
 
@@ -156,6 +157,35 @@ impl Default for CursorPosition {
     }
 }
 
+/// Replacement of the bytes `start..end` of the content with `text`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl TextEdit {
+    /// Insert `text` at byte offset `at`
+    pub fn insert(at: usize, text: impl Into<String>) -> Self {
+        Self::replace(at, at, text)
+    }
+
+    /// Delete the bytes `start..end`
+    pub fn delete(start: usize, end: usize) -> Self {
+        Self::replace(start, end, String::new())
+    }
+
+    /// Replace the bytes `start..end` with `text`
+    pub fn replace(start: usize, end: usize, text: impl Into<String>) -> Self {
+        Self {
+            start,
+            end,
+            text: text.into(),
+        }
+    }
+}
+
 /// Complete editor state for a session
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EditorState {
@@ -216,6 +246,56 @@ impl EditorState {
         self.cursor_position.update_absolute(&self.content);
     }
 
+    /// Apply `edits` in order, each against the content left by the ones
+    /// before it, as a single content update. Nothing changes if an edit is
+    /// out of range or splits a character. Returns the byte range of the new
+    /// content the batch touched, or `None` for an empty batch
+    pub fn apply_edits(&mut self, edits: &[TextEdit]) -> Result<Option<Range<usize>>, String> {
+        let mut content = self.content.clone();
+        let mut cursor = self.cursor_position.absolute;
+        let mut changed: Option<Range<usize>> = None;
+
+        for (index, edit) in edits.iter().enumerate() {
+            if edit.start > edit.end
+                || edit.end > content.len()
+                || !content.is_char_boundary(edit.start)
+                || !content.is_char_boundary(edit.end)
+            {
+                return Err(format!(
+                    "Edit {} has invalid range {}..{} for content length {}",
+                    index,
+                    edit.start,
+                    edit.end,
+                    content.len()
+                ));
+            }
+            content.replace_range(edit.start..edit.end, &edit.text);
+            let new_end = edit.start + edit.text.len();
+
+            // Keep the cursor on the text it was on
+            if cursor >= edit.end {
+                cursor = cursor - edit.end + new_end;
+            } else if cursor > edit.start {
+                cursor = new_end;
+            }
+
+            changed = Some(match changed {
+                None => edit.start..new_end,
+                Some(range) if range.end >= edit.end => {
+                    range.start.min(edit.start)..range.end - edit.end + new_end
+                }
+                Some(range) => range.start.min(edit.start)..new_end,
+            });
+        }
+
+        if changed.is_some() {
+            self.update_content(content);
+            self.cursor_position.absolute = cursor;
+            self.cursor_position.update_line_column(&self.content);
+        }
+        Ok(changed)
+    }
+
     /// Update cursor position with validation
     pub fn update_cursor_position(&mut self, position: CursorPosition) -> Result<(), String> {
         if !position.is_valid_for_content(&self.content) {
@@ -416,4 +496,34 @@ mod tests {
         state.auto_save_timer = Some(SystemTime::now() - std::time::Duration::from_secs(3));
         assert!(state.should_auto_save());
     }
+
+    #[test]
+    fn test_apply_edits_as_one_update() {
+        let mut state = EditorState::new(Uuid::new_v4(), "hello world\nsecond line".to_string());
+        state.cursor_position = CursorPosition::new(1, 0, 12);
+
+        let changed = state
+            .apply_edits(&[
+                TextEdit::insert(0, "# "),
+                TextEdit::replace(8, 13, "rune"),
+                TextEdit::delete(12, 13),
+                TextEdit::insert(12, " ✨\n"),
+            ])
+            .unwrap();
+        assert_eq!(state.content, "# hello rune ✨\nsecond line");
+        assert_eq!(changed, Some(0..17));
+        assert!(state.is_dirty);
+        // The cursor stays at the start of the second line
+        assert_eq!(state.cursor_position, CursorPosition::new(1, 0, 17));
+
+        // A bad edit anywhere in the batch leaves the content untouched
+        let before = state.content.clone();
+        assert!(state
+            .apply_edits(&[TextEdit::insert(0, "x"), TextEdit::delete(14, 15)])
+            .is_err());
+        assert!(state.apply_edits(&[TextEdit::delete(5, 100)]).is_err());
+        assert_eq!(state.content, before);
+
+        assert_eq!(state.apply_edits(&[]).unwrap(), None);
+    }
 }
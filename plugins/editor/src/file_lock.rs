@@ -0,0 +1,168 @@
+//! Advisory locks of documents with unsaved changes
+//!
+//! While a session has unsaved changes, Rune holds a lock file next to its
+//! document, `.<name>.rune-lock`, locked with `flock` so a lock left behind
+//! by a process that died is free again. Before taking it, the locks other
+//! editors leave are looked for: Vim's swap file, Emacs' `.#<name>` link and
+//! the `.~lock.<name>#` file of LibreOffice. A document locked by someone
+//! else opens read-only instead of being silently clobbered.
+
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A lock on a document held by someone else
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockHolder {
+    /// Editor holding the lock, like `Vim`
+    pub editor: String,
+    /// File the lock was found in
+    pub lock_file: PathBuf,
+}
+
+impl std::fmt::Display for LockHolder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is editing it ({})",
+            self.editor,
+            self.lock_file.display()
+        )
+    }
+}
+
+/// Lock files other editors keep while they edit a file named `name`
+fn foreign_lock_files(name: &str) -> [(&'static str, String); 4] {
+    [
+        ("Vim", format!(".{}.swp", name)),
+        ("Vim", format!(".{}.swo", name)),
+        ("Emacs", format!(".#{}", name)),
+        ("LibreOffice", format!(".~lock.{}#", name)),
+    ]
+}
+
+/// The lock another editor holds on `path`, if any
+pub fn foreign_lock(path: &Path) -> Option<LockHolder> {
+    let name = path.file_name()?.to_string_lossy();
+    let directory = path.parent().unwrap_or(Path::new(""));
+    foreign_lock_files(&name)
+        .into_iter()
+        .map(|(editor, file)| (editor, directory.join(file)))
+        // Emacs' lock is a symlink to nowhere
+        .find(|(_, lock_file)| fs::symlink_metadata(lock_file).is_ok())
+        .map(|(editor, lock_file)| LockHolder {
+            editor: editor.to_string(),
+            lock_file,
+        })
+}
+
+/// Rune's lock file of `path`
+fn rune_lock_file(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.parent()
+        .unwrap_or(Path::new(""))
+        .join(format!(".{}.rune-lock", name))
+}
+
+/// Who holds a lock on `path`: another editor or another Rune
+pub fn lock_holder(path: &Path) -> Option<LockHolder> {
+    if let Some(holder) = foreign_lock(path) {
+        return Some(holder);
+    }
+    let lock_file = rune_lock_file(path);
+    let file = File::open(&lock_file).ok()?;
+    match file.try_lock_shared() {
+        Err(TryLockError::WouldBlock) => Some(LockHolder {
+            editor: "Another Rune".to_string(),
+            lock_file,
+        }),
+        _ => None,
+    }
+}
+
+/// A held lock on a document, released when dropped
+#[derive(Debug)]
+pub struct DocumentLock {
+    /// Locked lock file, none for documents in directories Rune cannot write
+    held: Option<(File, PathBuf)>,
+}
+
+impl DocumentLock {
+    /// Lock `path`, or tell who holds a lock on it
+    pub fn acquire(path: &Path) -> std::result::Result<Self, LockHolder> {
+        if let Some(holder) = foreign_lock(path) {
+            return Err(holder);
+        }
+
+        let lock_file = rune_lock_file(path);
+        let mut file = match OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_file)
+        {
+            Ok(file) => file,
+            Err(e) => {
+                tracing::debug!("Editing {} unlocked: {}", path.display(), e);
+                return Ok(Self { held: None });
+            }
+        };
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                return Err(LockHolder {
+                    editor: "Another Rune".to_string(),
+                    lock_file,
+                })
+            }
+            // Filesystems without flock keep the lock file alone
+            Err(TryLockError::Error(e)) => {
+                tracing::debug!("Cannot flock {}: {}", lock_file.display(), e);
+            }
+        }
+        let _ = file.set_len(0);
+        let _ = writeln!(file, "{}", std::process::id());
+        Ok(Self {
+            held: Some((file, lock_file)),
+        })
+    }
+}
+
+impl Drop for DocumentLock {
+    fn drop(&mut self) {
+        if let Some((file, lock_file)) = &self.held {
+            // Removed while still locked, so no one locks the doomed file
+            let _ = fs::remove_file(lock_file);
+            let _ = file.unlock();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_locks_are_exclusive_and_respect_other_editors() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("notes.md");
+        fs::write(&path, "# Notes").unwrap();
+
+        let lock = DocumentLock::acquire(&path).unwrap();
+        let holder = DocumentLock::acquire(&path).unwrap_err();
+        assert_eq!(holder.editor, "Another Rune");
+        assert_eq!(lock_holder(&path), Some(holder));
+        drop(lock);
+        assert_eq!(lock_holder(&path), None);
+        assert!(!temp_dir.path().join(".notes.md.rune-lock").exists());
+
+        let lock = DocumentLock::acquire(&path).unwrap();
+        drop(lock);
+
+        fs::write(temp_dir.path().join(".notes.md.swp"), "").unwrap();
+        let holder = DocumentLock::acquire(&path).unwrap_err();
+        assert_eq!(holder.editor, "Vim");
+        assert_eq!(foreign_lock(&path), Some(holder));
+    }
+}
@@ -2,10 +2,11 @@
 
 use crate::EditorError;
 use async_trait::async_trait;
-use rune_core::Result;
+use rune_core::safe_write::{self, WriteStrategy};
+use rune_core::{LineEnding, Result};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 use tokio::fs;
 use tokio::sync::RwLock;
@@ -99,6 +100,8 @@ pub struct FileSyncManager {
     backup_dir: PathBuf,
     /// File metadata cache for change detection
     file_metadata: Arc<RwLock<std::collections::HashMap<PathBuf, FileMetadata>>>,
+    /// How files are written
+    write_strategy: Mutex<WriteStrategy>,
 }
 
 /// Metadata for tracking file changes
@@ -119,9 +122,31 @@ impl FileSyncManager {
         Self {
             backup_dir,
             file_metadata: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            write_strategy: Mutex::new(WriteStrategy::default()),
         }
     }
 
+    /// Write files in place instead of replacing them, or the other way
+    /// round
+    pub fn set_write_strategy(&self, strategy: WriteStrategy) {
+        *self.write_strategy.lock().unwrap() = strategy;
+    }
+
+    /// How files are written
+    pub fn write_strategy(&self) -> WriteStrategy {
+        *self.write_strategy.lock().unwrap()
+    }
+
+    /// Write `content` to `path` with the write strategy, so a save that
+    /// dies halfway leaves the previous content
+    async fn write_file(&self, path: &Path, content: &str) -> std::io::Result<()> {
+        let (path, content) = (path.to_path_buf(), content.as_bytes().to_vec());
+        let strategy = self.write_strategy();
+        tokio::task::spawn_blocking(move || safe_write::write_file(&path, &content, strategy))
+            .await
+            .map_err(std::io::Error::other)?
+    }
+
     /// Initialize the sync manager
     pub async fn initialize(&self) -> Result<()> {
         // Create backup directory if it doesn't exist
@@ -142,7 +167,7 @@ impl FileSyncManager {
     }
 
     /// Get backup file path for a session
-    fn get_backup_path(&self, session_id: Uuid) -> PathBuf {
+    pub(crate) fn get_backup_path(&self, session_id: Uuid) -> PathBuf {
         self.backup_dir.join(format!("{}.backup", session_id))
     }
 
@@ -254,10 +279,13 @@ impl FileSync for FileSyncManager {
         drop(cache);
 
         if has_changed {
-            // Read new content
-            let new_content = fs::read_to_string(file_path).await.map_err(|e| {
+            // Read new content, transcoded like files opened in sessions
+            let bytes = fs::read(file_path).await.map_err(|e| {
                 EditorError::FileOperationFailed(format!("Failed to read file: {}", e))
             })?;
+            let (new_content, _) = rune_core::text_encoding::decode(&bytes)
+                .ok_or_else(|| EditorError::BinaryFile(file_path.to_path_buf()))?;
+            let new_content = LineEnding::normalize(&new_content).into_owned();
 
             Ok(Some(ExternalChange {
                 file_path: file_path.to_path_buf(),
@@ -315,7 +343,7 @@ impl FileSync for FileSyncManager {
     async fn store_local_backup(&self, session_id: Uuid, content: &str) -> Result<()> {
         let backup_path = self.get_backup_path(session_id);
 
-        fs::write(&backup_path, content).await.map_err(|e| {
+        self.write_file(&backup_path, content).await.map_err(|e| {
             EditorError::FileOperationFailed(format!("Failed to write backup: {}", e))
         })?;
 
@@ -365,7 +393,7 @@ impl FileSync for FileSyncManager {
         }
 
         // Write content to file
-        fs::write(file_path, content).await.map_err(|e| {
+        self.write_file(file_path, content).await.map_err(|e| {
             EditorError::FileOperationFailed(format!("Failed to write file: {}", e))
         })?;
 
@@ -496,4 +524,31 @@ mod tests {
         assert!(change.is_some());
         assert_eq!(change.unwrap().new_content, "Modified content");
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sync_to_file_replaces_or_overwrites_the_file() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp_dir = tempdir().unwrap();
+        let sync_manager = FileSyncManager::new(temp_dir.path().join("backups"));
+        let file_path = temp_dir.path().join("test.md");
+        fs::write(&file_path, "Initial content").await.unwrap();
+        let inode = std::fs::metadata(&file_path).unwrap().ino();
+
+        sync_manager
+            .sync_to_file(&file_path, "Replaced")
+            .await
+            .unwrap();
+        let replaced = std::fs::metadata(&file_path).unwrap().ino();
+        assert_ne!(replaced, inode);
+
+        sync_manager.set_write_strategy(WriteStrategy::WriteThrough);
+        sync_manager
+            .sync_to_file(&file_path, "Overwritten")
+            .await
+            .unwrap();
+        assert_eq!(std::fs::metadata(&file_path).unwrap().ino(), replaced);
+        assert_eq!(fs::read_to_string(&file_path).await.unwrap(), "Overwritten");
+    }
 }
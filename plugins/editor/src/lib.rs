@@ -2,11 +2,15 @@
 
 use async_trait::async_trait;
 use rune_core::{
+    config::{FieldSchema, FieldType, ValidationRule},
     event::{SystemEvent, SystemEventHandler},
-    Plugin, PluginContext, PluginStatus, RenderContext, RendererRegistry, Result, RuneError,
+    safe_write::WriteStrategy,
+    LineEnding, Notification, Plugin, PluginContext, PluginStatus, RenderContext, RendererRegistry,
+    Result, RuneError,
 };
 use serde::{Deserialize, Serialize};
 use std::any::Any;
+use std::collections::HashMap;
 
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -15,7 +19,9 @@ use tokio::sync::RwLock;
 use uuid::Uuid;
 
 pub mod cursor_manager;
+pub mod document_window;
 pub mod editor_state;
+pub mod file_lock;
 pub mod file_sync;
 pub mod inline_renderer;
 pub mod keyboard_shortcuts;
@@ -24,9 +30,12 @@ pub mod render_trigger;
 pub mod session;
 pub mod syntax_highlighter;
 pub mod syntax_parser;
+pub mod write_access;
 
 pub use cursor_manager::{CursorManager, ElementMapping, MappingStats, PositionMapping};
-pub use editor_state::{CursorPosition, EditorMode, EditorState};
+pub use document_window::DocumentWindow;
+pub use editor_state::{CursorPosition, EditorMode, EditorState, TextEdit};
+pub use file_lock::{DocumentLock, LockHolder};
 pub use file_sync::{
     ConflictRegion, ConflictResolution, ConflictResolutionStrategy, ExternalChange, FileSync,
     FileSyncManager,
@@ -41,11 +50,14 @@ pub use live_editor::{
 pub use render_trigger::{
     RenderTriggerDetector, RenderTriggerHandler, TriggerConfig, TriggerEvent,
 };
-pub use session::{AutoSaveStatus, EditorSession, SessionManager};
+pub use session::{
+    AutoSaveFailure, AutoSaveStatus, EditorSession, FileFormat, SessionLeaseConfig, SessionManager,
+};
 pub use syntax_highlighter::{HighlightToken, SyntaxHighlighter, TokenType};
 pub use syntax_parser::{
     MarkdownSyntaxParser, PositionRange, SyntaxElement, SyntaxElementType, SyntaxParser,
 };
+pub use write_access::WriteAccess;
 
 /// Core editor plugin trait that provides WYSIWYG markdown editing capabilities
 #[async_trait]
@@ -65,12 +77,27 @@ pub trait EditorPlugin: Plugin {
     /// Set content for a session
     async fn set_content(&self, session_id: Uuid, content: String) -> Result<()>;
 
+    /// Apply an ordered batch of ranged edits atomically, as a single undo
+    /// step with one render and one auto-save debounce
+    async fn apply_edits(&self, session_id: Uuid, edits: Vec<TextEdit>) -> Result<()>;
+
     /// Create a new editing session
     async fn create_session(&self, file_path: PathBuf) -> Result<Uuid>;
 
+    /// Create a scratch session, an unsaved buffer not bound to a file
+    async fn create_scratch_session(&self) -> Result<Uuid>;
+
+    /// Save a session to a new file, which is then served and watched like
+    /// the file rune was started with
+    async fn save_as(&self, session_id: Uuid, file_path: PathBuf) -> Result<()>;
+
     /// Close an editing session
     async fn close_session(&self, session_id: Uuid) -> Result<()>;
 
+    /// Keep a session open; sessions not heard from for the configured idle
+    /// timeout are saved and closed. Returns when the lease runs out
+    async fn heartbeat(&self, session_id: Uuid) -> Result<Option<SystemTime>>;
+
     /// Get all active sessions
     async fn get_active_sessions(&self) -> Result<Vec<Uuid>>;
 
@@ -144,6 +171,20 @@ pub trait EditorPlugin: Plugin {
     /// Trigger auto-save for a session (with debouncing)
     async fn trigger_auto_save(&self, session_id: Uuid) -> Result<()>;
 
+    /// Who else holds a lock on a session's file, making it read-only
+    async fn get_lock_holder(&self, session_id: Uuid) -> Result<Option<LockHolder>>;
+
+    /// Encoding and line ending a session's file is saved with
+    async fn get_file_format(&self, session_id: Uuid) -> Result<FileFormat>;
+
+    /// Whether a session's file can be written and how much space is free
+    /// for it
+    async fn get_write_access(&self, session_id: Uuid) -> Result<WriteAccess>;
+
+    /// Read the window of a session's file starting at `offset`, for files
+    /// too large to load
+    async fn read_document_window(&self, session_id: Uuid, offset: u64) -> Result<DocumentWindow>;
+
     /// Apply a keyboard shortcut action to a session
     async fn apply_keyboard_shortcut(
         &self,
@@ -158,7 +199,7 @@ pub struct RuneEditorPlugin {
     name: String,
     version: String,
     status: PluginStatus,
-    session_manager: Arc<RwLock<SessionManager>>,
+    session_manager: Arc<SessionManager>,
     context: Option<PluginContext>,
     renderer_registry: Option<Arc<RendererRegistry>>,
     current_theme: Arc<RwLock<String>>,
@@ -171,7 +212,7 @@ impl RuneEditorPlugin {
             name: "editor".to_string(),
             version: "0.1.0".to_string(),
             status: PluginStatus::Loading,
-            session_manager: Arc::new(RwLock::new(SessionManager::new())),
+            session_manager: Arc::new(SessionManager::new()),
             context: None,
             renderer_registry: None,
             current_theme: Arc::new(RwLock::new("catppuccin-mocha".to_string())),
@@ -179,7 +220,7 @@ impl RuneEditorPlugin {
     }
 
     /// Get the session manager
-    pub fn session_manager(&self) -> Arc<RwLock<SessionManager>> {
+    pub fn session_manager(&self) -> Arc<SessionManager> {
         self.session_manager.clone()
     }
 
@@ -199,11 +240,16 @@ impl RuneEditorPlugin {
     /// This method integrates with the renderer pipeline to render editor content.
     /// It's called when editor content changes to ensure the preview is updated.
     pub async fn trigger_render_for_session(&self, session_id: Uuid) -> Result<()> {
-        let manager = self.session_manager.read().await;
-        let content = manager.get_content(session_id).await?;
-        let session = manager
-            .get_session_info(session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+        let content = self.session_manager.get_content(session_id).await?;
+        let file_path = self
+            .session_manager
+            .get_session(session_id)
+            .await
+            .ok_or(EditorError::SessionNotFound(session_id))?
+            .read()
+            .await
+            .file_path
+            .clone();
 
         if let Some(registry) = &self.renderer_registry {
             let start_time = std::time::Instant::now();
@@ -211,9 +257,8 @@ impl RuneEditorPlugin {
             // Create render context with current theme
             let theme = self.get_current_theme().await;
             let context = RenderContext::new(
-                session.file_path.clone(),
-                session
-                    .file_path
+                file_path.clone(),
+                file_path
                     .parent()
                     .unwrap_or(std::path::Path::new("."))
                     .to_path_buf(),
@@ -259,22 +304,17 @@ impl RuneEditorPlugin {
     /// this method detects the change and updates the session accordingly.
     #[allow(dead_code)]
     async fn handle_external_file_change(&self, file_path: &PathBuf) -> Result<()> {
-        let manager = self.session_manager.read().await;
+        let manager = &self.session_manager;
 
         // Find sessions editing this file
-        let matching_sessions: Vec<Uuid> = manager
-            .get_active_sessions()
-            .into_iter()
-            .filter(|session_id| {
-                if let Some(session) = manager.get_session_info(*session_id) {
-                    session.file_path == *file_path
-                } else {
-                    false
+        let mut matching_sessions = Vec::new();
+        for session_id in manager.get_active_sessions().await {
+            if let Some(session) = manager.get_session(session_id).await {
+                if session.read().await.file_path == *file_path {
+                    matching_sessions.push(session_id);
                 }
-            })
-            .collect();
-
-        drop(manager);
+            }
+        }
 
         // Handle external changes for each matching session
         for session_id in matching_sessions {
@@ -284,8 +324,6 @@ impl RuneEditorPlugin {
                 file_path.display()
             );
 
-            let mut manager = self.session_manager.write().await;
-
             // Check for external changes
             if let Ok(Some(external_change)) = manager.check_external_changes(session_id).await {
                 tracing::info!(
@@ -306,7 +344,6 @@ impl RuneEditorPlugin {
                             );
 
                             // Trigger render after resolving external change
-                            drop(manager);
                             self.trigger_render_for_session(session_id).await?;
                         } else {
                             tracing::warn!(
@@ -345,6 +382,10 @@ impl Plugin for RuneEditorPlugin {
         vec!["file-watcher", "renderer"]
     }
 
+    fn required_services(&self) -> Vec<&str> {
+        vec!["renderer-registry"]
+    }
+
     async fn initialize(&mut self, context: &PluginContext) -> Result<()> {
         tracing::info!("Initializing editor plugin");
 
@@ -362,10 +403,39 @@ impl Plugin for RuneEditorPlugin {
         }
 
         // Initialize session manager with context
+        self.session_manager.initialize(context.clone()).await?;
+        if let Ok(Some(lease)) = context
+            .get_config_value::<SessionLeaseConfig>("sessions")
+            .await
         {
-            let mut manager = self.session_manager.write().await;
-            manager.initialize(context.clone()).await?;
+            self.session_manager.set_lease_config(lease);
+        }
+        if let Ok(Some(strategy)) = context
+            .get_config_value::<WriteStrategy>("write_strategy")
+            .await
+        {
+            self.session_manager.set_write_strategy(strategy);
+        }
+        if let Ok(Some(enabled)) = context.get_config_value::<bool>("file_locking").await {
+            self.session_manager.set_file_locking(enabled);
         }
+        self.session_manager
+            .set_large_file_config(context.config.large_files.clone());
+        if let Ok(Some(keep)) = context.get_config_value::<bool>("keep_encoding").await {
+            self.session_manager.set_keep_encoding(keep);
+        }
+        if let Ok(Some(line_ending)) = context.get_config_value::<LineEnding>("line_ending").await {
+            self.session_manager.set_line_ending(Some(line_ending));
+        }
+        self.session_manager.start_session_sweeper();
+
+        // Account open sessions against the memory budget
+        context
+            .memory
+            .register(Arc::new(session::SessionMemory::new(
+                self.session_manager.clone(),
+            )))
+            .await;
 
         // Subscribe to system events for file changes and theme changes
         let event_handler = Arc::new(EditorEventHandler {
@@ -394,12 +464,13 @@ impl Plugin for RuneEditorPlugin {
 
         self.status = PluginStatus::Shutting;
 
-        // Shutdown session manager and save any unsaved changes
-        {
-            let mut manager = self.session_manager.write().await;
-            manager.shutdown().await?;
+        if let Some(context) = &self.context {
+            context.memory.unregister("editor-sessions").await;
         }
 
+        // Shutdown session manager and save any unsaved changes
+        self.session_manager.shutdown().await?;
+
         self.status = PluginStatus::Stopped;
         tracing::info!("Editor plugin shutdown complete");
         Ok(())
@@ -413,6 +484,46 @@ impl Plugin for RuneEditorPlugin {
         vec!["editor", "wysiwyg-editing", "markdown-editing"]
     }
 
+    fn config_schema(&self) -> HashMap<String, FieldSchema> {
+        let one_of = |field: FieldSchema, values: &[&str]| FieldSchema {
+            validation_rules: vec![ValidationRule::OneOf(
+                values.iter().map(|value| value.to_string()).collect(),
+            )],
+            ..field
+        };
+        HashMap::from([
+            (
+                "sessions".to_string(),
+                FieldSchema::new(FieldType::Object, "Leases of editing sessions"),
+            ),
+            (
+                "write_strategy".to_string(),
+                one_of(
+                    FieldSchema::new(FieldType::String, "How documents are saved"),
+                    &["atomic", "write_through"],
+                ),
+            ),
+            (
+                "file_locking".to_string(),
+                FieldSchema::new(FieldType::Boolean, "Lock documents while they are edited"),
+            ),
+            (
+                "keep_encoding".to_string(),
+                FieldSchema::new(
+                    FieldType::Boolean,
+                    "Save documents in the encoding they were read in",
+                ),
+            ),
+            (
+                "line_ending".to_string(),
+                one_of(
+                    FieldSchema::new(FieldType::String, "Line ending documents are saved with"),
+                    &["lf", "crlf"],
+                ),
+            ),
+        ])
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -425,14 +536,14 @@ impl Plugin for RuneEditorPlugin {
 #[async_trait]
 impl EditorPlugin for RuneEditorPlugin {
     async fn get_editor_state(&self, session_id: Uuid) -> Result<Arc<EditorState>> {
-        let manager = self.session_manager.read().await;
-        manager.get_editor_state(session_id).await
+        self.session_manager.get_editor_state(session_id).await
     }
 
     async fn switch_mode(&self, session_id: Uuid, mode: EditorMode) -> Result<()> {
         {
-            let mut manager = self.session_manager.write().await;
-            manager.switch_mode(session_id, mode.clone()).await?;
+            self.session_manager
+                .switch_mode(session_id, mode.clone())
+                .await?;
         }
 
         // Trigger rendering when switching to preview mode
@@ -445,8 +556,7 @@ impl EditorPlugin for RuneEditorPlugin {
 
     async fn save_content(&self, session_id: Uuid) -> Result<()> {
         {
-            let mut manager = self.session_manager.write().await;
-            manager.save_content(session_id).await?;
+            self.session_manager.save_content(session_id).await?;
         }
 
         // Trigger rendering after save to ensure preview is up to date
@@ -456,14 +566,14 @@ impl EditorPlugin for RuneEditorPlugin {
     }
 
     async fn get_content(&self, session_id: Uuid) -> Result<String> {
-        let manager = self.session_manager.read().await;
-        manager.get_content(session_id).await
+        self.session_manager.get_content(session_id).await
     }
 
     async fn set_content(&self, session_id: Uuid, content: String) -> Result<()> {
         {
-            let mut manager = self.session_manager.write().await;
-            manager.set_content(session_id, content).await?;
+            self.session_manager
+                .set_content(session_id, content)
+                .await?;
         }
 
         // Trigger rendering after content change
@@ -472,19 +582,57 @@ impl EditorPlugin for RuneEditorPlugin {
         Ok(())
     }
 
+    async fn apply_edits(&self, session_id: Uuid, edits: Vec<TextEdit>) -> Result<()> {
+        self.session_manager.apply_edits(session_id, edits).await?;
+
+        // One render for the whole batch
+        self.trigger_render_for_session(session_id).await?;
+
+        Ok(())
+    }
+
     async fn create_session(&self, file_path: PathBuf) -> Result<Uuid> {
-        let mut manager = self.session_manager.write().await;
-        manager.create_session(file_path).await
+        self.session_manager.create_session(file_path).await
+    }
+
+    async fn create_scratch_session(&self) -> Result<Uuid> {
+        self.session_manager.create_scratch_session().await
+    }
+
+    async fn save_as(&self, session_id: Uuid, file_path: PathBuf) -> Result<()> {
+        self.session_manager
+            .save_as(session_id, file_path.clone())
+            .await?;
+
+        // The server registers handlers for the current file when it hears of
+        // a change, and the file watcher starts watching its directory
+        if let Some(context) = &self.context {
+            context
+                .state_manager
+                .set_current_file(Some(file_path.clone()))
+                .await;
+            context
+                .event_bus
+                .publish_system_event(SystemEvent::file_changed(
+                    file_path,
+                    rune_core::event::ChangeType::Created,
+                ))
+                .await?;
+        }
+
+        self.trigger_render_for_session(session_id).await
     }
 
     async fn close_session(&self, session_id: Uuid) -> Result<()> {
-        let mut manager = self.session_manager.write().await;
-        manager.close_session(session_id).await
+        self.session_manager.close_session(session_id).await
+    }
+
+    async fn heartbeat(&self, session_id: Uuid) -> Result<Option<SystemTime>> {
+        self.session_manager.heartbeat(session_id).await
     }
 
     async fn get_active_sessions(&self) -> Result<Vec<Uuid>> {
-        let manager = self.session_manager.read().await;
-        Ok(manager.get_active_sessions())
+        Ok(self.session_manager.get_active_sessions().await)
     }
 
     async fn update_cursor_position(
@@ -492,18 +640,19 @@ impl EditorPlugin for RuneEditorPlugin {
         session_id: Uuid,
         position: CursorPosition,
     ) -> Result<()> {
-        let mut manager = self.session_manager.write().await;
-        manager.update_cursor_position(session_id, position).await
+        self.session_manager
+            .update_cursor_position(session_id, position)
+            .await
     }
 
     async fn has_unsaved_changes(&self, session_id: Uuid) -> Result<bool> {
-        let manager = self.session_manager.read().await;
-        manager.has_unsaved_changes(session_id).await
+        self.session_manager.has_unsaved_changes(session_id).await
     }
 
     async fn set_auto_save(&self, session_id: Uuid, enabled: bool) -> Result<()> {
-        let mut manager = self.session_manager.write().await;
-        manager.set_auto_save(session_id, enabled).await
+        self.session_manager
+            .set_auto_save(session_id, enabled)
+            .await
     }
 
     async fn handle_space_key(
@@ -511,33 +660,33 @@ impl EditorPlugin for RuneEditorPlugin {
         session_id: Uuid,
         cursor_position: CursorPosition,
     ) -> Result<bool> {
-        let mut manager = self.session_manager.write().await;
-        manager.handle_space_key(session_id, cursor_position).await
+        self.session_manager
+            .handle_space_key(session_id, cursor_position)
+            .await
     }
 
     async fn check_render_triggers(&self) -> Result<Vec<Uuid>> {
-        let mut manager = self.session_manager.write().await;
-        manager.check_render_triggers().await
+        self.session_manager.check_render_triggers().await
     }
 
     async fn get_pending_trigger_events(&self, session_id: Uuid) -> Result<Vec<TriggerEvent>> {
-        let manager = self.session_manager.read().await;
-        manager.get_pending_trigger_events(session_id).await
+        self.session_manager
+            .get_pending_trigger_events(session_id)
+            .await
     }
 
     async fn clear_trigger_events(&self, session_id: Uuid) -> Result<()> {
-        let mut manager = self.session_manager.write().await;
-        manager.clear_trigger_events(session_id).await
+        self.session_manager.clear_trigger_events(session_id).await
     }
 
     async fn force_render_trigger(&self, session_id: Uuid) -> Result<bool> {
-        let mut manager = self.session_manager.write().await;
-        manager.force_render_trigger(session_id).await
+        self.session_manager.force_render_trigger(session_id).await
     }
 
     async fn update_trigger_config(&self, session_id: Uuid, config: TriggerConfig) -> Result<()> {
-        let mut manager = self.session_manager.write().await;
-        manager.update_trigger_config(session_id, config).await
+        self.session_manager
+            .update_trigger_config(session_id, config)
+            .await
     }
 
     async fn process_live_content(
@@ -545,8 +694,7 @@ impl EditorPlugin for RuneEditorPlugin {
         session_id: Uuid,
         trigger_events: Vec<TriggerEvent>,
     ) -> Result<LiveEditorResult> {
-        let mut manager = self.session_manager.write().await;
-        manager
+        self.session_manager
             .process_live_content(session_id, trigger_events)
             .await
     }
@@ -556,8 +704,7 @@ impl EditorPlugin for RuneEditorPlugin {
         session_id: Uuid,
         click_position: usize,
     ) -> Result<ClickToEditResult> {
-        let mut manager = self.session_manager.write().await;
-        manager
+        self.session_manager
             .handle_click_to_edit(session_id, click_position)
             .await
     }
@@ -568,8 +715,7 @@ impl EditorPlugin for RuneEditorPlugin {
         from_mode: EditorMode,
         to_mode: EditorMode,
     ) -> Result<ModeSwitchResult> {
-        let mut manager = self.session_manager.write().await;
-        manager
+        self.session_manager
             .handle_mode_switch(session_id, from_mode, to_mode)
             .await
     }
@@ -579,20 +725,33 @@ impl EditorPlugin for RuneEditorPlugin {
         session_id: Uuid,
         new_content: String,
     ) -> Result<bool> {
-        let mut manager = self.session_manager.write().await;
-        manager
+        self.session_manager
             .update_active_element_content(session_id, new_content)
             .await
     }
 
     async fn get_auto_save_status(&self, session_id: Uuid) -> Result<AutoSaveStatus> {
-        let manager = self.session_manager.read().await;
-        manager.get_auto_save_status(session_id).await
+        self.session_manager.get_auto_save_status(session_id).await
     }
 
     async fn trigger_auto_save(&self, session_id: Uuid) -> Result<()> {
-        let mut manager = self.session_manager.write().await;
-        manager.trigger_auto_save(session_id).await
+        self.session_manager.trigger_auto_save(session_id).await
+    }
+
+    async fn get_lock_holder(&self, session_id: Uuid) -> Result<Option<LockHolder>> {
+        self.session_manager.lock_holder(session_id).await
+    }
+
+    async fn get_file_format(&self, session_id: Uuid) -> Result<FileFormat> {
+        self.session_manager.get_file_format(session_id).await
+    }
+
+    async fn get_write_access(&self, session_id: Uuid) -> Result<WriteAccess> {
+        self.session_manager.write_access(session_id).await
+    }
+
+    async fn read_document_window(&self, session_id: Uuid, offset: u64) -> Result<DocumentWindow> {
+        self.session_manager.read_window(session_id, offset).await
     }
 
     async fn apply_keyboard_shortcut(
@@ -601,8 +760,7 @@ impl EditorPlugin for RuneEditorPlugin {
         action: ShortcutAction,
         selection: TextSelection,
     ) -> Result<ShortcutResult> {
-        let mut manager = self.session_manager.write().await;
-        manager
+        self.session_manager
             .apply_keyboard_shortcut(session_id, action, selection)
             .await
     }
@@ -646,7 +804,10 @@ pub enum EditorEvent {
         file_path: PathBuf,
     },
     /// Session closed
-    SessionClosed { session_id: Uuid },
+    SessionClosed {
+        session_id: Uuid,
+        reason: SessionCloseReason,
+    },
     /// Auto-save status changed
     AutoSaveStatusChanged {
         session_id: Uuid,
@@ -654,6 +815,32 @@ pub enum EditorEvent {
     },
 }
 
+/// Why an editor session was closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionCloseReason {
+    /// Closed by its client
+    Requested,
+    /// No heartbeat or edit within the idle timeout
+    IdleTimeout,
+    /// Closed to free memory
+    MemoryPressure,
+    /// The editor shut down
+    Shutdown,
+}
+
+impl SessionCloseReason {
+    /// Reason code as sent to clients
+    pub fn code(self) -> &'static str {
+        match self {
+            SessionCloseReason::Requested => "requested",
+            SessionCloseReason::IdleTimeout => "idle_timeout",
+            SessionCloseReason::MemoryPressure => "memory_pressure",
+            SessionCloseReason::Shutdown => "shutdown",
+        }
+    }
+}
+
 impl EditorEvent {
     /// Get the event type as a string
     pub fn event_type(&self) -> &str {
@@ -718,6 +905,18 @@ pub enum EditorError {
 
     #[error("Content synchronization failed: {0}")]
     ContentSyncFailed(String),
+
+    #[error("Invalid edit: {0}")]
+    InvalidEdit(String),
+
+    #[error("Session {0} is a scratch buffer without a file; save it with save_as")]
+    ScratchSession(Uuid),
+
+    #[error("Session {session_id} is read-only: {reason}")]
+    ReadOnly { session_id: Uuid, reason: String },
+
+    #[error("{0} is a binary file; only text documents can be edited")]
+    BinaryFile(std::path::PathBuf),
 }
 
 impl From<EditorError> for RuneError {
@@ -731,20 +930,33 @@ impl From<EditorError> for RuneError {
 /// This structure provides access to the editor plugin's components
 /// from the event handler without requiring a full plugin reference.
 pub struct EditorPluginHandle {
-    session_manager: Arc<RwLock<SessionManager>>,
+    session_manager: Arc<SessionManager>,
     renderer_registry: Option<Arc<RendererRegistry>>,
     current_theme: Arc<RwLock<String>>,
     context: PluginContext,
 }
 
 impl EditorPluginHandle {
+    /// Show `notification` to the user
+    async fn notify(&self, notification: Notification) {
+        let event = SystemEvent::notification(notification);
+        if let Err(e) = self.context.event_bus.publish_system_event(event).await {
+            tracing::warn!("Failed to publish notification: {}", e);
+        }
+    }
+
     /// Trigger rendering for a session's content
     async fn trigger_render_for_session(&self, session_id: Uuid) -> Result<()> {
-        let manager = self.session_manager.read().await;
-        let content = manager.get_content(session_id).await?;
-        let session = manager
-            .get_session_info(session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+        let content = self.session_manager.get_content(session_id).await?;
+        let file_path = self
+            .session_manager
+            .get_session(session_id)
+            .await
+            .ok_or(EditorError::SessionNotFound(session_id))?
+            .read()
+            .await
+            .file_path
+            .clone();
 
         if let Some(registry) = &self.renderer_registry {
             let start_time = std::time::Instant::now();
@@ -752,9 +964,8 @@ impl EditorPluginHandle {
             // Create render context with current theme
             let theme = self.current_theme.read().await.clone();
             let context = RenderContext::new(
-                session.file_path.clone(),
-                session
-                    .file_path
+                file_path.clone(),
+                file_path
                     .parent()
                     .unwrap_or(std::path::Path::new("."))
                     .to_path_buf(),
@@ -791,22 +1002,17 @@ impl EditorPluginHandle {
 
     /// Handle external file change for a session
     async fn handle_external_file_change(&self, file_path: &PathBuf) -> Result<()> {
-        let manager = self.session_manager.read().await;
+        let manager = &self.session_manager;
 
         // Find sessions editing this file
-        let matching_sessions: Vec<Uuid> = manager
-            .get_active_sessions()
-            .into_iter()
-            .filter(|session_id| {
-                if let Some(session) = manager.get_session_info(*session_id) {
-                    session.file_path == *file_path
-                } else {
-                    false
+        let mut matching_sessions = Vec::new();
+        for session_id in manager.get_active_sessions().await {
+            if let Some(session) = manager.get_session(session_id).await {
+                if session.read().await.file_path == *file_path {
+                    matching_sessions.push(session_id);
                 }
-            })
-            .collect();
-
-        drop(manager);
+            }
+        }
 
         // Handle external changes for each matching session
         for session_id in matching_sessions {
@@ -816,8 +1022,6 @@ impl EditorPluginHandle {
                 file_path.display()
             );
 
-            let mut manager = self.session_manager.write().await;
-
             // Check for external changes
             if let Ok(Some(external_change)) = manager.check_external_changes(session_id).await {
                 tracing::info!(
@@ -826,6 +1030,10 @@ impl EditorPluginHandle {
                 );
 
                 // Handle the external change with conflict resolution
+                let document = file_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
                 match manager
                     .handle_external_change(session_id, external_change)
                     .await
@@ -836,9 +1044,13 @@ impl EditorPluginHandle {
                                 "Successfully resolved external change for session {}",
                                 session_id
                             );
+                            self.notify(Notification::info(
+                                "editor",
+                                format!("External changes to {} were merged", document),
+                            ))
+                            .await;
 
                             // Trigger render after resolving external change
-                            drop(manager);
                             self.trigger_render_for_session(session_id).await?;
                         } else {
                             tracing::warn!(
@@ -846,6 +1058,15 @@ impl EditorPluginHandle {
                                 session_id,
                                 resolution.unresolved_conflicts.len()
                             );
+                            self.notify(Notification::warn(
+                                "editor",
+                                format!(
+                                    "External changes to {} conflict with your edits in {} place(s)",
+                                    document,
+                                    resolution.unresolved_conflicts.len()
+                                ),
+                            ))
+                            .await;
                         }
                     }
                     Err(e) => {
@@ -901,9 +1122,7 @@ impl SystemEventHandler for EditorEventHandler {
                 *current_theme = theme_name.clone();
 
                 // Trigger re-rendering for all active sessions with new theme
-                let manager = plugin.session_manager.read().await;
-                let active_sessions = manager.get_active_sessions();
-                drop(manager);
+                let active_sessions = plugin.session_manager.get_active_sessions().await;
                 drop(current_theme);
 
                 for session_id in active_sessions {
@@ -1,6 +1,8 @@
 //! Session management for editor instances
 
-use crate::editor_state::{CursorPosition, EditorMode, EditorState};
+use crate::document_window::{self, DocumentWindow};
+use crate::editor_state::{CursorPosition, EditorMode, EditorState, TextEdit};
+use crate::file_lock::{self, DocumentLock, LockHolder};
 use crate::file_sync::{
     ConflictResolution, ConflictResolutionStrategy, ExternalChange, FileSync, FileSyncManager,
 };
@@ -12,14 +14,28 @@ use crate::live_editor::{
 };
 use crate::render_trigger::{RenderTriggerDetector, TriggerConfig, TriggerEvent};
 use crate::syntax_parser::{MarkdownSyntaxParser, SyntaxParser};
-use crate::EditorError;
-use rune_core::{PluginContext, Result};
+use crate::write_access::{self, WriteAccess};
+use crate::{EditorError, SessionCloseReason};
+use async_trait::async_trait;
+use dashmap::DashMap;
+use rune_core::crypto;
+use rune_core::history::HistoryStore;
+use rune_core::memory::{EvictionPriority, MemoryConsumer, MemoryUsage};
+use rune_core::safe_write::WriteStrategy;
+use rune_core::text_encoding::{self, LineEnding, TextEncoding};
+use rune_core::trace;
+use rune_core::{
+    EventBus, LargeFileConfig, Notification, NotificationAction, PluginContext, Result, SystemEvent,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::Arc;
-use std::time::SystemTime;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 use tokio::fs;
+use tokio::io::AsyncReadExt;
+use tokio::sync::RwLock;
 use uuid::Uuid;
 
 /// Auto-save command for background task communication
@@ -55,8 +71,11 @@ pub struct AutoSaveState {
 pub struct EditorSession {
     /// Unique session identifier
     pub id: Uuid,
-    /// Path to the file being edited
+    /// Path to the file being edited; scratch sessions have a placeholder
+    /// naming no file
     pub file_path: PathBuf,
+    /// Whether the session is a scratch buffer not bound to a file yet
+    pub scratch: bool,
     /// Current editor state
     pub state: Arc<EditorState>,
     /// When the session was created
@@ -77,21 +96,86 @@ pub struct EditorSession {
     pub conflict_strategy: ConflictResolutionStrategy,
     /// Whether to monitor for external file changes
     pub monitor_external_changes: bool,
+    /// Why auto-saving gave up, until a save reaches the file again
+    pub auto_save_failure: Option<AutoSaveFailure>,
+    /// How the file is written on save
+    pub write_strategy: WriteStrategy,
+    /// Lock on the file, held while there are unsaved changes
+    pub lock: Option<DocumentLock>,
+    /// Who else holds a lock on the file, making the session read-only
+    pub locked_by: Option<LockHolder>,
+    /// Whether the file is too large to load; its content is empty and the
+    /// file is read a window at a time with [`SessionManager::read_window`]
+    pub windowed: bool,
+    /// Encoding the file is saved in
+    pub encoding: TextEncoding,
+    /// Line ending the file is saved with; the content itself only has `\n`
+    pub line_ending: LineEnding,
+    /// Whether the file can be written, making the session read-only when
+    /// it cannot
+    pub write_access: WriteAccess,
+    /// Modification time of the file when the session last read or wrote
+    /// it; later ones are external changes
+    pub synced_at: Option<SystemTime>,
 }
 
 impl EditorSession {
     /// Create a new editor session
     pub async fn new(file_path: PathBuf) -> Result<Self> {
+        Self::open(file_path, LargeFileConfig::default().threshold_bytes()).await
+    }
+
+    /// Create a session of `file_path`, leaving files of at least
+    /// `large_file_threshold` bytes unloaded and read-only
+    #[tracing::instrument(name = "session_open", level = "debug", skip_all)]
+    pub async fn open(file_path: PathBuf, large_file_threshold: u64) -> Result<Self> {
         let session_id = Uuid::new_v4();
 
+        // Encrypted documents can only be decrypted whole
+        let windowed = !crypto::is_encrypted(&file_path)
+            && fs::metadata(&file_path)
+                .await
+                .is_ok_and(|metadata| metadata.len() >= large_file_threshold);
+
         // Load file content if it exists
-        let content = if file_path.exists() {
-            fs::read_to_string(&file_path).await.map_err(|e| {
+        let (content, encoding) = if !file_path.exists() {
+            (String::new(), TextEncoding::Utf8)
+        } else if crypto::is_encrypted(&file_path) {
+            // Encrypted documents are decrypted in memory
+            crypto::read_document_encoded(&file_path).map_err(|e| {
                 EditorError::FileOperationFailed(format!("Failed to read file: {}", e))
             })?
         } else {
-            String::new()
+            let read_failed = |e: std::io::Error| {
+                EditorError::FileOperationFailed(format!("Failed to read file: {}", e))
+            };
+            // Only the start of an unloaded file is sniffed
+            let bytes = if windowed {
+                let mut head = Vec::new();
+                fs::File::open(&file_path)
+                    .await
+                    .map_err(read_failed)?
+                    .take(text_encoding::SNIFF_BYTES as u64)
+                    .read_to_end(&mut head)
+                    .await
+                    .map_err(read_failed)?;
+                head
+            } else {
+                fs::read(&file_path).await.map_err(read_failed)?
+            };
+            let encoding = TextEncoding::detect(&bytes)
+                .ok_or_else(|| EditorError::BinaryFile(file_path.clone()))?;
+            let content = if windowed {
+                String::new()
+            } else {
+                encoding.decode(&bytes)
+            };
+            (content, encoding)
         };
+        let line_ending = LineEnding::detect(&content);
+        let content = LineEnding::normalize(&content).into_owned();
+        let write_access = write_access::check(&file_path, content.len() as u64);
+        let synced_at = modified_time(&file_path);
 
         let state = Arc::new(EditorState::new(session_id, content));
         let now = SystemTime::now();
@@ -100,6 +184,7 @@ impl EditorSession {
             id: session_id,
             file_path,
             state,
+            scratch: false,
             created_at: now,
             last_accessed: now,
             is_active: true,
@@ -109,9 +194,54 @@ impl EditorSession {
             live_editor: LiveEditorIntegration::new(),
             conflict_strategy: ConflictResolutionStrategy::PreferLocal,
             monitor_external_changes: true,
+            auto_save_failure: None,
+            write_strategy: WriteStrategy::default(),
+            lock: None,
+            locked_by: None,
+            windowed,
+            encoding,
+            line_ending,
+            write_access,
+            synced_at,
         })
     }
 
+    /// Create an empty scratch session; it is neither auto-saved nor saved
+    /// on close, and lives until it is closed or given a file with
+    /// [`SessionManager::save_as`]
+    pub fn scratch() -> Self {
+        let session_id = Uuid::new_v4();
+        let now = SystemTime::now();
+
+        Self {
+            id: session_id,
+            file_path: PathBuf::from(format!("scratch-{}.md", session_id)),
+            state: Arc::new(EditorState::new(session_id, String::new())),
+            scratch: true,
+            created_at: now,
+            last_accessed: now,
+            is_active: true,
+            auto_save_config: AutoSaveConfig {
+                enabled: false,
+                ..AutoSaveConfig::default()
+            },
+            render_trigger_detector: RenderTriggerDetector::with_defaults(),
+            syntax_parser: MarkdownSyntaxParser::new(),
+            live_editor: LiveEditorIntegration::new(),
+            conflict_strategy: ConflictResolutionStrategy::PreferLocal,
+            monitor_external_changes: false,
+            auto_save_failure: None,
+            write_strategy: WriteStrategy::default(),
+            lock: None,
+            locked_by: None,
+            windowed: false,
+            encoding: TextEncoding::Utf8,
+            line_ending: LineEnding::default(),
+            write_access: WriteAccess::default(),
+            synced_at: None,
+        }
+    }
+
     /// Update the last accessed time
     pub fn touch(&mut self) {
         self.last_accessed = SystemTime::now();
@@ -122,8 +252,30 @@ impl EditorSession {
         Arc::make_mut(&mut self.state)
     }
 
+    /// Content as written to the file, with the file's line endings
+    pub fn file_content(&self) -> std::borrow::Cow<'_, str> {
+        self.line_ending.apply(&self.state.content)
+    }
+
+    /// Encoding and line ending the file is saved with
+    pub fn file_format(&self) -> FileFormat {
+        FileFormat {
+            encoding: self.encoding,
+            line_ending: self.line_ending,
+        }
+    }
+
     /// Save the session content to file
+    #[tracing::instrument(name = "session_save", level = "debug", skip_all)]
     pub async fn save(&mut self) -> Result<()> {
+        if self.scratch {
+            return Err(EditorError::ScratchSession(self.id).into());
+        }
+        // The session never loaded the content it would write
+        if self.windowed {
+            return Err(self.too_large().into());
+        }
+
         // Ensure parent directory exists
         if let Some(parent) = self.file_path.parent() {
             fs::create_dir_all(parent).await.map_err(|e| {
@@ -131,26 +283,129 @@ impl EditorSession {
             })?;
         }
 
-        // Write content to file
-        fs::write(&self.file_path, &self.state.content)
+        // Write content to file, encrypted again for encrypted documents
+        let content = self.file_content().into_owned();
+        crypto::write_document_encoded(
+            &self.file_path,
+            &content,
+            self.write_strategy,
+            self.encoding,
+        )
+        .map_err(|e| EditorError::FileOperationFailed(format!("Failed to write file: {}", e)))?;
+        self.synced_at = modified_time(&self.file_path);
+
+        // The history store keeps plaintext, so encrypted documents have none
+        let file_path = self.file_path.clone();
+        if !crypto::is_encrypted(&file_path) {
+            match tokio::task::spawn_blocking(move || {
+                HistoryStore::record_save(&file_path, &content, None)
+            })
             .await
-            .map_err(|e| {
-                EditorError::FileOperationFailed(format!("Failed to write file: {}", e))
-            })?;
+            {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!("Failed to record version history: {}", e),
+                Err(e) => tracing::warn!("Version history task failed: {}", e),
+            }
+        }
 
         // Update state
         Arc::make_mut(&mut self.state).mark_saved();
+        self.auto_save_failure = None;
+        self.lock = None;
 
         self.touch();
         tracing::info!("Saved session {} to {}", self.id, self.file_path.display());
         Ok(())
     }
 
+    /// Whether the file is too large to edit, cannot be written or someone
+    /// else holds a lock on it
+    pub fn is_read_only(&self) -> bool {
+        self.windowed || !self.write_access.is_writable() || self.locked_by.is_some()
+    }
+
+    /// Check again whether the file can be written; tells whether it just
+    /// stopped being writable
+    fn recheck_write_access(&mut self) -> bool {
+        if self.scratch {
+            return false;
+        }
+        let was_writable = self.write_access.is_writable();
+        self.write_access = write_access::check(&self.file_path, self.state.content.len() as u64);
+        was_writable && !self.write_access.is_writable()
+    }
+
+    fn too_large(&self) -> EditorError {
+        EditorError::ReadOnly {
+            session_id: self.id,
+            reason: "the file is too large to edit".to_string(),
+        }
+    }
+
+    /// Lock the file before the session takes edits, unless `locking` is
+    /// off; fails with the session turned read-only when someone else holds
+    /// a lock on it
+    fn lock_for_editing(&mut self, locking: bool) -> Result<()> {
+        if self.windowed {
+            return Err(self.too_large().into());
+        }
+        // Permissions or mounts may have changed since the last check
+        if !self.write_access.is_writable() {
+            self.recheck_write_access();
+            if let Some(denied) = &self.write_access.denied {
+                return Err(EditorError::ReadOnly {
+                    session_id: self.id,
+                    reason: format!("the file cannot be written: {}", denied),
+                }
+                .into());
+            }
+        }
+        if !locking || self.scratch || self.lock.is_some() {
+            return Ok(());
+        }
+        match DocumentLock::acquire(&self.file_path) {
+            Ok(lock) => {
+                self.lock = Some(lock);
+                self.locked_by = None;
+                Ok(())
+            }
+            Err(holder) => {
+                let error = EditorError::ReadOnly {
+                    session_id: self.id,
+                    reason: holder.to_string(),
+                };
+                self.locked_by = Some(holder);
+                Err(error.into())
+            }
+        }
+    }
+
     /// Check if the session should be auto-saved
     pub fn should_auto_save(&self) -> bool {
         self.auto_save_config.enabled && self.state.should_auto_save()
     }
 
+    /// Where auto-saving of the session stands
+    pub fn auto_save_status(&self) -> AutoSaveStatus {
+        match &self.auto_save_failure {
+            Some(failure) if self.state.is_dirty => AutoSaveStatus::Failed {
+                reason: failure.reason.clone(),
+                retries: failure.retries,
+                backup: failure.backup.clone(),
+            },
+            _ if !self.auto_save_config.enabled => AutoSaveStatus::Disabled {
+                is_dirty: self.state.is_dirty,
+            },
+            _ if self.state.is_dirty => AutoSaveStatus::Dirty {
+                time_since_last_edit: self.state.time_since_last_edit(),
+                pending_save: self.state.auto_save_timer.is_some(),
+            },
+            _ => AutoSaveStatus::Saved {
+                last_save_time: self.state.last_save_time,
+            },
+        }
+    }
+
     /// Get session age
     pub fn age(&self) -> Option<std::time::Duration> {
         self.created_at.elapsed().ok()
@@ -234,8 +489,12 @@ pub struct AutoSaveConfig {
     pub enabled: bool,
     /// Delay in seconds before auto-save triggers
     pub delay_seconds: u64,
-    /// Maximum number of auto-save attempts
+    /// Maximum number of attempts of an auto-save before it gives up and
+    /// writes the content to the backup directory
     pub max_attempts: u32,
+    /// Wait in milliseconds before the first retry of a failed auto-save,
+    /// doubled for every further retry
+    pub retry_backoff_ms: u64,
 }
 
 impl Default for AutoSaveConfig {
@@ -244,30 +503,76 @@ impl Default for AutoSaveConfig {
             enabled: true,
             delay_seconds: 2,
             max_attempts: 3,
+            retry_backoff_ms: 500,
         }
     }
 }
 
+/// How long editor sessions live without hearing from their client
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SessionLeaseConfig {
+    /// Seconds without a heartbeat or edit after which a session is saved
+    /// and closed; 0 keeps sessions open until they are closed
+    pub idle_timeout_secs: u64,
+    /// Seconds between checks for abandoned sessions
+    pub sweep_interval_secs: u64,
+}
+
+impl Default for SessionLeaseConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout_secs: 30 * 60,
+            sweep_interval_secs: 30,
+        }
+    }
+}
+
+impl SessionLeaseConfig {
+    /// Idle time after which a session is abandoned, if sessions expire
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        (self.idle_timeout_secs > 0).then(|| Duration::from_secs(self.idle_timeout_secs))
+    }
+}
+
 /// Session manager that handles multiple editor sessions
+///
+/// Every session sits behind its own lock, so clients editing different
+/// files do not wait on each other. The session map is a [`DashMap`], whose
+/// shards are only locked long enough to look a session up, insert or
+/// remove one, and never across an `await`.
 pub struct SessionManager {
     /// Active sessions by ID
-    sessions: HashMap<Uuid, EditorSession>,
+    sessions: DashMap<Uuid, Arc<RwLock<EditorSession>>>,
     /// Plugin context for file operations and events
-    context: Option<PluginContext>,
+    context: Mutex<Option<PluginContext>>,
     /// Auto-save task handle
-    auto_save_handle: Option<tokio::task::JoinHandle<()>>,
+    auto_save_handle: Mutex<Option<tokio::task::JoinHandle<()>>>,
     /// Auto-save command sender
-    auto_save_sender: Option<tokio::sync::mpsc::UnboundedSender<AutoSaveCommand>>,
+    auto_save_sender: Mutex<Option<tokio::sync::mpsc::UnboundedSender<AutoSaveCommand>>>,
     /// File synchronization manager
     file_sync: Arc<FileSyncManager>,
     /// Keyboard shortcut handler
     keyboard_handler: KeyboardShortcutHandler,
+    /// Lease of sessions whose client stops sending heartbeats
+    lease: Mutex<SessionLeaseConfig>,
+    /// Task closing abandoned sessions
+    sweep_handle: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// Whether files with unsaved changes are locked
+    file_locking: AtomicBool,
+    /// When files are too large to load
+    large_files: Mutex<LargeFileConfig>,
+    /// Whether files not in UTF-8 are saved in their own encoding
+    keep_encoding: AtomicBool,
+    /// Line ending every file is saved with, instead of the one it had
+    line_ending: Mutex<Option<LineEnding>>,
 }
 
 impl SessionManager {
     /// Publish an editor event to the event bus
     async fn publish_editor_event(&self, event: crate::EditorEvent) -> Result<()> {
-        if let Some(_context) = &self.context {
+        let initialized = self.context.lock().unwrap().is_some();
+        if initialized {
             // Convert EditorEvent to SystemEvent for event bus
             let event_type = event.event_type();
             tracing::debug!("Publishing editor event: {}", event_type);
@@ -278,6 +583,22 @@ impl SessionManager {
         }
         Ok(())
     }
+
+    /// Look up a session; the map shard is released before the session is
+    /// locked
+    async fn session(&self, session_id: Uuid) -> Result<Arc<RwLock<EditorSession>>> {
+        self.get_session(session_id)
+            .await
+            .ok_or_else(|| EditorError::SessionNotFound(session_id).into())
+    }
+
+    /// Snapshot of all sessions, so each can be locked without holding the map
+    async fn all_sessions(&self) -> Vec<(Uuid, Arc<RwLock<EditorSession>>)> {
+        self.sessions
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().clone()))
+            .collect()
+    }
 }
 
 impl SessionManager {
@@ -288,18 +609,98 @@ impl SessionManager {
         let file_sync = Arc::new(FileSyncManager::new(backup_dir));
 
         Self {
-            sessions: HashMap::new(),
-            context: None,
-            auto_save_handle: None,
-            auto_save_sender: None,
+            sessions: DashMap::new(),
+            context: Mutex::new(None),
+            auto_save_handle: Mutex::new(None),
+            auto_save_sender: Mutex::new(None),
             file_sync,
             keyboard_handler: KeyboardShortcutHandler::new(),
+            lease: Mutex::new(SessionLeaseConfig::default()),
+            sweep_handle: Mutex::new(None),
+            file_locking: AtomicBool::new(true),
+            large_files: Mutex::new(LargeFileConfig::default()),
+            keep_encoding: AtomicBool::new(false),
+            line_ending: Mutex::new(None),
+        }
+    }
+
+    /// Set how long sessions live without a heartbeat
+    pub fn set_lease_config(&self, config: SessionLeaseConfig) {
+        *self.lease.lock().unwrap() = config;
+    }
+
+    /// Set how sessions write their files; sessions open already keep
+    /// theirs
+    pub fn set_write_strategy(&self, strategy: WriteStrategy) {
+        self.file_sync.set_write_strategy(strategy);
+    }
+
+    /// Turn locking files with unsaved changes on or off
+    pub fn set_file_locking(&self, enabled: bool) {
+        self.file_locking.store(enabled, Ordering::Relaxed);
+    }
+
+    fn file_locking(&self) -> bool {
+        self.file_locking.load(Ordering::Relaxed)
+    }
+
+    /// Set when files are too large to load; sessions open already keep
+    /// their content
+    pub fn set_large_file_config(&self, config: LargeFileConfig) {
+        *self.large_files.lock().unwrap() = config;
+    }
+
+    /// Save files in the encoding they were read in instead of converting
+    /// them to UTF-8
+    pub fn set_keep_encoding(&self, keep: bool) {
+        self.keep_encoding.store(keep, Ordering::Relaxed);
+    }
+
+    /// Save every file with `line_ending` instead of the line ending it was
+    /// read with; `None` keeps each file's own
+    pub fn set_line_ending(&self, line_ending: Option<LineEnding>) {
+        *self.line_ending.lock().unwrap() = line_ending;
+    }
+
+    /// Event bus of the plugin context, once initialized
+    fn event_bus(&self) -> Option<Arc<dyn EventBus>> {
+        self.context
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|context| context.event_bus.clone())
+    }
+
+    /// Current session lease configuration
+    pub fn lease_config(&self) -> SessionLeaseConfig {
+        self.lease.lock().unwrap().clone()
+    }
+
+    /// Start closing sessions whose lease ran out; the task stops with the
+    /// manager
+    pub fn start_session_sweeper(self: &Arc<Self>) {
+        let interval = Duration::from_secs(self.lease_config().sweep_interval_secs.max(1));
+        let manager = Arc::downgrade(self);
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                manager.close_abandoned_sessions().await;
+            }
+        });
+
+        if let Some(previous) = self.sweep_handle.lock().unwrap().replace(handle) {
+            previous.abort();
         }
     }
 
     /// Initialize the session manager with plugin context
-    pub async fn initialize(&mut self, context: PluginContext) -> Result<()> {
-        self.context = Some(context);
+    pub async fn initialize(&self, context: PluginContext) -> Result<()> {
+        *self.context.lock().unwrap() = Some(context);
 
         // Initialize file sync manager
         self.file_sync.initialize().await?;
@@ -312,27 +713,41 @@ impl SessionManager {
     }
 
     /// Shutdown the session manager
-    pub async fn shutdown(&mut self) -> Result<()> {
+    pub async fn shutdown(&self) -> Result<()> {
         tracing::info!("Shutting down session manager");
 
         // Stop auto-save task
-        if let Some(handle) = self.auto_save_handle.take() {
+        let auto_save_handle = self.auto_save_handle.lock().unwrap().take();
+        if let Some(handle) = auto_save_handle {
+            handle.abort();
+        }
+        let sweep_handle = self.sweep_handle.lock().unwrap().take();
+        if let Some(handle) = sweep_handle {
             handle.abort();
         }
 
-        // Save all sessions with unsaved changes
+        // Clear all sessions, saving those with unsaved changes
+        let sessions: Vec<_> = self
+            .get_active_sessions()
+            .await
+            .into_iter()
+            .filter_map(|session_id| self.sessions.remove(&session_id))
+            .collect();
         let mut save_errors = Vec::new();
-        for (session_id, session) in &mut self.sessions {
-            if session.state.is_dirty {
+        for (session_id, session) in sessions {
+            let mut session = session.write().await;
+            if session.state.is_dirty && !session.scratch {
                 if let Err(e) = session.save().await {
-                    save_errors.push((*session_id, e));
+                    save_errors.push((session_id, e));
                 }
             }
+            let event = crate::EditorEvent::SessionClosed {
+                session_id,
+                reason: SessionCloseReason::Shutdown,
+            };
+            self.publish_editor_event(event).await?;
         }
 
-        // Clear all sessions
-        self.sessions.clear();
-
         if !save_errors.is_empty() {
             tracing::warn!("Some sessions failed to save during shutdown:");
             for (id, error) in &save_errors {
@@ -345,11 +760,36 @@ impl SessionManager {
     }
 
     /// Create a new editing session
-    pub async fn create_session(&mut self, file_path: PathBuf) -> Result<Uuid> {
-        let session = EditorSession::new(file_path.clone()).await?;
+    #[tracing::instrument(name = "session_create", level = "debug", skip_all)]
+    pub async fn create_session(&self, file_path: PathBuf) -> Result<Uuid> {
+        let threshold = self.large_files.lock().unwrap().threshold_bytes();
+        let mut session = EditorSession::open(file_path.clone(), threshold).await?;
+        session.write_strategy = self.file_sync.write_strategy();
+        if self.file_locking() {
+            session.locked_by = file_lock::lock_holder(&file_path);
+        }
         let session_id = session.id;
+        if session.windowed {
+            self.warn_too_large(&file_path).await;
+        } else {
+            self.warn_write_access(&file_path, &session.write_access)
+                .await;
+        }
+        if !session.encoding.is_utf8() && !self.keep_encoding.load(Ordering::Relaxed) {
+            let message = format!(
+                "{} was read as {} and is saved as UTF-8",
+                file_path.display(),
+                session.encoding
+            );
+            self.notify(Notification::info("editor", message)).await;
+            session.encoding = TextEncoding::Utf8;
+        }
+        if let Some(line_ending) = *self.line_ending.lock().unwrap() {
+            session.line_ending = line_ending;
+        }
 
-        self.sessions.insert(session_id, session);
+        self.sessions
+            .insert(session_id, Arc::new(RwLock::new(session)));
 
         tracing::info!(
             "Created new session {} for {}",
@@ -357,7 +797,7 @@ impl SessionManager {
             file_path.display()
         );
 
-        // Publish session created event (after mutable borrow is released)
+        // Publish session created event
         let event = crate::EditorEvent::SessionCreated {
             session_id,
             file_path: file_path.clone(),
@@ -367,41 +807,157 @@ impl SessionManager {
         Ok(session_id)
     }
 
+    /// Create a scratch session, an unsaved buffer not bound to a file
+    pub async fn create_scratch_session(&self) -> Result<Uuid> {
+        let mut session = EditorSession::scratch();
+        session.write_strategy = self.file_sync.write_strategy();
+        if let Some(line_ending) = *self.line_ending.lock().unwrap() {
+            session.line_ending = line_ending;
+        }
+        let session_id = session.id;
+        let file_path = session.file_path.clone();
+
+        self.sessions
+            .insert(session_id, Arc::new(RwLock::new(session)));
+
+        tracing::info!("Created scratch session {}", session_id);
+
+        let event = crate::EditorEvent::SessionCreated {
+            session_id,
+            file_path,
+        };
+        self.publish_editor_event(event).await?;
+
+        Ok(session_id)
+    }
+
+    /// Save a session to `file_path` and keep editing that file; a scratch
+    /// session becomes a normal one. The session keeps its old file if the
+    /// save fails
+    #[tracing::instrument(name = "session_save_as", level = "debug", skip_all)]
+    pub async fn save_as(&self, session_id: Uuid, file_path: PathBuf) -> Result<()> {
+        let session = self.session(session_id).await?;
+        let mut session = session.write().await;
+
+        let previous = (
+            std::mem::replace(&mut session.file_path, file_path.clone()),
+            session.scratch,
+            session.monitor_external_changes,
+        );
+        session.scratch = false;
+        session.monitor_external_changes = true;
+        if let Err(e) = session.save().await {
+            (
+                session.file_path,
+                session.scratch,
+                session.monitor_external_changes,
+            ) = previous;
+            return Err(e);
+        }
+        if previous.1 {
+            session.auto_save_config.enabled = AutoSaveConfig::default().enabled;
+        }
+
+        tracing::info!("Saved session {} as {}", session_id, file_path.display());
+        Ok(())
+    }
+
     /// Close an editing session
-    pub async fn close_session(&mut self, session_id: Uuid) -> Result<()> {
-        if let Some(mut session) = self.sessions.remove(&session_id) {
+    pub async fn close_session(&self, session_id: Uuid) -> Result<()> {
+        self.close_session_for(session_id, SessionCloseReason::Requested)
+            .await
+    }
+
+    /// Close a session, saving unsaved changes first; a session that cannot
+    /// be saved stays open, and scratch sessions are discarded
+    #[tracing::instrument(name = "session_close", level = "debug", skip_all)]
+    async fn close_session_for(&self, session_id: Uuid, reason: SessionCloseReason) -> Result<()> {
+        let removed = self.sessions.remove(&session_id);
+        if let Some((_, session)) = removed {
+            // Waits for operations still running on the session
+            let mut locked = session.write().await;
+
             // Save if there are unsaved changes
-            if session.state.is_dirty {
-                session.save().await?;
+            if locked.state.is_dirty && !locked.scratch {
+                if let Err(e) = locked.save().await {
+                    drop(locked);
+                    self.sessions.insert(session_id, session);
+                    return Err(e);
+                }
             }
+            drop(locked);
 
             // Publish session closed event
-            let event = crate::EditorEvent::SessionClosed { session_id };
+            let event = crate::EditorEvent::SessionClosed { session_id, reason };
             self.publish_editor_event(event).await?;
 
-            tracing::info!("Closed session {}", session_id);
+            tracing::info!("Closed session {} ({})", session_id, reason.code());
         } else {
             return Err(EditorError::SessionNotFound(session_id).into());
         }
         Ok(())
     }
 
+    /// Renew a session's lease on behalf of its client; returns when the
+    /// lease runs out, or `None` if sessions do not expire
+    pub async fn heartbeat(&self, session_id: Uuid) -> Result<Option<SystemTime>> {
+        let session = self.session(session_id).await?;
+        let mut session = session.write().await;
+        session.touch();
+        Ok(self
+            .lease_config()
+            .idle_timeout()
+            .map(|timeout| session.last_accessed + timeout))
+    }
+
+    /// Save and close sessions that went without a heartbeat or edit for
+    /// the idle timeout; returns the sessions closed
+    pub async fn close_abandoned_sessions(&self) -> Vec<Uuid> {
+        let Some(timeout) = self.lease_config().idle_timeout() else {
+            return Vec::new();
+        };
+
+        let mut abandoned = Vec::new();
+        for (session_id, session) in self.all_sessions().await {
+            if session
+                .read()
+                .await
+                .idle_time()
+                .is_some_and(|idle| idle >= timeout)
+            {
+                abandoned.push(session_id);
+            }
+        }
+
+        let mut closed = Vec::new();
+        for session_id in abandoned {
+            match self
+                .close_session_for(session_id, SessionCloseReason::IdleTimeout)
+                .await
+            {
+                Ok(()) => closed.push(session_id),
+                Err(e) => tracing::warn!("Failed to close abandoned session {}: {}", session_id, e),
+            }
+        }
+
+        if !closed.is_empty() {
+            tracing::info!("Closed {} abandoned sessions", closed.len());
+        }
+        closed
+    }
+
     /// Get editor state for a session
     pub async fn get_editor_state(&self, session_id: Uuid) -> Result<Arc<EditorState>> {
-        let session = self
-            .sessions
-            .get(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
-        Ok(session.state.clone())
+        let session = self.session(session_id).await?;
+        let state = session.read().await.state.clone();
+        Ok(state)
     }
 
     /// Switch editing mode for a session
-    pub async fn switch_mode(&mut self, session_id: Uuid, mode: EditorMode) -> Result<()> {
+    pub async fn switch_mode(&self, session_id: Uuid, mode: EditorMode) -> Result<()> {
         {
-            let session = self
-                .sessions
-                .get_mut(&session_id)
-                .ok_or(EditorError::SessionNotFound(session_id))?;
+            let session = self.session(session_id).await?;
+            let mut session = session.write().await;
 
             session.state_mut().switch_mode(mode.clone());
             session.touch();
@@ -413,7 +969,7 @@ impl SessionManager {
             );
         }
 
-        // Publish mode changed event (after mutable borrow is released)
+        // Publish mode changed event (after the session lock is released)
         let event = crate::EditorEvent::ModeChanged { session_id, mode };
         self.publish_editor_event(event).await?;
 
@@ -422,20 +978,18 @@ impl SessionManager {
 
     /// Get content for a session
     pub async fn get_content(&self, session_id: Uuid) -> Result<String> {
-        let session = self
-            .sessions
-            .get(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
-        Ok(session.state.content.clone())
+        let session = self.session(session_id).await?;
+        let content = session.read().await.state.content.clone();
+        Ok(content)
     }
 
     /// Set content for a session
-    pub async fn set_content(&mut self, session_id: Uuid, content: String) -> Result<()> {
+    #[tracing::instrument(name = "session_set_content", level = "debug", skip_all)]
+    pub async fn set_content(&self, session_id: Uuid, content: String) -> Result<()> {
         let (cursor_position, should_trigger_auto_save) = {
-            let session = self
-                .sessions
-                .get_mut(&session_id)
-                .ok_or(EditorError::SessionNotFound(session_id))?;
+            let session = self.session(session_id).await?;
+            let mut session = session.write().await;
+            session.lock_for_editing(self.file_locking())?;
 
             let old_content_len = session.state.content.len();
             let was_dirty = session.state.is_dirty;
@@ -459,7 +1013,7 @@ impl SessionManager {
 
         tracing::debug!("Updated content for session {}", session_id);
 
-        // Publish content changed event (after mutable borrow is released)
+        // Publish content changed event (after the session lock is released)
         let event = crate::EditorEvent::ContentChanged {
             session_id,
             content: content.clone(),
@@ -475,20 +1029,77 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Apply an ordered batch of ranged edits to a session
+    ///
+    /// The batch is applied atomically and counts as one content change: one
+    /// content changed event, one render trigger check and one restart of the
+    /// auto-save debounce, however many edits it holds.
+    #[tracing::instrument(name = "session_apply_edits", level = "debug", skip_all)]
+    pub async fn apply_edits(&self, session_id: Uuid, edits: Vec<TextEdit>) -> Result<()> {
+        let (content, cursor_position, is_dirty) = {
+            let session = self.session(session_id).await?;
+            let mut session = session.write().await;
+            session.lock_for_editing(self.file_locking())?;
+
+            let Some(changed) = session
+                .state_mut()
+                .apply_edits(&edits)
+                .map_err(EditorError::InvalidEdit)?
+            else {
+                return Ok(());
+            };
+
+            let content = session.state.content.clone();
+            if session.handle_content_change(&content, changed.start, changed.end) {
+                tracing::debug!("Edit batch triggered render for session {}", session_id);
+            }
+            session.touch();
+
+            (
+                content,
+                session.state.cursor_position.clone(),
+                session.state.is_dirty,
+            )
+        };
+
+        tracing::debug!("Applied {} edits to session {}", edits.len(), session_id);
+
+        let event = crate::EditorEvent::ContentChanged {
+            session_id,
+            content,
+            cursor_position,
+        };
+        self.publish_editor_event(event).await?;
+
+        if is_dirty {
+            self.trigger_auto_save(session_id).await?;
+        }
+
+        Ok(())
+    }
+
     /// Save content for a session
-    pub async fn save_content(&mut self, session_id: Uuid) -> Result<()> {
+    #[tracing::instrument(name = "session_save_content", level = "debug", skip_all)]
+    pub async fn save_content(&self, session_id: Uuid) -> Result<()> {
         // Publish save requested event
         let save_requested_event = crate::EditorEvent::SaveRequested { session_id };
         self.publish_editor_event(save_requested_event).await?;
 
-        let result = {
-            let session = self
-                .sessions
-                .get_mut(&session_id)
-                .ok_or(EditorError::SessionNotFound(session_id))?;
-            session.save().await
+        let (result, backed_up) = {
+            let session = self.session(session_id).await?;
+            let mut session = session.write().await;
+            let backed_up = session
+                .auto_save_failure
+                .as_ref()
+                .is_some_and(|failure| failure.backup.is_some());
+            (session.save().await, backed_up)
         };
 
+        // The backup of a failed auto-save is stale once the file is saved
+        if result.is_ok() && backed_up {
+            self.clear_session_backup(session_id).await?;
+        }
+
         let success = result.is_ok();
         let timestamp = SystemTime::now();
 
@@ -507,15 +1118,13 @@ impl SessionManager {
 
     /// Update cursor position for a session
     pub async fn update_cursor_position(
-        &mut self,
+        &self,
         session_id: Uuid,
         position: CursorPosition,
     ) -> Result<()> {
         {
-            let session = self
-                .sessions
-                .get_mut(&session_id)
-                .ok_or(EditorError::SessionNotFound(session_id))?;
+            let session = self.session(session_id).await?;
+            let mut session = session.write().await;
 
             session
                 .state_mut()
@@ -538,7 +1147,7 @@ impl SessionManager {
             session.touch();
         }
 
-        // Publish cursor moved event (after mutable borrow is released)
+        // Publish cursor moved event (after the session lock is released)
         let event = crate::EditorEvent::CursorMoved {
             session_id,
             position,
@@ -550,19 +1159,15 @@ impl SessionManager {
 
     /// Check if session has unsaved changes
     pub async fn has_unsaved_changes(&self, session_id: Uuid) -> Result<bool> {
-        let session = self
-            .sessions
-            .get(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
-        Ok(session.state.is_dirty)
+        let session = self.session(session_id).await?;
+        let is_dirty = session.read().await.state.is_dirty;
+        Ok(is_dirty)
     }
 
     /// Set auto-save for a session
-    pub async fn set_auto_save(&mut self, session_id: Uuid, enabled: bool) -> Result<()> {
-        let session = self
-            .sessions
-            .get_mut(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+    pub async fn set_auto_save(&self, session_id: Uuid, enabled: bool) -> Result<()> {
+        let session = self.session(session_id).await?;
+        let mut session = session.write().await;
 
         session.auto_save_config.enabled = enabled;
         session.state_mut().set_auto_save(enabled);
@@ -573,23 +1178,26 @@ impl SessionManager {
     }
 
     /// Get all active session IDs
-    pub fn get_active_sessions(&self) -> Vec<Uuid> {
-        self.sessions.keys().copied().collect()
+    pub async fn get_active_sessions(&self) -> Vec<Uuid> {
+        self.sessions.iter().map(|entry| *entry.key()).collect()
     }
 
-    /// Get session information
-    pub fn get_session_info(&self, session_id: Uuid) -> Option<&EditorSession> {
-        self.sessions.get(&session_id)
+    /// Get a session; hold its lock only as long as needed, since every
+    /// operation on the session waits for it
+    pub async fn get_session(&self, session_id: Uuid) -> Option<Arc<RwLock<EditorSession>>> {
+        self.sessions
+            .get(&session_id)
+            .map(|entry| entry.value().clone())
     }
 
     /// Start the auto-save background task
-    async fn start_auto_save_task(&mut self) -> Result<()> {
+    async fn start_auto_save_task(&self) -> Result<()> {
         // Create a channel for auto-save commands
         let (auto_save_tx, mut auto_save_rx) =
             tokio::sync::mpsc::unbounded_channel::<AutoSaveCommand>();
 
         // Store the sender for triggering auto-saves
-        self.auto_save_sender = Some(auto_save_tx);
+        *self.auto_save_sender.lock().unwrap() = Some(auto_save_tx);
 
         let handle = tokio::spawn(async move {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(500));
@@ -640,26 +1248,27 @@ impl SessionManager {
             }
         });
 
-        self.auto_save_handle = Some(handle);
+        *self.auto_save_handle.lock().unwrap() = Some(handle);
         tracing::info!("Auto-save background task started");
         Ok(())
     }
 
     /// Perform auto-save for all eligible sessions
-    pub async fn perform_auto_save(&mut self) -> Result<Vec<Uuid>> {
+    pub async fn perform_auto_save(&self) -> Result<Vec<Uuid>> {
         let mut saved_sessions = Vec::new();
         let mut save_errors = Vec::new();
 
-        for (session_id, session) in &mut self.sessions {
+        for (session_id, session) in self.all_sessions().await {
+            let mut session = session.write().await;
             if session.should_auto_save() {
                 match session.save().await {
                     Ok(()) => {
-                        saved_sessions.push(*session_id);
+                        saved_sessions.push(session_id);
                         tracing::debug!("Auto-saved session {}", session_id);
                     }
                     Err(e) => {
                         tracing::error!("Auto-save failed for session {}: {}", session_id, e);
-                        save_errors.push((*session_id, e));
+                        save_errors.push((session_id, e));
                     }
                 }
             }
@@ -677,28 +1286,25 @@ impl SessionManager {
     }
 
     /// Clean up idle sessions
-    pub async fn cleanup_idle_sessions(&mut self, max_idle_minutes: u64) -> Result<Vec<Uuid>> {
+    pub async fn cleanup_idle_sessions(&self, max_idle_minutes: u64) -> Result<Vec<Uuid>> {
         let max_idle = std::time::Duration::from_secs(max_idle_minutes * 60);
         let mut closed_sessions = Vec::new();
 
-        let idle_session_ids: Vec<Uuid> = self
-            .sessions
-            .iter()
-            .filter_map(|(id, session)| {
-                if let Some(idle_time) = session.idle_time() {
-                    if idle_time > max_idle && !session.state.is_dirty {
-                        Some(*id)
-                    } else {
-                        None
-                    }
-                } else {
-                    None
+        let mut idle_session_ids = Vec::new();
+        for (id, session) in self.all_sessions().await {
+            let session = session.read().await;
+            if let Some(idle_time) = session.idle_time() {
+                if idle_time > max_idle && !session.state.is_dirty {
+                    idle_session_ids.push(id);
                 }
-            })
-            .collect();
+            }
+        }
 
         for session_id in idle_session_ids {
-            if let Err(e) = self.close_session(session_id).await {
+            if let Err(e) = self
+                .close_session_for(session_id, SessionCloseReason::IdleTimeout)
+                .await
+            {
                 tracing::warn!("Failed to close idle session {}: {}", session_id, e);
             } else {
                 closed_sessions.push(session_id);
@@ -712,35 +1318,79 @@ impl SessionManager {
         Ok(closed_sessions)
     }
 
-    /// Get session statistics
-    pub fn get_session_stats(&self) -> SessionStats {
-        let total_sessions = self.sessions.len();
-        let active_sessions = self.sessions.values().filter(|s| s.is_active).count();
-        let dirty_sessions = self.sessions.values().filter(|s| s.state.is_dirty).count();
-        let auto_save_enabled = self
-            .sessions
-            .values()
-            .filter(|s| s.auto_save_config.enabled)
-            .count();
+    /// Memory held by the content buffers of all sessions
+    pub async fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+        for (_, session) in self.all_sessions().await {
+            usage.bytes += session.read().await.state.content.len();
+            usage.entries += 1;
+        }
+        usage
+    }
+
+    /// Close sessions without unsaved changes that were idle for at least
+    /// `min_idle`, least recently accessed first, until about `bytes` bytes
+    /// are freed; returns the bytes freed
+    pub async fn evict_idle_sessions(&self, bytes: usize, min_idle: Duration) -> usize {
+        let mut idle: Vec<(SystemTime, Uuid, usize)> = Vec::new();
+        for (session_id, session) in self.all_sessions().await {
+            let session = session.read().await;
+            if !session.state.is_dirty && session.idle_time().is_some_and(|idle| idle >= min_idle) {
+                idle.push((
+                    session.last_accessed,
+                    session_id,
+                    session.state.content.len(),
+                ));
+            }
+        }
+        idle.sort();
+
+        let mut freed = 0;
+        for (_, session_id, size) in idle {
+            if freed >= bytes {
+                break;
+            }
+            match self
+                .close_session_for(session_id, SessionCloseReason::MemoryPressure)
+                .await
+            {
+                Ok(()) => freed += size,
+                Err(e) => tracing::warn!("Failed to close idle session {}: {}", session_id, e),
+            }
+        }
+
+        if freed > 0 {
+            tracing::info!("Closed idle sessions to free {} bytes", freed);
+        }
+        freed
+    }
 
-        SessionStats {
-            total_sessions,
-            active_sessions,
-            dirty_sessions,
-            auto_save_enabled,
+    /// Get session statistics
+    pub async fn get_session_stats(&self) -> SessionStats {
+        let mut stats = SessionStats {
+            total_sessions: 0,
+            active_sessions: 0,
+            dirty_sessions: 0,
+            auto_save_enabled: 0,
+        };
+        for (_, session) in self.all_sessions().await {
+            let session = session.read().await;
+            stats.total_sessions += 1;
+            stats.active_sessions += usize::from(session.is_active);
+            stats.dirty_sessions += usize::from(session.state.is_dirty);
+            stats.auto_save_enabled += usize::from(session.auto_save_config.enabled);
         }
+        stats
     }
 
     /// Handle space key press for a session
     pub async fn handle_space_key(
-        &mut self,
+        &self,
         session_id: Uuid,
         cursor_position: CursorPosition,
     ) -> Result<bool> {
-        let session = self
-            .sessions
-            .get_mut(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+        let session = self.session(session_id).await?;
+        let mut session = session.write().await;
 
         let should_render = session.handle_space_key(cursor_position);
         session.touch();
@@ -753,12 +1403,12 @@ impl SessionManager {
     }
 
     /// Check if any session should trigger rendering
-    pub async fn check_render_triggers(&mut self) -> Result<Vec<Uuid>> {
+    pub async fn check_render_triggers(&self) -> Result<Vec<Uuid>> {
         let mut sessions_to_render = Vec::new();
 
-        for (session_id, session) in &mut self.sessions {
-            if session.should_trigger_render() {
-                sessions_to_render.push(*session_id);
+        for (session_id, session) in self.all_sessions().await {
+            if session.write().await.should_trigger_render() {
+                sessions_to_render.push(session_id);
                 tracing::debug!("Session {} should trigger render", session_id);
             }
         }
@@ -768,31 +1418,22 @@ impl SessionManager {
 
     /// Get pending trigger events for a session
     pub async fn get_pending_trigger_events(&self, session_id: Uuid) -> Result<Vec<TriggerEvent>> {
-        let session = self
-            .sessions
-            .get(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
-
-        Ok(session.get_pending_trigger_events().to_vec())
+        let session = self.session(session_id).await?;
+        let events = session.read().await.get_pending_trigger_events().to_vec();
+        Ok(events)
     }
 
     /// Clear trigger events for a session
-    pub async fn clear_trigger_events(&mut self, session_id: Uuid) -> Result<()> {
-        let session = self
-            .sessions
-            .get_mut(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
-
-        session.clear_trigger_events();
+    pub async fn clear_trigger_events(&self, session_id: Uuid) -> Result<()> {
+        let session = self.session(session_id).await?;
+        session.write().await.clear_trigger_events();
         Ok(())
     }
 
     /// Force render trigger for a session
-    pub async fn force_render_trigger(&mut self, session_id: Uuid) -> Result<bool> {
-        let session = self
-            .sessions
-            .get_mut(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+    pub async fn force_render_trigger(&self, session_id: Uuid) -> Result<bool> {
+        let session = self.session(session_id).await?;
+        let mut session = session.write().await;
 
         let triggered = session.force_render_trigger();
         session.touch();
@@ -806,14 +1447,12 @@ impl SessionManager {
 
     /// Update render trigger configuration for a session
     pub async fn update_trigger_config(
-        &mut self,
+        &self,
         session_id: Uuid,
         config: TriggerConfig,
     ) -> Result<()> {
-        let session = self
-            .sessions
-            .get_mut(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+        let session = self.session(session_id).await?;
+        let mut session = session.write().await;
 
         session.update_trigger_config(config);
         session.touch();
@@ -823,15 +1462,15 @@ impl SessionManager {
     }
 
     /// Process content with live rendering integration
+    #[tracing::instrument(name = "session_process_live_content", level = "debug", skip_all)]
     pub async fn process_live_content(
-        &mut self,
+        &self,
         session_id: Uuid,
         trigger_events: Vec<TriggerEvent>,
     ) -> Result<LiveEditorResult> {
-        let session = self
-            .sessions
-            .get_mut(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+        let session = self.session(session_id).await?;
+        let mut session = session.write().await;
+        let session = &mut *session;
 
         let result = session.live_editor.process_content_with_cursor(
             &session.state.content,
@@ -846,14 +1485,13 @@ impl SessionManager {
 
     /// Handle click-to-edit functionality
     pub async fn handle_click_to_edit(
-        &mut self,
+        &self,
         session_id: Uuid,
         click_position: usize,
     ) -> Result<ClickToEditResult> {
-        let session = self
-            .sessions
-            .get_mut(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+        let session = self.session(session_id).await?;
+        let mut session = session.write().await;
+        let session = &mut *session;
 
         let result = session
             .live_editor
@@ -870,15 +1508,14 @@ impl SessionManager {
 
     /// Handle mode switching with cursor position preservation
     pub async fn handle_mode_switch(
-        &mut self,
+        &self,
         session_id: Uuid,
         from_mode: EditorMode,
         to_mode: EditorMode,
     ) -> Result<ModeSwitchResult> {
-        let session = self
-            .sessions
-            .get_mut(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+        let session = self.session(session_id).await?;
+        let mut session = session.write().await;
+        let session = &mut *session;
 
         let result = session.live_editor.handle_mode_switch(
             from_mode.clone(),
@@ -907,26 +1544,30 @@ impl SessionManager {
 
     /// Update content of the currently active element
     pub async fn update_active_element_content(
-        &mut self,
+        &self,
         session_id: Uuid,
         new_content: String,
     ) -> Result<bool> {
-        let session = self
-            .sessions
-            .get_mut(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
-
-        let updated = session
-            .live_editor
-            .update_active_element_content(&new_content);
+        let updated = {
+            let session = self.session(session_id).await?;
+            let mut session = session.write().await;
+            session.lock_for_editing(self.file_locking())?;
+
+            let updated = session
+                .live_editor
+                .update_active_element_content(&new_content);
+
+            if updated {
+                // Mark session as dirty since content was updated
+                let current_content = session.state.content.clone();
+                session.state_mut().update_content(current_content);
+                session.touch();
+            }
+            updated
+        };
 
         if updated {
-            // Mark session as dirty since content was updated
-            let current_content = session.state.content.clone();
-            session.state_mut().update_content(current_content);
-            session.touch();
-
-            // Trigger auto-save if enabled
+            // Trigger auto-save if enabled (after the session lock is released)
             self.trigger_auto_save(session_id).await?;
 
             tracing::debug!("Updated active element content for session {}", session_id);
@@ -942,21 +1583,23 @@ impl SessionManager {
     /// 1. Check if auto-save is enabled and the session has unsaved changes
     /// 2. Cancel any existing auto-save timer for the session
     /// 3. Start a new 2-second timer
-    /// 4. Save the content automatically when the timer expires
+    /// 4. Save the content automatically when the timer expires, retrying
+    ///    failed saves with backoff
     ///
     /// The debouncing ensures that rapid typing doesn't trigger multiple saves.
-    pub async fn trigger_auto_save(&mut self, session_id: Uuid) -> Result<()> {
-        let session = self
-            .sessions
-            .get(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+    pub async fn trigger_auto_save(&self, session_id: Uuid) -> Result<()> {
+        let session = self.session(session_id).await?;
+        {
+            let session = session.read().await;
 
-        // Only trigger auto-save if enabled and session is dirty
-        if !session.auto_save_config.enabled || !session.state.is_dirty {
-            return Ok(());
+            // Only trigger auto-save if enabled and session is dirty
+            if !session.auto_save_config.enabled || !session.state.is_dirty {
+                return Ok(());
+            }
         }
 
-        if let Some(sender) = &self.auto_save_sender {
+        let sender = self.auto_save_sender.lock().unwrap().clone();
+        if let Some(sender) = sender {
             let (response_tx, response_rx) = tokio::sync::oneshot::channel();
 
             // Cancel any existing timer for this session
@@ -969,11 +1612,11 @@ impl SessionManager {
             });
 
             // Spawn a task to handle the auto-save when ready
-            tokio::spawn(async move {
+            let file_sync = self.file_sync.clone();
+            let event_bus = self.event_bus();
+            trace::spawn(async move {
                 if let Ok(AutoSaveResult::ReadyToSave) = response_rx.await {
-                    // Note: In a real implementation, we would need a better way to access
-                    // the session manager from the background task. For now, we log the intent.
-                    tracing::info!("Auto-save ready for session {}", session_id);
+                    auto_save_session(&session, &file_sync, event_bus.as_ref()).await;
                 }
             });
         }
@@ -981,48 +1624,145 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Drop the pending auto-save of a session, if any
+    fn cancel_auto_save(&self, session_id: Uuid) {
+        if let Some(sender) = self.auto_save_sender.lock().unwrap().as_ref() {
+            let _ = sender.send(AutoSaveCommand::CancelTimer { session_id });
+        }
+    }
+
+    /// Read the window of a session's file starting at `offset`, for
+    /// sessions of files too large to load
+    pub async fn read_window(&self, session_id: Uuid, offset: u64) -> Result<DocumentWindow> {
+        let session = self.session(session_id).await?;
+        let file_path = session.read().await.file_path.clone();
+        let window_bytes = self.large_files.lock().unwrap().window_bytes();
+        tokio::task::spawn_blocking(move || {
+            document_window::read_window(&file_path, offset, window_bytes)
+        })
+        .await
+        .map_err(|e| EditorError::FileOperationFailed(format!("Read task failed: {}", e)))?
+        .map_err(|e| EditorError::FileOperationFailed(format!("Failed to read file: {}", e)).into())
+    }
+
+    /// Publish `notification` for the user
+    async fn notify(&self, notification: Notification) {
+        let Some(event_bus) = self.event_bus() else {
+            return;
+        };
+        if let Err(e) = event_bus
+            .publish_system_event(SystemEvent::notification(notification))
+            .await
+        {
+            tracing::warn!("Failed to publish notification: {}", e);
+        }
+    }
+
+    /// Tell the user a file cannot be saved, or is short of space to be
+    async fn warn_write_access(&self, file_path: &std::path::Path, access: &WriteAccess) {
+        let message = if let Some(denied) = &access.denied {
+            format!("{} opened read-only: {}", file_path.display(), denied)
+        } else if access.low_space {
+            format!(
+                "Only {:.1} MB is free next to {}; saving it may fail",
+                access.free_bytes.unwrap_or(0) as f64 / (1024.0 * 1024.0),
+                file_path.display()
+            )
+        } else {
+            return;
+        };
+        self.notify(Notification::warn("editor", message)).await;
+    }
+
+    /// Tell the user a file opened read-only and unloaded for its size
+    async fn warn_too_large(&self, file_path: &std::path::Path) {
+        let size = std::fs::metadata(file_path).map_or(0, |metadata| metadata.len());
+        let notification = Notification::warn(
+            "editor",
+            format!(
+                "{} is {:.1} MiB, so it opens read-only and loads as you scroll. \
+                 Diagrams, link previews and image sizes are left out of its preview.",
+                file_path.display(),
+                size as f64 / (1024.0 * 1024.0)
+            ),
+        );
+        self.notify(notification).await;
+    }
+
+    /// Who holds a lock on the session's file, making it read-only; looked
+    /// up anew, so a session turns writable once the other editor is done
+    pub async fn lock_holder(&self, session_id: Uuid) -> Result<Option<LockHolder>> {
+        let session = self.session(session_id).await?;
+        let mut session = session.write().await;
+        if session.lock.is_none() && !session.scratch {
+            session.locked_by = if self.file_locking() {
+                file_lock::lock_holder(&session.file_path)
+            } else {
+                None
+            };
+        }
+        Ok(session.locked_by.clone())
+    }
+
     /// Get auto-save status for a session
     ///
-    /// Returns comprehensive auto-save status including:
-    /// - Whether auto-save is enabled
-    /// - If the session has unsaved changes (is dirty)
-    /// - Last save time
-    /// - Time since last edit
-    /// - Whether an auto-save is currently pending
+    /// Returns whether auto-save is enabled, whether the session has unsaved
+    /// changes and whether an auto-save is pending, or why auto-saving gave
+    /// up after its retries
     pub async fn get_auto_save_status(&self, session_id: Uuid) -> Result<AutoSaveStatus> {
-        let session = self
-            .sessions
-            .get(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
-
-        let status = AutoSaveStatus {
-            enabled: session.auto_save_config.enabled,
-            is_dirty: session.state.is_dirty,
-            last_save_time: session.state.last_save_time,
-            time_since_last_edit: session.state.time_since_last_edit(),
-            pending_save: session.state.auto_save_timer.is_some(),
-        };
-
+        let session = self.session(session_id).await?;
+        let status = session.read().await.auto_save_status();
         Ok(status)
     }
 
+    /// Whether a session's file can be written and how much space is free
+    /// for it; checked anew, so a session turns writable once permissions
+    /// allow it
+    pub async fn write_access(&self, session_id: Uuid) -> Result<WriteAccess> {
+        let session = self.session(session_id).await?;
+        let mut session = session.write().await;
+        let file_path = session.file_path.clone();
+        let revoked = session.recheck_write_access();
+        let access = session.write_access.clone();
+        drop(session);
+        if revoked {
+            self.warn_write_access(&file_path, &access).await;
+        }
+        Ok(access)
+    }
+
+    /// Encoding and line ending a session's file is saved with
+    pub async fn get_file_format(&self, session_id: Uuid) -> Result<FileFormat> {
+        let session = self.session(session_id).await?;
+        let format = session.read().await.file_format();
+        Ok(format)
+    }
+
     /// Check for external file changes for a session
     ///
     /// Detects if the file has been modified externally while being edited.
     /// This is used to implement bidirectional synchronization.
     pub async fn check_external_changes(&self, session_id: Uuid) -> Result<Option<ExternalChange>> {
-        let session = self
-            .sessions
-            .get(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+        let session = self.session(session_id).await?;
+        let (file_path, synced_at) = {
+            let session = session.read().await;
 
-        if !session.monitor_external_changes {
-            return Ok(None);
-        }
+            if !session.monitor_external_changes {
+                return Ok(None);
+            }
+            (session.file_path.clone(), session.synced_at)
+        };
 
-        self.file_sync
-            .detect_external_change(&session.file_path)
-            .await
+        // Whatever changed the file may have changed its permissions too
+        self.write_access(session_id).await?;
+        let change = self.file_sync.detect_external_change(&file_path).await?;
+
+        // The session's own saves, and changes already seen, are not news
+        let change = change.filter(|change| synced_at.is_none_or(|at| change.modified_time > at));
+        if let Some(change) = &change {
+            session.write().await.synced_at = Some(change.modified_time);
+        }
+        Ok(change)
     }
 
     /// Handle external file change with conflict resolution
@@ -1030,17 +1770,15 @@ impl SessionManager {
     /// When an external change is detected, this method resolves any conflicts
     /// between the local edits and external changes using the configured strategy.
     pub async fn handle_external_change(
-        &mut self,
+        &self,
         session_id: Uuid,
         external_change: ExternalChange,
     ) -> Result<ConflictResolution> {
-        let session = self
-            .sessions
-            .get(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
-
-        let local_content = session.state.content.clone();
-        let strategy = session.conflict_strategy;
+        let (local_content, strategy) = {
+            let session = self.session(session_id).await?;
+            let session = session.read().await;
+            (session.state.content.clone(), session.conflict_strategy)
+        };
 
         // Resolve the conflict
         let resolution = self
@@ -1063,6 +1801,8 @@ impl SessionManager {
                 session_id,
                 resolution.unresolved_conflicts.len()
             );
+            // Auto-saving now would overwrite the external change unseen
+            self.cancel_auto_save(session_id);
         }
 
         Ok(resolution)
@@ -1073,13 +1813,10 @@ impl SessionManager {
     /// Creates a local backup of the session content. This is used when
     /// the connection is lost to prevent data loss.
     pub async fn store_session_backup(&self, session_id: Uuid) -> Result<()> {
-        let session = self
-            .sessions
-            .get(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+        let content = self.get_content(session_id).await?;
 
         self.file_sync
-            .store_local_backup(session_id, &session.state.content)
+            .store_local_backup(session_id, &content)
             .await?;
 
         tracing::debug!("Stored backup for session {}", session_id);
@@ -1090,7 +1827,7 @@ impl SessionManager {
     ///
     /// Retrieves and restores content from a local backup. This is used
     /// when reconnecting after a connection loss.
-    pub async fn restore_session_from_backup(&mut self, session_id: Uuid) -> Result<bool> {
+    pub async fn restore_session_from_backup(&self, session_id: Uuid) -> Result<bool> {
         if let Some(backup_content) = self.file_sync.retrieve_local_backup(session_id).await? {
             self.set_content(session_id, backup_content).await?;
             tracing::info!("Restored session {} from backup", session_id);
@@ -1116,16 +1853,13 @@ impl SessionManager {
 
     /// Set conflict resolution strategy for a session
     pub async fn set_conflict_strategy(
-        &mut self,
+        &self,
         session_id: Uuid,
         strategy: ConflictResolutionStrategy,
     ) -> Result<()> {
-        let session = self
-            .sessions
-            .get_mut(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+        let session = self.session(session_id).await?;
+        session.write().await.conflict_strategy = strategy;
 
-        session.conflict_strategy = strategy;
         tracing::debug!(
             "Set conflict strategy to {:?} for session {}",
             strategy,
@@ -1135,13 +1869,10 @@ impl SessionManager {
     }
 
     /// Enable or disable external change monitoring for a session
-    pub async fn set_external_monitoring(&mut self, session_id: Uuid, enabled: bool) -> Result<()> {
-        let session = self
-            .sessions
-            .get_mut(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+    pub async fn set_external_monitoring(&self, session_id: Uuid, enabled: bool) -> Result<()> {
+        let session = self.session(session_id).await?;
+        session.write().await.monitor_external_changes = enabled;
 
-        session.monitor_external_changes = enabled;
         tracing::debug!(
             "Set external monitoring {} for session {}",
             enabled,
@@ -1154,11 +1885,13 @@ impl SessionManager {
     ///
     /// Saves the session content to the file system and creates a backup.
     /// If the save fails, the backup can be used for recovery.
-    pub async fn sync_session_to_file(&mut self, session_id: Uuid) -> Result<()> {
-        let session = self
-            .sessions
-            .get(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
+    pub async fn sync_session_to_file(&self, session_id: Uuid) -> Result<()> {
+        let session = self.session(session_id).await?;
+        // Held throughout, so edits made during the sync are not marked saved
+        let mut session = session.write().await;
+        if session.windowed {
+            return Err(session.too_large().into());
+        }
 
         // Store backup before syncing
         self.file_sync
@@ -1166,19 +1899,13 @@ impl SessionManager {
             .await?;
 
         // Sync to file
-        let file_path = session.file_path.clone();
-        let content = session.state.content.clone();
-
-        self.file_sync.sync_to_file(&file_path, &content).await?;
+        self.file_sync
+            .sync_to_file(&session.file_path, &session.file_content())
+            .await?;
 
         // Clear backup after successful sync
         self.file_sync.clear_local_backup(session_id).await?;
 
-        // Now get mutable access to mark as saved
-        let session = self
-            .sessions
-            .get_mut(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
         session.state_mut().mark_saved();
 
         tracing::info!("Synced session {} to file", session_id);
@@ -1196,18 +1923,19 @@ impl SessionManager {
     /// The method applies the shortcut, updates the session content,
     /// and returns the result with the new cursor position.
     pub async fn apply_keyboard_shortcut(
-        &mut self,
+        &self,
         session_id: Uuid,
         action: ShortcutAction,
         selection: TextSelection,
     ) -> Result<ShortcutResult> {
-        let session = self
-            .sessions
-            .get(&session_id)
-            .ok_or(EditorError::SessionNotFound(session_id))?;
-
-        let content = session.state.content.clone();
-        let cursor_position = session.state.cursor_position.clone();
+        let (content, cursor_position) = {
+            let session = self.session(session_id).await?;
+            let session = session.read().await;
+            (
+                session.state.content.clone(),
+                session.state.cursor_position.clone(),
+            )
+        };
 
         // Apply the keyboard shortcut
         let result = self.keyboard_handler.apply_shortcut(
@@ -1258,14 +1986,216 @@ pub struct SessionStats {
     pub auto_save_enabled: usize,
 }
 
+/// When the file at `path` was last modified, if it exists
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Longest wait between retries of a failed auto-save
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Auto-save `session`, retrying failed saves with backoff
+///
+/// When every attempt fails, the content is written to the backup directory
+/// instead, the session stays dirty with the failure recorded, and the user
+/// is notified on `event_bus`.
+async fn auto_save_session(
+    session: &RwLock<EditorSession>,
+    file_sync: &FileSyncManager,
+    event_bus: Option<&Arc<dyn EventBus>>,
+) {
+    let (max_attempts, mut backoff) = {
+        let config = &session.read().await.auto_save_config;
+        (
+            config.max_attempts.max(1),
+            Duration::from_millis(config.retry_backoff_ms),
+        )
+    };
+
+    let mut attempts = 0;
+    let (error, mut locked) = loop {
+        let mut locked = session.write().await;
+        // Saved meanwhile, or auto-save was switched off
+        if !locked.auto_save_config.enabled || !locked.state.is_dirty {
+            return;
+        }
+        let backed_up = locked
+            .auto_save_failure
+            .as_ref()
+            .is_some_and(|failure| failure.backup.is_some());
+        match locked.save().await {
+            Ok(()) => {
+                if backed_up {
+                    if let Err(e) = file_sync.clear_local_backup(locked.id).await {
+                        tracing::warn!("Failed to remove backup of session {}: {}", locked.id, e);
+                    }
+                }
+                tracing::debug!("Auto-saved session {}", locked.id);
+                return;
+            }
+            Err(e) => {
+                attempts += 1;
+                if attempts >= max_attempts {
+                    break (e, locked);
+                }
+                tracing::warn!(
+                    "Auto-save of session {} failed, retrying in {:?}: {}",
+                    locked.id,
+                    backoff,
+                    e
+                );
+            }
+        }
+        drop(locked);
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+    };
+
+    // Plaintext of encrypted documents stays out of the backup directory
+    let backup = if crypto::is_encrypted(&locked.file_path) {
+        None
+    } else {
+        match file_sync
+            .store_local_backup(locked.id, &locked.state.content)
+            .await
+        {
+            Ok(()) => Some(file_sync.get_backup_path(locked.id)),
+            Err(e) => {
+                tracing::error!("Failed to back up session {}: {}", locked.id, e);
+                None
+            }
+        }
+    };
+    let retries = attempts - 1;
+    locked.auto_save_failure = Some(AutoSaveFailure {
+        reason: error.to_string(),
+        retries,
+        backup: backup.clone(),
+    });
+    let document = locked
+        .file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| locked.file_path.display().to_string());
+    tracing::error!(
+        "Auto-save of session {} failed after {} retries: {}",
+        locked.id,
+        retries,
+        error
+    );
+    drop(locked);
+
+    if let Some(event_bus) = event_bus {
+        let mut message = format!(
+            "Autosave of {} failed after {} retries: {}",
+            document, retries, error
+        );
+        if let Some(backup) = &backup {
+            message.push_str(&format!(". Your changes are kept in {}", backup.display()));
+        }
+        let notification = Notification::error("editor", message)
+            .with_action(NotificationAction::command("Retry", "editor.save"));
+        if let Err(e) = event_bus
+            .publish_system_event(SystemEvent::notification(notification))
+            .await
+        {
+            tracing::warn!("Failed to publish auto-save notification: {}", e);
+        }
+    }
+}
+
 /// Auto-save status for a session
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AutoSaveStatus {
-    pub enabled: bool,
-    pub is_dirty: bool,
-    pub last_save_time: Option<SystemTime>,
-    pub time_since_last_edit: Option<std::time::Duration>,
-    pub pending_save: bool,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AutoSaveStatus {
+    /// Auto-save is off; changes wait for an explicit save
+    Disabled { is_dirty: bool },
+    /// Every change is saved
+    Saved { last_save_time: Option<SystemTime> },
+    /// Changes are waiting for the auto-save delay
+    Dirty {
+        time_since_last_edit: Option<Duration>,
+        pending_save: bool,
+    },
+    /// Auto-saving gave up after `retries` retries; the changes are in
+    /// `backup` if they could be written there
+    Failed {
+        reason: String,
+        retries: u32,
+        backup: Option<PathBuf>,
+    },
+}
+
+impl AutoSaveStatus {
+    /// Whether changes are saved automatically
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self, AutoSaveStatus::Disabled { .. })
+    }
+
+    /// Whether the session has changes not saved to its file
+    pub fn is_dirty(&self) -> bool {
+        match self {
+            AutoSaveStatus::Disabled { is_dirty } => *is_dirty,
+            AutoSaveStatus::Saved { .. } => false,
+            AutoSaveStatus::Dirty { .. } | AutoSaveStatus::Failed { .. } => true,
+        }
+    }
+}
+
+/// Why auto-saving of a session gave up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoSaveFailure {
+    /// Error of the last attempt
+    pub reason: String,
+    /// Attempts after the first
+    pub retries: u32,
+    /// Where the content was written instead
+    pub backup: Option<PathBuf>,
+}
+
+/// How a session's file is stored, so a save writes it back the same way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileFormat {
+    pub encoding: TextEncoding,
+    pub line_ending: LineEnding,
+}
+
+/// Idle time after which a session without unsaved changes may be closed
+/// to stay within the memory budget
+pub const SESSION_EVICTION_IDLE: Duration = Duration::from_secs(5 * 60);
+
+/// Accounts session buffers against the memory budget
+pub struct SessionMemory {
+    session_manager: Arc<SessionManager>,
+}
+
+impl SessionMemory {
+    pub fn new(session_manager: Arc<SessionManager>) -> Self {
+        Self { session_manager }
+    }
+}
+
+#[async_trait]
+impl MemoryConsumer for SessionMemory {
+    fn name(&self) -> &str {
+        "editor-sessions"
+    }
+
+    fn priority(&self) -> EvictionPriority {
+        EvictionPriority::Session
+    }
+
+    async fn usage(&self) -> MemoryUsage {
+        self.session_manager.memory_usage().await
+    }
+
+    async fn evict(&self, bytes: usize) -> usize {
+        self.session_manager
+            .evict_idle_sessions(bytes, SESSION_EVICTION_IDLE)
+            .await
+    }
 }
 
 #[cfg(test)]
@@ -1287,13 +2217,13 @@ mod tests {
 
     #[tokio::test]
     async fn test_session_manager_basic_operations() {
-        let mut manager = SessionManager::new();
+        let manager = SessionManager::new();
         let temp_dir = tempdir().unwrap();
         let file_path = temp_dir.path().join("test.md");
 
         // Create session
         let session_id = manager.create_session(file_path.clone()).await.unwrap();
-        assert!(manager.sessions.contains_key(&session_id));
+        assert!(manager.get_session(session_id).await.is_some());
 
         // Update content
         manager
@@ -1312,12 +2242,12 @@ mod tests {
 
         // Close session
         manager.close_session(session_id).await.unwrap();
-        assert!(!manager.sessions.contains_key(&session_id));
+        assert!(manager.get_session(session_id).await.is_none());
     }
 
     #[tokio::test]
     async fn test_cursor_position_updates() {
-        let mut manager = SessionManager::new();
+        let manager = SessionManager::new();
         let temp_dir = tempdir().unwrap();
         let file_path = temp_dir.path().join("test.md");
 
@@ -1341,7 +2271,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_mode_switching() {
-        let mut manager = SessionManager::new();
+        let manager = SessionManager::new();
         let temp_dir = tempdir().unwrap();
         let file_path = temp_dir.path().join("test.md");
 
@@ -1366,7 +2296,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_auto_save_status() {
-        let mut manager = SessionManager::new();
+        let manager = SessionManager::new();
         let temp_dir = tempdir().unwrap();
         let file_path = temp_dir.path().join("test.md");
 
@@ -1374,9 +2304,9 @@ mod tests {
 
         // Initially should not be dirty
         let status = manager.get_auto_save_status(session_id).await.unwrap();
-        assert!(status.enabled);
-        assert!(!status.is_dirty);
-        assert!(!status.pending_save);
+        assert!(status.is_enabled());
+        assert!(!status.is_dirty());
+        assert!(matches!(status, AutoSaveStatus::Saved { .. }));
 
         // Update content to make it dirty
         manager
@@ -1385,21 +2315,275 @@ mod tests {
             .unwrap();
 
         let status = manager.get_auto_save_status(session_id).await.unwrap();
-        assert!(status.enabled);
-        assert!(status.is_dirty);
+        assert!(status.is_enabled());
+        assert!(status.is_dirty());
 
         // Save content
         manager.save_content(session_id).await.unwrap();
 
         let status = manager.get_auto_save_status(session_id).await.unwrap();
-        assert!(status.enabled);
-        assert!(!status.is_dirty);
-        assert!(!status.pending_save);
+        assert!(status.is_enabled());
+        assert!(!status.is_dirty());
+        assert!(matches!(status, AutoSaveStatus::Saved { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_files_locked_by_others_open_read_only() {
+        let manager = SessionManager::new();
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.md");
+        let swap = temp_dir.path().join(".test.md.swp");
+        let rune_lock = temp_dir.path().join(".test.md.rune-lock");
+        std::fs::write(&swap, "").unwrap();
+
+        let session_id = manager.create_session(file_path).await.unwrap();
+        let holder = manager.lock_holder(session_id).await.unwrap().unwrap();
+        assert_eq!(holder.editor, "Vim");
+        assert!(manager
+            .set_content(session_id, "Clobbered".to_string())
+            .await
+            .is_err());
+
+        std::fs::remove_file(&swap).unwrap();
+        assert_eq!(manager.lock_holder(session_id).await.unwrap(), None);
+        manager
+            .set_content(session_id, "Mine".to_string())
+            .await
+            .unwrap();
+        assert!(rune_lock.exists());
+        manager.save_content(session_id).await.unwrap();
+        assert!(!rune_lock.exists());
+
+        manager.set_file_locking(false);
+        std::fs::write(&swap, "").unwrap();
+        assert_eq!(manager.lock_holder(session_id).await.unwrap(), None);
+        manager
+            .set_content(session_id, "Anyway".to_string())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_large_files_open_windowed_and_read_only() {
+        let manager = SessionManager::new();
+        manager.set_large_file_config(LargeFileConfig {
+            threshold_mb: Some(1),
+            window_kb: Some(1),
+        });
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("server.log");
+        let document = "GET /index.html 200\n".repeat(60_000);
+        std::fs::write(&file_path, &document).unwrap();
+
+        let session_id = manager.create_session(file_path.clone()).await.unwrap();
+        {
+            let session = manager.session(session_id).await.unwrap();
+            let session = session.read().await;
+            assert!(session.windowed && session.is_read_only());
+            assert!(session.state.content.is_empty());
+        }
+        let window = manager.read_window(session_id, 0).await.unwrap();
+        assert_eq!(window.total_bytes, document.len() as u64);
+        assert_eq!(window.end, 1020);
+        assert!(window.text.ends_with("200\n"));
+        let next = manager.read_window(session_id, window.end).await.unwrap();
+        assert_eq!(next.offset, window.end);
+
+        assert!(manager
+            .set_content(session_id, "Clobbered".to_string())
+            .await
+            .is_err());
+        assert!(manager.sync_session_to_file(session_id).await.is_err());
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), document);
+    }
+
+    #[tokio::test]
+    async fn test_files_are_sniffed_and_transcoded() {
+        let manager = SessionManager::new();
+        let temp_dir = tempdir().unwrap();
+
+        let image = temp_dir.path().join("photo.png");
+        std::fs::write(&image, b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR").unwrap();
+        let error = manager.create_session(image).await.unwrap_err();
+        assert!(error.to_string().contains("binary file"));
+
+        let latin1 = temp_dir.path().join("latin1.md");
+        std::fs::write(&latin1, b"# Caf\xE9\n").unwrap();
+        let session_id = manager.create_session(latin1.clone()).await.unwrap();
+        assert_eq!(manager.get_content(session_id).await.unwrap(), "# Café\n");
+        manager
+            .set_content(session_id, "# Café crème\n".to_string())
+            .await
+            .unwrap();
+        manager.save_content(session_id).await.unwrap();
+        assert_eq!(std::fs::read(&latin1).unwrap(), "# Café crème\n".as_bytes());
+
+        manager.set_keep_encoding(true);
+        let utf16 = temp_dir.path().join("utf16.md");
+        std::fs::write(&utf16, TextEncoding::Utf16Le.encode("# Notes\n").unwrap()).unwrap();
+        let session_id = manager.create_session(utf16.clone()).await.unwrap();
+        assert_eq!(manager.get_content(session_id).await.unwrap(), "# Notes\n");
+        manager
+            .set_content(session_id, "# Notes ✓\n".to_string())
+            .await
+            .unwrap();
+        manager.save_content(session_id).await.unwrap();
+        assert_eq!(
+            std::fs::read(&utf16).unwrap(),
+            TextEncoding::Utf16Le.encode("# Notes ✓\n").unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_line_endings_and_bom_survive_a_save() {
+        let manager = SessionManager::new();
+        let temp_dir = tempdir().unwrap();
+
+        let windows = temp_dir.path().join("windows.md");
+        std::fs::write(&windows, "\u{FEFF}# Title\r\n\r\nBody\r\n").unwrap();
+        let session_id = manager.create_session(windows.clone()).await.unwrap();
+        assert_eq!(
+            manager.get_content(session_id).await.unwrap(),
+            "# Title\n\nBody\n"
+        );
+        assert_eq!(
+            manager.get_file_format(session_id).await.unwrap(),
+            FileFormat {
+                encoding: TextEncoding::Utf8Bom,
+                line_ending: LineEnding::Crlf,
+            }
+        );
+        manager
+            .set_content(session_id, "# Title\n\nBody\nMore\n".to_string())
+            .await
+            .unwrap();
+        manager.save_content(session_id).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&windows).unwrap(),
+            "\u{FEFF}# Title\r\n\r\nBody\r\nMore\r\n"
+        );
+
+        // The configured line ending wins over the file's own
+        manager.set_line_ending(Some(LineEnding::Lf));
+        let session_id = manager.create_session(windows.clone()).await.unwrap();
+        manager
+            .set_content(session_id, "# Title\n".to_string())
+            .await
+            .unwrap();
+        manager.save_content(session_id).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&windows).unwrap(),
+            "\u{FEFF}# Title\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_access_is_checked_before_editing() {
+        let manager = SessionManager::new();
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("notes.md");
+        std::fs::write(&file_path, "# Notes\n").unwrap();
+
+        let session_id = manager.create_session(file_path.clone()).await.unwrap();
+        let access = manager.write_access(session_id).await.unwrap();
+        assert!(access.is_writable());
+        #[cfg(unix)]
+        assert!(access.free_bytes.is_some());
+
+        // A session that could not write its file checks again before an edit
+        {
+            let session = manager.get_session(session_id).await.unwrap();
+            let mut session = session.write().await;
+            session.write_access.denied = Some("the mount was read-only".to_string());
+            assert!(session.is_read_only());
+        }
+        manager
+            .set_content(session_id, "# Notes\nmore\n".to_string())
+            .await
+            .unwrap();
+        manager.save_content(session_id).await.unwrap();
+
+        #[cfg(unix)]
+        if unsafe { libc::geteuid() } != 0 {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o444)).unwrap();
+            assert!(!manager
+                .write_access(session_id)
+                .await
+                .unwrap()
+                .is_writable());
+            let error = manager
+                .set_content(session_id, "# Changed\n".to_string())
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("cannot be written"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_auto_save_retries_and_falls_back_to_backup() {
+        struct Notifications(Mutex<Vec<Notification>>);
+        #[async_trait]
+        impl rune_core::SystemEventHandler for Notifications {
+            async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+                if let SystemEvent::Notification { notification, .. } = event {
+                    self.0.lock().unwrap().push(notification.clone());
+                }
+                Ok(())
+            }
+            fn handler_name(&self) -> &str {
+                "notifications"
+            }
+        }
+
+        let temp_dir = tempdir().unwrap();
+        let file_sync = FileSyncManager::new(temp_dir.path().join("backups"));
+        file_sync.initialize().await.unwrap();
+        let event_bus: Arc<dyn EventBus> = Arc::new(rune_core::InMemoryEventBus::new());
+        let notifications = Arc::new(Notifications(Mutex::new(Vec::new())));
+        event_bus
+            .subscribe_system_events(notifications.clone())
+            .await
+            .unwrap();
+
+        // A file where a directory should be makes the target unwritable
+        std::fs::write(temp_dir.path().join("blocker"), "").unwrap();
+        let mut session = EditorSession::new(temp_dir.path().join("blocker/doc.md"))
+            .await
+            .unwrap();
+        session.auto_save_config.retry_backoff_ms = 1;
+        session.state_mut().update_content("Unsaved".to_string());
+        let session = RwLock::new(session);
+
+        auto_save_session(&session, &file_sync, Some(&event_bus)).await;
+
+        let backup = file_sync.get_backup_path(session.read().await.id);
+        match session.read().await.auto_save_status() {
+            AutoSaveStatus::Failed {
+                retries,
+                backup: Some(kept),
+                ..
+            } => {
+                assert_eq!(retries, 2);
+                assert_eq!(kept, backup);
+            }
+            status => panic!("Unexpected auto-save status {:?}", status),
+        }
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "Unsaved");
+        let notifications = notifications.0.lock().unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert!(notifications[0]
+            .message
+            .contains("doc.md failed after 2 retries"));
+        assert_eq!(
+            notifications[0].actions[0].command.as_deref(),
+            Some("editor.save")
+        );
     }
 
     #[tokio::test]
     async fn test_auto_save_trigger() {
-        let mut manager = SessionManager::new();
+        let manager = SessionManager::new();
         let temp_dir = tempdir().unwrap();
         let file_path = temp_dir.path().join("test.md");
 
@@ -1422,4 +2606,249 @@ mod tests {
         let result = manager.trigger_auto_save(session_id).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_memory_eviction_closes_idle_clean_sessions() {
+        let temp_dir = tempdir().unwrap();
+        let manager = Arc::new(SessionManager::new());
+        let mut ids = Vec::new();
+        for (name, idle_minutes) in [("old.md", 30), ("recent.md", 10), ("open.md", 0)] {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, "x".repeat(100)).unwrap();
+            let id = manager.create_session(path).await.unwrap();
+            manager
+                .get_session(id)
+                .await
+                .unwrap()
+                .write()
+                .await
+                .last_accessed = SystemTime::now() - Duration::from_secs(idle_minutes * 60);
+            ids.push(id);
+        }
+
+        let memory = SessionMemory::new(manager.clone());
+        assert_eq!(memory.usage().await.bytes, 300);
+
+        // Only the least recently used session is needed
+        assert_eq!(memory.evict(50).await, 100);
+        assert!(manager.get_session(ids[0]).await.is_none());
+
+        // Sessions in use or with unsaved changes stay open
+        manager
+            .get_session(ids[1])
+            .await
+            .unwrap()
+            .write()
+            .await
+            .state_mut()
+            .is_dirty = true;
+        assert_eq!(memory.evict(1000).await, 0);
+        assert_eq!(memory.usage().await.entries, 2);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_sessions_are_saved_and_closed() {
+        let temp_dir = tempdir().unwrap();
+        let manager = SessionManager::new();
+        let path = temp_dir.path().join("abandoned.md");
+        let abandoned = manager.create_session(path.clone()).await.unwrap();
+        let alive = manager
+            .create_session(temp_dir.path().join("alive.md"))
+            .await
+            .unwrap();
+        manager
+            .set_content(abandoned, "# Unsaved".to_string())
+            .await
+            .unwrap();
+        for id in [abandoned, alive] {
+            manager
+                .get_session(id)
+                .await
+                .unwrap()
+                .write()
+                .await
+                .last_accessed = SystemTime::now() - Duration::from_secs(2 * 60 * 60);
+        }
+
+        // A heartbeat renews the lease
+        let expires = manager.heartbeat(alive).await.unwrap().unwrap();
+        assert!(expires > SystemTime::now() + Duration::from_secs(60));
+
+        assert_eq!(manager.close_abandoned_sessions().await, vec![abandoned]);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "# Unsaved");
+        assert_eq!(manager.get_active_sessions().await, vec![alive]);
+
+        manager.set_lease_config(SessionLeaseConfig {
+            idle_timeout_secs: 0,
+            ..Default::default()
+        });
+        assert_eq!(manager.heartbeat(alive).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_scratch_sessions_save_as_a_file() {
+        let temp_dir = tempdir().unwrap();
+        let manager = SessionManager::new();
+        let scratch = manager.create_scratch_session().await.unwrap();
+        manager
+            .set_content(scratch, "# Note".to_string())
+            .await
+            .unwrap();
+
+        // Nothing to save to until the session has a file
+        assert!(manager.save_content(scratch).await.is_err());
+        let path = temp_dir.path().join("notes").join("note.md");
+        manager.save_as(scratch, path.clone()).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "# Note");
+        let session = manager.get_session(scratch).await.unwrap();
+        assert!(!session.read().await.scratch);
+        assert!(!manager.has_unsaved_changes(scratch).await.unwrap());
+
+        // Closing a scratch session discards it
+        let discarded = manager.create_scratch_session().await.unwrap();
+        manager
+            .set_content(discarded, "Draft".to_string())
+            .await
+            .unwrap();
+        manager.close_session(discarded).await.unwrap();
+        assert_eq!(manager.get_active_sessions().await, vec![scratch]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_edits_to_different_sessions() {
+        let temp_dir = tempdir().unwrap();
+        let manager = Arc::new(SessionManager::new());
+        let mut ids = Vec::new();
+        for i in 0..8 {
+            let path = temp_dir.path().join(format!("doc{}.md", i));
+            ids.push(manager.create_session(path).await.unwrap());
+        }
+
+        // A slow operation holding one session must not stall the others
+        let busy = manager.get_session(ids[0]).await.unwrap();
+        let busy_guard = busy.write().await;
+
+        let mut tasks = Vec::new();
+        for (i, id) in ids.iter().copied().enumerate().skip(1) {
+            let manager = manager.clone();
+            tasks.push(tokio::spawn(async move {
+                let mut content = String::new();
+                for edit in 0..50 {
+                    content.push_str(&format!("session {} edit {}\n", i, edit));
+                    manager.set_content(id, content.clone()).await.unwrap();
+                    let position = CursorPosition::new(edit, 0, content.len());
+                    manager.update_cursor_position(id, position).await.unwrap();
+                    assert_eq!(manager.get_content(id).await.unwrap(), content);
+                }
+                content
+            }));
+        }
+
+        let mut results = Vec::new();
+        for task in tasks {
+            results.push(
+                tokio::time::timeout(Duration::from_secs(10), task)
+                    .await
+                    .expect("edits blocked by another session's lock")
+                    .unwrap(),
+            );
+        }
+        drop(busy_guard);
+
+        for (id, content) in ids.iter().skip(1).zip(&results) {
+            assert_eq!(&manager.get_content(*id).await.unwrap(), content);
+            assert!(content.ends_with("edit 49\n"));
+        }
+
+        // Shutdown saves every session with unsaved changes
+        manager
+            .set_content(ids[0], "first".to_string())
+            .await
+            .unwrap();
+        let stats = manager.get_session_stats().await;
+        assert_eq!(stats.total_sessions, 8);
+        assert_eq!(stats.dirty_sessions, 8);
+        manager.shutdown().await.unwrap();
+        assert!(manager.get_active_sessions().await.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("doc0.md")).unwrap(),
+            "first"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_edits_batch() {
+        let manager = SessionManager::new();
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.md");
+        std::fs::write(&file_path, "one two three").unwrap();
+
+        let session_id = manager.create_session(file_path).await.unwrap();
+        manager
+            .apply_edits(
+                session_id,
+                vec![
+                    TextEdit::replace(4, 7, "2"),
+                    TextEdit::insert(0, "# "),
+                    TextEdit::insert(13, "\n"),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.get_content(session_id).await.unwrap(),
+            "# one 2 three\n"
+        );
+        assert!(manager.has_unsaved_changes(session_id).await.unwrap());
+
+        // A batch with an invalid edit is rejected as a whole
+        let result = manager
+            .apply_edits(
+                session_id,
+                vec![TextEdit::delete(0, 2), TextEdit::delete(10, 50)],
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(
+            manager.get_content(session_id).await.unwrap(),
+            "# one 2 three\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_own_saves_are_not_external_changes() {
+        let manager = SessionManager::new();
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.md");
+        std::fs::write(&file_path, "Original").unwrap();
+
+        let session_id = manager.create_session(file_path.clone()).await.unwrap();
+        assert!(manager
+            .check_external_changes(session_id)
+            .await
+            .unwrap()
+            .is_none());
+
+        manager
+            .set_content(session_id, "Saved".to_string())
+            .await
+            .unwrap();
+        manager.save_content(session_id).await.unwrap();
+        assert!(manager
+            .check_external_changes(session_id)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Another writer's change is reported once
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        std::fs::write(&file_path, "External").unwrap();
+        let change = manager.check_external_changes(session_id).await.unwrap();
+        assert_eq!(change.unwrap().new_content, "External");
+        assert!(manager
+            .check_external_changes(session_id)
+            .await
+            .unwrap()
+            .is_none());
+    }
 }
@@ -0,0 +1,140 @@
+//! Whether documents can be saved, checked before they are edited
+//!
+//! Finding out that a file cannot be written only when saving it loses the
+//! edits made since it was opened. Before a session takes edits, [`check`]
+//! asks the system whether the file, or the directory a new file goes in,
+//! may be written, which covers permissions, ACLs and read-only mounts
+//! alike, and how much space its filesystem has left. A document that
+//! cannot be written opens read-only; sessions check again as they go, so
+//! one turns writable once its permissions or mount change.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+/// Free space wanted beyond the size of the document, since an atomic save
+/// writes a whole copy of it first
+pub const SPACE_MARGIN: u64 = 1024 * 1024;
+
+/// Whether a document can be saved
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteAccess {
+    /// Why the document cannot be written; `None` when it can
+    pub denied: Option<String>,
+    /// Bytes free on the document's filesystem, where the platform tells
+    pub free_bytes: Option<u64>,
+    /// Whether the free space is too little to save the document
+    pub low_space: bool,
+}
+
+impl WriteAccess {
+    /// Whether the document can be written
+    pub fn is_writable(&self) -> bool {
+        self.denied.is_none()
+    }
+}
+
+/// Whether `path` can be saved with `size` bytes of content
+pub fn check(path: &Path, size: u64) -> WriteAccess {
+    // A new file is created in the closest directory that exists
+    let target = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let denied = writable(target).err().map(|e| match e.kind() {
+        io::ErrorKind::PermissionDenied => "you do not have permission to write it".to_string(),
+        io::ErrorKind::ReadOnlyFilesystem => "it is on a read-only filesystem".to_string(),
+        _ => e.to_string(),
+    });
+
+    let directory = if target.is_dir() {
+        target
+    } else {
+        target.parent().unwrap_or(Path::new("."))
+    };
+    let free_bytes = free_bytes(directory);
+    WriteAccess {
+        denied,
+        free_bytes,
+        low_space: free_bytes.is_some_and(|free| free < size.saturating_add(SPACE_MARGIN)),
+    }
+}
+
+#[cfg(unix)]
+fn writable(path: &Path) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: `path` is a valid NUL-terminated string
+    if unsafe { libc::access(path.as_ptr(), libc::W_OK) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn writable(path: &Path) -> io::Result<()> {
+    if std::fs::metadata(path)?.permissions().readonly() {
+        Err(io::ErrorKind::PermissionDenied.into())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn free_bytes(directory: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(directory.as_os_str().as_bytes()).ok()?;
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is a valid NUL-terminated string and `stats` is only
+    // read once `statvfs` filled it
+    if unsafe { libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let stats = unsafe { stats.assume_init() };
+    #[allow(clippy::unnecessary_cast)]
+    Some(stats.f_bavail as u64 * stats.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_bytes(_directory: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_writable_files_and_free_space_are_reported() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("notes.md");
+        std::fs::write(&path, "# Notes").unwrap();
+
+        let access = check(&path, 7);
+        assert!(access.is_writable());
+        // New files are checked against the directory they go in
+        assert!(check(&temp_dir.path().join("new/deeper.md"), 0).is_writable());
+
+        #[cfg(unix)]
+        {
+            assert!(access.free_bytes.is_some());
+            assert!(check(&path, u64::MAX).low_space);
+
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o444)).unwrap();
+            // Root may write anything
+            if unsafe { libc::geteuid() } != 0 {
+                let access = check(&path, 7);
+                assert_eq!(
+                    access.denied.as_deref(),
+                    Some("you do not have permission to write it")
+                );
+            }
+        }
+    }
+}
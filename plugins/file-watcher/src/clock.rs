@@ -0,0 +1,77 @@
+//! Time as the file watcher sees it
+//!
+//! Debouncing and recovery ask a [`Clock`] for the time and for pauses
+//! instead of the system, so the [simulation](crate::sim) can run them on a
+//! [`VirtualClock`] that only moves when a test moves it.
+
+use async_trait::async_trait;
+use std::time::{Duration, Instant};
+
+#[async_trait]
+pub(crate) trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// Wait for `duration` to pass
+    async fn sleep(&self, duration: Duration);
+}
+
+/// The system's clock
+pub(crate) struct SystemClock;
+
+#[async_trait]
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+/// Clock standing still until [advanced](VirtualClock::advance)
+///
+/// Waiting on it passes the time at once, so recovery delays take none.
+#[cfg(any(test, feature = "sim"))]
+pub struct VirtualClock {
+    start: Instant,
+    elapsed: std::sync::Mutex<Duration>,
+}
+
+#[cfg(any(test, feature = "sim"))]
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            elapsed: std::sync::Mutex::new(Duration::ZERO),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    /// Time passed since the clock was created
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+#[cfg(any(test, feature = "sim"))]
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(test, feature = "sim"))]
+#[async_trait]
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
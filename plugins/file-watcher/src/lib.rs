@@ -2,17 +2,28 @@
 //!
 //! This plugin provides file system watching capabilities with configurable filtering
 //! and debouncing. It implements the FileWatcher trait defined in rune-core.
+//! With the `sim` feature, [`sim::Simulation`] drives it without the file
+//! system or the system clock.
+
+mod clock;
+#[cfg(any(test, feature = "sim"))]
+pub mod sim;
 
 use async_trait::async_trait;
-use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use clock::{Clock, SystemClock};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use rune_core::{
     event::{ChangeType, SystemEvent, SystemEventHandler},
-    FileFilter, FileWatcher, Plugin, PluginContext, PluginStatus, Result, RuneError, WatcherId,
+    supervisor::RestartPolicy,
+    FileFilter, FileWatcher, Plugin, PluginContext, PluginStatus, Result, RuneError,
+    WatchStatisticsProvider, WatcherId,
 };
 use std::any::Any;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, error, info, warn};
@@ -25,14 +36,19 @@ struct WatchedPath {
     filter: Arc<dyn FileFilter>,
 }
 
-/// Statistics about file watching activity
-#[derive(Debug, Clone)]
-pub struct WatchStatistics {
-    pub watched_path_count: usize,
-    pub pending_events_count: usize,
-    pub total_events_processed: u64,
+impl WatchedPath {
+    /// Whether changes to `path` are reported by this watch
+    fn covers(&self, path: &Path) -> bool {
+        if self.recursive {
+            path.starts_with(&self.path)
+        } else {
+            path.parent() == Some(&self.path) || path == self.path
+        }
+    }
 }
 
+pub use rune_core::WatchStatistics;
+
 /// Debounced file change event
 #[derive(Debug, Clone)]
 struct DebouncedEvent {
@@ -41,16 +57,72 @@ struct DebouncedEvent {
     last_seen: Instant,
 }
 
+/// Filter letting only the page template and theme stylesheet through
+#[derive(Debug)]
+struct TemplateFileFilter {
+    files: Vec<PathBuf>,
+}
+
+impl FileFilter for TemplateFileFilter {
+    fn should_watch(&self, path: &Path) -> bool {
+        self.files.iter().any(|file| file == path)
+    }
+
+    fn debounce_duration(&self) -> Duration {
+        Duration::from_millis(100)
+    }
+
+    fn filter_name(&self) -> &str {
+        "TemplateFileFilter"
+    }
+}
+
+/// Name of the supervised task processing file system events
+pub const WATCHER_TASK: &str = "file-watcher";
+
+/// Errors in a row after which the watcher recovers
+const MAX_CONSECUTIVE_ERRORS: u32 = 10;
+
 /// File watcher plugin implementation using notify
 pub struct FileWatcherPlugin {
     name: String,
     version: String,
     status: PluginStatus,
     context: Option<PluginContext>,
-    watcher: Option<RecommendedWatcher>,
+    watcher: Arc<Mutex<Option<RecommendedWatcher>>>,
     watched_paths: Arc<RwLock<HashMap<WatcherId, WatchedPath>>>,
     debounced_events: Arc<RwLock<HashMap<PathBuf, DebouncedEvent>>>,
     event_sender: Option<mpsc::UnboundedSender<notify::Result<Event>>>,
+    events_processed: Arc<AtomicU64>,
+    /// Page template and theme stylesheet files, with the change type
+    /// reported for them
+    template_files: Arc<RwLock<HashMap<PathBuf, ChangeType>>>,
+    clock: Arc<dyn Clock>,
+}
+
+/// Statistics view over the watcher state, shared with other plugins
+struct StatisticsSource {
+    watched_paths: Arc<RwLock<HashMap<WatcherId, WatchedPath>>>,
+    debounced_events: Arc<RwLock<HashMap<PathBuf, DebouncedEvent>>>,
+    events_processed: Arc<AtomicU64>,
+}
+
+#[async_trait]
+impl WatchStatisticsProvider for StatisticsSource {
+    async fn watch_statistics(&self) -> WatchStatistics {
+        let watched_paths = self.watched_paths.read().await;
+        let debounced_events = self.debounced_events.read().await;
+
+        let mut paths: Vec<PathBuf> = watched_paths.values().map(|wp| wp.path.clone()).collect();
+        paths.sort();
+
+        WatchStatistics {
+            watched_path_count: watched_paths.len(),
+            pending_events_count: debounced_events.len(),
+            total_events_processed: self.events_processed.load(Ordering::Relaxed),
+            watched_paths: paths,
+        }
+    }
 }
 
 impl FileWatcherPlugin {
@@ -61,34 +133,99 @@ impl FileWatcherPlugin {
             version: "0.1.0".to_string(),
             status: PluginStatus::Loading,
             context: None,
-            watcher: None,
+            watcher: Arc::new(Mutex::new(None)),
             watched_paths: Arc::new(RwLock::new(HashMap::new())),
             debounced_events: Arc::new(RwLock::new(HashMap::new())),
             event_sender: None,
+            events_processed: Arc::new(AtomicU64::new(0)),
+            template_files: Arc::new(RwLock::new(HashMap::new())),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Watch the configured page template, theme stylesheet and theme
+    /// script, which may live outside of the watched directories
+    async fn watch_template_files(&mut self, config: &rune_core::Config) {
+        let configured = [
+            (config.get_template_path(), ChangeType::TemplateModified),
+            (config.get_theme_css_path(), ChangeType::StyleModified),
+            // Scripts cannot be swapped in a running page, it reloads
+            (config.get_theme_js_path(), ChangeType::TemplateModified),
+        ];
+        let mut files = HashMap::new();
+        for (path, change_type) in configured {
+            let Some(path) = path else {
+                continue;
+            };
+            match path.canonicalize() {
+                Ok(path) => {
+                    files.insert(path, change_type);
+                }
+                Err(e) => warn!("Not watching {}: {}", path.display(), e),
+            }
+        }
+        if files.is_empty() {
+            return;
+        }
+
+        let filter = Arc::new(TemplateFileFilter {
+            files: files.keys().cloned().collect(),
+        });
+        let mut dirs: Vec<PathBuf> = files
+            .keys()
+            .filter_map(|file| file.parent().map(Path::to_path_buf))
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        for dir in dirs {
+            // Directories watched already only need the filter added
+            if !self.is_watching(&dir).await {
+                if let Some(watcher) = self.watcher.lock().unwrap().as_mut() {
+                    if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                        warn!(
+                            "Failed to watch template directory {}: {}",
+                            dir.display(),
+                            e
+                        );
+                        continue;
+                    }
+                }
+            }
+            self.watched_paths.write().await.insert(
+                WatcherId::new(),
+                WatchedPath {
+                    path: dir,
+                    recursive: false,
+                    filter: filter.clone(),
+                },
+            );
+        }
+        for file in files.keys() {
+            info!("Watching template file: {}", file.display());
         }
+        *self.template_files.write().await = files;
     }
 
     /// Process file system events with debouncing and error recovery
     async fn process_events(
         &self,
-        mut event_receiver: mpsc::UnboundedReceiver<notify::Result<Event>>,
+        event_receiver: &mut mpsc::UnboundedReceiver<notify::Result<Event>>,
     ) {
         let mut debounce_timer = tokio::time::interval(Duration::from_millis(50));
         let mut error_count = 0u32;
-        const MAX_CONSECUTIVE_ERRORS: u32 = 10;
         const ERROR_RESET_INTERVAL: Duration = Duration::from_secs(60);
-        let mut last_error_reset = Instant::now();
+        let mut last_error_reset = self.clock.now();
 
         info!("Starting file watcher event processing loop");
 
         loop {
             // Reset error count periodically
-            if last_error_reset.elapsed() >= ERROR_RESET_INTERVAL {
+            if self.clock.now().duration_since(last_error_reset) >= ERROR_RESET_INTERVAL {
                 if error_count > 0 {
                     debug!("Resetting error count from {} to 0", error_count);
                     error_count = 0;
                 }
-                last_error_reset = Instant::now();
+                last_error_reset = self.clock.now();
             }
 
             tokio::select! {
@@ -107,34 +244,7 @@ impl FileWatcherPlugin {
                                 error_count += 1;
                             }
                         }
-                        Some(Err(e)) => {
-                            error_count += 1;
-                            error!("File watcher error (count: {}): {}", error_count, e);
-
-                            // Publish error event for monitoring
-                            if let Some(context) = &self.context {
-                                let error_event = SystemEvent::error(
-                                    "file-watcher".to_string(),
-                                    format!("File system watcher error: {}", e),
-                                    rune_core::event::ErrorSeverity::High,
-                                );
-
-                                if let Err(publish_err) = context.event_bus.publish_system_event(error_event).await {
-                                    error!("Failed to publish watcher error event: {}", publish_err);
-                                }
-                            }
-
-                            // Check if we need to trigger recovery
-                            if error_count >= MAX_CONSECUTIVE_ERRORS {
-                                error!("Too many consecutive errors ({}), attempting recovery", error_count);
-                                if let Err(recovery_err) = self.attempt_watcher_recovery().await {
-                                    error!("Watcher recovery failed: {}", recovery_err);
-                                } else {
-                                    info!("Watcher recovery completed successfully");
-                                    error_count = 0;
-                                }
-                            }
-                        }
+                        Some(Err(e)) => self.handle_watcher_error(&mut error_count, e).await,
                         None => {
                             warn!("File watcher event channel closed, attempting to reconnect");
                             if let Err(e) = self.attempt_watcher_recovery().await {
@@ -156,13 +266,47 @@ impl FileWatcherPlugin {
 
             // Add a small delay if we're experiencing errors to prevent tight error loops
             if error_count > 0 {
-                tokio::time::sleep(Duration::from_millis(100)).await;
+                self.clock.sleep(Duration::from_millis(100)).await;
             }
         }
 
         warn!("File watcher event processing loop terminated");
     }
 
+    /// Count an error of the watcher, publishing it, and recover once
+    /// [`MAX_CONSECUTIVE_ERRORS`] came in a row
+    async fn handle_watcher_error(&self, error_count: &mut u32, e: notify::Error) {
+        *error_count += 1;
+        error!("File watcher error (count: {}): {}", error_count, e);
+
+        // Publish error event for monitoring
+        if let Some(context) = &self.context {
+            let error_event = SystemEvent::error(
+                "file-watcher".to_string(),
+                format!("File system watcher error: {}", e),
+                rune_core::event::ErrorSeverity::High,
+            );
+
+            if let Err(publish_err) = context.event_bus.publish_system_event(error_event).await {
+                error!("Failed to publish watcher error event: {}", publish_err);
+            }
+        }
+
+        // Check if we need to trigger recovery
+        if *error_count >= MAX_CONSECUTIVE_ERRORS {
+            error!(
+                "Too many consecutive errors ({}), attempting recovery",
+                error_count
+            );
+            if let Err(recovery_err) = self.attempt_watcher_recovery().await {
+                error!("Watcher recovery failed: {}", recovery_err);
+            } else {
+                info!("Watcher recovery completed successfully");
+                *error_count = 0;
+            }
+        }
+    }
+
     /// Attempt to recover from watcher failures
     async fn attempt_watcher_recovery(&self) -> Result<()> {
         warn!("Attempting file watcher recovery");
@@ -187,7 +331,7 @@ impl FileWatcherPlugin {
         }
 
         // Add a delay to prevent immediate re-failure
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        self.clock.sleep(Duration::from_secs(1)).await;
 
         info!("File watcher recovery completed");
         Ok(())
@@ -195,6 +339,19 @@ impl FileWatcherPlugin {
 
     /// Handle a single file system event
     async fn handle_file_event(&self, event: Event) -> Result<()> {
+        // Reads (e.g. by the renderer or an exporter) are not changes; reporting them
+        // would make anything that reads a file on change trigger itself again
+        if matches!(event.kind, EventKind::Access(_)) {
+            return Ok(());
+        }
+
+        // Both names of a renamed file arrive in one event
+        if let (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) =
+            (event.kind, event.paths.as_slice())
+        {
+            return self.handle_rename(from, to).await;
+        }
+
         for path in event.paths {
             // Check if any watched path should handle this event
             let watched_paths = self.watched_paths.read().await;
@@ -212,10 +369,21 @@ impl FileWatcherPlugin {
             drop(watched_paths);
 
             if should_process {
-                let change_type = match event.kind {
-                    notify::EventKind::Create(_) => ChangeType::Created,
-                    notify::EventKind::Modify(_) => ChangeType::Modified,
-                    notify::EventKind::Remove(_) => ChangeType::Deleted,
+                let template_change = self.template_files.read().await.get(&path).cloned();
+                let change_type = match (template_change, event.kind) {
+                    // Editors often save by replacing the file, so any change
+                    // to a template file counts as an edit
+                    (Some(change_type), _) => change_type,
+                    (None, EventKind::Create(_)) => ChangeType::Created,
+                    // Half of a rename whose other half is not watched
+                    (None, EventKind::Modify(ModifyKind::Name(RenameMode::From))) => {
+                        ChangeType::Deleted
+                    }
+                    (None, EventKind::Modify(ModifyKind::Name(RenameMode::To))) => {
+                        ChangeType::Created
+                    }
+                    (None, EventKind::Modify(_)) => ChangeType::Modified,
+                    (None, EventKind::Remove(_)) => ChangeType::Deleted,
                     _ => ChangeType::Modified, // Default to modified for other events
                 };
 
@@ -226,7 +394,7 @@ impl FileWatcherPlugin {
                     DebouncedEvent {
                         path: path.clone(),
                         change_type,
-                        last_seen: Instant::now(),
+                        last_seen: self.clock.now(),
                     },
                 );
             }
@@ -235,20 +403,56 @@ impl FileWatcherPlugin {
         Ok(())
     }
 
+    /// Report a rename within the watched paths as one change of the new
+    /// path, replacing the removal the old name's half of it may have left
+    async fn handle_rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let is_watched = |path: &Path, watched_paths: &HashMap<WatcherId, WatchedPath>| {
+            watched_paths
+                .values()
+                .any(|watched| watched.covers(path) && watched.filter.should_watch(path))
+        };
+        let watched_paths = self.watched_paths.read().await;
+        let (from_watched, to_watched) = (
+            is_watched(from, &watched_paths),
+            is_watched(to, &watched_paths),
+        );
+        drop(watched_paths);
+
+        let mut debounced_events = self.debounced_events.write().await;
+        debounced_events.remove(from);
+        let (path, change_type) = match (from_watched, to_watched) {
+            (_, true) => (
+                to,
+                ChangeType::Renamed {
+                    from: from.to_path_buf(),
+                    to: to.to_path_buf(),
+                },
+            ),
+            // Moved out of sight, e.g. into a directory that is not watched
+            (true, false) => (from, ChangeType::Deleted),
+            (false, false) => return Ok(()),
+        };
+        debounced_events.insert(
+            path.to_path_buf(),
+            DebouncedEvent {
+                path: path.to_path_buf(),
+                change_type,
+                last_seen: self.clock.now(),
+            },
+        );
+        Ok(())
+    }
+
     /// Check if a path matches a watched path configuration
     fn path_matches_watch(&self, path: &Path, watched_path: &WatchedPath) -> bool {
-        if watched_path.recursive {
-            path.starts_with(&watched_path.path)
-        } else {
-            path.parent() == Some(&watched_path.path) || path == watched_path.path
-        }
+        watched_path.covers(path)
     }
 
     /// Process debounced events and publish them
     async fn process_debounced_events(&self) -> Result<()> {
         let mut events_to_publish = Vec::new();
         let mut debounced_events = self.debounced_events.write().await;
-        let now = Instant::now();
+        let now = self.clock.now();
 
         // Find events that have been debounced long enough
         let mut expired_paths = Vec::new();
@@ -275,6 +479,7 @@ impl FileWatcherPlugin {
                 let system_event =
                     SystemEvent::file_changed(event.path.clone(), event.change_type.clone());
 
+                self.events_processed.fetch_add(1, Ordering::Relaxed);
                 if let Err(e) = context.event_bus.publish_system_event(system_event).await {
                     error!("Failed to publish file change event: {}", e);
                 }
@@ -300,13 +505,14 @@ impl FileWatcherPlugin {
 
     /// Get statistics about file watching activity
     pub async fn get_watch_statistics(&self) -> WatchStatistics {
-        let watched_paths = self.watched_paths.read().await;
-        let debounced_events = self.debounced_events.read().await;
+        self.statistics_source().watch_statistics().await
+    }
 
-        WatchStatistics {
-            watched_path_count: watched_paths.len(),
-            pending_events_count: debounced_events.len(),
-            total_events_processed: 0, // Would track this in a real implementation
+    fn statistics_source(&self) -> StatisticsSource {
+        StatisticsSource {
+            watched_paths: self.watched_paths.clone(),
+            debounced_events: self.debounced_events.clone(),
+            events_processed: self.events_processed.clone(),
         }
     }
 }
@@ -336,6 +542,12 @@ impl Plugin for FileWatcherPlugin {
 
         self.context = Some(context.clone());
 
+        // Let other plugins (e.g. the dev dashboard) report watching activity
+        let statistics: Arc<dyn WatchStatisticsProvider> = Arc::new(self.statistics_source());
+        context
+            .set_shared_resource("file_watcher_statistics".to_string(), statistics)
+            .await?;
+
         // Create event channel for file system events
         let (event_sender, event_receiver) = mpsc::unbounded_channel();
         self.event_sender = Some(event_sender.clone());
@@ -351,41 +563,72 @@ impl Plugin for FileWatcherPlugin {
         )
         .map_err(|e| RuneError::Plugin(format!("Failed to create file watcher: {}", e)))?;
 
-        self.watcher = Some(watcher);
+        *self.watcher.lock().unwrap() = Some(watcher);
 
         // Start event processing task
         let plugin_clone = self.watched_paths.clone();
         let debounced_events_clone = self.debounced_events.clone();
+        let events_processed_clone = self.events_processed.clone();
+        let template_files_clone = self.template_files.clone();
+        let clock_clone = self.clock.clone();
         let context_clone = context.clone();
 
-        tokio::spawn(async move {
-            let temp_plugin = FileWatcherPlugin {
-                name: "file-watcher".to_string(),
-                version: "0.1.0".to_string(),
-                status: PluginStatus::Active,
-                context: Some(context_clone),
-                watcher: None,
-                watched_paths: plugin_clone,
-                debounced_events: debounced_events_clone,
-                event_sender: None,
-            };
-            temp_plugin.process_events(event_receiver).await;
-        });
+        // Restarted if it panics; the receiver outlives each run of the loop
+        let event_receiver = Arc::new(tokio::sync::Mutex::new(event_receiver));
+        context
+            .supervisor
+            .supervise(WATCHER_TASK, RestartPolicy::on_failure(), move || {
+                let temp_plugin = FileWatcherPlugin {
+                    name: "file-watcher".to_string(),
+                    version: "0.1.0".to_string(),
+                    status: PluginStatus::Active,
+                    context: Some(context_clone.clone()),
+                    watcher: Arc::new(Mutex::new(None)),
+                    watched_paths: plugin_clone.clone(),
+                    debounced_events: debounced_events_clone.clone(),
+                    event_sender: None,
+                    events_processed: events_processed_clone.clone(),
+                    template_files: template_files_clone.clone(),
+                    clock: clock_clone.clone(),
+                };
+                let event_receiver = event_receiver.clone();
+                async move {
+                    let mut event_receiver = event_receiver.lock().await;
+                    temp_plugin.process_events(&mut event_receiver).await;
+                    Ok(())
+                }
+            })
+            .await?;
 
         // Start watching the current directory by default
         let current_dir = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
 
         // Create a default filter for common file types
+        let mut watch_extensions = vec![
+            "md".to_string(),
+            "markdown".to_string(),
+            // Encrypted documents, like notes.md.age
+            "age".to_string(),
+            "txt".to_string(),
+            "html".to_string(),
+            "css".to_string(),
+            "js".to_string(),
+            // Images, so the renderer re-probes their dimensions
+            "png".to_string(),
+            "jpg".to_string(),
+            "jpeg".to_string(),
+            "gif".to_string(),
+            "webp".to_string(),
+        ];
+        // Documents previewed through pandoc
+        watch_extensions.extend(
+            rune_core::pandoc::INPUT_FORMATS
+                .iter()
+                .flat_map(|input| input.extensions.iter().map(|ext| ext.to_string())),
+        );
         let config = rune_core::FileWatcherConfig {
             debounce_ms: 200,
-            watch_extensions: vec![
-                "md".to_string(),
-                "markdown".to_string(),
-                "txt".to_string(),
-                "html".to_string(),
-                "css".to_string(),
-                "js".to_string(),
-            ],
+            watch_extensions,
             ignore_patterns: vec![
                 "*.tmp".to_string(),
                 "*.swp".to_string(),
@@ -402,8 +645,14 @@ impl Plugin for FileWatcherPlugin {
         let filter = Arc::new(rune_core::DefaultFileFilter::new(config));
 
         // Start watching the current directory
-        if let Some(watcher) = &mut self.watcher {
-            if let Err(e) = watcher.watch(&current_dir, RecursiveMode::NonRecursive) {
+        let watched = self
+            .watcher
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(|watcher| watcher.watch(&current_dir, RecursiveMode::NonRecursive));
+        if let Some(watched) = watched {
+            if let Err(e) = watched {
                 warn!("Failed to start watching current directory: {}", e);
             } else {
                 // Store the watched path
@@ -411,7 +660,7 @@ impl Plugin for FileWatcherPlugin {
                 let watched_path = WatchedPath {
                     path: current_dir.clone(),
                     recursive: false,
-                    filter,
+                    filter: filter.clone(),
                 };
 
                 {
@@ -426,11 +675,28 @@ impl Plugin for FileWatcherPlugin {
             }
         }
 
+        // Watch every served root, including the subdirectories of directory roots
+        for root in context.state_manager.served_roots().await {
+            for dir in root.watch_dirs() {
+                if self.is_watching(&dir).await {
+                    continue;
+                }
+                if let Err(e) = self.watch(&dir, filter.clone()).await {
+                    warn!("Failed to watch served root {}: {}", dir.display(), e);
+                }
+            }
+        }
+
+        self.watch_template_files(&context.config).await;
+
         self.status = PluginStatus::Active;
 
         // Subscribe to system events for better integration
         let handler = Arc::new(FileWatcherEventHandler {
             plugin_name: self.name.clone(),
+            watcher: self.watcher.clone(),
+            watched_paths: self.watched_paths.clone(),
+            filter,
         });
 
         if let Err(e) = context.event_bus.subscribe_system_events(handler).await {
@@ -458,8 +724,12 @@ impl Plugin for FileWatcherPlugin {
             }
         }
 
+        if let Some(context) = &self.context {
+            context.supervisor.stop(WATCHER_TASK).await;
+        }
+
         // Drop the watcher
-        self.watcher = None;
+        *self.watcher.lock().unwrap() = None;
         self.event_sender = None;
         self.context = None;
 
@@ -497,7 +767,7 @@ impl FileWatcher for FileWatcherPlugin {
         );
 
         // Add to watcher if we have one
-        if let Some(watcher) = &mut self.watcher {
+        if let Some(watcher) = self.watcher.lock().unwrap().as_mut() {
             let recursive_mode = if filter.filter_name().contains("recursive") {
                 RecursiveMode::Recursive
             } else {
@@ -534,7 +804,7 @@ impl FileWatcher for FileWatcherPlugin {
         if let Some(path) = path {
             info!("Stopping watch for path: {}", path.display());
 
-            if let Some(watcher) = &mut self.watcher {
+            if let Some(watcher) = self.watcher.lock().unwrap().as_mut() {
                 watcher
                     .unwatch(&path)
                     .map_err(|e| RuneError::Plugin(format!("Failed to unwatch path: {}", e)))?;
@@ -576,6 +846,53 @@ impl FileWatcher for FileWatcherPlugin {
 /// Event handler for system events
 pub struct FileWatcherEventHandler {
     plugin_name: String,
+    watcher: Arc<Mutex<Option<RecommendedWatcher>>>,
+    watched_paths: Arc<RwLock<HashMap<WatcherId, WatchedPath>>>,
+    /// Filter for directories of files created elsewhere, like the editor
+    /// saving a scratch buffer
+    filter: Arc<dyn FileFilter>,
+}
+
+impl FileWatcherEventHandler {
+    /// Start watching the directory of a file created outside of the
+    /// watched directories
+    async fn watch_created_file(&self, path: &Path) {
+        let Some(dir) = path
+            .canonicalize()
+            .ok()
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+        else {
+            return;
+        };
+        let mut watched_paths = self.watched_paths.write().await;
+        if watched_paths.values().any(|watched| {
+            watched.path == dir || (watched.recursive && dir.starts_with(&watched.path))
+        }) {
+            return;
+        }
+
+        let watched = self
+            .watcher
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(|watcher| watcher.watch(&dir, RecursiveMode::NonRecursive));
+        match watched {
+            Some(Ok(())) => {
+                info!("Started watching directory of new file: {}", dir.display());
+                watched_paths.insert(
+                    WatcherId::new(),
+                    WatchedPath {
+                        path: dir,
+                        recursive: false,
+                        filter: self.filter.clone(),
+                    },
+                );
+            }
+            Some(Err(e)) => warn!("Failed to watch {}: {}", dir.display(), e),
+            None => {}
+        }
+    }
 }
 
 #[async_trait]
@@ -587,23 +904,31 @@ impl SystemEventHandler for FileWatcherEventHandler {
                 message,
                 severity,
                 ..
-            } => {
+            }
                 // If there's a critical error from another component, we might need to adjust our behavior
                 if source == "file-system"
                     && matches!(severity, rune_core::event::ErrorSeverity::Critical)
-                {
+                => {
                     warn!(
                         "Critical file system error detected, may affect file watching: {}",
                         message
                     );
                 }
-            }
             SystemEvent::PluginLoaded { plugin_name, .. } => {
                 debug!(
                     "Plugin {} loaded, file watcher ready for integration",
                     plugin_name
                 );
             }
+            SystemEvent::FileChanged {
+                path,
+                change_type: ChangeType::Created,
+                ..
+            } => {
+                // Files created by other plugins may live outside of the
+                // watched directories
+                self.watch_created_file(path).await;
+            }
             SystemEvent::FileChanged { path, .. } => {
                 debug!(
                     "FileWatcher received file change event for: {}",
@@ -0,0 +1,251 @@
+//! Deterministic simulation of the file watcher
+//!
+//! A [`Simulation`] runs a [`FileWatcherPlugin`] without the file system or
+//! the system clock: tests watch directories that need not exist, inject the
+//! events the operating system would send, and [advance](Simulation::advance)
+//! a [`VirtualClock`] to see which changes come out of debouncing. Errors
+//! can be injected as well, to drive the watcher into recovery.
+//!
+//! Available in the crate's tests and with the `sim` feature.
+
+pub use crate::clock::VirtualClock;
+
+use crate::{FileWatcherPlugin, MAX_CONSECUTIVE_ERRORS};
+use notify::event::{CreateKind, DataChange, ModifyKind, RemoveKind, RenameMode};
+use notify::{Event, EventKind};
+use rune_core::{FileFilter, FileWatcher, PluginContext, Result, WatchStatistics, WatcherId};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A file watcher driven by hand
+pub struct Simulation {
+    plugin: FileWatcherPlugin,
+    clock: Arc<VirtualClock>,
+    /// Errors in a row, as the event loop would count them
+    error_count: u32,
+}
+
+impl Simulation {
+    /// Watcher publishing on the event bus of `context`, watching nothing yet
+    pub fn new(context: PluginContext) -> Self {
+        let clock = Arc::new(VirtualClock::new());
+        let mut plugin = FileWatcherPlugin::new();
+        plugin.context = Some(context);
+        plugin.clock = clock.clone();
+        Self {
+            plugin,
+            clock,
+            error_count: 0,
+        }
+    }
+
+    pub fn clock(&self) -> &VirtualClock {
+        &self.clock
+    }
+
+    pub fn plugin(&self) -> &FileWatcherPlugin {
+        &self.plugin
+    }
+
+    /// Report the changes under `dir` that `filter` lets through; the
+    /// directory is never looked at
+    pub async fn watch(&mut self, dir: &Path, filter: Arc<dyn FileFilter>) -> Result<WatcherId> {
+        self.plugin.watch(dir, filter).await
+    }
+
+    /// Feed `event` to the watcher as if the file system sent it
+    pub async fn inject(&mut self, event: Event) -> Result<()> {
+        self.error_count = 0;
+        self.plugin.handle_file_event(event).await
+    }
+
+    pub async fn create(&mut self, path: &Path) -> Result<()> {
+        self.inject(event(EventKind::Create(CreateKind::File), &[path]))
+            .await
+    }
+
+    pub async fn modify(&mut self, path: &Path) -> Result<()> {
+        self.inject(event(
+            EventKind::Modify(ModifyKind::Data(DataChange::Content)),
+            &[path],
+        ))
+        .await
+    }
+
+    pub async fn remove(&mut self, path: &Path) -> Result<()> {
+        self.inject(event(EventKind::Remove(RemoveKind::File), &[path]))
+            .await
+    }
+
+    /// Rename `from` to `to` the way Linux reports it: the old name, the
+    /// new name, then both together
+    pub async fn rename(&mut self, from: &Path, to: &Path) -> Result<()> {
+        let name = |mode| EventKind::Modify(ModifyKind::Name(mode));
+        self.inject(event(name(RenameMode::From), &[from])).await?;
+        self.inject(event(name(RenameMode::To), &[to])).await?;
+        self.inject(event(name(RenameMode::Both), &[from, to]))
+            .await
+    }
+
+    /// Make the watcher fail with `message`; the
+    /// [`MAX_CONSECUTIVE_ERRORS`]th failure in a row makes it recover
+    pub async fn fail(&mut self, message: &str) {
+        self.plugin
+            .handle_watcher_error(&mut self.error_count, notify::Error::generic(message))
+            .await;
+    }
+
+    /// Failures in a row since the last event or recovery
+    pub fn consecutive_errors(&self) -> u32 {
+        self.error_count
+    }
+
+    /// Failures in a row that make the watcher recover
+    pub fn errors_before_recovery() -> u32 {
+        MAX_CONSECUTIVE_ERRORS
+    }
+
+    /// Let `duration` pass, then publish the changes whose debounce
+    /// period is over
+    pub async fn advance(&mut self, duration: Duration) -> Result<()> {
+        self.clock.advance(duration);
+        self.plugin.process_debounced_events().await
+    }
+
+    pub async fn statistics(&self) -> WatchStatistics {
+        self.plugin.get_watch_statistics().await
+    }
+}
+
+fn event(kind: EventKind, paths: &[&Path]) -> Event {
+    paths.iter().fold(Event::new(kind), |event, path| {
+        event.add_path(path.to_path_buf())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::event::{ChangeType, SystemEvent};
+    use rune_core::{Config, DefaultFileFilter, FileWatcherConfig, StateManager};
+    use rune_test_utils::MockEventBus;
+    use std::path::PathBuf;
+
+    fn simulation() -> (Simulation, Arc<MockEventBus>) {
+        let bus = Arc::new(MockEventBus::new());
+        let context = PluginContext::new(
+            bus.clone(),
+            Arc::new(Config::new()),
+            Arc::new(StateManager::new()),
+        );
+        (Simulation::new(context), bus)
+    }
+
+    fn markdown_filter(debounce_ms: u64) -> Arc<dyn FileFilter> {
+        Arc::new(DefaultFileFilter::new(FileWatcherConfig {
+            debounce_ms,
+            watch_extensions: vec!["md".to_string()],
+            ignore_patterns: Vec::new(),
+            recursive: true,
+            max_depth: None,
+        }))
+    }
+
+    fn changes(bus: &MockEventBus) -> Vec<(PathBuf, ChangeType)> {
+        bus.published()
+            .into_iter()
+            .filter_map(|event| match event {
+                SystemEvent::FileChanged {
+                    path, change_type, ..
+                } => Some((path, change_type)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_published_once_the_debounce_period_passes() {
+        let (mut sim, bus) = simulation();
+        let dir = Path::new("/virtual/notes");
+        sim.watch(dir, markdown_filter(200)).await.unwrap();
+        let file = dir.join("todo.md");
+
+        sim.create(&file).await.unwrap();
+        sim.advance(Duration::from_millis(150)).await.unwrap();
+        // Saving again restarts the period
+        sim.modify(&file).await.unwrap();
+        sim.advance(Duration::from_millis(150)).await.unwrap();
+        sim.modify(&dir.join("image.png")).await.unwrap();
+        assert!(changes(&bus).is_empty());
+        assert_eq!(sim.statistics().await.pending_events_count, 1);
+
+        sim.advance(Duration::from_millis(50)).await.unwrap();
+        assert_eq!(changes(&bus), vec![(file.clone(), ChangeType::Modified)]);
+        assert_eq!(sim.statistics().await.total_events_processed, 1);
+        assert_eq!(sim.clock().elapsed(), Duration::from_millis(350));
+
+        // Outside of the watched directory nothing is reported
+        sim.remove(Path::new("/elsewhere/todo.md")).await.unwrap();
+        sim.advance(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(changes(&bus).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_renames_are_detected() {
+        let (mut sim, bus) = simulation();
+        let dir = Path::new("/virtual/notes");
+        sim.watch(dir, markdown_filter(100)).await.unwrap();
+        let (from, to) = (dir.join("draft.md"), dir.join("final.md"));
+
+        sim.rename(&from, &to).await.unwrap();
+        sim.advance(Duration::from_millis(100)).await.unwrap();
+        assert_eq!(
+            changes(&bus),
+            vec![(
+                to.clone(),
+                ChangeType::Renamed {
+                    from: from.clone(),
+                    to
+                }
+            )]
+        );
+
+        // Moving a document out of the watched directory removes it
+        bus.clear();
+        sim.rename(&from, Path::new("/archive/draft.md"))
+            .await
+            .unwrap();
+        sim.advance(Duration::from_millis(100)).await.unwrap();
+        assert_eq!(changes(&bus), vec![(from, ChangeType::Deleted)]);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_errors_make_the_watcher_recover() {
+        let (mut sim, bus) = simulation();
+        let dir = Path::new("/virtual/notes");
+        sim.watch(dir, markdown_filter(100)).await.unwrap();
+        sim.modify(&dir.join("todo.md")).await.unwrap();
+
+        for _ in 1..Simulation::errors_before_recovery() {
+            sim.fail("inotify queue overflow").await;
+        }
+        assert_eq!(
+            sim.consecutive_errors(),
+            Simulation::errors_before_recovery() - 1
+        );
+        assert_eq!(sim.statistics().await.pending_events_count, 1);
+
+        // Recovery drops the pending changes and waits a second, on the
+        // virtual clock
+        sim.fail("inotify queue overflow").await;
+        assert_eq!(sim.consecutive_errors(), 0);
+        assert_eq!(sim.statistics().await.pending_events_count, 0);
+        assert_eq!(sim.clock().elapsed(), Duration::from_secs(1));
+        assert_eq!(
+            bus.published_of("error").len(),
+            Simulation::errors_before_recovery() as usize + 1
+        );
+        assert!(changes(&bus).is_empty());
+    }
+}
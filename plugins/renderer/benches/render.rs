@@ -0,0 +1,133 @@
+//! Markdown rendering benchmarks
+//!
+//! Run with `cargo bench -p rune-renderer --bench render`; criterion's
+//! `--save-baseline` and `--baseline` options compare runs.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures_util::TryStreamExt;
+use rune_core::renderer::DEFAULT_STREAM_CHUNK_BYTES;
+use rune_core::{ContentRenderer, RenderContext, RendererRegistry};
+use rune_renderer::{MarkdownRenderer, MermaidRenderer};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Markdown of roughly `bytes` bytes mixing headings, prose, lists, tables
+/// and code blocks
+fn sample_markdown(bytes: usize) -> String {
+    let mut document = String::with_capacity(bytes + 1024);
+    let mut section = 0;
+    while document.len() < bytes {
+        section += 1;
+        document.push_str(&format!(
+            "## Section {section}\n\n\
+            Rune renders *markdown* with **live reload**, `inline code` and \
+            [links](https://example.com/{section}). This paragraph exists to give \
+            the parser some prose to chew through in section {section}.\n\n\
+            - first item\n- second item with `code`\n  - nested item\n\n\
+            | Column | Value |\n|--------|-------|\n| alpha | {section} |\n| beta | {section} |\n\n\
+            ```rust\nfn section_{section}() -> usize {{\n    {section}\n}}\n```\n\n\
+            > A quote closing section {section}.\n\n"
+        ));
+    }
+    document
+}
+
+fn context() -> RenderContext {
+    RenderContext::new(
+        PathBuf::from("bench.md"),
+        PathBuf::from("."),
+        "default".to_string(),
+    )
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime")
+}
+
+fn markdown_renderer(c: &mut Criterion) {
+    let runtime = runtime();
+    let context = context();
+    let renderer = MarkdownRenderer::new();
+    let mut group = c.benchmark_group("markdown_renderer");
+    // Render time grows faster than linearly with document size, so the huge
+    // document gets the fewest samples criterion accepts
+    for (size, bytes) in [
+        ("small", 2 * 1024),
+        ("medium", 100 * 1024),
+        ("huge", 1024 * 1024),
+    ] {
+        let document = sample_markdown(bytes);
+        if size == "huge" {
+            group
+                .sample_size(10)
+                .measurement_time(Duration::from_secs(30));
+        }
+        group.throughput(Throughput::Bytes(document.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &document,
+            |b, document| {
+                b.to_async(&runtime)
+                    .iter(|| async { renderer.render(document, &context).await.unwrap() })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn pipeline(c: &mut Criterion) {
+    let runtime = runtime();
+    let context = context();
+    let registry = runtime.block_on(async {
+        let registry = Arc::new(RendererRegistry::new());
+        registry
+            .register_renderer(Box::new(MarkdownRenderer::new()))
+            .await
+            .unwrap();
+        registry
+            .register_renderer(Box::new(MermaidRenderer::new()))
+            .await
+            .unwrap();
+        registry
+    });
+
+    // Cost of the registry lookup and the chained pipeline over a bare render
+    let small = sample_markdown(2 * 1024);
+    let mut group = c.benchmark_group("pipeline");
+    group.bench_function("registry_small", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { registry.render_content(&small, &context).await.unwrap() })
+    });
+    group.bench_function("chained_small", |b| {
+        b.to_async(&runtime).iter(|| async {
+            registry
+                .render_with_pipeline(&small, &context)
+                .await
+                .unwrap()
+        })
+    });
+    group.finish();
+
+    // Block-by-block render of the huge document, as the preview streams it
+    let huge = sample_markdown(1024 * 1024);
+    let mut group = c.benchmark_group("render_stream");
+    group
+        .sample_size(10)
+        .measurement_time(Duration::from_secs(30));
+    group.bench_function("huge", |b| {
+        b.to_async(&runtime).iter(|| {
+            registry
+                .clone()
+                .render_stream(huge.clone(), context.clone(), DEFAULT_STREAM_CHUNK_BYTES)
+                .try_collect::<Vec<String>>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, markdown_renderer, pipeline);
+criterion_main!(benches);
@@ -0,0 +1,404 @@
+//! Accessibility checks of rendered HTML
+//!
+//! An optional last pipeline stage that leaves the HTML as it is and
+//! reports problems in the render metadata: images without alt text,
+//! skipped heading levels and links without text. Themes are checked apart
+//! from documents, for text colours with too little contrast to their
+//! background.
+
+use async_trait::async_trait;
+use regex::Regex;
+use rune_core::{
+    ContentRenderer, Plugin, PluginContext, PluginStatus, RenderContext, RenderMetadata,
+    RenderResult, Result, RuneError,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Minimum contrast ratio of body text (WCAG 2 level AA)
+pub const MIN_CONTRAST_RATIO: f64 = 4.5;
+
+/// Theme variables drawn on top of each other, as (foreground, background)
+const CONTRAST_PAIRS: &[(&str, &str)] = &[
+    ("--text-color", "--bg-color"),
+    ("--link-color", "--bg-color"),
+    ("--blockquote-color", "--bg-color"),
+    ("--text-color", "--code-bg"),
+    ("--text-color", "--table-header-bg"),
+];
+
+/// Kind of accessibility problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum A11yRule {
+    MissingAlt,
+    SkippedHeading,
+    EmptyLink,
+    LowContrast,
+}
+
+impl A11yRule {
+    /// Name of the rule in reports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::MissingAlt => "missing-alt",
+            Self::SkippedHeading => "skipped-heading",
+            Self::EmptyLink => "empty-link",
+            Self::LowContrast => "low-contrast",
+        }
+    }
+}
+
+/// An accessibility problem found in a document or theme
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct A11yIssue {
+    pub rule: A11yRule,
+    pub message: String,
+    /// Offending markup, or the theme and variables for contrast issues
+    pub context: String,
+}
+
+impl A11yIssue {
+    fn new(rule: A11yRule, message: impl Into<String>, markup: &str) -> Self {
+        const MAX_CONTEXT_CHARS: usize = 80;
+        let mut context: String = markup.chars().take(MAX_CONTEXT_CHARS).collect();
+        if context.len() < markup.len() {
+            context.push('…');
+        }
+        Self {
+            rule,
+            message: message.into(),
+            context,
+        }
+    }
+}
+
+/// Colour of a `#rgb` or `#rrggbb` value as red, green and blue
+fn parse_hex_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.trim().strip_prefix('#')?;
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+    match hex.len() {
+        3 => {
+            let mut rgb = [0; 3];
+            for (i, c) in hex.chars().enumerate() {
+                rgb[i] = channel(&c.to_string())? * 17;
+            }
+            Some(rgb)
+        }
+        6 => Some([
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        ]),
+        _ => None,
+    }
+}
+
+/// Relative luminance of a colour
+fn luminance([r, g, b]: [u8; 3]) -> f64 {
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// Contrast ratio of two colours, from 1 to 21
+pub fn contrast_ratio(a: [u8; 3], b: [u8; 3]) -> f64 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Custom properties declared in `css`, by name
+pub fn theme_variables(css: &str) -> HashMap<String, String> {
+    css.split([';', '{', '}'])
+        .filter_map(|declaration| {
+            let (name, value) = declaration.split_once(':')?;
+            let name = name.trim();
+            name.starts_with("--")
+                .then(|| (name.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Text colour combinations of a theme below [`MIN_CONTRAST_RATIO`]
+pub fn check_theme_contrast(theme: &str, css: &str) -> Vec<A11yIssue> {
+    let variables = theme_variables(css);
+    CONTRAST_PAIRS
+        .iter()
+        .filter_map(|(foreground, background)| {
+            let fg = parse_hex_color(variables.get(*foreground)?)?;
+            let bg = parse_hex_color(variables.get(*background)?)?;
+            let ratio = contrast_ratio(fg, bg);
+            (ratio < MIN_CONTRAST_RATIO).then(|| {
+                A11yIssue::new(
+                    A11yRule::LowContrast,
+                    format!(
+                        "{} on {} has a contrast of {:.2}:1, below {}:1",
+                        variables[*foreground], variables[*background], ratio, MIN_CONTRAST_RATIO
+                    ),
+                    &format!("{}: {} on {}", theme, foreground, background),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Pipeline stage reporting accessibility problems
+pub struct A11yChecker {
+    name: String,
+    version: String,
+    status: PluginStatus,
+    img: Regex,
+    alt: Regex,
+    heading: Regex,
+    link: Regex,
+    label: Regex,
+}
+
+impl A11yChecker {
+    /// Create a checker of document markup
+    pub fn new() -> Result<Self> {
+        let pattern = |re: &str| {
+            Regex::new(re)
+                .map_err(|e| RuneError::Plugin(format!("Regex compilation failed: {}", e)))
+        };
+        Ok(Self {
+            name: "a11y-checker".to_string(),
+            version: "0.1.0".to_string(),
+            status: PluginStatus::Loading,
+            img: pattern(r"<img\b[^>]*>")?,
+            alt: pattern(r#"\salt="([^"]*)""#)?,
+            heading: pattern(r"<h([1-6])\b[^>]*>")?,
+            link: pattern(r"(?s)<a\b([^>]*)>(.*?)</a>")?,
+            label: pattern(r#"\s(?:aria-label|title)="\s*[^"\s][^"]*""#)?,
+        })
+    }
+
+    /// Problems in the markup of `html`, in document order per rule
+    pub fn check_html(&self, html: &str) -> Vec<A11yIssue> {
+        let mut issues = Vec::new();
+
+        for img in self.img.find_iter(html) {
+            let alt = self.alt.captures(img.as_str());
+            if alt.is_none_or(|alt| alt[1].trim().is_empty()) {
+                issues.push(A11yIssue::new(
+                    A11yRule::MissingAlt,
+                    "Image has no alt text",
+                    img.as_str(),
+                ));
+            }
+        }
+
+        let mut previous: Option<u8> = None;
+        for heading in self.heading.captures_iter(html) {
+            let level = heading[1].parse::<u8>().unwrap_or(1);
+            if let Some(previous) = previous.filter(|&previous| level > previous + 1) {
+                issues.push(A11yIssue::new(
+                    A11yRule::SkippedHeading,
+                    format!("Heading level {} follows level {}", level, previous),
+                    &heading[0],
+                ));
+            }
+            previous = Some(level);
+        }
+
+        for link in self.link.captures_iter(html) {
+            let (attributes, content) = (&link[1], &link[2]);
+            let has_text = !strip_tags(content).trim().is_empty()
+                || self
+                    .alt
+                    .captures_iter(content)
+                    .any(|alt| !alt[1].trim().is_empty());
+            if !has_text && !self.label.is_match(attributes) {
+                issues.push(A11yIssue::new(
+                    A11yRule::EmptyLink,
+                    "Link has no text",
+                    &link[0],
+                ));
+            }
+        }
+
+        issues
+    }
+}
+
+/// Text of `html` without its tags
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+#[async_trait]
+impl Plugin for A11yChecker {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn dependencies(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    async fn initialize(&mut self, _context: &PluginContext) -> Result<()> {
+        tracing::info!("Initializing accessibility checker plugin");
+        self.status = PluginStatus::Active;
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        tracing::info!("Shutting down accessibility checker plugin");
+        self.status = PluginStatus::Stopped;
+        Ok(())
+    }
+
+    fn status(&self) -> PluginStatus {
+        self.status.clone()
+    }
+
+    fn provided_services(&self) -> Vec<&str> {
+        vec!["a11y-checks"]
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[async_trait]
+impl ContentRenderer for A11yChecker {
+    fn can_render(&self, content_type: &str) -> bool {
+        matches!(content_type, "text/html" | "application/html")
+    }
+
+    async fn render(&self, content: &str, context: &RenderContext) -> Result<RenderResult> {
+        let start_time = Instant::now();
+        let issues = self.check_html(content);
+        if !issues.is_empty() {
+            tracing::debug!(
+                "{} accessibility issues in {:?}",
+                issues.len(),
+                context.file_path
+            );
+        }
+
+        let mut custom_metadata = HashMap::new();
+        custom_metadata.insert(
+            "issue_count".to_string(),
+            serde_json::Value::Number(issues.len().into()),
+        );
+        custom_metadata.insert("issues".to_string(), serde_json::to_value(&issues)?);
+
+        let metadata = RenderMetadata {
+            renderer_name: self.name.clone(),
+            renderer_version: self.version.clone(),
+            render_time_ms: Some(start_time.elapsed().as_millis() as u64),
+            content_hash: Some(format!("{:x}", content.len() as u64)),
+            custom_metadata,
+        };
+
+        Ok(RenderResult::new(content.to_string()).with_metadata(metadata))
+    }
+
+    fn supported_extensions(&self) -> Vec<&str> {
+        vec!["html", "htm"]
+    }
+
+    fn priority(&self) -> u32 {
+        10 // Last, so it sees what the other stages produced
+    }
+
+    fn is_expensive(&self) -> bool {
+        true // Walks the whole document
+    }
+
+    fn renderer_metadata(&self) -> RenderMetadata {
+        let mut custom_metadata = HashMap::new();
+        custom_metadata.insert(
+            "features".to_string(),
+            serde_json::json!([
+                "missing_alt",
+                "skipped_heading",
+                "empty_link",
+                "low_contrast"
+            ]),
+        );
+
+        RenderMetadata {
+            renderer_name: self.name.clone(),
+            renderer_version: self.version.clone(),
+            render_time_ms: None,
+            content_hash: None,
+            custom_metadata,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markup_issues() {
+        let checker = A11yChecker::new().unwrap();
+        let html = concat!(
+            "<h1>Title</h1>\n<h3>Details</h3>\n<h2>Back</h2>\n<h4 id=\"x\">Deep</h4>\n",
+            "<p><img src=\"a.png\" alt=\"\" /><img src=\"b.png\" alt=\"Chart\" /><img src=\"c.png\"></p>\n",
+            "<p><a href=\"/a\"></a> <a href=\"/b\"><img src=\"d.png\" alt=\"Home\"></a>",
+            " <a href=\"/c\" aria-label=\"Close\"><span></span></a> <a href=\"/d\"> <em> </em></a></p>",
+        );
+
+        let issues = checker.check_html(html);
+        let found: Vec<_> = issues
+            .iter()
+            .map(|issue| (issue.rule, issue.context.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (A11yRule::MissingAlt, r#"<img src="a.png" alt="" />"#),
+                (A11yRule::MissingAlt, r#"<img src="c.png">"#),
+                (A11yRule::SkippedHeading, "<h3>"),
+                (A11yRule::SkippedHeading, r#"<h4 id="x">"#),
+                (A11yRule::EmptyLink, r#"<a href="/a"></a>"#),
+                (A11yRule::EmptyLink, r#"<a href="/d"> <em> </em></a>"#),
+            ]
+        );
+        assert_eq!(issues[2].message, "Heading level 3 follows level 1");
+    }
+
+    #[test]
+    fn test_theme_contrast() {
+        assert_eq!(parse_hex_color("#fff"), Some([255, 255, 255]));
+        assert_eq!(parse_hex_color("#0366d6"), Some([3, 102, 214]));
+        assert_eq!(parse_hex_color("red"), None);
+        assert!((contrast_ratio([0, 0, 0], [255, 255, 255]) - 21.0).abs() < 1e-9);
+
+        let css = ":root {\n  --bg-color: #fff;\n  --text-color: #333;\n  --link-color: #99bbff;\n  --code-bg: var(--x);\n}";
+        let issues = check_theme_contrast("light", css);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, A11yRule::LowContrast);
+        assert_eq!(issues[0].context, "light: --link-color on --bg-color");
+    }
+}
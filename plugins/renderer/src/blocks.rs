@@ -0,0 +1,249 @@
+//! Splitting markdown at top-level block boundaries for streamed renders
+//!
+//! Pieces end on a blank line that is followed by a new top-level block, so
+//! fenced code, raw HTML blocks, indented code and lists are never cut in
+//! half. Link reference definitions are copied into every piece, so
+//! `[text][label]` links still resolve; documents with footnotes stay whole
+//! because their definitions are collected at the end of the output.
+
+/// Split `content` into pieces of at least `target_bytes`, each ending at a
+/// top-level block boundary
+pub(crate) fn split_top_level_blocks(content: &str, target_bytes: usize) -> Vec<String> {
+    if content.len() <= target_bytes {
+        return vec![content.to_string()];
+    }
+
+    let mut scanner = Scanner::default();
+    let mut boundaries = Vec::new();
+    let mut definitions = String::new();
+    let mut chunk_start = 0;
+    let mut offset = 0;
+    // End of the latest blank line at top level, if no text followed it yet
+    let mut pending_boundary = None;
+    // Whether the current top-level block is a list, which a blank line and
+    // another item continue
+    let mut in_list = false;
+
+    for line in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        if scanner.in_block() {
+            scanner.scan(line);
+            continue;
+        }
+        if line.trim().is_empty() {
+            pending_boundary = Some(offset);
+            continue;
+        }
+        if is_footnote_definition(line) {
+            return vec![content.to_string()];
+        }
+        let starts_block = pending_boundary.is_some() || line_start == 0;
+        if !line.starts_with([' ', '\t']) {
+            let item = is_list_item(line);
+            let continues_list = in_list && item;
+            if item || starts_block {
+                in_list = item;
+            }
+            if let Some(boundary) = pending_boundary.filter(|_| !continues_list) {
+                if boundary - chunk_start >= target_bytes {
+                    boundaries.push(boundary);
+                    chunk_start = line_start;
+                }
+            }
+        }
+        pending_boundary = None;
+        if is_link_definition(line) {
+            definitions.push_str(line.trim_end());
+            definitions.push('\n');
+        }
+        scanner.scan(line);
+    }
+
+    if boundaries.is_empty() {
+        return vec![content.to_string()];
+    }
+
+    // Definitions appended to an unclosed fence would show up as code
+    let ends_in_block = scanner.in_block();
+    let mut pieces = Vec::with_capacity(boundaries.len() + 1);
+    let mut start = 0;
+    for end in boundaries.into_iter().chain(std::iter::once(content.len())) {
+        let mut piece = content[start..end].to_string();
+        let inside_block = end == content.len() && ends_in_block;
+        if !definitions.is_empty() && !inside_block {
+            if !piece.ends_with('\n') {
+                piece.push('\n');
+            }
+            piece.push('\n');
+            piece.push_str(&definitions);
+        }
+        pieces.push(piece);
+        start = end;
+    }
+    pieces
+}
+
+/// Tracks fenced code and raw HTML blocks, which may contain blank lines
+#[derive(Default)]
+struct Scanner {
+    /// Fence character and length of the open code fence
+    fence: Option<(char, usize)>,
+    /// Text that closes the open raw HTML block
+    html_end: Option<&'static str>,
+}
+
+impl Scanner {
+    fn in_block(&self) -> bool {
+        self.fence.is_some() || self.html_end.is_some()
+    }
+
+    fn scan(&mut self, line: &str) {
+        if let Some((fence_char, fence_len)) = self.fence {
+            let trimmed = line.trim();
+            if leading_spaces(line) <= 3
+                && trimmed.chars().take_while(|&c| c == fence_char).count() >= fence_len
+                && trimmed.chars().all(|c| c == fence_char)
+            {
+                self.fence = None;
+            }
+            return;
+        }
+        if let Some(end) = self.html_end {
+            if line.to_ascii_lowercase().contains(end) {
+                self.html_end = None;
+            }
+            return;
+        }
+
+        if leading_spaces(line) > 3 {
+            return;
+        }
+        let trimmed = line.trim_start();
+        for fence_char in ['`', '~'] {
+            let fence_len = trimmed.chars().take_while(|&c| c == fence_char).count();
+            if fence_len >= 3 {
+                self.fence = Some((fence_char, fence_len));
+                return;
+            }
+        }
+
+        let lower = trimmed.to_ascii_lowercase();
+        let html_end = if lower.starts_with("<!--") {
+            Some("-->")
+        } else if lower.starts_with("<pre") {
+            Some("</pre>")
+        } else if lower.starts_with("<script") {
+            Some("</script>")
+        } else if lower.starts_with("<style") {
+            Some("</style>")
+        } else if lower.starts_with("<textarea") {
+            Some("</textarea>")
+        } else {
+            None
+        };
+        // The closing text may be on the opening line
+        if let Some(end) = html_end {
+            let opening_len = if end == "-->" { 4 } else { 1 };
+            if !lower[opening_len..].contains(end) {
+                self.html_end = Some(end);
+            }
+        }
+    }
+}
+
+fn leading_spaces(line: &str) -> usize {
+    line.chars().take_while(|&c| c == ' ').count()
+}
+
+/// Whether a line starts a list item; thematic breaks such as `- - -` or
+/// `***` do not
+fn is_list_item(line: &str) -> bool {
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    if marks.len() >= 3 && (marks.chars().all(|c| c == '-') || marks.chars().all(|c| c == '*')) {
+        return false;
+    }
+    let item_text = |rest: &str| rest.is_empty() || rest.starts_with([' ', '\t', '\n', '\r']);
+    let bullet = line.strip_prefix(['-', '*', '+']).is_some_and(item_text);
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let ordered = (1..=9).contains(&digits)
+        && line[digits..]
+            .strip_prefix(['.', ')'])
+            .is_some_and(item_text);
+    bullet || ordered
+}
+
+fn is_link_definition(line: &str) -> bool {
+    leading_spaces(line) <= 3
+        && line
+            .trim_start()
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once("]:"))
+            .is_some_and(|(label, _)| !label.is_empty() && !label.starts_with('^'))
+}
+
+fn is_footnote_definition(line: &str) -> bool {
+    leading_spaces(line) <= 3
+        && line
+            .trim_start()
+            .strip_prefix("[^")
+            .is_some_and(|rest| rest.contains("]:"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(markdown: &str) -> String {
+        let mut options = markdown::Options::gfm();
+        options.compile.allow_dangerous_html = true;
+        markdown::to_html_with_options(markdown, &options).unwrap()
+    }
+
+    #[test]
+    fn test_pieces_render_like_the_whole_document() {
+        let document = "# Title\n\nIntro with a [reference][docs].\n\n\
+            ```rust\nfn main() {\n\n    println!(\"blank line above\");\n}\n```\n\n\
+            - one\n\n- two\n\n  continued\n\n\
+            <!-- a comment\n\nspanning blank lines -->\n\n\
+            | a | b |\n|---|---|\n| 1 | 2 |\n\n\
+            Closing [reference][docs].\n\n[docs]: https://example.com/docs\n";
+
+        let pieces = split_top_level_blocks(document, 1);
+        assert!(pieces.len() > 3);
+        assert!(pieces
+            .iter()
+            .any(|piece| piece.starts_with("```rust") && piece.contains("blank line above")));
+        assert!(pieces
+            .iter()
+            .any(|piece| piece.starts_with("- one") && piece.contains("continued")));
+
+        let normalize = |html: &str| html.replace('\n', "");
+        let streamed: String = pieces.iter().map(|piece| render(piece)).collect();
+        assert_eq!(normalize(&streamed), normalize(&render(document)));
+
+        let sections: String = (1..=200)
+            .map(|n| {
+                format!(
+                    "## Section {n}\n\nProse with `code` and [a link](https://example.com/{n}).\n\n\
+                    - item\n  - nested\n\n| a | b |\n|---|---|\n| {n} | {n} |\n\n\
+                    ```rust\nfn f() {{}}\n```\n\n> Quote {n}.\n\n"
+                )
+            })
+            .collect();
+        let document = format!("Intro\n- tight\n\n- loose\n\n{}", sections);
+        let pieces = split_top_level_blocks(&document, 4096);
+        assert!(pieces.len() > 5);
+        let streamed: String = pieces.iter().map(|piece| render(piece)).collect();
+        assert_eq!(normalize(&streamed), normalize(&render(&document)));
+    }
+
+    #[test]
+    fn test_small_and_footnoted_documents_stay_whole() {
+        assert_eq!(split_top_level_blocks("# One\n\nTwo\n", 1024).len(), 1);
+
+        let footnotes = "Text with a note.[^1]\n\nMore text.\n\n[^1]: The note.\n";
+        assert_eq!(split_top_level_blocks(footnotes, 1), vec![footnotes]);
+    }
+}
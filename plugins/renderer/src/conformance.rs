@@ -0,0 +1,303 @@
+//! CommonMark and GFM conformance
+//!
+//! The CommonMark and GFM specs keep their examples in `spec.txt` files: a
+//! fenced `example` block holds the markdown, a `.` line, then the HTML it
+//! must render to. This runs such examples through the renderer's markdown
+//! to HTML step, before rune's own additions (heading anchors, numbering,
+//! typography) which no spec describes, and compares the output with the
+//! expected HTML.
+//!
+//! Not every example passes, so the examples known to fail are recorded in
+//! a [`Baseline`]; a run that fails a different set of examples is a change
+//! in rendering to look into, and bless into the baseline once it is
+//! intended. Both specs are [bundled](BUNDLED) in full with their baselines:
+//! CommonMark 0.31.2 and GFM 0.28, and a test fails on any regression.
+
+use crate::markdown_options;
+use rune_core::{Result, RuneError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Spec shipped with the renderer
+#[derive(Debug, Clone, Copy)]
+pub struct BundledSuite {
+    pub name: &'static str,
+    pub spec: &'static str,
+    pub baseline: &'static str,
+}
+
+/// Specs run by `rune conformance` when no spec is given
+pub const BUNDLED: &[BundledSuite] = &[
+    BundledSuite {
+        name: "commonmark-spec.txt",
+        spec: include_str!("../conformance/commonmark-spec.txt"),
+        baseline: include_str!("../conformance/commonmark-spec.baseline.json"),
+    },
+    BundledSuite {
+        name: "gfm-spec.txt",
+        spec: include_str!("../conformance/gfm-spec.txt"),
+        baseline: include_str!("../conformance/gfm-spec.baseline.json"),
+    },
+];
+
+/// Fence opening and closing an example
+const EXAMPLE_FENCE: &str = "````````````````````````````````";
+
+/// An example of a spec
+#[derive(Debug, Clone, PartialEq)]
+pub struct Example {
+    /// Position in the spec, from 1, as the spec numbers its examples
+    pub number: usize,
+    /// Heading the example is under
+    pub section: String,
+    /// Extension the example is for, e.g. `table` in the GFM spec
+    pub extension: Option<String>,
+    pub markdown: String,
+    pub html: String,
+}
+
+/// Examples of a `spec.txt`
+///
+/// The spec shows tabs as `→`, which are turned back into tabs. Examples
+/// marked `disabled` are left out, without taking a number.
+pub fn parse_spec(spec: &str) -> Vec<Example> {
+    let mut examples = Vec::new();
+    let mut section = String::new();
+    let mut lines = spec.lines();
+    while let Some(line) = lines.next() {
+        let Some(info) = line
+            .strip_prefix(EXAMPLE_FENCE)
+            .and_then(|rest| rest.trim().strip_prefix("example"))
+        else {
+            if let Some(heading) = line.strip_prefix('#') {
+                section = heading.trim_start_matches('#').trim().to_string();
+            }
+            continue;
+        };
+
+        let mut markdown = String::new();
+        let mut html = String::new();
+        let mut in_html = false;
+        for line in lines.by_ref() {
+            if line == EXAMPLE_FENCE {
+                break;
+            }
+            if line == "." && !in_html {
+                in_html = true;
+                continue;
+            }
+            let text = if in_html { &mut html } else { &mut markdown };
+            text.push_str(&line.replace('→', "\t"));
+            text.push('\n');
+        }
+
+        let extension = info.split_whitespace().next().map(str::to_string);
+        if extension.as_deref() == Some("disabled") {
+            continue;
+        }
+        examples.push(Example {
+            number: examples.len() + 1,
+            section: section.clone(),
+            extension,
+            markdown,
+            html,
+        });
+    }
+    examples
+}
+
+/// HTML the renderer's markdown step makes of `markdown`
+pub fn render(markdown: &str) -> Result<String> {
+    markdown::to_html_with_options(markdown, &markdown_options(markdown))
+        .map_err(|e| RuneError::Plugin(format!("Markdown parsing failed: {}", e)))
+}
+
+/// Outcome of one example
+#[derive(Debug, Clone, Serialize)]
+pub struct ExampleResult {
+    pub number: usize,
+    pub section: String,
+    pub passed: bool,
+    pub markdown: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Outcome of a spec's examples
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub spec: String,
+    pub results: Vec<ExampleResult>,
+}
+
+impl Report {
+    /// Numbers of the examples that passed
+    pub fn passing(&self) -> BTreeSet<usize> {
+        self.numbers(true)
+    }
+
+    /// Numbers of the examples that failed
+    pub fn failing(&self) -> BTreeSet<usize> {
+        self.numbers(false)
+    }
+
+    fn numbers(&self, passed: bool) -> BTreeSet<usize> {
+        self.results
+            .iter()
+            .filter(|result| result.passed == passed)
+            .map(|result| result.number)
+            .collect()
+    }
+
+    pub fn result(&self, number: usize) -> Option<&ExampleResult> {
+        self.results.iter().find(|result| result.number == number)
+    }
+}
+
+/// Run the examples of the spec named `spec`
+pub fn run(spec: &str, examples: &[Example]) -> Report {
+    let results = examples
+        .iter()
+        .map(|example| {
+            let actual = render(&example.markdown).unwrap_or_else(|e| e.to_string());
+            ExampleResult {
+                number: example.number,
+                section: example.section.clone(),
+                passed: normalize(&actual) == normalize(&example.html),
+                markdown: example.markdown.clone(),
+                expected: example.html.clone(),
+                actual,
+            }
+        })
+        .collect();
+    Report {
+        spec: spec.to_string(),
+        results,
+    }
+}
+
+/// HTML with line endings and trailing blank lines that do not matter
+/// for conformance evened out
+fn normalize(html: &str) -> String {
+    html.replace("\r\n", "\n").trim_end().to_string()
+}
+
+/// Examples of a spec allowed to fail
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub spec: String,
+    /// Examples the spec had when the baseline was taken
+    pub examples: usize,
+    pub failing: BTreeSet<usize>,
+}
+
+/// Difference between a run and its baseline
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Changes {
+    /// Examples that fail now but are not allowed to
+    pub regressed: Vec<usize>,
+    /// Examples allowed to fail that pass now
+    pub fixed: Vec<usize>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.regressed.is_empty() && self.fixed.is_empty()
+    }
+}
+
+impl Baseline {
+    /// What `report` failed, to bless
+    pub fn of(report: &Report) -> Self {
+        Self {
+            spec: report.spec.clone(),
+            examples: report.results.len(),
+            failing: report.failing(),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    /// How `report` differs from the baseline
+    pub fn changes(&self, report: &Report) -> Changes {
+        let failing = report.failing();
+        Changes {
+            regressed: failing.difference(&self.failing).copied().collect(),
+            fixed: self.failing.difference(&failing).copied().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_examples_are_parsed() {
+        let spec = format!(
+            "# Blocks\n\n## Tabs\n\n{fence} example\n→foo\n.\n<pre><code>foo\n</code></pre>\n{fence}\n\n\
+            ## Tables\n\n{fence} example disabled\n|a|\n.\n{fence}\n\n{fence} example table\n.\n.\n{fence}\n",
+            fence = EXAMPLE_FENCE
+        );
+        let examples = parse_spec(&spec);
+        assert_eq!(examples.len(), 2);
+        assert_eq!(examples[0].section, "Tabs");
+        assert_eq!(examples[0].markdown, "\tfoo\n");
+        assert_eq!(examples[0].html, "<pre><code>foo\n</code></pre>\n");
+        assert_eq!(examples[0].extension, None);
+        // A `.` in the HTML is content, and disabled examples take no number
+        assert_eq!(examples[1].number, 2);
+        assert_eq!(examples[1].extension.as_deref(), Some("table"));
+        assert_eq!(examples[1].markdown, "");
+        assert_eq!(examples[1].html, ".\n");
+    }
+
+    #[test]
+    fn test_bundled_suites_match_their_baselines() {
+        for suite in BUNDLED {
+            let examples = parse_spec(suite.spec);
+            let report = run(suite.name, &examples);
+            let baseline = Baseline::from_json(suite.baseline).unwrap();
+            let changes = baseline.changes(&report);
+            assert!(
+                changes.is_empty() && baseline.examples == examples.len(),
+                "{} no longer matches its baseline: {:?}; run `rune conformance \
+                plugins/renderer/conformance/{} --bless` if the change is intended",
+                suite.name,
+                changes,
+                suite.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_changes_against_a_baseline() {
+        let examples = parse_spec(&format!(
+            "{fence} example\n*a*\n.\n<p><em>a</em></p>\n{fence}\n\
+            {fence} example\n*b*\n.\n<p>*b*</p>\n{fence}\n",
+            fence = EXAMPLE_FENCE
+        ));
+        let report = run("spec.txt", &examples);
+        assert_eq!(report.passing(), BTreeSet::from([1]));
+
+        let baseline = Baseline {
+            spec: "spec.txt".to_string(),
+            examples: 2,
+            failing: BTreeSet::from([1]),
+        };
+        assert_eq!(
+            baseline.changes(&report),
+            Changes {
+                regressed: vec![2],
+                fixed: vec![1]
+            }
+        );
+        assert!(Baseline::of(&report).changes(&report).is_empty());
+    }
+}
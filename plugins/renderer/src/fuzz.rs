@@ -0,0 +1,94 @@
+//! Entry points for fuzzing the parser and the render pipeline
+//!
+//! Each target takes arbitrary bytes, as a fuzzer produces them, and must
+//! not panic whatever they are; errors are fine and ignored. The cargo-fuzz
+//! targets in `fuzz/` call these, and `rune fuzz-corpus` replays a corpus
+//! through them to check that a fix holds.
+
+use crate::{MarkdownRenderer, MermaidRenderer, Typography};
+use rune_core::{drafts::PublishState, frontmatter, MarkdownParser, RenderContext};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// A fuzz target: arbitrary input in, no panic out
+pub type FuzzTarget = fn(&[u8]);
+
+/// Targets by name
+pub const TARGETS: &[(&str, FuzzTarget)] = &[
+    ("parser", parse_markdown),
+    ("frontmatter", parse_frontmatter),
+    ("render", render),
+];
+
+/// Target named `name`
+pub fn target(name: &str) -> Option<FuzzTarget> {
+    TARGETS
+        .iter()
+        .find(|(target, _)| *target == name)
+        .map(|(_, run)| *run)
+}
+
+/// Build the syntax tree of the input
+pub fn parse_markdown(data: &[u8]) {
+    let markdown = String::from_utf8_lossy(data);
+    MarkdownParser::new().parse(&markdown);
+}
+
+/// Read the frontmatter of the input the ways the renderers do
+pub fn parse_frontmatter(data: &[u8]) {
+    let markdown = String::from_utf8_lossy(data);
+    for (key, value) in frontmatter::entries(&markdown) {
+        frontmatter::unquote(value);
+        frontmatter::list(&markdown, key);
+    }
+    Typography::from_frontmatter(&markdown);
+    PublishState::of(&markdown, SystemTime::UNIX_EPOCH);
+}
+
+/// Render the input to HTML and post-process it, then run the Mermaid
+/// post-processing on the raw input too, which may be any HTML
+pub fn render(data: &[u8]) {
+    let markdown = String::from_utf8_lossy(data);
+    // A document in a directory that does not exist, so nothing is read
+    // from next to it
+    let context = RenderContext::new(
+        PathBuf::from("/nonexistent/fuzz.md"),
+        PathBuf::from("/nonexistent"),
+        "catppuccin-mocha".to_string(),
+    );
+    let mermaid = MermaidRenderer::new();
+    if let Ok(rendered) = MarkdownRenderer::new().markdown_to_html(&markdown, &context) {
+        let _ = mermaid.process_mermaid(&rendered.html, &context);
+    }
+    let _ = mermaid.process_mermaid(&markdown, &context);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_targets_survive_awkward_input() {
+        let inputs: &[&[u8]] = &[
+            b"",
+            b"---",
+            b"---\n",
+            b"---\ntitle: \"\n---\n",
+            b"---\ntags: [a, \"b\n---\n# \xff\xfe",
+            b"# \xc3\n\n| a |\n|---|\n| \xe2\x82 |",
+            b"<pre><code class=\"language-mermaid\">&#xFFFFFFFF;</code></pre>",
+            b"```mermaid\ngraph TD\n  A-->B\n```\n[^1]\n\n[^1]: note",
+            b"# \n## \n###### \n\n* [ ] \n1. \n> ",
+            // Frontmatter that is never closed used to panic the markdown crate
+            b"---\n1. a\r\n[^1]: b",
+            b"+++\n> a\n\n  > - [ ] b",
+        ];
+        for input in inputs {
+            for (_, run) in TARGETS {
+                run(input);
+            }
+        }
+        assert!(target("render").is_some());
+        assert!(target("lexer").is_none());
+    }
+}
@@ -0,0 +1,190 @@
+//! Heading ids, permalink anchors and the table of contents
+//!
+//! Every heading gets an id slugged from its text (an id the author wrote
+//! is kept), a `#` link to itself shown on hover and a button copying that
+//! link. The same slugs make up the table of contents, which replaces a
+//! `[TOC]` or `[[toc]]` paragraph.
+//!
+//! Streamed documents render block by block, so duplicate headings in
+//! different blocks are not told apart there.
+
+use regex::{Captures, Regex};
+use rune_core::{SlugStyle, Slugger};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// A heading in the table of contents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TocEntry {
+    pub level: u8,
+    pub text: String,
+    pub id: String,
+}
+
+/// Text of heading markup, without tags and with character references decoded
+fn heading_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    html_escape::decode_html_entities(text.trim()).into_owned()
+}
+
+/// Give the headings of `html` ids and permalinks, returning the new HTML
+/// and its table of contents
+pub fn anchor_headings(html: &str, style: SlugStyle) -> (String, Vec<TocEntry>) {
+    static HEADING: OnceLock<Regex> = OnceLock::new();
+    static ID: OnceLock<Regex> = OnceLock::new();
+    let heading = HEADING.get_or_init(|| {
+        Regex::new(r"(?s)<h([1-6])(\s[^>]*)?>(.*?)</h[1-6]>").expect("valid heading pattern")
+    });
+    let id = ID.get_or_init(|| Regex::new(r#"\sid="([^"]*)""#).expect("valid id pattern"));
+
+    // Ids written by the author win over generated ones
+    let mut slugger = Slugger::new(style);
+    for caps in heading.captures_iter(html) {
+        if let Some(existing) = caps
+            .get(2)
+            .and_then(|attributes| id.captures(attributes.as_str()))
+        {
+            slugger.reserve(&html_escape::decode_html_entities(&existing[1]));
+        }
+    }
+
+    let mut toc = Vec::new();
+    let anchored = heading.replace_all(html, |caps: &Captures| {
+        let level = &caps[1];
+        let attributes = caps.get(2).map_or("", |m| m.as_str());
+        let content = &caps[3];
+        let text = heading_text(content);
+
+        let (slug, attributes) = match id.captures(attributes) {
+            Some(existing) => (
+                html_escape::decode_html_entities(&existing[1]).into_owned(),
+                attributes.to_string(),
+            ),
+            None => {
+                let slug = slugger.slug(&text);
+                let attributes = format!(r#" id="{}"{}"#, slug, attributes);
+                (slug, attributes)
+            }
+        };
+        toc.push(TocEntry {
+            level: level.parse().unwrap_or(1),
+            text,
+            id: slug.clone(),
+        });
+
+        format!(
+            "<h{level}{attributes}>{content}<a class=\"rune-anchor\" href=\"#{id}\" aria-label=\"Link to this section\">#</a><button class=\"rune-copy-link\" type=\"button\" data-anchor=\"{id}\" aria-label=\"Copy link to this section\">⧉</button></h{level}>",
+            level = level,
+            attributes = attributes,
+            content = content,
+            id = html_escape::encode_double_quoted_attribute(&slug),
+        )
+    });
+
+    (anchored.into_owned(), toc)
+}
+
+/// Nested list linking the entries of a table of contents
+pub fn toc_html(entries: &[TocEntry]) -> String {
+    let Some(top) = entries.iter().map(|entry| entry.level).min() else {
+        return String::new();
+    };
+
+    let mut html = String::from("<nav class=\"rune-toc\">\n<ul>\n");
+    // Lists open below the top one, and whether the last item is still open
+    let mut depth = 0;
+    let mut item_open = false;
+    for entry in entries {
+        let level = (entry.level - top) as usize;
+        while depth < level {
+            // A skipped level still needs an item to hold its list
+            if !item_open {
+                html.push_str("<li>");
+            }
+            html.push_str("\n<ul>\n");
+            depth += 1;
+            item_open = false;
+        }
+        while depth > level {
+            if item_open {
+                html.push_str("</li>\n");
+            }
+            html.push_str("</ul>\n");
+            depth -= 1;
+            item_open = true;
+        }
+        if item_open {
+            html.push_str("</li>\n");
+        }
+        html.push_str(&format!(
+            "<li><a href=\"#{}\">{}</a>",
+            html_escape::encode_double_quoted_attribute(&entry.id),
+            html_escape::encode_text(&entry.text)
+        ));
+        item_open = true;
+    }
+    while depth > 0 {
+        html.push_str("</li>\n</ul>\n");
+        depth -= 1;
+    }
+    html.push_str("</li>\n");
+    html.push_str("</ul>\n</nav>");
+    html
+}
+
+/// Replace `[TOC]` and `[[toc]]` paragraphs with the table of contents
+pub fn insert_toc(html: &str, entries: &[TocEntry]) -> String {
+    static MARKER: OnceLock<Regex> = OnceLock::new();
+    let marker = MARKER.get_or_init(|| {
+        Regex::new(r"(?i)<p>\s*(?:\[toc\]|\[\[toc\]\])\s*</p>").expect("valid TOC marker pattern")
+    });
+    if !marker.is_match(html) {
+        return html.to_string();
+    }
+    marker.replace_all(html, toc_html(entries)).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anchors_and_toc_share_slugs() {
+        let html = concat!(
+            "<p>[TOC]</p>\n",
+            "<h1>Intro &amp; <em>Setup</em></h1>\n",
+            "<h2>Setup</h2>\n",
+            "<h3 id=\"setup-1\">Custom</h3>\n",
+            "<h2>Setup</h2>\n",
+        );
+        let (anchored, toc) = anchor_headings(html, SlugStyle::Github);
+
+        let ids: Vec<_> = toc.iter().map(|entry| entry.id.as_str()).collect();
+        assert_eq!(ids, ["intro--setup", "setup", "setup-1", "setup-2"]);
+        assert_eq!(toc[0].text, "Intro & Setup");
+        assert!(anchored.contains(concat!(
+            "<h2 id=\"setup\">Setup<a class=\"rune-anchor\" href=\"#setup\" aria-label=\"Link to this section\">#</a>",
+            "<button class=\"rune-copy-link\" type=\"button\" data-anchor=\"setup\" aria-label=\"Copy link to this section\">⧉</button></h2>"
+        )));
+        assert!(anchored.contains("<h3 id=\"setup-1\">Custom<a"));
+
+        let page = insert_toc(&anchored, &toc);
+        assert!(page.starts_with(concat!(
+            "<nav class=\"rune-toc\">\n<ul>\n",
+            "<li><a href=\"#intro--setup\">Intro &amp; Setup</a>\n",
+            "<ul>\n<li><a href=\"#setup\">Setup</a>\n",
+            "<ul>\n<li><a href=\"#setup-1\">Custom</a></li>\n</ul>\n",
+            "</li>\n<li><a href=\"#setup-2\">Setup</a></li>\n</ul>\n",
+            "</li>\n</ul>\n</nav>\n<h1"
+        )));
+    }
+}
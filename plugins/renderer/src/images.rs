@@ -0,0 +1,375 @@
+//! Dimension injection for local images
+//!
+//! Images next to the document get the `width` and `height` of their file
+//! so the preview reserves their space before they load, and
+//! `loading="lazy"` so offscreen images load on demand. Probed dimensions
+//! are cached until the file watcher reports a change of the image.
+
+use async_trait::async_trait;
+use regex::{Captures, Regex};
+use rune_core::{
+    event::{SystemEvent, SystemEventHandler},
+    export::assets::image_dimensions,
+    ContentRenderer, Plugin, PluginContext, PluginStatus, RenderContext, RenderMetadata,
+    RenderResult, Result, RuneError,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::RwLock;
+
+/// Dimensions of probed image files, by canonical path
+#[derive(Debug, Default)]
+pub struct ImageProbeCache {
+    entries: RwLock<HashMap<PathBuf, Option<(u32, u32)>>>,
+}
+
+impl ImageProbeCache {
+    /// Width and height of an image file, probing it on first use
+    pub async fn dimensions(&self, path: &Path) -> Option<(u32, u32)> {
+        let path = path.canonicalize().ok()?;
+        if let Some(dimensions) = self.entries.read().await.get(&path) {
+            return *dimensions;
+        }
+
+        // Unreadable files are not cached; they may appear later
+        let bytes = tokio::fs::read(&path).await.ok()?;
+        let dimensions = image_dimensions(&bytes);
+        self.entries.write().await.insert(path, dimensions);
+        dimensions
+    }
+
+    /// Forget the dimensions of a changed file
+    pub async fn invalidate(&self, path: &Path) -> bool {
+        // Deleted files cannot be canonicalized; compare them as reported
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.entries.write().await.remove(&path).is_some()
+    }
+
+    /// Number of cached images
+    pub async fn len(&self) -> usize {
+        self.entries.read().await.len()
+    }
+
+    /// Whether no image has been probed yet
+    pub async fn is_empty(&self) -> bool {
+        self.entries.read().await.is_empty()
+    }
+}
+
+/// HTML processor sizing local images and deferring their loading
+pub struct ImageDimensionRenderer {
+    name: String,
+    version: String,
+    status: PluginStatus,
+    cache: Arc<ImageProbeCache>,
+    img: Regex,
+    src: Regex,
+    size: Regex,
+    loading: Regex,
+}
+
+impl ImageDimensionRenderer {
+    /// Create a renderer probing images through the given cache
+    pub fn new(cache: Arc<ImageProbeCache>) -> Result<Self> {
+        let pattern = |re: &str| {
+            Regex::new(re)
+                .map_err(|e| RuneError::Plugin(format!("Regex compilation failed: {}", e)))
+        };
+        Ok(Self {
+            name: "image-dimension-renderer".to_string(),
+            version: "0.1.0".to_string(),
+            status: PluginStatus::Loading,
+            cache,
+            img: pattern(r"<img\b[^>]*>")?,
+            src: pattern(r#"\ssrc=["']([^"']+)["']"#)?,
+            size: pattern(r"\s(?:width|height)=")?,
+            loading: pattern(r"\sloading=")?,
+        })
+    }
+
+    /// File behind an `src` attribute, skipping remote and inline images
+    fn local_file(src: &str, base_dir: &Path) -> Option<PathBuf> {
+        if src.starts_with("data:") || src.contains("://") || src.starts_with("//") {
+            return None;
+        }
+        let file = src.split(['?', '#']).next().unwrap_or(src);
+        if file.is_empty() {
+            return None;
+        }
+        Some(base_dir.join(file.trim_start_matches('/')))
+    }
+
+    /// Add dimensions and lazy loading to the image tags of `html`,
+    /// returning the new HTML and the number of sized images
+    async fn process_images(&self, html: &str, context: &RenderContext) -> (String, usize) {
+        let mut dimensions = HashMap::new();
+        for caps in self.img.captures_iter(html) {
+            let tag = &caps[0];
+            let Some(src) = self.src.captures(tag).map(|src| src[1].to_string()) else {
+                continue;
+            };
+            if self.size.is_match(tag) || dimensions.contains_key(&src) {
+                continue;
+            }
+            if let Some(file) = Self::local_file(&src, &context.base_dir) {
+                let probed = self.cache.dimensions(&file).await;
+                dimensions.insert(src, probed);
+            }
+        }
+
+        let mut sized = 0;
+        let html = self.img.replace_all(html, |caps: &Captures| {
+            let tag = &caps[0];
+            let mut attributes = String::new();
+            if !self.size.is_match(tag) {
+                let probed = self
+                    .src
+                    .captures(tag)
+                    .and_then(|src| dimensions.get(&src[1]).copied().flatten());
+                if let Some((width, height)) = probed {
+                    attributes.push_str(&format!(r#" width="{}" height="{}""#, width, height));
+                    sized += 1;
+                }
+            }
+            if !self.loading.is_match(tag) {
+                attributes.push_str(r#" loading="lazy""#);
+            }
+            if attributes.is_empty() {
+                return tag.to_string();
+            }
+
+            let (head, tail) = match tag.strip_suffix("/>") {
+                Some(head) => (head, " />"),
+                None => (&tag[..tag.len() - 1], ">"),
+            };
+            format!("{}{}{}", head.trim_end(), attributes, tail)
+        });
+        (html.into_owned(), sized)
+    }
+}
+
+#[async_trait]
+impl Plugin for ImageDimensionRenderer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn dependencies(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    async fn initialize(&mut self, _context: &PluginContext) -> Result<()> {
+        tracing::info!("Initializing image dimension renderer plugin");
+        self.status = PluginStatus::Active;
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        tracing::info!("Shutting down image dimension renderer plugin");
+        self.status = PluginStatus::Stopped;
+        Ok(())
+    }
+
+    fn status(&self) -> PluginStatus {
+        self.status.clone()
+    }
+
+    fn provided_services(&self) -> Vec<&str> {
+        vec!["image-dimensions"]
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[async_trait]
+impl ContentRenderer for ImageDimensionRenderer {
+    fn can_render(&self, content_type: &str) -> bool {
+        matches!(content_type, "text/html" | "application/html")
+    }
+
+    async fn render(&self, content: &str, context: &RenderContext) -> Result<RenderResult> {
+        let start_time = Instant::now();
+        let (html, sized) = self.process_images(content, context).await;
+
+        let mut custom_metadata = HashMap::new();
+        custom_metadata.insert(
+            "images_sized".to_string(),
+            serde_json::Value::Number(sized.into()),
+        );
+
+        let metadata = RenderMetadata {
+            renderer_name: self.name.clone(),
+            renderer_version: self.version.clone(),
+            render_time_ms: Some(start_time.elapsed().as_millis() as u64),
+            content_hash: Some(format!("{:x}", html.len() as u64)),
+            custom_metadata,
+        };
+
+        Ok(RenderResult::new(html).with_metadata(metadata))
+    }
+
+    fn supported_extensions(&self) -> Vec<&str> {
+        vec!["html", "htm"]
+    }
+
+    fn priority(&self) -> u32 {
+        100 // After mermaid, before theme processing
+    }
+
+    fn is_expensive(&self) -> bool {
+        true // Reads every image
+    }
+
+    fn renderer_metadata(&self) -> RenderMetadata {
+        let mut custom_metadata = HashMap::new();
+        custom_metadata.insert(
+            "features".to_string(),
+            serde_json::json!(["image_dimensions", "lazy_loading"]),
+        );
+
+        RenderMetadata {
+            renderer_name: self.name.clone(),
+            renderer_version: self.version.clone(),
+            render_time_ms: None,
+            content_hash: None,
+            custom_metadata,
+        }
+    }
+}
+
+/// Event handler dropping cached dimensions of changed files
+pub(crate) struct ImageChangeHandler {
+    pub(crate) cache: Arc<ImageProbeCache>,
+}
+
+#[async_trait]
+impl SystemEventHandler for ImageChangeHandler {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        if let SystemEvent::FileChanged { path, .. } = event {
+            if self.cache.invalidate(path).await {
+                tracing::debug!("Dropped cached dimensions of {}", path.display());
+            }
+        }
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "ImageChangeHandler"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::event::ChangeType;
+    use std::time::SystemTime;
+    use tempfile::TempDir;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&[8, 6, 0, 0, 0]);
+        bytes
+    }
+
+    #[tokio::test]
+    async fn test_local_images_are_sized_and_lazy() {
+        let temp_dir = TempDir::new().unwrap();
+        let image = temp_dir.path().join("photo.png");
+        std::fs::write(&image, png(640, 480)).unwrap();
+
+        let cache = Arc::new(ImageProbeCache::default());
+        let renderer = ImageDimensionRenderer::new(cache.clone()).unwrap();
+        let context = RenderContext::new(
+            temp_dir.path().join("doc.md"),
+            temp_dir.path().to_path_buf(),
+            "light".to_string(),
+        );
+
+        let html = concat!(
+            r#"<p><img src="photo.png?v=1" alt="A" /></p>"#,
+            r#"<img src="https://example.com/a.png" alt="remote" />"#,
+            r#"<img src="photo.png" width="10" loading="eager">"#,
+            r#"<img src="missing.png" alt="">"#,
+        );
+        let result = renderer.render(html, &context).await.unwrap();
+        assert_eq!(
+            result.html,
+            concat!(
+                r#"<p><img src="photo.png?v=1" alt="A" width="640" height="480" loading="lazy" /></p>"#,
+                r#"<img src="https://example.com/a.png" alt="remote" loading="lazy" />"#,
+                r#"<img src="photo.png" width="10" loading="eager">"#,
+                r#"<img src="missing.png" alt="" loading="lazy">"#,
+            )
+        );
+        assert_eq!(cache.len().await, 1);
+
+        // A changed image is probed again after the watcher reports it
+        std::fs::write(&image, png(320, 200)).unwrap();
+        let unchanged = renderer
+            .render(r#"<img src="photo.png">"#, &context)
+            .await
+            .unwrap();
+        assert!(unchanged.html.contains(r#"width="640""#));
+
+        let handler = ImageChangeHandler {
+            cache: cache.clone(),
+        };
+        handler
+            .handle_system_event(&SystemEvent::FileChanged {
+                path: image.clone(),
+                change_type: ChangeType::Modified,
+                timestamp: SystemTime::now(),
+            })
+            .await
+            .unwrap();
+        assert!(cache.is_empty().await);
+
+        let changed = renderer
+            .render(r#"<img src="photo.png">"#, &context)
+            .await
+            .unwrap();
+        assert_eq!(
+            changed.html,
+            r#"<img src="photo.png" width="320" height="200" loading="lazy">"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_large_documents_skip_image_sizing() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("photo.png"), png(640, 480)).unwrap();
+
+        let registry = rune_core::RendererRegistry::new();
+        let renderer = ImageDimensionRenderer::new(Arc::new(ImageProbeCache::default())).unwrap();
+        registry
+            .register_renderer(Box::new(renderer))
+            .await
+            .unwrap();
+        let context = RenderContext::new(
+            temp_dir.path().join("doc.html"),
+            temp_dir.path().to_path_buf(),
+            "light".to_string(),
+        );
+
+        let html = r#"<img src="photo.png">"#;
+        let sized = registry.render_with_pipeline(html, &context).await.unwrap();
+        assert!(sized.html.contains(r#"width="640""#));
+        let large = context.with_large_document();
+        let skipped = registry.render_with_pipeline(html, &large).await.unwrap();
+        assert_eq!(skipped.html, html);
+    }
+}
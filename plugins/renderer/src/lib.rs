@@ -2,21 +2,54 @@
 
 use async_trait::async_trait;
 use rune_core::{
+    drafts::{self, PublishState},
     event::{SystemEvent, SystemEventHandler},
+    frontmatter,
+    glossary::{self, Glossary},
+    journal::Journal,
     Asset, AssetType, ContentRenderer, Plugin, PluginContext, PluginStatus, RenderContext,
-    RenderMetadata, RenderResult, RendererRegistry, Result, RuneError,
+    RenderMetadata, RenderResult, RendererRegistry, Result, RuneError, SlugStyle,
+    NONCE_PLACEHOLDER,
 };
 
 use regex::Regex;
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
+
+mod a11y;
+mod blocks;
+pub mod conformance;
+pub mod fuzz;
+mod headings;
+mod images;
+mod link_preview;
+mod media;
+mod numbering;
+mod pandoc;
+mod typography;
+
+pub use a11y::{check_theme_contrast, A11yChecker, A11yIssue, A11yRule};
+pub use headings::{anchor_headings, insert_toc, toc_html, TocEntry};
+pub use images::{ImageDimensionRenderer, ImageProbeCache};
+pub use link_preview::{LinkPreview, LinkPreviewRenderer, LinkPreviewer};
+pub use media::MediaRenderer;
+pub use numbering::Numbering;
+pub use pandoc::PandocRenderer;
+pub use typography::{Direction, Typography};
+
+/// Notice put above documents marked `draft: true`; scheduled documents get
+/// one naming their publish time
+const DRAFT_BANNER: &str = "<div class=\"rune-draft-banner\" role=\"note\">Draft: this document is not published yet.</div>";
 
 /// Markdown content renderer implementation
 pub struct MarkdownRenderer {
     name: String,
     version: String,
     status: PluginStatus,
+    slug_style: SlugStyle,
+    numbering: Numbering,
+    journal: Option<Journal>,
 }
 
 impl MarkdownRenderer {
@@ -26,19 +59,77 @@ impl MarkdownRenderer {
             name: "markdown-renderer".to_string(),
             version: "0.1.0".to_string(),
             status: PluginStatus::Loading,
+            slug_style: SlugStyle::default(),
+            numbering: Numbering::default(),
+            journal: None,
         }
     }
 
+    /// Slug heading ids in `style`
+    pub fn with_slug_style(mut self, style: SlugStyle) -> Self {
+        self.slug_style = style;
+        self
+    }
+
+    /// Number sections, figures and tables as `numbering` says, unless a
+    /// document's frontmatter says otherwise
+    pub fn with_numbering(mut self, numbering: Numbering) -> Self {
+        self.numbering = numbering;
+        self
+    }
+
+    /// Link the daily notes of `journal` to the previous and next day's
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
     /// Convert markdown content to HTML
-    fn markdown_to_html(&self, content: &str, _context: &RenderContext) -> Result<RenderResult> {
+    fn markdown_to_html(&self, content: &str, context: &RenderContext) -> Result<RenderResult> {
         let start_time = Instant::now();
 
-        // Create GFM options with HTML rendering enabled
-        let mut options = markdown::Options::gfm();
-        options.compile.allow_dangerous_html = true;
+        // Term definitions are not rendered, the terms get their tooltips
+        let glossary = if glossary::enabled(content) {
+            Glossary::for_document(&context.file_path, content)
+        } else {
+            Glossary::default()
+        };
+        let source = glossary::strip_definitions(content);
 
-        let html_body = markdown::to_html_with_options(content, &options)
+        let html_body = markdown::to_html_with_options(&source, &markdown_options(&source))
             .map_err(|e| RuneError::Plugin(format!("Markdown parsing failed: {}", e)))?;
+        let (html_body, glossary_terms) = glossary.expand(&html_body);
+        let (html_body, mut toc) = headings::anchor_headings(&html_body, self.slug_style);
+        let (html_body, cross_references) = numbering::number(
+            &html_body,
+            &source,
+            self.numbering.for_document(content),
+            &mut toc,
+        );
+        let html_body = headings::insert_toc(&html_body, &toc);
+        let (html_body, direction) = Typography::from_frontmatter(content).annotate(&html_body);
+        let publish_state = PublishState::of(content, SystemTime::now());
+        let html_body = match publish_state {
+            PublishState::Published => html_body,
+            PublishState::Draft => format!("{}\n{}", DRAFT_BANNER, html_body),
+            PublishState::Scheduled(time) => format!(
+                "<div class=\"rune-draft-banner\" role=\"note\">Scheduled: this document is published at <time datetime=\"{time}\">{time}</time>.</div>\n{}",
+                html_body,
+                time = drafts::format_time(time)
+            ),
+        };
+        let journal_date = self
+            .journal
+            .as_ref()
+            .and_then(|journal| journal.date_of(&context.file_path));
+        let html_body = match self
+            .journal
+            .as_ref()
+            .and_then(|journal| journal.navigation_html(&context.file_path))
+        {
+            Some(navigation) => format!("{}{}", navigation, html_body),
+            None => html_body,
+        };
 
         let mut custom_metadata = HashMap::new();
 
@@ -59,6 +150,35 @@ impl MarkdownRenderer {
             "has_mermaid_blocks".to_string(),
             serde_json::Value::Bool(has_mermaid_blocks),
         );
+        custom_metadata.insert(
+            "direction".to_string(),
+            serde_json::Value::String(direction.as_str().to_string()),
+        );
+        custom_metadata.insert("toc".to_string(), serde_json::to_value(&toc)?);
+        custom_metadata.insert(
+            "glossary_terms".to_string(),
+            serde_json::Value::Number(glossary_terms.into()),
+        );
+        custom_metadata.insert(
+            "cross_references".to_string(),
+            serde_json::Value::Number(cross_references.into()),
+        );
+        custom_metadata.insert(
+            "draft".to_string(),
+            serde_json::Value::Bool(publish_state == PublishState::Draft),
+        );
+        if let PublishState::Scheduled(time) = publish_state {
+            custom_metadata.insert(
+                "publish_at".to_string(),
+                serde_json::Value::String(drafts::format_time(time)),
+            );
+        }
+        if let Some(date) = journal_date {
+            custom_metadata.insert(
+                "journal_date".to_string(),
+                serde_json::Value::String(date.to_string()),
+            );
+        }
 
         // Create metadata
         let metadata = RenderMetadata {
@@ -75,6 +195,15 @@ impl MarkdownRenderer {
     }
 }
 
+/// Options of the markdown to HTML step: GFM with raw HTML kept, and
+/// frontmatter read for typography settings instead of being rendered
+fn markdown_options(source: &str) -> markdown::Options {
+    let mut options = markdown::Options::gfm();
+    options.compile.allow_dangerous_html = true;
+    options.parse.constructs.frontmatter = frontmatter::is_closed(source);
+    options
+}
+
 impl Default for MarkdownRenderer {
     fn default() -> Self {
         Self::new()
@@ -157,6 +286,10 @@ impl ContentRenderer for MarkdownRenderer {
             custom_metadata,
         }
     }
+
+    fn split_blocks(&self, content: &str, target_bytes: usize) -> Vec<String> {
+        blocks::split_top_level_blocks(content, target_bytes)
+    }
 }
 
 /// Mermaid diagram renderer implementation
@@ -207,6 +340,7 @@ impl MermaidRenderer {
                 url: "/mermaid.min.js".to_string(),
                 is_critical: true,
                 integrity: None,
+                nonce: Some(NONCE_PLACEHOLDER.to_string()),
             });
 
             custom_metadata.insert(
@@ -311,6 +445,10 @@ impl ContentRenderer for MermaidRenderer {
         150 // Medium priority, should run after markdown but before final processing
     }
 
+    fn is_expensive(&self) -> bool {
+        true // Every diagram has the page load Mermaid and lay it out
+    }
+
     fn renderer_metadata(&self) -> RenderMetadata {
         let mut custom_metadata = HashMap::new();
         custom_metadata.insert(
@@ -590,21 +728,93 @@ impl Plugin for RendererPlugin {
         };
 
         // Register built-in renderers
-        let markdown_renderer = Box::new(MarkdownRenderer::new());
+        let slug_style = match context.config.get_global_setting::<String>("heading_slugs") {
+            Some(style) => style.parse()?,
+            None => SlugStyle::default(),
+        };
+        let numbering = match context.config.get_global_setting::<String>("numbering") {
+            Some(numbering) => numbering.parse()?,
+            None => Numbering::default(),
+        };
+        let journal = Journal::from_config(&context.config.journal)?;
+        let markdown_renderer = Box::new(
+            MarkdownRenderer::new()
+                .with_slug_style(slug_style)
+                .with_numbering(numbering)
+                .with_journal(journal),
+        );
         registry.register_renderer(markdown_renderer).await?;
 
         let mermaid_renderer = Box::new(MermaidRenderer::new());
         registry.register_renderer(mermaid_renderer).await?;
 
+        // Other document formats are previewed when pandoc is installed
+        match rune_core::pandoc::Pandoc::detect() {
+            Some(pandoc) => {
+                tracing::info!(
+                    "Previewing other document formats with {}",
+                    pandoc.version()
+                );
+                registry
+                    .register_renderer(Box::new(PandocRenderer::new(pandoc)))
+                    .await?;
+            }
+            None => tracing::info!("pandoc not found, only markdown documents are previewed"),
+        }
+
+        // Offline, nothing may reach out to the network: no third-party
+        // players and link previews only from the cache
+        let offline = context.config.get_global_setting::<bool>("offline") == Some(true);
+
+        let embed_videos = !offline
+            && context
+                .config
+                .get_global_setting::<bool>("embed_videos")
+                .unwrap_or(false);
+        let media_renderer = Box::new(MediaRenderer::new(embed_videos)?);
+        registry.register_renderer(media_renderer).await?;
+
+        // Link preview cards fetch pages, so they are only added on request
+        if context.config.link_previews.enabled == Some(true) {
+            let mut link_previews = context.config.link_previews.clone();
+            if offline {
+                link_previews.offline = Some(true);
+            }
+            let link_preview_renderer = Box::new(LinkPreviewRenderer::new(&link_previews)?);
+            registry.register_renderer(link_preview_renderer).await?;
+        }
+
+        // Size local images, forgetting probed dimensions when a file changes
+        let image_cache = Arc::new(ImageProbeCache::default());
+        context
+            .event_bus
+            .subscribe_system_events(Arc::new(images::ImageChangeHandler {
+                cache: image_cache.clone(),
+            }))
+            .await?;
+        let image_renderer = Box::new(ImageDimensionRenderer::new(image_cache)?);
+        registry.register_renderer(image_renderer).await?;
+
         // Register theme-aware renderer
         let theme_aware_renderer = Box::new(ThemeAwareRenderer::new());
         registry.register_renderer(theme_aware_renderer).await?;
 
+        // Accessibility checks only report, so they are left out unless asked for
+        if context
+            .config
+            .get_global_setting::<bool>("a11y_checks")
+            .unwrap_or(false)
+        {
+            registry
+                .register_renderer(Box::new(A11yChecker::new()?))
+                .await?;
+        }
+
         self.registry = Some(registry.clone());
         self.status = PluginStatus::Active;
 
         tracing::info!(
-            "Renderer plugin initialized with markdown, mermaid, and theme-aware renderers"
+            "Renderer plugin initialized with markdown, mermaid, media, image and theme-aware renderers"
         );
         Ok(())
     }
@@ -0,0 +1,655 @@
+//! Preview cards for bare links
+//!
+//! A URL standing alone in a paragraph becomes a card showing the title,
+//! description and thumbnail of the page it points to. Pages are fetched
+//! over HTTP and read for OpenGraph tags; links to a few well-known
+//! providers also ask the provider's oEmbed endpoint. Endpoints a page
+//! advertises itself are not followed. Previews are cached in
+//! `.rune/cache/link-previews/` next to the document; in offline mode only cached
+//! previews are used and other links stay plain.
+
+use async_trait::async_trait;
+use futures_util::future;
+use regex::{Captures, Regex};
+use reqwest::Url;
+use rune_core::{
+    ContentRenderer, LinkPreviewConfig, Plugin, PluginContext, PluginStatus, RenderContext,
+    RenderMetadata, RenderResult, Result, RuneError,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Cache directory of fetched previews, relative to the document
+pub const LINK_PREVIEW_CACHE_DIR: &str = ".rune/cache/link-previews";
+
+/// Time allowed for fetching one link
+pub const DEFAULT_LINK_PREVIEW_TIMEOUT: Duration = Duration::from_millis(3000);
+
+/// Bytes read from a fetched page; its metadata is in the head
+const MAX_PAGE_BYTES: usize = 2 * 1024 * 1024;
+
+/// Redirects followed for one link
+const MAX_REDIRECTS: usize = 5;
+
+/// oEmbed endpoints of known providers, by the hosts of their links
+const OEMBED_PROVIDERS: &[(&[&str], &str)] = &[
+    (
+        &["youtube.com", "youtu.be"],
+        "https://www.youtube.com/oembed?format=json",
+    ),
+    (&["vimeo.com"], "https://vimeo.com/api/oembed.json"),
+    (
+        &["twitter.com", "x.com"],
+        "https://publish.twitter.com/oembed",
+    ),
+    (
+        &["flickr.com", "flic.kr"],
+        "https://www.flickr.com/services/oembed/?format=json",
+    ),
+    (
+        &["soundcloud.com"],
+        "https://soundcloud.com/oembed?format=json",
+    ),
+    (&["open.spotify.com"], "https://open.spotify.com/oembed"),
+];
+
+/// What a card shows about a linked page
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LinkPreview {
+    pub url: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Thumbnail URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+}
+
+impl LinkPreview {
+    /// Card markup for the preview
+    pub fn to_html(&self) -> String {
+        let attribute =
+            |value: &str| html_escape::encode_double_quoted_attribute(value).to_string();
+        let text = |value: &str| html_escape::encode_text(value).to_string();
+
+        let mut card = format!(
+            r#"<a class="rune-link-card" href="{}" rel="noopener noreferrer">"#,
+            attribute(&self.url)
+        );
+        if let Some(image) = &self.image {
+            card.push_str(&format!(
+                r#"<img class="rune-link-card-image" src="{}" alt="" loading="lazy">"#,
+                attribute(image)
+            ));
+        }
+        card.push_str(&format!(
+            r#"<span class="rune-link-card-body"><strong class="rune-link-card-title">{}</strong>"#,
+            text(&self.title)
+        ));
+        if let Some(description) = &self.description {
+            card.push_str(&format!(
+                r#"<span class="rune-link-card-description">{}</span>"#,
+                text(description)
+            ));
+        }
+        if let Some(site_name) = &self.site_name {
+            card.push_str(&format!(
+                r#"<span class="rune-link-card-site">{}</span>"#,
+                text(site_name)
+            ));
+        }
+        card.push_str("</span></a>");
+        card
+    }
+}
+
+/// Compile a regex used by the link preview stage
+fn pattern(re: &str) -> Result<Regex> {
+    Regex::new(re).map_err(|e| RuneError::Plugin(format!("Regex compilation failed: {}", e)))
+}
+
+/// Attributes of the tags of `name` in an HTML page, lowercased by name
+fn tag_attributes(html: &str, name: &str) -> Vec<HashMap<String, String>> {
+    let (Ok(tag), Ok(attribute)) = (
+        pattern(&format!(r"(?i)<{}\b[^>]*>", name)),
+        pattern(r#"([\w:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#),
+    ) else {
+        return Vec::new();
+    };
+
+    tag.find_iter(html)
+        .map(|tag| {
+            attribute
+                .captures_iter(tag.as_str())
+                .map(|caps| {
+                    let value = caps
+                        .get(2)
+                        .or_else(|| caps.get(3))
+                        .map_or("", |m| m.as_str());
+                    (
+                        caps[1].to_lowercase(),
+                        html_escape::decode_html_entities(value).trim().to_string(),
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Resolve a possibly relative `href` found on the page at `base`
+fn absolute_url(href: &str, base: &str) -> Option<String> {
+    if href.starts_with("https://") || href.starts_with("http://") {
+        return Some(href.to_string());
+    }
+    let (scheme, rest) = base.split_once("://")?;
+    if let Some(rest) = href.strip_prefix("//") {
+        return Some(format!("{}://{}", scheme, rest));
+    }
+    let host = rest.split(['/', '?', '#']).next()?;
+    href.starts_with('/')
+        .then(|| format!("{}://{}{}", scheme, host, href))
+}
+
+/// Preview of a page from its OpenGraph and standard metadata
+pub fn parse_page(url: &str, html: &str) -> Option<LinkPreview> {
+    let mut meta = HashMap::new();
+    for attributes in tag_attributes(html, "meta") {
+        let key = attributes
+            .get("property")
+            .or_else(|| attributes.get("name"))
+            .map(|key| key.to_lowercase());
+        if let (Some(key), Some(content)) = (key, attributes.get("content")) {
+            if !content.is_empty() {
+                meta.entry(key).or_insert_with(|| content.clone());
+            }
+        }
+    }
+    let first = |keys: &[&str]| keys.iter().find_map(|key| meta.get(*key).cloned());
+
+    let title = first(&["og:title", "twitter:title"]).or_else(|| {
+        let title = pattern(r"(?is)<title[^>]*>(.*?)</title>").ok()?;
+        let text = title.captures(html)?[1].trim().to_string();
+        (!text.is_empty()).then(|| html_escape::decode_html_entities(&text).to_string())
+    })?;
+
+    Some(LinkPreview {
+        url: url.to_string(),
+        title,
+        description: first(&["og:description", "twitter:description", "description"]),
+        image: first(&["og:image", "og:image:url", "twitter:image"])
+            .and_then(|image| absolute_url(&image, url)),
+        site_name: first(&["og:site_name"]),
+    })
+}
+
+/// oEmbed endpoint for `url` if it links to a known provider
+pub fn oembed_endpoint(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_ascii_lowercase();
+    let (_, endpoint) = OEMBED_PROVIDERS.iter().find(|(hosts, _)| {
+        hosts
+            .iter()
+            .any(|known| host == *known || host.ends_with(&format!(".{}", known)))
+    })?;
+    let mut endpoint = Url::parse(endpoint).ok()?;
+    endpoint.query_pairs_mut().append_pair("url", url);
+    Some(endpoint.into())
+}
+
+/// Fill a preview from an oEmbed response, which takes precedence over the
+/// page's own metadata
+pub fn apply_oembed(preview: &mut LinkPreview, oembed: &serde_json::Value) {
+    let field = |name: &str| {
+        oembed[name]
+            .as_str()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    if let Some(title) = field("title") {
+        preview.title = title;
+    }
+    if let Some(thumbnail) = field("thumbnail_url") {
+        preview.image = Some(thumbnail);
+    }
+    if let Some(provider) = field("provider_name") {
+        preview.site_name = Some(provider);
+    }
+    if preview.description.is_none() {
+        preview.description = field("author_name");
+    }
+}
+
+/// Fetches and caches link previews
+pub struct LinkPreviewer {
+    offline: bool,
+    client: reqwest::Client,
+    /// Previews and failed fetches of this session, by URL
+    fetched: RwLock<HashMap<String, Option<LinkPreview>>>,
+}
+
+impl LinkPreviewer {
+    /// Create a previewer from the `link_previews` settings
+    pub fn new(config: &LinkPreviewConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(
+                config
+                    .timeout_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or(DEFAULT_LINK_PREVIEW_TIMEOUT),
+            )
+            .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+            .user_agent("rune-link-preview")
+            .build()
+            .map_err(|e| RuneError::Plugin(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self {
+            offline: config.offline.unwrap_or(false),
+            client,
+            fetched: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Preview of `url`, from the cache in `cache_dir` or fetched
+    pub async fn preview(&self, url: &str, cache_dir: &Path) -> Option<LinkPreview> {
+        if let Some(preview) = self.fetched.read().await.get(url) {
+            return preview.clone();
+        }
+
+        let cache_file = cache_dir.join(format!("{:x}.json", md5::compute(url)));
+        if let Ok(cached) = tokio::fs::read(&cache_file).await {
+            if let Ok(preview) = serde_json::from_slice::<LinkPreview>(&cached) {
+                self.fetched
+                    .write()
+                    .await
+                    .insert(url.to_string(), Some(preview.clone()));
+                return Some(preview);
+            }
+        }
+        if self.offline {
+            return None;
+        }
+
+        let preview = self.fetch_preview(url).await;
+        if let Some(preview) = &preview {
+            let stored = match serde_json::to_vec_pretty(preview) {
+                Ok(json) => match tokio::fs::create_dir_all(cache_dir).await {
+                    Ok(()) => tokio::fs::write(&cache_file, json).await,
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = stored {
+                tracing::warn!("Could not cache link preview of {}: {}", url, e);
+            }
+        }
+        // Failures are remembered too, so a dead link is not fetched on every render
+        self.fetched
+            .write()
+            .await
+            .insert(url.to_string(), preview.clone());
+        preview
+    }
+
+    /// Fetch a page and its oEmbed data
+    async fn fetch_preview(&self, url: &str) -> Option<LinkPreview> {
+        let page = self.fetch(url).await?;
+        let mut preview = parse_page(url, &page);
+        if let Some(endpoint) = oembed_endpoint(url) {
+            let oembed = self
+                .fetch(&endpoint)
+                .await
+                .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok());
+            if let Some(oembed) = oembed {
+                let preview = preview.get_or_insert_with(|| LinkPreview {
+                    url: url.to_string(),
+                    ..Default::default()
+                });
+                apply_oembed(preview, &oembed);
+            }
+        }
+        preview.filter(|preview| !preview.title.is_empty())
+    }
+
+    /// Fetch `url`, returning the start of its body
+    async fn fetch(&self, url: &str) -> Option<String> {
+        let result = async {
+            let mut response = self.client.get(url).send().await?.error_for_status()?;
+            let mut body = Vec::new();
+            while let Some(chunk) = response.chunk().await? {
+                body.extend_from_slice(&chunk);
+                if body.len() >= MAX_PAGE_BYTES {
+                    body.truncate(MAX_PAGE_BYTES);
+                    break;
+                }
+            }
+            Ok::<_, reqwest::Error>(body)
+        }
+        .await;
+
+        match result {
+            Ok(body) => Some(String::from_utf8_lossy(&body).into_owned()),
+            Err(e) => {
+                tracing::debug!("Fetching {} failed: {}", url, e);
+                None
+            }
+        }
+    }
+}
+
+/// HTML processor turning bare links into preview cards
+pub struct LinkPreviewRenderer {
+    name: String,
+    version: String,
+    status: PluginStatus,
+    previewer: LinkPreviewer,
+    bare_link: Regex,
+}
+
+impl LinkPreviewRenderer {
+    /// Create a link preview stage from the `link_previews` settings
+    pub fn new(config: &LinkPreviewConfig) -> Result<Self> {
+        Ok(Self {
+            name: "link-preview-renderer".to_string(),
+            version: "0.1.0".to_string(),
+            status: PluginStatus::Loading,
+            previewer: LinkPreviewer::new(config)?,
+            bare_link: pattern(r#"<p><a href="(https?://[^"]+)">([^<]*)</a></p>"#)?,
+        })
+    }
+
+    /// Replace bare links in `html` with cards, returning the new HTML and
+    /// the number of cards
+    async fn process_links(&self, html: &str, context: &RenderContext) -> (String, usize) {
+        let decode = |text: &str| html_escape::decode_html_entities(text).to_string();
+        let mut urls: Vec<String> = self
+            .bare_link
+            .captures_iter(html)
+            .filter(|caps| decode(&caps[1]) == decode(&caps[2]))
+            .map(|caps| decode(&caps[1]))
+            .collect();
+        urls.sort();
+        urls.dedup();
+        if urls.is_empty() {
+            return (html.to_string(), 0);
+        }
+
+        let cache_dir = context.base_dir.join(LINK_PREVIEW_CACHE_DIR);
+        let previews: HashMap<String, LinkPreview> = future::join_all(
+            urls.iter()
+                .map(|url| self.previewer.preview(url, &cache_dir)),
+        )
+        .await
+        .into_iter()
+        .flatten()
+        .map(|preview| (preview.url.clone(), preview))
+        .collect();
+
+        let mut cards = 0;
+        let html = self.bare_link.replace_all(html, |caps: &Captures| {
+            let url = decode(&caps[1]);
+            match previews.get(&url).filter(|_| url == decode(&caps[2])) {
+                Some(preview) => {
+                    cards += 1;
+                    preview.to_html()
+                }
+                None => caps[0].to_string(),
+            }
+        });
+        (html.into_owned(), cards)
+    }
+}
+
+#[async_trait]
+impl Plugin for LinkPreviewRenderer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn dependencies(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    async fn initialize(&mut self, _context: &PluginContext) -> Result<()> {
+        tracing::info!("Initializing link preview renderer plugin");
+        self.status = PluginStatus::Active;
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        tracing::info!("Shutting down link preview renderer plugin");
+        self.status = PluginStatus::Stopped;
+        Ok(())
+    }
+
+    fn status(&self) -> PluginStatus {
+        self.status.clone()
+    }
+
+    fn provided_services(&self) -> Vec<&str> {
+        vec!["link-previews"]
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[async_trait]
+impl ContentRenderer for LinkPreviewRenderer {
+    fn can_render(&self, content_type: &str) -> bool {
+        matches!(content_type, "text/html" | "application/html")
+    }
+
+    async fn render(&self, content: &str, context: &RenderContext) -> Result<RenderResult> {
+        let start_time = Instant::now();
+        let (html, cards) = self.process_links(content, context).await;
+
+        let mut custom_metadata = HashMap::new();
+        custom_metadata.insert(
+            "link_cards".to_string(),
+            serde_json::Value::Number(cards.into()),
+        );
+
+        let metadata = RenderMetadata {
+            renderer_name: self.name.clone(),
+            renderer_version: self.version.clone(),
+            render_time_ms: Some(start_time.elapsed().as_millis() as u64),
+            content_hash: Some(format!("{:x}", html.len() as u64)),
+            custom_metadata,
+        };
+
+        Ok(RenderResult::new(html).with_metadata(metadata))
+    }
+
+    fn supported_extensions(&self) -> Vec<&str> {
+        vec!["html", "htm"]
+    }
+
+    fn priority(&self) -> u32 {
+        110 // After media players took their links, before images are sized
+    }
+
+    fn is_expensive(&self) -> bool {
+        true // Fetches every linked page
+    }
+
+    fn renderer_metadata(&self) -> RenderMetadata {
+        let mut custom_metadata = HashMap::new();
+        custom_metadata.insert(
+            "features".to_string(),
+            serde_json::json!(["oembed", "open_graph", "offline_cache"]),
+        );
+
+        RenderMetadata {
+            renderer_name: self.name.clone(),
+            renderer_version: self.version.clone(),
+            render_time_ms: None,
+            content_hash: None,
+            custom_metadata,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages_are_read_for_open_graph_and_oembed() {
+        let page = r#"<html><head>
+            <title>Fallback &amp; title</title>
+            <meta property="og:title" content="Rune &amp; friends">
+            <meta name="description" content="A markdown previewer">
+            <meta property='og:image' content='/cover.png'>
+            <link rel="alternate" type="application/json+oembed" href="//example.com/oembed?url=x">
+        </head></html>"#;
+
+        let mut preview = parse_page("https://example.com/post", page).unwrap();
+        assert_eq!(
+            preview,
+            LinkPreview {
+                url: "https://example.com/post".to_string(),
+                title: "Rune & friends".to_string(),
+                description: Some("A markdown previewer".to_string()),
+                image: Some("https://example.com/cover.png".to_string()),
+                site_name: None,
+            }
+        );
+        // Only known providers are asked, never the endpoint the page names
+        assert_eq!(oembed_endpoint("https://example.com/post"), None);
+        assert_eq!(
+            oembed_endpoint("https://youtu.be/abc?t=1").as_deref(),
+            Some("https://www.youtube.com/oembed?format=json&url=https%3A%2F%2Fyoutu.be%2Fabc%3Ft%3D1")
+        );
+        assert_eq!(
+            oembed_endpoint("https://m.youtube.com/watch?v=abc").as_deref(),
+            Some("https://www.youtube.com/oembed?format=json&url=https%3A%2F%2Fm.youtube.com%2Fwatch%3Fv%3Dabc")
+        );
+        assert_eq!(oembed_endpoint("https://notyoutube.com/watch"), None);
+
+        apply_oembed(
+            &mut preview,
+            &serde_json::json!({"title": "Embedded", "provider_name": "Example"}),
+        );
+        assert_eq!(preview.title, "Embedded");
+        assert_eq!(preview.site_name.as_deref(), Some("Example"));
+
+        assert_eq!(
+            parse_page("https://example.com", "<title>Plain</title>").map(|p| p.title),
+            Some("Plain".to_string())
+        );
+        assert_eq!(parse_page("https://example.com", "<p>nothing</p>"), None);
+    }
+
+    /// Serve `/post` and a slow `/slow` on a local port, answering 404
+    /// otherwise; returns the base URL
+    fn serve_pages() -> String {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                std::thread::spawn(move || {
+                    let mut request = [0; 1024];
+                    let read = stream.read(&mut request).unwrap_or(0);
+                    let request = String::from_utf8_lossy(&request[..read]);
+                    let (status, body) = match request.split(' ').nth(1).unwrap_or("/") {
+                        "/post" => (
+                            "200 OK",
+                            r#"<meta property="og:title" content="A &lt;post&gt;"><meta property="og:description" content="About things">"#,
+                        ),
+                        "/slow" => {
+                            std::thread::sleep(Duration::from_secs(5));
+                            ("200 OK", "<title>Too late</title>")
+                        }
+                        _ => ("404 Not Found", "<title>Not found</title>"),
+                    };
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 {}\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                });
+            }
+        });
+        base
+    }
+
+    #[tokio::test]
+    async fn test_bare_links_become_cached_cards() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let base = serve_pages();
+        let config = LinkPreviewConfig {
+            enabled: Some(true),
+            ..Default::default()
+        };
+        let context = RenderContext::new(
+            temp_dir.path().join("doc.md"),
+            temp_dir.path().to_path_buf(),
+            "light".to_string(),
+        );
+        let link = |path: &str| format!(r#"<p><a href="{0}{1}">{0}{1}</a></p>"#, base, path);
+        let html = format!(
+            "{}\n{}\n<p><a href=\"{}/post\">Named link</a></p>",
+            link("/post"),
+            link("/missing"),
+            base
+        );
+
+        let renderer = LinkPreviewRenderer::new(&config).unwrap();
+        let (rendered, cards) = renderer.process_links(&html, &context).await;
+        assert_eq!(cards, 1);
+        assert!(rendered.starts_with(&format!(
+            concat!(
+                r#"<a class="rune-link-card" href="{}/post" rel="noopener noreferrer">"#,
+                r#"<span class="rune-link-card-body"><strong class="rune-link-card-title">A &lt;post&gt;</strong>"#,
+                r#"<span class="rune-link-card-description">About things</span></span></a>"#
+            ),
+            base
+        )));
+        assert!(rendered.contains(&link("/missing")));
+        assert!(rendered.ends_with(&format!(r#"<p><a href="{}/post">Named link</a></p>"#, base)));
+
+        // Offline, the cached card stays and nothing is fetched
+        let offline = LinkPreviewRenderer::new(&LinkPreviewConfig {
+            offline: Some(true),
+            ..config.clone()
+        })
+        .unwrap();
+        let (offline_rendered, cards) = offline.process_links(&html, &context).await;
+        assert_eq!(cards, 1);
+        assert_eq!(offline_rendered, rendered);
+        let uncached = link("/other");
+        assert_eq!(offline.process_links(&uncached, &context).await.0, uncached);
+
+        // Slow fetches give up and leave the link plain
+        let slow = LinkPreviewRenderer::new(&LinkPreviewConfig {
+            timeout_ms: Some(50),
+            ..config
+        })
+        .unwrap();
+        let started = Instant::now();
+        assert_eq!(
+            slow.process_links(&link("/slow"), &context).await.0,
+            link("/slow")
+        );
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
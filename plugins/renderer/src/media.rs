@@ -0,0 +1,321 @@
+//! Video and audio embedding
+//!
+//! Images pointing to video or audio files, and links to them standing
+//! alone in a paragraph, become `<video>` or `<audio>` players. With the
+//! `embed_videos` setting, standalone YouTube and Vimeo links become
+//! embedded players as well.
+
+use async_trait::async_trait;
+use regex::{Captures, Regex};
+use rune_core::{
+    ContentRenderer, Plugin, PluginContext, PluginStatus, RenderContext, RenderMetadata,
+    RenderResult, Result, RuneError,
+};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Kind of player for a media file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MediaKind {
+    Video,
+    Audio,
+}
+
+impl MediaKind {
+    /// Player for the file an URL points to, by extension
+    fn of(url: &str) -> Option<Self> {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let (_, extension) = path.rsplit_once('.')?;
+        match extension.to_lowercase().as_str() {
+            "mp4" | "webm" => Some(Self::Video),
+            "mp3" => Some(Self::Audio),
+            _ => None,
+        }
+    }
+
+    fn tag(self) -> &'static str {
+        match self {
+            Self::Video => "video",
+            Self::Audio => "audio",
+        }
+    }
+}
+
+/// Player element for a media file; `label` is shown by browsers that
+/// cannot play it
+fn media_element(kind: MediaKind, src: &str, label: &str) -> String {
+    let label = if label.is_empty() { src } else { label };
+    format!(
+        r#"<{tag} controls preload="metadata" src="{src}"><a href="{src}">{label}</a></{tag}>"#,
+        tag = kind.tag(),
+        src = src,
+        label = label
+    )
+}
+
+/// Embed URL of a YouTube or Vimeo video link
+fn video_embed_url(href: &str) -> Option<String> {
+    let href = html_escape::decode_html_entities(href);
+    let rest = href
+        .strip_prefix("https://")
+        .or_else(|| href.strip_prefix("http://"))?;
+    let rest = rest
+        .strip_prefix("www.")
+        .or_else(|| rest.strip_prefix("m."))
+        .unwrap_or(rest);
+    let (host, path) = rest.split_once('/')?;
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let path = path.split('#').next().unwrap_or(path);
+
+    let youtube_id = |id: &str| {
+        (id.len() == 11
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .then(|| format!("https://www.youtube-nocookie.com/embed/{}", id))
+    };
+
+    match host {
+        "youtube.com" => match path.split_once('/') {
+            Some(("shorts" | "embed" | "live", id)) => youtube_id(id),
+            None if path == "watch" => query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("v="))
+                .and_then(youtube_id),
+            _ => None,
+        },
+        "youtu.be" => youtube_id(path),
+        "vimeo.com" => (!path.is_empty() && path.chars().all(|c| c.is_ascii_digit()))
+            .then(|| format!("https://player.vimeo.com/video/{}", path)),
+        _ => None,
+    }
+}
+
+/// HTML processor turning media references into players
+pub struct MediaRenderer {
+    name: String,
+    version: String,
+    status: PluginStatus,
+    embed_videos: bool,
+    img: Regex,
+    src: Regex,
+    alt: Regex,
+    standalone_link: Regex,
+}
+
+impl MediaRenderer {
+    /// Create a media renderer; `embed_videos` enables YouTube and Vimeo players
+    pub fn new(embed_videos: bool) -> Result<Self> {
+        let pattern = |re: &str| {
+            Regex::new(re)
+                .map_err(|e| RuneError::Plugin(format!("Regex compilation failed: {}", e)))
+        };
+        Ok(Self {
+            name: "media-renderer".to_string(),
+            version: "0.1.0".to_string(),
+            status: PluginStatus::Loading,
+            embed_videos,
+            img: pattern(r"<img\b[^>]*>")?,
+            src: pattern(r#"\ssrc="([^"]+)""#)?,
+            alt: pattern(r#"\salt="([^"]*)""#)?,
+            standalone_link: pattern(r#"<p><a href="([^"]+)"[^>]*>([^<]*)</a></p>"#)?,
+        })
+    }
+
+    /// Replace media references in `html`, returning the new HTML and the
+    /// number of players added
+    fn process_media(&self, html: &str) -> (String, usize) {
+        let mut players = 0;
+
+        let html = self.img.replace_all(html, |caps: &Captures| {
+            let tag = &caps[0];
+            let media = self
+                .src
+                .captures(tag)
+                .and_then(|src| Some((MediaKind::of(&src[1])?, src[1].to_string())));
+            match media {
+                Some((kind, src)) => {
+                    players += 1;
+                    let alt = self.alt.captures(tag).map(|alt| alt[1].to_string());
+                    media_element(kind, &src, alt.as_deref().unwrap_or(""))
+                }
+                None => tag.to_string(),
+            }
+        });
+
+        let html = self.standalone_link.replace_all(&html, |caps: &Captures| {
+            let (href, text) = (&caps[1], &caps[2]);
+            if let Some(kind) = MediaKind::of(href) {
+                players += 1;
+                return format!("<p>{}</p>", media_element(kind, href, text));
+            }
+            match video_embed_url(href).filter(|_| self.embed_videos) {
+                Some(embed) => {
+                    players += 1;
+                    format!(
+                        r#"<div class="rune-embed"><iframe src="{}" title="Video player" loading="lazy" allow="autoplay; encrypted-media; picture-in-picture; fullscreen" allowfullscreen></iframe></div>"#,
+                        embed
+                    )
+                }
+                None => caps[0].to_string(),
+            }
+        });
+
+        (html.into_owned(), players)
+    }
+}
+
+#[async_trait]
+impl Plugin for MediaRenderer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn dependencies(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    async fn initialize(&mut self, _context: &PluginContext) -> Result<()> {
+        tracing::info!("Initializing media renderer plugin");
+        self.status = PluginStatus::Active;
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        tracing::info!("Shutting down media renderer plugin");
+        self.status = PluginStatus::Stopped;
+        Ok(())
+    }
+
+    fn status(&self) -> PluginStatus {
+        self.status.clone()
+    }
+
+    fn provided_services(&self) -> Vec<&str> {
+        vec!["media-embedding"]
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[async_trait]
+impl ContentRenderer for MediaRenderer {
+    fn can_render(&self, content_type: &str) -> bool {
+        matches!(content_type, "text/html" | "application/html")
+    }
+
+    async fn render(&self, content: &str, _context: &RenderContext) -> Result<RenderResult> {
+        let start_time = Instant::now();
+        let (html, players) = self.process_media(content);
+
+        let mut custom_metadata = HashMap::new();
+        custom_metadata.insert(
+            "media_players".to_string(),
+            serde_json::Value::Number(players.into()),
+        );
+
+        let metadata = RenderMetadata {
+            renderer_name: self.name.clone(),
+            renderer_version: self.version.clone(),
+            render_time_ms: Some(start_time.elapsed().as_millis() as u64),
+            content_hash: Some(format!("{:x}", html.len() as u64)),
+            custom_metadata,
+        };
+
+        Ok(RenderResult::new(html).with_metadata(metadata))
+    }
+
+    fn supported_extensions(&self) -> Vec<&str> {
+        vec!["html", "htm"]
+    }
+
+    fn priority(&self) -> u32 {
+        120 // Before images are sized, so media references are no longer images
+    }
+
+    fn renderer_metadata(&self) -> RenderMetadata {
+        let mut custom_metadata = HashMap::new();
+        custom_metadata.insert(
+            "features".to_string(),
+            serde_json::json!(["video", "audio", "video_embeds"]),
+        );
+
+        RenderMetadata {
+            renderer_name: self.name.clone(),
+            renderer_version: self.version.clone(),
+            render_time_ms: None,
+            content_hash: None,
+            custom_metadata,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_references_become_players() {
+        let renderer = MediaRenderer::new(false).unwrap();
+        let html = concat!(
+            r#"<p><img src="clip.mp4" alt="A clip" /> and <img src="photo.png" alt="" /></p>"#,
+            "\n",
+            r#"<p><a href="media/song.MP3?v=2">Song</a></p>"#,
+            "\n",
+            r#"<p>See <a href="clip.webm">the clip</a></p>"#,
+            "\n",
+            r#"<p><a href="https://youtu.be/dQw4w9WgXcQ">https://youtu.be/dQw4w9WgXcQ</a></p>"#,
+        );
+
+        let (processed, players) = renderer.process_media(html);
+        assert_eq!(players, 2);
+        assert_eq!(
+            processed,
+            concat!(
+                r#"<p><video controls preload="metadata" src="clip.mp4"><a href="clip.mp4">A clip</a></video> and <img src="photo.png" alt="" /></p>"#,
+                "\n",
+                r#"<p><audio controls preload="metadata" src="media/song.MP3?v=2"><a href="media/song.MP3?v=2">Song</a></audio></p>"#,
+                "\n",
+                r#"<p>See <a href="clip.webm">the clip</a></p>"#,
+                "\n",
+                r#"<p><a href="https://youtu.be/dQw4w9WgXcQ">https://youtu.be/dQw4w9WgXcQ</a></p>"#,
+            )
+        );
+
+        let (embedded, players) = MediaRenderer::new(true).unwrap().process_media(html);
+        assert_eq!(players, 3);
+        assert!(embedded.contains(
+            r#"<div class="rune-embed"><iframe src="https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ""#
+        ));
+    }
+
+    #[test]
+    fn test_video_embed_urls() {
+        let youtube = Some("https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ".to_string());
+        assert_eq!(
+            video_embed_url("https://www.youtube.com/watch?list=x&amp;v=dQw4w9WgXcQ"),
+            youtube
+        );
+        assert_eq!(
+            video_embed_url("https://youtube.com/shorts/dQw4w9WgXcQ"),
+            youtube
+        );
+        assert_eq!(
+            video_embed_url("https://vimeo.com/76979871"),
+            Some("https://player.vimeo.com/video/76979871".to_string())
+        );
+        assert_eq!(video_embed_url("https://youtube.com/watch?v=short"), None);
+        assert_eq!(video_embed_url("https://vimeo.com/channels/staff"), None);
+        assert_eq!(video_embed_url("https://example.com/dQw4w9WgXcQ"), None);
+    }
+}
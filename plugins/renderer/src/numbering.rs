@@ -0,0 +1,466 @@
+//! Section numbers, numbered captions and cross-references
+//!
+//! With numbering on, headings get section numbers like `1.2.3`; a
+//! document whose only `h1` opens it is titled by it, and its sections are
+//! numbered from `h2`. Figures and tables get numbered captions:
+//!
+//! - a figure is an image alone in a paragraph, captioned by its alt text
+//!   and labelled by a `{#fig:label}` after it
+//! - a table is captioned by a `Table: caption {#tbl:label}` paragraph
+//!   right after it, or right before it when none follows
+//!
+//! `@fig:label` and `@tbl:label` become links reading "Figure 2" or
+//! "Table 1", wherever the reference is in the document. The numbers come
+//! from a pass over the markdown AST, and the rendered HTML then gets them
+//! in document order. The `numbering` setting turns numbering on for every
+//! document and the frontmatter key of the same name for one; both take
+//! `all`, `sections`, `figures` or `off`.
+//!
+//! Streamed documents render block by block, so they are numbered per block.
+
+use markdown::mdast::Node;
+use regex::{Captures, Regex};
+use rune_core::{frontmatter, Result, RuneError};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::headings::TocEntry;
+
+/// What gets numbered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Numbering {
+    pub sections: bool,
+    pub figures: bool,
+}
+
+impl Numbering {
+    /// Numbering of a document, its frontmatter overriding `self`
+    pub fn for_document(self, markdown: &str) -> Self {
+        frontmatter::value(markdown, "numbering")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(self)
+    }
+
+    /// Whether nothing is numbered
+    pub fn is_off(self) -> bool {
+        !self.sections && !self.figures
+    }
+}
+
+impl FromStr for Numbering {
+    type Err = RuneError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (sections, figures) = match s.trim().to_lowercase().as_str() {
+            "all" | "true" => (true, true),
+            "sections" => (true, false),
+            "figures" => (false, true),
+            "off" | "false" | "none" => (false, false),
+            other => {
+                return Err(RuneError::config(format!(
+                    "Unknown numbering '{}', expected all, sections, figures or off",
+                    other
+                )))
+            }
+        };
+        Ok(Self { sections, figures })
+    }
+}
+
+/// Numbers given out by a pass over the document's AST
+#[derive(Debug, Default)]
+struct Plan {
+    /// Number of each heading in order, empty for the title
+    sections: Vec<String>,
+    /// Whether each standalone image in order is a figure
+    figures: Vec<bool>,
+    /// Whether each table in order has a caption
+    tables: Vec<bool>,
+    /// Text of the references to each label
+    labels: HashMap<String, String>,
+}
+
+impl Plan {
+    fn of(markdown: &str) -> Self {
+        let mut options = markdown::ParseOptions::gfm();
+        options.constructs.frontmatter = frontmatter::is_closed(markdown);
+        let Ok(tree) = markdown::to_mdast(markdown, &options) else {
+            return Self::default();
+        };
+
+        let mut plan = Self::default();
+        let mut levels = Vec::new();
+        plan.visit(&tree, &mut levels, false);
+
+        let titled = levels.first() == Some(&1) && levels.iter().filter(|l| **l == 1).count() == 1;
+        let top = if titled {
+            2
+        } else {
+            levels.iter().copied().min().unwrap_or(1)
+        };
+        let mut counters = [0usize; 6];
+        for level in levels {
+            if level < top {
+                plan.sections.push(String::new());
+                continue;
+            }
+            let depth = (level - top) as usize;
+            counters[depth] += 1;
+            counters[depth + 1..].fill(0);
+            let number = counters[..=depth]
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(".");
+            plan.sections.push(number);
+        }
+        plan
+    }
+
+    fn visit(&mut self, node: &Node, levels: &mut Vec<u8>, in_list: bool) {
+        let in_list = in_list || matches!(node, Node::List(_));
+        let children = node.children().map(Vec::as_slice).unwrap_or_default();
+        let mut caption_used = false;
+        for (i, child) in children.iter().enumerate() {
+            match child {
+                Node::Heading(heading) => levels.push(heading.depth),
+                Node::Paragraph(paragraph) if !in_list => {
+                    if let Some((alt, label)) = figure(&paragraph.children) {
+                        self.add_figure(!alt.is_empty(), label);
+                    }
+                }
+                // Raw HTML is passed through, so its headings, tables and
+                // images take their place in the order too
+                Node::Html(raw) => {
+                    levels.extend(
+                        heading_pattern()
+                            .find_iter(&raw.value)
+                            .map(|found| found.as_str().as_bytes()[2] - b'0'),
+                    );
+                    if !in_list {
+                        for _ in figure_pattern().find_iter(&raw.value) {
+                            self.add_figure(false, None);
+                        }
+                    }
+                    self.tables
+                        .extend(table_pattern().find_iter(&raw.value).map(|_| false));
+                }
+                Node::Table(_) => {
+                    // A caption after the table wins over one before it,
+                    // unless the latter followed the previous table
+                    let after = children.get(i + 1).and_then(table_caption);
+                    let before = (!caption_used)
+                        .then(|| i.checked_sub(1).and_then(|i| table_caption(&children[i])))
+                        .flatten();
+                    caption_used = after.is_some();
+                    let caption = after.or(before);
+                    self.tables.push(caption.is_some());
+                    if let Some(label) = caption.flatten() {
+                        let number = self.tables.iter().filter(|t| **t).count();
+                        self.labels.insert(label, format!("Table {}", number));
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            caption_used = false;
+            self.visit(child, levels, in_list);
+        }
+    }
+
+    fn add_figure(&mut self, captioned: bool, label: Option<String>) {
+        self.figures.push(captioned);
+        if captioned {
+            let number = self.figures.iter().filter(|f| **f).count();
+            if let Some(label) = label {
+                self.labels.insert(label, format!("Figure {}", number));
+            }
+        }
+    }
+}
+
+/// Alt text and label of a paragraph holding only an image
+fn figure(children: &[Node]) -> Option<(String, Option<String>)> {
+    let (Node::Image(image), rest) = children.split_first()? else {
+        return None;
+    };
+    let label = match rest {
+        [] => None,
+        [Node::Text(text)] => Some(attribute_label(&text.value, "fig")?),
+        _ => return None,
+    };
+    Some((image.alt.clone(), label))
+}
+
+/// Label of a `Table: caption` paragraph, if it is one
+fn table_caption(node: &Node) -> Option<Option<String>> {
+    let Node::Paragraph(paragraph) = node else {
+        return None;
+    };
+    let Some(Node::Text(first)) = paragraph.children.first() else {
+        return None;
+    };
+    first
+        .value
+        .starts_with("Table:")
+        .then(|| match paragraph.children.last() {
+            Some(Node::Text(last)) => last
+                .value
+                .trim_end()
+                .rsplit_once("{#")
+                .and_then(|(_, attribute)| attribute_label(&format!("{{#{}", attribute), "tbl")),
+            _ => None,
+        })
+}
+
+/// Label of a `{#kind:label}` attribute making up all of `text`
+fn attribute_label(text: &str, kind: &str) -> Option<String> {
+    let label = text.trim().strip_prefix("{#")?.strip_suffix('}')?;
+    (label.starts_with(kind) && label[kind.len()..].starts_with(':') && !label.contains(' '))
+        .then(|| label.to_string())
+}
+
+fn pattern(cell: &'static OnceLock<Regex>, re: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(re).expect("valid numbering pattern"))
+}
+
+/// Opening tag of a heading
+fn heading_pattern() -> &'static Regex {
+    static HEADING: OnceLock<Regex> = OnceLock::new();
+    pattern(&HEADING, r"<h[1-6](?:\s[^>]*)?>")
+}
+
+/// Paragraph holding only an image, and a figure label
+fn figure_pattern() -> &'static Regex {
+    static FIGURE: OnceLock<Regex> = OnceLock::new();
+    pattern(
+        &FIGURE,
+        r#"<p>(<img\s[^>]*?alt="([^"]*)"[^>]*>)\s*(?:\{#(fig:[^}\s]+)\})?</p>"#,
+    )
+}
+
+/// A table element
+fn table_pattern() -> &'static Regex {
+    static TABLE: OnceLock<Regex> = OnceLock::new();
+    pattern(&TABLE, r"(?s)<table\b.*?</table>")
+}
+
+/// Number the sections, figures and tables of `html`, rendered from
+/// `markdown`, and resolve its cross-references; section numbers are
+/// added to the table of contents too. Returns the new HTML and the
+/// number of references resolved.
+pub fn number(
+    html: &str,
+    markdown: &str,
+    numbering: Numbering,
+    toc: &mut [TocEntry],
+) -> (String, usize) {
+    if numbering.is_off() {
+        return (html.to_string(), 0);
+    }
+    let plan = Plan::of(markdown);
+    let mut html = html.to_string();
+
+    if numbering.sections {
+        let mut sections = plan.sections.iter();
+        html = heading_pattern()
+            .replace_all(&html, |caps: &Captures| match sections.next() {
+                Some(number) if !number.is_empty() => format!(
+                    "{}<span class=\"rune-section-number\">{}</span> ",
+                    &caps[0], number
+                ),
+                _ => caps[0].to_string(),
+            })
+            .into_owned();
+        for (entry, number) in toc.iter_mut().zip(&plan.sections) {
+            if !number.is_empty() {
+                entry.text = format!("{} {}", number, entry.text);
+            }
+        }
+    }
+
+    if !numbering.figures {
+        return (html, 0);
+    }
+
+    // Images in list items are never figures
+    let mut figures = plan.figures.iter();
+    let mut figure_number = 0;
+    let mut open_items = 0usize;
+    let mut scanned = 0;
+    let source = html;
+    html = figure_pattern()
+        .replace_all(&source, |caps: &Captures| {
+            let start = caps.get(0).map_or(0, |m| m.start());
+            let between = &source[scanned..start];
+            open_items = (open_items + between.matches("<li").count())
+                .saturating_sub(between.matches("</li>").count());
+            scanned = start;
+            if open_items > 0 || figures.next() != Some(&true) || caps[2].is_empty() {
+                return caps[0].to_string();
+            }
+            figure_number += 1;
+            let id = caps
+                .get(3)
+                .map(|label| format!(" id=\"{}\"", label.as_str()))
+                .unwrap_or_default();
+            format!(
+                "<figure class=\"rune-figure\"{}>{}<figcaption>Figure {}: {}</figcaption></figure>",
+                id, &caps[1], figure_number, &caps[2]
+            )
+        })
+        .into_owned();
+
+    html = caption_tables(&html, &plan.tables);
+
+    static REFERENCE: OnceLock<Regex> = OnceLock::new();
+    let reference = pattern(
+        &REFERENCE,
+        r"(?s)<pre.*?</pre>|<code.*?</code>|<[^>]*>|@((?:fig|tbl):[\w:-]*\w)",
+    );
+    let mut resolved = 0;
+    html = reference
+        .replace_all(&html, |caps: &Captures| {
+            let Some(label) = caps.get(1) else {
+                return caps[0].to_string();
+            };
+            match plan.labels.get(label.as_str()) {
+                Some(text) => {
+                    resolved += 1;
+                    format!(
+                        "<a class=\"rune-xref\" href=\"#{}\">{}</a>",
+                        label.as_str(),
+                        text
+                    )
+                }
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned();
+
+    (html, resolved)
+}
+
+/// Wrap the captioned tables of `html` in figures, `captioned` telling in
+/// order which tables have a caption
+fn caption_tables(html: &str, captioned: &[bool]) -> String {
+    static CAPTION: OnceLock<Regex> = OnceLock::new();
+    let table = table_pattern();
+    let caption = pattern(
+        &CAPTION,
+        r"(?s)^\n?<p>Table:\s*(.*?)\s*(?:\{#(tbl:[^}\s]+)\})?</p>",
+    );
+
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    let mut number = 0;
+    for has_caption in captioned {
+        let Some(found) = table.find(rest) else {
+            break;
+        };
+        let mut before = &rest[..found.start()];
+        let after = &rest[found.end()..];
+        if !has_caption {
+            output.push_str(&rest[..found.end()]);
+            rest = after;
+            continue;
+        }
+
+        // The caption follows the table, or else ends the text before it
+        let (text, label, rest_after) = match caption.captures(after) {
+            Some(caps) => (
+                caps[1].to_string(),
+                caps.get(2).map(|m| m.as_str().to_string()),
+                &after[caps[0].len()..],
+            ),
+            None => {
+                let start = before.rfind("<p>Table:").unwrap_or(before.len());
+                let Some(caps) = caption.captures(&before[start..]) else {
+                    output.push_str(&rest[..found.end()]);
+                    rest = after;
+                    continue;
+                };
+                let text = caps[1].to_string();
+                let label = caps.get(2).map(|m| m.as_str().to_string());
+                before = &before[..start];
+                (text, label, after)
+            }
+        };
+
+        number += 1;
+        output.push_str(before);
+        output.push_str(&format!(
+            "<figure class=\"rune-table\"{}><figcaption>Table {}: {}</figcaption>\n{}</figure>",
+            label
+                .map(|label| format!(" id=\"{}\"", label))
+                .unwrap_or_default(),
+            number,
+            text,
+            found.as_str()
+        ));
+        rest = rest_after;
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(markdown: &str) -> String {
+        let html = markdown::to_html_with_options(markdown, &markdown::Options::gfm()).unwrap();
+        number(
+            &html,
+            markdown,
+            Numbering {
+                sections: true,
+                figures: true,
+            },
+            &mut [],
+        )
+        .0
+    }
+
+    #[test]
+    fn test_sections_are_numbered_below_the_title() {
+        let html = render("# Title\n\n## Intro\n\n### Scope\n\n## Usage\n\n`## not a heading`\n");
+        assert_eq!(
+            html,
+            "<h1>Title</h1>\n\
+             <h2><span class=\"rune-section-number\">1</span> Intro</h2>\n\
+             <h3><span class=\"rune-section-number\">1.1</span> Scope</h3>\n\
+             <h2><span class=\"rune-section-number\">2</span> Usage</h2>\n\
+             <p><code>## not a heading</code></p>\n"
+        );
+        assert_eq!(
+            Numbering::default().for_document("---\nnumbering: sections\n---\n"),
+            Numbering {
+                sections: true,
+                figures: false,
+            }
+        );
+        assert!("every".parse::<Numbering>().is_err());
+    }
+
+    #[test]
+    fn test_figures_and_tables_are_captioned_and_referenced() {
+        let html = render(concat!(
+            "See @fig:chart and @tbl:sizes, not @fig:missing or `@fig:chart`.\n\n",
+            "![Sales by month](chart.png){#fig:chart}\n\n",
+            "![](decoration.png)\n\n",
+            "| Size | Count |\n| --- | --- |\n| S | 1 |\n\n",
+            "Table: Sizes *sold* {#tbl:sizes}\n",
+        ));
+        assert!(html.starts_with(concat!(
+            "<p>See <a class=\"rune-xref\" href=\"#fig:chart\">Figure 1</a> and ",
+            "<a class=\"rune-xref\" href=\"#tbl:sizes\">Table 1</a>, not @fig:missing or <code>@fig:chart</code>.</p>\n",
+            "<figure class=\"rune-figure\" id=\"fig:chart\"><img src=\"chart.png\" alt=\"Sales by month\" />",
+            "<figcaption>Figure 1: Sales by month</figcaption></figure>\n",
+            "<p><img src=\"decoration.png\" alt=\"\" /></p>\n",
+            "<figure class=\"rune-table\" id=\"tbl:sizes\"><figcaption>Table 1: Sizes <em>sold</em></figcaption>\n<table>"
+        )));
+        assert!(html.ends_with("</table></figure>\n"));
+    }
+}
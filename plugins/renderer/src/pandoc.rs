@@ -0,0 +1,189 @@
+//! Preview of documents pandoc reads
+//!
+//! Org, reStructuredText, LaTeX and the other [`INPUT_FORMATS`] are
+//! converted to HTML by pandoc, after which they go through the same HTML
+//! processors as markdown. The renderer is only registered when pandoc is
+//! installed.
+
+use async_trait::async_trait;
+use rune_core::pandoc::{Pandoc, INPUT_FORMATS};
+use rune_core::{
+    ContentRenderer, Plugin, PluginContext, PluginStatus, RenderContext, RenderMetadata,
+    RenderResult, Result, RuneError,
+};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Renderer converting documents to HTML through pandoc
+pub struct PandocRenderer {
+    name: String,
+    version: String,
+    status: PluginStatus,
+    pandoc: Pandoc,
+}
+
+impl PandocRenderer {
+    /// Create a renderer running `pandoc`
+    pub fn new(pandoc: Pandoc) -> Self {
+        Self {
+            name: "pandoc-renderer".to_string(),
+            version: "0.1.0".to_string(),
+            status: PluginStatus::Loading,
+            pandoc,
+        }
+    }
+}
+
+#[async_trait]
+impl Plugin for PandocRenderer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn dependencies(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    async fn initialize(&mut self, _context: &PluginContext) -> Result<()> {
+        tracing::info!("Initializing pandoc renderer plugin");
+        self.status = PluginStatus::Active;
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        tracing::info!("Shutting down pandoc renderer plugin");
+        self.status = PluginStatus::Stopped;
+        Ok(())
+    }
+
+    fn status(&self) -> PluginStatus {
+        self.status.clone()
+    }
+
+    fn provided_services(&self) -> Vec<&str> {
+        vec!["pandoc-rendering"]
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[async_trait]
+impl ContentRenderer for PandocRenderer {
+    fn can_render(&self, content_type: &str) -> bool {
+        INPUT_FORMATS
+            .iter()
+            .any(|input| input.content_type == content_type)
+    }
+
+    async fn render(&self, content: &str, context: &RenderContext) -> Result<RenderResult> {
+        let start_time = Instant::now();
+        let input = INPUT_FORMATS
+            .iter()
+            .find(|input| input.content_type == context.content_type)
+            .ok_or_else(|| {
+                RuneError::Plugin(format!(
+                    "pandoc does not read {} documents",
+                    context.content_type
+                ))
+            })?;
+
+        let html = self
+            .pandoc
+            .convert(content, input.format, "html5", &context.base_dir, false)
+            .await?;
+        let html = String::from_utf8_lossy(&html).into_owned();
+
+        let mut custom_metadata = HashMap::new();
+        custom_metadata.insert(
+            "input_format".to_string(),
+            serde_json::Value::String(input.format.to_string()),
+        );
+        custom_metadata.insert(
+            "pandoc_version".to_string(),
+            serde_json::Value::String(self.pandoc.version().to_string()),
+        );
+
+        let metadata = RenderMetadata {
+            renderer_name: self.name.clone(),
+            renderer_version: self.version.clone(),
+            render_time_ms: Some(start_time.elapsed().as_millis() as u64),
+            content_hash: Some(format!("{:x}", html.len() as u64)),
+            custom_metadata,
+        };
+
+        Ok(RenderResult::new(html).with_metadata(metadata))
+    }
+
+    fn supported_extensions(&self) -> Vec<&str> {
+        INPUT_FORMATS
+            .iter()
+            .flat_map(|input| input.extensions.iter().copied())
+            .collect()
+    }
+
+    fn priority(&self) -> u32 {
+        200 // Converts the document, like the markdown renderer
+    }
+
+    fn renderer_metadata(&self) -> RenderMetadata {
+        let mut custom_metadata = HashMap::new();
+        custom_metadata.insert(
+            "pandoc_version".to_string(),
+            serde_json::Value::String(self.pandoc.version().to_string()),
+        );
+
+        RenderMetadata {
+            renderer_name: self.name.clone(),
+            renderer_version: self.version.clone(),
+            render_time_ms: None,
+            content_hash: None,
+            custom_metadata,
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[tokio::test]
+    async fn test_renders_org_through_pandoc() {
+        // A stand-in turning every input into the same paragraph
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let program = temp_dir.path().join("pandoc");
+        std::fs::write(
+            &program,
+            "#!/bin/sh\n[ \"$1\" = --version ] && { echo 'pandoc 9.9'; exit 0; }\n\
+             cat >/dev/null; echo \"<p>from $2</p>\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let renderer = PandocRenderer::new(Pandoc::at(program).unwrap());
+
+        let context = RenderContext::new(
+            temp_dir.path().join("notes.org"),
+            temp_dir.path().to_path_buf(),
+            "light".to_string(),
+        );
+        assert!(renderer.can_render(&context.content_type));
+        assert!(!renderer.can_render("text/markdown"));
+
+        let result = renderer.render("* Notes", &context).await.unwrap();
+        assert_eq!(result.html, "<p>from org</p>\n");
+        assert_eq!(
+            result.metadata.custom_metadata["pandoc_version"],
+            "pandoc 9.9"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Text direction and CJK annotations of rendered blocks
+//!
+//! Documents containing right-to-left text get a `dir` attribute on every
+//! block element: the direction of the block's first strong character, or
+//! the document's where the block has none. Frontmatter `dir: rtl` (or
+//! `ltr`) overrides detection for the whole document and `lang: …` tags
+//! every block. Blocks with Chinese, Japanese or Korean text are marked
+//! `data-cjk` for the template's line-breaking rules.
+
+use regex::{Captures, Regex};
+use rune_core::frontmatter;
+use std::sync::OnceLock;
+
+/// Base direction of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+}
+
+impl Direction {
+    /// Value of the `dir` attribute
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ltr => "ltr",
+            Self::Rtl => "rtl",
+        }
+    }
+
+    /// Direction of the first strong character of `html`'s text, if any
+    pub fn detect(html: &str) -> Option<Self> {
+        text_chars(html).find_map(|c| {
+            if is_rtl(c) {
+                Some(Self::Rtl)
+            } else if c.is_alphabetic() {
+                Some(Self::Ltr)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Whether `c` belongs to a right-to-left script (Hebrew, Arabic, Syriac,
+/// Thaana, N'Ko and their extensions)
+fn is_rtl(c: char) -> bool {
+    matches!(c as u32,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF)
+}
+
+/// Whether `c` is Han, Hiragana, Katakana or Hangul
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x11FF | 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF
+            | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0x20000..=0x3134F)
+}
+
+/// Characters of `html` outside tags and character references
+fn text_chars(html: &str) -> impl Iterator<Item = char> + '_ {
+    let mut skip_until = None;
+    html.chars().filter(move |&c| match skip_until {
+        Some(end) => {
+            if c == end {
+                skip_until = None;
+            }
+            false
+        }
+        None => match c {
+            '<' => {
+                skip_until = Some('>');
+                false
+            }
+            '&' => {
+                skip_until = Some(';');
+                false
+            }
+            _ => true,
+        },
+    })
+}
+
+/// Direction and language a document asks for in its frontmatter
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Typography {
+    pub dir: Option<Direction>,
+    pub lang: Option<String>,
+}
+
+impl Typography {
+    /// Read `dir` and `lang` from the YAML frontmatter of `markdown`
+    pub fn from_frontmatter(markdown: &str) -> Self {
+        let mut typography = Self::default();
+        for (key, value) in frontmatter::entries(markdown) {
+            match key {
+                "dir" => {
+                    typography.dir = match value.to_ascii_lowercase().as_str() {
+                        "rtl" => Some(Direction::Rtl),
+                        "ltr" => Some(Direction::Ltr),
+                        _ => None,
+                    }
+                }
+                "lang" if !value.is_empty() => {
+                    typography.lang = Some(value.replace('_', "-"));
+                }
+                _ => {}
+            }
+        }
+        typography
+    }
+
+    /// Add `dir`, `lang` and `data-cjk` attributes to the block elements of
+    /// `html`, returning the new HTML and the document's direction
+    pub fn annotate(&self, html: &str) -> (String, Direction) {
+        static BLOCK: OnceLock<Regex> = OnceLock::new();
+        let block = BLOCK.get_or_init(|| {
+            Regex::new(r"<(p|h[1-6]|li|ul|ol|dl|dt|dd|blockquote|table|th|td|pre)(\s[^>]*)?>")
+                .expect("valid block pattern")
+        });
+
+        let has_rtl = self.dir.is_some() || text_chars(html).any(is_rtl);
+        let has_cjk = text_chars(html).any(is_cjk);
+        let document = self
+            .dir
+            .or_else(|| has_rtl.then(|| Direction::detect(html)).flatten())
+            .unwrap_or(Direction::Ltr);
+        if !has_rtl && !has_cjk && self.lang.is_none() {
+            return (html.to_string(), document);
+        }
+
+        let annotated = block.replace_all(html, |caps: &Captures| {
+            let (name, attributes) = (&caps[1], caps.get(2).map_or("", |m| m.as_str()));
+            let end = caps.get(0).map_or(0, |m| m.end());
+            let content = &html[end..];
+            let content = &content[..content
+                .find(&format!("</{}>", name))
+                .unwrap_or(content.len())];
+
+            let mut added = String::new();
+            if has_rtl && !attributes.contains("dir=") {
+                let dir = if name == "pre" {
+                    Direction::Ltr
+                } else {
+                    self.dir
+                        .or_else(|| Direction::detect(content))
+                        .unwrap_or(document)
+                };
+                added.push_str(&format!(r#" dir="{}""#, dir.as_str()));
+            }
+            if let Some(lang) = self.lang.as_deref().filter(|_| name != "pre") {
+                if !attributes.contains("lang=") {
+                    added.push_str(&format!(
+                        r#" lang="{}""#,
+                        html_escape::encode_double_quoted_attribute(lang)
+                    ));
+                }
+            }
+            if name != "pre" && text_chars(content).any(is_cjk) {
+                added.push_str(" data-cjk");
+            }
+            format!("<{}{}{}>", name, attributes, added)
+        });
+
+        (annotated.into_owned(), document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixed_direction_blocks() {
+        let html = concat!(
+            "<h1>Notes &amp; שלום</h1>\n",
+            "<p>مرحبا بالعالم and more</p>\n",
+            "<ul>\n<li>English item</li>\n<li>42</li>\n</ul>\n",
+            "<pre><code>let x = \"שלום\";</code></pre>\n",
+            "<p dir=\"ltr\">کد</p>",
+        );
+        let (annotated, direction) = Typography::default().annotate(html);
+        assert_eq!(direction, Direction::Ltr);
+        assert_eq!(
+            annotated,
+            concat!(
+                "<h1 dir=\"ltr\">Notes &amp; שלום</h1>\n",
+                "<p dir=\"rtl\">مرحبا بالعالم and more</p>\n",
+                "<ul dir=\"ltr\">\n<li dir=\"ltr\">English item</li>\n<li dir=\"ltr\">42</li>\n</ul>\n",
+                "<pre dir=\"ltr\"><code>let x = \"שלום\";</code></pre>\n",
+                "<p dir=\"ltr\">کد</p>",
+            )
+        );
+
+        // A document starting in Hebrew is right-to-left, and so are its
+        // blocks without strong characters
+        let (annotated, direction) =
+            Typography::default().annotate("<p>שלום world</p>\n<p>1, 2, 3</p>\n<p>Hi</p>");
+        assert_eq!(direction, Direction::Rtl);
+        assert_eq!(
+            annotated,
+            "<p dir=\"rtl\">שלום world</p>\n<p dir=\"rtl\">1, 2, 3</p>\n<p dir=\"ltr\">Hi</p>"
+        );
+
+        // English-only documents are left alone
+        let plain = "<p>Hello <em>world</em></p>";
+        assert_eq!(Typography::default().annotate(plain).0, plain);
+    }
+
+    #[test]
+    fn test_frontmatter_override_and_cjk() {
+        let typography =
+            Typography::from_frontmatter("---\ntitle: x\ndir: RTL\nlang: \"zh_TW\"\n---\n# Hi\n");
+        assert_eq!(
+            typography,
+            Typography {
+                dir: Some(Direction::Rtl),
+                lang: Some("zh-TW".to_string()),
+            }
+        );
+        assert_eq!(
+            Typography::from_frontmatter("# dir: rtl"),
+            Typography::default()
+        );
+
+        let (annotated, direction) = typography
+            .annotate("<p>English</p>\n<p class=\"x\">中文排版</p>\n<pre><code>x</code></pre>");
+        assert_eq!(direction, Direction::Rtl);
+        assert_eq!(
+            annotated,
+            concat!(
+                "<p dir=\"rtl\" lang=\"zh-TW\">English</p>\n",
+                "<p class=\"x\" dir=\"rtl\" lang=\"zh-TW\" data-cjk>中文排版</p>\n",
+                "<pre dir=\"ltr\"><code>x</code></pre>",
+            )
+        );
+
+        let (annotated, _) = Typography::default().annotate("<p>日本語のテキスト</p>");
+        assert_eq!(annotated, "<p data-cjk>日本語のテキスト</p>");
+    }
+}
@@ -0,0 +1,121 @@
+//! Golden snapshots of the render pipeline
+//!
+//! Every document in `tests/corpus` is rendered through the pipeline the
+//! renderer plugin builds, and the HTML is compared with the snapshot under
+//! `tests/snapshots`. The markup does not depend on the theme, so each
+//! built-in theme has a snapshot of what it does change: its stylesheet and
+//! the Mermaid theme it picks. When a change to the output is intended, bless the new snapshots with `cargo insta review` (or run the
+//! tests with `INSTA_UPDATE=always`) and commit them with the change.
+
+use rune_core::{
+    Config, InMemoryEventBus, Plugin, PluginContext, RenderContext, RendererRegistry, StateManager,
+};
+use rune_renderer::RendererPlugin;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const CORPUS_DIR: &str = "tests/corpus";
+
+fn context() -> PluginContext {
+    PluginContext::new(
+        Arc::new(InMemoryEventBus::new()),
+        Arc::new(Config::new()),
+        Arc::new(StateManager::new()),
+    )
+}
+
+async fn pipeline() -> Arc<RendererRegistry> {
+    let mut plugin = RendererPlugin::new();
+    plugin.initialize(&context()).await.unwrap();
+    plugin.registry().unwrap()
+}
+
+/// Built-in themes, sorted by name, and the theme pages start with
+async fn themes() -> (Vec<rune_theme::Theme>, String) {
+    let mut plugin = rune_theme::ThemePlugin::new();
+    plugin.initialize(&context()).await.unwrap();
+    let provider = plugin.theme_provider().unwrap();
+    let mut themes = Vec::new();
+    for info in provider.available_themes().await.unwrap() {
+        themes.push(provider.load_theme(&info.name).await.unwrap());
+    }
+    themes.sort_by(|a, b| a.info.name.cmp(&b.info.name));
+    let current = provider.get_current_theme().await.unwrap().unwrap();
+    (themes, current)
+}
+
+fn corpus() -> Vec<PathBuf> {
+    let mut documents: Vec<PathBuf> = std::fs::read_dir(CORPUS_DIR)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "md"))
+        .collect();
+    documents.sort();
+    documents
+}
+
+/// What a document snapshot holds: the renderers that ran, the assets the
+/// page loads and the HTML
+async fn render(registry: &RendererRegistry, document: &Path, theme: &str) -> String {
+    let content = std::fs::read_to_string(document).unwrap();
+    let context = RenderContext::new(
+        document.to_path_buf(),
+        PathBuf::from(CORPUS_DIR),
+        theme.to_string(),
+    );
+    let result = registry
+        .render_with_pipeline(&content, &context)
+        .await
+        .unwrap();
+
+    let assets: Vec<&str> = result
+        .assets
+        .iter()
+        .map(|asset| asset.url.as_str())
+        .collect();
+    format!(
+        "renderers: {}\nassets: {:?}\n\n{}",
+        result.metadata.renderer_name, assets, result.html
+    )
+}
+
+/// What a theme snapshot holds: whether it is dark, its Mermaid theme and
+/// its stylesheet
+fn themed(theme: &rune_theme::Theme) -> String {
+    format!(
+        "dark: {}\nmermaid: {}\n\n{}\n",
+        theme.info.is_dark,
+        theme.mermaid_theme.as_deref().unwrap_or("none"),
+        theme
+            .css
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    )
+}
+
+#[tokio::test]
+async fn test_corpus_renders_like_its_snapshots() {
+    let registry = pipeline().await;
+    let (_, theme) = themes().await;
+    let documents = corpus();
+    assert!(!documents.is_empty());
+
+    for document in &documents {
+        let name = document.file_stem().unwrap().to_string_lossy();
+        let rendered = render(&registry, document, &theme).await;
+        insta::assert_snapshot!(name.to_string(), rendered);
+    }
+}
+
+#[tokio::test]
+async fn test_themes_look_like_their_snapshots() {
+    let (themes, _) = themes().await;
+    assert!(!themes.is_empty());
+
+    for theme in &themes {
+        insta::assert_snapshot!(format!("theme@{}", theme.info.name), themed(theme));
+    }
+}
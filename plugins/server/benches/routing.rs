@@ -0,0 +1,86 @@
+//! HTTP handler routing benchmarks
+//!
+//! Run with `cargo bench -p rune-server --bench routing`; criterion's
+//! `--save-baseline` and `--baseline` options compare runs.
+
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use criterion::{criterion_group, criterion_main, Criterion};
+use rune_core::event::InMemoryEventBus;
+use rune_core::Result;
+use rune_server::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use std::sync::Arc;
+
+struct Route {
+    path: String,
+    priority: i32,
+}
+
+#[async_trait]
+impl HttpHandler for Route {
+    fn path_pattern(&self) -> &str {
+        &self.path
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        Ok(HttpResponse::new(StatusCode::OK))
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+fn lookup(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+
+    // Roughly the number of handlers a multi-root session registers
+    let registry = runtime.block_on(async {
+        let registry = HandlerRegistry::new(Arc::new(InMemoryEventBus::new()));
+        for i in 0..64 {
+            registry
+                .register_http_handler(Arc::new(Route {
+                    path: format!("/docs/section-{}", i),
+                    priority: 10,
+                }))
+                .await
+                .unwrap();
+        }
+        registry
+            .register_http_handler(Arc::new(Route {
+                path: "/api/status".to_string(),
+                priority: 5,
+            }))
+            .await
+            .unwrap();
+        registry
+    });
+
+    let mut group = c.benchmark_group("routing");
+    let lookups = [
+        ("first_match", "/api/status"),
+        ("last_match", "/docs/section-63/page.md"),
+        ("no_match", "/missing"),
+    ];
+    for (name, path) in lookups {
+        group.bench_function(name, |b| {
+            b.to_async(&runtime)
+                .iter(|| registry.find_http_handler(path, &Method::GET))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lookup);
+criterion_main!(benches);
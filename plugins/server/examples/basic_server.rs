@@ -115,9 +115,16 @@ blockquote {
         hostname: "127.0.0.1".to_string(),
         port: 3030,
         enable_cors: true,
+        cors: Default::default(),
         max_connections: Some(100),
         request_timeout_secs: Some(30),
         websocket_ping_interval_secs: Some(30),
+        enable_discovery: false,
+        offline: false,
+        not_found_page: None,
+        tunnel: None,
+        max_body_size: rune_server::DEFAULT_MAX_BODY_SIZE,
+        shutdown_grace_secs: 10,
     };
 
     let server_plugin = ServerPlugin::with_config(server_config);
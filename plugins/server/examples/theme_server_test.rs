@@ -28,7 +28,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         path: "/themes".to_string(),
         query_params: HashMap::new(),
         headers: HeaderMap::new(),
-        body: Vec::new(),
+        body: Default::default(),
         path_params: HashMap::new(),
     };
 
@@ -41,7 +41,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         path: "/themes/catppuccin-mocha/css".to_string(),
         query_params: HashMap::new(),
         headers: HeaderMap::new(),
-        body: Vec::new(),
+        body: Default::default(),
         path_params: HashMap::new(),
     };
 
@@ -55,7 +55,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         path: "/themes/catppuccin-mocha/metadata".to_string(),
         query_params: HashMap::new(),
         headers: HeaderMap::new(),
-        body: Vec::new(),
+        body: Default::default(),
         path_params: HashMap::new(),
     };
 
@@ -75,7 +75,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         path: "/api/theme".to_string(),
         query_params: HashMap::new(),
         headers: HeaderMap::new(),
-        body: r#"{"theme": "catppuccin-latte"}"#.as_bytes().to_vec(),
+        body: r#"{"theme": "catppuccin-latte"}"#.into(),
         path_params: HashMap::new(),
     };
 
@@ -85,7 +85,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         switch_response.status
     );
 
-    if let Ok(response_text) = String::from_utf8(switch_response.body.clone()) {
+    if let Ok(response_text) = String::from_utf8(switch_response.body.to_vec()) {
         println!("📝 Switch response: {}", response_text);
     }
 
@@ -98,14 +98,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         path: "/api/theme".to_string(),
         query_params: HashMap::new(),
         headers: HeaderMap::new(),
-        body: Vec::new(),
+        body: Default::default(),
         path_params: HashMap::new(),
     };
 
     let info_response = theme_info_handler.handle(info_request).await?;
     println!("✅ Theme info response status: {:?}", info_response.status);
 
-    if let Ok(response_text) = String::from_utf8(info_response.body.clone()) {
+    if let Ok(response_text) = String::from_utf8(info_response.body.to_vec()) {
         println!("📝 Info response: {}", response_text);
     }
 
@@ -0,0 +1,347 @@
+//! Heading anchor checks across the documents of directory roots
+//!
+//! Links like `setup.md#linux` or `#usage` only work when the target
+//! document has a heading with that slug. An [`AnchorIndex`] maps each
+//! document of a root to the ids its headings get, slugged the way the
+//! renderer slugs them, and [`check_root`] reports the links of the root's
+//! documents whose document or anchor does not exist. `GET /api/anchors`
+//! returns the broken links of every directory root being served.
+
+use crate::{roots, HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::Method;
+use markdown::mdast::Node;
+use percent_encoding::percent_decode_str;
+use rune_core::{
+    error::Result, frontmatter, plugin::PluginContext, state::ServedRoot, SlugStyle, Slugger,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// A link of a document to a missing document or heading
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BrokenAnchor {
+    /// Linking document, relative to the root
+    pub document: String,
+    /// Line of the link in the document
+    pub line: usize,
+    /// Link target as written
+    pub target: String,
+    pub reason: String,
+}
+
+/// Heading ids of every document of a directory root
+#[derive(Debug, Default)]
+pub struct AnchorIndex {
+    anchors: HashMap<PathBuf, HashSet<String>>,
+}
+
+impl AnchorIndex {
+    /// Index the markdown documents below `dir`
+    pub fn build(dir: &Path, style: SlugStyle) -> Self {
+        let anchors = roots::markdown_documents(dir)
+            .into_iter()
+            .filter_map(|document| {
+                let content = std::fs::read_to_string(&document).ok()?;
+                Some((document, heading_ids(&content, style)))
+            })
+            .collect();
+        Self { anchors }
+    }
+
+    /// Whether `document` is indexed
+    pub fn has_document(&self, document: &Path) -> bool {
+        self.anchors.contains_key(document)
+    }
+
+    /// Whether `document` has a heading with id `anchor`
+    pub fn has_anchor(&self, document: &Path, anchor: &str) -> bool {
+        self.anchors
+            .get(document)
+            .is_some_and(|ids| ids.contains(anchor))
+    }
+}
+
+fn parse_options(content: &str) -> markdown::ParseOptions {
+    // Frontmatter as the renderer reads it, so it never looks like a heading
+    let mut options = markdown::ParseOptions::gfm();
+    options.constructs.frontmatter = frontmatter::is_closed(content);
+    options
+}
+
+/// Ids the headings of a document get when rendered
+pub fn heading_ids(content: &str, style: SlugStyle) -> HashSet<String> {
+    let Ok(tree) = markdown::to_mdast(content, &parse_options(content)) else {
+        return HashSet::new();
+    };
+    let mut slugger = Slugger::new(style);
+    let mut ids = HashSet::new();
+    walk(&tree, &mut |node| {
+        if let Node::Heading(heading) = node {
+            let mut text = String::new();
+            for child in &heading.children {
+                plain_text(child, &mut text);
+            }
+            ids.insert(slugger.slug(&text));
+        }
+    });
+    ids
+}
+
+/// Text the renderer shows for inline content: markup and raw HTML tags
+/// are left out
+fn plain_text(node: &Node, text: &mut String) {
+    match node {
+        Node::Text(t) => text.push_str(&t.value),
+        Node::InlineCode(code) => text.push_str(&code.value),
+        Node::InlineMath(math) => text.push_str(&math.value),
+        Node::Html(_) => {}
+        _ => {
+            for child in node.children().into_iter().flatten() {
+                plain_text(child, text);
+            }
+        }
+    }
+}
+
+fn walk(node: &Node, visit: &mut impl FnMut(&Node)) {
+    visit(node);
+    for child in node.children().into_iter().flatten() {
+        walk(child, visit);
+    }
+}
+
+/// Links of a document, with their lines
+fn links(content: &str) -> Vec<(String, usize)> {
+    let Ok(tree) = markdown::to_mdast(content, &parse_options(content)) else {
+        return Vec::new();
+    };
+    let mut links = Vec::new();
+    walk(&tree, &mut |node| {
+        let url = match node {
+            Node::Link(link) => &link.url,
+            Node::Definition(definition) => &definition.url,
+            _ => return,
+        };
+        let line = node.position().map_or(0, |position| position.start.line);
+        links.push((url.clone(), line));
+    });
+    links
+}
+
+/// `path` with `.` and `..` components resolved, without touching the disk
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Broken heading links of the documents of a directory root
+pub fn check_root(dir: &Path, style: SlugStyle) -> Vec<BrokenAnchor> {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    let index = AnchorIndex::build(&dir, style);
+    let mut broken = Vec::new();
+
+    for document in roots::markdown_documents(&dir) {
+        let Ok(content) = std::fs::read_to_string(&document) else {
+            continue;
+        };
+        let relative = document
+            .strip_prefix(&dir)
+            .unwrap_or(&document)
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        for (url, line) in links(&content) {
+            // Only relative links to markdown documents, or within this one
+            if url.contains("://") || url.starts_with('/') || url.starts_with("mailto:") {
+                continue;
+            }
+            let (path, anchor) = url.split_once('#').unwrap_or((url.as_str(), ""));
+            let path = percent_decode_str(path).decode_utf8_lossy();
+            let target = if path.is_empty() {
+                document.clone()
+            } else {
+                let lower = path.to_lowercase();
+                if !lower.ends_with(".md") && !lower.ends_with(".markdown") {
+                    continue;
+                }
+                normalize(&document.parent().unwrap_or(&dir).join(path.as_ref()))
+            };
+
+            let reason = if !index.has_document(&target) {
+                if !target.starts_with(&dir) || !target.is_file() {
+                    "document not found".to_string()
+                } else {
+                    continue;
+                }
+            } else if anchor.is_empty() {
+                continue;
+            } else {
+                let anchor = percent_decode_str(anchor).decode_utf8_lossy();
+                if index.has_anchor(&target, &anchor) {
+                    continue;
+                }
+                format!("no heading with id '{}'", anchor)
+            };
+            broken.push(BrokenAnchor {
+                document: relative.clone(),
+                line,
+                target: url.clone(),
+                reason,
+            });
+        }
+    }
+    broken
+}
+
+/// Handler for `GET /api/anchors`
+pub struct AnchorCheckHandler {
+    path_pattern: String,
+    roots: Vec<ServedRoot>,
+    style: SlugStyle,
+}
+
+impl AnchorCheckHandler {
+    pub fn new(path_pattern: String, roots: Vec<ServedRoot>, style: SlugStyle) -> Self {
+        Self {
+            path_pattern,
+            roots,
+            style,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for AnchorCheckHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        let roots: Vec<ServedRoot> = self
+            .roots
+            .iter()
+            .filter(|root| root.is_dir())
+            .cloned()
+            .collect();
+        let style = self.style;
+
+        // Reading every document is blocking work
+        let report = tokio::task::spawn_blocking(move || {
+            roots
+                .iter()
+                .map(|root| {
+                    serde_json::json!({
+                        "root": root.prefix,
+                        "broken": check_root(&root.path, style),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .map_err(|e| rune_core::RuneError::Server(format!("Anchor check failed: {}", e)))?;
+
+        Ok(HttpResponse::json(&serde_json::json!({ "roots": report }))?
+            .with_header("cache-control", "no-store"))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Register the anchor check of the served roots
+pub async fn register_anchor_check_handler(
+    registry: &HandlerRegistry,
+    roots: &[ServedRoot],
+    context: &PluginContext,
+) -> Result<()> {
+    let style = match context
+        .config
+        .get_global_setting::<String>("heading_slugs")
+        .map(|style| style.parse::<SlugStyle>())
+    {
+        Some(Ok(style)) => style,
+        Some(Err(e)) => {
+            warn!("{}; checking anchors with the default slugs", e);
+            SlugStyle::default()
+        }
+        None => SlugStyle::default(),
+    };
+
+    registry
+        .register_http_handler(Arc::new(AnchorCheckHandler::new(
+            "/api/anchors".to_string(),
+            roots.to_vec(),
+            style,
+        )))
+        .await?;
+
+    info!("Registered anchor check handler");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_broken_anchors_across_documents() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::create_dir(dir.join("guide")).unwrap();
+        std::fs::write(
+            dir.join("guide/setup.md"),
+            "---\ntitle: Setup\n---\n# Install `rune`\n\n## Linux\n\n## Linux\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("index.md"),
+            concat!(
+                "# Index\n\n",
+                "[ok](guide/setup.md#install-rune) [dup](guide/setup.md#linux-1)\n",
+                "[missing](guide/setup.md#windows)\n",
+                "[self](#index) [gone](#nowhere) [doc](missing.md#x)\n",
+                "[web](https://example.com/a.md#x) [image](logo.png#y)\n",
+                "[up](./guide/../index.md#index)\n",
+            ),
+        )
+        .unwrap();
+
+        let broken = check_root(dir, SlugStyle::Github);
+        let found: Vec<_> = broken
+            .iter()
+            .map(|b| (b.line, b.target.as_str(), b.reason.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (4, "guide/setup.md#windows", "no heading with id 'windows'"),
+                (5, "#nowhere", "no heading with id 'nowhere'"),
+                (5, "missing.md#x", "document not found"),
+            ]
+        );
+        assert!(broken.iter().all(|b| b.document == "index.md"));
+    }
+}
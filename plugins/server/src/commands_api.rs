@@ -0,0 +1,508 @@
+//! Command palette API
+//!
+//! `GET /api/commands` lists every command the palette offers, by id, with
+//! its title, category and keybinding: the editor's commands and those
+//! plugins registered in the shared [`CommandRegistry`], a command per
+//! [transform](crate::transform_api) of the served document, per theme and
+//! per export format. `POST /api/commands/<id>/execute` runs one, with the
+//! JSON object of the request body as its arguments, and answers with the
+//! [`CommandOutcome`]: commands of the editor leave the page an action to
+//! perform, exports a URL to download.
+
+use crate::handlers::{DocumentSlot, THEMES};
+use crate::transform_api::run_transform;
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::command::{Command, CommandArgs, CommandInfo, CommandOutcome, CommandRegistry};
+use rune_core::export::markup::Markup;
+use rune_core::plugin::PluginContext;
+use rune_core::transform::TransformRegistry;
+use rune_core::{EventBus, Result, RuneError, SystemEvent};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+/// Route of the command API
+const COMMANDS_ROUTE: &str = "/api/commands";
+
+/// How long a command may run; exports through pandoc take their time
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Prefix of the ids of transform commands
+const TRANSFORM_PREFIX: &str = "transform.";
+
+/// Applies a transform to the served document
+struct TransformCommand {
+    name: String,
+    description: String,
+    transforms: Arc<TransformRegistry>,
+    document: DocumentSlot,
+}
+
+#[async_trait]
+impl Command for TransformCommand {
+    fn info(&self) -> CommandInfo {
+        CommandInfo::new(
+            &format!("{}{}", TRANSFORM_PREFIX, self.name),
+            &self.description,
+            "Transform",
+        )
+    }
+
+    async fn execute(&self, args: &CommandArgs) -> Result<CommandOutcome> {
+        let document = self.document.read().await.clone();
+        let document = document.ok_or_else(|| RuneError::config("No document is being served"))?;
+        let (_, changed) = run_transform(
+            &self.transforms,
+            document.markdown_file(),
+            &self.name,
+            args,
+            true,
+        )
+        .await?;
+        Ok(CommandOutcome::Done {
+            message: if changed {
+                format!("Applied {}", self.name)
+            } else {
+                format!("{} changed nothing", self.name)
+            },
+            data: Some(serde_json::json!({ "changed": changed })),
+        })
+    }
+}
+
+/// Switches every page to a theme
+struct ThemeCommand {
+    theme: &'static str,
+    display_name: &'static str,
+    event_bus: Arc<dyn EventBus>,
+}
+
+#[async_trait]
+impl Command for ThemeCommand {
+    fn info(&self) -> CommandInfo {
+        CommandInfo::new(
+            &format!("theme.{}", self.theme),
+            &format!("Theme: {}", self.display_name),
+            "Theme",
+        )
+    }
+
+    async fn execute(&self, _args: &CommandArgs) -> Result<CommandOutcome> {
+        self.event_bus
+            .publish_system_event(SystemEvent::theme_changed(self.theme.to_string()))
+            .await?;
+        Ok(CommandOutcome::Done {
+            message: format!("Theme switched to {}", self.theme),
+            data: None,
+        })
+    }
+}
+
+/// Downloads the document of the page in another format
+///
+/// Arguments: `url`, the address of the page's document when it is not the
+/// one served at `/`.
+struct ExportCommand {
+    /// Format of the copy API, `html` or a [`Markup`]
+    format: String,
+    title: String,
+    extension: &'static str,
+    document: DocumentSlot,
+}
+
+#[async_trait]
+impl Command for ExportCommand {
+    fn info(&self) -> CommandInfo {
+        CommandInfo::new(&format!("export.{}", self.format), &self.title, "Export")
+    }
+
+    async fn execute(&self, args: &CommandArgs) -> Result<CommandOutcome> {
+        let page = args
+            .get("url")
+            .and_then(|url| url.as_str())
+            .map(|url| url.split(['?', '#']).next().unwrap_or_default())
+            .filter(|url| !url.is_empty() && *url != "/");
+        let (copy, stem) = match page {
+            Some(page) => {
+                let stem = page.trim_end_matches('/').rsplit('/').next();
+                let stem = stem.and_then(|name| Path::new(name).file_stem());
+                (
+                    format!("{}/copy", page.trim_end_matches('/')),
+                    stem.map(|stem| percent_encoding::percent_decode(stem.as_encoded_bytes()))
+                        .map(|stem| stem.decode_utf8_lossy().into_owned()),
+                )
+            }
+            None => {
+                let document = self.document.read().await.clone();
+                let document =
+                    document.ok_or_else(|| RuneError::config("No document is being served"))?;
+                let stem = document.markdown_file().file_stem();
+                (
+                    "/copy".to_string(),
+                    stem.map(|stem| stem.to_string_lossy().into_owned()),
+                )
+            }
+        };
+        Ok(CommandOutcome::Download {
+            url: format!("{}?format={}", copy, self.format),
+            file_name: format!(
+                "{}.{}",
+                stem.unwrap_or_else(|| "document".to_string()),
+                self.extension
+            ),
+        })
+    }
+}
+
+/// Commands of the palette: those of the registry and one per transform
+pub struct CommandPalette {
+    commands: Arc<CommandRegistry>,
+    transforms: Arc<TransformRegistry>,
+    document: DocumentSlot,
+}
+
+impl CommandPalette {
+    pub fn new(
+        commands: Arc<CommandRegistry>,
+        transforms: Arc<TransformRegistry>,
+        document: DocumentSlot,
+    ) -> Self {
+        Self {
+            commands,
+            transforms,
+            document,
+        }
+    }
+
+    /// Transform commands, made anew as plugins may add transforms any time
+    async fn transform_commands(&self) -> Vec<Arc<dyn Command>> {
+        self.transforms
+            .list_transforms()
+            .await
+            .into_iter()
+            .map(|transform| {
+                let command: Arc<dyn Command> = Arc::new(TransformCommand {
+                    name: transform.name,
+                    description: transform.description,
+                    transforms: self.transforms.clone(),
+                    document: self.document.clone(),
+                });
+                command
+            })
+            .collect()
+    }
+
+    /// Every command, by id
+    pub async fn list(&self) -> Vec<CommandInfo> {
+        let mut commands = self.commands.list_commands().await;
+        for command in self.transform_commands().await {
+            let info = command.info();
+            if !commands.iter().any(|listed| listed.id == info.id) {
+                commands.push(info);
+            }
+        }
+        commands.sort_by(|a, b| a.id.cmp(&b.id));
+        commands
+    }
+
+    /// The command `id`, if there is one
+    pub async fn command(&self, id: &str) -> Option<Arc<dyn Command>> {
+        if let Some(command) = self.commands.command(id).await {
+            return Some(command);
+        }
+        id.strip_prefix(TRANSFORM_PREFIX)?;
+        self.transform_commands()
+            .await
+            .into_iter()
+            .find(|command| command.info().id == id)
+    }
+}
+
+/// Handler for `GET /api/commands`
+pub struct CommandListHandler {
+    path_pattern: String,
+    palette: Arc<CommandPalette>,
+}
+
+impl CommandListHandler {
+    pub fn new(path_pattern: String, palette: Arc<CommandPalette>) -> Self {
+        Self {
+            path_pattern,
+            palette,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for CommandListHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        HttpResponse::json(&serde_json::json!({ "commands": self.palette.list().await }))
+    }
+
+    fn matches_path(&self, path: &str) -> bool {
+        path == self.path_pattern
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Handler for `POST /api/commands/<id>/execute`
+pub struct CommandExecuteHandler {
+    path_pattern: String,
+    palette: Arc<CommandPalette>,
+}
+
+impl CommandExecuteHandler {
+    pub fn new(path_pattern: String, palette: Arc<CommandPalette>) -> Self {
+        Self {
+            path_pattern,
+            palette,
+        }
+    }
+
+    /// Id of the command executed at `path`
+    fn command_id<'a>(&self, path: &'a str) -> Option<&'a str> {
+        let id = path
+            .strip_prefix(&self.path_pattern)?
+            .strip_prefix('/')?
+            .strip_suffix("/execute")?;
+        (!id.is_empty() && !id.contains('/')).then_some(id)
+    }
+}
+
+#[async_trait]
+impl HttpHandler for CommandExecuteHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let Some(id) = self.command_id(&request.path) else {
+            return Ok(HttpResponse::error(
+                StatusCode::NOT_FOUND,
+                "Commands are executed at /api/commands/<id>/execute",
+            ));
+        };
+        let args = if request.body.iter().all(u8::is_ascii_whitespace) {
+            CommandArgs::new()
+        } else {
+            match serde_json::from_slice(&request.body) {
+                Ok(args) => args,
+                Err(e) => {
+                    return Ok(HttpResponse::error(
+                        StatusCode::BAD_REQUEST,
+                        &format!("Command arguments must be a JSON object: {}", e),
+                    ))
+                }
+            }
+        };
+        let Some(command) = self.palette.command(id).await else {
+            return Ok(HttpResponse::error(
+                StatusCode::NOT_FOUND,
+                &format!("Unknown command '{}'", id),
+            ));
+        };
+
+        let outcome = match command.execute(&args).await {
+            Ok(outcome) => outcome,
+            Err(RuneError::Config(message)) => {
+                return Ok(HttpResponse::error(StatusCode::BAD_REQUEST, &message))
+            }
+            Err(e) => return Err(e),
+        };
+        info!("Executed command {}", id);
+        HttpResponse::json(&serde_json::json!({ "command": id, "outcome": outcome }))
+    }
+
+    fn matches_path(&self, path: &str) -> bool {
+        self.command_id(path).is_some()
+    }
+
+    fn edits(&self) -> bool {
+        true // Transform commands save the document
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        Some(COMMAND_TIMEOUT)
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Commands of the server itself: a theme switch per theme and a download
+/// per export format
+fn server_commands(
+    event_bus: &Arc<dyn EventBus>,
+    document: &DocumentSlot,
+) -> Vec<Arc<dyn Command>> {
+    let mut commands: Vec<Arc<dyn Command>> = THEMES
+        .iter()
+        .map(|&(theme, display_name)| {
+            let command: Arc<dyn Command> = Arc::new(ThemeCommand {
+                theme,
+                display_name,
+                event_bus: event_bus.clone(),
+            });
+            command
+        })
+        .collect();
+    commands.push(Arc::new(ExportCommand {
+        format: "html".to_string(),
+        title: "Export as HTML".to_string(),
+        extension: "html",
+        document: document.clone(),
+    }));
+    for markup in Markup::all() {
+        let (title, extension) = match markup {
+            Markup::PlainText => ("Export as plain text", "txt"),
+            Markup::Slack => ("Export for Slack", "txt"),
+            Markup::Jira => ("Export for Jira", "txt"),
+        };
+        commands.push(Arc::new(ExportCommand {
+            format: markup.to_string(),
+            title: title.to_string(),
+            extension,
+            document: document.clone(),
+        }));
+    }
+    commands
+}
+
+/// Register the command palette API for the document in `document`
+pub async fn register_command_api_handlers(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+    document: DocumentSlot,
+) -> Result<()> {
+    let commands = CommandRegistry::shared(context).await?;
+    for command in server_commands(&context.event_bus, &document) {
+        // A restarted server finds its commands registered already
+        if commands.command(&command.info().id).await.is_none() {
+            commands.register_command(command).await?;
+        }
+    }
+    let transforms = TransformRegistry::shared(context).await?;
+    let palette = Arc::new(CommandPalette::new(commands, transforms, document));
+
+    registry
+        .register_http_handler(Arc::new(CommandListHandler::new(
+            COMMANDS_ROUTE.to_string(),
+            palette.clone(),
+        )))
+        .await?;
+    registry
+        .register_http_handler(Arc::new(CommandExecuteHandler::new(
+            COMMANDS_ROUTE.to_string(),
+            palette,
+        )))
+        .await?;
+
+    info!("Registered command palette API handlers");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::MarkdownHandler;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_lists_and_executes_commands() {
+        let temp_dir = TempDir::new().unwrap();
+        let doc = temp_dir.path().join("list.md");
+        std::fs::write(&doc, "- b\n- a\n").unwrap();
+        let document: DocumentSlot = Default::default();
+        *document.write().await =
+            Some(Arc::new(MarkdownHandler::new("/".to_string(), doc.clone())));
+        let event_bus: Arc<dyn EventBus> = Arc::new(rune_core::InMemoryEventBus::new());
+        let commands = Arc::new(CommandRegistry::new());
+        for command in server_commands(&event_bus, &document) {
+            commands.register_command(command).await.unwrap();
+        }
+        let palette = Arc::new(CommandPalette::new(
+            commands,
+            Arc::new(TransformRegistry::new()),
+            document,
+        ));
+
+        let response = CommandListHandler::new("/api/commands".to_string(), palette.clone())
+            .handle(HttpRequest::get("/api/commands"))
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        let commands = body["commands"].as_array().unwrap();
+        let command = |id: &str| commands.iter().find(|command| command["id"] == id);
+        assert_eq!(command("editor.bold").unwrap()["keybinding"], "Mod+B");
+        assert_eq!(
+            command("transform.sort-list").unwrap()["category"],
+            "Transform"
+        );
+        assert!(command("theme.dark").is_some());
+        assert!(command("export.slack").is_some());
+
+        let execute = CommandExecuteHandler::new("/api/commands".to_string(), palette);
+        assert!(execute.matches_path("/api/commands/editor.bold/execute"));
+        assert!(!execute.matches_path("/api/commands"));
+        let run = |id: &str, body: &'static str| {
+            execute.handle(
+                HttpRequest::new(Method::POST, &format!("/api/commands/{}/execute", id))
+                    .with_body(body),
+            )
+        };
+
+        let response = run("editor.bold", "").await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(body["outcome"]["kind"], "client");
+        assert_eq!(body["outcome"]["action"], "editor.bold");
+
+        let response = run("transform.sort-list", "{}").await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(body["outcome"]["data"]["changed"], true);
+        assert_eq!(std::fs::read_to_string(&doc).unwrap(), "- a\n- b\n");
+
+        let response = run("export.html", r#"{"url": "/docs/read%20me.md"}"#)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(
+            body["outcome"]["url"],
+            "/docs/read%20me.md/copy?format=html"
+        );
+        assert_eq!(body["outcome"]["file_name"], "read me.html");
+
+        let response = run("transform.wrap-lines", r#"{"width": 0}"#)
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+        let response = run("nope", "").await.unwrap();
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,839 @@
+//! Runtime configuration API
+//!
+//! `GET /api/config` returns the active configuration with secrets redacted.
+//! `PATCH /api/config` takes `{"changes": {"server.port": 4000}, "persist": true}`,
+//! applies the changes through the [`RuntimeConfigManager`], validates the
+//! result and announces it with a `ConfigChanged` event so the affected
+//! plugins can pick it up. With `persist` the changes are also written to the
+//! configuration file. Only the [`RUNTIME_SETTINGS`](config::RUNTIME_SETTINGS)
+//! and plugins' `enabled` flags may be changed; access, trust and paths are
+//! left to the file. Both are only available to local clients in dev mode,
+//! or with the configured `api_token` sent as a bearer token or Basic
+//! password.
+
+use crate::{BodyStream, HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::body::Bytes;
+use axum::http::{Method, StatusCode};
+use base64::Engine;
+use rune_core::{
+    config::{self, Config, RuntimeConfigManager},
+    error::Result,
+    event::{EventBus, SystemEvent},
+    plugin::PluginContext,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Shared resource key of the runtime configuration manager
+pub const CONFIG_MANAGER_RESOURCE: &str = "config_manager";
+
+/// Body of `PATCH /api/config`
+#[derive(Debug, Default, Deserialize)]
+struct ConfigPatch {
+    changes: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    persist: bool,
+}
+
+/// Outcome of a configuration change
+#[derive(Debug, Serialize)]
+struct ConfigPatchResponse {
+    changes: rune_core::config::ConfigDiff,
+    changed_fields: Vec<String>,
+    affected_plugins: Vec<String>,
+    /// The listening address only changes after a restart
+    restart_required: bool,
+    persisted_to: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    persist_error: Option<String>,
+}
+
+/// Handler for `GET` or `PATCH /api/config`
+pub struct ConfigApiHandler {
+    path_pattern: String,
+    method: Method,
+    manager: Arc<RwLock<RuntimeConfigManager>>,
+    event_bus: Arc<dyn EventBus>,
+}
+
+impl ConfigApiHandler {
+    /// Create a handler answering `method` requests
+    pub fn new(
+        path_pattern: String,
+        method: Method,
+        manager: Arc<RwLock<RuntimeConfigManager>>,
+        event_bus: Arc<dyn EventBus>,
+    ) -> Self {
+        Self {
+            path_pattern,
+            method,
+            manager,
+            event_bus,
+        }
+    }
+
+    async fn check_access(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        let manager = self.manager.read().await;
+        check_api_access(manager.get_config(), request, "The configuration API")
+    }
+
+    async fn get(&self) -> Result<HttpResponse> {
+        let manager = self.manager.read().await;
+        let body = serde_json::json!({
+            "config": manager.get_config().redacted()?,
+            "metadata": manager.get_metadata(),
+        });
+        Ok(HttpResponse::json(&body)?.with_header("cache-control", "no-store"))
+    }
+
+    async fn patch(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let patch: ConfigPatch = match serde_json::from_slice(&request.body) {
+            Ok(patch) => patch,
+            Err(e) => {
+                return Ok(HttpResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Invalid configuration patch: {}", e),
+                ))
+            }
+        };
+
+        let mut refused: Vec<&str> = patch
+            .changes
+            .keys()
+            .map(String::as_str)
+            .filter(|key| !config::is_runtime_setting(key))
+            .collect();
+        if !refused.is_empty() {
+            refused.sort_unstable();
+            return Ok(HttpResponse::error(
+                StatusCode::BAD_REQUEST,
+                &format!(
+                    "Cannot be changed at runtime, edit the configuration file instead: {}",
+                    refused.join(", ")
+                ),
+            ));
+        }
+
+        let mut manager = self.manager.write().await;
+        let diff = match manager.update_config(patch.changes.clone()) {
+            Ok(diff) => diff,
+            Err(e) => {
+                return Ok(HttpResponse::error(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    &e.to_string(),
+                ))
+            }
+        };
+
+        let (persisted_to, persist_error) = if patch.persist {
+            match manager.persist(&patch.changes) {
+                Ok(path) => (Some(path), None),
+                Err(e) => {
+                    warn!("Configuration applied but not persisted: {}", e);
+                    (None, Some(e.to_string()))
+                }
+            }
+        } else {
+            (None, None)
+        };
+        drop(manager);
+
+        let changed_fields = diff.changed_fields();
+        let affected_plugins = diff.affected_plugins();
+        if diff.has_changes() {
+            info!("Configuration changed: {}", changed_fields.join(", "));
+            self.event_bus
+                .publish_system_event(SystemEvent::config_changed(
+                    changed_fields.clone(),
+                    affected_plugins.clone(),
+                ))
+                .await?;
+        }
+
+        HttpResponse::json(&ConfigPatchResponse {
+            changes: diff.redacted(),
+            changed_fields,
+            affected_plugins,
+            restart_required: diff.requires_restart(),
+            persisted_to,
+            persist_error,
+        })
+    }
+}
+
+/// Refusal response unless the request carries the API token, or comes from
+/// a local client in dev mode
+///
+/// Dev mode only waives the token for requests naming the server by a local
+/// address, so neither pages on other sites nor DNS rebinding can use the
+/// APIs on the user's behalf. Behind a tunnel the token is asked for even
+/// then, since anyone with the public URL can reach the server.
+pub(crate) fn check_api_access(
+    config: &Config,
+    request: &HttpRequest,
+    api: &str,
+) -> Option<HttpResponse> {
+    check_access(config, request, api, Waiver::DevMode)
+}
+
+/// Refusal response unless the request carries the API token or comes from a
+/// local client, for endpoints like the editor that work outside dev mode
+pub(crate) fn check_edit_access(
+    config: &Config,
+    request: &HttpRequest,
+    what: &str,
+) -> Option<HttpResponse> {
+    check_access(config, request, what, Waiver::Local)
+}
+
+/// Refusal response for a WebSocket upgrade from a page of another site than
+/// the server and the allowed CORS origins, or one without the API token
+/// when `websocket_requires_token` is set
+///
+/// Browsers cannot set headers on sockets, so the token may also be given
+/// as a `token` query parameter; they do send cached Basic credentials.
+/// Pages opened with the token get it in a [`TOKEN_META`] element and add
+/// it to their sockets' URLs.
+pub(crate) fn check_websocket_access(
+    config: &Config,
+    request: &HttpRequest,
+) -> Option<HttpResponse> {
+    if !is_same_origin(request) && !is_allowed_origin(config, request) {
+        return Some(HttpResponse::error(
+            StatusCode::FORBIDDEN,
+            "WebSocket connections from other sites are refused",
+        ));
+    }
+    if config.get_global_setting::<bool>("websocket_requires_token") != Some(true) {
+        return None;
+    }
+    let Some(token) = config.get_global_setting::<String>("api_token") else {
+        return Some(HttpResponse::error(
+            StatusCode::FORBIDDEN,
+            "WebSocket connections require an api_token to be configured",
+        ));
+    };
+    match socket_token(request) {
+        Some(presented) if tokens_match(&presented, &token) => None,
+        _ => Some(unauthorized()),
+    }
+}
+
+/// Name of the `<meta>` element handing the API token to a page
+pub(crate) const TOKEN_META: &str = "rune-token";
+
+/// Token for the page answering `request` to open its sockets with: the
+/// API token, when sockets require it and the request presented it
+pub(crate) fn page_token(config: &Config, request: &HttpRequest) -> Option<String> {
+    if config.get_global_setting::<bool>("websocket_requires_token") != Some(true) {
+        return None;
+    }
+    let token = config.get_global_setting::<String>("api_token")?;
+    let presented = socket_token(request)?;
+    tokens_match(&presented, &token).then_some(token)
+}
+
+/// `response` with `token` in a [`TOKEN_META`] element when it is a page,
+/// kept out of caches
+pub(crate) fn with_page_token(mut response: HttpResponse, token: &str) -> HttpResponse {
+    use futures_util::StreamExt;
+
+    let is_page = response
+        .headers
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    if !is_page {
+        return response;
+    }
+    let meta = format!(
+        "<head>\n    <meta name=\"{}\" content=\"{}\">",
+        TOKEN_META,
+        html_escape::encode_double_quoted_attribute(token)
+    );
+    match response.stream.take() {
+        // A streamed page sends its head in the first piece
+        Some(BodyStream(stream)) => {
+            let mut head = Some(meta);
+            let stream = stream.map(move |piece| {
+                let meta = head.take();
+                piece.map(|piece| match (meta, std::str::from_utf8(&piece)) {
+                    (Some(meta), Ok(text)) => Bytes::from(text.replacen("<head>", &meta, 1)),
+                    _ => piece,
+                })
+            });
+            response.stream = Some(BodyStream(stream.boxed()));
+        }
+        None => {
+            if let Ok(page) = std::str::from_utf8(&response.body) {
+                response.body = Bytes::from(page.replacen("<head>", &meta, 1));
+            }
+        }
+    }
+    response.with_header("cache-control", "no-store")
+}
+
+/// Token a socket or page request presents: as credentials or as the
+/// `token` query parameter
+fn socket_token(request: &HttpRequest) -> Option<String> {
+    presented_token(request).or_else(|| request.query_params.get("token").cloned())
+}
+
+/// Configuration access is checked against: the runtime configuration when
+/// the host shares its manager, else the one the server started with
+#[derive(Clone)]
+pub struct ApiAccess {
+    config: Arc<Config>,
+    manager: Option<Arc<RwLock<RuntimeConfigManager>>>,
+}
+
+impl ApiAccess {
+    pub fn new(config: Arc<Config>, manager: Option<Arc<RwLock<RuntimeConfigManager>>>) -> Self {
+        Self { config, manager }
+    }
+
+    /// Access following the configuration of `context`
+    pub async fn from_context(context: &PluginContext) -> Self {
+        let manager = context
+            .get_shared_resource::<RwLock<RuntimeConfigManager>>(CONFIG_MANAGER_RESOURCE)
+            .await;
+        Self::new(context.config.clone(), manager)
+    }
+
+    /// [`check_api_access`] against the current configuration
+    pub(crate) async fn check(&self, request: &HttpRequest, api: &str) -> Option<HttpResponse> {
+        match &self.manager {
+            Some(manager) => check_api_access(manager.read().await.get_config(), request, api),
+            None => check_api_access(&self.config, request, api),
+        }
+    }
+
+    /// [`check_websocket_access`] against the current configuration
+    pub(crate) async fn check_websocket(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        match &self.manager {
+            Some(manager) => check_websocket_access(manager.read().await.get_config(), request),
+            None => check_websocket_access(&self.config, request),
+        }
+    }
+
+    /// The configured `api_token`
+    pub(crate) async fn api_token(&self) -> Option<String> {
+        match &self.manager {
+            Some(manager) => manager
+                .read()
+                .await
+                .get_config()
+                .get_global_setting::<String>("api_token"),
+            None => self.config.get_global_setting::<String>("api_token"),
+        }
+    }
+
+    /// [`page_token`] against the current configuration
+    pub(crate) async fn page_token(&self, request: &HttpRequest) -> Option<String> {
+        match &self.manager {
+            Some(manager) => page_token(manager.read().await.get_config(), request),
+            None => page_token(&self.config, request),
+        }
+    }
+
+    /// [`check_edit_access`] against the current configuration
+    pub(crate) async fn check_edit(
+        &self,
+        request: &HttpRequest,
+        what: &str,
+    ) -> Option<HttpResponse> {
+        match &self.manager {
+            Some(manager) => check_edit_access(manager.read().await.get_config(), request, what),
+            None => check_edit_access(&self.config, request, what),
+        }
+    }
+}
+
+/// Clients trusted without the API token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Waiver {
+    /// Local clients in dev mode
+    DevMode,
+    /// Local clients
+    Local,
+}
+
+fn check_access(
+    config: &Config,
+    request: &HttpRequest,
+    api: &str,
+    waiver: Waiver,
+) -> Option<HttpResponse> {
+    let token = config.get_global_setting::<String>("api_token");
+    if let (Some(token), Some(presented)) = (&token, presented_token(request)) {
+        return if tokens_match(&presented, token) {
+            None
+        } else {
+            Some(unauthorized())
+        };
+    }
+
+    if !is_same_origin(request) {
+        return Some(HttpResponse::error(
+            StatusCode::FORBIDDEN,
+            &format!("{} refuses requests from other sites", api),
+        ));
+    }
+    let local = names_local_host(request);
+    let waived = match waiver {
+        Waiver::DevMode => {
+            let tunneled = config.get_global_setting::<String>("tunnel").is_some();
+            local
+                && config.get_global_setting::<bool>("dev_mode") == Some(true)
+                && !(tunneled && token.is_some())
+        }
+        Waiver::Local => local,
+    };
+    if waived {
+        return None;
+    }
+
+    match (token, waiver) {
+        (Some(_), _) => Some(unauthorized()),
+        (None, Waiver::DevMode) => Some(HttpResponse::error(
+            StatusCode::FORBIDDEN,
+            &format!("{} requires --dev-mode or an api_token", api),
+        )),
+        (None, Waiver::Local) => Some(HttpResponse::error(
+            StatusCode::FORBIDDEN,
+            &format!("{} requires an api_token when not used locally", api),
+        )),
+    }
+}
+
+fn unauthorized() -> HttpResponse {
+    HttpResponse::error(StatusCode::UNAUTHORIZED, "Invalid or missing API token")
+        .with_header("www-authenticate", "Basic realm=\"rune\", Bearer")
+}
+
+/// Token sent as a bearer token or as the password of Basic authentication,
+/// with any user name
+fn presented_token(request: &HttpRequest) -> Option<String> {
+    let authorization = request
+        .headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())?;
+    if let Some(bearer) = authorization.strip_prefix("Bearer ") {
+        return Some(bearer.trim().to_string());
+    }
+    let encoded = authorization.strip_prefix("Basic ")?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()?;
+    let credentials = String::from_utf8(decoded).ok()?;
+    credentials
+        .split_once(':')
+        .map(|(_, password)| password.to_string())
+}
+
+/// Host and explicit port of a `Host` header or origin; IPv6 hosts keep
+/// their brackets
+pub(crate) fn authority(value: &str) -> Option<(String, Option<u16>)> {
+    let url = url::Url::parse(&format!("http://{}/", value)).ok()?;
+    Some((url.host_str()?.to_ascii_lowercase(), url.port()))
+}
+
+/// Whether the request's `Origin`, which browsers send with cross-origin and
+/// state-changing requests, is the server itself
+fn is_same_origin(request: &HttpRequest) -> bool {
+    let Some(origin) = request.headers.get("origin") else {
+        return true;
+    };
+    let Some(origin) = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| url::Url::parse(origin).ok())
+    else {
+        return false;
+    };
+    let Some(host) = request
+        .headers
+        .get("host")
+        .and_then(|host| host.to_str().ok())
+        .and_then(authority)
+    else {
+        return false;
+    };
+    let Some(origin_host) = origin.host_str() else {
+        return false;
+    };
+    origin_host.eq_ignore_ascii_case(&host.0)
+        && host
+            .1
+            .is_none_or(|port| origin.port_or_known_default() == Some(port))
+}
+
+/// Whether the request's `Origin` is one the CORS configuration allows
+fn is_allowed_origin(config: &Config, request: &HttpRequest) -> bool {
+    let Some(origin) = request
+        .headers
+        .get("origin")
+        .and_then(|origin| origin.to_str().ok())
+    else {
+        return false;
+    };
+    config.server.cors_enabled
+        && config.server.cors.origins().iter().any(|allowed| {
+            allowed == "*" || allowed.trim_end_matches('/').eq_ignore_ascii_case(origin)
+        })
+}
+
+/// Whether the request names the server by a local address: an IP address,
+/// `localhost`, a `.local` or a single-label name. Other names, e.g. of a
+/// tunnel or of a site rebinding its DNS to this machine, are not local.
+fn names_local_host(request: &HttpRequest) -> bool {
+    let Some(host) = request.headers.get("host") else {
+        // Browsers always send one
+        return true;
+    };
+    let Some((host, _)) = host.to_str().ok().and_then(authority) else {
+        return false;
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.parse::<std::net::IpAddr>().is_ok()
+        || host == "localhost"
+        || host.ends_with(".localhost")
+        || host.ends_with(".local")
+        || !host.contains('.')
+}
+
+/// Compare tokens without stopping at the first differing byte
+pub(crate) fn tokens_match(presented: &str, expected: &str) -> bool {
+    presented.len() == expected.len()
+        && presented
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[async_trait]
+impl HttpHandler for ConfigApiHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        self.method.clone()
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        if let Some(refusal) = self.check_access(&request).await {
+            return Ok(refusal);
+        }
+
+        if request.method == Method::PATCH {
+            self.patch(request).await
+        } else {
+            self.get().await
+        }
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Register the configuration API if a configuration manager is shared
+pub async fn register_config_api_handlers(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+) -> Result<()> {
+    let Some(manager) = context
+        .get_shared_resource::<RwLock<RuntimeConfigManager>>(CONFIG_MANAGER_RESOURCE)
+        .await
+    else {
+        return Ok(());
+    };
+
+    for method in [Method::GET, Method::PATCH] {
+        registry
+            .register_http_handler(Arc::new(ConfigApiHandler::new(
+                "/api/config".to_string(),
+                method,
+                manager.clone(),
+                context.event_bus.clone(),
+            )))
+            .await?;
+    }
+
+    info!("Registered configuration API handlers");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::config::ConfigLoadContext;
+    use rune_core::event::InMemoryEventBus;
+
+    fn handler(method: Method, config: Config) -> ConfigApiHandler {
+        let manager =
+            RuntimeConfigManager::from_config(config, ConfigLoadContext::default()).unwrap();
+        ConfigApiHandler::new(
+            "/api/config".to_string(),
+            method,
+            Arc::new(RwLock::new(manager)),
+            Arc::new(InMemoryEventBus::new()),
+        )
+    }
+
+    fn json(response: &HttpResponse) -> serde_json::Value {
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_config_api_requires_dev_mode_or_token() {
+        let token = "0123456789abcdef";
+        let mut config = Config::new();
+        config
+            .set_global_setting("api_token".to_string(), token)
+            .unwrap();
+        let api = handler(Method::GET, config);
+        let bearer = format!("Bearer {}", token);
+
+        let response = api.handle(HttpRequest::get("/api/config")).await.unwrap();
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+
+        let response = api
+            .handle(HttpRequest::get("/api/config").with_header("authorization", &bearer))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        let body = json(&response);
+        assert_eq!(body["config"]["server"]["port"], 3000);
+        assert_eq!(body["config"]["global_settings"]["api_token"], "[redacted]");
+
+        let response = handler(Method::GET, Config::new())
+            .handle(HttpRequest::get("/api/config").with_header("authorization", &bearer))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+        // Behind a tunnel dev mode does not stand in for the token
+        let mut config = Config::new();
+        config
+            .set_global_setting("api_token".to_string(), token)
+            .unwrap();
+        config
+            .set_global_setting("dev_mode".to_string(), true)
+            .unwrap();
+        config
+            .set_global_setting("tunnel".to_string(), "localhost.run")
+            .unwrap();
+        let response = handler(Method::GET, config)
+            .handle(HttpRequest::get("/api/config"))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_dev_mode_trusts_only_local_same_origin_requests() {
+        let mut config = Config::new();
+        config
+            .set_global_setting("dev_mode".to_string(), true)
+            .unwrap();
+        let api = handler(Method::PATCH, config);
+        let with_headers = |headers: &[(&str, &str)]| {
+            HttpRequest::new(Method::PATCH, "/api/config")
+                .with_headers(headers)
+                .with_body(r#"{"changes": {}}"#)
+        };
+
+        for allowed in [
+            &[
+                ("host", "localhost:3000"),
+                ("origin", "http://localhost:3000"),
+            ][..],
+            &[("host", "192.168.1.20:3000")],
+            &[("host", "[::1]:3000"), ("origin", "http://[::1]:3000")],
+        ] {
+            let response = api.handle(with_headers(allowed)).await.unwrap();
+            assert_eq!(response.status, StatusCode::OK, "{:?}", allowed);
+        }
+        for refused in [
+            // Another site, or another server on this machine
+            &[
+                ("host", "localhost:3000"),
+                ("origin", "https://evil.example"),
+            ][..],
+            &[
+                ("host", "localhost:3000"),
+                ("origin", "http://localhost:5173"),
+            ],
+            &[("host", "localhost:3000"), ("origin", "null")],
+            // DNS rebinding, or a tunnel
+            &[("host", "evil.example:3000")],
+        ] {
+            let response = api.handle(with_headers(refused)).await.unwrap();
+            assert_eq!(response.status, StatusCode::FORBIDDEN, "{:?}", refused);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_as_basic_password() {
+        let token = "0123456789abcdef";
+        let mut config = Config::new();
+        config
+            .set_global_setting("api_token".to_string(), token)
+            .unwrap();
+        let api = handler(Method::GET, config);
+        let basic = |credentials: &str| {
+            let authorization = format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(credentials)
+            );
+            // The token stands in for the origin check
+            HttpRequest::get("/api/config")
+                .with_header("authorization", &authorization)
+                .with_header("origin", "https://elsewhere.example")
+        };
+
+        let response = api.handle(basic("phone:0123456789abcdef")).await.unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        let response = api.handle(basic("phone:wrong")).await.unwrap();
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+        assert!(response.headers["www-authenticate"]
+            .to_str()
+            .unwrap()
+            .starts_with("Basic"));
+    }
+
+    #[test]
+    fn test_websocket_upgrades_check_origin_and_token() {
+        let socket = |origin: &str| {
+            HttpRequest::get("/ws")
+                .with_header("host", "localhost:3000")
+                .with_header("origin", origin)
+        };
+        let status = |config: &Config, request: &HttpRequest| {
+            check_websocket_access(config, request).map(|refusal| refusal.status)
+        };
+
+        let mut config = Config::new();
+        assert_eq!(status(&config, &socket("http://localhost:3000")), None);
+        assert_eq!(status(&config, &HttpRequest::get("/ws")), None);
+        let notes = socket("https://notes.example.com");
+        assert_eq!(status(&config, &notes), Some(StatusCode::FORBIDDEN));
+        config.server.cors.allowed_origins = Some(vec!["https://notes.example.com".to_string()]);
+        assert_eq!(status(&config, &notes), None);
+        let evil = socket("https://evil.example");
+        assert_eq!(status(&config, &evil), Some(StatusCode::FORBIDDEN));
+
+        config
+            .set_global_setting("websocket_requires_token".to_string(), true)
+            .unwrap();
+        let local = socket("http://localhost:3000");
+        assert_eq!(status(&config, &local), Some(StatusCode::FORBIDDEN));
+        config
+            .set_global_setting("api_token".to_string(), "0123456789abcdef")
+            .unwrap();
+        assert_eq!(status(&config, &local), Some(StatusCode::UNAUTHORIZED));
+        let mut with_token = local.clone();
+        with_token
+            .query_params
+            .insert("token".to_string(), "0123456789abcdef".to_string());
+        assert_eq!(status(&config, &with_token), None);
+        let bearer = local.with_header("authorization", "Bearer 0123456789abcdef");
+        assert_eq!(status(&config, &bearer), None);
+        // The token does not stand in for the origin check
+        let evil = evil.with_header("authorization", "Bearer 0123456789abcdef");
+        assert_eq!(status(&config, &evil), Some(StatusCode::FORBIDDEN));
+    }
+
+    #[tokio::test]
+    async fn test_config_api_applies_changes() {
+        let mut config = Config::new();
+        config
+            .set_global_setting("dev_mode".to_string(), true)
+            .unwrap();
+        let api = handler(Method::PATCH, config);
+
+        let response = api
+            .handle(
+                HttpRequest::new(Method::PATCH, "/api/config").with_body(
+                    r#"{"changes": {"server.port": 4000, "global.log_level": "debug"}}"#,
+                ),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        let body = json(&response);
+        assert_eq!(body["affected_plugins"], serde_json::json!(["server"]));
+        assert_eq!(body["restart_required"], true);
+        assert_eq!(api.manager.read().await.get_config().server.port, 4000);
+
+        // Invalid values are rejected and leave the configuration untouched
+        let response = api
+            .handle(
+                HttpRequest::new(Method::PATCH, "/api/config")
+                    .with_body(r#"{"changes": {"global.log_level": "loud"}}"#),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+        let manager = api.manager.read().await;
+        assert_eq!(
+            manager
+                .get_config()
+                .get_global_setting::<String>("log_level")
+                .as_deref(),
+            Some("debug")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_config_api_refuses_security_settings() {
+        let mut config = Config::new();
+        config
+            .set_global_setting("dev_mode".to_string(), true)
+            .unwrap();
+        config
+            .set_global_setting("api_token".to_string(), "0123456789abcdef")
+            .unwrap();
+        let api = handler(Method::PATCH, config);
+
+        for change in [
+            r#"{"global.api_token": "guessed"}"#,
+            r#"{"global.websocket_requires_token": false}"#,
+            r#"{"global.workspace_trusted": true, "global.log_level": "debug"}"#,
+            r#"{"global.template_path": "/tmp/page.html"}"#,
+            r#"{"plugin.server.static_dir": "/"}"#,
+        ] {
+            let response = api
+                .handle(
+                    HttpRequest::new(Method::PATCH, "/api/config")
+                        .with_header("authorization", "Bearer 0123456789abcdef")
+                        .with_body(format!(r#"{{"changes": {}}}"#, change)),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status, StatusCode::BAD_REQUEST, "{}", change);
+        }
+        let manager = api.manager.read().await;
+        let config = manager.get_config();
+        assert_eq!(
+            config.get_global_setting::<String>("api_token").as_deref(),
+            Some("0123456789abcdef")
+        );
+        assert_eq!(config.get_global_setting::<bool>("workspace_trusted"), None);
+        assert_eq!(config.get_global_setting::<String>("log_level"), None);
+        assert!(config::is_runtime_setting("plugin.server.enabled"));
+        assert!(!config::is_runtime_setting("server.hostname"));
+    }
+}
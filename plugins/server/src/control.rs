@@ -0,0 +1,385 @@
+//! Starting, stopping and restarting the HTTP server
+//!
+//! A new port or hostname used to take restarting the whole process.
+//! [`ServerControl`] owns the listener instead: [restarting](ServerControl::restart)
+//! picks up the listening address of the runtime configuration, drains the
+//! running server, rebuilds the router from the handlers registered now and
+//! binds again, publishing [`SystemEvent::ServerStopped`] and
+//! [`SystemEvent::ServerStarted`] as it goes. Moving to another address binds
+//! it before the running server is let go, so a port that is taken leaves
+//! the server where it was. In dev mode `POST /api/server/restart` restarts
+//! the server.
+
+use crate::{
+    config_api::CONFIG_MANAGER_RESOURCE, error_pages::ErrorPages, HandlerRegistry, HttpHandler,
+    HttpRequest, HttpResponse, ServerConfig, ServerPlugin, SERVER_TASK,
+};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::{
+    config::RuntimeConfigManager,
+    error::{Result, RuneError},
+    event::{ErrorSeverity, SystemEvent},
+    plugin::PluginContext,
+    supervisor::RestartPolicy,
+    DrainReport,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::sync::{Mutex, RwLock};
+use tracing::{error, info, warn};
+
+/// The HTTP server of a [`ServerPlugin`], which can be stopped and restarted
+pub struct ServerControl {
+    context: PluginContext,
+    registry: Arc<HandlerRegistry>,
+    error_pages: Arc<ErrorPages>,
+    config: RwLock<ServerConfig>,
+    /// Address the server listens on while it runs
+    address: RwLock<Option<String>>,
+    /// Keeps starts, stops and restarts from interleaving
+    transition: Mutex<()>,
+}
+
+impl ServerControl {
+    pub fn new(
+        context: PluginContext,
+        registry: Arc<HandlerRegistry>,
+        error_pages: Arc<ErrorPages>,
+        config: ServerConfig,
+    ) -> Self {
+        Self {
+            context,
+            registry,
+            error_pages,
+            config: RwLock::new(config),
+            address: RwLock::new(None),
+            transition: Mutex::new(()),
+        }
+    }
+
+    /// Address the server listens on, while it runs
+    pub async fn address(&self) -> Option<String> {
+        self.address.read().await.clone()
+    }
+
+    /// Configuration the server runs with
+    pub async fn config(&self) -> ServerConfig {
+        self.config.read().await.clone()
+    }
+
+    /// Bind the configured address and serve it; returns the address bound
+    pub async fn start(&self) -> Result<String> {
+        let _transition = self.transition.lock().await;
+        let config = self.config().await;
+        let listener = bind(&config).await?;
+        self.serve(listener, &config).await
+    }
+
+    /// Stop serving, letting requests in flight finish; returns how the
+    /// drain went, or `None` when the server was not running
+    pub async fn stop(&self) -> Option<DrainReport> {
+        let _transition = self.transition.lock().await;
+        self.drain().await
+    }
+
+    /// Stop the server and serve the address of the current configuration
+    /// with the handlers registered now; returns the address bound
+    pub async fn restart(&self) -> Result<String> {
+        let _transition = self.transition.lock().await;
+        let config = self.reload_config().await;
+        let target = address_of(&config);
+
+        // A new address is bound first, so failing to leaves the server be
+        let listener = match self.address().await {
+            Some(address) if address == target => None,
+            _ => Some(bind(&config).await?),
+        };
+        self.drain().await;
+        self.registry.renew_drain();
+        let listener = match listener {
+            Some(listener) => listener,
+            None => bind(&config).await?,
+        };
+        self.serve(listener, &config).await
+    }
+
+    /// Take the listening address and CORS policy from the runtime
+    /// configuration, when the host shares its manager
+    async fn reload_config(&self) -> ServerConfig {
+        let mut config = self.config.write().await;
+        if let Some(manager) = self
+            .context
+            .get_shared_resource::<RwLock<RuntimeConfigManager>>(CONFIG_MANAGER_RESOURCE)
+            .await
+        {
+            let manager = manager.read().await;
+            let server = &manager.get_config().server;
+            config.hostname = server.hostname.clone();
+            config.port = server.port;
+            config.enable_cors = server.cors_enabled;
+            config.cors = server.cors.clone();
+        }
+        config.clone()
+    }
+
+    async fn serve(&self, listener: TcpListener, config: &ServerConfig) -> Result<String> {
+        let address = listener
+            .local_addr()
+            .map(|address| address.to_string())
+            .unwrap_or_else(|_| address_of(config));
+        let router =
+            ServerPlugin::build_router(config, self.registry.clone(), self.error_pages.clone());
+        let drain = self.registry.drain();
+
+        info!("Starting HTTP server on {}", address);
+
+        // The supervisor restarts the server if it stops; restarts bind anew
+        let listener = Arc::new(std::sync::Mutex::new(Some(listener)));
+        let task_address = address.clone();
+        self.context
+            .supervisor
+            .supervise(SERVER_TASK, RestartPolicy::default(), move || {
+                let listener = listener
+                    .lock()
+                    .ok()
+                    .and_then(|mut listener| listener.take());
+                let router = router.clone();
+                let address = task_address.clone();
+                let drain = drain.clone();
+                async move {
+                    let listener = match listener {
+                        Some(listener) => listener,
+                        None => TcpListener::bind(&address).await.map_err(|e| {
+                            RuneError::Server(format!("Failed to bind to {}: {}", address, e))
+                        })?,
+                    };
+                    // Stops accepting once draining and returns when the
+                    // requests in flight are answered
+                    let shutdown = drain.clone();
+                    axum::serve(listener, router)
+                        .with_graceful_shutdown(async move { shutdown.begun().await })
+                        .await
+                        .map_err(|e| RuneError::Server(format!("Server error: {}", e)))?;
+                    drain.websockets_closed().await;
+                    Ok(())
+                }
+            })
+            .await?;
+
+        *self.address.write().await = Some(address.clone());
+        self.context
+            .event_bus
+            .publish_system_event(SystemEvent::server_started(address.clone()))
+            .await?;
+        Ok(address)
+    }
+
+    async fn drain(&self) -> Option<DrainReport> {
+        let address = self.address.write().await.take()?;
+        let grace = Duration::from_secs(self.config.read().await.shutdown_grace_secs);
+        let drain = self.registry.drain();
+        let supervisor = &self.context.supervisor;
+
+        let started = Instant::now();
+        drain.begin();
+        let finished = supervisor.drain(SERVER_TASK, grace).await;
+        let report = drain.report(SERVER_TASK, finished, started.elapsed());
+        info!(
+            "Server drained: {} requests completed, {} interrupted, {} WebSockets closed",
+            report.completed, report.interrupted, report.closed_connections
+        );
+        supervisor.report_drain(report.clone()).await;
+
+        if let Err(e) = self
+            .context
+            .event_bus
+            .publish_system_event(SystemEvent::server_stopped(address))
+            .await
+        {
+            warn!("Failed to publish server stopped event: {}", e);
+        }
+        Some(report)
+    }
+}
+
+fn address_of(config: &ServerConfig) -> String {
+    format!("{}:{}", config.hostname, config.port)
+}
+
+async fn bind(config: &ServerConfig) -> Result<TcpListener> {
+    let address = address_of(config);
+    TcpListener::bind(&address)
+        .await
+        .map_err(|e| RuneError::Server(format!("Failed to bind to {}: {}", address, e)))
+}
+
+/// Handler restarting the server (`POST /api/server/restart`), in dev mode
+pub struct ServerRestartHandler {
+    path_pattern: String,
+    control: Arc<ServerControl>,
+}
+
+impl ServerRestartHandler {
+    /// Create a new server restart handler
+    pub fn new(path_pattern: String, control: Arc<ServerControl>) -> Self {
+        Self {
+            path_pattern,
+            control,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for ServerRestartHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        // This request is in flight on the server being drained, so the
+        // restart cannot be waited for here
+        let control = self.control.clone();
+        tokio::spawn(async move {
+            if let Err(e) = control.restart().await {
+                error!("Failed to restart the server: {}", e);
+                let _ = control
+                    .context
+                    .event_bus
+                    .publish_system_event(SystemEvent::error(
+                        "server".to_string(),
+                        format!("Failed to restart the server: {}", e),
+                        ErrorSeverity::High,
+                    ))
+                    .await;
+            }
+        });
+
+        let mut response = HttpResponse::json(&serde_json::json!({ "restarting": true }))?;
+        response.status = StatusCode::ACCEPTED;
+        Ok(response)
+    }
+
+    fn edits(&self) -> bool {
+        true // Cuts off every client
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dashboard::EventLog;
+    use rune_core::{
+        event::{EventBus, InMemoryEventBus},
+        state::StateManager,
+        Config,
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    struct Hello;
+
+    #[async_trait]
+    impl HttpHandler for Hello {
+        fn path_pattern(&self) -> &str {
+            "/hello"
+        }
+
+        fn method(&self) -> Method {
+            Method::GET
+        }
+
+        async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+            Ok(HttpResponse::text("hello"))
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    async fn get(address: &str, path: &str) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(address).await?;
+        stream
+            .write_all(
+                format!("GET {path} HTTP/1.1\r\nHost: rune\r\nConnection: close\r\n\r\n")
+                    .as_bytes(),
+            )
+            .await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
+    #[tokio::test]
+    async fn test_restarts_serve_the_current_handlers_on_a_new_listener() {
+        let event_bus = Arc::new(InMemoryEventBus::new());
+        let events = Arc::new(EventLog::new(16));
+        event_bus
+            .subscribe_system_events(events.clone())
+            .await
+            .unwrap();
+        let context = PluginContext::new(
+            event_bus.clone(),
+            Arc::new(Config::new()),
+            Arc::new(StateManager::new()),
+        );
+        let registry = Arc::new(HandlerRegistry::new(event_bus));
+        let error_pages = Arc::new(ErrorPages::new(None, Vec::new(), None));
+        let config = ServerConfig {
+            port: 0,
+            ..ServerConfig::default()
+        };
+        let control = ServerControl::new(context, registry.clone(), error_pages, config);
+
+        let first = control.start().await.unwrap();
+        assert!(get(&first, "/hello").await.unwrap().contains("404"));
+
+        // Handlers registered since are served after the restart, on the
+        // port the new listener got
+        registry
+            .register_http_handler(Arc::new(Hello))
+            .await
+            .unwrap();
+        let second = control.restart().await.unwrap();
+        assert_ne!(first, second);
+        assert!(get(&second, "/hello").await.unwrap().ends_with("hello"));
+        assert!(get(&first, "/hello").await.is_err());
+
+        let report = control.stop().await.unwrap();
+        assert!(report.finished);
+        assert!(control.stop().await.is_none());
+        assert!(get(&second, "/hello").await.is_err());
+
+        let published: Vec<String> = events
+            .recent()
+            .await
+            .into_iter()
+            .rev()
+            .map(|event| event.event_type)
+            .filter(|event| event.starts_with("server_st"))
+            .collect();
+        assert_eq!(
+            published,
+            vec![
+                "server_started",
+                "server_stopped",
+                "server_started",
+                "server_stopped"
+            ]
+        );
+    }
+}
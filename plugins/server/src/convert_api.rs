@@ -0,0 +1,109 @@
+//! HTML to markdown conversion API
+//!
+//! `POST /api/convert/html-to-md` takes a JSON object with the `html` to
+//! convert, e.g. the rich text of a paste, and answers with its `markdown`
+//! as written by [`rune_core::convert`]. The editors call it when HTML is
+//! pasted, so content copied from Google Docs or a web page lands as
+//! markdown rather than as plain text without its structure.
+
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::{convert, error::Result};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::info;
+
+/// Body of a conversion request
+#[derive(Debug, Deserialize)]
+struct ConvertRequest {
+    html: String,
+}
+
+/// Handler converting HTML to markdown
+pub struct HtmlToMarkdownHandler {
+    path_pattern: String,
+}
+
+impl HtmlToMarkdownHandler {
+    pub fn new(path_pattern: String) -> Self {
+        Self { path_pattern }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for HtmlToMarkdownHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let convert_request: ConvertRequest = match serde_json::from_slice(&request.body) {
+            Ok(convert_request) => convert_request,
+            Err(e) => {
+                return Ok(HttpResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Invalid conversion request: {}", e),
+                ))
+            }
+        };
+
+        // Parsing large pastes is CPU bound
+        let markdown =
+            tokio::task::spawn_blocking(move || convert::html_to_markdown(&convert_request.html))
+                .await
+                .map_err(|e| rune_core::RuneError::Server(format!("Conversion failed: {}", e)))?;
+
+        HttpResponse::json(&serde_json::json!({ "markdown": markdown }))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Register the conversion API
+pub async fn register_convert_api_handlers(registry: &HandlerRegistry) -> Result<()> {
+    registry
+        .register_http_handler(Arc::new(HtmlToMarkdownHandler::new(
+            "/api/convert/html-to-md".to_string(),
+        )))
+        .await?;
+
+    info!("Registered HTML to markdown conversion API handler");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_converts_posted_html() {
+        let handler = HtmlToMarkdownHandler::new("/api/convert/html-to-md".to_string());
+
+        let response = handler
+            .handle(
+                HttpRequest::new(Method::POST, "/api/convert/html-to-md")
+                    .with_body(r#"{"html": "<h1>Hi</h1><p>Some <b>bold</b> text</p>"}"#),
+            )
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(body["markdown"], "# Hi\n\nSome **bold** text\n");
+
+        let response = handler
+            .handle(HttpRequest::new(Method::POST, "/api/convert/html-to-md").with_body("<p>"))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    }
+}
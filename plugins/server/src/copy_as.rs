@@ -0,0 +1,199 @@
+//! Copying the document in other formats
+//!
+//! `GET /copy?format=<format>` returns the current document ready for the
+//! clipboard: `html` is its rendered content without the page around it,
+//! while `text`, `slack` and `jira` are written from the markdown AST by
+//! [`rune_core::export::markup`]. Relative links and image sources are
+//! resolved against `base`, the URL of the page copying, so they keep
+//! working wherever the copy is pasted. Served roots answer the same next to
+//! each document's raw markdown, e.g. `/docs/guide.md/copy`.
+
+use crate::handlers::{DocumentSlot, MarkdownHandler};
+use crate::{HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use regex::{Captures, Regex};
+use rune_core::export::markup::{write_markup, Markup};
+use rune_core::{crypto, Result};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use tracing::warn;
+use url::Url;
+
+/// Handler copying the served document
+pub struct CopyHandler {
+    path_pattern: String,
+    markdown_file: PathBuf,
+    document: DocumentSlot,
+}
+
+impl CopyHandler {
+    /// Create a copy handler for `markdown_file`, rendering HTML copies
+    /// through the document's handler once it is in `document`
+    pub fn new(path_pattern: String, markdown_file: PathBuf, document: DocumentSlot) -> Self {
+        Self {
+            path_pattern,
+            markdown_file,
+            document,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for CopyHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let document = self.document.read().await.clone();
+        copy_document(&self.markdown_file, document, &request).await
+    }
+
+    fn priority(&self) -> i32 {
+        10 // Same as the raw markdown handler
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Answer a copy request for `markdown_file`; HTML is rendered by
+/// `document` when given, so it matches the preview
+pub async fn copy_document(
+    markdown_file: &Path,
+    document: Option<Arc<MarkdownHandler>>,
+    request: &HttpRequest,
+) -> Result<HttpResponse> {
+    let format = request
+        .query_params
+        .get("format")
+        .map(String::as_str)
+        .unwrap_or("html");
+    let base = page_url(request);
+    let resolve = |href: &str| resolve(base.as_ref(), href);
+
+    let copied = if format.eq_ignore_ascii_case("html") {
+        let document = document
+            .unwrap_or_else(|| Arc::new(MarkdownHandler::new(String::new(), markdown_file.into())));
+        document
+            .extract_content_only()
+            .await
+            .map(|html| HttpResponse::html(resolve_html(&html, &resolve)))
+    } else {
+        let markup: Markup = match format.parse() {
+            Ok(markup) => markup,
+            Err(e) => return Ok(HttpResponse::error(StatusCode::BAD_REQUEST, &e.to_string())),
+        };
+        crypto::read_document(markdown_file)
+            .map(|content| HttpResponse::text(write_markup(&content, markup, &resolve)))
+    };
+
+    match copied {
+        Ok(response) => Ok(response.with_header("cache-control", "no-store")),
+        Err(e) => {
+            warn!("Failed to copy {:?} as {}: {}", markdown_file, format, e);
+            Ok(HttpResponse::error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read markdown file",
+            ))
+        }
+    }
+}
+
+/// URL of the page asking for the copy: its `base` parameter, or else the
+/// document's address on this server
+fn page_url(request: &HttpRequest) -> Option<Url> {
+    if let Some(base) = request.query_params.get("base") {
+        return Url::parse(base).ok();
+    }
+    let host = request.headers.get("host")?.to_str().ok()?;
+    let path = request.path.strip_suffix("/copy").unwrap_or(&request.path);
+    Url::parse(&format!("http://{}{}", host, path)).ok()
+}
+
+fn resolve(base: Option<&Url>, href: &str) -> String {
+    if href.is_empty() {
+        return String::new();
+    }
+    base.and_then(|base| base.join(href).ok())
+        .map(String::from)
+        .unwrap_or_else(|| href.to_string())
+}
+
+/// Rendered HTML with its `href` and `src` attributes resolved, and without
+/// the heading permalink controls, which only work in the preview
+fn resolve_html(html: &str, resolve: &dyn Fn(&str) -> String) -> String {
+    static PERMALINKS: OnceLock<Regex> = OnceLock::new();
+    static ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+    let permalinks = PERMALINKS.get_or_init(|| {
+        Regex::new(
+            r#"<a class="rune-anchor"[^>]*>#</a>|<button class="rune-copy-link"[^>]*>⧉</button>"#,
+        )
+        .unwrap()
+    });
+    let attribute = ATTRIBUTE.get_or_init(|| Regex::new(r#"\b(href|src)="([^"]*)""#).unwrap());
+    attribute
+        .replace_all(&permalinks.replace_all(html, ""), |captures: &Captures| {
+            let value = html_escape::decode_html_entities(&captures[2]);
+            format!(
+                "{}=\"{}\"",
+                &captures[1],
+                html_escape::encode_double_quoted_attribute(&resolve(&value))
+            )
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_copy_resolves_links_for_every_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let doc = temp_dir.path().join("notes.md");
+        std::fs::write(&doc, "# Notes\n\nSee [the guide](guide.md) and **this**.\n").unwrap();
+        let handler = CopyHandler::new("/copy".to_string(), doc, Default::default());
+        let base = ("base", "http://example.com:3000/");
+
+        let copy = |format: &'static str| {
+            handler.handle(HttpRequest::get("/copy").with_query(&[("format", format), base]))
+        };
+        let text = copy("text").await.unwrap();
+        assert_eq!(text.headers.get("cache-control").unwrap(), "no-store");
+        assert_eq!(
+            String::from_utf8_lossy(&text.body),
+            "Notes\n\nSee the guide (http://example.com:3000/guide.md) and this.\n"
+        );
+
+        let jira = copy("jira").await.unwrap();
+        assert!(String::from_utf8_lossy(&jira.body)
+            .contains("[the guide|http://example.com:3000/guide.md] and *this*"));
+
+        let html = copy("html").await.unwrap();
+        assert!(String::from_utf8_lossy(&html.body)
+            .contains("href=\"http://example.com:3000/guide.md\""));
+
+        let unknown = copy("rtf").await.unwrap();
+        assert_eq!(unknown.status, StatusCode::BAD_REQUEST);
+
+        // Without a base, links resolve against the server's address
+        let response = handler
+            .handle(
+                HttpRequest::get("/copy")
+                    .with_query(&[("format", "slack")])
+                    .with_header("host", "localhost:3000"),
+            )
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&response.body)
+            .contains("<http://localhost:3000/guide.md|the guide>"));
+    }
+}
@@ -0,0 +1,125 @@
+//! Cross-origin request policy
+//!
+//! Browsers only let other sites read the server's responses when the
+//! policy allows their origin. Without `server.cors.allowed_origins` no CORS
+//! headers are sent, so only the server's own pages may call it; listing
+//! origins, or `*`, widens that to the configured methods and headers.
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use rune_core::CorsConfig;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+use tracing::warn;
+
+/// Layer answering preflights and marking responses as `config` allows,
+/// or `None` when no other origin is allowed
+pub fn layer(config: &CorsConfig) -> Option<CorsLayer> {
+    let origins = config.origins();
+    if origins.is_empty() {
+        return None;
+    }
+    let methods = config.methods();
+    let headers = config.headers();
+    let any = |values: &[String]| values.iter().any(|value| value == "*");
+
+    let allow_origin = if any(origins) {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(parsed(origins, "origin", |origin| {
+            HeaderValue::from_str(origin).ok()
+        }))
+    };
+    let allow_methods = if any(&methods) {
+        AllowMethods::any()
+    } else {
+        AllowMethods::list(parsed(&methods, "method", |method| {
+            Method::from_bytes(method.as_bytes()).ok()
+        }))
+    };
+    let allow_headers = if any(&headers) {
+        AllowHeaders::any()
+    } else {
+        AllowHeaders::list(parsed(&headers, "header", |header| {
+            HeaderName::from_bytes(header.as_bytes()).ok()
+        }))
+    };
+
+    // Browsers refuse credentials with a wildcard, and so does the layer
+    let wildcard = any(origins) || any(&methods) || any(&headers);
+    if config.allows_credentials() && wildcard {
+        warn!("Not allowing CORS credentials together with '*'");
+    }
+    let credentials = config.allows_credentials() && !wildcard;
+
+    Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods(allow_methods)
+            .allow_headers(allow_headers)
+            .allow_credentials(credentials),
+    )
+}
+
+/// Parse the configured values, skipping the invalid ones
+fn parsed<T>(values: &[String], kind: &str, parse: impl Fn(&str) -> Option<T>) -> Vec<T> {
+    values
+        .iter()
+        .filter_map(|value| {
+            let parsed = parse(value);
+            if parsed.is_none() {
+                warn!("Ignoring invalid CORS {} '{}'", kind, value);
+            }
+            parsed
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn preflight(config: &CorsConfig, origin: &str, method: &str) -> Option<String> {
+        let router = Router::new()
+            .route("/api/theme", get(|| async { "ok" }))
+            .layer(layer(config).unwrap());
+        let request = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/api/theme")
+            .header("origin", origin)
+            .header("access-control-request-method", method)
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        response
+            .headers()
+            .get("access-control-allow-origin")
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_only_configured_origins_are_allowed() {
+        assert!(layer(&CorsConfig::default()).is_none());
+
+        let config = CorsConfig {
+            allowed_origins: Some(vec!["https://notes.example.com".to_string()]),
+            allow_credentials: Some(true),
+            ..Default::default()
+        };
+        let allowed = preflight(&config, "https://notes.example.com", "POST").await;
+        assert_eq!(allowed.as_deref(), Some("https://notes.example.com"));
+        assert_eq!(
+            preflight(&config, "https://evil.example", "POST").await,
+            None
+        );
+
+        let any = CorsConfig {
+            allowed_origins: Some(vec!["*".to_string()]),
+            // Dropped rather than making the layer panic
+            allow_credentials: Some(true),
+            ..Default::default()
+        };
+        let allowed = preflight(&any, "https://evil.example", "GET").await;
+        assert_eq!(allowed.as_deref(), Some("*"));
+    }
+}
@@ -0,0 +1,84 @@
+//! Content security policy of served pages
+//!
+//! The page template and render assets mark their elements with
+//! [`NONCE_PLACEHOLDER`]. Each time a page is served the placeholder is
+//! replaced with a fresh nonce, which the policy then allows. The policy is
+//! enforced in offline mode.
+
+use rune_core::NONCE_PLACEHOLDER;
+use std::borrow::Cow;
+
+/// Nonce a response's page was filled with, kept in the response extensions
+#[derive(Debug, Clone)]
+pub struct CspNonce(pub String);
+
+/// Fresh nonce for a page
+pub fn new_nonce() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// Replace the nonce placeholders in `page` with `nonce`
+pub fn fill_nonces(page: &str, nonce: &str) -> String {
+    page.replace(NONCE_PLACEHOLDER, nonce)
+}
+
+/// Remove the nonce attributes of a page served without a nonce
+pub fn strip_nonces(page: &str) -> String {
+    page.replace(&format!(r#" nonce="{}""#, NONCE_PLACEHOLDER), "")
+}
+
+/// Escape placeholders in document content, so a document cannot obtain
+/// the nonce for scripts of its own
+pub fn escape_nonce_placeholders(content: &str) -> Cow<'_, str> {
+    if content.contains(NONCE_PLACEHOLDER) {
+        Cow::Owned(content.replace(
+            NONCE_PLACEHOLDER,
+            &NONCE_PLACEHOLDER.replacen('{', "&#123;", 1),
+        ))
+    } else {
+        Cow::Borrowed(content)
+    }
+}
+
+/// Policy keeping a page to the server's own resources
+///
+/// With a nonce, inline `<script>` elements only run when they carry it and
+/// event handler attributes do not run at all, so the preview binds its
+/// controls from its own script. Inline styles stay allowed because Mermaid
+/// styles its diagrams with `<style>` elements it creates.
+pub fn policy(nonce: Option<&str>) -> String {
+    let scripts = match nonce {
+        Some(nonce) => format!("'self' 'nonce-{}'", nonce),
+        None => "'self' 'unsafe-inline'".to_string(),
+    };
+    format!(
+        "default-src 'self' data: blob:; script-src {}; style-src 'self' 'unsafe-inline'; connect-src 'self' ws: wss:",
+        scripts
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonces_fill_only_trusted_placeholders() {
+        let content = escape_nonce_placeholders(r#"<script nonce="{RUNE_NONCE}">steal()</script>"#);
+        let page = format!(
+            r#"<script nonce="{{RUNE_NONCE}}">init()</script>{}"#,
+            content
+        );
+
+        assert_eq!(
+            fill_nonces(&page, "abc"),
+            r#"<script nonce="abc">init()</script><script nonce="&#123;RUNE_NONCE}">steal()</script>"#
+        );
+        assert_eq!(
+            strip_nonces(r#"<style nonce="{RUNE_NONCE}">p {}</style>"#),
+            "<style>p {}</style>"
+        );
+        assert!(policy(Some("abc")).contains("script-src 'self' 'nonce-abc';"));
+        assert!(!policy(Some("abc")).contains("script-src-attr"));
+        assert!(policy(None).contains("script-src 'self' 'unsafe-inline';"));
+    }
+}
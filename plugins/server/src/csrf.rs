@@ -0,0 +1,183 @@
+//! Cross-site request forgery protection of the APIs
+//!
+//! Requests changing state, any method but `GET`, `HEAD` and `OPTIONS`, to
+//! `/api/` and `/_rune/api/` must send the value of the `rune_csrf` cookie
+//! in an `X-CSRF-Token` header: the double-submit cookie pattern. Other
+//! sites can make a browser send the cookie, but cannot read it to send the
+//! header too. Responses set the cookie when the request had none, and the
+//! server's pages load [`FETCH_SCRIPT`], which adds the header to their own
+//! `fetch` calls.
+//!
+//! Clients outside a browser either send the configured API token as a
+//! bearer token, which browsers never add on their own, or pick any random
+//! value and send it both ways: `Cookie: rune_csrf=<value>` and
+//! `X-CSRF-Token: <value>`. Any other bearer token gets no exemption.
+
+use crate::config_api::tokens_match;
+use crate::HttpResponse;
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use rune_core::NONCE_PLACEHOLDER;
+
+/// Cookie holding the token
+pub const CSRF_COOKIE: &str = "rune_csrf";
+
+/// Header the token is sent back in
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Script making a page's `fetch` calls to its own server send the token
+pub const FETCH_SCRIPT: &str = r#"(() => {
+  const send = window.fetch.bind(window);
+  window.fetch = (input, init = {}) => {
+    const request = input instanceof Request ? input : null;
+    const method = (init.method || (request ? request.method : 'GET')).toUpperCase();
+    const url = new URL(request ? request.url : input, location.href);
+    const token = document.cookie.split('; ').find((c) => c.startsWith('rune_csrf='));
+    if (token && url.origin === location.origin && !['GET', 'HEAD', 'OPTIONS'].includes(method)) {
+      const headers = new Headers(init.headers || (request ? request.headers : undefined));
+      headers.set('X-CSRF-Token', token.slice('rune_csrf='.length));
+      init = Object.assign({}, init, { headers });
+    }
+    return send(input, init);
+  };
+})();"#;
+
+/// Whether a request to `path` with `method` must carry the token
+pub fn is_protected(method: &Method, path: &str) -> bool {
+    let changes_state = !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+    changes_state && (path.starts_with("/api/") || path.starts_with("/_rune/api/"))
+}
+
+/// Token in the request's `rune_csrf` cookie
+pub fn cookie_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .find_map(|cookie| {
+            let (name, value) = cookie.trim().split_once('=')?;
+            (name == CSRF_COOKIE && !value.is_empty()).then_some(value)
+        })
+}
+
+/// Refusal response unless a protected request carries the token or
+/// `api_token` as its bearer token
+pub fn check(
+    method: &Method,
+    path: &str,
+    headers: &HeaderMap,
+    api_token: Option<&str>,
+) -> Option<Response> {
+    if !is_protected(method, path) {
+        return None;
+    }
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if let (Some(bearer), Some(api_token)) = (bearer, api_token) {
+        if tokens_match(bearer.trim(), api_token) {
+            return None;
+        }
+    }
+    let sent = headers
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok());
+    match (cookie_token(headers), sent) {
+        (Some(cookie), Some(sent)) if tokens_match(sent, cookie) => None,
+        _ => Some(
+            HttpResponse::error(
+                StatusCode::FORBIDDEN,
+                "Missing or invalid CSRF token; send the rune_csrf cookie in an X-CSRF-Token header",
+            )
+            .into_response(),
+        ),
+    }
+}
+
+/// Give the client a token, for a request that had none
+pub fn issue(response: &mut Response) {
+    let cookie = format!(
+        "{}={}; Path=/; SameSite=Strict",
+        CSRF_COOKIE,
+        uuid::Uuid::new_v4().simple()
+    );
+    if let Ok(cookie) = HeaderValue::from_str(&cookie) {
+        response.headers_mut().append(header::SET_COOKIE, cookie);
+    }
+}
+
+/// `page` loading [`FETCH_SCRIPT`] first, for pages served without a nonce
+pub fn with_fetch_script(page: &str) -> String {
+    page.replacen(
+        "</head>",
+        &format!("<script>{}</script>\n</head>", FETCH_SCRIPT),
+        1,
+    )
+}
+
+/// `page` loading [`FETCH_SCRIPT`] first, for pages filled with a nonce
+pub(crate) fn with_fetch_script_nonce(page: &str) -> String {
+    page.replacen(
+        "</head>",
+        &format!(
+            "<script nonce=\"{}\">{}</script>\n</head>",
+            NONCE_PLACEHOLDER, FETCH_SCRIPT
+        ),
+        1,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_state_changing_api_requests_need_the_token() {
+        let post = Method::POST;
+        assert!(check(&Method::GET, "/api/theme", &HeaderMap::new(), None).is_none());
+        assert!(check(&post, "/docs/notes.md", &HeaderMap::new(), None).is_none());
+        assert!(check(&Method::PUT, "/api/files/a.md", &HeaderMap::new(), None).is_some());
+
+        let cookie = ("cookie", "theme=dark; rune_csrf=abc123");
+        assert!(check(&post, "/api/theme", &headers(&[cookie]), None).is_some());
+        let forged = headers(&[cookie, ("x-csrf-token", "abc124")]);
+        assert!(check(&post, "/api/theme", &forged, None).is_some());
+        let sent = headers(&[cookie, ("x-csrf-token", "abc123")]);
+        assert!(check(&post, "/_rune/api/restart", &sent, None).is_none());
+        let bearer = headers(&[("authorization", "Bearer secret")]);
+        assert!(check(&post, "/api/theme", &bearer, Some("secret")).is_none());
+        // Only the configured token is exempt
+        assert_eq!(
+            check(&post, "/api/theme", &bearer, Some("other"))
+                .unwrap()
+                .status(),
+            StatusCode::FORBIDDEN
+        );
+        assert!(check(&post, "/api/theme", &bearer, None).is_some());
+        // Browsers replay Basic credentials on their own
+        let basic = headers(&[("authorization", "Basic OnNlY3JldA==")]);
+        assert!(check(&post, "/api/theme", &basic, Some("secret")).is_some());
+    }
+
+    #[test]
+    fn test_issued_cookie_is_read_back() {
+        let mut response = Response::new(axum::body::Body::empty());
+        issue(&mut response);
+        let cookie = response.headers()[header::SET_COOKIE].to_str().unwrap();
+        assert!(cookie.contains("SameSite=Strict"));
+        let (pair, _) = cookie.split_once(';').unwrap();
+        let sent = headers(&[("cookie", pair)]);
+        assert_eq!(cookie_token(&sent), pair.strip_prefix("rune_csrf="));
+        assert_eq!(cookie_token(&headers(&[("cookie", "rune_csrf=")])), None);
+    }
+}
@@ -0,0 +1,583 @@
+//! Developer dashboard under `/_rune/`
+//!
+//! Served only in dev mode. The page polls `/_rune/api/status` for the plugin
+//! table, watcher statistics, recent system events, render cache size, open
+//! WebSocket connections and supervised background tasks, and offers buttons
+//! to force a reload of connected previews or to clear render caches. Captured
+//! logs come from `/api/logs` when the host shares a log controller.
+//! Everything shown comes from the state manager, the event bus, the handler
+//! registry and resources shared by other plugins; the dashboard keeps no
+//! state of its own beyond the event log.
+
+use crate::csrf;
+use crate::handlers::{MarkdownHandler, ServerMessage};
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse, WebSocketClient};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::{
+    error::Result,
+    event::{Event, SystemEvent, SystemEventHandler},
+    plugin::{PluginContext, PluginInfo},
+    state::RenderCacheStats,
+    supervisor::TaskStatus,
+    WatchStatistics, WatchStatisticsProvider,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use tokio::sync::{broadcast, RwLock};
+use tracing::info;
+
+/// Number of system events kept for the dashboard
+const EVENT_LOG_CAPACITY: usize = 100;
+
+/// A system event as shown on the dashboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedEvent {
+    pub event_type: String,
+    pub description: String,
+    /// Event time in seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+/// Ring buffer of recent system events
+pub struct EventLog {
+    capacity: usize,
+    events: RwLock<VecDeque<LoggedEvent>>,
+}
+
+impl EventLog {
+    /// Create a log keeping at most `capacity` events
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: RwLock::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Record an event, dropping the oldest one when full
+    pub async fn record(&self, event: &SystemEvent) {
+        // Periodic health checks would crowd out everything else
+        if matches!(event, SystemEvent::PluginHealthCheck { .. }) {
+            return;
+        }
+
+        let mut events = self.events.write().await;
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(LoggedEvent {
+            event_type: event.event_type().to_string(),
+            description: event.description(),
+            timestamp: event
+                .timestamp()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        });
+    }
+
+    /// Recorded events, newest first
+    pub async fn recent(&self) -> Vec<LoggedEvent> {
+        let events = self.events.read().await;
+        events.iter().rev().cloned().collect()
+    }
+}
+
+#[async_trait]
+impl SystemEventHandler for EventLog {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        self.record(event).await;
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "dashboard-event-log"
+    }
+}
+
+/// Everything shown on the dashboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardStatus {
+    pub plugins: Vec<PluginInfo>,
+    /// Missing when no watcher plugin shares its statistics
+    pub watcher: Option<WatchStatistics>,
+    pub render_cache: RenderCacheStats,
+    pub clients: Vec<WebSocketClient>,
+    pub events: Vec<LoggedEvent>,
+    /// Supervised background tasks and their restarts
+    pub tasks: Vec<TaskStatus>,
+}
+
+/// Result of clearing caches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClearedCaches {
+    pub render_cache_entries: usize,
+    pub page_caches: usize,
+}
+
+/// Data sources and actions behind the dashboard
+pub struct Dashboard {
+    context: PluginContext,
+    registry: Arc<HandlerRegistry>,
+    events: Arc<EventLog>,
+    reload_sender: broadcast::Sender<ServerMessage>,
+}
+
+impl Dashboard {
+    /// Create a dashboard over the given plugin context and handler registry
+    pub fn new(
+        context: PluginContext,
+        registry: Arc<HandlerRegistry>,
+        events: Arc<EventLog>,
+        reload_sender: broadcast::Sender<ServerMessage>,
+    ) -> Self {
+        Self {
+            context,
+            registry,
+            events,
+            reload_sender,
+        }
+    }
+
+    /// Collect the current status
+    pub async fn status(&self) -> DashboardStatus {
+        let watcher = match self
+            .context
+            .get_shared_resource::<Arc<dyn WatchStatisticsProvider>>("file_watcher_statistics")
+            .await
+        {
+            Some(provider) => Some(provider.watch_statistics().await),
+            None => None,
+        };
+
+        DashboardStatus {
+            plugins: self.context.state_manager.list_plugins().await,
+            watcher,
+            render_cache: self.context.state_manager.render_cache_stats().await,
+            clients: self.registry.list_websocket_clients().await,
+            events: self.events.recent().await,
+            tasks: self.context.supervisor.status().await,
+        }
+    }
+
+    /// Ask every connected preview to reload, returning how many were reached
+    pub fn force_reload(&self) -> usize {
+        // Sending only fails when no preview is connected
+        self.reload_sender.send(ServerMessage::Reload).unwrap_or(0)
+    }
+
+    /// Drop cached renders so the next request renders from scratch
+    pub async fn clear_caches(&self) -> ClearedCaches {
+        let render_cache_entries = self.context.state_manager.clear_render_cache().await;
+
+        let mut page_caches = 0;
+        for handler in self.registry.get_all_http_handlers().await {
+            if let Some(markdown) = handler.as_any().downcast_ref::<MarkdownHandler>() {
+                markdown.clear_cache().await;
+                page_caches += 1;
+            }
+        }
+
+        ClearedCaches {
+            render_cache_entries,
+            page_caches,
+        }
+    }
+}
+
+/// Handler serving the dashboard page
+pub struct DashboardPageHandler {
+    path_pattern: String,
+}
+
+impl DashboardPageHandler {
+    /// Create a new dashboard page handler
+    pub fn new(path_pattern: String) -> Self {
+        Self { path_pattern }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for DashboardPageHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        if request.path.trim_end_matches('/') != self.path_pattern {
+            return Ok(HttpResponse::error(StatusCode::NOT_FOUND, "Not found"));
+        }
+        Ok(HttpResponse::html(csrf::with_fetch_script(DASHBOARD_PAGE))
+            .with_header("cache-control", "no-store"))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for specific page
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Handler returning the dashboard status as JSON
+pub struct DashboardStatusHandler {
+    path_pattern: String,
+    dashboard: Arc<Dashboard>,
+}
+
+impl DashboardStatusHandler {
+    /// Create a new dashboard status handler
+    pub fn new(path_pattern: String, dashboard: Arc<Dashboard>) -> Self {
+        Self {
+            path_pattern,
+            dashboard,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for DashboardStatusHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        Ok(HttpResponse::json(&self.dashboard.status().await)?
+            .with_header("cache-control", "no-store"))
+    }
+
+    fn priority(&self) -> i32 {
+        4 // Ahead of the page handler, whose pattern is a prefix of this one
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Handler for the dashboard buttons (`POST <pattern>/reload`, `POST <pattern>/clear-cache`)
+pub struct DashboardActionHandler {
+    path_pattern: String,
+    dashboard: Arc<Dashboard>,
+}
+
+impl DashboardActionHandler {
+    /// Create a new dashboard action handler
+    pub fn new(path_pattern: String, dashboard: Arc<Dashboard>) -> Self {
+        Self {
+            path_pattern,
+            dashboard,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for DashboardActionHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let action = request
+            .path
+            .strip_prefix(&self.path_pattern)
+            .unwrap_or("")
+            .trim_matches('/');
+
+        match action {
+            "reload" => {
+                let clients = self.dashboard.force_reload();
+                info!("Dashboard forced a reload of {} preview(s)", clients);
+                HttpResponse::json(&serde_json::json!({ "clients": clients }))
+            }
+            "clear-cache" => {
+                let cleared = self.dashboard.clear_caches().await;
+                info!(
+                    "Dashboard cleared {} cached render(s) and {} page cache(s)",
+                    cleared.render_cache_entries, cleared.page_caches
+                );
+                HttpResponse::json(&cleared)
+            }
+            _ => Ok(HttpResponse::error(
+                StatusCode::NOT_FOUND,
+                &format!("Unknown dashboard action: {}", action),
+            )),
+        }
+    }
+
+    fn edits(&self) -> bool {
+        true // Reloads every preview
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Register the dashboard page, status API and actions under `/_rune`
+pub async fn register_dashboard_handlers(
+    registry: &Arc<HandlerRegistry>,
+    context: &PluginContext,
+    reload_sender: broadcast::Sender<ServerMessage>,
+) -> Result<()> {
+    let events = Arc::new(EventLog::new(EVENT_LOG_CAPACITY));
+    context
+        .event_bus
+        .subscribe_system_events(events.clone())
+        .await?;
+
+    let dashboard = Arc::new(Dashboard::new(
+        context.clone(),
+        registry.clone(),
+        events,
+        reload_sender,
+    ));
+
+    registry
+        .register_http_handler(Arc::new(DashboardPageHandler::new("/_rune".to_string())))
+        .await?;
+    registry
+        .register_http_handler(Arc::new(DashboardStatusHandler::new(
+            "/_rune/api/status".to_string(),
+            dashboard.clone(),
+        )))
+        .await?;
+    registry
+        .register_http_handler(Arc::new(DashboardActionHandler::new(
+            "/_rune/api".to_string(),
+            dashboard,
+        )))
+        .await?;
+
+    info!("Registered dev dashboard at /_rune/");
+    Ok(())
+}
+
+const DASHBOARD_PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Rune dev dashboard</title>
+<style>
+body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif; margin: 0; padding: 1.5rem 2rem; color: #24292e; background: #f6f8fa; }
+h1 { font-size: 1.4rem; margin: 0 0 1rem; }
+h2 { font-size: 1rem; margin: 0 0 .5rem; }
+section { background: #fff; border: 1px solid #e1e4e8; border-radius: 6px; padding: 1rem; margin-bottom: 1rem; }
+table { border-collapse: collapse; width: 100%; font-size: .9rem; }
+th, td { text-align: left; padding: .3rem .5rem; border-bottom: 1px solid #eaecef; vertical-align: top; }
+th { color: #586069; font-weight: 600; }
+.grid { display: grid; grid-template-columns: repeat(auto-fit, minmax(320px, 1fr)); gap: 1rem; }
+.grid section { margin-bottom: 0; }
+.ok { color: #22863a; }
+.bad { color: #cb2431; }
+.muted { color: #6a737d; }
+button { padding: .4rem .9rem; margin-right: .5rem; border: 1px solid #d1d5da; border-radius: 4px; background: #fafbfc; cursor: pointer; }
+button:hover { background: #f3f4f6; }
+#message { margin-left: .5rem; }
+</style>
+</head>
+<body>
+<h1>Rune dev dashboard</h1>
+<section>
+<button id="reload">Force reload</button><button id="clear-cache">Clear caches</button><span id="message" class="muted"></span>
+</section>
+<section><h2>Plugins</h2><table id="plugins"></table></section>
+<div class="grid">
+<section><h2>File watcher</h2><table id="watcher"></table></section>
+<section><h2>Render cache</h2><table id="cache"></table></section>
+<section><h2>WebSocket clients</h2><table id="clients"></table></section>
+<section><h2>Background tasks</h2><table id="tasks"></table></section>
+</div>
+<section style="margin-top: 1rem"><h2>Recent events</h2><table id="events"></table></section>
+<section><h2>Logs <select id="log-level"><option value="">all</option><option>error</option><option>warn</option><option selected>info</option><option>debug</option><option>trace</option></select></h2><table id="logs"></table></section>
+<script>
+function fill(id, headers, rows) {
+  const table = document.getElementById(id);
+  table.replaceChildren();
+  const head = table.insertRow();
+  headers.forEach(h => { const th = document.createElement('th'); th.textContent = h; head.appendChild(th); });
+  if (rows.length === 0) {
+    const cell = table.insertRow().insertCell();
+    cell.colSpan = headers.length; cell.className = 'muted'; cell.textContent = 'None';
+  }
+  rows.forEach(row => {
+    const tr = table.insertRow();
+    row.forEach(value => {
+      const td = tr.insertCell();
+      if (value && typeof value === 'object') { td.textContent = value.text; td.className = value.cls; }
+      else td.textContent = value;
+    });
+  });
+}
+function status(s) {
+  if (typeof s === 'string') return { text: s, cls: s === 'Active' ? 'ok' : '' };
+  const [kind, detail] = Object.entries(s)[0];
+  return { text: kind + ': ' + detail, cls: 'bad' };
+}
+function time(secs) { return new Date(secs * 1000).toLocaleTimeString(); }
+function bytes(n) { return n < 1024 ? n + ' B' : (n / 1024).toFixed(1) + ' KiB'; }
+async function refresh() {
+  try {
+    const s = await (await fetch('/_rune/api/status')).json();
+    fill('plugins', ['Name', 'Version', 'Status', 'Health', 'Services', 'Restarts'],
+      s.plugins.map(p => [p.name, p.version, status(p.status),
+        { text: p.health_status, cls: p.health_status === 'Healthy' ? 'ok' : (p.health_status === 'Unhealthy' ? 'bad' : '') },
+        p.provided_services.join(', '), p.restart_count]));
+    fill('watcher', ['Statistic', 'Value'], s.watcher ? [
+      ['Watched paths', s.watcher.watched_paths.join('\n') || s.watcher.watched_path_count],
+      ['Pending events', s.watcher.pending_events_count],
+      ['Events processed', s.watcher.total_events_processed]] : []);
+    fill('cache', ['Statistic', 'Value'], [['Entries', s.render_cache.entries], ['Size', bytes(s.render_cache.total_bytes)]]);
+    fill('clients', ['Path', 'Connected', 'Id'], s.clients.map(c => [c.path, time(c.connected_at), c.id]));
+    fill('tasks', ['Task', 'State', 'Restarts', 'Last error'], s.tasks.map(t => [t.name,
+      { text: t.state, cls: t.state === 'Running' ? 'ok' : (t.state === 'Failed' ? 'bad' : '') },
+      t.restarts, t.last_error || '']));
+    fill('events', ['Time', 'Type', 'Description'], s.events.map(e => [time(e.timestamp), e.event_type, e.description]));
+    const level = document.getElementById('log-level').value;
+    const logs = await fetch('/api/logs?limit=50' + (level ? '&level=' + level : ''));
+    if (logs.ok) {
+      const records = (await logs.json()).records.reverse();
+      fill('logs', ['Time', 'Level', 'Target', 'Message'], records.map(r => [time(r.timestamp / 1000),
+        { text: r.level, cls: r.level === 'ERROR' || r.level === 'WARN' ? 'bad' : '' }, r.target, r.message]));
+    }
+  } catch (e) {
+    document.getElementById('message').textContent = 'Server unreachable';
+  }
+}
+async function action(name, describe) {
+  const response = await fetch('/_rune/api/' + name, { method: 'POST' });
+  document.getElementById('message').textContent = response.ok ? describe(await response.json()) : 'Failed';
+  refresh();
+}
+document.getElementById('reload').onclick = () => action('reload', r => 'Reloaded ' + r.clients + ' preview(s)');
+document.getElementById('clear-cache').onclick = () => action('clear-cache',
+  r => 'Cleared ' + r.render_cache_entries + ' cached render(s) and ' + r.page_caches + ' page cache(s)');
+document.getElementById('log-level').onchange = refresh;
+refresh();
+setInterval(refresh, 2000);
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::config::Config;
+    use rune_core::event::InMemoryEventBus;
+    use rune_core::state::{CachedRender, RenderMetadata, StateManager};
+    use std::path::PathBuf;
+
+    struct FixedStatistics;
+
+    #[async_trait]
+    impl WatchStatisticsProvider for FixedStatistics {
+        async fn watch_statistics(&self) -> WatchStatistics {
+            WatchStatistics {
+                watched_path_count: 1,
+                total_events_processed: 7,
+                watched_paths: vec![PathBuf::from("/docs")],
+                ..Default::default()
+            }
+        }
+    }
+
+    fn dashboard() -> (Dashboard, PluginContext) {
+        let event_bus = Arc::new(InMemoryEventBus::new());
+        let context = PluginContext::new(
+            event_bus.clone(),
+            Arc::new(Config::new()),
+            Arc::new(StateManager::new()),
+        );
+        let registry = Arc::new(HandlerRegistry::new(event_bus));
+        let (reload_sender, _) = broadcast::channel(4);
+        let dashboard = Dashboard::new(
+            context.clone(),
+            registry,
+            Arc::new(EventLog::new(2)),
+            reload_sender,
+        );
+        (dashboard, context)
+    }
+
+    #[tokio::test]
+    async fn test_event_log_keeps_newest_events() {
+        let log = EventLog::new(2);
+        log.record(&SystemEvent::theme_changed("a".to_string()))
+            .await;
+        log.record(&SystemEvent::plugin_health_check(
+            "server".to_string(),
+            rune_core::plugin::PluginHealthStatus::Healthy,
+        ))
+        .await;
+        log.record(&SystemEvent::theme_changed("b".to_string()))
+            .await;
+        log.record(&SystemEvent::theme_changed("c".to_string()))
+            .await;
+
+        let events = log.recent().await;
+        let descriptions: Vec<&str> = events.iter().map(|e| e.description.as_str()).collect();
+        assert_eq!(
+            descriptions,
+            vec!["Theme changed to c", "Theme changed to b"]
+        );
+        assert_eq!(events[0].event_type, "theme_changed");
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_shared_watcher_statistics() {
+        let (dashboard, context) = dashboard();
+        assert!(dashboard.status().await.watcher.is_none());
+
+        let provider: Arc<dyn WatchStatisticsProvider> = Arc::new(FixedStatistics);
+        context
+            .set_shared_resource("file_watcher_statistics".to_string(), provider)
+            .await
+            .unwrap();
+
+        let status = dashboard.status().await;
+        let watcher = status.watcher.unwrap();
+        assert_eq!(watcher.total_events_processed, 7);
+        assert_eq!(watcher.watched_paths, vec![PathBuf::from("/docs")]);
+        assert!(status.clients.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_clear_caches_empties_render_cache() {
+        let (dashboard, context) = dashboard();
+        let metadata = RenderMetadata::default();
+        context
+            .state_manager
+            .cache_render(
+                "abc".to_string(),
+                CachedRender::new("abc".to_string(), "<p>x</p>".to_string(), metadata),
+            )
+            .await;
+        assert_eq!(dashboard.status().await.render_cache.total_bytes, 8);
+
+        let cleared = dashboard.clear_caches().await;
+        assert_eq!(cleared.render_cache_entries, 1);
+        assert_eq!(dashboard.status().await.render_cache.entries, 0);
+        assert_eq!(dashboard.force_reload(), 0);
+    }
+}
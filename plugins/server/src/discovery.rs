@@ -0,0 +1,319 @@
+//! LAN discovery for the preview server
+//!
+//! Advertises a running Rune instance over mDNS/DNS-SD as `_rune._tcp` so that
+//! other machines on the same network can find the live preview without being
+//! told the address and port. Discovery is opt-in and never blocks the server
+//! from starting: any failure is logged and the server keeps running.
+
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use rune_core::error::{Result, RuneError};
+use std::net::IpAddr;
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+/// DNS-SD service type advertised by Rune
+pub const SERVICE_TYPE: &str = "_rune._tcp.local.";
+
+/// Maximum length of a DNS label (RFC 1035)
+const MAX_INSTANCE_NAME_LEN: usize = 63;
+
+/// Active mDNS advertisement for a Rune server
+pub struct LanDiscovery {
+    daemon: ServiceDaemon,
+    fullname: String,
+    instance_name: String,
+}
+
+impl LanDiscovery {
+    /// Start advertising the server bound to `hostname:port` under the given document title
+    pub fn start(title: &str, hostname: &str, port: u16) -> Result<Self> {
+        let instance_name = instance_name_for(title, port);
+        let host_name = format!("rune-{}.local.", port);
+        let properties = [
+            ("title", title),
+            ("path", "/"),
+            ("version", env!("CARGO_PKG_VERSION")),
+        ];
+
+        let daemon = ServiceDaemon::new()
+            .map_err(|e| RuneError::Server(format!("Failed to start mDNS daemon: {}", e)))?;
+
+        // When bound to a specific address advertise only that one, otherwise let the
+        // daemon publish every address of the host and keep them up to date.
+        let service = match specific_bind_address(hostname) {
+            Some(ip) => ServiceInfo::new(
+                SERVICE_TYPE,
+                &instance_name,
+                &host_name,
+                ip,
+                port,
+                &properties[..],
+            ),
+            None => ServiceInfo::new(
+                SERVICE_TYPE,
+                &instance_name,
+                &host_name,
+                (),
+                port,
+                &properties[..],
+            )
+            .map(ServiceInfo::enable_addr_auto),
+        }
+        .map_err(|e| RuneError::Server(format!("Invalid mDNS service description: {}", e)))?;
+
+        let fullname = service.get_fullname().to_string();
+        daemon
+            .register(service)
+            .map_err(|e| RuneError::Server(format!("Failed to register mDNS service: {}", e)))?;
+
+        info!("Advertising '{}' via mDNS as {}", title, fullname);
+
+        Ok(Self {
+            daemon,
+            fullname,
+            instance_name,
+        })
+    }
+
+    /// Get the advertised service instance name
+    pub fn instance_name(&self) -> &str {
+        &self.instance_name
+    }
+
+    /// Get the fully qualified advertised service name
+    pub fn fullname(&self) -> &str {
+        &self.fullname
+    }
+
+    /// Withdraw the advertisement and stop the mDNS daemon
+    pub fn shutdown(self) {
+        match self.daemon.unregister(&self.fullname) {
+            Ok(receiver) => {
+                // Give the daemon a moment to send the goodbye packets
+                let _ = receiver.recv_timeout(std::time::Duration::from_millis(500));
+            }
+            Err(e) => warn!("Failed to unregister mDNS service {}: {}", self.fullname, e),
+        }
+
+        if let Err(e) = self.daemon.shutdown() {
+            warn!("Failed to shut down mDNS daemon: {}", e);
+        }
+
+        debug!("mDNS advertisement for {} withdrawn", self.fullname);
+    }
+}
+
+/// Check whether a bind address is reachable from other machines at all
+pub fn is_lan_reachable(hostname: &str) -> bool {
+    if hostname.eq_ignore_ascii_case("localhost") {
+        return false;
+    }
+
+    match hostname.parse::<IpAddr>() {
+        Ok(ip) => !ip.is_loopback(),
+        Err(_) => true,
+    }
+}
+
+/// List the URLs under which a server bound to `hostname:port` can be reached from other devices
+///
+/// Wildcard binds are expanded to the addresses of all non-loopback interfaces,
+/// IPv4 first since those are the ones phones on the same network typically use.
+pub fn reachable_urls(hostname: &str, port: u16) -> Vec<String> {
+    if !is_lan_reachable(hostname) {
+        return Vec::new();
+    }
+
+    let ips = match hostname.parse::<IpAddr>() {
+        Ok(ip) if ip.is_unspecified() => interface_addresses(),
+        Ok(ip) => vec![ip],
+        Err(_) => return vec![format!("http://{}:{}/", hostname, port)],
+    };
+
+    ips.into_iter().map(|ip| url_for(ip, port)).collect()
+}
+
+/// Format an address and port as a preview URL
+pub fn url_for(ip: IpAddr, port: u16) -> String {
+    match ip {
+        IpAddr::V4(v4) => format!("http://{}:{}/", v4, port),
+        IpAddr::V6(v6) => format!("http://[{}]:{}/", v6, port),
+    }
+}
+
+/// Collect the non-loopback addresses of all local interfaces
+fn interface_addresses() -> Vec<IpAddr> {
+    let interfaces = match if_addrs::get_if_addrs() {
+        Ok(interfaces) => interfaces,
+        Err(e) => {
+            warn!("Failed to enumerate network interfaces: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut ips: Vec<IpAddr> = interfaces
+        .into_iter()
+        .filter(|iface| !iface.is_loopback())
+        .map(|iface| iface.ip())
+        .filter(|ip| match ip {
+            IpAddr::V4(v4) => !v4.is_link_local(),
+            // Link-local IPv6 addresses need a zone id and are useless in a URL
+            IpAddr::V6(v6) => (v6.segments()[0] & 0xffc0) != 0xfe80,
+        })
+        .collect();
+
+    ips.sort_by_key(|ip| ip.is_ipv6());
+    ips.dedup();
+    ips
+}
+
+/// Resolve a bind address to a specific IP, or `None` for wildcard and named hosts
+fn specific_bind_address(hostname: &str) -> Option<IpAddr> {
+    hostname
+        .parse::<IpAddr>()
+        .ok()
+        .filter(|ip| !ip.is_unspecified())
+}
+
+/// Build a DNS-SD instance name from the document title
+///
+/// The port is appended so that several Rune instances on one host do not
+/// collide, and the result is truncated to fit in a single DNS label.
+pub fn instance_name_for(title: &str, port: u16) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| if c == '.' || c.is_control() { ' ' } else { c })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let base = if cleaned.is_empty() {
+        "Rune"
+    } else {
+        cleaned.as_str()
+    };
+
+    let suffix = format!(" ({})", port);
+    let mut name = String::new();
+    for c in base.chars() {
+        if name.len() + c.len_utf8() + suffix.len() > MAX_INSTANCE_NAME_LEN {
+            break;
+        }
+        name.push(c);
+    }
+
+    format!("{}{}", name.trim_end(), suffix)
+}
+
+/// Determine the title of a markdown document
+///
+/// Uses the first level-one ATX heading, falling back to the file stem.
+pub fn document_title(path: &Path) -> String {
+    if let Ok(content) = std::fs::read_to_string(path) {
+        if let Some(title) = title_from_markdown(&content) {
+            return title;
+        }
+    }
+
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Rune".to_string())
+}
+
+/// Extract the first level-one heading from markdown content
+fn title_from_markdown(content: &str) -> Option<String> {
+    let mut in_fence = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+
+        if let Some(heading) = trimmed.strip_prefix("# ") {
+            let heading = heading.trim().trim_end_matches('#').trim();
+            if !heading.is_empty() {
+                return Some(heading.to_string());
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_name_includes_port() {
+        assert_eq!(
+            instance_name_for("Design Notes", 3000),
+            "Design Notes (3000)"
+        );
+    }
+
+    #[test]
+    fn test_instance_name_sanitizes_and_truncates() {
+        assert_eq!(instance_name_for("v1.2\trelease", 80), "v1 2 release (80)");
+        assert_eq!(instance_name_for("   ", 8080), "Rune (8080)");
+
+        let long_title = "ü".repeat(100);
+        let name = instance_name_for(&long_title, 3000);
+        assert!(name.len() <= MAX_INSTANCE_NAME_LEN);
+        assert!(name.ends_with(" (3000)"));
+    }
+
+    #[test]
+    fn test_title_from_markdown() {
+        let content = "```\n# not a title\n```\n\n## Sub\n# Project Guide #\n";
+        assert_eq!(
+            title_from_markdown(content),
+            Some("Project Guide".to_string())
+        );
+        assert_eq!(title_from_markdown("no headings here"), None);
+    }
+
+    #[test]
+    fn test_document_title_falls_back_to_file_stem() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("meeting-notes.md");
+        std::fs::write(&path, "just text").unwrap();
+        assert_eq!(document_title(&path), "meeting-notes");
+
+        std::fs::write(&path, "# Weekly Sync\n").unwrap();
+        assert_eq!(document_title(&path), "Weekly Sync");
+    }
+
+    #[test]
+    fn test_lan_reachability() {
+        assert!(!is_lan_reachable("127.0.0.1"));
+        assert!(!is_lan_reachable("localhost"));
+        assert!(!is_lan_reachable("::1"));
+        assert!(is_lan_reachable("0.0.0.0"));
+        assert!(is_lan_reachable("192.168.1.20"));
+        assert_eq!(specific_bind_address("0.0.0.0"), None);
+        assert!(specific_bind_address("192.168.1.20").is_some());
+    }
+
+    #[test]
+    fn test_reachable_urls() {
+        assert!(reachable_urls("127.0.0.1", 3000).is_empty());
+        assert_eq!(
+            reachable_urls("192.168.1.20", 3000),
+            vec!["http://192.168.1.20:3000/".to_string()]
+        );
+        assert_eq!(
+            url_for("fd00::1".parse().unwrap(), 8080),
+            "http://[fd00::1]:8080/"
+        );
+
+        for url in reachable_urls("0.0.0.0", 3000) {
+            assert!(!url.contains("127.0.0.1"));
+        }
+    }
+}
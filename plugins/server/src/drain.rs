@@ -0,0 +1,160 @@
+//! Draining the server when it shuts down
+//!
+//! Aborting the server task cut responses off halfway and dropped WebSocket
+//! clients without a word. Shutting down now [begins](Drain::begin) a
+//! drain instead: the server stops accepting connections and lets the
+//! requests in flight finish, and every WebSocket client gets a Close frame
+//! saying the server is going away. The server task ends once the last
+//! request is answered and the last WebSocket closed, or is aborted by the
+//! supervisor at the deadline. What was let finish and what was cut off
+//! ends up in a [`DrainReport`].
+
+use rune_core::DrainReport;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Reason WebSocket clients are given when the server shuts down
+pub const SHUTDOWN_REASON: &str = "Server is shutting down";
+
+/// Time a WebSocket client has to answer the Close frame
+pub const CLOSE_HANDSHAKE: Duration = Duration::from_secs(1);
+
+/// Requests and WebSocket connections of a server, and whether it drains
+pub struct Drain {
+    draining: watch::Sender<bool>,
+    /// Open WebSocket connections
+    websockets: watch::Sender<usize>,
+    in_flight: AtomicUsize,
+    /// Requests answered since the drain began
+    completed: AtomicUsize,
+    /// WebSocket clients sent a Close frame
+    closed: AtomicUsize,
+}
+
+impl Default for Drain {
+    fn default() -> Self {
+        Self {
+            draining: watch::channel(false).0,
+            websockets: watch::channel(0).0,
+            in_flight: AtomicUsize::new(0),
+            completed: AtomicUsize::new(0),
+            closed: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Drain {
+    /// Start draining
+    pub fn begin(&self) {
+        self.draining.send_replace(true);
+    }
+
+    /// Whether the drain began
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+
+    /// Wait for the drain to begin
+    pub async fn begun(&self) {
+        let mut draining = self.draining.subscribe();
+        let _ = draining.wait_for(|draining| *draining).await;
+    }
+
+    /// Wait for every WebSocket connection to close
+    pub async fn websockets_closed(&self) {
+        let mut websockets = self.websockets.subscribe();
+        let _ = websockets.wait_for(|open| *open == 0).await;
+    }
+
+    /// Count a request in flight until the guard is dropped
+    pub fn request(self: &Arc<Self>) -> InFlight {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight(self.clone())
+    }
+
+    /// Count an open WebSocket connection until the guard is dropped
+    pub fn websocket(self: &Arc<Self>) -> OpenWebSocket {
+        self.websockets.send_modify(|open| *open += 1);
+        OpenWebSocket(self.clone())
+    }
+
+    /// Record that a WebSocket client was sent a Close frame
+    pub fn websocket_closed(&self) {
+        self.closed.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Report of the drain of `task`
+    pub fn report(&self, task: &str, finished: bool, elapsed: Duration) -> DrainReport {
+        DrainReport {
+            task: task.to_string(),
+            finished,
+            elapsed,
+            completed: self.completed.load(Ordering::SeqCst),
+            interrupted: self.in_flight.load(Ordering::SeqCst),
+            closed_connections: self.closed.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// A request in flight
+pub struct InFlight(Arc<Drain>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+        if self.0.is_draining() {
+            self.0.completed.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// An open WebSocket connection
+pub struct OpenWebSocket(Arc<Drain>);
+
+impl Drop for OpenWebSocket {
+    fn drop(&mut self) {
+        self.0.websockets.send_modify(|open| *open -= 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drains_count_requests_and_wait_for_websockets() {
+        let drain = Arc::new(Drain::default());
+        let before = drain.request();
+        drop(before);
+        let answered = drain.request();
+        let stuck = drain.request();
+        let socket = drain.websocket();
+
+        drain.begin();
+        drain.begun().await;
+        drop(answered);
+        drain.websocket_closed();
+        let waiting = tokio::spawn({
+            let drain = drain.clone();
+            async move { drain.websockets_closed().await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+        drop(socket);
+        waiting.await.unwrap();
+
+        // Requests answered before the drain are not counted
+        let report = drain.report("server", false, Duration::from_secs(1));
+        assert_eq!(
+            (
+                report.completed,
+                report.interrupted,
+                report.closed_connections
+            ),
+            (1, 1, 1)
+        );
+        drop(stuck);
+    }
+}
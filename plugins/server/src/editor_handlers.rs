@@ -1,11 +1,30 @@
 //! Editor handlers for raw text editing interface
-
+//!
+//! Besides full content updates, the editor WebSocket speaks a versioned edit
+//! protocol for clients that cannot embed a CRDT: a client sends `ot_join`
+//! and receives the content, its client ID and the revision, then sends
+//! `ot_edit` messages carrying the revision its edits were made against.
+//! The server rebases them over concurrent edits (see [`crate::ot`]),
+//! acknowledges with `ot_ack` and broadcasts `ot_applied` to every client,
+//! which skips its own by `author`. Rejected edits get an `ot_error` with an
+//! [`OtErrorCode`]; on `stale_revision` the client rejoins.
+//!
+//! Cursor positions from `content_update` and `cursor_move` are shared with
+//! preview viewers as anonymous presence (see [`crate::presence`]).
+
+use crate::csrf;
+use crate::handlers::ServerMessage;
+use crate::ot::{OtDocument, OtErrorCode, RangeEdit};
+use crate::presence::{self, PresenceTracker};
 use crate::{
     HttpHandler, HttpRequest, HttpResponse, WebSocketConnection, WebSocketHandler, WebSocketMessage,
 };
 use async_trait::async_trait;
 use axum::http::Method;
-use rune_core::{Result, RuneError};
+use rune_core::history::HistoryStore;
+use rune_core::{
+    crypto, EventBus, LineEnding, Notification, NotificationAction, Result, RuneError, SystemEvent,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -49,8 +68,18 @@ pub enum EditorMessage {
         content: String,
         cursor_position: CursorPosition,
     },
+    #[serde(rename = "cursor_move")]
+    CursorMove {
+        session_id: String,
+        cursor_position: CursorPosition,
+    },
     #[serde(rename = "save_request")]
-    SaveRequest { session_id: String },
+    SaveRequest {
+        session_id: String,
+        /// Recorded with the version in the document's history
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+    },
     #[serde(rename = "mode_switch")]
     ModeSwitch { session_id: String, mode: String },
     #[serde(rename = "click_to_edit")]
@@ -86,6 +115,41 @@ pub enum EditorMessage {
         success: bool,
         timestamp: String,
     },
+    #[serde(rename = "ot_join")]
+    OtJoin { session_id: String },
+    #[serde(rename = "ot_state")]
+    OtState {
+        session_id: String,
+        client_id: String,
+        revision: u64,
+        content: String,
+    },
+    #[serde(rename = "ot_edit")]
+    OtEdit {
+        session_id: String,
+        revision: u64,
+        edits: Vec<RangeEdit>,
+    },
+    #[serde(rename = "ot_ack")]
+    OtAck {
+        session_id: String,
+        revision: u64,
+        rebased: bool,
+    },
+    #[serde(rename = "ot_applied")]
+    OtApplied {
+        session_id: String,
+        revision: u64,
+        edits: Vec<RangeEdit>,
+        author: String,
+    },
+    #[serde(rename = "ot_error")]
+    OtError {
+        session_id: String,
+        code: OtErrorCode,
+        message: String,
+        revision: u64,
+    },
 }
 
 impl RawEditorHandler {
@@ -225,7 +289,10 @@ impl RawEditorHandler {
         
         function initWebSocket() {{
             const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
-            const wsUrl = `${{protocol}}//${{window.location.host}}/ws/editor`;
+            const token = document.querySelector('meta[name="rune-token"]')?.content
+                || sessionStorage.getItem('rune-token');
+            const query = token ? `?token=${{encodeURIComponent(token)}}` : '';
+            const wsUrl = `${{protocol}}//${{window.location.host}}/ws/editor${{query}}`;
             ws = new WebSocket(wsUrl);
             ws.onopen = () => console.log('Editor WebSocket connected');
             ws.onclose = () => setTimeout(initWebSocket, 1000);
@@ -350,9 +417,59 @@ impl RawEditorHandler {
             setDirty(true);
             updateStatus();
         }});
+
+        // Rich text pastes land as markdown rather than bare text; plain
+        // text is pasted when conversion fails
+        editor.addEventListener('paste', async (e) => {{
+            const html = e.clipboardData && e.clipboardData.getData('text/html');
+            if (!html) return;
+            e.preventDefault();
+            const start = editor.selectionStart;
+            const end = editor.selectionEnd;
+            let markdown = e.clipboardData.getData('text/plain');
+            try {{
+                const response = await fetch('/api/convert/html-to-md', {{
+                    method: 'POST',
+                    headers: {{ 'Content-Type': 'application/json' }},
+                    body: JSON.stringify({{ html }})
+                }});
+                if (response.ok) {{
+                    markdown = (await response.json()).markdown.replace(/\\n$/, '');
+                }}
+            }} catch (error) {{
+                console.warn('Failed to convert pasted HTML:', error);
+            }}
+            editor.setRangeText(markdown, start, end, 'end');
+            editor.dispatchEvent(new Event('input'));
+        }});
+        
+        // Share where the cursor is with preview viewers, at most every 200ms
+        let cursorTimer = null;
+        function shareCursor() {{
+            if (cursorTimer) return;
+            cursorTimer = setTimeout(() => {{
+                cursorTimer = null;
+                const start = editor.selectionStart;
+                const before = editor.value.slice(0, start);
+                const line = before.split('\\n').length - 1;
+                sendMessage({{
+                    type: 'cursor_move',
+                    session_id: sessionId,
+                    cursor_position: {{
+                        line: line,
+                        column: start - before.lastIndexOf('\\n') - 1,
+                        selection_start: start,
+                        selection_end: editor.selectionEnd
+                    }}
+                }});
+            }}, 200);
+        }}
         
         editor.addEventListener('click', updateStatus);
         editor.addEventListener('keyup', updateStatus);
+        editor.addEventListener('click', shareCursor);
+        editor.addEventListener('keyup', shareCursor);
+        editor.addEventListener('select', shareCursor);
         
         document.addEventListener('keydown', (e) => {{
             if (e.ctrlKey || e.metaKey) {{
@@ -404,9 +521,7 @@ impl HttpHandler for RawEditorHandler {
     async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
         let session_id = Uuid::new_v4().to_string();
 
-        let content = tokio::fs::read_to_string(&self.markdown_file)
-            .await
-            .unwrap_or_default();
+        let content = crypto::read_document(&self.markdown_file).unwrap_or_default();
 
         let session = EditorSession {
             session_id: session_id.clone(),
@@ -422,7 +537,7 @@ impl HttpHandler for RawEditorHandler {
         }
 
         let html = self.generate_editor_html(&content, &session_id);
-        Ok(HttpResponse::html(&html))
+        Ok(HttpResponse::html(csrf::with_fetch_script(&html)))
     }
 
     fn priority(&self) -> i32 {
@@ -442,6 +557,13 @@ pub struct EditorWebSocketHandler {
     event_sender: Arc<RwLock<Option<tokio::sync::broadcast::Sender<EditorBroadcastMessage>>>>,
     /// Current markdown file being edited
     markdown_file: Arc<RwLock<Option<PathBuf>>>,
+    /// Documents of versioned-edit clients by file
+    documents: Arc<RwLock<HashMap<PathBuf, OtDocument>>>,
+    /// Preview WebSocket channel that receives author presence
+    preview_sender: Arc<RwLock<Option<tokio::sync::broadcast::Sender<ServerMessage>>>>,
+    presence: Arc<RwLock<PresenceTracker>>,
+    /// Bus the user is notified of failed saves on
+    event_bus: Arc<RwLock<Option<Arc<dyn EventBus>>>>,
 }
 
 /// Broadcast message for editor events
@@ -461,9 +583,23 @@ impl EditorWebSocketHandler {
             editor_sessions: Arc::new(RwLock::new(HashMap::new())),
             event_sender: Arc::new(RwLock::new(Some(event_sender))),
             markdown_file: Arc::new(RwLock::new(None)),
+            documents: Arc::new(RwLock::new(HashMap::new())),
+            preview_sender: Arc::new(RwLock::new(None)),
+            presence: Arc::new(RwLock::new(PresenceTracker::default())),
+            event_bus: Arc::new(RwLock::new(None)),
         }
     }
 
+    /// Share author presence with the preview WebSocket's viewers
+    pub async fn set_preview_sender(&self, sender: tokio::sync::broadcast::Sender<ServerMessage>) {
+        *self.preview_sender.write().await = Some(sender);
+    }
+
+    /// Notify the user of failed saves on `event_bus`
+    pub async fn set_event_bus(&self, event_bus: Arc<dyn EventBus>) {
+        *self.event_bus.write().await = Some(event_bus);
+    }
+
     /// Set the current markdown file being edited
     pub async fn set_markdown_file(&self, file_path: PathBuf) {
         let mut markdown_file = self.markdown_file.write().await;
@@ -541,8 +677,225 @@ impl EditorWebSocketHandler {
         Ok(())
     }
 
+    /// Send a session's cursor to preview viewers as the rendered block it is in
+    async fn publish_presence(
+        &self,
+        connection: &WebSocketConnection,
+        session_id: &str,
+        cursor_position: &CursorPosition,
+    ) {
+        let Some(sender) = self.preview_sender.read().await.clone() else {
+            return;
+        };
+        let session_content = self
+            .editor_sessions
+            .read()
+            .await
+            .get(session_id)
+            .map(|session| session.content.clone());
+        // Sessions that have not sent content yet show the file on disk
+        let content = match session_content {
+            Some(content) => content,
+            None => match self.session_file(session_id).await {
+                Ok(file_path) => crypto::read_document(&file_path).unwrap_or_default(),
+                Err(_) => return,
+            },
+        };
+        let author = self
+            .presence
+            .write()
+            .await
+            .author(session_id, &connection.id);
+        let selection = cursor_position
+            .selection_start
+            .zip(cursor_position.selection_end);
+        // Nobody watching the preview is not an error
+        let _ = sender.send(presence::locate(
+            &content,
+            author,
+            cursor_position.line,
+            selection,
+        ));
+    }
+
+    /// File edited by a session, or the current markdown file for new sessions
+    async fn session_file(&self, session_id: &str) -> Result<PathBuf> {
+        if let Some(session) = self.editor_sessions.read().await.get(session_id) {
+            return Ok(session.file_path.clone());
+        }
+        self.markdown_file
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| RuneError::Server("No markdown file set for editor".to_string()))
+    }
+
+    /// Copy a document's content into every session editing its file
+    async fn sync_sessions(&self, file_path: &PathBuf, content: &str) {
+        let mut sessions = self.editor_sessions.write().await;
+        for session in sessions.values_mut() {
+            if session.file_path == *file_path {
+                session.content = content.to_string();
+                session.is_dirty = true;
+            }
+        }
+    }
+
+    /// Handle a versioned-edit client joining a session's document
+    async fn handle_ot_join(
+        &self,
+        connection: &WebSocketConnection,
+        session_id: &str,
+    ) -> Result<()> {
+        let file_path = self.session_file(session_id).await?;
+        let mut documents = self.documents.write().await;
+        if !documents.contains_key(&file_path) {
+            let content = match self.editor_sessions.read().await.get(session_id) {
+                Some(session) => session.content.clone(),
+                None => crypto::read_document(&file_path).unwrap_or_default(),
+            };
+            documents.insert(file_path.clone(), OtDocument::new(content));
+        }
+        let document = &documents[&file_path];
+
+        self.editor_sessions
+            .write()
+            .await
+            .entry(session_id.to_string())
+            .or_insert_with(|| EditorSession {
+                session_id: session_id.to_string(),
+                file_path: file_path.clone(),
+                content: document.content().to_string(),
+                cursor_position: CursorPosition::default(),
+                is_dirty: false,
+            });
+
+        connection
+            .send_json(&EditorMessage::OtState {
+                session_id: session_id.to_string(),
+                client_id: connection.id.clone(),
+                revision: document.revision(),
+                content: document.content().to_string(),
+            })
+            .await
+    }
+
+    /// Handle versioned edits, rebasing them over concurrent ones
+    async fn handle_ot_edit(
+        &self,
+        connection: &WebSocketConnection,
+        session_id: &str,
+        revision: u64,
+        edits: &[RangeEdit],
+    ) -> Result<()> {
+        let file_path = self.session_file(session_id).await?;
+        // Held until the broadcast, so clients see revisions in order
+        let mut documents = self.documents.write().await;
+        let Some(document) = documents.get_mut(&file_path) else {
+            return connection
+                .send_json(&EditorMessage::OtError {
+                    session_id: session_id.to_string(),
+                    code: OtErrorCode::NotJoined,
+                    message: "Send ot_join before editing".to_string(),
+                    revision: 0,
+                })
+                .await;
+        };
+
+        let applied = match document.apply(revision, edits) {
+            Ok(applied) => applied,
+            Err(error) => {
+                tracing::debug!(
+                    "Rejected edits for session {}: {}",
+                    session_id,
+                    error.message
+                );
+                return connection
+                    .send_json(&EditorMessage::OtError {
+                        session_id: session_id.to_string(),
+                        code: error.code,
+                        message: error.message,
+                        revision: error.revision,
+                    })
+                    .await;
+            }
+        };
+        self.sync_sessions(&file_path, document.content()).await;
+
+        connection
+            .send_json(&EditorMessage::OtAck {
+                session_id: session_id.to_string(),
+                revision: applied.revision,
+                rebased: applied.rebased,
+            })
+            .await?;
+        self.broadcast_editor_event(
+            session_id.to_string(),
+            EditorMessage::OtApplied {
+                session_id: session_id.to_string(),
+                revision: applied.revision,
+                edits: applied.edits,
+                author: connection.id.clone(),
+            },
+        )
+        .await
+    }
+
+    /// Carry a full content update into the session's document, if any
+    /// versioned-edit client joined it
+    async fn replace_ot_document(
+        &self,
+        connection: &WebSocketConnection,
+        session_id: &str,
+        content: &str,
+    ) -> Result<()> {
+        let file_path = self.session_file(session_id).await?;
+        let mut documents = self.documents.write().await;
+        let Some(document) = documents.get_mut(&file_path) else {
+            return Ok(());
+        };
+        if document.content() == content {
+            return Ok(());
+        }
+        let applied = document.replace(content.to_string());
+        self.broadcast_editor_event(
+            session_id.to_string(),
+            EditorMessage::OtApplied {
+                session_id: session_id.to_string(),
+                revision: applied.revision,
+                edits: applied.edits,
+                author: connection.id.clone(),
+            },
+        )
+        .await
+    }
+
+    /// Tell the user a save failed, offering to try again
+    async fn notify_save_failed(&self, error: &RuneError) {
+        let Some(event_bus) = self.event_bus.read().await.clone() else {
+            return;
+        };
+        let document = self
+            .markdown_file
+            .read()
+            .await
+            .as_ref()
+            .and_then(|file| file.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "the document".to_string());
+        let notification =
+            Notification::error("editor", format!("Could not save {}: {}", document, error))
+                .with_action(NotificationAction::command("Retry", "editor.save"));
+        if let Err(e) = event_bus
+            .publish_system_event(SystemEvent::notification(notification))
+            .await
+        {
+            tracing::warn!("Failed to publish save notification: {}", e);
+        }
+    }
+
     /// Handle save request
-    async fn handle_save_request(&self, session_id: &str) -> Result<()> {
+    async fn handle_save_request(&self, session_id: &str, message: Option<String>) -> Result<()> {
         // Get the current markdown file path
         let markdown_file = self.markdown_file.read().await;
         let file_path = markdown_file
@@ -565,13 +918,33 @@ impl EditorWebSocketHandler {
 
         drop(sessions);
 
+        // Keep the line endings the file has, so saving does not rewrite
+        // every line of a Windows document
+        let content = match crypto::read_document(file_path) {
+            Ok(existing) => LineEnding::detect(&existing).apply(&content).into_owned(),
+            Err(_) => content,
+        };
+
         // Write content to file
-        tokio::fs::write(file_path, &content)
-            .await
+        crypto::write_document(file_path, &content)
             .map_err(|e| RuneError::Server(format!("Failed to save file: {}", e)))?;
 
         tracing::info!("✅ Saved content to file: {:?}", file_path);
 
+        // The history store keeps plaintext, so encrypted documents have none
+        let history_file = file_path.clone();
+        if !crypto::is_encrypted(&history_file) {
+            match tokio::task::spawn_blocking(move || {
+                HistoryStore::record_save(&history_file, &content, message)
+            })
+            .await
+            {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!("Failed to record version history: {}", e),
+                Err(e) => tracing::warn!("Version history task failed: {}", e),
+            }
+        }
+
         // Mark session as not dirty
         let mut sessions = self.editor_sessions.write().await;
         if let Some(session) = sessions.get_mut(session_id) {
@@ -588,6 +961,10 @@ impl WebSocketHandler for EditorWebSocketHandler {
         &self.path
     }
 
+    fn edits(&self) -> bool {
+        true // Clients save documents
+    }
+
     async fn on_connect(&self, connection: &WebSocketConnection) -> Result<()> {
         tracing::info!("Editor WebSocket client connected: {}", connection.id);
 
@@ -640,6 +1017,10 @@ impl WebSocketHandler for EditorWebSocketHandler {
                             cursor_position.clone(),
                         )
                         .await?;
+                        self.replace_ot_document(connection, session_id, content)
+                            .await?;
+                        self.publish_presence(connection, session_id, cursor_position)
+                            .await;
 
                         // Broadcast content update to all other clients
                         self.broadcast_editor_event(session_id.clone(), editor_msg)
@@ -650,8 +1031,27 @@ impl WebSocketHandler for EditorWebSocketHandler {
                             session_id
                         );
                     }
-                    EditorMessage::SaveRequest { ref session_id } => {
-                        self.handle_save_request(session_id).await?;
+                    EditorMessage::CursorMove {
+                        ref session_id,
+                        ref cursor_position,
+                    } => {
+                        if let Some(session) =
+                            self.editor_sessions.write().await.get_mut(session_id)
+                        {
+                            session.cursor_position = cursor_position.clone();
+                        }
+                        self.publish_presence(connection, session_id, cursor_position)
+                            .await;
+                    }
+                    EditorMessage::SaveRequest {
+                        ref session_id,
+                        ref message,
+                    } => {
+                        if let Err(e) = self.handle_save_request(session_id, message.clone()).await
+                        {
+                            self.notify_save_failed(&e).await;
+                            return Err(e);
+                        }
 
                         let save_complete_msg = EditorMessage::SaveComplete {
                             session_id: session_id.clone(),
@@ -839,6 +1239,24 @@ impl WebSocketHandler for EditorWebSocketHandler {
                         // Save complete messages are typically sent from server to client
                         tracing::debug!("Received save complete message from client (unexpected)");
                     }
+                    EditorMessage::OtJoin { ref session_id } => {
+                        self.handle_ot_join(connection, session_id).await?;
+                    }
+                    EditorMessage::OtEdit {
+                        ref session_id,
+                        revision,
+                        ref edits,
+                    } => {
+                        self.handle_ot_edit(connection, session_id, revision, edits)
+                            .await?;
+                    }
+                    EditorMessage::OtState { .. }
+                    | EditorMessage::OtAck { .. }
+                    | EditorMessage::OtApplied { .. }
+                    | EditorMessage::OtError { .. } => {
+                        // Sent from server to client
+                        tracing::debug!("Received versioned edit reply from client (unexpected)");
+                    }
                 },
                 Err(e) => {
                     tracing::warn!("Failed to parse editor message: {}", e);
@@ -851,6 +1269,12 @@ impl WebSocketHandler for EditorWebSocketHandler {
 
     async fn on_disconnect(&self, connection: &WebSocketConnection) -> Result<()> {
         tracing::info!("Editor WebSocket client disconnected: {}", connection.id);
+        let left = self.presence.write().await.leave(&connection.id);
+        if let Some(sender) = self.preview_sender.read().await.as_ref() {
+            for author in left {
+                let _ = sender.send(ServerMessage::PresenceLeft { author });
+            }
+        }
         Ok(())
     }
 
@@ -1163,7 +1587,10 @@ impl LiveEditorHandler {
 
         function initWebSocket() {{
             const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
-            const wsUrl = `${{protocol}}//${{window.location.host}}/ws/editor`;
+            const token = document.querySelector('meta[name="rune-token"]')?.content
+                || sessionStorage.getItem('rune-token');
+            const query = token ? `?token=${{encodeURIComponent(token)}}` : '';
+            const wsUrl = `${{protocol}}//${{window.location.host}}/ws/editor${{query}}`;
             ws = new WebSocket(wsUrl);
             ws.onopen = () => console.log('Live Editor WebSocket connected');
             ws.onclose = () => setTimeout(initWebSocket, 1000);
@@ -1601,7 +2028,10 @@ impl LiveEditorHandler {
 
         function initWebSocket() {{
             const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
-            const wsUrl = `${{protocol}}//${{window.location.host}}/ws/editor`;
+            const token = document.querySelector('meta[name="rune-token"]')?.content
+                || sessionStorage.getItem('rune-token');
+            const query = token ? `?token=${{encodeURIComponent(token)}}` : '';
+            const wsUrl = `${{protocol}}//${{window.location.host}}/ws/editor${{query}}`;
             ws = new WebSocket(wsUrl);
             ws.onopen = () => console.log('Editor WebSocket connected');
             ws.onclose = () => setTimeout(initWebSocket, 1000);
@@ -1677,3 +2107,154 @@ impl LiveEditorHandler {
 
 #[async_trait]
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(
+        id: &str,
+    ) -> (
+        WebSocketConnection,
+        tokio::sync::broadcast::Receiver<WebSocketMessage>,
+    ) {
+        let (sender, receiver) = tokio::sync::broadcast::channel(16);
+        let connection = WebSocketConnection {
+            id: id.to_string(),
+            remote_addr: "127.0.0.1:9000".parse().unwrap(),
+            headers: Default::default(),
+            sender,
+        };
+        (connection, receiver)
+    }
+
+    async fn send(
+        handler: &EditorWebSocketHandler,
+        connection: &WebSocketConnection,
+        message: serde_json::Value,
+    ) {
+        handler
+            .on_message(connection, WebSocketMessage::Text(message.to_string()))
+            .await
+            .unwrap();
+    }
+
+    fn reply(
+        receiver: &mut tokio::sync::broadcast::Receiver<WebSocketMessage>,
+    ) -> serde_json::Value {
+        match receiver.try_recv().unwrap() {
+            WebSocketMessage::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_versioned_edits_are_rebased() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file = temp_dir.path().join("doc.md");
+        std::fs::write(&file, "one two").unwrap();
+        let handler = EditorWebSocketHandler::new("/ws/editor".to_string());
+        handler.set_markdown_file(file.clone()).await;
+        let mut events = handler.get_event_sender().await.unwrap().subscribe();
+
+        let (alice, mut alice_rx) = connection("alice");
+        let (bob, mut bob_rx) = connection("bob");
+        send(
+            &handler,
+            &alice,
+            serde_json::json!({"type": "ot_join", "session_id": "a"}),
+        )
+        .await;
+        send(
+            &handler,
+            &bob,
+            serde_json::json!({"type": "ot_join", "session_id": "b"}),
+        )
+        .await;
+        let state = reply(&mut alice_rx);
+        assert_eq!(state["type"], "ot_state");
+        assert_eq!(state["client_id"], "alice");
+        assert_eq!(
+            (state["revision"].as_u64(), state["content"].as_str()),
+            (Some(0), Some("one two"))
+        );
+        reply(&mut bob_rx);
+
+        // Both edit revision 0; Bob's edit is rebased over Alice's
+        let edit = |session: &str, start: usize, end: usize, text: &str| {
+            serde_json::json!({
+                "type": "ot_edit",
+                "session_id": session,
+                "revision": 0,
+                "edits": [{"start": start, "end": end, "text": text}],
+            })
+        };
+        send(&handler, &alice, edit("a", 0, 3, "1")).await;
+        send(&handler, &bob, edit("b", 7, 7, " three")).await;
+        assert_eq!(reply(&mut alice_rx)["rebased"], false);
+        let ack = reply(&mut bob_rx);
+        assert_eq!(
+            (ack["type"].as_str(), ack["revision"].as_u64()),
+            (Some("ot_ack"), Some(2))
+        );
+        assert_eq!(ack["rebased"], true);
+
+        events.recv().await.unwrap();
+        let EditorMessage::OtApplied { edits, author, .. } = events.recv().await.unwrap().event
+        else {
+            panic!("expected ot_applied");
+        };
+        assert_eq!(author, "bob");
+        assert_eq!(edits, vec![RangeEdit::new(5, 5, " three")]);
+        assert_eq!(
+            handler.editor_sessions.read().await["a"].content,
+            "1 two three"
+        );
+
+        let mut ahead = edit("b", 0, 0, "x");
+        ahead["revision"] = 9.into();
+        send(&handler, &bob, ahead).await;
+        let error = reply(&mut bob_rx);
+        assert_eq!(
+            (error["type"].as_str(), error["code"].as_str()),
+            (Some("ot_error"), Some("future_revision"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cursor_presence_reaches_preview() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file = temp_dir.path().join("doc.md");
+        std::fs::write(&file, "# Title\n\nFirst\n\nSecond\n").unwrap();
+        let handler = EditorWebSocketHandler::new("/ws/editor".to_string());
+        handler.set_markdown_file(file).await;
+        let (preview, mut viewers) = tokio::sync::broadcast::channel(16);
+        handler.set_preview_sender(preview).await;
+
+        let (alice, _alice_rx) = connection("alice");
+        send(
+            &handler,
+            &alice,
+            serde_json::json!({
+                "type": "cursor_move",
+                "session_id": "a",
+                "cursor_position": {"line": 4, "column": 2}
+            }),
+        )
+        .await;
+        assert_eq!(
+            viewers.try_recv().unwrap(),
+            ServerMessage::Presence {
+                author: 1,
+                block: Some(2),
+                selection: None
+            }
+        );
+
+        handler.on_disconnect(&alice).await.unwrap();
+        assert_eq!(
+            viewers.try_recv().unwrap(),
+            ServerMessage::PresenceLeft { author: 1 }
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Themed error pages
+//!
+//! Browsers get a page built from the preview template instead of the
+//! plain-text status line of a failed request. A missing page shows the
+//! configured not-found markdown file, when there is one, followed by links
+//! to the served documents whose URLs come closest to the one requested.
+//! API requests and clients not asking for HTML keep the plain-text answer.
+
+use crate::{handlers, roots, HttpResponse};
+use axum::http::{header, HeaderMap, StatusCode};
+use percent_encoding::percent_decode_str;
+use rune_core::{
+    renderer::{RenderContext, RendererRegistry},
+    state::ServedRoot,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Most near matches listed on a not-found page
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Builds the pages replacing plain-text error responses
+pub struct ErrorPages {
+    not_found_page: Option<PathBuf>,
+    roots: Vec<ServedRoot>,
+    renderer_registry: Option<Arc<RendererRegistry>>,
+}
+
+impl ErrorPages {
+    pub fn new(
+        not_found_page: Option<PathBuf>,
+        roots: Vec<ServedRoot>,
+        renderer_registry: Option<Arc<RendererRegistry>>,
+    ) -> Self {
+        Self {
+            not_found_page,
+            roots,
+            renderer_registry,
+        }
+    }
+
+    /// Whether a failed request for `path` with `headers` gets a page
+    pub fn wants_page(path: &str, headers: &HeaderMap) -> bool {
+        !path.starts_with("/api/")
+            && headers
+                .get(header::ACCEPT)
+                .and_then(|accept| accept.to_str().ok())
+                .is_some_and(|accept| accept.contains("text/html"))
+    }
+
+    /// `response` to a request for `path`, with a plain-text error turned
+    /// into a page
+    pub async fn replace(&self, path: &str, response: HttpResponse) -> HttpResponse {
+        let is_plain_error = (response.status.is_client_error()
+            || response.status.is_server_error())
+            && response.stream.is_none()
+            && response
+                .headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with("text/plain"));
+        if !is_plain_error {
+            return response;
+        }
+
+        let status = response.status;
+        let title = format!(
+            "{} {}",
+            status.as_u16(),
+            status.canonical_reason().unwrap_or("Error")
+        );
+        let custom = match &self.not_found_page {
+            Some(page) if status == StatusCode::NOT_FOUND => self.render_page(page).await,
+            _ => None,
+        };
+        let mut body = custom.unwrap_or_else(|| {
+            format!(
+                "<h1>{}</h1>\n<p>{}</p>\n",
+                html_escape::encode_text(&title),
+                html_escape::encode_text(&String::from_utf8_lossy(&response.body))
+            )
+        });
+
+        if status == StatusCode::NOT_FOUND {
+            let suggestions = suggestions(path, &self.document_urls(), MAX_SUGGESTIONS);
+            if !suggestions.is_empty() {
+                body.push_str("<h2>Did you mean</h2>\n<ul class=\"rune-suggestions\">\n");
+                for (url, label) in suggestions {
+                    body.push_str(&format!(
+                        "<li><a href=\"{}\">{}</a></li>\n",
+                        html_escape::encode_double_quoted_attribute(&url),
+                        html_escape::encode_text(&label)
+                    ));
+                }
+                body.push_str("</ul>\n");
+            }
+        }
+        body.push_str("<p><a href=\"/\">Back to the start page</a></p>");
+
+        let mut page = HttpResponse::html(handlers::standalone_page(&title, &body))
+            .with_header("cache-control", "no-store");
+        page.status = status;
+        page
+    }
+
+    /// The not-found markdown file as HTML, or `None` when it cannot be read
+    async fn render_page(&self, page: &PathBuf) -> Option<String> {
+        let content = match tokio::fs::read_to_string(page).await {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Cannot read not-found page {}: {}", page.display(), e);
+                return None;
+            }
+        };
+
+        if let Some(registry) = &self.renderer_registry {
+            let base_dir = page.parent().map(PathBuf::from).unwrap_or_default();
+            let context =
+                RenderContext::new(page.clone(), base_dir, "catppuccin-mocha".to_string());
+            match registry.render_with_pipeline(&content, &context).await {
+                Ok(rendered) => return Some(rendered.html),
+                Err(e) => warn!("Cannot render not-found page {}: {}", page.display(), e),
+            }
+        }
+
+        let mut options = markdown::Options::gfm();
+        options.compile.allow_dangerous_html = true;
+        markdown::to_html_with_options(&content, &options).ok()
+    }
+
+    /// URL and label of every document served
+    fn document_urls(&self) -> Vec<(String, String)> {
+        let mut urls = Vec::new();
+        for root in &self.roots {
+            if !root.is_dir() {
+                let name = root
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| root.prefix.clone());
+                urls.push((
+                    format!("/{}/", root.prefix),
+                    format!("{}/{}", root.prefix, name),
+                ));
+                continue;
+            }
+            for document in roots::markdown_documents(&root.path) {
+                if let Ok(relative) = document.strip_prefix(&root.path) {
+                    urls.push((
+                        format!("/{}/{}", root.prefix, roots::document_href(relative)),
+                        format!(
+                            "{}/{}",
+                            root.prefix,
+                            relative.to_string_lossy().replace('\\', "/")
+                        ),
+                    ));
+                }
+            }
+        }
+        urls
+    }
+}
+
+/// Number of single-character edits turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Up to `limit` of `candidates` (URL and label) closest to the requested
+/// `path`, nearest first; candidates differing in more than half their
+/// characters are left out
+pub fn suggestions(
+    path: &str,
+    candidates: &[(String, String)],
+    limit: usize,
+) -> Vec<(String, String)> {
+    let requested = percent_decode_str(path.trim_matches('/'))
+        .decode_utf8_lossy()
+        .to_lowercase();
+    let mut ranked: Vec<(usize, &(String, String))> = candidates
+        .iter()
+        .filter_map(|candidate| {
+            let label = candidate.1.to_lowercase();
+            let distance = edit_distance(&requested, &label);
+            let longest = requested.chars().count().max(label.chars().count());
+            (distance * 2 <= longest).then_some((distance, candidate))
+        })
+        .collect();
+    ranked.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1 .1.cmp(&b.1 .1)));
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(labels: &[&str]) -> Vec<(String, String)> {
+        labels
+            .iter()
+            .map(|label| (format!("/{}", label), label.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_suggestions_rank_near_matches() {
+        let candidates = candidates(&[
+            "docs/guide/setup.md",
+            "docs/guide/usage.md",
+            "docs/changelog.md",
+            "notes/README.md",
+        ]);
+        let found = suggestions("/docs/guide/setpu.md", &candidates, 5);
+        let labels: Vec<_> = found.iter().map(|(_, label)| label.as_str()).collect();
+        assert_eq!(labels, ["docs/guide/setup.md", "docs/guide/usage.md"]);
+
+        assert_eq!(
+            suggestions("/Notes/readme.md", &candidates, 5)[0].1,
+            "notes/README.md"
+        );
+        assert!(suggestions("/zzz", &candidates, 5).is_empty());
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[tokio::test]
+    async fn test_plain_errors_become_pages() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let page = temp_dir.path().join("404.md");
+        std::fs::write(&page, "# Lost?\n\nThis page is *gone*.").unwrap();
+        let root = ServedRoot {
+            prefix: "docs".to_string(),
+            path: temp_dir.path().to_path_buf(),
+        };
+        let pages = ErrorPages::new(Some(page), vec![root], None);
+
+        let missing = HttpResponse::error(StatusCode::NOT_FOUND, "Document not found");
+        let response = pages.replace("/docs/4O4.md", missing).await;
+        let body = String::from_utf8_lossy(&response.body);
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+        assert!(body.contains("<title>404 Not Found</title>"));
+        assert!(body.contains("<h1>Lost?</h1>\n<p>This page is <em>gone</em>.</p>"));
+        assert!(body.contains("<li><a href=\"/docs/404.md\">docs/404.md</a></li>"));
+
+        let failed =
+            HttpResponse::error(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error");
+        let body = String::from_utf8_lossy(&pages.replace("/", failed).await.body).into_owned();
+        assert!(body.contains("<h1>500 Internal Server Error</h1>\n<p>Internal server error</p>"));
+
+        let json = HttpResponse::json(&serde_json::json!({"error": "gone"})).unwrap();
+        let json = HttpResponse {
+            status: StatusCode::NOT_FOUND,
+            ..json
+        };
+        assert_eq!(pages.replace("/x", json).await.body, "{\"error\":\"gone\"}");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, "text/html,*/*".parse().unwrap());
+        assert!(ErrorPages::wants_page("/docs/x.md", &headers));
+        assert!(!ErrorPages::wants_page("/api/reading", &headers));
+        assert!(!ErrorPages::wants_page("/docs/x.md", &HeaderMap::new()));
+    }
+}
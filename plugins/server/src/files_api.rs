@@ -0,0 +1,331 @@
+//! Creating documents from templates
+//!
+//! `GET /api/templates` lists the document [`Templates`]. `POST
+//! /api/files/new` creates a document from one of them. It takes a JSON
+//! object with the new document's `path`, relative to a served directory,
+//! and optionally:
+//! - `root`: the route prefix of that directory, needed when several are
+//!   served.
+//! - `template`: the template's name; without one the document starts
+//!   blank.
+//! - `title`: the document's title, taken from the file name by default.
+//! - `variables`: more values to fill in.
+//!
+//! It answers `201 Created` with the document's `path`, its preview `url`,
+//! and an `editor_url` opening the raw editor at the template's cursor.
+//! Existing files are never overwritten; the request gets `409 Conflict`.
+
+use crate::jail::{self, PathJail, SymlinkPolicy};
+use crate::roots::document_href;
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::plugin::PluginContext;
+use rune_core::state::ServedRoot;
+use rune_core::templates::{self, Templates};
+use rune_core::{Config, Result, RuneError};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Component, Path};
+use std::sync::Arc;
+use tracing::info;
+
+/// Body of a new document request
+#[derive(Debug, Deserialize)]
+struct NewFileRequest {
+    path: String,
+    #[serde(default)]
+    root: Option<String>,
+    #[serde(default)]
+    template: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    variables: BTreeMap<String, String>,
+}
+
+/// Handler listing the document templates
+pub struct TemplateListHandler {
+    path_pattern: String,
+    templates: Templates,
+}
+
+impl TemplateListHandler {
+    pub fn new(path_pattern: String, templates: Templates) -> Self {
+        Self {
+            path_pattern,
+            templates,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for TemplateListHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        Ok(HttpResponse::json(&self.templates.list())?.with_header("cache-control", "no-store"))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Handler creating documents from templates in the served directories
+pub struct NewFileHandler {
+    path_pattern: String,
+    templates: Templates,
+    config: Arc<Config>,
+    roots: Vec<ServedRoot>,
+    symlinks: SymlinkPolicy,
+}
+
+impl NewFileHandler {
+    pub fn new(
+        path_pattern: String,
+        templates: Templates,
+        config: Arc<Config>,
+        roots: &[ServedRoot],
+    ) -> Self {
+        let roots = roots
+            .iter()
+            .map(|root| ServedRoot {
+                prefix: root.prefix.clone(),
+                path: root
+                    .path
+                    .canonicalize()
+                    .unwrap_or_else(|_| root.path.clone()),
+            })
+            .collect();
+        Self {
+            path_pattern,
+            templates,
+            symlinks: SymlinkPolicy::from_config(&config),
+            config,
+            roots,
+        }
+    }
+
+    /// Directory root the request names, or the only one
+    fn root(&self, prefix: Option<&str>) -> std::result::Result<&ServedRoot, String> {
+        let mut directories = self.roots.iter().filter(|root| root.is_dir());
+        match prefix {
+            Some(prefix) => directories
+                .find(|root| root.prefix == prefix.trim_matches('/'))
+                .ok_or_else(|| format!("No served directory at /{}/", prefix.trim_matches('/'))),
+            None => {
+                let first = directories
+                    .next()
+                    .ok_or_else(|| "No directory is being served".to_string())?;
+                match directories.next() {
+                    Some(_) => {
+                        Err("Several directories are served, name one as 'root'".to_string())
+                    }
+                    None => Ok(first),
+                }
+            }
+        }
+    }
+}
+
+/// Whether `path` stays inside the directory it is relative to and names
+/// a markdown document
+fn is_document_path(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        && path.extension().is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown")
+        })
+}
+
+#[async_trait]
+impl HttpHandler for NewFileHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let new_file: NewFileRequest = match serde_json::from_slice(&request.body) {
+            Ok(new_file) => new_file,
+            Err(e) => {
+                return Ok(HttpResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Invalid new document request: {}", e),
+                ))
+            }
+        };
+        let relative = Path::new(new_file.path.trim_start_matches('/'));
+        if !is_document_path(relative) {
+            return Ok(HttpResponse::error(
+                StatusCode::BAD_REQUEST,
+                "The path must name a .md or .markdown file inside the served directory",
+            ));
+        }
+        let root = match self.root(new_file.root.as_deref()) {
+            Ok(root) => root,
+            Err(message) => return Ok(HttpResponse::error(StatusCode::BAD_REQUEST, &message)),
+        };
+        let path = match PathJail::new(&root.path, self.symlinks).resolve_new(relative) {
+            Ok(path) => path,
+            Err(rejection) => {
+                jail::audit(&new_file.path, rejection);
+                return Ok(HttpResponse::error(StatusCode::FORBIDDEN, "Access denied"));
+            }
+        };
+        if path.exists() {
+            return Ok(HttpResponse::error(
+                StatusCode::CONFLICT,
+                &format!("{} already exists", new_file.path),
+            ));
+        }
+
+        let template = match self.templates.source(new_file.template.as_deref()) {
+            Ok(template) => template,
+            Err(RuneError::Config(message)) => {
+                return Ok(HttpResponse::error(StatusCode::BAD_REQUEST, &message))
+            }
+            Err(e) => return Err(e),
+        };
+        let mut variables = templates::variables(&self.config, &path);
+        if let Some(title) = new_file.title {
+            variables.insert("title".to_string(), title);
+        }
+        variables.extend(new_file.variables);
+        let document = {
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || templates::create(&path, &template, &variables))
+                .await
+                .map_err(|e| RuneError::Server(format!("New document task failed: {}", e)))??
+        };
+        info!(
+            "Created {:?} from template {}",
+            document.path,
+            new_file.template.as_deref().unwrap_or("(blank)")
+        );
+
+        let url = if root.prefix.is_empty() {
+            format!("/{}", document_href(relative))
+        } else {
+            format!("/{}/{}", root.prefix, document_href(relative))
+        };
+        let mut response = HttpResponse::json(&serde_json::json!({
+            "path": document.path,
+            "url": url,
+            "editor_url": format!("{}#edit=L{}", url, document.cursor_line),
+            "cursor_line": document.cursor_line,
+        }))?;
+        response.status = StatusCode::CREATED;
+        Ok(response)
+    }
+
+    fn edits(&self) -> bool {
+        true // Creates documents
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Register the template list and new document API for `roots`
+pub async fn register_file_api_handlers(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+    roots: &[ServedRoot],
+) -> Result<()> {
+    let templates = Templates::from_config(&context.config);
+    registry
+        .register_http_handler(Arc::new(TemplateListHandler::new(
+            "/api/templates".to_string(),
+            templates.clone(),
+        )))
+        .await?;
+    registry
+        .register_http_handler(Arc::new(NewFileHandler::new(
+            "/api/files/new".to_string(),
+            templates,
+            context.config.clone(),
+            roots,
+        )))
+        .await?;
+
+    info!("Registered template and new document API handlers");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_creates_documents_in_the_served_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let docs = temp_dir.path().join("docs");
+        let template_dir = temp_dir.path().join("templates");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::create_dir_all(&template_dir).unwrap();
+        std::fs::write(
+            template_dir.join("meeting.md"),
+            "# {{title}}\n\n{{cursor}}\n\n{{room}}\n",
+        )
+        .unwrap();
+        let roots = [ServedRoot {
+            prefix: "docs".to_string(),
+            path: docs.clone(),
+        }];
+        let handler = NewFileHandler::new(
+            "/api/files/new".to_string(),
+            Templates::in_dirs(vec![template_dir]),
+            Arc::new(Config::new()),
+            &roots,
+        );
+        let request = |body: &str| {
+            HttpRequest::new(Method::POST, "/api/files/new").with_body(body.as_bytes().to_vec())
+        };
+
+        let body = r#"{"path": "notes/team sync.md", "template": "meeting", "variables": {"room": "Lab"}}"#;
+        let response = handler.handle(request(body)).await.unwrap();
+        assert_eq!(response.status, StatusCode::CREATED);
+        let created: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(created["url"], "/docs/notes/team%20sync.md");
+        assert_eq!(created["editor_url"], "/docs/notes/team%20sync.md#edit=L3");
+        assert_eq!(
+            std::fs::read_to_string(docs.join("notes/team sync.md")).unwrap(),
+            "# Team sync\n\n\n\nLab\n"
+        );
+
+        let response = handler.handle(request(body)).await.unwrap();
+        assert_eq!(response.status, StatusCode::CONFLICT);
+        for body in [
+            r#"{"path": "../escape.md"}"#,
+            r#"{"path": "notes/script.sh"}"#,
+            r#"{"path": "other.md", "template": "standup"}"#,
+            r#"{"path": "other.md", "root": "elsewhere"}"#,
+        ] {
+            let response = handler.handle(request(body)).await.unwrap();
+            assert_eq!(response.status, StatusCode::BAD_REQUEST, "{}", body);
+        }
+        assert!(!temp_dir.path().join("escape.md").exists());
+        assert!(!docs.join("other.md").exists());
+    }
+}
@@ -1,27 +1,46 @@
 //! Concrete handler implementations for the server plugin
 
+use crate::jail::{self, PathJail, Rejection, SymlinkPolicy};
+use crate::protocol::{self, Envelope, PROTOCOL_VERSION};
+use crate::reading::ReadingPreferences;
 use crate::{
-    HttpHandler, HttpRequest, HttpResponse, WebSocketConnection, WebSocketHandler, WebSocketMessage,
+    csp, HttpHandler, HttpRequest, HttpResponse, WebSocketConnection, WebSocketHandler,
+    WebSocketMessage,
 };
 use async_trait::async_trait;
+use axum::body::Bytes;
 use axum::http::{Method, StatusCode};
+use futures_util::{future, stream, StreamExt, TryStreamExt};
 use rune_core::{
+    crypto,
+    drafts::PublishState,
     error::{Result, RuneError},
     event::{EventBus, SystemEvent},
-    renderer::{RenderContext, RendererRegistry},
+    i18n,
+    renderer::{RenderContext, RendererRegistry, DEFAULT_STREAM_CHUNK_BYTES},
+    Asset, AssetType, LargeFileConfig, Notification, NONCE_PLACEHOLDER,
 };
 use serde::{Deserialize, Serialize};
 
+use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::SystemTime;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tokio::sync::{broadcast, RwLock};
+use tokio_util::io::ReaderStream;
 use tracing::{debug, info, warn};
 
+/// Static files at least this large are streamed from disk
+pub const STATIC_STREAMING_THRESHOLD: u64 = 256 * 1024;
+
 /// Static file handler for serving files from the filesystem
 pub struct StaticHandler {
-    base_path: PathBuf,
+    jail: PathJail,
     path_pattern: String,
     allowed_extensions: Vec<String>,
 }
@@ -42,10 +61,13 @@ impl StaticHandler {
             "js".to_string(),
             "html".to_string(),
             "txt".to_string(),
+            "mp4".to_string(),
+            "webm".to_string(),
+            "mp3".to_string(),
         ];
 
         Self {
-            base_path,
+            jail: PathJail::new(&base_path, SymlinkPolicy::default()),
             path_pattern,
             allowed_extensions,
         }
@@ -65,12 +87,18 @@ impl StaticHandler {
         ];
 
         Self {
-            base_path,
+            jail: PathJail::new(&base_path, SymlinkPolicy::default()),
             path_pattern,
             allowed_extensions,
         }
     }
 
+    /// Treat symbolic links under the base path as `policy` says
+    pub fn with_symlink_policy(mut self, policy: SymlinkPolicy) -> Self {
+        self.jail = PathJail::new(self.jail.root(), policy);
+        self
+    }
+
     /// Check if the file extension is allowed
     fn is_allowed_extension(&self, path: &Path) -> bool {
         if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
@@ -95,6 +123,9 @@ impl StaticHandler {
                 "js" => "application/javascript",
                 "html" => "text/html; charset=utf-8",
                 "txt" => "text/plain; charset=utf-8",
+                "mp4" => "video/mp4",
+                "webm" => "video/webm",
+                "mp3" => "audio/mpeg",
                 _ => "application/octet-stream",
             }
         } else {
@@ -102,6 +133,92 @@ impl StaticHandler {
         }
         .to_string()
     }
+
+    /// Stream the bytes `start..=end` of a file as a partial response
+    async fn serve_range(
+        &self,
+        path: &Path,
+        content_type: &str,
+        start: u64,
+        end: u64,
+        size: u64,
+    ) -> Result<HttpResponse> {
+        let mut file = match tokio::fs::File::open(path).await {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Failed to open file {:?}: {}", path, e);
+                return Ok(HttpResponse::error(StatusCode::NOT_FOUND, "File not found"));
+            }
+        };
+        file.seek(SeekFrom::Start(start)).await?;
+
+        let length = end - start + 1;
+        debug!(
+            "Serving bytes {}-{} of static file: {:?} ({})",
+            start, end, path, content_type
+        );
+        let body = ReaderStream::new(file.take(length))
+            .map_err(|e| RuneError::Server(format!("Failed to read static file: {}", e)));
+        Ok(HttpResponse::new(StatusCode::PARTIAL_CONTENT)
+            .with_header("content-type", content_type)
+            .with_header("content-length", &length.to_string())
+            .with_header(
+                "content-range",
+                &format!("bytes {}-{}/{}", start, end, size),
+            )
+            .with_header("accept-ranges", "bytes")
+            .with_stream(body.boxed()))
+    }
+}
+
+/// Single byte range of a `Range` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// Inclusive range within the file
+    Bytes { start: u64, end: u64 },
+    /// Range starting past the end of the file
+    Unsatisfiable,
+}
+
+impl ByteRange {
+    /// Parse a `Range` header for a file of `size` bytes; malformed and
+    /// multi-range headers yield `None`, so the whole file is served
+    pub fn parse(header: &str, size: u64) -> Option<Self> {
+        let spec = header.trim().strip_prefix("bytes=")?;
+        if spec.contains(',') {
+            return None;
+        }
+        let (start, end) = spec.split_once('-')?;
+        let (start, end) = (start.trim(), end.trim());
+
+        if start.is_empty() {
+            // Suffix range: the last `end` bytes
+            let suffix: u64 = end.parse().ok()?;
+            if suffix == 0 || size == 0 {
+                return Some(Self::Unsatisfiable);
+            }
+            return Some(Self::Bytes {
+                start: size.saturating_sub(suffix),
+                end: size - 1,
+            });
+        }
+
+        let start: u64 = start.parse().ok()?;
+        let end = match end {
+            "" => None,
+            end => Some(end.parse::<u64>().ok()?),
+        };
+        if end.is_some_and(|end| end < start) {
+            return None;
+        }
+        if start >= size {
+            return Some(Self::Unsatisfiable);
+        }
+        Some(Self::Bytes {
+            start,
+            end: end.map_or(size - 1, |end| end.min(size - 1)),
+        })
+    }
 }
 
 #[async_trait]
@@ -126,49 +243,90 @@ impl HttpHandler for StaticHandler {
             return Ok(HttpResponse::error(StatusCode::NOT_FOUND, "File not found"));
         }
 
-        // Construct the full file path
-        let file_path = self.base_path.join(requested_path);
+        let canonical_path = match self.jail.resolve(requested_path) {
+            Ok(path) => path,
+            Err(Rejection::NotFound) => {
+                debug!("Static file not found: {}", requested_path);
+                return Ok(HttpResponse::error(StatusCode::NOT_FOUND, "File not found"));
+            }
+            Err(rejection) => {
+                jail::audit(&request.path, rejection);
+                return Ok(HttpResponse::error(StatusCode::FORBIDDEN, "Access denied"));
+            }
+        };
 
-        // Security check: ensure the resolved path is still within base_path
-        match file_path.canonicalize() {
-            Ok(canonical_path) => {
-                if !canonical_path.starts_with(&self.base_path) {
-                    warn!(
-                        "Access denied for path outside base directory: {:?}",
-                        canonical_path
+        // Check if file extension is allowed
+        if !self.is_allowed_extension(&canonical_path) {
+            return Ok(HttpResponse::error(
+                StatusCode::FORBIDDEN,
+                "File type not allowed",
+            ));
+        }
+
+        let content_type = self.guess_content_type(&canonical_path);
+        let size = fs::metadata(&canonical_path).map(|m| m.len()).unwrap_or(0);
+
+        // Media players seek with range requests
+        let range = request
+            .headers
+            .get("range")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| ByteRange::parse(value, size));
+        match range {
+            Some(ByteRange::Bytes { start, end }) => {
+                return self
+                    .serve_range(&canonical_path, &content_type, start, end, size)
+                    .await;
+            }
+            Some(ByteRange::Unsatisfiable) => {
+                return Ok(HttpResponse::error(
+                    StatusCode::RANGE_NOT_SATISFIABLE,
+                    "Range not satisfiable",
+                )
+                .with_header("content-range", &format!("bytes */{}", size)));
+            }
+            None => {}
+        }
+
+        // Large files are streamed from disk instead of read whole
+        if size >= STATIC_STREAMING_THRESHOLD {
+            return match tokio::fs::File::open(&canonical_path).await {
+                Ok(file) => {
+                    debug!(
+                        "Streaming static file: {:?} ({}, {} bytes)",
+                        canonical_path, content_type, size
                     );
-                    return Ok(HttpResponse::error(StatusCode::FORBIDDEN, "Access denied"));
+                    let body = ReaderStream::new(file).map_err(|e| {
+                        RuneError::Server(format!("Failed to read static file: {}", e))
+                    });
+                    Ok(HttpResponse::new(StatusCode::OK)
+                        .with_header("content-type", &content_type)
+                        .with_header("content-length", &size.to_string())
+                        .with_header("accept-ranges", "bytes")
+                        .with_stream(body.boxed()))
                 }
-
-                // Check if file extension is allowed
-                if !self.is_allowed_extension(&canonical_path) {
-                    return Ok(HttpResponse::error(
-                        StatusCode::FORBIDDEN,
-                        "File type not allowed",
-                    ));
+                Err(e) => {
+                    warn!("Failed to open file {:?}: {}", canonical_path, e);
+                    Ok(HttpResponse::error(StatusCode::NOT_FOUND, "File not found"))
                 }
+            };
+        }
 
-                // Try to read and serve the file
-                match fs::read(&canonical_path) {
-                    Ok(contents) => {
-                        let content_type = self.guess_content_type(&canonical_path);
-                        debug!(
-                            "Serving static file: {:?} ({})",
-                            canonical_path, content_type
-                        );
-
-                        Ok(HttpResponse::new(StatusCode::OK)
-                            .with_header("content-type", &content_type)
-                            .with_body(contents))
-                    }
-                    Err(e) => {
-                        warn!("Failed to read file {:?}: {}", canonical_path, e);
-                        Ok(HttpResponse::error(StatusCode::NOT_FOUND, "File not found"))
-                    }
-                }
+        // Try to read and serve the file
+        match fs::read(&canonical_path) {
+            Ok(contents) => {
+                debug!(
+                    "Serving static file: {:?} ({})",
+                    canonical_path, content_type
+                );
+
+                Ok(HttpResponse::new(StatusCode::OK)
+                    .with_header("content-type", &content_type)
+                    .with_header("accept-ranges", "bytes")
+                    .with_body(contents))
             }
             Err(e) => {
-                warn!("Failed to canonicalize path {:?}: {}", file_path, e);
+                warn!("Failed to read file {:?}: {}", canonical_path, e);
                 Ok(HttpResponse::error(StatusCode::NOT_FOUND, "File not found"))
             }
         }
@@ -226,6 +384,94 @@ impl HttpHandler for FaviconHandler {
     }
 }
 
+/// HTML template used for rendered markdown pages
+pub(crate) const PAGE_TEMPLATE: &str = include_str!("../../../template.html");
+
+/// Build a static page around `body`, reusing the preview template's head and styles
+///
+/// Unlike the live preview, the page carries no editor, WebSocket or live
+/// reload scripts, so it can be viewed without a running server.
+pub fn standalone_page(title: &str, body: &str) -> String {
+    let head_end = PAGE_TEMPLATE.find("<!-- {MERMAID_ASSETS} -->").unwrap_or(0);
+    let head = i18n::localize_template(
+        &csp::strip_nonces(&PAGE_TEMPLATE[..head_end]),
+        &i18n::catalog(),
+    )
+    .replace(
+        "<title>Markdown Preview</title>",
+        &format!("<title>{}</title>", html_escape::encode_text(title)),
+    );
+
+    format!(
+        "{}\n</head>\n<body>\n<div class=\"preview-mode active\" id=\"preview-mode\">\n{}\n</div>\n</body>\n</html>\n",
+        head, body
+    )
+}
+
+/// Script rendering Mermaid diagrams
+fn mermaid_asset() -> Asset {
+    Asset {
+        asset_type: AssetType::JavaScript,
+        url: "/mermaid.min.js".to_string(),
+        is_critical: true,
+        integrity: None,
+        nonce: Some(NONCE_PLACEHOLDER.to_string()),
+    }
+}
+
+/// Elements loading `assets` into a page, with the Mermaid script added when
+/// `html` has diagrams no renderer asked for it for
+fn asset_tags(html: &str, assets: &[Asset]) -> String {
+    let mermaid = has_mermaid(html).then(mermaid_asset);
+    let mut urls = std::collections::HashSet::new();
+    assets
+        .iter()
+        .chain(mermaid.as_ref())
+        .filter(|asset| urls.insert(asset.url.as_str()))
+        .filter_map(Asset::to_html)
+        .collect::<Vec<_>>()
+        .join("\n    ")
+}
+
+/// Check whether rendered HTML contains Mermaid diagrams
+pub fn has_mermaid(html: &str) -> bool {
+    html.contains(r#"class="language-mermaid""#) || html.contains(r#"<div class="mermaid""#)
+}
+
+/// Script tags that render Mermaid diagrams using the library at `src`
+pub fn mermaid_scripts(src: &str) -> String {
+    format!(
+        r#"
+<script src="{}"></script>
+<script>
+document.querySelectorAll('code.language-mermaid').forEach(function (code) {{
+    var diagram = document.createElement('div');
+    diagram.className = 'mermaid';
+    diagram.textContent = code.textContent;
+    code.parentElement.replaceWith(diagram);
+}});
+mermaid.initialize({{ startOnLoad: false }});
+mermaid.run();
+</script>"#,
+        src
+    )
+}
+
+/// Documents at least this large are streamed to the browser while they render
+pub const DEFAULT_STREAMING_THRESHOLD: u64 = 1024 * 1024;
+
+/// Tells readers what the preview of a large document leaves out
+const LARGE_DOCUMENT_BANNER: &str = "<div class=\"rune-large-document-banner\" role=\"note\">This document is large, so its preview leaves out diagrams, link previews, image sizes and accessibility checks.</div>\n";
+
+/// Stands in for the content while the page template is split around it
+const STREAM_MARKER: &str = "<!-- rune:stream -->";
+
+/// Renders a reconnecting page may still show and be patched from
+const RESUMABLE_RENDERS: usize = 16;
+
+/// Live reload handler's view of the document it serves
+pub type DocumentSlot = Arc<RwLock<Option<Arc<MarkdownHandler>>>>;
+
 /// Markdown handler for serving rendered markdown content with live reload
 pub struct MarkdownHandler {
     path_pattern: String,
@@ -233,23 +479,36 @@ pub struct MarkdownHandler {
     base_dir: PathBuf,
     renderer_registry: Option<Arc<RendererRegistry>>,
     cached_state: Arc<RwLock<CachedMarkdownState>>,
-    template: String,
+    route_base: Option<String>,
+    streaming_threshold: Option<u64>,
+    large_file_threshold: Option<u64>,
 }
 
 /// Cached state for markdown rendering
 #[derive(Debug, Clone)]
 struct CachedMarkdownState {
     last_modified: SystemTime,
-    cached_html: String,
+    /// Shared with responses, so serving the page does not copy it
+    cached_html: Bytes,
     content_hash: String,
+    /// Publish time of a document rendered while still scheduled; the
+    /// render goes stale once it passes
+    publish_at: Option<SystemTime>,
+    /// Generation of the page template the render was built with
+    template_generation: u64,
+    /// Hashes of recent renders and their template generations, newest last
+    recent: VecDeque<(String, u64)>,
 }
 
 impl CachedMarkdownState {
     fn new() -> Self {
         Self {
             last_modified: SystemTime::UNIX_EPOCH,
-            cached_html: String::new(),
+            cached_html: Bytes::new(),
             content_hash: String::new(),
+            publish_at: None,
+            template_generation: 0,
+            recent: VecDeque::new(),
         }
     }
 }
@@ -269,16 +528,15 @@ impl MarkdownHandler {
                     .to_path_buf()
             });
 
-        // Use the template from mdserve
-        let template = include_str!("../../../template.html").to_string();
-
         Self {
             path_pattern,
             markdown_file,
             base_dir,
             renderer_registry: None,
             cached_state: Arc::new(RwLock::new(CachedMarkdownState::new())),
-            template,
+            route_base: None,
+            streaming_threshold: Some(DEFAULT_STREAMING_THRESHOLD),
+            large_file_threshold: Some(LargeFileConfig::default().threshold_bytes()),
         }
     }
 
@@ -293,6 +551,61 @@ impl MarkdownHandler {
         handler
     }
 
+    /// Serve the page below `base`, so its raw markdown, live reload and
+    /// editor endpoints are looked up at `{base}/raw`, `{base}/ws` and
+    /// `{base}/ws/editor`
+    pub fn with_route_base(mut self, base: String) -> Self {
+        self.route_base = Some(base);
+        self
+    }
+
+    /// Stream pages of documents of at least `threshold` bytes instead of
+    /// rendering them in one go; `None` never streams
+    pub fn with_streaming_threshold(mut self, threshold: Option<u64>) -> Self {
+        self.streaming_threshold = threshold;
+        self
+    }
+
+    /// Preview documents of at least `threshold` bytes without the expensive
+    /// renderers; `None` renders every document fully
+    pub fn with_large_file_threshold(mut self, threshold: Option<u64>) -> Self {
+        self.large_file_threshold = threshold;
+        self
+    }
+
+    /// Context to render a document of `size` bytes with
+    fn render_context(&self, size: usize) -> RenderContext {
+        let context = RenderContext::new(
+            self.markdown_file.clone(),
+            self.base_dir.clone(),
+            "catppuccin-mocha".to_string(), // Default theme - will be overridden by theme-aware renderer
+        );
+        match self.large_file_threshold {
+            Some(threshold) if size as u64 >= threshold => context.with_large_document(),
+            _ => context,
+        }
+    }
+
+    /// Fill the page template with rendered content
+    fn apply_template(&self, body: &str, assets: &str) -> String {
+        let body = crate::vendor::localize_cdn_references(&csp::escape_nonce_placeholders(body));
+        let page = crate::template::page()
+            .replace("{CONTENT}", &body)
+            .replace("<!-- {MERMAID_ASSETS} -->", assets);
+
+        match &self.route_base {
+            Some(base) => page.replacen(
+                "<head>",
+                &format!(
+                    "<head>\n    <meta name=\"rune-base\" content=\"{}\">",
+                    html_escape::encode_double_quoted_attribute(base)
+                ),
+                1,
+            ),
+            None => page,
+        }
+    }
+
     /// Check if the markdown file needs to be refreshed
     async fn refresh_if_needed(&self) -> Result<bool> {
         let metadata = fs::metadata(&self.markdown_file)
@@ -304,16 +617,34 @@ impl MarkdownHandler {
 
         let mut state = self.cached_state.write().await;
 
-        if current_modified > state.last_modified {
-            let content = fs::read_to_string(&self.markdown_file)
+        let published = state
+            .publish_at
+            .is_some_and(|publish_at| publish_at <= SystemTime::now());
+        let template_generation = crate::template::generation();
+        if current_modified > state.last_modified
+            || published
+            || state.template_generation != template_generation
+        {
+            let content = crypto::read_document(&self.markdown_file)
                 .map_err(|e| RuneError::Server(format!("Failed to read markdown file: {}", e)))?;
 
             let rendered_html = self.render_markdown(&content).await?;
-            let content_hash = format!("{:x}", content.len() as u64);
+            let mut hasher = DefaultHasher::new();
+            rendered_html.hash(&mut hasher);
+            let content_hash = format!("{:016x}", hasher.finish());
 
             state.last_modified = current_modified;
-            state.cached_html = rendered_html;
-            state.content_hash = content_hash;
+            state.cached_html = Bytes::from(rendered_html);
+            state.content_hash = content_hash.clone();
+            state.template_generation = template_generation;
+            if state.recent.len() == RESUMABLE_RENDERS {
+                state.recent.pop_front();
+            }
+            state.recent.push_back((content_hash, template_generation));
+            state.publish_at = match PublishState::of(&content, SystemTime::now()) {
+                PublishState::Scheduled(publish_at) => Some(publish_at),
+                _ => None,
+            };
 
             debug!("Refreshed markdown content: {:?}", self.markdown_file);
             Ok(true)
@@ -322,42 +653,142 @@ impl MarkdownHandler {
         }
     }
 
+    /// Drop the cached render so the next request renders the file again
+    pub async fn clear_cache(&self) {
+        let mut state = self.cached_state.write().await;
+        let recent = std::mem::take(&mut state.recent);
+        *state = CachedMarkdownState::new();
+        state.recent = recent;
+    }
+
+    /// Bytes held by the cached render
+    pub async fn cached_bytes(&self) -> usize {
+        self.cached_state.read().await.cached_html.len()
+    }
+
     /// Render markdown content to HTML using the renderer plugin
     async fn render_markdown(&self, content: &str) -> Result<String> {
         if let Some(registry) = &self.renderer_registry {
             // Create render context with theme support
-            let context = RenderContext::new(
-                self.markdown_file.clone(),
-                self.base_dir.clone(),
-                "catppuccin-mocha".to_string(), // Default theme - will be overridden by theme-aware renderer
-            );
+            let context = self.render_context(content.len());
 
             // Use the pipeline renderer to apply all transformations including theme
-            let result = registry.render_with_pipeline(content, &context).await?;
-
-            // Check if we have mermaid diagrams
-            let has_mermaid = result.html.contains(r#"class="language-mermaid""#)
-                || result.html.contains(r#"<div class="mermaid""#);
-
-            let mermaid_assets = if has_mermaid {
-                r#"<script src="/mermaid.min.js"></script>"#
-            } else {
-                ""
-            };
+            let (html, assets) = self.render_pipeline(registry, content, context).await?;
 
             // Apply template
-            let final_html = self
-                .template
-                .replace("{CONTENT}", &result.html)
-                .replace("<!-- {MERMAID_ASSETS} -->", mermaid_assets);
-
-            Ok(final_html)
+            Ok(self.apply_template(&html, &asset_tags(&html, &assets)))
         } else {
             // Fallback to simple markdown rendering
             self.render_markdown_fallback(content)
         }
     }
 
+    /// Render through the pipeline; documents above the streaming threshold
+    /// are rendered block by block, which is much faster for them than one
+    /// pass over the whole text. Returns the HTML and the assets it needs,
+    /// which are not collected from streamed renders. Large documents start
+    /// with a banner telling what their preview leaves out.
+    async fn render_pipeline(
+        &self,
+        registry: &Arc<RendererRegistry>,
+        content: &str,
+        context: RenderContext,
+    ) -> Result<(String, Vec<Asset>)> {
+        let banner = if context.large_document {
+            LARGE_DOCUMENT_BANNER
+        } else {
+            ""
+        };
+        let (html, assets) = match self.streaming_threshold {
+            Some(threshold) if content.len() as u64 >= threshold => {
+                let chunks: Vec<String> = registry
+                    .clone()
+                    .render_stream(content.to_string(), context, DEFAULT_STREAM_CHUNK_BYTES)
+                    .try_collect()
+                    .await?;
+                (chunks.concat(), Vec::new())
+            }
+            _ => {
+                let result = registry.render_with_pipeline(content, &context).await?;
+                (result.html, result.assets)
+            }
+        };
+        Ok((format!("{}{}", banner, html), assets))
+    }
+
+    /// Page of a document above the streaming threshold, sent with chunked
+    /// transfer encoding: the template head goes out at once and the content
+    /// follows block by block as it renders, so the browser shows the start
+    /// of the document while the rest is still on its way. `None` when the
+    /// document is small enough to render in one go.
+    async fn streamed_page(&self, reading: &ReadingPreferences) -> Result<Option<HttpResponse>> {
+        let (Some(threshold), Some(registry)) = (self.streaming_threshold, &self.renderer_registry)
+        else {
+            return Ok(None);
+        };
+        let size = tokio::fs::metadata(&self.markdown_file)
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        if size < threshold {
+            return Ok(None);
+        }
+
+        let content = crypto::read_document(&self.markdown_file)
+            .map_err(|e| RuneError::Server(format!("Failed to read markdown file: {}", e)))?;
+        let context = self.render_context(content.len());
+        // Whether diagrams follow is only known once everything rendered, so
+        // look for Mermaid fences in the source instead
+        let assets = if context.large_document {
+            String::new()
+        } else if content.contains("```mermaid") || content.contains("~~~mermaid") {
+            asset_tags("", &[mermaid_asset()])
+        } else {
+            String::new()
+        };
+        let nonce = csp::new_nonce();
+        let page = csp::fill_nonces(
+            &reading.apply(&self.apply_template(STREAM_MARKER, &assets)),
+            &nonce,
+        );
+        let (head, tail) = page.split_once(STREAM_MARKER).unwrap_or((&page, ""));
+        let mut head = head.to_string();
+        let tail = tail.to_string();
+        if context.large_document {
+            head.push_str(LARGE_DOCUMENT_BANNER);
+        }
+
+        let file = self.markdown_file.clone();
+        let chunks = registry
+            .clone()
+            .render_stream(content, context, DEFAULT_STREAM_CHUNK_BYTES)
+            .map(move |chunk| {
+                // The status line is already sent, so report failures in the page
+                let html = chunk.unwrap_or_else(|e| {
+                    warn!("Failed to render part of {:?}: {}", file, e);
+                    format!(
+                        "<div class=\"rune-render-error\">Failed to render part of this document: {}</div>",
+                        html_escape::encode_text(&e.to_string())
+                    )
+                });
+                Ok(Bytes::from(html))
+            });
+        let body = stream::once(future::ready(Ok(Bytes::from(head))))
+            .chain(chunks)
+            .chain(stream::once(future::ready(Ok(Bytes::from(tail)))));
+
+        info!(
+            "Streaming render of {:?} ({} bytes)",
+            self.markdown_file, size
+        );
+        Ok(Some(
+            HttpResponse::new(StatusCode::OK)
+                .with_header("content-type", "text/html; charset=utf-8")
+                .with_stream(body.boxed())
+                .with_nonce(nonce),
+        ))
+    }
+
     /// Fallback markdown rendering without renderer plugin
     fn render_markdown_fallback(&self, content: &str) -> Result<String> {
         // Create GFM options with HTML rendering enabled
@@ -367,21 +798,7 @@ impl MarkdownHandler {
         let html_body = markdown::to_html_with_options(content, &options)
             .map_err(|e| RuneError::Server(format!("Markdown parsing failed: {}", e)))?;
 
-        // Check if the HTML contains mermaid code blocks
-        let has_mermaid = html_body.contains(r#"class="language-mermaid""#);
-
-        let mermaid_assets = if has_mermaid {
-            r#"<script src="/mermaid.min.js"></script>"#
-        } else {
-            ""
-        };
-
-        let final_html = self
-            .template
-            .replace("{CONTENT}", &html_body)
-            .replace("<!-- {MERMAID_ASSETS} -->", mermaid_assets);
-
-        Ok(final_html)
+        Ok(self.apply_template(&html_body, &asset_tags(&html_body, &[])))
     }
 
     /// Get the base directory for resolving relative paths
@@ -403,18 +820,7 @@ impl MarkdownHandler {
         let needs_refresh = self.refresh_if_needed().await?;
 
         if needs_refresh {
-            let state = self.cached_state.read().await;
-
-            // Extract just the content part (without full HTML template)
-            let content_html = self.extract_content_only().await?;
-
-            // Create metadata
-            let metadata = ContentMetadata {
-                title: self.extract_title_from_content(&content_html),
-                last_modified: Some(state.last_modified),
-                file_path: Some(self.markdown_file.to_string_lossy().to_string()),
-                word_count: Some(self.count_words(&content_html)),
-            };
+            let (content_html, metadata) = self.content_update().await?;
 
             // Push content update via WebSocket
             websocket_handler
@@ -430,20 +836,64 @@ impl MarkdownHandler {
         Ok(())
     }
 
+    /// Content of the current render, without the page template, and its
+    /// metadata
+    async fn content_update(&self) -> Result<(String, ContentMetadata)> {
+        let state = self.cached_state.read().await;
+
+        // Extract just the content part (without full HTML template)
+        let content_html = self.extract_content_only().await?;
+
+        // Create metadata
+        let metadata = ContentMetadata {
+            title: self.extract_title_from_content(&content_html),
+            last_modified: Some(state.last_modified),
+            file_path: Some(self.markdown_file.to_string_lossy().to_string()),
+            word_count: Some(self.count_words(&content_html)),
+            content_hash: Some(state.content_hash.clone()),
+        };
+        Ok((content_html, metadata))
+    }
+
+    /// Answer to a page reconnecting with the hash of the render it shows:
+    /// nothing to do, the current content to patch it with if it shows a
+    /// recent render of the same template, or else loading the page again
+    pub async fn resume(&self, hash: &str) -> Result<ServerMessage> {
+        self.refresh_if_needed().await?;
+        let patchable = {
+            let state = self.cached_state.read().await;
+            if state.content_hash == hash {
+                return Ok(ServerMessage::UpToDate);
+            }
+            state.recent.iter().any(|(recent, generation)| {
+                recent == hash && *generation == state.template_generation
+            })
+        };
+        if !patchable {
+            return Ok(ServerMessage::Refresh);
+        }
+
+        let (html, metadata) = self.content_update().await?;
+        Ok(ServerMessage::ContentUpdate {
+            html,
+            css: None,
+            metadata: Some(metadata),
+        })
+    }
+
+    /// Hash of the content last rendered by this handler
+    pub async fn content_hash(&self) -> String {
+        self.cached_state.read().await.content_hash.clone()
+    }
+
     /// Extract only the content part without the full HTML template
-    async fn extract_content_only(&self) -> Result<String> {
-        let content = fs::read_to_string(&self.markdown_file)
+    pub(crate) async fn extract_content_only(&self) -> Result<String> {
+        let content = crypto::read_document(&self.markdown_file)
             .map_err(|e| RuneError::Server(format!("Failed to read markdown file: {}", e)))?;
 
         if let Some(registry) = &self.renderer_registry {
-            let context = RenderContext::new(
-                self.markdown_file.clone(),
-                self.base_dir.clone(),
-                "catppuccin-mocha".to_string(),
-            );
-
-            let result = registry.render_with_pipeline(&content, &context).await?;
-            Ok(result.html)
+            let context = self.render_context(content.len());
+            Ok(self.render_pipeline(registry, &content, context).await?.0)
         } else {
             // Fallback rendering
             let mut options = markdown::Options::gfm();
@@ -457,7 +907,7 @@ impl MarkdownHandler {
     }
 
     /// Extract title from HTML content
-    fn extract_title_from_content(&self, html: &str) -> Option<String> {
+    pub(crate) fn extract_title_from_content(&self, html: &str) -> Option<String> {
         // Simple regex to extract first h1 tag
         if let Some(start) = html.find("<h1") {
             if let Some(content_start) = html[start..].find('>') {
@@ -499,7 +949,12 @@ impl HttpHandler for MarkdownHandler {
         Method::GET
     }
 
-    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let reading = ReadingPreferences::from_headers(&request.headers);
+        if let Some(response) = self.streamed_page(&reading).await? {
+            return Ok(response);
+        }
+
         // Refresh content if needed
         if let Err(e) = self.refresh_if_needed().await {
             warn!("Failed to refresh markdown content: {}", e);
@@ -518,8 +973,23 @@ impl HttpHandler for MarkdownHandler {
             ));
         }
 
+        // Every response gets its own nonce, so one cannot be learned in advance
+        let page = std::str::from_utf8(&state.cached_html)
+            .map_err(|e| RuneError::Server(format!("Rendered page is not UTF-8: {}", e)))?;
+        let nonce = csp::new_nonce();
+
         debug!("Serving markdown file: {:?}", self.markdown_file);
-        Ok(HttpResponse::html(&state.cached_html))
+        // The hash lets the page ask what it missed when it reconnects
+        let page = page.replacen(
+            "<head>",
+            &format!(
+                "<head>\n    <meta name=\"rune-content-hash\" content=\"{}\">",
+                state.content_hash
+            ),
+            1,
+        );
+        let page = csp::fill_nonces(&reading.apply(&page), &nonce);
+        Ok(HttpResponse::html(page).with_nonce(nonce))
     }
 
     fn priority(&self) -> i32 {
@@ -558,10 +1028,10 @@ impl HttpHandler for RawMarkdownHandler {
     }
 
     async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
-        match fs::read_to_string(&self.markdown_file) {
+        match crypto::read_document(&self.markdown_file) {
             Ok(content) => {
                 debug!("Serving raw markdown file: {:?}", self.markdown_file);
-                Ok(HttpResponse::text(&content))
+                Ok(HttpResponse::text(content))
             }
             Err(e) => {
                 warn!(
@@ -585,6 +1055,15 @@ impl HttpHandler for RawMarkdownHandler {
     }
 }
 
+/// Names of the built-in themes and what they are shown as
+pub(crate) const THEMES: &[(&str, &str)] = &[
+    ("light", "Light"),
+    ("dark", "Dark"),
+    ("catppuccin-latte", "Catppuccin Latte"),
+    ("catppuccin-macchiato", "Catppuccin Macchiato"),
+    ("catppuccin-mocha", "Catppuccin Mocha"),
+];
+
 /// Theme API handler for theme management operations
 pub struct ThemeApiHandler {
     path_pattern: String,
@@ -603,10 +1082,10 @@ impl ThemeApiHandler {
     /// Handle theme switching via POST request
     async fn handle_theme_switch_post(&self, request: &HttpRequest) -> Result<HttpResponse> {
         // Parse JSON body to get theme name
-        let body_str = String::from_utf8(request.body.clone())
+        let body_str = std::str::from_utf8(&request.body)
             .map_err(|e| RuneError::Server(format!("Invalid UTF-8 in request body: {}", e)))?;
 
-        let theme_request: serde_json::Value = serde_json::from_str(&body_str)
+        let theme_request: serde_json::Value = serde_json::from_str(body_str)
             .map_err(|e| RuneError::Server(format!("Invalid JSON in request body: {}", e)))?;
 
         let theme_name = theme_request
@@ -615,13 +1094,7 @@ impl ThemeApiHandler {
             .ok_or_else(|| RuneError::Server("Missing 'theme' field in request".to_string()))?;
 
         // Validate theme name
-        let valid_themes = vec![
-            "light",
-            "dark",
-            "catppuccin-latte",
-            "catppuccin-macchiato",
-            "catppuccin-mocha",
-        ];
+        let valid_themes: Vec<&str> = THEMES.iter().map(|&(name, _)| name).collect();
         if !valid_themes.contains(&theme_name) {
             return Ok(HttpResponse::error(
                 StatusCode::BAD_REQUEST,
@@ -674,6 +1147,10 @@ impl HttpHandler for ThemeApiHandler {
         path == self.path_pattern && *method == Method::POST
     }
 
+    fn edits(&self) -> bool {
+        true // Switches the theme for every client
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -983,8 +1460,19 @@ impl HttpHandler for ThemeAssetHandler {
                 "catppuccin-macchiato",
                 "catppuccin-mocha",
             ];
+            // Versioned stylesheet URLs, which browsers may keep for good
+            let mut stylesheets = serde_json::Map::new();
+            for theme in &themes {
+                let path = format!("{}/{}/css", self.path_pattern, theme);
+                let css = self.generate_theme_css(theme)?;
+                stylesheets.insert(
+                    theme.to_string(),
+                    rune_theme::assets::versioned_url(&path, css.as_bytes()).into(),
+                );
+            }
             return HttpResponse::json(&serde_json::json!({
-                "available_themes": themes
+                "available_themes": themes,
+                "stylesheets": stylesheets
             }));
         }
 
@@ -996,10 +1484,9 @@ impl HttpHandler for ThemeAssetHandler {
                 // Serve theme CSS
                 let css = self.generate_theme_css(theme_name)?;
                 debug!("Serving CSS for theme: {}", theme_name);
-                Ok(HttpResponse::new(StatusCode::OK)
-                    .with_header("content-type", "text/css")
-                    .with_header("cache-control", "public, max-age=3600")
-                    .with_body(css.as_bytes()))
+                Ok(crate::template::versioned_response(
+                    &request, "text/css", css,
+                ))
             }
             [theme_name, "metadata"] => {
                 // Serve theme metadata
@@ -1008,7 +1495,7 @@ impl HttpHandler for ThemeAssetHandler {
                 Ok(HttpResponse::new(StatusCode::OK)
                     .with_header("content-type", "application/json")
                     .with_header("cache-control", "public, max-age=3600")
-                    .with_body(metadata.as_bytes()))
+                    .with_body(metadata))
             }
             [theme_name, "switch"] => {
                 // Handle theme switching
@@ -1018,10 +1505,9 @@ impl HttpHandler for ThemeAssetHandler {
                 // Default to serving CSS for the theme
                 let css = self.generate_theme_css(theme_name)?;
                 debug!("Serving default CSS for theme: {}", theme_name);
-                Ok(HttpResponse::new(StatusCode::OK)
-                    .with_header("content-type", "text/css")
-                    .with_header("cache-control", "public, max-age=3600")
-                    .with_body(css.as_bytes()))
+                Ok(crate::template::versioned_response(
+                    &request, "text/css", css,
+                ))
             }
             _ => Ok(HttpResponse::error(
                 StatusCode::NOT_FOUND,
@@ -1043,10 +1529,14 @@ impl HttpHandler for ThemeAssetHandler {
     }
 }
 
+/// Bundled Mermaid.js library
+pub fn mermaid_js() -> Cow<'static, [u8]> {
+    crate::vendor::file("mermaid.min.js")
+}
+
 /// Mermaid.js handler for serving the Mermaid JavaScript library
 pub struct MermaidHandler {
     path_pattern: String,
-    mermaid_js: &'static str,
     etag: &'static str,
 }
 
@@ -1055,7 +1545,6 @@ impl MermaidHandler {
     pub fn new(path_pattern: String) -> Self {
         Self {
             path_pattern,
-            mermaid_js: include_str!("../../../mermaid.min.js"),
             etag: concat!("\"", env!("CARGO_PKG_VERSION"), "\""),
         }
     }
@@ -1095,7 +1584,7 @@ impl HttpHandler for MermaidHandler {
             .with_header("content-type", "application/javascript")
             .with_header("etag", self.etag)
             .with_header("cache-control", "public, no-cache")
-            .with_body(self.mermaid_js.as_bytes()))
+            .with_body(crate::vendor::body(mermaid_js())))
     }
 
     fn priority(&self) -> i32 {
@@ -1107,18 +1596,176 @@ impl HttpHandler for MermaidHandler {
     }
 }
 
-/// Client message types for WebSocket communication
+/// Share page handler rendering a QR code of the externally reachable preview URL
+pub struct ShareHandler {
+    path_pattern: String,
+    hostname: String,
+    port: u16,
+}
+
+impl ShareHandler {
+    /// Create a new share handler for a server bound to `hostname:port`
+    pub fn new(path_pattern: String, hostname: String, port: u16) -> Self {
+        Self {
+            path_pattern,
+            hostname,
+            port,
+        }
+    }
+
+    /// Collect candidate URLs, preferring the address the request arrived on
+    fn candidate_urls(&self, request: &HttpRequest) -> Vec<String> {
+        // A tunnel's URL works from anywhere
+        let mut urls: Vec<String> = crate::tunnel::public_url().into_iter().collect();
+
+        if let Some(host) = request
+            .headers
+            .get("host")
+            .and_then(|value| value.to_str().ok())
+        {
+            // `192.168.1.20:3000`, `[fe80::1]:3000`, or a bare IPv6 address
+            let ip = host.parse::<std::net::IpAddr>().ok().or_else(|| {
+                let (name, _) = crate::config_api::authority(host)?;
+                name.trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .parse()
+                    .ok()
+            });
+            if let Some(ip) = ip.filter(|ip| !ip.is_loopback() && !ip.is_unspecified()) {
+                urls.push(crate::discovery::url_for(ip, self.port));
+            }
+        }
+
+        for url in crate::discovery::reachable_urls(&self.hostname, self.port) {
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+
+        urls
+    }
+
+    /// Render a URL as an inline SVG QR code
+    fn render_qr_svg(url: &str) -> Result<String> {
+        let code = qrcode::QrCode::new(url.as_bytes())
+            .map_err(|e| RuneError::Server(format!("Failed to encode QR code: {}", e)))?;
+
+        Ok(code
+            .render::<qrcode::render::svg::Color>()
+            .min_dimensions(240, 240)
+            .quiet_zone(true)
+            .build())
+    }
+
+    /// Build the share page for the given URLs
+    fn render_page(&self, urls: &[String]) -> Result<String> {
+        let body = match urls.first() {
+            Some(primary) => {
+                let qr = Self::render_qr_svg(primary)?;
+                let alternatives: String = urls
+                    .iter()
+                    .skip(1)
+                    .map(|url| {
+                        let escaped = html_escape::encode_text(url);
+                        format!("<li><a href=\"{0}\">{0}</a></li>", escaped)
+                    })
+                    .collect();
+                let alternatives = if alternatives.is_empty() {
+                    String::new()
+                } else {
+                    format!("<p>Other addresses:</p><ul>{}</ul>", alternatives)
+                };
+
+                format!(
+                    "<div class=\"qr\">{}</div><p><a href=\"{url}\">{url}</a></p>{}",
+                    qr,
+                    alternatives,
+                    url = html_escape::encode_text(primary)
+                )
+            }
+            None => format!(
+                "<p>The preview is only reachable from this machine because the server is \
+                bound to <code>{}</code>.</p><p>Restart Rune with <code>-H 0.0.0.0</code> \
+                to open it from your phone.</p>",
+                html_escape::encode_text(&self.hostname)
+            ),
+        };
+
+        Ok(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Share preview - Rune</title>
+<style>
+body {{ font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif; text-align: center; padding: 2rem; color: #333; }}
+.qr svg {{ width: 280px; height: 280px; }}
+ul {{ list-style: none; padding: 0; }}
+a {{ color: #0366d6; word-break: break-all; }}
+</style>
+</head>
+<body>
+<h1>Open this preview on another device</h1>
+{}
+</body>
+</html>"#,
+            body
+        ))
+    }
+}
+
+#[async_trait]
+impl HttpHandler for ShareHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let urls = self.candidate_urls(&request);
+        debug!("Serving share page with {} reachable URL(s)", urls.len());
+
+        Ok(HttpResponse::html(self.render_page(&urls)?).with_header("cache-control", "no-store"))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for specific page
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Client message types for WebSocket communication; see
+/// [`crate::protocol`] for the wire format
 #[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(tag = "type")]
+#[serde(tag = "type", content = "payload")]
 pub enum ClientMessage {
     Ping,
     RequestRefresh,
+    /// Protocol version the client speaks
+    Hello {
+        version: u32,
+    },
+    /// Reconnected showing the render with this content hash
+    Resume {
+        hash: String,
+    },
 }
 
-/// Server message types for WebSocket communication
+/// Server message types for WebSocket communication; on the wire each is
+/// wrapped in a [`crate::protocol::Envelope`]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-#[serde(tag = "type")]
+#[serde(tag = "type", content = "payload")]
 pub enum ServerMessage {
+    /// Protocol version the server speaks, sent on connecting and in answer
+    /// to `Hello`
+    Welcome { version: u32 },
     /// Traditional reload message (fallback)
     Reload,
     /// Direct content update with rendered HTML
@@ -1138,6 +1785,34 @@ pub enum ServerMessage {
         message: String,
         code: Option<String>,
     },
+    /// Rendered block an author's cursor is in, by index among the
+    /// top-level content elements
+    Presence {
+        author: usize,
+        block: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        selection: Option<BlockRange>,
+    },
+    /// An author closed their editor
+    PresenceLeft { author: usize },
+    /// The theme stylesheet at `href` changed; only styles need loading again
+    StyleReload { href: String },
+    /// The page template changed; the whole page needs loading again
+    TemplateReload,
+    /// Answer to `Resume`: the page shows the current render
+    UpToDate,
+    /// Answer to `Resume`: the page is too far behind to patch and needs
+    /// loading again
+    Refresh,
+    /// Message for the user, shown as a toast
+    Notification { notification: Notification },
+}
+
+/// Inclusive range of top-level content elements
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct BlockRange {
+    pub start: usize,
+    pub end: usize,
 }
 
 /// Metadata about the content
@@ -1147,6 +1822,9 @@ pub struct ContentMetadata {
     pub last_modified: Option<SystemTime>,
     pub file_path: Option<String>,
     pub word_count: Option<usize>,
+    /// Hash of the render, which the page sends back to resume
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
 }
 
 /// Individual element update for incremental updates
@@ -1174,6 +1852,8 @@ pub enum UpdateType {
 pub struct LiveReloadHandler {
     path: String,
     reload_sender: Arc<RwLock<Option<broadcast::Sender<ServerMessage>>>>,
+    /// Document whose pages connect, for answering `Resume`
+    document: DocumentSlot,
 }
 
 impl LiveReloadHandler {
@@ -1182,6 +1862,7 @@ impl LiveReloadHandler {
         Self {
             path,
             reload_sender: Arc::new(RwLock::new(None)),
+            document: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -1190,9 +1871,22 @@ impl LiveReloadHandler {
         Self {
             path,
             reload_sender: Arc::new(RwLock::new(Some(sender))),
+            document: Arc::new(RwLock::new(None)),
         }
     }
 
+    /// Look up the served document in `document`, which its owner keeps
+    /// up to date
+    pub fn with_document(mut self, document: DocumentSlot) -> Self {
+        self.document = document;
+        self
+    }
+
+    /// Set the document whose pages connect
+    pub async fn set_document(&self, document: Arc<MarkdownHandler>) {
+        *self.document.write().await = Some(document);
+    }
+
     /// Set the reload sender for broadcasting reload messages
     pub async fn set_reload_sender(&self, sender: broadcast::Sender<ServerMessage>) {
         let mut reload_sender = self.reload_sender.write().await;
@@ -1308,15 +2002,19 @@ impl WebSocketHandler for LiveReloadHandler {
             connection.id, connection.remote_addr
         );
 
-        // Subscribe this connection to the shared reload sender
+        // Subscribe this connection to the shared reload sender, and to the
+        // template and stylesheet reloads sent to every page
+        let mut receivers = vec![crate::template::subscribe()];
         if let Some(reload_sender) = self.get_reload_sender().await {
-            let mut rx = reload_sender.subscribe();
+            receivers.push(reload_sender.subscribe());
+        }
+        for mut rx in receivers {
             let conn_sender = connection.sender.clone();
 
             tokio::spawn(async move {
                 while let Ok(msg) = rx.recv().await {
                     // Convert ServerMessage to WebSocketMessage
-                    if let Ok(text) = serde_json::to_string(&msg) {
+                    if let Ok(text) = serde_json::to_string(&Envelope::new(msg)) {
                         if conn_sender.send(WebSocketMessage::Text(text)).is_err() {
                             // Connection closed
                             break;
@@ -1328,9 +2026,8 @@ impl WebSocketHandler for LiveReloadHandler {
 
         // Send a welcome message
         connection
-            .send_json(&serde_json::json!({
-                "type": "welcome",
-                "message": "Connected to live reload server"
+            .send_json(&Envelope::new(ServerMessage::Welcome {
+                version: PROTOCOL_VERSION,
             }))
             .await?;
 
@@ -1353,7 +2050,44 @@ impl WebSocketHandler for LiveReloadHandler {
                 if let Ok(client_msg) = serde_json::from_str::<ClientMessage>(&text) {
                     match client_msg {
                         ClientMessage::Ping => {
-                            connection.send_json(&ServerMessage::Pong).await?;
+                            connection
+                                .send_json(&Envelope::new(ServerMessage::Pong))
+                                .await?;
+                        }
+                        ClientMessage::Resume { hash } => {
+                            let document = self.document.read().await.clone();
+                            let answer = match document {
+                                Some(document) => {
+                                    document.resume(&hash).await.unwrap_or_else(|e| {
+                                        warn!("Failed to resume client {}: {}", connection.id, e);
+                                        ServerMessage::Refresh
+                                    })
+                                }
+                                // Without the render cache, the page fetches itself again
+                                None => ServerMessage::Reload,
+                            };
+                            connection.send_json(&Envelope::new(answer)).await?;
+                        }
+                        ClientMessage::Hello { version } if protocol::supports(version) => {
+                            connection
+                                .send_json(&Envelope::new(ServerMessage::Welcome {
+                                    version: PROTOCOL_VERSION,
+                                }))
+                                .await?;
+                        }
+                        ClientMessage::Hello { version } => {
+                            debug!(
+                                "Client {} speaks protocol version {}, closing",
+                                connection.id, version
+                            );
+                            connection
+                                .send_json(&Envelope::new(protocol::unsupported(version)))
+                                .await?;
+                            connection
+                                .send(WebSocketMessage::Close(Some(
+                                    protocol::UNSUPPORTED_VERSION.to_string(),
+                                )))
+                                .await?;
                         }
                         ClientMessage::RequestRefresh => {
                             debug!("Client {} requested refresh", connection.id);
@@ -1367,7 +2101,9 @@ impl WebSocketHandler for LiveReloadHandler {
                         if let Some(msg_type) = parsed.get("type").and_then(|t| t.as_str()) {
                             match msg_type {
                                 "ping" => {
-                                    connection.send_json(&ServerMessage::Pong).await?;
+                                    connection
+                                        .send_json(&Envelope::new(ServerMessage::Pong))
+                                        .await?;
                                 }
                                 "request_refresh" => {
                                     debug!(
@@ -1466,6 +2202,125 @@ mod tests {
         assert_eq!(handler.priority(), 10);
     }
 
+    #[tokio::test]
+    async fn test_resume_answers_from_the_render_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let markdown_file = temp_dir.path().join("test.md");
+        fs::write(&markdown_file, "# First").await.unwrap();
+        let handler = MarkdownHandler::new("/".to_string(), markdown_file.clone());
+
+        assert_eq!(
+            handler.resume("0000000000000000").await.unwrap(),
+            ServerMessage::Refresh
+        );
+        let first = handler.content_hash().await;
+        assert_eq!(
+            handler.resume(&first).await.unwrap(),
+            ServerMessage::UpToDate
+        );
+
+        fs::write(&markdown_file, "# Second").await.unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&markdown_file)
+            .unwrap()
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(5))
+            .unwrap();
+        let ServerMessage::ContentUpdate { html, metadata, .. } =
+            handler.resume(&first).await.unwrap()
+        else {
+            panic!("expected a content update");
+        };
+        assert!(html.contains("Second"));
+        let second = metadata.unwrap().content_hash.unwrap();
+        assert_ne!(second, first);
+        assert_eq!(handler.content_hash().await, second);
+    }
+
+    #[tokio::test]
+    async fn test_large_documents_are_streamed() {
+        let temp_dir = TempDir::new().unwrap();
+        let markdown_file = temp_dir.path().join("big.md");
+        fs::write(&markdown_file, "# Big\n\nLots of text.\n")
+            .await
+            .unwrap();
+        let request = HttpRequest::get("/");
+        let handler = |threshold| {
+            MarkdownHandler::with_renderer_registry(
+                "/".to_string(),
+                markdown_file.clone(),
+                Arc::new(RendererRegistry::new()),
+            )
+            .with_streaming_threshold(threshold)
+        };
+
+        let response = handler(Some(8)).handle(request.clone()).await.unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert!(response.body.is_empty());
+        let pieces: Vec<Bytes> = response
+            .stream
+            .unwrap()
+            .0
+            .map(|piece| piece.unwrap())
+            .collect()
+            .await;
+        assert!(pieces.len() >= 3);
+        let page = String::from_utf8(pieces.concat()).unwrap();
+        let content = page.find("Lots of text.").unwrap();
+        assert!(page[..content].contains("<div id=\"content\">"));
+        assert!(page.trim_end().ends_with("</html>"));
+        assert!(!page.contains(STREAM_MARKER));
+
+        let response = handler(None).handle(request).await.unwrap();
+        assert!(response.stream.is_none());
+        assert!(!response.body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_large_documents_are_previewed_with_a_banner() {
+        let temp_dir = TempDir::new().unwrap();
+        let markdown_file = temp_dir.path().join("big.md");
+        fs::write(&markdown_file, "# Big\n\nLots of text.\n")
+            .await
+            .unwrap();
+        let handler = |large_file_threshold, streaming_threshold| {
+            MarkdownHandler::with_renderer_registry(
+                "/".to_string(),
+                markdown_file.clone(),
+                Arc::new(RendererRegistry::new()),
+            )
+            .with_large_file_threshold(large_file_threshold)
+            .with_streaming_threshold(streaming_threshold)
+        };
+        let page = |response: HttpResponse| async move {
+            match response.stream {
+                Some(stream) => {
+                    let pieces: Vec<Bytes> = stream.0.map(|piece| piece.unwrap()).collect().await;
+                    String::from_utf8(pieces.concat()).unwrap()
+                }
+                None => String::from_utf8(response.body.to_vec()).unwrap(),
+            }
+        };
+
+        let request = HttpRequest::get("/");
+        let large = handler(Some(8), None)
+            .handle(request.clone())
+            .await
+            .unwrap();
+        let large = page(large).await;
+        let banner = large.find(LARGE_DOCUMENT_BANNER).unwrap();
+        assert!(banner < large.find("Lots of text.").unwrap());
+
+        let streamed = handler(Some(8), Some(8))
+            .handle(request.clone())
+            .await
+            .unwrap();
+        assert!(page(streamed).await.contains(LARGE_DOCUMENT_BANNER));
+
+        let small = handler(None, None).handle(request).await.unwrap();
+        assert!(!page(small).await.contains(LARGE_DOCUMENT_BANNER));
+    }
+
     #[tokio::test]
     async fn test_mermaid_handler_creation() {
         let handler = MermaidHandler::new("/mermaid.min.js".to_string());
@@ -1475,6 +2330,42 @@ mod tests {
         assert_eq!(handler.priority(), 5);
     }
 
+    #[tokio::test]
+    async fn test_share_handler_renders_qr_for_lan_address() {
+        let handler = ShareHandler::new("/share".to_string(), "0.0.0.0".to_string(), 3000);
+        let request = HttpRequest::get("/share").with_header("host", "192.168.1.20:3000");
+
+        let urls = handler.candidate_urls(&request);
+        assert_eq!(urls[0], "http://192.168.1.20:3000/");
+
+        for host in ["[fd00::20]:3000", "[fd00::20]", "fd00::20"] {
+            let request = HttpRequest::get("/share").with_header("host", host);
+            assert_eq!(
+                handler.candidate_urls(&request)[0],
+                "http://[fd00::20]:3000/"
+            );
+        }
+        let loopback = HttpRequest::get("/share").with_header("host", "[::1]:3000");
+        assert!(!handler
+            .candidate_urls(&loopback)
+            .contains(&"http://[::1]:3000/".to_string()));
+
+        let response = handler.handle(request).await.unwrap();
+        let body = String::from_utf8(response.body.to_vec()).unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert!(body.contains("<svg"));
+        assert!(body.contains("http://192.168.1.20:3000/"));
+    }
+
+    #[test]
+    fn test_share_handler_explains_loopback_binding() {
+        let handler = ShareHandler::new("/share".to_string(), "127.0.0.1".to_string(), 3000);
+        let page = handler.render_page(&[]).unwrap();
+
+        assert!(!page.contains("<svg"));
+        assert!(page.contains("-H 0.0.0.0"));
+    }
+
     #[tokio::test]
     async fn test_live_reload_handler_creation() {
         let handler = LiveReloadHandler::new("/ws".to_string());
@@ -1517,6 +2408,117 @@ mod tests {
         assert!(!handler.is_allowed_extension(Path::new("test")));
     }
 
+    #[tokio::test]
+    async fn test_large_static_files_are_streamed() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path().canonicalize().unwrap();
+        let large = vec![7u8; STATIC_STREAMING_THRESHOLD as usize + 1];
+        fs::write(base.join("large.png"), &large).await.unwrap();
+        fs::write(base.join("small.png"), b"tiny").await.unwrap();
+        let handler = StaticHandler::new(base, "/static".to_string());
+        let response = handler
+            .handle(HttpRequest::get("/static/small.png"))
+            .await
+            .unwrap();
+        assert!(response.stream.is_none());
+        assert_eq!(response.body, &b"tiny"[..]);
+
+        let response = handler
+            .handle(HttpRequest::get("/static/large.png"))
+            .await
+            .unwrap();
+        assert!(response.body.is_empty());
+        assert_eq!(
+            response.headers["content-length"],
+            large.len().to_string().as_str()
+        );
+        let pieces: Vec<Bytes> = response.stream.unwrap().0.try_collect().await.unwrap();
+        assert_eq!(pieces.concat(), large);
+    }
+
+    #[tokio::test]
+    async fn test_static_requests_stay_in_the_base_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path().join("site");
+        fs::create_dir(&base).await.unwrap();
+        fs::write(base.join("logo.png"), b"png").await.unwrap();
+        fs::write(temp_dir.path().join("secret.txt"), b"secret")
+            .await
+            .unwrap();
+        // Not canonical, as handlers get it from the command line
+        let handler = StaticHandler::new(base.join("."), "/static".to_string());
+        let status = |path: &'static str| {
+            let handler = &handler;
+            async move { handler.handle(HttpRequest::get(path)).await.unwrap().status }
+        };
+
+        assert_eq!(status("/static/logo.png").await, StatusCode::OK);
+        assert_eq!(status("/static/missing.png").await, StatusCode::NOT_FOUND);
+        for escape in [
+            "/static/../secret.txt",
+            "/static/%2e%2e/secret.txt",
+            "/static/%2e%2e%2fsecret.txt",
+            "/static/..%5csecret.txt",
+        ] {
+            assert_eq!(status(escape).await, StatusCode::FORBIDDEN, "{}", escape);
+        }
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(temp_dir.path().join("secret.txt"), base.join("leak.txt"))
+                .unwrap();
+            assert_eq!(status("/static/leak.txt").await, StatusCode::FORBIDDEN);
+            let handler = StaticHandler::new(base, "/static".to_string())
+                .with_symlink_policy(SymlinkPolicy::Follow);
+            let response = handler
+                .handle(HttpRequest::get("/static/leak.txt"))
+                .await
+                .unwrap();
+            assert_eq!(response.body, &b"secret"[..]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_range_requests_serve_partial_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path().canonicalize().unwrap();
+        fs::write(base.join("clip.mp4"), b"0123456789")
+            .await
+            .unwrap();
+        let handler = StaticHandler::new(base, "/static".to_string());
+        let request =
+            |range: &str| HttpRequest::get("/static/clip.mp4").with_header("range", range);
+
+        let response = handler.handle(request("bytes=2-5")).await.unwrap();
+        assert_eq!(response.status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers["content-type"], "video/mp4");
+        assert_eq!(response.headers["content-range"], "bytes 2-5/10");
+        assert_eq!(response.headers["content-length"], "4");
+        let pieces: Vec<Bytes> = response.stream.unwrap().0.try_collect().await.unwrap();
+        assert_eq!(pieces.concat(), b"2345");
+
+        let response = handler.handle(request("bytes=-3")).await.unwrap();
+        let pieces: Vec<Bytes> = response.stream.unwrap().0.try_collect().await.unwrap();
+        assert_eq!(pieces.concat(), b"789");
+
+        let response = handler.handle(request("bytes=10-")).await.unwrap();
+        assert_eq!(response.status, StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers["content-range"], "bytes */10");
+
+        // Multiple ranges are answered with the whole file
+        let response = handler.handle(request("bytes=0-1,4-5")).await.unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.headers["accept-ranges"], "bytes");
+        assert_eq!(response.body, &b"0123456789"[..]);
+
+        assert_eq!(
+            ByteRange::parse("bytes=4-100", 10),
+            Some(ByteRange::Bytes { start: 4, end: 9 })
+        );
+        assert_eq!(ByteRange::parse("bytes=5-2", 10), None);
+        assert_eq!(ByteRange::parse("items=0-1", 10), None);
+    }
+
     #[test]
     fn test_client_message_serialization() {
         let ping_msg = ClientMessage::Ping;
@@ -0,0 +1,373 @@
+//! Version history API
+//!
+//! `GET /api/history/<file>` lists the saved versions of a document in the
+//! served directory, newest first. `GET /api/history/<file>/diff?from=&to=`
+//! returns a page comparing two versions side by side: both are split into
+//! top-level blocks, and the blocks that changed are shown rendered through
+//! the renderer plugin. `to` defaults to the latest version and `from` to the
+//! one before it. Versions are recorded on save (see
+//! [`rune_core::history`]).
+
+use crate::handlers::standalone_page;
+use crate::presence::rendered_blocks;
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use markdown::mdast::Node;
+use percent_encoding::percent_decode_str;
+use rune_core::{
+    error::{Result, RuneError},
+    history::{side_by_side, DiffKind, HistoryStore, VersionEntry},
+    renderer::{RenderContext, RendererRegistry},
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Handler for the version history of the documents next to a markdown file
+pub struct HistoryApiHandler {
+    path_pattern: String,
+    base_dir: PathBuf,
+    store: Arc<HistoryStore>,
+    renderer_registry: Option<Arc<RendererRegistry>>,
+}
+
+impl HistoryApiHandler {
+    /// Create a history handler for the directory of `markdown_file`
+    pub fn new(
+        path_pattern: String,
+        markdown_file: &Path,
+        renderer_registry: Option<Arc<RendererRegistry>>,
+    ) -> Self {
+        Self {
+            path_pattern,
+            base_dir: markdown_file
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .to_path_buf(),
+            store: Arc::new(HistoryStore::for_document(markdown_file)),
+            renderer_registry,
+        }
+    }
+
+    async fn versions(&self, file_name: &str) -> Result<Vec<VersionEntry>> {
+        let store = self.store.clone();
+        let file_name = file_name.to_string();
+        tokio::task::spawn_blocking(move || store.versions(&file_name))
+            .await
+            .map_err(|e| RuneError::Server(format!("History task failed: {}", e)))?
+    }
+
+    async fn content(&self, hash: &str) -> Result<Option<String>> {
+        let store = self.store.clone();
+        let hash = hash.to_string();
+        tokio::task::spawn_blocking(move || store.content(&hash))
+            .await
+            .map_err(|e| RuneError::Server(format!("History task failed: {}", e)))?
+    }
+
+    async fn list(&self, file_name: &str) -> Result<HttpResponse> {
+        let mut versions = self.versions(file_name).await?;
+        versions.reverse();
+        Ok(HttpResponse::json(&serde_json::json!({
+            "file": file_name,
+            "versions": versions,
+        }))?
+        .with_header("cache-control", "no-store"))
+    }
+
+    async fn diff(&self, file_name: &str, request: &HttpRequest) -> Result<HttpResponse> {
+        let versions = self.versions(file_name).await?;
+        let position = |hash: &str| versions.iter().rposition(|version| version.hash == hash);
+
+        let to = match request.query_params.get("to") {
+            Some(hash) => position(hash),
+            None => versions.len().checked_sub(1),
+        };
+        let Some(to) = to else {
+            return Ok(HttpResponse::error(
+                StatusCode::NOT_FOUND,
+                "Version not found",
+            ));
+        };
+        let from = match request.query_params.get("from") {
+            Some(hash) => match position(hash) {
+                Some(from) => Some(from),
+                None => {
+                    return Ok(HttpResponse::error(
+                        StatusCode::NOT_FOUND,
+                        "Version not found",
+                    ))
+                }
+            },
+            // The first version is compared with an empty document
+            None => to.checked_sub(1),
+        };
+
+        let old = match from {
+            Some(from) => self.version_content(&versions[from]).await?,
+            None => String::new(),
+        };
+        let new = self.version_content(&versions[to]).await?;
+
+        let title = format!("Changes to {}", file_name);
+        let body = self.render_diff(file_name, &title, &old, &new).await?;
+        Ok(HttpResponse::html(standalone_page(&title, &body))
+            .with_header("cache-control", "no-store"))
+    }
+
+    async fn version_content(&self, version: &VersionEntry) -> Result<String> {
+        self.content(&version.hash).await?.ok_or_else(|| {
+            RuneError::file_system(format!("Missing content of version {}", version.hash))
+        })
+    }
+
+    /// Table of the blocks of both versions, changed blocks rendered
+    async fn render_diff(
+        &self,
+        file_name: &str,
+        title: &str,
+        old: &str,
+        new: &str,
+    ) -> Result<String> {
+        let rows = side_by_side(&split_blocks(old), &split_blocks(new));
+        let (old_definitions, new_definitions) = (definitions(old), definitions(new));
+
+        let mut html = format!(
+            "<h1>{}</h1>\n<table class=\"rune-diff\">\n",
+            html_escape::encode_text(title)
+        );
+        let mut unchanged = 0;
+        for row in rows {
+            if row.kind == DiffKind::Unchanged {
+                unchanged += 1;
+                continue;
+            }
+            if unchanged > 0 {
+                html.push_str(&unchanged_row(unchanged));
+                unchanged = 0;
+            }
+
+            let old_cell = match &row.old {
+                Some((_, block)) => {
+                    self.render_block(file_name, block, &old_definitions)
+                        .await?
+                }
+                None => String::new(),
+            };
+            let new_cell = match &row.new {
+                Some((_, block)) => {
+                    self.render_block(file_name, block, &new_definitions)
+                        .await?
+                }
+                None => String::new(),
+            };
+            let kind = match row.kind {
+                DiffKind::Changed => "changed",
+                DiffKind::Removed => "removed",
+                _ => "added",
+            };
+            html.push_str(&format!(
+                "<tr class=\"rune-diff-{}\"><td class=\"rune-diff-old\">{}</td><td class=\"rune-diff-new\">{}</td></tr>\n",
+                kind, old_cell, new_cell
+            ));
+        }
+        if unchanged > 0 {
+            html.push_str(&unchanged_row(unchanged));
+        }
+        html.push_str("</table>\n");
+        html.push_str(DIFF_STYLE);
+        Ok(html)
+    }
+
+    /// Render one block, with the version's link definitions so references
+    /// still resolve
+    async fn render_block(
+        &self,
+        file_name: &str,
+        block: &str,
+        definitions: &str,
+    ) -> Result<String> {
+        let source = format!("{}\n\n{}", block, definitions);
+        if let Some(registry) = &self.renderer_registry {
+            let context = RenderContext::new(
+                self.base_dir.join(file_name),
+                self.base_dir.clone(),
+                "catppuccin-mocha".to_string(),
+            );
+            return Ok(registry.render_with_pipeline(&source, &context).await?.html);
+        }
+
+        let mut options = markdown::Options::gfm();
+        options.compile.allow_dangerous_html = true;
+        markdown::to_html_with_options(&source, &options)
+            .map_err(|e| RuneError::Server(format!("Markdown parsing failed: {}", e)))
+    }
+}
+
+const DIFF_STYLE: &str = r#"<style>
+.rune-diff { table-layout: fixed; }
+.rune-diff td { vertical-align: top; }
+.rune-diff-unchanged td { text-align: center; color: var(--blockquote-color); }
+.rune-diff-old:not(:empty) { background: rgba(248, 81, 73, 0.12); }
+.rune-diff-new:not(:empty) { background: rgba(63, 185, 80, 0.12); }
+</style>
+"#;
+
+/// Row standing in for a run of unchanged blocks
+fn unchanged_row(count: usize) -> String {
+    format!(
+        "<tr class=\"rune-diff-unchanged\"><td colspan=\"2\">{} unchanged block{}</td></tr>\n",
+        count,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// Source of each top-level block that renders to an element
+fn split_blocks(markdown: &str) -> Vec<String> {
+    rendered_blocks(markdown)
+        .into_iter()
+        .map(|position| markdown[position.start.offset..position.end.offset].to_string())
+        .collect()
+}
+
+/// Link and footnote definitions of a document
+fn definitions(markdown: &str) -> String {
+    let Ok(root) = markdown::to_mdast(markdown, &markdown::ParseOptions::gfm()) else {
+        return String::new();
+    };
+    root.children()
+        .into_iter()
+        .flatten()
+        .filter(|node| matches!(node, Node::Definition(_) | Node::FootnoteDefinition(_)))
+        .filter_map(|node| node.position())
+        .map(|position| &markdown[position.start.offset..position.end.offset])
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[async_trait]
+impl HttpHandler for HistoryApiHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let rest = request
+            .path
+            .strip_prefix(&self.path_pattern)
+            .unwrap_or(&request.path)
+            .trim_matches('/');
+        let (file, diff) = match rest.strip_suffix("/diff") {
+            Some(file) => (file, true),
+            None => (rest, false),
+        };
+        let file_name = percent_decode_str(file).decode_utf8_lossy().to_string();
+        if file_name.is_empty() || file_name.starts_with('.') || file_name.contains('/') {
+            return Ok(HttpResponse::error(StatusCode::NOT_FOUND, "Not found"));
+        }
+
+        if diff {
+            self.diff(&file_name, &request).await
+        } else {
+            self.list(&file_name).await
+        }
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Register the version history API for the directory of a markdown document
+pub async fn register_history_handlers(
+    registry: &HandlerRegistry,
+    markdown_file: &Path,
+    renderer_registry: Option<Arc<RendererRegistry>>,
+) -> Result<()> {
+    registry
+        .register_http_handler(Arc::new(HistoryApiHandler::new(
+            "/api/history".to_string(),
+            markdown_file,
+            renderer_registry,
+        )))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_history_lists_versions_and_renders_diffs() {
+        let temp_dir = TempDir::new().unwrap();
+        let doc = temp_dir.path().join("my notes.md");
+        let first = HistoryStore::record_save(
+            &doc,
+            "# Notes\n\nKeep [this][ref].\n\nOld **text**.\n\n[ref]: https://example.com\n",
+            None,
+        )
+        .unwrap();
+        let second = HistoryStore::record_save(
+            &doc,
+            "# Notes\n\nKeep [this][ref].\n\nNew *text*.\n\n```\ncode\n```\n\n[ref]: https://example.com\n",
+            Some("Rewrite".to_string()),
+        )
+        .unwrap();
+
+        let handler = HistoryApiHandler::new("/api/history".to_string(), &doc, None);
+        let response = handler
+            .handle(HttpRequest::get("/api/history/my%20notes.md"))
+            .await
+            .unwrap();
+        assert_eq!(response.headers.get("cache-control").unwrap(), "no-store");
+        let listed: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(listed["versions"][0]["hash"], second.hash.as_str());
+        assert_eq!(listed["versions"][0]["message"], "Rewrite");
+        assert_eq!(listed["versions"][1]["hash"], first.hash.as_str());
+
+        let response = handler
+            .handle(HttpRequest::get("/api/history/my%20notes.md/diff"))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        let page = String::from_utf8(response.body.to_vec()).unwrap();
+        assert!(page.contains("<strong>text</strong>"));
+        assert!(page.contains("<em>text</em>"));
+        assert!(page.contains("<pre><code>code\n</code></pre>"));
+        assert!(page.contains("2 unchanged blocks"));
+        assert!(!page.contains("Keep"));
+
+        // The first version is compared with an empty document
+        let response = handler
+            .handle(
+                HttpRequest::get("/api/history/my%20notes.md/diff")
+                    .with_query(&[("to", &first.hash)]),
+            )
+            .await
+            .unwrap();
+        let page = String::from_utf8(response.body.to_vec()).unwrap();
+        assert!(page.contains(r#"<a href="https://example.com">this</a>"#));
+
+        let missing = handler
+            .handle(
+                HttpRequest::get("/api/history/my%20notes.md/diff").with_query(&[("from", "0123")]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+        let outside = handler
+            .handle(HttpRequest::get("/api/history/..%2Fsecret.md"))
+            .await
+            .unwrap();
+        assert_eq!(outside.status, StatusCode::NOT_FOUND);
+    }
+}
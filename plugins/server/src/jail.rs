@@ -0,0 +1,355 @@
+//! Keeping file requests inside the directory they are served from
+//!
+//! A [`PathJail`] resolves request paths against its root. Paths with `..`
+//! components, before or after percent-decoding, backslashes or NUL bytes
+//! are refused outright; the rest are canonicalized and must stay under the
+//! canonical root. How symbolic links are treated is the `symlinks` global
+//! setting:
+//! - `within_root`, the default, follows links that end inside the root.
+//! - `deny` refuses any path going through a link.
+//! - `follow` follows links wherever they lead, for roots linking to shared
+//!   asset directories on purpose.
+//!
+//! Refused requests are logged as warnings with the `rune_server::audit`
+//! target.
+
+use percent_encoding::percent_decode_str;
+use rune_core::{Config, Result, RuneError};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use tracing::warn;
+
+/// Log target of refused file requests
+pub const AUDIT_TARGET: &str = "rune_server::audit";
+
+/// How symbolic links under a served root are treated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// No path may go through a link
+    Deny,
+    /// Links are followed when they end inside the root
+    #[default]
+    WithinRoot,
+    /// Links are followed wherever they lead
+    Follow,
+}
+
+impl SymlinkPolicy {
+    /// Policy of the `symlinks` global setting
+    pub fn from_config(config: &Config) -> Self {
+        match config
+            .get_global_setting::<String>("symlinks")
+            .map(|policy| policy.parse())
+        {
+            Some(Ok(policy)) => policy,
+            Some(Err(e)) => {
+                warn!("{}; following links within the served directory", e);
+                Self::default()
+            }
+            None => Self::default(),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Deny => "deny",
+            Self::WithinRoot => "within_root",
+            Self::Follow => "follow",
+        }
+    }
+}
+
+impl FromStr for SymlinkPolicy {
+    type Err = RuneError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "deny" => Ok(Self::Deny),
+            "within_root" => Ok(Self::WithinRoot),
+            "follow" => Ok(Self::Follow),
+            other => Err(RuneError::config(format!(
+                "Unknown symlink policy '{}'. Supported values: deny, within_root, follow",
+                other
+            ))),
+        }
+    }
+}
+
+/// Why a path was not resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// Not valid UTF-8 once decoded, or with a backslash or NUL byte
+    Malformed,
+    /// With a `..` component
+    Traversal,
+    /// Resolving to a file outside the root
+    Escape,
+    /// Going through a link the policy refuses
+    Symlink,
+    /// No such file
+    NotFound,
+}
+
+impl Rejection {
+    /// Whether the request tried to get out of the root, as opposed to
+    /// naming a missing file
+    pub fn is_attack(self) -> bool {
+        self != Self::NotFound
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Malformed => "malformed path",
+            Self::Traversal => "path traversal",
+            Self::Escape => "path outside the served directory",
+            Self::Symlink => "symbolic link",
+            Self::NotFound => "not found",
+        })
+    }
+}
+
+/// Root that resolved paths may not leave
+#[derive(Debug, Clone)]
+pub struct PathJail {
+    root: PathBuf,
+    symlinks: SymlinkPolicy,
+}
+
+impl PathJail {
+    /// Jail at `root`, canonicalized when it exists
+    pub fn new(root: &Path, symlinks: SymlinkPolicy) -> Self {
+        Self {
+            root: root.canonicalize().unwrap_or_else(|_| root.to_path_buf()),
+            symlinks,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn symlinks(&self) -> SymlinkPolicy {
+        self.symlinks
+    }
+
+    /// Canonical path of the existing file at the percent-encoded `path`,
+    /// relative to the root
+    pub fn resolve(&self, path: &str) -> std::result::Result<PathBuf, Rejection> {
+        let decoded = percent_decode_str(path)
+            .decode_utf8()
+            .map_err(|_| Rejection::Malformed)?;
+        let relative = plain(&decoded)?;
+        let joined = self.root.join(&relative);
+        let canonical = joined.canonicalize().map_err(|_| Rejection::NotFound)?;
+        self.check(&relative, &canonical)?;
+        Ok(canonical)
+    }
+
+    /// Path of a file yet to be created at the relative, decoded `path`;
+    /// its nearest existing ancestor must be inside the root. Links that
+    /// lead nowhere are refused, as writing through one would create their
+    /// target wherever it is
+    pub fn resolve_new(&self, path: &Path) -> std::result::Result<PathBuf, Rejection> {
+        let relative = plain(path.to_str().ok_or(Rejection::Malformed)?)?;
+        let joined = self.root.join(&relative);
+        let mut existing = relative.as_path();
+        loop {
+            let path = self.root.join(existing);
+            match path.canonicalize() {
+                Ok(canonical) => {
+                    self.check(existing, &canonical)?;
+                    return Ok(joined);
+                }
+                Err(_) if path.symlink_metadata().is_ok() => return Err(Rejection::Symlink),
+                Err(_) => existing = existing.parent().ok_or(Rejection::NotFound)?,
+            }
+        }
+    }
+
+    /// Path of the file at the percent-encoded `path`, which may not exist
+    /// yet, checked like [`Self::resolve`] but with its links left in place,
+    /// so a link itself can be removed or renamed
+    pub fn locate(&self, path: &str) -> std::result::Result<PathBuf, Rejection> {
+        let decoded = percent_decode_str(path)
+            .decode_utf8()
+            .map_err(|_| Rejection::Malformed)?;
+        match self.resolve(path) {
+            Ok(_) => Ok(self.root.join(plain(&decoded)?)),
+            Err(Rejection::NotFound) => self.resolve_new(Path::new(decoded.as_ref())),
+            Err(rejection) => Err(rejection),
+        }
+    }
+
+    /// Check the canonical form of the existing `relative` path against the
+    /// root and the link policy
+    fn check(&self, relative: &Path, canonical: &Path) -> std::result::Result<(), Rejection> {
+        match self.symlinks {
+            SymlinkPolicy::Follow => Ok(()),
+            SymlinkPolicy::WithinRoot if canonical.starts_with(&self.root) => Ok(()),
+            SymlinkPolicy::WithinRoot => Err(Rejection::Escape),
+            SymlinkPolicy::Deny => {
+                let mut path = self.root.clone();
+                for component in relative.components() {
+                    path.push(component);
+                    if path
+                        .symlink_metadata()
+                        .is_ok_and(|m| m.file_type().is_symlink())
+                    {
+                        return Err(Rejection::Symlink);
+                    }
+                }
+                if canonical.starts_with(&self.root) {
+                    Ok(())
+                } else {
+                    Err(Rejection::Escape)
+                }
+            }
+        }
+    }
+}
+
+/// Decoded `path` checked to only have plain components
+fn plain(path: &str) -> std::result::Result<PathBuf, Rejection> {
+    if path.contains(['\\', '\0']) {
+        return Err(Rejection::Malformed);
+    }
+    let mut relative = PathBuf::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => continue,
+            ".." => return Err(Rejection::Traversal),
+            part => {
+                // Drive prefixes and the like are not plain names
+                let mut components = Path::new(part).components();
+                match (components.next(), components.next()) {
+                    (Some(Component::Normal(_)), None) => relative.push(part),
+                    _ => return Err(Rejection::Malformed),
+                }
+            }
+        }
+    }
+    Ok(relative)
+}
+
+/// Log a refused request for `path`
+pub fn audit(path: &str, rejection: Rejection) {
+    warn!(
+        target: AUDIT_TARGET,
+        path,
+        reason = %rejection,
+        "Refused file request for {}: {}",
+        path,
+        rejection
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn site() -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("site");
+        std::fs::create_dir_all(root.join("img")).unwrap();
+        std::fs::write(root.join("img/logo.png"), b"png").unwrap();
+        std::fs::write(root.join("my notes.txt"), b"notes").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), b"secret").unwrap();
+        (dir, root)
+    }
+
+    #[test]
+    fn test_traversal_is_refused() {
+        let (_dir, root) = site();
+        let jail = PathJail::new(&root, SymlinkPolicy::default());
+        assert!(jail
+            .resolve("img/logo.png")
+            .unwrap()
+            .ends_with("img/logo.png"));
+        assert!(jail.resolve("./img//logo.png").is_ok());
+        assert!(jail.resolve("my%20notes.txt").is_ok());
+
+        for traversal in [
+            "../secret.txt",
+            "img/../../secret.txt",
+            "%2e%2e/secret.txt",
+            "%2E%2E%2Fsecret.txt",
+            "img/%2e%2e/%2e%2e/secret.txt",
+        ] {
+            assert_eq!(
+                jail.resolve(traversal),
+                Err(Rejection::Traversal),
+                "{}",
+                traversal
+            );
+        }
+        for malformed in [
+            "..%5csecret.txt",
+            "img\\..\\logo.png",
+            "logo.png%00.txt",
+            "%ff",
+        ] {
+            assert_eq!(
+                jail.resolve(malformed),
+                Err(Rejection::Malformed),
+                "{}",
+                malformed
+            );
+        }
+        assert_eq!(jail.resolve("img/missing.png"), Err(Rejection::NotFound));
+        assert!(!Rejection::NotFound.is_attack());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_policies() {
+        let (dir, root) = site();
+        std::os::unix::fs::symlink(dir.path().join("secret.txt"), root.join("escape.txt")).unwrap();
+        std::os::unix::fs::symlink(root.join("img"), root.join("pictures")).unwrap();
+
+        let within = PathJail::new(&root, SymlinkPolicy::WithinRoot);
+        assert_eq!(within.resolve("escape.txt"), Err(Rejection::Escape));
+        assert!(within.resolve("pictures/logo.png").is_ok());
+
+        let deny = PathJail::new(&root, SymlinkPolicy::Deny);
+        assert_eq!(deny.resolve("escape.txt"), Err(Rejection::Symlink));
+        assert_eq!(deny.resolve("pictures/logo.png"), Err(Rejection::Symlink));
+        assert!(deny.resolve("img/logo.png").is_ok());
+
+        let follow = PathJail::new(&root, SymlinkPolicy::Follow);
+        assert!(follow.resolve("escape.txt").is_ok());
+
+        // New files may not be created through a link out of the root
+        std::os::unix::fs::symlink(dir.path(), root.join("outside")).unwrap();
+        assert!(within.resolve_new(Path::new("drafts/new.md")).is_ok());
+        assert_eq!(
+            within.resolve_new(Path::new("outside/new.md")),
+            Err(Rejection::Escape)
+        );
+        assert_eq!(
+            within.resolve_new(Path::new("../new.md")),
+            Err(Rejection::Traversal)
+        );
+        // Nor through a link leading nowhere, under any policy
+        std::os::unix::fs::symlink(dir.path().join("gone.md"), root.join("dangling.md")).unwrap();
+        assert_eq!(
+            follow.resolve_new(Path::new("dangling.md")),
+            Err(Rejection::Symlink)
+        );
+        assert_eq!(within.locate("dangling.md"), Err(Rejection::Symlink));
+        assert_eq!(
+            within.locate("pictures").unwrap(),
+            within.root().join("pictures")
+        );
+        assert!(within.locate("img/new%20logo.png").is_ok());
+
+        assert_eq!(
+            "deny".parse::<SymlinkPolicy>().unwrap(),
+            SymlinkPolicy::Deny
+        );
+        assert!("sometimes".parse::<SymlinkPolicy>().is_err());
+    }
+}
@@ -0,0 +1,207 @@
+//! Daily notes API
+//!
+//! `POST /api/journal` opens the note of a day in the configured
+//! [`Journal`], creating it from the template when it does not exist yet.
+//! It takes an optional JSON object whose `date` is `today` (the default),
+//! `yesterday`, `tomorrow` or like `2024-05-01`, and answers with the note's
+//! `date`, `path`, whether it was `created` and the `url` of its preview, or
+//! `null` when no served root holds it. `rune journal` serves the journal
+//! directory, so every note has one there.
+
+use crate::roots::document_href;
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::journal::Journal;
+use rune_core::plugin::PluginContext;
+use rune_core::state::ServedRoot;
+use rune_core::{Result, RuneError};
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::info;
+
+/// Body of a journal request
+#[derive(Debug, Default, Deserialize)]
+struct JournalRequest {
+    #[serde(default)]
+    date: Option<String>,
+}
+
+/// Handler opening or creating the note of a day
+pub struct JournalHandler {
+    path_pattern: String,
+    journal: Journal,
+    roots: Vec<ServedRoot>,
+}
+
+impl JournalHandler {
+    pub fn new(path_pattern: String, journal: Journal, roots: &[ServedRoot]) -> Self {
+        let roots = roots
+            .iter()
+            .map(|root| ServedRoot {
+                prefix: root.prefix.clone(),
+                path: root
+                    .path
+                    .canonicalize()
+                    .unwrap_or_else(|_| root.path.clone()),
+            })
+            .collect();
+        Self {
+            path_pattern,
+            journal,
+            roots,
+        }
+    }
+
+    /// URL of the preview of the note at `path`
+    fn url(&self, path: &Path) -> Option<String> {
+        self.roots.iter().find_map(|root| {
+            let base = if root.prefix.is_empty() {
+                String::new()
+            } else {
+                format!("/{}", root.prefix)
+            };
+            if root.is_dir() {
+                let relative = path.strip_prefix(&root.path).ok()?;
+                Some(format!("{}/{}", base, document_href(relative)))
+            } else {
+                (path == root.path).then(|| format!("{}/", base))
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl HttpHandler for JournalHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let journal_request: JournalRequest = if request.body.is_empty() {
+            JournalRequest::default()
+        } else {
+            match serde_json::from_slice(&request.body) {
+                Ok(journal_request) => journal_request,
+                Err(e) => {
+                    return Ok(HttpResponse::error(
+                        StatusCode::BAD_REQUEST,
+                        &format!("Invalid journal request: {}", e),
+                    ))
+                }
+            }
+        };
+        let date = match self
+            .journal
+            .date(journal_request.date.as_deref().unwrap_or("today"))
+        {
+            Ok(date) => date,
+            Err(RuneError::Config(message)) => {
+                return Ok(HttpResponse::error(StatusCode::BAD_REQUEST, &message))
+            }
+            Err(e) => return Err(e),
+        };
+
+        let journal = self.journal.clone();
+        let (path, created) = tokio::task::spawn_blocking(move || journal.open(date))
+            .await
+            .map_err(|e| RuneError::Server(format!("Journal task failed: {}", e)))??;
+        if created {
+            info!("Created the journal note of {} at {:?}", date, path);
+        }
+
+        HttpResponse::json(&serde_json::json!({
+            "date": date.to_string(),
+            "title": date.title(),
+            "path": path,
+            "created": created,
+            "url": self.url(&path),
+        }))
+    }
+
+    fn edits(&self) -> bool {
+        true // Creates notes
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Register the journal API, linking notes to their previews in `roots`
+pub async fn register_journal_handler(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+    roots: &[ServedRoot],
+) -> Result<()> {
+    let journal = Journal::from_config(&context.config.journal)?;
+    registry
+        .register_http_handler(Arc::new(JournalHandler::new(
+            "/api/journal".to_string(),
+            journal,
+            roots,
+        )))
+        .await?;
+
+    info!("Registered journal API handler");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::JournalConfig;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_opens_and_creates_notes() {
+        let temp_dir = TempDir::new().unwrap();
+        let journal = Journal::from_config(&JournalConfig {
+            dir: Some(temp_dir.path().to_path_buf()),
+            path: Some("%Y-%m-%d.md".to_string()),
+            ..JournalConfig::default()
+        })
+        .unwrap();
+        let roots = [ServedRoot {
+            prefix: "notes".to_string(),
+            path: temp_dir.path().to_path_buf(),
+        }];
+        let handler = JournalHandler::new("/api/journal".to_string(), journal, &roots);
+
+        let request = || {
+            HttpRequest::new(Method::POST, "/api/journal")
+                .with_body(br#"{"date": "2024-05-01"}"#.to_vec())
+        };
+        let response = handler.handle(request()).await.unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(body["created"], true);
+        assert_eq!(body["url"], "/notes/2024-05-01.md");
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("2024-05-01.md")).unwrap(),
+            "# Wednesday, 1 May 2024\n\n"
+        );
+
+        let response = handler.handle(request()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(body["created"], false);
+
+        let response = handler
+            .handle(
+                HttpRequest::new(Method::POST, "/api/journal")
+                    .with_body(br#"{"date": "someday"}"#.to_vec()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -5,24 +5,73 @@
 //! This plugin provides a modular web server with pluggable handlers and middleware.
 //! It supports dynamic route registration, handler hot-reloading, and multiple protocols.
 
+pub mod anchors;
+pub mod commands_api;
+pub mod config_api;
+pub mod control;
+pub mod convert_api;
+pub mod copy_as;
+pub mod cors;
+pub mod csp;
+pub mod csrf;
+pub mod dashboard;
+pub mod discovery;
+pub mod drain;
 pub mod editor_handlers;
+pub mod error_pages;
+pub mod files_api;
 pub mod handlers;
+pub mod history_api;
+pub mod jail;
+pub mod journal_api;
+pub mod logs_api;
+pub mod memory_api;
+pub mod notifications_api;
+pub mod ot;
+pub mod plugins_api;
+pub mod presence;
+pub mod profile_api;
+pub mod protocol;
+pub mod reading;
+pub mod recent_api;
+pub mod redirects;
+pub mod roots;
+pub mod search;
 pub mod simple_live_editor;
+pub mod snapshots;
+pub mod stats;
+pub mod tags;
+pub mod tasks;
+pub mod template;
+#[cfg(test)]
+mod test_support;
+pub mod transform_api;
+pub mod tunnel;
+pub mod uploads;
+pub mod vendor;
+pub mod webdav;
+pub mod wire;
 
 pub use editor_handlers::{EditorWebSocketHandler, RawEditorHandler}; // LiveEditorHandler temporarily disabled
 pub use simple_live_editor::SimpleLiveEditorHandler;
 
 use async_trait::async_trait;
 use axum::{
+    body::Bytes,
     extract::{FromRequest, WebSocketUpgrade},
     http::{HeaderMap, Method, StatusCode},
     response::{IntoResponse, Response},
     Router,
 };
+use futures_util::stream::BoxStream;
 use rune_core::{
+    config::{FieldSchema, FieldType},
+    crash,
     error::{Result, RuneError},
     event::{EventBus, SystemEvent},
     plugin::{Plugin, PluginContext, PluginStatus},
+    supervisor::{RestartPolicy, TaskSupervisor},
+    trace::{self, TraceId, TRACE_HEADER},
 };
 use serde::{Deserialize, Serialize};
 use std::{
@@ -30,13 +79,10 @@ use std::{
     net::SocketAddr,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
-use tokio::{
-    net::TcpListener,
-    sync::{broadcast, RwLock},
-};
-use tower_http::cors::CorsLayer;
-use tracing::{debug, error, info, warn};
+use tokio::sync::{broadcast, RwLock};
+use tracing::{debug, info, warn, Instrument};
 
 /// HTTP handler trait for processing HTTP requests
 #[async_trait]
@@ -58,6 +104,25 @@ pub trait HttpHandler: Send + Sync {
     /// For downcasting to concrete types
     fn as_any(&self) -> &dyn std::any::Any;
 
+    /// Whether requests change documents or server state, so only local
+    /// clients or those presenting the API token may send them
+    fn edits(&self) -> bool {
+        false
+    }
+
+    /// How long the handler may take to produce its response, `None` for
+    /// the server's `request_timeout_secs`; a streamed body is sent after
+    /// that and is not limited
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Largest request body the handler takes, in bytes, `None` for the
+    /// server's `max_body_size`
+    fn max_body_size(&self) -> Option<usize> {
+        None
+    }
+
     /// Check if this handler can process the given request
     fn can_handle(&self, path: &str, method: &Method) -> bool {
         // Handle both GET and HEAD for GET handlers (HEAD is used for testing endpoints)
@@ -107,6 +172,12 @@ pub trait WebSocketHandler: Send + Sync {
     fn priority(&self) -> i32 {
         0
     }
+
+    /// Whether clients change documents through the socket, so only local
+    /// clients or those presenting the API token may connect
+    fn edits(&self) -> bool {
+        false
+    }
 }
 
 /// HTTP request wrapper
@@ -116,16 +187,32 @@ pub struct HttpRequest {
     pub path: String,
     pub query_params: HashMap<String, String>,
     pub headers: HeaderMap,
-    pub body: Vec<u8>,
+    /// Request body as received; cloning shares the buffer
+    pub body: Bytes,
     pub path_params: HashMap<String, String>,
 }
 
+/// Response body sent to the client piece by piece as it is produced
+pub struct BodyStream(pub BoxStream<'static, Result<Bytes>>);
+
+impl std::fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BodyStream")
+    }
+}
+
 /// HTTP response wrapper
 #[derive(Debug)]
 pub struct HttpResponse {
     pub status: StatusCode,
     pub headers: HeaderMap,
-    pub body: Vec<u8>,
+    /// Buffered body; owned `String`s and `Vec<u8>`s are taken over without
+    /// copying
+    pub body: Bytes,
+    /// Streamed body sent instead of `body`
+    pub stream: Option<BodyStream>,
+    /// Nonce the page's inline scripts and styles were filled with
+    pub nonce: Option<String>,
 }
 
 impl HttpResponse {
@@ -134,12 +221,26 @@ impl HttpResponse {
         Self {
             status,
             headers: HeaderMap::new(),
-            body: Vec::new(),
+            body: Bytes::new(),
+            stream: None,
+            nonce: None,
         }
     }
 
+    /// Mark the page as filled with `nonce`, allowed by the content security policy
+    pub fn with_nonce(mut self, nonce: String) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    /// Stream the body, flushing each piece to the client as it arrives
+    pub fn with_stream(mut self, stream: BoxStream<'static, Result<Bytes>>) -> Self {
+        self.stream = Some(BodyStream(stream));
+        self
+    }
+
     /// Set response body
-    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+    pub fn with_body(mut self, body: impl Into<Bytes>) -> Self {
         self.body = body.into();
         self
     }
@@ -166,31 +267,43 @@ impl HttpResponse {
             .with_body(body))
     }
 
-    /// Create an HTML response
-    pub fn html(content: &str) -> Self {
+    /// Create an HTML response; an owned `String` is sent without copying
+    pub fn html(content: impl Into<String>) -> Self {
         Self::new(StatusCode::OK)
             .with_header("content-type", "text/html; charset=utf-8")
-            .with_body(content.as_bytes())
+            .with_body(content.into())
     }
 
-    /// Create a text response
-    pub fn text(content: &str) -> Self {
+    /// Create a text response; an owned `String` is sent without copying
+    pub fn text(content: impl Into<String>) -> Self {
         Self::new(StatusCode::OK)
             .with_header("content-type", "text/plain; charset=utf-8")
-            .with_body(content.as_bytes())
+            .with_body(content.into())
     }
 
     /// Create an error response
     pub fn error(status: StatusCode, message: &str) -> Self {
         Self::new(status)
             .with_header("content-type", "text/plain; charset=utf-8")
-            .with_body(message.as_bytes())
+            .with_body(Bytes::copy_from_slice(message.as_bytes()))
     }
 }
 
 impl IntoResponse for HttpResponse {
     fn into_response(self) -> Response {
-        (self.status, self.headers, self.body).into_response()
+        let mut response = match self.stream {
+            Some(BodyStream(stream)) => (
+                self.status,
+                self.headers,
+                axum::body::Body::from_stream(stream),
+            )
+                .into_response(),
+            None => (self.status, self.headers, self.body).into_response(),
+        };
+        if let Some(nonce) = self.nonce {
+            response.extensions_mut().insert(csp::CspNonce(nonce));
+        }
+        response
     }
 }
 
@@ -241,11 +354,28 @@ pub enum WebSocketMessage {
     Close(Option<String>),
 }
 
+/// An open WebSocket connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketClient {
+    pub id: String,
+    pub path: String,
+    /// Connection time in seconds since the Unix epoch
+    pub connected_at: u64,
+}
+
 /// Handler registry for managing HTTP and WebSocket handlers
 pub struct HandlerRegistry {
     http_handlers: RwLock<Vec<Arc<dyn HttpHandler>>>,
     websocket_handlers: RwLock<Vec<Arc<dyn WebSocketHandler>>>,
+    websocket_clients: RwLock<HashMap<String, WebSocketClient>>,
     event_bus: Arc<dyn EventBus>,
+    /// Checks for handlers that edit; without them anyone may edit
+    access: RwLock<Option<config_api::ApiAccess>>,
+    /// Requests and connections in flight of the running server, drained
+    /// when it stops
+    drain: std::sync::RwLock<Arc<drain::Drain>>,
+    /// Counts documents opened and features used, when the host records usage
+    usage: RwLock<Option<Arc<rune_core::telemetry::UsageRecorder>>>,
 }
 
 impl HandlerRegistry {
@@ -254,10 +384,85 @@ impl HandlerRegistry {
         Self {
             http_handlers: RwLock::new(Vec::new()),
             websocket_handlers: RwLock::new(Vec::new()),
+            websocket_clients: RwLock::new(HashMap::new()),
             event_bus,
+            access: RwLock::new(None),
+            drain: std::sync::RwLock::new(Arc::new(drain::Drain::default())),
+            usage: RwLock::new(None),
+        }
+    }
+
+    /// Drain of the running server
+    pub fn drain(&self) -> Arc<drain::Drain> {
+        self.drain
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Start a fresh drain for the next server, once the last one drained
+    pub(crate) fn renew_drain(&self) {
+        *self
+            .drain
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(drain::Drain::default());
+    }
+
+    /// Check requests to handlers that edit against `access`
+    pub async fn set_access(&self, access: config_api::ApiAccess) {
+        *self.access.write().await = Some(access);
+    }
+
+    /// Access checks handlers registered later are to apply
+    pub async fn access(&self) -> Option<config_api::ApiAccess> {
+        self.access.read().await.clone()
+    }
+
+    /// Count the requests answered from now on into `usage`
+    pub async fn set_usage(&self, usage: Arc<rune_core::telemetry::UsageRecorder>) {
+        *self.usage.write().await = Some(usage);
+    }
+
+    /// Count a request answered by the handler of `route` in the usage
+    /// statistics: a document page as an opened document, others as a use
+    /// of the feature the route belongs to
+    pub(crate) async fn record_usage(&self, route: &str, document_page: bool, status: StatusCode) {
+        let usage = self.usage.read().await;
+        let Some(usage) = usage.as_ref().filter(|_| status.is_success()) else {
+            return;
+        };
+        if document_page {
+            usage.record_document_opened();
+        } else if let Some(feature) = stats::route_feature(route) {
+            usage.record_feature(feature);
         }
     }
 
+    /// Refusal response for a WebSocket upgrade from another site
+    async fn check_websocket_access(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        let access = self.access.read().await;
+        access.as_ref()?.check_websocket(request).await
+    }
+
+    /// The configured API token, which clients may send as a bearer token
+    async fn api_token(&self) -> Option<String> {
+        let access = self.access.read().await;
+        access.as_ref()?.api_token().await
+    }
+
+    /// Token to hand to the page answering `request`, see
+    /// [`config_api::page_token`]
+    async fn page_token(&self, request: &HttpRequest) -> Option<String> {
+        let access = self.access.read().await;
+        access.as_ref()?.page_token(request).await
+    }
+
+    /// Refusal response for a request to a handler that edits
+    async fn check_edit_access(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        let access = self.access.read().await;
+        access.as_ref()?.check_edit(request, "Editing").await
+    }
+
     /// Register an HTTP handler
     pub async fn register_http_handler(&self, handler: Arc<dyn HttpHandler>) -> Result<()> {
         let path = handler.path_pattern().to_string();
@@ -457,6 +662,35 @@ impl HandlerRegistry {
         let handlers = self.websocket_handlers.read().await;
         handlers.clone()
     }
+
+    /// List open WebSocket connections, oldest first
+    pub async fn list_websocket_clients(&self) -> Vec<WebSocketClient> {
+        let clients = self.websocket_clients.read().await;
+        let mut clients: Vec<WebSocketClient> = clients.values().cloned().collect();
+        clients.sort_by(|a, b| (a.connected_at, &a.id).cmp(&(b.connected_at, &b.id)));
+        clients
+    }
+
+    async fn add_websocket_client(&self, id: &str, path: &str) {
+        let connected_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let mut clients = self.websocket_clients.write().await;
+        clients.insert(
+            id.to_string(),
+            WebSocketClient {
+                id: id.to_string(),
+                path: path.to_string(),
+                connected_at,
+            },
+        );
+    }
+
+    async fn remove_websocket_client(&self, id: &str) {
+        let mut clients = self.websocket_clients.write().await;
+        clients.remove(id);
+    }
 }
 
 /// Server plugin configuration
@@ -465,9 +699,52 @@ pub struct ServerConfig {
     pub hostname: String,
     pub port: u16,
     pub enable_cors: bool,
+    /// Origins, methods and headers allowed when `enable_cors` is set; no
+    /// other origin by default
+    #[serde(default)]
+    pub cors: rune_core::CorsConfig,
     pub max_connections: Option<usize>,
+    /// How long a handler may take to answer a request before the client
+    /// gets a 504; `None` waits for it however long it takes
     pub request_timeout_secs: Option<u64>,
     pub websocket_ping_interval_secs: Option<u64>,
+    /// Advertise the server on the local network via mDNS
+    #[serde(default)]
+    pub enable_discovery: bool,
+    /// Forbid pages from loading anything but the server's own resources
+    #[serde(default)]
+    pub offline: bool,
+    /// Markdown file rendered as the page of requests for missing documents
+    #[serde(default)]
+    pub not_found_page: Option<PathBuf>,
+    /// Provider of a public URL tunneled to the server
+    #[serde(default)]
+    pub tunnel: Option<String>,
+    /// Largest request body accepted, in bytes
+    #[serde(default = "default_max_body_size")]
+    pub max_body_size: usize,
+    /// How long shutting down waits for requests in flight and WebSocket
+    /// clients before cutting them off
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+}
+
+/// Handlers taking longer than this share of their timeout are logged as
+/// slow, or than [`SLOW_HANDLER`] without a timeout
+const SLOW_HANDLER_SHARE: u32 = 2;
+
+/// Time after which a handler without a timeout is logged as slow
+const SLOW_HANDLER: Duration = Duration::from_secs(15);
+
+/// Request bodies accepted unless configured otherwise: 16 MiB
+pub const DEFAULT_MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+fn default_max_body_size() -> usize {
+    DEFAULT_MAX_BODY_SIZE
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    10
 }
 
 impl Default for ServerConfig {
@@ -476,13 +753,71 @@ impl Default for ServerConfig {
             hostname: "127.0.0.1".to_string(),
             port: 3000,
             enable_cors: true,
+            cors: rune_core::CorsConfig::default(),
             max_connections: None,
             request_timeout_secs: Some(30),
             websocket_ping_interval_secs: Some(30),
+            enable_discovery: false,
+            offline: false,
+            not_found_page: None,
+            tunnel: None,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            shutdown_grace_secs: default_shutdown_grace_secs(),
+        }
+    }
+}
+
+/// Message for a failed request, naming its trace id so its logs can be
+/// found
+fn failure_message(message: &str) -> String {
+    match trace::current() {
+        Some(trace_id) => format!("{} (request {})", message, trace_id),
+        None => message.to_string(),
+    }
+}
+
+/// Read a request body of at most `limit` bytes; a body received in one
+/// piece is not copied
+async fn read_body(
+    body: axum::body::Body,
+    headers: &HeaderMap,
+    limit: usize,
+) -> std::result::Result<Bytes, HttpResponse> {
+    use futures_util::StreamExt;
+
+    let too_large = || uploads::too_large(limit);
+    let declared = headers
+        .get(axum::http::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > limit as u64) {
+        return Err(too_large());
+    }
+
+    let mut chunks = body.into_data_stream();
+    let mut pieces: Vec<Bytes> = Vec::new();
+    let mut size = 0;
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|e| {
+            debug!("Failed to read request body: {}", e);
+            HttpResponse::error(StatusCode::BAD_REQUEST, "Failed to read request body")
+        })?;
+        size += chunk.len();
+        if size > limit {
+            return Err(too_large());
         }
+        pieces.push(chunk);
     }
+    Ok(match pieces.len() {
+        0 => Bytes::new(),
+        1 => pieces.remove(0),
+        _ => pieces.concat().into(),
+    })
 }
 
+/// Name of the supervised task running the HTTP server
+pub const SERVER_TASK: &str = "server";
+
 /// Server plugin implementation
 pub struct ServerPlugin {
     name: String,
@@ -490,9 +825,15 @@ pub struct ServerPlugin {
     status: PluginStatus,
     config: ServerConfig,
     handler_registry: Option<Arc<HandlerRegistry>>,
-    server_handle: Option<tokio::task::JoinHandle<()>>,
+    /// The HTTP server, once started
+    control: Option<Arc<control::ServerControl>>,
+    /// Supervisor running the server task, once started
+    supervisor: Option<Arc<TaskSupervisor>>,
     reload_sender: Option<tokio::sync::broadcast::Sender<handlers::ServerMessage>>,
     editor_ws_handler: Arc<RwLock<Option<Arc<editor_handlers::EditorWebSocketHandler>>>>,
+    /// Document served at `/`, which live reload clients resume against
+    document: handlers::DocumentSlot,
+    discovery: Option<discovery::LanDiscovery>,
 }
 
 impl ServerPlugin {
@@ -504,9 +845,12 @@ impl ServerPlugin {
             status: PluginStatus::Loading,
             config: ServerConfig::default(),
             editor_ws_handler: Arc::new(RwLock::new(None)),
+            document: Arc::new(RwLock::new(None)),
             handler_registry: None,
-            server_handle: None,
+            control: None,
+            supervisor: None,
             reload_sender: None,
+            discovery: None,
         }
     }
 
@@ -518,9 +862,12 @@ impl ServerPlugin {
             status: PluginStatus::Loading,
             config,
             handler_registry: None,
-            server_handle: None,
+            control: None,
+            supervisor: None,
             reload_sender: None,
             editor_ws_handler: Arc::new(RwLock::new(None)),
+            document: Arc::new(RwLock::new(None)),
+            discovery: None,
         }
     }
 
@@ -529,13 +876,173 @@ impl ServerPlugin {
         self.handler_registry.clone()
     }
 
+    /// Stop the server and serve the listening address of the current
+    /// configuration with the handlers registered now, e.g. after a port
+    /// change; returns the address bound
+    pub async fn restart(&self) -> Result<String> {
+        match &self.control {
+            Some(control) => control.restart().await,
+            None => Err(RuneError::Server("Server is not running".to_string())),
+        }
+    }
+
+    /// Keep a tunnel to the server open through `provider`
+    ///
+    /// Like discovery, the tunnel is best effort and never stops the server.
+    async fn start_tunnel(&self, provider: &str, context: &PluginContext) {
+        let provider = match tunnel::provider(provider) {
+            Ok(provider) => provider,
+            Err(e) => {
+                warn!("Tunnel unavailable: {}", e);
+                return;
+            }
+        };
+        // Dev mode leaves the editing and settings APIs open, which would let
+        // anyone with the public URL in; with a token they require it instead
+        if context.config.get_global_setting::<bool>("dev_mode") == Some(true)
+            && context
+                .config
+                .get_global_setting::<String>("api_token")
+                .is_none()
+        {
+            warn!("Not opening a tunnel in dev mode without an api_token: anyone with its URL could edit documents and change settings");
+            return;
+        }
+
+        let local = tunnel::local_address(&self.config.hostname, self.config.port);
+        let event_bus = context.event_bus.clone();
+        let supervised = context
+            .supervisor
+            .supervise(tunnel::TUNNEL_TASK, RestartPolicy::default(), move || {
+                tunnel::run(provider.clone(), local.clone(), event_bus.clone())
+            })
+            .await;
+        if let Err(e) = supervised {
+            warn!("Tunnel unavailable: {}", e);
+        }
+    }
+
+    /// Start advertising the server on the local network
+    ///
+    /// Discovery is best effort: failures are logged and never stop the server.
+    async fn start_discovery(&mut self, context: &PluginContext) {
+        if !discovery::is_lan_reachable(&self.config.hostname) {
+            warn!(
+                "LAN discovery enabled but server is bound to {}; bind to 0.0.0.0 or a LAN address to be discoverable",
+                self.config.hostname
+            );
+            return;
+        }
+
+        let title = match context.state_manager.get_state().await.current_file {
+            Some(file) => discovery::document_title(&file),
+            None => "Rune".to_string(),
+        };
+
+        match discovery::LanDiscovery::start(&title, &self.config.hostname, self.config.port) {
+            Ok(advertisement) => self.discovery = Some(advertisement),
+            Err(e) => warn!("LAN discovery unavailable: {}", e),
+        }
+    }
+
     /// Register core handlers (markdown, static files, etc.)
     async fn register_core_handlers(&self, context: &PluginContext) -> Result<()> {
         if let Some(registry) = &self.handler_registry {
+            // Share page with a QR code for opening the preview on other devices
+            let share_handler = Arc::new(handlers::ShareHandler::new(
+                "/share".to_string(),
+                self.config.hostname.clone(),
+                self.config.port,
+            ));
+            registry.register_http_handler(share_handler).await?;
+
+            // Client-side libraries bundled into the binary
+            registry
+                .register_http_handler(Arc::new(vendor::VendoredAssetHandler::new()))
+                .await?;
+
+            // Plugin status API and lifecycle event stream
+            plugins_api::register_plugin_api_handlers(registry, context).await?;
+
+            // Latest notifications, which pages also get on their live reload socket
+            notifications_api::register_notification_handlers(registry, context).await?;
+
+            // Runtime configuration API, when the host shares a config manager
+            config_api::register_config_api_handlers(registry, context).await?;
+
+            // Captured logs and runtime log levels, when the host shares a log controller
+            logs_api::register_logs_api_handlers(registry, context).await?;
+
+            // Memory budget report, with preview pages accounted against it
+            memory_api::register_memory_api_handlers(registry, context).await?;
+
+            // Local usage statistics, counted when the host enables them
+            stats::register_stats_handlers(registry, context).await?;
+
+            // Font size, line width and reader mode chosen by each client
+            reading::register_reading_api_handlers(registry).await?;
+
+            // Conversion of pasted HTML to markdown for the editors
+            convert_api::register_convert_api_handlers(registry).await?;
+
+            // Named transforms of the served document, plugins' ones included
+            transform_api::register_transform_api_handlers(
+                registry,
+                context,
+                self.document.clone(),
+            )
+            .await?;
+
+            // Command palette: editor actions, transforms, themes and exports
+            commands_api::register_command_api_handlers(registry, context, self.document.clone())
+                .await?;
+
+            // Custom page template and theme stylesheet, reloaded on change
+            template::register_template_handlers(registry, context).await?;
+
             // Get current file from application state
             let state = context.state_manager.get_state().await;
 
-            if let Some(current_file) = state.current_file {
+            // Old paths of renamed documents and configured redirects
+            let redirected = if !state.served_roots.is_empty() {
+                state.served_roots.clone()
+            } else {
+                // The single previewed document is served at `/`
+                state
+                    .current_file
+                    .iter()
+                    .map(|file| rune_core::state::ServedRoot {
+                        prefix: String::new(),
+                        path: file.clone(),
+                    })
+                    .collect()
+            };
+            // Served directories for external editors, when enabled
+            webdav::register_webdav_handler(registry, context, &redirected).await?;
+
+            // Tasks and TODO markers of the served documents
+            tasks::register_task_handlers(registry, context, &redirected).await?;
+
+            // Tags of the served documents and their listing pages
+            tags::register_tag_handlers(registry, context, &redirected).await?;
+
+            // File tree of the served documents, with the saved searches
+            search::register_search_handlers(registry, context, &redirected).await?;
+
+            // Recently edited and pinned documents of the workspace
+            recent_api::register_recent_handlers(registry, context, &redirected).await?;
+
+            // Daily notes, opened or created on request
+            journal_api::register_journal_handler(registry, context, &redirected).await?;
+
+            // Documents created from templates
+            files_api::register_file_api_handlers(registry, context, &redirected).await?;
+
+            redirects::register_redirect_handler(registry, context, redirected).await?;
+
+            if !state.served_roots.is_empty() {
+                roots::register_root_handlers(registry, &state.served_roots, context).await?;
+            } else if let Some(current_file) = state.current_file {
                 self.register_file_handlers(&current_file, context).await?;
             } else {
                 info!("No current file set during initialization, handlers will be registered when file is set");
@@ -557,26 +1064,37 @@ impl ServerPlugin {
             );
 
             // Get renderer registry from shared resources if available
-            let renderer_registry = context
-                .get_shared_resource::<rune_core::renderer::RendererRegistry>("renderer_registry")
-                .await;
+            let renderer_registry = shared_renderer_registry(context).await;
 
             // Register main markdown handler for root path
-            let markdown_handler = if let Some(renderer_registry) = renderer_registry {
-                Arc::new(handlers::MarkdownHandler::with_renderer_registry(
+            let markdown_handler = match renderer_registry.clone() {
+                Some(renderer_registry) => handlers::MarkdownHandler::with_renderer_registry(
                     "/".to_string(),
                     current_file.to_path_buf(),
                     renderer_registry,
-                ))
-            } else {
-                Arc::new(handlers::MarkdownHandler::new(
-                    "/".to_string(),
-                    current_file.to_path_buf(),
-                ))
+                ),
+                None => handlers::MarkdownHandler::new("/".to_string(), current_file.to_path_buf()),
             };
+            let markdown_handler = Arc::new(
+                markdown_handler
+                    .with_large_file_threshold(Some(context.config.large_files.threshold_bytes())),
+            );
 
+            *self.document.write().await = Some(markdown_handler.clone());
             registry.register_http_handler(markdown_handler).await?;
 
+            // Register snapshot API and viewer
+            snapshots::register_snapshot_handlers(
+                registry,
+                current_file,
+                renderer_registry.clone(),
+            )
+            .await?;
+
+            // Register version history API
+            history_api::register_history_handlers(registry, current_file, renderer_registry)
+                .await?;
+
             // Register raw markdown handler
             info!("About to register raw markdown handler");
             let raw_handler = Arc::new(handlers::RawMarkdownHandler::new(
@@ -586,6 +1104,14 @@ impl ServerPlugin {
             registry.register_http_handler(raw_handler).await?;
             info!("Successfully registered raw markdown handler");
 
+            // Register copy-as handler for the preview's copy menu
+            let copy_handler = Arc::new(copy_as::CopyHandler::new(
+                "/copy".to_string(),
+                current_file.to_path_buf(),
+                self.document.clone(),
+            ));
+            registry.register_http_handler(copy_handler).await?;
+
             // Register raw text editor handler
             info!("About to register editor handler");
             let editor_handler = Arc::new(editor_handlers::RawEditorHandler::new(
@@ -600,17 +1126,21 @@ impl ServerPlugin {
 
             // Register static file handler for assets in the same directory
             if let Some(base_dir) = current_file.parent() {
-                let static_handler = Arc::new(handlers::StaticHandler::new(
-                    base_dir.to_path_buf(),
-                    "/assets".to_string(),
-                ));
+                let symlinks = jail::SymlinkPolicy::from_config(&context.config);
+                let static_handler = Arc::new(
+                    handlers::StaticHandler::new(base_dir.to_path_buf(), "/assets".to_string())
+                        .with_symlink_policy(symlinks),
+                );
                 registry.register_http_handler(static_handler).await?;
 
                 // Also register image handler for images in the same directory
-                let image_handler = Arc::new(handlers::StaticHandler::new_image_handler(
-                    base_dir.to_path_buf(),
-                    "/images".to_string(),
-                ));
+                let image_handler = Arc::new(
+                    handlers::StaticHandler::new_image_handler(
+                        base_dir.to_path_buf(),
+                        "/images".to_string(),
+                    )
+                    .with_symlink_policy(symlinks),
+                );
                 registry.register_http_handler(image_handler).await?;
             }
 
@@ -637,16 +1167,19 @@ impl ServerPlugin {
     }
 
     /// Register WebSocket handlers for live reload
-    async fn register_websocket_handlers(&self, event_bus: Arc<dyn EventBus>) -> Result<()> {
+    async fn register_websocket_handlers(&mut self, event_bus: Arc<dyn EventBus>) -> Result<()> {
         if let Some(registry) = &self.handler_registry {
             // Create a broadcast channel for reload messages
             let (reload_sender, _) = broadcast::channel::<handlers::ServerMessage>(16);
 
             // Register live reload WebSocket handler
-            let live_reload_handler = Arc::new(handlers::LiveReloadHandler::with_reload_sender(
-                "/ws".to_string(),
-                reload_sender.clone(),
-            ));
+            let live_reload_handler = Arc::new(
+                handlers::LiveReloadHandler::with_reload_sender(
+                    "/ws".to_string(),
+                    reload_sender.clone(),
+                )
+                .with_document(self.document.clone()),
+            );
 
             registry
                 .register_websocket_handler(live_reload_handler.clone())
@@ -656,6 +1189,10 @@ impl ServerPlugin {
             let editor_ws_handler = Arc::new(editor_handlers::EditorWebSocketHandler::new(
                 "/ws/editor".to_string(),
             ));
+            editor_ws_handler
+                .set_preview_sender(reload_sender.clone())
+                .await;
+            editor_ws_handler.set_event_bus(event_bus.clone()).await;
             registry
                 .register_websocket_handler(editor_ws_handler.clone())
                 .await?;
@@ -666,11 +1203,14 @@ impl ServerPlugin {
                 *handler = Some(editor_ws_handler);
             }
 
+            self.reload_sender = Some(reload_sender.clone());
+
             // Create and register a file change event handler that will trigger reloads
             let reload_event_handler = Arc::new(LiveReloadEventHandler {
                 reload_sender,
                 live_reload_handler,
                 handler_registry: registry.clone(),
+                event_bus: event_bus.clone(),
             });
 
             event_bus
@@ -712,36 +1252,139 @@ impl ServerPlugin {
     }
 
     /// Build the Axum router with all registered handlers
-    async fn build_router(&self, registry: Arc<HandlerRegistry>) -> Router {
+    pub(crate) fn build_router(
+        config: &ServerConfig,
+        registry: Arc<HandlerRegistry>,
+        error_pages: Arc<error_pages::ErrorPages>,
+    ) -> Router {
         let registry_clone = registry.clone();
+        let max_body_size = config.max_body_size;
+        let request_timeout = config.request_timeout_secs.map(Duration::from_secs);
 
         // Create a catch-all router that dynamically handles requests
+        let dav_error_pages = error_pages.clone();
         let router = Router::new().fallback(move |req| {
             let registry = registry_clone.clone();
-            async move { Self::handle_dynamic_request(req, registry).await }
+            let error_pages = error_pages.clone();
+            async move {
+                Self::handle_dynamic_request(
+                    req,
+                    registry,
+                    error_pages,
+                    max_body_size,
+                    request_timeout,
+                )
+                .await
+            }
         });
 
-        // Add CORS if enabled
-        if self.config.enable_cors {
-            router.layer(CorsLayer::permissive())
+        // Keep pages from reaching anything but this server
+        let router = if config.offline {
+            router.layer(axum::middleware::map_response(
+                |mut response: Response| async move {
+                    let nonce = response.extensions().get::<csp::CspNonce>();
+                    let policy = csp::policy(nonce.map(|nonce| nonce.0.as_str()));
+                    if let Ok(policy) = axum::http::HeaderValue::from_str(&policy) {
+                        response
+                            .headers_mut()
+                            .insert(axum::http::header::CONTENT_SECURITY_POLICY, policy);
+                    }
+                    response
+                },
+            ))
         } else {
             router
-        }
+        };
+
+        // Refuse state-changing API requests without the CSRF token, and
+        // give clients without one a token
+        let csrf_registry = registry.clone();
+        let router = router.layer(axum::middleware::from_fn(
+            move |req: axum::extract::Request, next: axum::middleware::Next| {
+                let registry = csrf_registry.clone();
+                async move {
+                    let api_token = registry.api_token().await;
+                    if let Some(refusal) = csrf::check(
+                        req.method(),
+                        req.uri().path(),
+                        req.headers(),
+                        api_token.as_deref(),
+                    ) {
+                        return refusal;
+                    }
+                    let has_token = csrf::cookie_token(req.headers()).is_some();
+                    let mut response = next.run(req).await;
+                    if !has_token {
+                        csrf::issue(&mut response);
+                    }
+                    response
+                }
+            },
+        ));
+
+        // Add CORS if enabled and other origins are allowed
+        let drain = registry.drain();
+        let cors = config
+            .enable_cors
+            .then(|| cors::layer(&config.cors))
+            .flatten();
+        let router = if let Some(cors) = cors {
+            // WebDAV stays out of CORS, so other sites cannot script it, and
+            // its plain OPTIONS requests are not taken for preflights
+            router.layer(cors).layer(axum::middleware::from_fn(
+                move |req: axum::extract::Request, next: axum::middleware::Next| {
+                    let registry = registry.clone();
+                    let error_pages = dav_error_pages.clone();
+                    async move {
+                        if webdav::is_dav_path(req.uri().path()) {
+                            Self::handle_dynamic_request(
+                                req,
+                                registry,
+                                error_pages,
+                                max_body_size,
+                                request_timeout,
+                            )
+                            .await
+                        } else {
+                            next.run(req).await
+                        }
+                    }
+                },
+            ))
+        } else {
+            router
+        };
+
+        // Count requests in flight, so shutting down lets them finish
+        router.layer(axum::middleware::from_fn(
+            move |req: axum::extract::Request, next: axum::middleware::Next| {
+                let in_flight = drain.request();
+                async move {
+                    let response = next.run(req).await;
+                    drop(in_flight);
+                    response
+                }
+            },
+        ))
     }
 
     /// Handle dynamic HTTP request (catch-all handler)
     async fn handle_dynamic_request(
         req: axum::extract::Request,
         registry: Arc<HandlerRegistry>,
+        error_pages: Arc<error_pages::ErrorPages>,
+        max_body_size: usize,
+        request_timeout: Option<Duration>,
     ) -> Response {
-        // Check if this is a WebSocket upgrade request
+        // Check if this is a WebSocket upgrade request; connections are
+        // long-lived and take no timeout
         if req.headers().get("upgrade").and_then(|v| v.to_str().ok()) == Some("websocket") {
             return Self::handle_websocket_upgrade(req, registry)
                 .await
                 .into_response();
         }
 
-        Self::handle_http_request(req, registry)
+        Self::handle_http_request(req, registry, error_pages, max_body_size, request_timeout)
             .await
             .into_response()
     }
@@ -753,11 +1396,38 @@ impl ServerPlugin {
     ) -> Response {
         let path = req.uri().path().to_string();
 
-        if let Some(_handler) = registry.find_websocket_handler(&path).await {
+        if let Some(handler) = registry.find_websocket_handler(&path).await {
+            let request = HttpRequest {
+                method: req.method().clone(),
+                path: path.clone(),
+                query_params: req
+                    .uri()
+                    .query()
+                    .map(|q| {
+                        url::form_urlencoded::parse(q.as_bytes())
+                            .into_owned()
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                headers: req.headers().clone(),
+                body: Default::default(),
+                path_params: Default::default(),
+            };
+            // Pages of other sites may not attach to the sockets
+            if let Some(refusal) = registry.check_websocket_access(&request).await {
+                return refusal.into_response();
+            }
+            if handler.edits() {
+                if let Some(refusal) = registry.check_edit_access(&request).await {
+                    return refusal.into_response();
+                }
+            }
+
             // Handle WebSocket upgrade
             let ws_upgrade = WebSocketUpgrade::from_request(req, &()).await;
             match ws_upgrade {
                 Ok(upgrade) => upgrade
+                    .protocols(wire::WireFormat::PROTOCOLS)
                     .on_upgrade(move |socket| {
                         Self::handle_websocket_connection(socket, registry, path)
                     })
@@ -771,10 +1441,38 @@ impl ServerPlugin {
         }
     }
 
-    /// Handle HTTP request
+    /// Handle HTTP request under the trace id its client sent, or a new
+    /// one, and return the id in the `X-Request-Id` header
     async fn handle_http_request(
         req: axum::extract::Request,
         registry: Arc<HandlerRegistry>,
+        error_pages: Arc<error_pages::ErrorPages>,
+        max_body_size: usize,
+        request_timeout: Option<Duration>,
+    ) -> Response {
+        let trace_id = req
+            .headers()
+            .get(TRACE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(TraceId::parse)
+            .unwrap_or_default();
+        let span = tracing::info_span!("request", trace_id = %trace_id);
+        let handled =
+            Self::route_http_request(req, registry, error_pages, max_body_size, request_timeout);
+        let mut response = trace::scope(trace_id.clone(), handled.instrument(span)).await;
+        if let Ok(value) = axum::http::HeaderValue::from_str(trace_id.as_str()) {
+            response.headers_mut().insert(TRACE_HEADER, value);
+        }
+        response
+    }
+
+    /// Find the handler for a request and let it answer
+    async fn route_http_request(
+        req: axum::extract::Request,
+        registry: Arc<HandlerRegistry>,
+        error_pages: Arc<error_pages::ErrorPages>,
+        max_body_size: usize,
+        request_timeout: Option<Duration>,
     ) -> Response {
         use std::collections::HashMap;
 
@@ -783,6 +1481,7 @@ impl ServerPlugin {
         let uri = req.uri().clone();
         let path = uri.path().to_string();
         let headers = req.headers().clone();
+        let wants_page = error_pages::ErrorPages::wants_page(&path, &headers);
 
         // Extract query parameters
         let query_params: HashMap<String, String> = uri
@@ -794,11 +1493,15 @@ impl ServerPlugin {
             })
             .unwrap_or_default();
 
-        // Extract body
+        let handler = registry.find_http_handler(&path, &method).await;
+        let limit = handler
+            .as_ref()
+            .and_then(|handler| handler.max_body_size())
+            .unwrap_or(max_body_size);
         let (_parts, body) = req.into_parts();
-        let body_bytes = match axum::body::to_bytes(body, usize::MAX).await {
-            Ok(bytes) => bytes.to_vec(),
-            Err(_) => Vec::new(),
+        let body_bytes = match read_body(body, &headers, limit).await {
+            Ok(body) => body,
+            Err(refusal) => return refusal.into_response(),
         };
 
         // Create HttpRequest
@@ -812,13 +1515,30 @@ impl ServerPlugin {
         };
 
         // Find and call the appropriate handler
-        if let Some(handler) = registry.find_http_handler(&path, &method).await {
-            match handler.handle(http_request).await {
-                Ok(response) => response.into_response(),
-                Err(e) => {
-                    tracing::error!("Handler error for {} {}: {}", method, path, e);
-                    HttpResponse::error(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
-                        .into_response()
+        let response = if let Some(handler) = handler {
+            let refusal = if handler.edits() {
+                registry.check_edit_access(&http_request).await
+            } else {
+                None
+            };
+            match refusal {
+                Some(refusal) => refusal,
+                None => {
+                    let timeout = handler.timeout().or(request_timeout);
+                    let route = handler.path_pattern().to_string();
+                    let document_page = wants_page
+                        && (handler.as_any().is::<handlers::MarkdownHandler>()
+                            || handler.as_any().is::<roots::RootHandler>());
+                    let page_token = registry.page_token(&http_request).await;
+                    let mut response = Self::run_handler(handler, http_request, timeout).await;
+                    registry
+                        .record_usage(&route, document_page, response.status)
+                        .await;
+                    // Pages opened with the token open their sockets with it
+                    if let Some(token) = page_token {
+                        response = config_api::with_page_token(response, &token);
+                    }
+                    response
                 }
             }
         } else {
@@ -841,7 +1561,91 @@ impl ServerPlugin {
                 );
             }
 
-            HttpResponse::error(StatusCode::NOT_FOUND, "Not found").into_response()
+            HttpResponse::error(StatusCode::NOT_FOUND, "Not found")
+        };
+
+        // Browsers get a themed page for a failed request
+        if wants_page {
+            error_pages.replace(&path, response).await.into_response()
+        } else {
+            response.into_response()
+        }
+    }
+
+    /// Let `handler` answer `request` within `timeout`, logging it when it
+    /// is slow
+    async fn run_handler(
+        handler: Arc<dyn HttpHandler>,
+        request: HttpRequest,
+        timeout: Option<Duration>,
+    ) -> HttpResponse {
+        let (method, path) = (request.method.clone(), request.path.clone());
+        let route = handler.path_pattern();
+        let started = std::time::Instant::now();
+
+        // A panicking page render fails this request only
+        let span = tracing::debug_span!("handler", name = %route, method = %method);
+        let handled = crash::catch_panic(&path, handler.handle(request).instrument(span));
+        let outcome = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, handled).await.ok(),
+            None => Some(handled.await),
+        };
+
+        let elapsed = started.elapsed();
+        let slow_after = timeout.map_or(SLOW_HANDLER, |timeout| timeout / SLOW_HANDLER_SHARE);
+        if elapsed >= slow_after {
+            tracing::warn!(
+                "Slow handler {} took {:.1}s for {} {}",
+                route,
+                elapsed.as_secs_f64(),
+                method,
+                path
+            );
+        }
+
+        match outcome {
+            Some(Ok(Ok(response))) => response,
+            Some(Ok(Err(e))) => {
+                tracing::error!("Handler error for {} {}: {}", method, path, e);
+                HttpResponse::error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    &failure_message("Internal server error"),
+                )
+            }
+            Some(Err(report)) => {
+                tracing::error!("Handler for {} {}", method, report.summary());
+                HttpResponse::error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    &failure_message("Internal server error"),
+                )
+            }
+            None => {
+                tracing::warn!(
+                    "Handler {} timed out after {:.1}s for {} {}",
+                    route,
+                    elapsed.as_secs_f64(),
+                    method,
+                    path
+                );
+                HttpResponse::error(
+                    StatusCode::GATEWAY_TIMEOUT,
+                    &failure_message("The server took too long to answer the request"),
+                )
+            }
+        }
+    }
+
+    /// Pass a WebSocket message to `handler` under a new trace id
+    async fn dispatch_message(
+        handler: &dyn WebSocketHandler,
+        connection: &WebSocketConnection,
+        message: WebSocketMessage,
+    ) {
+        let trace_id = TraceId::new();
+        let span = tracing::info_span!("message", trace_id = %trace_id);
+        let handled = handler.on_message(connection, message).instrument(span);
+        if let Err(e) = trace::scope(trace_id, handled).await {
+            tracing::error!("WebSocket handler on_message error: {}", e);
         }
     }
 
@@ -857,6 +1661,14 @@ impl ServerPlugin {
         // Generate connection ID
         let connection_id = Uuid::new_v4().to_string();
 
+        // Encoding the client asked for in the handshake
+        let format = wire::WireFormat::from_protocol(
+            socket
+                .protocol()
+                .and_then(|protocol| protocol.to_str().ok()),
+        )
+        .unwrap_or_default();
+
         // Create broadcast channel for this connection
         let (tx, _rx) = broadcast::channel::<WebSocketMessage>(16);
 
@@ -870,18 +1682,49 @@ impl ServerPlugin {
 
         // Find the WebSocket handler
         if let Some(handler) = registry.find_websocket_handler(&path).await {
+            // Subscribe before notifying the handler so messages sent on connect are delivered
+            let mut rx = connection.sender.subscribe();
+
             // Notify handler of connection
             if let Err(e) = handler.on_connect(&connection).await {
                 tracing::error!("WebSocket handler on_connect error: {}", e);
                 return;
             }
 
+            registry.add_websocket_client(&connection_id, &path).await;
+            let open = registry.drain().websocket();
             let (mut ws_sender, mut ws_receiver) = socket.split();
-            let mut rx = connection.sender.subscribe();
 
             // Spawn task to handle outgoing messages
+            let drain = registry.drain();
             let send_task = tokio::spawn(async move {
-                while let Ok(msg) = rx.recv().await {
+                loop {
+                    let msg = tokio::select! {
+                        msg = rx.recv() => match msg {
+                            Ok(msg) => msg,
+                            Err(_) => break,
+                        },
+                        // Tell the client the server is going away
+                        _ = drain.begun() => {
+                            let close = axum::extract::ws::Message::Close(Some(
+                                axum::extract::ws::CloseFrame {
+                                    code: axum::extract::ws::close_code::AWAY,
+                                    reason: drain::SHUTDOWN_REASON.into(),
+                                },
+                            ));
+                            if ws_sender.send(close).await.is_ok() {
+                                drain.websocket_closed();
+                            }
+                            break;
+                        }
+                    };
+                    let msg = match format.encode(msg) {
+                        Ok(msg) => msg,
+                        Err(e) => {
+                            tracing::warn!("Dropping WebSocket message: {}", e);
+                            continue;
+                        }
+                    };
                     let ws_msg = match msg {
                         WebSocketMessage::Text(text) => axum::extract::ws::Message::Text(text),
                         WebSocketMessage::Binary(data) => axum::extract::ws::Message::Binary(data),
@@ -903,39 +1746,49 @@ impl ServerPlugin {
                 }
             });
 
-            // Handle incoming messages
-            while let Some(msg) = ws_receiver.next().await {
+            // Handle incoming messages, until the client answers the Close
+            // frame of a drain or fails to in time
+            let drain = registry.drain();
+            let drained = async move {
+                drain.begun().await;
+                tokio::time::sleep(drain::CLOSE_HANDSHAKE).await;
+            };
+            tokio::pin!(drained);
+            loop {
+                let msg = tokio::select! {
+                    msg = ws_receiver.next() => match msg {
+                        Some(msg) => msg,
+                        None => break,
+                    },
+                    _ = &mut drained => break,
+                };
                 match msg {
                     Ok(axum::extract::ws::Message::Text(text)) => {
                         let ws_msg = WebSocketMessage::Text(text);
-                        if let Err(e) = handler.on_message(&connection, ws_msg).await {
-                            tracing::error!("WebSocket handler on_message error: {}", e);
-                        }
+                        Self::dispatch_message(&*handler, &connection, ws_msg).await;
                     }
                     Ok(axum::extract::ws::Message::Binary(data)) => {
-                        let ws_msg = WebSocketMessage::Binary(data);
-                        if let Err(e) = handler.on_message(&connection, ws_msg).await {
-                            tracing::error!("WebSocket handler on_message error: {}", e);
-                        }
+                        let ws_msg = match format.decode(WebSocketMessage::Binary(data)) {
+                            Ok(ws_msg) => ws_msg,
+                            Err(e) => {
+                                tracing::warn!("Ignoring WebSocket message: {}", e);
+                                continue;
+                            }
+                        };
+                        Self::dispatch_message(&*handler, &connection, ws_msg).await;
                     }
                     Ok(axum::extract::ws::Message::Ping(data)) => {
                         let ws_msg = WebSocketMessage::Ping(data);
-                        if let Err(e) = handler.on_message(&connection, ws_msg).await {
-                            tracing::error!("WebSocket handler on_message error: {}", e);
-                        }
+                        Self::dispatch_message(&*handler, &connection, ws_msg).await;
                     }
                     Ok(axum::extract::ws::Message::Pong(data)) => {
                         let ws_msg = WebSocketMessage::Pong(data);
-                        if let Err(e) = handler.on_message(&connection, ws_msg).await {
-                            tracing::error!("WebSocket handler on_message error: {}", e);
-                        }
+                        Self::dispatch_message(&*handler, &connection, ws_msg).await;
                     }
                     Ok(axum::extract::ws::Message::Close(frame)) => {
                         let reason = frame.map(|f| f.reason.to_string());
                         let ws_msg = WebSocketMessage::Close(reason);
-                        if let Err(e) = handler.on_message(&connection, ws_msg).await {
-                            tracing::error!("WebSocket handler on_message error: {}", e);
-                        }
+                        Self::dispatch_message(&*handler, &connection, ws_msg).await;
                         break;
                     }
                     Err(e) => {
@@ -947,9 +1800,11 @@ impl ServerPlugin {
 
             // Clean up
             send_task.abort();
+            registry.remove_websocket_client(&connection_id).await;
             if let Err(e) = handler.on_disconnect(&connection).await {
                 tracing::error!("WebSocket handler on_disconnect error: {}", e);
             }
+            drop(open);
         } else {
             tracing::debug!("No WebSocket handler found for path: {}", path);
         }
@@ -976,6 +1831,8 @@ impl Plugin for ServerPlugin {
         // Load configuration from global config (not plugin namespace)
         self.config.hostname = context.config.server.hostname.clone();
         self.config.port = context.config.server.port;
+        self.config.enable_cors = context.config.server.cors_enabled;
+        self.config.cors = context.config.server.cors.clone();
 
         // Load additional server config from plugin context if available
         if let Ok(Some(plugin_config)) = context.get_config_value::<ServerConfig>("server").await {
@@ -984,6 +1841,32 @@ impl Plugin for ServerPlugin {
             self.config.max_connections = plugin_config.max_connections;
             self.config.request_timeout_secs = plugin_config.request_timeout_secs;
             self.config.websocket_ping_interval_secs = plugin_config.websocket_ping_interval_secs;
+            self.config.enable_discovery = plugin_config.enable_discovery;
+            self.config.max_body_size = plugin_config.max_body_size;
+            self.config.shutdown_grace_secs = plugin_config.shutdown_grace_secs;
+        }
+
+        if let Some(size) = context.config.get_global_setting::<usize>("max_body_size") {
+            self.config.max_body_size = size;
+        }
+
+        if let Some(enabled) = context.config.get_global_setting::<bool>("lan_discovery") {
+            self.config.enable_discovery = enabled;
+        }
+
+        if let Some(offline) = context.config.get_global_setting::<bool>("offline") {
+            self.config.offline = offline;
+        }
+
+        if let Some(page) = context
+            .config
+            .get_global_setting::<String>("not_found_page")
+        {
+            self.config.not_found_page = Some(PathBuf::from(page));
+        }
+
+        if let Some(provider) = context.config.get_global_setting::<String>("tunnel") {
+            self.config.tunnel = Some(provider);
         }
 
         info!(
@@ -1000,6 +1883,9 @@ impl Plugin for ServerPlugin {
             .await?;
 
         self.handler_registry = Some(registry.clone());
+        registry
+            .set_access(config_api::ApiAccess::from_context(context).await)
+            .await;
 
         // Register core handlers
         self.register_core_handlers(context).await?;
@@ -1012,6 +1898,44 @@ impl Plugin for ServerPlugin {
         self.register_websocket_handlers(context.event_bus.clone())
             .await?;
 
+        // The file handlers were registered before the editor socket existed,
+        // which would otherwise only learn its document from a file event
+        if let Some(current_file) = context.state_manager.get_state().await.current_file {
+            if let Some(editor_handler) = self.editor_ws_handler.read().await.as_ref() {
+                editor_handler.set_markdown_file(current_file).await;
+            }
+        }
+
+        let error_pages = Arc::new(error_pages::ErrorPages::new(
+            self.config.not_found_page.clone(),
+            context.state_manager.get_state().await.served_roots,
+            shared_renderer_registry(context).await,
+        ));
+        let control = Arc::new(control::ServerControl::new(
+            context.clone(),
+            registry.clone(),
+            error_pages,
+            self.config.clone(),
+        ));
+
+        // Developer dashboard and restarts, only in dev mode
+        if context
+            .config
+            .get_global_setting::<bool>("dev_mode")
+            .unwrap_or(false)
+        {
+            if let Some(reload_sender) = self.reload_sender.clone() {
+                dashboard::register_dashboard_handlers(&registry, context, reload_sender).await?;
+            }
+            profile_api::register_profile_api_handler(&registry, context).await?;
+            registry
+                .register_http_handler(Arc::new(control::ServerRestartHandler::new(
+                    "/api/server/restart".to_string(),
+                    control.clone(),
+                )))
+                .await?;
+        }
+
         // Subscribe to system events to handle file changes
         // Note: We no longer start our own file monitoring - we rely on the FileWatcher plugin
         let server_event_handler = Arc::new(ServerEventHandler {
@@ -1019,6 +1943,7 @@ impl Plugin for ServerPlugin {
             handler_registry: registry.clone(),
             current_served_file: Arc::new(RwLock::new(None)),
             editor_ws_handler: self.editor_ws_handler.clone(),
+            document: self.document.clone(),
         });
 
         context
@@ -1029,32 +1954,21 @@ impl Plugin for ServerPlugin {
         info!("Server plugin will rely on FileWatcher plugin for file change detection");
 
         // Build and start the server
-        let router = self.build_router(registry).await;
-        let addr = format!("{}:{}", self.config.hostname, self.config.port);
-
-        info!("Starting HTTP server on {}", addr);
-
-        let listener = TcpListener::bind(&addr)
-            .await
-            .map_err(|e| RuneError::Server(format!("Failed to bind to {}: {}", addr, e)))?;
-
-        // Spawn server task
-        let server_handle = tokio::spawn(async move {
-            if let Err(e) = axum::serve(listener, router).await {
-                error!("Server error: {}", e);
-            }
-        });
+        let addr = control.start().await?;
+        self.control = Some(control);
+        self.supervisor = Some(context.supervisor.clone());
 
-        self.server_handle = Some(server_handle);
         self.status = PluginStatus::Active;
 
-        // Publish server started event
-        context
-            .event_bus
-            .publish_system_event(SystemEvent::server_started(addr))
-            .await?;
+        if self.config.enable_discovery {
+            self.start_discovery(context).await;
+        }
+
+        if let Some(provider) = self.config.tunnel.clone() {
+            self.start_tunnel(&provider, context).await;
+        }
 
-        info!("Server plugin initialized successfully");
+        info!("Server plugin initialized successfully on {}", addr);
         Ok(())
     }
 
@@ -1063,12 +1977,17 @@ impl Plugin for ServerPlugin {
 
         self.status = PluginStatus::Shutting;
 
-        // Stop the server
-        if let Some(handle) = self.server_handle.take() {
-            handle.abort();
+        // Withdraw the LAN advertisement before the server goes away
+        if let Some(discovery) = self.discovery.take() {
+            discovery.shutdown();
+        }
 
-            // Wait a bit for graceful shutdown
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        // Stop the server, letting requests in flight finish
+        if let Some(supervisor) = self.supervisor.take() {
+            supervisor.stop(tunnel::TUNNEL_TASK).await;
+        }
+        if let Some(control) = self.control.take() {
+            control.stop().await;
         }
 
         // Clear handler registry
@@ -1091,6 +2010,16 @@ impl Plugin for ServerPlugin {
         vec!["http_server", "websocket_server", "handler_registry"]
     }
 
+    fn config_schema(&self) -> HashMap<String, FieldSchema> {
+        HashMap::from([(
+            "server".to_string(),
+            FieldSchema::new(
+                FieldType::Object,
+                "CORS, timeouts, body size and shutdown grace of the server",
+            ),
+        )])
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -1112,6 +2041,7 @@ struct ServerEventHandler {
     handler_registry: Arc<HandlerRegistry>,
     current_served_file: Arc<RwLock<Option<PathBuf>>>,
     editor_ws_handler: Arc<RwLock<Option<Arc<editor_handlers::EditorWebSocketHandler>>>>,
+    document: handlers::DocumentSlot,
 }
 
 #[async_trait]
@@ -1171,12 +2101,16 @@ struct LiveReloadEventHandler {
     reload_sender: broadcast::Sender<handlers::ServerMessage>,
     live_reload_handler: Arc<handlers::LiveReloadHandler>,
     handler_registry: Arc<HandlerRegistry>,
+    event_bus: Arc<dyn EventBus>,
 }
 
 #[async_trait]
 impl rune_core::event::SystemEventHandler for LiveReloadEventHandler {
     async fn handle_system_event(&self, event: &rune_core::event::SystemEvent) -> Result<()> {
         match event {
+            // Template and stylesheet edits reload pages on their own
+            rune_core::event::SystemEvent::FileChanged { change_type, .. }
+                if change_type.is_template_change() => {}
             rune_core::event::SystemEvent::FileChanged { path, .. } => {
                 info!(
                     "File changed, triggering optimized content push: {}",
@@ -1198,6 +2132,14 @@ impl rune_core::event::SystemEventHandler for LiveReloadEventHandler {
                     info!("Successfully pushed content update via WebSocket");
                 }
             }
+            rune_core::event::SystemEvent::Notification { notification, .. } => {
+                // Sending only fails when no page is open
+                let _ = self
+                    .reload_sender
+                    .send(handlers::ServerMessage::Notification {
+                        notification: notification.clone(),
+                    });
+            }
             _ => {
                 // Ignore other events
             }
@@ -1210,6 +2152,26 @@ impl rune_core::event::SystemEventHandler for LiveReloadEventHandler {
     }
 }
 
+/// Renderer registry the renderer plugin shares as an `Arc<RendererRegistry>`
+pub(crate) async fn shared_renderer_registry(
+    context: &PluginContext,
+) -> Option<Arc<rune_core::renderer::RendererRegistry>> {
+    context
+        .get_shared_resource::<Arc<rune_core::renderer::RendererRegistry>>("renderer_registry")
+        .await
+        .map(|registry| registry.as_ref().clone())
+}
+
+/// Publish a render outcome without blocking the event currently being handled
+pub(crate) fn publish_detached(event_bus: &Arc<dyn EventBus>, event: SystemEvent) {
+    let event_bus = event_bus.clone();
+    trace::spawn(async move {
+        if let Err(e) = event_bus.publish_system_event(event).await {
+            warn!("Failed to publish render event: {}", e);
+        }
+    });
+}
+
 impl LiveReloadEventHandler {
     /// Try to push content update directly via WebSocket
     async fn try_push_content_update(&self, file_path: &std::path::Path) -> Result<()> {
@@ -1218,10 +2180,53 @@ impl LiveReloadEventHandler {
             file_path.display()
         );
 
+        // A changed image re-renders the documents that may show it, since
+        // their cached HTML carries the image's old dimensions; a changed
+        // glossary those below it, whose terms it explains
+        let is_image = rune_core::export::guess_mime_type(file_path).starts_with("image/");
+        let is_glossary = rune_core::glossary::is_glossary_file(file_path);
+        if is_image || is_glossary {
+            let changed = file_path
+                .canonicalize()
+                .unwrap_or_else(|_| file_path.to_path_buf());
+            let mut pushed = false;
+            for handler in self.handler_registry.get_all_http_handlers().await {
+                let Some(markdown_handler) =
+                    handler.as_any().downcast_ref::<handlers::MarkdownHandler>()
+                else {
+                    continue;
+                };
+                let base_dir = markdown_handler
+                    .base_dir()
+                    .canonicalize()
+                    .unwrap_or_else(|_| markdown_handler.base_dir().to_path_buf());
+                let affected = if is_glossary {
+                    changed
+                        .parent()
+                        .is_some_and(|dir| base_dir.starts_with(dir))
+                } else {
+                    changed.starts_with(&base_dir)
+                };
+                if affected {
+                    markdown_handler.clear_cache().await;
+                    markdown_handler
+                        .render_and_push_content(&self.live_reload_handler)
+                        .await?;
+                    pushed = true;
+                }
+            }
+            if pushed {
+                return Ok(());
+            }
+        }
+
         // Check if this is a markdown file
-        if let Some(extension) = file_path.extension() {
-            if extension == "md" || extension == "markdown" {
-                debug!("File is markdown, looking for handlers");
+        if let Some(extension) = rune_core::crypto::plain_path(file_path).extension() {
+            if extension == "md"
+                || extension == "markdown"
+                || rune_core::pandoc::input_format(file_path).is_some()
+            {
+                debug!("File is a document, looking for handlers");
 
                 // Get all HTTP handlers from the registry
                 let handlers = self.handler_registry.get_all_http_handlers().await;
@@ -1271,9 +2276,32 @@ impl LiveReloadEventHandler {
                             info!("Found matching handler for file: {}", file_path.display());
 
                             // Use the markdown handler to render and push content
-                            markdown_handler
+                            let start = std::time::Instant::now();
+                            let event = match markdown_handler
                                 .render_and_push_content(&self.live_reload_handler)
-                                .await?;
+                                .await
+                            {
+                                Ok(()) => SystemEvent::render_complete(
+                                    markdown_handler.content_hash().await,
+                                    start.elapsed(),
+                                ),
+                                Err(e) => {
+                                    publish_detached(
+                                        &self.event_bus,
+                                        SystemEvent::error(
+                                            "server".to_string(),
+                                            format!(
+                                                "Failed to render {}: {}",
+                                                file_path.display(),
+                                                e
+                                            ),
+                                            rune_core::event::ErrorSeverity::Medium,
+                                        ),
+                                    );
+                                    return Err(e);
+                                }
+                            };
+                            publish_detached(&self.event_bus, event);
                             return Ok(());
                         } else {
                             debug!(
@@ -1310,10 +2338,13 @@ impl ServerEventHandler {
     /// Register handlers for a new file (when the current file changes)
     async fn register_handlers_for_new_file(&self, file_path: &Path) -> Result<()> {
         // Only register handlers for markdown files
-        if let Some(extension) = file_path.extension() {
-            if extension != "md" && extension != "markdown" {
+        if let Some(extension) = rune_core::crypto::plain_path(file_path).extension() {
+            if extension != "md"
+                && extension != "markdown"
+                && rune_core::pandoc::input_format(file_path).is_none()
+            {
                 info!(
-                    "Skipping handler registration for non-markdown file: {}",
+                    "Skipping handler registration for non-document file: {}",
                     file_path.display()
                 );
                 return Ok(());
@@ -1332,29 +2363,42 @@ impl ServerEventHandler {
         );
 
         // Get renderer registry from shared resources if available
-        let renderer_registry = self
-            .plugin_context
-            .get_shared_resource::<rune_core::renderer::RendererRegistry>("renderer_registry")
-            .await;
+        let renderer_registry = shared_renderer_registry(&self.plugin_context).await;
 
         // Register main markdown handler for root path
-        let markdown_handler = if let Some(renderer_registry) = renderer_registry {
-            Arc::new(handlers::MarkdownHandler::with_renderer_registry(
+        let markdown_handler = match renderer_registry.clone() {
+            Some(renderer_registry) => handlers::MarkdownHandler::with_renderer_registry(
                 "/".to_string(),
                 file_path.to_path_buf(),
                 renderer_registry,
-            ))
-        } else {
-            Arc::new(handlers::MarkdownHandler::new(
-                "/".to_string(),
-                file_path.to_path_buf(),
-            ))
+            ),
+            None => handlers::MarkdownHandler::new("/".to_string(), file_path.to_path_buf()),
         };
+        let markdown_handler = Arc::new(markdown_handler.with_large_file_threshold(Some(
+            self.plugin_context.config.large_files.threshold_bytes(),
+        )));
 
+        *self.document.write().await = Some(markdown_handler.clone());
         self.handler_registry
             .register_http_handler(markdown_handler)
             .await?;
 
+        // Register snapshot API and viewer
+        snapshots::register_snapshot_handlers(
+            &self.handler_registry,
+            file_path,
+            renderer_registry.clone(),
+        )
+        .await?;
+
+        // Register version history API
+        history_api::register_history_handlers(
+            &self.handler_registry,
+            file_path,
+            renderer_registry,
+        )
+        .await?;
+
         // Register raw markdown handler
         let raw_handler = Arc::new(handlers::RawMarkdownHandler::new(
             "/raw".to_string(),
@@ -1364,6 +2408,16 @@ impl ServerEventHandler {
             .register_http_handler(raw_handler)
             .await?;
 
+        // Register copy-as handler for the preview's copy menu
+        let copy_handler = Arc::new(copy_as::CopyHandler::new(
+            "/copy".to_string(),
+            file_path.to_path_buf(),
+            self.document.clone(),
+        ));
+        self.handler_registry
+            .register_http_handler(copy_handler)
+            .await?;
+
         // Register raw text editor handler
         let editor_handler = Arc::new(editor_handlers::RawEditorHandler::new(
             "/editor".to_string(),
@@ -1399,19 +2453,23 @@ impl ServerEventHandler {
 
         // Register static file handler for assets in the same directory
         if let Some(base_dir) = file_path.parent() {
-            let static_handler = Arc::new(handlers::StaticHandler::new(
-                base_dir.to_path_buf(),
-                "/assets".to_string(),
-            ));
+            let symlinks = jail::SymlinkPolicy::from_config(&self.plugin_context.config);
+            let static_handler = Arc::new(
+                handlers::StaticHandler::new(base_dir.to_path_buf(), "/assets".to_string())
+                    .with_symlink_policy(symlinks),
+            );
             self.handler_registry
                 .register_http_handler(static_handler)
                 .await?;
 
             // Also register image handler for images in the same directory
-            let image_handler = Arc::new(handlers::StaticHandler::new_image_handler(
-                base_dir.to_path_buf(),
-                "/images".to_string(),
-            ));
+            let image_handler = Arc::new(
+                handlers::StaticHandler::new_image_handler(
+                    base_dir.to_path_buf(),
+                    "/images".to_string(),
+                )
+                .with_symlink_policy(symlinks),
+            );
             self.handler_registry
                 .register_http_handler(image_handler)
                 .await?;
@@ -1452,19 +2510,356 @@ mod tests {
         // assert!(registry.list_http_handlers().await.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_editing_handlers_refuse_remote_clients() {
+        let registry = Arc::new(HandlerRegistry::new(Arc::new(
+            rune_core::event::InMemoryEventBus::new(),
+        )));
+        registry
+            .register_websocket_handler(Arc::new(EditorWebSocketHandler::new(
+                "/ws/editor".to_string(),
+            )))
+            .await
+            .unwrap();
+        registry
+            .register_http_handler(Arc::new(handlers::ThemeApiHandler::new(
+                "/api/theme".to_string(),
+                registry.event_bus.clone(),
+            )))
+            .await
+            .unwrap();
+        let mut config = rune_core::config::Config::new();
+        config
+            .set_global_setting("dev_mode".to_string(), true)
+            .unwrap();
+        registry
+            .set_access(config_api::ApiAccess::new(Arc::new(config), None))
+            .await;
+        let error_pages = Arc::new(error_pages::ErrorPages::new(None, Vec::new(), None));
+
+        // Through a tunnel the Host is public, so dev mode alone is not enough
+        let socket = |host: &str| {
+            axum::extract::Request::builder()
+                .uri("/ws/editor")
+                .header("host", host)
+                .header("upgrade", "websocket")
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        let response = ServerPlugin::handle_dynamic_request(
+            socket("x1.lhr.life"),
+            registry.clone(),
+            error_pages.clone(),
+            DEFAULT_MAX_BODY_SIZE,
+            None,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        // Local clients get to the upgrade, which this request cannot complete
+        let response = ServerPlugin::handle_dynamic_request(
+            socket("localhost:3000"),
+            registry.clone(),
+            error_pages.clone(),
+            DEFAULT_MAX_BODY_SIZE,
+            None,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let switch = |host: &str, origin: &str| {
+            axum::extract::Request::builder()
+                .method(Method::POST)
+                .uri("/api/theme")
+                .header("host", host)
+                .header("origin", origin)
+                .body(axum::body::Body::from(r#"{"theme": "dark"}"#))
+                .unwrap()
+        };
+        let response = ServerPlugin::handle_dynamic_request(
+            switch("localhost:3000", "https://evil.example"),
+            registry.clone(),
+            error_pages.clone(),
+            DEFAULT_MAX_BODY_SIZE,
+            None,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = ServerPlugin::handle_dynamic_request(
+            switch("localhost:3000", "http://localhost:3000"),
+            registry,
+            error_pages,
+            DEFAULT_MAX_BODY_SIZE,
+            None,
+        )
+        .await;
+        assert_ne!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_pages_open_sockets_with_the_required_token() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = temp_dir.path().join("doc.md");
+        std::fs::write(&file, "# Doc\n").unwrap();
+        let registry = Arc::new(HandlerRegistry::new(Arc::new(
+            rune_core::event::InMemoryEventBus::new(),
+        )));
+        registry
+            .register_http_handler(Arc::new(handlers::MarkdownHandler::new(
+                "/".to_string(),
+                file,
+            )))
+            .await
+            .unwrap();
+        registry
+            .register_websocket_handler(Arc::new(EditorWebSocketHandler::new(
+                "/ws/editor".to_string(),
+            )))
+            .await
+            .unwrap();
+        let mut config = rune_core::config::Config::new();
+        config
+            .set_global_setting("websocket_requires_token".to_string(), true)
+            .unwrap();
+        config
+            .set_global_setting("api_token".to_string(), "0123456789abcdef")
+            .unwrap();
+        registry
+            .set_access(config_api::ApiAccess::new(Arc::new(config), None))
+            .await;
+        let error_pages = Arc::new(error_pages::ErrorPages::new(None, Vec::new(), None));
+        let request = |uri: &str, socket: bool| {
+            let builder = axum::extract::Request::builder()
+                .uri(uri)
+                .header("host", "localhost:3000");
+            let builder = if socket {
+                builder
+                    .header("origin", "http://localhost:3000")
+                    .header("upgrade", "websocket")
+            } else {
+                builder
+            };
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+        let serve = |request| {
+            ServerPlugin::handle_dynamic_request(
+                request,
+                registry.clone(),
+                error_pages.clone(),
+                DEFAULT_MAX_BODY_SIZE,
+                None,
+            )
+        };
+        let page = |response: Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+
+        // A page opened without the token does not get it
+        let response = serve(request("/", false)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!page(response).await.contains("<meta name=\"rune-token\""));
+        let response = serve(request("/ws/editor", true)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = serve(request("/?token=0123456789abcdef", false)).await;
+        assert_eq!(response.headers()["cache-control"], "no-store");
+        let page = page(response).await;
+        let token = regex::Regex::new(r#"<meta name="rune-token" content="([^"]*)">"#)
+            .unwrap()
+            .captures(&page)
+            .map(|caps| caps[1].to_string())
+            .unwrap();
+        assert!(page.contains("`${url}?token=${encodeURIComponent(RUNE_TOKEN)}`"));
+        // The socket URL the page builds gets past the access checks to the
+        // upgrade, which this request cannot complete
+        let response = serve(request(&format!("/ws/editor?token={}", token), true)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_slow_handlers_time_out() {
+        struct Sleepy(Option<Duration>);
+
+        #[async_trait]
+        impl HttpHandler for Sleepy {
+            fn path_pattern(&self) -> &str {
+                "/sleepy"
+            }
+
+            fn method(&self) -> Method {
+                Method::GET
+            }
+
+            async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                Ok(HttpResponse::text("awake"))
+            }
+
+            fn timeout(&self) -> Option<Duration> {
+                self.0
+            }
+
+            fn as_any(&self) -> &dyn std::any::Any {
+                self
+            }
+        }
+
+        let request = || {
+            axum::extract::Request::builder()
+                .uri("/sleepy")
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        let status = |handler: Sleepy, timeout: Option<Duration>| async move {
+            let registry = Arc::new(HandlerRegistry::new(Arc::new(
+                rune_core::event::InMemoryEventBus::new(),
+            )));
+            registry
+                .register_http_handler(Arc::new(handler))
+                .await
+                .unwrap();
+            let error_pages = Arc::new(error_pages::ErrorPages::new(None, Vec::new(), None));
+            ServerPlugin::handle_dynamic_request(
+                request(),
+                registry,
+                error_pages,
+                DEFAULT_MAX_BODY_SIZE,
+                timeout,
+            )
+            .await
+            .status()
+        };
+
+        let short = Some(Duration::from_millis(20));
+        assert_eq!(
+            status(Sleepy(None), short).await,
+            StatusCode::GATEWAY_TIMEOUT
+        );
+        assert_eq!(status(Sleepy(None), None).await, StatusCode::OK);
+        // A handler's own timeout wins over the server's
+        let long = Some(Duration::from_secs(10));
+        assert_eq!(status(Sleepy(long), short).await, StatusCode::OK);
+        assert_eq!(
+            status(Sleepy(short), long).await,
+            StatusCode::GATEWAY_TIMEOUT
+        );
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_traced() {
+        struct Broken;
+
+        #[async_trait]
+        impl HttpHandler for Broken {
+            fn path_pattern(&self) -> &str {
+                "/broken"
+            }
+
+            fn method(&self) -> Method {
+                Method::GET
+            }
+
+            async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+                assert!(trace::current().is_some());
+                Err(RuneError::generic("broken"))
+            }
+
+            fn as_any(&self) -> &dyn std::any::Any {
+                self
+            }
+        }
+
+        let registry = Arc::new(HandlerRegistry::new(Arc::new(
+            rune_core::event::InMemoryEventBus::new(),
+        )));
+        registry
+            .register_http_handler(Arc::new(Broken))
+            .await
+            .unwrap();
+        let error_pages = Arc::new(error_pages::ErrorPages::new(None, Vec::new(), None));
+        let traced = |request_id: Option<&str>| {
+            let mut request = axum::extract::Request::builder().uri("/broken");
+            if let Some(request_id) = request_id {
+                request = request.header(TRACE_HEADER, request_id);
+            }
+            let request = request.body(axum::body::Body::empty()).unwrap();
+            let (registry, error_pages) = (registry.clone(), error_pages.clone());
+            async move {
+                let response = ServerPlugin::handle_dynamic_request(
+                    request,
+                    registry,
+                    error_pages,
+                    DEFAULT_MAX_BODY_SIZE,
+                    None,
+                )
+                .await;
+                let trace_id = response.headers()[TRACE_HEADER]
+                    .to_str()
+                    .unwrap()
+                    .to_string();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (trace_id, String::from_utf8(body.to_vec()).unwrap())
+            }
+        };
+
+        // A client's id is kept and reported with the failure
+        let (trace_id, body) = traced(Some("client-7")).await;
+        assert_eq!(trace_id, "client-7");
+        assert_eq!(body, "Internal server error (request client-7)");
+
+        for request_id in [None, Some("not an id")] {
+            let (trace_id, body) = traced(request_id).await;
+            assert!(TraceId::parse(&trace_id).is_some());
+            assert_ne!(trace_id, "not an id");
+            assert!(body.ends_with(&format!("(request {})", trace_id)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oversized_bodies_are_refused() {
+        let body = |content: &'static str| axum::body::Body::from(content);
+        let small = read_body(body("hello"), &HeaderMap::new(), 5).await;
+        assert_eq!(small.unwrap(), &b"hello"[..]);
+        let large = read_body(body("hello!"), &HeaderMap::new(), 5).await;
+        assert_eq!(large.unwrap_err().status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        // A declared length is refused before anything is read
+        let mut headers = HeaderMap::new();
+        headers.insert("content-length", "1000000".parse().unwrap());
+        let declared = read_body(body(""), &headers, 5).await;
+        assert_eq!(declared.unwrap_err().status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Bodies arriving in pieces are counted as a whole
+        let pieces = futures_util::stream::iter(
+            ["hel", "lo", "!"]
+                .map(|piece| Ok::<_, std::io::Error>(Bytes::from_static(piece.as_bytes()))),
+        );
+        let streamed = read_body(axum::body::Body::from_stream(pieces), &HeaderMap::new(), 5).await;
+        assert_eq!(streamed.unwrap_err().status, StatusCode::PAYLOAD_TOO_LARGE);
+        let broken = futures_util::stream::iter([Err::<Bytes, _>(std::io::Error::other("reset"))]);
+        let broken = read_body(axum::body::Body::from_stream(broken), &HeaderMap::new(), 5).await;
+        assert_eq!(broken.unwrap_err().status, StatusCode::BAD_REQUEST);
+    }
+
     #[test]
     fn test_server_config_default() {
         let config = ServerConfig::default();
         assert_eq!(config.hostname, "127.0.0.1");
         assert_eq!(config.port, 3000);
         assert!(config.enable_cors);
+        assert!(!config.enable_discovery);
+        assert_eq!(config.max_body_size, DEFAULT_MAX_BODY_SIZE);
     }
 
     #[test]
     fn test_http_response_creation() {
         let response = HttpResponse::text("Hello, World!");
         assert_eq!(response.status, StatusCode::OK);
-        assert_eq!(response.body, b"Hello, World!");
+        assert_eq!(response.body, &b"Hello, World!"[..]);
     }
 
     #[test]
@@ -0,0 +1,316 @@
+//! Log capture and level API
+//!
+//! `GET /api/logs?level=warn&target=rune_server&after=120&limit=100` returns
+//! captured records, oldest first; pass the last seen `id` as `after` to poll
+//! for new ones, and a request's `X-Request-Id` as `trace_id` to follow it. `GET /api/logs/level` lists the active levels and
+//! `PUT /api/logs/level` with `{"target": "rune_server", "level": "debug"}`
+//! changes one. Without a target the default level changes, and a `null`
+//! level removes the target's override. Access follows the configuration API.
+
+use crate::config_api::{check_api_access, CONFIG_MANAGER_RESOURCE};
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::{
+    config::{Config, RuntimeConfigManager},
+    error::{Result, RuneError},
+    logging::{LogController, LogQuery},
+    plugin::PluginContext,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{info, Level};
+
+/// Shared resource key of the `Arc<LogController>` installed by the host
+pub const LOG_CONTROLLER_RESOURCE: &str = "log_controller";
+
+/// Records returned when the request sets no `limit`
+const DEFAULT_LIMIT: usize = 200;
+
+/// Body of `PUT /api/logs/level`
+#[derive(Debug, Deserialize)]
+struct LevelChange {
+    #[serde(default)]
+    target: Option<String>,
+    level: Option<String>,
+}
+
+/// Handler for `GET /api/logs` and `GET`/`PUT /api/logs/level`
+pub struct LogsApiHandler {
+    path_pattern: String,
+    method: Method,
+    controller: Arc<LogController>,
+    config: Arc<Config>,
+    config_manager: Option<Arc<RwLock<RuntimeConfigManager>>>,
+}
+
+impl LogsApiHandler {
+    /// Create a handler answering `method` requests; access is checked
+    /// against the runtime configuration when a manager is given
+    pub fn new(
+        path_pattern: String,
+        method: Method,
+        controller: Arc<LogController>,
+        config: Arc<Config>,
+        config_manager: Option<Arc<RwLock<RuntimeConfigManager>>>,
+    ) -> Self {
+        Self {
+            path_pattern,
+            method,
+            controller,
+            config,
+            config_manager,
+        }
+    }
+
+    async fn check_access(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        match &self.config_manager {
+            Some(manager) => {
+                let manager = manager.read().await;
+                check_api_access(manager.get_config(), request, "The logs API")
+            }
+            None => check_api_access(&self.config, request, "The logs API"),
+        }
+    }
+
+    fn records(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let params = &request.query_params;
+        let level = match params.get("level").map(|level| level.parse::<Level>()) {
+            Some(Ok(level)) => Some(level),
+            Some(Err(_)) => {
+                return Ok(HttpResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    "level must be one of: error, warn, info, debug, trace",
+                ))
+            }
+            None => None,
+        };
+        let after = match params.get("after").map(|after| after.parse::<u64>()) {
+            Some(Ok(after)) => Some(after),
+            Some(Err(_)) => {
+                return Ok(HttpResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    "after must be a record id",
+                ))
+            }
+            None => None,
+        };
+        let limit = params
+            .get("limit")
+            .and_then(|limit| limit.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_LIMIT);
+
+        let buffer = self.controller.buffer();
+        let records = buffer.query(&LogQuery {
+            level,
+            target: params
+                .get("target")
+                .filter(|target| !target.is_empty())
+                .cloned(),
+            trace_id: params
+                .get("trace_id")
+                .filter(|trace_id| !trace_id.is_empty())
+                .cloned(),
+            after,
+            limit: Some(limit),
+        });
+        let body = serde_json::json!({
+            "records": records,
+            "capacity": buffer.capacity(),
+        });
+        Ok(HttpResponse::json(&body)?.with_header("cache-control", "no-store"))
+    }
+
+    fn change_level(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let change: LevelChange = match serde_json::from_slice(&request.body) {
+            Ok(change) => change,
+            Err(e) => {
+                return Ok(HttpResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Invalid level change: {}", e),
+                ))
+            }
+        };
+
+        let result = match (change.target.as_deref(), change.level.as_deref()) {
+            (Some(target), level) => self.controller.set_level(target, level),
+            (None, Some(level)) => self.controller.set_default_level(level),
+            (None, None) => {
+                return Ok(HttpResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    "The default level cannot be removed",
+                ))
+            }
+        };
+        if let Err(e) = result {
+            let message = match e {
+                RuneError::Config(message) => message,
+                other => other.to_string(),
+            };
+            return Ok(HttpResponse::error(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                &message,
+            ));
+        }
+
+        info!(
+            "Log level of {} set to {}",
+            change.target.as_deref().unwrap_or("default"),
+            change.level.as_deref().unwrap_or("default")
+        );
+        HttpResponse::json(&self.controller.levels())
+    }
+}
+
+#[async_trait]
+impl HttpHandler for LogsApiHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        self.method.clone()
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        if let Some(refusal) = self.check_access(&request).await {
+            return Ok(refusal);
+        }
+
+        match (request.path.trim_end_matches('/'), &request.method) {
+            ("/api/logs/level", &Method::PUT) => self.change_level(&request),
+            ("/api/logs/level", _) => HttpResponse::json(&self.controller.levels()),
+            ("/api/logs", _) => self.records(&request),
+            _ => Ok(HttpResponse::error(StatusCode::NOT_FOUND, "Not found")),
+        }
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Register the logs API if the host shares a log controller
+pub async fn register_logs_api_handlers(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+) -> Result<()> {
+    let Some(controller) = context
+        .get_shared_resource::<Arc<LogController>>(LOG_CONTROLLER_RESOURCE)
+        .await
+    else {
+        return Ok(());
+    };
+    let config_manager = context
+        .get_shared_resource::<RwLock<RuntimeConfigManager>>(CONFIG_MANAGER_RESOURCE)
+        .await;
+
+    // The GET handler also answers `/api/logs/level` by prefix
+    for (path, method) in [("/api/logs", Method::GET), ("/api/logs/level", Method::PUT)] {
+        registry
+            .register_http_handler(Arc::new(LogsApiHandler::new(
+                path.to_string(),
+                method,
+                (*controller).clone(),
+                context.config.clone(),
+                config_manager.clone(),
+            )))
+            .await?;
+    }
+
+    info!("Registered logs API handlers");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::level_filters::LevelFilter;
+
+    fn api(method: Method, dev_mode: bool) -> LogsApiHandler {
+        let mut config = Config::new();
+        config
+            .set_global_setting("dev_mode".to_string(), dev_mode)
+            .unwrap();
+        LogsApiHandler::new(
+            "/api/logs".to_string(),
+            method,
+            Arc::new(LogController::new(LevelFilter::INFO, 10)),
+            Arc::new(config),
+            None,
+        )
+    }
+
+    fn json(response: &HttpResponse) -> serde_json::Value {
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_logs_api_queries_records() {
+        let api = api(Method::GET, true);
+        let buffer = api.controller.buffer();
+        buffer.push(Level::DEBUG, "rune_server::handlers", "request".to_string());
+        let warning = buffer.push(Level::WARN, "rune_core", "slow".to_string());
+
+        let response = api
+            .handle(HttpRequest::get("/api/logs").with_query(&[("level", "warn")]))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        let body = json(&response);
+        assert_eq!(body["records"].as_array().unwrap().len(), 1);
+        assert_eq!(body["records"][0]["id"], warning);
+        assert_eq!(body["records"][0]["level"], "WARN");
+
+        let response = api
+            .handle(HttpRequest::get("/api/logs").with_query(&[("level", "loud")]))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+
+        let response = self::api(Method::GET, false)
+            .handle(HttpRequest::get("/api/logs"))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_logs_api_changes_levels() {
+        let api = api(Method::PUT, true);
+        let response = api
+            .handle(
+                HttpRequest::new(Method::PUT, "/api/logs/level")
+                    .with_body(r#"{"target": "rune_server", "level": "debug"}"#),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(json(&response)["targets"]["rune_server"], "debug");
+
+        let response = api
+            .handle(
+                HttpRequest::new(Method::PUT, "/api/logs/level")
+                    .with_body(r#"{"level": "chatty"}"#),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = api
+            .handle(
+                HttpRequest::new(Method::PUT, "/api/logs/level")
+                    .with_body(r#"{"target": "rune_server", "level": null}"#),
+            )
+            .await
+            .unwrap();
+        let body = json(&response);
+        assert_eq!(body["default"], "info");
+        assert_eq!(body["targets"], serde_json::json!({}));
+    }
+}
@@ -0,0 +1,240 @@
+//! Memory budget report
+//!
+//! `GET /api/debug/memory` returns the budget, the memory every registered
+//! consumer holds, largest first, and how often each one was asked to evict.
+//! Access follows the configuration API. The server also accounts the pages
+//! its preview handlers keep rendered as the `preview-pages` consumer.
+
+use crate::config_api::{check_api_access, CONFIG_MANAGER_RESOURCE};
+use crate::handlers::MarkdownHandler;
+use crate::roots::RootHandler;
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::{
+    config::{Config, RuntimeConfigManager},
+    error::Result,
+    memory::{MemoryBudget, MemoryConsumer, MemoryUsage},
+    plugin::PluginContext,
+};
+use std::sync::{Arc, Weak};
+use tokio::sync::RwLock;
+use tracing::info;
+
+/// Accounts rendered preview pages against the memory budget
+pub struct PreviewPageMemory {
+    // Weak, since the registry owns the memory API handler
+    registry: Weak<HandlerRegistry>,
+}
+
+impl PreviewPageMemory {
+    pub fn new(registry: &Arc<HandlerRegistry>) -> Self {
+        Self {
+            registry: Arc::downgrade(registry),
+        }
+    }
+}
+
+#[async_trait]
+impl MemoryConsumer for PreviewPageMemory {
+    fn name(&self) -> &str {
+        "preview-pages"
+    }
+
+    async fn usage(&self) -> MemoryUsage {
+        let Some(registry) = self.registry.upgrade() else {
+            return MemoryUsage::default();
+        };
+        let mut usage = MemoryUsage::default();
+        for handler in registry.get_all_http_handlers().await {
+            if let Some(markdown) = handler.as_any().downcast_ref::<MarkdownHandler>() {
+                let bytes = markdown.cached_bytes().await;
+                if bytes > 0 {
+                    usage.bytes += bytes;
+                    usage.entries += 1;
+                }
+            } else if let Some(root) = handler.as_any().downcast_ref::<RootHandler>() {
+                let pages = root.page_cache_usage().await;
+                usage.bytes += pages.bytes;
+                usage.entries += pages.entries;
+            }
+        }
+        usage
+    }
+
+    async fn evict(&self, bytes: usize) -> usize {
+        let Some(registry) = self.registry.upgrade() else {
+            return 0;
+        };
+        let mut freed = 0;
+        for handler in registry.get_all_http_handlers().await {
+            if freed >= bytes {
+                break;
+            }
+            if let Some(markdown) = handler.as_any().downcast_ref::<MarkdownHandler>() {
+                freed += markdown.cached_bytes().await;
+                markdown.clear_cache().await;
+            } else if let Some(root) = handler.as_any().downcast_ref::<RootHandler>() {
+                freed += root.evict_pages(bytes - freed).await;
+            }
+        }
+        freed
+    }
+}
+
+/// Handler for `GET /api/debug/memory`
+pub struct MemoryApiHandler {
+    path_pattern: String,
+    memory: Arc<MemoryBudget>,
+    config: Arc<Config>,
+    config_manager: Option<Arc<RwLock<RuntimeConfigManager>>>,
+}
+
+impl MemoryApiHandler {
+    /// Create a handler reporting `memory`; access is checked against the
+    /// runtime configuration when a manager is given
+    pub fn new(
+        path_pattern: String,
+        memory: Arc<MemoryBudget>,
+        config: Arc<Config>,
+        config_manager: Option<Arc<RwLock<RuntimeConfigManager>>>,
+    ) -> Self {
+        Self {
+            path_pattern,
+            memory,
+            config,
+            config_manager,
+        }
+    }
+
+    async fn check_access(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        match &self.config_manager {
+            Some(manager) => {
+                let manager = manager.read().await;
+                check_api_access(manager.get_config(), request, "The memory API")
+            }
+            None => check_api_access(&self.config, request, "The memory API"),
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for MemoryApiHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        if let Some(refusal) = self.check_access(&request).await {
+            return Ok(refusal);
+        }
+        if request.path.trim_end_matches('/') != self.path_pattern {
+            return Ok(HttpResponse::error(StatusCode::NOT_FOUND, "Not found"));
+        }
+
+        let report = self.memory.report().await;
+        Ok(HttpResponse::json(&report)?.with_header("cache-control", "no-store"))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Register the memory report and account preview pages against the budget
+pub async fn register_memory_api_handlers(
+    registry: &Arc<HandlerRegistry>,
+    context: &PluginContext,
+) -> Result<()> {
+    context
+        .memory
+        .register(Arc::new(PreviewPageMemory::new(registry)))
+        .await;
+
+    let config_manager = context
+        .get_shared_resource::<RwLock<RuntimeConfigManager>>(CONFIG_MANAGER_RESOURCE)
+        .await;
+    registry
+        .register_http_handler(Arc::new(MemoryApiHandler::new(
+            "/api/debug/memory".to_string(),
+            context.memory.clone(),
+            context.config.clone(),
+            config_manager,
+        )))
+        .await?;
+
+    info!("Registered memory API handler");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::event::InMemoryEventBus;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_preview_pages_are_reported_and_evicted() {
+        let temp_dir = TempDir::new().unwrap();
+        let markdown_file = temp_dir.path().join("doc.md");
+        std::fs::write(&markdown_file, "# Doc\n\nSome text.\n").unwrap();
+
+        let registry = Arc::new(HandlerRegistry::new(Arc::new(InMemoryEventBus::new())));
+        let markdown = Arc::new(MarkdownHandler::new("/".to_string(), markdown_file));
+        registry
+            .register_http_handler(markdown.clone())
+            .await
+            .unwrap();
+        markdown.handle(HttpRequest::get("/")).await.unwrap();
+
+        let mut config = Config::new();
+        config
+            .set_global_setting("dev_mode".to_string(), true)
+            .unwrap();
+        let memory = Arc::new(MemoryBudget::new(0));
+        memory
+            .register(Arc::new(PreviewPageMemory::new(&registry)))
+            .await;
+        let api = MemoryApiHandler::new(
+            "/api/debug/memory".to_string(),
+            memory.clone(),
+            Arc::new(config),
+            None,
+        );
+
+        let response = api
+            .handle(HttpRequest::get("/api/debug/memory"))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        let report: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(report["consumers"][0]["name"], "preview-pages");
+        assert_eq!(report["consumers"][0]["entries"], 1);
+        let cached = markdown.cached_bytes().await;
+        assert!(cached > 0);
+        assert_eq!(report["used_bytes"], cached);
+
+        assert_eq!(memory.enforce().await, cached);
+        assert_eq!(markdown.cached_bytes().await, 0);
+
+        let locked = MemoryApiHandler::new(
+            "/api/debug/memory".to_string(),
+            memory,
+            Arc::new(Config::new()),
+            None,
+        );
+        let response = locked
+            .handle(HttpRequest::get("/api/debug/memory"))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+    }
+}
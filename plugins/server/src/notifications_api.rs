@@ -0,0 +1,185 @@
+//! Notifications of the last while
+//!
+//! [Notifications](rune_core::notification) published on the event bus
+//! reach open pages on their live reload socket as `Notification` messages.
+//! A [`NotificationLog`] keeps the latest of them so a page opened later, or
+//! a notification panel, can catch up: `GET /api/notifications` returns them
+//! oldest first, each with the time it was sent.
+
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::Method;
+use rune_core::{
+    error::Result,
+    event::{SystemEvent, SystemEventHandler},
+    plugin::PluginContext,
+    Notification,
+};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use tokio::sync::RwLock;
+use tracing::info;
+
+/// Notifications the log keeps
+const LOG_LIMIT: usize = 50;
+
+/// A notification and when it was sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LoggedNotification {
+    #[serde(flatten)]
+    pub notification: Notification,
+    /// Seconds since the Unix epoch
+    pub sent_at: u64,
+}
+
+/// The latest notifications
+#[derive(Default)]
+pub struct NotificationLog {
+    notifications: RwLock<VecDeque<LoggedNotification>>,
+}
+
+impl NotificationLog {
+    /// Keep `notification`, forgetting the oldest beyond the limit
+    pub async fn push(&self, notification: LoggedNotification) {
+        let mut notifications = self.notifications.write().await;
+        if notifications.len() == LOG_LIMIT {
+            notifications.pop_front();
+        }
+        notifications.push_back(notification);
+    }
+
+    /// Kept notifications, oldest first
+    pub async fn list(&self) -> Vec<LoggedNotification> {
+        self.notifications.read().await.iter().cloned().collect()
+    }
+}
+
+/// Handler for `GET /api/notifications`
+pub struct NotificationsHandler {
+    path_pattern: String,
+    log: Arc<NotificationLog>,
+}
+
+impl NotificationsHandler {
+    pub fn new(path_pattern: String, log: Arc<NotificationLog>) -> Self {
+        Self { path_pattern, log }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for NotificationsHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        Ok(
+            HttpResponse::json(&serde_json::json!({ "notifications": self.log.list().await }))?
+                .with_header("cache-control", "no-store"),
+        )
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Keeps published notifications in the log
+struct NotificationLogger {
+    log: Arc<NotificationLog>,
+}
+
+#[async_trait]
+impl SystemEventHandler for NotificationLogger {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        if let SystemEvent::Notification {
+            notification,
+            timestamp,
+        } = event
+        {
+            let sent_at = timestamp
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default();
+            self.log
+                .push(LoggedNotification {
+                    notification: notification.clone(),
+                    sent_at,
+                })
+                .await;
+        }
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "notification-logger"
+    }
+}
+
+/// Register the notification log and its API
+pub async fn register_notification_handlers(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+) -> Result<()> {
+    let log = Arc::new(NotificationLog::default());
+    registry
+        .register_http_handler(Arc::new(NotificationsHandler::new(
+            "/api/notifications".to_string(),
+            log.clone(),
+        )))
+        .await?;
+    context
+        .event_bus
+        .subscribe_system_events(Arc::new(NotificationLogger { log }))
+        .await?;
+
+    info!("Registered notification API handler");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::{EventBus, InMemoryEventBus, NotificationAction};
+
+    #[tokio::test]
+    async fn test_logs_published_notifications() {
+        let event_bus = InMemoryEventBus::new();
+        let log = Arc::new(NotificationLog::default());
+        event_bus
+            .subscribe_system_events(Arc::new(NotificationLogger { log: log.clone() }))
+            .await
+            .unwrap();
+
+        for n in 0..=LOG_LIMIT {
+            let notification = Notification::warn("editor", format!("Autosave {} failed", n))
+                .with_action(NotificationAction::command("Retry", "editor.save"));
+            event_bus
+                .publish_system_event(SystemEvent::notification(notification))
+                .await
+                .unwrap();
+        }
+
+        let response = NotificationsHandler::new("/api/notifications".to_string(), log)
+            .handle(HttpRequest::get("/api/notifications"))
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        let notifications = body["notifications"].as_array().unwrap();
+        assert_eq!(notifications.len(), LOG_LIMIT);
+        assert_eq!(notifications[0]["message"], "Autosave 1 failed");
+        assert_eq!(notifications[0]["level"], "warn");
+        assert_eq!(notifications[0]["actions"][0]["command"], "editor.save");
+        assert!(notifications[0]["sent_at"].as_u64().unwrap() > 0);
+    }
+}
@@ -0,0 +1,488 @@
+//! Operational transformation for editor clients that cannot embed a CRDT
+//!
+//! Clients send their edits together with the document revision they were
+//! made against. [`OtDocument`] transforms edits made against an older
+//! revision over the operations applied since, so concurrent edits from
+//! several clients converge, and rejects edits it can no longer rebase with
+//! an [`OtErrorCode`]. Offsets count characters (Unicode scalar values), not
+//! bytes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Operations kept for rebasing edits made against older revisions
+pub const OT_HISTORY_LIMIT: usize = 500;
+
+/// Replacement of the characters `start..end` with `text`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeEdit {
+    pub start: usize,
+    pub end: usize,
+    #[serde(default)]
+    pub text: String,
+}
+
+impl RangeEdit {
+    pub fn new(start: usize, end: usize, text: impl Into<String>) -> Self {
+        Self {
+            start,
+            end,
+            text: text.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Component {
+    Retain(usize),
+    Insert(String),
+    Delete(usize),
+}
+
+/// Retains, inserts and deletes spanning a whole document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextOperation {
+    components: Vec<Component>,
+    base_len: usize,
+    target_len: usize,
+}
+
+impl TextOperation {
+    fn retain(&mut self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        self.base_len += n;
+        self.target_len += n;
+        match self.components.last_mut() {
+            Some(Component::Retain(m)) => *m += n,
+            _ => self.components.push(Component::Retain(n)),
+        }
+    }
+
+    fn insert(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.target_len += text.chars().count();
+        // Inserts go before adjacent deletes, so equal operations compare equal
+        let len = self.components.len();
+        match self.components.as_mut_slice() {
+            [.., Component::Insert(last)] | [.., Component::Insert(last), Component::Delete(_)] => {
+                last.push_str(text)
+            }
+            [.., Component::Delete(_)] => self
+                .components
+                .insert(len - 1, Component::Insert(text.to_string())),
+            _ => self.components.push(Component::Insert(text.to_string())),
+        }
+    }
+
+    fn delete(&mut self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        self.base_len += n;
+        match self.components.last_mut() {
+            Some(Component::Delete(m)) => *m += n,
+            _ => self.components.push(Component::Delete(n)),
+        }
+    }
+
+    /// Build an operation from edits applied in order to a document of
+    /// `len` characters, each against the result of the ones before it
+    pub fn from_edits(len: usize, edits: &[RangeEdit]) -> Result<Self, String> {
+        let mut operation = Self::default();
+        operation.retain(len);
+        for (index, edit) in edits.iter().enumerate() {
+            let current = operation.target_len;
+            if edit.start > edit.end || edit.end > current {
+                return Err(format!(
+                    "Edit {} has invalid range {}..{} for a document of {} characters",
+                    index, edit.start, edit.end, current
+                ));
+            }
+            let mut single = Self::default();
+            single.retain(edit.start);
+            single.delete(edit.end - edit.start);
+            single.insert(&edit.text);
+            single.retain(current - edit.end);
+            operation = operation.compose(&single)?;
+        }
+        Ok(operation)
+    }
+
+    /// The operation as range edits applied in order
+    pub fn to_edits(&self) -> Vec<RangeEdit> {
+        let mut edits: Vec<RangeEdit> = Vec::new();
+        let mut position = 0;
+        for component in &self.components {
+            match component {
+                Component::Retain(n) => position += n,
+                Component::Insert(text) => {
+                    edits.push(RangeEdit::new(position, position, text.clone()));
+                    position += text.chars().count();
+                }
+                Component::Delete(n) => match edits.last_mut() {
+                    // Deleting right after an insert replaces the range instead
+                    Some(last)
+                        if last.start == last.end
+                            && last.start + last.text.chars().count() == position =>
+                    {
+                        last.end = last.start + n;
+                    }
+                    _ => edits.push(RangeEdit::new(position, position + n, "")),
+                },
+            }
+        }
+        edits
+    }
+
+    /// Apply the operation to `document`
+    pub fn apply(&self, document: &str) -> Result<String, String> {
+        let mut chars = document.chars();
+        if chars.clone().count() != self.base_len {
+            return Err(format!(
+                "Operation expects a document of {} characters",
+                self.base_len
+            ));
+        }
+        let mut result = String::with_capacity(document.len());
+        for component in &self.components {
+            match component {
+                Component::Retain(n) => result.extend(chars.by_ref().take(*n)),
+                Component::Insert(text) => result.push_str(text),
+                Component::Delete(n) => {
+                    chars.by_ref().take(*n).for_each(drop);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Operation with the effect of `self` followed by `other`
+    pub fn compose(&self, other: &Self) -> Result<Self, String> {
+        if self.target_len != other.base_len {
+            return Err("Cannot compose operations of different lengths".to_string());
+        }
+        let mut result = Self::default();
+        let mut first = self.components.iter().cloned();
+        let mut second = other.components.iter().cloned();
+        let (mut a, mut b) = (first.next(), second.next());
+        loop {
+            match (a.take(), b.take()) {
+                (None, None) => break,
+                (Some(Component::Delete(n)), rest) => {
+                    result.delete(n);
+                    a = first.next();
+                    b = rest;
+                }
+                (rest, Some(Component::Insert(text))) => {
+                    result.insert(&text);
+                    a = rest;
+                    b = second.next();
+                }
+                (Some(x), Some(y)) => {
+                    let n = x.len().min(y.len());
+                    match (&x, &y) {
+                        (Component::Retain(_), Component::Retain(_)) => result.retain(n),
+                        (Component::Retain(_), Component::Delete(_)) => result.delete(n),
+                        (Component::Insert(text), Component::Retain(_)) => {
+                            result.insert(&take_chars(text, n))
+                        }
+                        // Inserted, then deleted again
+                        _ => {}
+                    }
+                    a = x.skip(n).or_else(|| first.next());
+                    b = y.skip(n).or_else(|| second.next());
+                }
+                _ => return Err("Cannot compose operations of different lengths".to_string()),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Transform two operations made against the same document, returning
+    /// `(a', b')` such that `a` then `b'` equals `b` then `a'`. Inserts of
+    /// `a` go first when both insert at the same position.
+    pub fn transform(a: &Self, b: &Self) -> Result<(Self, Self), String> {
+        if a.base_len != b.base_len {
+            return Err("Cannot transform operations of different lengths".to_string());
+        }
+        let (mut a_prime, mut b_prime) = (Self::default(), Self::default());
+        let mut first = a.components.iter().cloned();
+        let mut second = b.components.iter().cloned();
+        let (mut x, mut y) = (first.next(), second.next());
+        loop {
+            match (x.take(), y.take()) {
+                (None, None) => break,
+                (Some(Component::Insert(text)), rest) => {
+                    a_prime.insert(&text);
+                    b_prime.retain(text.chars().count());
+                    x = first.next();
+                    y = rest;
+                }
+                (rest, Some(Component::Insert(text))) => {
+                    a_prime.retain(text.chars().count());
+                    b_prime.insert(&text);
+                    x = rest;
+                    y = second.next();
+                }
+                (Some(p), Some(q)) => {
+                    let n = p.len().min(q.len());
+                    match (&p, &q) {
+                        (Component::Retain(_), Component::Retain(_)) => {
+                            a_prime.retain(n);
+                            b_prime.retain(n);
+                        }
+                        (Component::Delete(_), Component::Retain(_)) => a_prime.delete(n),
+                        (Component::Retain(_), Component::Delete(_)) => b_prime.delete(n),
+                        // Both deleted the same text
+                        _ => {}
+                    }
+                    x = p.skip(n).or_else(|| first.next());
+                    y = q.skip(n).or_else(|| second.next());
+                }
+                _ => return Err("Cannot transform operations of different lengths".to_string()),
+            }
+        }
+        Ok((a_prime, b_prime))
+    }
+}
+
+impl Component {
+    fn len(&self) -> usize {
+        match self {
+            Component::Retain(n) | Component::Delete(n) => *n,
+            Component::Insert(text) => text.chars().count(),
+        }
+    }
+
+    /// What is left after the first `n` characters
+    fn skip(self, n: usize) -> Option<Self> {
+        match self {
+            Component::Retain(m) => (m > n).then(|| Component::Retain(m - n)),
+            Component::Delete(m) => (m > n).then(|| Component::Delete(m - n)),
+            Component::Insert(text) => {
+                let rest: String = text.chars().skip(n).collect();
+                (!rest.is_empty()).then_some(Component::Insert(rest))
+            }
+        }
+    }
+}
+
+fn take_chars(text: &str, n: usize) -> String {
+    text.chars().take(n).collect()
+}
+
+/// Why an edit was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OtErrorCode {
+    /// Made against a revision older than the kept history; rejoin to resync
+    StaleRevision,
+    /// Made against a revision the server never produced
+    FutureRevision,
+    /// A range lies outside the document it was made against
+    InvalidEdit,
+    /// The session has not joined the document
+    NotJoined,
+}
+
+/// A rejected edit with the document's current revision
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtError {
+    pub code: OtErrorCode,
+    pub message: String,
+    pub revision: u64,
+}
+
+/// Edits accepted into a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtApplied {
+    /// Revision the edits produced
+    pub revision: u64,
+    /// The edits as applied to the previous revision
+    pub edits: Vec<RangeEdit>,
+    /// Whether the edits were transformed over concurrent ones
+    pub rebased: bool,
+}
+
+/// A document shared by OT clients, with the operations of recent revisions
+pub struct OtDocument {
+    content: String,
+    revision: u64,
+    history: VecDeque<TextOperation>,
+}
+
+impl OtDocument {
+    pub fn new(content: String) -> Self {
+        Self {
+            content,
+            revision: 0,
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Apply `edits` made against `base_revision`, rebasing them over the
+    /// operations applied since
+    pub fn apply(&mut self, base_revision: u64, edits: &[RangeEdit]) -> Result<OtApplied, OtError> {
+        let error = |code, message: String| OtError {
+            code,
+            message,
+            revision: self.revision,
+        };
+        if base_revision > self.revision {
+            return Err(error(
+                OtErrorCode::FutureRevision,
+                format!(
+                    "Revision {} is ahead of the document at revision {}",
+                    base_revision, self.revision
+                ),
+            ));
+        }
+        let behind = (self.revision - base_revision) as usize;
+        if behind > self.history.len() {
+            return Err(error(
+                OtErrorCode::StaleRevision,
+                format!(
+                    "Revision {} is older than the {} revisions kept; rejoin to resync",
+                    base_revision,
+                    self.history.len()
+                ),
+            ));
+        }
+
+        let concurrent = self.history.range(self.history.len() - behind..);
+        let base_len = match concurrent.clone().next() {
+            Some(first) => first.base_len,
+            None => self.content.chars().count(),
+        };
+        let mut operation = TextOperation::from_edits(base_len, edits)
+            .map_err(|message| error(OtErrorCode::InvalidEdit, message))?;
+        for past in concurrent {
+            operation = TextOperation::transform(past, &operation)
+                .map_err(|message| error(OtErrorCode::InvalidEdit, message))?
+                .1;
+        }
+        self.content = operation
+            .apply(&self.content)
+            .map_err(|message| error(OtErrorCode::InvalidEdit, message))?;
+
+        Ok(self.record(operation, behind > 0))
+    }
+
+    /// Replace the whole content at the current revision, for clients that
+    /// send full documents instead of edits
+    pub fn replace(&mut self, content: String) -> OtApplied {
+        let mut operation = TextOperation::default();
+        operation.delete(self.content.chars().count());
+        operation.insert(&content);
+        self.content = content;
+        self.record(operation, false)
+    }
+
+    fn record(&mut self, operation: TextOperation, rebased: bool) -> OtApplied {
+        self.revision += 1;
+        let edits = operation.to_edits();
+        self.history.push_back(operation);
+        if self.history.len() > OT_HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        OtApplied {
+            revision: self.revision,
+            edits,
+            rebased,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply_edits(document: &str, edits: &[RangeEdit]) -> String {
+        TextOperation::from_edits(document.chars().count(), edits)
+            .unwrap()
+            .apply(document)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_transform_converges() {
+        let document = "héllo wörld";
+        let cases = [
+            (
+                vec![RangeEdit::new(0, 0, "¡")],
+                vec![RangeEdit::new(6, 11, "rune")],
+            ),
+            (
+                vec![RangeEdit::new(2, 8, "")],
+                vec![RangeEdit::new(4, 10, "ICK")],
+            ),
+            (
+                vec![RangeEdit::new(5, 5, "A")],
+                vec![RangeEdit::new(5, 5, "B")],
+            ),
+            (
+                vec![RangeEdit::new(11, 11, "!"), RangeEdit::new(0, 1, "H")],
+                vec![RangeEdit::new(0, 11, "replaced")],
+            ),
+        ];
+
+        for (left, right) in cases {
+            let len = document.chars().count();
+            let a = TextOperation::from_edits(len, &left).unwrap();
+            let b = TextOperation::from_edits(len, &right).unwrap();
+            let (a_prime, b_prime) = TextOperation::transform(&a, &b).unwrap();
+            let via_a = b_prime.apply(&a.apply(document).unwrap()).unwrap();
+            let via_b = a_prime.apply(&b.apply(document).unwrap()).unwrap();
+            assert_eq!(via_a, via_b, "{:?} vs {:?}", left, right);
+
+            // Edits round-trip through operations
+            assert_eq!(
+                apply_edits(document, &a.to_edits()),
+                apply_edits(document, &left)
+            );
+        }
+    }
+
+    #[test]
+    fn test_document_rebases_concurrent_edits() {
+        let mut document = OtDocument::new("one two".to_string());
+
+        // Two clients edit revision 0 concurrently
+        let first = document.apply(0, &[RangeEdit::new(0, 3, "1")]).unwrap();
+        assert_eq!((first.revision, first.rebased), (1, false));
+        let second = document
+            .apply(0, &[RangeEdit::new(7, 7, " three")])
+            .unwrap();
+        assert!(second.rebased);
+        assert_eq!(second.edits, vec![RangeEdit::new(5, 5, " three")]);
+        assert_eq!(document.content(), "1 two three");
+        assert_eq!(document.revision(), 2);
+
+        let error = document.apply(5, &[]).unwrap_err();
+        assert_eq!(error.code, OtErrorCode::FutureRevision);
+        let error = document.apply(2, &[RangeEdit::new(3, 50, "")]).unwrap_err();
+        assert_eq!((error.code, error.revision), (OtErrorCode::InvalidEdit, 2));
+        assert_eq!(document.content(), "1 two three");
+
+        let replaced = document.replace("fresh".to_string());
+        assert_eq!(replaced.edits, vec![RangeEdit::new(0, 11, "fresh")]);
+
+        for _ in 0..OT_HISTORY_LIMIT {
+            document.apply(document.revision(), &[]).unwrap();
+        }
+        let error = document.apply(0, &[]).unwrap_err();
+        assert_eq!(error.code, OtErrorCode::StaleRevision);
+    }
+}
@@ -0,0 +1,312 @@
+//! Plugin status API
+//!
+//! `GET /api/plugins` lists every known plugin with its status, health,
+//! provided services and restart count. `/ws/plugins` sends the same table when
+//! a client connects and then streams lifecycle events as plugins load, fail or
+//! unload, so a web UI can keep a live plugin dashboard without polling.
+
+use crate::{
+    HandlerRegistry, HttpHandler, HttpRequest, HttpResponse, WebSocketConnection, WebSocketHandler,
+    WebSocketMessage,
+};
+use async_trait::async_trait;
+use axum::http::Method;
+use rune_core::{
+    error::Result,
+    event::{SystemEvent, SystemEventHandler},
+    plugin::{PluginContext, PluginInfo},
+    state::StateManager,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::{debug, info};
+
+/// Messages sent to plugin event stream clients
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum PluginEvent {
+    /// Current plugin table, sent when a client connects
+    Plugins { plugins: Vec<PluginInfo> },
+    /// A plugin finished initializing
+    PluginLoaded {
+        plugin_name: String,
+        version: String,
+        info: Option<PluginInfo>,
+    },
+    /// A plugin failed to initialize
+    PluginFailed {
+        plugin_name: String,
+        error: String,
+        info: Option<PluginInfo>,
+    },
+    /// A plugin was unloaded
+    PluginUnloaded { plugin_name: String },
+}
+
+impl PluginEvent {
+    /// Convert a system event into a stream message, if it is a lifecycle event
+    pub async fn from_system_event(
+        event: &SystemEvent,
+        state_manager: &StateManager,
+    ) -> Option<Self> {
+        match event {
+            SystemEvent::PluginLoaded {
+                plugin_name,
+                version,
+                ..
+            } => Some(Self::PluginLoaded {
+                plugin_name: plugin_name.clone(),
+                version: version.clone(),
+                info: plugin_info(state_manager, plugin_name).await,
+            }),
+            SystemEvent::PluginFailed {
+                plugin_name, error, ..
+            } => Some(Self::PluginFailed {
+                plugin_name: plugin_name.clone(),
+                error: error.clone(),
+                info: plugin_info(state_manager, plugin_name).await,
+            }),
+            SystemEvent::PluginUnloaded { plugin_name, .. } => Some(Self::PluginUnloaded {
+                plugin_name: plugin_name.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+async fn plugin_info(state_manager: &StateManager, name: &str) -> Option<PluginInfo> {
+    state_manager.get_state().await.loaded_plugins.remove(name)
+}
+
+/// Handler listing plugin information as JSON
+pub struct PluginsApiHandler {
+    path_pattern: String,
+    state_manager: Arc<StateManager>,
+}
+
+impl PluginsApiHandler {
+    /// Create a new plugin list handler
+    pub fn new(path_pattern: String, state_manager: Arc<StateManager>) -> Self {
+        Self {
+            path_pattern,
+            state_manager,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for PluginsApiHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        let plugins = self.state_manager.list_plugins().await;
+        Ok(HttpResponse::json(&plugins)?.with_header("cache-control", "no-store"))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// WebSocket handler streaming plugin lifecycle events
+pub struct PluginEventsHandler {
+    path: String,
+    sender: broadcast::Sender<PluginEvent>,
+    state_manager: Arc<StateManager>,
+}
+
+impl PluginEventsHandler {
+    /// Create a new plugin event stream handler fed by `sender`
+    pub fn new(
+        path: String,
+        sender: broadcast::Sender<PluginEvent>,
+        state_manager: Arc<StateManager>,
+    ) -> Self {
+        Self {
+            path,
+            sender,
+            state_manager,
+        }
+    }
+}
+
+#[async_trait]
+impl WebSocketHandler for PluginEventsHandler {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    async fn on_connect(&self, connection: &WebSocketConnection) -> Result<()> {
+        debug!("Plugin event stream client connected: {}", connection.id);
+
+        // Subscribe before taking the table so no event falls in between
+        let mut rx = self.sender.subscribe();
+        connection
+            .send_json(&PluginEvent::Plugins {
+                plugins: self.state_manager.list_plugins().await,
+            })
+            .await?;
+
+        let conn_sender = connection.sender.clone();
+        tokio::spawn(async move {
+            while let Ok(event) = rx.recv().await {
+                if let Ok(text) = serde_json::to_string(&event) {
+                    if conn_sender.send(WebSocketMessage::Text(text)).is_err() {
+                        // Connection closed
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn on_message(
+        &self,
+        connection: &WebSocketConnection,
+        message: WebSocketMessage,
+    ) -> Result<()> {
+        if let WebSocketMessage::Ping(data) = message {
+            connection.send(WebSocketMessage::Pong(data)).await?;
+        }
+        Ok(())
+    }
+
+    async fn on_disconnect(&self, connection: &WebSocketConnection) -> Result<()> {
+        debug!("Plugin event stream client disconnected: {}", connection.id);
+        Ok(())
+    }
+}
+
+/// Forwards plugin lifecycle system events to the stream
+struct PluginEventForwarder {
+    sender: broadcast::Sender<PluginEvent>,
+    state_manager: Arc<StateManager>,
+}
+
+#[async_trait]
+impl SystemEventHandler for PluginEventForwarder {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        if let Some(event) = PluginEvent::from_system_event(event, &self.state_manager).await {
+            // Sending only fails when no client is listening
+            let _ = self.sender.send(event);
+        }
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "plugin-event-forwarder"
+    }
+}
+
+/// Register the plugin list API and event stream
+pub async fn register_plugin_api_handlers(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+) -> Result<()> {
+    let (sender, _) = broadcast::channel::<PluginEvent>(32);
+
+    registry
+        .register_http_handler(Arc::new(PluginsApiHandler::new(
+            "/api/plugins".to_string(),
+            context.state_manager.clone(),
+        )))
+        .await?;
+    registry
+        .register_websocket_handler(Arc::new(PluginEventsHandler::new(
+            "/ws/plugins".to_string(),
+            sender.clone(),
+            context.state_manager.clone(),
+        )))
+        .await?;
+
+    context
+        .event_bus
+        .subscribe_system_events(Arc::new(PluginEventForwarder {
+            sender,
+            state_manager: context.state_manager.clone(),
+        }))
+        .await?;
+
+    info!("Registered plugin API handlers");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::plugin::{PluginHealthStatus, PluginStatus};
+    use std::time::SystemTime;
+
+    fn info(name: &str, status: PluginStatus) -> PluginInfo {
+        PluginInfo {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            status,
+            load_time: SystemTime::now(),
+            dependencies: Vec::new(),
+            provided_services: vec!["rendering".to_string()],
+            health_status: PluginHealthStatus::Healthy,
+            last_health_check: SystemTime::now(),
+            restart_count: 2,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plugins_api_lists_plugins() {
+        let state_manager = Arc::new(StateManager::new());
+        state_manager
+            .update_plugin(info("theme", PluginStatus::Active))
+            .await;
+        state_manager
+            .update_plugin(info("renderer", PluginStatus::Active))
+            .await;
+
+        let handler = PluginsApiHandler::new("/api/plugins".to_string(), state_manager);
+        let request = HttpRequest::get("/api/plugins");
+        let response = handler.handle(request).await.unwrap();
+
+        let plugins: Vec<PluginInfo> = serde_json::from_slice(&response.body).unwrap();
+        let names: Vec<&str> = plugins.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["renderer", "theme"]);
+        assert_eq!(plugins[0].restart_count, 2);
+        assert_eq!(plugins[0].provided_services, vec!["rendering"]);
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_events_become_stream_messages() {
+        let state_manager = StateManager::new();
+        state_manager
+            .update_plugin(info(
+                "server",
+                PluginStatus::Error("Initialization failed: port in use".to_string()),
+            ))
+            .await;
+
+        let event = SystemEvent::plugin_failed("server".to_string(), "port in use".to_string());
+        let message = PluginEvent::from_system_event(&event, &state_manager)
+            .await
+            .unwrap();
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["type"], "PluginFailed");
+        assert_eq!(json["error"], "port in use");
+        assert_eq!(json["info"]["restart_count"], 2);
+
+        let event = SystemEvent::theme_changed("dark".to_string());
+        assert!(PluginEvent::from_system_event(&event, &state_manager)
+            .await
+            .is_none());
+    }
+}
@@ -0,0 +1,158 @@
+//! Author cursor presence for preview viewers
+//!
+//! Editor clients report their cursor and selection over the editor
+//! WebSocket. Viewers of the live preview receive them as indexes of the
+//! top-level rendered blocks, the children of `#content`, so they can
+//! highlight the section being edited. Authors are only identified by an
+//! ordinal, never by session or connection.
+
+use crate::handlers::{BlockRange, ServerMessage};
+use markdown::mdast::Node;
+use markdown::unist::Position;
+use std::collections::HashMap;
+
+/// Source positions of the blocks that render to top-level elements;
+/// definitions render nothing and are skipped
+pub(crate) fn rendered_blocks(markdown: &str) -> Vec<Position> {
+    let Ok(root) = markdown::to_mdast(markdown, &markdown::ParseOptions::gfm()) else {
+        return Vec::new();
+    };
+    root.children()
+        .into_iter()
+        .flatten()
+        .filter(|node| {
+            !matches!(
+                node,
+                Node::Definition(_) | Node::FootnoteDefinition(_) | Node::Yaml(_) | Node::Toml(_)
+            )
+        })
+        .filter_map(|node| node.position().cloned())
+        .collect()
+}
+
+/// Index of the rendered block at 0-based `line`; a line between blocks
+/// belongs to the block above it
+fn block_at_line(blocks: &[Position], line: usize) -> Option<usize> {
+    let line = line + 1;
+    let following = blocks.iter().position(|block| block.start.line > line);
+    match following {
+        Some(0) => Some(0),
+        Some(index) => Some(index - 1),
+        None => blocks.len().checked_sub(1),
+    }
+}
+
+/// 0-based line of the character at `offset`
+fn line_at_offset(markdown: &str, offset: usize) -> usize {
+    markdown.chars().take(offset).filter(|&c| c == '\n').count()
+}
+
+/// Presence of one author at `line`, with the blocks spanned by a selection
+/// of character offsets
+pub fn locate(
+    markdown: &str,
+    author: usize,
+    line: usize,
+    selection: Option<(usize, usize)>,
+) -> ServerMessage {
+    let blocks = rendered_blocks(markdown);
+    let selection = selection
+        .filter(|(start, end)| start != end)
+        .and_then(|(start, end)| {
+            let (start, end) = (start.min(end), start.max(end));
+            Some(BlockRange {
+                start: block_at_line(&blocks, line_at_offset(markdown, start))?,
+                end: block_at_line(&blocks, line_at_offset(markdown, end))?,
+            })
+        });
+    ServerMessage::Presence {
+        author,
+        block: block_at_line(&blocks, line),
+        selection,
+    }
+}
+
+/// Anonymous ordinals of the sessions reporting their cursor
+#[derive(Debug, Default)]
+pub struct PresenceTracker {
+    /// Ordinal and connection by session
+    authors: HashMap<String, (usize, String)>,
+    next: usize,
+}
+
+impl PresenceTracker {
+    /// Ordinal of a session, assigned on its first report
+    pub fn author(&mut self, session_id: &str, connection_id: &str) -> usize {
+        let next = &mut self.next;
+        self.authors
+            .entry(session_id.to_string())
+            .or_insert_with(|| {
+                *next += 1;
+                (*next, connection_id.to_string())
+            })
+            .0
+    }
+
+    /// Forget the sessions of a closed connection, returning their ordinals
+    pub fn leave(&mut self, connection_id: &str) -> Vec<usize> {
+        let mut left = Vec::new();
+        self.authors.retain(|_, (author, connection)| {
+            let keep = connection != connection_id;
+            if !keep {
+                left.push(*author);
+            }
+            keep
+        });
+        left
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_lines_map_to_rendered_blocks() {
+        let markdown = "# Title\n\nFirst paragraph\nstill first.\n\n[link]: https://example.com\n\n- one\n- two\n\n```\ncode\n```\n";
+
+        let block = |line| match locate(markdown, 1, line, None) {
+            ServerMessage::Presence { block, .. } => block,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(block(0), Some(0));
+        assert_eq!(block(1), Some(0));
+        assert_eq!(block(3), Some(1));
+        // The definition renders nothing, so the list is the third block
+        assert_eq!(block(5), Some(1));
+        assert_eq!(block(8), Some(2));
+        assert_eq!(block(11), Some(3));
+        assert_eq!(block(40), Some(3));
+
+        let ServerMessage::Presence { selection, .. } = locate(markdown, 1, 2, Some((70, 12)))
+        else {
+            panic!("expected presence");
+        };
+        assert_eq!(selection, Some(BlockRange { start: 1, end: 2 }));
+
+        assert_eq!(
+            locate("", 2, 0, None),
+            ServerMessage::Presence {
+                author: 2,
+                block: None,
+                selection: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_tracker_hands_out_anonymous_ordinals() {
+        let mut tracker = PresenceTracker::default();
+        assert_eq!(tracker.author("session-a", "conn-1"), 1);
+        assert_eq!(tracker.author("session-b", "conn-2"), 2);
+        assert_eq!(tracker.author("session-a", "conn-1"), 1);
+
+        assert_eq!(tracker.leave("conn-1"), vec![1]);
+        assert!(tracker.leave("conn-1").is_empty());
+        assert_eq!(tracker.author("session-a", "conn-3"), 3);
+    }
+}
@@ -0,0 +1,160 @@
+//! Span profiles for diagnosing slow renders
+//!
+//! In dev mode, `GET /api/debug/profile?secs=5` records the render stages,
+//! handlers and editor session operations run during the next `secs`
+//! seconds and answers with their self time in the folded-stacks format,
+//! ready for `inferno-flamegraph` or speedscope. Access follows the
+//! configuration API, and the host must share its log controller, whose
+//! subscriber does the profiling.
+
+use crate::config_api::ApiAccess;
+use crate::logs_api::LOG_CONTROLLER_RESOURCE;
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::{error::Result, logging::LogController, plugin::PluginContext, profile::Profiler};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+/// Seconds recorded when the request sets no `secs`
+const DEFAULT_SECS: u64 = 10;
+
+/// Longest recording a request may ask for
+const MAX_SECS: u64 = 60;
+
+/// Handler for `GET /api/debug/profile`
+pub struct ProfileApiHandler {
+    path_pattern: String,
+    profiler: Arc<Profiler>,
+    access: ApiAccess,
+}
+
+impl ProfileApiHandler {
+    pub fn new(path_pattern: String, profiler: Arc<Profiler>, access: ApiAccess) -> Self {
+        Self {
+            path_pattern,
+            profiler,
+            access,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for ProfileApiHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        if let Some(refusal) = self.access.check(&request, "The profiling API").await {
+            return Ok(refusal);
+        }
+        if request.path.trim_end_matches('/') != self.path_pattern {
+            return Ok(HttpResponse::error(StatusCode::NOT_FOUND, "Not found"));
+        }
+
+        let secs = match request.query_params.get("secs").map(|secs| secs.parse()) {
+            None => DEFAULT_SECS,
+            Some(Ok(secs)) if (1..=MAX_SECS).contains(&secs) => secs,
+            Some(_) => {
+                return Ok(HttpResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    &format!("secs must be a number of seconds from 1 to {}", MAX_SECS),
+                ))
+            }
+        };
+        match self.profiler.record(Duration::from_secs(secs)).await {
+            Ok(profile) => {
+                Ok(HttpResponse::text(profile.folded()).with_header("cache-control", "no-store"))
+            }
+            Err(e) => Ok(HttpResponse::error(StatusCode::CONFLICT, &e.to_string())),
+        }
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        // The recording itself takes up to a minute
+        Some(Duration::from_secs(MAX_SECS + 10))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Register the profiling API, when the host shares its log controller
+pub async fn register_profile_api_handler(
+    registry: &Arc<HandlerRegistry>,
+    context: &PluginContext,
+) -> Result<()> {
+    let Some(controller) = context
+        .get_shared_resource::<Arc<LogController>>(LOG_CONTROLLER_RESOURCE)
+        .await
+    else {
+        return Ok(());
+    };
+    registry
+        .register_http_handler(Arc::new(ProfileApiHandler::new(
+            "/api/debug/profile".to_string(),
+            controller.profiler().clone(),
+            ApiAccess::from_context(context).await,
+        )))
+        .await?;
+
+    info!("Registered profiling API handler");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::config::Config;
+
+    fn handler(profiler: Arc<Profiler>) -> ProfileApiHandler {
+        let mut config = Config::new();
+        config
+            .set_global_setting("dev_mode".to_string(), true)
+            .unwrap();
+        ProfileApiHandler::new(
+            "/api/debug/profile".to_string(),
+            profiler,
+            ApiAccess::new(Arc::new(config), None),
+        )
+    }
+
+    fn request(secs: &str) -> HttpRequest {
+        let mut request = HttpRequest::get("/api/debug/profile");
+        request
+            .query_params
+            .insert("secs".to_string(), secs.to_string());
+        request
+    }
+
+    #[tokio::test]
+    async fn test_profile_requests() {
+        let profiler = Arc::new(Profiler::new());
+        let handler = handler(profiler.clone());
+
+        for secs in ["0", "61", "soon"] {
+            let response = handler.handle(request(secs)).await.unwrap();
+            assert_eq!(response.status, StatusCode::BAD_REQUEST);
+        }
+
+        // Only one recording runs at a time
+        let (first, second) = tokio::join!(handler.handle(request("1")), async {
+            tokio::task::yield_now().await;
+            handler.handle(request("1")).await
+        });
+        assert_eq!(first.unwrap().status, StatusCode::OK);
+        assert_eq!(second.unwrap().status, StatusCode::CONFLICT);
+        assert!(!profiler.is_recording());
+    }
+}
@@ -0,0 +1,80 @@
+//! Live reload wire protocol
+//!
+//! Every message the server sends on a live reload socket (`/ws`) is an
+//! envelope naming the protocol version, a message id and the kind of
+//! message, with the message's fields under `payload`:
+//!
+//! ```json
+//! {"version": 1, "id": 42, "type": "StyleReload", "payload": {"href": "/theme.css"}}
+//! ```
+//!
+//! Messages without fields, like `Reload`, have no `payload`. Ids increase
+//! along a connection. The message kinds and their fields are those of
+//! [`ServerMessage`].
+//!
+//! On connecting, the server sends `Welcome` with its version. Clients
+//! send [`ClientMessage`]s in the same shape; `version` and `id` may be left
+//! out. A client may send `Hello` with the version it speaks; the server
+//! answers with `Welcome` if it speaks that version too, and otherwise with
+//! an `Error` coded `unsupported_version` before closing the connection.
+//! Clients that never send `Hello` are served the current version.
+//!
+//! Pages carry the hash of the render they show in a `rune-content-hash`
+//! meta tag, and `ContentUpdate` metadata carries the hash of the render it
+//! sends. A page reconnecting after a drop sends `Resume` with its hash; the
+//! server answers `UpToDate` if nothing changed, a `ContentUpdate` if the
+//! page shows one of the last few renders, and otherwise `Refresh`, asking
+//! the page to load itself again.
+
+use crate::handlers::ServerMessage;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub use crate::handlers::ClientMessage;
+
+/// Version of the protocol the server speaks
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Versions of the protocol the server can serve clients
+pub const SUPPORTED_VERSIONS: &[u32] = &[PROTOCOL_VERSION];
+
+/// `Error` code for a client speaking a version the server does not
+pub const UNSUPPORTED_VERSION: &str = "unsupported_version";
+
+/// A server message as sent on the wire
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Envelope {
+    pub version: u32,
+    pub id: u64,
+    #[serde(flatten)]
+    pub message: ServerMessage,
+}
+
+impl Envelope {
+    /// Wrap `message` in the current version with the next id
+    pub fn new(message: ServerMessage) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self {
+            version: PROTOCOL_VERSION,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            message,
+        }
+    }
+}
+
+/// Whether the server can serve a client speaking `version`
+pub fn supports(version: u32) -> bool {
+    SUPPORTED_VERSIONS.contains(&version)
+}
+
+/// `Error` for a client speaking `version`, sent before closing its
+/// connection
+pub fn unsupported(version: u32) -> ServerMessage {
+    ServerMessage::Error {
+        message: format!(
+            "Protocol version {} is not supported; this server speaks version {}",
+            version, PROTOCOL_VERSION
+        ),
+        code: Some(UNSUPPORTED_VERSION.to_string()),
+    }
+}
@@ -0,0 +1,345 @@
+//! Reading preferences of a client
+//!
+//! Font size, line width, font family and reader mode are kept by the
+//! browser, in `localStorage` for the page script and in the `rune_reading`
+//! cookie so pages arrive already styled. `GET /api/reading` returns the
+//! preferences the request's cookie holds; `POST /api/reading` takes a JSON
+//! object of the preferences to change (`null` resets one to the theme's),
+//! clamps them to the supported ranges and sets the cookie. Pages carry the
+//! preferences as attributes of `<html>`, which the template's CSS
+//! variables honor.
+
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use rune_core::error::Result;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use tracing::info;
+
+/// Name of the cookie holding the preferences
+pub const READING_COOKIE: &str = "rune_reading";
+
+/// Supported font sizes, in pixels
+pub const FONT_SIZES: RangeInclusive<u32> = 12..=28;
+
+/// Supported line widths, in characters
+pub const LINE_WIDTHS: RangeInclusive<u32> = 40..=120;
+
+/// How long browsers keep the cookie, in seconds
+const COOKIE_MAX_AGE: u64 = 365 * 24 * 60 * 60;
+
+/// Typeface of document text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FontFamily {
+    System,
+    Serif,
+    Sans,
+    Mono,
+}
+
+impl FontFamily {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::Serif => "serif",
+            Self::Sans => "sans",
+            Self::Mono => "mono",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "system" => Some(Self::System),
+            "serif" => Some(Self::Serif),
+            "sans" => Some(Self::Sans),
+            "mono" => Some(Self::Mono),
+            _ => None,
+        }
+    }
+}
+
+/// Reading preferences; unset ones leave the theme's styles alone
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadingPreferences {
+    pub font_size: Option<u32>,
+    pub line_width: Option<u32>,
+    pub font_family: Option<FontFamily>,
+    #[serde(default)]
+    pub reader_mode: bool,
+}
+
+/// Changes of `POST /api/reading`; absent fields are kept, `null` unsets
+#[derive(Debug, Default, Deserialize)]
+struct ReadingPatch {
+    #[serde(default, with = "patch_field")]
+    font_size: Option<Option<u32>>,
+    #[serde(default, with = "patch_field")]
+    line_width: Option<Option<u32>>,
+    #[serde(default, with = "patch_field")]
+    font_family: Option<Option<FontFamily>>,
+    reader_mode: Option<bool>,
+}
+
+/// Tells an absent field from an explicit `null`
+mod patch_field {
+    use serde::{Deserialize, Deserializer};
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Option::deserialize(deserializer).map(Some)
+    }
+}
+
+impl ReadingPreferences {
+    /// Preferences kept in the request's cookie, defaults without one
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .find_map(|cookie| {
+                let (name, value) = cookie.trim().split_once('=')?;
+                (name == READING_COOKIE).then(|| Self::from_cookie_value(value))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Parse a cookie value like `font_size=18&line_width=72&font_family=serif&reader_mode=1`,
+    /// ignoring unknown and out-of-range settings
+    pub fn from_cookie_value(value: &str) -> Self {
+        let mut preferences = Self::default();
+        for pair in value.split('&') {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            match key {
+                "font_size" => preferences.font_size = value.parse().ok(),
+                "line_width" => preferences.line_width = value.parse().ok(),
+                "font_family" => preferences.font_family = FontFamily::parse(value),
+                "reader_mode" => preferences.reader_mode = value == "1",
+                _ => {}
+            }
+        }
+        preferences.validated()
+    }
+
+    /// Value of the cookie holding these preferences
+    pub fn to_cookie_value(&self) -> String {
+        let mut pairs = Vec::new();
+        if let Some(size) = self.font_size {
+            pairs.push(format!("font_size={}", size));
+        }
+        if let Some(width) = self.line_width {
+            pairs.push(format!("line_width={}", width));
+        }
+        if let Some(family) = self.font_family {
+            pairs.push(format!("font_family={}", family.as_str()));
+        }
+        if self.reader_mode {
+            pairs.push("reader_mode=1".to_string());
+        }
+        pairs.join("&")
+    }
+
+    /// Clamp sizes to the supported ranges
+    fn validated(mut self) -> Self {
+        self.font_size = self
+            .font_size
+            .map(|size| size.clamp(*FONT_SIZES.start(), *FONT_SIZES.end()));
+        self.line_width = self
+            .line_width
+            .map(|width| width.clamp(*LINE_WIDTHS.start(), *LINE_WIDTHS.end()));
+        self
+    }
+
+    fn apply_patch(mut self, patch: ReadingPatch) -> Self {
+        if let Some(size) = patch.font_size {
+            self.font_size = size;
+        }
+        if let Some(width) = patch.line_width {
+            self.line_width = width;
+        }
+        if let Some(family) = patch.font_family {
+            self.font_family = family;
+        }
+        if let Some(reader_mode) = patch.reader_mode {
+            self.reader_mode = reader_mode;
+        }
+        self.validated()
+    }
+
+    /// Attributes of `<html>` carrying the preferences to the page's CSS
+    pub fn html_attributes(&self) -> String {
+        let mut attributes = String::new();
+        let mut style = Vec::new();
+        if let Some(size) = self.font_size {
+            style.push(format!("--reading-font-size: {}px", size));
+        }
+        if let Some(width) = self.line_width {
+            style.push(format!("--reading-width: {}ch", width));
+        }
+        if !style.is_empty() {
+            attributes.push_str(&format!(r#" style="{}""#, style.join("; ")));
+        }
+        if let Some(family) = self.font_family {
+            attributes.push_str(&format!(r#" data-reading-font="{}""#, family.as_str()));
+        }
+        if self.reader_mode {
+            attributes.push_str(" data-reader");
+        }
+        attributes
+    }
+
+    /// Add the preferences to the `<html>` element of `page`
+    pub fn apply(&self, page: &str) -> String {
+        let attributes = self.html_attributes();
+        match page.find("<html") {
+            Some(start) if !attributes.is_empty() => {
+                let end = start + "<html".len();
+                format!("{}{}{}", &page[..end], attributes, &page[end..])
+            }
+            _ => page.to_string(),
+        }
+    }
+
+    /// `Set-Cookie` value storing the preferences, or clearing the cookie
+    /// when nothing is set
+    fn set_cookie(&self) -> String {
+        let value = self.to_cookie_value();
+        let max_age = if value.is_empty() { 0 } else { COOKIE_MAX_AGE };
+        format!(
+            "{}={}; Path=/; Max-Age={}; SameSite=Lax",
+            READING_COOKIE, value, max_age
+        )
+    }
+}
+
+/// Handler for `GET` or `POST /api/reading`
+pub struct ReadingApiHandler {
+    path_pattern: String,
+    method: Method,
+}
+
+impl ReadingApiHandler {
+    /// Create a handler answering `method` requests
+    pub fn new(path_pattern: String, method: Method) -> Self {
+        Self {
+            path_pattern,
+            method,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for ReadingApiHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        self.method.clone()
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let preferences = ReadingPreferences::from_headers(&request.headers);
+        if request.method != Method::POST {
+            return Ok(HttpResponse::json(&preferences)?.with_header("cache-control", "no-store"));
+        }
+
+        let patch: ReadingPatch = match serde_json::from_slice(&request.body) {
+            Ok(patch) => patch,
+            Err(e) => {
+                return Ok(HttpResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Invalid reading preferences: {}", e),
+                ))
+            }
+        };
+        let preferences = preferences.apply_patch(patch);
+        Ok(HttpResponse::json(&preferences)?
+            .with_header("cache-control", "no-store")
+            .with_header("set-cookie", &preferences.set_cookie()))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Register the reading preferences API
+pub async fn register_reading_api_handlers(registry: &HandlerRegistry) -> Result<()> {
+    for method in [Method::GET, Method::POST] {
+        registry
+            .register_http_handler(Arc::new(ReadingApiHandler::new(
+                "/api/reading".to_string(),
+                method,
+            )))
+            .await?;
+    }
+
+    info!("Registered reading preferences API handlers");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_preferences_round_trip_through_cookie() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            "theme=x; rune_reading=font_size=40&line_width=72&font_family=comic"
+                .parse()
+                .unwrap(),
+        );
+        let preferences = ReadingPreferences::from_headers(&headers);
+        assert_eq!(
+            preferences,
+            ReadingPreferences {
+                font_size: Some(28),
+                line_width: Some(72),
+                font_family: None,
+                reader_mode: false,
+            }
+        );
+
+        let request = HttpRequest {
+            method: Method::POST,
+            path: "/api/reading".to_string(),
+            query_params: Default::default(),
+            headers,
+            body: r#"{"font_size": null, "font_family": "serif", "reader_mode": true}"#.into(),
+            path_params: Default::default(),
+        };
+        let handler = ReadingApiHandler::new("/api/reading".to_string(), Method::POST);
+        let response = handler.handle(request).await.unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(
+            response.headers["set-cookie"],
+            "rune_reading=line_width=72&font_family=serif&reader_mode=1; Path=/; Max-Age=31536000; SameSite=Lax"
+        );
+
+        let preferences: ReadingPreferences = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(
+            preferences.apply(r#"<html lang="en"><body></body></html>"#),
+            r#"<html style="--reading-width: 72ch" data-reading-font="serif" data-reader lang="en"><body></body></html>"#
+        );
+        assert_eq!(
+            ReadingPreferences::default().apply("<html>"),
+            "<html>".to_string()
+        );
+    }
+}
@@ -0,0 +1,389 @@
+//! Recently edited and pinned documents
+//!
+//! The [`StateManager`] keeps the documents of the workspace that were
+//! edited last and those pinned for quick access, saved in the
+//! [`WORKSPACE_STATE_FILE`] of the directory of the first served root so
+//! they outlive the server. File change events record the edits and follow
+//! renamed and deleted documents.
+//!
+//! `GET /api/recent` returns the `recent` documents, most recently edited
+//! first with their `edited_at` time, and the `pinned` ones. `POST
+//! /api/recent/pin` takes a JSON object with the `url` of a document's
+//! preview and whether it is `pinned` (the default) and answers with the
+//! pinned documents. The file tree of `GET /api/files` carries both lists
+//! too.
+
+use crate::jail::SymlinkPolicy;
+use crate::roots::{document_at, document_link, holds};
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::{
+    error::Result,
+    event::{ChangeType, SystemEvent, SystemEventHandler},
+    plugin::PluginContext,
+    state::{ServedRoot, StateManager, WORKSPACE_STATE_FILE},
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// A recent or pinned document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuickAccessDocument {
+    /// Document path, relative to its root
+    pub document: String,
+    /// URL of the document's preview
+    pub url: String,
+    /// When it was last edited, in seconds since the Unix epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edited_at: Option<u64>,
+}
+
+/// Recently edited and pinned documents of `roots`, leaving out those no
+/// longer served
+pub(crate) async fn quick_access(
+    state_manager: &StateManager,
+    roots: &[ServedRoot],
+) -> (Vec<QuickAccessDocument>, Vec<QuickAccessDocument>) {
+    let documents = state_manager.workspace_documents().await;
+    let entry = |path: &Path, edited_at: Option<u64>| {
+        let (document, url) = document_link(roots, path)?;
+        Some(QuickAccessDocument {
+            document,
+            url,
+            edited_at,
+        })
+    };
+    let recent = documents
+        .recent
+        .iter()
+        .filter_map(|recent| entry(&recent.path, Some(recent.edited_at)))
+        .collect();
+    let pinned = documents
+        .pinned
+        .iter()
+        .filter_map(|path| entry(path, None))
+        .collect();
+    (recent, pinned)
+}
+
+/// Canonical form of `roots`, as documents are recorded
+fn canonical_roots(roots: &[ServedRoot]) -> Vec<ServedRoot> {
+    roots
+        .iter()
+        .map(|root| ServedRoot {
+            prefix: root.prefix.clone(),
+            path: root
+                .path
+                .canonicalize()
+                .unwrap_or_else(|_| root.path.clone()),
+        })
+        .collect()
+}
+
+/// Handler for `GET /api/recent`
+pub struct RecentHandler {
+    path_pattern: String,
+    state_manager: Arc<StateManager>,
+    roots: Vec<ServedRoot>,
+}
+
+impl RecentHandler {
+    pub fn new(
+        path_pattern: String,
+        state_manager: Arc<StateManager>,
+        roots: &[ServedRoot],
+    ) -> Self {
+        Self {
+            path_pattern,
+            state_manager,
+            roots: canonical_roots(roots),
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for RecentHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        let (recent, pinned) = quick_access(&self.state_manager, &self.roots).await;
+        Ok(HttpResponse::json(&serde_json::json!({
+            "recent": recent,
+            "pinned": pinned,
+        }))?
+        .with_header("cache-control", "no-store"))
+    }
+
+    fn matches_path(&self, path: &str) -> bool {
+        // `/api/recent/pin` is the pin handler's
+        path == self.path_pattern
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Body of a pin request
+#[derive(Debug, Deserialize)]
+struct PinRequest {
+    url: String,
+    #[serde(default = "pinned_by_default")]
+    pinned: bool,
+}
+
+fn pinned_by_default() -> bool {
+    true
+}
+
+/// Handler for `POST /api/recent/pin`
+pub struct PinHandler {
+    path_pattern: String,
+    state_manager: Arc<StateManager>,
+    roots: Vec<ServedRoot>,
+    symlinks: SymlinkPolicy,
+}
+
+impl PinHandler {
+    pub fn new(
+        path_pattern: String,
+        state_manager: Arc<StateManager>,
+        roots: &[ServedRoot],
+    ) -> Self {
+        Self {
+            path_pattern,
+            state_manager,
+            roots: canonical_roots(roots),
+            symlinks: SymlinkPolicy::default(),
+        }
+    }
+
+    /// Treat symbolic links in directory roots as `policy` says
+    pub fn with_symlink_policy(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlinks = policy;
+        self
+    }
+}
+
+#[async_trait]
+impl HttpHandler for PinHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let pin: PinRequest = match serde_json::from_slice(&request.body) {
+            Ok(pin) => pin,
+            Err(e) => {
+                return Ok(HttpResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Invalid pin request: {}", e),
+                ))
+            }
+        };
+        let Some(path) = document_at(&self.roots, &pin.url, self.symlinks) else {
+            return Ok(HttpResponse::error(
+                StatusCode::NOT_FOUND,
+                &format!("No served document at {}", pin.url),
+            ));
+        };
+        self.state_manager.set_pinned(&path, pin.pinned).await?;
+
+        let (_, pinned) = quick_access(&self.state_manager, &self.roots).await;
+        HttpResponse::json(&serde_json::json!({ "pinned": pinned }))
+    }
+
+    fn edits(&self) -> bool {
+        true // Changes the saved workspace state
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Records edits of served documents and follows renamed and deleted ones
+struct RecentEventHandler {
+    state_manager: Arc<StateManager>,
+    roots: Vec<ServedRoot>,
+}
+
+impl RecentEventHandler {
+    fn serves(&self, path: &Path) -> bool {
+        let markdown = path.extension().is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown")
+        });
+        markdown && self.roots.iter().any(|root| holds(root, path))
+    }
+}
+
+#[async_trait]
+impl SystemEventHandler for RecentEventHandler {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        let SystemEvent::FileChanged {
+            path, change_type, ..
+        } = event
+        else {
+            return Ok(());
+        };
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let path = canonical(path);
+        let saved = match change_type {
+            ChangeType::Created | ChangeType::Modified if self.serves(&path) => {
+                self.state_manager.record_edit(&path).await
+            }
+            ChangeType::Deleted => self.state_manager.move_document(&path, None).await,
+            ChangeType::Renamed { from, to } => {
+                let to = canonical(to);
+                let to = self.serves(&to).then_some(to.as_path());
+                self.state_manager.move_document(from, to).await
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = saved {
+            warn!("Failed to save the recent documents: {}", e);
+        }
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "recent-documents-event-handler"
+    }
+}
+
+/// Register the recent and pinned documents API for `roots`, loading the
+/// workspace state of the first root's directory
+pub async fn register_recent_handlers(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+    roots: &[ServedRoot],
+) -> Result<()> {
+    let Some(first) = roots.first() else {
+        return Ok(());
+    };
+    let workspace = first.base_dir();
+    if let Err(e) = context.state_manager.load_workspace(&workspace).await {
+        warn!(
+            "Failed to load {} of {}, starting afresh: {}",
+            WORKSPACE_STATE_FILE,
+            workspace.display(),
+            e
+        );
+    }
+
+    let state_manager = context.state_manager.clone();
+    registry
+        .register_http_handler(Arc::new(RecentHandler::new(
+            "/api/recent".to_string(),
+            state_manager.clone(),
+            roots,
+        )))
+        .await?;
+    registry
+        .register_http_handler(Arc::new(
+            PinHandler::new("/api/recent/pin".to_string(), state_manager.clone(), roots)
+                .with_symlink_policy(SymlinkPolicy::from_config(&context.config)),
+        ))
+        .await?;
+    context
+        .event_bus
+        .subscribe_system_events(Arc::new(RecentEventHandler {
+            state_manager,
+            roots: canonical_roots(roots),
+        }))
+        .await?;
+
+    info!("Registered recent documents API handlers");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_records_edits_and_pins() {
+        let temp_dir = TempDir::new().unwrap();
+        let docs = temp_dir.path().canonicalize().unwrap();
+        std::fs::write(docs.join("a b.md"), "# A").unwrap();
+        std::fs::write(docs.join("c.md"), "# C").unwrap();
+        let roots = [ServedRoot {
+            prefix: "docs".to_string(),
+            path: docs.clone(),
+        }];
+        let state_manager = Arc::new(StateManager::new());
+        state_manager.load_workspace(&docs).await.unwrap();
+
+        let events = RecentEventHandler {
+            state_manager: state_manager.clone(),
+            roots: roots.to_vec(),
+        };
+        for (path, change_type) in [
+            (docs.join("c.md"), ChangeType::Modified),
+            (docs.join("a b.md"), ChangeType::Modified),
+            (docs.join("logo.png"), ChangeType::Modified),
+        ] {
+            events
+                .handle_system_event(&SystemEvent::FileChanged {
+                    path,
+                    change_type,
+                    timestamp: SystemTime::now(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let pin = PinHandler::new("/api/recent/pin".to_string(), state_manager.clone(), &roots);
+        let response = pin
+            .handle(
+                HttpRequest::new(Method::POST, "/api/recent/pin")
+                    .with_body(br#"{"url": "/docs/c.md"}"#.to_vec()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        let response = pin
+            .handle(
+                HttpRequest::new(Method::POST, "/api/recent/pin")
+                    .with_body(br#"{"url": "/docs/../secret.md"}"#.to_vec()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+        let response = RecentHandler::new("/api/recent".to_string(), state_manager, &roots)
+            .handle(HttpRequest::get("/api/recent"))
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(body["recent"][0]["url"], "/docs/a%20b.md");
+        assert_eq!(body["recent"][1]["document"], "c.md");
+        assert_eq!(body["recent"].as_array().unwrap().len(), 2);
+        assert_eq!(body["pinned"][0]["url"], "/docs/c.md");
+        assert!(body["pinned"][0].get("edited_at").is_none());
+        assert!(docs.join(WORKSPACE_STATE_FILE).is_file());
+    }
+}
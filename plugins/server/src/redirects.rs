@@ -0,0 +1,246 @@
+//! Redirects of renamed documents and configured paths
+//!
+//! The `aliases` of a served document's frontmatter and the `redirects`
+//! section of the configuration become `301`/`302` responses, so links to a
+//! document's old path keep working. Aliases are read again whenever a
+//! markdown file of a served root changes.
+
+use crate::{roots, HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::{
+    error::Result,
+    event::{SystemEvent, SystemEventHandler},
+    plugin::PluginContext,
+    redirects::{frontmatter_aliases, normalize, RedirectRule},
+    state::ServedRoot,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tracing::info;
+
+/// Handler answering old paths with a redirect to the new one
+pub struct RedirectHandler {
+    rules: BTreeMap<String, RedirectRule>,
+    roots: Vec<ServedRoot>,
+    /// Alias paths and the URL of the document listing them
+    aliases: RwLock<HashMap<String, String>>,
+}
+
+impl RedirectHandler {
+    /// Redirect the configured `rules` and the aliases of the documents of
+    /// `roots`; a root with an empty prefix is served at `/`
+    pub fn new(rules: BTreeMap<String, RedirectRule>, roots: Vec<ServedRoot>) -> Self {
+        let handler = Self {
+            rules,
+            roots,
+            aliases: RwLock::new(HashMap::new()),
+        };
+        handler.refresh();
+        handler
+    }
+
+    /// Read the aliases of the served documents again
+    pub fn refresh(&self) {
+        let aliases = alias_redirects(&self.roots);
+        if let Ok(mut current) = self.aliases.write() {
+            *current = aliases;
+        }
+    }
+
+    /// Where a request for `path` is sent, and whether for good
+    pub fn lookup(&self, path: &str) -> Option<(String, bool)> {
+        if let Some(rule) = self.rules.get(path) {
+            return Some((rule.target().to_string(), rule.is_permanent()));
+        }
+        self.aliases
+            .read()
+            .ok()?
+            .get(path)
+            .map(|target| (target.clone(), true))
+    }
+
+    fn serves(&self, path: &Path) -> bool {
+        self.roots.iter().any(|root| root.contains(path))
+    }
+}
+
+/// Alias paths of the documents of `roots`, each with its document's URL
+fn alias_redirects(roots: &[ServedRoot]) -> HashMap<String, String> {
+    let mut redirects = HashMap::new();
+    for root in roots {
+        let route = if root.prefix.is_empty() {
+            String::new()
+        } else {
+            format!("/{}", root.prefix)
+        };
+        let documents: Vec<(PathBuf, PathBuf, String)> = if root.is_dir() {
+            roots::markdown_documents(&root.path)
+                .into_iter()
+                .filter_map(|document| {
+                    let relative = document.strip_prefix(&root.path).ok()?.to_path_buf();
+                    let url = format!("{}/{}", route, roots::document_href(&relative));
+                    let dir = relative.parent().map(Path::to_path_buf).unwrap_or_default();
+                    Some((document, dir, url))
+                })
+                .collect()
+        } else {
+            vec![(root.path.clone(), PathBuf::new(), format!("{}/", route))]
+        };
+
+        for (document, dir, url) in documents {
+            let Ok(content) = std::fs::read_to_string(&document) else {
+                continue;
+            };
+            for alias in frontmatter_aliases(&content) {
+                let path = match alias.strip_prefix('/') {
+                    Some(absolute) => normalize(Path::new(absolute)),
+                    None => normalize(&dir.join(&alias)),
+                };
+                let from = format!("{}/{}", route, roots::document_href(&path));
+                if from != url {
+                    redirects.insert(from, url.clone());
+                }
+            }
+        }
+    }
+    redirects
+}
+
+#[async_trait]
+impl HttpHandler for RedirectHandler {
+    fn path_pattern(&self) -> &str {
+        // Paths match by lookup; the pattern only keeps this handler from
+        // replacing the one registered for `/`
+        "/*redirects"
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn matches_path(&self, path: &str) -> bool {
+        self.lookup(path).is_some()
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        Ok(match self.lookup(&request.path) {
+            Some((target, permanent)) => {
+                let status = if permanent {
+                    StatusCode::MOVED_PERMANENTLY
+                } else {
+                    StatusCode::FOUND
+                };
+                HttpResponse::new(status).with_header("location", &target)
+            }
+            None => HttpResponse::error(StatusCode::NOT_FOUND, "Not found"),
+        })
+    }
+
+    fn priority(&self) -> i32 {
+        1 // Old paths redirect even when something else would serve them
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Reads aliases again when a served markdown file changes
+struct AliasEventHandler {
+    handler: Arc<RedirectHandler>,
+}
+
+#[async_trait]
+impl SystemEventHandler for AliasEventHandler {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        if let SystemEvent::FileChanged { path, .. } = event {
+            let is_markdown = path
+                .extension()
+                .is_some_and(|extension| extension == "md" || extension == "markdown");
+            let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if is_markdown && self.handler.serves(&path) {
+                let handler = self.handler.clone();
+                tokio::task::spawn_blocking(move || handler.refresh());
+            }
+        }
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "redirect-alias-event-handler"
+    }
+}
+
+/// Register the redirects of the configuration and of the documents of `roots`
+pub async fn register_redirect_handler(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+    roots: Vec<ServedRoot>,
+) -> Result<()> {
+    let handler = Arc::new(RedirectHandler::new(
+        context.config.redirects.clone(),
+        roots,
+    ));
+    registry.register_http_handler(handler.clone()).await?;
+    context
+        .event_bus
+        .subscribe_system_events(Arc::new(AliasEventHandler { handler }))
+        .await?;
+
+    info!("Registered redirect handler");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_aliases_and_rules_redirect() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::create_dir(dir.join("guide")).unwrap();
+        std::fs::write(
+            dir.join("guide/install.md"),
+            "---\naliases: [setup.md, /old/getting started.md]\n---\n# Install\n",
+        )
+        .unwrap();
+        let rules = serde_json::from_str(
+            r#"{"/beta/": {"to": "/docs/", "permanent": false}, "/docs/guide/setup.md": "/elsewhere"}"#,
+        )
+        .unwrap();
+        let handler = RedirectHandler::new(
+            rules,
+            vec![ServedRoot {
+                prefix: "docs".to_string(),
+                path: dir.to_path_buf(),
+            }],
+        );
+
+        assert_eq!(
+            handler.lookup("/docs/old/getting%20started.md"),
+            Some(("/docs/guide/install.md".to_string(), true))
+        );
+        assert_eq!(
+            handler.lookup("/beta/"),
+            Some(("/docs/".to_string(), false))
+        );
+        // Configured redirects win over aliases
+        assert_eq!(
+            handler.lookup("/docs/guide/setup.md"),
+            Some(("/elsewhere".to_string(), true))
+        );
+        assert!(!handler.matches_path("/docs/guide/install.md"));
+
+        std::fs::write(dir.join("note.md"), "---\naliases: memo.md\n---\n").unwrap();
+        assert!(handler.lookup("/docs/memo.md").is_none());
+        handler.refresh();
+        assert_eq!(
+            handler.lookup("/docs/memo.md"),
+            Some(("/docs/note.md".to_string(), true))
+        );
+    }
+}
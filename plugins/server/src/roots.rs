@@ -0,0 +1,720 @@
+//! Serving several documents from one server
+//!
+//! With `rune serve a.md b.md docs/` every [`ServedRoot`] is served below its
+//! own route prefix: `/a/`, `/b/` and `/docs/`. A [`RootHandler`] renders the
+//! root's documents, serves their raw markdown, copies and local assets, and lists
+//! the documents of a directory root. Each document page gets its own live
+//! reload and editor sockets below its URL, so pushed updates and edits never
+//! reach another document. `GET /` lists the roots.
+
+use crate::{
+    copy_as,
+    editor_handlers::EditorWebSocketHandler,
+    handlers::{self, LiveReloadHandler, MarkdownHandler, RawMarkdownHandler, StaticHandler},
+    jail::{self, PathJail, SymlinkPolicy},
+    publish_detached, HandlerRegistry, HttpHandler, HttpRequest, HttpResponse,
+};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use rune_core::{
+    drafts::{DraftVisibility, PublishState},
+    error::Result,
+    event::{ErrorSeverity, SystemEvent, SystemEventHandler},
+    memory::MemoryUsage,
+    plugin::PluginContext,
+    renderer::RendererRegistry,
+    state::ServedRoot,
+    LargeFileConfig,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::Instant;
+use tokio::sync::{broadcast, RwLock};
+use tracing::{debug, info};
+
+pub(crate) use rune_core::state::markdown_documents;
+
+/// Characters escaped in links to documents
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Live preview of one document of a root
+struct DocumentSite {
+    markdown: Arc<MarkdownHandler>,
+    live_reload: Arc<LiveReloadHandler>,
+}
+
+/// Handler serving one root below `/<prefix>`
+pub struct RootHandler {
+    root: ServedRoot,
+    route: String,
+    // Weak, since the registry owns this handler
+    registry: Weak<HandlerRegistry>,
+    renderer_registry: Option<Arc<RendererRegistry>>,
+    assets: StaticHandler,
+    /// Keeps the documents of a directory root inside it
+    jail: PathJail,
+    documents: RwLock<HashMap<PathBuf, Arc<DocumentSite>>>,
+    drafts: DraftVisibility,
+    large_file_threshold: Option<u64>,
+}
+
+impl RootHandler {
+    /// Create a handler for `root`, registering document sockets in `registry`
+    pub fn new(
+        root: ServedRoot,
+        registry: &Arc<HandlerRegistry>,
+        renderer_registry: Option<Arc<RendererRegistry>>,
+    ) -> Self {
+        let route = format!("/{}", root.prefix);
+        let assets = StaticHandler::new(root.base_dir(), route.clone());
+        let jail = PathJail::new(&root.base_dir(), SymlinkPolicy::default());
+        Self {
+            root,
+            route,
+            registry: Arc::downgrade(registry),
+            renderer_registry,
+            assets,
+            jail,
+            documents: RwLock::new(HashMap::new()),
+            drafts: DraftVisibility::default(),
+            large_file_threshold: Some(LargeFileConfig::default().threshold_bytes()),
+        }
+    }
+
+    /// List and serve drafts and scheduled documents only when `drafts`
+    /// shows them
+    pub fn with_draft_visibility(mut self, drafts: DraftVisibility) -> Self {
+        self.drafts = drafts;
+        self
+    }
+
+    /// Treat symbolic links in the root's directory as `policy` says
+    pub fn with_symlink_policy(mut self, policy: SymlinkPolicy) -> Self {
+        self.assets = self.assets.with_symlink_policy(policy);
+        self.jail = PathJail::new(self.jail.root(), policy);
+        self
+    }
+
+    /// Preview documents of at least `threshold` bytes without the expensive
+    /// renderers
+    pub fn with_large_file_threshold(mut self, threshold: Option<u64>) -> Self {
+        self.large_file_threshold = threshold;
+        self
+    }
+
+    fn hides(&self, document: &Path) -> bool {
+        self.drafts == DraftVisibility::Hide && !PublishState::of_file(document).is_published()
+    }
+
+    /// The root served by this handler
+    pub fn root(&self) -> &ServedRoot {
+        &self.root
+    }
+
+    /// URL of the root's landing page
+    pub fn url(&self) -> String {
+        format!("{}/", self.route)
+    }
+
+    /// Memory held by cached pages of the documents previewed so far
+    pub async fn page_cache_usage(&self) -> MemoryUsage {
+        let documents = self.documents.read().await;
+        let mut usage = MemoryUsage::default();
+        for site in documents.values() {
+            let bytes = site.markdown.cached_bytes().await;
+            if bytes > 0 {
+                usage.bytes += bytes;
+                usage.entries += 1;
+            }
+        }
+        usage
+    }
+
+    /// Drop cached document pages until about `bytes` bytes are freed,
+    /// returning the bytes freed
+    pub async fn evict_pages(&self, bytes: usize) -> usize {
+        let documents = self.documents.read().await;
+        let mut freed = 0;
+        for site in documents.values() {
+            if freed >= bytes {
+                break;
+            }
+            freed += site.markdown.cached_bytes().await;
+            site.markdown.clear_cache().await;
+        }
+        freed
+    }
+
+    /// Live preview of a document, set up on first use
+    async fn site(&self, document: &Path, base: &str) -> Result<Arc<DocumentSite>> {
+        let mut documents = self.documents.write().await;
+        if let Some(site) = documents.get(document) {
+            return Ok(site.clone());
+        }
+
+        let markdown = match &self.renderer_registry {
+            Some(renderer_registry) => MarkdownHandler::with_renderer_registry(
+                base.to_string(),
+                document.to_path_buf(),
+                renderer_registry.clone(),
+            ),
+            None => MarkdownHandler::new(base.to_string(), document.to_path_buf()),
+        }
+        .with_route_base(base.to_string())
+        .with_large_file_threshold(self.large_file_threshold);
+
+        let (reload_sender, _) = broadcast::channel(16);
+        let markdown = Arc::new(markdown);
+        let live_reload = Arc::new(
+            LiveReloadHandler::with_reload_sender(format!("{}/ws", base), reload_sender.clone())
+                .with_document(Arc::new(RwLock::new(Some(markdown.clone())))),
+        );
+        let editor = Arc::new(EditorWebSocketHandler::new(format!("{}/ws/editor", base)));
+        editor.set_markdown_file(document.to_path_buf()).await;
+        editor.set_preview_sender(reload_sender).await;
+
+        if let Some(registry) = self.registry.upgrade() {
+            registry
+                .register_websocket_handler(live_reload.clone())
+                .await?;
+            registry.register_websocket_handler(editor).await?;
+        }
+        debug!("Serving {} at {}", document.display(), base);
+
+        let site = Arc::new(DocumentSite {
+            markdown,
+            live_reload,
+        });
+        documents.insert(document.to_path_buf(), site.clone());
+        Ok(site)
+    }
+
+    /// Render a document page served at `base`
+    async fn page(
+        &self,
+        document: &Path,
+        base: &str,
+        request: HttpRequest,
+    ) -> Result<HttpResponse> {
+        let site = self.site(document, base).await?;
+        site.markdown.handle(request).await
+    }
+
+    /// Copy of a document, see [`crate::copy_as`]
+    async fn copy(
+        &self,
+        document: &Path,
+        base: &str,
+        request: HttpRequest,
+    ) -> Result<HttpResponse> {
+        let site = self.site(document, base).await?;
+        copy_as::copy_document(document, Some(site.markdown.clone()), &request).await
+    }
+
+    /// Page listing the documents of a directory root
+    fn listing(&self) -> HttpResponse {
+        let title = self
+            .root
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.root.prefix.clone());
+
+        let items: String = markdown_documents(&self.root.path)
+            .iter()
+            .filter(|document| !self.hides(document))
+            .filter_map(|document| document.strip_prefix(&self.root.path).ok())
+            .map(|relative| {
+                format!(
+                    "<li><a href=\"{}\">{}</a></li>\n",
+                    document_href(relative),
+                    html_escape::encode_text(&relative.to_string_lossy().replace('\\', "/"))
+                )
+            })
+            .collect();
+
+        let body = if items.is_empty() {
+            "<p>No markdown documents found.</p>".to_string()
+        } else {
+            format!("<ul>\n{}</ul>", items)
+        };
+        HttpResponse::html(handlers::standalone_page(
+            &title,
+            &format!("<h1>{}</h1>\n{}", html_escape::encode_text(&title), body),
+        ))
+    }
+
+    /// Push updates to the open pages affected by a changed file
+    async fn file_changed(&self, path: &Path, context: &PluginContext) {
+        let site = self.documents.read().await.get(path).cloned();
+        match site {
+            Some(site) => {
+                let start = Instant::now();
+                let event = match site
+                    .markdown
+                    .render_and_push_content(&site.live_reload)
+                    .await
+                {
+                    Ok(()) => SystemEvent::render_complete(
+                        site.markdown.content_hash().await,
+                        start.elapsed(),
+                    ),
+                    Err(e) => SystemEvent::error(
+                        "server".to_string(),
+                        format!("Failed to render {}: {}", path.display(), e),
+                        ErrorSeverity::Medium,
+                    ),
+                };
+                publish_detached(&context.event_bus, event);
+            }
+            None => {
+                // An asset or a document nobody has open: reload the root's pages
+                let sites: Vec<Arc<DocumentSite>> =
+                    self.documents.read().await.values().cloned().collect();
+                for site in sites {
+                    let _ = site.live_reload.broadcast_reload().await;
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for RootHandler {
+    fn path_pattern(&self) -> &str {
+        &self.route
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let rest = request
+            .path
+            .strip_prefix(&self.route)
+            .unwrap_or_default()
+            .to_string();
+        if rest.is_empty() {
+            // Relative links and assets resolve against the trailing slash
+            return Ok(HttpResponse::new(StatusCode::PERMANENT_REDIRECT)
+                .with_header("location", &self.url()));
+        }
+
+        if !self.root.is_dir() {
+            return match rest.as_str() {
+                "/" => self.page(&self.root.path, &self.route, request).await,
+                "/raw" => raw(&self.root.path, request).await,
+                "/copy" => self.copy(&self.root.path, &self.route, request).await,
+                _ => self.assets.handle(request).await,
+            };
+        }
+
+        if rest == "/" {
+            return Ok(self.listing());
+        }
+
+        // Documents live at `/<prefix>/<path>.md`, their raw markdown and
+        // copies below it
+        let (document_url, wants_raw, wants_copy) =
+            match (rest.strip_suffix("/raw"), rest.strip_suffix("/copy")) {
+                (Some(document_url), _) if is_markdown(document_url) => (document_url, true, false),
+                (_, Some(document_url)) if is_markdown(document_url) => (document_url, false, true),
+                _ => (rest.as_str(), false, false),
+            };
+        if !is_markdown(document_url) {
+            return self.assets.handle(request).await;
+        }
+
+        match self.jail.resolve(document_url.trim_start_matches('/')) {
+            Ok(document) if document.is_file() && !self.hides(&document) => {
+                let base = format!("{}{}", self.route, document_url);
+                if wants_raw {
+                    raw(&document, request).await
+                } else if wants_copy {
+                    self.copy(&document, &base, request).await
+                } else {
+                    self.page(&document, &base, request).await
+                }
+            }
+            rejected => {
+                if let Err(rejection) = rejected {
+                    if rejection.is_attack() {
+                        jail::audit(&request.path, rejection);
+                    }
+                }
+                Ok(HttpResponse::error(
+                    StatusCode::NOT_FOUND,
+                    "Document not found",
+                ))
+            }
+        }
+    }
+
+    fn priority(&self) -> i32 {
+        10 // Same as the single-document markdown handler
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+async fn raw(document: &Path, request: HttpRequest) -> Result<HttpResponse> {
+    RawMarkdownHandler::new(String::new(), document.to_path_buf())
+        .handle(request)
+        .await
+}
+
+fn is_markdown(path: &str) -> bool {
+    let path = path.to_lowercase();
+    path.ends_with(".md") || path.ends_with(".markdown")
+}
+
+/// Link to a document from its root's listing, given its path below the root
+pub(crate) fn document_href(relative: &Path) -> String {
+    relative
+        .to_string_lossy()
+        .replace('\\', "/")
+        .split('/')
+        .map(|segment| utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether `path` is a document of `root`
+pub(crate) fn holds(root: &ServedRoot, path: &Path) -> bool {
+    if root.is_dir() {
+        path.starts_with(&root.path)
+    } else {
+        path == root.path
+    }
+}
+
+/// Name of the document at `path` of `roots`, relative to its root, and
+/// the URL of its preview
+pub(crate) fn document_link(roots: &[ServedRoot], path: &Path) -> Option<(String, String)> {
+    let root = roots.iter().find(|root| holds(root, path))?;
+    let base = if root.prefix.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", root.prefix)
+    };
+    Some(if root.is_dir() {
+        let relative = path.strip_prefix(&root.path).unwrap_or(path);
+        (
+            relative.to_string_lossy().replace('\\', "/"),
+            format!("{}/{}", base, document_href(relative)),
+        )
+    } else {
+        (
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            format!("{}/", base),
+        )
+    })
+}
+
+/// Document of `roots` whose preview is at `url`, e.g. `/docs/a%20b.md`,
+/// with links in directory roots treated as `symlinks` says
+pub(crate) fn document_at(
+    roots: &[ServedRoot],
+    url: &str,
+    symlinks: SymlinkPolicy,
+) -> Option<PathBuf> {
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    let url = url.trim_start_matches('/');
+    roots.iter().find_map(|root| {
+        let rest = if root.prefix.is_empty() {
+            url
+        } else {
+            url.strip_prefix(root.prefix.as_str())?
+                .strip_prefix('/')
+                .or_else(|| url.eq(root.prefix.as_str()).then_some(""))?
+        };
+        if !root.is_dir() {
+            return rest.is_empty().then(|| root.path.clone());
+        }
+        let document = match PathJail::new(&root.path, symlinks).resolve(rest) {
+            Ok(document) => document,
+            Err(rejection) => {
+                if rejection.is_attack() {
+                    jail::audit(url, rejection);
+                }
+                return None;
+            }
+        };
+        (document.is_file() && is_markdown(&document.to_string_lossy())).then_some(document)
+    })
+}
+
+/// Handler listing the served roots at `/`
+pub struct RootIndexHandler {
+    roots: Vec<ServedRoot>,
+}
+
+impl RootIndexHandler {
+    /// Create an index of `roots`
+    pub fn new(roots: Vec<ServedRoot>) -> Self {
+        Self { roots }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for RootIndexHandler {
+    fn path_pattern(&self) -> &str {
+        "/"
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        let items: String = self
+            .roots
+            .iter()
+            .map(|root| {
+                format!(
+                    "<li><a href=\"/{}/\">{}</a> <code>{}</code></li>\n",
+                    root.prefix,
+                    root.prefix,
+                    html_escape::encode_text(&root.path.to_string_lossy())
+                )
+            })
+            .collect();
+
+        Ok(HttpResponse::html(handlers::standalone_page(
+            "Rune",
+            &format!("<h1>Served documents</h1>\n<ul>\n{}</ul>", items),
+        )))
+    }
+
+    fn priority(&self) -> i32 {
+        10
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Forwards file changes to the roots containing them
+struct RootsEventHandler {
+    roots: Vec<Arc<RootHandler>>,
+    context: PluginContext,
+}
+
+#[async_trait]
+impl SystemEventHandler for RootsEventHandler {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        if let SystemEvent::FileChanged {
+            path, change_type, ..
+        } = event
+        {
+            if change_type.is_template_change() {
+                return Ok(());
+            }
+            // Deleted files cannot be canonicalized; compare them as reported
+            let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+            for root in &self.roots {
+                if root.root().contains(&path) {
+                    root.file_changed(&path, &self.context).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "served-roots-event-handler"
+    }
+}
+
+/// Register a handler per served root, the root index and change forwarding
+pub async fn register_root_handlers(
+    registry: &Arc<HandlerRegistry>,
+    roots: &[ServedRoot],
+    context: &PluginContext,
+) -> Result<Vec<Arc<RootHandler>>> {
+    let renderer_registry = crate::shared_renderer_registry(context).await;
+    let drafts = DraftVisibility::from_config(&context.config);
+    let symlinks = SymlinkPolicy::from_config(&context.config);
+
+    let mut handlers = Vec::new();
+    for root in roots {
+        let handler = Arc::new(
+            RootHandler::new(root.clone(), registry, renderer_registry.clone())
+                .with_draft_visibility(drafts)
+                .with_symlink_policy(symlinks)
+                .with_large_file_threshold(Some(context.config.large_files.threshold_bytes())),
+        );
+        registry.register_http_handler(handler.clone()).await?;
+        info!("Serving {} at {}", root.path.display(), handler.url());
+        handlers.push(handler);
+    }
+
+    registry
+        .register_http_handler(Arc::new(RootIndexHandler::new(roots.to_vec())))
+        .await?;
+    crate::anchors::register_anchor_check_handler(registry, roots, context).await?;
+
+    context
+        .event_bus
+        .subscribe_system_events(Arc::new(RootsEventHandler {
+            roots: handlers.clone(),
+            context: context.clone(),
+        }))
+        .await?;
+
+    Ok(handlers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::event::InMemoryEventBus;
+    use tempfile::TempDir;
+
+    async fn get(registry: &HandlerRegistry, path: &str) -> HttpResponse {
+        let handler = registry
+            .find_http_handler(path, &Method::GET)
+            .await
+            .unwrap();
+        handler.handle(HttpRequest::get(path)).await.unwrap()
+    }
+
+    fn body(response: &HttpResponse) -> String {
+        String::from_utf8_lossy(&response.body).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_roots_are_served_below_their_prefix() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(dir.join("docs/guide")).unwrap();
+        std::fs::write(dir.join("notes.md"), "# Notes\n").unwrap();
+        std::fs::write(dir.join("logo.png"), b"png").unwrap();
+        std::fs::write(dir.join("docs/guide/my intro.md"), "# Intro\n").unwrap();
+
+        let event_bus = Arc::new(InMemoryEventBus::new());
+        let registry = Arc::new(HandlerRegistry::new(event_bus.clone()));
+        let state_manager = Arc::new(rune_core::state::StateManager::new());
+        let notes = state_manager.add_served_root(dir.join("notes.md")).await;
+        let docs = state_manager.add_served_root(dir.join("docs")).await;
+        let context =
+            PluginContext::new(event_bus, Arc::new(rune_core::Config::new()), state_manager);
+        register_root_handlers(&registry, &[notes, docs], &context)
+            .await
+            .unwrap();
+
+        assert!(body(&get(&registry, "/").await).contains("href=\"/docs/\""));
+
+        let redirect = get(&registry, "/notes").await;
+        assert_eq!(redirect.status, StatusCode::PERMANENT_REDIRECT);
+
+        let page = body(&get(&registry, "/notes/").await);
+        assert!(page.contains("<meta name=\"rune-base\" content=\"/notes\">"));
+        assert!(page.contains("Notes"));
+        assert_eq!(body(&get(&registry, "/notes/raw").await), "# Notes\n");
+        assert_eq!(body(&get(&registry, "/notes/logo.png").await), "png");
+
+        let listing = body(&get(&registry, "/docs/").await);
+        assert!(listing.contains("href=\"guide/my%20intro.md\""));
+        let page = body(&get(&registry, "/docs/guide/my%20intro.md").await);
+        assert!(page.contains("content=\"/docs/guide/my%20intro.md\""));
+        assert!(registry
+            .find_websocket_handler("/docs/guide/my%20intro.md/ws")
+            .await
+            .is_some());
+        assert_eq!(
+            body(&get(&registry, "/docs/guide/my%20intro.md/raw").await),
+            "# Intro\n"
+        );
+
+        let missing = get(&registry, "/docs/../notes.md").await;
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_hidden_drafts_are_not_listed_or_served() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().canonicalize().unwrap();
+        std::fs::write(dir.join("done.md"), "# Done\n").unwrap();
+        std::fs::write(dir.join("wip.md"), "---\ndraft: true\n---\n# Wip\n").unwrap();
+
+        let event_bus = Arc::new(InMemoryEventBus::new());
+        let registry = Arc::new(HandlerRegistry::new(event_bus.clone()));
+        let root = ServedRoot {
+            prefix: "docs".to_string(),
+            path: dir,
+        };
+        let hidden = RootHandler::new(root.clone(), &registry, None)
+            .with_draft_visibility(DraftVisibility::Hide);
+        let listing = body(&hidden.handle(HttpRequest::get("/docs/")).await.unwrap());
+        assert!(listing.contains("done.md") && !listing.contains("wip.md"));
+        let draft = hidden
+            .handle(HttpRequest::get("/docs/wip.md"))
+            .await
+            .unwrap();
+        assert_eq!(draft.status, StatusCode::NOT_FOUND);
+
+        let shown = RootHandler::new(root, &registry, None);
+        assert!(body(&shown.handle(HttpRequest::get("/docs/")).await.unwrap()).contains("wip.md"));
+        let draft = shown
+            .handle(HttpRequest::get("/docs/wip.md"))
+            .await
+            .unwrap();
+        assert_eq!(draft.status, StatusCode::OK);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlink_policy_applies_to_documents() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().canonicalize().unwrap();
+        std::fs::write(dir.join("real.md"), "# Real\n").unwrap();
+        std::os::unix::fs::symlink(dir.join("real.md"), dir.join("linked.md")).unwrap();
+
+        let registry = Arc::new(HandlerRegistry::new(Arc::new(InMemoryEventBus::new())));
+        let root = ServedRoot {
+            prefix: "docs".to_string(),
+            path: dir,
+        };
+        let within = RootHandler::new(root.clone(), &registry, None);
+        let linked = within
+            .handle(HttpRequest::get("/docs/linked.md"))
+            .await
+            .unwrap();
+        assert_eq!(linked.status, StatusCode::OK);
+
+        let deny = RootHandler::new(root.clone(), &registry, None)
+            .with_symlink_policy(SymlinkPolicy::Deny);
+        for path in ["/docs/linked.md", "/docs/linked.md/raw"] {
+            let response = deny.handle(HttpRequest::get(path)).await.unwrap();
+            assert_eq!(response.status, StatusCode::NOT_FOUND, "{}", path);
+        }
+        let real = deny
+            .handle(HttpRequest::get("/docs/real.md"))
+            .await
+            .unwrap();
+        assert_eq!(real.status, StatusCode::OK);
+
+        let roots = [root];
+        assert!(document_at(&roots, "/docs/linked.md", SymlinkPolicy::WithinRoot).is_some());
+        assert!(document_at(&roots, "/docs/linked.md", SymlinkPolicy::Deny).is_none());
+    }
+}
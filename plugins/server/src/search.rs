@@ -0,0 +1,651 @@
+//! Search index, file tree and saved searches of the served documents
+//!
+//! A [`SearchIndex`] holds what the [saved searches](rune_core::search) of
+//! the configuration look at in every served document, and which documents
+//! each search matches. It is built when the server starts; file change
+//! events update the changed document and its place in the results of each
+//! search, without running the searches over every document again.
+//!
+//! `GET /api/files` returns the tree of the served roots, their folders and
+//! documents, followed by a virtual folder per saved search holding its
+//! matches, and the [pinned and recently edited](crate::recent_api)
+//! documents. `GET /saved` lists the saved searches on a page and
+//! `GET /saved/<slug>` the documents of one.
+
+use crate::roots::{document_link, holds, markdown_documents};
+use crate::{handlers, HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::search::{self, SavedSearch, SearchDocument};
+use rune_core::{
+    error::Result,
+    event::{SystemEvent, SystemEventHandler},
+    plugin::PluginContext,
+    state::{ServedRoot, StateManager},
+    RuneError,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+/// Route of the saved search pages
+const SAVED_ROUTE: &str = "/saved";
+
+/// Documents and saved search results
+#[derive(Debug, Default)]
+struct IndexState {
+    documents: BTreeMap<PathBuf, SearchDocument>,
+    /// Documents matching each saved search, by name
+    results: BTreeMap<String, BTreeSet<PathBuf>>,
+}
+
+/// What saved searches look at in the documents of the served roots
+pub struct SearchIndex {
+    roots: Vec<ServedRoot>,
+    searches: BTreeMap<String, SavedSearch>,
+    /// Whether a search needs the text of documents, which is kept only then
+    with_text: bool,
+    state: RwLock<IndexState>,
+}
+
+impl SearchIndex {
+    /// Create an empty index of the documents of `roots` for `searches`
+    pub fn new(roots: &[ServedRoot], searches: BTreeMap<String, SavedSearch>) -> Self {
+        let roots = roots
+            .iter()
+            .map(|root| ServedRoot {
+                prefix: root.prefix.clone(),
+                path: root
+                    .path
+                    .canonicalize()
+                    .unwrap_or_else(|_| root.path.clone()),
+            })
+            .collect();
+        let with_text = searches.values().any(SavedSearch::needs_text);
+        let results = searches
+            .keys()
+            .map(|name| (name.clone(), BTreeSet::new()))
+            .collect();
+        Self {
+            roots,
+            searches,
+            with_text,
+            state: RwLock::new(IndexState {
+                documents: BTreeMap::new(),
+                results,
+            }),
+        }
+    }
+
+    /// Read every document of the roots again and run every search
+    pub async fn rebuild(&self) -> Result<()> {
+        let roots = self.roots.clone();
+        let with_text = self.with_text;
+        // Reading every document is blocking work
+        let documents = tokio::task::spawn_blocking(move || {
+            roots
+                .iter()
+                .flat_map(|root| {
+                    if root.is_dir() {
+                        markdown_documents(&root.path)
+                    } else {
+                        vec![root.path.clone()]
+                    }
+                })
+                .filter_map(|path| {
+                    let content = std::fs::read_to_string(&path).ok()?;
+                    let document = SearchDocument::of(&path, &content, with_text);
+                    Some((path, document))
+                })
+                .collect::<BTreeMap<_, _>>()
+        })
+        .await
+        .map_err(|e| RuneError::Server(format!("Search indexing failed: {}", e)))?;
+
+        let results = self
+            .searches
+            .iter()
+            .map(|(name, search)| {
+                let matches = documents
+                    .iter()
+                    .filter(|(_, document)| search.matches(document))
+                    .map(|(path, _)| path.clone())
+                    .collect();
+                (name.clone(), matches)
+            })
+            .collect();
+        info!("Indexed {} document(s) for search", documents.len());
+        *self.state.write().await = IndexState { documents, results };
+        Ok(())
+    }
+
+    /// Read a changed document again, or forget it when it is gone, and
+    /// update the results it is in
+    pub async fn update(&self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if !self.roots.iter().any(|root| holds(root, &path)) {
+            return;
+        }
+
+        let document = tokio::fs::read_to_string(&path)
+            .await
+            .ok()
+            .map(|content| SearchDocument::of(&path, &content, self.with_text));
+        let mut state = self.state.write().await;
+        let IndexState { documents, results } = &mut *state;
+        for (name, search) in &self.searches {
+            let matches = results.entry(name.clone()).or_default();
+            if document.as_ref().is_some_and(|d| search.matches(d)) {
+                matches.insert(path.clone());
+            } else {
+                matches.remove(&path);
+            }
+        }
+        match document {
+            Some(document) => {
+                debug!("Reindexed {} for search", path.display());
+                documents.insert(path, document);
+            }
+            None => {
+                documents.remove(&path);
+            }
+        }
+    }
+
+    /// Documents matching the saved search `name`, by path
+    pub async fn results(&self, name: &str) -> Vec<TreeEntry> {
+        let state = self.state.read().await;
+        state
+            .results
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter_map(|path| self.document_entry(&state, path))
+            .collect()
+    }
+
+    /// Entries of the served documents among `paths`, in their order
+    pub async fn documents<'a>(&self, paths: impl IntoIterator<Item = &'a Path>) -> Vec<TreeEntry> {
+        let state = self.state.read().await;
+        paths
+            .into_iter()
+            .filter_map(|path| self.document_entry(&state, path))
+            .collect()
+    }
+
+    fn document_entry(&self, state: &IndexState, path: &Path) -> Option<TreeEntry> {
+        let (document, url) = document_link(&self.roots, path)?;
+        Some(TreeEntry {
+            name: document.rsplit('/').next().unwrap_or_default().to_string(),
+            kind: EntryKind::Document,
+            title: state.documents.get(path).map(|d| d.title.clone()),
+            url: Some(url),
+            children: Vec::new(),
+        })
+    }
+
+    /// Tree of the served roots, then of the saved searches
+    pub async fn tree(&self) -> Vec<TreeEntry> {
+        let state = self.state.read().await;
+        let mut tree = Vec::new();
+        for root in &self.roots {
+            if !root.is_dir() {
+                if let Some(entry) = self.document_entry(&state, &root.path) {
+                    tree.push(entry);
+                }
+                continue;
+            }
+            let (name, url) = if root.prefix.is_empty() {
+                let name = root
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                (name, "/".to_string())
+            } else {
+                (root.prefix.clone(), format!("/{}/", root.prefix))
+            };
+            let mut folder = TreeEntry::folder(&name, Some(url));
+            for path in state
+                .documents
+                .keys()
+                .filter(|path| path.starts_with(&root.path))
+            {
+                let Some(entry) = self.document_entry(&state, path) else {
+                    continue;
+                };
+                let relative = path.strip_prefix(&root.path).unwrap_or(path);
+                let folders: Vec<String> = relative
+                    .parent()
+                    .into_iter()
+                    .flat_map(Path::components)
+                    .map(|component| component.as_os_str().to_string_lossy().into_owned())
+                    .collect();
+                folder.insert(&folders, entry);
+            }
+            tree.push(folder);
+        }
+        drop(state);
+
+        for name in self.searches.keys() {
+            tree.push(TreeEntry {
+                name: name.clone(),
+                kind: EntryKind::SavedSearch,
+                title: None,
+                url: Some(saved_search_url(name)),
+                children: self.results(name).await,
+            });
+        }
+        tree
+    }
+}
+
+/// What an entry of the file tree is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Folder,
+    Document,
+    /// Virtual folder of the matches of a saved search
+    SavedSearch,
+}
+
+/// Entry of the file tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TreeEntry {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: EntryKind,
+    /// Title of a document
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TreeEntry>,
+}
+
+impl TreeEntry {
+    fn folder(name: &str, url: Option<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: EntryKind::Folder,
+            title: None,
+            url,
+            children: Vec::new(),
+        }
+    }
+
+    /// Add `entry` below the nested `folders` of this one, creating them
+    fn insert(&mut self, folders: &[String], entry: TreeEntry) {
+        let Some((first, rest)) = folders.split_first() else {
+            self.children.push(entry);
+            return;
+        };
+        let index = match self
+            .children
+            .iter()
+            .position(|child| child.kind == EntryKind::Folder && &child.name == first)
+        {
+            Some(index) => index,
+            None => {
+                let url = self.url.as_deref().map(|url| {
+                    format!("{}{}/", url, crate::roots::document_href(Path::new(first)))
+                });
+                self.children.push(Self::folder(first, url));
+                self.children.len() - 1
+            }
+        };
+        self.children[index].insert(rest, entry);
+    }
+}
+
+/// URL of the page of the saved search `name`
+fn saved_search_url(name: &str) -> String {
+    format!("{}/{}", SAVED_ROUTE, search::slug(name))
+}
+
+/// Recently edited documents listed with the file tree
+const TREE_RECENT: usize = 10;
+
+/// Handler for `GET /api/files`
+pub struct FileTreeHandler {
+    path_pattern: String,
+    index: Arc<SearchIndex>,
+    state_manager: Arc<StateManager>,
+}
+
+impl FileTreeHandler {
+    pub fn new(
+        path_pattern: String,
+        index: Arc<SearchIndex>,
+        state_manager: Arc<StateManager>,
+    ) -> Self {
+        Self {
+            path_pattern,
+            index,
+            state_manager,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for FileTreeHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        let workspace = self.state_manager.workspace_documents().await;
+        let pinned = self
+            .index
+            .documents(workspace.pinned.iter().map(PathBuf::as_path))
+            .await;
+        let mut recent = self
+            .index
+            .documents(workspace.recent.iter().map(|recent| recent.path.as_path()))
+            .await;
+        recent.truncate(TREE_RECENT);
+        Ok(HttpResponse::json(&serde_json::json!({
+            "tree": self.index.tree().await,
+            "pinned": pinned,
+            "recent": recent,
+        }))?
+        .with_header("cache-control", "no-store"))
+    }
+
+    fn matches_path(&self, path: &str) -> bool {
+        // `/api/files/new` is the new document API
+        path == self.path_pattern
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Handler for the `/saved` pages
+pub struct SavedSearchPageHandler {
+    path_pattern: String,
+    index: Arc<SearchIndex>,
+}
+
+impl SavedSearchPageHandler {
+    pub fn new(path_pattern: String, index: Arc<SearchIndex>) -> Self {
+        Self {
+            path_pattern,
+            index,
+        }
+    }
+}
+
+/// Description of the filters of `search`
+fn describe(search: &SavedSearch) -> String {
+    let mut filters = Vec::new();
+    if let Some(text) = search.text.as_deref().filter(|t| !t.trim().is_empty()) {
+        filters.push(format!("containing “{}”", text.trim()));
+    }
+    if !search.tags.is_empty() {
+        let tags: Vec<String> = search
+            .tags
+            .iter()
+            .map(|tag| format!("#{}", tag.trim_start_matches('#')))
+            .collect();
+        filters.push(format!("tagged {}", tags.join(" ")));
+    }
+    for (key, value) in &search.frontmatter {
+        filters.push(format!("with {}: {}", key, value));
+    }
+    format!("Documents {}", filters.join(", "))
+}
+
+#[async_trait]
+impl HttpHandler for SavedSearchPageHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let slug = request
+            .path
+            .strip_prefix(&self.path_pattern)
+            .unwrap_or_default()
+            .trim_matches('/');
+        let (title, body) = if slug.is_empty() {
+            let mut body = String::from("<h1>Saved searches</h1>\n");
+            if self.index.searches.is_empty() {
+                body.push_str(
+                    "<p>No saved searches. Add some to the <code>saved_searches</code> \
+                    section of the configuration.</p>\n",
+                );
+            } else {
+                body.push_str("<ul class=\"rune-saved-searches\">\n");
+                for (name, search) in &self.index.searches {
+                    body.push_str(&format!(
+                        "<li><a href=\"{}\">{}</a> <span class=\"rune-tag-count\">{}</span><br><small>{}</small></li>\n",
+                        html_escape::encode_double_quoted_attribute(&saved_search_url(name)),
+                        html_escape::encode_text(name),
+                        self.index.results(name).await.len(),
+                        html_escape::encode_text(&describe(search))
+                    ));
+                }
+                body.push_str("</ul>\n");
+            }
+            ("Saved searches".to_string(), body)
+        } else {
+            let Some((name, search)) = self
+                .index
+                .searches
+                .iter()
+                .find(|(name, _)| search::slug(name) == slug)
+            else {
+                return Ok(HttpResponse::error(
+                    StatusCode::NOT_FOUND,
+                    &format!("No saved search named {}", slug),
+                ));
+            };
+            let mut body = format!(
+                "<p><a href=\"{}\">All saved searches</a></p>\n<h1>{}</h1>\n<p>{}</p>\n",
+                self.path_pattern,
+                html_escape::encode_text(name),
+                html_escape::encode_text(&describe(search))
+            );
+            let documents = self.index.results(name).await;
+            if documents.is_empty() {
+                body.push_str("<p>No documents match.</p>\n");
+            } else {
+                body.push_str("<ul class=\"rune-tag-documents\">\n");
+                for document in documents {
+                    body.push_str(&format!(
+                        "<li><a href=\"{}\">{}</a></li>\n",
+                        html_escape::encode_double_quoted_attribute(
+                            document.url.as_deref().unwrap_or_default()
+                        ),
+                        html_escape::encode_text(document.title.as_ref().unwrap_or(&document.name))
+                    ));
+                }
+                body.push_str("</ul>\n");
+            }
+            (name.clone(), body)
+        };
+        Ok(HttpResponse::html(handlers::standalone_page(&title, &body))
+            .with_header("cache-control", "no-store"))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // Before the document handlers
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Reindexes changed documents
+struct SearchEventHandler {
+    index: Arc<SearchIndex>,
+}
+
+#[async_trait]
+impl SystemEventHandler for SearchEventHandler {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        if let SystemEvent::FileChanged {
+            path, change_type, ..
+        } = event
+        {
+            let markdown = path.extension().is_some_and(|extension| {
+                extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown")
+            });
+            if markdown && !change_type.is_template_change() {
+                self.index.update(path).await;
+            }
+        }
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "search-index-event-handler"
+    }
+}
+
+/// Register the file tree API and saved search pages for the documents of
+/// `roots`
+pub async fn register_search_handlers(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+    roots: &[ServedRoot],
+) -> Result<()> {
+    let index = Arc::new(SearchIndex::new(
+        roots,
+        context.config.saved_searches.clone(),
+    ));
+    let initial = index.clone();
+    tokio::spawn(async move {
+        if let Err(e) = initial.rebuild().await {
+            tracing::warn!("Failed to index documents for search: {}", e);
+        }
+    });
+
+    registry
+        .register_http_handler(Arc::new(FileTreeHandler::new(
+            "/api/files".to_string(),
+            index.clone(),
+            context.state_manager.clone(),
+        )))
+        .await?;
+    registry
+        .register_http_handler(Arc::new(SavedSearchPageHandler::new(
+            SAVED_ROUTE.to_string(),
+            index.clone(),
+        )))
+        .await?;
+    context
+        .event_bus
+        .subscribe_system_events(Arc::new(SearchEventHandler { index }))
+        .await?;
+
+    info!("Registered file tree API and saved search handlers");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_saved_searches_are_virtual_folders() {
+        let temp_dir = TempDir::new().unwrap();
+        let docs = temp_dir.path();
+        std::fs::create_dir_all(docs.join("notes")).unwrap();
+        std::fs::write(
+            docs.join("notes/plan.md"),
+            "# Plan\n\nAn open question. #research\n",
+        )
+        .unwrap();
+        std::fs::write(docs.join("readme.md"), "---\nstatus: draft\n---\nHello\n").unwrap();
+        let searches = BTreeMap::from([
+            (
+                "Open questions".to_string(),
+                serde_json::from_str(r#"{"text": "question", "tags": ["research"]}"#).unwrap(),
+            ),
+            (
+                "Drafts".to_string(),
+                serde_json::from_str(r#"{"frontmatter": {"status": "draft"}}"#).unwrap(),
+            ),
+        ]);
+        let index = Arc::new(SearchIndex::new(
+            &[ServedRoot {
+                prefix: "docs".to_string(),
+                path: docs.to_path_buf(),
+            }],
+            searches,
+        ));
+        index.rebuild().await.unwrap();
+
+        let state_manager = Arc::new(StateManager::new());
+        let readme = docs.canonicalize().unwrap().join("readme.md");
+        state_manager.set_pinned(&readme, true).await.unwrap();
+        state_manager.record_edit(&readme).await.unwrap();
+        let response = FileTreeHandler::new("/api/files".to_string(), index.clone(), state_manager)
+            .handle(HttpRequest::get("/api/files"))
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        let tree = &body["tree"];
+        assert_eq!(tree[0]["name"], "docs");
+        assert_eq!(tree[0]["children"][0]["name"], "notes");
+        assert_eq!(tree[0]["children"][0]["url"], "/docs/notes/");
+        assert_eq!(
+            tree[0]["children"][0]["children"][0]["url"],
+            "/docs/notes/plan.md"
+        );
+        assert_eq!(tree[0]["children"][0]["children"][0]["title"], "Plan");
+        assert_eq!(tree[1]["name"], "Drafts");
+        assert_eq!(tree[1]["type"], "saved_search");
+        assert_eq!(tree[1]["children"][0]["url"], "/docs/readme.md");
+        assert_eq!(tree[2]["url"], "/saved/open-questions");
+        assert_eq!(tree[2]["children"].as_array().unwrap().len(), 1);
+        assert_eq!(body["pinned"][0]["url"], "/docs/readme.md");
+        assert_eq!(body["recent"][0]["name"], "readme.md");
+
+        // Changes move documents in and out of the results
+        std::fs::write(
+            docs.join("notes/plan.md"),
+            "# Plan\n\nAnswered. #research\n",
+        )
+        .unwrap();
+        index.update(&docs.join("notes/plan.md")).await;
+        std::fs::write(docs.join("todo.md"), "A question? #research/ml\n").unwrap();
+        index.update(&docs.join("todo.md")).await;
+        let results = index.results("Open questions").await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].url.as_deref(), Some("/docs/todo.md"));
+
+        let pages = SavedSearchPageHandler::new("/saved".to_string(), index);
+        let page = pages
+            .handle(HttpRequest::get("/saved/open-questions"))
+            .await
+            .unwrap();
+        let html = String::from_utf8(page.body.to_vec()).unwrap();
+        assert!(html.contains("Documents containing “question”, tagged #research"));
+        assert!(html.contains("<a href=\"/docs/todo.md\">Todo</a>"));
+        let missing = pages.handle(HttpRequest::get("/saved/none")).await.unwrap();
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    }
+}
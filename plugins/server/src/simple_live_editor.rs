@@ -5,7 +5,7 @@
 //! 2. DOM 仅用于渲染显示  
 //! 3. 编辑操作直接修改 markdown 文本，而不是依赖 DOM 反向工程
 
-use crate::{HttpHandler, HttpRequest, HttpResponse};
+use crate::{csrf, HttpHandler, HttpRequest, HttpResponse};
 use async_trait::async_trait;
 use axum::http::{Method, StatusCode};
 use rune_core::{quill::Quill, Result};
@@ -279,7 +279,10 @@ impl SimpleLiveEditorHandler {
         
         function initWebSocket() {{
             const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
-            const wsUrl = `${{protocol}}//${{window.location.host}}/ws/editor`;
+            const token = document.querySelector('meta[name="rune-token"]')?.content
+                || sessionStorage.getItem('rune-token');
+            const query = token ? `?token=${{encodeURIComponent(token)}}` : '';
+            const wsUrl = `${{protocol}}//${{window.location.host}}/ws/editor${{query}}`;
             ws = new WebSocket(wsUrl);
             
             ws.onopen = () => {{
@@ -567,12 +570,10 @@ impl HttpHandler for SimpleLiveEditorHandler {
     async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
         let session_id = Uuid::new_v4().to_string();
 
-        let content = tokio::fs::read_to_string(&self.markdown_file)
-            .await
-            .unwrap_or_default();
+        let content = rune_core::crypto::read_document(&self.markdown_file).unwrap_or_default();
 
         let html = self.generate_simple_live_editor_html(&content, &session_id);
-        Ok(HttpResponse::html(&html))
+        Ok(HttpResponse::html(csrf::with_fetch_script(&html)))
     }
 
     fn priority(&self) -> i32 {
@@ -0,0 +1,736 @@
+//! Frozen snapshots of the rendered document
+//!
+//! A snapshot captures the rendered HTML of the current document together with
+//! the local images it references, so a stable link (`/s/<id>`) can be shared
+//! while the source keeps changing. Snapshots live on disk next to the document
+//! in `.rune/snapshots/<id>/`, each with an `index.html`, an `assets/` directory
+//! and a `metadata.json` file. Only the page and its assets are served; taking
+//! a snapshot is an API call with the configuration API's access rules.
+//! Snapshots are plaintext, so encrypted documents have none.
+
+use crate::config_api::ApiAccess;
+use crate::handlers::{
+    has_mermaid, mermaid_js, mermaid_scripts, standalone_page, MarkdownHandler, StaticHandler,
+};
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use regex::Regex;
+use rune_core::crypto;
+use rune_core::error::{Result, RuneError};
+use rune_core::renderer::RendererRegistry;
+use rune_core::rune_dir::RuneDir;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+/// Metadata stored alongside each snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotMetadata {
+    pub id: String,
+    pub title: Option<String>,
+    pub label: Option<String>,
+    pub source_file: String,
+    /// Creation time in seconds since the Unix epoch
+    pub created_at: u64,
+    pub content_hash: String,
+    /// Asset paths copied into the snapshot, relative to its `assets/` directory
+    pub assets: Vec<String>,
+}
+
+impl SnapshotMetadata {
+    /// URL path under which the snapshot is served
+    pub fn url(&self) -> String {
+        format!("/s/{}", self.id)
+    }
+}
+
+/// On-disk store of frozen snapshots
+#[derive(Debug, Clone)]
+pub struct SnapshotStore {
+    root: PathBuf,
+}
+
+impl SnapshotStore {
+    /// Create a store rooted at the given directory
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Create the store used for a markdown document (`<dir>/.rune/snapshots`)
+    pub fn for_document(markdown_file: &Path) -> Self {
+        let dir = markdown_file.parent().unwrap_or_else(|| Path::new("."));
+        Self::new(RuneDir::of(dir).snapshots_dir())
+    }
+
+    /// Get the root directory of the store
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Freeze rendered content into a new snapshot
+    ///
+    /// Relative image references are resolved against `base_dir`, copied into
+    /// the snapshot and rewritten to point at the frozen copies.
+    pub fn create(
+        &self,
+        content_html: &str,
+        base_dir: &Path,
+        source_file: &Path,
+        title: Option<String>,
+        label: Option<String>,
+    ) -> Result<SnapshotMetadata> {
+        if crypto::is_encrypted(source_file) {
+            return Err(RuneError::Server(format!(
+                "{} is encrypted and snapshots are not",
+                source_file.display()
+            )));
+        }
+        let id = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+        let snapshot_dir = self.root.join(&id);
+        let assets_dir = snapshot_dir.join("assets");
+        fs::create_dir_all(&assets_dir).map_err(|e| {
+            RuneError::Server(format!("Failed to create snapshot directory: {}", e))
+        })?;
+
+        let (mut content, mut assets) = freeze_assets(content_html, base_dir, &assets_dir, &id)?;
+
+        if has_mermaid(&content) {
+            fs::write(assets_dir.join("mermaid.min.js"), mermaid_js())
+                .map_err(|e| RuneError::Server(format!("Failed to write snapshot asset: {}", e)))?;
+            assets.push("mermaid.min.js".to_string());
+            content.push_str(&mermaid_scripts(&format!(
+                "/s/{}/assets/mermaid.min.js",
+                id
+            )));
+        }
+
+        let mut hasher = DefaultHasher::new();
+        content_html.hash(&mut hasher);
+
+        let metadata = SnapshotMetadata {
+            id: id.clone(),
+            title,
+            label,
+            source_file: source_file.to_string_lossy().to_string(),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            content_hash: format!("{:016x}", hasher.finish()),
+            assets,
+        };
+
+        fs::write(
+            snapshot_dir.join("index.html"),
+            snapshot_page(&metadata, &content),
+        )
+        .map_err(|e| RuneError::Server(format!("Failed to write snapshot page: {}", e)))?;
+
+        let metadata_json = serde_json::to_string_pretty(&metadata)?;
+        fs::write(snapshot_dir.join("metadata.json"), metadata_json)
+            .map_err(|e| RuneError::Server(format!("Failed to write snapshot metadata: {}", e)))?;
+
+        info!("Created snapshot {} of {}", id, source_file.display());
+        Ok(metadata)
+    }
+
+    /// Load the metadata of a snapshot
+    pub fn get(&self, id: &str) -> Result<Option<SnapshotMetadata>> {
+        if !is_valid_id(id) {
+            return Ok(None);
+        }
+
+        let path = self.root.join(id).join("metadata.json");
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| RuneError::Server(format!("Failed to read snapshot metadata: {}", e)))?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// List all snapshots, newest first
+    pub fn list(&self) -> Result<Vec<SnapshotMetadata>> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(RuneError::Server(format!(
+                    "Failed to read snapshot directory: {}",
+                    e
+                )))
+            }
+        };
+
+        let mut snapshots = Vec::new();
+        for entry in entries.flatten() {
+            let id = entry.file_name().to_string_lossy().to_string();
+            match self.get(&id) {
+                Ok(Some(metadata)) => snapshots.push(metadata),
+                Ok(None) => {}
+                Err(e) => warn!("Skipping unreadable snapshot {}: {}", id, e),
+            }
+        }
+
+        snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.created_at));
+        Ok(snapshots)
+    }
+
+    /// Get the path of a snapshot's page, if the snapshot exists
+    pub fn page_path(&self, id: &str) -> Option<PathBuf> {
+        if !is_valid_id(id) {
+            return None;
+        }
+
+        let path = self.root.join(id).join("index.html");
+        path.is_file().then_some(path)
+    }
+}
+
+/// Check that a snapshot id is safe to use as a directory name
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 32 && id.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Copy locally referenced images into the snapshot and rewrite their URLs
+fn freeze_assets(
+    html: &str,
+    base_dir: &Path,
+    assets_dir: &Path,
+    id: &str,
+) -> Result<(String, Vec<String>)> {
+    let img_src = Regex::new(r#"(<img\b[^>]*?\bsrc=")([^"]+)(")"#)
+        .map_err(|e| RuneError::Server(format!("Invalid asset pattern: {}", e)))?;
+    let base_dir = base_dir
+        .canonicalize()
+        .unwrap_or_else(|_| base_dir.to_path_buf());
+    let mut assets: Vec<String> = Vec::new();
+
+    let rewritten = img_src.replace_all(html, |caps: &regex::Captures| {
+        let src = &caps[2];
+        let original = caps[0].to_string();
+
+        let Some(relative) = local_asset_path(src, &base_dir) else {
+            return original;
+        };
+
+        let key = relative.to_string_lossy().replace('\\', "/");
+        if !assets.contains(&key) {
+            let target = assets_dir.join(&relative);
+            let copied = target
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::copy(base_dir.join(&relative), &target));
+            if let Err(e) = copied {
+                warn!("Failed to copy snapshot asset {}: {}", src, e);
+                return original;
+            }
+            assets.push(key.clone());
+        }
+
+        format!("{}/s/{}/assets/{}{}", &caps[1], id, key, &caps[3])
+    });
+
+    Ok((rewritten.into_owned(), assets))
+}
+
+/// Resolve an image reference to a file inside `base_dir`, relative to it
+fn local_asset_path(src: &str, base_dir: &Path) -> Option<PathBuf> {
+    if src.starts_with('/')
+        || src.starts_with('#')
+        || src.starts_with("data:")
+        || src.contains("://")
+    {
+        return None;
+    }
+
+    let path = src.split(['?', '#']).next().unwrap_or(src);
+    let canonical = base_dir.join(path).canonicalize().ok()?;
+    if !canonical.is_file() {
+        return None;
+    }
+
+    canonical
+        .strip_prefix(base_dir)
+        .ok()
+        .map(|relative| relative.to_path_buf())
+}
+
+/// Build the static snapshot page
+fn snapshot_page(metadata: &SnapshotMetadata, content: &str) -> String {
+    let title = format!(
+        "{} (snapshot)",
+        metadata.title.as_deref().unwrap_or("Untitled")
+    );
+    let label = metadata
+        .label
+        .as_deref()
+        .map(|label| format!(" &middot; {}", html_escape::encode_text(label)))
+        .unwrap_or_default();
+
+    let body = format!(
+        r#"    <p class="snapshot-banner" style="opacity: 0.7; font-size: 0.85em;">
+        Snapshot <code>{id}</code> &middot; <time data-created="{created}"></time>{label}
+    </p>
+    <div id="content">
+{content}
+    </div>
+<script>
+document.querySelectorAll('time[data-created]').forEach(function (el) {{
+    el.textContent = new Date(Number(el.dataset.created) * 1000).toLocaleString();
+}});
+</script>"#,
+        id = metadata.id,
+        created = metadata.created_at,
+        label = label,
+        content = content
+    );
+
+    standalone_page(&title, &body)
+}
+
+/// Request body accepted by `POST /api/snapshots`
+#[derive(Debug, Default, Deserialize)]
+struct CreateSnapshotRequest {
+    label: Option<String>,
+}
+
+/// Handler for `POST /api/snapshots`, freezing the current rendering
+pub struct SnapshotApiHandler {
+    path_pattern: String,
+    markdown: MarkdownHandler,
+    store: Arc<SnapshotStore>,
+    access: ApiAccess,
+}
+
+impl SnapshotApiHandler {
+    /// Create a new snapshot API handler for the given document
+    pub fn new(
+        path_pattern: String,
+        markdown: MarkdownHandler,
+        store: Arc<SnapshotStore>,
+        access: ApiAccess,
+    ) -> Self {
+        Self {
+            path_pattern,
+            markdown,
+            store,
+            access,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for SnapshotApiHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        if let Some(refusal) = self.access.check(&request, "The snapshot API").await {
+            return Ok(refusal);
+        }
+
+        let body: CreateSnapshotRequest = if request.body.is_empty() {
+            CreateSnapshotRequest::default()
+        } else {
+            match serde_json::from_slice(&request.body) {
+                Ok(body) => body,
+                Err(e) => {
+                    return Ok(HttpResponse::error(
+                        StatusCode::BAD_REQUEST,
+                        &format!("Invalid snapshot request: {}", e),
+                    ))
+                }
+            }
+        };
+
+        // The page would be written out in plaintext
+        if crypto::is_encrypted(self.markdown.markdown_file()) {
+            return Ok(HttpResponse::error(
+                StatusCode::CONFLICT,
+                "Encrypted documents cannot be snapshotted",
+            ));
+        }
+
+        let content = self.markdown.extract_content_only().await?;
+        let title = self.markdown.extract_title_from_content(&content);
+        let store = self.store.clone();
+        let base_dir = self.markdown.base_dir().to_path_buf();
+        let source_file = self.markdown.markdown_file().to_path_buf();
+
+        let metadata = tokio::task::spawn_blocking(move || {
+            store.create(&content, &base_dir, &source_file, title, body.label)
+        })
+        .await
+        .map_err(|e| RuneError::Server(format!("Snapshot task failed: {}", e)))??;
+
+        let mut response = HttpResponse::json(&serde_json::json!({
+            "snapshot": metadata,
+            "url": metadata.url(),
+        }))?
+        .with_header("location", &metadata.url());
+        response.status = StatusCode::CREATED;
+        Ok(response)
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Handler for `GET /api/snapshots`, listing stored snapshots
+pub struct SnapshotListHandler {
+    path_pattern: String,
+    store: Arc<SnapshotStore>,
+}
+
+impl SnapshotListHandler {
+    /// Create a new snapshot list handler
+    pub fn new(path_pattern: String, store: Arc<SnapshotStore>) -> Self {
+        Self {
+            path_pattern,
+            store,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for SnapshotListHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        let snapshots = self.store.list()?;
+        HttpResponse::json(&serde_json::json!({ "snapshots": snapshots }))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Handler serving frozen snapshots under `/s/<id>`
+pub struct SnapshotHandler {
+    path_pattern: String,
+    store: Arc<SnapshotStore>,
+}
+
+impl SnapshotHandler {
+    /// Create a new snapshot handler
+    pub fn new(path_pattern: String, store: Arc<SnapshotStore>) -> Self {
+        Self {
+            path_pattern,
+            store,
+        }
+    }
+
+    /// Serve a frozen asset, reusing the static handler's containment checks
+    async fn serve_asset(&self, request: HttpRequest) -> Result<HttpResponse> {
+        // The store directory only exists once a snapshot was taken, and the
+        // static handler needs its canonical form
+        let Ok(root) = self.store.root().canonicalize() else {
+            return Ok(HttpResponse::error(StatusCode::NOT_FOUND, "File not found"));
+        };
+
+        StaticHandler::new(root, self.path_pattern.clone())
+            .handle(request)
+            .await
+    }
+}
+
+#[async_trait]
+impl HttpHandler for SnapshotHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let rest = request
+            .path
+            .strip_prefix(&self.path_pattern)
+            .unwrap_or(&request.path)
+            .trim_start_matches('/');
+
+        match rest.split_once('/') {
+            // Metadata names the source file, so nothing but assets is served
+            Some((_, asset)) if asset.starts_with("assets/") => self.serve_asset(request).await,
+            Some((_, rest)) if !rest.is_empty() => {
+                Ok(HttpResponse::error(StatusCode::NOT_FOUND, "File not found"))
+            }
+            _ => {
+                let id = rest.trim_end_matches('/');
+                let Some(page) = self.store.page_path(id) else {
+                    return Ok(HttpResponse::error(
+                        StatusCode::NOT_FOUND,
+                        "Snapshot not found",
+                    ));
+                };
+
+                let html = fs::read_to_string(&page)
+                    .map_err(|e| RuneError::Server(format!("Failed to read snapshot: {}", e)))?;
+                debug!("Serving snapshot {}", id);
+                Ok(HttpResponse::html(html)
+                    .with_header("cache-control", "public, max-age=31536000, immutable"))
+            }
+        }
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for specific routes
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Register the snapshot API and viewer for a markdown document
+pub async fn register_snapshot_handlers(
+    registry: &HandlerRegistry,
+    markdown_file: &Path,
+    renderer_registry: Option<Arc<RendererRegistry>>,
+) -> Result<()> {
+    let store = Arc::new(SnapshotStore::for_document(markdown_file));
+    // Without configured checks only a token would do, and none is set
+    let access = registry
+        .access()
+        .await
+        .unwrap_or_else(|| ApiAccess::new(Arc::default(), None));
+
+    let markdown = match renderer_registry {
+        Some(renderer_registry) => MarkdownHandler::with_renderer_registry(
+            "/".to_string(),
+            markdown_file.to_path_buf(),
+            renderer_registry,
+        ),
+        None => MarkdownHandler::new("/".to_string(), markdown_file.to_path_buf()),
+    };
+
+    registry
+        .register_http_handler(Arc::new(SnapshotApiHandler::new(
+            "/api/snapshots".to_string(),
+            markdown,
+            store.clone(),
+            access,
+        )))
+        .await?;
+    registry
+        .register_http_handler(Arc::new(SnapshotListHandler::new(
+            "/api/snapshots".to_string(),
+            store.clone(),
+        )))
+        .await?;
+    registry
+        .register_http_handler(Arc::new(SnapshotHandler::new("/s".to_string(), store)))
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_snapshot_freezes_local_images() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("img")).unwrap();
+        fs::write(temp_dir.path().join("img/logo.png"), b"png").unwrap();
+        let doc = temp_dir.path().join("doc.md");
+
+        let store = SnapshotStore::for_document(&doc);
+        let html =
+            r#"<h1>Doc</h1><img src="img/logo.png" alt="x"><img src="https://example.com/a.png">"#;
+        let metadata = store
+            .create(
+                html,
+                temp_dir.path(),
+                &doc,
+                Some("Doc".to_string()),
+                Some("v1".to_string()),
+            )
+            .unwrap();
+
+        assert_eq!(metadata.assets, vec!["img/logo.png".to_string()]);
+        assert!(store
+            .root()
+            .join(&metadata.id)
+            .join("assets/img/logo.png")
+            .exists());
+
+        let page = fs::read_to_string(store.page_path(&metadata.id).unwrap()).unwrap();
+        assert!(page.contains(&format!("/s/{}/assets/img/logo.png", metadata.id)));
+        assert!(page.contains("https://example.com/a.png"));
+        assert!(page.contains("<title>Doc (snapshot)</title>"));
+        assert!(!page.contains("new WebSocket"));
+    }
+
+    #[test]
+    fn test_snapshot_ignores_assets_outside_base_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let docs = temp_dir.path().join("docs");
+        fs::create_dir_all(&docs).unwrap();
+        fs::write(temp_dir.path().join("secret.png"), b"png").unwrap();
+
+        let store = SnapshotStore::new(temp_dir.path().join("snapshots"));
+        let metadata = store
+            .create(
+                r#"<img src="../secret.png">"#,
+                &docs,
+                &docs.join("doc.md"),
+                None,
+                None,
+            )
+            .unwrap();
+
+        assert!(metadata.assets.is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_store_lookup_and_listing() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::new(temp_dir.path().join("snapshots"));
+        assert!(store.list().unwrap().is_empty());
+
+        let metadata = store
+            .create(
+                "<p>hi</p>",
+                temp_dir.path(),
+                Path::new("doc.md"),
+                None,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(store.get(&metadata.id).unwrap(), Some(metadata.clone()));
+        assert_eq!(store.list().unwrap(), vec![metadata.clone()]);
+        assert_eq!(metadata.url(), format!("/s/{}", metadata.id));
+        assert_eq!(store.get("../etc").unwrap(), None);
+        assert!(store.page_path("missing").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_api_creates_and_serves_snapshot() {
+        let temp_dir = TempDir::new().unwrap();
+        let doc = temp_dir.path().join("doc.md");
+        fs::write(&doc, "# Hello\n\nWorld").unwrap();
+
+        let store = Arc::new(SnapshotStore::for_document(&doc));
+        let api = |dev_mode: bool| {
+            let mut config = rune_core::config::Config::new();
+            config
+                .set_global_setting("dev_mode".to_string(), dev_mode)
+                .unwrap();
+            SnapshotApiHandler::new(
+                "/api/snapshots".to_string(),
+                MarkdownHandler::new("/".to_string(), doc.clone()),
+                store.clone(),
+                ApiAccess::new(Arc::new(config), None),
+            )
+        };
+        let take =
+            || HttpRequest::new(Method::POST, "/api/snapshots").with_body(r#"{"label": "draft"}"#);
+        let refused = api(false).handle(take()).await.unwrap();
+        assert_eq!(refused.status, StatusCode::FORBIDDEN);
+        let forged = api(true)
+            .handle(
+                take()
+                    .with_header("host", "localhost:3000")
+                    .with_header("origin", "https://evil.example"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(forged.status, StatusCode::FORBIDDEN);
+
+        let response = api(true).handle(take()).await.unwrap();
+        assert_eq!(response.status, StatusCode::CREATED);
+        let created: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        let url = created["url"].as_str().unwrap().to_string();
+        assert_eq!(created["snapshot"]["label"], "draft");
+        assert_eq!(created["snapshot"]["title"], "Hello");
+
+        // Editing the source must not change the frozen snapshot
+        fs::write(&doc, "# Changed").unwrap();
+
+        let viewer = SnapshotHandler::new("/s".to_string(), store);
+        let response = viewer.handle(HttpRequest::get(&url)).await.unwrap();
+        let page = String::from_utf8(response.body.to_vec()).unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert!(page.contains("World"));
+        assert!(!page.contains("Changed"));
+
+        // Metadata stays private
+        let metadata = viewer
+            .handle(HttpRequest::get(&format!("{}/metadata.json", url)))
+            .await
+            .unwrap();
+        assert_eq!(metadata.status, StatusCode::NOT_FOUND);
+
+        let missing = viewer
+            .handle(HttpRequest::get("/s/doesnotexist"))
+            .await
+            .unwrap();
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_documents_have_no_snapshots() {
+        let temp_dir = TempDir::new().unwrap();
+        let doc = temp_dir.path().join("diary.md.age");
+        fs::write(&doc, "age-encryption.org/v1").unwrap();
+        let store = Arc::new(SnapshotStore::for_document(&doc));
+        let mut config = rune_core::config::Config::new();
+        config
+            .set_global_setting("api_token".to_string(), "s3cret")
+            .unwrap();
+        let api = SnapshotApiHandler::new(
+            "/api/snapshots".to_string(),
+            MarkdownHandler::new("/".to_string(), doc.clone()),
+            store.clone(),
+            ApiAccess::new(Arc::new(config), None),
+        );
+
+        let response = api
+            .handle(
+                HttpRequest::new(Method::POST, "/api/snapshots")
+                    .with_header("authorization", "Bearer s3cret"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::CONFLICT);
+        assert!(store
+            .create("<p>secret</p>", temp_dir.path(), &doc, None, None)
+            .is_err());
+        assert!(store.list().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+//! Local usage statistics page
+//!
+//! `/stats` shows the counts the usage recorder keeps when the host enables
+//! telemetry, and how to enable it when it does not; `GET /api/stats`
+//! returns them as JSON. Only local clients, or requests with the API
+//! token, may see them. The handler registry counts the documents opened
+//! and the APIs used as the server answers requests.
+
+use crate::config_api::ApiAccess;
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::{
+    error::Result,
+    plugin::PluginContext,
+    telemetry::{UsageRecorder, UsageStats, USAGE_STATS_RESOURCE},
+};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::info;
+
+/// Body of `GET /api/stats`
+#[derive(Debug, Serialize)]
+pub struct StatsReport {
+    /// Whether usage is being recorded
+    pub enabled: bool,
+    /// File the counts are kept in
+    pub path: Option<PathBuf>,
+    pub stats: Option<UsageStats>,
+}
+
+/// Handler for the `/stats` page and `GET /api/stats`
+pub struct StatsHandler {
+    path_pattern: String,
+    recorder: Option<Arc<UsageRecorder>>,
+    access: ApiAccess,
+}
+
+impl StatsHandler {
+    /// Create a handler showing what `recorder` counted, if usage is recorded
+    pub fn new(
+        path_pattern: String,
+        recorder: Option<Arc<UsageRecorder>>,
+        access: ApiAccess,
+    ) -> Self {
+        Self {
+            path_pattern,
+            recorder,
+            access,
+        }
+    }
+
+    fn report(&self) -> StatsReport {
+        StatsReport {
+            enabled: self.recorder.is_some(),
+            path: self
+                .recorder
+                .as_ref()
+                .map(|recorder| recorder.path().to_path_buf()),
+            stats: self.recorder.as_ref().map(|recorder| recorder.snapshot()),
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for StatsHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        if let Some(refusal) = self.access.check_edit(&request, "Usage statistics").await {
+            return Ok(refusal);
+        }
+        if request.path.trim_end_matches('/') != self.path_pattern {
+            return Ok(HttpResponse::error(StatusCode::NOT_FOUND, "Not found"));
+        }
+        let response = if self.path_pattern.starts_with("/api/") {
+            HttpResponse::json(&self.report())?
+        } else {
+            HttpResponse::html(STATS_PAGE)
+        };
+        Ok(response.with_header("cache-control", "no-store"))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for specific page
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Register the statistics page and API, and count requests into the
+/// recorder the host shares, if any
+pub async fn register_stats_handlers(
+    registry: &Arc<HandlerRegistry>,
+    context: &PluginContext,
+) -> Result<()> {
+    let recorder = context
+        .get_shared_resource::<Arc<UsageRecorder>>(USAGE_STATS_RESOURCE)
+        .await
+        .map(|recorder| (*recorder).clone());
+    if let Some(recorder) = &recorder {
+        registry.set_usage(recorder.clone()).await;
+    }
+
+    let access = ApiAccess::from_context(context).await;
+    for path in ["/stats", "/api/stats"] {
+        registry
+            .register_http_handler(Arc::new(StatsHandler::new(
+                path.to_string(),
+                recorder.clone(),
+                access.clone(),
+            )))
+            .await?;
+    }
+
+    info!("Registered usage statistics page at /stats");
+    Ok(())
+}
+
+/// Feature a request to a handler of `route` counts as a use of: the API
+/// it belongs to, or the share page
+pub(crate) fn route_feature(route: &str) -> Option<&str> {
+    if route == "/share" {
+        return Some("share");
+    }
+    let api = route.strip_prefix("/api/")?.split('/').next()?;
+    (!api.is_empty() && api != "stats").then_some(api)
+}
+
+const STATS_PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Rune usage statistics</title>
+<style>
+body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif; margin: 0; padding: 1.5rem 2rem; color: #24292e; background: #f6f8fa; }
+h1 { font-size: 1.4rem; margin: 0 0 1rem; }
+h2 { font-size: 1rem; margin: 0 0 .5rem; }
+section { background: #fff; border: 1px solid #e1e4e8; border-radius: 6px; padding: 1rem; margin-bottom: 1rem; max-width: 48rem; }
+table { border-collapse: collapse; width: 100%; font-size: .9rem; }
+th, td { text-align: left; padding: .3rem .5rem; border-bottom: 1px solid #eaecef; }
+th { color: #586069; font-weight: 600; }
+td.count { text-align: right; font-variant-numeric: tabular-nums; }
+code { background: #f3f4f6; padding: .1rem .3rem; border-radius: 3px; }
+.muted { color: #6a737d; }
+</style>
+</head>
+<body>
+<h1>Usage statistics</h1>
+<section id="disabled" hidden>
+<p>Usage statistics are off. To count the documents you open, the renders and the features you use on this machine, add this to your configuration:</p>
+<p><code>"telemetry": {"enabled": true}</code></p>
+</section>
+<div id="enabled" hidden>
+<section><h2>Totals</h2><table id="totals"></table></section>
+<section><h2>Features</h2><table id="features"></table></section>
+</div>
+<section class="muted">
+<p>Counts are kept on this machine only and never sent anywhere; no document names or contents are recorded. <span id="path"></span></p>
+<p>Print or export them with <code>rune stats</code> and <code>rune stats --export stats.json</code>, and delete them with <code>rune stats --delete</code>.</p>
+</section>
+<script>
+function fill(id, rows) {
+  const table = document.getElementById(id);
+  table.replaceChildren();
+  if (rows.length === 0) {
+    const cell = table.insertRow().insertCell();
+    cell.className = 'muted'; cell.textContent = 'None yet';
+  }
+  rows.forEach(([name, value]) => {
+    const tr = table.insertRow();
+    tr.insertCell().textContent = name;
+    const td = tr.insertCell(); td.textContent = value; td.className = 'count';
+  });
+}
+function date(secs) { return new Date(secs * 1000).toLocaleString(); }
+async function refresh() {
+  const report = await (await fetch('/api/stats')).json();
+  document.getElementById('disabled').hidden = report.enabled;
+  document.getElementById('enabled').hidden = !report.enabled;
+  if (!report.enabled) return;
+  const s = report.stats;
+  document.getElementById('path').textContent = 'They are kept in ' + report.path + '.';
+  fill('totals', [['Counting since', date(s.since)], ['Last updated', date(s.updated)],
+    ['Sessions', s.sessions], ['Documents opened', s.documents_opened], ['Renders', s.renders]]);
+  fill('features', Object.entries(s.features).sort((a, b) => b[1] - a[1]));
+}
+refresh();
+setInterval(refresh, 5000);
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::{config::Config, event::InMemoryEventBus};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_routes_name_their_feature() {
+        assert_eq!(route_feature("/api/transform"), Some("transform"));
+        assert_eq!(route_feature("/api/logs/level"), Some("logs"));
+        assert_eq!(route_feature("/share"), Some("share"));
+        assert_eq!(route_feature("/api/stats"), None);
+        assert_eq!(route_feature("/"), None);
+        assert_eq!(route_feature("/docs"), None);
+    }
+
+    #[tokio::test]
+    async fn test_report_shows_recorded_usage_only_when_enabled() {
+        let access = ApiAccess::new(Arc::new(Config::new()), None);
+        let disabled = StatsHandler::new("/api/stats".to_string(), None, access.clone());
+        let response = disabled
+            .handle(HttpRequest::get("/api/stats"))
+            .await
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(report["enabled"], false);
+        assert!(report["stats"].is_null());
+
+        let dir = TempDir::new().unwrap();
+        let recorder = Arc::new(UsageRecorder::open(dir.path().join("stats.json")));
+        let registry = Arc::new(HandlerRegistry::new(Arc::new(InMemoryEventBus::new())));
+        registry.set_usage(recorder.clone()).await;
+        registry
+            .record_usage("/api/transform", false, StatusCode::OK)
+            .await;
+        registry.record_usage("/", true, StatusCode::OK).await;
+        registry
+            .record_usage("/api/search", false, StatusCode::NOT_FOUND)
+            .await;
+
+        let enabled = StatsHandler::new("/api/stats".to_string(), Some(recorder), access);
+        let response = enabled
+            .handle(HttpRequest::get("/api/stats"))
+            .await
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(report["enabled"], true);
+        assert_eq!(report["stats"]["sessions"], 1);
+        assert_eq!(report["stats"]["documents_opened"], 1);
+        assert_eq!(report["stats"]["features"]["transform"], 1);
+        assert!(report["stats"]["features"].get("search").is_none());
+    }
+}
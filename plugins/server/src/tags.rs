@@ -0,0 +1,376 @@
+//! Tags across the served documents
+//!
+//! A [`TagIndex`] holds the [tags](rune_core::tags) and title of every
+//! served document. It is built when the server starts and kept current
+//! from file change events. `GET /api/tags` returns the tags with their
+//! documents, those starting with the `prefix` query parameter when given,
+//! which the editor completes `#` tokens from. `GET /tags` lists the tags
+//! on a page and `GET /tags/<slug>` the documents of one.
+
+use crate::roots::{document_link, holds, markdown_documents};
+use crate::{handlers, HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::tags::{self, TagPages, TaggedDocument, TAG_DIR};
+use rune_core::{
+    error::Result,
+    event::{SystemEvent, SystemEventHandler},
+    plugin::PluginContext,
+    state::ServedRoot,
+    RuneError,
+};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+/// Tags and title of a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DocumentTags {
+    title: String,
+    tags: Vec<String>,
+}
+
+impl DocumentTags {
+    fn of(path: &Path, markdown: &str) -> Self {
+        Self {
+            title: tags::document_title(path, markdown),
+            tags: tags::parse(markdown),
+        }
+    }
+}
+
+/// Tags of the documents of the served roots
+pub struct TagIndex {
+    roots: Vec<ServedRoot>,
+    documents: RwLock<BTreeMap<PathBuf, DocumentTags>>,
+}
+
+impl TagIndex {
+    /// Create an empty index of the documents of `roots`
+    pub fn new(roots: &[ServedRoot]) -> Self {
+        let roots = roots
+            .iter()
+            .map(|root| ServedRoot {
+                prefix: root.prefix.clone(),
+                path: root
+                    .path
+                    .canonicalize()
+                    .unwrap_or_else(|_| root.path.clone()),
+            })
+            .collect();
+        Self {
+            roots,
+            documents: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Read every document of the roots again
+    pub async fn rebuild(&self) -> Result<()> {
+        let roots = self.roots.clone();
+        // Reading every document is blocking work
+        let documents = tokio::task::spawn_blocking(move || {
+            roots
+                .iter()
+                .flat_map(|root| {
+                    if root.is_dir() {
+                        markdown_documents(&root.path)
+                    } else {
+                        vec![root.path.clone()]
+                    }
+                })
+                .filter_map(|document| {
+                    let content = std::fs::read_to_string(&document).ok()?;
+                    let tags = DocumentTags::of(&document, &content);
+                    Some((document, tags))
+                })
+                .collect::<BTreeMap<_, _>>()
+        })
+        .await
+        .map_err(|e| RuneError::Server(format!("Tag scan failed: {}", e)))?;
+
+        info!("Indexed tags of {} document(s)", documents.len());
+        *self.documents.write().await = documents;
+        Ok(())
+    }
+
+    /// Read a changed document again, or forget it when it is gone
+    pub async fn update(&self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if !self.roots.iter().any(|root| holds(root, &path)) {
+            return;
+        }
+
+        let content = tokio::fs::read_to_string(&path).await;
+        let mut documents = self.documents.write().await;
+        match content {
+            Ok(content) => {
+                debug!("Rescanned tags of {}", path.display());
+                let tags = DocumentTags::of(&path, &content);
+                documents.insert(path, tags);
+            }
+            Err(_) => {
+                documents.remove(&path);
+            }
+        }
+    }
+
+    /// Documents of every tag, linked to their previews
+    pub async fn pages(&self) -> TagPages {
+        let documents = self.documents.read().await;
+        let mut pages = TagPages::default();
+        for (path, document) in documents.iter() {
+            if let Some((_, url)) = document_link(&self.roots, path) {
+                pages.add(
+                    &document.tags,
+                    TaggedDocument {
+                        title: document.title.clone(),
+                        url,
+                    },
+                );
+            }
+        }
+        pages
+    }
+}
+
+/// URL of the page of `tag`
+fn page_url(tag: &str) -> String {
+    format!("/{}/{}", TAG_DIR, tags::slug(tag))
+}
+
+/// Handler for `GET /api/tags`
+pub struct TagListHandler {
+    path_pattern: String,
+    index: Arc<TagIndex>,
+}
+
+impl TagListHandler {
+    pub fn new(path_pattern: String, index: Arc<TagIndex>) -> Self {
+        Self {
+            path_pattern,
+            index,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for TagListHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let prefix = request
+            .query_params
+            .get("prefix")
+            .map(|prefix| prefix.trim_start_matches('#').to_lowercase())
+            .unwrap_or_default();
+        let pages = self.index.pages().await;
+        let tags: Vec<serde_json::Value> = pages
+            .iter()
+            .filter(|(tag, _)| tag.starts_with(&prefix))
+            .map(|(tag, documents)| {
+                serde_json::json!({
+                    "tag": tag,
+                    "count": documents.len(),
+                    "url": page_url(tag),
+                    "documents": documents,
+                })
+            })
+            .collect();
+
+        Ok(HttpResponse::json(&serde_json::json!({ "tags": tags }))?
+            .with_header("cache-control", "no-store"))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Handler for the `/tags` pages
+pub struct TagPageHandler {
+    path_pattern: String,
+    index: Arc<TagIndex>,
+}
+
+impl TagPageHandler {
+    pub fn new(path_pattern: String, index: Arc<TagIndex>) -> Self {
+        Self {
+            path_pattern,
+            index,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for TagPageHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let pages = self.index.pages().await;
+        let slug = request
+            .path
+            .strip_prefix(&self.path_pattern)
+            .unwrap_or_default()
+            .trim_matches('/');
+        let (title, body) = if slug.is_empty() {
+            ("Tags".to_string(), pages.index_html(page_url))
+        } else {
+            let Some((tag, documents)) = pages.page(slug) else {
+                return Ok(HttpResponse::error(
+                    StatusCode::NOT_FOUND,
+                    &format!("No documents are tagged {}", slug),
+                ));
+            };
+            (
+                format!("#{}", tag),
+                TagPages::page_html(tag, documents, &self.path_pattern),
+            )
+        };
+        Ok(HttpResponse::html(handlers::standalone_page(&title, &body))
+            .with_header("cache-control", "no-store"))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // Before the document handlers
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Reads the tags of changed documents
+struct TagEventHandler {
+    index: Arc<TagIndex>,
+}
+
+#[async_trait]
+impl SystemEventHandler for TagEventHandler {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        if let SystemEvent::FileChanged {
+            path, change_type, ..
+        } = event
+        {
+            let markdown = path.extension().is_some_and(|extension| {
+                extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown")
+            });
+            if markdown && !change_type.is_template_change() {
+                self.index.update(path).await;
+            }
+        }
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "tag-index-event-handler"
+    }
+}
+
+/// Register the tag API and pages for the documents of `roots`
+pub async fn register_tag_handlers(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+    roots: &[ServedRoot],
+) -> Result<()> {
+    let index = Arc::new(TagIndex::new(roots));
+    let initial = index.clone();
+    tokio::spawn(async move {
+        if let Err(e) = initial.rebuild().await {
+            tracing::warn!("Failed to index tags: {}", e);
+        }
+    });
+
+    registry
+        .register_http_handler(Arc::new(TagListHandler::new(
+            "/api/tags".to_string(),
+            index.clone(),
+        )))
+        .await?;
+    registry
+        .register_http_handler(Arc::new(TagPageHandler::new(
+            format!("/{}", TAG_DIR),
+            index.clone(),
+        )))
+        .await?;
+    context
+        .event_bus
+        .subscribe_system_events(Arc::new(TagEventHandler { index }))
+        .await?;
+
+    info!("Registered tag API and page handlers");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_lists_tags_and_their_pages() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("plan.md"),
+            "---\ntags: [rust]\n---\n# Plan\n\nFor #project/rune.\n",
+        )
+        .unwrap();
+        std::fs::write(temp_dir.path().join("notes.md"), "Some #rusty notes\n").unwrap();
+        let index = Arc::new(TagIndex::new(&[ServedRoot {
+            prefix: "docs".to_string(),
+            path: temp_dir.path().to_path_buf(),
+        }]));
+        index.rebuild().await.unwrap();
+
+        let api = TagListHandler::new("/api/tags".to_string(), index.clone());
+        let response = api
+            .handle(HttpRequest::get("/api/tags").with_query(&[("prefix", "#Rust")]))
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        let tags: Vec<&str> = body["tags"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tag| tag["tag"].as_str().unwrap())
+            .collect();
+        assert_eq!(tags, ["rust", "rusty"]);
+        assert_eq!(body["tags"][0]["documents"][0]["url"], "/docs/plan.md");
+        assert_eq!(body["tags"][0]["documents"][0]["title"], "Plan");
+
+        let pages = TagPageHandler::new("/tags".to_string(), index.clone());
+        let page = pages
+            .handle(HttpRequest::get("/tags/project-rune"))
+            .await
+            .unwrap();
+        let html = String::from_utf8(page.body.to_vec()).unwrap();
+        assert!(html.contains("<a href=\"/docs/plan.md\">Plan</a>"));
+        let index_page = pages.handle(HttpRequest::get("/tags")).await.unwrap();
+        let html = String::from_utf8(index_page.body.to_vec()).unwrap();
+        assert!(html.contains("href=\"/tags/project-rune\">#project/rune</a>"));
+        let missing = pages.handle(HttpRequest::get("/tags/none")).await.unwrap();
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+
+        // Edits are picked up
+        std::fs::write(temp_dir.path().join("notes.md"), "No tags left\n").unwrap();
+        index.update(&temp_dir.path().join("notes.md")).await;
+        assert_eq!(index.pages().await.len(), 2);
+    }
+}
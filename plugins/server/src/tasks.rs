@@ -0,0 +1,506 @@
+//! Tasks and TODO markers across the served documents
+//!
+//! A [`TaskIndex`] holds the task list items (`- [ ] ...`) and the `TODO:`
+//! and `FIXME:` markers of every served document, with their line and
+//! column. It is built when the server starts and kept current from file
+//! change events. `GET /api/tasks` returns the items grouped by document
+//! and `GET /tasks` lists them on a page; each item links to its
+//! document's editor at its line, with an `#edit=L12` fragment the page
+//! template opens the raw editor for.
+
+use crate::roots::{document_link, holds, markdown_documents};
+use crate::{handlers, HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::Method;
+use markdown::mdast::Node;
+use rune_core::{
+    error::Result,
+    event::{SystemEvent, SystemEventHandler},
+    frontmatter,
+    plugin::PluginContext,
+    state::ServedRoot,
+    RuneError,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+/// Markers of work left in a document
+const MARKERS: &[(&str, TaskKind)] = &[("TODO:", TaskKind::Todo), ("FIXME:", TaskKind::Fixme)];
+
+/// What an item of the index is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskKind {
+    /// A task list item
+    Task,
+    /// A `TODO:` marker
+    Todo,
+    /// A `FIXME:` marker
+    Fixme,
+}
+
+/// A task or marker of a document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaskItem {
+    pub kind: TaskKind,
+    /// Whether a task list item is checked; none for markers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub done: Option<bool>,
+    pub text: String,
+    /// Line of the item in the document, from 1
+    pub line: usize,
+    /// Column of the item in its line, from 1
+    pub column: usize,
+}
+
+/// The items of one document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DocumentTasks {
+    /// Document path, relative to its root
+    pub document: String,
+    /// URL of the document's preview
+    pub url: String,
+    pub tasks: Vec<TaskItem>,
+}
+
+/// Task list items and markers of a document, in document order
+pub fn scan(content: &str) -> Vec<TaskItem> {
+    let mut items = Vec::new();
+
+    let mut options = markdown::ParseOptions::gfm();
+    options.constructs.frontmatter = frontmatter::is_closed(content);
+    if let Ok(tree) = markdown::to_mdast(content, &options) {
+        collect_tasks(&tree, &mut items);
+    }
+
+    // Markers count anywhere, code blocks and HTML comments included
+    for (index, line) in content.lines().enumerate() {
+        let found = MARKERS
+            .iter()
+            .filter_map(|(marker, kind)| {
+                line.match_indices(marker)
+                    .find(|(start, _)| {
+                        !line[..*start]
+                            .chars()
+                            .next_back()
+                            .is_some_and(char::is_alphanumeric)
+                    })
+                    .map(|(start, _)| (start, marker.len(), *kind))
+            })
+            .min_by_key(|(start, _, _)| *start);
+        if let Some((start, length, kind)) = found {
+            let text = line[start + length..].trim();
+            let text = text.strip_suffix("-->").unwrap_or(text).trim_end();
+            items.push(TaskItem {
+                kind,
+                done: None,
+                text: text.to_string(),
+                line: index + 1,
+                column: line[..start].chars().count() + 1,
+            });
+        }
+    }
+
+    items.sort_by_key(|item| (item.line, item.column));
+    items
+}
+
+fn collect_tasks(node: &Node, items: &mut Vec<TaskItem>) {
+    if let Node::ListItem(item) = node {
+        if let (Some(done), Some(position)) = (item.checked, &item.position) {
+            // The item's own text, not that of its nested lists
+            let mut text = String::new();
+            for child in item
+                .children
+                .iter()
+                .take_while(|child| !matches!(child, Node::List(_)))
+            {
+                plain_text(child, &mut text);
+            }
+            items.push(TaskItem {
+                kind: TaskKind::Task,
+                done: Some(done),
+                text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+                line: position.start.line,
+                column: position.start.column,
+            });
+        }
+    }
+    for child in node.children().into_iter().flatten() {
+        collect_tasks(child, items);
+    }
+}
+
+fn plain_text(node: &Node, text: &mut String) {
+    match node {
+        Node::Text(t) => text.push_str(&t.value),
+        Node::InlineCode(code) => text.push_str(&code.value),
+        Node::Break(_) => text.push(' '),
+        _ => {
+            for child in node.children().into_iter().flatten() {
+                plain_text(child, text);
+            }
+        }
+    }
+}
+
+/// Items of the documents of the served roots
+pub struct TaskIndex {
+    roots: Vec<ServedRoot>,
+    documents: RwLock<BTreeMap<PathBuf, Vec<TaskItem>>>,
+}
+
+impl TaskIndex {
+    /// Create an empty index of the documents of `roots`
+    pub fn new(roots: &[ServedRoot]) -> Self {
+        let roots = roots
+            .iter()
+            .map(|root| ServedRoot {
+                prefix: root.prefix.clone(),
+                path: root
+                    .path
+                    .canonicalize()
+                    .unwrap_or_else(|_| root.path.clone()),
+            })
+            .collect();
+        Self {
+            roots,
+            documents: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Scan every document of the roots again
+    pub async fn rebuild(&self) -> Result<()> {
+        let roots = self.roots.clone();
+        // Reading every document is blocking work
+        let documents = tokio::task::spawn_blocking(move || {
+            roots
+                .iter()
+                .flat_map(|root| {
+                    if root.is_dir() {
+                        markdown_documents(&root.path)
+                    } else {
+                        vec![root.path.clone()]
+                    }
+                })
+                .filter_map(|document| {
+                    let content = std::fs::read_to_string(&document).ok()?;
+                    Some((document, scan(&content)))
+                })
+                .collect::<BTreeMap<_, _>>()
+        })
+        .await
+        .map_err(|e| RuneError::Server(format!("Task scan failed: {}", e)))?;
+
+        info!("Indexed tasks of {} document(s)", documents.len());
+        *self.documents.write().await = documents;
+        Ok(())
+    }
+
+    /// Scan a changed document again, or forget it when it is gone
+    pub async fn update(&self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if !self.roots.iter().any(|root| holds(root, &path)) {
+            return;
+        }
+
+        let content = tokio::fs::read_to_string(&path).await;
+        let mut documents = self.documents.write().await;
+        match content {
+            Ok(content) => {
+                debug!("Rescanned tasks of {}", path.display());
+                documents.insert(path, scan(&content));
+            }
+            Err(_) => {
+                documents.remove(&path);
+            }
+        }
+    }
+
+    /// Items grouped by document, leaving out documents without any
+    pub async fn grouped(&self) -> Vec<DocumentTasks> {
+        let documents = self.documents.read().await;
+        documents
+            .iter()
+            .filter(|(_, tasks)| !tasks.is_empty())
+            .filter_map(|(path, tasks)| {
+                let (document, url) = document_link(&self.roots, path)?;
+                Some(DocumentTasks {
+                    document,
+                    url,
+                    tasks: tasks.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Handler for `GET /api/tasks`
+pub struct TaskListHandler {
+    path_pattern: String,
+    index: Arc<TaskIndex>,
+}
+
+impl TaskListHandler {
+    pub fn new(path_pattern: String, index: Arc<TaskIndex>) -> Self {
+        Self {
+            path_pattern,
+            index,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for TaskListHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        let documents = self.index.grouped().await;
+        let open = documents
+            .iter()
+            .flat_map(|document| &document.tasks)
+            .filter(|task| task.done != Some(true))
+            .count();
+
+        Ok(HttpResponse::json(&serde_json::json!({
+            "open": open,
+            "documents": documents,
+        }))?
+        .with_header("cache-control", "no-store"))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Handler for the `/tasks` page
+pub struct TaskPageHandler {
+    path_pattern: String,
+    index: Arc<TaskIndex>,
+}
+
+impl TaskPageHandler {
+    pub fn new(path_pattern: String, index: Arc<TaskIndex>) -> Self {
+        Self {
+            path_pattern,
+            index,
+        }
+    }
+}
+
+/// Body of the tasks page
+fn tasks_page(documents: &[DocumentTasks]) -> String {
+    let mut body = String::from("<h1>Tasks</h1>\n");
+    if documents.is_empty() {
+        body.push_str("<p>No tasks or TODO markers in the served documents.</p>\n");
+        return body;
+    }
+    for document in documents {
+        let url = html_escape::encode_double_quoted_attribute(&document.url);
+        body.push_str(&format!(
+            "<h2><a href=\"{}\">{}</a></h2>\n<ul class=\"rune-tasks\">\n",
+            url,
+            html_escape::encode_text(&document.document)
+        ));
+        for task in &document.tasks {
+            let label = match (task.kind, task.done) {
+                (TaskKind::Task, Some(true)) => "<input type=\"checkbox\" checked disabled>",
+                (TaskKind::Task, _) => "<input type=\"checkbox\" disabled>",
+                (TaskKind::Todo, _) => "<span class=\"rune-task-marker\">TODO</span>",
+                (TaskKind::Fixme, _) => "<span class=\"rune-task-marker\">FIXME</span>",
+            };
+            body.push_str(&format!(
+                "<li>{} <a href=\"{}#edit=L{}\" title=\"Edit at line {}\">{}</a></li>\n",
+                label,
+                url,
+                task.line,
+                task.line,
+                html_escape::encode_text(&task.text)
+            ));
+        }
+        body.push_str("</ul>\n");
+    }
+    body
+}
+
+#[async_trait]
+impl HttpHandler for TaskPageHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        let body = tasks_page(&self.index.grouped().await);
+        Ok(
+            HttpResponse::html(handlers::standalone_page("Tasks", &body))
+                .with_header("cache-control", "no-store"),
+        )
+    }
+
+    fn priority(&self) -> i32 {
+        5 // Before the document handlers
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Rescans changed documents
+struct TaskEventHandler {
+    index: Arc<TaskIndex>,
+}
+
+#[async_trait]
+impl SystemEventHandler for TaskEventHandler {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        if let SystemEvent::FileChanged {
+            path, change_type, ..
+        } = event
+        {
+            let markdown = path.extension().is_some_and(|extension| {
+                extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown")
+            });
+            if markdown && !change_type.is_template_change() {
+                self.index.update(path).await;
+            }
+        }
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "task-index-event-handler"
+    }
+}
+
+/// Register the task API and page for the documents of `roots`
+pub async fn register_task_handlers(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+    roots: &[ServedRoot],
+) -> Result<()> {
+    let index = Arc::new(TaskIndex::new(roots));
+    let initial = index.clone();
+    tokio::spawn(async move {
+        if let Err(e) = initial.rebuild().await {
+            tracing::warn!("Failed to index tasks: {}", e);
+        }
+    });
+
+    registry
+        .register_http_handler(Arc::new(TaskListHandler::new(
+            "/api/tasks".to_string(),
+            index.clone(),
+        )))
+        .await?;
+    registry
+        .register_http_handler(Arc::new(TaskPageHandler::new(
+            "/tasks".to_string(),
+            index.clone(),
+        )))
+        .await?;
+    context
+        .event_bus
+        .subscribe_system_events(Arc::new(TaskEventHandler { index }))
+        .await?;
+
+    info!("Registered task API and page handlers");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scans_tasks_and_markers() {
+        let items = scan(concat!(
+            "---\ntitle: TODO: not a task list\n---\n",
+            "# Plan\n\n",
+            "- [ ] Write **docs**\n",
+            "  - [x] Outline\n",
+            "- plain item\n\n",
+            "Some AUTOTODO: text. FIXME: the intro <!-- TODO: later -->\n",
+        ));
+        let summary: Vec<_> = items
+            .iter()
+            .map(|item| {
+                (
+                    item.kind,
+                    item.done,
+                    item.text.as_str(),
+                    item.line,
+                    item.column,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (TaskKind::Todo, None, "not a task list", 2, 8),
+                (TaskKind::Task, Some(false), "Write docs", 6, 1),
+                (TaskKind::Task, Some(true), "Outline", 7, 3),
+                (TaskKind::Fixme, None, "the intro <!-- TODO: later", 10, 22),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_index_groups_tasks_by_document() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().canonicalize().unwrap();
+        std::fs::create_dir(dir.join("guide")).unwrap();
+        std::fs::write(dir.join("guide/my setup.md"), "- [ ] Install\n").unwrap();
+        std::fs::write(dir.join("notes.md"), "# Notes\n").unwrap();
+
+        let index = Arc::new(TaskIndex::new(&[ServedRoot {
+            prefix: "docs".to_string(),
+            path: dir.clone(),
+        }]));
+        index.rebuild().await.unwrap();
+        let grouped = index.grouped().await;
+        assert_eq!(grouped.len(), 1);
+        assert_eq!(grouped[0].document, "guide/my setup.md");
+        assert_eq!(grouped[0].url, "/docs/guide/my%20setup.md");
+
+        std::fs::write(dir.join("notes.md"), "TODO: write notes\n").unwrap();
+        index.update(&dir.join("notes.md")).await;
+        std::fs::remove_file(dir.join("guide/my setup.md")).unwrap();
+        index.update(&dir.join("guide/my setup.md")).await;
+
+        let handler = TaskListHandler::new("/api/tasks".to_string(), index.clone());
+        let response = handler
+            .handle(HttpRequest::get("/api/tasks"))
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(body["open"], 1);
+        assert_eq!(body["documents"][0]["document"], "notes.md");
+        assert_eq!(body["documents"][0]["tasks"][0]["kind"], "todo");
+
+        let page = tasks_page(&index.grouped().await);
+        assert!(page.contains(
+            "<a href=\"/docs/notes.md#edit=L1\" title=\"Edit at line 1\">write notes</a>"
+        ));
+    }
+}
@@ -0,0 +1,467 @@
+//! Page template and theme stylesheet, reloaded while the server runs
+//!
+//! Pages are built from the built-in template unless the `template_path`
+//! setting names another one, and the `theme_css` setting adds a stylesheet,
+//! served at [`STYLESHEET_PATH`], after the built-in styles. The `theme_js`
+//! setting adds a theme script, served at [`SCRIPT_PATH`] and run against
+//! the theme API of [`rune_theme::script`], where theme scripts are allowed.
+//! The file watcher reports edits to the template or the script as a
+//! [`ChangeType::TemplateModified`] change and to the stylesheet as a
+//! [`ChangeType::StyleModified`] change: an edited template or script has
+//! open pages reload, while an edited stylesheet only has them fetch their
+//! styles again.
+//!
+//! Pages link the stylesheet and script by their content hash, see
+//! [`rune_theme::assets`]: browsers keep them for good and fetch them again
+//! as soon as an edit changes the URL.
+
+use crate::csrf;
+use crate::handlers::{ServerMessage, PAGE_TEMPLATE};
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::body::Bytes;
+use axum::http::{Method, StatusCode};
+use rune_core::{
+    error::Result,
+    event::{ChangeType, SystemEvent, SystemEventHandler},
+    i18n,
+    plugin::PluginContext,
+};
+use rune_theme::{assets, script};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+/// URL path the theme stylesheet is served at
+pub const STYLESHEET_PATH: &str = "/rune-theme.css";
+
+/// URL path the theme script is served at
+pub const SCRIPT_PATH: &str = "/rune-theme.js";
+
+/// Template and stylesheet in use
+struct Templates {
+    template_path: Option<PathBuf>,
+    stylesheet_path: Option<PathBuf>,
+    script_path: Option<PathBuf>,
+    /// Localized page template, loaded on first use
+    page: Option<Arc<str>>,
+    generation: u64,
+}
+
+static TEMPLATES: RwLock<Templates> = RwLock::new(Templates {
+    template_path: None,
+    stylesheet_path: None,
+    script_path: None,
+    page: None,
+    generation: 0,
+});
+
+/// Messages for every open page, e.g. to reload after a template change
+fn changes() -> &'static broadcast::Sender<ServerMessage> {
+    static CHANGES: OnceLock<broadcast::Sender<ServerMessage>> = OnceLock::new();
+    CHANGES.get_or_init(|| broadcast::channel(16).0)
+}
+
+/// Build pages from the template at `template_path` and add the stylesheet
+/// at `stylesheet_path` and the script at `script_path`; `None` keeps the
+/// built-in template and no stylesheet or script
+pub fn configure(
+    template_path: Option<PathBuf>,
+    stylesheet_path: Option<PathBuf>,
+    script_path: Option<PathBuf>,
+) {
+    let mut templates = TEMPLATES.write().unwrap_or_else(|e| e.into_inner());
+    templates.template_path = template_path;
+    templates.stylesheet_path = stylesheet_path;
+    templates.script_path = script_path;
+    templates.page = None;
+    templates.generation += 1;
+}
+
+/// Load the page template again on its next use
+pub fn reload() {
+    let mut templates = TEMPLATES.write().unwrap_or_else(|e| e.into_inner());
+    templates.page = None;
+    templates.generation += 1;
+}
+
+/// Template pages are built from, in the language of the current catalog
+pub fn page() -> Arc<str> {
+    if let Some(page) = &TEMPLATES.read().unwrap_or_else(|e| e.into_inner()).page {
+        return page.clone();
+    }
+    let mut templates = TEMPLATES.write().unwrap_or_else(|e| e.into_inner());
+    let page = load(
+        templates.template_path.as_deref(),
+        templates.stylesheet_path.as_deref(),
+        templates.script_path.as_deref(),
+    );
+    templates.page.get_or_insert(page).clone()
+}
+
+/// Number changing whenever the template does, so pages built with an
+/// older template can be told apart
+pub fn generation() -> u64 {
+    TEMPLATES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .generation
+}
+
+/// Receive the messages sent to every open page
+pub fn subscribe() -> broadcast::Receiver<ServerMessage> {
+    changes().subscribe()
+}
+
+/// Read the template at `template_path`, falling back to the built-in one,
+/// and link the theme stylesheet at `stylesheet_path` and script at
+/// `script_path` when there are ones
+fn load(
+    template_path: Option<&Path>,
+    stylesheet_path: Option<&Path>,
+    script_path: Option<&Path>,
+) -> Arc<str> {
+    let template = match template_path.map(|path| (path, std::fs::read_to_string(path))) {
+        Some((_, Ok(template))) if template.contains("{CONTENT}") => template,
+        Some((path, Ok(_))) => {
+            warn!(
+                "Template {} has no {{CONTENT}} placeholder; using the built-in template",
+                path.display()
+            );
+            PAGE_TEMPLATE.to_string()
+        }
+        Some((path, Err(e))) => {
+            warn!(
+                "Failed to read template {}: {}; using the built-in template",
+                path.display(),
+                e
+            );
+            PAGE_TEMPLATE.to_string()
+        }
+        None => PAGE_TEMPLATE.to_string(),
+    };
+
+    let page = i18n::localize_template(&template, &i18n::catalog());
+    let mut page = csrf::with_fetch_script_nonce(&page);
+    if let Some(path) = script_path {
+        let src = match std::fs::read_to_string(path) {
+            Ok(javascript) => {
+                assets::versioned_url(SCRIPT_PATH, script_body(path, &javascript).as_bytes())
+            }
+            Err(_) => SCRIPT_PATH.to_string(),
+        };
+        page = page.replacen(
+            "</head>",
+            &format!(
+                "<script defer id=\"rune-theme-js\" src=\"{}\"></script>\n</head>",
+                src
+            ),
+            1,
+        );
+    }
+    if let Some(path) = stylesheet_path {
+        page = page.replacen(
+            "</head>",
+            &format!(
+                "<link rel=\"stylesheet\" id=\"rune-theme-css\" href=\"{}\">\n</head>",
+                stylesheet_url(path)
+            ),
+            1,
+        );
+    }
+    page.into()
+}
+
+/// URL of the stylesheet at `path`, with its content hash when it can be read
+fn stylesheet_url(path: &Path) -> String {
+    match std::fs::read(path) {
+        Ok(css) => assets::versioned_url(STYLESHEET_PATH, &css),
+        Err(_) => STYLESHEET_PATH.to_string(),
+    }
+}
+
+/// The theme script at `path` as served: the theme API, then the script
+fn script_body(path: &Path, javascript: &str) -> String {
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    script::runtime() + &script::wrap(&name, javascript)
+}
+
+/// `body` as the answer to `request`: kept for good by the browser when the
+/// request names its hash, revalidated by entity tag otherwise
+pub(crate) fn versioned_response(
+    request: &HttpRequest,
+    content_type: &str,
+    body: impl Into<Bytes>,
+) -> HttpResponse {
+    let body = body.into();
+    let hash = assets::content_hash(&body);
+    let etag = format!("\"{}\"", hash);
+    let cache_control = assets::cache_control(
+        request
+            .query_params
+            .get(assets::VERSION_PARAM)
+            .map(String::as_str),
+        &hash,
+    );
+    let cached = request
+        .headers
+        .get("if-none-match")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == etag));
+    let response = HttpResponse::new(if cached {
+        StatusCode::NOT_MODIFIED
+    } else {
+        StatusCode::OK
+    })
+    .with_header("content-type", content_type)
+    .with_header("cache-control", cache_control)
+    .with_header("etag", &etag);
+    if cached {
+        response
+    } else {
+        response.with_body(body)
+    }
+}
+
+/// Handler serving the theme stylesheet, read anew for every request
+pub struct StylesheetHandler {
+    path: PathBuf,
+}
+
+impl StylesheetHandler {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for StylesheetHandler {
+    fn path_pattern(&self) -> &str {
+        STYLESHEET_PATH
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        Ok(match tokio::fs::read(&self.path).await {
+            Ok(css) => versioned_response(&request, "text/css; charset=utf-8", css),
+            Err(e) => {
+                warn!("Failed to read stylesheet {}: {}", self.path.display(), e);
+                HttpResponse::error(StatusCode::NOT_FOUND, "Stylesheet not found")
+            }
+        })
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for specific asset
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Handler serving the theme script with the theme API, read anew for
+/// every request
+pub struct ThemeScriptHandler {
+    path: PathBuf,
+}
+
+impl ThemeScriptHandler {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for ThemeScriptHandler {
+    fn path_pattern(&self) -> &str {
+        SCRIPT_PATH
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        Ok(match tokio::fs::read_to_string(&self.path).await {
+            Ok(javascript) => versioned_response(
+                &request,
+                "text/javascript; charset=utf-8",
+                script_body(&self.path, &javascript),
+            ),
+            Err(e) => {
+                warn!("Failed to read theme script {}: {}", self.path.display(), e);
+                HttpResponse::error(StatusCode::NOT_FOUND, "Theme script not found")
+            }
+        })
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for specific asset
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Reloads the template, and tells open pages, when the watcher reports an
+/// edit to it, to the stylesheet or to the script
+struct TemplateEventHandler;
+
+#[async_trait]
+impl SystemEventHandler for TemplateEventHandler {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        let SystemEvent::FileChanged {
+            path, change_type, ..
+        } = event
+        else {
+            return Ok(());
+        };
+        let message = match change_type {
+            ChangeType::TemplateModified => {
+                info!("Page template {} changed, reloading pages", path.display());
+                reload();
+                ServerMessage::TemplateReload
+            }
+            ChangeType::StyleModified => {
+                info!("Stylesheet {} changed, reloading styles", path.display());
+                // Pages served from now on link the new hash
+                reload();
+                ServerMessage::StyleReload {
+                    href: stylesheet_url(path),
+                }
+            }
+            _ => return Ok(()),
+        };
+        // Nobody listening just means no page is open
+        let _ = changes().send(message);
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "template-event-handler"
+    }
+}
+
+/// Use the configured template, stylesheet and script, serve the
+/// stylesheet and script and reload them when they change
+pub async fn register_template_handlers(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+) -> Result<()> {
+    let template_path = context.config.get_template_path();
+    let stylesheet_path = context.config.get_theme_css_path();
+    let mut script_path = context.config.get_theme_js_path();
+    if let Some(path) = &script_path {
+        if !script::allowed(&context.config) {
+            warn!(
+                "Not adding theme script {}: theme scripts are not allowed",
+                path.display()
+            );
+            script_path = None;
+        }
+    }
+    configure(
+        template_path.clone(),
+        stylesheet_path.clone(),
+        script_path.clone(),
+    );
+
+    if let Some(path) = stylesheet_path {
+        info!("Adding stylesheet {} to pages", path.display());
+        registry
+            .register_http_handler(Arc::new(StylesheetHandler::new(path)))
+            .await?;
+    }
+    if let Some(path) = script_path {
+        info!("Adding theme script {} to pages", path.display());
+        registry
+            .register_http_handler(Arc::new(ThemeScriptHandler::new(path)))
+            .await?;
+    }
+    if let Some(path) = template_path {
+        info!("Building pages from template {}", path.display());
+    }
+
+    context
+        .event_bus
+        .subscribe_system_events(Arc::new(TemplateEventHandler))
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_template_and_stylesheet_link() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let template = temp_dir.path().join("page.html");
+        std::fs::write(
+            &template,
+            "<html><head></head><body>{CONTENT}</body></html>",
+        )
+        .unwrap();
+
+        let stylesheet = temp_dir.path().join("theme.css");
+        std::fs::write(&stylesheet, "body { color: teal; }").unwrap();
+        let script = temp_dir.path().join("theme.js");
+        std::fs::write(&script, "rune.setVariable('--accent', 'teal');").unwrap();
+
+        let page = load(Some(&template), Some(&stylesheet), Some(&script));
+        assert!(page.contains("<body>{CONTENT}</body>"));
+        let href = stylesheet_url(&stylesheet);
+        assert!(href.starts_with("/rune-theme.css?v="));
+        assert!(page.contains(&format!(
+            "<link rel=\"stylesheet\" id=\"rune-theme-css\" href=\"{}\">\n</head>",
+            href
+        )));
+        assert!(page.contains("<script defer id=\"rune-theme-js\" src=\"/rune-theme.js?v="));
+
+        // Templates without a place for the content are not used
+        std::fs::write(&template, "<html><body>Oops</body></html>").unwrap();
+        let page = load(Some(&template), None, None);
+        assert!(page.contains("{CONTENT}"));
+        assert!(!page.contains("Oops"));
+        assert!(!page.contains("rune-theme-css"));
+        assert!(!page.contains("rune-theme-js"));
+    }
+
+    #[tokio::test]
+    async fn test_theme_assets_are_cached_by_content_hash() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let stylesheet = temp_dir.path().join("theme.css");
+        std::fs::write(&stylesheet, "body { color: teal; }").unwrap();
+        let handler = StylesheetHandler::new(stylesheet.clone());
+
+        let href = stylesheet_url(&stylesheet);
+        let version = href.split("?v=").nth(1).unwrap().to_string();
+        let request = HttpRequest::get(STYLESHEET_PATH).with_query(&[("v", &version)]);
+        let response = handler.handle(request.clone()).await.unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.headers["cache-control"], assets::IMMUTABLE);
+        assert_eq!(response.headers["etag"], format!("\"{}\"", version));
+
+        // An edit changes the URL; the old one is only revalidated
+        std::fs::write(&stylesheet, "body { color: navy; }").unwrap();
+        assert_ne!(stylesheet_url(&stylesheet), href);
+        let response = handler.handle(request).await.unwrap();
+        assert_eq!(response.headers["cache-control"], assets::REVALIDATE);
+        assert_eq!(&response.body[..], b"body { color: navy; }");
+
+        let etag = response.headers["etag"].to_str().unwrap().to_string();
+        let request = HttpRequest::get(STYLESHEET_PATH).with_header("if-none-match", &etag);
+        let response = handler.handle(request).await.unwrap();
+        assert_eq!(response.status, StatusCode::NOT_MODIFIED);
+        assert!(response.body.is_empty());
+    }
+}
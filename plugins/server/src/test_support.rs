@@ -0,0 +1,57 @@
+//! Requests for handler tests
+
+use crate::HttpRequest;
+use axum::body::Bytes;
+use axum::http::{HeaderName, Method};
+
+impl HttpRequest {
+    /// Request for `path` without headers, query or body
+    pub(crate) fn new(method: Method, path: &str) -> Self {
+        Self {
+            method,
+            path: path.to_string(),
+            query_params: Default::default(),
+            headers: Default::default(),
+            body: Default::default(),
+            path_params: Default::default(),
+        }
+    }
+
+    /// `GET` request for `path`
+    pub(crate) fn get(path: &str) -> Self {
+        Self::new(Method::GET, path)
+    }
+
+    /// Request with a method named by `method`, e.g. `PROPFIND`
+    pub(crate) fn named(method: &str, path: &str) -> Self {
+        Self::new(Method::from_bytes(method.as_bytes()).unwrap(), path)
+    }
+
+    pub(crate) fn with_query(mut self, query: &[(&str, &str)]) -> Self {
+        self.query_params.extend(
+            query
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string())),
+        );
+        self
+    }
+
+    pub(crate) fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(
+            HeaderName::from_bytes(name.as_bytes()).unwrap(),
+            value.parse().unwrap(),
+        );
+        self
+    }
+
+    pub(crate) fn with_headers(self, headers: &[(&str, &str)]) -> Self {
+        headers.iter().fold(self, |request, (name, value)| {
+            request.with_header(name, value)
+        })
+    }
+
+    pub(crate) fn with_body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = body.into();
+        self
+    }
+}
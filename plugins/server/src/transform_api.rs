@@ -0,0 +1,285 @@
+//! Document transform API
+//!
+//! `GET /api/transforms` lists the transforms of the shared
+//! [`TransformRegistry`], the built-in ones and those plugins registered.
+//! `POST /api/transform` runs the served document through one of them: it
+//! takes a JSON object with the `transform`, its `options` and a `mode`.
+//! `preview`, the default, answers with the transformed `markdown` and
+//! whether it `changed`; `apply` also saves it, recording a version in the
+//! document's history, and the file watcher reloads the preview.
+
+use crate::handlers::DocumentSlot;
+use crate::{HandlerRegistry, HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use rune_core::history::HistoryStore;
+use rune_core::plugin::PluginContext;
+use rune_core::transform::{TransformOptions, TransformRegistry};
+use rune_core::{crypto, Result, RuneError};
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Whether a transform is only shown or also saved
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TransformMode {
+    #[default]
+    Preview,
+    Apply,
+}
+
+/// Body of a transform request
+#[derive(Debug, Deserialize)]
+struct TransformRequest {
+    transform: String,
+    #[serde(default)]
+    options: TransformOptions,
+    #[serde(default)]
+    mode: TransformMode,
+}
+
+/// Handler listing the registered transforms
+pub struct TransformListHandler {
+    path_pattern: String,
+    transforms: Arc<TransformRegistry>,
+}
+
+impl TransformListHandler {
+    pub fn new(path_pattern: String, transforms: Arc<TransformRegistry>) -> Self {
+        Self {
+            path_pattern,
+            transforms,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for TransformListHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        HttpResponse::json(&self.transforms.list_transforms().await)
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Handler running the served document through a transform
+pub struct TransformHandler {
+    path_pattern: String,
+    transforms: Arc<TransformRegistry>,
+    document: DocumentSlot,
+}
+
+impl TransformHandler {
+    pub fn new(
+        path_pattern: String,
+        transforms: Arc<TransformRegistry>,
+        document: DocumentSlot,
+    ) -> Self {
+        Self {
+            path_pattern,
+            transforms,
+            document,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for TransformHandler {
+    fn path_pattern(&self) -> &str {
+        &self.path_pattern
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let transform_request: TransformRequest = match serde_json::from_slice(&request.body) {
+            Ok(transform_request) => transform_request,
+            Err(e) => {
+                return Ok(HttpResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Invalid transform request: {}", e),
+                ))
+            }
+        };
+        let Some(document) = self.document.read().await.clone() else {
+            return Ok(HttpResponse::error(
+                StatusCode::NOT_FOUND,
+                "No document is being served",
+            ));
+        };
+        let apply = transform_request.mode == TransformMode::Apply;
+        let (markdown, changed) = match run_transform(
+            &self.transforms,
+            document.markdown_file(),
+            &transform_request.transform,
+            &transform_request.options,
+            apply,
+        )
+        .await
+        {
+            Ok(transformed) => transformed,
+            Err(RuneError::Config(message)) => {
+                return Ok(HttpResponse::error(StatusCode::BAD_REQUEST, &message))
+            }
+            Err(e) => return Err(e),
+        };
+        let applied = apply && changed;
+
+        HttpResponse::json(&serde_json::json!({
+            "transform": transform_request.transform,
+            "markdown": markdown,
+            "changed": changed,
+            "applied": applied,
+        }))
+    }
+
+    fn edits(&self) -> bool {
+        true // Applied transforms save the document
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for API endpoints
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Run the document at `file_path` through the transform `name`, saving the
+/// result when `apply`, and return it with whether it changed
+///
+/// Unknown transforms and bad options are [`RuneError::Config`] errors.
+pub(crate) async fn run_transform(
+    transforms: &TransformRegistry,
+    file_path: &Path,
+    name: &str,
+    options: &TransformOptions,
+    apply: bool,
+) -> Result<(String, bool)> {
+    let content = crypto::read_document(file_path)?;
+    let markdown = transforms.transform(name, &content, options).await?;
+    let changed = markdown != content;
+    if !(apply && changed) {
+        return Ok((markdown, changed));
+    }
+
+    crypto::write_document(file_path, &markdown)?;
+    info!("Applied transform '{}' to {:?}", name, file_path);
+
+    // The history store keeps plaintext, so encrypted documents have none
+    if !crypto::is_encrypted(file_path) {
+        let file_path = file_path.to_path_buf();
+        let saved = markdown.clone();
+        let message = format!("Transform: {}", name);
+        match tokio::task::spawn_blocking(move || {
+            HistoryStore::record_save(&file_path, &saved, Some(message))
+        })
+        .await
+        {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => warn!("Failed to record version history: {}", e),
+            Err(e) => warn!("Version history task failed: {}", e),
+        }
+    }
+    Ok((markdown, changed))
+}
+
+/// Register the transform API for the document in `document`
+pub async fn register_transform_api_handlers(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+    document: DocumentSlot,
+) -> Result<()> {
+    let transforms = TransformRegistry::shared(context).await?;
+
+    registry
+        .register_http_handler(Arc::new(TransformListHandler::new(
+            "/api/transforms".to_string(),
+            transforms.clone(),
+        )))
+        .await?;
+    registry
+        .register_http_handler(Arc::new(TransformHandler::new(
+            "/api/transform".to_string(),
+            transforms,
+            document,
+        )))
+        .await?;
+
+    info!("Registered document transform API handlers");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::MarkdownHandler;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_preview_and_apply_transform() {
+        let temp_dir = TempDir::new().unwrap();
+        let doc = temp_dir.path().join("list.md");
+        std::fs::write(&doc, "# List\n\n- b\n- a\n").unwrap();
+        let document: DocumentSlot = Default::default();
+        let handler = TransformHandler::new(
+            "/api/transform".to_string(),
+            Arc::new(TransformRegistry::new()),
+            document.clone(),
+        );
+        let post = |body: &'static str| {
+            handler.handle(HttpRequest::new(Method::POST, "/api/transform").with_body(body))
+        };
+
+        let response = post(r#"{"transform": "sort-list"}"#).await.unwrap();
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+        *document.write().await =
+            Some(Arc::new(MarkdownHandler::new("/".to_string(), doc.clone())));
+        let response = post(r#"{"transform": "sort-list"}"#).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(body["markdown"], "# List\n\n- a\n- b\n");
+        assert_eq!(body["changed"], true);
+        assert_eq!(body["applied"], false);
+        assert_eq!(
+            std::fs::read_to_string(&doc).unwrap(),
+            "# List\n\n- b\n- a\n"
+        );
+
+        let response = post(r#"{"transform": "sort-list", "mode": "apply"}"#)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(body["applied"], true);
+        assert_eq!(
+            std::fs::read_to_string(&doc).unwrap(),
+            "# List\n\n- a\n- b\n"
+        );
+
+        let response = post(r#"{"transform": "wrap-lines", "options": {"width": 0}}"#)
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+        let response = post(r#"{"transform": "nope"}"#).await.unwrap();
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    }
+}
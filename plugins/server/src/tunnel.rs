@@ -0,0 +1,353 @@
+//! Public URLs for the preview through a tunnel
+//!
+//! With the `tunnel` setting (`rune serve --tunnel`) the server asks a
+//! [`TunnelProvider`] for a public URL forwarding to it, so a live preview
+//! can be shared beyond the local network without forwarding ports by hand.
+//! The built-in providers run `ssh` against localhost.run, serveo or pinggy,
+//! or the `ngrok` agent, and read the URL from their output. Once open, the
+//! URL is logged, published as a [`SystemEvent::TunnelOpened`] event and
+//! offered first on the share page. A tunnel that drops is opened again by
+//! the supervisor, possibly with a new URL.
+
+use async_trait::async_trait;
+use rune_core::{
+    error::{Result, RuneError},
+    event::{EventBus, SystemEvent},
+    process,
+};
+use std::net::IpAddr;
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Child;
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+
+/// Name of the supervised task keeping the tunnel open
+pub const TUNNEL_TASK: &str = "tunnel";
+
+/// Provider used by a bare `--tunnel`
+pub const DEFAULT_PROVIDER: &str = "localhost.run";
+
+/// Names of the built-in providers
+pub const PROVIDERS: [&str; 4] = ["localhost.run", "serveo", "pinggy", "ngrok"];
+
+/// Longest wait for a provider to report its public URL
+const OPEN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Public URL of the open tunnel
+static PUBLIC_URL: RwLock<Option<String>> = RwLock::new(None);
+
+/// Public URL of the preview, while a tunnel is open
+pub fn public_url() -> Option<String> {
+    PUBLIC_URL.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn set_public_url(url: Option<String>) {
+    *PUBLIC_URL.write().unwrap_or_else(|e| e.into_inner()) = url;
+}
+
+/// Service forwarding a public URL to the local server
+#[async_trait]
+pub trait TunnelProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Open a tunnel to `local`, a `host:port` address, resolving once its
+    /// public URL is known
+    async fn open(&self, local: &str) -> Result<Tunnel>;
+}
+
+/// Open tunnel, kept up by a running process
+pub struct Tunnel {
+    pub public_url: String,
+    process: Child,
+}
+
+impl Tunnel {
+    /// Wait for the tunnel to close
+    pub async fn closed(mut self) -> std::io::Result<ExitStatus> {
+        self.process.wait().await
+    }
+}
+
+/// Provider running a command that prints the public URL, e.g. `ssh -R`
+pub struct CommandTunnelProvider {
+    name: String,
+    program: String,
+    /// Arguments, with `{local}` standing for the local address
+    args: Vec<String>,
+    /// Domains the public URL is a subdomain of, telling it apart from other
+    /// URLs in the output
+    domains: Vec<String>,
+}
+
+impl CommandTunnelProvider {
+    pub fn new(
+        name: impl Into<String>,
+        program: impl Into<String>,
+        args: &[&str],
+        domains: &[&str],
+    ) -> Self {
+        Self {
+            name: name.into(),
+            program: program.into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            domains: domains.iter().map(|domain| domain.to_string()).collect(),
+        }
+    }
+
+    /// Tunnel over SSH remote forwarding to `destination`
+    fn ssh(name: &str, destination: &str, remote: &str, extra: &[&str], domains: &[&str]) -> Self {
+        let forward = format!("{}:{{local}}", remote);
+        let mut args = vec![
+            "-T",
+            "-o",
+            "StrictHostKeyChecking=accept-new",
+            "-o",
+            "ServerAliveInterval=30",
+            "-o",
+            "ExitOnForwardFailure=yes",
+            "-R",
+            &forward,
+        ];
+        args.extend_from_slice(extra);
+        args.push(destination);
+        Self::new(name, "ssh", &args, domains)
+    }
+}
+
+#[async_trait]
+impl TunnelProvider for CommandTunnelProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn open(&self, local: &str) -> Result<Tunnel> {
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| arg.replace("{local}", local))
+            .collect();
+        debug!("Opening tunnel: {} {}", self.program, args.join(" "));
+        // Runs as long as the tunnel is open, so only the program and its
+        // environment are restricted
+        let mut process = process::runner()
+            .command(&self.program)?
+            .args(&args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                RuneError::Server(format!(
+                    "Failed to run {} for the tunnel: {}",
+                    self.program, e
+                ))
+            })?;
+
+        // The URL may come on either stream; both are read for as long as the
+        // process runs so it never blocks on a full pipe
+        let (lines, mut received) = mpsc::unbounded_channel();
+        if let Some(stdout) = process.stdout.take() {
+            forward_lines(stdout, lines.clone());
+        }
+        if let Some(stderr) = process.stderr.take() {
+            forward_lines(stderr, lines);
+        }
+
+        let domains: Vec<&str> = self.domains.iter().map(String::as_str).collect();
+        let found = tokio::time::timeout(OPEN_TIMEOUT, async {
+            while let Some(line) = received.recv().await {
+                debug!("{}: {}", self.name, line);
+                if let Some(url) = find_public_url(&line, &domains) {
+                    return Some(url);
+                }
+            }
+            None
+        })
+        .await;
+        match found {
+            Ok(Some(public_url)) => {
+                tokio::spawn(async move { while received.recv().await.is_some() {} });
+                Ok(Tunnel {
+                    public_url,
+                    process,
+                })
+            }
+            Ok(None) => Err(RuneError::Server(format!(
+                "{} exited without a public URL",
+                self.name
+            ))),
+            Err(_) => Err(RuneError::Server(format!(
+                "{} reported no public URL within {:?}",
+                self.name, OPEN_TIMEOUT
+            ))),
+        }
+    }
+}
+
+fn forward_lines(
+    stream: impl AsyncRead + Unpin + Send + 'static,
+    lines: mpsc::UnboundedSender<String>,
+) {
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(stream).lines();
+        while let Ok(Some(line)) = reader.next_line().await {
+            if lines.send(line).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Built-in provider called `name`
+pub fn provider(name: &str) -> Result<Arc<dyn TunnelProvider>> {
+    let provider = match name {
+        "localhost.run" => CommandTunnelProvider::ssh(
+            name,
+            "nokey@localhost.run",
+            "80",
+            &[],
+            &["lhr.life", "lhr.rocks"],
+        ),
+        "serveo" => CommandTunnelProvider::ssh(
+            name,
+            "serveo.net",
+            "80",
+            &[],
+            &["serveo.net", "serveousercontent.com"],
+        ),
+        "pinggy" => CommandTunnelProvider::ssh(
+            name,
+            "a.pinggy.io",
+            "0",
+            &["-p", "443"],
+            &["pinggy.link", "pinggy.online"],
+        ),
+        "ngrok" => CommandTunnelProvider::new(
+            name,
+            "ngrok",
+            &["http", "{local}", "--log", "stdout"],
+            &[
+                "ngrok-free.app",
+                "ngrok-free.dev",
+                "ngrok.app",
+                "ngrok.dev",
+                "ngrok.io",
+            ],
+        ),
+        other => {
+            return Err(RuneError::config(format!(
+                "Unknown tunnel provider '{}'. Supported values: {}",
+                other,
+                PROVIDERS.join(", ")
+            )))
+        }
+    };
+    Ok(Arc::new(provider))
+}
+
+/// First `https` URL in `line` on a subdomain of one of `domains`
+fn find_public_url(line: &str, domains: &[&str]) -> Option<String> {
+    line.match_indices("https://").find_map(|(start, _)| {
+        let candidate = line[start..]
+            .split(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '<' | '>' | ','))
+            .next()?;
+        let url = url::Url::parse(candidate).ok()?;
+        let host = url.host_str()?;
+        domains
+            .iter()
+            .any(|domain| host.ends_with(&format!(".{}", domain)))
+            .then(|| format!("https://{}/", host))
+    })
+}
+
+/// Address the tunnel forwards to for a server bound to `hostname:port`
+pub fn local_address(hostname: &str, port: u16) -> String {
+    match hostname.parse::<IpAddr>() {
+        Ok(ip) if ip.is_unspecified() => format!("localhost:{}", port),
+        Ok(IpAddr::V6(ip)) => format!("[{}]:{}", ip, port),
+        _ => format!("{}:{}", hostname, port),
+    }
+}
+
+/// Open a tunnel to `local` and keep it until it closes, announcing its URL
+pub async fn run(
+    provider: Arc<dyn TunnelProvider>,
+    local: String,
+    event_bus: Arc<dyn EventBus>,
+) -> Result<()> {
+    info!("Opening a tunnel via {}", provider.name());
+    let tunnel = provider.open(&local).await?;
+    let public_url = tunnel.public_url.clone();
+    info!("Tunnel via {} open at {}", provider.name(), public_url);
+    set_public_url(Some(public_url.clone()));
+    event_bus
+        .publish_system_event(SystemEvent::tunnel_opened(
+            public_url,
+            provider.name().to_string(),
+        ))
+        .await?;
+
+    let status = tunnel.closed().await;
+    set_public_url(None);
+    Err(RuneError::Server(match status {
+        Ok(status) => format!("Tunnel via {} closed ({})", provider.name(), status),
+        Err(e) => format!("Tunnel via {} failed: {}", provider.name(), e),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_url_and_local_address() {
+        let domains = ["lhr.life"];
+        assert_eq!(
+            find_public_url(
+                "abc123.lhr.life tunneled with tls termination, https://abc123.lhr.life",
+                &domains
+            )
+            .as_deref(),
+            Some("https://abc123.lhr.life/")
+        );
+        // Links in the banner are not the tunnel
+        assert_eq!(
+            find_public_url(
+                "see https://localhost.run/docs/ and https://lhr.life",
+                &domains
+            ),
+            None
+        );
+        assert_eq!(
+            find_public_url(
+                "t=2024 lvl=info msg=\"started tunnel\" addr=http://localhost:3000 url=https://f00.ngrok-free.app",
+                &["ngrok-free.app"]
+            )
+            .as_deref(),
+            Some("https://f00.ngrok-free.app/")
+        );
+
+        assert_eq!(local_address("0.0.0.0", 3000), "localhost:3000");
+        assert_eq!(local_address("::1", 3000), "[::1]:3000");
+        assert_eq!(local_address("127.0.0.1", 8080), "127.0.0.1:8080");
+        assert!(PROVIDERS.iter().all(|name| provider(name).is_ok()));
+        assert!(provider("carrier-pigeon").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_command_provider_reads_url() {
+        let provider = CommandTunnelProvider::new(
+            "echo",
+            "sh",
+            &[
+                "-c",
+                "echo forwarding {local} >&2; echo https://x1.example.test; sleep 5",
+            ],
+            &["example.test"],
+        );
+        let tunnel = provider.open("localhost:3000").await.unwrap();
+        assert_eq!(tunnel.public_url, "https://x1.example.test/");
+    }
+}
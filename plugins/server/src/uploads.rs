@@ -0,0 +1,168 @@
+//! Limits and checks on uploaded files
+//!
+//! The router refuses request bodies over `max_body_size`, or over the
+//! handler's own [`HttpHandler::max_body_size`](crate::HttpHandler::max_body_size):
+//! handlers taking file uploads, WebDAV `PUT` so far, accept up to
+//! `max_upload_size`. An upload of a type known by its first bytes must
+//! start with them, and text must not be a file of one of those types, so a
+//! script is not saved as `photo.png` nor an image as `notes.md`. The type
+//! is the request's `Content-Type`, or the one the file name implies.
+//!
+//! Refusals are JSON: `413` with `{"error": "payload_too_large", "message",
+//! "limit"}` and `415` with `{"error": "unsupported_media_type", "message",
+//! "declared", "detected"}`.
+
+use crate::HttpResponse;
+use axum::http::StatusCode;
+
+/// Uploads accepted unless configured otherwise: 64 MiB
+pub const DEFAULT_MAX_UPLOAD_SIZE: usize = 64 * 1024 * 1024;
+
+/// Type of `bytes` when they start with a known signature
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    let at = |offset: usize, magic: &[u8]| bytes.get(offset..offset + magic.len()) == Some(magic);
+    Some(match () {
+        _ if at(0, b"\x89PNG\r\n\x1a\n") => "image/png",
+        _ if at(0, b"\xff\xd8\xff") => "image/jpeg",
+        _ if at(0, b"GIF87a") || at(0, b"GIF89a") => "image/gif",
+        _ if at(0, b"RIFF") && at(8, b"WEBP") => "image/webp",
+        _ if at(0, b"BM") => "image/bmp",
+        _ if at(0, b"\0\0\x01\0") => "image/x-icon",
+        _ if at(0, b"wOFF") => "font/woff",
+        _ if at(0, b"wOF2") => "font/woff2",
+        _ if at(0, b"\0\x01\0\0") => "font/ttf",
+        _ if at(0, b"OTTO") => "font/otf",
+        _ if at(0, b"%PDF-") => "application/pdf",
+        _ => return None,
+    })
+}
+
+/// Media type of an upload: its `Content-Type` without parameters, unless
+/// missing or generic, else `by_name`
+pub fn declared_type(content_type: Option<&str>, by_name: &str) -> String {
+    let declared = content_type
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .filter(|value| !value.is_empty() && value != "application/octet-stream");
+    let declared = declared.unwrap_or_else(|| {
+        by_name
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    });
+    match declared.as_str() {
+        "image/jpg" | "image/pjpeg" => "image/jpeg".to_string(),
+        "image/vnd.microsoft.icon" => "image/x-icon".to_string(),
+        _ => declared,
+    }
+}
+
+/// Refusal unless `body` looks like the `declared` type
+pub fn check(declared: &str, body: &[u8]) -> Option<HttpResponse> {
+    let detected = sniff(body);
+    let accepted = if is_sniffed(declared) {
+        body.is_empty() || detected == Some(declared)
+    } else if declared.starts_with("text/") || declared.ends_with("+xml") {
+        // Some signatures are plain letters, like the `BM` of bitmaps
+        detected.is_none() || std::str::from_utf8(body).is_ok()
+    } else {
+        // Other types have no signature to check
+        true
+    };
+    if accepted {
+        return None;
+    }
+    let message = match detected {
+        Some(detected) => format!("Content is {}, not {}", detected, declared),
+        None => format!("Content is not {}", declared),
+    };
+    Some(refusal(
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        serde_json::json!({
+            "error": "unsupported_media_type",
+            "message": message,
+            "declared": declared,
+            "detected": detected,
+        }),
+    ))
+}
+
+/// Refusal of a body over `limit` bytes
+pub fn too_large(limit: usize) -> HttpResponse {
+    refusal(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        serde_json::json!({
+            "error": "payload_too_large",
+            "message": format!("Request body exceeds {} bytes", limit),
+            "limit": limit,
+        }),
+    )
+}
+
+/// Whether [`sniff`] recognizes `media_type`
+fn is_sniffed(media_type: &str) -> bool {
+    matches!(
+        media_type,
+        "image/png"
+            | "image/jpeg"
+            | "image/gif"
+            | "image/webp"
+            | "image/bmp"
+            | "image/x-icon"
+            | "font/woff"
+            | "font/woff2"
+            | "font/ttf"
+            | "font/otf"
+            | "application/pdf"
+    )
+}
+
+fn refusal(status: StatusCode, body: serde_json::Value) -> HttpResponse {
+    HttpResponse::new(status)
+        .with_header("content-type", "application/json")
+        .with_body(body.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn test_uploads_must_match_their_type() {
+        assert_eq!(sniff(PNG), Some("image/png"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WAVE"), None);
+        assert_eq!(sniff(b"# Notes"), None);
+
+        assert_eq!(declared_type(Some("image/JPG"), "image/png"), "image/jpeg");
+        let by_name = "text/markdown; charset=utf-8";
+        assert_eq!(declared_type(None, by_name), "text/markdown");
+        let generic = Some("application/octet-stream");
+        assert_eq!(declared_type(generic, "image/png"), "image/png");
+
+        assert!(check("image/png", PNG).is_none());
+        assert!(check("image/png", b"").is_none());
+        assert!(check("text/markdown", b"# Notes\n").is_none());
+        assert!(check("text/markdown", b"BMW service notes").is_none());
+        assert!(check("application/zip", b"PK\x03\x04").is_none());
+
+        let script = check("image/png", b"<script>alert(1)</script>").unwrap();
+        assert_eq!(script.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let refusal: serde_json::Value = serde_json::from_slice(&script.body).unwrap();
+        assert_eq!(refusal["error"], "unsupported_media_type");
+        assert_eq!(refusal["detected"], serde_json::Value::Null);
+        let image = check("text/markdown", PNG).unwrap();
+        let refusal: serde_json::Value = serde_json::from_slice(&image.body).unwrap();
+        assert_eq!(refusal["detected"], "image/png");
+        assert!(check("image/gif", PNG).is_some());
+
+        let large = too_large(5);
+        assert_eq!(large.status, StatusCode::PAYLOAD_TOO_LARGE);
+        let refusal: serde_json::Value = serde_json::from_slice(&large.body).unwrap();
+        assert_eq!(refusal["limit"], 5);
+    }
+}
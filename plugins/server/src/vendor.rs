@@ -0,0 +1,230 @@
+//! Client-side libraries bundled into the binary
+//!
+//! Preview pages only load scripts from the server itself. References to
+//! public CDN copies of a bundled library, e.g. a Mermaid `<script>` in a
+//! document's raw HTML, are pointed at the bundled version, and in offline
+//! mode every response carries a [`crate::csp::policy`] so the browser
+//! fetches nothing from elsewhere.
+//!
+//! The libraries live in `plugins/server/vendor` and are embedded with
+//! `rust-embed`, in debug builds too.
+
+use crate::{HttpHandler, HttpRequest, HttpResponse};
+use async_trait::async_trait;
+use axum::body::Bytes;
+use axum::http::{Method, StatusCode};
+use regex::{Captures, Regex};
+use rune_core::error::Result;
+use rust_embed::RustEmbed;
+use std::borrow::Cow;
+use std::sync::OnceLock;
+use tracing::debug;
+
+/// Files of the bundled libraries
+#[derive(RustEmbed)]
+#[folder = "vendor/"]
+struct VendorFiles;
+
+/// Content of the bundled file `name`, empty when there is none
+pub fn file(name: &str) -> Cow<'static, [u8]> {
+    VendorFiles::get(name)
+        .map(|file| file.data)
+        .unwrap_or_default()
+}
+
+/// A library served from the binary
+#[derive(Debug)]
+pub struct VendoredAsset {
+    /// URL path the asset is served at
+    pub path: &'static str,
+    pub content_type: &'static str,
+    /// Name of the file in the vendor directory
+    pub file: &'static str,
+    /// CDN URL prefixes hosting the same library, without scheme
+    pub cdn_prefixes: &'static [&'static str],
+}
+
+impl VendoredAsset {
+    /// Content of the asset
+    pub fn content(&self) -> Cow<'static, [u8]> {
+        file(self.file)
+    }
+}
+
+/// Every bundled client-side library
+pub const VENDORED_ASSETS: &[VendoredAsset] = &[VendoredAsset {
+    path: "/mermaid.min.js",
+    content_type: "application/javascript",
+    file: "mermaid.min.js",
+    cdn_prefixes: &[
+        "cdn.jsdelivr.net/npm/mermaid",
+        "unpkg.com/mermaid",
+        "cdnjs.cloudflare.com/ajax/libs/mermaid/",
+    ],
+}];
+
+/// The bundled asset served at `path`
+pub fn vendored_asset(path: &str) -> Option<&'static VendoredAsset> {
+    VENDORED_ASSETS.iter().find(|asset| asset.path == path)
+}
+
+/// The bundled asset a CDN script URL refers to
+fn vendored_for_url(url: &str) -> Option<&'static VendoredAsset> {
+    let location = url
+        .strip_prefix("https:")
+        .or_else(|| url.strip_prefix("http:"))
+        .unwrap_or(url)
+        .strip_prefix("//")?;
+    let file = location.split(['?', '#']).next().unwrap_or(location);
+    if !file.ends_with(".js") {
+        return None;
+    }
+    VENDORED_ASSETS.iter().find(|asset| {
+        asset.cdn_prefixes.iter().any(|prefix| {
+            // `mermaid` must not match `mermaid-plugin`
+            location
+                .strip_prefix(prefix)
+                .is_some_and(|rest| prefix.ends_with('/') || rest.starts_with(['@', '/']))
+        })
+    })
+}
+
+/// Point `src` attributes referring to CDN copies of bundled libraries at
+/// the bundled versions
+pub fn localize_cdn_references(html: &str) -> String {
+    static SRC: OnceLock<Regex> = OnceLock::new();
+    let src = SRC.get_or_init(|| {
+        Regex::new(r#"(<script\b[^>]*?\bsrc=)(["'])((?:https?:)?//[^"']+)["']"#)
+            .expect("valid CDN reference pattern")
+    });
+    if !html.contains("//") {
+        return html.to_string();
+    }
+
+    src.replace_all(html, |caps: &Captures| match vendored_for_url(&caps[3]) {
+        Some(asset) => {
+            debug!("Serving bundled {} instead of {}", asset.path, &caps[3]);
+            format!("{}{}{}{}", &caps[1], &caps[2], asset.path, &caps[2])
+        }
+        None => caps[0].to_string(),
+    })
+    .into_owned()
+}
+
+/// Response body of embedded content, without copying it
+pub(crate) fn body(content: Cow<'static, [u8]>) -> Bytes {
+    match content {
+        Cow::Borrowed(content) => Bytes::from_static(content),
+        Cow::Owned(content) => Bytes::from(content),
+    }
+}
+
+/// Serves the bundled libraries at their paths
+pub struct VendoredAssetHandler {
+    etag: &'static str,
+}
+
+impl Default for VendoredAssetHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VendoredAssetHandler {
+    /// Create a handler for [`VENDORED_ASSETS`]
+    pub fn new() -> Self {
+        Self {
+            etag: concat!("\"", env!("CARGO_PKG_VERSION"), "\""),
+        }
+    }
+}
+
+#[async_trait]
+impl HttpHandler for VendoredAssetHandler {
+    fn path_pattern(&self) -> &str {
+        "/mermaid.min.js"
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let Some(asset) = vendored_asset(&request.path) else {
+            return Ok(HttpResponse::error(
+                StatusCode::NOT_FOUND,
+                "Asset not found",
+            ));
+        };
+
+        let cached = request
+            .headers
+            .get("if-none-match")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == self.etag));
+        if cached {
+            return Ok(HttpResponse::new(StatusCode::NOT_MODIFIED)
+                .with_header("etag", self.etag)
+                .with_header("cache-control", "public, no-cache"));
+        }
+
+        Ok(HttpResponse::new(StatusCode::OK)
+            .with_header("content-type", asset.content_type)
+            .with_header("etag", self.etag)
+            .with_header("cache-control", "public, no-cache")
+            .with_body(body(asset.content())))
+    }
+
+    fn priority(&self) -> i32 {
+        5 // High priority for specific assets
+    }
+
+    fn matches_path(&self, path: &str) -> bool {
+        vendored_asset(path).is_some()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cdn_scripts_point_at_bundled_assets() {
+        let html = concat!(
+            r#"<script src="https://cdn.jsdelivr.net/npm/mermaid@10/dist/mermaid.min.js"></script>"#,
+            r#"<script defer src='//unpkg.com/mermaid/dist/mermaid.js?v=1'></script>"#,
+            r#"<script src="https://cdn.jsdelivr.net/npm/mermaid-plugin/index.js"></script>"#,
+            r#"<script src="https://example.com/app.js"></script>"#,
+            r#"<a href="https://cdn.jsdelivr.net/npm/mermaid@10/dist/mermaid.min.js">x</a>"#,
+        );
+        assert_eq!(
+            localize_cdn_references(html),
+            concat!(
+                r#"<script src="/mermaid.min.js"></script>"#,
+                r#"<script defer src='/mermaid.min.js'></script>"#,
+                r#"<script src="https://cdn.jsdelivr.net/npm/mermaid-plugin/index.js"></script>"#,
+                r#"<script src="https://example.com/app.js"></script>"#,
+                r#"<a href="https://cdn.jsdelivr.net/npm/mermaid@10/dist/mermaid.min.js">x</a>"#,
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_vendored_assets_are_served() {
+        let handler = VendoredAssetHandler::new();
+        assert!(handler.matches_path("/mermaid.min.js"));
+        assert!(!handler.matches_path("/mermaid.min.js/other"));
+
+        let request = HttpRequest::get("/mermaid.min.js");
+        let response = handler.handle(request).await.unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.headers["content-type"], "application/javascript");
+        assert_eq!(response.body[..], file("mermaid.min.js")[..]);
+        assert!(response.body.len() > 1000);
+        assert!(file("missing.js").is_empty());
+    }
+}
@@ -0,0 +1,1207 @@
+//! WebDAV access to the workspace
+//!
+//! With the `webdav` setting (`rune serve --webdav`) the served documents
+//! are exposed at `/dav/`, so external editors, including mobile markdown
+//! apps, can open and save files directly. A served directory is exposed
+//! whole, a served document as a collection holding just that document. A
+//! single root is the `/dav/` collection itself; with several roots each is
+//! a collection named after its route prefix. Saves, moves and deletions
+//! are announced as file changes, so open previews reload just as for edits
+//! on disk.
+//!
+//! Access follows the configuration API: clients present the configured
+//! `api_token`, either as the password of HTTP Basic authentication or as a
+//! bearer token, and only local same-origin clients go without in dev mode.
+//! `/dav/` is left out of CORS. Locks are granted so clients that insist on
+//! locking can save, but they are not enforced. Hidden files and
+//! directories are left out. Paths are resolved by the [`PathJail`] of their
+//! share, and links are left out of listings and copies. Saved files are
+//! checked as [`crate::uploads`] describes.
+
+use crate::config_api::ApiAccess;
+use crate::jail::{self, PathJail, SymlinkPolicy};
+use crate::roots::document_href;
+use crate::{
+    handlers, publish_detached, uploads, HandlerRegistry, HttpHandler, HttpRequest, HttpResponse,
+};
+use async_trait::async_trait;
+use axum::http::{Method, StatusCode};
+use percent_encoding::percent_decode_str;
+use rune_core::{
+    error::Result,
+    event::{ChangeType, EventBus, SystemEvent},
+    plugin::PluginContext,
+    state::ServedRoot,
+    time::http_date,
+};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+/// URL path below which the workspace is exposed
+pub const DAV_PREFIX: &str = "/dav";
+
+/// Methods answered below [`DAV_PREFIX`]
+const DAV_METHODS: &str =
+    "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, PROPPATCH, MKCOL, COPY, MOVE, LOCK, UNLOCK";
+
+/// Directory exposed as a collection
+#[derive(Debug, Clone)]
+struct Share {
+    /// Collection name below `/dav/`; empty for `/dav/` itself
+    name: String,
+    dir: PathBuf,
+    /// The one document of `dir` exposed, for a document root
+    document: Option<PathBuf>,
+    /// Keeps requests inside `dir`
+    jail: PathJail,
+}
+
+impl Share {
+    /// Whether `path` is what the share exposes, which may not be removed
+    fn is_root(&self, path: &Path) -> bool {
+        path == self.dir || self.document.as_deref() == Some(path)
+    }
+}
+
+/// Whether `path` is below [`DAV_PREFIX`]
+pub(crate) fn is_dav_path(path: &str) -> bool {
+    path.strip_prefix(DAV_PREFIX)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// What a request path names
+enum Target {
+    /// `/dav/`, listing the shares
+    Shares,
+    /// A file or directory of a share, which may not exist yet
+    Entry { share: usize, path: PathBuf },
+}
+
+/// Handler answering WebDAV requests for the served directories
+pub struct WebDavHandler {
+    shares: Vec<Share>,
+    access: ApiAccess,
+    event_bus: Arc<dyn EventBus>,
+    max_upload_size: usize,
+}
+
+impl WebDavHandler {
+    /// Expose `roots`: a directory root whole, a document root as its
+    /// directory with only the document in it
+    pub fn new(roots: &[ServedRoot], access: ApiAccess, event_bus: Arc<dyn EventBus>) -> Self {
+        let single = roots.len() == 1;
+        let shares = roots
+            .iter()
+            .filter_map(|root| {
+                let path = root.path.canonicalize().unwrap_or(root.path.clone());
+                let (dir, document) = if root.is_dir() {
+                    (path, None)
+                } else {
+                    (path.parent()?.to_path_buf(), Some(path))
+                };
+                Some(Share {
+                    name: if single {
+                        String::new()
+                    } else {
+                        root.prefix.clone()
+                    },
+                    jail: PathJail::new(&dir, SymlinkPolicy::default()),
+                    dir,
+                    document,
+                })
+            })
+            .collect();
+        Self {
+            shares,
+            access,
+            event_bus,
+            max_upload_size: uploads::DEFAULT_MAX_UPLOAD_SIZE,
+        }
+    }
+
+    /// Treat symbolic links in the shares as `policy` says
+    pub fn with_symlink_policy(mut self, policy: SymlinkPolicy) -> Self {
+        for share in &mut self.shares {
+            share.jail = PathJail::new(&share.dir, policy);
+        }
+        self
+    }
+
+    /// Accept files of up to `size` bytes
+    pub fn with_max_upload_size(mut self, size: usize) -> Self {
+        self.max_upload_size = size;
+        self
+    }
+
+    /// Resolve a URL path below [`DAV_PREFIX`] through the jail of its
+    /// share; `None` for paths outside of the shares or naming hidden files
+    fn resolve(&self, url_path: &str) -> Option<Target> {
+        if !is_dav_path(url_path) {
+            return None;
+        }
+        let rest = &url_path[DAV_PREFIX.len()..];
+        let segments: Vec<&str> = rest.split('/').filter(|s| !s.is_empty()).collect();
+        if segments.iter().any(|segment| {
+            percent_decode_str(segment)
+                .decode_utf8_lossy()
+                .starts_with('.')
+        }) {
+            return None;
+        }
+
+        let (share, segments) = match self.shares.as_slice() {
+            [only] if only.name.is_empty() => (0, &segments[..]),
+            _ => {
+                let Some(name) = segments.first() else {
+                    return Some(Target::Shares);
+                };
+                let name = percent_decode_str(name).decode_utf8_lossy();
+                let share = self.shares.iter().position(|share| share.name == name)?;
+                (share, &segments[1..])
+            }
+        };
+        let relative = segments.join("/");
+        let path = match self.shares[share].jail.locate(&relative) {
+            Ok(path) => path,
+            Err(rejection) => {
+                if rejection.is_attack() {
+                    jail::audit(url_path, rejection);
+                }
+                return None;
+            }
+        };
+        if let Some(document) = &self.shares[share].document {
+            if !segments.is_empty() && path != *document {
+                return None;
+            }
+        }
+        Some(Target::Entry { share, path })
+    }
+
+    /// URL of `path` of the share at `share`
+    fn href(&self, share: usize, path: &Path, collection: bool) -> String {
+        let share = &self.shares[share];
+        let mut href = format!("{}/", DAV_PREFIX);
+        if !share.name.is_empty() {
+            href.push_str(&document_href(Path::new(&share.name)));
+            href.push('/');
+        }
+        let relative = path.strip_prefix(&share.dir).unwrap_or(Path::new(""));
+        if !relative.as_os_str().is_empty() {
+            href.push_str(&document_href(relative));
+            if collection {
+                href.push('/');
+            }
+        }
+        href
+    }
+
+    /// Visible members of the collection `dir` of the share at `share`
+    fn children(&self, share: usize, dir: &Path) -> Vec<(PathBuf, Metadata)> {
+        let mut children = visible_children(dir);
+        if let Some(document) = &self.shares[share].document {
+            children.retain(|(child, _)| child == document);
+        }
+        children
+    }
+
+    fn announce(&self, path: &Path, change_type: ChangeType) {
+        publish_detached(
+            &self.event_bus,
+            SystemEvent::file_changed(path.to_path_buf(), change_type),
+        );
+    }
+
+    async fn propfind(&self, target: Target, depth_zero: bool) -> HttpResponse {
+        let mut responses = String::new();
+        match target {
+            Target::Shares => {
+                responses.push_str(&prop_response(&format!("{}/", DAV_PREFIX), "dav", None));
+                if !depth_zero {
+                    for (index, share) in self.shares.iter().enumerate() {
+                        let metadata = std::fs::metadata(&share.dir).ok();
+                        responses.push_str(&prop_response(
+                            &self.href(index, &share.dir, true),
+                            &share.name,
+                            metadata.as_ref(),
+                        ));
+                    }
+                }
+            }
+            Target::Entry { share, path } => {
+                let Ok(metadata) = std::fs::metadata(&path) else {
+                    return HttpResponse::error(StatusCode::NOT_FOUND, "Not found");
+                };
+                responses.push_str(&prop_response(
+                    &self.href(share, &path, metadata.is_dir()),
+                    &display_name(&path),
+                    Some(&metadata),
+                ));
+                if metadata.is_dir() && !depth_zero {
+                    for (child, metadata) in self.children(share, &path) {
+                        responses.push_str(&prop_response(
+                            &self.href(share, &child, metadata.is_dir()),
+                            &display_name(&child),
+                            Some(&metadata),
+                        ));
+                    }
+                }
+            }
+        }
+        multistatus(&responses)
+    }
+
+    async fn get(&self, share: usize, path: &Path) -> HttpResponse {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return HttpResponse::error(StatusCode::NOT_FOUND, "Not found");
+        };
+        if metadata.is_dir() {
+            let items: String = self
+                .children(share, path)
+                .into_iter()
+                .map(|(child, metadata)| {
+                    format!(
+                        "<li><a href=\"{}\">{}</a></li>\n",
+                        html_escape::encode_double_quoted_attribute(&self.href(
+                            share,
+                            &child,
+                            metadata.is_dir()
+                        )),
+                        html_escape::encode_text(&display_name(&child))
+                    )
+                })
+                .collect();
+            let title = display_name(path);
+            return HttpResponse::html(handlers::standalone_page(
+                &title,
+                &format!(
+                    "<h1>{}</h1>\n<ul>\n{}</ul>",
+                    html_escape::encode_text(&title),
+                    items
+                ),
+            ));
+        }
+
+        match tokio::fs::read(path).await {
+            Ok(bytes) => HttpResponse::new(StatusCode::OK)
+                .with_header("content-type", content_type(path))
+                .with_header("etag", &etag(&metadata))
+                .with_header("last-modified", &http_date(modified(&metadata)))
+                .with_body(bytes),
+            Err(e) => {
+                warn!("WebDAV read of {} failed: {}", path.display(), e);
+                HttpResponse::error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to read file")
+            }
+        }
+    }
+
+    async fn put(&self, path: &Path, request: &HttpRequest) -> HttpResponse {
+        if path.is_dir() {
+            return HttpResponse::error(StatusCode::METHOD_NOT_ALLOWED, "Is a collection");
+        }
+        if !path.parent().is_some_and(Path::is_dir) {
+            return HttpResponse::error(StatusCode::CONFLICT, "Parent collection missing");
+        }
+        let body = &request.body[..];
+        let declared = request
+            .headers
+            .get("content-type")
+            .and_then(|value| value.to_str().ok());
+        let declared = uploads::declared_type(declared, content_type(path));
+        if let Some(refusal) = uploads::check(&declared, body) {
+            warn!(
+                "Refused WebDAV upload of {}: not {}",
+                path.display(),
+                declared
+            );
+            return refusal;
+        }
+        let existed = path.exists();
+        let written = if existed {
+            tokio::fs::write(path, body).await
+        } else {
+            create_file(path, body).await
+        };
+        if let Err(e) = written {
+            warn!("WebDAV write of {} failed: {}", path.display(), e);
+            return HttpResponse::error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to write file");
+        }
+        info!("Saved {} over WebDAV", path.display());
+        if existed {
+            self.announce(path, ChangeType::Modified);
+            HttpResponse::new(StatusCode::NO_CONTENT)
+        } else {
+            self.announce(path, ChangeType::Created);
+            HttpResponse::new(StatusCode::CREATED)
+        }
+    }
+
+    async fn delete(&self, share: usize, path: &Path) -> HttpResponse {
+        if self.shares[share].is_root(path) {
+            return HttpResponse::error(StatusCode::FORBIDDEN, "Cannot delete what is shared");
+        }
+        let removed = if path.is_dir() {
+            tokio::fs::remove_dir_all(path).await
+        } else {
+            tokio::fs::remove_file(path).await
+        };
+        match removed {
+            Ok(()) => {
+                info!("Deleted {} over WebDAV", path.display());
+                self.announce(path, ChangeType::Deleted);
+                HttpResponse::new(StatusCode::NO_CONTENT)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                HttpResponse::error(StatusCode::NOT_FOUND, "Not found")
+            }
+            Err(e) => {
+                warn!("WebDAV delete of {} failed: {}", path.display(), e);
+                HttpResponse::error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete")
+            }
+        }
+    }
+
+    async fn mkcol(&self, path: &Path, body: &[u8]) -> HttpResponse {
+        if !body.is_empty() {
+            return HttpResponse::error(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Unexpected body");
+        }
+        if path.exists() {
+            return HttpResponse::error(StatusCode::METHOD_NOT_ALLOWED, "Already exists");
+        }
+        if !path.parent().is_some_and(Path::is_dir) {
+            return HttpResponse::error(StatusCode::CONFLICT, "Parent collection missing");
+        }
+        match tokio::fs::create_dir(path).await {
+            Ok(()) => HttpResponse::new(StatusCode::CREATED),
+            Err(e) => {
+                warn!("WebDAV mkcol of {} failed: {}", path.display(), e);
+                HttpResponse::error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create")
+            }
+        }
+    }
+
+    /// Copy or move `from` to the request's `Destination`
+    async fn transfer(
+        &self,
+        share: usize,
+        from: &Path,
+        request: &HttpRequest,
+        moving: bool,
+    ) -> HttpResponse {
+        if !from.exists() {
+            return HttpResponse::error(StatusCode::NOT_FOUND, "Not found");
+        }
+        if moving && self.shares[share].is_root(from) {
+            return HttpResponse::error(StatusCode::FORBIDDEN, "Cannot move what is shared");
+        }
+        let header = |name: &str| {
+            request
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+        };
+        let destination = header("destination");
+        let destination = match url::Url::parse(destination) {
+            Ok(url) => url.path().to_string(),
+            Err(_) => destination.to_string(),
+        };
+        let Some(Target::Entry {
+            share: to_share,
+            path: to,
+        }) = self.resolve(&destination)
+        else {
+            return HttpResponse::error(StatusCode::BAD_GATEWAY, "Invalid destination");
+        };
+        if to == self.shares[to_share].dir {
+            return HttpResponse::error(StatusCode::FORBIDDEN, "Cannot replace a shared directory");
+        }
+        if to.starts_with(from) {
+            return HttpResponse::error(StatusCode::FORBIDDEN, "Destination inside source");
+        }
+        if !to.parent().is_some_and(Path::is_dir) {
+            return HttpResponse::error(StatusCode::CONFLICT, "Parent collection missing");
+        }
+        let existed = to.exists();
+        if existed {
+            if header("overwrite").eq_ignore_ascii_case("F") {
+                return HttpResponse::error(StatusCode::PRECONDITION_FAILED, "Destination exists");
+            }
+            let removed = if to.is_dir() {
+                tokio::fs::remove_dir_all(&to).await
+            } else {
+                tokio::fs::remove_file(&to).await
+            };
+            if let Err(e) = removed {
+                warn!("WebDAV overwrite of {} failed: {}", to.display(), e);
+                return HttpResponse::error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to replace");
+            }
+        }
+
+        let transferred = if moving {
+            tokio::fs::rename(from, &to).await
+        } else {
+            let (from, to) = (from.to_path_buf(), to.clone());
+            tokio::task::spawn_blocking(move || copy_recursively(&from.canonicalize()?, &to))
+                .await
+                .unwrap_or_else(|e| Err(std::io::Error::other(e)))
+        };
+        if let Err(e) = transferred {
+            warn!(
+                "WebDAV transfer of {} to {} failed: {}",
+                from.display(),
+                to.display(),
+                e
+            );
+            return HttpResponse::error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to transfer");
+        }
+
+        if moving {
+            info!("Moved {} to {} over WebDAV", from.display(), to.display());
+            self.announce(from, ChangeType::Deleted);
+        }
+        self.announce(
+            &to,
+            if existed {
+                ChangeType::Modified
+            } else {
+                ChangeType::Created
+            },
+        );
+        HttpResponse::new(if existed {
+            StatusCode::NO_CONTENT
+        } else {
+            StatusCode::CREATED
+        })
+    }
+
+    /// Grant a lock nobody else is held to, creating missing files as
+    /// clients expect
+    async fn lock(&self, share: usize, path: &Path) -> HttpResponse {
+        let created = !path.exists();
+        if created {
+            if !path.parent().is_some_and(Path::is_dir) {
+                return HttpResponse::error(StatusCode::CONFLICT, "Parent collection missing");
+            }
+            if let Err(e) = create_file(path, b"").await {
+                warn!("WebDAV lock of {} failed: {}", path.display(), e);
+                return HttpResponse::error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to lock");
+            }
+        }
+        let token = format!("opaquelocktoken:{}", uuid::Uuid::new_v4());
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:prop xmlns:D=\"DAV:\"><D:lockdiscovery><D:activelock><D:locktype><D:write/></D:locktype><D:lockscope><D:exclusive/></D:lockscope><D:depth>0</D:depth><D:timeout>Second-3600</D:timeout><D:locktoken><D:href>{}</D:href></D:locktoken><D:lockroot><D:href>{}</D:href></D:lockroot></D:activelock></D:lockdiscovery></D:prop>\n",
+            token,
+            html_escape::encode_text(&self.href(share, path, path.is_dir()))
+        );
+        HttpResponse::new(if created {
+            StatusCode::CREATED
+        } else {
+            StatusCode::OK
+        })
+        .with_header("content-type", "application/xml; charset=utf-8")
+        .with_header("lock-token", &format!("<{}>", token))
+        .with_body(body)
+    }
+}
+
+#[async_trait]
+impl HttpHandler for WebDavHandler {
+    fn path_pattern(&self) -> &str {
+        DAV_PREFIX
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn can_handle(&self, path: &str, method: &Method) -> bool {
+        DAV_METHODS
+            .split(", ")
+            .any(|dav_method| dav_method == method.as_str())
+            && self.matches_path(path)
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse> {
+        // Clients discover the server before authenticating
+        if request.method == Method::OPTIONS {
+            return Ok(HttpResponse::new(StatusCode::OK)
+                .with_header("dav", "1, 2")
+                .with_header("allow", DAV_METHODS)
+                .with_header("ms-author-via", "DAV"));
+        }
+        if let Some(refusal) = self.access.check(&request, "WebDAV").await {
+            return Ok(refusal);
+        }
+
+        let Some(target) = self.resolve(&request.path) else {
+            return Ok(HttpResponse::error(StatusCode::NOT_FOUND, "Not found"));
+        };
+        let method = request.method.as_str();
+        let (share, path) = match target {
+            Target::Shares if method == "PROPFIND" => {
+                return Ok(self.propfind(Target::Shares, depth_zero(&request)).await);
+            }
+            Target::Shares if method == "GET" || method == "HEAD" => {
+                let items: String = self
+                    .shares
+                    .iter()
+                    .enumerate()
+                    .map(|(index, share)| {
+                        format!(
+                            "<li><a href=\"{}\">{}</a></li>\n",
+                            html_escape::encode_double_quoted_attribute(
+                                &self.href(index, &share.dir, true)
+                            ),
+                            html_escape::encode_text(&share.name)
+                        )
+                    })
+                    .collect();
+                return Ok(HttpResponse::html(handlers::standalone_page(
+                    "WebDAV",
+                    &format!("<h1>WebDAV</h1>\n<ul>\n{}</ul>", items),
+                )));
+            }
+            Target::Shares => {
+                return Ok(HttpResponse::error(
+                    StatusCode::FORBIDDEN,
+                    "The share list is read-only",
+                ))
+            }
+            Target::Entry { share, path } => (share, path),
+        };
+
+        Ok(match method {
+            "PROPFIND" => {
+                self.propfind(Target::Entry { share, path }, depth_zero(&request))
+                    .await
+            }
+            "GET" | "HEAD" => self.get(share, &path).await,
+            "PUT" => self.put(&path, &request).await,
+            "DELETE" => self.delete(share, &path).await,
+            "MKCOL" => self.mkcol(&path, &request.body).await,
+            "COPY" => self.transfer(share, &path, &request, false).await,
+            "MOVE" => self.transfer(share, &path, &request, true).await,
+            "LOCK" => self.lock(share, &path).await,
+            "UNLOCK" => HttpResponse::new(StatusCode::NO_CONTENT),
+            // Dead properties are not stored; report them as set so clients
+            // saving file times carry on
+            "PROPPATCH" => multistatus(&format!(
+                "<D:response><D:href>{}</D:href><D:propstat><D:prop/><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>\n",
+                html_escape::encode_text(&self.href(share, &path, path.is_dir()))
+            )),
+            _ => HttpResponse::error(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed"),
+        })
+    }
+
+    fn priority(&self) -> i32 {
+        5 // Same as the APIs
+    }
+
+    fn max_body_size(&self) -> Option<usize> {
+        Some(self.max_upload_size)
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Whether a PROPFIND asks about the resource only, not its members
+fn depth_zero(request: &HttpRequest) -> bool {
+    request
+        .headers
+        .get("depth")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|depth| depth.trim() == "0")
+}
+
+/// Files and directories in `dir`, sorted, without hidden ones and links
+fn visible_children(dir: &Path) -> Vec<(PathBuf, Metadata)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut children: Vec<(PathBuf, Metadata)> = entries
+        .flatten()
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|entry| {
+            let metadata = entry.path().symlink_metadata().ok()?;
+            (!metadata.is_symlink()).then(|| (entry.path(), metadata))
+        })
+        .collect();
+    children.sort_by(|a, b| a.0.cmp(&b.0));
+    children
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// MIME type of the file at `path`, knowing markdown and other text besides
+/// assets
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown") => {
+            "text/markdown; charset=utf-8"
+        }
+        Some(ext) if ext.eq_ignore_ascii_case("txt") => "text/plain; charset=utf-8",
+        Some(ext) if ext.eq_ignore_ascii_case("html") => "text/html; charset=utf-8",
+        _ => rune_core::export::guess_mime_type(path),
+    }
+}
+
+fn modified(metadata: &Metadata) -> SystemTime {
+    metadata.modified().unwrap_or(UNIX_EPOCH)
+}
+
+fn etag(metadata: &Metadata) -> String {
+    let modified = modified(metadata)
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", metadata.len(), modified)
+}
+
+/// `<D:response>` describing one resource
+fn prop_response(href: &str, name: &str, metadata: Option<&Metadata>) -> String {
+    let mut props = format!(
+        "<D:displayname>{}</D:displayname>",
+        html_escape::encode_text(name)
+    );
+    match metadata {
+        Some(metadata) if metadata.is_file() => {
+            props.push_str(&format!(
+                "<D:resourcetype/><D:getcontentlength>{}</D:getcontentlength><D:getcontenttype>{}</D:getcontenttype><D:getetag>{}</D:getetag>",
+                metadata.len(),
+                content_type(Path::new(name)),
+                html_escape::encode_text(&etag(metadata))
+            ));
+        }
+        _ => props.push_str("<D:resourcetype><D:collection/></D:resourcetype>"),
+    }
+    if let Some(metadata) = metadata {
+        props.push_str(&format!(
+            "<D:getlastmodified>{}</D:getlastmodified>",
+            http_date(modified(metadata))
+        ));
+    }
+    format!(
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop>{}</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>\n",
+        html_escape::encode_text(href),
+        props
+    )
+}
+
+/// `207 Multi-Status` response of `responses`
+fn multistatus(responses: &str) -> HttpResponse {
+    HttpResponse::new(StatusCode::MULTI_STATUS)
+        .with_header("content-type", "application/xml; charset=utf-8")
+        .with_body(format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">\n{}</D:multistatus>\n",
+            responses
+        ))
+}
+
+/// Copy the tree at `from` to `to`, leaving out the links in it, which
+/// may lead anywhere
+fn copy_recursively(from: &Path, to: &Path) -> std::io::Result<()> {
+    let metadata = from.symlink_metadata()?;
+    if metadata.is_symlink() {
+        Ok(())
+    } else if metadata.is_dir() {
+        std::fs::create_dir(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursively(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// Create the file at `path` holding `contents`, failing when anything is
+/// there already, even a link leading nowhere
+async fn create_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .await?;
+    file.write_all(contents).await?;
+    file.flush().await
+}
+
+/// Register the WebDAV handler when the `webdav` setting enables it
+pub async fn register_webdav_handler(
+    registry: &HandlerRegistry,
+    context: &PluginContext,
+    roots: &[ServedRoot],
+) -> Result<()> {
+    if context.config.get_global_setting::<bool>("webdav") != Some(true) {
+        return Ok(());
+    }
+    let max_upload_size = context
+        .config
+        .get_global_setting::<usize>("max_upload_size")
+        .unwrap_or(uploads::DEFAULT_MAX_UPLOAD_SIZE);
+    let handler = WebDavHandler::new(
+        roots,
+        ApiAccess::from_context(context).await,
+        context.event_bus.clone(),
+    )
+    .with_symlink_policy(SymlinkPolicy::from_config(&context.config))
+    .with_max_upload_size(max_upload_size);
+    if handler.shares.is_empty() {
+        warn!("WebDAV is enabled but nothing is served");
+        return Ok(());
+    }
+    for (index, share) in handler.shares.iter().enumerate() {
+        info!(
+            "Exposing {} over WebDAV at {}",
+            share.document.as_ref().unwrap_or(&share.dir).display(),
+            handler.href(index, &share.dir, true)
+        );
+    }
+    registry.register_http_handler(Arc::new(handler)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::{config::Config, event::InMemoryEventBus};
+    use tempfile::TempDir;
+
+    fn dav_handler(roots: &[ServedRoot], settings: &[(&str, serde_json::Value)]) -> WebDavHandler {
+        let mut config = Config::new();
+        for (name, value) in settings {
+            config
+                .set_global_setting(name.to_string(), value.clone())
+                .unwrap();
+        }
+        WebDavHandler::new(
+            roots,
+            ApiAccess::new(Arc::new(config), None),
+            Arc::new(InMemoryEventBus::new()),
+        )
+    }
+
+    /// Handler exposing `dir` at `/dav/`, authenticated with `s3cret`
+    fn workspace(dir: &Path) -> WebDavHandler {
+        dav_handler(
+            &[ServedRoot {
+                prefix: "docs".to_string(),
+                path: dir.to_path_buf(),
+            }],
+            &[("api_token", "s3cret".into())],
+        )
+    }
+
+    /// Any user name goes with the token as password
+    const AUTH: (&str, &str) = ("authorization", "Basic cGhvbmU6czNjcmV0");
+
+    async fn call(
+        handler: &WebDavHandler,
+        method: &str,
+        path: &str,
+        extra: &[(&str, &str)],
+        body: &str,
+    ) -> HttpResponse {
+        let request = HttpRequest::named(method, path)
+            .with_header(AUTH.0, AUTH.1)
+            .with_headers(extra)
+            .with_body(body.to_string());
+        handler.handle(request).await.unwrap()
+    }
+
+    fn workspace_dir() -> (TempDir, PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().canonicalize().unwrap();
+        std::fs::write(dir.join("README.md"), "# Hello\n").unwrap();
+        std::fs::write(dir.join(".secret"), "hidden").unwrap();
+        (temp_dir, dir)
+    }
+
+    #[tokio::test]
+    async fn test_webdav_requires_token_or_local_dev_mode() {
+        let (_temp_dir, dir) = workspace_dir();
+        let handler = workspace(&dir);
+        let propfind = |headers: &[(&'static str, &'static str)]| {
+            handler.handle(HttpRequest::named("PROPFIND", "/dav/").with_headers(headers))
+        };
+        assert_eq!(
+            propfind(&[]).await.unwrap().status,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            propfind(&[("authorization", "Basic cGhvbmU6d3Jvbmc=")])
+                .await
+                .unwrap()
+                .status,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            propfind(&[("authorization", "Bearer s3cret")])
+                .await
+                .unwrap()
+                .status,
+            StatusCode::MULTI_STATUS
+        );
+        assert_eq!(
+            propfind(&[AUTH]).await.unwrap().status,
+            StatusCode::MULTI_STATUS
+        );
+        // Discovery goes without
+        assert_eq!(
+            handler
+                .handle(HttpRequest::named("OPTIONS", "/dav/"))
+                .await
+                .unwrap()
+                .status,
+            StatusCode::OK
+        );
+
+        let root = [ServedRoot {
+            prefix: "docs".to_string(),
+            path: dir.clone(),
+        }];
+        let dev = dav_handler(&root, &[("dev_mode", true.into())]);
+        let put = |headers: &[(&'static str, &'static str)]| {
+            dev.handle(
+                HttpRequest::named("PUT", "/dav/README.md")
+                    .with_headers(headers)
+                    .with_body("# Owned\n"),
+            )
+        };
+        // Another site's page may not write, even in dev mode
+        let forged = put(&[
+            ("host", "localhost:3000"),
+            ("origin", "https://evil.example"),
+        ])
+        .await
+        .unwrap();
+        assert_eq!(forged.status, StatusCode::FORBIDDEN);
+        assert_eq!(
+            put(&[("host", "x1.lhr.life")]).await.unwrap().status,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("README.md")).unwrap(),
+            "# Hello\n"
+        );
+        assert_eq!(
+            put(&[("host", "localhost:3000")]).await.unwrap().status,
+            StatusCode::NO_CONTENT
+        );
+
+        // Through a tunnel the token is required despite dev mode
+        let tunneled = dav_handler(
+            &root,
+            &[("dev_mode", true.into()), ("api_token", "s3cret".into())],
+        );
+        let response = tunneled
+            .handle(HttpRequest::get("/dav/README.md").with_header("host", "x1.lhr.life"))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_list_and_save_over_webdav() {
+        let (_temp_dir, dir) = workspace_dir();
+        let handler = workspace(&dir);
+        assert!(handler.can_handle("/dav/README.md", &Method::from_bytes(b"PROPFIND").unwrap()));
+        assert!(!handler.can_handle("/david", &Method::GET));
+
+        let listing = call(&handler, "PROPFIND", "/dav/", &[("depth", "1")], "").await;
+        assert_eq!(listing.status, StatusCode::MULTI_STATUS);
+        let xml = String::from_utf8_lossy(&listing.body);
+        assert!(xml.contains("<D:href>/dav/README.md</D:href>"));
+        assert!(xml.contains("<D:getcontentlength>8</D:getcontentlength>"));
+        assert!(!xml.contains(".secret"));
+
+        let saved = call(&handler, "PUT", "/dav/New%20note.md", &[], "# New\n").await;
+        assert_eq!(saved.status, StatusCode::CREATED);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("New note.md")).unwrap(),
+            "# New\n"
+        );
+        let file = call(&handler, "GET", "/dav/New%20note.md", &[], "").await;
+        assert_eq!(&file.body[..], b"# New\n");
+        assert_eq!(
+            file.headers.get("content-type").unwrap(),
+            "text/markdown; charset=utf-8"
+        );
+
+        // Uploads must be what their name or declared type says
+        let fake = call(&handler, "PUT", "/dav/logo.png", &[], "<svg/>").await;
+        assert_eq!(fake.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(!dir.join("logo.png").exists());
+        let declared = [("content-type", "image/gif")];
+        let mislabeled = call(&handler, "PUT", "/dav/notes.txt", &declared, "GIF89a").await;
+        assert_eq!(mislabeled.status, StatusCode::CREATED);
+        let declared = [("content-type", "image/png")];
+        let mislabeled = call(&handler, "PUT", "/dav/image.bin", &declared, "GIF89a").await;
+        assert_eq!(mislabeled.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(
+            handler.max_body_size(),
+            Some(uploads::DEFAULT_MAX_UPLOAD_SIZE)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_copy_and_move_over_webdav() {
+        let (_temp_dir, dir) = workspace_dir();
+        let handler = workspace(&dir);
+        assert_eq!(
+            call(&handler, "MKCOL", "/dav/notes", &[], "").await.status,
+            StatusCode::CREATED
+        );
+
+        let copied = call(
+            &handler,
+            "COPY",
+            "/dav/README.md",
+            &[("destination", "http://localhost:3000/dav/notes/copy.md")],
+            "",
+        )
+        .await;
+        assert_eq!(copied.status, StatusCode::CREATED);
+        assert!(dir.join("README.md").is_file());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("notes/copy.md")).unwrap(),
+            "# Hello\n"
+        );
+
+        std::fs::write(dir.join("notes/other.md"), "other").unwrap();
+        let kept = call(
+            &handler,
+            "MOVE",
+            "/dav/notes/copy.md",
+            &[("destination", "/dav/notes/other.md"), ("overwrite", "F")],
+            "",
+        )
+        .await;
+        assert_eq!(kept.status, StatusCode::PRECONDITION_FAILED);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("notes/other.md")).unwrap(),
+            "other"
+        );
+
+        let replaced = call(
+            &handler,
+            "MOVE",
+            "/dav/notes/copy.md",
+            &[("destination", "/dav/notes/other.md")],
+            "",
+        )
+        .await;
+        assert_eq!(replaced.status, StatusCode::NO_CONTENT);
+        assert!(!dir.join("notes/copy.md").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("notes/other.md")).unwrap(),
+            "# Hello\n"
+        );
+
+        assert_eq!(
+            call(&handler, "DELETE", "/dav/notes", &[], "").await.status,
+            StatusCode::NO_CONTENT
+        );
+        assert!(!dir.join("notes").exists());
+    }
+
+    #[tokio::test]
+    async fn test_webdav_stays_inside_the_share() {
+        let (_temp_dir, dir) = workspace_dir();
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("private.md"), "private").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(outside.path(), dir.join("escape")).unwrap();
+        let handler = workspace(&dir);
+
+        for path in [
+            "/dav/.secret",
+            "/dav/../etc/passwd",
+            "/dav/%2e%2e/etc/passwd",
+            "/dav/escape/private.md",
+        ] {
+            assert_eq!(
+                call(&handler, "GET", path, &[], "").await.status,
+                StatusCode::NOT_FOUND,
+                "{}",
+                path
+            );
+        }
+        #[cfg(unix)]
+        assert_eq!(
+            call(&handler, "PUT", "/dav/escape/new.md", &[], "x")
+                .await
+                .status,
+            StatusCode::NOT_FOUND
+        );
+        assert!(!outside.path().join("new.md").exists());
+
+        // The shared directory itself cannot go
+        assert_eq!(
+            call(&handler, "DELETE", "/dav/", &[], "").await.status,
+            StatusCode::FORBIDDEN
+        );
+        let moved = call(
+            &handler,
+            "MOVE",
+            "/dav/",
+            &[("destination", "/dav/gone")],
+            "",
+        )
+        .await;
+        assert_eq!(moved.status, StatusCode::FORBIDDEN);
+        let replaced = call(
+            &handler,
+            "COPY",
+            "/dav/README.md",
+            &[("destination", "/dav/")],
+            "",
+        )
+        .await;
+        assert_eq!(replaced.status, StatusCode::FORBIDDEN);
+        let escaped = call(
+            &handler,
+            "COPY",
+            "/dav/README.md",
+            &[("destination", "/dav/../README.md")],
+            "",
+        )
+        .await;
+        assert_eq!(escaped.status, StatusCode::BAD_GATEWAY);
+        assert!(dir.join("README.md").is_file());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_links_never_lead_writes_or_copies_out() {
+        let (_temp_dir, dir) = workspace_dir();
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("private.md"), "private").unwrap();
+        std::os::unix::fs::symlink(outside.path().join("planted.md"), dir.join("dangling.md"))
+            .unwrap();
+        let handler = workspace(&dir);
+
+        // Writing through a link leading nowhere would create its target
+        for method in ["PUT", "LOCK"] {
+            assert_eq!(
+                call(&handler, method, "/dav/dangling.md", &[], "")
+                    .await
+                    .status,
+                StatusCode::NOT_FOUND,
+                "{}",
+                method
+            );
+        }
+        assert!(!outside.path().join("planted.md").exists());
+
+        std::fs::create_dir(dir.join("notes")).unwrap();
+        std::fs::write(dir.join("notes/mine.md"), "mine").unwrap();
+        std::os::unix::fs::symlink(outside.path().join("private.md"), dir.join("notes/leak.md"))
+            .unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.join("notes/elsewhere")).unwrap();
+        let listing = call(&handler, "PROPFIND", "/dav/notes", &[("depth", "1")], "").await;
+        let xml = String::from_utf8_lossy(&listing.body);
+        assert!(xml.contains("/dav/notes/mine.md"));
+        assert!(!xml.contains("leak.md") && !xml.contains("elsewhere"));
+
+        let copied = call(
+            &handler,
+            "COPY",
+            "/dav/notes",
+            &[("destination", "/dav/copied")],
+            "",
+        )
+        .await;
+        assert_eq!(copied.status, StatusCode::CREATED);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("copied/mine.md")).unwrap(),
+            "mine"
+        );
+        assert!(dir.join("copied/leak.md").symlink_metadata().is_err());
+        assert!(dir.join("copied/elsewhere").symlink_metadata().is_err());
+
+        // With `symlinks = deny` no link is followed, even within the share
+        std::os::unix::fs::symlink(dir.join("README.md"), dir.join("linked.md")).unwrap();
+        assert_eq!(
+            call(&handler, "GET", "/dav/linked.md", &[], "")
+                .await
+                .status,
+            StatusCode::OK
+        );
+        let deny = workspace(&dir).with_symlink_policy(SymlinkPolicy::Deny);
+        for method in ["GET", "PUT"] {
+            assert_eq!(
+                call(&deny, method, "/dav/linked.md", &[], "x").await.status,
+                StatusCode::NOT_FOUND,
+                "{}",
+                method
+            );
+        }
+        assert_eq!(
+            std::fs::read_to_string(dir.join("README.md")).unwrap(),
+            "# Hello\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_document_root_exposes_only_the_document() {
+        let (_temp_dir, dir) = workspace_dir();
+        std::fs::write(dir.join("diary.md"), "dear diary").unwrap();
+        let handler = dav_handler(
+            &[ServedRoot {
+                prefix: String::new(),
+                path: dir.join("README.md"),
+            }],
+            &[("api_token", "s3cret".into())],
+        );
+
+        let listing = call(&handler, "PROPFIND", "/dav/", &[("depth", "1")], "").await;
+        let xml = String::from_utf8_lossy(&listing.body);
+        assert!(xml.contains("<D:href>/dav/README.md</D:href>"));
+        assert!(!xml.contains("diary"));
+        assert_eq!(
+            call(&handler, "GET", "/dav/diary.md", &[], "").await.status,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            call(&handler, "PUT", "/dav/new.md", &[], "x").await.status,
+            StatusCode::NOT_FOUND
+        );
+
+        assert_eq!(
+            call(&handler, "PUT", "/dav/README.md", &[], "# Saved\n")
+                .await
+                .status,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("README.md")).unwrap(),
+            "# Saved\n"
+        );
+        for method in ["DELETE", "MOVE"] {
+            let response = call(
+                &handler,
+                method,
+                "/dav/README.md",
+                &[("destination", "/dav/README.md")],
+                "",
+            )
+            .await;
+            assert_eq!(response.status, StatusCode::FORBIDDEN, "{}", method);
+        }
+        assert!(dir.join("README.md").is_file());
+        assert!(dir.join("diary.md").is_file());
+    }
+}
@@ -0,0 +1,168 @@
+//! Encodings of WebSocket messages
+//!
+//! Handlers speak JSON text. A client may ask for a more compact encoding
+//! by offering WebSocket subprotocols in its handshake:
+//!
+//! - `rune.msgpack.deflate`: binary frames of raw-deflated MessagePack
+//! - `rune.msgpack`: binary frames of MessagePack
+//! - `rune.json.deflate`: binary frames of raw-deflated JSON
+//! - `rune.json`: JSON text frames, as without a subprotocol
+//!
+//! Of those offered, the server picks the first in this list and applies it
+//! to every message of the connection, both ways. Deflate is applied to each message on its
+//! own, like the `permessage-deflate` extension, which the WebSocket
+//! library does not offer; browsers read it with
+//! `DecompressionStream("deflate-raw")`.
+
+use crate::WebSocketMessage;
+use rune_core::{Result, RuneError};
+use std::io::{Read, Write};
+
+/// Encoding of a connection's messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WireFormat {
+    pub msgpack: bool,
+    pub deflate: bool,
+}
+
+/// Largest message a deflated frame may expand to
+const MAX_INFLATED_BYTES: u64 = 16 * 1024 * 1024;
+
+impl WireFormat {
+    /// Subprotocols the server accepts, most compact first
+    pub const PROTOCOLS: [&'static str; 4] = [
+        "rune.msgpack.deflate",
+        "rune.msgpack",
+        "rune.json.deflate",
+        "rune.json",
+    ];
+
+    /// Format the negotiated subprotocol names; plain JSON without one
+    pub fn from_protocol(protocol: Option<&str>) -> Option<Self> {
+        Some(match protocol {
+            None | Some("rune.json") => Self::default(),
+            Some("rune.json.deflate") => Self {
+                msgpack: false,
+                deflate: true,
+            },
+            Some("rune.msgpack") => Self {
+                msgpack: true,
+                deflate: false,
+            },
+            Some("rune.msgpack.deflate") => Self {
+                msgpack: true,
+                deflate: true,
+            },
+            Some(_) => return None,
+        })
+    }
+
+    /// Whether messages go out unchanged
+    pub fn is_plain(self) -> bool {
+        self == Self::default()
+    }
+
+    /// Message to send for the handler's `message`
+    pub fn encode(self, message: WebSocketMessage) -> Result<WebSocketMessage> {
+        let WebSocketMessage::Text(text) = message else {
+            return Ok(message);
+        };
+        if self.is_plain() {
+            return Ok(WebSocketMessage::Text(text));
+        }
+
+        let mut bytes = if self.msgpack {
+            let value: serde_json::Value = serde_json::from_str(&text)
+                .map_err(|e| RuneError::Server(format!("Message is not JSON: {}", e)))?;
+            rmp_serde::to_vec_named(&value)
+                .map_err(|e| RuneError::Server(format!("MessagePack encoding failed: {}", e)))?
+        } else {
+            text.into_bytes()
+        };
+        if self.deflate {
+            let mut encoder =
+                flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+            bytes = encoder
+                .write_all(&bytes)
+                .and_then(|()| encoder.finish())
+                .map_err(|e| RuneError::Server(format!("Deflate failed: {}", e)))?;
+        }
+        Ok(WebSocketMessage::Binary(bytes))
+    }
+
+    /// Message for the handler from what the client sent
+    pub fn decode(self, message: WebSocketMessage) -> Result<WebSocketMessage> {
+        let WebSocketMessage::Binary(mut bytes) = message else {
+            return Ok(message);
+        };
+        if self.is_plain() {
+            return Ok(WebSocketMessage::Binary(bytes));
+        }
+
+        if self.deflate {
+            let mut inflated = Vec::new();
+            flate2::read::DeflateDecoder::new(bytes.as_slice())
+                .take(MAX_INFLATED_BYTES)
+                .read_to_end(&mut inflated)
+                .map_err(|e| RuneError::Server(format!("Inflate failed: {}", e)))?;
+            bytes = inflated;
+        }
+        let text = if self.msgpack {
+            let value: serde_json::Value = rmp_serde::from_slice(&bytes)
+                .map_err(|e| RuneError::Server(format!("MessagePack decoding failed: {}", e)))?;
+            value.to_string()
+        } else {
+            String::from_utf8(bytes)
+                .map_err(|e| RuneError::Server(format!("Message is not UTF-8: {}", e)))?
+        };
+        Ok(WebSocketMessage::Text(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::ServerMessage;
+
+    #[test]
+    fn test_messages_round_trip_in_every_format() {
+        let message = ServerMessage::ContentUpdate {
+            html: "<p>Hello</p>".repeat(200),
+            css: None,
+            metadata: None,
+        };
+        let json = serde_json::to_string(&message).unwrap();
+
+        for protocol in WireFormat::PROTOCOLS {
+            let format = WireFormat::from_protocol(Some(protocol)).unwrap();
+            let encoded = format.encode(WebSocketMessage::Text(json.clone())).unwrap();
+            match &encoded {
+                WebSocketMessage::Text(text) => assert_eq!(text, &json),
+                WebSocketMessage::Binary(bytes) if format.deflate => {
+                    assert!(bytes.len() < json.len() / 10)
+                }
+                WebSocketMessage::Binary(bytes) => {
+                    let value: serde_json::Value = rmp_serde::from_slice(bytes).unwrap();
+                    assert_eq!(value["type"], "ContentUpdate");
+                }
+                other => panic!("unexpected message {:?}", other),
+            }
+
+            let decoded = format.decode(encoded).unwrap();
+            let WebSocketMessage::Text(text) = decoded else {
+                panic!("{} did not decode to text", protocol);
+            };
+            assert_eq!(
+                serde_json::from_str::<ServerMessage>(&text).unwrap(),
+                message
+            );
+        }
+
+        assert_eq!(WireFormat::from_protocol(None), Some(WireFormat::default()));
+        assert_eq!(WireFormat::from_protocol(Some("rune.cbor")), None);
+        assert!(WireFormat::from_protocol(Some("rune.json.deflate"))
+            .unwrap()
+            .decode(WebSocketMessage::Binary(b"not deflate".to_vec()))
+            .is_err());
+    }
+}
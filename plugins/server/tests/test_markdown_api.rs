@@ -1,6 +1,6 @@
 use axum::http::Method;
 use rune_server::simple_live_editor::MarkdownRenderHandler;
-use rune_server::{HttpHandler, HttpRequest, HttpResponse};
+use rune_server::{HttpHandler, HttpRequest};
 
 #[tokio::test]
 async fn test_markdown_render_api() {
@@ -16,7 +16,7 @@ async fn test_markdown_render_api() {
         path: "/api/render-markdown".to_string(),
         query_params: std::collections::HashMap::new(),
         headers: axum::http::HeaderMap::new(),
-        body: serde_json::to_vec(&request_body).unwrap(),
+        body: serde_json::to_vec(&request_body).unwrap().into(),
         path_params: std::collections::HashMap::new(),
     };
 
@@ -50,7 +50,7 @@ async fn test_markdown_render_api_invalid_method() {
         path: "/api/render-markdown".to_string(),
         query_params: std::collections::HashMap::new(),
         headers: axum::http::HeaderMap::new(),
-        body: Vec::new(),
+        body: Default::default(),
         path_params: std::collections::HashMap::new(),
     };
 
@@ -69,7 +69,7 @@ async fn test_markdown_render_api_invalid_json() {
         path: "/api/render-markdown".to_string(),
         query_params: std::collections::HashMap::new(),
         headers: axum::http::HeaderMap::new(),
-        body: b"invalid json".to_vec(),
+        body: b"invalid json".as_slice().into(),
         path_params: std::collections::HashMap::new(),
     };
 
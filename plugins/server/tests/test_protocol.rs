@@ -0,0 +1,180 @@
+//! Conformance tests for the live reload wire protocol
+//!
+//! The JSON here is what clients see on the wire; a change that breaks one
+//! of these fixtures needs a new protocol version.
+
+use rune_server::handlers::{
+    BlockRange, ElementUpdate, LiveReloadHandler, ServerMessage, UpdateType,
+};
+use rune_server::protocol::{ClientMessage, Envelope, PROTOCOL_VERSION};
+use rune_server::{WebSocketConnection, WebSocketHandler, WebSocketMessage};
+use serde_json::json;
+use tokio::sync::broadcast;
+
+fn connection() -> (WebSocketConnection, broadcast::Receiver<WebSocketMessage>) {
+    let (sender, receiver) = broadcast::channel(16);
+    let connection = WebSocketConnection {
+        id: "client".to_string(),
+        remote_addr: "127.0.0.1:9000".parse().unwrap(),
+        headers: Default::default(),
+        sender,
+    };
+    (connection, receiver)
+}
+
+fn envelope(receiver: &mut broadcast::Receiver<WebSocketMessage>) -> serde_json::Value {
+    match receiver.try_recv().unwrap() {
+        WebSocketMessage::Text(text) => serde_json::from_str(&text).unwrap(),
+        other => panic!("unexpected message {:?}", other),
+    }
+}
+
+#[test]
+fn test_server_messages_on_the_wire() {
+    let fixtures = [
+        (
+            ServerMessage::Welcome { version: 1 },
+            json!({"type": "Welcome", "payload": {"version": 1}}),
+        ),
+        (ServerMessage::Reload, json!({"type": "Reload"})),
+        (
+            ServerMessage::ContentUpdate {
+                html: "<p>Hi</p>".to_string(),
+                css: None,
+                metadata: None,
+            },
+            json!({"type": "ContentUpdate", "payload": {"html": "<p>Hi</p>"}}),
+        ),
+        (
+            ServerMessage::IncrementalUpdate {
+                updates: vec![ElementUpdate {
+                    selector: "#intro".to_string(),
+                    content: "<p>New</p>".to_string(),
+                    update_type: UpdateType::Replace,
+                }],
+            },
+            json!({"type": "IncrementalUpdate", "payload": {"updates": [
+                {"selector": "#intro", "content": "<p>New</p>", "update_type": "Replace"}
+            ]}}),
+        ),
+        (ServerMessage::Pong, json!({"type": "Pong"})),
+        (
+            ServerMessage::Error {
+                message: "Broken".to_string(),
+                code: None,
+            },
+            json!({"type": "Error", "payload": {"message": "Broken", "code": null}}),
+        ),
+        (
+            ServerMessage::Presence {
+                author: 2,
+                block: Some(3),
+                selection: Some(BlockRange { start: 3, end: 4 }),
+            },
+            json!({"type": "Presence", "payload": {
+                "author": 2, "block": 3, "selection": {"start": 3, "end": 4}
+            }}),
+        ),
+        (
+            ServerMessage::PresenceLeft { author: 2 },
+            json!({"type": "PresenceLeft", "payload": {"author": 2}}),
+        ),
+        (
+            ServerMessage::StyleReload {
+                href: "/theme.css".to_string(),
+            },
+            json!({"type": "StyleReload", "payload": {"href": "/theme.css"}}),
+        ),
+        (
+            ServerMessage::TemplateReload,
+            json!({"type": "TemplateReload"}),
+        ),
+        (ServerMessage::UpToDate, json!({"type": "UpToDate"})),
+        (ServerMessage::Refresh, json!({"type": "Refresh"})),
+    ];
+
+    for (message, mut expected) in fixtures {
+        expected["version"] = json!(1);
+        expected["id"] = json!(7);
+        let envelope = Envelope {
+            version: 1,
+            id: 7,
+            message,
+        };
+        assert_eq!(serde_json::to_value(&envelope).unwrap(), expected);
+        assert_eq!(
+            serde_json::from_value::<Envelope>(expected).unwrap(),
+            envelope
+        );
+    }
+}
+
+#[test]
+fn test_client_messages_on_the_wire() {
+    let parse = |value: serde_json::Value| serde_json::from_value::<ClientMessage>(value).unwrap();
+
+    assert!(matches!(
+        parse(json!({"type": "Ping"})),
+        ClientMessage::Ping
+    ));
+    assert!(matches!(
+        parse(json!({"version": 1, "id": 3, "type": "RequestRefresh"})),
+        ClientMessage::RequestRefresh
+    ));
+    assert!(matches!(
+        parse(json!({"type": "Hello", "payload": {"version": 1}})),
+        ClientMessage::Hello { version: 1 }
+    ));
+    assert!(matches!(
+        parse(json!({"type": "Resume", "payload": {"hash": "00ff"}})),
+        ClientMessage::Resume { hash } if hash == "00ff"
+    ));
+    assert!(serde_json::from_value::<ClientMessage>(json!({"type": "Hello"})).is_err());
+}
+
+#[tokio::test]
+async fn test_handshake_negotiates_the_version() {
+    let handler = LiveReloadHandler::new("/ws".to_string());
+    let (connection, mut receiver) = connection();
+    let hello = |version: u32| {
+        WebSocketMessage::Text(
+            json!({"type": "Hello", "payload": {"version": version}}).to_string(),
+        )
+    };
+
+    handler.on_connect(&connection).await.unwrap();
+    let welcome = envelope(&mut receiver);
+    assert_eq!(welcome["type"], "Welcome");
+    assert_eq!(welcome["version"], PROTOCOL_VERSION);
+    assert_eq!(welcome["payload"]["version"], PROTOCOL_VERSION);
+
+    handler
+        .on_message(&connection, hello(PROTOCOL_VERSION))
+        .await
+        .unwrap();
+    let answer = envelope(&mut receiver);
+    assert_eq!(answer["type"], "Welcome");
+    assert!(answer["id"].as_u64() > welcome["id"].as_u64());
+
+    handler
+        .on_message(
+            &connection,
+            WebSocketMessage::Text(json!({"type": "Ping"}).to_string()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(envelope(&mut receiver)["type"], "Pong");
+
+    // A client speaking another version is told so and disconnected
+    handler
+        .on_message(&connection, hello(PROTOCOL_VERSION + 1))
+        .await
+        .unwrap();
+    let refusal = envelope(&mut receiver);
+    assert_eq!(refusal["type"], "Error");
+    assert_eq!(refusal["payload"]["code"], "unsupported_version");
+    assert!(matches!(
+        receiver.try_recv().unwrap(),
+        WebSocketMessage::Close(Some(_))
+    ));
+}
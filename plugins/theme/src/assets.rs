@@ -0,0 +1,62 @@
+//! Content-hashed URLs of theme assets
+//!
+//! Theme stylesheets and scripts are linked with a hash of their content,
+//! as in `/themes/catppuccin-mocha/css?v=3f2a9c0e41d7b865`. A request for
+//! the current hash may be cached for good, since an edit changes the URL
+//! pages link; one for an older hash, or for none, has to be revalidated,
+//! so edits show up on the next load.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Query parameter carrying the content hash
+pub const VERSION_PARAM: &str = "v";
+
+/// `cache-control` of a request for the current content
+pub const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// `cache-control` of any other request
+pub const REVALIDATE: &str = "no-cache";
+
+/// Hash of `content`, as used in URLs and entity tags
+pub fn content_hash(content: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// `path` with the hash of `content` it serves
+pub fn versioned_url(path: &str, content: &[u8]) -> String {
+    format!("{}?{}={}", path, VERSION_PARAM, content_hash(content))
+}
+
+/// `cache-control` of a request for `version` of content hashing to `hash`
+pub fn cache_control(version: Option<&str>, hash: &str) -> &'static str {
+    if version == Some(hash) {
+        IMMUTABLE
+    } else {
+        REVALIDATE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edited_assets_get_new_urls() {
+        let url = versioned_url("/themes/light/css", b"body { color: #333 }");
+        let edited = versioned_url("/themes/light/css", b"body { color: #000 }");
+        assert!(url.starts_with("/themes/light/css?v="));
+        assert_eq!(url.len(), "/themes/light/css?v=".len() + 16);
+        assert_ne!(url, edited);
+        assert_eq!(
+            url,
+            versioned_url("/themes/light/css", b"body { color: #333 }")
+        );
+
+        let hash = content_hash(b"body { color: #333 }");
+        assert_eq!(cache_control(Some(&hash), &hash), IMMUTABLE);
+        assert_eq!(cache_control(Some("0123456789abcdef"), &hash), REVALIDATE);
+        assert_eq!(cache_control(None, &hash), REVALIDATE);
+    }
+}
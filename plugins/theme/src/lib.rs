@@ -1,10 +1,16 @@
 //! Theme management plugin for Rune
 
+pub mod assets;
+pub mod script;
+
 use async_trait::async_trait;
+use rune_core::i18n::Catalog;
+use rune_core::memory::{EvictionPriority, MemoryBudget, MemoryConsumer, MemoryUsage};
 use rune_core::{Plugin, PluginContext, PluginStatus, Result, RuneError};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::SystemTime;
 use tokio::sync::RwLock;
 
@@ -53,6 +59,20 @@ pub struct ThemeInfo {
     pub modified_at: SystemTime,
 }
 
+impl ThemeInfo {
+    /// Use the catalog's `theme-<name>` and `theme-<name>-description`
+    /// messages as display name and description, where it has them
+    pub fn localized(mut self, catalog: &Catalog) -> Self {
+        if let Some(display_name) = catalog.get(&format!("theme-{}", self.name), None) {
+            self.display_name = display_name;
+        }
+        if let Some(description) = catalog.get(&format!("theme-{}-description", self.name), None) {
+            self.description = description;
+        }
+        self
+    }
+}
+
 /// Complete theme definition with all assets and metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Theme {
@@ -88,6 +108,14 @@ impl Theme {
         }
     }
 
+    /// Script of the theme wrapped to run against the theme API, see
+    /// [`script`]
+    pub fn script(&self) -> Option<String> {
+        self.javascript
+            .as_deref()
+            .map(|javascript| script::wrap(&self.info.name, javascript))
+    }
+
     /// Get theme variable value
     pub fn get_variable(&self, key: &str) -> Option<&String> {
         self.variables.get(key)
@@ -115,6 +143,13 @@ impl Theme {
         self.info = info;
         self.info.modified_at = SystemTime::now();
     }
+
+    /// Bytes held by the stylesheet, script and assets
+    pub fn size(&self) -> usize {
+        self.css.len()
+            + self.javascript.as_ref().map_or(0, String::len)
+            + self.assets.values().map(Vec::len).sum::<usize>()
+    }
 }
 
 /// Theme change event for notifications
@@ -145,10 +180,12 @@ pub struct ThemeValidationResult {
 
 /// Default theme provider implementation
 pub struct DefaultThemeProvider {
-    themes: RwLock<HashMap<String, Theme>>,
+    themes: Arc<RwLock<HashMap<String, Theme>>>,
     current_theme: RwLock<Option<String>>,
     theme_change_sender: tokio::sync::broadcast::Sender<ThemeChangeEvent>,
     template_path: Option<PathBuf>,
+    /// Whether themes loaded from files keep their scripts
+    scripts: bool,
 }
 
 impl DefaultThemeProvider {
@@ -157,10 +194,11 @@ impl DefaultThemeProvider {
         let (sender, _) = tokio::sync::broadcast::channel(100);
 
         Self {
-            themes: RwLock::new(HashMap::new()),
+            themes: Arc::new(RwLock::new(HashMap::new())),
             current_theme: RwLock::new(None),
             theme_change_sender: sender,
             template_path: None,
+            scripts: true,
         }
     }
 
@@ -169,10 +207,25 @@ impl DefaultThemeProvider {
         let (sender, _) = tokio::sync::broadcast::channel(100);
 
         Self {
-            themes: RwLock::new(HashMap::new()),
+            themes: Arc::new(RwLock::new(HashMap::new())),
             current_theme: RwLock::new(None),
             theme_change_sender: sender,
             template_path: Some(template_path),
+            scripts: true,
+        }
+    }
+
+    /// Keep or drop the scripts of themes loaded from files; they are
+    /// dropped where [`script::allowed`] says so
+    pub fn with_scripts(mut self, scripts: bool) -> Self {
+        self.scripts = scripts;
+        self
+    }
+
+    /// Memory consumer accounting for the loaded themes
+    pub fn memory_consumer(&self) -> ThemeAssetMemory {
+        ThemeAssetMemory {
+            themes: self.themes.clone(),
         }
     }
 
@@ -426,8 +479,14 @@ impl ThemeProvider for DefaultThemeProvider {
             .await
             .map_err(|e| RuneError::theme(format!("Failed to read theme file: {}", e)))?;
 
-        let theme: Theme = serde_json::from_str(&content)
+        let mut theme: Theme = serde_json::from_str(&content)
             .map_err(|e| RuneError::theme(format!("Failed to parse theme file: {}", e)))?;
+        if !self.scripts && theme.javascript.take().is_some() {
+            tracing::warn!(
+                "Leaving out the script of theme {}: theme scripts are not allowed",
+                theme.info.name
+            );
+        }
 
         // Add to themes collection
         {
@@ -495,12 +554,38 @@ impl ThemeProvider for DefaultThemeProvider {
     }
 }
 
+/// Accounts loaded themes against the memory budget; previews need them,
+/// so they are never evicted
+pub struct ThemeAssetMemory {
+    themes: Arc<RwLock<HashMap<String, Theme>>>,
+}
+
+#[async_trait]
+impl MemoryConsumer for ThemeAssetMemory {
+    fn name(&self) -> &str {
+        "theme-assets"
+    }
+
+    fn priority(&self) -> EvictionPriority {
+        EvictionPriority::Pinned
+    }
+
+    async fn usage(&self) -> MemoryUsage {
+        let themes = self.themes.read().await;
+        MemoryUsage {
+            bytes: themes.values().map(Theme::size).sum(),
+            entries: themes.len(),
+        }
+    }
+}
+
 /// Theme management plugin implementation
 pub struct ThemePlugin {
     name: String,
     version: String,
     status: PluginStatus,
     theme_provider: Option<Box<dyn ThemeProvider>>,
+    memory: Option<Arc<MemoryBudget>>,
 }
 
 impl ThemePlugin {
@@ -511,6 +596,7 @@ impl ThemePlugin {
             version: "0.1.0".to_string(),
             status: PluginStatus::Loading,
             theme_provider: None,
+            memory: None,
         }
     }
 
@@ -549,11 +635,18 @@ impl Plugin for ThemePlugin {
             .get_template_path()
             .unwrap_or_else(|| PathBuf::from("template.html"));
 
-        let provider = DefaultThemeProvider::with_template_path(template_path);
+        let provider = DefaultThemeProvider::with_template_path(template_path)
+            .with_scripts(script::allowed(&context.config));
 
         // Load built-in themes
         provider.load_builtin_themes().await?;
 
+        context
+            .memory
+            .register(Arc::new(provider.memory_consumer()))
+            .await;
+        self.memory = Some(context.memory.clone());
+
         self.theme_provider = Some(Box::new(provider));
         self.status = PluginStatus::Active;
 
@@ -563,6 +656,9 @@ impl Plugin for ThemePlugin {
 
     async fn shutdown(&mut self) -> Result<()> {
         tracing::info!("Shutting down theme plugin");
+        if let Some(memory) = self.memory.take() {
+            memory.unregister("theme-assets").await;
+        }
         self.theme_provider = None;
         Ok(())
     }
@@ -0,0 +1,147 @@
+//! Scripts of themes and the API they run against
+//!
+//! A theme script is not pasted into the page as it is. [`wrap`] puts it in
+//! a function that gets a `rune` object and shadows the page globals, so
+//! scripts written for the API keep to it:
+//!
+//! - `rune.name`: name of the theme the script belongs to
+//! - `rune.theme`: name of the theme the page shows
+//! - `rune.onThemeChange(callback)`: calls `callback(name)` whenever the
+//!   reader switches themes
+//! - `rune.area(name)`: element of one of the [`AREAS`] of the page, the
+//!   only parts a theme may change, or `null`
+//! - `rune.setVariable(name, value)`: sets a `--` CSS custom property of the
+//!   page
+//!
+//! Shadowing is no security boundary: a script can still reach the page
+//! through prototypes. What keeps scripts out is [`allowed`]: theme scripts
+//! only run in trusted workspaces and while the `theme_scripts` setting is
+//! on, which locked-down deployments turn off.
+
+use rune_core::{trust, Config};
+
+/// Parts of the page a theme may change, matched by their
+/// `data-theme-area` attribute
+pub const AREAS: &[&str] = &["toolbar", "content", "status"];
+
+/// Setting turning theme scripts off when `false`
+pub const SCRIPTS_SETTING: &str = "theme_scripts";
+
+/// Globals hidden from theme scripts; `eval` cannot be a parameter name
+/// of strict code, so it stays in reach
+const SHADOWED: &[&str] = &[
+    "window",
+    "self",
+    "globalThis",
+    "parent",
+    "top",
+    "frames",
+    "opener",
+    "document",
+    "navigator",
+    "location",
+    "history",
+    "localStorage",
+    "sessionStorage",
+    "indexedDB",
+    "caches",
+    "cookieStore",
+    "fetch",
+    "XMLHttpRequest",
+    "WebSocket",
+    "EventSource",
+    "Worker",
+    "SharedWorker",
+    "importScripts",
+    "Function",
+];
+
+/// Defines `RuneTheme.scope`, which builds the `rune` object of a script
+const RUNTIME: &str = r#"(function () {
+    "use strict";
+    if (window.RuneTheme) return;
+    var areas = __AREAS__;
+    var root = document.documentElement;
+    var listeners = [];
+    new MutationObserver(function () {
+        var theme = root.getAttribute("data-theme");
+        listeners.forEach(function (listener) {
+            try { listener(theme); } catch (e) { console.error("Theme script failed:", e); }
+        });
+    }).observe(root, { attributes: true, attributeFilter: ["data-theme"] });
+    window.RuneTheme = Object.freeze({
+        scope: function (name) {
+            return Object.freeze({
+                name: name,
+                get theme() { return root.getAttribute("data-theme"); },
+                onThemeChange: function (callback) {
+                    if (typeof callback === "function") listeners.push(callback);
+                },
+                area: function (area) {
+                    if (areas.indexOf(area) < 0) return null;
+                    return document.querySelector('[data-theme-area="' + area + '"]');
+                },
+                setVariable: function (variable, value) {
+                    if (!/^--[A-Za-z0-9_-]+$/.test(variable)) return;
+                    root.style.setProperty(variable, String(value));
+                }
+            });
+        }
+    });
+})();
+"#;
+
+/// Whether themes may bring scripts with `config`
+pub fn allowed(config: &Config) -> bool {
+    trust::is_trusted(config)
+        && config
+            .get_global_setting::<bool>(SCRIPTS_SETTING)
+            .unwrap_or(true)
+}
+
+/// Script defining the API that wrapped scripts use; it has to run before
+/// them, and only defines it once
+pub fn runtime() -> String {
+    let areas = serde_json::to_string(AREAS).unwrap_or_else(|_| "[]".to_string());
+    RUNTIME.replace("__AREAS__", &areas)
+}
+
+/// `script` of the theme `name`, run against the theme API
+pub fn wrap(name: &str, script: &str) -> String {
+    let name = serde_json::to_string(name).unwrap_or_else(|_| "\"\"".to_string());
+    format!(
+        "(function ({}) {{\n\"use strict\";\n{}\n}}).call(undefined, window.RuneTheme.scope({}));\n",
+        std::iter::once("rune")
+            .chain(SHADOWED.iter().copied())
+            .collect::<Vec<_>>()
+            .join(", "),
+        script,
+        name
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_scripts_run_against_the_api() {
+        let wrapped = wrap("mocha", "rune.setVariable('--accent', 'pink');");
+        assert!(wrapped.starts_with("(function (rune, window, self, globalThis,"));
+        assert!(wrapped.contains("\n\"use strict\";\nrune.setVariable('--accent', 'pink');\n"));
+        assert!(wrapped.ends_with(".call(undefined, window.RuneTheme.scope(\"mocha\"));\n"));
+        assert!(runtime().contains(r#"var areas = ["toolbar","content","status"];"#));
+
+        let mut config = Config::new();
+        assert!(allowed(&config));
+        config
+            .set_global_setting(SCRIPTS_SETTING.to_string(), false)
+            .unwrap();
+        assert!(!allowed(&config));
+        let mut config = Config::new();
+        config
+            .set_global_setting(trust::TRUSTED_SETTING.to_string(), false)
+            .unwrap();
+        assert!(!allowed(&config));
+    }
+}
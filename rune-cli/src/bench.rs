@@ -0,0 +1,301 @@
+//! `rune bench` - render a file repeatedly and report latency percentiles
+//!
+//! A hidden command for field debugging: it times the markdown renderer on
+//! its own, the full renderer pipeline, the block-by-block render used to
+//! stream large documents, and the export page render (file read included)
+//! on the user's actual document.
+
+use crate::export::{build_exporter, builtin_registry};
+use futures_util::TryStreamExt;
+use rune_core::export::ExportOptions;
+use rune_core::renderer::DEFAULT_STREAM_CHUNK_BYTES;
+use rune_core::{ContentRenderer, RenderContext, Result, RuneError};
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Arguments of the `bench` subcommand
+#[derive(Debug, Clone)]
+pub struct BenchArgs {
+    pub file: PathBuf,
+    pub iterations: usize,
+    pub warmup: usize,
+    pub json: bool,
+}
+
+/// Timings of one render stage
+#[derive(Debug, Serialize)]
+struct StageReport {
+    stage: &'static str,
+    #[serde(flatten)]
+    stats: LatencyStats,
+    /// Input bytes per second at the median latency
+    throughput: f64,
+}
+
+/// Everything `rune bench` measured
+#[derive(Debug, Serialize)]
+struct BenchReport {
+    file: PathBuf,
+    bytes: usize,
+    iterations: usize,
+    warmup: usize,
+    stages: Vec<StageReport>,
+}
+
+/// Summary of measured latencies
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub min: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl LatencyStats {
+    /// Summarize samples; `None` when there are none
+    pub fn from_samples(mut samples: Vec<Duration>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort();
+        let total: Duration = samples.iter().sum();
+        Some(Self {
+            samples: samples.len(),
+            min: samples[0],
+            mean: total / samples.len() as u32,
+            p50: percentile(&samples, 50.0),
+            p90: percentile(&samples, 90.0),
+            p99: percentile(&samples, 99.0),
+            max: samples[samples.len() - 1],
+        })
+    }
+
+    /// Bytes processed per second at the median latency
+    pub fn throughput(&self, bytes: usize) -> f64 {
+        let seconds = self.p50.as_secs_f64();
+        if seconds == 0.0 {
+            return f64::INFINITY;
+        }
+        bytes as f64 / seconds
+    }
+}
+
+impl fmt::Display for LatencyStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "n={} min={:?} mean={:?} p50={:?} p90={:?} p99={:?} max={:?}",
+            self.samples, self.min, self.mean, self.p50, self.p90, self.p99, self.max
+        )
+    }
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[Duration], percent: f64) -> Duration {
+    let rank = ((percent / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Time `iterations` runs of `run` after `warmup` untimed ones
+pub async fn measure_async<T, F, Fut>(warmup: usize, iterations: usize, mut run: F) -> LatencyStats
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = T>,
+{
+    for _ in 0..warmup {
+        std::hint::black_box(run().await);
+    }
+    let mut samples = Vec::with_capacity(iterations.max(1));
+    for _ in 0..iterations.max(1) {
+        let start = Instant::now();
+        std::hint::black_box(run().await);
+        samples.push(start.elapsed());
+    }
+    LatencyStats::from_samples(samples).expect("at least one sample")
+}
+
+impl BenchArgs {
+    /// Build bench arguments from the `bench` subcommand matches
+    pub fn from_matches(matches: &clap::ArgMatches) -> Result<Self> {
+        Ok(Self {
+            file: matches
+                .get_one::<PathBuf>("file")
+                .cloned()
+                .ok_or_else(|| RuneError::config("A markdown file is required"))?,
+            iterations: matches
+                .get_one::<usize>("iterations")
+                .copied()
+                .unwrap_or(100),
+            warmup: matches.get_one::<usize>("warmup").copied().unwrap_or(5),
+            json: matches.get_flag("json"),
+        })
+    }
+
+    /// Build the hidden `bench` subcommand definition
+    pub fn command() -> clap::Command {
+        use clap::{Arg, Command};
+
+        Command::new("bench")
+            .about("Render a file repeatedly and report latency percentiles")
+            .long_about(
+                "Render a markdown file N times through the markdown renderer, the full \
+                renderer pipeline, the streamed block-by-block render and the export \
+                page template, then report min, mean, p50, p90, p99 and max latencies \
+                plus throughput for each stage. Meant for diagnosing slow previews on a \
+                specific document.",
+            )
+            .hide(true)
+            .arg(
+                Arg::new("file")
+                    .help("Markdown file to render")
+                    .required(true)
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new("iterations")
+                    .short('n')
+                    .long("iterations")
+                    .help("Number of timed renders per stage")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("100"),
+            )
+            .arg(
+                Arg::new("warmup")
+                    .long("warmup")
+                    .help("Untimed renders before measuring")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("5"),
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .help("Print the report as JSON")
+                    .action(clap::ArgAction::SetTrue),
+            )
+    }
+}
+
+/// Run the `bench` subcommand
+pub async fn run_bench(args: &BenchArgs) -> Result<()> {
+    if !args.file.is_file() {
+        return Err(RuneError::config(format!(
+            "Markdown file not found: {}\n\n\
+            Example: rune bench -n 200 README.md",
+            args.file.display()
+        )));
+    }
+    let content = tokio::fs::read_to_string(&args.file).await?;
+    let base_dir = args
+        .file
+        .parent()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let context = RenderContext::new(args.file.clone(), base_dir, "catppuccin-mocha".to_string());
+
+    let markdown = rune_renderer::MarkdownRenderer::new();
+    let registry = builtin_registry().await?;
+    let exporter = build_exporter().await?;
+    let options = ExportOptions::default();
+
+    // Surface render errors once instead of timing failures
+    registry.render_with_pipeline(&content, &context).await?;
+    exporter.render_page(&args.file, &options).await?;
+
+    let stages = [
+        (
+            "markdown",
+            measure_async(args.warmup, args.iterations, || {
+                markdown.render(&content, &context)
+            })
+            .await,
+        ),
+        (
+            "pipeline",
+            measure_async(args.warmup, args.iterations, || {
+                registry.render_with_pipeline(&content, &context)
+            })
+            .await,
+        ),
+        (
+            "stream",
+            measure_async(args.warmup, args.iterations, || {
+                registry
+                    .clone()
+                    .render_stream(content.clone(), context.clone(), DEFAULT_STREAM_CHUNK_BYTES)
+                    .try_collect::<Vec<String>>()
+            })
+            .await,
+        ),
+        (
+            "page",
+            measure_async(args.warmup, args.iterations, || {
+                exporter.render_page(&args.file, &options)
+            })
+            .await,
+        ),
+    ];
+
+    let report = BenchReport {
+        file: args.file.clone(),
+        bytes: content.len(),
+        iterations: args.iterations,
+        warmup: args.warmup,
+        stages: stages
+            .into_iter()
+            .map(|(stage, stats)| StageReport {
+                stage,
+                throughput: stats.throughput(content.len()),
+                stats,
+            })
+            .collect(),
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+fn print_report(report: &BenchReport) {
+    println!(
+        "⏱️  {} ({} bytes) · {} iterations after {} warm-up",
+        report.file.display(),
+        report.bytes,
+        report.iterations,
+        report.warmup
+    );
+    for stage in &report.stages {
+        println!("   {:<9} {}", stage.stage, stage.stats);
+        println!(
+            "   {:<9} {:.1} MB/s at p50",
+            "",
+            stage.throughput / 1_000_000.0
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_stats_percentiles() {
+        let samples = (1..=100).rev().map(Duration::from_millis).collect();
+        let stats = LatencyStats::from_samples(samples).unwrap();
+        assert_eq!(stats.min, Duration::from_millis(1));
+        assert_eq!(stats.p50, Duration::from_millis(50));
+        assert_eq!(stats.p90, Duration::from_millis(90));
+        assert_eq!(stats.p99, Duration::from_millis(99));
+        assert_eq!(stats.max, Duration::from_millis(100));
+        assert_eq!(stats.mean, Duration::from_micros(50_500));
+        assert!(LatencyStats::from_samples(Vec::new()).is_none());
+    }
+}
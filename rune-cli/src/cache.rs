@@ -0,0 +1,148 @@
+//! `rune cache` - report and clear the data plugins keep on disk
+//!
+//! Plugin caches, indexes and history stores live in the `.rune/` directory
+//! of the workspace in the current directory and in the user data
+//! directory. `rune cache list` reports how much each plugin keeps in either,
+//! `rune cache clear [plugin]` removes it; clearing every plugin also empties
+//! the workspace's `.rune/cache/`.
+
+use rune_core::rune_dir::{RuneDir, RUNE_DIR};
+use rune_core::{plugin_data, Result, RuneError};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// What `rune cache` does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheArgs {
+    /// Report the space taken by each plugin
+    List { json: bool },
+    /// Remove the data of one plugin, or of all of them
+    Clear { plugin: Option<String> },
+}
+
+/// Plugin data found under one root
+#[derive(Debug, Serialize)]
+struct RootReport {
+    root: PathBuf,
+    plugins: Vec<plugin_data::PluginDataUsage>,
+}
+
+impl CacheArgs {
+    /// Build cache arguments from the `cache` subcommand matches
+    pub fn from_matches(matches: &clap::ArgMatches) -> Result<Self> {
+        match matches.subcommand() {
+            Some(("list", list)) => Ok(Self::List {
+                json: list.get_flag("json"),
+            }),
+            Some(("clear", clear)) => Ok(Self::Clear {
+                plugin: clear.get_one::<String>("plugin").cloned(),
+            }),
+            _ => Err(RuneError::config(
+                "Expected `rune cache list` or `rune cache clear`",
+            )),
+        }
+    }
+
+    /// Build the `cache` subcommand definition
+    pub fn command() -> clap::Command {
+        use clap::{Arg, Command};
+
+        Command::new("cache")
+            .about("Report and clear plugin caches")
+            .long_about(
+                "Plugins keep caches, indexes and history stores in .rune/plugin-data \
+                of the workspace in the current directory and in the user data directory. \
+                Clearing every plugin also empties the workspace's .rune/cache.",
+            )
+            .subcommand_required(true)
+            .arg_required_else_help(true)
+            .subcommand(
+                Command::new("list")
+                    .about("Show the space each plugin's data takes")
+                    .arg(
+                        Arg::new("json")
+                            .long("json")
+                            .help("Print the report as JSON")
+                            .action(clap::ArgAction::SetTrue),
+                    ),
+            )
+            .subcommand(
+                Command::new("clear")
+                    .about("Remove plugin data")
+                    .arg(Arg::new("plugin").help("Only clear the data of this plugin")),
+            )
+    }
+}
+
+/// Roots plugin data is kept under: the workspace in the current directory,
+/// then the user data directory
+fn roots() -> Vec<PathBuf> {
+    let workspace = std::env::current_dir()
+        .map(|dir| dir.join(RUNE_DIR))
+        .ok()
+        .filter(|dir| dir.is_dir());
+    workspace
+        .into_iter()
+        .chain(plugin_data::user_root())
+        .collect()
+}
+
+/// Run `rune cache`
+pub fn run_cache(args: &CacheArgs) -> Result<()> {
+    match args {
+        CacheArgs::List { json } => {
+            let reports = roots()
+                .into_iter()
+                .map(|root| {
+                    Ok(RootReport {
+                        plugins: plugin_data::usage(&root)?,
+                        root,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&reports)?);
+                return Ok(());
+            }
+            for report in &reports {
+                println!(
+                    "{}",
+                    report.root.join(plugin_data::PLUGIN_DATA_DIR).display()
+                );
+                if report.plugins.is_empty() {
+                    println!("  (empty)");
+                }
+                for usage in &report.plugins {
+                    println!(
+                        "  {:<20} {:>10}  {} files",
+                        usage.plugin,
+                        format_bytes(usage.bytes),
+                        usage.files
+                    );
+                }
+            }
+            Ok(())
+        }
+        CacheArgs::Clear { plugin } => {
+            let mut freed = 0;
+            for root in roots() {
+                freed += plugin_data::clear(&root, plugin.as_deref())?;
+            }
+            if plugin.is_none() {
+                if let Ok(dir) = std::env::current_dir() {
+                    freed += RuneDir::of(&dir).clear_cache()?;
+                }
+            }
+            println!("Freed {}", format_bytes(freed));
+            Ok(())
+        }
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1048575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0)),
+    }
+}
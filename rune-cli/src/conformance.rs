@@ -0,0 +1,250 @@
+//! `rune conformance` - run CommonMark and GFM spec examples
+//!
+//! Without arguments the specs bundled with the renderer are run against
+//! their baselines. Given `spec.txt` files, such as the complete
+//! CommonMark and GFM specs, each is run against the baseline next to it,
+//! `spec.baseline.json` for `spec.txt`, which `--bless` writes.
+
+use rune_core::{Result, RuneError};
+use rune_renderer::conformance::{self, Baseline, Changes, Report, BUNDLED};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Arguments of the `conformance` subcommand
+#[derive(Debug, Clone)]
+pub struct ConformanceArgs {
+    pub specs: Vec<PathBuf>,
+    pub bless: bool,
+    pub verbose: bool,
+    pub json: bool,
+}
+
+/// Outcome of one spec against its baseline
+#[derive(Debug, Serialize)]
+struct SpecReport {
+    spec: String,
+    examples: usize,
+    passed: usize,
+    /// Differences from the baseline; none when there is no baseline
+    changes: Option<Changes>,
+    #[serde(skip)]
+    report: Report,
+}
+
+impl ConformanceArgs {
+    /// Build conformance arguments from the `conformance` subcommand matches
+    pub fn from_matches(matches: &clap::ArgMatches) -> Result<Self> {
+        let specs: Vec<PathBuf> = matches
+            .get_many::<PathBuf>("specs")
+            .map(|specs| specs.cloned().collect())
+            .unwrap_or_default();
+        let bless = matches.get_flag("bless");
+        if bless && specs.is_empty() {
+            return Err(RuneError::config(
+                "Give the spec files whose baselines to bless\n\n\
+                Example: rune conformance plugins/renderer/conformance/gfm-spec.txt --bless",
+            ));
+        }
+        Ok(Self {
+            specs,
+            bless,
+            verbose: matches.get_flag("verbose"),
+            json: matches.get_flag("json"),
+        })
+    }
+
+    /// Build the `conformance` subcommand definition
+    pub fn command() -> clap::Command {
+        use clap::{Arg, Command};
+
+        Command::new("conformance")
+            .about("Run CommonMark and GFM spec examples against the renderer")
+            .long_about(
+                "Render the examples of CommonMark and GFM spec files and compare the \
+                HTML with the spec's. The examples allowed to fail are recorded in a baseline \
+                next to each spec (spec.baseline.json for spec.txt); examples that pass \
+                or fail differently from the baseline are reported and make the command \
+                fail, so changes in rendering are noticed. Without spec files, the \
+                CommonMark and GFM specs bundled with the renderer are run.",
+            )
+            .arg(
+                Arg::new("specs")
+                    .help("spec.txt files, e.g. the CommonMark and GFM specs")
+                    .num_args(0..)
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new("bless")
+                    .long("bless")
+                    .help("Record the current results as the specs' baselines")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("verbose")
+                    .short('v')
+                    .long("verbose")
+                    .help("Show the markdown and HTML of failing examples")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .help("Print the report as JSON")
+                    .action(clap::ArgAction::SetTrue),
+            )
+    }
+}
+
+/// Run the specs, returning whether every one matched its baseline
+pub async fn run_conformance(args: &ConformanceArgs) -> Result<bool> {
+    let mut reports = Vec::new();
+    if args.specs.is_empty() {
+        for suite in BUNDLED {
+            let baseline = Baseline::from_json(suite.baseline)?;
+            reports.push(check(suite.name, suite.spec, Some(baseline)));
+        }
+    }
+    for spec in &args.specs {
+        let text = tokio::fs::read_to_string(spec)
+            .await
+            .map_err(|e| RuneError::config(format!("Failed to read {}: {}", spec.display(), e)))?;
+        let baseline_path = baseline_path(spec);
+        let baseline = match tokio::fs::read_to_string(&baseline_path).await {
+            Ok(json) => Some(Baseline::from_json(&json)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let name = spec
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| spec.display().to_string());
+        let report = check(&name, &text, baseline);
+        if args.bless {
+            tokio::fs::write(&baseline_path, Baseline::of(&report.report).to_json()?).await?;
+        }
+        reports.push(report);
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        for report in &reports {
+            print_report(report, args.verbose);
+        }
+        if args.bless {
+            println!("Baselines blessed");
+        }
+    }
+
+    Ok(args.bless
+        || reports.iter().all(|report| {
+            report
+                .changes
+                .as_ref()
+                .is_some_and(|changes| changes.is_empty())
+        }))
+}
+
+/// Baseline of the spec at `spec`: `gfm.baseline.json` for `gfm.txt`
+fn baseline_path(spec: &Path) -> PathBuf {
+    let stem = spec
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    spec.with_file_name(format!("{}.baseline.json", stem))
+}
+
+fn check(name: &str, spec: &str, baseline: Option<Baseline>) -> SpecReport {
+    let examples = conformance::parse_spec(spec);
+    let report = conformance::run(name, &examples);
+    let changes = baseline.map(|baseline| baseline.changes(&report));
+    SpecReport {
+        spec: name.to_string(),
+        examples: examples.len(),
+        passed: report.passing().len(),
+        changes,
+        report,
+    }
+}
+
+fn print_report(spec: &SpecReport, verbose: bool) {
+    let rate = if spec.examples == 0 {
+        0.0
+    } else {
+        spec.passed as f64 * 100.0 / spec.examples as f64
+    };
+    println!(
+        "{}: {}/{} examples pass ({:.1}%)",
+        spec.spec, spec.passed, spec.examples, rate
+    );
+    match &spec.changes {
+        None => println!("  no baseline yet, run with --bless to record one"),
+        Some(changes) if changes.is_empty() => println!("  matches the baseline"),
+        Some(changes) => {
+            for (label, numbers) in [("regressed", &changes.regressed), ("fixed", &changes.fixed)] {
+                for number in numbers.iter() {
+                    let section = spec
+                        .report
+                        .result(*number)
+                        .map(|result| result.section.as_str())
+                        .unwrap_or_default();
+                    println!("  {} example {} ({})", label, number, section);
+                }
+            }
+        }
+    }
+
+    if verbose {
+        for result in spec.report.results.iter().filter(|result| !result.passed) {
+            println!(
+                "\n  example {} ({}) fails\n  markdown:\n{}  expected:\n{}  actual:\n{}",
+                result.number,
+                result.section,
+                indent(&result.markdown),
+                indent(&result.expected),
+                indent(&result.actual)
+            );
+        }
+    }
+}
+
+fn indent(text: &str) -> String {
+    text.lines().map(|line| format!("    {}\n", line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bless_records_a_baseline_next_to_the_spec() {
+        let dir = tempfile::tempdir().unwrap();
+        let spec = dir.path().join("spec.txt");
+        let fence = "`".repeat(32);
+        std::fs::write(
+            &spec,
+            format!("## Emphasis\n\n{fence} example\n*a*\n.\n<p><em>a</em></p>\n{fence}\n"),
+        )
+        .unwrap();
+        let args = ConformanceArgs {
+            specs: vec![spec.clone()],
+            bless: false,
+            verbose: false,
+            json: true,
+        };
+
+        // Without a baseline there is nothing to match
+        assert!(!run_conformance(&args).await.unwrap());
+        let blessed = ConformanceArgs {
+            bless: true,
+            ..args.clone()
+        };
+        assert!(run_conformance(&blessed).await.unwrap());
+        let baseline = std::fs::read_to_string(dir.path().join("spec.baseline.json")).unwrap();
+        assert_eq!(
+            Baseline::from_json(&baseline).unwrap().failing,
+            Default::default()
+        );
+        assert!(run_conformance(&args).await.unwrap());
+    }
+}
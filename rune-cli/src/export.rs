@@ -0,0 +1,609 @@
+//! `rune export` - render markdown files to standalone output files
+
+use async_trait::async_trait;
+use rune_core::drafts::{self, PublishState};
+use rune_core::event::{SystemEvent, SystemEventHandler};
+use rune_core::export::watch::{BuildOutcome, BuildStatus, ExportSession};
+use rune_core::export::{ExportFormat, ExportOptions, ExportReport, Exporter, PageBuilder};
+use rune_core::glossary::{self, Glossary};
+use rune_core::pandoc;
+use rune_core::process;
+use rune_core::tags::{self, TagPages, TaggedDocument};
+use rune_core::{
+    Config, DefaultFileFilter, FileWatcher, FileWatcherConfig, InMemoryEventBus, Plugin,
+    PluginContext, RendererRegistry, Result, RuneError, StateManager,
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Arguments of the `export` subcommand
+#[derive(Debug, Clone)]
+pub struct ExportArgs {
+    pub inputs: Vec<PathBuf>,
+    pub format: ExportFormat,
+    pub self_contained: bool,
+    pub output: Option<PathBuf>,
+    pub config_file: Option<PathBuf>,
+    pub no_hooks: bool,
+    pub optimize_assets: bool,
+    pub watch: bool,
+    /// Export drafts and documents scheduled for later too
+    pub drafts: bool,
+    /// Export encrypted documents, writing their content out in plaintext
+    pub allow_decrypt: bool,
+    /// Trust the workspace of the inputs
+    pub trust: bool,
+}
+
+impl ExportArgs {
+    /// Build export arguments from the `export` subcommand matches
+    pub fn from_matches(matches: &clap::ArgMatches) -> Result<Self> {
+        let format = matches
+            .get_one::<String>("format")
+            .map(String::as_str)
+            .unwrap_or("html")
+            .parse()?;
+
+        Ok(Self {
+            inputs: matches
+                .get_many::<PathBuf>("input")
+                .map(|inputs| inputs.cloned().collect())
+                .unwrap_or_default(),
+            format,
+            self_contained: matches.get_flag("self-contained"),
+            output: matches.get_one::<PathBuf>("output").cloned(),
+            config_file: matches.get_one::<PathBuf>("config").cloned(),
+            no_hooks: matches.get_flag("no-hooks"),
+            optimize_assets: matches.get_flag("optimize-assets"),
+            watch: matches.get_flag("watch"),
+            drafts: matches.get_flag("drafts"),
+            allow_decrypt: matches.get_flag("allow-decrypt"),
+            trust: matches.get_flag("trust"),
+        })
+    }
+
+    /// Build the `export` subcommand definition
+    pub fn command() -> clap::Command {
+        use clap::{Arg, Command};
+
+        Command::new("export")
+            .about("Export markdown files to standalone documents")
+            .long_about(
+                "Render a markdown file through the same pipeline as the live preview and \
+                write the result to a file. With --self-contained, stylesheets, fonts, \
+                images and the Mermaid runtime are inlined so the output opens offline.\n\n\
+                DOCX and EPUB are built from the document structure and always embed \
+                local images; EPUB books get one chapter per top-level section.\n\n\
+                LaTeX, org, reStructuredText, MediaWiki, ODT and the other formats \
+                pandoc writes are converted by pandoc from the markdown source; they \
+                need pandoc on the PATH, or at the path in RUNE_PANDOC.\n\n\
+                With --optimize-assets, local images are stripped of metadata, scaled \
+                down and converted to WebP or AVIF when that makes them smaller, using \
+                the encoders and limits of the config file's export_assets section.\n\n\
+                With --watch, rune keeps running and re-exports a document whenever it \
+                or a file embedded into its output changes.\n\n\
+                Terms defined in a glossary.md of the workspace get their definitions \
+                as tooltips, and HTML exports write a glossary.html index of them.\n\n\
+                HTML exports of several tagged documents, by a frontmatter 'tags' \
+                list or #tag tokens, write a tags/ directory with a page per tag, \
+                unless they are self-contained.\n\n\
+                Documents with 'draft: true' in their frontmatter are skipped unless \
+                --drafts is given.\n\n\
+                Encrypted .md.age documents are refused unless --allow-decrypt is given, \
+                as their export is written in plaintext.\n\n\
+                Export hooks only run in trusted workspaces; see --trust.",
+            )
+            .arg(
+                Arg::new("input")
+                    .help("Markdown files to export (.md, .markdown or .md.age)")
+                    .required(true)
+                    .num_args(1..)
+                    .index(1)
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new("format")
+                    .short('f')
+                    .long("format")
+                    .help("Output format (formats past epub need pandoc)")
+                    .default_value("html")
+                    .value_parser(clap::builder::PossibleValuesParser::new(
+                        ["html", "docx", "epub"]
+                            .into_iter()
+                            .chain(pandoc::OUTPUT_FORMATS.iter().map(|output| output.format)),
+                    )),
+            )
+            .arg(
+                Arg::new("self-contained")
+                    .long("self-contained")
+                    .help("Inline CSS, fonts, images and scripts into a single file")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("output")
+                    .short('o')
+                    .long("output")
+                    .help("Output file (defaults to the input with the format's extension)")
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new("watch")
+                    .short('w')
+                    .long("watch")
+                    .help("Keep running and re-export documents when they change")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("optimize-assets")
+                    .long("optimize-assets")
+                    .help("Strip, scale down and convert images (HTML only)")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("drafts")
+                    .long("drafts")
+                    .help("Also export drafts and documents scheduled for later (publish_at is UTC unless it gives an offset)")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("allow-decrypt")
+                    .long("allow-decrypt")
+                    .help("Export encrypted .md.age documents, writing them out in plaintext")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("no-hooks")
+                    .long("no-hooks")
+                    .help("Skip the pre/post export hooks from the configuration")
+                    .action(clap::ArgAction::SetTrue),
+            )
+    }
+
+    /// The configuration file given with --config, or the defaults, with
+    /// the workspace of the first input applied; its process settings apply
+    /// to the programs the export runs
+    fn config(&self) -> Result<Config> {
+        let mut config = match &self.config_file {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+        if let Some(input) = self.inputs.first() {
+            crate::trust::apply(&mut config, &crate::trust::workspace_of(input), self.trust)?;
+        }
+        process::configure(&config.processes);
+        Ok(config)
+    }
+
+    /// Convert to core export options, taking hooks and asset settings from
+    /// the configuration file
+    pub fn options(&self) -> Result<ExportOptions> {
+        let config = self.config()?;
+
+        Ok(ExportOptions {
+            format: self.format,
+            self_contained: self.self_contained,
+            output: self.output.clone(),
+            hooks: if self.no_hooks {
+                Default::default()
+            } else {
+                config.export_hooks
+            },
+            assets: self.optimize_assets.then_some(config.export_assets),
+            ..Default::default()
+        })
+    }
+}
+
+/// Create a renderer registry holding the built-in markdown and Mermaid renderers
+pub async fn builtin_registry() -> Result<Arc<RendererRegistry>> {
+    let registry = Arc::new(RendererRegistry::new());
+    registry
+        .register_renderer(Box::new(rune_renderer::MarkdownRenderer::new()))
+        .await?;
+    registry
+        .register_renderer(Box::new(rune_renderer::MermaidRenderer::new()))
+        .await?;
+    Ok(registry)
+}
+
+/// Create an exporter wired to the built-in renderers and preview page template
+pub async fn build_exporter() -> Result<Exporter> {
+    let registry = builtin_registry().await?;
+
+    let page_builder: PageBuilder = Arc::new(|title: &str, html: &str| {
+        let mut body = format!("<div id=\"content\">\n{}\n</div>", html);
+        if rune_server::handlers::has_mermaid(html) {
+            body.push_str(&rune_server::handlers::mermaid_scripts("/mermaid.min.js"));
+        }
+        rune_server::handlers::standalone_page(title, &body)
+    });
+
+    Ok(Exporter::new(registry)
+        .with_page_builder(page_builder)
+        .with_builtin_asset(
+            "/mermaid.min.js",
+            "application/javascript",
+            rune_server::handlers::mermaid_js().into_owned(),
+        ))
+}
+
+/// Run the `export` subcommand
+pub async fn run_export(args: &ExportArgs) -> Result<()> {
+    for input in &args.inputs {
+        if !input.is_file() {
+            return Err(RuneError::config(format!(
+                "Markdown file not found: {}\n\n\
+                Example: rune export --self-contained README.md",
+                input.display()
+            )));
+        }
+    }
+    if args.output.is_some() && args.inputs.len() > 1 {
+        return Err(RuneError::config(
+            "--output can only be used when exporting a single file",
+        ));
+    }
+
+    // Encrypted documents would be written out in plaintext
+    let encrypted: Vec<&PathBuf> = args
+        .inputs
+        .iter()
+        .filter(|input| rune_core::crypto::is_encrypted(input))
+        .collect();
+    if !encrypted.is_empty() {
+        if !args.allow_decrypt {
+            return Err(RuneError::config(format!(
+                "{} is encrypted and its export would not be. \
+                Pass --allow-decrypt to export it anyway",
+                encrypted[0].display()
+            )));
+        }
+        let config = args.config()?;
+        for input in encrypted {
+            crate::unlock::unlock_document(input, &config).await?;
+        }
+    }
+
+    // Drafts and documents scheduled for later stay unpublished unless asked for
+    let inputs: Vec<PathBuf> = args
+        .inputs
+        .iter()
+        .filter(|input| {
+            let state = if args.drafts {
+                PublishState::Published
+            } else {
+                PublishState::of_file(input)
+            };
+            match state {
+                PublishState::Published => return true,
+                PublishState::Draft => println!(
+                    "⏭  Skipped draft {} (pass --drafts to export it)",
+                    input.display()
+                ),
+                PublishState::Scheduled(time) => println!(
+                    "⏭  Skipped {}, scheduled for {} (pass --drafts to export it)",
+                    input.display(),
+                    drafts::format_time(time)
+                ),
+            }
+            false
+        })
+        .cloned()
+        .collect();
+    if inputs.is_empty() {
+        return Ok(());
+    }
+
+    let options = args.options()?;
+    if args.watch {
+        return run_watch(&inputs, options).await;
+    }
+
+    let exporter = build_exporter().await?;
+    let mut output_dirs = Vec::new();
+    let mut outputs = Vec::new();
+    for input in &inputs {
+        let report = exporter.export(input, &options).await?;
+        print_report(args, input, &report);
+        output_dirs.push(
+            report
+                .output_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+        );
+        outputs.push(report.output_path);
+    }
+
+    // Configured redirects become stub pages of the exported site
+    let redirects = args.config()?.redirects;
+    if args.format == ExportFormat::Html && !redirects.is_empty() {
+        let site_dir = common_dir(&output_dirs);
+        let written = rune_core::redirects::write_redirect_stubs(&site_dir, &redirects).await?;
+        println!(
+            "↪  {} redirect stub(s) written below {}",
+            written,
+            site_dir.display()
+        );
+    }
+
+    // The terms explained in the exported documents get an index page
+    if args.format == ExportFormat::Html {
+        let mut terms = Glossary::default();
+        for input in &inputs {
+            let markdown = rune_core::crypto::read_document(input)?;
+            if glossary::enabled(&markdown) {
+                terms.merge(Glossary::for_document(input, &markdown));
+            }
+        }
+        if !terms.is_empty() {
+            let index = common_dir(&output_dirs).join(glossary::INDEX_PAGE);
+            let page = rune_server::handlers::standalone_page("Glossary", &terms.index_html());
+            tokio::fs::write(&index, page).await?;
+            println!(
+                "📖 Glossary of {} term(s) written to {}",
+                terms.len(),
+                index.display()
+            );
+        }
+    }
+
+    // Tagged documents get a page per tag, linking to their exports; a
+    // single or self-contained document is meant to stand on its own
+    if args.format == ExportFormat::Html && inputs.len() > 1 && !args.self_contained {
+        let site_dir = common_dir(&output_dirs);
+        let mut pages = TagPages::default();
+        for (input, output) in inputs.iter().zip(&outputs) {
+            let markdown = rune_core::crypto::read_document(input)?;
+            let relative = output.strip_prefix(&site_dir).unwrap_or(output);
+            pages.add(
+                &tags::parse(&markdown),
+                TaggedDocument {
+                    title: tags::document_title(input, &markdown),
+                    url: format!("../{}", relative.to_string_lossy().replace('\\', "/")),
+                },
+            );
+        }
+        if !pages.is_empty() {
+            let tag_dir = site_dir.join(tags::TAG_DIR);
+            tokio::fs::create_dir_all(&tag_dir).await?;
+            let index = rune_server::handlers::standalone_page(
+                "Tags",
+                &pages.index_html(|tag| format!("{}.html", tags::slug(tag))),
+            );
+            tokio::fs::write(tag_dir.join("index.html"), index).await?;
+            for (tag, documents) in pages.iter() {
+                let page = rune_server::handlers::standalone_page(
+                    &format!("#{}", tag),
+                    &TagPages::page_html(tag, documents, "index.html"),
+                );
+                tokio::fs::write(tag_dir.join(format!("{}.html", tags::slug(tag))), page).await?;
+            }
+            println!(
+                "🏷  Pages of {} tag(s) written to {}",
+                pages.len(),
+                tag_dir.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Deepest directory containing all of `dirs`, `.` for relative paths
+/// without one
+fn common_dir(dirs: &[PathBuf]) -> PathBuf {
+    let mut common = dirs.first().cloned().unwrap_or_default();
+    for dir in &dirs[1.min(dirs.len())..] {
+        while !dir.starts_with(&common) {
+            if !common.pop() {
+                break;
+            }
+        }
+    }
+    if common.as_os_str().is_empty() {
+        common.push(".");
+    }
+    common
+}
+
+fn print_report(args: &ExportArgs, input: &Path, report: &ExportReport) {
+    println!(
+        "📦 Exported {} → {}",
+        input.display(),
+        report.output_path.display()
+    );
+    println!(
+        "   {} bytes · render {:?} · post-process {:?}{}",
+        report.bytes_written,
+        report.render_time,
+        report.post_process_time,
+        if args.self_contained && args.format == ExportFormat::Html {
+            " · self-contained"
+        } else {
+            ""
+        }
+    );
+    if report.assets.optimized > 0 {
+        println!(
+            "   {} image(s) optimized · {} bytes saved",
+            report.assets.optimized, report.assets.bytes_saved
+        );
+    }
+    if report.redirect_stubs > 0 {
+        println!("   {} redirect stub(s) for aliases", report.redirect_stubs);
+    }
+    if report.hooks_run > 0 {
+        println!("   {} hook(s) · {:?}", report.hooks_run, report.hook_time);
+    }
+}
+
+/// Forwards file change events from the file watcher plugin to the watch loop
+struct ChangeForwarder {
+    sender: mpsc::UnboundedSender<PathBuf>,
+}
+
+#[async_trait]
+impl SystemEventHandler for ChangeForwarder {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        if let SystemEvent::FileChanged { path, .. } = event {
+            let _ = self.sender.send(path.clone());
+        }
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "export-watch"
+    }
+}
+
+/// Export once, then keep the file watcher running and re-export changed documents
+async fn run_watch(inputs: &[PathBuf], options: ExportOptions) -> Result<()> {
+    let state_manager = Arc::new(StateManager::new());
+    let exporter = Arc::new(
+        build_exporter()
+            .await?
+            .with_render_cache(state_manager.clone()),
+    );
+    let mut session = ExportSession::new(exporter, options, inputs)?;
+
+    let build_start = Instant::now();
+    let outcomes = session.build_all().await;
+    print_outcomes(&outcomes, build_start.elapsed());
+
+    let event_bus = Arc::new(InMemoryEventBus::new());
+    let context = PluginContext::new(event_bus.clone(), Arc::new(Config::new()), state_manager);
+    let (sender, mut changes) = mpsc::unbounded_channel();
+    context
+        .event_bus
+        .subscribe_system_events(Arc::new(ChangeForwarder { sender }))
+        .await?;
+
+    let mut watcher = rune_file_watcher::FileWatcherPlugin::new();
+    watcher.initialize(&context).await?;
+    let filter = Arc::new(DefaultFileFilter::new(FileWatcherConfig::default()));
+    let mut watched_dirs = Vec::new();
+    watch_new_dirs(&mut watcher, &session, &mut watched_dirs, &filter).await?;
+
+    println!(
+        "👀 Watching {} document(s) for changes (Ctrl+C to stop)",
+        session.documents().len()
+    );
+
+    loop {
+        tokio::select! {
+            Some(path) = changes.recv() => {
+                // Collect the rest of a burst of saves into one rebuild
+                let mut changed = vec![path];
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                while let Ok(path) = changes.try_recv() {
+                    changed.push(path);
+                }
+
+                let rebuild_start = Instant::now();
+                let outcomes = session.rebuild_changed(&changed).await;
+                if !outcomes.is_empty() {
+                    print_outcomes(&outcomes, rebuild_start.elapsed());
+                    // Newly referenced images may live in directories not watched yet
+                    watch_new_dirs(&mut watcher, &session, &mut watched_dirs, &filter).await?;
+                }
+            }
+            _ = tokio::signal::ctrl_c() => {
+                println!("\n👋 Stopped watching");
+                break;
+            }
+        }
+    }
+
+    watcher.shutdown().await
+}
+
+async fn watch_new_dirs(
+    watcher: &mut rune_file_watcher::FileWatcherPlugin,
+    session: &ExportSession,
+    watched_dirs: &mut Vec<PathBuf>,
+    filter: &Arc<DefaultFileFilter>,
+) -> Result<()> {
+    for dir in session.watch_dirs() {
+        if !watched_dirs.contains(&dir) {
+            watcher.watch(&dir, filter.clone()).await?;
+            watched_dirs.push(dir);
+        }
+    }
+    Ok(())
+}
+
+fn print_outcomes(outcomes: &[BuildOutcome], total: Duration) {
+    let mut exported = 0;
+    let mut failed = 0;
+
+    for outcome in outcomes {
+        let name = outcome
+            .input
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| outcome.input.display().to_string());
+        match &outcome.status {
+            BuildStatus::Exported(report) => {
+                exported += 1;
+                println!(
+                    "📦 {} → {} in {:?} (render {:?} · post-process {:?})",
+                    name,
+                    report.output_path.display(),
+                    outcome.elapsed,
+                    report.render_time,
+                    report.post_process_time
+                );
+            }
+            BuildStatus::Unchanged => println!("⏭️  {} unchanged", name),
+            BuildStatus::Failed(e) => {
+                failed += 1;
+                eprintln!("❌ {}: {}", name, e);
+            }
+        }
+    }
+
+    if exported + failed > 0 {
+        println!("✅ {} exported, {} failed in {:?}", exported, failed, total);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_self_contained_export_writes_one_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("notes.md");
+        std::fs::write(
+            &input,
+            "---\ntags: [rune]\n---\n# Notes\n\nAbout #export.\n",
+        )
+        .unwrap();
+        let args = ExportArgs {
+            inputs: vec![input.clone()],
+            format: ExportFormat::Html,
+            self_contained: true,
+            output: None,
+            config_file: None,
+            no_hooks: true,
+            optimize_assets: false,
+            watch: false,
+            drafts: false,
+            allow_decrypt: false,
+            trust: false,
+        };
+        run_export(&args).await.unwrap();
+
+        let mut written: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .filter(|name| *name != "notes.md")
+            .collect();
+        written.sort();
+        assert_eq!(written, ["notes.html"]);
+    }
+}
@@ -0,0 +1,143 @@
+//! `rune fuzz-corpus` - replay a fuzzing corpus through a fuzz target
+//!
+//! A hidden command for checking a fix: every file of a corpus or crash
+//! directory, as cargo-fuzz leaves them under `fuzz/`, is fed to the named
+//! target of `rune_renderer::fuzz` and the inputs that still panic are
+//! reported.
+
+use rune_core::{Result, RuneError};
+use rune_renderer::fuzz;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// Arguments of the `fuzz-corpus` subcommand
+#[derive(Debug, Clone)]
+pub struct FuzzCorpusArgs {
+    pub target: String,
+    pub dir: PathBuf,
+}
+
+impl FuzzCorpusArgs {
+    /// Build fuzz-corpus arguments from the `fuzz-corpus` subcommand matches
+    pub fn from_matches(matches: &clap::ArgMatches) -> Result<Self> {
+        Ok(Self {
+            target: matches
+                .get_one::<String>("target")
+                .cloned()
+                .ok_or_else(|| RuneError::config("A fuzz target is required"))?,
+            dir: matches
+                .get_one::<PathBuf>("dir")
+                .cloned()
+                .ok_or_else(|| RuneError::config("A corpus directory is required"))?,
+        })
+    }
+
+    /// Build the hidden `fuzz-corpus` subcommand definition
+    pub fn command() -> clap::Command {
+        use clap::{Arg, Command};
+
+        let targets: Vec<&str> = fuzz::TARGETS.iter().map(|(name, _)| *name).collect();
+        Command::new("fuzz-corpus")
+            .about("Replay a fuzzing corpus through a fuzz target")
+            .long_about(
+                "Feed every file in a directory to one of the fuzz targets (parser, \
+                frontmatter or render) and report the inputs that panic. Meant for \
+                checking that a fix holds against the corpus and crashes cargo-fuzz \
+                collected.",
+            )
+            .hide(true)
+            .arg(
+                Arg::new("target")
+                    .help("Fuzz target to replay through")
+                    .required(true)
+                    .value_parser(targets),
+            )
+            .arg(
+                Arg::new("dir")
+                    .help("Directory of inputs, e.g. fuzz/corpus/render")
+                    .required(true)
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+    }
+}
+
+/// Replay the corpus, returning whether no input panicked
+pub fn run_fuzz_corpus(args: &FuzzCorpusArgs) -> Result<bool> {
+    let target = fuzz::target(&args.target)
+        .ok_or_else(|| RuneError::config(format!("Unknown fuzz target: {}", args.target)))?;
+    if !args.dir.is_dir() {
+        return Err(RuneError::config(format!(
+            "Corpus directory not found: {}\n\n\
+            Example: rune fuzz-corpus render fuzz/corpus/render",
+            args.dir.display()
+        )));
+    }
+    let inputs = corpus_files(&args.dir)?;
+
+    // The crash report hook would keep a report per panicking input; the
+    // panics are reported below instead
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let panics: Vec<(PathBuf, String)> = inputs
+        .iter()
+        .filter_map(|path| {
+            let data = std::fs::read(path).ok()?;
+            panic::catch_unwind(AssertUnwindSafe(|| target(&data)))
+                .err()
+                .map(|payload| (path.clone(), panic_message(payload.as_ref())))
+        })
+        .collect();
+    panic::set_hook(hook);
+
+    for (path, message) in &panics {
+        println!("PANIC {}: {}", path.display(), message);
+    }
+    println!(
+        "{} inputs replayed through {}, {} panicked",
+        inputs.len(),
+        args.target,
+        panics.len()
+    );
+    Ok(panics.is_empty())
+}
+
+/// Files of the corpus, in a stable order; cargo-fuzz keeps them flat
+fn corpus_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replays_every_file_of_the_corpus() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a"), "# Title\n").unwrap();
+        std::fs::write(dir.path().join("b"), b"---\ntitle: \xff\n---\n").unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+
+        assert_eq!(corpus_files(dir.path()).unwrap().len(), 2);
+        let args = FuzzCorpusArgs {
+            target: "render".to_string(),
+            dir: dir.path().to_path_buf(),
+        };
+        assert!(run_fuzz_corpus(&args).unwrap());
+    }
+}
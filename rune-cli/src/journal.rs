@@ -0,0 +1,90 @@
+//! `rune journal` - open the note of a day
+//!
+//! The note is created from the configured template when it does not exist
+//! yet, see [`rune_core::journal`]. The journal directory is then served
+//! like `rune serve <dir>`, so the links between adjacent days work, and the
+//! note is opened in the browser. With `--print` the note's path is printed
+//! instead, for opening it in another editor.
+
+use rune_core::journal::Journal;
+use rune_core::{Config, Result};
+use std::path::PathBuf;
+
+/// Arguments of the `journal` subcommand
+#[derive(Debug, Clone)]
+pub struct JournalArgs {
+    /// `today`, `yesterday`, `tomorrow` or a date like `2024-05-01`
+    pub date: String,
+    pub print: bool,
+    pub no_open: bool,
+}
+
+/// The day's note, and the directory to serve it from
+#[derive(Debug, Clone)]
+pub struct JournalNote {
+    pub dir: PathBuf,
+    pub path: PathBuf,
+}
+
+impl JournalArgs {
+    /// Build journal arguments from the `journal` subcommand matches
+    pub fn from_matches(matches: &clap::ArgMatches) -> Result<Self> {
+        Ok(Self {
+            date: matches
+                .get_one::<String>("date")
+                .cloned()
+                .unwrap_or_else(|| "today".to_string()),
+            print: matches.get_flag("print"),
+            no_open: matches.get_flag("no-open"),
+        })
+    }
+
+    /// Build the `journal` subcommand definition, without the serve arguments
+    pub fn command() -> clap::Command {
+        use clap::{Arg, Command};
+
+        Command::new("journal")
+            .about("Open or create the daily note and serve the journal")
+            .long_about(
+                "Open the note of a day, creating it from the configured template if \
+                it does not exist yet, then serve the journal directory and open the \
+                note in the browser. Notes link to the previous and next day's. The \
+                'journal' section of the configuration sets the directory ('dir'), \
+                the date pattern of note paths ('path', journal/%Y/%Y-%m-%d.md by \
+                default), the 'template' and the 'utc_offset' deciding when a day \
+                starts.",
+            )
+            .arg(
+                Arg::new("date")
+                    .help("Day of the note: today (default), yesterday, tomorrow or YYYY-MM-DD")
+                    .index(1)
+                    .value_parser(clap::value_parser!(String)),
+            )
+            .arg(
+                Arg::new("print")
+                    .long("print")
+                    .help("Print the note's path instead of serving it")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("no-open")
+                    .long("no-open")
+                    .help("Do not open the note in the browser")
+                    .action(clap::ArgAction::SetTrue),
+            )
+    }
+}
+
+/// Open, and if need be create, the note the arguments name
+pub fn open_note(args: &JournalArgs, config: &Config) -> Result<JournalNote> {
+    let journal = Journal::from_config(&config.journal)?;
+    let date = journal.date(&args.date)?;
+    let (path, created) = journal.open(date)?;
+    if created && !args.print {
+        println!("📝 Created {}", path.display());
+    }
+    Ok(JournalNote {
+        dir: journal.dir().to_path_buf(),
+        path,
+    })
+}
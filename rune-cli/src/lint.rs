@@ -0,0 +1,193 @@
+//! `rune lint` - check documents and themes for accessibility issues
+//!
+//! Each file is rendered through the built-in pipeline with the
+//! accessibility checker as its last stage, and the issues it put in the
+//! render metadata are reported. The colours of the built-in themes, or of
+//! the one given with `--theme`, are checked for contrast.
+
+use crate::export::builtin_registry;
+use rune_core::{
+    Config, InMemoryEventBus, Plugin, PluginContext, RenderContext, Result, RuneError, StateManager,
+};
+use rune_renderer::{check_theme_contrast, A11yChecker, A11yIssue};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Arguments of the `lint` subcommand
+#[derive(Debug, Clone)]
+pub struct LintArgs {
+    pub files: Vec<PathBuf>,
+    /// Run the accessibility checks; they are also run when no check is chosen
+    pub a11y: bool,
+    pub theme: Option<String>,
+    pub json: bool,
+}
+
+/// Issues found in one document or theme
+#[derive(Debug, Serialize)]
+struct LintReport {
+    target: String,
+    issues: Vec<A11yIssue>,
+}
+
+impl LintArgs {
+    /// Build lint arguments from the `lint` subcommand matches
+    pub fn from_matches(matches: &clap::ArgMatches) -> Result<Self> {
+        let files: Vec<PathBuf> = matches
+            .get_many::<PathBuf>("files")
+            .map(|files| files.cloned().collect())
+            .unwrap_or_default();
+        if files.is_empty() {
+            return Err(RuneError::config("At least one markdown file is required"));
+        }
+        Ok(Self {
+            files,
+            a11y: matches.get_flag("a11y"),
+            theme: matches.get_one::<String>("theme").cloned(),
+            json: matches.get_flag("json"),
+        })
+    }
+
+    /// Build the `lint` subcommand definition
+    pub fn command() -> clap::Command {
+        use clap::{Arg, Command};
+
+        Command::new("lint")
+            .about("Check documents and themes for accessibility issues")
+            .long_about(
+                "Render markdown files and report images without alt text, skipped \
+                heading levels and links without text, plus theme colours with a \
+                contrast below 4.5:1. Exits with status 1 when issues are found.",
+            )
+            .arg(
+                Arg::new("files")
+                    .help("Markdown files to check")
+                    .required(true)
+                    .num_args(1..)
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new("a11y")
+                    .long("a11y")
+                    .help("Run the accessibility checks")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("theme")
+                    .long("theme")
+                    .value_name("NAME")
+                    .help("Check the contrast of this theme only"),
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .help("Print the issues as JSON")
+                    .action(clap::ArgAction::SetTrue),
+            )
+    }
+}
+
+/// Run the `lint` subcommand, returning whether no issues were found
+pub async fn run_lint(args: &LintArgs) -> Result<bool> {
+    let registry = builtin_registry().await?;
+    registry
+        .register_renderer(Box::new(A11yChecker::new()?))
+        .await?;
+
+    let mut reports = Vec::new();
+    for file in &args.files {
+        if !file.is_file() {
+            return Err(RuneError::config(format!(
+                "Markdown file not found: {}\n\n\
+                Example: rune lint --a11y README.md docs/guide.md",
+                file.display()
+            )));
+        }
+        let content = tokio::fs::read_to_string(file).await?;
+        let base_dir = file
+            .parent()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("."));
+        let context = RenderContext::new(file.clone(), base_dir, "catppuccin-mocha".to_string());
+
+        let result = registry.render_with_pipeline(&content, &context).await?;
+        let issues = match result.metadata.custom_metadata.get("a11y-checker_issues") {
+            Some(issues) => serde_json::from_value(issues.clone())?,
+            None => Vec::new(),
+        };
+        reports.push(LintReport {
+            target: file.display().to_string(),
+            issues,
+        });
+    }
+
+    for (name, css) in theme_styles(args.theme.as_deref()).await? {
+        reports.push(LintReport {
+            target: format!("theme {}", name),
+            issues: check_theme_contrast(&name, &css),
+        });
+    }
+
+    let issue_count: usize = reports.iter().map(|report| report.issues.len()).sum();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        print_reports(&reports, issue_count);
+    }
+    Ok(issue_count == 0)
+}
+
+/// CSS of the built-in themes, or of `only`, by name
+async fn theme_styles(only: Option<&str>) -> Result<Vec<(String, String)>> {
+    let context = PluginContext::new(
+        Arc::new(InMemoryEventBus::new()),
+        Arc::new(Config::new()),
+        Arc::new(StateManager::new()),
+    );
+    let mut plugin = rune_theme::ThemePlugin::new();
+    plugin.initialize(&context).await?;
+    let provider = plugin
+        .theme_provider()
+        .ok_or_else(|| RuneError::plugin("Theme provider is not available"))?;
+
+    let mut names: Vec<String> = match only {
+        Some(name) => vec![name.to_string()],
+        None => provider
+            .available_themes()
+            .await?
+            .into_iter()
+            .map(|theme| theme.name)
+            .collect(),
+    };
+    names.sort();
+
+    let mut styles = Vec::with_capacity(names.len());
+    for name in names {
+        let theme = provider.load_theme(&name).await?;
+        styles.push((name, theme.css));
+    }
+    Ok(styles)
+}
+
+fn print_reports(reports: &[LintReport], issue_count: usize) {
+    for report in reports.iter().filter(|report| !report.issues.is_empty()) {
+        println!("{}", report.target);
+        for issue in &report.issues {
+            println!("   {:<16} {}", issue.rule.as_str(), issue.message);
+            println!("   {:<16} {}", "", issue.context);
+        }
+    }
+    if issue_count == 0 {
+        println!("✅ No accessibility issues in {} target(s)", reports.len());
+    } else {
+        println!(
+            "⚠️  {} accessibility issue(s) in {} target(s)",
+            issue_count,
+            reports
+                .iter()
+                .filter(|report| !report.issues.is_empty())
+                .count()
+        );
+    }
+}
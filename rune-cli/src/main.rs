@@ -1,10 +1,45 @@
 //! Rune CLI - Command line interface for the Rune markdown live editor
 
-use clap::{Arg, Command};
-use rune_core::{Config, CoreEngine, Result, RuneError};
+mod bench;
+mod cache;
+mod conformance;
+mod export;
+mod fuzz_corpus;
+mod journal;
+mod lint;
+mod new;
+mod stats;
+mod trust;
+mod tui;
+mod unlock;
+
+use clap::{parser::ValueSource, Arg, Command};
+use rune_core::crash;
+use rune_core::logging::{self, LogController, LogOutput};
+use rune_core::{
+    i18n, t, Config, ConfigLoadContext, CoreEngine, Result, RuneError, RuntimeConfigManager,
+};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use tracing::{debug, error, info, warn, Level};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, error, info, level_filters::LevelFilter, warn};
+
+/// Prints the public URL whenever the tunnel (re)opens
+struct TunnelAnnouncer;
+
+#[async_trait::async_trait]
+impl rune_core::event::SystemEventHandler for TunnelAnnouncer {
+    async fn handle_system_event(&self, event: &rune_core::event::SystemEvent) -> Result<()> {
+        if let rune_core::event::SystemEvent::TunnelOpened { public_url, .. } = event {
+            println!("🌐 Public URL: {}", public_url);
+        }
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "tunnel-announcer"
+    }
+}
 
 /// Discovered plugin information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,223 +60,586 @@ pub enum PluginType {
     Unknown,
 }
 
+/// Command selected on the command line
+#[derive(Debug, Clone)]
+pub enum CliCommand {
+    /// Serve a markdown file with live preview (`rune serve`, or plain `rune <file>`)
+    Serve,
+    /// Export markdown files (`rune export`)
+    Export(export::ExportArgs),
+    /// Time renders of a file (hidden `rune bench`)
+    Bench(bench::BenchArgs),
+    /// Replay a fuzzing corpus (hidden `rune fuzz-corpus`)
+    FuzzCorpus(fuzz_corpus::FuzzCorpusArgs),
+    /// Run spec examples against the renderer (`rune conformance`)
+    Conformance(conformance::ConformanceArgs),
+    /// Check documents and themes for accessibility issues (`rune lint`)
+    Lint(lint::LintArgs),
+    /// List available plugins (`rune plugins list`)
+    PluginsList,
+    /// Validate a configuration file (`rune config validate`)
+    ConfigValidate,
+    /// List available themes (`rune theme list`)
+    ThemeList,
+    /// Report or clear plugin data (`rune cache`)
+    Cache(cache::CacheArgs),
+    /// Show, export or delete local usage statistics (`rune stats`)
+    Stats(stats::StatsArgs),
+    /// Terminal interface for a file or directory (`rune tui`)
+    Tui,
+    /// Open the day's note and serve the journal (`rune journal`)
+    Journal(journal::JournalArgs),
+    /// Create a document from a template and serve it (`rune new`)
+    New(new::NewArgs),
+}
+
 /// CLI arguments structure
 #[derive(Debug, Clone)]
 pub struct Args {
+    pub command: CliCommand,
     pub file: PathBuf,
+    /// Every path given to `rune serve`; `file` is the first of them
+    pub files: Vec<PathBuf>,
     pub hostname: String,
     pub port: u16,
+    /// Whether `--hostname` and `--port` were given rather than defaulted
+    pub hostname_given: bool,
+    pub port_given: bool,
     pub config_file: Option<PathBuf>,
+    /// Profile of the configuration file to apply
+    pub profile: Option<String>,
     pub plugins_dir: Option<PathBuf>,
     pub dev_mode: bool,
-    pub list_plugins: bool,
-    pub validate_config: bool,
+    pub discoverable: bool,
+    /// Serve documents marked draft even where the config hides them
+    pub drafts: bool,
+    /// Expose the served directories over WebDAV
+    pub webdav: bool,
+    /// Provider of a public URL tunneled to the server
+    pub tunnel: Option<String>,
+    /// Forbid outbound network fetches
+    pub offline: bool,
+    /// Locale of messages and the web interface
+    pub lang: Option<String>,
+    /// Trust the workspace of the opened files
+    pub trust: bool,
 }
 
 impl Args {
     /// Parse command line arguments
     pub fn parse() -> Self {
-        let matches = Command::new("rune")
+        let matches = Self::command().get_matches();
+        Self::from_matches(&matches).unwrap_or_else(|e| {
+            eprintln!("{}\n{}", t!("cli-invalid-arguments"), e);
+            std::process::exit(2);
+        })
+    }
+
+    /// Build the argument definition
+    ///
+    /// `rune <file>` is kept as a shorthand for `rune serve <file>`, so the
+    /// serve arguments are accepted both at the top level and by `serve`.
+    pub fn command() -> Command {
+        Command::new("rune")
             .version("0.1.0")
             .author("Rune Team")
             .about("Modular markdown live editor with plugin support")
             .long_about(
                 "Rune is a modular markdown live editor that provides real-time preview \
                 capabilities with a plugin-based architecture. It serves markdown files \
-                through a web interface with live reload functionality."
+                through a web interface with live reload functionality.",
             )
+            .args(Self::global_args())
+            .args(Self::serve_args(false))
             .arg(
-                Arg::new("file")
-                    .help("Markdown file to serve (.md or .markdown)")
-                    .long_help(
-                        "Path to the markdown file to serve. The file must exist and have \
-                        a .md or .markdown extension. The web interface will display the \
-                        rendered content and automatically update when the file changes. \
-                        Not required for utility commands like --list-plugins or --validate-config."
-                    )
-                    .required_unless_present_any(["list-plugins", "validate-config"])
-                    .index(1)
-                    .value_parser(clap::value_parser!(PathBuf)),
+                Arg::new("list-plugins")
+                    .long("list-plugins")
+                    .help("Deprecated alias for 'rune plugins list'")
+                    .hide(true)
+                    .action(clap::ArgAction::SetTrue),
             )
             .arg(
-                Arg::new("hostname")
-                    .short('H')
-                    .long("hostname")
-                    .help("Hostname or IP address to bind the server to")
-                    .long_help(
-                        "The hostname or IP address where the server will listen for connections. \
-                        Use '0.0.0.0' to bind to all available interfaces, or '127.0.0.1' for \
-                        localhost only (default)."
-                    )
-                    .default_value("127.0.0.1")
-                    .value_parser(clap::value_parser!(String)),
+                Arg::new("validate-config")
+                    .long("validate-config")
+                    .help("Deprecated alias for 'rune config validate'")
+                    .hide(true)
+                    .action(clap::ArgAction::SetTrue),
             )
-            .arg(
-                Arg::new("port")
-                    .short('p')
-                    .long("port")
-                    .help("Port number to bind the server to (1-65535)")
-                    .long_help(
-                        "The port number where the server will listen for HTTP connections. \
-                        Must be between 1 and 65535. If the port is already in use, the \
-                        application will display an error and suggest alternatives."
-                    )
-                    .default_value("3000")
-                    .value_parser(clap::value_parser!(u16)),
+            .subcommand(
+                Command::new("serve")
+                    .about("Serve markdown files with live preview (default)")
+                    .args(Self::serve_args(true)),
             )
-            .arg(
-                Arg::new("config")
-                    .short('c')
-                    .long("config")
-                    .help("Path to configuration file (JSON format)")
-                    .long_help(
-                        "Path to a JSON configuration file that contains plugin settings, \
-                        server options, and other system configuration. CLI arguments will \
-                        override settings from the configuration file."
+            .subcommand(export::ExportArgs::command())
+            .subcommand(bench::BenchArgs::command())
+            .subcommand(fuzz_corpus::FuzzCorpusArgs::command())
+            .subcommand(lint::LintArgs::command())
+            .subcommand(conformance::ConformanceArgs::command())
+            .subcommand(
+                Command::new("tui")
+                    .about("Preview with an interactive terminal interface")
+                    .long_about(
+                        "Serve a markdown file, or every markdown file in a directory, and \
+                        follow it from the terminal: files and their render status, recent \
+                        events and watcher errors. Switch files, force a reload, cycle \
+                        themes or open the browser from the keyboard.",
                     )
-                    .value_parser(clap::value_parser!(PathBuf)),
+                    .args(Self::serve_args(true))
+                    .mut_arg("file", |file| {
+                        file.num_args(1)
+                            .help("Markdown file or directory of markdown files to preview")
+                    }),
             )
-            .arg(
-                Arg::new("plugins-dir")
-                    .long("plugins-dir")
-                    .help("Directory containing plugin files")
-                    .long_help(
-                        "Path to a directory containing plugin files. Plugins in this directory \
-                        will be automatically discovered and loaded based on the configuration. \
-                        If not specified, the system will look for plugins in default locations."
-                    )
-                    .value_parser(clap::value_parser!(PathBuf)),
+            .subcommand(
+                journal::JournalArgs::command().args(
+                    Self::serve_args(true)
+                        .into_iter()
+                        .filter(|arg| arg.get_id() != "file"),
+                ),
             )
-            .arg(
-                Arg::new("dev-mode")
-                    .long("dev-mode")
-                    .help("Enable development mode with enhanced logging and debugging")
-                    .long_help(
-                        "Enable development mode which provides enhanced logging, plugin \
-                        hot-reloading, and additional debugging information. This mode is \
-                        useful for plugin development and troubleshooting."
-                    )
-                    .action(clap::ArgAction::SetTrue),
+            .subcommand(
+                new::NewArgs::command().args(
+                    Self::serve_args(true)
+                        .into_iter()
+                        .filter(|arg| arg.get_id() != "file"),
+                ),
             )
-            .arg(
-                Arg::new("list-plugins")
-                    .long("list-plugins")
-                    .help("List available plugins and exit")
-                    .long_help(
-                        "Display information about available plugins including their status, \
-                        version, and dependencies, then exit without starting the server."
-                    )
-                    .action(clap::ArgAction::SetTrue),
+            .subcommand(
+                Command::new("plugins")
+                    .about("Inspect plugins")
+                    .subcommand_required(true)
+                    .arg_required_else_help(true)
+                    .subcommand(
+                        Command::new("list")
+                            .about("List available plugins")
+                            .long_about(
+                                "Display information about available plugins including their \
+                                status, version, and dependencies.",
+                            ),
+                    ),
             )
-            .arg(
-                Arg::new("validate-config")
-                    .long("validate-config")
-                    .help("Validate configuration file and exit")
-                    .long_help(
-                        "Validate the configuration file syntax and plugin dependencies, \
-                        then exit without starting the server. Useful for testing \
-                        configuration changes."
-                    )
-                    .action(clap::ArgAction::SetTrue),
+            .subcommand(
+                Command::new("config")
+                    .about("Work with configuration files")
+                    .subcommand_required(true)
+                    .arg_required_else_help(true)
+                    .subcommand(
+                        Command::new("validate")
+                            .about("Validate a configuration file")
+                            .long_about(
+                                "Validate the configuration file given with --config, checking \
+                                its syntax and plugin dependencies. Useful for testing \
+                                configuration changes.",
+                            ),
+                    ),
             )
+            .subcommand(
+                Command::new("theme")
+                    .about("Inspect themes")
+                    .subcommand_required(true)
+                    .arg_required_else_help(true)
+                    .subcommand(Command::new("list").about("List available themes")),
+            )
+            .subcommand(cache::CacheArgs::command())
+            .subcommand(stats::StatsArgs::command())
+            .subcommand_negates_reqs(true)
             .after_help(
                 "EXAMPLES:\n    \
                 rune README.md                           Start server with default settings\n    \
-                rune -p 8080 -h 0.0.0.0 docs/guide.md   Bind to all interfaces on port 8080\n    \
+                rune serve -p 8080 -H 0.0.0.0 docs/guide.md  Bind to all interfaces on port 8080\n    \
                 rune --config config.json README.md     Use custom configuration file\n    \
+                rune -c config.json --profile prod README.md  Apply the config's prod profile\n    \
                 rune --dev-mode --plugins-dir ./plugins README.md  Development mode with custom plugins\n    \
-                rune --list-plugins                      Show available plugins\n    \
-                rune --validate-config --config config.json  Validate configuration\n\n\
-                For more information, visit: https://github.com/rune-rs/rune"
+                rune -H 0.0.0.0 --discoverable README.md  Advertise the preview on the LAN\n    \
+                rune --offline README.md                 Preview without touching the network\n    \
+                rune serve a.md b.md docs/               Serve several documents, each under its own path\n    \
+                rune serve --drafts docs/                Also list and serve draft documents\n    \
+                rune serve --dev-mode --webdav docs/     Edit the served files from other apps over WebDAV\n    \
+                rune serve --tunnel README.md            Share the preview at a public URL\n    \
+                rune plugins list                        Show available plugins\n    \
+                rune config validate --config config.json  Validate configuration\n    \
+                rune theme list                          Show available themes\n    \
+                rune cache clear search                  Remove the data kept by the search plugin\n    \
+                rune stats --export stats.json           Export the local usage statistics\n    \
+                rune tui docs/                           Preview a directory from the terminal\n    \
+                rune journal                             Open today's note and serve the journal\n    \
+                rune journal yesterday --print           Print the path of yesterday's note\n    \
+                rune new notes/sync.md --template meeting  Create a document from a template\n    \
+                rune export --self-contained README.md   Export a single portable HTML file\n    \
+                rune export -f epub book.md              Export an EPUB with one chapter per section\n    \
+                rune lint --a11y README.md docs/*.md     Check documents and themes for accessibility issues\n\n\
+                For more information, visit: https://github.com/rune-rs/rune",
             )
-            .get_matches();
-
-        Self {
-            file: matches
-                .get_one::<PathBuf>("file")
-                .cloned()
-                .unwrap_or_default(),
-            hostname: matches.get_one::<String>("hostname").unwrap().clone(),
-            port: *matches.get_one::<u16>("port").unwrap(),
+    }
+
+    /// Flags shared by every command
+    fn global_args() -> [Arg; 7] {
+        [
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .help("Path to configuration file (JSON format)")
+                .long_help(
+                    "Path to a JSON configuration file that contains plugin settings, \
+                    server options, export hooks and other system configuration. CLI \
+                    arguments will override settings from the configuration file.",
+                )
+                .global(true)
+                .value_parser(clap::value_parser!(PathBuf)),
+            Arg::new("profile")
+                .long("profile")
+                .value_name("NAME")
+                .help("Configuration profile to apply, e.g. dev or prod")
+                .long_help(
+                    "Name of a profile in the configuration file's 'profiles' section. \
+                    The profile's server, plugin and global settings are layered on top \
+                    of the base configuration; explicit --hostname and --port flags \
+                    still take precedence.",
+                )
+                .global(true)
+                .requires("config")
+                .value_parser(clap::value_parser!(String)),
+            Arg::new("plugins-dir")
+                .long("plugins-dir")
+                .help("Directory containing plugin files")
+                .long_help(
+                    "Path to a directory containing plugin files. Plugins in this directory \
+                    will be automatically discovered and loaded based on the configuration. \
+                    If not specified, the system will look for plugins in default locations.",
+                )
+                .global(true)
+                .value_parser(clap::value_parser!(PathBuf)),
+            Arg::new("dev-mode")
+                .long("dev-mode")
+                .help("Enable development mode with enhanced logging and debugging")
+                .long_help(
+                    "Enable development mode which provides enhanced logging, plugin \
+                    hot-reloading, and additional debugging information. This mode is \
+                    useful for plugin development and troubleshooting.",
+                )
+                .global(true)
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("offline")
+                .long("offline")
+                .help("Forbid outbound network fetches")
+                .long_help(
+                    "Never fetch anything from the network: link previews only use \
+                    their cache, video links are not embedded, and preview pages are \
+                    served with a content security policy that keeps the browser to \
+                    the bundled client assets.",
+                )
+                .global(true)
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("trust")
+                .long("trust")
+                .help("Trust the workspace of the opened files and remember it")
+                .long_help(
+                    "Trust the workspace the opened files are in, and the directories \
+                    below it, from now on. Until a workspace is trusted, rune ignores its \
+                    .rune/config.json, leaves out theme scripts and skips export hooks; \
+                    on a terminal it asks first when the workspace has a configuration \
+                    or export hooks are configured.",
+                )
+                .global(true)
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("lang")
+                .long("lang")
+                .value_name("LOCALE")
+                .help("Language of messages and the web interface, e.g. zh-CN")
+                .long_help(
+                    "Locale of command line messages, error help text and web interface \
+                    labels. Defaults to the 'lang' setting of the configuration file, \
+                    then to the LC_ALL, LC_MESSAGES and LANG environment variables. \
+                    Untranslated messages are shown in English.",
+                )
+                .global(true)
+                .value_parser(clap::value_parser!(String)),
+        ]
+    }
+
+    /// Arguments of `rune serve`, also accepted by plain `rune <file>`
+    fn serve_args(file_required: bool) -> [Arg; 7] {
+        let file = Arg::new("file")
+            .help("Markdown files or directories to serve (.md or .markdown)")
+            .long_help(
+                "Path to the markdown file to serve. The file must exist and have \
+                a .md or .markdown extension, or .md.age for a passphrase-encrypted \
+                document. The web interface will display the \
+                rendered content and automatically update when the file changes. \
+                Given several files or a directory, each is served under its own \
+                path, e.g. /a/ and /docs/, and / lists them.",
+            )
+            .index(1)
+            .num_args(1..)
+            .value_parser(clap::value_parser!(PathBuf));
+        let file = if file_required {
+            file.required(true)
+        } else {
+            file.required_unless_present_any(["list-plugins", "validate-config"])
+        };
+
+        [
+            file,
+            Arg::new("hostname")
+                .short('H')
+                .long("hostname")
+                .help("Hostname or IP address to bind the server to")
+                .long_help(
+                    "The hostname or IP address where the server will listen for connections. \
+                    Use '0.0.0.0' to bind to all available interfaces, or '127.0.0.1' for \
+                    localhost only (default).",
+                )
+                .default_value("127.0.0.1")
+                .value_parser(clap::value_parser!(String)),
+            Arg::new("port")
+                .short('p')
+                .long("port")
+                .help("Port number to bind the server to (1-65535)")
+                .long_help(
+                    "The port number where the server will listen for HTTP connections. \
+                    Must be between 1 and 65535. If the port is already in use, the \
+                    application will display an error and suggest alternatives.",
+                )
+                .default_value("3000")
+                .value_parser(clap::value_parser!(u16)),
+            Arg::new("discoverable")
+                .long("discoverable")
+                .help("Advertise the preview on the local network via mDNS")
+                .long_help(
+                    "Announce the running preview as a '_rune._tcp' mDNS service, named \
+                    after the document title, so others on the same network can find it \
+                    without knowing the address. Requires binding to a non-loopback \
+                    address, e.g. '-H 0.0.0.0'.",
+                )
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("drafts")
+                .long("drafts")
+                .help("List and serve drafts and documents scheduled for later")
+                .long_help(
+                    "Show documents with 'draft: true' in their frontmatter, or with a \
+                    'publish_at' time still ahead, in directory listings and serve them, \
+                    even when the configuration's 'drafts' setting hides them. Drafts are \
+                    always shown in dev mode. A 'publish_at' without an offset, e.g. \
+                    '2024-05-01T09:30', is UTC; one that cannot be read counts as a draft.",
+                )
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("webdav")
+                .long("webdav")
+                .help("Expose the served directories over WebDAV at /dav/")
+                .long_help(
+                    "Let external editors, e.g. on a phone, open and save the served files \
+                    over WebDAV at /dav/. Saves reload open previews. Outside dev mode \
+                    clients must give the configured 'api_token' as their password.",
+                )
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("tunnel")
+                .long("tunnel")
+                .value_name("PROVIDER")
+                .help("Share the preview at a public URL through a tunnel")
+                .long_help(
+                    "Open a tunnel giving the preview a public URL, printed once known and \
+                    shown on the /share page, so it can be opened outside the local network. \
+                    Providers: localhost.run (default), serveo and pinggy over ssh, or ngrok \
+                    with its agent installed. Choose one with e.g. '--tunnel=ngrok'.",
+                )
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value(rune_server::tunnel::DEFAULT_PROVIDER)
+                .value_parser(rune_server::tunnel::PROVIDERS),
+        ]
+    }
+
+    /// Build the arguments from parsed matches
+    pub fn from_matches(matches: &clap::ArgMatches) -> Result<Self> {
+        // Global flags may come before a subcommand, the top-level serve
+        // arguments may not
+        if let Some((name, _)) = matches.subcommand() {
+            let misplaced = Self::serve_args(false).into_iter().find(|arg| {
+                matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+            });
+            if let Some(arg) = misplaced {
+                return Err(RuneError::config(format!(
+                    "--{} cannot be given before the '{}' subcommand",
+                    arg.get_long().unwrap_or_default(),
+                    name
+                )));
+            }
+        }
+
+        let (command, serve_matches) = match matches.subcommand() {
+            Some(("serve", serve_matches)) => (CliCommand::Serve, serve_matches),
+            Some(("tui", tui_matches)) => (CliCommand::Tui, tui_matches),
+            Some(("journal", journal_matches)) => (
+                CliCommand::Journal(journal::JournalArgs::from_matches(journal_matches)?),
+                journal_matches,
+            ),
+            Some(("new", new_matches)) => (
+                CliCommand::New(new::NewArgs::from_matches(new_matches)?),
+                new_matches,
+            ),
+            Some(("export", export_matches)) => (
+                CliCommand::Export(export::ExportArgs::from_matches(export_matches)?),
+                matches,
+            ),
+            Some(("bench", bench_matches)) => (
+                CliCommand::Bench(bench::BenchArgs::from_matches(bench_matches)?),
+                matches,
+            ),
+            Some(("fuzz-corpus", fuzz_matches)) => (
+                CliCommand::FuzzCorpus(fuzz_corpus::FuzzCorpusArgs::from_matches(fuzz_matches)?),
+                matches,
+            ),
+            Some(("conformance", conformance_matches)) => (
+                CliCommand::Conformance(conformance::ConformanceArgs::from_matches(
+                    conformance_matches,
+                )?),
+                matches,
+            ),
+            Some(("lint", lint_matches)) => (
+                CliCommand::Lint(lint::LintArgs::from_matches(lint_matches)?),
+                matches,
+            ),
+            Some(("plugins", _)) => (CliCommand::PluginsList, matches),
+            Some(("config", _)) => (CliCommand::ConfigValidate, matches),
+            Some(("theme", _)) => (CliCommand::ThemeList, matches),
+            Some(("cache", cache_matches)) => (
+                CliCommand::Cache(cache::CacheArgs::from_matches(cache_matches)?),
+                matches,
+            ),
+            Some(("stats", stats_matches)) => (
+                CliCommand::Stats(stats::StatsArgs::from_matches(stats_matches)?),
+                matches,
+            ),
+            _ if matches.get_flag("list-plugins") => (CliCommand::PluginsList, matches),
+            _ if matches.get_flag("validate-config") => (CliCommand::ConfigValidate, matches),
+            _ => (CliCommand::Serve, matches),
+        };
+
+        // `rune journal` and `rune new` take no files, they serve the journal
+        // directory and the new document
+        let files: Vec<PathBuf> = serve_matches
+            .try_get_many::<PathBuf>("file")
+            .ok()
+            .flatten()
+            .map(|files| files.cloned().collect())
+            .unwrap_or_default();
+
+        Ok(Self {
+            command,
+            file: files.first().cloned().unwrap_or_default(),
+            files,
+            hostname: serve_matches.get_one::<String>("hostname").unwrap().clone(),
+            port: *serve_matches.get_one::<u16>("port").unwrap(),
+            hostname_given: serve_matches.value_source("hostname")
+                == Some(ValueSource::CommandLine),
+            port_given: serve_matches.value_source("port") == Some(ValueSource::CommandLine),
             config_file: matches.get_one::<PathBuf>("config").cloned(),
+            profile: matches.get_one::<String>("profile").cloned(),
             plugins_dir: matches.get_one::<PathBuf>("plugins-dir").cloned(),
             dev_mode: matches.get_flag("dev-mode"),
-            list_plugins: matches.get_flag("list-plugins"),
-            validate_config: matches.get_flag("validate-config"),
-        }
+            discoverable: serve_matches.get_flag("discoverable"),
+            drafts: serve_matches.get_flag("drafts"),
+            webdav: serve_matches.get_flag("webdav"),
+            tunnel: serve_matches.get_one::<String>("tunnel").cloned(),
+            offline: matches.get_flag("offline"),
+            lang: matches.get_one::<String>("lang").cloned(),
+            trust: matches.get_flag("trust"),
+        })
     }
 
-    /// Validate the arguments with detailed error messages
-    pub fn validate(&self) -> Result<()> {
-        // Skip file validation for utility commands
-        if self.list_plugins || self.validate_config {
-            return self.validate_utility_args();
-        }
+    /// Whether several roots are served, each under its own route prefix
+    pub fn serves_roots(&self) -> bool {
+        matches!(self.command, CliCommand::Serve | CliCommand::Journal(_))
+            && (self.files.len() > 1 || self.file.is_dir())
+    }
 
+    /// Check that a path is a readable markdown file, or with `pandoc_formats`
+    /// a document pandoc reads
+    fn validate_markdown_file(file: &Path, pandoc_formats: bool) -> Result<()> {
         // Check if file exists
-        if !self.file.exists() {
-            return Err(RuneError::config(format!(
-                "Markdown file not found: {}\n\n\
-                Please check that:\n\
-                • The file path is correct\n\
-                • You have read permissions for the file\n\
-                • The file hasn't been moved or deleted\n\n\
-                Example: rune README.md",
-                self.file.display()
+        if !file.exists() {
+            return Err(RuneError::config(t!(
+                "error-file-not-found",
+                path = file.display().to_string()
             )));
         }
 
         // Check if it's actually a file (not a directory)
-        if self.file.is_dir() {
-            return Err(RuneError::config(format!(
-                "Path is a directory, not a file: {}\n\n\
-                Please specify a markdown file, not a directory.\n\n\
-                Example: rune {}/README.md",
-                self.file.display(),
-                self.file.display()
+        if file.is_dir() {
+            return Err(RuneError::config(t!(
+                "error-path-is-directory",
+                path = file.display().to_string()
             )));
         }
 
         // Check if file is readable
-        match std::fs::File::open(&self.file) {
+        match std::fs::File::open(file) {
             Ok(_) => {}
             Err(e) => {
-                return Err(RuneError::config(format!(
-                    "Cannot read file: {}\n\
-                    Error: {}\n\n\
-                    Please check that you have read permissions for this file.",
-                    self.file.display(),
-                    e
+                return Err(RuneError::config(t!(
+                    "error-file-unreadable",
+                    path = file.display().to_string(),
+                    error = e.to_string()
                 )));
             }
         }
 
-        // Check if file is a markdown file
-        if let Some(extension) = self.file.extension() {
+        // Check if file is a markdown file, possibly encrypted as .md.age
+        if let Some(extension) = rune_core::crypto::plain_path(file).extension() {
             let ext_str = extension.to_string_lossy().to_lowercase();
-            if ext_str != "md" && ext_str != "markdown" {
-                return Err(RuneError::config(format!(
-                    "File must be a markdown file (.md or .markdown): {}\n\n\
-                    Current extension: .{}\n\n\
-                    Supported extensions:\n\
-                    • .md\n\
-                    • .markdown\n\n\
-                    Example: rune document.md",
-                    self.file.display(),
-                    ext_str
+            if pandoc_formats && rune_core::pandoc::input_format(file).is_some() {
+                if rune_core::pandoc::Pandoc::detect().is_none() {
+                    return Err(RuneError::config(t!(
+                        "error-needs-pandoc",
+                        path = file.display().to_string(),
+                        extension = ext_str
+                    )));
+                }
+            } else if ext_str != "md" && ext_str != "markdown" {
+                return Err(RuneError::config(t!(
+                    "error-not-markdown",
+                    path = file.display().to_string(),
+                    extension = ext_str
                 )));
             }
         } else {
-            return Err(RuneError::config(format!(
-                "File must have a markdown extension (.md or .markdown): {}\n\n\
-                Please rename your file to include a proper extension.\n\n\
-                Example: mv {} {}.md",
-                self.file.display(),
-                self.file.display(),
-                self.file.display()
+            return Err(RuneError::config(t!(
+                "error-no-extension",
+                path = file.display().to_string()
             )));
         }
 
+        Ok(())
+    }
+
+    /// Validate the arguments with detailed error messages
+    pub fn validate(&self) -> Result<()> {
+        // Skip file validation for utility commands
+        if !matches!(
+            self.command,
+            CliCommand::Serve | CliCommand::Tui | CliCommand::Journal(_) | CliCommand::New(_)
+        ) {
+            return self.validate_utility_args();
+        }
+
+        if self.serves_roots() {
+            for root in &self.files {
+                if !root.exists() {
+                    return Err(RuneError::config(t!(
+                        "error-root-not-found",
+                        path = root.display().to_string()
+                    )));
+                }
+                if !root.is_dir() {
+                    Self::validate_markdown_file(root, true)?;
+                }
+            }
+        } else {
+            // The terminal viewer only shows markdown
+            let pandoc_formats = matches!(self.command, CliCommand::Serve);
+            Self::validate_markdown_file(&self.file, pandoc_formats)?;
+        }
+
         // Validate port range
         if self.port == 0 {
             return Err(RuneError::config(
@@ -313,7 +711,7 @@ impl Args {
         Ok(())
     }
 
-    /// Validate arguments for utility commands (list-plugins, validate-config)
+    /// Validate arguments for utility commands (plugins list, config validate)
     fn validate_utility_args(&self) -> Result<()> {
         // For utility commands, we only need to validate the config file if provided
         if let Some(config_file) = &self.config_file {
@@ -321,7 +719,7 @@ impl Args {
                 return Err(RuneError::config(format!(
                     "Configuration file not found: {}\n\n\
                     Cannot validate a configuration file that doesn't exist.\n\n\
-                    Example: rune --validate-config --config config.json",
+                    Example: rune config validate --config config.json",
                     config_file.display()
                 )));
             }
@@ -348,7 +746,7 @@ impl Args {
                         • The file contains valid JSON\n\
                         • All required fields are present\n\
                         • Plugin configurations are correct\n\n\
-                        You can validate your config with: rune --validate-config --config {}",
+                        You can validate your config with: rune config validate --config {}",
                         config_file.display(),
                         e,
                         config_file.display()
@@ -360,9 +758,26 @@ impl Args {
             Config::new()
         };
 
-        // Override config with CLI arguments
-        config.server.hostname = self.hostname.clone();
-        config.server.port = self.port;
+        if let Some(profile) = &self.profile {
+            config.apply_profile(profile)?;
+            info!("Applied configuration profile: {}", profile);
+        }
+
+        // The workspace of the opened files may add its own configuration
+        if matches!(
+            self.command,
+            CliCommand::Serve | CliCommand::Tui | CliCommand::Journal(_) | CliCommand::New(_)
+        ) {
+            trust::apply(&mut config, &trust::workspace_of(&self.file), self.trust)?;
+        }
+
+        // Override config with CLI arguments; with a profile only explicit ones
+        if self.profile.is_none() || self.hostname_given {
+            config.server.hostname = self.hostname.clone();
+        }
+        if self.profile.is_none() || self.port_given {
+            config.server.port = self.port;
+        }
 
         // Add plugins directory to global settings if provided
         if let Some(plugins_dir) = &self.plugins_dir {
@@ -375,6 +790,26 @@ impl Args {
         // Set development mode
         config.set_global_setting("dev_mode".to_string(), self.dev_mode)?;
 
+        // Only enable discovery from the CLI; a config file may also turn it on
+        if self.discoverable {
+            config.set_global_setting("lan_discovery".to_string(), true)?;
+        }
+        if self.offline {
+            config.set_global_setting("offline".to_string(), true)?;
+        }
+        if self.drafts {
+            config.set_global_setting("drafts".to_string(), "show")?;
+        }
+        if self.webdav {
+            config.set_global_setting("webdav".to_string(), true)?;
+        }
+        if let Some(provider) = &self.tunnel {
+            config.set_global_setting("tunnel".to_string(), provider.clone())?;
+        }
+        if let Some(lang) = &self.lang {
+            config.set_global_setting("lang".to_string(), lang.clone())?;
+        }
+
         Ok(config)
     }
 
@@ -428,10 +863,11 @@ impl Args {
                 }
 
                 let suggestions = if available_ports.is_empty() {
-                    "Try using a different port number.".to_string()
+                    t!("error-port-try-another")
                 } else {
                     format!(
-                        "Try one of these available ports:\n{}",
+                        "{}\n{}",
+                        t!("error-port-try-these"),
                         available_ports
                             .iter()
                             .map(|p| format!(
@@ -445,15 +881,12 @@ impl Args {
                     )
                 };
 
-                Err(RuneError::config(format!(
-                    "Port {} is already in use on {}\n\
-                    Error: {}\n\n\
-                    {}\n\n\
-                    You can also:\n\
-                    • Stop the process using port {}\n\
-                    • Use 'lsof -i :{} ' to find what's using the port\n\
-                    • Choose a different port with -p <port>",
-                    self.port, self.hostname, e, suggestions, self.port, self.port
+                Err(RuneError::config(t!(
+                    "error-port-in-use",
+                    port = self.port,
+                    hostname = self.hostname.clone(),
+                    error = e.to_string(),
+                    suggestions = suggestions
                 )))
             }
         }
@@ -601,6 +1034,62 @@ async fn list_plugins(args: &Args) -> Result<()> {
     Ok(())
 }
 
+/// List available themes, marking the active one
+async fn list_themes(args: &Args) -> Result<()> {
+    use rune_core::plugin::Plugin;
+
+    println!("{}\n", t!("themes-title"));
+
+    let config = args.load_config()?;
+    let mut engine = CoreEngine::new(config)?;
+    engine.initialize().await?;
+    let context = engine.create_plugin_context();
+
+    let mut plugin = rune_theme::ThemePlugin::new();
+    plugin.initialize(&context).await?;
+    let provider = plugin
+        .theme_provider()
+        .ok_or_else(|| RuneError::plugin("Theme provider is not available"))?;
+
+    let current = provider.get_current_theme().await?;
+    let catalog = i18n::catalog();
+    let mut themes: Vec<_> = provider
+        .available_themes()
+        .await?
+        .into_iter()
+        .map(|theme| theme.localized(&catalog))
+        .collect();
+    themes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for theme in &themes {
+        let marker = if current.as_deref() == Some(theme.name.as_str()) {
+            "▶"
+        } else {
+            " "
+        };
+        println!(
+            "{} {} {:<24} {} ({})",
+            marker,
+            theme.icon.as_deref().unwrap_or("🎨"),
+            theme.name,
+            theme.display_name,
+            if theme.is_dark {
+                t!("themes-dark")
+            } else {
+                t!("themes-light")
+            }
+        );
+        if args.dev_mode && !theme.description.is_empty() {
+            println!("      {}", theme.description);
+        }
+    }
+
+    println!("\n{}", t!("themes-count", count = themes.len()));
+    plugin.shutdown().await?;
+
+    Ok(())
+}
+
 /// Validate configuration file
 async fn validate_config(args: &Args) -> Result<()> {
     if args.dev_mode {
@@ -619,7 +1108,7 @@ async fn simple_config_validation(args: &Args) -> Result<()> {
     let config_file = args.config_file.as_ref().ok_or_else(|| {
         RuneError::config(
             "No configuration file specified.\n\n\
-            Use: rune --validate-config --config <file.json>"
+            Use: rune config validate --config <file.json>"
                 .to_string(),
         )
     })?;
@@ -631,6 +1120,10 @@ async fn simple_config_validation(args: &Args) -> Result<()> {
         Ok(config) => {
             println!("✅ Configuration is valid\n");
 
+            if let Some(profile) = &args.profile {
+                println!("Profile: {}\n", profile);
+            }
+
             // Display configuration summary
             println!("Server Configuration:");
             println!("  Hostname: {}", config.server.hostname);
@@ -662,6 +1155,29 @@ async fn simple_config_validation(args: &Args) -> Result<()> {
                 }
             }
 
+            if !config.profiles.is_empty() {
+                println!("\nProfiles: {}", config.profile_names().join(", "));
+            }
+
+            // Also checks every profile as it would be applied
+            match config.validate_comprehensive() {
+                Ok(result) => {
+                    if !result.warnings.is_empty() {
+                        println!("\nWarnings:");
+                        for warning in &result.warnings {
+                            println!("  ⚠️  {}: {}", warning.field_path, warning.message);
+                            if let Some(suggestion) = &warning.suggestion {
+                                println!("      {}", suggestion);
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    println!("\n❌ Configuration validation failed\n");
+                    return Err(e);
+                }
+            }
+
             println!("\n✅ All validations passed!");
         }
         Err(e) => {
@@ -673,6 +1189,22 @@ async fn simple_config_validation(args: &Args) -> Result<()> {
     Ok(())
 }
 
+/// Register the configuration schemas of the built-in plugins
+fn register_plugin_schemas() {
+    let plugins: Vec<Box<dyn rune_core::plugin::Plugin>> = vec![
+        Box::new(rune_file_watcher::FileWatcherPlugin::new()),
+        Box::new(rune_renderer::RendererPlugin::new()),
+        Box::new(rune_server::ServerPlugin::new()),
+        Box::new(rune_theme::ThemePlugin::new()),
+    ];
+    for plugin in plugins {
+        let schema = plugin.config_schema();
+        if !schema.is_empty() {
+            rune_core::config::register_plugin_schema(plugin.name(), schema);
+        }
+    }
+}
+
 /// Scan a directory for available plugins
 fn scan_plugin_directory(dir: &PathBuf) -> Result<Vec<DiscoveredPlugin>> {
     let mut discovered = Vec::new();
@@ -845,10 +1377,17 @@ fn get_default_plugin_directories() -> Vec<PathBuf> {
     dirs
 }
 
+/// Open `url` in the default browser, logging failures
+fn open_in_browser(url: &str) {
+    if let Err(e) = open::that_detached(url) {
+        warn!("Failed to open {}: {}", url, e);
+    }
+}
+
 /// Start configuration hot-reload in development mode
 async fn start_config_hot_reload(
     config_path: PathBuf,
-    _config: std::sync::Arc<rune_core::Config>,
+    _config: Arc<rune_core::Config>,
 ) -> Result<()> {
     info!(
         "Starting configuration hot-reload for: {}",
@@ -895,7 +1434,7 @@ async fn interactive_config_validation(args: &Args) -> Result<()> {
     let config_file = args.config_file.as_ref().ok_or_else(|| {
         RuneError::config(
             "No configuration file specified.\n\n\
-            Use: rune --validate-config --config <file.json>"
+            Use: rune config validate --config <file.json>"
                 .to_string(),
         )
     })?;
@@ -1095,63 +1634,257 @@ async fn interactive_config_validation(args: &Args) -> Result<()> {
     Ok(())
 }
 
+/// Register the built-in plugins used when serving a document
+async fn register_builtin_plugins(
+    engine: &mut CoreEngine,
+    args: &Args,
+    logs: &Arc<LogController>,
+) -> Result<()> {
+    let context = engine.create_plugin_context();
+
+    // Configured levels replace the command line default; the server exposes them
+    logs.apply_config(&engine.config().log)?;
+    context
+        .set_shared_resource(
+            rune_server::logs_api::LOG_CONTROLLER_RESOURCE.to_string(),
+            logs.clone(),
+        )
+        .await?;
+
+    // Backs the server's configuration API; changes persist to the --config file
+    let config_manager = RuntimeConfigManager::from_config(
+        (*engine.config()).clone(),
+        ConfigLoadContext {
+            base_path: args.config_file.clone().unwrap_or_default(),
+            profile: args.profile.clone(),
+            ..Default::default()
+        },
+    )?;
+    context
+        .set_shared_resource(
+            rune_server::config_api::CONFIG_MANAGER_RESOURCE.to_string(),
+            tokio::sync::RwLock::new(config_manager),
+        )
+        .await?;
+
+    // Usage counts, only when the configuration opts in; the server counts
+    // the pages and APIs it serves
+    rune_core::telemetry::install(&engine.config(), &context).await?;
+
+    let plugins: Vec<Box<dyn rune_core::plugin::Plugin>> = vec![
+        Box::new(rune_file_watcher::FileWatcherPlugin::new()),
+        Box::new(rune_renderer::RendererPlugin::new()),
+        Box::new(rune_server::ServerPlugin::new()),
+        Box::new(rune_theme::ThemePlugin::new()),
+    ];
+    engine
+        .register_plugins(plugins, &context)
+        .await
+        .map_err(|e| RuneError::plugin(format!("Failed to register plugins: {}", e)))?;
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Parse command line arguments
-    let args = Args::parse();
+    let mut args = Args::parse();
+    i18n::set_locale(&i18n::detect_locale(args.lang.as_deref()));
 
     // Initialize enhanced logging for development mode
     let log_level = if args.dev_mode {
-        Level::DEBUG
+        LevelFilter::DEBUG
     } else {
-        Level::INFO
+        LevelFilter::INFO
     };
+    let log_output = if matches!(args.command, CliCommand::Tui) {
+        // Log lines would corrupt the terminal interface, which shows events itself
+        LogOutput::Silent
+    } else if matches!(
+        args.command,
+        CliCommand::Bench(_)
+            | CliCommand::FuzzCorpus(_)
+            | CliCommand::Lint(_)
+            | CliCommand::Conformance(_)
+            | CliCommand::Stats(_)
+    ) {
+        // Keep the report (possibly JSON) free of registration chatter
+        LogOutput::Silent
+    } else if args.dev_mode {
+        LogOutput::Verbose
+    } else {
+        LogOutput::Plain
+    };
+    let logs = logging::init(log_level, log_output)?;
 
-    let subscriber = tracing_subscriber::fmt()
-        .with_max_level(log_level)
-        .with_target(args.dev_mode) // Show targets in dev mode
-        .with_line_number(args.dev_mode) // Show line numbers in dev mode
-        .with_file(args.dev_mode); // Show file names in dev mode
+    // Panics are logged with backtraces and kept as crash reports
+    crash::install_panic_hook(crash::default_report_dir());
 
-    if args.dev_mode {
-        subscriber.with_ansi(true).pretty().init();
+    // Configured plugin keys are checked against the plugins' own schemas
+    register_plugin_schemas();
 
+    if log_output == LogOutput::Verbose {
         info!("🔧 Development mode enabled");
         info!("📊 Enhanced logging active");
-    } else {
-        subscriber.with_ansi(true).init();
     }
 
     // Handle utility commands first
-    if args.list_plugins {
-        return match list_plugins(&args).await {
-            Ok(()) => Ok(()),
+    match &args.command {
+        CliCommand::Serve | CliCommand::Journal(_) | CliCommand::New(_) => {}
+        CliCommand::Export(export_args) => {
+            return match export::run_export(export_args).await {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    eprintln!("{}\n{}", t!("cli-export-failed"), e);
+                    std::process::exit(1);
+                }
+            };
+        }
+        CliCommand::Bench(bench_args) => {
+            return match bench::run_bench(bench_args).await {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    eprintln!("{}\n{}", t!("cli-bench-failed"), e);
+                    std::process::exit(1);
+                }
+            };
+        }
+        CliCommand::FuzzCorpus(fuzz_args) => {
+            return match fuzz_corpus::run_fuzz_corpus(fuzz_args) {
+                Ok(true) => Ok(()),
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("{}\n{}", t!("cli-fuzz-corpus-failed"), e);
+                    std::process::exit(1);
+                }
+            };
+        }
+        CliCommand::Lint(lint_args) => {
+            return match lint::run_lint(lint_args).await {
+                Ok(true) => Ok(()),
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("{}\n{}", t!("cli-lint-failed"), e);
+                    std::process::exit(1);
+                }
+            };
+        }
+        CliCommand::Conformance(conformance_args) => {
+            return match conformance::run_conformance(conformance_args).await {
+                Ok(true) => Ok(()),
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("{}\n{}", t!("cli-conformance-failed"), e);
+                    std::process::exit(1);
+                }
+            };
+        }
+        CliCommand::PluginsList => {
+            return match list_plugins(&args).await {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    eprintln!("{} {}", t!("cli-list-plugins-failed"), e);
+                    std::process::exit(1);
+                }
+            };
+        }
+        CliCommand::ConfigValidate => {
+            return match validate_config(&args).await {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    eprintln!("{}\n{}", t!("cli-config-invalid"), e);
+                    std::process::exit(1);
+                }
+            };
+        }
+        CliCommand::Tui => {
+            return match tui::run_tui(&args, &logs).await {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    eprintln!("{}\n{}", t!("cli-tui-failed"), e);
+                    std::process::exit(1);
+                }
+            };
+        }
+        CliCommand::ThemeList => {
+            return match list_themes(&args).await {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    eprintln!("{} {}", t!("cli-list-themes-failed"), e);
+                    std::process::exit(1);
+                }
+            };
+        }
+        CliCommand::Cache(cache_args) => {
+            return match cache::run_cache(cache_args) {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    eprintln!("{} {}", t!("cli-cache-failed"), e);
+                    std::process::exit(1);
+                }
+            };
+        }
+        CliCommand::Stats(stats_args) => {
+            return match args
+                .load_config()
+                .and_then(|config| stats::run_stats(stats_args, &config))
+            {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    eprintln!("{} {}", t!("cli-stats-failed"), e);
+                    std::process::exit(1);
+                }
+            };
+        }
+    }
+
+    // `rune journal` serves the journal directory, opening the day's note
+    let mut journal_note = None;
+    if let CliCommand::Journal(journal_args) = &args.command {
+        let note = match args
+            .load_config()
+            .and_then(|config| journal::open_note(journal_args, &config))
+        {
+            Ok(note) => note,
             Err(e) => {
-                eprintln!("❌ Failed to list plugins: {}", e);
+                eprintln!("{}\n{}", t!("cli-journal-failed"), e);
                 std::process::exit(1);
             }
         };
+        if journal_args.print {
+            println!("{}", note.path.display());
+            return Ok(());
+        }
+        args.files = vec![note.dir.clone()];
+        args.file = note.dir.clone();
+        journal_note = Some((note.path, !journal_args.no_open));
     }
 
-    if args.validate_config {
-        return match validate_config(&args).await {
-            Ok(()) => Ok(()),
+    // `rune new` serves the document it creates, opening its editor
+    let mut new_document = None;
+    if let CliCommand::New(new_args) = &args.command {
+        let document = match args
+            .load_config()
+            .and_then(|config| new::create_document(new_args, &config))
+        {
+            Ok(document) => document,
             Err(e) => {
-                eprintln!("❌ Configuration validation failed:\n{}", e);
+                eprintln!("{}\n{}", t!("cli-new-failed"), e);
                 std::process::exit(1);
             }
         };
+        if new_args.print {
+            println!("{}", document.path.display());
+            return Ok(());
+        }
+        args.files = vec![document.path.clone()];
+        args.file = document.path;
+        new_document = Some((document.cursor_line, !new_args.no_open));
     }
 
     // For server mode, validate all arguments
     if let Err(e) = args.validate() {
-        eprintln!("❌ Invalid arguments:\n{}", e);
-        std::process::exit(1);
-    }
-
-    // Check port availability early
-    if let Err(e) = args.check_port_availability() {
-        eprintln!("❌ Port check failed:\n{}", e);
+        eprintln!("{}\n{}", t!("cli-invalid-arguments"), e);
         std::process::exit(1);
     }
 
@@ -1166,6 +1899,30 @@ async fn main() -> Result<()> {
         }
     };
 
+    if !args.serves_roots() && rune_core::crypto::is_encrypted(&args.file) {
+        if let Err(e) = unlock::unlock_document(&args.file, &config).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // The configuration file may choose the language when --lang did not
+    if args.lang.is_none() {
+        if let Some(lang) = config.get_locale() {
+            i18n::set_locale(&i18n::detect_locale(Some(&lang)));
+        }
+    }
+
+    // A profile may have moved the server to another address
+    args.hostname = config.server.hostname.clone();
+    args.port = config.server.port;
+
+    // Check port availability before starting any plugin
+    if let Err(e) = args.check_port_availability() {
+        eprintln!("{}\n{}", t!("cli-port-check-failed"), e);
+        std::process::exit(1);
+    }
+
     // Validate configuration
     if let Err(e) = config.validate() {
         error!("Configuration validation failed: {}", e);
@@ -1186,58 +1943,89 @@ async fn main() -> Result<()> {
         std::process::exit(1);
     }
 
-    // Register built-in plugins
-    let context = engine.create_plugin_context();
-
-    // Register file watcher plugin
-    let file_watcher = Box::new(rune_file_watcher::FileWatcherPlugin::new());
-    if let Err(e) = engine.register_plugin(file_watcher, &context).await {
-        error!("Failed to register file watcher plugin: {}", e);
-        std::process::exit(1);
-    }
-
-    // Register renderer plugin
-    let renderer = Box::new(rune_renderer::RendererPlugin::new());
-    if let Err(e) = engine.register_plugin(renderer, &context).await {
-        error!("Failed to register renderer plugin: {}", e);
-        std::process::exit(1);
-    }
-
-    // Register server plugin
-    let server = Box::new(rune_server::ServerPlugin::new());
-    if let Err(e) = engine.register_plugin(server, &context).await {
-        error!("Failed to register server plugin: {}", e);
-        std::process::exit(1);
+    // Roots must be known before the server and file watcher initialize
+    let mut roots = Vec::new();
+    if args.serves_roots() {
+        for path in &args.files {
+            match engine.add_served_root(path.clone()).await {
+                Ok(root) => roots.push(root),
+                Err(e) => {
+                    error!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
     }
 
-    // Register theme plugin
-    let theme = Box::new(rune_theme::ThemePlugin::new());
-    if let Err(e) = engine.register_plugin(theme, &context).await {
-        error!("Failed to register theme plugin: {}", e);
+    // Register built-in plugins
+    if let Err(e) = register_builtin_plugins(&mut engine, &args, &logs).await {
+        error!("{}", e);
         std::process::exit(1);
     }
 
     info!("All built-in plugins registered successfully");
 
     // Add the markdown file to watch
-    if let Err(e) = engine.watch_file(args.file.clone()).await {
-        error!("Failed to start watching file: {}", e);
-        std::process::exit(1);
+    if roots.is_empty() {
+        if let Err(e) = engine.watch_file(args.file.clone()).await {
+            error!("Failed to start watching file: {}", e);
+            std::process::exit(1);
+        }
     }
 
     // Display startup information
-    println!("🌟 Rune Markdown Live Editor");
-    println!("📁 File: {}", args.file.display());
+    println!("{}", t!("startup-title"));
+    if roots.is_empty() {
+        println!(
+            "{}",
+            t!("startup-file", path = args.file.display().to_string())
+        );
+    }
 
     if let Some(server_addr) = engine.get_server_address().await {
-        println!("🌐 Server: http://{}", server_addr);
+        println!(
+            "{}",
+            t!("startup-server", address = server_addr.to_string())
+        );
+        for root in &roots {
+            println!(
+                "📁 {} → http://{}/{}/",
+                root.path.display(),
+                server_addr,
+                root.prefix
+            );
+        }
+        if let Some((note, open_browser)) = &journal_note {
+            let url = roots.iter().find_map(|root| {
+                let relative = note.strip_prefix(&root.path).ok()?;
+                Some(format!(
+                    "http://{}/{}/{}",
+                    server_addr,
+                    root.prefix,
+                    relative.to_string_lossy().replace('\\', "/")
+                ))
+            });
+            if let Some(url) = url {
+                println!("{}", t!("startup-journal", url = url.clone()));
+                if *open_browser {
+                    open_in_browser(&url);
+                }
+            }
+        }
+        if let Some((cursor_line, open_browser)) = new_document {
+            let url = format!("http://{}/#edit=L{}", server_addr, cursor_line);
+            println!("{}", t!("startup-new-document", url = url.clone()));
+            if open_browser {
+                open_in_browser(&url);
+            }
+        }
     } else {
-        println!("⚠️  Server not available");
+        println!("{}", t!("startup-server-unavailable"));
     }
 
     // Display plugin information
     let loaded_plugins = engine.get_loaded_plugins();
-    println!("🔌 Loaded plugins: {}", loaded_plugins.len());
+    println!("{}", t!("startup-plugins", count = loaded_plugins.len()));
 
     if args.dev_mode {
         println!("🔧 Development mode enabled");
@@ -1265,15 +2053,24 @@ async fn main() -> Result<()> {
         }
     }
 
-    if args.plugins_dir.is_some() {
-        println!(
-            "🔌 Custom plugins directory: {}",
-            args.plugins_dir.as_ref().unwrap().display()
-        );
+    if let Some(plugins_dir) = &args.plugins_dir {
+        println!("🔌 Custom plugins directory: {}", plugins_dir.display());
     }
 
     println!("📡 WebSocket live reload enabled");
 
+    if engine.config().get_global_setting::<bool>("lan_discovery") == Some(true) {
+        println!("📶 LAN discovery: advertising as _rune._tcp");
+    }
+
+    if let Some(provider) = engine.config().get_global_setting::<String>("tunnel") {
+        println!("🌐 Tunnel: opening via {}", provider);
+        engine
+            .event_bus()
+            .subscribe_system_events(Arc::new(TunnelAnnouncer))
+            .await?;
+    }
+
     // Display system health
     let system_health = engine.get_system_health();
     let health_icon = match system_health {
@@ -1312,7 +2109,7 @@ async fn main() -> Result<()> {
         }
     }
 
-    println!("\n✨ Server ready! Press Ctrl+C to stop.\n");
+    println!("\n{}\n", t!("startup-ready"));
 
     info!(
         "Rune server started successfully on {}:{} for file: {}",
@@ -1332,3 +2129,180 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(argv: &[&str]) -> Args {
+        let matches = Args::command()
+            .try_get_matches_from(std::iter::once("rune").chain(argv.iter().copied()))
+            .unwrap();
+        Args::from_matches(&matches).unwrap()
+    }
+
+    #[test]
+    fn test_serve_shorthand_and_legacy_flags() {
+        let args = parse(&["a.md"]);
+        assert!(matches!(args.command, CliCommand::Serve));
+        assert_eq!(args.files, [PathBuf::from("a.md")]);
+        assert!(!args.port_given);
+
+        let args = parse(&["serve", "a.md", "b.md", "-p", "4000"]);
+        assert!(matches!(args.command, CliCommand::Serve));
+        assert_eq!(args.file, PathBuf::from("a.md"));
+        assert_eq!(args.files.len(), 2);
+        assert_eq!(args.port, 4000);
+        assert!(args.port_given);
+
+        assert!(matches!(
+            parse(&["--list-plugins"]).command,
+            CliCommand::PluginsList
+        ));
+        assert!(matches!(
+            parse(&["--validate-config", "-c", "rune.json"]).command,
+            CliCommand::ConfigValidate
+        ));
+        assert!(Args::command()
+            .try_get_matches_from(["rune", "serve"])
+            .is_err());
+
+        let args = parse(&["journal", "yesterday", "--print", "-p", "4000"]);
+        let CliCommand::Journal(journal_args) = &args.command else {
+            panic!("not a journal command: {:?}", args.command);
+        };
+        assert_eq!(journal_args.date, "yesterday");
+        assert!(journal_args.print && !journal_args.no_open);
+        assert!(args.files.is_empty());
+        assert_eq!(args.port, 4000);
+
+        let args = parse(&[
+            "new",
+            "notes/sync.md",
+            "--template",
+            "meeting",
+            "--var",
+            "room=Lab 2",
+            "--no-open",
+        ]);
+        let CliCommand::New(new_args) = &args.command else {
+            panic!("not a new command: {:?}", args.command);
+        };
+        assert_eq!(new_args.path, PathBuf::from("notes/sync.md"));
+        assert_eq!(new_args.template.as_deref(), Some("meeting"));
+        assert_eq!(
+            new_args.variables,
+            [("room".to_string(), "Lab 2".to_string())]
+        );
+        assert!(new_args.no_open && args.files.is_empty());
+        assert!(Args::command()
+            .try_get_matches_from(["rune", "new", "a.md", "--var", "room"])
+            .is_ok_and(|matches| Args::from_matches(&matches).is_err()));
+    }
+
+    #[test]
+    fn test_global_flags_around_subcommands() {
+        for argv in [
+            [
+                "--dev-mode",
+                "--offline",
+                "-c",
+                "rune.json",
+                "serve",
+                "a.md",
+            ],
+            [
+                "serve",
+                "a.md",
+                "--dev-mode",
+                "--offline",
+                "-c",
+                "rune.json",
+            ],
+            [
+                "--dev-mode",
+                "serve",
+                "--offline",
+                "a.md",
+                "-c",
+                "rune.json",
+            ],
+        ] {
+            let args = parse(&argv);
+            assert!(matches!(args.command, CliCommand::Serve), "{:?}", argv);
+            assert!(args.dev_mode && args.offline, "{:?}", argv);
+            assert_eq!(args.config_file, Some(PathBuf::from("rune.json")));
+        }
+
+        for argv in [
+            ["-c", "rune.json", "--lang", "fr", "plugins", "list"],
+            ["plugins", "list", "-c", "rune.json", "--lang", "fr"],
+        ] {
+            let args = parse(&argv);
+            assert!(
+                matches!(args.command, CliCommand::PluginsList),
+                "{:?}",
+                argv
+            );
+            assert_eq!(args.config_file, Some(PathBuf::from("rune.json")));
+            assert_eq!(args.lang.as_deref(), Some("fr"));
+        }
+
+        // Serve arguments belong to the subcommand
+        let matches = Args::command()
+            .try_get_matches_from(["rune", "-p", "4000", "serve", "a.md"])
+            .unwrap();
+        assert!(Args::from_matches(&matches).is_err());
+
+        // A profile needs a configuration file to come from
+        assert!(Args::command()
+            .try_get_matches_from(["rune", "a.md", "--profile", "dev"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_cache_subcommands() {
+        assert!(matches!(
+            parse(&["cache", "list", "--json"]).command,
+            CliCommand::Cache(cache::CacheArgs::List { json: true })
+        ));
+        assert!(matches!(
+            parse(&["cache", "clear", "search"]).command,
+            CliCommand::Cache(cache::CacheArgs::Clear { plugin: Some(ref plugin) }) if plugin == "search"
+        ));
+        assert!(matches!(
+            parse(&["cache", "clear"]).command,
+            CliCommand::Cache(cache::CacheArgs::Clear { plugin: None })
+        ));
+    }
+
+    #[test]
+    fn test_stats_subcommand() {
+        assert!(matches!(
+            parse(&["stats"]).command,
+            CliCommand::Stats(stats::StatsArgs::Show { json: false })
+        ));
+        assert!(matches!(
+            parse(&["stats", "--export", "out.json"]).command,
+            CliCommand::Stats(stats::StatsArgs::Export { ref file }) if file == Path::new("out.json")
+        ));
+        assert!(matches!(
+            parse(&["stats", "--delete"]).command,
+            CliCommand::Stats(stats::StatsArgs::Delete)
+        ));
+        assert!(Args::command()
+            .try_get_matches_from(["rune", "stats", "--json", "--delete"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_fuzz_corpus_takes_a_known_target() {
+        assert!(matches!(
+            parse(&["fuzz-corpus", "render", "fuzz/corpus/render"]).command,
+            CliCommand::FuzzCorpus(ref args) if args.target == "render"
+        ));
+        assert!(Args::command()
+            .try_get_matches_from(["rune", "fuzz-corpus", "lexer", "corpus"])
+            .is_err());
+    }
+}
@@ -0,0 +1,134 @@
+//! `rune new` - create a document from a template
+//!
+//! The document is created from the named template, see
+//! [`rune_core::templates`], along with its parent directories. It is then
+//! served like `rune serve <file>` and opened in the browser with the raw
+//! editor at the template's cursor. With `--print` the document's path is
+//! printed instead, for opening it in another editor.
+
+use rune_core::templates::{self, NewDocument, Templates};
+use rune_core::{Config, Result, RuneError};
+use std::path::PathBuf;
+
+/// Arguments of the `new` subcommand
+#[derive(Debug, Clone)]
+pub struct NewArgs {
+    pub path: PathBuf,
+    /// Name of the template, blank without one
+    pub template: Option<String>,
+    pub title: Option<String>,
+    /// Variables given with `--var KEY=VALUE`
+    pub variables: Vec<(String, String)>,
+    pub print: bool,
+    pub no_open: bool,
+}
+
+impl NewArgs {
+    /// Build new document arguments from the `new` subcommand matches
+    pub fn from_matches(matches: &clap::ArgMatches) -> Result<Self> {
+        let variables = matches
+            .get_many::<String>("var")
+            .into_iter()
+            .flatten()
+            .map(|variable| {
+                variable
+                    .split_once('=')
+                    .filter(|(key, _)| !key.trim().is_empty())
+                    .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+                    .ok_or_else(|| {
+                        RuneError::config(format!("--var expects KEY=VALUE, got '{}'", variable))
+                    })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            path: matches
+                .get_one::<PathBuf>("path")
+                .cloned()
+                .expect("path is required"),
+            template: matches.get_one::<String>("template").cloned(),
+            title: matches.get_one::<String>("title").cloned(),
+            variables,
+            print: matches.get_flag("print"),
+            no_open: matches.get_flag("no-open"),
+        })
+    }
+
+    /// Build the `new` subcommand definition, without the serve arguments
+    pub fn command() -> clap::Command {
+        use clap::{Arg, Command};
+
+        Command::new("new")
+            .about("Create a document from a template and open it in the editor")
+            .long_about(
+                "Create a markdown document from a template, along with its parent \
+                directories, then serve it and open the editor at the template's \
+                cursor. Templates are markdown files like meeting.md, looked up in the \
+                'templates_dir' setting, in .rune/templates and in the rune/templates \
+                directory of the user's configuration. They may use {{date}}, \
+                {{time}}, {{title}}, {{author}} (the 'author' setting), {{file}}, the \
+                --var variables and {{cursor}}.",
+            )
+            .arg(
+                Arg::new("path")
+                    .help("Markdown file to create")
+                    .required(true)
+                    .index(1)
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new("template")
+                    .short('t')
+                    .long("template")
+                    .value_name("NAME")
+                    .help("Template to start from; blank without one"),
+            )
+            .arg(
+                Arg::new("title")
+                    .long("title")
+                    .value_name("TITLE")
+                    .help("Title of the document; taken from the file name by default"),
+            )
+            .arg(
+                Arg::new("var")
+                    .long("var")
+                    .value_name("KEY=VALUE")
+                    .help("Fill in {{KEY}} of the template with VALUE")
+                    .action(clap::ArgAction::Append),
+            )
+            .arg(
+                Arg::new("print")
+                    .long("print")
+                    .help("Print the document's path instead of serving it")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("no-open")
+                    .long("no-open")
+                    .help("Do not open the document in the browser")
+                    .action(clap::ArgAction::SetTrue),
+            )
+    }
+}
+
+/// Create the document the arguments name
+pub fn create_document(args: &NewArgs, config: &Config) -> Result<NewDocument> {
+    if !args.path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown")
+    }) {
+        return Err(RuneError::config(format!(
+            "{} is not a .md or .markdown file",
+            args.path.display()
+        )));
+    }
+    let template = Templates::from_config(config).source(args.template.as_deref())?;
+    let mut variables = templates::variables(config, &args.path);
+    if let Some(title) = &args.title {
+        variables.insert("title".to_string(), title.clone());
+    }
+    variables.extend(args.variables.iter().cloned());
+    let document = templates::create(&args.path, &template, &variables)?;
+    if !args.print {
+        println!("📝 Created {}", document.path.display());
+    }
+    Ok(document)
+}
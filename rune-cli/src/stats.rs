@@ -0,0 +1,120 @@
+//! `rune stats` - print, export or delete the local usage statistics
+//!
+//! The statistics are only recorded with `"telemetry": {"enabled": true}` in
+//! the configuration and never leave the machine. `rune stats` prints them,
+//! `--json` prints them as JSON, `--export <file>` copies them to a file and
+//! `--delete` removes them.
+
+use rune_core::{telemetry, Config, Result, RuneError};
+use std::path::PathBuf;
+
+/// What `rune stats` does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatsArgs {
+    /// Print the statistics
+    Show { json: bool },
+    /// Write the statistics as JSON to a file
+    Export { file: PathBuf },
+    /// Remove the statistics
+    Delete,
+}
+
+impl StatsArgs {
+    /// Build stats arguments from the `stats` subcommand matches
+    pub fn from_matches(matches: &clap::ArgMatches) -> Result<Self> {
+        if let Some(file) = matches.get_one::<PathBuf>("export") {
+            Ok(Self::Export { file: file.clone() })
+        } else if matches.get_flag("delete") {
+            Ok(Self::Delete)
+        } else {
+            Ok(Self::Show {
+                json: matches.get_flag("json"),
+            })
+        }
+    }
+
+    /// Build the `stats` subcommand definition
+    pub fn command() -> clap::Command {
+        use clap::{Arg, ArgAction, Command};
+
+        Command::new("stats")
+            .about("Show, export or delete local usage statistics")
+            .long_about(
+                "With \"telemetry\": {\"enabled\": true} in the configuration, rune counts \
+                the documents opened, the renders and the features used, in stats.json in \
+                the rune data directory. The counts never leave this machine.",
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .help("Print the statistics as JSON")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("export")
+                    .long("export")
+                    .value_name("FILE")
+                    .help("Write the statistics as JSON to FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .conflicts_with_all(["json", "delete"]),
+            )
+            .arg(
+                Arg::new("delete")
+                    .long("delete")
+                    .help("Delete the recorded statistics")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("json"),
+            )
+    }
+}
+
+/// Run `rune stats` against the statistics file of `config`
+pub fn run_stats(args: &StatsArgs, config: &Config) -> Result<()> {
+    let path = telemetry::stats_path(config)
+        .ok_or_else(|| RuneError::config("No data directory to keep usage statistics in"))?;
+
+    if *args == StatsArgs::Delete {
+        if telemetry::delete(&path)? {
+            println!("Deleted {}", path.display());
+        } else {
+            println!("No usage statistics recorded");
+        }
+        return Ok(());
+    }
+
+    let Some(stats) = telemetry::load(&path)? else {
+        if config.telemetry.is_enabled() {
+            println!("No usage statistics recorded yet");
+        } else {
+            println!(
+                "Usage statistics are off; enable them with \"telemetry\": {{\"enabled\": true}} in the configuration"
+            );
+        }
+        return Ok(());
+    };
+    match args {
+        StatsArgs::Export { file } => {
+            std::fs::write(file, serde_json::to_string_pretty(&stats)?)?;
+            println!("Exported usage statistics to {}", file.display());
+        }
+        StatsArgs::Show { json: true } => println!("{}", serde_json::to_string_pretty(&stats)?),
+        _ => {
+            println!("{}", path.display());
+            println!("  {:<20} {:>10}", "sessions", stats.sessions);
+            println!(
+                "  {:<20} {:>10}",
+                "documents opened", stats.documents_opened
+            );
+            println!("  {:<20} {:>10}", "renders", stats.renders);
+            let mut features: Vec<_> = stats.features.iter().collect();
+            features.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            if !features.is_empty() {
+                println!("features");
+            }
+            for (feature, count) in features {
+                println!("  {:<20} {:>10}", feature, count);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+//! Deciding whether to trust the workspace of the opened files
+
+use rune_core::trust::{self, TrustStore};
+use rune_core::{Config, Result};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+/// Decision of this run, so commands loading the configuration more than
+/// once ask only once
+static DECISION: OnceLock<bool> = OnceLock::new();
+
+/// Directory of the workspace `path` belongs to
+pub fn workspace_of(path: &Path) -> PathBuf {
+    let dir = if path.is_dir() {
+        Some(path)
+    } else {
+        path.parent()
+    };
+    match dir {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Apply the workspace in `workspace` to `config`, trusting it when the user
+/// trusted it before, gave `--trust`, or says so at the prompt
+pub fn apply(config: &mut Config, workspace: &Path, trust_flag: bool) -> Result<()> {
+    let trusted = match DECISION.get() {
+        Some(trusted) => *trusted,
+        None => {
+            let trusted = decide(config, workspace, trust_flag)?;
+            *DECISION.get_or_init(|| trusted)
+        }
+    };
+    trust::apply(config, workspace, trusted)
+}
+
+fn decide(config: &Config, workspace: &Path, trust_flag: bool) -> Result<bool> {
+    let mut store = TrustStore::load()?;
+    if store.is_trusted(workspace) {
+        return Ok(true);
+    }
+    if trust_flag {
+        store.trust(workspace)?;
+        info!("Trusting workspace {}", workspace.display());
+        return Ok(true);
+    }
+    if !trust::needs_trust(config, workspace) {
+        return Ok(false);
+    }
+    if !std::io::stdin().is_terminal() {
+        warn!(
+            "{} is not a trusted workspace; run with --trust to use its configuration and export hooks",
+            workspace.display()
+        );
+        return Ok(false);
+    }
+
+    eprint!(
+        "⚠️  {} has its own configuration or export hooks, which can run programs.\n\
+        Only trust workspaces whose authors you trust. Trust this workspace? [y/N] ",
+        workspace.display()
+    );
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        return Ok(false);
+    }
+    store.trust(workspace)?;
+    Ok(true)
+}
@@ -0,0 +1,661 @@
+//! Interactive terminal interface (`rune tui`)
+//!
+//! Shows the markdown files being previewed, their render status, recent
+//! system events and watcher errors. The interface only talks to the rest of
+//! rune through the event bus: it learns about changes from system events and
+//! switches files, forces reloads and changes themes by publishing events, so
+//! it does not depend on the server plugin.
+
+use async_trait::async_trait;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+use rune_core::{
+    event::{ChangeType, Event as _, EventBus, SystemEvent, SystemEventHandler},
+    logging::LogController,
+    CoreEngine, Result, RuneError,
+};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+
+use crate::Args;
+
+/// Number of recent events kept for display
+const MAX_EVENTS: usize = 100;
+
+/// Number of recent errors kept for display
+const MAX_ERRORS: usize = 20;
+
+/// Render state of a file, as reported on the event bus
+#[derive(Debug, Clone, PartialEq)]
+enum RenderStatus {
+    Idle,
+    /// Changed and waiting for the preview to catch up
+    Pending(SystemTime),
+    Rendered {
+        duration: Duration,
+        at: SystemTime,
+    },
+}
+
+#[derive(Debug)]
+struct FileEntry {
+    path: PathBuf,
+    label: String,
+    status: RenderStatus,
+}
+
+/// Something the user asked for that has to be carried out outside the view
+#[derive(Debug, PartialEq)]
+enum Action {
+    Quit,
+    Serve(PathBuf),
+    Reload(PathBuf),
+    SwitchTheme(String),
+    OpenBrowser(String),
+}
+
+/// View state of the terminal interface
+struct TuiApp {
+    files: Vec<FileEntry>,
+    selected: usize,
+    serving: Option<usize>,
+    themes: Vec<String>,
+    current_theme: Option<String>,
+    server_address: Option<String>,
+    events: VecDeque<(SystemTime, String)>,
+    errors: VecDeque<(SystemTime, String)>,
+    notice: Option<String>,
+}
+
+impl TuiApp {
+    fn new(files: Vec<PathBuf>, root: &Path, themes: Vec<String>) -> Self {
+        let files = files
+            .into_iter()
+            .map(|path| FileEntry {
+                label: path
+                    .strip_prefix(root)
+                    .unwrap_or(&path)
+                    .display()
+                    .to_string(),
+                path,
+                status: RenderStatus::Idle,
+            })
+            .collect();
+
+        Self {
+            files,
+            selected: 0,
+            serving: None,
+            themes,
+            current_theme: None,
+            server_address: None,
+            events: VecDeque::new(),
+            errors: VecDeque::new(),
+            notice: None,
+        }
+    }
+
+    fn position(&self, path: &Path) -> Option<usize> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.files.iter().position(|file| file.path == path)
+    }
+
+    /// Update the view from a system event
+    fn apply(&mut self, event: &SystemEvent) {
+        match event {
+            // Periodic and bookkeeping events would drown out the interesting ones
+            SystemEvent::PluginHealthCheck { .. }
+            | SystemEvent::ServerHandlerRegistered { .. }
+            | SystemEvent::ServerHandlerUnregistered { .. } => return,
+            SystemEvent::FileChanged {
+                path, timestamp, ..
+            } => {
+                if let Some(index) = self.position(path) {
+                    self.files[index].status = RenderStatus::Pending(*timestamp);
+                }
+            }
+            SystemEvent::RenderComplete {
+                duration,
+                timestamp,
+                ..
+            } => {
+                // The preview only renders the file being served
+                if let Some(index) = self.serving {
+                    self.files[index].status = RenderStatus::Rendered {
+                        duration: *duration,
+                        at: *timestamp,
+                    };
+                }
+            }
+            SystemEvent::ThemeChanged { theme_name, .. } => {
+                self.current_theme = Some(theme_name.clone());
+            }
+            SystemEvent::ServerStarted { address, .. } => {
+                self.server_address = Some(address.clone());
+            }
+            SystemEvent::ServerStopped { .. } => {
+                self.server_address = None;
+            }
+            SystemEvent::Error {
+                source,
+                message,
+                timestamp,
+                ..
+            } => {
+                push_bounded(
+                    &mut self.errors,
+                    (*timestamp, format!("[{}] {}", source, message)),
+                    MAX_ERRORS,
+                );
+            }
+            _ => {}
+        }
+
+        push_bounded(
+            &mut self.events,
+            (event.timestamp(), event.description()),
+            MAX_EVENTS,
+        );
+    }
+
+    /// Mark a file as the one being previewed
+    fn serve(&mut self, index: usize) -> Option<Action> {
+        let file = self.files.get_mut(index)?;
+        file.status = RenderStatus::Pending(SystemTime::now());
+        self.serving = Some(index);
+        Some(Action::Serve(file.path.clone()))
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Action> {
+        self.notice = None;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => Some(Action::Quit),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(Action::Quit)
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+                None
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.selected + 1 < self.files.len() {
+                    self.selected += 1;
+                }
+                None
+            }
+            KeyCode::Enter => self.serve(self.selected),
+            KeyCode::Char('r') => {
+                let file = &mut self.files[self.serving?];
+                file.status = RenderStatus::Pending(SystemTime::now());
+                Some(Action::Reload(file.path.clone()))
+            }
+            KeyCode::Char('t') => self.next_theme().map(Action::SwitchTheme),
+            KeyCode::Char('o') => match &self.server_address {
+                Some(address) => Some(Action::OpenBrowser(format!("http://{}", address))),
+                None => {
+                    self.notice = Some("Server is not running".to_string());
+                    None
+                }
+            },
+            _ => None,
+        }
+    }
+
+    /// Theme following the current one
+    fn next_theme(&self) -> Option<String> {
+        let current = self
+            .current_theme
+            .as_ref()
+            .and_then(|current| self.themes.iter().position(|theme| theme == current));
+        let next = current.map_or(0, |index| (index + 1) % self.themes.len().max(1));
+        self.themes.get(next).cloned()
+    }
+}
+
+fn push_bounded<T>(queue: &mut VecDeque<T>, item: T, capacity: usize) {
+    if queue.len() == capacity {
+        queue.pop_front();
+    }
+    queue.push_back(item);
+}
+
+/// Forwards every system event to the interface
+struct TuiEventForwarder {
+    sender: mpsc::UnboundedSender<SystemEvent>,
+}
+
+#[async_trait]
+impl SystemEventHandler for TuiEventForwarder {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        // Sending only fails once the interface has exited
+        let _ = self.sender.send(event.clone());
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "tui-event-forwarder"
+    }
+}
+
+/// Run the terminal interface for a markdown file or a directory of them
+pub async fn run_tui(args: &Args, logs: &Arc<LogController>) -> Result<()> {
+    let (root, files) = if args.file.is_dir() {
+        let root = args.file.canonicalize()?;
+        let files = markdown_files(&root)?;
+        if files.is_empty() {
+            return Err(RuneError::config(format!(
+                "No markdown files found in {}",
+                args.file.display()
+            )));
+        }
+        (root, files)
+    } else {
+        args.validate()?;
+        let file = args.file.canonicalize()?;
+        let root = file.parent().map(Path::to_path_buf).unwrap_or_default();
+        (root, vec![file])
+    };
+    let config = args.load_config()?;
+    if files.len() == 1 && rune_core::crypto::is_encrypted(&files[0]) {
+        crate::unlock::unlock_document(&files[0], &config).await?;
+    }
+    // A profile may have moved the server to another address
+    let mut args = args.clone();
+    args.hostname = config.server.hostname.clone();
+    args.port = config.server.port;
+    args.check_port_availability()?;
+
+    let mut engine = CoreEngine::new(config)?;
+    engine.initialize().await?;
+    crate::register_builtin_plugins(&mut engine, &args, logs).await?;
+
+    let event_bus = engine.event_bus();
+    let (event_sender, mut event_receiver) = mpsc::unbounded_channel();
+    event_bus
+        .subscribe_system_events(Arc::new(TuiEventForwarder {
+            sender: event_sender,
+        }))
+        .await?;
+
+    let (themes, current_theme) = themes(&engine).await;
+    let mut app = TuiApp::new(files, &root, themes);
+    app.current_theme = current_theme;
+    app.server_address = engine.get_server_address().await;
+
+    let state_manager = engine.state_manager();
+    if let Some(Action::Serve(path)) = app.serve(0) {
+        state_manager.set_current_file(Some(path.clone())).await;
+        publish_change(&event_bus, path).await;
+    }
+
+    // Terminal input is read on its own thread so the event loop stays async
+    let (key_sender, mut key_receiver) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if key_sender.send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut terminal = ratatui::init();
+    let mut tick = tokio::time::interval(Duration::from_secs(1));
+    let result: Result<()> = loop {
+        if let Err(e) = terminal.draw(|frame| draw(frame, &app)) {
+            break Err(e.into());
+        }
+
+        let action = tokio::select! {
+            Some(event) = event_receiver.recv() => {
+                app.apply(&event);
+                None
+            }
+            Some(input) = key_receiver.recv() => match input {
+                Event::Key(key) if key.kind == KeyEventKind::Press => app.handle_key(key),
+                _ => None,
+            },
+            _ = tick.tick() => None,
+        };
+
+        match action {
+            None => {}
+            Some(Action::Quit) => break Ok(()),
+            Some(Action::Serve(path)) => {
+                state_manager.set_current_file(Some(path.clone())).await;
+                publish_change(&event_bus, path).await;
+            }
+            Some(Action::Reload(path)) => publish_change(&event_bus, path).await,
+            Some(Action::SwitchTheme(theme)) => {
+                if let Err(e) = event_bus
+                    .publish_system_event(SystemEvent::theme_changed(theme))
+                    .await
+                {
+                    app.notice = Some(format!("Failed to switch theme: {}", e));
+                }
+            }
+            Some(Action::OpenBrowser(url)) => {
+                if let Err(e) = open::that_detached(&url) {
+                    app.notice = Some(format!("Failed to open {}: {}", url, e));
+                }
+            }
+        }
+    };
+    ratatui::restore();
+
+    engine.shutdown().await?;
+    result
+}
+
+async fn publish_change(event_bus: &Arc<dyn EventBus>, path: PathBuf) {
+    let event = SystemEvent::file_changed(path, ChangeType::Modified);
+    if let Err(e) = event_bus.publish_system_event(event).await {
+        tracing::warn!("Failed to publish file change: {}", e);
+    }
+}
+
+/// Names of the themes offered by the theme plugin, and the active one
+async fn themes(engine: &CoreEngine) -> (Vec<String>, Option<String>) {
+    let provider = engine
+        .plugin_registry()
+        .get_plugin("theme")
+        .and_then(|plugin| plugin.as_any().downcast_ref::<rune_theme::ThemePlugin>())
+        .and_then(|plugin| plugin.theme_provider());
+    let Some(provider) = provider else {
+        return (Vec::new(), None);
+    };
+
+    let mut names: Vec<String> = provider
+        .available_themes()
+        .await
+        .map(|themes| themes.into_iter().map(|theme| theme.name).collect())
+        .unwrap_or_default();
+    names.sort();
+    (
+        names,
+        provider.get_current_theme().await.unwrap_or_default(),
+    )
+}
+
+/// Markdown files below a directory, skipping hidden directories
+fn markdown_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    fn collect(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if path.is_dir() {
+                collect(&path, files)?;
+            } else if matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("md") | Some("markdown")
+            ) {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    collect(dir, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+/// Short "how long ago" label
+fn ago(time: SystemTime) -> String {
+    let seconds = time.elapsed().unwrap_or_default().as_secs();
+    match seconds {
+        0..=59 => format!("{}s ago", seconds),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        _ => format!("{}h ago", seconds / 3600),
+    }
+}
+
+fn status_span(status: &RenderStatus) -> Span<'static> {
+    match status {
+        RenderStatus::Idle => Span::raw(""),
+        RenderStatus::Pending(_) => "pending".yellow(),
+        RenderStatus::Rendered { duration, at } => {
+            format!("{}ms, {}", duration.as_millis(), ago(*at)).green()
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, app: &TuiApp) {
+    let [header, body, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [files, details] =
+        Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)]).areas(body);
+    let [status, events, errors] = Layout::vertical([
+        Constraint::Length(5),
+        Constraint::Min(0),
+        Constraint::Length(8),
+    ])
+    .areas(details);
+
+    frame.render_widget(
+        Line::from(vec![
+            " rune ".bold().reversed(),
+            Span::raw(" "),
+            Span::raw(
+                app.server_address
+                    .as_deref()
+                    .map(|address| format!("http://{}", address))
+                    .unwrap_or_else(|| "server not running".to_string()),
+            ),
+        ]),
+        header,
+    );
+
+    draw_files(frame, app, files);
+    draw_status(frame, app, status);
+    draw_log(frame, "Recent events", &app.events, Style::new(), events);
+    draw_log(
+        frame,
+        "Errors",
+        &app.errors,
+        Style::new().fg(Color::Red),
+        errors,
+    );
+
+    let help = match &app.notice {
+        Some(notice) => Line::from(notice.clone().yellow()),
+        None => {
+            Line::from(" ↑/↓ select  enter serve  r reload  t theme  o open browser  q quit".dim())
+        }
+    };
+    frame.render_widget(help, footer);
+}
+
+fn draw_files(frame: &mut Frame, app: &TuiApp, area: Rect) {
+    let items: Vec<ListItem> = app
+        .files
+        .iter()
+        .enumerate()
+        .map(|(index, file)| {
+            let marker = if app.serving == Some(index) {
+                "● "
+            } else {
+                "  "
+            };
+            ListItem::new(Line::from(vec![
+                Span::raw(marker),
+                Span::raw(file.label.clone()),
+                Span::raw("  "),
+                status_span(&file.status),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::bordered().title(format!(" Files ({}) ", app.files.len())))
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_status(frame: &mut Frame, app: &TuiApp, area: Rect) {
+    let serving = app.serving.map(|index| &app.files[index]);
+    let render = match serving.map(|file| &file.status) {
+        Some(RenderStatus::Rendered { duration, at }) => {
+            format!("rendered in {}ms, {}", duration.as_millis(), ago(*at))
+        }
+        Some(RenderStatus::Pending(since)) => format!("changed {}, not rendered yet", ago(*since)),
+        _ => "no render reported yet".to_string(),
+    };
+
+    let lines = vec![
+        Line::from(vec![
+            "Serving: ".bold(),
+            Span::raw(serving.map_or("-".to_string(), |file| file.label.clone())),
+        ]),
+        Line::from(vec!["Render:  ".bold(), Span::raw(render)]),
+        Line::from(vec![
+            "Theme:   ".bold(),
+            Span::raw(app.current_theme.clone().unwrap_or_else(|| "-".to_string())),
+        ]),
+    ];
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Status ")),
+        area,
+    );
+}
+
+fn draw_log(
+    frame: &mut Frame,
+    title: &str,
+    entries: &VecDeque<(SystemTime, String)>,
+    style: Style,
+    area: Rect,
+) {
+    // Newest first, as many as fit inside the borders
+    let lines: Vec<Line> = entries
+        .iter()
+        .rev()
+        .take(area.height.saturating_sub(2) as usize)
+        .map(|(time, message)| {
+            Line::from(vec![
+                format!("{:>8} ", ago(*time)).dim(),
+                Span::styled(message.clone(), style),
+            ])
+        })
+        .collect();
+
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title(format!(" {} ", title))),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::event::ErrorSeverity;
+    use tempfile::TempDir;
+
+    fn app(dir: &Path) -> TuiApp {
+        std::fs::create_dir_all(dir.join("guide")).unwrap();
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        for name in [
+            "README.md",
+            "guide/intro.markdown",
+            ".git/x.md",
+            "notes.txt",
+        ] {
+            std::fs::write(dir.join(name), "# Doc\n").unwrap();
+        }
+        let root = dir.canonicalize().unwrap();
+        let files = markdown_files(&root).unwrap();
+        TuiApp::new(files, &root, vec!["dark".to_string(), "light".to_string()])
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_events_update_file_status() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = app(temp_dir.path());
+        let labels: Vec<&str> = app.files.iter().map(|f| f.label.as_str()).collect();
+        assert_eq!(labels, vec!["README.md", "guide/intro.markdown"]);
+
+        app.serve(1);
+        app.apply(&SystemEvent::file_changed(
+            temp_dir.path().join("guide/intro.markdown"),
+            ChangeType::Modified,
+        ));
+        assert!(matches!(app.files[1].status, RenderStatus::Pending(_)));
+
+        app.apply(&SystemEvent::render_complete(
+            "abc".to_string(),
+            Duration::from_millis(12),
+        ));
+        assert!(matches!(
+            app.files[1].status,
+            RenderStatus::Rendered { duration, .. } if duration == Duration::from_millis(12)
+        ));
+        assert_eq!(app.files[0].status, RenderStatus::Idle);
+
+        app.apply(&SystemEvent::error(
+            "file-watcher".to_string(),
+            "watch limit reached".to_string(),
+            ErrorSeverity::High,
+        ));
+        assert_eq!(app.errors[0].1, "[file-watcher] watch limit reached");
+        assert_eq!(app.events.len(), 3);
+    }
+
+    #[test]
+    fn test_key_controls() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = app(temp_dir.path());
+
+        // Nothing to reload or open before a file is served and the server runs
+        assert_eq!(app.handle_key(key(KeyCode::Char('r'))), None);
+        assert_eq!(app.handle_key(key(KeyCode::Char('o'))), None);
+        assert!(app.notice.is_some());
+
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Down));
+        let served = app.files[1].path.clone();
+        assert_eq!(
+            app.handle_key(key(KeyCode::Enter)),
+            Some(Action::Serve(served.clone()))
+        );
+        assert_eq!(
+            app.handle_key(key(KeyCode::Char('r'))),
+            Some(Action::Reload(served))
+        );
+
+        assert_eq!(
+            app.handle_key(key(KeyCode::Char('t'))),
+            Some(Action::SwitchTheme("dark".to_string()))
+        );
+        app.apply(&SystemEvent::theme_changed("light".to_string()));
+        assert_eq!(
+            app.handle_key(key(KeyCode::Char('t'))),
+            Some(Action::SwitchTheme("dark".to_string()))
+        );
+        assert_eq!(app.handle_key(key(KeyCode::Char('q'))), Some(Action::Quit));
+    }
+}
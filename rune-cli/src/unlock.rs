@@ -0,0 +1,43 @@
+//! Unlocking encrypted `.md.age` documents before they are opened
+
+use rune_core::{crypto, Config, Result, RuneError};
+use std::io::IsTerminal;
+use std::path::Path;
+
+/// Passphrase prompts before giving up
+const PROMPT_ATTEMPTS: usize = 3;
+
+/// Unlock an encrypted document with the passphrase from the environment,
+/// the configured passphrase command or, on a terminal, a prompt
+pub async fn unlock_document(path: &Path, config: &Config) -> Result<()> {
+    if crypto::is_unlocked(path) {
+        return Ok(());
+    }
+
+    if let Some(passphrase) = crypto::configured_passphrase(path, &config.encryption).await? {
+        return crypto::unlock(path, &passphrase);
+    }
+
+    if !std::io::stdin().is_terminal() {
+        return Err(RuneError::config(format!(
+            "{} is encrypted. Set {} or encryption.passphrase_command in the \
+            configuration to give its passphrase",
+            path.display(),
+            crypto::PASSPHRASE_ENV
+        )));
+    }
+
+    let prompt = format!("🔒 Passphrase for {}: ", path.display());
+    let mut last_error = None;
+    for _ in 0..PROMPT_ATTEMPTS {
+        let passphrase = rpassword::prompt_password(&prompt)?;
+        match crypto::unlock(path, &passphrase) {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                eprintln!("❌ Wrong passphrase");
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| RuneError::config("No passphrase given")))
+}
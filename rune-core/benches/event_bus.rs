@@ -0,0 +1,58 @@
+//! Event bus throughput benchmarks
+//!
+//! Run with `cargo bench -p rune-core --bench event_bus`; criterion's
+//! `--save-baseline` and `--baseline` options compare runs.
+
+use async_trait::async_trait;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rune_core::event::{ChangeType, EventBus, InMemoryEventBus, SystemEvent, SystemEventHandler};
+use rune_core::Result;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Handler doing the least possible work, so routing dominates
+struct Counter(AtomicU64);
+
+#[async_trait]
+impl SystemEventHandler for Counter {
+    async fn handle_system_event(&self, _event: &SystemEvent) -> Result<()> {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+fn publish(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+    let mut group = c.benchmark_group("event_bus");
+    group.throughput(Throughput::Elements(1));
+
+    for handlers in [1, 16] {
+        let event_bus = runtime.block_on(async {
+            let event_bus = InMemoryEventBus::new();
+            for _ in 0..handlers {
+                event_bus
+                    .subscribe_system_events(Arc::new(Counter(AtomicU64::new(0))))
+                    .await
+                    .unwrap();
+            }
+            event_bus
+        });
+        let path = PathBuf::from("bench.md");
+        group.bench_function(BenchmarkId::new("publish", handlers), |b| {
+            b.to_async(&runtime).iter(|| {
+                event_bus.publish_system_event(SystemEvent::file_changed(
+                    path.clone(),
+                    ChangeType::Modified,
+                ))
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, publish);
+criterion_main!(benches);
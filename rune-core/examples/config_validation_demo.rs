@@ -90,6 +90,7 @@ async fn demo_configuration_overrides() -> Result<()> {
             cors_enabled: false,
             websocket_enabled: true,
             static_dir: Some(PathBuf::from("./public")),
+            cors: Default::default(),
         },
         plugins: vec![],
         global_settings: {
@@ -101,6 +102,20 @@ async fn demo_configuration_overrides() -> Result<()> {
             );
             settings
         },
+        export_hooks: Default::default(),
+        export_assets: Default::default(),
+        profiles: Default::default(),
+        log: Default::default(),
+        memory: Default::default(),
+        large_files: Default::default(),
+        link_previews: Default::default(),
+        redirects: Default::default(),
+        encryption: Default::default(),
+        journal: Default::default(),
+        saved_searches: Default::default(),
+        chaos: Default::default(),
+        telemetry: Default::default(),
+        processes: Default::default(),
     };
 
     let override_path = PathBuf::from("rune-core/examples/config/override.json");
@@ -151,6 +166,7 @@ async fn demo_runtime_management() -> Result<()> {
             );
             cli
         },
+        profile: None,
         validation_enabled: true,
         strict_mode: false,
     };
@@ -206,6 +222,7 @@ async fn demo_validation_errors() -> Result<()> {
             cors_enabled: true,
             websocket_enabled: true,
             static_dir: None,
+            cors: Default::default(),
         },
         plugins: vec![
             PluginConfig {
@@ -237,6 +254,20 @@ async fn demo_validation_errors() -> Result<()> {
             ); // Warning: unknown setting
             settings
         },
+        export_hooks: Default::default(),
+        export_assets: Default::default(),
+        profiles: Default::default(),
+        log: Default::default(),
+        memory: Default::default(),
+        large_files: Default::default(),
+        link_previews: Default::default(),
+        redirects: Default::default(),
+        encryption: Default::default(),
+        journal: Default::default(),
+        saved_searches: Default::default(),
+        chaos: Default::default(),
+        telemetry: Default::default(),
+        processes: Default::default(),
     };
 
     println!("🔍 Validating intentionally invalid configuration...");
@@ -0,0 +1,138 @@
+## Command line
+
+cli-invalid-arguments = ❌ Invalid arguments:
+cli-export-failed = ❌ Export failed:
+cli-bench-failed = ❌ Benchmark failed:
+cli-fuzz-corpus-failed = ❌ Corpus replay failed:
+cli-lint-failed = ❌ Lint failed:
+cli-conformance-failed = ❌ Conformance run failed:
+cli-list-plugins-failed = ❌ Failed to list plugins:
+cli-config-invalid = ❌ Configuration validation failed:
+cli-tui-failed = ❌ Terminal interface failed:
+cli-list-themes-failed = ❌ Failed to list themes:
+cli-cache-failed = ❌ Failed to manage the plugin cache:
+cli-stats-failed = ❌ Failed to manage the usage statistics:
+cli-journal-failed = ❌ Failed to open the journal:
+cli-new-failed = ❌ Failed to create the document:
+cli-port-check-failed = ❌ Port check failed:
+
+startup-title = 🌟 Rune Markdown Live Editor
+startup-file = 📁 File: { $path }
+startup-server = 🌐 Server: http://{ $address }
+startup-server-unavailable = ⚠️  Server not available
+startup-journal = 📓 Journal note: { $url }
+startup-new-document = 📝 New document: { $url }
+startup-plugins = 🔌 Loaded plugins: { $count }
+startup-ready = ✨ Server ready! Press Ctrl+C to stop.
+
+themes-title = 🎨 Available Themes
+themes-dark = dark
+themes-light = light
+themes-count = 📊 { $count ->
+    [one] { $count } theme available
+   *[other] { $count } themes available
+}
+
+## Error help text
+
+error-file-not-found =
+    Markdown file not found: { $path }
+
+    Please check that:
+    • The file path is correct
+    • You have read permissions for the file
+    • The file hasn't been moved or deleted
+
+    Example: rune README.md
+error-path-is-directory =
+    Path is a directory, not a file: { $path }
+
+    Please specify a markdown file, not a directory.
+
+    Example: rune { $path }/README.md
+error-file-unreadable =
+    Cannot read file: { $path }
+    Error: { $error }
+
+    Please check that you have read permissions for this file.
+error-not-markdown =
+    File must be a markdown file (.md or .markdown): { $path }
+
+    Current extension: .{ $extension }
+
+    Supported extensions:
+    • .md
+    • .markdown
+
+    Example: rune document.md
+error-needs-pandoc =
+    Previewing .{ $extension } documents needs pandoc, which was not found: { $path }
+
+    Install pandoc from https://pandoc.org, or set RUNE_PANDOC to its path.
+error-no-extension =
+    File must have a markdown extension (.md or .markdown): { $path }
+
+    Please rename your file to include a proper extension.
+
+    Example: mv { $path } { $path }.md
+error-root-not-found =
+    Path not found: { $path }
+
+    Every path given to 'rune serve' must be an existing markdown file or directory.
+error-port-in-use =
+    Port { $port } is already in use on { $hostname }
+    Error: { $error }
+
+    { $suggestions }
+
+    You can also:
+    • Stop the process using port { $port }
+    • Use 'lsof -i :{ $port }' to find what's using the port
+    • Choose a different port with -p <port>
+error-port-try-another = Try using a different port number.
+error-port-try-these = Try one of these available ports:
+
+## Web interface
+
+ui-preview = Preview
+ui-raw = Raw
+ui-live = Live
+ui-save = Save
+ui-exit-editor = Exit Editor
+ui-edit = Edit
+ui-change-theme = Change theme
+ui-shortcuts = Keyboard Shortcuts
+ui-shortcut-save = Save
+ui-shortcut-toggle-mode = Toggle Raw/Live Mode
+ui-shortcut-toggle-preview = Toggle Preview
+ui-shortcut-bold = Bold
+ui-shortcut-italic = Italic
+ui-shortcut-theme = Change Theme
+ui-shortcut-indent = Indent
+ui-shortcut-escape = Exit Editor / Close Dialog
+ui-shortcut-help = Show/Hide Shortcuts
+ui-choose-theme = Choose Theme
+ui-reading-settings = Reading settings
+ui-font-size = Font size
+ui-line-width = Line width
+ui-font-family = Font
+ui-font-theme = Theme default
+ui-font-system = System
+ui-font-serif = Serif
+ui-font-sans = Sans-serif
+ui-font-mono = Monospace
+ui-reader-mode = Reader mode
+ui-reset = Reset
+ui-copy-as = Copy as
+ui-copy-html = Rich text (HTML)
+ui-copy-text = Plain text
+
+## Themes
+
+theme-light = Light
+theme-dark = Dark
+theme-catppuccin-latte-description = Warm light theme
+theme-catppuccin-macchiato-description = Medium contrast
+theme-catppuccin-mocha-description = Dark and cozy
+theme-light-description = Classic bright
+theme-dark-description = Classic dark
@@ -0,0 +1,135 @@
+## Command line
+
+cli-invalid-arguments = ❌ 参数无效：
+cli-export-failed = ❌ 导出失败：
+cli-bench-failed = ❌ 基准测试失败：
+cli-fuzz-corpus-failed = ❌ 语料回放失败：
+cli-lint-failed = ❌ 检查失败：
+cli-conformance-failed = ❌ 规范一致性测试失败：
+cli-list-plugins-failed = ❌ 无法列出插件：
+cli-config-invalid = ❌ 配置校验失败：
+cli-tui-failed = ❌ 终端界面出错：
+cli-list-themes-failed = ❌ 无法列出主题：
+cli-cache-failed = ❌ 无法管理插件缓存：
+cli-stats-failed = ❌ 无法管理使用统计：
+cli-journal-failed = ❌ 无法打开日记：
+cli-new-failed = ❌ 无法创建文档：
+cli-port-check-failed = ❌ 端口检查失败：
+
+startup-title = 🌟 Rune Markdown 实时编辑器
+startup-file = 📁 文件：{ $path }
+startup-server = 🌐 服务器：http://{ $address }
+startup-server-unavailable = ⚠️  服务器不可用
+startup-journal = 📓 日记：{ $url }
+startup-new-document = 📝 新文档：{ $url }
+startup-plugins = 🔌 已加载插件：{ $count }
+startup-ready = ✨ 服务器已就绪！按 Ctrl+C 停止。
+
+themes-title = 🎨 可用主题
+themes-dark = 深色
+themes-light = 浅色
+themes-count = 📊 共 { $count } 个可用主题
+
+## Error help text
+
+error-file-not-found =
+    找不到 Markdown 文件：{ $path }
+
+    请检查：
+    • 文件路径是否正确
+    • 是否有读取该文件的权限
+    • 文件是否已被移动或删除
+
+    示例：rune README.md
+error-path-is-directory =
+    路径是目录而不是文件：{ $path }
+
+    请指定一个 Markdown 文件，而不是目录。
+
+    示例：rune { $path }/README.md
+error-file-unreadable =
+    无法读取文件：{ $path }
+    错误：{ $error }
+
+    请检查是否有读取该文件的权限。
+error-not-markdown =
+    文件必须是 Markdown 文件（.md 或 .markdown）：{ $path }
+
+    当前扩展名：.{ $extension }
+
+    支持的扩展名：
+    • .md
+    • .markdown
+
+    示例：rune document.md
+error-needs-pandoc =
+    预览 .{ $extension } 文档需要 pandoc，但未找到：{ $path }
+
+    请从 https://pandoc.org 安装 pandoc，或将 RUNE_PANDOC 设为其路径。
+error-no-extension =
+    文件必须带有 Markdown 扩展名（.md 或 .markdown）：{ $path }
+
+    请为文件加上正确的扩展名。
+
+    示例：mv { $path } { $path }.md
+error-root-not-found =
+    找不到路径：{ $path }
+
+    传给 'rune serve' 的每个路径都必须是已存在的 Markdown 文件或目录。
+error-port-in-use =
+    端口 { $port } 在 { $hostname } 上已被占用
+    错误：{ $error }
+
+    { $suggestions }
+
+    你还可以：
+    • 停止占用端口 { $port } 的进程
+    • 使用 'lsof -i :{ $port }' 查看占用端口的程序
+    • 使用 -p <port> 选择其他端口
+error-port-try-another = 请尝试使用其他端口号。
+error-port-try-these = 可以尝试以下空闲端口：
+
+## Web interface
+
+ui-preview = 预览
+ui-raw = 源码
+ui-live = 实时
+ui-save = 保存
+ui-exit-editor = 退出编辑器
+ui-edit = 编辑
+ui-change-theme = 切换主题
+ui-shortcuts = 键盘快捷键
+ui-shortcut-save = 保存
+ui-shortcut-toggle-mode = 切换源码/实时模式
+ui-shortcut-toggle-preview = 切换预览
+ui-shortcut-bold = 粗体
+ui-shortcut-italic = 斜体
+ui-shortcut-theme = 切换主题
+ui-shortcut-indent = 缩进
+ui-shortcut-escape = 退出编辑器 / 关闭对话框
+ui-shortcut-help = 显示/隐藏快捷键
+ui-choose-theme = 选择主题
+ui-reading-settings = 阅读设置
+ui-font-size = 字号
+ui-line-width = 行宽
+ui-font-family = 字体
+ui-font-theme = 主题默认
+ui-font-system = 系统
+ui-font-serif = 衬线
+ui-font-sans = 无衬线
+ui-font-mono = 等宽
+ui-reader-mode = 阅读模式
+ui-reset = 重置
+ui-copy-as = 复制为
+ui-copy-html = 富文本（HTML）
+ui-copy-text = 纯文本
+
+## Themes
+
+theme-light = 浅色
+theme-dark = 深色
+theme-catppuccin-latte-description = 温暖的浅色主题
+theme-catppuccin-macchiato-description = 中等对比度
+theme-catppuccin-mocha-description = 深色而舒适
+theme-light-description = 经典明亮
+theme-dark-description = 经典深色
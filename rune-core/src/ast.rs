@@ -205,6 +205,8 @@ impl Node {
     pub fn text_content(&self) -> String {
         match self.node_type {
             NodeType::Text => self.data.clone(),
+            NodeType::SoftBreak => " ".to_string(),
+            NodeType::LineBreak => "\n".to_string(),
             _ => self
                 .children
                 .iter()
@@ -0,0 +1,242 @@
+//! Fault injection for exercising failure handling
+//!
+//! Health checks, supervisor restarts and panic recovery only run when
+//! something breaks, which is rarely while developing. In development mode a
+//! [`ChaosInjector`], configured by the `chaos` section of the configuration,
+//! makes things break on purpose: event handlers and supervised tasks, such
+//! as the server loop or the file watcher, are randomly delayed, failed or
+//! made to panic before they run, with the probabilities configured.
+//!
+//! ```json
+//! "chaos": {"error_probability": 0.05, "panic_probability": 0.01,
+//!           "targets": ["file-watcher"], "seed": 7}
+//! ```
+//!
+//! Outside development mode the section is ignored.
+
+use crate::config::{ChaosConfig, Config};
+use crate::error::{Result, RuneError};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Longest injected delay when none is configured
+pub const DEFAULT_CHAOS_MAX_DELAY: Duration = Duration::from_millis(500);
+
+/// Fault injected into a call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The call runs after this delay
+    Delay(Duration),
+    /// The call fails without running
+    Error,
+    /// The call panics without running
+    Panic,
+}
+
+/// Faults injected so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChaosStats {
+    pub delays: u64,
+    pub errors: u64,
+    pub panics: u64,
+}
+
+/// Randomly injects faults into the calls it is asked about
+#[derive(Debug)]
+pub struct ChaosInjector {
+    config: ChaosConfig,
+    /// State of the random choices
+    state: AtomicU64,
+    delays: AtomicU64,
+    errors: AtomicU64,
+    panics: AtomicU64,
+}
+
+impl ChaosInjector {
+    pub fn new(config: ChaosConfig) -> Self {
+        let seed = config.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+        Self {
+            config,
+            state: AtomicU64::new(seed),
+            delays: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            panics: AtomicU64::new(0),
+        }
+    }
+
+    /// Injector of `config`, if it is in development mode and asks for faults
+    pub fn from_config(config: &Config) -> Option<Arc<Self>> {
+        if !config.chaos.is_active() {
+            return None;
+        }
+        if config.get_global_setting::<bool>("dev_mode") != Some(true) {
+            warn!("Chaos settings are ignored outside development mode");
+            return None;
+        }
+        warn!(
+            "Chaos injection active: delays {:.0}%, errors {:.0}%, panics {:.0}% of calls to {}",
+            config.chaos.delay_probability.unwrap_or_default() * 100.0,
+            config.chaos.error_probability.unwrap_or_default() * 100.0,
+            config.chaos.panic_probability.unwrap_or_default() * 100.0,
+            if config.chaos.targets.is_empty() {
+                "every handler and task".to_string()
+            } else {
+                config.chaos.targets.join(", ")
+            }
+        );
+        Some(Arc::new(Self::new(config.chaos.clone())))
+    }
+
+    /// Whether faults are injected into calls to `target`
+    pub fn targets(&self, target: &str) -> bool {
+        self.config.targets.is_empty() || self.config.targets.iter().any(|name| name == target)
+    }
+
+    /// Fault to inject into a call to `target`, if any; panics are chosen
+    /// first, then errors, then delays
+    pub fn roll(&self, target: &str) -> Option<Fault> {
+        if !self.targets(target) {
+            return None;
+        }
+        let chance = |probability: Option<f64>| self.next_unit() < probability.unwrap_or_default();
+        let fault = if chance(self.config.panic_probability) {
+            Fault::Panic
+        } else if chance(self.config.error_probability) {
+            Fault::Error
+        } else if chance(self.config.delay_probability) {
+            let max = self.config.max_delay().as_millis() as u64;
+            Fault::Delay(Duration::from_millis(self.next() % (max + 1)))
+        } else {
+            return None;
+        };
+        let counter = match fault {
+            Fault::Delay(_) => &self.delays,
+            Fault::Error => &self.errors,
+            Fault::Panic => &self.panics,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        Some(fault)
+    }
+
+    /// Run before a call to `target`: waits, fails or panics if a fault is
+    /// rolled, and returns `Ok` to let the call go ahead otherwise
+    pub async fn inject(&self, target: &str) -> Result<()> {
+        match self.roll(target) {
+            None => Ok(()),
+            Some(Fault::Delay(delay)) => {
+                warn!("Chaos: delaying {} by {:?}", target, delay);
+                tokio::time::sleep(delay).await;
+                Ok(())
+            }
+            Some(Fault::Error) => {
+                warn!("Chaos: failing {}", target);
+                Err(RuneError::generic(format!(
+                    "Chaos: injected failure in {}",
+                    target
+                )))
+            }
+            Some(Fault::Panic) => {
+                warn!("Chaos: panicking in {}", target);
+                panic!("Chaos: injected panic in {}", target);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> ChaosStats {
+        ChaosStats {
+            delays: self.delays.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            panics: self.panics.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Next random number, from splitmix64
+    fn next(&self) -> u64 {
+        let mut z = self
+            .state
+            .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Random number in `0..1`
+    fn next_unit(&self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crash::catch_panic;
+
+    fn injector(config: ChaosConfig) -> ChaosInjector {
+        ChaosInjector::new(ChaosConfig {
+            seed: Some(7),
+            ..config
+        })
+    }
+
+    #[test]
+    fn test_faults_follow_their_probabilities() {
+        let chaos = injector(ChaosConfig {
+            error_probability: Some(0.25),
+            delay_probability: Some(1.0),
+            max_delay_ms: Some(10),
+            ..Default::default()
+        });
+        let faults: Vec<Fault> = (0..1000).filter_map(|_| chaos.roll("server")).collect();
+        assert_eq!(faults.len(), 1000);
+        let stats = chaos.stats();
+        assert!((180..320).contains(&stats.errors), "{:?}", stats);
+        assert_eq!(stats.delays + stats.errors, 1000);
+        assert_eq!(stats.panics, 0);
+        assert!(faults.iter().all(|fault| match fault {
+            Fault::Delay(delay) => *delay <= Duration::from_millis(10),
+            _ => true,
+        }));
+
+        // The same seed rolls the same faults
+        let again = injector(chaos.config.clone());
+        let replayed: Vec<Fault> = (0..1000).filter_map(|_| again.roll("server")).collect();
+        assert_eq!(faults, replayed);
+    }
+
+    #[tokio::test]
+    async fn test_only_targets_get_faults() {
+        let chaos = injector(ChaosConfig {
+            panic_probability: Some(1.0),
+            targets: vec!["file-watcher".to_string()],
+            ..Default::default()
+        });
+        assert!(chaos.inject("server").await.is_ok());
+        let report = catch_panic("file-watcher", chaos.inject("file-watcher"))
+            .await
+            .unwrap_err();
+        assert!(report.message.contains("injected panic in file-watcher"));
+        assert_eq!(chaos.stats().panics, 1);
+    }
+
+    #[test]
+    fn test_injection_needs_development_mode() {
+        let mut config = Config::new();
+        config.chaos.error_probability = Some(0.5);
+        assert!(ChaosInjector::from_config(&config).is_none());
+        config
+            .set_global_setting("dev_mode".to_string(), true)
+            .unwrap();
+        assert!(ChaosInjector::from_config(&config).is_some());
+
+        config.chaos.error_probability = Some(0.0);
+        assert!(ChaosInjector::from_config(&config).is_none());
+    }
+}
@@ -0,0 +1,277 @@
+//! Commands of the command palette
+//!
+//! Every action a user can invoke by name, like making text bold, running a
+//! transform or switching the theme, is a [`Command`] with an id, a title and
+//! possibly a keybinding. Some run in the browser, where the editor is, and
+//! answer with the action for the page to perform; others run on the server.
+//! Every [`CommandRegistry`] starts with the editor's commands; plugins add
+//! their own to the registry shared as [`COMMAND_REGISTRY_RESOURCE`].
+
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::error::{Result, RuneError};
+use crate::plugin::PluginContext;
+
+/// Shared resource key of the [`CommandRegistry`], an `Arc<CommandRegistry>`
+pub const COMMAND_REGISTRY_RESOURCE: &str = "command_registry";
+
+/// Arguments given to a command, as sent to the command API
+pub type CommandArgs = serde_json::Map<String, serde_json::Value>;
+
+/// A command as listed in the palette
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommandInfo {
+    /// Id the command is executed by, like `editor.bold`
+    pub id: String,
+    pub title: String,
+    /// Group of the command in the palette, like `Editor`
+    pub category: String,
+    /// Keys invoking the command, like `Mod+B`, where `Mod` is Cmd on macOS
+    /// and Ctrl elsewhere
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keybinding: Option<String>,
+}
+
+impl CommandInfo {
+    pub fn new(id: &str, title: &str, category: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            title: title.to_string(),
+            category: category.to_string(),
+            keybinding: None,
+        }
+    }
+
+    pub fn with_keybinding(mut self, keybinding: &str) -> Self {
+        self.keybinding = Some(keybinding.to_string());
+        self
+    }
+}
+
+/// What executing a command came to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CommandOutcome {
+    /// The page performs `action` itself
+    Client { action: String },
+    /// The page fetches `url` and saves it as `file_name`
+    Download { url: String, file_name: String },
+    /// The server did it
+    Done {
+        message: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        data: Option<serde_json::Value>,
+    },
+}
+
+/// An action invocable from the command palette
+#[async_trait]
+pub trait Command: Send + Sync {
+    /// Id, title and keybinding of the command
+    fn info(&self) -> CommandInfo;
+
+    /// Run the command with `args`
+    async fn execute(&self, args: &CommandArgs) -> Result<CommandOutcome>;
+}
+
+/// A command the page performs, answered with its own id as the action
+pub struct ClientCommand {
+    info: CommandInfo,
+}
+
+impl ClientCommand {
+    pub fn new(info: CommandInfo) -> Self {
+        Self { info }
+    }
+}
+
+#[async_trait]
+impl Command for ClientCommand {
+    fn info(&self) -> CommandInfo {
+        self.info.clone()
+    }
+
+    async fn execute(&self, _args: &CommandArgs) -> Result<CommandOutcome> {
+        Ok(CommandOutcome::Client {
+            action: self.info.id.clone(),
+        })
+    }
+}
+
+/// Commands of the editor page and their keybindings
+const EDITOR_COMMANDS: &[(&str, &str, Option<&str>)] = &[
+    ("editor.save", "Save document", Some("Mod+S")),
+    (
+        "editor.toggle-mode",
+        "Toggle raw and live editing",
+        Some("Mod+E"),
+    ),
+    ("editor.preview", "Show preview", Some("Mod+/")),
+    ("editor.bold", "Bold", Some("Mod+B")),
+    ("editor.italic", "Italic", Some("Mod+I")),
+    ("editor.indent-list", "Indent list item", Some("Tab")),
+    (
+        "editor.unindent-list",
+        "Unindent list item",
+        Some("Shift+Tab"),
+    ),
+    ("editor.shortcuts", "Show keyboard shortcuts", Some("?")),
+    ("editor.exit", "Exit editor", Some("Escape")),
+    ("theme.choose", "Choose theme", Some("Mod+T")),
+];
+
+/// Registry of the commands of the palette
+pub struct CommandRegistry {
+    commands: RwLock<BTreeMap<String, Arc<dyn Command>>>,
+}
+
+impl CommandRegistry {
+    /// Create a registry holding the editor's commands
+    pub fn new() -> Self {
+        let commands = EDITOR_COMMANDS
+            .iter()
+            .map(|&(id, title, keybinding)| {
+                let category = if id.starts_with("theme.") {
+                    "Theme"
+                } else {
+                    "Editor"
+                };
+                let mut info = CommandInfo::new(id, title, category);
+                info.keybinding = keybinding.map(str::to_string);
+                let command: Arc<dyn Command> = Arc::new(ClientCommand::new(info));
+                (id.to_string(), command)
+            })
+            .collect();
+        Self {
+            commands: RwLock::new(commands),
+        }
+    }
+
+    /// The registry shared between plugins, created on first use
+    pub async fn shared(context: &PluginContext) -> Result<Arc<CommandRegistry>> {
+        if let Some(registry) = context
+            .get_shared_resource::<Arc<CommandRegistry>>(COMMAND_REGISTRY_RESOURCE)
+            .await
+        {
+            return Ok(registry.as_ref().clone());
+        }
+        let registry = Arc::new(CommandRegistry::new());
+        context
+            .set_shared_resource(COMMAND_REGISTRY_RESOURCE.to_string(), registry.clone())
+            .await?;
+        Ok(registry)
+    }
+
+    /// Register a command
+    pub async fn register_command(&self, command: Arc<dyn Command>) -> Result<()> {
+        let id = command.info().id;
+        let mut commands = self.commands.write().await;
+        if commands.contains_key(&id) {
+            return Err(RuneError::Plugin(format!(
+                "Command '{}' is already registered",
+                id
+            )));
+        }
+        commands.insert(id.clone(), command);
+        tracing::info!("Registered command: {}", id);
+        Ok(())
+    }
+
+    /// Unregister a command
+    pub async fn unregister_command(&self, id: &str) -> Result<()> {
+        match self.commands.write().await.remove(id) {
+            Some(_) => Ok(()),
+            None => Err(RuneError::Plugin(format!(
+                "Command '{}' is not registered",
+                id
+            ))),
+        }
+    }
+
+    /// Registered commands, by id
+    pub async fn list_commands(&self) -> Vec<CommandInfo> {
+        self.commands
+            .read()
+            .await
+            .values()
+            .map(|command| command.info())
+            .collect()
+    }
+
+    /// The command `id`, if registered
+    pub async fn command(&self, id: &str) -> Option<Arc<dyn Command>> {
+        self.commands.read().await.get(id).cloned()
+    }
+
+    /// Run the command `id` with `args`
+    pub async fn execute(&self, id: &str, args: &CommandArgs) -> Result<CommandOutcome> {
+        let command = self
+            .command(id)
+            .await
+            .ok_or_else(|| RuneError::config(format!("Unknown command '{}'", id)))?;
+        command.execute(args).await
+    }
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_registry_runs_editor_and_plugin_commands() {
+        struct Greet;
+        #[async_trait]
+        impl Command for Greet {
+            fn info(&self) -> CommandInfo {
+                CommandInfo::new("greet", "Greet", "Plugins").with_keybinding("Mod+G")
+            }
+            async fn execute(&self, args: &CommandArgs) -> Result<CommandOutcome> {
+                let name = args.get("name").and_then(|name| name.as_str());
+                Ok(CommandOutcome::Done {
+                    message: format!("Hello, {}", name.unwrap_or("world")),
+                    data: None,
+                })
+            }
+        }
+
+        let registry = CommandRegistry::new();
+        let args = CommandArgs::new();
+        assert_eq!(
+            registry.execute("editor.bold", &args).await.unwrap(),
+            CommandOutcome::Client {
+                action: "editor.bold".to_string()
+            }
+        );
+        let bold = registry.list_commands().await;
+        let bold = bold.iter().find(|info| info.id == "editor.bold").unwrap();
+        assert_eq!(bold.keybinding.as_deref(), Some("Mod+B"));
+
+        registry.register_command(Arc::new(Greet)).await.unwrap();
+        assert!(registry.register_command(Arc::new(Greet)).await.is_err());
+        let args = serde_json::json!({ "name": "Ada" });
+        let outcome = registry
+            .execute("greet", args.as_object().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(outcome).unwrap(),
+            serde_json::json!({ "kind": "done", "message": "Hello, Ada" })
+        );
+
+        registry.unregister_command("greet").await.unwrap();
+        assert!(registry
+            .execute("greet", &CommandArgs::new())
+            .await
+            .is_err());
+    }
+}
@@ -1,11 +1,14 @@
 //! Configuration management for the Rune system
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
+use std::sync::RwLock;
 use std::time::SystemTime;
 
 use crate::error::{Result, RuneError};
+use crate::redirects::RedirectRule;
+use crate::search::SavedSearch;
 
 /// Main system configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,6 +16,53 @@ pub struct Config {
     pub server: ServerConfig,
     pub plugins: Vec<PluginConfig>,
     pub global_settings: HashMap<String, serde_json::Value>,
+    #[serde(default, skip_serializing_if = "ExportHooks::is_empty")]
+    pub export_hooks: ExportHooks,
+    /// Image optimization of `rune export --optimize-assets`
+    #[serde(default, skip_serializing_if = "ExportAssetConfig::is_empty")]
+    pub export_assets: ExportAssetConfig,
+    /// Named overlays selected with `--profile`, e.g. `profiles.dev`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub profiles: HashMap<String, ConfigProfile>,
+    /// Log levels, e.g. `"log": {"levels": {"rune_server": "debug"}}`
+    #[serde(default, skip_serializing_if = "LogConfig::is_empty")]
+    pub log: LogConfig,
+    /// Memory budget of caches and buffers, e.g. `"memory": {"budget_mb": 128}`
+    #[serde(default, skip_serializing_if = "MemoryConfig::is_empty")]
+    pub memory: MemoryConfig,
+    /// When documents count as large, e.g. `"large_files": {"threshold_mb": 50}`
+    #[serde(default, skip_serializing_if = "LargeFileConfig::is_empty")]
+    pub large_files: LargeFileConfig,
+    /// Preview cards for bare links, e.g. `"link_previews": {"enabled": true}`
+    #[serde(default, skip_serializing_if = "LinkPreviewConfig::is_empty")]
+    pub link_previews: LinkPreviewConfig,
+    /// Old URL paths and where they moved, e.g. `"redirects": {"/old.md": "/new.md"}`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub redirects: BTreeMap<String, RedirectRule>,
+    /// Passphrases of encrypted documents, e.g.
+    /// `"encryption": {"passphrase_command": "secret-tool lookup rune notes"}`
+    #[serde(default, skip_serializing_if = "EncryptionConfig::is_empty")]
+    pub encryption: EncryptionConfig,
+    /// Daily notes of `rune journal`, e.g.
+    /// `"journal": {"dir": "~/notes", "path": "journal/%Y/%Y-%m-%d.md"}`
+    #[serde(default, skip_serializing_if = "JournalConfig::is_empty")]
+    pub journal: JournalConfig,
+    /// Named queries shown as folders of the served documents, e.g.
+    /// `"saved_searches": {"Drafts": {"tags": ["draft"], "text": "TODO"}}`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub saved_searches: BTreeMap<String, SavedSearch>,
+    /// Faults injected in development mode, e.g.
+    /// `"chaos": {"error_probability": 0.05}`; see [`crate::chaos`]
+    #[serde(default, skip_serializing_if = "ChaosConfig::is_empty")]
+    pub chaos: ChaosConfig,
+    /// Local usage statistics, e.g. `"telemetry": {"enabled": true}`; see
+    /// [`crate::telemetry`]
+    #[serde(default, skip_serializing_if = "TelemetryConfig::is_empty")]
+    pub telemetry: TelemetryConfig,
+    /// Limits on external programs, e.g.
+    /// `"processes": {"allowed_programs": ["pandoc"]}`; see [`crate::process`]
+    #[serde(default, skip_serializing_if = "ProcessConfig::is_empty")]
+    pub processes: ProcessConfig,
 }
 
 impl Config {
@@ -22,6 +72,20 @@ impl Config {
             server: ServerConfig::default(),
             plugins: Vec::new(),
             global_settings: HashMap::new(),
+            export_hooks: ExportHooks::default(),
+            export_assets: ExportAssetConfig::default(),
+            profiles: HashMap::new(),
+            log: LogConfig::default(),
+            memory: MemoryConfig::default(),
+            large_files: LargeFileConfig::default(),
+            link_previews: LinkPreviewConfig::default(),
+            redirects: BTreeMap::new(),
+            encryption: EncryptionConfig::default(),
+            journal: JournalConfig::default(),
+            saved_searches: BTreeMap::new(),
+            chaos: ChaosConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            processes: ProcessConfig::default(),
         }
     }
 
@@ -125,13 +189,38 @@ impl Config {
         // Check for plugin dependency cycles
         self.validate_plugin_dependencies(&mut result);
 
+        // Validate export hooks
+        self.validate_export_hooks(&mut result);
+        self.export_assets.validate("export_assets", &mut result);
+
+        // Validate log levels
+        self.log.validate("log", &mut result);
+        self.memory.validate("memory", &mut result);
+        self.large_files.validate("large_files", &mut result);
+        self.link_previews.validate("link_previews", &mut result);
+        self.journal.validate("journal", &mut result);
+        self.chaos.validate("chaos", &mut result);
+        self.telemetry.validate("telemetry", &mut result);
+        self.processes.validate("processes", &mut result);
+        self.validate_redirects(&mut result);
+        self.validate_saved_searches(&mut result);
+
+        // Validate every profile as it would be applied
+        self.validate_profiles(&mut result);
+
         // Set overall validity
         result.is_valid = result.errors.is_empty();
 
         if !result.is_valid {
+            let details: String = result
+                .errors
+                .iter()
+                .map(|error| format!("\n  • {}: {}", error.field_path, error.message))
+                .collect();
             return Err(RuneError::Config(format!(
-                "Configuration validation failed with {} errors",
-                result.errors.len()
+                "Configuration validation failed with {} errors{}",
+                result.errors.len(),
+                details
             )));
         }
 
@@ -169,6 +258,8 @@ impl Config {
                 });
             }
         }
+
+        self.server.cors.validate("server.cors", result);
     }
 
     /// Validate plugin configuration against schema
@@ -220,6 +311,183 @@ impl Config {
                 suggested_fix: Some(format!("Remove '{}' from dependencies", plugin.name)),
             });
         }
+
+        // Plugins that registered a schema have their own keys checked too
+        let Some(keys) = plugin_schema(&plugin.name) else {
+            return;
+        };
+        for (key, field_schema) in &keys {
+            if field_schema.required && !plugin.config.contains_key(key) {
+                result.errors.push(ValidationError {
+                    field_path: format!("{}.config.{}", base_path, key),
+                    error_type: ValidationErrorType::MissingRequired,
+                    message: format!("Plugin '{}' requires the key '{}'", plugin.name, key),
+                    suggested_fix: field_schema
+                        .default_value
+                        .as_ref()
+                        .map(|v| format!("Add '{}': {}", key, v)),
+                });
+            }
+        }
+        for (key, value) in &plugin.config {
+            let field_path = format!("{}.config.{}", base_path, key);
+            match keys.get(key) {
+                Some(field_schema) => {
+                    if let Err(error) = self.validate_field_value(&field_path, value, field_schema)
+                    {
+                        result.errors.push(error);
+                    }
+                }
+                None => {
+                    let closest = keys
+                        .keys()
+                        .map(|known| (edit_distance(key, known), known))
+                        .filter(|(distance, _)| *distance <= 2)
+                        .min();
+                    result.warnings.push(ValidationWarning {
+                        field_path,
+                        warning_type: ValidationWarningType::UnknownField,
+                        message: format!("Plugin '{}' has no key '{}'", plugin.name, key),
+                        suggestion: Some(match closest {
+                            Some((_, known)) => format!("Did you mean '{}'?", known),
+                            None => "Remove this key or check for typos".to_string(),
+                        }),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Validate profiles by checking the configuration each one produces
+    fn validate_profiles(&self, result: &mut ValidationResult) {
+        let schema = ConfigSchema::default();
+        let mut names: Vec<&String> = self.profiles.keys().collect();
+        names.sort();
+
+        for name in names {
+            let prefix = format!("profiles.{}", name);
+            let profiled = match self.resolve_profile(name) {
+                Ok(profiled) => profiled,
+                Err(e) => {
+                    let message = match e {
+                        RuneError::Config(message) => message,
+                        other => other.to_string(),
+                    };
+                    result.errors.push(ValidationError {
+                        field_path: format!("{}.extends", prefix),
+                        error_type: ValidationErrorType::DependencyError,
+                        message,
+                        suggested_fix: Some(
+                            "Extend an existing profile without cycles".to_string(),
+                        ),
+                    });
+                    continue;
+                }
+            };
+
+            // Only report problems the profile introduces, not the base config's own
+            let mut profile_result = ValidationResult {
+                is_valid: true,
+                errors: Vec::new(),
+                warnings: Vec::new(),
+            };
+            profiled.validate_server_config(&schema.server_schema, &mut profile_result);
+            for plugin in &profiled.plugins {
+                profiled.validate_plugin_config(plugin, &schema.plugin_schema, &mut profile_result);
+            }
+            profiled.validate_plugin_dependencies(&mut profile_result);
+            profiled.log.validate("log", &mut profile_result);
+            profiled.memory.validate("memory", &mut profile_result);
+            profiled
+                .large_files
+                .validate("large_files", &mut profile_result);
+            profiled
+                .link_previews
+                .validate("link_previews", &mut profile_result);
+            profiled.chaos.validate("chaos", &mut profile_result);
+
+            let base_errors: std::collections::HashSet<(String, String)> = result
+                .errors
+                .iter()
+                .map(|e| (e.field_path.clone(), e.message.clone()))
+                .collect();
+            for mut error in profile_result.errors {
+                if !base_errors.contains(&(error.field_path.clone(), error.message.clone())) {
+                    error.field_path = format!("{}.{}", prefix, error.field_path);
+                    result.errors.push(error);
+                }
+            }
+        }
+    }
+
+    /// Validate pre/post export hooks
+    fn validate_export_hooks(&self, result: &mut ValidationResult) {
+        let stages = [
+            ("pre", &self.export_hooks.pre),
+            ("post", &self.export_hooks.post),
+        ];
+        for (stage, hooks) in stages {
+            for (index, hook) in hooks.iter().enumerate() {
+                let (kind, target) = match &hook.action {
+                    ExportHookAction::Command(command) => ("command", command),
+                    ExportHookAction::Plugin(plugin) => ("plugin", plugin),
+                };
+                if target.trim().is_empty() {
+                    result.errors.push(ValidationError {
+                        field_path: format!("export_hooks.{}[{}].{}", stage, index, kind),
+                        error_type: ValidationErrorType::MissingRequired,
+                        message: format!("Export hook {} must not be empty", kind),
+                        suggested_fix: Some(format!("Remove the hook or set its {}", kind)),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Validate that redirects go from server paths to somewhere
+    fn validate_redirects(&self, result: &mut ValidationResult) {
+        for (from, rule) in &self.redirects {
+            if !from.starts_with('/') {
+                result.errors.push(ValidationError {
+                    field_path: format!("redirects.{}", from),
+                    error_type: ValidationErrorType::InvalidValue,
+                    message: "Redirected paths must start with '/'".to_string(),
+                    suggested_fix: Some(format!("Use \"/{}\"", from)),
+                });
+            }
+            if rule.target().trim().is_empty() {
+                result.errors.push(ValidationError {
+                    field_path: format!("redirects.{}", from),
+                    error_type: ValidationErrorType::MissingRequired,
+                    message: "Redirect target must not be empty".to_string(),
+                    suggested_fix: Some("Remove the redirect or set where it leads".to_string()),
+                });
+            }
+        }
+    }
+
+    /// Validate that saved searches filter something and have a page name
+    fn validate_saved_searches(&self, result: &mut ValidationResult) {
+        for (name, search) in &self.saved_searches {
+            if crate::search::slug(name).is_empty() {
+                result.errors.push(ValidationError {
+                    field_path: format!("saved_searches.{}", name),
+                    error_type: ValidationErrorType::InvalidValue,
+                    message: "Saved search names need a letter or digit".to_string(),
+                    suggested_fix: Some("Rename the search".to_string()),
+                });
+            }
+            if search.is_empty() {
+                result.errors.push(ValidationError {
+                    field_path: format!("saved_searches.{}", name),
+                    error_type: ValidationErrorType::MissingRequired,
+                    message: "Saved search has no text, tags or frontmatter to match".to_string(),
+                    suggested_fix: Some(
+                        "Set at least one of text, tags or frontmatter".to_string(),
+                    ),
+                });
+            }
+        }
     }
 
     /// Validate global settings against schema
@@ -520,6 +788,11 @@ impl Config {
             }
         }
 
+        // Apply the selected profile
+        if let Some(profile) = &context.profile {
+            config.apply_profile(profile)?;
+        }
+
         // Apply environment variable overrides
         config.apply_environment_overrides(&context.environment_overrides)?;
 
@@ -704,6 +977,24 @@ impl Config {
             });
         }
 
+        if self.server.cors_enabled != other.server.cors_enabled {
+            diff.server_changes.push(ConfigChange {
+                field: "cors_enabled".to_string(),
+                old_value: Some(serde_json::Value::Bool(self.server.cors_enabled)),
+                new_value: Some(serde_json::Value::Bool(other.server.cors_enabled)),
+                change_type: ConfigChangeType::Modified,
+            });
+        }
+
+        if self.server.websocket_enabled != other.server.websocket_enabled {
+            diff.server_changes.push(ConfigChange {
+                field: "websocket_enabled".to_string(),
+                old_value: Some(serde_json::Value::Bool(self.server.websocket_enabled)),
+                new_value: Some(serde_json::Value::Bool(other.server.websocket_enabled)),
+                change_type: ConfigChangeType::Modified,
+            });
+        }
+
         // Compare plugins (simplified - could be more detailed)
         let self_plugin_names: std::collections::HashSet<_> =
             self.plugins.iter().map(|p| &p.name).collect();
@@ -730,6 +1021,20 @@ impl Config {
             });
         }
 
+        // Find plugins whose settings changed
+        for plugin in &self.plugins {
+            if let Some(other_plugin) = other.get_plugin_config(&plugin.name) {
+                if plugin.enabled != other_plugin.enabled || plugin.config != other_plugin.config {
+                    diff.plugin_changes.push(ConfigChange {
+                        field: plugin.name.clone(),
+                        old_value: serde_json::to_value(plugin).ok(),
+                        new_value: serde_json::to_value(other_plugin).ok(),
+                        change_type: ConfigChangeType::Modified,
+                    });
+                }
+            }
+        }
+
         // Compare global settings
         for (key, old_value) in &self.global_settings {
             match other.global_settings.get(key) {
@@ -782,6 +1087,7 @@ impl Config {
         }
         self.server.cors_enabled = other.server.cors_enabled;
         self.server.websocket_enabled = other.server.websocket_enabled;
+        self.server.cors.merge(other.server.cors);
 
         // Merge plugin configurations
         for other_plugin in other.plugins {
@@ -818,9 +1124,172 @@ impl Config {
             self.global_settings.insert(key, value);
         }
 
+        // Hooks are appended so an override file can add steps to the base pipeline
+        self.export_hooks.pre.extend(other.export_hooks.pre);
+        self.export_hooks.post.extend(other.export_hooks.post);
+        self.export_assets.merge(other.export_assets);
+
+        // Profiles of an override file replace base profiles of the same name
+        self.profiles.extend(other.profiles);
+
+        self.log.merge(other.log);
+        self.memory.merge(other.memory);
+        self.large_files.merge(other.large_files);
+        self.link_previews.merge(other.link_previews);
+        self.encryption.merge(other.encryption);
+        self.journal.merge(other.journal);
+        self.chaos.merge(other.chaos);
+        self.telemetry.merge(other.telemetry);
+        self.processes.merge(other.processes);
+        // Saved searches of an override file replace those of the same name
+        self.saved_searches.extend(other.saved_searches);
+
+        Ok(())
+    }
+
+    /// Change a single setting addressed like `server.port`, `global.<key>`,
+    /// `plugin.<name>.enabled` or `plugin.<name>.<key>`
+    pub fn apply_update(&mut self, key: &str, value: serde_json::Value) -> Result<()> {
+        fn invalid(key: &str, expected: &str) -> RuneError {
+            RuneError::Config(format!("'{}' must be {}", key, expected))
+        }
+
+        if let Some(field) = key.strip_prefix("server.") {
+            match field {
+                "hostname" => {
+                    self.server.hostname = value
+                        .as_str()
+                        .ok_or_else(|| invalid(key, "a string"))?
+                        .to_string();
+                }
+                "port" => {
+                    self.server.port = value
+                        .as_u64()
+                        .and_then(|port| u16::try_from(port).ok())
+                        .ok_or_else(|| invalid(key, "a port number"))?;
+                }
+                "cors_enabled" => {
+                    self.server.cors_enabled =
+                        value.as_bool().ok_or_else(|| invalid(key, "a boolean"))?;
+                }
+                "websocket_enabled" => {
+                    self.server.websocket_enabled =
+                        value.as_bool().ok_or_else(|| invalid(key, "a boolean"))?;
+                }
+                _ => {
+                    return Err(RuneError::Config(format!(
+                        "Unknown configuration key: {}",
+                        key
+                    )))
+                }
+            }
+        } else if let Some(setting) = key.strip_prefix("global.") {
+            self.global_settings.insert(setting.to_string(), value);
+        } else if let Some((name, setting)) = key
+            .strip_prefix("plugin.")
+            .and_then(|rest| rest.split_once('.'))
+        {
+            let plugin = self.get_plugin_config_mut(name).ok_or_else(|| {
+                RuneError::Config(format!("No configuration for plugin '{}'", name))
+            })?;
+            if setting == "enabled" {
+                plugin.enabled = value.as_bool().ok_or_else(|| invalid(key, "a boolean"))?;
+            } else {
+                plugin.config.insert(setting.to_string(), value);
+            }
+        } else {
+            return Err(RuneError::Config(format!(
+                "Unknown configuration key: {}",
+                key
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// JSON view of the configuration with secrets replaced
+    pub fn redacted(&self) -> Result<serde_json::Value> {
+        fn redact_settings(settings: Option<&mut serde_json::Value>) {
+            if let Some(map) = settings.and_then(|settings| settings.as_object_mut()) {
+                for (key, value) in map.iter_mut() {
+                    if is_sensitive_key(key) {
+                        *value = serde_json::Value::String(REDACTED.to_string());
+                    }
+                }
+            }
+        }
+        fn redact_section(section: &mut serde_json::Value) {
+            redact_settings(section.get_mut("global_settings"));
+            if let Some(plugins) = section.get_mut("plugins").and_then(|p| p.as_array_mut()) {
+                for plugin in plugins {
+                    redact_settings(plugin.get_mut("config"));
+                }
+            }
+        }
+
+        let mut value = serde_json::to_value(self)
+            .map_err(|e| RuneError::Config(format!("Failed to serialize config: {}", e)))?;
+        redact_section(&mut value);
+        if let Some(profiles) = value.get_mut("profiles").and_then(|p| p.as_object_mut()) {
+            for profile in profiles.values_mut() {
+                redact_section(profile);
+            }
+        }
+        Ok(value)
+    }
+
+    /// Apply a profile on top of this configuration
+    ///
+    /// Profiles are layered: a profile that `extends` another is applied after
+    /// it, so `prod` can extend `base` and only change what differs.
+    pub fn apply_profile(&mut self, name: &str) -> Result<()> {
+        *self = self.resolve_profile(name)?;
         Ok(())
     }
 
+    /// Configuration produced by applying a profile, leaving this one untouched
+    pub fn resolve_profile(&self, name: &str) -> Result<Config> {
+        let mut chain = Vec::new();
+        let mut next = Some(name);
+        while let Some(current) = next {
+            if chain.contains(&current) {
+                chain.push(current);
+                return Err(RuneError::Config(format!(
+                    "Profile inheritance cycle: {}",
+                    chain.join(" -> ")
+                )));
+            }
+            let profile = self.profiles.get(current).ok_or_else(|| {
+                let mut available: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                available.sort();
+                RuneError::Config(format!(
+                    "Unknown profile '{}' (available: {})",
+                    current,
+                    if available.is_empty() {
+                        "none".to_string()
+                    } else {
+                        available.join(", ")
+                    }
+                ))
+            })?;
+            chain.push(current);
+            next = profile.extends.as_deref();
+        }
+
+        let mut config = self.clone();
+        for layer in chain.iter().rev() {
+            self.profiles[*layer].apply_to(&mut config);
+        }
+        Ok(config)
+    }
+
+    /// Names of the configured profiles, sorted
+    pub fn profile_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
     /// Get enabled plugins only
     pub fn get_enabled_plugins(&self) -> Vec<&PluginConfig> {
         self.plugins.iter().filter(|p| p.enabled).collect()
@@ -869,6 +1338,45 @@ impl Default for Config {
     }
 }
 
+/// Placeholder shown instead of secret values
+pub const REDACTED: &str = "[redacted]";
+
+/// Whether a setting name looks like it holds a secret
+pub fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    ["token", "secret", "password", "api_key", "credential"]
+        .iter()
+        .any(|marker| key.contains(marker))
+}
+
+/// Settings the configuration API may change at runtime, addressed like the
+/// keys of [`Config::apply_update`]: how documents are rendered and shown.
+/// Access, trust, paths and what the server exposes are only changed in the
+/// configuration file
+pub const RUNTIME_SETTINGS: &[&str] = &[
+    "server.port",
+    "server.websocket_enabled",
+    "global.log_level",
+    "global.cache_enabled",
+    "global.config_auto_reload",
+    "global.lang",
+    "global.offline",
+    "global.drafts",
+    "global.embed_videos",
+    "global.heading_slugs",
+    "global.numbering",
+    "global.author",
+];
+
+/// Whether `key` is one of the [`RUNTIME_SETTINGS`] or turns a plugin on or off
+pub fn is_runtime_setting(key: &str) -> bool {
+    RUNTIME_SETTINGS.contains(&key)
+        || key
+            .strip_prefix("plugin.")
+            .and_then(|rest| rest.split_once('.'))
+            .is_some_and(|(_, setting)| setting == "enabled")
+}
+
 /// Server configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
@@ -877,6 +1385,9 @@ pub struct ServerConfig {
     pub static_dir: Option<PathBuf>,
     pub cors_enabled: bool,
     pub websocket_enabled: bool,
+    /// Cross-origin requests allowed when `cors_enabled` is set
+    #[serde(default, skip_serializing_if = "CorsConfig::is_empty")]
+    pub cors: CorsConfig,
 }
 
 impl Default for ServerConfig {
@@ -887,109 +1398,1088 @@ impl Default for ServerConfig {
             static_dir: None,
             cors_enabled: true,
             websocket_enabled: true,
+            cors: CorsConfig::default(),
         }
     }
 }
 
-/// Plugin-specific configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PluginConfig {
-    pub name: String,
-    pub enabled: bool,
-    pub version: Option<String>,
-    pub config: HashMap<String, serde_json::Value>,
-    pub dependencies: Vec<String>,
-    pub load_order: Option<i32>,
+/// Methods cross-origin requests may use unless configured otherwise
+const DEFAULT_CORS_METHODS: [&str; 3] = ["GET", "HEAD", "POST"];
+
+/// Headers cross-origin requests may send unless configured otherwise
+const DEFAULT_CORS_HEADERS: [&str; 1] = ["content-type"];
+
+/// Which other sites may call the server from a browser; without
+/// `allowed_origins` only the server's own pages may
+///
+/// ```json
+/// "server": {"cors": {"allowed_origins": ["https://notes.example.com"],
+///                     "allowed_methods": ["GET", "PUT"]}}
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CorsConfig {
+    /// Origins like `https://notes.example.com`, or `*` for any site
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_origins: Option<Vec<String>>,
+    /// Methods allowed, `GET`, `HEAD` and `POST` by default, or `*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_methods: Option<Vec<String>>,
+    /// Request headers allowed, `content-type` by default, or `*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_headers: Option<Vec<String>>,
+    /// Let requests carry cookies and credentials; not with any `*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_credentials: Option<bool>,
 }
 
-impl PluginConfig {
-    /// Create a new plugin configuration
-    pub fn new(name: String) -> Self {
-        Self {
-            name,
-            enabled: true,
-            version: None,
-            config: HashMap::new(),
-            dependencies: Vec::new(),
-            load_order: None,
-        }
+impl CorsConfig {
+    /// Check whether no CORS settings are configured
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
     }
 
-    /// Get a configuration value by key
-    pub fn get<T>(&self, key: &str) -> Option<T>
-    where
-        T: for<'de> Deserialize<'de>,
-    {
-        self.config
-            .get(key)
-            .and_then(|v| serde_json::from_value(v.clone()).ok())
+    /// Origins allowed besides the server's own
+    pub fn origins(&self) -> &[String] {
+        self.allowed_origins.as_deref().unwrap_or_default()
     }
 
-    /// Set a configuration value
-    pub fn set<T>(&mut self, key: String, value: T) -> Result<()>
-    where
-        T: Serialize,
-    {
-        let json_value = serde_json::to_value(value)
-            .map_err(|e| RuneError::Config(format!("Failed to serialize config value: {}", e)))?;
+    /// Methods cross-origin requests may use
+    pub fn methods(&self) -> Vec<String> {
+        self.allowed_methods.clone().unwrap_or_else(|| {
+            DEFAULT_CORS_METHODS
+                .iter()
+                .map(|method| method.to_string())
+                .collect()
+        })
+    }
 
-        self.config.insert(key, json_value);
-        Ok(())
+    /// Headers cross-origin requests may send
+    pub fn headers(&self) -> Vec<String> {
+        self.allowed_headers.clone().unwrap_or_else(|| {
+            DEFAULT_CORS_HEADERS
+                .iter()
+                .map(|header| header.to_string())
+                .collect()
+        })
     }
 
-    /// Validate the plugin configuration
-    pub fn validate(&self) -> Result<()> {
-        if self.name.is_empty() {
-            return Err(RuneError::Config("Plugin name cannot be empty".to_string()));
+    pub fn allows_credentials(&self) -> bool {
+        self.allow_credentials == Some(true)
+    }
+
+    /// Overwrite the settings `other` sets
+    fn merge(&mut self, other: CorsConfig) {
+        if other.allowed_origins.is_some() {
+            self.allowed_origins = other.allowed_origins;
+        }
+        if other.allowed_methods.is_some() {
+            self.allowed_methods = other.allowed_methods;
+        }
+        if other.allowed_headers.is_some() {
+            self.allowed_headers = other.allowed_headers;
         }
+        if other.allow_credentials.is_some() {
+            self.allow_credentials = other.allow_credentials;
+        }
+    }
 
-        // Validate plugin name format (alphanumeric, hyphens, underscores)
-        if !self
-            .name
+    fn validate(&self, prefix: &str, result: &mut ValidationResult) {
+        let mut invalid = |field: &str, message: String, fix: &str| {
+            result.errors.push(ValidationError {
+                field_path: format!("{}.{}", prefix, field),
+                error_type: ValidationErrorType::InvalidValue,
+                message,
+                suggested_fix: Some(fix.to_string()),
+            });
+        };
+        for origin in self.origins() {
+            if origin != "*" && !is_origin(origin) {
+                invalid(
+                    "allowed_origins",
+                    format!("'{}' is not an origin", origin),
+                    "Use a scheme and host without a path, like https://example.com",
+                );
+            }
+        }
+        for method in self.methods() {
+            let token = !method.is_empty() && method.chars().all(|c| c.is_ascii_uppercase());
+            if method != "*" && !token {
+                invalid(
+                    "allowed_methods",
+                    format!("'{}' is not an HTTP method", method),
+                    "Use upper-case method names like GET or PUT",
+                );
+            }
+        }
+        for header in self.headers() {
+            let token = !header.is_empty()
+                && header
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if header != "*" && !token {
+                invalid(
+                    "allowed_headers",
+                    format!("'{}' is not a header name", header),
+                    "Use header names like content-type",
+                );
+            }
+        }
+        let wildcard = [self.origins().to_vec(), self.methods(), self.headers()]
+            .iter()
+            .any(|values| values.iter().any(|value| value == "*"));
+        if self.allows_credentials() && wildcard {
+            invalid(
+                "allow_credentials",
+                "Credentials cannot be allowed together with '*'".to_string(),
+                "List the allowed origins, methods and headers instead of '*'",
+            );
+        }
+    }
+}
+
+/// Whether `origin` is a scheme and host, with an optional port
+fn is_origin(origin: &str) -> bool {
+    let Some((scheme, host)) = origin.split_once("://") else {
+        return false;
+    };
+    matches!(scheme, "http" | "https")
+        && !host.is_empty()
+        && !host
             .chars()
-            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
-        {
-            return Err(RuneError::Config(format!(
-                "Plugin name '{}' contains invalid characters. Only alphanumeric, hyphens, and underscores are allowed",
-                self.name
-            )));
+            .any(|c| c.is_whitespace() || matches!(c, '/' | '?' | '#' | '*' | '@'))
+}
+
+/// Settings a profile changes on top of the base configuration
+///
+/// ```json
+/// "profiles": {
+///   "dev": {"server": {"port": 3001}, "global_settings": {"dev_mode": true}},
+///   "prod": {"server": {"hostname": "0.0.0.0"},
+///            "plugins": [{"name": "editor", "enabled": false}]}
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConfigProfile {
+    /// Profile applied before this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    #[serde(default)]
+    pub server: ServerProfile,
+    /// Plugin overrides, matched by name; unknown plugins are added
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<PluginProfile>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub global_settings: HashMap<String, serde_json::Value>,
+    #[serde(default, skip_serializing_if = "LogConfig::is_empty")]
+    pub log: LogConfig,
+    #[serde(default, skip_serializing_if = "MemoryConfig::is_empty")]
+    pub memory: MemoryConfig,
+    #[serde(default, skip_serializing_if = "LargeFileConfig::is_empty")]
+    pub large_files: LargeFileConfig,
+    #[serde(default, skip_serializing_if = "LinkPreviewConfig::is_empty")]
+    pub link_previews: LinkPreviewConfig,
+    #[serde(default, skip_serializing_if = "ChaosConfig::is_empty")]
+    pub chaos: ChaosConfig,
+}
+
+impl ConfigProfile {
+    /// Overwrite the settings this profile sets
+    fn apply_to(&self, config: &mut Config) {
+        let server = &self.server;
+        if let Some(hostname) = &server.hostname {
+            config.server.hostname = hostname.clone();
+        }
+        if let Some(port) = server.port {
+            config.server.port = port;
+        }
+        if let Some(static_dir) = &server.static_dir {
+            config.server.static_dir = Some(static_dir.clone());
         }
+        if let Some(cors_enabled) = server.cors_enabled {
+            config.server.cors_enabled = cors_enabled;
+        }
+        if let Some(websocket_enabled) = server.websocket_enabled {
+            config.server.websocket_enabled = websocket_enabled;
+        }
+        config.server.cors.merge(server.cors.clone());
 
-        // Validate version format if provided
-        if let Some(version) = &self.version {
-            if version.is_empty() {
-                return Err(RuneError::Config(format!(
-                    "Plugin '{}' version cannot be empty",
-                    self.name
-                )));
+        for plugin in &self.plugins {
+            let existing = match config.get_plugin_config_mut(&plugin.name) {
+                Some(existing) => existing,
+                None => {
+                    config.plugins.push(PluginConfig::new(plugin.name.clone()));
+                    config.plugins.last_mut().unwrap()
+                }
+            };
+            if let Some(enabled) = plugin.enabled {
+                existing.enabled = enabled;
+            }
+            for (key, value) in &plugin.config {
+                existing.config.insert(key.clone(), value.clone());
             }
         }
 
-        // Validate dependencies don't include self
-        if self.dependencies.contains(&self.name) {
-            return Err(RuneError::Config(format!(
-                "Plugin '{}' cannot depend on itself",
-                self.name
-            )));
+        for (key, value) in &self.global_settings {
+            config.global_settings.insert(key.clone(), value.clone());
         }
 
-        Ok(())
+        config.log.merge(self.log.clone());
+        config.memory.merge(self.memory.clone());
+        config.large_files.merge(self.large_files.clone());
+        config.link_previews.merge(self.link_previews.clone());
+        config.chaos.merge(self.chaos.clone());
     }
+}
 
-    /// Check if plugin has a specific dependency
-    pub fn has_dependency(&self, dependency: &str) -> bool {
-        self.dependencies.contains(&dependency.to_string())
+/// Server settings of a profile; unset fields keep the base value
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub static_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub websocket_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "CorsConfig::is_empty")]
+    pub cors: CorsConfig,
+}
+
+/// Plugin settings of a profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginProfile {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Keys merged into the plugin's configuration
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub config: HashMap<String, serde_json::Value>,
+}
+
+/// Log level settings; targets are module paths such as `rune_server` or
+/// `rune_server::handlers`, and the most specific one wins
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LogConfig {
+    /// Level of targets without an override, e.g. `"info"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    /// Per-target overrides
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub levels: HashMap<String, String>,
+}
+
+impl LogConfig {
+    /// Check whether no levels are configured
+    pub fn is_empty(&self) -> bool {
+        self.level.is_none() && self.levels.is_empty()
     }
 
-    /// Add a dependency if not already present
-    pub fn add_dependency(&mut self, dependency: String) {
-        if !self.dependencies.contains(&dependency) && dependency != self.name {
-            self.dependencies.push(dependency);
+    /// Overwrite the levels `other` sets
+    fn merge(&mut self, other: LogConfig) {
+        if other.level.is_some() {
+            self.level = other.level;
         }
+        self.levels.extend(other.levels);
     }
 
-    /// Remove a dependency
-    pub fn remove_dependency(&mut self, dependency: &str) {
+    fn validate(&self, prefix: &str, result: &mut ValidationResult) {
+        let mut levels: Vec<(String, &String)> = self
+            .levels
+            .iter()
+            .map(|(target, level)| (format!("{}.levels.{}", prefix, target), level))
+            .collect();
+        levels.sort();
+        if let Some(level) = &self.level {
+            levels.insert(0, (format!("{}.level", prefix), level));
+        }
+
+        for (field_path, level) in levels {
+            if crate::logging::parse_level(level).is_err() {
+                result.errors.push(ValidationError {
+                    field_path,
+                    error_type: ValidationErrorType::InvalidValue,
+                    message: format!("Unknown log level '{}'", level),
+                    suggested_fix: Some(
+                        "Use one of: off, error, warn, info, debug, trace".to_string(),
+                    ),
+                });
+            }
+        }
+        for target in self.levels.keys() {
+            if target.trim().is_empty() {
+                result.errors.push(ValidationError {
+                    field_path: format!("{}.levels", prefix),
+                    error_type: ValidationErrorType::InvalidFormat,
+                    message: "Log target must not be empty".to_string(),
+                    suggested_fix: Some("Set \"level\" to change the default".to_string()),
+                });
+            }
+        }
+    }
+}
+
+/// Memory budget settings; unset fields use the defaults of
+/// [`crate::memory`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MemoryConfig {
+    /// Budget of all accounted caches and buffers in MiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget_mb: Option<u64>,
+    /// Seconds between budget checks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check_interval_secs: Option<u64>,
+}
+
+impl MemoryConfig {
+    /// Check whether no memory settings are configured
+    pub fn is_empty(&self) -> bool {
+        self.budget_mb.is_none() && self.check_interval_secs.is_none()
+    }
+
+    /// Budget in bytes
+    pub fn budget_bytes(&self) -> usize {
+        let budget_mb = self
+            .budget_mb
+            .unwrap_or(crate::memory::DEFAULT_MEMORY_BUDGET_MB);
+        usize::try_from(budget_mb.saturating_mul(1024 * 1024)).unwrap_or(usize::MAX)
+    }
+
+    /// Interval between budget checks
+    pub fn check_interval(&self) -> std::time::Duration {
+        self.check_interval_secs
+            .map(std::time::Duration::from_secs)
+            .unwrap_or(crate::memory::DEFAULT_MEMORY_CHECK_INTERVAL)
+    }
+
+    /// Overwrite the settings `other` sets
+    fn merge(&mut self, other: MemoryConfig) {
+        if other.budget_mb.is_some() {
+            self.budget_mb = other.budget_mb;
+        }
+        if other.check_interval_secs.is_some() {
+            self.check_interval_secs = other.check_interval_secs;
+        }
+    }
+
+    fn validate(&self, prefix: &str, result: &mut ValidationResult) {
+        if self.budget_mb == Some(0) {
+            result.errors.push(ValidationError {
+                field_path: format!("{}.budget_mb", prefix),
+                error_type: ValidationErrorType::InvalidValue,
+                message: "Memory budget must be at least 1 MiB".to_string(),
+                suggested_fix: Some(format!(
+                    "Remove it to use the default of {} MiB",
+                    crate::memory::DEFAULT_MEMORY_BUDGET_MB
+                )),
+            });
+        }
+        if self.check_interval_secs == Some(0) {
+            result.errors.push(ValidationError {
+                field_path: format!("{}.check_interval_secs", prefix),
+                error_type: ValidationErrorType::InvalidValue,
+                message: "Memory check interval must be at least 1 second".to_string(),
+                suggested_fix: Some(format!(
+                    "Remove it to check every {} seconds",
+                    crate::memory::DEFAULT_MEMORY_CHECK_INTERVAL.as_secs()
+                )),
+            });
+        }
+    }
+}
+
+/// Documents of at least this many MiB are large
+pub const DEFAULT_LARGE_FILE_THRESHOLD_MB: u64 = 10;
+
+/// KiB of a large document the editor loads at a time
+pub const DEFAULT_LARGE_FILE_WINDOW_KB: u64 = 256;
+
+/// Large document settings
+///
+/// Large documents are opened a window at a time instead of whole, are
+/// read-only in the editor and are previewed without the expensive
+/// renderers, like diagrams and link previews.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LargeFileConfig {
+    /// Size in MiB from which a document is large
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold_mb: Option<u64>,
+    /// Size in KiB of the windows a large document is read in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_kb: Option<u64>,
+}
+
+impl LargeFileConfig {
+    /// Check whether no large document settings are configured
+    pub fn is_empty(&self) -> bool {
+        self.threshold_mb.is_none() && self.window_kb.is_none()
+    }
+
+    /// Size in bytes from which a document is large
+    pub fn threshold_bytes(&self) -> u64 {
+        self.threshold_mb
+            .unwrap_or(DEFAULT_LARGE_FILE_THRESHOLD_MB)
+            .saturating_mul(1024 * 1024)
+    }
+
+    /// Size in bytes of the windows a large document is read in
+    pub fn window_bytes(&self) -> usize {
+        let window_kb = self.window_kb.unwrap_or(DEFAULT_LARGE_FILE_WINDOW_KB);
+        usize::try_from(window_kb.saturating_mul(1024)).unwrap_or(usize::MAX)
+    }
+
+    /// Overwrite the settings `other` sets
+    fn merge(&mut self, other: LargeFileConfig) {
+        if other.threshold_mb.is_some() {
+            self.threshold_mb = other.threshold_mb;
+        }
+        if other.window_kb.is_some() {
+            self.window_kb = other.window_kb;
+        }
+    }
+
+    fn validate(&self, prefix: &str, result: &mut ValidationResult) {
+        if self.threshold_mb == Some(0) {
+            result.errors.push(ValidationError {
+                field_path: format!("{}.threshold_mb", prefix),
+                error_type: ValidationErrorType::InvalidValue,
+                message: "Large file threshold must be at least 1 MiB".to_string(),
+                suggested_fix: Some(format!(
+                    "Remove it to use the default of {} MiB",
+                    DEFAULT_LARGE_FILE_THRESHOLD_MB
+                )),
+            });
+        }
+        if self.window_kb == Some(0) {
+            result.errors.push(ValidationError {
+                field_path: format!("{}.window_kb", prefix),
+                error_type: ValidationErrorType::InvalidValue,
+                message: "Large file window must be at least 1 KiB".to_string(),
+                suggested_fix: Some(format!(
+                    "Remove it to use the default of {} KiB",
+                    DEFAULT_LARGE_FILE_WINDOW_KB
+                )),
+            });
+        }
+    }
+}
+
+/// Preview cards for bare links on their own line
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LinkPreviewConfig {
+    /// Turn bare links into cards; off by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Only use cached previews, leaving other links plain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline: Option<bool>,
+    /// Time allowed for fetching one link, 3000 by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+impl LinkPreviewConfig {
+    /// Check whether no link preview settings are configured
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Overwrite the settings `other` sets
+    fn merge(&mut self, other: LinkPreviewConfig) {
+        if other.enabled.is_some() {
+            self.enabled = other.enabled;
+        }
+        if other.offline.is_some() {
+            self.offline = other.offline;
+        }
+        if other.timeout_ms.is_some() {
+            self.timeout_ms = other.timeout_ms;
+        }
+    }
+
+    fn validate(&self, prefix: &str, result: &mut ValidationResult) {
+        if self.timeout_ms == Some(0) {
+            result.errors.push(ValidationError {
+                field_path: format!("{}.timeout_ms", prefix),
+                error_type: ValidationErrorType::InvalidValue,
+                message: "Link preview timeout must be at least 1 millisecond".to_string(),
+                suggested_fix: Some("Remove it to use the default of 3000".to_string()),
+            });
+        }
+    }
+}
+
+/// Faults injected into event handlers and supervised tasks in development
+/// mode, to exercise health checks, restarts and recovery; nothing is
+/// injected unless a probability is set
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChaosConfig {
+    /// Chance, from 0 to 1, that a call is delayed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_probability: Option<f64>,
+    /// Longest delay, 500 by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_delay_ms: Option<u64>,
+    /// Chance that a call fails instead of running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_probability: Option<f64>,
+    /// Chance that a call panics instead of running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub panic_probability: Option<f64>,
+    /// Names of the event handlers and tasks to inject faults into, such as
+    /// `server` or `file-watcher`; all of them when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+    /// Seed of the random choices, to replay the same faults
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl ChaosConfig {
+    /// Check whether no chaos settings are configured
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether any fault has a chance of being injected
+    pub fn is_active(&self) -> bool {
+        [
+            self.delay_probability,
+            self.error_probability,
+            self.panic_probability,
+        ]
+        .into_iter()
+        .any(|probability| probability.is_some_and(|probability| probability > 0.0))
+    }
+
+    /// Longest injected delay
+    pub fn max_delay(&self) -> std::time::Duration {
+        self.max_delay_ms
+            .map(std::time::Duration::from_millis)
+            .unwrap_or(crate::chaos::DEFAULT_CHAOS_MAX_DELAY)
+    }
+
+    /// Overwrite the settings `other` sets
+    fn merge(&mut self, other: ChaosConfig) {
+        if other.delay_probability.is_some() {
+            self.delay_probability = other.delay_probability;
+        }
+        if other.max_delay_ms.is_some() {
+            self.max_delay_ms = other.max_delay_ms;
+        }
+        if other.error_probability.is_some() {
+            self.error_probability = other.error_probability;
+        }
+        if other.panic_probability.is_some() {
+            self.panic_probability = other.panic_probability;
+        }
+        if !other.targets.is_empty() {
+            self.targets = other.targets;
+        }
+        if other.seed.is_some() {
+            self.seed = other.seed;
+        }
+    }
+
+    fn validate(&self, prefix: &str, result: &mut ValidationResult) {
+        let probabilities = [
+            ("delay_probability", self.delay_probability),
+            ("error_probability", self.error_probability),
+            ("panic_probability", self.panic_probability),
+        ];
+        for (field, probability) in probabilities {
+            if probability.is_some_and(|probability| !(0.0..=1.0).contains(&probability)) {
+                result.errors.push(ValidationError {
+                    field_path: format!("{}.{}", prefix, field),
+                    error_type: ValidationErrorType::InvalidValue,
+                    message: "Probability must be between 0 and 1".to_string(),
+                    suggested_fix: Some("Use e.g. 0.05 for 5% of calls".to_string()),
+                });
+            }
+        }
+    }
+}
+
+/// Usage statistics kept on this machine; nothing is collected unless
+/// `enabled` is set
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    /// Count opened documents, renders and used features; off by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// File the counts are kept in, `stats.json` in the rune data directory
+    /// by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+impl TelemetryConfig {
+    /// Check whether no telemetry settings are configured
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether usage statistics are collected
+    pub fn is_enabled(&self) -> bool {
+        self.enabled == Some(true)
+    }
+
+    /// Overwrite the settings `other` sets
+    fn merge(&mut self, other: TelemetryConfig) {
+        if other.enabled.is_some() {
+            self.enabled = other.enabled;
+        }
+        if other.path.is_some() {
+            self.path = other.path;
+        }
+    }
+
+    fn validate(&self, prefix: &str, result: &mut ValidationResult) {
+        if self
+            .path
+            .as_ref()
+            .is_some_and(|path| path.as_os_str().is_empty())
+        {
+            result.errors.push(ValidationError {
+                field_path: format!("{}.path", prefix),
+                error_type: ValidationErrorType::InvalidValue,
+                message: "Statistics file path must not be empty".to_string(),
+                suggested_fix: Some("Remove it to use the rune data directory".to_string()),
+            });
+        }
+    }
+}
+
+/// Limits on the external programs rune runs, like pandoc, image encoders,
+/// export hooks and tunnels; see [`crate::process`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessConfig {
+    /// Programs that may run, by name or path; `*` allows any and is the
+    /// default, while an empty list allows none. Commands run through the
+    /// shell need `sh`, or `cmd` on Windows
+    #[serde(
+        default = "default_allowed_programs",
+        skip_serializing_if = "is_default_allowed_programs"
+    )]
+    pub allowed_programs: Vec<String>,
+    /// Seconds a program may run, 300 by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Megabytes of output a program may write, 64 by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_mb: Option<u64>,
+    /// Environment variables passed to programs besides the path, home,
+    /// locale and a few more, e.g. `["NGROK_AUTHTOKEN"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pass_env: Vec<String>,
+}
+
+impl Default for ProcessConfig {
+    fn default() -> Self {
+        Self {
+            allowed_programs: default_allowed_programs(),
+            timeout_secs: None,
+            max_output_mb: None,
+            pass_env: Vec::new(),
+        }
+    }
+}
+
+fn default_allowed_programs() -> Vec<String> {
+    vec![crate::process::ANY_PROGRAM.to_string()]
+}
+
+fn is_default_allowed_programs(programs: &[String]) -> bool {
+    programs == default_allowed_programs().as_slice()
+}
+
+impl ProcessConfig {
+    /// Check whether no process settings are configured
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// How long a program may run
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(
+            self.timeout_secs
+                .unwrap_or(crate::process::DEFAULT_PROCESS_TIMEOUT_SECS),
+        )
+    }
+
+    /// Most bytes of output kept from a program
+    pub fn max_output_bytes(&self) -> usize {
+        let mb = self
+            .max_output_mb
+            .unwrap_or(crate::process::DEFAULT_MAX_OUTPUT_MB);
+        usize::try_from(mb.saturating_mul(1024 * 1024)).unwrap_or(usize::MAX)
+    }
+
+    /// Overwrite the settings `other` sets
+    fn merge(&mut self, other: ProcessConfig) {
+        if !is_default_allowed_programs(&other.allowed_programs) {
+            self.allowed_programs = other.allowed_programs;
+        }
+        if other.timeout_secs.is_some() {
+            self.timeout_secs = other.timeout_secs;
+        }
+        if other.max_output_mb.is_some() {
+            self.max_output_mb = other.max_output_mb;
+        }
+        if !other.pass_env.is_empty() {
+            self.pass_env = other.pass_env;
+        }
+    }
+
+    fn validate(&self, prefix: &str, result: &mut ValidationResult) {
+        for (field, value) in [
+            ("timeout_secs", self.timeout_secs),
+            ("max_output_mb", self.max_output_mb),
+        ] {
+            if value == Some(0) {
+                result.errors.push(ValidationError {
+                    field_path: format!("{}.{}", prefix, field),
+                    error_type: ValidationErrorType::InvalidValue,
+                    message: "Must be at least 1".to_string(),
+                    suggested_fix: Some("Remove it to use the default".to_string()),
+                });
+            }
+        }
+        for (field, names) in [
+            ("allowed_programs", &self.allowed_programs),
+            ("pass_env", &self.pass_env),
+        ] {
+            if names.iter().any(|name| name.trim().is_empty()) {
+                result.errors.push(ValidationError {
+                    field_path: format!("{}.{}", prefix, field),
+                    error_type: ValidationErrorType::InvalidValue,
+                    message: "Names must not be empty".to_string(),
+                    suggested_fix: Some("Remove the empty entries".to_string()),
+                });
+            }
+        }
+    }
+}
+
+/// How passphrases of encrypted documents are found when the
+/// `RUNE_PASSPHRASE` environment variable is not set
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    /// Shell command printing the passphrase, e.g. one reading a keyring;
+    /// it gets the document's path in `RUNE_DOCUMENT`. Without it, rune
+    /// asks on the terminal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase_command: Option<String>,
+}
+
+impl EncryptionConfig {
+    /// Check whether no encryption settings are configured
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Overwrite the settings `other` sets
+    fn merge(&mut self, other: EncryptionConfig) {
+        if other.passphrase_command.is_some() {
+            self.passphrase_command = other.passphrase_command;
+        }
+    }
+}
+
+/// Where daily notes live and what a new one starts with
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JournalConfig {
+    /// Directory of the journal, served by `rune journal`; the current
+    /// directory by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// Path of a day's note below `dir`, `journal/%Y/%Y-%m-%d.md` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Markdown file new notes are created from, relative to `dir`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<PathBuf>,
+    /// Offset from UTC deciding when a day starts, e.g. `+02:00`; UTC by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utc_offset: Option<String>,
+}
+
+impl JournalConfig {
+    /// Check whether no journal settings are configured
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Overwrite the settings `other` sets
+    fn merge(&mut self, other: JournalConfig) {
+        if other.dir.is_some() {
+            self.dir = other.dir;
+        }
+        if other.path.is_some() {
+            self.path = other.path;
+        }
+        if other.template.is_some() {
+            self.template = other.template;
+        }
+        if other.utc_offset.is_some() {
+            self.utc_offset = other.utc_offset;
+        }
+    }
+
+    fn validate(&self, prefix: &str, result: &mut ValidationResult) {
+        if let Some(path) = &self.path {
+            if let Err(e) = crate::journal::PathPattern::new(path) {
+                result.errors.push(ValidationError {
+                    field_path: format!("{}.path", prefix),
+                    error_type: ValidationErrorType::InvalidValue,
+                    message: e.to_string(),
+                    suggested_fix: Some(format!(
+                        "Use a pattern like {}",
+                        crate::journal::DEFAULT_PATH
+                    )),
+                });
+            }
+        }
+        if let Some(offset) = &self.utc_offset {
+            if crate::time::parse_utc_offset(offset).is_none() {
+                result.errors.push(ValidationError {
+                    field_path: format!("{}.utc_offset", prefix),
+                    error_type: ValidationErrorType::InvalidValue,
+                    message: format!("'{}' is not an offset from UTC", offset),
+                    suggested_fix: Some("Use e.g. +02:00, -0530 or Z".to_string()),
+                });
+            }
+        }
+    }
+}
+
+/// Commands and plugin calls run around `rune export`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExportHooks {
+    /// Run before the document is rendered; a failure aborts the export
+    #[serde(default)]
+    pub pre: Vec<ExportHook>,
+    /// Run after the output file has been written
+    #[serde(default)]
+    pub post: Vec<ExportHook>,
+}
+
+impl ExportHooks {
+    /// Check whether no hooks are configured
+    pub fn is_empty(&self) -> bool {
+        self.pre.is_empty() && self.post.is_empty()
+    }
+}
+
+/// A single export hook, e.g. `{"command": "rsync -a \"$RUNE_EXPORT_DIR/\" host:/srv"}`
+/// or `{"plugin": "deploy", "allow_failure": true}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportHook {
+    #[serde(flatten)]
+    pub action: ExportHookAction,
+    /// Log failures instead of failing the export
+    #[serde(default)]
+    pub allow_failure: bool,
+}
+
+/// What an export hook does
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportHookAction {
+    /// Shell command, run with the document's directory as working directory
+    Command(String),
+    /// Name of a plugin-provided hook handler
+    Plugin(String),
+}
+
+/// Image optimization settings, e.g.
+/// `"export_assets": {"max_width": 1200, "formats": ["webp"]}`; unset fields
+/// use the defaults of [`crate::export::assets`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExportAssetConfig {
+    /// Images wider than this many pixels are scaled down
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_width: Option<u32>,
+    /// Encoder quality from 1 to 100
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<u8>,
+    /// Formats tried for each image, from `webp` and `avif`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formats: Option<Vec<String>>,
+    /// Remove EXIF, XMP and text metadata from JPEG and PNG images
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strip_metadata: Option<bool>,
+    /// Encoder commands replacing the defaults, keyed by `webp`, `avif` or
+    /// `resize`; they run through the platform shell and read the image from
+    /// `RUNE_ASSET_INPUT` (`%RUNE_ASSET_INPUT%` under `cmd`)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub encoders: HashMap<String, String>,
+}
+
+impl ExportAssetConfig {
+    /// Check whether no asset settings are configured
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Overwrite the settings `other` sets
+    fn merge(&mut self, other: ExportAssetConfig) {
+        if other.max_width.is_some() {
+            self.max_width = other.max_width;
+        }
+        if other.quality.is_some() {
+            self.quality = other.quality;
+        }
+        if other.formats.is_some() {
+            self.formats = other.formats;
+        }
+        if other.strip_metadata.is_some() {
+            self.strip_metadata = other.strip_metadata;
+        }
+        self.encoders.extend(other.encoders);
+    }
+
+    fn validate(&self, prefix: &str, result: &mut ValidationResult) {
+        if self.max_width == Some(0) {
+            result.errors.push(ValidationError {
+                field_path: format!("{}.max_width", prefix),
+                error_type: ValidationErrorType::InvalidValue,
+                message: "Maximum image width must be at least 1 pixel".to_string(),
+                suggested_fix: Some("Remove it to use the default".to_string()),
+            });
+        }
+        if let Some(quality) = self.quality.filter(|quality| !(1..=100).contains(quality)) {
+            result.errors.push(ValidationError {
+                field_path: format!("{}.quality", prefix),
+                error_type: ValidationErrorType::InvalidValue,
+                message: format!("Image quality {} is outside 1-100", quality),
+                suggested_fix: Some("Use a quality between 1 and 100".to_string()),
+            });
+        }
+        for format in self.formats.iter().flatten() {
+            if !["webp", "avif"].contains(&format.as_str()) {
+                result.errors.push(ValidationError {
+                    field_path: format!("{}.formats", prefix),
+                    error_type: ValidationErrorType::InvalidValue,
+                    message: format!("Unknown image format '{}'", format),
+                    suggested_fix: Some("Use webp or avif".to_string()),
+                });
+            }
+        }
+        for target in self.encoders.keys() {
+            if !["webp", "avif", "resize"].contains(&target.as_str()) {
+                result.errors.push(ValidationError {
+                    field_path: format!("{}.encoders.{}", prefix, target),
+                    error_type: ValidationErrorType::InvalidValue,
+                    message: format!("Unknown encoder '{}'", target),
+                    suggested_fix: Some("Use webp, avif or resize".to_string()),
+                });
+            }
+        }
+    }
+}
+
+/// Plugin-specific configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginConfig {
+    pub name: String,
+    pub enabled: bool,
+    pub version: Option<String>,
+    pub config: HashMap<String, serde_json::Value>,
+    pub dependencies: Vec<String>,
+    pub load_order: Option<i32>,
+}
+
+impl PluginConfig {
+    /// Create a new plugin configuration
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            enabled: true,
+            version: None,
+            config: HashMap::new(),
+            dependencies: Vec::new(),
+            load_order: None,
+        }
+    }
+
+    /// Get a configuration value by key
+    pub fn get<T>(&self, key: &str) -> Option<T>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        self.config
+            .get(key)
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+    }
+
+    /// Set a configuration value
+    pub fn set<T>(&mut self, key: String, value: T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let json_value = serde_json::to_value(value)
+            .map_err(|e| RuneError::Config(format!("Failed to serialize config value: {}", e)))?;
+
+        self.config.insert(key, json_value);
+        Ok(())
+    }
+
+    /// Validate the plugin configuration
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            return Err(RuneError::Config("Plugin name cannot be empty".to_string()));
+        }
+
+        // Validate plugin name format (alphanumeric, hyphens, underscores)
+        if !self
+            .name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(RuneError::Config(format!(
+                "Plugin name '{}' contains invalid characters. Only alphanumeric, hyphens, and underscores are allowed",
+                self.name
+            )));
+        }
+
+        // Validate version format if provided
+        if let Some(version) = &self.version {
+            if version.is_empty() {
+                return Err(RuneError::Config(format!(
+                    "Plugin '{}' version cannot be empty",
+                    self.name
+                )));
+            }
+        }
+
+        // Validate dependencies don't include self
+        if self.dependencies.contains(&self.name) {
+            return Err(RuneError::Config(format!(
+                "Plugin '{}' cannot depend on itself",
+                self.name
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Check if plugin has a specific dependency
+    pub fn has_dependency(&self, dependency: &str) -> bool {
+        self.dependencies.contains(&dependency.to_string())
+    }
+
+    /// Add a dependency if not already present
+    pub fn add_dependency(&mut self, dependency: String) {
+        if !self.dependencies.contains(&dependency) && dependency != self.name {
+            self.dependencies.push(dependency);
+        }
+    }
+
+    /// Remove a dependency
+    pub fn remove_dependency(&mut self, dependency: &str) {
         self.dependencies.retain(|d| d != dependency);
     }
 
@@ -1026,6 +2516,23 @@ impl Config {
         self.get_global_setting::<String>("template_path")
             .map(PathBuf::from)
     }
+
+    /// Get the stylesheet added to every page after the built-in styles, if any
+    pub fn get_theme_css_path(&self) -> Option<PathBuf> {
+        self.get_global_setting::<String>("theme_css")
+            .map(PathBuf::from)
+    }
+
+    /// Get the theme script added to every page, if any
+    pub fn get_theme_js_path(&self) -> Option<PathBuf> {
+        self.get_global_setting::<String>("theme_js")
+            .map(PathBuf::from)
+    }
+
+    /// Get the configured locale, if any
+    pub fn get_locale(&self) -> Option<String> {
+        self.get_global_setting::<String>("lang")
+    }
 }
 
 /// System-wide configuration
@@ -1070,32 +2577,271 @@ impl Default for ConfigSchema {
     }
 }
 
-impl ConfigSchema {
-    fn default_global_settings_schema() -> HashMap<String, FieldSchema> {
-        let mut schema = HashMap::new();
+impl ConfigSchema {
+    fn default_global_settings_schema() -> HashMap<String, FieldSchema> {
+        let mut schema = HashMap::new();
+
+        schema.insert(
+            "log_level".to_string(),
+            FieldSchema {
+                field_type: FieldType::String,
+                description: "Logging level for the application".to_string(),
+                default_value: Some(serde_json::Value::String("info".to_string())),
+                required: false,
+                validation_rules: vec![ValidationRule::OneOf(vec![
+                    "trace".to_string(),
+                    "debug".to_string(),
+                    "info".to_string(),
+                    "warn".to_string(),
+                    "error".to_string(),
+                ])],
+            },
+        );
+
+        schema.insert(
+            "dev_mode".to_string(),
+            FieldSchema {
+                field_type: FieldType::Boolean,
+                description: "Enable development mode with enhanced debugging".to_string(),
+                default_value: Some(serde_json::Value::Bool(false)),
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "cache_enabled".to_string(),
+            FieldSchema {
+                field_type: FieldType::Boolean,
+                description: "Enable caching for improved performance".to_string(),
+                default_value: Some(serde_json::Value::Bool(true)),
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "config_auto_reload".to_string(),
+            FieldSchema {
+                field_type: FieldType::Boolean,
+                description: "Automatically reload configuration when files change".to_string(),
+                default_value: Some(serde_json::Value::Bool(false)),
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "plugin_hot_reload".to_string(),
+            FieldSchema {
+                field_type: FieldType::Boolean,
+                description: "Enable hot reloading of plugins during development".to_string(),
+                default_value: Some(serde_json::Value::Bool(false)),
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "api_token".to_string(),
+            FieldSchema {
+                field_type: FieldType::String,
+                description: "Bearer token granting access to the runtime configuration API"
+                    .to_string(),
+                default_value: None,
+                required: false,
+                validation_rules: vec![ValidationRule::MinLength(16)],
+            },
+        );
+
+        schema.insert(
+            "lan_discovery".to_string(),
+            FieldSchema {
+                field_type: FieldType::Boolean,
+                description: "Advertise the preview server on the local network via mDNS"
+                    .to_string(),
+                default_value: Some(serde_json::Value::Bool(false)),
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "lang".to_string(),
+            FieldSchema {
+                field_type: FieldType::String,
+                description:
+                    "Locale of command line messages and the web interface, e.g. en-US or zh-CN"
+                        .to_string(),
+                default_value: None,
+                required: false,
+                validation_rules: vec![ValidationRule::Pattern(
+                    r"^[A-Za-z]{2,8}([_-][A-Za-z0-9]{1,8})*$".to_string(),
+                )],
+            },
+        );
+
+        schema.insert(
+            "offline".to_string(),
+            FieldSchema {
+                field_type: FieldType::Boolean,
+                description:
+                    "Forbid outbound network fetches and keep preview pages to bundled assets"
+                        .to_string(),
+                default_value: Some(serde_json::Value::Bool(false)),
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "drafts".to_string(),
+            FieldSchema {
+                field_type: FieldType::String,
+                description:
+                    "Whether drafts and documents whose publish_at (UTC unless it gives an offset) is still ahead are listed and served outside dev mode: show or hide"
+                        .to_string(),
+                default_value: Some(serde_json::Value::String("show".to_string())),
+                required: false,
+                validation_rules: vec![ValidationRule::OneOf(vec![
+                    "show".to_string(),
+                    "hide".to_string(),
+                ])],
+            },
+        );
+
+        schema.insert(
+            "not_found_page".to_string(),
+            FieldSchema {
+                field_type: FieldType::String,
+                description: "Markdown file shown as the page of requests for missing documents"
+                    .to_string(),
+                default_value: None,
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "template_path".to_string(),
+            FieldSchema {
+                field_type: FieldType::String,
+                description:
+                    "HTML page template used instead of the built-in one; reloaded when it changes"
+                        .to_string(),
+                default_value: None,
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "theme_css".to_string(),
+            FieldSchema {
+                field_type: FieldType::String,
+                description: "Stylesheet added to every page after the built-in styles; reloaded when it changes"
+                    .to_string(),
+                default_value: None,
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "theme_js".to_string(),
+            FieldSchema {
+                field_type: FieldType::String,
+                description: "Script added to every page, run against the theme API; reloads pages when it changes"
+                    .to_string(),
+                default_value: None,
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "theme_scripts".to_string(),
+            FieldSchema {
+                field_type: FieldType::Boolean,
+                description:
+                    "Allow themes to add scripts to pages; turn off for locked-down deployments"
+                        .to_string(),
+                default_value: Some(serde_json::Value::Bool(true)),
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "max_body_size".to_string(),
+            FieldSchema {
+                field_type: FieldType::Number,
+                description: "Largest request body the server accepts, in bytes".to_string(),
+                default_value: Some(serde_json::Value::Number(serde_json::Number::from(
+                    16 * 1024 * 1024,
+                ))),
+                required: false,
+                validation_rules: vec![ValidationRule::Range {
+                    min: 1.0,
+                    max: u32::MAX as f64,
+                }],
+            },
+        );
+
+        schema.insert(
+            "max_upload_size".to_string(),
+            FieldSchema {
+                field_type: FieldType::Number,
+                description: "Largest file accepted by uploads such as WebDAV saves, in bytes"
+                    .to_string(),
+                default_value: Some(serde_json::Value::Number(serde_json::Number::from(
+                    64 * 1024 * 1024,
+                ))),
+                required: false,
+                validation_rules: vec![ValidationRule::Range {
+                    min: 1.0,
+                    max: u32::MAX as f64,
+                }],
+            },
+        );
 
         schema.insert(
-            "log_level".to_string(),
+            "tunnel".to_string(),
             FieldSchema {
                 field_type: FieldType::String,
-                description: "Logging level for the application".to_string(),
-                default_value: Some(serde_json::Value::String("info".to_string())),
+                description:
+                    "Provider of a public URL tunneled to the server, e.g. localhost.run or ngrok"
+                        .to_string(),
+                default_value: None,
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "symlinks".to_string(),
+            FieldSchema {
+                field_type: FieldType::String,
+                description:
+                    "How symbolic links in served directories are treated: deny, within_root (followed when they stay inside the directory) or follow"
+                        .to_string(),
+                default_value: Some(serde_json::Value::String("within_root".to_string())),
                 required: false,
                 validation_rules: vec![ValidationRule::OneOf(vec![
-                    "trace".to_string(),
-                    "debug".to_string(),
-                    "info".to_string(),
-                    "warn".to_string(),
-                    "error".to_string(),
+                    "deny".to_string(),
+                    "within_root".to_string(),
+                    "follow".to_string(),
                 ])],
             },
         );
 
         schema.insert(
-            "dev_mode".to_string(),
+            "websocket_requires_token".to_string(),
             FieldSchema {
                 field_type: FieldType::Boolean,
-                description: "Enable development mode with enhanced debugging".to_string(),
+                description: "Refuse WebSocket connections that do not present the api_token; \
+                    pages opened with ?token=<api_token> pass it on to their sockets"
+                    .to_string(),
                 default_value: Some(serde_json::Value::Bool(false)),
                 required: false,
                 validation_rules: vec![],
@@ -1103,10 +2849,12 @@ impl ConfigSchema {
         );
 
         schema.insert(
-            "cache_enabled".to_string(),
+            "workspace_trusted".to_string(),
             FieldSchema {
                 field_type: FieldType::Boolean,
-                description: "Enable caching for improved performance".to_string(),
+                description:
+                    "Whether the open workspace is trusted; set by rune from the trusted workspaces"
+                        .to_string(),
                 default_value: Some(serde_json::Value::Bool(true)),
                 required: false,
                 validation_rules: vec![],
@@ -1114,10 +2862,12 @@ impl ConfigSchema {
         );
 
         schema.insert(
-            "config_auto_reload".to_string(),
+            "webdav".to_string(),
             FieldSchema {
                 field_type: FieldType::Boolean,
-                description: "Automatically reload configuration when files change".to_string(),
+                description:
+                    "Expose the served directories over WebDAV at /dav/ for external editors"
+                        .to_string(),
                 default_value: Some(serde_json::Value::Bool(false)),
                 required: false,
                 validation_rules: vec![],
@@ -1125,10 +2875,82 @@ impl ConfigSchema {
         );
 
         schema.insert(
-            "plugin_hot_reload".to_string(),
+            "embed_videos".to_string(),
             FieldSchema {
                 field_type: FieldType::Boolean,
-                description: "Enable hot reloading of plugins during development".to_string(),
+                description: "Embed standalone YouTube and Vimeo links as video players"
+                    .to_string(),
+                default_value: Some(serde_json::Value::Bool(false)),
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "heading_slugs".to_string(),
+            FieldSchema {
+                field_type: FieldType::String,
+                description:
+                    "How heading ids are made from heading text: github, ascii or preserve"
+                        .to_string(),
+                default_value: Some(serde_json::Value::String("github".to_string())),
+                required: false,
+                validation_rules: vec![ValidationRule::OneOf(vec![
+                    "github".to_string(),
+                    "ascii".to_string(),
+                    "preserve".to_string(),
+                ])],
+            },
+        );
+
+        schema.insert(
+            "numbering".to_string(),
+            FieldSchema {
+                field_type: FieldType::String,
+                description:
+                    "What rendered documents number: all (sections, figures and tables), sections, figures or off"
+                    .to_string(),
+                default_value: Some(serde_json::Value::String("off".to_string())),
+                required: false,
+                validation_rules: vec![ValidationRule::OneOf(vec![
+                    "all".to_string(),
+                    "sections".to_string(),
+                    "figures".to_string(),
+                    "off".to_string(),
+                ])],
+            },
+        );
+
+        schema.insert(
+            "author".to_string(),
+            FieldSchema {
+                field_type: FieldType::String,
+                description: "Author filled into {{author}} of documents created from templates"
+                    .to_string(),
+                default_value: None,
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "templates_dir".to_string(),
+            FieldSchema {
+                field_type: FieldType::String,
+                description: "Directory of document templates, looked in before .rune/templates"
+                    .to_string(),
+                default_value: None,
+                required: false,
+                validation_rules: vec![],
+            },
+        );
+
+        schema.insert(
+            "a11y_checks".to_string(),
+            FieldSchema {
+                field_type: FieldType::Boolean,
+                description: "Report accessibility issues of rendered documents in their metadata"
+                    .to_string(),
                 default_value: Some(serde_json::Value::Bool(false)),
                 required: false,
                 validation_rules: vec![],
@@ -1263,6 +3085,59 @@ pub struct FieldSchema {
     pub validation_rules: Vec<ValidationRule>,
 }
 
+impl FieldSchema {
+    /// Optional field without a default or rules
+    pub fn new(field_type: FieldType, description: &str) -> Self {
+        Self {
+            field_type,
+            description: description.to_string(),
+            default_value: None,
+            required: false,
+            validation_rules: Vec::new(),
+        }
+    }
+}
+
+/// Keys of the plugins' configuration, by plugin name
+static PLUGIN_SCHEMAS: RwLock<BTreeMap<String, HashMap<String, FieldSchema>>> =
+    RwLock::new(BTreeMap::new());
+
+/// Check the configuration of plugin `plugin` against `schema` from now on
+///
+/// Plugins register their schema when they are discovered, so that
+/// [`Config::validate_comprehensive`] catches unknown and mistyped keys of
+/// theirs rather than only the core fields.
+pub fn register_plugin_schema(plugin: &str, schema: HashMap<String, FieldSchema>) {
+    PLUGIN_SCHEMAS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(plugin.to_string(), schema);
+}
+
+/// Schema registered for the configuration of `plugin`
+pub fn plugin_schema(plugin: &str) -> Option<HashMap<String, FieldSchema>> {
+    PLUGIN_SCHEMAS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(plugin)
+        .cloned()
+}
+
+/// Number of single character edits turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
 /// Field types for configuration validation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FieldType {
@@ -1337,6 +3212,8 @@ pub struct ConfigLoadContext {
     pub override_paths: Vec<PathBuf>,
     pub environment_overrides: HashMap<String, String>,
     pub cli_overrides: HashMap<String, serde_json::Value>,
+    /// Profile applied after the override files
+    pub profile: Option<String>,
     pub validation_enabled: bool,
     pub strict_mode: bool,
 }
@@ -1348,6 +3225,7 @@ impl Default for ConfigLoadContext {
             override_paths: vec![],
             environment_overrides: HashMap::new(),
             cli_overrides: HashMap::new(),
+            profile: None,
             validation_enabled: true,
             strict_mode: false,
         }
@@ -1375,7 +3253,7 @@ pub enum ValidationStatus {
 }
 
 /// Configuration difference between two configs
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ConfigDiff {
     pub server_changes: Vec<ConfigChange>,
     pub plugin_changes: Vec<ConfigChange>,
@@ -1395,6 +3273,84 @@ impl ConfigDiff {
         self.server_changes.len() + self.plugin_changes.len() + self.global_setting_changes.len()
     }
 
+    /// Changed settings, addressed like the keys of [`Config::apply_update`]
+    pub fn changed_fields(&self) -> Vec<String> {
+        let server = self
+            .server_changes
+            .iter()
+            .map(|change| format!("server.{}", change.field));
+        let plugins = self
+            .plugin_changes
+            .iter()
+            .map(|change| format!("plugin.{}", change.field));
+        let globals = self
+            .global_setting_changes
+            .iter()
+            .map(|change| format!("global.{}", change.field));
+        server.chain(plugins).chain(globals).collect()
+    }
+
+    /// Plugins whose configuration changed; server settings belong to the server plugin
+    pub fn affected_plugins(&self) -> Vec<String> {
+        let mut plugins: Vec<String> = self
+            .plugin_changes
+            .iter()
+            .map(|change| change.field.clone())
+            .collect();
+        if !self.server_changes.is_empty() {
+            plugins.push("server".to_string());
+        }
+        plugins.sort();
+        plugins.dedup();
+        plugins
+    }
+
+    /// Changes that only take effect after a restart (the listening address)
+    pub fn requires_restart(&self) -> bool {
+        self.server_changes
+            .iter()
+            .any(|change| change.field == "hostname" || change.field == "port")
+    }
+
+    /// Copy of the diff with secret values replaced
+    pub fn redacted(&self) -> Self {
+        let redact = |changes: &[ConfigChange]| -> Vec<ConfigChange> {
+            changes
+                .iter()
+                .map(|change| {
+                    if is_sensitive_key(&change.field) {
+                        let hidden = |value: &Option<serde_json::Value>| {
+                            value
+                                .as_ref()
+                                .map(|_| serde_json::Value::String(REDACTED.to_string()))
+                        };
+                        ConfigChange {
+                            old_value: hidden(&change.old_value),
+                            new_value: hidden(&change.new_value),
+                            ..change.clone()
+                        }
+                    } else {
+                        change.clone()
+                    }
+                })
+                .collect()
+        };
+        Self {
+            server_changes: redact(&self.server_changes),
+            // Plugin values embed their whole configuration, which may hold secrets
+            plugin_changes: self
+                .plugin_changes
+                .iter()
+                .map(|change| ConfigChange {
+                    old_value: None,
+                    new_value: None,
+                    ..change.clone()
+                })
+                .collect(),
+            global_setting_changes: redact(&self.global_setting_changes),
+        }
+    }
+
     /// Format diff as human-readable string
     pub fn format_summary(&self) -> String {
         let mut summary = Vec::new();
@@ -1423,7 +3379,7 @@ impl ConfigDiff {
 }
 
 /// Individual configuration change
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ConfigChange {
     pub field: String,
     pub old_value: Option<serde_json::Value>,
@@ -1432,7 +3388,7 @@ pub struct ConfigChange {
 }
 
 /// Type of configuration change
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum ConfigChangeType {
     Added,
     Modified,
@@ -1465,6 +3421,60 @@ impl RuntimeConfigManager {
         })
     }
 
+    /// Manage an already loaded configuration that came from `context`
+    pub fn from_config(config: Config, context: ConfigLoadContext) -> Result<Self> {
+        let now = SystemTime::now();
+        let metadata = ConfigMetadata {
+            version: "1.0.0".to_string(),
+            created_at: now,
+            updated_at: now,
+            source_files: if context.base_path.exists() {
+                vec![context.base_path.clone()]
+            } else {
+                Vec::new()
+            },
+            checksum: config.calculate_checksum()?,
+            validation_status: ValidationStatus::NotValidated,
+        };
+
+        Ok(Self {
+            current_config: config,
+            current_metadata: metadata,
+            load_context: context,
+            validation_enabled: true,
+            change_listeners: Vec::new(),
+        })
+    }
+
+    /// Write updates to the base configuration file
+    ///
+    /// Only the given keys are changed in the file; profiles, override files
+    /// and command line settings stay out of it.
+    pub fn persist(&self, updates: &HashMap<String, serde_json::Value>) -> Result<PathBuf> {
+        let path = &self.load_context.base_path;
+        if path.as_os_str().is_empty() {
+            return Err(RuneError::Config(
+                "Cannot persist configuration: no configuration file was loaded".to_string(),
+            ));
+        }
+        if !path.exists() {
+            return Err(RuneError::Config(format!(
+                "Cannot persist configuration: {} does not exist",
+                path.display()
+            )));
+        }
+
+        let mut config = Config::from_file(path)?;
+        for (key, value) in updates {
+            config.apply_update(key, value.clone())?;
+        }
+        if self.validation_enabled {
+            config.validate_comprehensive()?;
+        }
+        config.save_to_file(path)?;
+        Ok(path.clone())
+    }
+
     /// Get current configuration
     pub fn get_config(&self) -> &Config {
         &self.current_config
@@ -1516,33 +3526,7 @@ impl RuntimeConfigManager {
 
         // Apply updates
         for (key, value) in updates {
-            match key.as_str() {
-                key if key.starts_with("server.") => {
-                    let field = key.strip_prefix("server.").unwrap();
-                    match field {
-                        "hostname" => {
-                            if let serde_json::Value::String(hostname) = value {
-                                new_config.server.hostname = hostname;
-                            }
-                        }
-                        "port" => {
-                            if let serde_json::Value::Number(port) = value {
-                                if let Some(port_val) = port.as_u64() {
-                                    new_config.server.port = port_val as u16;
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-                key if key.starts_with("global.") => {
-                    let setting_key = key.strip_prefix("global.").unwrap();
-                    new_config
-                        .global_settings
-                        .insert(setting_key.to_string(), value);
-                }
-                _ => {}
-            }
+            new_config.apply_update(&key, value)?;
         }
 
         // Validate if enabled
@@ -1833,6 +3817,52 @@ mod tests {
         assert_eq!(manager.get_config().server.port, 4000);
     }
 
+    #[test]
+    fn test_runtime_updates_are_redacted_and_persisted() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut base = Config::new();
+        base.set_plugin_config(PluginConfig::new("deploy".to_string()));
+        base.save_to_file(temp_file.path()).unwrap();
+
+        let mut config = base.clone();
+        config.server.port = 4000; // e.g. from the command line
+        let mut manager = RuntimeConfigManager::from_config(
+            config,
+            ConfigLoadContext {
+                base_path: temp_file.path().to_path_buf(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let mut updates = HashMap::new();
+        updates.insert("plugin.deploy.auth_token".to_string(), "s3cr3t".into());
+        updates.insert("global.log_level".to_string(), "debug".into());
+        let diff = manager.update_config(updates.clone()).unwrap();
+        assert_eq!(diff.affected_plugins(), vec!["deploy"]);
+        assert!(!diff.requires_restart());
+
+        let view = manager.get_config().redacted().unwrap();
+        assert_eq!(view["plugins"][0]["config"]["auth_token"], REDACTED);
+        assert_eq!(view["global_settings"]["log_level"], "debug");
+
+        // Only the updated keys reach the file
+        manager.persist(&updates).unwrap();
+        let saved = Config::from_file(temp_file.path()).unwrap();
+        assert_eq!(saved.server.port, 3000);
+        assert_eq!(
+            saved
+                .get_plugin_config("deploy")
+                .unwrap()
+                .get::<String>("auth_token"),
+            Some("s3cr3t".to_string())
+        );
+
+        let mut unknown = HashMap::new();
+        unknown.insert("server.colour".to_string(), "blue".into());
+        assert!(manager.update_config(unknown).is_err());
+    }
+
     #[test]
     fn test_validation_error_types() {
         let schema = FieldSchema {
@@ -1871,4 +3901,310 @@ mod tests {
             ValidationErrorType::InvalidValue
         ));
     }
+
+    #[test]
+    fn test_config_profiles() {
+        let json = r#"{
+            "server": {"hostname": "127.0.0.1", "port": 3000, "static_dir": null,
+                       "cors_enabled": true, "websocket_enabled": true},
+            "plugins": [{"name": "editor", "enabled": true, "version": null, "config": {},
+                         "dependencies": [], "load_order": null}],
+            "global_settings": {"log_level": "info"},
+            "profiles": {
+                "dev": {"server": {"port": 3001}, "global_settings": {"log_level": "debug"}},
+                "prod": {"extends": "dev", "server": {"hostname": "0.0.0.0"},
+                         "plugins": [{"name": "editor", "enabled": false}]},
+                "broken": {"server": {"port": 0}},
+                "loop": {"extends": "loop"}
+            }
+        }"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+
+        let prod = config.resolve_profile("prod").unwrap();
+        assert_eq!(prod.server.port, 3001);
+        assert_eq!(prod.server.hostname, "0.0.0.0");
+        assert!(!prod.get_plugin_config("editor").unwrap().enabled);
+        assert_eq!(
+            prod.get_global_setting::<String>("log_level").as_deref(),
+            Some("debug")
+        );
+        // The base configuration is left untouched
+        assert_eq!(config.server.port, 3000);
+
+        let error = config.resolve_profile("staging").unwrap_err().to_string();
+        assert!(error.contains("available: broken, dev, loop, prod"));
+        assert!(config.resolve_profile("loop").is_err());
+
+        let mut result = ValidationResult {
+            is_valid: true,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        };
+        config.validate_profiles(&mut result);
+        let fields: Vec<&str> = result
+            .errors
+            .iter()
+            .map(|e| e.field_path.as_str())
+            .collect();
+        assert_eq!(
+            fields,
+            vec!["profiles.broken.server.port", "profiles.loop.extends"]
+        );
+    }
+
+    #[test]
+    fn test_export_hooks_config() {
+        let json = r#"{
+            "server": {"hostname": "127.0.0.1", "port": 3000, "static_dir": null,
+                       "cors_enabled": true, "websocket_enabled": true},
+            "plugins": [],
+            "global_settings": {},
+            "export_hooks": {
+                "pre": [{"command": "optipng images/*.png"}],
+                "post": [{"plugin": "deploy", "allow_failure": true}, {"command": " "}]
+            }
+        }"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            config.export_hooks.pre[0].action,
+            ExportHookAction::Command("optipng images/*.png".to_string())
+        );
+        assert!(config.export_hooks.post[0].allow_failure);
+
+        let result = config.validate_comprehensive();
+        assert!(result.is_err());
+
+        // Configs written before hooks existed still load
+        let legacy: Config =
+            serde_json::from_str(&serde_json::to_string(&Config::new()).unwrap()).unwrap();
+        assert!(legacy.export_hooks.is_empty());
+    }
+
+    #[test]
+    fn test_log_config() {
+        let json = r#"{
+            "server": {"hostname": "127.0.0.1", "port": 3000, "static_dir": null,
+                       "cors_enabled": true, "websocket_enabled": true},
+            "plugins": [],
+            "global_settings": {},
+            "log": {"levels": {"rune_server": "debug"}},
+            "profiles": {
+                "quiet": {"log": {"level": "warn", "levels": {"rune_server": "info"}}},
+                "noisy": {"log": {"levels": {"rune_core": "chatty"}}}
+            }
+        }"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.log.levels.get("rune_server").unwrap(), "debug");
+
+        let quiet = config.resolve_profile("quiet").unwrap();
+        assert_eq!(quiet.log.level.as_deref(), Some("warn"));
+        assert_eq!(quiet.log.levels.get("rune_server").unwrap(), "info");
+
+        let error = config.validate_comprehensive().unwrap_err().to_string();
+        assert!(error.contains("profiles.noisy.log.levels.rune_core: Unknown log level 'chatty'"));
+    }
+
+    #[test]
+    fn test_memory_config() {
+        let config = Config::new();
+        assert_eq!(config.memory.budget_bytes(), 256 * 1024 * 1024);
+        assert_eq!(
+            config.memory.check_interval(),
+            std::time::Duration::from_secs(30)
+        );
+
+        let json = r#"{
+            "server": {"hostname": "127.0.0.1", "port": 3000, "static_dir": null,
+                       "cors_enabled": true, "websocket_enabled": true},
+            "plugins": [],
+            "global_settings": {},
+            "memory": {"budget_mb": 64},
+            "profiles": {
+                "small": {"memory": {"budget_mb": 16, "check_interval_secs": 5}},
+                "broken": {"memory": {"check_interval_secs": 0}}
+            }
+        }"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.memory.budget_bytes(), 64 * 1024 * 1024);
+
+        let small = config.resolve_profile("small").unwrap();
+        assert_eq!(small.memory.budget_bytes(), 16 * 1024 * 1024);
+        assert_eq!(
+            small.memory.check_interval(),
+            std::time::Duration::from_secs(5)
+        );
+
+        let error = config.validate_comprehensive().unwrap_err().to_string();
+        assert!(error.contains("profiles.broken.memory.check_interval_secs"));
+    }
+
+    #[test]
+    fn test_large_file_config() {
+        let config = Config::new();
+        assert_eq!(config.large_files.threshold_bytes(), 10 * 1024 * 1024);
+        assert_eq!(config.large_files.window_bytes(), 256 * 1024);
+
+        let json = r#"{
+            "server": {"hostname": "127.0.0.1", "port": 3000, "static_dir": null,
+                       "cors_enabled": true, "websocket_enabled": true},
+            "plugins": [],
+            "global_settings": {},
+            "large_files": {"threshold_mb": 50},
+            "profiles": {
+                "laptop": {"large_files": {"window_kb": 64}},
+                "broken": {"large_files": {"threshold_mb": 0}}
+            }
+        }"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.large_files.threshold_bytes(), 50 * 1024 * 1024);
+
+        let laptop = config.resolve_profile("laptop").unwrap();
+        assert_eq!(laptop.large_files.threshold_bytes(), 50 * 1024 * 1024);
+        assert_eq!(laptop.large_files.window_bytes(), 64 * 1024);
+
+        let error = config.validate_comprehensive().unwrap_err().to_string();
+        assert!(error.contains("profiles.broken.large_files.threshold_mb"));
+    }
+
+    #[test]
+    fn test_link_preview_config() {
+        let json = r#"{
+            "server": {"hostname": "127.0.0.1", "port": 3000, "static_dir": null,
+                       "cors_enabled": true, "websocket_enabled": true},
+            "plugins": [],
+            "global_settings": {},
+            "link_previews": {"enabled": true, "timeout_ms": 1500},
+            "profiles": {
+                "offline": {"link_previews": {"offline": true}},
+                "broken": {"link_previews": {"timeout_ms": 0}}
+            }
+        }"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.link_previews.enabled, Some(true));
+        assert!(Config::new().link_previews.is_empty());
+
+        let offline = config.resolve_profile("offline").unwrap();
+        assert_eq!(offline.link_previews.offline, Some(true));
+        assert_eq!(offline.link_previews.timeout_ms, Some(1500));
+
+        let error = config.validate_comprehensive().unwrap_err().to_string();
+        assert!(error.contains("profiles.broken.link_previews.timeout_ms"));
+    }
+
+    #[test]
+    fn test_process_config() {
+        let json = r#"{
+            "server": {"hostname": "127.0.0.1", "port": 3000, "static_dir": null,
+                       "cors_enabled": true, "websocket_enabled": true},
+            "plugins": [],
+            "global_settings": {},
+            "processes": {"allowed_programs": ["pandoc"], "timeout_secs": 30}
+        }"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.processes.allowed_programs, ["pandoc"]);
+        let unset: ProcessConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(unset.allowed_programs, ["*"]);
+        let none: ProcessConfig = serde_json::from_str(r#"{"allowed_programs": []}"#).unwrap();
+        assert!(none.allowed_programs.is_empty());
+        assert_eq!(
+            config.processes.timeout(),
+            std::time::Duration::from_secs(30)
+        );
+        assert_eq!(config.processes.max_output_bytes(), 64 * 1024 * 1024);
+        assert!(config.validate_comprehensive().is_ok());
+
+        let mut invalid = config.clone();
+        invalid.processes.max_output_mb = Some(0);
+        invalid.processes.pass_env = vec![" ".to_string()];
+        let error = invalid.validate_comprehensive().unwrap_err().to_string();
+        assert!(error.contains("processes.max_output_mb"), "{}", error);
+        assert!(error.contains("processes.pass_env"), "{}", error);
+    }
+
+    #[test]
+    fn test_cors_config() {
+        let json = r#"{
+            "server": {"hostname": "127.0.0.1", "port": 3000, "static_dir": null,
+                       "cors_enabled": true, "websocket_enabled": true,
+                       "cors": {"allowed_origins": ["https://notes.example.com"]}},
+            "plugins": [],
+            "global_settings": {}
+        }"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.server.cors.origins(), ["https://notes.example.com"]);
+        assert_eq!(config.server.cors.methods(), ["GET", "HEAD", "POST"]);
+        assert!(!config.server.cors.allows_credentials());
+        assert!(Config::new().server.cors.origins().is_empty());
+        assert!(config.validate_comprehensive().is_ok());
+
+        let mut invalid = config.clone();
+        invalid.server.cors = CorsConfig {
+            allowed_origins: Some(vec!["*".to_string(), "example.com/notes".to_string()]),
+            allowed_methods: Some(vec!["get".to_string()]),
+            allowed_headers: Some(vec!["x header".to_string()]),
+            allow_credentials: Some(true),
+        };
+        let error = invalid.validate_comprehensive().unwrap_err().to_string();
+        for field in [
+            "allowed_origins: 'example.com/notes'",
+            "allowed_methods",
+            "allowed_headers",
+            "allow_credentials",
+        ] {
+            assert!(
+                error.contains(&format!("server.cors.{}", field)),
+                "{}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_plugin_keys_are_checked_against_registered_schemas() {
+        register_plugin_schema(
+            "schema-test",
+            HashMap::from([
+                (
+                    "tls_cert_path".to_string(),
+                    FieldSchema::new(FieldType::String, "Certificate file"),
+                ),
+                (
+                    "retries".to_string(),
+                    FieldSchema::new(FieldType::Number, "Attempts"),
+                ),
+            ]),
+        );
+        let mut config = Config::new();
+        let mut plugin = PluginConfig::new("schema-test".to_string());
+        plugin.set("tls_cert_pth".to_string(), "cert.pem").unwrap();
+        plugin.set("colour".to_string(), "red").unwrap();
+        config.plugins.push(plugin.clone());
+
+        let result = config.validate_comprehensive().unwrap();
+        let mut warnings: Vec<(&str, Option<&str>)> = result
+            .warnings
+            .iter()
+            .filter(|w| w.field_path.starts_with("plugins.schema-test"))
+            .map(|w| (w.field_path.as_str(), w.suggestion.as_deref()))
+            .collect();
+        warnings.sort();
+        assert_eq!(
+            warnings,
+            vec![
+                (
+                    "plugins.schema-test.config.colour",
+                    Some("Remove this key or check for typos")
+                ),
+                (
+                    "plugins.schema-test.config.tls_cert_pth",
+                    Some("Did you mean 'tls_cert_path'?")
+                ),
+            ]
+        );
+
+        plugin.set("retries".to_string(), "three").unwrap();
+        config.plugins = vec![plugin];
+        let error = config.validate_comprehensive().unwrap_err().to_string();
+        assert!(error.contains("plugins.schema-test.config.retries"));
+    }
 }
@@ -0,0 +1,510 @@
+//! Conversion of HTML to markdown
+//!
+//! Rich text pasted from word processors and web pages arrives as HTML.
+//! [`parse_html`] reads it into the markdown AST, keeping the structure
+//! markdown can express and dropping styling, scripts and layout wrappers,
+//! and [`MarkdownSerializer`] writes it out as markdown. Formatting that
+//! Google Docs expresses through inline styles (`font-weight: 700`,
+//! `font-style: italic`, monospace fonts) is read as the matching markdown
+//! emphasis or code.
+
+use scraper::{ElementRef, Html};
+
+use crate::ast::{Node, NodeType, Tree};
+use crate::serializer::MarkdownSerializer;
+
+/// Convert an HTML fragment to markdown
+pub fn html_to_markdown(html: &str) -> String {
+    MarkdownSerializer::new().serialize(&parse_html(html))
+}
+
+/// Read an HTML fragment into the markdown AST
+pub fn parse_html(html: &str) -> Tree {
+    let document = Html::parse_fragment(html);
+    let mut tree = Tree::new();
+    tree.root.children = blocks(document.root_element());
+    tree
+}
+
+/// Elements whose content is never part of the document
+const SKIPPED: &[&str] = &[
+    "head", "script", "style", "meta", "link", "title", "noscript", "template", "button",
+];
+
+/// Elements that start a block of their own
+const BLOCKS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "center",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "html",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "ul",
+];
+
+fn is_block(element: &ElementRef) -> bool {
+    BLOCKS.contains(&element.value().name())
+}
+
+fn contains_block(element: &ElementRef) -> bool {
+    element
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .any(|descendant| is_block(&descendant))
+}
+
+/// Block nodes for the content of a container, with runs of inline
+/// content gathered into paragraphs
+fn blocks(container: ElementRef) -> Vec<Node> {
+    let mut blocks = Vec::new();
+    let mut paragraph = Node::new(NodeType::Paragraph);
+    for child in container.children() {
+        match ElementRef::wrap(child) {
+            Some(element) if SKIPPED.contains(&element.value().name()) => {}
+            Some(element) if is_block(&element) => {
+                flush_paragraph(&mut blocks, &mut paragraph);
+                blocks.extend(block(element));
+            }
+            // Inline wrappers around blocks, like the `b` Google Docs puts
+            // around a whole fragment, are dropped
+            Some(element) if contains_block(&element) => {
+                flush_paragraph(&mut blocks, &mut paragraph);
+                blocks.extend(self::blocks(element));
+            }
+            Some(element) => inline(element, &mut paragraph),
+            None => {
+                if let Some(text) = child.value().as_text() {
+                    push_text(&mut paragraph, text);
+                }
+            }
+        }
+    }
+    flush_paragraph(&mut blocks, &mut paragraph);
+    blocks
+}
+
+fn flush_paragraph(blocks: &mut Vec<Node>, paragraph: &mut Node) {
+    let finished = std::mem::replace(paragraph, Node::new(NodeType::Paragraph));
+    if !finished.text_content().trim().is_empty()
+        || finished
+            .children
+            .iter()
+            .any(|child| child.node_type == NodeType::Image)
+    {
+        blocks.push(finished);
+    }
+}
+
+fn block(element: ElementRef) -> Vec<Node> {
+    let name = element.value().name();
+    let node = match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let mut heading = Node::new(NodeType::Heading);
+            heading.level = name[1..].parse().ok();
+            inlines(element, &mut heading);
+            heading
+        }
+        "ul" | "ol" => list(element),
+        "pre" => code_block(element),
+        "blockquote" => {
+            let mut quote = Node::new(NodeType::Blockquote);
+            quote.children = blocks(element);
+            quote
+        }
+        "table" => table(element),
+        "hr" => Node::new(NodeType::ThematicBreak),
+        "dt" => {
+            let mut term = Node::new(NodeType::Paragraph);
+            let mut strong = Node::new(NodeType::Strong);
+            inlines(element, &mut strong);
+            term.append_child(strong);
+            term
+        }
+        _ => return blocks(element),
+    };
+    vec![node]
+}
+
+fn list(element: ElementRef) -> Node {
+    let mut list = Node::new(NodeType::List);
+    let ordered = element.value().name() == "ol";
+    list.set_attribute("type", if ordered { "ordered" } else { "unordered" });
+    if let Some(start) = element.value().attr("start") {
+        list.set_attribute("start", start.trim());
+    }
+
+    for child in element.children().filter_map(ElementRef::wrap) {
+        if child.value().name() == "li" {
+            let mut item = Node::new(NodeType::ListItem);
+            item.children = blocks(child);
+            if let Some(checkbox) = child.children().filter_map(ElementRef::wrap).find(|input| {
+                input.value().name() == "input" && input.value().attr("type") == Some("checkbox")
+            }) {
+                let checked = checkbox.value().attr("checked").is_some();
+                item.set_attribute("checked", if checked { "true" } else { "false" });
+            }
+            list.append_child(item);
+        } else if is_block(&child) {
+            // Google Docs nests lists as siblings of their parent item
+            match list.children.last_mut() {
+                Some(item) => item.children.extend(block(child)),
+                None => {
+                    let mut item = Node::new(NodeType::ListItem);
+                    item.children = block(child);
+                    list.append_child(item);
+                }
+            }
+        }
+    }
+    list
+}
+
+fn code_block(element: ElementRef) -> Node {
+    let mut code_block = Node::new(NodeType::CodeBlock);
+    let language = std::iter::once(element)
+        .chain(element.children().filter_map(ElementRef::wrap))
+        .filter_map(|element| element.value().attr("class"))
+        .flat_map(str::split_whitespace)
+        .find_map(|class| class.strip_prefix("language-"));
+    if let Some(language) = language {
+        code_block.data = language.to_string();
+    }
+
+    let mut code = String::new();
+    preformatted_text(element, &mut code);
+    code_block.append_child(Node::text(code.trim_end_matches('\n')));
+    code_block
+}
+
+/// Text of a preformatted element, keeping its whitespace
+fn preformatted_text(element: ElementRef, code: &mut String) {
+    for child in element.children() {
+        match ElementRef::wrap(child) {
+            Some(element) if element.value().name() == "br" => code.push('\n'),
+            Some(element) => preformatted_text(element, code),
+            None => {
+                if let Some(text) = child.value().as_text() {
+                    code.push_str(text);
+                }
+            }
+        }
+    }
+}
+
+fn table(element: ElementRef) -> Node {
+    let mut rows = Vec::new();
+    collect_rows(element, &mut rows);
+
+    let mut table = Node::new(NodeType::Table);
+    let mut rows = rows.into_iter();
+    // Markdown tables need a header; without one the first row is used
+    if let Some(header) = rows.next() {
+        let mut head = Node::new(NodeType::TableHead);
+        head.append_child(header);
+        table.append_child(head);
+    }
+    table.children.extend(rows);
+    table
+}
+
+/// Rows of a table, without descending into nested tables
+fn collect_rows(element: ElementRef, rows: &mut Vec<Node>) {
+    for child in element.children().filter_map(ElementRef::wrap) {
+        match child.value().name() {
+            "thead" | "tbody" | "tfoot" => collect_rows(child, rows),
+            "tr" => {
+                let mut row = Node::new(NodeType::TableRow);
+                for cell in child.children().filter_map(ElementRef::wrap) {
+                    let name = cell.value().name();
+                    if name != "td" && name != "th" {
+                        continue;
+                    }
+                    let mut node = Node::new(NodeType::TableCell);
+                    node.data = alignment(cell).unwrap_or_default().to_string();
+                    // Cells hold a single line; their paragraphs are run together
+                    for (index, paragraph) in blocks(cell).into_iter().enumerate() {
+                        if index > 0 {
+                            node.append_child(Node::text(" "));
+                        }
+                        node.children.extend(paragraph.children);
+                    }
+                    row.append_child(node);
+                }
+                rows.push(row);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn alignment(cell: ElementRef) -> Option<&'static str> {
+    let style = cell.value().attr("style").unwrap_or_default();
+    let align = cell
+        .value()
+        .attr("align")
+        .or_else(|| style_value(style, "text-align"))?;
+    match align.trim().to_ascii_lowercase().as_str() {
+        "left" => Some("left"),
+        "center" => Some("center"),
+        "right" => Some("right"),
+        _ => None,
+    }
+}
+
+fn inlines(element: ElementRef, parent: &mut Node) {
+    for child in element.children() {
+        match ElementRef::wrap(child) {
+            Some(element) if SKIPPED.contains(&element.value().name()) => {}
+            Some(element) => inline(element, parent),
+            None => {
+                if let Some(text) = child.value().as_text() {
+                    push_text(parent, text);
+                }
+            }
+        }
+    }
+}
+
+fn inline(element: ElementRef, parent: &mut Node) {
+    let value = element.value();
+    let node = match value.name() {
+        "br" => Node::new(NodeType::LineBreak),
+        "img" => {
+            let mut image = Node::new(NodeType::Image);
+            image.set_attribute("src", value.attr("src").unwrap_or_default());
+            image.set_attribute("alt", value.attr("alt").unwrap_or_default().trim());
+            image
+        }
+        "code" | "kbd" | "samp" | "tt" => {
+            let mut code = Node::new(NodeType::Code);
+            code.append_child(Node::text(&collapse_whitespace(&text_of(element))));
+            code
+        }
+        "a" => match value.attr("href").map(str::trim) {
+            Some(href) if !href.is_empty() && !href.starts_with("javascript:") => {
+                let mut link = Node::new(NodeType::Link);
+                link.set_attribute("href", href);
+                inlines(element, &mut link);
+                link
+            }
+            _ => return inlines(element, parent),
+        },
+        "input" => return,
+        name => {
+            let style = value.attr("style").unwrap_or_default();
+            let mut kinds = Vec::new();
+            let bold = match name {
+                // Google Docs wraps whole fragments in a `b` styled normal
+                "b" | "strong" => !style_value(style, "font-weight").is_some_and(is_normal_weight),
+                _ => style_value(style, "font-weight").is_some_and(is_bold_weight),
+            };
+            if bold {
+                kinds.push(NodeType::Strong);
+            }
+            if matches!(name, "em" | "i" | "cite" | "dfn")
+                || style_value(style, "font-style").is_some_and(|v| v.contains("italic"))
+            {
+                kinds.push(NodeType::Emph);
+            }
+            if matches!(name, "s" | "del" | "strike")
+                || style_value(style, "text-decoration").is_some_and(|v| v.contains("line-through"))
+            {
+                kinds.push(NodeType::Strikethrough);
+            }
+            if style_value(style, "font-family").is_some_and(is_monospace) {
+                let mut code = Node::new(NodeType::Code);
+                code.append_child(Node::text(&collapse_whitespace(&text_of(element))));
+                return push_inline(parent, wrap(kinds, code));
+            }
+
+            let Some(innermost) = kinds.pop() else {
+                return inlines(element, parent);
+            };
+            let mut node = Node::new(innermost);
+            inlines(element, &mut node);
+            wrap(kinds, node)
+        }
+    };
+    push_inline(parent, node);
+}
+
+/// `node` inside nested nodes of the given kinds, outermost first
+fn wrap(kinds: Vec<NodeType>, node: Node) -> Node {
+    kinds.into_iter().rev().fold(node, |inner, kind| {
+        let mut outer = Node::new(kind);
+        outer.append_child(inner);
+        outer
+    })
+}
+
+/// Append an inline node, merging it into a preceding node of the same
+/// emphasis so split spans do not produce `**a****b**`
+fn push_inline(parent: &mut Node, node: Node) {
+    if let Some(last) = parent.children.last_mut() {
+        let mergeable = matches!(
+            node.node_type,
+            NodeType::Strong | NodeType::Emph | NodeType::Strikethrough
+        );
+        if mergeable && last.node_type == node.node_type {
+            last.children.extend(node.children);
+            return;
+        }
+    }
+    if node.node_type != NodeType::Text && node.children.is_empty() && is_container(&node) {
+        return;
+    }
+    parent.append_child(node);
+}
+
+fn is_container(node: &Node) -> bool {
+    matches!(
+        node.node_type,
+        NodeType::Strong | NodeType::Emph | NodeType::Strikethrough | NodeType::Link
+    )
+}
+
+fn push_text(parent: &mut Node, text: &str) {
+    let text = collapse_whitespace(text);
+    if text.is_empty() {
+        return;
+    }
+    match parent.children.last_mut() {
+        // Text split around comments or skipped elements
+        Some(last) if last.node_type == NodeType::Text => {
+            let text = if last.data.ends_with(' ') {
+                text.trim_start()
+            } else {
+                &text
+            };
+            last.data.push_str(text)
+        }
+        _ => parent.append_child(Node::text(&text)),
+    }
+}
+
+fn text_of(element: ElementRef) -> String {
+    element.text().collect()
+}
+
+/// Whitespace runs as single spaces, as browsers show them
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !in_space {
+                collapsed.push(' ');
+            }
+            in_space = true;
+        } else {
+            collapsed.push(c);
+            in_space = false;
+        }
+    }
+    collapsed
+}
+
+/// Value of a property in an inline `style` attribute
+fn style_value<'a>(style: &'a str, property: &str) -> Option<&'a str> {
+    style.split(';').find_map(|declaration| {
+        let (name, value) = declaration.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case(property)
+            .then(|| value.trim())
+    })
+}
+
+fn is_bold_weight(weight: &str) -> bool {
+    weight.starts_with("bold") || weight.parse::<u32>().is_ok_and(|weight| weight >= 600)
+}
+
+fn is_normal_weight(weight: &str) -> bool {
+    weight == "normal" || weight.parse::<u32>().is_ok_and(|weight| weight < 600)
+}
+
+fn is_monospace(family: &str) -> bool {
+    let family = family.to_ascii_lowercase();
+    [
+        "monospace",
+        "courier",
+        "consolas",
+        "menlo",
+        "monaco",
+        "source code",
+    ]
+    .iter()
+    .any(|mono| family.contains(mono))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_google_docs_html_becomes_clean_markdown() {
+        let html = r#"<meta charset="utf-8"><b style="font-weight:normal;" id="docs-internal-guid-1">
+            <h2 dir="ltr"><span style="font-size:16pt;">Release plan</span></h2>
+            <p dir="ltr"><span style="font-weight:700;">Ship</span><span style="font-weight:700;"> it</span>
+            <span style="font-style:italic;">soon</span>, see <a href="https://example.com/a b">the doc</a>
+            and <span style="font-family:'Courier New';">cargo run</span>.</p>
+            <ul><li dir="ltr"><p dir="ltr"><span>First</span></p></li>
+            <ul><li><p><span>Nested</span></p></li></ul>
+            <li><p><span>Second * item</span></p></li></ul>
+            <table><tbody><tr><td><p>Name</p></td><td style="text-align: right">Cost</td></tr>
+            <tr><td>Tea | cake</td><td>4</td></tr></tbody></table>
+            <pre><code class="language-rust">fn main() {
+    println!("hi");
+}</code></pre><script>alert(1)</script></b>"#;
+
+        let markdown = html_to_markdown(html);
+        assert_eq!(
+            markdown,
+            "## Release plan\n\n\
+             **Ship it** *soon*, see [the doc](<https://example.com/a b>) and `cargo run`.\n\n\
+             - First\n  - Nested\n- Second \\* item\n\n\
+             | Name | Cost |\n| --- | ---: |\n| Tea \\| cake | 4 |\n\n\
+             ```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_web_page_html_keeps_structure() {
+        let html = "<div><h1>Title</h1>Loose text<br>next line<blockquote><p>Quoted \
+                    <strong>bold </strong>text</p></blockquote><ol start=\"3\"><li>three</li>\
+                    <li><input type=\"checkbox\" checked> done</li></ol><hr>\
+                    <p>1. not a list</p><img src=\"cat.png\" alt=\"A cat\"></div>";
+
+        assert_eq!(
+            html_to_markdown(html),
+            "# Title\n\nLoose text  \nnext line\n\n> Quoted **bold** text\n\n\
+             3. three\n4. [x] done\n\n---\n\n1\\. not a list\n\n![A cat](cat.png)\n"
+        );
+    }
+}
@@ -0,0 +1,334 @@
+//! Panic capture and crash reports
+//!
+//! [`install_panic_hook`] records every panic with its backtrace and, when a
+//! report directory is given, writes it to a `crash-*.txt` file there. Tasks
+//! started with [`spawn_guarded`], and futures run through [`catch_panic`],
+//! turn a panic into a [`CrashReport`] instead of vanishing silently;
+//! [`spawn_guarded`] also announces it as a critical [`SystemEvent::Error`] so
+//! the rest of the engine keeps running and can react.
+
+use crate::event::{ErrorSeverity, EventBus, SystemEvent};
+use futures_util::FutureExt;
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tracing::error;
+
+tokio::task_local! {
+    /// Name of the guarded task being polled, for reports written by the hook
+    static TASK: String;
+}
+
+thread_local! {
+    /// Report of the latest panic on this thread, picked up by [`catch_panic`]
+    static LAST_PANIC: RefCell<Option<CrashReport>> = const { RefCell::new(None) };
+}
+
+static REPORT_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Details of a panic
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    /// Guarded task or event handler that panicked, if known
+    pub task: Option<String>,
+    pub thread: Option<String>,
+    pub message: String,
+    /// `file:line:column` of the panic
+    pub location: Option<String>,
+    /// Empty when the panic hook was not installed
+    pub backtrace: String,
+    pub timestamp: SystemTime,
+    /// File the report was written to
+    pub report_path: Option<PathBuf>,
+}
+
+impl CrashReport {
+    fn from_hook(info: &PanicHookInfo<'_>) -> Self {
+        Self {
+            task: TASK.try_with(|task| task.clone()).ok(),
+            thread: std::thread::current().name().map(str::to_string),
+            message: payload_message(info.payload()),
+            location: info.location().map(|location| location.to_string()),
+            backtrace: Backtrace::force_capture().to_string(),
+            timestamp: SystemTime::now(),
+            report_path: None,
+        }
+    }
+
+    fn from_payload(task: &str, payload: &(dyn Any + Send)) -> Self {
+        Self {
+            task: Some(task.to_string()),
+            thread: std::thread::current().name().map(str::to_string),
+            message: payload_message(payload),
+            location: None,
+            backtrace: String::new(),
+            timestamp: SystemTime::now(),
+            report_path: None,
+        }
+    }
+
+    /// One-line description, e.g. `'server' panicked at src/lib.rs:10:5: boom`
+    pub fn summary(&self) -> String {
+        let mut summary = match &self.task {
+            Some(task) => format!("'{}' panicked", task),
+            None => "Panicked".to_string(),
+        };
+        if let Some(location) = &self.location {
+            let _ = write!(summary, " at {}", location);
+        }
+        let _ = write!(summary, ": {}", self.message);
+        summary
+    }
+
+    /// Critical error event carrying the summary, backtrace and report file
+    pub fn to_event(&self) -> SystemEvent {
+        let mut message = self.summary();
+        if let Some(path) = &self.report_path {
+            let _ = write!(message, "\nCrash report: {}", path.display());
+        }
+        if !self.backtrace.is_empty() {
+            let _ = write!(message, "\n\nBacktrace:\n{}", self.backtrace);
+        }
+        SystemEvent::error(
+            self.task.clone().unwrap_or_else(|| "unknown".to_string()),
+            message,
+            ErrorSeverity::Critical,
+        )
+    }
+
+    /// Plain text report as written to disk
+    pub fn render(&self) -> String {
+        let seconds = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let mut report = String::new();
+        let _ = writeln!(report, "Rune crash report");
+        let _ = writeln!(report, "version: {}", env!("CARGO_PKG_VERSION"));
+        let _ = writeln!(report, "time: {} (unix seconds)", seconds);
+        let _ = writeln!(report, "task: {}", self.task.as_deref().unwrap_or("-"));
+        let _ = writeln!(report, "thread: {}", self.thread.as_deref().unwrap_or("-"));
+        let _ = writeln!(
+            report,
+            "location: {}",
+            self.location.as_deref().unwrap_or("-")
+        );
+        let _ = writeln!(report, "message: {}", self.message);
+        let _ = writeln!(report, "\nbacktrace:\n{}", self.backtrace);
+        report
+    }
+
+    /// Write the report to a new file in `dir`
+    pub fn write_to(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let seconds = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let path = dir.join(format!(
+            "crash-{}-{}-{}.txt",
+            seconds,
+            std::process::id(),
+            REPORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&path, self.render())?;
+        Ok(path)
+    }
+}
+
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+/// Default directory for crash reports, `<data dir>/rune/crashes`
+pub fn default_report_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("rune").join("crashes"))
+}
+
+/// Record panics with backtraces, writing reports to `report_dir` if given;
+/// the previously installed hook still runs afterwards
+pub fn install_panic_hook(report_dir: Option<PathBuf>) {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let mut report = CrashReport::from_hook(info);
+        if let Some(dir) = &report_dir {
+            match report.write_to(dir) {
+                Ok(path) => report.report_path = Some(path),
+                Err(e) => eprintln!("Failed to write crash report: {}", e),
+            }
+        }
+        LAST_PANIC.with(|last| *last.borrow_mut() = Some(report));
+        previous(info);
+    }));
+}
+
+/// Run `future`, returning a report instead of unwinding if it panics
+pub async fn catch_panic<F: Future>(
+    task: &str,
+    future: F,
+) -> std::result::Result<F::Output, CrashReport> {
+    let outcome = TASK
+        .scope(task.to_string(), AssertUnwindSafe(future).catch_unwind())
+        .await;
+    outcome.map_err(|payload| {
+        LAST_PANIC
+            .with(|last| last.borrow_mut().take())
+            .map(|mut report| {
+                report.task = Some(task.to_string());
+                report
+            })
+            .unwrap_or_else(|| CrashReport::from_payload(task, payload.as_ref()))
+    })
+}
+
+/// Spawn a task whose panic is logged and published as a critical error
+pub fn spawn_guarded<F>(
+    task: impl Into<String>,
+    event_bus: Arc<dyn EventBus>,
+    future: F,
+) -> JoinHandle<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let task = task.into();
+    tokio::spawn(async move {
+        if let Err(report) = catch_panic(&task, future).await {
+            error!("Task {}", report.summary());
+            if let Err(e) = event_bus.publish_system_event(report.to_event()).await {
+                error!("Failed to publish crash of task '{}': {}", task, e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{Event, InMemoryEventBus, SystemEventHandler};
+    use async_trait::async_trait;
+    use tokio::sync::Mutex;
+
+    struct Collector(Mutex<Vec<SystemEvent>>);
+
+    #[async_trait]
+    impl SystemEventHandler for Collector {
+        async fn handle_system_event(&self, event: &SystemEvent) -> crate::error::Result<()> {
+            self.0.lock().await.push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_catch_panic_reports_task() {
+        assert_eq!(catch_panic("fine", async { 7 }).await.unwrap(), 7);
+
+        let report = catch_panic("render", async {
+            panic!("boom");
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(report.task.as_deref(), Some("render"));
+        assert_eq!(report.message, "boom");
+        assert!(report.summary().starts_with("'render' panicked"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = report.write_to(dir.path()).unwrap();
+        let written = std::fs::read_to_string(path).unwrap();
+        assert!(written.contains("task: render"));
+        assert!(written.contains("message: boom"));
+    }
+
+    #[tokio::test]
+    async fn test_spawn_guarded_publishes_error() {
+        let event_bus: Arc<dyn EventBus> = Arc::new(InMemoryEventBus::new());
+        let collector = Arc::new(Collector(Mutex::new(Vec::new())));
+        event_bus
+            .subscribe_system_events(collector.clone())
+            .await
+            .unwrap();
+
+        spawn_guarded("watcher", event_bus.clone(), async {
+            panic!("lost the watcher");
+        })
+        .await
+        .unwrap();
+
+        let events = collector.0.lock().await;
+        match events.as_slice() {
+            [SystemEvent::Error {
+                source,
+                message,
+                severity,
+                ..
+            }] => {
+                assert_eq!(source, "watcher");
+                assert!(message.contains("lost the watcher"));
+                assert!(matches!(severity, ErrorSeverity::Critical));
+            }
+            other => panic!("unexpected events: {:?}", other),
+        }
+    }
+
+    struct Panicker;
+
+    #[async_trait]
+    impl SystemEventHandler for Panicker {
+        async fn handle_system_event(&self, event: &SystemEvent) -> crate::error::Result<()> {
+            if !event.is_error() {
+                panic!("cannot render");
+            }
+            Ok(())
+        }
+
+        fn handler_name(&self) -> &str {
+            "renderer"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_panicking_handler_is_reported() {
+        let event_bus = InMemoryEventBus::new();
+        let collector = Arc::new(Collector(Mutex::new(Vec::new())));
+        event_bus
+            .subscribe_system_events(Arc::new(Panicker))
+            .await
+            .unwrap();
+        event_bus
+            .subscribe_system_events(collector.clone())
+            .await
+            .unwrap();
+
+        event_bus
+            .publish_system_event(SystemEvent::file_changed(
+                PathBuf::from("doc.md"),
+                crate::event::ChangeType::Modified,
+            ))
+            .await
+            .unwrap();
+
+        // The other handler still got the event, followed by the crash report
+        let events = collector.0.lock().await;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event_type(), "file_changed");
+        match &events[1] {
+            SystemEvent::Error { source, .. } => assert_eq!(source, "renderer"),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+}
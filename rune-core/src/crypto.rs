@@ -0,0 +1,248 @@
+//! Passphrase-encrypted documents
+//!
+//! A document named like `notes.md.age` is an [age](https://age-encryption.org)
+//! file encrypted with a passphrase, as written by `age --passphrase`. Once
+//! unlocked with its passphrase, [`read_document`] decrypts it in memory and
+//! [`write_document`] encrypts what is saved, so the plaintext never reaches
+//! the disk. Other documents are read and written as they are, transcoded
+//! from and to their [text encoding](crate::text_encoding).
+
+use crate::config::EncryptionConfig;
+use crate::safe_write::{self, WriteStrategy};
+use crate::text_encoding::{self, TextEncoding};
+use crate::{Result, RuneError};
+use age::secrecy::{ExposeSecret, SecretString};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Extension of encrypted documents, after the markdown extension
+pub const ENCRYPTED_EXTENSION: &str = "age";
+
+/// Environment variable a passphrase may be given in
+pub const PASSPHRASE_ENV: &str = "RUNE_PASSPHRASE";
+
+/// Passphrase and last decrypted content of an unlocked document
+struct UnlockedDocument {
+    passphrase: SecretString,
+    plaintext: String,
+    /// Modification time and size of the file `plaintext` was read from
+    stamp: Option<(SystemTime, u64)>,
+}
+
+fn unlocked() -> &'static Mutex<HashMap<PathBuf, UnlockedDocument>> {
+    static UNLOCKED: OnceLock<Mutex<HashMap<PathBuf, UnlockedDocument>>> = OnceLock::new();
+    UNLOCKED.get_or_init(Default::default)
+}
+
+/// Whether `path` names an encrypted document
+pub fn is_encrypted(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(ENCRYPTED_EXTENSION))
+}
+
+/// `path` without the encryption extension, e.g. `notes.md` for
+/// `notes.md.age`; the extension of the result tells the document's format
+pub fn plain_path(path: &Path) -> PathBuf {
+    if is_encrypted(path) {
+        path.with_extension("")
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Encrypt `plaintext` with `passphrase`
+pub fn encrypt(plaintext: &str, passphrase: &str) -> Result<Vec<u8>> {
+    encrypt_with(
+        plaintext,
+        age::scrypt::Recipient::new(SecretString::from(passphrase.to_string())),
+    )
+}
+
+fn encrypt_with(plaintext: &str, recipient: age::scrypt::Recipient) -> Result<Vec<u8>> {
+    let encryptor = age::Encryptor::with_recipients(std::iter::once(&recipient as _))
+        .map_err(|e| RuneError::file_system(format!("Encryption failed: {}", e)))?;
+    let mut ciphertext = Vec::with_capacity(plaintext.len() + 256);
+    let mut writer = encryptor.wrap_output(&mut ciphertext)?;
+    writer.write_all(plaintext.as_bytes())?;
+    writer.finish()?;
+    Ok(ciphertext)
+}
+
+/// Decrypt an age file, binary or ASCII-armored, with `passphrase`
+pub fn decrypt(ciphertext: &[u8], passphrase: &str) -> Result<String> {
+    decrypt_text(ciphertext, passphrase)
+        .map_err(|e| RuneError::file_system(format!("Decryption failed: {}", e)))
+}
+
+fn decrypt_text(ciphertext: &[u8], passphrase: &str) -> std::result::Result<String, String> {
+    let identity = age::scrypt::Identity::new(SecretString::from(passphrase.to_string()));
+    let plaintext = age::decrypt(&identity, ciphertext).map_err(|e| e.to_string())?;
+    String::from_utf8(plaintext).map_err(|_| "the document is not UTF-8 text".to_string())
+}
+
+/// Remember the passphrase of an encrypted document, after checking that
+/// it decrypts the document
+pub fn unlock(path: &Path, passphrase: &str) -> Result<()> {
+    let key = canonical(path);
+    let ciphertext = std::fs::read(&key)?;
+    let plaintext = decrypt_text(&ciphertext, passphrase)
+        .map_err(|e| RuneError::file_system(format!("Cannot open {}: {}", path.display(), e)))?;
+    unlocked().lock().unwrap().insert(
+        key.clone(),
+        UnlockedDocument {
+            passphrase: SecretString::from(passphrase.to_string()),
+            plaintext,
+            stamp: stamp(&key),
+        },
+    );
+    Ok(())
+}
+
+/// Forget the passphrase and content of a document
+pub fn lock(path: &Path) {
+    unlocked().lock().unwrap().remove(&canonical(path));
+}
+
+/// Whether an encrypted document was unlocked
+pub fn is_unlocked(path: &Path) -> bool {
+    unlocked().lock().unwrap().contains_key(&canonical(path))
+}
+
+/// Content of a document; encrypted documents must have been unlocked and
+/// are only decrypted again when the file changed
+pub fn read_document(path: &Path) -> Result<String> {
+    Ok(read_document_encoded(path)?.0)
+}
+
+/// Read a document and the encoding it is stored in; encrypted documents
+/// are always UTF-8
+pub fn read_document_encoded(path: &Path) -> Result<(String, TextEncoding)> {
+    if !is_encrypted(path) {
+        return text_encoding::read_text(path);
+    }
+
+    let key = canonical(path);
+    let mut documents = unlocked().lock().unwrap();
+    let document = documents.get_mut(&key).ok_or_else(|| locked(path))?;
+    let current = stamp(&key);
+    if current.is_none() || current != document.stamp {
+        let ciphertext = std::fs::read(&key)?;
+        document.plaintext = decrypt(&ciphertext, document.passphrase.expose_secret())?;
+        document.stamp = current;
+    }
+    Ok((document.plaintext.clone(), TextEncoding::Utf8))
+}
+
+/// Save a document; encrypted documents must have been unlocked and are
+/// encrypted again with their passphrase
+pub fn write_document(path: &Path, content: &str) -> Result<()> {
+    write_document_with(path, content, WriteStrategy::default())
+}
+
+/// Save a document the way `strategy` says
+pub fn write_document_with(path: &Path, content: &str, strategy: WriteStrategy) -> Result<()> {
+    write_document_encoded(path, content, strategy, TextEncoding::Utf8)
+}
+
+/// Save a document in `encoding`; encrypted documents are always UTF-8
+pub fn write_document_encoded(
+    path: &Path,
+    content: &str,
+    strategy: WriteStrategy,
+    encoding: TextEncoding,
+) -> Result<()> {
+    if !is_encrypted(path) {
+        safe_write::write_file(path, &encoding.encode(content)?, strategy)?;
+        return Ok(());
+    }
+
+    let key = canonical(path);
+    let mut documents = unlocked().lock().unwrap();
+    let document = documents.get_mut(&key).ok_or_else(|| locked(path))?;
+    let recipient = age::scrypt::Recipient::new(document.passphrase.clone());
+    safe_write::write_file(&key, &encrypt_with(content, recipient)?, strategy)?;
+    document.plaintext = content.to_string();
+    document.stamp = stamp(&key);
+    Ok(())
+}
+
+/// Passphrase of an encrypted document from the environment or the
+/// configured passphrase command, which may look it up in a keyring
+pub async fn configured_passphrase(
+    path: &Path,
+    config: &EncryptionConfig,
+) -> Result<Option<String>> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(Some(passphrase));
+    }
+    let Some(command) = &config.passphrase_command else {
+        return Ok(None);
+    };
+
+    let runner = crate::process::runner();
+    let mut process = runner.shell(command)?;
+    process.env("RUNE_DOCUMENT", path);
+    let output = runner
+        .output(process, None)
+        .await
+        .map_err(|e| RuneError::config(format!("Passphrase command failed: {}", e)))?;
+    let passphrase = String::from_utf8(output)
+        .map_err(|_| RuneError::config("Passphrase command printed invalid UTF-8"))?;
+    Ok(Some(passphrase.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn locked(path: &Path) -> RuneError {
+    RuneError::file_system(format!(
+        "{} is encrypted and has not been unlocked",
+        path.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap encryption, as the default work factor takes about a second
+    fn quick_encrypt(plaintext: &str, passphrase: &str) -> Vec<u8> {
+        let mut recipient = age::scrypt::Recipient::new(SecretString::from(passphrase.to_string()));
+        recipient.set_work_factor(4);
+        encrypt_with(plaintext, recipient).unwrap()
+    }
+
+    #[test]
+    fn test_encrypted_documents_round_trip_in_memory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("notes.md.age");
+        std::fs::write(&path, quick_encrypt("# Secret", "hunter2")).unwrap();
+
+        assert!(is_encrypted(&path));
+        assert_eq!(plain_path(&path), temp_dir.path().join("notes.md"));
+        assert!(read_document(&path).is_err());
+        assert!(unlock(&path, "wrong").is_err());
+        assert!(!is_unlocked(&path));
+
+        unlock(&path, "hunter2").unwrap();
+        assert_eq!(read_document(&path).unwrap(), "# Secret");
+
+        write_document(&path, "# Changed").unwrap();
+        let on_disk = std::fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&on_disk).contains("Changed"));
+        assert_eq!(decrypt(&on_disk, "hunter2").unwrap(), "# Changed");
+        assert_eq!(read_document(&path).unwrap(), "# Changed");
+
+        lock(&path);
+        assert!(read_document(&path).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! Draft and scheduled documents
+//!
+//! A document with `draft: true` in its frontmatter is work in progress,
+//! and one with a `publish_at` time still ahead is not out yet. The preview
+//! marks both with a banner, `rune export` skips them unless given
+//! `--drafts`, and outside dev mode the `drafts` setting can keep them out
+//! of directory listings and off the server altogether. The
+//! [`PublishScheduler`](crate::schedule::PublishScheduler) reloads a
+//! scheduled document's pages once its time has come.
+
+use crate::config::Config;
+use crate::error::{Result, RuneError};
+use crate::frontmatter;
+use crate::time;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Whether the YAML frontmatter of `markdown` sets `draft: true`
+pub fn is_draft(markdown: &str) -> bool {
+    frontmatter::value(markdown, "draft")
+        .is_some_and(|value| matches!(value.to_ascii_lowercase().as_str(), "true" | "yes" | "on"))
+}
+
+/// Time the frontmatter's `publish_at` names, if it names one
+pub fn publish_at(markdown: &str) -> Option<SystemTime> {
+    frontmatter::value(markdown, "publish_at").and_then(parse_time)
+}
+
+/// Parse `2024-05-01`, `2024-05-01T09:30`, `2024-05-01 09:30:00` or an RFC
+/// 3339 time like `2024-05-01T09:30:00+02:00` (`+0200` works too); times
+/// without an offset are UTC
+pub fn parse_time(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+    let (date, time) = match value.split_once(['T', 't', ' ']) {
+        Some((date, time)) => (date, time.trim()),
+        None => (value, ""),
+    };
+
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if !(1..=month_days).contains(&day) {
+        return None;
+    }
+
+    // Offset of the local time from UTC, in seconds
+    let (clock, offset) = match time.rfind(['+', '-', 'Z', 'z']) {
+        Some(at) => (&time[..at], time::parse_utc_offset(&time[at..])?),
+        None => (time, 0),
+    };
+    let mut clock = clock.split(':');
+    let mut field = |max: i64| -> Option<i64> {
+        match clock.next() {
+            // Fractions of a second are dropped
+            Some(part) => part
+                .split('.')
+                .next()?
+                .parse()
+                .ok()
+                .filter(|value| (0..=max).contains(value)),
+            None => Some(0),
+        }
+    };
+    let (hour, minute, second) = if time.is_empty() {
+        (0, 0, 0)
+    } else {
+        (field(23)?, field(59)?, field(60)?)
+    };
+
+    let days = time::days_from_civil(year, month, day);
+
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second - offset;
+    let seconds = u64::try_from(seconds).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// `time` as `CCYY-MM-DDThh:mm:ssZ`
+pub fn format_time(time: SystemTime) -> String {
+    time::utc_timestamp(time)
+}
+
+/// Whether a document is out, and if not, why
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublishState {
+    Published,
+    Draft,
+    /// Published at the given time
+    Scheduled(SystemTime),
+}
+
+impl PublishState {
+    /// State of the document `markdown` at `now`
+    ///
+    /// A `publish_at` that cannot be read keeps the document back like a
+    /// draft rather than publishing it early.
+    pub fn of(markdown: &str, now: SystemTime) -> Self {
+        if is_draft(markdown) {
+            return Self::Draft;
+        }
+        let Some(value) = frontmatter::value(markdown, "publish_at").filter(|v| !v.is_empty())
+        else {
+            return Self::Published;
+        };
+        match parse_time(value) {
+            Some(time) if time > now => Self::Scheduled(time),
+            Some(_) => Self::Published,
+            None => {
+                tracing::warn!(
+                    "Unreadable publish_at '{}'; keeping the document back as a draft",
+                    value
+                );
+                Self::Draft
+            }
+        }
+    }
+
+    /// State of the markdown file at `path` now; unreadable files count as
+    /// published
+    pub fn of_file(path: &Path) -> Self {
+        match crate::crypto::read_document(path) {
+            Ok(content) => Self::of(&content, SystemTime::now()),
+            Err(_) => Self::Published,
+        }
+    }
+
+    pub fn is_published(self) -> bool {
+        self == Self::Published
+    }
+}
+
+/// Whether the server shows drafts and documents scheduled for later
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DraftVisibility {
+    /// Listed and served, with a banner
+    #[default]
+    Show,
+    /// Left out of directory listings and answered with 404
+    Hide,
+}
+
+impl DraftVisibility {
+    /// Visibility the configuration asks for; dev mode always shows drafts
+    pub fn from_config(config: &Config) -> Self {
+        if config.get_global_setting::<bool>("dev_mode") == Some(true) {
+            return Self::Show;
+        }
+        match config
+            .get_global_setting::<String>("drafts")
+            .map(|drafts| drafts.parse())
+        {
+            Some(Ok(visibility)) => visibility,
+            Some(Err(e)) => {
+                tracing::warn!("{}; showing drafts", e);
+                Self::Show
+            }
+            None => Self::Show,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::Hide => "hide",
+        }
+    }
+}
+
+impl FromStr for DraftVisibility {
+    type Err = RuneError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "show" => Ok(Self::Show),
+            "hide" => Ok(Self::Hide),
+            other => Err(RuneError::config(format!(
+                "Unknown draft visibility '{}'. Supported values: show, hide",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drafts_and_visibility() {
+        assert!(is_draft("---\ntitle: Wip\ndraft: true\n---\n# Wip\n"));
+        assert!(is_draft("---\r\ndraft: \"yes\"\r\n---\r\n"));
+        assert!(!is_draft("---\ndraft: false\n---\n"));
+        assert!(!is_draft("---\ntitle: x\n---\ndraft: true\n"));
+        assert!(!is_draft("draft: true\n"));
+
+        let at = |value| parse_time(value).map(format_time);
+        assert_eq!(at("2024-02-29").as_deref(), Some("2024-02-29T00:00:00Z"));
+        assert_eq!(
+            at("2024-05-01T09:30").as_deref(),
+            Some("2024-05-01T09:30:00Z")
+        );
+        assert_eq!(
+            at("2024-05-01 09:30:15.250+02:00").as_deref(),
+            Some("2024-05-01T07:30:15Z")
+        );
+        assert_eq!(
+            at("1999-12-31T23:00:00-01:30").as_deref(),
+            Some("2000-01-01T00:30:00Z")
+        );
+        assert_eq!(
+            at("2024-05-01T09:30+0530").as_deref(),
+            Some("2024-05-01T04:00:00Z")
+        );
+        assert_eq!(
+            at("2024-05-01T09:30-05").as_deref(),
+            Some("2024-05-01T14:30:00Z")
+        );
+        assert!(parse_time("2024-13-01").is_none());
+        assert!(parse_time("2023-02-29").is_none());
+        assert!(parse_time("2024-05-01T09:30+5:30").is_none());
+        assert!(parse_time("soon").is_none());
+
+        let now = parse_time("2024-05-01").unwrap();
+        let scheduled = "---\npublish_at: \"2024-06-01\"\n---\n";
+        assert_eq!(
+            PublishState::of(scheduled, now),
+            PublishState::Scheduled(parse_time("2024-06-01").unwrap())
+        );
+        assert!(PublishState::of(scheduled, parse_time("2024-06-01").unwrap()).is_published());
+        assert_eq!(
+            PublishState::of("---\ndraft: true\npublish_at: 2000-01-01\n---\n", now),
+            PublishState::Draft
+        );
+        // A time that cannot be read fails closed
+        assert_eq!(
+            PublishState::of("---\npublish_at: 2024-13-01\n---\n", now),
+            PublishState::Draft
+        );
+
+        let mut config = Config::new();
+        assert_eq!(DraftVisibility::from_config(&config), DraftVisibility::Show);
+        config
+            .set_global_setting("drafts".to_string(), "hide")
+            .unwrap();
+        assert_eq!(DraftVisibility::from_config(&config), DraftVisibility::Hide);
+        config
+            .set_global_setting("dev_mode".to_string(), true)
+            .unwrap();
+        assert_eq!(DraftVisibility::from_config(&config), DraftVisibility::Show);
+        assert!("publish".parse::<DraftVisibility>().is_err());
+    }
+}
@@ -48,6 +48,10 @@ pub enum RuneError {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    /// External program errors
+    #[error("Process error: {0}")]
+    Process(#[from] crate::process::ProcessError),
+
     /// Generic errors
     #[error("Error: {0}")]
     Generic(String),
@@ -112,6 +116,7 @@ impl RuneError {
             RuneError::State(_) => true,
             RuneError::Io(_) => true,
             RuneError::Json(_) => false,
+            RuneError::Process(_) => true,
             RuneError::Generic(_) => true,
         }
     }
@@ -129,6 +134,7 @@ impl RuneError {
             RuneError::State(_) => ErrorSeverity::Medium,
             RuneError::Io(_) => ErrorSeverity::Medium,
             RuneError::Json(_) => ErrorSeverity::Low,
+            RuneError::Process(_) => ErrorSeverity::Medium,
             RuneError::Generic(_) => ErrorSeverity::Low,
         }
     }
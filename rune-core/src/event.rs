@@ -10,7 +10,11 @@ use std::time::{Duration, SystemTime};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+use crate::chaos::ChaosInjector;
+use crate::crash::{catch_panic, CrashReport};
 use crate::error::Result;
+use crate::notification::Notification;
+use crate::trace::{self, TraceId};
 
 /// Event serialization utilities for persistence and debugging
 pub mod serialization {
@@ -237,6 +241,12 @@ pub enum SystemEvent {
         version: String,
         timestamp: SystemTime,
     },
+    /// Plugin failed to initialize
+    PluginFailed {
+        plugin_name: String,
+        error: String,
+        timestamp: SystemTime,
+    },
     /// Plugin was unloaded
     PluginUnloaded {
         plugin_name: String,
@@ -253,11 +263,20 @@ pub enum SystemEvent {
         theme_name: String,
         timestamp: SystemTime,
     },
+    /// Configuration was changed at runtime
+    ConfigChanged {
+        changed_fields: Vec<String>,
+        affected_plugins: Vec<String>,
+        timestamp: SystemTime,
+    },
     /// Content rendering completed
     RenderComplete {
         content_hash: String,
         duration: Duration,
         timestamp: SystemTime,
+        /// Request the render was done for
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        trace_id: Option<TraceId>,
     },
     /// System error occurred
     Error {
@@ -265,12 +284,31 @@ pub enum SystemEvent {
         message: String,
         severity: ErrorSeverity,
         timestamp: SystemTime,
+        /// Request that failed
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        trace_id: Option<TraceId>,
     },
     /// Server started
     ServerStarted {
         address: String,
         timestamp: SystemTime,
     },
+    /// Server stopped listening, for good or to be started again
+    ServerStopped {
+        address: String,
+        timestamp: SystemTime,
+    },
+    /// Tunnel to the server opened at a public URL
+    TunnelOpened {
+        public_url: String,
+        provider: String,
+        timestamp: SystemTime,
+    },
+    /// Message for the user, shown by every open page
+    Notification {
+        notification: Notification,
+        timestamp: SystemTime,
+    },
     /// Server handler registered
     ServerHandlerRegistered {
         handler_type: String,
@@ -287,6 +325,15 @@ pub enum SystemEvent {
     SystemShutdownInitiated { timestamp: SystemTime },
     /// System preparing for shutdown
     SystemShutdownPreparing { timestamp: SystemTime },
+    /// Plugins of one shutdown stage are being shut down; stages run from
+    /// the plugins nothing depends on to the ones everything does
+    SystemShutdownStage {
+        /// Stage number, counting from 1
+        stage: usize,
+        stages: usize,
+        plugins: Vec<String>,
+        timestamp: SystemTime,
+    },
     /// System shutdown completed
     SystemShutdownComplete { timestamp: SystemTime },
 }
@@ -300,16 +347,22 @@ impl Event for SystemEvent {
             SystemEvent::ClientDisconnected { .. } => "client_disconnected",
             SystemEvent::PluginLoading { .. } => "plugin_loading",
             SystemEvent::PluginLoaded { .. } => "plugin_loaded",
+            SystemEvent::PluginFailed { .. } => "plugin_failed",
             SystemEvent::PluginUnloaded { .. } => "plugin_unloaded",
             SystemEvent::PluginHealthCheck { .. } => "plugin_health_check",
             SystemEvent::ThemeChanged { .. } => "theme_changed",
+            SystemEvent::ConfigChanged { .. } => "config_changed",
             SystemEvent::RenderComplete { .. } => "render_complete",
             SystemEvent::Error { .. } => "error",
             SystemEvent::ServerStarted { .. } => "server_started",
+            SystemEvent::ServerStopped { .. } => "server_stopped",
+            SystemEvent::TunnelOpened { .. } => "tunnel_opened",
+            SystemEvent::Notification { .. } => "notification",
             SystemEvent::ServerHandlerRegistered { .. } => "server_handler_registered",
             SystemEvent::ServerHandlerUnregistered { .. } => "server_handler_unregistered",
             SystemEvent::SystemShutdownInitiated { .. } => "system_shutdown_initiated",
             SystemEvent::SystemShutdownPreparing { .. } => "system_shutdown_preparing",
+            SystemEvent::SystemShutdownStage { .. } => "system_shutdown_stage",
             SystemEvent::SystemShutdownComplete { .. } => "system_shutdown_complete",
         }
     }
@@ -321,16 +374,22 @@ impl Event for SystemEvent {
             SystemEvent::ClientDisconnected { timestamp, .. } => *timestamp,
             SystemEvent::PluginLoading { timestamp, .. } => *timestamp,
             SystemEvent::PluginLoaded { timestamp, .. } => *timestamp,
+            SystemEvent::PluginFailed { timestamp, .. } => *timestamp,
             SystemEvent::PluginUnloaded { timestamp, .. } => *timestamp,
             SystemEvent::PluginHealthCheck { timestamp, .. } => *timestamp,
             SystemEvent::ThemeChanged { timestamp, .. } => *timestamp,
+            SystemEvent::ConfigChanged { timestamp, .. } => *timestamp,
             SystemEvent::RenderComplete { timestamp, .. } => *timestamp,
             SystemEvent::Error { timestamp, .. } => *timestamp,
             SystemEvent::ServerStarted { timestamp, .. } => *timestamp,
+            SystemEvent::ServerStopped { timestamp, .. } => *timestamp,
+            SystemEvent::TunnelOpened { timestamp, .. } => *timestamp,
+            SystemEvent::Notification { timestamp, .. } => *timestamp,
             SystemEvent::ServerHandlerRegistered { timestamp, .. } => *timestamp,
             SystemEvent::ServerHandlerUnregistered { timestamp, .. } => *timestamp,
             SystemEvent::SystemShutdownInitiated { timestamp, .. } => *timestamp,
             SystemEvent::SystemShutdownPreparing { timestamp, .. } => *timestamp,
+            SystemEvent::SystemShutdownStage { timestamp, .. } => *timestamp,
             SystemEvent::SystemShutdownComplete { timestamp, .. } => *timestamp,
         }
     }
@@ -368,6 +427,12 @@ impl Event for SystemEvent {
                 metadata.insert("plugin_name".to_string(), plugin_name.clone());
                 metadata.insert("version".to_string(), version.clone());
             }
+            SystemEvent::PluginFailed {
+                plugin_name, error, ..
+            } => {
+                metadata.insert("plugin_name".to_string(), plugin_name.clone());
+                metadata.insert("error".to_string(), error.clone());
+            }
             SystemEvent::PluginUnloaded { plugin_name, .. } => {
                 metadata.insert("plugin_name".to_string(), plugin_name.clone());
             }
@@ -382,6 +447,14 @@ impl Event for SystemEvent {
             SystemEvent::ThemeChanged { theme_name, .. } => {
                 metadata.insert("theme_name".to_string(), theme_name.clone());
             }
+            SystemEvent::ConfigChanged {
+                changed_fields,
+                affected_plugins,
+                ..
+            } => {
+                metadata.insert("changed_fields".to_string(), changed_fields.join(","));
+                metadata.insert("affected_plugins".to_string(), affected_plugins.join(","));
+            }
             SystemEvent::RenderComplete {
                 content_hash,
                 duration,
@@ -400,9 +473,23 @@ impl Event for SystemEvent {
                 metadata.insert("message".to_string(), message.clone());
                 metadata.insert("severity".to_string(), format!("{:?}", severity));
             }
-            SystemEvent::ServerStarted { address, .. } => {
+            SystemEvent::ServerStarted { address, .. }
+            | SystemEvent::ServerStopped { address, .. } => {
                 metadata.insert("address".to_string(), address.clone());
             }
+            SystemEvent::TunnelOpened {
+                public_url,
+                provider,
+                ..
+            } => {
+                metadata.insert("public_url".to_string(), public_url.clone());
+                metadata.insert("provider".to_string(), provider.clone());
+            }
+            SystemEvent::Notification { notification, .. } => {
+                metadata.insert("level".to_string(), format!("{:?}", notification.level));
+                metadata.insert("source".to_string(), notification.source.clone());
+                metadata.insert("message".to_string(), notification.message.clone());
+            }
             SystemEvent::ServerHandlerRegistered {
                 handler_type, path, ..
             } => {
@@ -421,22 +508,50 @@ impl Event for SystemEvent {
             SystemEvent::SystemShutdownPreparing { .. } => {
                 // No additional metadata for shutdown events
             }
+            SystemEvent::SystemShutdownStage {
+                stage,
+                stages,
+                plugins,
+                ..
+            } => {
+                metadata.insert("stage".to_string(), stage.to_string());
+                metadata.insert("stages".to_string(), stages.to_string());
+                metadata.insert("plugins".to_string(), plugins.join(","));
+            }
             SystemEvent::SystemShutdownComplete { .. } => {
                 // No additional metadata for shutdown events
             }
         }
+        if let Some(trace_id) = self.trace_id() {
+            metadata.insert("trace_id".to_string(), trace_id.to_string());
+        }
 
         metadata
     }
 }
 
 /// Types of file system changes
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ChangeType {
     Created,
     Modified,
     Deleted,
-    Renamed { from: PathBuf, to: PathBuf },
+    Renamed {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// The page template changed; open pages need to load again
+    TemplateModified,
+    /// A theme stylesheet changed; open pages only need their styles again
+    StyleModified,
+}
+
+impl ChangeType {
+    /// Whether the change is to the page template or theme stylesheet
+    /// rather than to a document or its assets
+    pub fn is_template_change(&self) -> bool {
+        matches!(self, Self::TemplateModified | Self::StyleModified)
+    }
 }
 
 /// Information about connected clients
@@ -500,6 +615,15 @@ impl SystemEvent {
         }
     }
 
+    /// Create a new plugin failed event with current timestamp
+    pub fn plugin_failed(plugin_name: String, error: String) -> Self {
+        Self::PluginFailed {
+            plugin_name,
+            error,
+            timestamp: SystemTime::now(),
+        }
+    }
+
     /// Create a new plugin unloaded event with current timestamp
     pub fn plugin_unloaded(plugin_name: String) -> Self {
         Self::PluginUnloaded {
@@ -528,22 +652,45 @@ impl SystemEvent {
         }
     }
 
-    /// Create a new render complete event with current timestamp
+    /// Create a new config changed event with current timestamp
+    pub fn config_changed(changed_fields: Vec<String>, affected_plugins: Vec<String>) -> Self {
+        Self::ConfigChanged {
+            changed_fields,
+            affected_plugins,
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    /// Create a new render complete event with current timestamp and the
+    /// current trace id
     pub fn render_complete(content_hash: String, duration: Duration) -> Self {
         Self::RenderComplete {
             content_hash,
             duration,
             timestamp: SystemTime::now(),
+            trace_id: trace::current(),
         }
     }
 
-    /// Create a new error event with current timestamp
+    /// Create a new error event with current timestamp and the current
+    /// trace id
     pub fn error(source: String, message: String, severity: ErrorSeverity) -> Self {
         Self::Error {
             source,
             message,
             severity,
             timestamp: SystemTime::now(),
+            trace_id: trace::current(),
+        }
+    }
+
+    /// Request the event happened for, if it is traced
+    pub fn trace_id(&self) -> Option<&TraceId> {
+        match self {
+            SystemEvent::RenderComplete { trace_id, .. } | SystemEvent::Error { trace_id, .. } => {
+                trace_id.as_ref()
+            }
+            _ => None,
         }
     }
 
@@ -555,6 +702,31 @@ impl SystemEvent {
         }
     }
 
+    /// Create a new server stopped event with current timestamp
+    pub fn server_stopped(address: String) -> Self {
+        Self::ServerStopped {
+            address,
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    /// Create a new tunnel opened event with current timestamp
+    pub fn tunnel_opened(public_url: String, provider: String) -> Self {
+        Self::TunnelOpened {
+            public_url,
+            provider,
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    /// Create a new notification event with current timestamp
+    pub fn notification(notification: Notification) -> Self {
+        Self::Notification {
+            notification,
+            timestamp: SystemTime::now(),
+        }
+    }
+
     /// Create a new server handler registered event with current timestamp
     pub fn server_handler_registered(handler_type: String, path: String) -> Self {
         Self::ServerHandlerRegistered {
@@ -587,6 +759,16 @@ impl SystemEvent {
         }
     }
 
+    /// Create a new shutdown stage event with current timestamp
+    pub fn system_shutdown_stage(stage: usize, stages: usize, plugins: Vec<String>) -> Self {
+        Self::SystemShutdownStage {
+            stage,
+            stages,
+            plugins,
+            timestamp: SystemTime::now(),
+        }
+    }
+
     /// Create a new system shutdown complete event with current timestamp
     pub fn system_shutdown_complete() -> Self {
         Self::SystemShutdownComplete {
@@ -620,6 +802,11 @@ impl SystemEvent {
             } => {
                 format!("Plugin {} v{} loaded", plugin_name, version)
             }
+            SystemEvent::PluginFailed {
+                plugin_name, error, ..
+            } => {
+                format!("Plugin {} failed: {}", plugin_name, error)
+            }
             SystemEvent::PluginUnloaded { plugin_name, .. } => {
                 format!("Plugin {} unloaded", plugin_name)
             }
@@ -633,6 +820,9 @@ impl SystemEvent {
             SystemEvent::ThemeChanged { theme_name, .. } => {
                 format!("Theme changed to {}", theme_name)
             }
+            SystemEvent::ConfigChanged { changed_fields, .. } => {
+                format!("Configuration changed: {}", changed_fields.join(", "))
+            }
             SystemEvent::RenderComplete {
                 content_hash,
                 duration,
@@ -651,6 +841,22 @@ impl SystemEvent {
             SystemEvent::ServerStarted { address, .. } => {
                 format!("Server started on {}", address)
             }
+            SystemEvent::ServerStopped { address, .. } => {
+                format!("Server stopped on {}", address)
+            }
+            SystemEvent::TunnelOpened {
+                public_url,
+                provider,
+                ..
+            } => {
+                format!("Tunnel via {} opened at {}", provider, public_url)
+            }
+            SystemEvent::Notification { notification, .. } => {
+                format!(
+                    "{:?} notification from {}: {}",
+                    notification.level, notification.source, notification.message
+                )
+            }
             SystemEvent::ServerHandlerRegistered {
                 handler_type, path, ..
             } => {
@@ -665,6 +871,17 @@ impl SystemEvent {
             SystemEvent::SystemShutdownPreparing { .. } => {
                 "System preparing for shutdown".to_string()
             }
+            SystemEvent::SystemShutdownStage {
+                stage,
+                stages,
+                plugins,
+                ..
+            } => format!(
+                "Shutdown stage {}/{}: {}",
+                stage,
+                stages,
+                plugins.join(", ")
+            ),
             SystemEvent::SystemShutdownComplete { .. } => "System shutdown completed".to_string(),
         }
     }
@@ -693,6 +910,7 @@ impl SystemEvent {
             self,
             SystemEvent::PluginLoading { .. }
                 | SystemEvent::PluginLoaded { .. }
+                | SystemEvent::PluginFailed { .. }
                 | SystemEvent::PluginUnloaded { .. }
                 | SystemEvent::PluginHealthCheck { .. }
         )
@@ -703,6 +921,7 @@ impl SystemEvent {
         matches!(
             self,
             SystemEvent::ServerStarted { .. }
+                | SystemEvent::ServerStopped { .. }
                 | SystemEvent::ServerHandlerRegistered { .. }
                 | SystemEvent::ServerHandlerUnregistered { .. }
         )
@@ -722,6 +941,8 @@ struct Subscription {
 pub struct InMemoryEventBus {
     subscriptions: RwLock<HashMap<SubscriptionId, Subscription>>,
     type_subscriptions: RwLock<HashMap<TypeId, Vec<SubscriptionId>>>,
+    /// Injects faults into handlers in development mode
+    chaos: Option<Arc<ChaosInjector>>,
 }
 
 impl InMemoryEventBus {
@@ -730,11 +951,19 @@ impl InMemoryEventBus {
         Self {
             subscriptions: RwLock::new(HashMap::new()),
             type_subscriptions: RwLock::new(HashMap::new()),
+            chaos: None,
         }
     }
 
+    /// Let `chaos` delay, fail or panic handlers before they handle events
+    pub fn with_chaos(mut self, chaos: Arc<ChaosInjector>) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
     /// Route an event to all matching subscribers
-    async fn route_event<T: Event>(&self, event: &T) -> Result<()> {
+    /// Deliver `event` to its subscribers, returning reports of handlers that panicked
+    async fn route_event<T: Event>(&self, event: &T) -> Result<Vec<CrashReport>> {
         let type_id = TypeId::of::<T>();
 
         // Get all subscription IDs for this event type
@@ -745,12 +974,13 @@ impl InMemoryEventBus {
 
         if subscription_ids.is_empty() {
             tracing::trace!("No subscribers for event type: {}", event.event_type());
-            return Ok(());
+            return Ok(Vec::new());
         }
 
         // Process each subscription
         let subscriptions = self.subscriptions.read().await;
         let mut handlers_called = 0;
+        let mut crashes = Vec::new();
 
         for sub_id in subscription_ids {
             if let Some(subscription) = subscriptions.get(&sub_id) {
@@ -771,21 +1001,37 @@ impl InMemoryEventBus {
                     };
 
                     if should_handle {
-                        // Handle the event asynchronously
-                        if let Err(e) = handler.handle_event(event).await {
-                            tracing::error!(
+                        // Handle the event asynchronously; a panic only loses this delivery
+                        let name = handler.handler_name();
+                        let handled = async {
+                            if let Some(chaos) = &self.chaos {
+                                chaos.inject(name).await?;
+                            }
+                            handler.handle_event(event).await
+                        };
+                        match catch_panic(name, handled).await {
+                            Ok(Ok(())) => {
+                                handlers_called += 1;
+                                tracing::trace!(
+                                    "Handler {} processed event {}",
+                                    name,
+                                    event.event_type()
+                                );
+                            }
+                            Ok(Err(e)) => tracing::error!(
                                 "Handler {} failed to process event {}: {}",
-                                handler.handler_name(),
+                                name,
                                 event.event_type(),
                                 e
-                            );
-                        } else {
-                            handlers_called += 1;
-                            tracing::trace!(
-                                "Handler {} processed event {}",
-                                handler.handler_name(),
-                                event.event_type()
-                            );
+                            ),
+                            Err(report) => {
+                                tracing::error!(
+                                    "Handler {} while processing event {}",
+                                    report.summary(),
+                                    event.event_type()
+                                );
+                                crashes.push(report);
+                            }
                         }
                     }
                 }
@@ -798,7 +1044,7 @@ impl InMemoryEventBus {
             handlers_called
         );
 
-        Ok(())
+        Ok(crashes)
     }
 }
 
@@ -852,7 +1098,14 @@ impl ExtendedEventBus for InMemoryEventBus {
         tracing::debug!("Publishing event: {}", event.event_type());
 
         // Route the event to all matching subscribers
-        self.route_event(&event).await?;
+        let crashes = self.route_event(&event).await?;
+
+        // Report panicking handlers, unless they panicked on such a report
+        if event.event_type() != "error" {
+            for report in crashes {
+                self.route_event(&report.to_event()).await?;
+            }
+        }
 
         Ok(())
     }
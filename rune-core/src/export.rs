@@ -0,0 +1,960 @@
+//! Export of rendered documents to standalone files
+//!
+//! Exports run the regular render pipeline and then apply post-processing
+//! stages to its output. The self-contained stage inlines every local asset
+//! (stylesheets, fonts, images and scripts) so the result is a single portable
+//! file that opens without a running server or network access.
+//!
+//! Document formats (DOCX and EPUB) are written from the markdown AST instead
+//! of the rendered HTML, see the [`docx`] and [`epub`] modules, as are the
+//! plain text, Slack and Jira [`markup`] documents can be copied as. With
+//! pandoc installed, the formats it writes (LaTeX, org, MediaWiki, ODT and
+//! more) are converted by it from the markdown source, see
+//! [`crate::pandoc`]. Configured
+//! [`hooks`] run before rendering and after the output has been written,
+//! [`assets`] optimizes referenced images, and [`watch`] re-exports
+//! documents incrementally as they change.
+
+pub mod assets;
+pub mod docx;
+pub mod epub;
+pub mod hooks;
+pub mod markup;
+pub mod watch;
+
+use base64::Engine;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::ast::{NodeType, Tree};
+use crate::config::{ExportAssetConfig, ExportHooks};
+use crate::error::{Result, RuneError};
+use crate::pandoc::{self, Pandoc, PandocOutput};
+use crate::parser::MarkdownParser;
+use crate::renderer::{RenderContext, RendererRegistry};
+use crate::state::{CachedRender, RenderMetadata, StateManager};
+use assets::{AssetOptimizer, AssetReport};
+use hooks::{ExportHookHandler, HookEnvironment, HookRunner, HookStage};
+
+/// Supported export formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Html,
+    Docx,
+    Epub,
+    /// A format written by pandoc, see [`crate::pandoc`]
+    Pandoc(&'static PandocOutput),
+}
+
+impl ExportFormat {
+    /// All formats written without pandoc
+    pub fn all() -> &'static [ExportFormat] {
+        &[ExportFormat::Html, ExportFormat::Docx, ExportFormat::Epub]
+    }
+
+    /// File extension used for exported files
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Html => "html",
+            ExportFormat::Docx => "docx",
+            ExportFormat::Epub => "epub",
+            ExportFormat::Pandoc(output) => output.extension,
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::Html => write!(f, "html"),
+            ExportFormat::Docx => write!(f, "docx"),
+            ExportFormat::Epub => write!(f, "epub"),
+            ExportFormat::Pandoc(output) => write!(f, "{}", output.format),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = RuneError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "html" | "htm" => Ok(ExportFormat::Html),
+            "docx" | "word" => Ok(ExportFormat::Docx),
+            "epub" => Ok(ExportFormat::Epub),
+            other => match pandoc::output_format(other) {
+                Some(output) => Ok(ExportFormat::Pandoc(output)),
+                None => Err(RuneError::config(format!(
+                    "Unsupported export format '{}'. Supported formats: {}, and with \
+                     pandoc installed: {}",
+                    other,
+                    ExportFormat::all()
+                        .iter()
+                        .map(|f| f.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    pandoc::OUTPUT_FORMATS
+                        .iter()
+                        .map(|output| output.format)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))),
+            },
+        }
+    }
+}
+
+/// Options controlling a single export
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// Inline all local assets into the output file (HTML only; document
+    /// formats always embed their images)
+    pub self_contained: bool,
+    /// Output path; defaults to the input file with the format's extension
+    pub output: Option<PathBuf>,
+    /// Theme passed to the render pipeline
+    pub theme: String,
+    /// Hooks run before and after the export
+    pub hooks: ExportHooks,
+    /// Optimize referenced images (HTML only), see [`assets`]
+    pub assets: Option<ExportAssetConfig>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Html,
+            self_contained: false,
+            output: None,
+            theme: "catppuccin-mocha".to_string(),
+            hooks: ExportHooks::default(),
+            assets: None,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Resolve the output path for the given input file
+    pub fn output_path(&self, input: &Path) -> PathBuf {
+        self.output.clone().unwrap_or_else(|| {
+            crate::crypto::plain_path(input).with_extension(self.format.extension())
+        })
+    }
+
+    /// Whether referenced local files end up inside the output
+    pub fn embeds_assets(&self) -> bool {
+        self.self_contained || self.format != ExportFormat::Html
+    }
+}
+
+/// Summary of a completed export
+#[derive(Debug, Clone)]
+pub struct ExportReport {
+    pub output_path: PathBuf,
+    pub bytes_written: usize,
+    pub render_time: Duration,
+    pub post_process_time: Duration,
+    pub hooks_run: usize,
+    pub hook_time: Duration,
+    pub assets: AssetReport,
+    /// Redirect pages written for the document's aliases
+    pub redirect_stubs: usize,
+}
+
+/// Builds the final page from a document title and rendered body HTML
+pub type PageBuilder = Arc<dyn Fn(&str, &str) -> String + Send + Sync>;
+
+/// Asset with fixed content, addressed by the URL the rendered HTML uses for it
+#[derive(Debug, Clone)]
+pub struct EmbeddedAsset {
+    pub mime_type: String,
+    pub content: Vec<u8>,
+}
+
+/// Exports markdown documents through the render pipeline
+pub struct Exporter {
+    registry: Arc<RendererRegistry>,
+    page_builder: PageBuilder,
+    builtin_assets: HashMap<String, EmbeddedAsset>,
+    hooks: HookRunner,
+    render_cache: Option<Arc<StateManager>>,
+}
+
+impl Exporter {
+    /// Create a new exporter using the given renderer registry
+    pub fn new(registry: Arc<RendererRegistry>) -> Self {
+        Self {
+            registry,
+            page_builder: Arc::new(default_page),
+            builtin_assets: HashMap::new(),
+            hooks: HookRunner::new(),
+            render_cache: None,
+        }
+    }
+
+    /// Reuse rendered pages for unchanged content through the state manager's render cache
+    pub fn with_render_cache(mut self, state_manager: Arc<StateManager>) -> Self {
+        self.render_cache = Some(state_manager);
+        self
+    }
+
+    /// Use a custom page builder to wrap rendered content
+    pub fn with_page_builder(mut self, page_builder: PageBuilder) -> Self {
+        self.page_builder = page_builder;
+        self
+    }
+
+    /// Register content for an absolute URL that is normally served by Rune itself
+    pub fn with_builtin_asset(mut self, url: &str, mime_type: &str, content: Vec<u8>) -> Self {
+        self.builtin_assets.insert(
+            url.to_string(),
+            EmbeddedAsset {
+                mime_type: mime_type.to_string(),
+                content,
+            },
+        );
+        self
+    }
+
+    /// Register the handler for `{"plugin": "<name>"}` export hooks
+    pub fn with_hook_handler(mut self, plugin: &str, handler: Arc<dyn ExportHookHandler>) -> Self {
+        self.hooks.register_handler(plugin, handler);
+        self
+    }
+
+    /// Render a markdown file to a complete HTML page
+    pub async fn render_page(&self, input: &Path, options: &ExportOptions) -> Result<String> {
+        let content = crate::crypto::read_document(input)?;
+        let cache_key = content_hash(&[&input.to_string_lossy(), &options.theme, &content]);
+        if let Some(cache) = &self.render_cache {
+            if let Some(cached) = cache.get_cached_render(&cache_key).await {
+                return Ok(cached.rendered_html);
+            }
+        }
+
+        let render_start = Instant::now();
+        let base_dir = document_dir(input);
+        let context =
+            RenderContext::new(input.to_path_buf(), base_dir.clone(), options.theme.clone());
+
+        let result = self
+            .registry
+            .render_with_pipeline(&content, &context)
+            .await?;
+        let title = extract_title(&result.html).unwrap_or_else(|| {
+            crate::crypto::plain_path(input)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Document".to_string())
+        });
+
+        let page = (self.page_builder)(&title, &result.html);
+
+        if let Some(cache) = &self.render_cache {
+            let metadata = RenderMetadata {
+                file_path: Some(input.to_path_buf()),
+                theme: options.theme.clone(),
+                render_time: render_start.elapsed(),
+                content_type: "text/html".to_string(),
+                has_mermaid: result.html.contains("class=\"mermaid\""),
+            };
+            cache
+                .cache_render(
+                    cache_key.clone(),
+                    CachedRender::new(cache_key, page.clone(), metadata),
+                )
+                .await;
+        }
+
+        Ok(page)
+    }
+
+    /// Export a markdown file according to the given options
+    pub async fn export(&self, input: &Path, options: &ExportOptions) -> Result<ExportReport> {
+        let output_path = options.output_path(input);
+        let mut environment = HookEnvironment {
+            stage: HookStage::Pre,
+            input: std::path::absolute(input)?,
+            output: std::path::absolute(&output_path)?,
+            format: options.format,
+            bytes_written: None,
+        };
+
+        let hook_start = Instant::now();
+        let mut hooks_run = self.hooks.run_all(&options.hooks.pre, &environment).await?;
+        let mut hook_time = hook_start.elapsed();
+
+        let (output, render_time, post_process_time, assets) = match options.format {
+            ExportFormat::Html => self.export_html(input, options).await?,
+            ExportFormat::Docx | ExportFormat::Epub => {
+                let (output, render_time, post_process_time) =
+                    self.export_document(input, options.format).await?;
+                (
+                    output,
+                    render_time,
+                    post_process_time,
+                    AssetReport::default(),
+                )
+            }
+            ExportFormat::Pandoc(output) => {
+                let render_start = Instant::now();
+                let output = export_pandoc(input, output).await?;
+                (
+                    output,
+                    render_start.elapsed(),
+                    Duration::ZERO,
+                    AssetReport::default(),
+                )
+            }
+        };
+
+        if let Some(parent) = output_path.parent() {
+            if !parent.as_os_str().is_empty() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+        }
+        tokio::fs::write(&output_path, &output).await?;
+
+        // Old paths of the document lead to the exported page
+        let redirect_stubs = if options.format == ExportFormat::Html {
+            let content = crate::crypto::read_document(input)?;
+            crate::redirects::write_alias_stubs(&content, &output_path).await?
+        } else {
+            0
+        };
+
+        environment.stage = HookStage::Post;
+        environment.bytes_written = Some(output.len());
+        let hook_start = Instant::now();
+        hooks_run += self
+            .hooks
+            .run_all(&options.hooks.post, &environment)
+            .await?;
+        hook_time += hook_start.elapsed();
+
+        Ok(ExportReport {
+            output_path,
+            bytes_written: output.len(),
+            render_time,
+            post_process_time,
+            hooks_run,
+            hook_time,
+            assets,
+            redirect_stubs,
+        })
+    }
+
+    /// Render through the pipeline and apply the HTML post-processing stages
+    async fn export_html(
+        &self,
+        input: &Path,
+        options: &ExportOptions,
+    ) -> Result<(Vec<u8>, Duration, Duration, AssetReport)> {
+        let render_start = Instant::now();
+        let mut page = self.render_page(input, options).await?;
+        let render_time = render_start.elapsed();
+
+        let post_process_start = Instant::now();
+        let mut asset_report = AssetReport::default();
+        if let Some(config) = &options.assets {
+            let optimizer = AssetOptimizer::for_document(config, input);
+            // Self-contained pages inline the cached copies; otherwise they
+            // are copied next to the output
+            let (assets_dir, url_prefix) = if options.self_contained {
+                (
+                    optimizer.cache_dir().to_path_buf(),
+                    format!("{}/", assets::ASSET_CACHE_DIR),
+                )
+            } else {
+                let output = options.output_path(input);
+                let name = format!(
+                    "{}_assets",
+                    output.file_stem().unwrap_or_default().to_string_lossy()
+                );
+                (document_dir(&output).join(&name), format!("{}/", name))
+            };
+            let (optimized, report) = optimizer
+                .rewrite(&page, &document_dir(input), &assets_dir, &url_prefix)
+                .await?;
+            page = optimized;
+            asset_report = report;
+        }
+        if options.self_contained {
+            let inliner = SelfContainedInliner::new(document_dir(input))
+                .with_builtin_assets(self.builtin_assets.clone());
+            page = inliner.inline(&page)?;
+        }
+        let post_process_time = post_process_start.elapsed();
+
+        Ok((
+            page.into_bytes(),
+            render_time,
+            post_process_time,
+            asset_report,
+        ))
+    }
+
+    /// Parse the document, without its frontmatter, and package it as DOCX or
+    /// EPUB
+    async fn export_document(
+        &self,
+        input: &Path,
+        format: ExportFormat,
+    ) -> Result<(Vec<u8>, Duration, Duration)> {
+        let content = crate::crypto::read_document(input)?;
+
+        let render_start = Instant::now();
+        let (_, body) = crate::frontmatter::split(&content);
+        let tree = MarkdownParser::new().parse(body);
+        let title = crate::frontmatter::value(&content, "title")
+            .filter(|title| !title.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| document_title(&tree, input));
+        let render_time = render_start.elapsed();
+
+        let post_process_start = Instant::now();
+        let base_dir = document_dir(input);
+        let output = match format {
+            ExportFormat::Docx => docx::write_docx(&tree, &title, &base_dir)?,
+            ExportFormat::Epub => epub::write_epub(&tree, &title, &base_dir)?,
+            ExportFormat::Html | ExportFormat::Pandoc(_) => {
+                unreachable!("only DOCX and EPUB are written from the AST")
+            }
+        };
+        let post_process_time = post_process_start.elapsed();
+
+        Ok((output, render_time, post_process_time))
+    }
+}
+
+/// Hash content for change detection and cache keys
+fn content_hash(parts: &[&str]) -> String {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    parts.hash(&mut hasher);
+    format!("{:x}", hasher.finish())
+}
+
+/// Minimal page used when no page builder is configured
+fn default_page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n\
+         <title>{}</title>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        escape_html(title),
+        body
+    )
+}
+
+/// Convert the document with pandoc, which resolves its images itself
+async fn export_pandoc(input: &Path, output: &PandocOutput) -> Result<Vec<u8>> {
+    let pandoc = Pandoc::require(&format!("Exporting to {}", output.format))?;
+    let content = crate::crypto::read_document(input)?;
+    pandoc
+        .convert(
+            &content,
+            "markdown",
+            output.format,
+            &document_dir(input),
+            true,
+        )
+        .await
+}
+
+/// Directory used to resolve relative references of a document
+fn document_dir(input: &Path) -> PathBuf {
+    input
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf()
+}
+
+/// Title of a parsed document: its first level-one heading, else the file stem
+fn document_title(tree: &Tree, input: &Path) -> String {
+    tree.root
+        .children
+        .iter()
+        .find(|node| node.node_type == NodeType::Heading && node.level == Some(1))
+        .map(|heading| heading.text_content().trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| {
+            crate::crypto::plain_path(input)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Document".to_string())
+        })
+}
+
+/// Extract the text of the first `<h1>` in rendered HTML, without the
+/// section link controls the renderer adds to headings
+fn extract_title(html: &str) -> Option<String> {
+    let heading = Regex::new(r"(?s)<h1[^>]*>(.*?)</h1>").ok()?;
+    let controls = Regex::new(
+        r#"(?s)<a class="rune-anchor"[^>]*>.*?</a>|<button class="rune-copy-link"[^>]*>.*?</button>"#,
+    )
+    .ok()?;
+    let tags = Regex::new(r"<[^>]+>").ok()?;
+    let caps = heading.captures(html)?;
+    let content = controls.replace_all(&caps[1], "");
+    let title = tags.replace_all(&content, "").trim().to_string();
+    (!title.is_empty()).then_some(title)
+}
+
+/// Escape text for use in HTML
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Local image referenced by a document, loaded for embedding
+pub(crate) struct LocalImage {
+    pub mime_type: &'static str,
+    pub content: Vec<u8>,
+}
+
+impl LocalImage {
+    /// Load the image behind an `src` attribute, skipping remote and non-image references
+    pub(crate) fn load(src: &str, base_dir: &Path) -> Option<Self> {
+        if src.is_empty()
+            || src.starts_with("data:")
+            || src.contains("://")
+            || src.starts_with("//")
+        {
+            return None;
+        }
+
+        let src = src.split(['?', '#']).next().unwrap_or(src);
+        let path = base_dir.join(src.trim_start_matches('/'));
+        let mime_type = guess_mime_type(&path);
+        if !mime_type.starts_with("image/") {
+            return None;
+        }
+
+        match std::fs::read(&path) {
+            Ok(content) => Some(Self { mime_type, content }),
+            Err(e) => {
+                tracing::warn!("Could not embed image {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// File extension matching the image's MIME type
+    pub(crate) fn extension(&self) -> &'static str {
+        match self.mime_type {
+            "image/png" => "png",
+            "image/jpeg" => "jpeg",
+            "image/gif" => "gif",
+            "image/svg+xml" => "svg",
+            "image/webp" => "webp",
+            "image/bmp" => "bmp",
+            _ => "img",
+        }
+    }
+}
+
+/// Zip container shared by the DOCX and EPUB writers
+pub(crate) struct ZipPackage {
+    writer: zip::ZipWriter<Cursor<Vec<u8>>>,
+}
+
+impl ZipPackage {
+    pub(crate) fn new() -> Self {
+        Self {
+            writer: zip::ZipWriter::new(Cursor::new(Vec::new())),
+        }
+    }
+
+    /// Add an uncompressed entry
+    pub(crate) fn stored(&mut self, name: &str, content: &[u8]) -> Result<()> {
+        self.add(name, content, zip::CompressionMethod::Stored)
+    }
+
+    /// Add a deflate-compressed entry
+    pub(crate) fn deflated(&mut self, name: &str, content: &[u8]) -> Result<()> {
+        self.add(name, content, zip::CompressionMethod::Deflated)
+    }
+
+    fn add(&mut self, name: &str, content: &[u8], method: zip::CompressionMethod) -> Result<()> {
+        let options = zip::write::SimpleFileOptions::default().compression_method(method);
+        self.writer
+            .start_file(name, options)
+            .map_err(|e| RuneError::rendering(format!("Failed to add {}: {}", name, e)))?;
+        self.writer.write_all(content)?;
+        Ok(())
+    }
+
+    /// Finish the archive and return its bytes
+    pub(crate) fn finish(self) -> Result<Vec<u8>> {
+        self.writer
+            .finish()
+            .map(Cursor::into_inner)
+            .map_err(|e| RuneError::rendering(format!("Failed to finish package: {}", e)))
+    }
+}
+
+/// Post-processing stage that inlines local assets into an HTML page
+///
+/// Stylesheet links become `<style>` blocks, script sources become inline
+/// scripts, and images and fonts become `data:` URIs. Remote URLs are left
+/// untouched; missing files are left as-is and logged.
+pub struct SelfContainedInliner {
+    base_dir: PathBuf,
+    builtin_assets: HashMap<String, EmbeddedAsset>,
+}
+
+impl SelfContainedInliner {
+    /// Create an inliner resolving relative paths against `base_dir`
+    pub fn new(base_dir: PathBuf) -> Self {
+        Self {
+            base_dir,
+            builtin_assets: HashMap::new(),
+        }
+    }
+
+    /// Provide content for absolute URLs normally served by Rune
+    pub fn with_builtin_assets(mut self, assets: HashMap<String, EmbeddedAsset>) -> Self {
+        self.builtin_assets = assets;
+        self
+    }
+
+    /// Inline all local assets referenced by the page
+    pub fn inline(&self, html: &str) -> Result<String> {
+        let stylesheet = pattern(r#"<link\b[^>]*\brel=["']?stylesheet["']?[^>]*>"#)?;
+        let href = pattern(r#"\bhref=["']([^"']+)["']"#)?;
+        let script = pattern(r#"(?s)<script\b([^>]*?)\bsrc=["']([^"']+)["']([^>]*)>\s*</script>"#)?;
+        let img = pattern(r#"(<img\b[^>]*?\bsrc=["'])([^"']+)(["'])"#)?;
+        let style_block = pattern(r"(?s)(<style\b[^>]*>)(.*?)(</style>)")?;
+
+        let html = stylesheet.replace_all(html, |caps: &Captures| {
+            let tag = &caps[0];
+            let Some(url) = href.captures(tag).map(|c| c[1].to_string()) else {
+                return tag.to_string();
+            };
+            match self.resolve(&url, &self.base_dir) {
+                Some((path, asset)) => {
+                    let css = String::from_utf8_lossy(&asset.content);
+                    let css_dir = path
+                        .as_deref()
+                        .and_then(Path::parent)
+                        .map(Path::to_path_buf)
+                        .unwrap_or_else(|| self.base_dir.clone());
+                    format!(
+                        "<style>\n{}\n</style>",
+                        self.inline_css_urls(&css, &css_dir)
+                    )
+                }
+                None => tag.to_string(),
+            }
+        });
+
+        let html = img.replace_all(&html, |caps: &Captures| {
+            match self.resolve(&caps[2], &self.base_dir) {
+                Some((_, asset)) => format!("{}{}{}", &caps[1], data_uri(&asset), &caps[3]),
+                None => caps[0].to_string(),
+            }
+        });
+
+        let html = style_block.replace_all(&html, |caps: &Captures| {
+            format!(
+                "{}{}{}",
+                &caps[1],
+                self.inline_css_urls(&caps[2], &self.base_dir),
+                &caps[3]
+            )
+        });
+
+        // Scripts go last so their contents are not rewritten by the passes above
+        let html = script.replace_all(&html, |caps: &Captures| {
+            match self.resolve(&caps[2], &self.base_dir) {
+                Some((_, asset)) => {
+                    // A literal closing tag inside the script would end the element early
+                    let code =
+                        String::from_utf8_lossy(&asset.content).replace("</script", "<\\/script");
+                    format!(
+                        "<script{}{}>{}</script>",
+                        caps[1].trim_end(),
+                        &caps[3],
+                        code
+                    )
+                }
+                None => caps[0].to_string(),
+            }
+        });
+
+        Ok(html.into_owned())
+    }
+
+    /// Replace `url(...)` references in CSS (fonts, background images) with data URIs
+    fn inline_css_urls(&self, css: &str, css_dir: &Path) -> String {
+        let Ok(url) = pattern(r#"url\(\s*["']?([^"')]+?)["']?\s*\)"#) else {
+            return css.to_string();
+        };
+
+        url.replace_all(css, |caps: &Captures| {
+            match self.resolve(&caps[1], css_dir) {
+                Some((_, asset)) => format!("url(\"{}\")", data_uri(&asset)),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+    }
+
+    /// Resolve a URL to asset content, returning the file path for local files
+    fn resolve(&self, url: &str, relative_to: &Path) -> Option<(Option<PathBuf>, EmbeddedAsset)> {
+        if url.starts_with("data:")
+            || url.starts_with('#')
+            || url.contains("://")
+            || url.starts_with("//")
+        {
+            return None;
+        }
+
+        let url = url.split(['?', '#']).next().unwrap_or(url);
+        if let Some(asset) = self.builtin_assets.get(url) {
+            return Some((None, asset.clone()));
+        }
+
+        let path = match url.strip_prefix('/') {
+            Some(absolute) => self.base_dir.join(absolute),
+            None => relative_to.join(url),
+        };
+
+        match std::fs::read(&path) {
+            Ok(content) => Some((
+                Some(path.clone()),
+                EmbeddedAsset {
+                    mime_type: guess_mime_type(&path).to_string(),
+                    content,
+                },
+            )),
+            Err(e) => {
+                tracing::warn!("Could not inline asset {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+/// Compile a regex used by the inliner
+fn pattern(re: &str) -> Result<Regex> {
+    Regex::new(re).map_err(|e| RuneError::rendering(format!("Invalid export pattern: {}", e)))
+}
+
+/// Encode an asset as a `data:` URI
+fn data_uri(asset: &EmbeddedAsset) -> String {
+    format!(
+        "data:{};base64,{}",
+        asset.mime_type,
+        base64::engine::general_purpose::STANDARD.encode(&asset.content)
+    )
+}
+
+/// Guess the MIME type of an asset from its extension
+pub fn guess_mime_type(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .as_deref()
+    {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("bmp") => "image/bmp",
+        Some("ico") => "image/x-icon",
+        Some("css") => "text/css",
+        Some("js") => "application/javascript",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("eot") => "application/vnd.ms-fontobject",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_export_format_parsing() {
+        assert_eq!("html".parse::<ExportFormat>().unwrap(), ExportFormat::Html);
+        assert_eq!("HTML".parse::<ExportFormat>().unwrap(), ExportFormat::Html);
+        assert_eq!("epub".parse::<ExportFormat>().unwrap(), ExportFormat::Epub);
+        assert_eq!("Word".parse::<ExportFormat>().unwrap(), ExportFormat::Docx);
+        assert!("pdf".parse::<ExportFormat>().is_err());
+        assert_eq!(ExportFormat::Html.to_string(), "html");
+        assert_eq!(ExportFormat::Docx.extension(), "docx");
+    }
+
+    #[test]
+    fn test_output_path_defaults_to_input_with_extension() {
+        let options = ExportOptions::default();
+        assert_eq!(
+            options.output_path(Path::new("docs/guide.md")),
+            PathBuf::from("docs/guide.html")
+        );
+
+        let options = ExportOptions {
+            output: Some(PathBuf::from("out/index.html")),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.output_path(Path::new("docs/guide.md")),
+            PathBuf::from("out/index.html")
+        );
+    }
+
+    #[test]
+    fn test_inliner_embeds_local_assets() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("css/fonts")).unwrap();
+        std::fs::write(temp_dir.path().join("logo.png"), [0x89, b'P', b'N', b'G']).unwrap();
+        std::fs::write(temp_dir.path().join("css/fonts/body.woff2"), b"font").unwrap();
+        std::fs::write(
+            temp_dir.path().join("css/site.css"),
+            "@font-face { src: url('fonts/body.woff2'); }",
+        )
+        .unwrap();
+        std::fs::write(temp_dir.path().join("app.js"), "console.log('</script>');").unwrap();
+
+        let html = r#"<link rel="stylesheet" href="css/site.css"><script src="app.js"></script><img src="logo.png"><img src="https://example.com/x.png">"#;
+        let inlined = SelfContainedInliner::new(temp_dir.path().to_path_buf())
+            .inline(html)
+            .unwrap();
+
+        assert!(!inlined.contains("<link"));
+        assert!(inlined.contains("url(\"data:font/woff2;base64,"));
+        assert!(inlined.contains("<script>console.log('<\\/script>');</script>"));
+        assert!(inlined.contains("src=\"data:image/png;base64,iVBORw==\""));
+        assert!(inlined.contains("https://example.com/x.png"));
+    }
+
+    #[test]
+    fn test_inliner_uses_builtin_assets_and_keeps_missing_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut builtin = HashMap::new();
+        builtin.insert(
+            "/mermaid.min.js".to_string(),
+            EmbeddedAsset {
+                mime_type: "application/javascript".to_string(),
+                content: b"var mermaid = {};".to_vec(),
+            },
+        );
+
+        let html = r#"<script src="/mermaid.min.js"></script><img src="missing.png">"#;
+        let inlined = SelfContainedInliner::new(temp_dir.path().to_path_buf())
+            .with_builtin_assets(builtin)
+            .inline(html)
+            .unwrap();
+
+        assert!(inlined.contains("<script>var mermaid = {};</script>"));
+        assert!(inlined.contains(r#"<img src="missing.png">"#));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_export_runs_hooks_around_output() {
+        use crate::config::{ExportHook, ExportHookAction};
+
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("doc.md");
+        std::fs::write(&input, "# Doc\n").unwrap();
+        let command = |command: &str| ExportHook {
+            action: ExportHookAction::Command(command.to_string()),
+            allow_failure: false,
+        };
+
+        let exporter = Exporter::new(Arc::new(RendererRegistry::new()));
+        let mut options = ExportOptions {
+            format: ExportFormat::Epub,
+            ..Default::default()
+        };
+        options.hooks.post = vec![command("test -s \"$RUNE_EXPORT_OUTPUT\" && touch posted")];
+        let report = exporter.export(&input, &options).await.unwrap();
+        assert_eq!(report.hooks_run, 1);
+        assert!(temp_dir.path().join("posted").exists());
+
+        // A failing pre-export hook aborts before anything is written
+        std::fs::remove_file(&report.output_path).unwrap();
+        options.hooks.pre = vec![command("exit 1")];
+        assert!(exporter.export(&input, &options).await.is_err());
+        assert!(!report.output_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_documents_leave_out_frontmatter() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("doc.md");
+        std::fs::write(
+            &input,
+            "---\ntitle: \"Field Notes\"\ntags: [a]\n---\n# One\n\ntext\n\n# Two\n",
+        )
+        .unwrap();
+        let exporter = Exporter::new(Arc::new(RendererRegistry::new()));
+        let read = |format: ExportFormat, name: &str| {
+            let bytes = std::fs::read(input.with_extension(format.extension())).unwrap();
+            let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+            let mut content = String::new();
+            std::io::Read::read_to_string(&mut archive.by_name(name).unwrap(), &mut content)
+                .unwrap();
+            content
+        };
+
+        for format in [ExportFormat::Docx, ExportFormat::Epub] {
+            let options = ExportOptions {
+                format,
+                ..Default::default()
+            };
+            exporter.export(&input, &options).await.unwrap();
+        }
+
+        let document = read(ExportFormat::Docx, "word/document.xml");
+        assert!(!document.contains("title:"));
+        assert!(!document.contains("w:val=\"Heading2\""));
+        assert!(document.contains(">One</w:t>"));
+        let properties = read(ExportFormat::Docx, "docProps/core.xml");
+        assert!(properties.contains("<dc:title>Field Notes</dc:title>"));
+
+        let opf = read(ExportFormat::Epub, "OEBPS/content.opf");
+        assert!(opf.contains("<dc:title>Field Notes</dc:title>"));
+        assert!(opf.contains("<itemref idref=\"chapter-2\"/>"));
+        assert!(!opf.contains("chapter-3"));
+        let first = read(ExportFormat::Epub, "OEBPS/chapter-1.xhtml");
+        assert!(first.contains("<h1>One</h1>"));
+        assert!(!first.contains("title:"));
+    }
+
+    #[test]
+    fn test_extract_title() {
+        assert_eq!(
+            extract_title("<h1 id=\"a\">Hello <em>World</em></h1>"),
+            Some("Hello World".to_string())
+        );
+        assert_eq!(
+            extract_title(
+                "<h1 id=\"hello\">Hello<a class=\"rune-anchor\" href=\"#hello\" aria-label=\"Link to this section\">#</a>\
+                 <button class=\"rune-copy-link\" type=\"button\" data-anchor=\"hello\" aria-label=\"Copy link to this section\">⧉</button></h1>"
+            ),
+            Some("Hello".to_string())
+        );
+        assert_eq!(extract_title("<p>none</p>"), None);
+    }
+}
@@ -0,0 +1,790 @@
+//! Image optimization stage of exports
+//!
+//! Local images referenced by an exported page are replaced with optimized
+//! copies: EXIF, XMP and text metadata are stripped from JPEG and PNG files,
+//! images wider than the configured maximum are scaled down, and WebP and
+//! AVIF versions are tried, keeping whichever candidate is smallest. Scaling
+//! and conversion run `cwebp` and ImageMagick directly, without a shell;
+//! commands configured in
+//! [`ExportAssetConfig`](crate::config::ExportAssetConfig) replace them and
+//! run through the platform shell. A step whose encoder is missing, fails or
+//! runs longer than [`ENCODER_TIMEOUT`] is skipped. Results are cached in
+//! `.rune/cache/assets/` next to the document, keyed by the hash of the source
+//! and the settings.
+
+use regex::Captures;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use super::{document_dir, guess_mime_type, pattern};
+use crate::config::ExportAssetConfig;
+use crate::error::Result;
+use crate::history::content_hash;
+use crate::process::{self, CheckedCommand, CommandRunner, ProcessError};
+
+/// Images wider than this are scaled down unless configured otherwise
+pub const DEFAULT_MAX_WIDTH: u32 = 1600;
+/// Encoder quality unless configured otherwise
+pub const DEFAULT_QUALITY: u8 = 80;
+/// Formats tried unless configured otherwise
+pub const DEFAULT_FORMATS: &[&str] = &["webp", "avif"];
+/// Cache directory, relative to the document's directory
+pub const ASSET_CACHE_DIR: &str = ".rune/cache/assets";
+
+/// Longest an encoder may take for one image
+pub const ENCODER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Default encoders, as a program and its arguments; `{input}`, `{output}`,
+/// `{quality}` and `{width}` stand for the values of the image being encoded
+const DEFAULT_ENCODERS: &[(&str, &[&str])] = &[
+    (
+        "webp",
+        &[
+            "cwebp",
+            "-quiet",
+            "-metadata",
+            "none",
+            "-q",
+            "{quality}",
+            "-resize",
+            "{width}",
+            "0",
+            "{input}",
+            "-o",
+            "{output}",
+        ],
+    ),
+    (
+        "avif",
+        &[
+            "magick",
+            "{input}",
+            "-strip",
+            "-resize",
+            "{width}x>",
+            "-quality",
+            "{quality}",
+            "{output}",
+        ],
+    ),
+    (
+        "resize",
+        &[
+            "magick",
+            "{input}",
+            "-strip",
+            "-resize",
+            "{width}x>",
+            "-quality",
+            "{quality}",
+            "{output}",
+        ],
+    ),
+];
+
+/// How the image for one target is encoded
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Encoder {
+    /// Built-in program and arguments, run without a shell
+    Program(&'static [&'static str]),
+    /// Configured command, run through the platform shell; it reads
+    /// `RUNE_ASSET_INPUT`, `RUNE_ASSET_OUTPUT`, `RUNE_ASSET_QUALITY` and
+    /// `RUNE_ASSET_WIDTH`
+    Shell(String),
+}
+
+impl Encoder {
+    /// Arguments of a built-in program with the placeholders filled in
+    fn args(
+        template: &[&str],
+        input: &Path,
+        output: &Path,
+        quality: u8,
+        width: u32,
+    ) -> Vec<OsString> {
+        template
+            .iter()
+            .map(|arg| match *arg {
+                "{input}" => input.as_os_str().to_owned(),
+                "{output}" => output.as_os_str().to_owned(),
+                arg => arg
+                    .replace("{quality}", &quality.to_string())
+                    .replace("{width}", &width.to_string())
+                    .into(),
+            })
+            .collect()
+    }
+
+    fn command(
+        &self,
+        runner: &CommandRunner,
+        input: &Path,
+        output: &Path,
+        quality: u8,
+        width: u32,
+    ) -> std::result::Result<CheckedCommand, ProcessError> {
+        let mut process = match self {
+            Self::Program(template) => {
+                let mut process = runner.command(template[0])?;
+                process.args(Self::args(&template[1..], input, output, quality, width));
+                process
+            }
+            Self::Shell(command) => runner.shell(command)?,
+        };
+        process
+            .env("RUNE_ASSET_INPUT", input)
+            .env("RUNE_ASSET_OUTPUT", output)
+            .env("RUNE_ASSET_QUALITY", quality.to_string())
+            .env("RUNE_ASSET_WIDTH", width.to_string());
+        Ok(process)
+    }
+}
+
+impl fmt::Display for Encoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Program(template) => write!(f, "{}", template.join(" ")),
+            Self::Shell(command) => write!(f, "{}", command),
+        }
+    }
+}
+
+/// Optimized copy of an image
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimizedImage {
+    pub path: PathBuf,
+    pub original_bytes: u64,
+    pub bytes: u64,
+}
+
+/// What the optimization stage did to an exported page
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AssetReport {
+    /// Images whose references were rewritten to optimized copies
+    pub optimized: usize,
+    pub bytes_saved: u64,
+}
+
+/// Optimizes the images referenced by exported pages
+pub struct AssetOptimizer {
+    max_width: u32,
+    quality: u8,
+    formats: Vec<String>,
+    strip_metadata: bool,
+    encoders: HashMap<String, Encoder>,
+    cache_dir: PathBuf,
+    /// Encoders that already failed, warned about once
+    failed_encoders: Mutex<HashSet<String>>,
+}
+
+impl AssetOptimizer {
+    /// Create an optimizer caching its results in `cache_dir`
+    pub fn new(config: &ExportAssetConfig, cache_dir: PathBuf) -> Self {
+        let mut encoders: HashMap<String, Encoder> = DEFAULT_ENCODERS
+            .iter()
+            .map(|(target, template)| (target.to_string(), Encoder::Program(template)))
+            .collect();
+        encoders.extend(
+            config
+                .encoders
+                .iter()
+                .map(|(target, command)| (target.clone(), Encoder::Shell(command.clone()))),
+        );
+
+        Self {
+            max_width: config.max_width.unwrap_or(DEFAULT_MAX_WIDTH),
+            quality: config.quality.unwrap_or(DEFAULT_QUALITY),
+            formats: config.formats.clone().unwrap_or_else(|| {
+                DEFAULT_FORMATS
+                    .iter()
+                    .map(|format| format.to_string())
+                    .collect()
+            }),
+            strip_metadata: config.strip_metadata.unwrap_or(true),
+            encoders,
+            cache_dir,
+            failed_encoders: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Create the optimizer used for a markdown document
+    pub fn for_document(config: &ExportAssetConfig, input: &Path) -> Self {
+        Self::new(config, document_dir(input).join(ASSET_CACHE_DIR))
+    }
+
+    /// Get the directory optimized copies are cached in
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    /// Optimize a JPEG, PNG or WebP image; other files give `None`
+    pub async fn optimize(&self, source: &Path) -> Result<Option<OptimizedImage>> {
+        let extension = match guess_mime_type(source) {
+            "image/jpeg" => "jpg",
+            "image/png" => "png",
+            "image/webp" => "webp",
+            _ => return Ok(None),
+        };
+        let original = tokio::fs::read(source).await?;
+        let original_bytes = original.len() as u64;
+        if exif_orientation(&original).is_some_and(|orientation| orientation != 1) {
+            // Stripping or re-encoding would lose the rotation browsers apply
+            debug!("Leaving rotated image {} as it is", source.display());
+            return Ok(None);
+        }
+
+        let key = content_hash(format!(
+            "{}\n{}",
+            content_hash(&original),
+            self.fingerprint()
+        ));
+        for cached_extension in ["webp", "avif", "jpg", "png"] {
+            let path = self.cache_dir.join(format!("{}.{}", key, cached_extension));
+            if let Ok(metadata) = tokio::fs::metadata(&path).await {
+                return Ok(Some(OptimizedImage {
+                    path,
+                    original_bytes,
+                    bytes: metadata.len(),
+                }));
+            }
+        }
+
+        let work_dir = self.cache_dir.join(format!("{}.work", key));
+        tokio::fs::create_dir_all(&work_dir).await?;
+        let result = self
+            .encode_candidates(&original, extension, &work_dir)
+            .await;
+        let (best, best_extension) = match result {
+            Ok(best) => best,
+            Err(e) => {
+                let _ = tokio::fs::remove_dir_all(&work_dir).await;
+                return Err(e);
+            }
+        };
+
+        let path = self.cache_dir.join(format!("{}.{}", key, best_extension));
+        let partial = work_dir.join("best");
+        tokio::fs::write(&partial, &best).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        let _ = tokio::fs::remove_dir_all(&work_dir).await;
+
+        debug!(
+            "Optimized {} from {} to {} bytes",
+            source.display(),
+            original_bytes,
+            best.len()
+        );
+        Ok(Some(OptimizedImage {
+            path,
+            original_bytes,
+            bytes: best.len() as u64,
+        }))
+    }
+
+    /// Smallest acceptable encoding of an image and its extension
+    async fn encode_candidates(
+        &self,
+        original: &[u8],
+        extension: &str,
+        work_dir: &Path,
+    ) -> Result<(Vec<u8>, String)> {
+        let mut best = if self.strip_metadata {
+            strip_metadata(original)
+        } else {
+            original.to_vec()
+        };
+        let mut best_extension = extension.to_string();
+
+        let Some((width, _)) = image_dimensions(original) else {
+            return Ok((best, best_extension));
+        };
+        let target_width = width.min(self.max_width);
+        let input = work_dir.join(format!("source.{}", extension));
+        tokio::fs::write(&input, &best).await?;
+
+        if width > self.max_width {
+            let output = work_dir.join(format!("resized.{}", extension));
+            match self.encode("resize", &input, &output, target_width).await {
+                Some(resized) => best = resized,
+                None => warn!("Could not scale down an image wider than {}px", width),
+            }
+        }
+
+        for format in &self.formats {
+            let output = work_dir.join(format!("converted.{}", format));
+            if let Some(converted) = self.encode(format, &input, &output, target_width).await {
+                if converted.len() < best.len() {
+                    best = converted;
+                    best_extension = format.clone();
+                }
+            }
+        }
+
+        Ok((best, best_extension))
+    }
+
+    /// Run the encoder for `target`, returning its output if it succeeded
+    async fn encode(
+        &self,
+        target: &str,
+        input: &Path,
+        output: &Path,
+        width: u32,
+    ) -> Option<Vec<u8>> {
+        let encoder = self.encoders.get(target)?;
+        let runner = process::runner()
+            .as_ref()
+            .clone()
+            .with_timeout(ENCODER_TIMEOUT);
+        let status = match encoder.command(&runner, input, output, self.quality, width) {
+            Ok(process) => runner.output(process, None).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(ProcessError::TimedOut { timeout, .. }) = &status {
+            warn!(
+                "Image encoder for {} took longer than {:?}: {}",
+                target, timeout, encoder
+            );
+            return None;
+        }
+
+        let encoded = if status.is_ok() {
+            tokio::fs::read(output)
+                .await
+                .ok()
+                .filter(|bytes| !bytes.is_empty())
+        } else {
+            None
+        };
+        if encoded.is_none() {
+            let mut failed = self
+                .failed_encoders
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            if failed.insert(target.to_string()) {
+                warn!(
+                    "Image encoder for {} is unavailable or failed: {}",
+                    target, encoder
+                );
+            }
+        }
+        encoded
+    }
+
+    /// Settings that change the optimized output, part of the cache key
+    fn fingerprint(&self) -> String {
+        let mut encoders: Vec<_> = self.encoders.iter().collect();
+        encoders.sort();
+        format!(
+            "{} {} {:?} {} {:?}",
+            self.max_width, self.quality, self.formats, self.strip_metadata, encoders
+        )
+    }
+
+    /// Point the page's local images at optimized copies placed in
+    /// `assets_dir`, referenced as `url_prefix` followed by the file name
+    pub async fn rewrite(
+        &self,
+        html: &str,
+        base_dir: &Path,
+        assets_dir: &Path,
+        url_prefix: &str,
+    ) -> Result<(String, AssetReport)> {
+        let img = pattern(r#"(<img\b[^>]*?\bsrc=["'])([^"']+)(["'])"#)?;
+        let mut report = AssetReport::default();
+        let mut replacements = HashMap::new();
+
+        for caps in img.captures_iter(html) {
+            let src = &caps[2];
+            if replacements.contains_key(src)
+                || src.starts_with("data:")
+                || src.contains("://")
+                || src.starts_with("//")
+            {
+                continue;
+            }
+            let file = src.split(['?', '#']).next().unwrap_or(src);
+            let path = base_dir.join(file.trim_start_matches('/'));
+            if !path.is_file() {
+                continue;
+            }
+
+            let optimized = match self.optimize(&path).await {
+                Ok(Some(optimized)) if optimized.bytes < optimized.original_bytes => optimized,
+                Ok(_) => continue,
+                Err(e) => {
+                    warn!("Could not optimize {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let Some(name) = optimized.path.file_name() else {
+                continue;
+            };
+            if assets_dir != self.cache_dir {
+                tokio::fs::create_dir_all(assets_dir).await?;
+                tokio::fs::copy(&optimized.path, assets_dir.join(name)).await?;
+            }
+
+            report.optimized += 1;
+            report.bytes_saved += optimized.original_bytes - optimized.bytes;
+            replacements.insert(
+                src.to_string(),
+                format!("{}{}", url_prefix, name.to_string_lossy()),
+            );
+        }
+
+        let html = img.replace_all(html, |caps: &Captures| match replacements.get(&caps[2]) {
+            Some(src) => format!("{}{}{}", &caps[1], src, &caps[3]),
+            None => caps[0].to_string(),
+        });
+        Ok((html.into_owned(), report))
+    }
+}
+
+/// Width and height of a PNG, JPEG, GIF or WebP image, read from its header
+pub fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let u16_be = |at: usize| Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+    let u16_le = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+    let u24_le = |at: usize| {
+        let b = bytes.get(at..at + 3)?;
+        Some(u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16)
+    };
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") && bytes.get(12..16) == Some(b"IHDR") {
+        let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+        let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+        return Some((width, height));
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return Some((u32::from(u16_le(6)?), u32::from(u16_le(8)?)));
+    }
+    if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        return match bytes.get(12..16)? {
+            b"VP8 " => Some((
+                u32::from(u16_le(26)? & 0x3fff),
+                u32::from(u16_le(28)? & 0x3fff),
+            )),
+            b"VP8L" => {
+                let b = bytes.get(21..25)?;
+                let bits = u32::from_le_bytes(b.try_into().ok()?);
+                Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+            }
+            b"VP8X" => Some((u24_le(24)? + 1, u24_le(27)? + 1)),
+            _ => None,
+        };
+    }
+    if bytes.starts_with(&[0xff, 0xd8]) {
+        for (marker, start, _) in jpeg_segments(bytes) {
+            // Start-of-frame markers, except DHT, JPG and DAC which share the range
+            if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+                let height = u32::from(u16_be(start + 5)?);
+                let width = u32::from(u16_be(start + 7)?);
+                return Some((width, height));
+            }
+        }
+    }
+    None
+}
+
+/// Marker, start and end of each JPEG segment before the image data
+fn jpeg_segments(bytes: &[u8]) -> Vec<(u8, usize, usize)> {
+    let mut segments = Vec::new();
+    let mut at = 2;
+    while at + 4 <= bytes.len() && bytes[at] == 0xff {
+        let marker = bytes[at + 1];
+        if marker == 0xda {
+            break;
+        }
+        let length = usize::from(u16::from_be_bytes([bytes[at + 2], bytes[at + 3]]));
+        let end = at + 2 + length;
+        if length < 2 || end > bytes.len() {
+            break;
+        }
+        segments.push((marker, at, end));
+        at = end;
+    }
+    segments
+}
+
+/// Copy of a JPEG or PNG image without EXIF, XMP, comments and text chunks;
+/// other or malformed files are returned unchanged
+pub fn strip_metadata(bytes: &[u8]) -> Vec<u8> {
+    if bytes.starts_with(&[0xff, 0xd8]) {
+        let segments = jpeg_segments(bytes);
+        let Some(&(_, _, data_start)) = segments.last() else {
+            return bytes.to_vec();
+        };
+        let mut stripped = bytes[..2].to_vec();
+        for (marker, start, end) in segments {
+            // APP1 holds EXIF and XMP, APP3-APP13 and APP15 other application
+            // data, COM comments; JFIF, ICC profiles and Adobe color info stay
+            let metadata = marker == 0xe1
+                || (0xe3..=0xed).contains(&marker)
+                || marker == 0xef
+                || marker == 0xfe;
+            if !metadata {
+                stripped.extend_from_slice(&bytes[start..end]);
+            }
+        }
+        stripped.extend_from_slice(&bytes[data_start..]);
+        return stripped;
+    }
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let mut stripped = bytes[..8].to_vec();
+        let mut at = 8;
+        while at + 12 <= bytes.len() {
+            let length =
+                u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+            let end = at + 12 + length as usize;
+            if end > bytes.len() {
+                return bytes.to_vec();
+            }
+            let kind = &bytes[at + 4..at + 8];
+            if !matches!(kind, b"tEXt" | b"zTXt" | b"iTXt" | b"eXIf" | b"tIME") {
+                stripped.extend_from_slice(&bytes[at..end]);
+            }
+            at = end;
+        }
+        return stripped;
+    }
+
+    bytes.to_vec()
+}
+
+/// EXIF orientation of a JPEG image, if it has one
+fn exif_orientation(bytes: &[u8]) -> Option<u16> {
+    if !bytes.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+    let (_, start, end) = jpeg_segments(bytes)
+        .into_iter()
+        .find(|&(marker, start, _)| {
+            marker == 0xe1 && bytes[start + 4..].starts_with(b"Exif\0\0")
+        })?;
+    let tiff = &bytes[start + 10..end];
+    let little_endian = tiff.starts_with(b"II");
+    let read_u16 = |at: usize| {
+        let b: [u8; 2] = tiff.get(at..at + 2)?.try_into().ok()?;
+        Some(if little_endian {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    };
+    let read_u32 = |at: usize| {
+        let b: [u8; 4] = tiff.get(at..at + 4)?.try_into().ok()?;
+        Some(if little_endian {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    };
+
+    let ifd = read_u32(4)? as usize;
+    let entries = read_u16(ifd)? as usize;
+    (0..entries)
+        .map(|index| ifd + 2 + index * 12)
+        .find(|&entry| read_u16(entry) == Some(0x0112))
+        .and_then(|entry| read_u16(entry + 8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut chunk = |kind: &[u8], data: &[u8]| {
+            bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(kind);
+            bytes.extend_from_slice(data);
+            bytes.extend_from_slice(&[0; 4]);
+        };
+        let mut header = width.to_be_bytes().to_vec();
+        header.extend_from_slice(&height.to_be_bytes());
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+        chunk(b"IHDR", &header);
+        chunk(b"tEXt", b"Author\0Someone");
+        chunk(b"IDAT", &[0; 64]);
+        chunk(b"IEND", &[]);
+        bytes
+    }
+
+    fn jpeg(orientation: u16) -> Vec<u8> {
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01".to_vec();
+        exif.extend_from_slice(&[0x01, 0x12, 0, 3, 0, 0, 0, 1]);
+        exif.extend_from_slice(&orientation.to_be_bytes());
+        exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+
+        let mut bytes = vec![0xff, 0xd8];
+        let mut segment = |marker: u8, data: &[u8]| {
+            bytes.extend_from_slice(&[0xff, marker]);
+            bytes.extend_from_slice(&((data.len() + 2) as u16).to_be_bytes());
+            bytes.extend_from_slice(data);
+        };
+        segment(0xe0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        segment(0xe1, &exif);
+        segment(0xfe, b"a comment");
+        segment(0xc0, &[8, 0x01, 0x2c, 0x02, 0x58, 3, 1, 0x22, 0]);
+        segment(0xda, &[1, 1, 0, 0, 0x3f, 0]);
+        bytes.extend_from_slice(&[0x12, 0x34, 0xff, 0xd9]);
+        bytes
+    }
+
+    #[test]
+    fn test_image_dimensions_from_headers() {
+        assert_eq!(image_dimensions(&png(2400, 1200)), Some((2400, 1200)));
+        assert_eq!(image_dimensions(&jpeg(1)), Some((600, 300)));
+        assert_eq!(
+            image_dimensions(b"GIF89a\x40\x01\xf0\x00rest"),
+            Some((320, 240))
+        );
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0\0\0\0\0".to_vec();
+        webp.extend_from_slice(&[0x7f, 0x02, 0x00, 0xdf, 0x01, 0x00]);
+        assert_eq!(image_dimensions(&webp), Some((640, 480)));
+
+        assert_eq!(image_dimensions(b"<svg/>"), None);
+    }
+
+    #[test]
+    fn test_strip_metadata_keeps_image_data() {
+        let stripped = strip_metadata(&png(10, 10));
+        assert!(!stripped.windows(4).any(|w| w == b"tEXt"));
+        assert_eq!(stripped.len(), png(10, 10).len() - 26);
+        assert_eq!(image_dimensions(&stripped), Some((10, 10)));
+
+        let original = jpeg(1);
+        let stripped = strip_metadata(&original);
+        assert!(!stripped.windows(4).any(|w| w == b"Exif"));
+        assert!(!stripped.windows(9).any(|w| w == b"a comment"));
+        assert!(stripped.windows(4).any(|w| w == b"JFIF"));
+        assert!(stripped.ends_with(&[0x12, 0x34, 0xff, 0xd9]));
+        assert_eq!(image_dimensions(&stripped), Some((600, 300)));
+
+        assert_eq!(exif_orientation(&jpeg(6)), Some(6));
+        assert_eq!(exif_orientation(&stripped), None);
+        assert_eq!(strip_metadata(b"plain"), b"plain".to_vec());
+    }
+
+    #[test]
+    fn test_default_encoders_take_paths_as_single_arguments() {
+        // Paths are passed as they are, without a shell to quote them for
+        let input = Path::new(r"C:\Users\Ana\My Pictures\a&b.png");
+        let output = Path::new(r"C:\Users\Ana\.rune\asset-cache\x.work\converted.avif");
+        let avif = DEFAULT_ENCODERS
+            .iter()
+            .find(|(target, _)| *target == "avif")
+            .unwrap()
+            .1;
+        let args = Encoder::args(&avif[1..], input, output, 75, 1200);
+        let expected: Vec<OsString> = [
+            input.as_os_str(),
+            "-strip".as_ref(),
+            "-resize".as_ref(),
+            "1200x>".as_ref(),
+            "-quality".as_ref(),
+            "75".as_ref(),
+            output.as_os_str(),
+        ]
+        .iter()
+        .map(|arg| arg.to_os_string())
+        .collect();
+        assert_eq!(args, expected);
+        assert_eq!(avif[0], "magick");
+        assert!(DEFAULT_ENCODERS
+            .iter()
+            .all(|(_, template)| !template.iter().any(|arg| arg.contains('$'))));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_optimizer_picks_smallest_candidate_and_caches_it() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        std::fs::write(base.join("wide.png"), png(2400, 1200)).unwrap();
+        std::fs::write(base.join("small.png"), png(100, 100)).unwrap();
+        std::fs::write(base.join("rotated.jpg"), jpeg(6)).unwrap();
+
+        // Fake encoders: WebP output is tiny, AVIF output is not smaller
+        let mut config = ExportAssetConfig {
+            max_width: Some(1000),
+            ..Default::default()
+        };
+        config.encoders.insert(
+            "resize".to_string(),
+            r#"echo "$RUNE_ASSET_WIDTH" > "$RUNE_ASSET_OUTPUT""#.to_string(),
+        );
+        config.encoders.insert(
+            "webp".to_string(),
+            r#"head -c 8 "$RUNE_ASSET_INPUT" > "$RUNE_ASSET_OUTPUT""#.to_string(),
+        );
+        config.encoders.insert(
+            "avif".to_string(),
+            r#"cp "$RUNE_ASSET_INPUT" "$RUNE_ASSET_OUTPUT""#.to_string(),
+        );
+        let input = base.join("doc.md");
+        let optimizer = AssetOptimizer::for_document(&config, &input);
+
+        let wide = optimizer
+            .optimize(&base.join("wide.png"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(wide.path.extension().unwrap(), "png");
+        assert_eq!(std::fs::read_to_string(&wide.path).unwrap(), "1000\n");
+        assert!(wide.path.starts_with(base.join(ASSET_CACHE_DIR)));
+
+        let small = optimizer
+            .optimize(&base.join("small.png"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(small.path.extension().unwrap(), "webp");
+        assert_eq!(small.bytes, 8);
+        assert!(optimizer
+            .optimize(&base.join("rotated.jpg"))
+            .await
+            .unwrap()
+            .is_none());
+
+        // Cached results are reused, and work directories are cleaned up
+        let again = optimizer
+            .optimize(&base.join("small.png"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(again, small);
+        assert_eq!(std::fs::read_dir(optimizer.cache_dir()).unwrap().count(), 2);
+        let other = AssetOptimizer::for_document(
+            &ExportAssetConfig {
+                quality: Some(50),
+                ..config.clone()
+            },
+            &input,
+        );
+        assert_ne!(other.fingerprint(), optimizer.fingerprint());
+
+        let html = r#"<p><img src="small.png" alt="a"> <img src="missing.png"> <img src="https://example.com/x.png"> <img src='small.png'></p>"#;
+        let out_dir = base.join("out/doc_assets");
+        let (rewritten, report) = optimizer
+            .rewrite(html, base, &out_dir, "doc_assets/")
+            .await
+            .unwrap();
+        let name = small.path.file_name().unwrap().to_string_lossy();
+        assert!(rewritten.contains(&format!(r#"<img src="doc_assets/{}" alt="a">"#, name)));
+        assert!(rewritten.contains(&format!("<img src='doc_assets/{}'>", name)));
+        assert!(rewritten.contains(r#"<img src="missing.png">"#));
+        assert!(rewritten.contains("https://example.com/x.png"));
+        assert_eq!(report.optimized, 1);
+        assert_eq!(report.bytes_saved, small.original_bytes - 8);
+        assert!(out_dir.join(name.as_ref()).is_file());
+    }
+}
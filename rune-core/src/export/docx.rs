@@ -0,0 +1,790 @@
+//! DOCX export
+//!
+//! Writes a WordprocessingML package straight from the markdown AST. Headings
+//! use Word's built-in `Heading1`..`Heading6` styles so the navigation pane and
+//! generated tables of contents work, lists get real numbering definitions and
+//! local images are embedded as inline drawings.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::{escape_html as escape_xml, LocalImage, ZipPackage};
+use crate::ast::{Node, NodeType, Tree};
+use crate::error::Result;
+
+/// EMUs per pixel at 96 DPI
+const EMU_PER_PIXEL: u64 = 9525;
+
+/// Width of the text column (6.5in on a letter page with 1in margins)
+const MAX_IMAGE_WIDTH_EMU: u64 = 5_943_600;
+
+/// Numbering definition shared by all bullet lists
+const BULLET_NUM_ID: usize = 1;
+
+const WORD_NAMESPACES: &str = concat!(
+    r#"xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" "#,
+    r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" "#,
+    r#"xmlns:wp="http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing" "#,
+    r#"xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" "#,
+    r#"xmlns:pic="http://schemas.openxmlformats.org/drawingml/2006/picture""#
+);
+
+const RELATIONSHIP_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+/// Write a parsed markdown document as a DOCX package
+pub fn write_docx(tree: &Tree, title: &str, base_dir: &Path) -> Result<Vec<u8>> {
+    let mut writer = DocxWriter::new(base_dir);
+    writer.block(&tree.root, None);
+
+    let mut package = ZipPackage::new();
+    package.deflated("[Content_Types].xml", writer.content_types().as_bytes())?;
+    package.deflated("_rels/.rels", ROOT_RELATIONSHIPS.as_bytes())?;
+    package.deflated("docProps/core.xml", core_properties(title).as_bytes())?;
+    package.deflated("word/document.xml", writer.document().as_bytes())?;
+    package.deflated("word/styles.xml", STYLES.as_bytes())?;
+    package.deflated("word/numbering.xml", writer.numbering().as_bytes())?;
+    package.deflated(
+        "word/_rels/document.xml.rels",
+        writer.relationships().as_bytes(),
+    )?;
+    for (name, content) in &writer.media {
+        // Images are already compressed
+        package.stored(&format!("word/media/{}", name), content)?;
+    }
+    package.finish()
+}
+
+/// Relationship from the main document part to another part or URL
+struct Relationship {
+    id: String,
+    kind: &'static str,
+    target: String,
+    external: bool,
+}
+
+/// Embedded image and its displayed size
+#[derive(Clone)]
+struct EmbeddedImage {
+    relationship_id: String,
+    width: u64,
+    height: u64,
+}
+
+/// Character formatting of a text run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct RunFormat {
+    bold: bool,
+    italic: bool,
+    strike: bool,
+    code: bool,
+    link: bool,
+}
+
+/// Converts AST nodes to WordprocessingML and collects the parts they reference
+struct DocxWriter<'a> {
+    base_dir: &'a Path,
+    body: String,
+    relationships: Vec<Relationship>,
+    media: Vec<(String, Vec<u8>)>,
+    images: HashMap<String, Option<EmbeddedImage>>,
+    /// Number of drawings placed, used for their unique ids
+    drawings: usize,
+    /// Number of ordered lists; each one restarts its numbering
+    ordered_lists: usize,
+    /// Pending run text and its formatting, merged until the formatting changes
+    pending: String,
+    pending_format: RunFormat,
+}
+
+impl<'a> DocxWriter<'a> {
+    fn new(base_dir: &'a Path) -> Self {
+        Self {
+            base_dir,
+            body: String::new(),
+            relationships: vec![
+                Relationship {
+                    id: "rId1".to_string(),
+                    kind: "styles",
+                    target: "styles.xml".to_string(),
+                    external: false,
+                },
+                Relationship {
+                    id: "rId2".to_string(),
+                    kind: "numbering",
+                    target: "numbering.xml".to_string(),
+                    external: false,
+                },
+            ],
+            media: Vec::new(),
+            images: HashMap::new(),
+            drawings: 0,
+            ordered_lists: 0,
+            pending: String::new(),
+            pending_format: RunFormat::default(),
+        }
+    }
+
+    /// Write a block node; `style` overrides the paragraph style (e.g. inside quotes)
+    fn block(&mut self, node: &Node, style: Option<&str>) {
+        match node.node_type {
+            NodeType::Document => {
+                for child in &node.children {
+                    self.block(child, style);
+                }
+            }
+            NodeType::Heading => {
+                let level = node.level.unwrap_or(1).clamp(1, 6);
+                self.paragraph(&format!("<w:pStyle w:val=\"Heading{}\"/>", level), node);
+            }
+            NodeType::Paragraph => {
+                let properties = style
+                    .map(|style| format!("<w:pStyle w:val=\"{}\"/>", style))
+                    .unwrap_or_default();
+                self.paragraph(&properties, node);
+            }
+            NodeType::CodeBlock | NodeType::MathBlock | NodeType::HTMLBlock => {
+                self.body
+                    .push_str("<w:p><w:pPr><w:pStyle w:val=\"SourceCode\"/></w:pPr>");
+                for (index, line) in node.text_content().lines().enumerate() {
+                    if index > 0 {
+                        self.body.push_str("<w:r><w:br/></w:r>");
+                    }
+                    self.body.push_str(&text_run(line, RunFormat::default()));
+                }
+                self.body.push_str("</w:p>");
+            }
+            NodeType::Blockquote => {
+                for child in &node.children {
+                    self.block(child, Some("Quote"));
+                }
+            }
+            NodeType::List => {
+                let num_id = if node.get_attribute("type").map(String::as_str) == Some("ordered") {
+                    self.ordered_lists += 1;
+                    BULLET_NUM_ID + self.ordered_lists
+                } else {
+                    BULLET_NUM_ID
+                };
+                for item in &node.children {
+                    self.list_item(item, num_id);
+                }
+            }
+            NodeType::Table => self.table(node),
+            NodeType::ThematicBreak => {
+                self.body.push_str(
+                    "<w:p><w:pPr><w:pBdr><w:bottom w:val=\"single\" w:sz=\"6\" \
+                     w:space=\"1\" w:color=\"auto\"/></w:pBdr></w:pPr></w:p>",
+                );
+            }
+            _ if node.children.iter().any(Node::is_block) => {
+                for child in &node.children {
+                    self.block(child, style);
+                }
+            }
+            _ => self.paragraph("", node),
+        }
+    }
+
+    /// Write a list item as a numbered paragraph
+    fn list_item(&mut self, item: &Node, num_id: usize) {
+        self.body.push_str(&format!(
+            "<w:p><w:pPr><w:pStyle w:val=\"ListParagraph\"/><w:numPr><w:ilvl w:val=\"0\"/>\
+             <w:numId w:val=\"{}\"/></w:numPr></w:pPr>",
+            num_id
+        ));
+        if let Some(checked) = item.get_attribute("checked") {
+            let marker = if checked == "true" { "☒ " } else { "☐ " };
+            self.text(marker, RunFormat::default());
+        }
+        self.inlines(&item.children, RunFormat::default());
+        self.flush();
+        self.body.push_str("</w:p>");
+    }
+
+    /// Write a table with bordered cells; header rows repeat on each page
+    fn table(&mut self, table: &Node) {
+        let rows: Vec<(&Node, bool)> = table
+            .children
+            .iter()
+            .flat_map(|child| match child.node_type {
+                NodeType::TableHead => child.children.iter().map(|row| (row, true)).collect(),
+                _ => vec![(child, false)],
+            })
+            .collect();
+        let columns = rows
+            .iter()
+            .map(|(row, _)| row.children.len())
+            .max()
+            .unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+
+        self.body
+            .push_str("<w:tbl><w:tblPr><w:tblW w:w=\"5000\" w:type=\"pct\"/><w:tblBorders>");
+        for side in ["top", "left", "bottom", "right", "insideH", "insideV"] {
+            self.body.push_str(&format!(
+                "<w:{} w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"A0A0A0\"/>",
+                side
+            ));
+        }
+        self.body.push_str(
+            "</w:tblBorders><w:tblCellMar><w:left w:w=\"80\" w:type=\"dxa\"/>\
+             <w:right w:w=\"80\" w:type=\"dxa\"/></w:tblCellMar></w:tblPr><w:tblGrid>",
+        );
+        for _ in 0..columns {
+            self.body.push_str("<w:gridCol/>");
+        }
+        self.body.push_str("</w:tblGrid>");
+
+        for (row, header) in rows {
+            self.body.push_str("<w:tr>");
+            if header {
+                self.body.push_str("<w:trPr><w:tblHeader/></w:trPr>");
+            }
+            for cell in &row.children {
+                self.body.push_str("<w:tc><w:p>");
+                if !cell.data.is_empty() {
+                    self.body
+                        .push_str(&format!("<w:pPr><w:jc w:val=\"{}\"/></w:pPr>", cell.data));
+                }
+                self.inlines(
+                    &cell.children,
+                    RunFormat {
+                        bold: header,
+                        ..RunFormat::default()
+                    },
+                );
+                self.flush();
+                self.body.push_str("</w:p></w:tc>");
+            }
+            // Every row needs a cell for each grid column
+            for _ in row.children.len()..columns {
+                self.body.push_str("<w:tc><w:p/></w:tc>");
+            }
+            self.body.push_str("</w:tr>");
+        }
+        self.body.push_str("</w:tbl>");
+    }
+
+    /// Write a paragraph with the given properties and the node's inline content
+    fn paragraph(&mut self, properties: &str, node: &Node) {
+        self.body.push_str("<w:p>");
+        if !properties.is_empty() {
+            self.body
+                .push_str(&format!("<w:pPr>{}</w:pPr>", properties));
+        }
+        self.inlines(&node.children, RunFormat::default());
+        self.flush();
+        self.body.push_str("</w:p>");
+    }
+
+    fn inlines(&mut self, nodes: &[Node], format: RunFormat) {
+        for node in nodes {
+            self.inline(node, format);
+        }
+    }
+
+    fn inline(&mut self, node: &Node, format: RunFormat) {
+        match node.node_type {
+            NodeType::Text => self.text(&node.data, format),
+            NodeType::Strong => self.inlines(
+                &node.children,
+                RunFormat {
+                    bold: true,
+                    ..format
+                },
+            ),
+            NodeType::Emph => self.inlines(
+                &node.children,
+                RunFormat {
+                    italic: true,
+                    ..format
+                },
+            ),
+            NodeType::Strikethrough => self.inlines(
+                &node.children,
+                RunFormat {
+                    strike: true,
+                    ..format
+                },
+            ),
+            NodeType::Code | NodeType::InlineMath => self.text(
+                &node.text_content(),
+                RunFormat {
+                    code: true,
+                    ..format
+                },
+            ),
+            NodeType::SoftBreak => self.text(" ", format),
+            NodeType::LineBreak => self.raw("<w:r><w:br/></w:r>"),
+            NodeType::Link => self.link(node, format),
+            NodeType::Image => self.image(node, format),
+            NodeType::TaskListItemMarker => {}
+            _ => self.inlines(&node.children, format),
+        }
+    }
+
+    fn link(&mut self, node: &Node, format: RunFormat) {
+        let href = node.get_attribute("href").cloned().unwrap_or_default();
+        // In-document anchors have no matching bookmarks, keep them as plain text
+        if href.is_empty() || href.starts_with('#') {
+            self.inlines(&node.children, format);
+            return;
+        }
+
+        let id = self.add_relationship("hyperlink", &href, true);
+        self.raw(&format!("<w:hyperlink r:id=\"{}\">", id));
+        self.inlines(
+            &node.children,
+            RunFormat {
+                link: true,
+                ..format
+            },
+        );
+        self.raw("</w:hyperlink>");
+    }
+
+    fn image(&mut self, node: &Node, format: RunFormat) {
+        let src = node.get_attribute("src").cloned().unwrap_or_default();
+        let alt = node.get_attribute("alt").cloned().unwrap_or_default();
+
+        let Some(image) = self.embed_image(&src) else {
+            // Remote or unsupported images degrade to their alt text
+            self.text(&alt, format);
+            return;
+        };
+
+        self.drawings += 1;
+        let drawing_id = self.drawings;
+        self.raw(&format!(
+            "<w:r><w:drawing><wp:inline distT=\"0\" distB=\"0\" distL=\"0\" distR=\"0\">\
+             <wp:extent cx=\"{cx}\" cy=\"{cy}\"/>\
+             <wp:docPr id=\"{id}\" name=\"Picture {id}\" descr=\"{alt}\"/>\
+             <wp:cNvGraphicFramePr><a:graphicFrameLocks noChangeAspect=\"1\"/></wp:cNvGraphicFramePr>\
+             <a:graphic><a:graphicData uri=\"http://schemas.openxmlformats.org/drawingml/2006/picture\">\
+             <pic:pic><pic:nvPicPr><pic:cNvPr id=\"{id}\" name=\"Picture {id}\"/><pic:cNvPicPr/></pic:nvPicPr>\
+             <pic:blipFill><a:blip r:embed=\"{rid}\"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill>\
+             <pic:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"{cx}\" cy=\"{cy}\"/></a:xfrm>\
+             <a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></pic:spPr></pic:pic>\
+             </a:graphicData></a:graphic></wp:inline></w:drawing></w:r>",
+            cx = image.width,
+            cy = image.height,
+            id = drawing_id,
+            alt = escape_xml(&alt),
+            rid = image.relationship_id,
+        ));
+    }
+
+    /// Add an image to the package once per source, returning its relationship and size
+    fn embed_image(&mut self, src: &str) -> Option<EmbeddedImage> {
+        if let Some(image) = self.images.get(src) {
+            return image.clone();
+        }
+
+        let image = LocalImage::load(src, self.base_dir)
+            // Word needs a raster fallback for SVG, which we cannot produce
+            .filter(|image| image.mime_type != "image/svg+xml")
+            .and_then(|image| {
+                let (width, height) = image_size(&image.content)?;
+                Some((image, width, height))
+            })
+            .map(|(image, width, height)| {
+                let name = format!("image{}.{}", self.media.len() + 1, image.extension());
+                let relationship_id =
+                    self.add_relationship("image", &format!("media/{}", name), false);
+                self.media.push((name, image.content));
+
+                let (width, height) = display_size(width, height);
+                EmbeddedImage {
+                    relationship_id,
+                    width,
+                    height,
+                }
+            });
+
+        self.images.insert(src.to_string(), image.clone());
+        image
+    }
+
+    fn add_relationship(&mut self, kind: &'static str, target: &str, external: bool) -> String {
+        let id = format!("rId{}", self.relationships.len() + 1);
+        self.relationships.push(Relationship {
+            id: id.clone(),
+            kind,
+            target: target.to_string(),
+            external,
+        });
+        id
+    }
+
+    /// Append text to the pending run, starting a new run when the formatting changes
+    fn text(&mut self, text: &str, format: RunFormat) {
+        if format != self.pending_format {
+            self.flush();
+            self.pending_format = format;
+        }
+        self.pending.push_str(text);
+    }
+
+    /// Append raw markup after the pending run
+    fn raw(&mut self, xml: &str) {
+        self.flush();
+        self.body.push_str(xml);
+    }
+
+    fn flush(&mut self) {
+        if !self.pending.is_empty() {
+            let run = text_run(&self.pending, self.pending_format);
+            self.body.push_str(&run);
+            self.pending.clear();
+        }
+    }
+
+    fn document(&self) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <w:document {}><w:body>{}\
+             <w:sectPr><w:pgSz w:w=\"12240\" w:h=\"15840\"/>\
+             <w:pgMar w:top=\"1440\" w:right=\"1440\" w:bottom=\"1440\" w:left=\"1440\" \
+             w:header=\"720\" w:footer=\"720\" w:gutter=\"0\"/></w:sectPr>\
+             </w:body></w:document>",
+            WORD_NAMESPACES, self.body
+        )
+    }
+
+    fn relationships(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+        );
+        for relationship in &self.relationships {
+            xml.push_str(&format!(
+                "<Relationship Id=\"{}\" Type=\"{}/{}\" Target=\"{}\"{}/>",
+                relationship.id,
+                RELATIONSHIP_NS,
+                relationship.kind,
+                escape_xml(&relationship.target),
+                if relationship.external {
+                    " TargetMode=\"External\""
+                } else {
+                    ""
+                }
+            ));
+        }
+        xml.push_str("</Relationships>");
+        xml
+    }
+
+    fn numbering(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <w:numbering xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">\
+             <w:abstractNum w:abstractNumId=\"0\"><w:multiLevelType w:val=\"singleLevel\"/>\
+             <w:lvl w:ilvl=\"0\"><w:start w:val=\"1\"/><w:numFmt w:val=\"bullet\"/>\
+             <w:lvlText w:val=\"•\"/><w:lvlJc w:val=\"left\"/>\
+             <w:pPr><w:ind w:left=\"720\" w:hanging=\"360\"/></w:pPr></w:lvl></w:abstractNum>\
+             <w:abstractNum w:abstractNumId=\"1\"><w:multiLevelType w:val=\"singleLevel\"/>\
+             <w:lvl w:ilvl=\"0\"><w:start w:val=\"1\"/><w:numFmt w:val=\"decimal\"/>\
+             <w:lvlText w:val=\"%1.\"/><w:lvlJc w:val=\"left\"/>\
+             <w:pPr><w:ind w:left=\"720\" w:hanging=\"360\"/></w:pPr></w:lvl></w:abstractNum>",
+        );
+        xml.push_str(&format!(
+            "<w:num w:numId=\"{}\"><w:abstractNumId w:val=\"0\"/></w:num>",
+            BULLET_NUM_ID
+        ));
+        for list in 1..=self.ordered_lists {
+            xml.push_str(&format!(
+                "<w:num w:numId=\"{}\"><w:abstractNumId w:val=\"1\"/>\
+                 <w:lvlOverride w:ilvl=\"0\"><w:startOverride w:val=\"1\"/></w:lvlOverride></w:num>",
+                BULLET_NUM_ID + list
+            ));
+        }
+        xml.push_str("</w:numbering>");
+        xml
+    }
+
+    fn content_types(&self) -> String {
+        let mut extensions: Vec<&str> = self
+            .media
+            .iter()
+            .filter_map(|(name, _)| name.rsplit('.').next())
+            .collect();
+        extensions.sort_unstable();
+        extensions.dedup();
+
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+             <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+             <Default Extension=\"xml\" ContentType=\"application/xml\"/>",
+        );
+        for extension in extensions {
+            xml.push_str(&format!(
+                "<Default Extension=\"{}\" ContentType=\"{}\"/>",
+                extension,
+                super::guess_mime_type(Path::new(&format!("image.{}", extension)))
+            ));
+        }
+        xml.push_str(
+            "<Override PartName=\"/word/document.xml\" \
+             ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml\"/>\
+             <Override PartName=\"/word/styles.xml\" \
+             ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml\"/>\
+             <Override PartName=\"/word/numbering.xml\" \
+             ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml\"/>\
+             <Override PartName=\"/docProps/core.xml\" \
+             ContentType=\"application/vnd.openxmlformats-package.core-properties+xml\"/>\
+             </Types>",
+        );
+        xml
+    }
+}
+
+/// Format a single text run
+fn text_run(text: &str, format: RunFormat) -> String {
+    let mut properties = String::new();
+    if format.link {
+        properties.push_str("<w:rStyle w:val=\"Hyperlink\"/>");
+    } else if format.code {
+        properties.push_str("<w:rStyle w:val=\"VerbatimChar\"/>");
+    }
+    if format.bold {
+        properties.push_str("<w:b/>");
+    }
+    if format.italic {
+        properties.push_str("<w:i/>");
+    }
+    if format.strike {
+        properties.push_str("<w:strike/>");
+    }
+
+    let properties = if properties.is_empty() {
+        properties
+    } else {
+        format!("<w:rPr>{}</w:rPr>", properties)
+    };
+    format!(
+        "<w:r>{}<w:t xml:space=\"preserve\">{}</w:t></w:r>",
+        properties,
+        escape_xml(text)
+    )
+}
+
+/// Scale pixel dimensions to EMUs, shrinking wide images to the text column
+fn display_size(width: u32, height: u32) -> (u64, u64) {
+    let width = u64::from(width.max(1)) * EMU_PER_PIXEL;
+    let height = u64::from(height.max(1)) * EMU_PER_PIXEL;
+    if width <= MAX_IMAGE_WIDTH_EMU {
+        (width, height)
+    } else {
+        (MAX_IMAGE_WIDTH_EMU, height * MAX_IMAGE_WIDTH_EMU / width)
+    }
+}
+
+/// Read the pixel dimensions from a PNG, GIF, BMP or JPEG header
+fn image_size(data: &[u8]) -> Option<(u32, u32)> {
+    let be32 = |at: usize| -> Option<u32> {
+        Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+    };
+    let le16 = |at: usize| -> Option<u32> {
+        Some(u32::from(u16::from_le_bytes(
+            data.get(at..at + 2)?.try_into().ok()?,
+        )))
+    };
+
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some((be32(16)?, be32(20)?));
+    }
+    if data.starts_with(b"GIF8") {
+        return Some((le16(6)?, le16(8)?));
+    }
+    if data.starts_with(b"BM") {
+        let width = i32::from_le_bytes(data.get(18..22)?.try_into().ok()?);
+        let height = i32::from_le_bytes(data.get(22..26)?.try_into().ok()?);
+        return Some((width.unsigned_abs(), height.unsigned_abs()));
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        return jpeg_size(data);
+    }
+    None
+}
+
+/// Find the frame header of a JPEG and read its dimensions
+fn jpeg_size(data: &[u8]) -> Option<(u32, u32)> {
+    let mut offset = 2;
+    while offset + 9 < data.len() {
+        if data[offset] != 0xFF {
+            return None;
+        }
+        let marker = data[offset + 1];
+        let length = usize::from(u16::from_be_bytes([data[offset + 2], data[offset + 3]]));
+        // SOF0..SOF15, excluding DHT (C4), JPG (C8) and DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+            let height = u16::from_be_bytes([data[offset + 5], data[offset + 6]]);
+            let width = u16::from_be_bytes([data[offset + 7], data[offset + 8]]);
+            return Some((u32::from(width), u32::from(height)));
+        }
+        offset += 2 + length;
+    }
+    None
+}
+
+fn core_properties(title: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <cp:coreProperties \
+         xmlns:cp=\"http://schemas.openxmlformats.org/package/2006/metadata/core-properties\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\
+         <dc:title>{}</dc:title><dc:creator>Rune</dc:creator></cp:coreProperties>",
+        escape_xml(title)
+    )
+}
+
+const ROOT_RELATIONSHIPS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+<Relationship Id=\"rId1\" \
+Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" \
+Target=\"word/document.xml\"/>\
+<Relationship Id=\"rId2\" \
+Type=\"http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties\" \
+Target=\"docProps/core.xml\"/>\
+</Relationships>";
+
+const STYLES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<w:styles xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">\
+<w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii=\"Calibri\" w:hAnsi=\"Calibri\" \
+w:eastAsia=\"Calibri\" w:cs=\"Calibri\"/><w:sz w:val=\"22\"/></w:rPr></w:rPrDefault>\
+<w:pPrDefault><w:pPr><w:spacing w:after=\"160\" w:line=\"264\" w:lineRule=\"auto\"/></w:pPr>\
+</w:pPrDefault></w:docDefaults>\
+<w:style w:type=\"paragraph\" w:default=\"1\" w:styleId=\"Normal\"><w:name w:val=\"Normal\"/></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Heading1\"><w:name w:val=\"heading 1\"/><w:basedOn w:val=\"Normal\"/>\
+<w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before=\"360\" w:after=\"120\"/>\
+<w:outlineLvl w:val=\"0\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"36\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Heading2\"><w:name w:val=\"heading 2\"/><w:basedOn w:val=\"Normal\"/>\
+<w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before=\"280\" w:after=\"100\"/>\
+<w:outlineLvl w:val=\"1\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"30\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Heading3\"><w:name w:val=\"heading 3\"/><w:basedOn w:val=\"Normal\"/>\
+<w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before=\"240\" w:after=\"80\"/>\
+<w:outlineLvl w:val=\"2\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"26\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Heading4\"><w:name w:val=\"heading 4\"/><w:basedOn w:val=\"Normal\"/>\
+<w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before=\"200\" w:after=\"60\"/>\
+<w:outlineLvl w:val=\"3\"/></w:pPr><w:rPr><w:b/><w:i/><w:sz w:val=\"24\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Heading5\"><w:name w:val=\"heading 5\"/><w:basedOn w:val=\"Normal\"/>\
+<w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before=\"200\" w:after=\"60\"/>\
+<w:outlineLvl w:val=\"4\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"22\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Heading6\"><w:name w:val=\"heading 6\"/><w:basedOn w:val=\"Normal\"/>\
+<w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before=\"200\" w:after=\"60\"/>\
+<w:outlineLvl w:val=\"5\"/></w:pPr><w:rPr><w:i/><w:sz w:val=\"22\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Quote\"><w:name w:val=\"Quote\"/><w:basedOn w:val=\"Normal\"/>\
+<w:qFormat/><w:pPr><w:pBdr><w:left w:val=\"single\" w:sz=\"18\" w:space=\"8\" w:color=\"A0A0A0\"/></w:pBdr>\
+<w:ind w:left=\"360\"/></w:pPr><w:rPr><w:i/><w:color w:val=\"595959\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"SourceCode\"><w:name w:val=\"Source Code\"/><w:basedOn w:val=\"Normal\"/>\
+<w:pPr><w:shd w:val=\"clear\" w:color=\"auto\" w:fill=\"F2F2F2\"/><w:spacing w:after=\"160\" w:line=\"240\" \
+w:lineRule=\"auto\"/></w:pPr><w:rPr><w:rFonts w:ascii=\"Consolas\" w:hAnsi=\"Consolas\" w:cs=\"Consolas\"/>\
+<w:sz w:val=\"20\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"ListParagraph\"><w:name w:val=\"List Paragraph\"/>\
+<w:basedOn w:val=\"Normal\"/><w:qFormat/><w:pPr><w:spacing w:after=\"60\"/><w:ind w:left=\"720\"/></w:pPr></w:style>\
+<w:style w:type=\"character\" w:styleId=\"Hyperlink\"><w:name w:val=\"Hyperlink\"/>\
+<w:rPr><w:color w:val=\"0563C1\"/><w:u w:val=\"single\"/></w:rPr></w:style>\
+<w:style w:type=\"character\" w:styleId=\"VerbatimChar\"><w:name w:val=\"Verbatim Char\"/>\
+<w:rPr><w:rFonts w:ascii=\"Consolas\" w:hAnsi=\"Consolas\" w:cs=\"Consolas\"/><w:sz w:val=\"20\"/>\
+<w:shd w:val=\"clear\" w:color=\"auto\" w:fill=\"F2F2F2\"/></w:rPr></w:style>\
+</w:styles>";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::MarkdownParser;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    /// Minimal PNG header with the given dimensions
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data
+    }
+
+    fn read_entry(archive: &mut zip::ZipArchive<std::io::Cursor<Vec<u8>>>, name: &str) -> String {
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn test_write_docx_package() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("chart.png"), png_header(1200, 600)).unwrap();
+
+        let markdown = "# Report\n\nHello **bold** [site](https://example.com)\n\n\
+                        1. one\n2. two\n\n- [x] done\n\n![Chart](chart.png) ![Remote](https://x.io/a.png)\n";
+        let tree = MarkdownParser::new().parse(markdown);
+        let bytes = write_docx(&tree, "Report & Notes", temp_dir.path()).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let document = read_entry(&mut archive, "word/document.xml");
+        assert!(document.contains("<w:pStyle w:val=\"Heading1\"/>"));
+        assert!(document.contains(">Report</w:t>"));
+        assert!(document.contains("<w:b/></w:rPr><w:t xml:space=\"preserve\">bold</w:t>"));
+        assert!(document.contains("<w:hyperlink r:id=\"rId3\">"));
+        assert!(document.contains("<w:numId w:val=\"2\"/>"));
+        assert!(document.contains("☒ done"));
+        assert!(document.contains("r:embed=\"rId4\""));
+        assert!(document.contains(&format!("cx=\"{}\"", MAX_IMAGE_WIDTH_EMU)));
+        assert!(document.contains(" Remote</w:t>"));
+
+        let relationships = read_entry(&mut archive, "word/_rels/document.xml.rels");
+        assert!(relationships.contains("Target=\"https://example.com\" TargetMode=\"External\""));
+        assert!(relationships.contains("Target=\"media/image1.png\""));
+        assert!(read_entry(&mut archive, "[Content_Types].xml").contains("Extension=\"png\""));
+        assert!(read_entry(&mut archive, "docProps/core.xml").contains("Report &amp; Notes"));
+        assert!(archive.by_name("word/media/image1.png").is_ok());
+    }
+
+    #[test]
+    fn test_tables_and_line_breaks() {
+        let markdown = "| Item | Cost |\n|------|-----:|\n| Tea & cake | 4 |\n| Extra |\n\n\
+                        Line one  \nline two\nsame line\n";
+        let tree = MarkdownParser::new().parse(markdown);
+        let bytes = write_docx(&tree, "Bill", Path::new(".")).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let document = read_entry(&mut archive, "word/document.xml");
+        assert!(document.contains("<w:tblGrid><w:gridCol/><w:gridCol/></w:tblGrid>"));
+        assert_eq!(document.matches("<w:tr>").count(), 3);
+        assert!(document.contains("<w:trPr><w:tblHeader/></w:trPr>"));
+        assert!(document.contains("<w:b/></w:rPr><w:t xml:space=\"preserve\">Item</w:t>"));
+        assert!(document.contains(
+            "<w:pPr><w:jc w:val=\"right\"/></w:pPr><w:r><w:t xml:space=\"preserve\">4</w:t>"
+        ));
+        assert!(document.contains(">Tea &amp; cake</w:t>"));
+        // No pipes leak into the text and short rows are padded
+        assert!(!document.contains('|'));
+        assert!(document.contains(
+            "<w:tc><w:p><w:pPr><w:jc w:val=\"right\"/></w:pPr></w:p></w:tc></w:tr></w:tbl>"
+        ));
+
+        assert!(document.contains(
+            ">Line one</w:t></w:r><w:r><w:br/></w:r><w:r><w:t xml:space=\"preserve\">line two same line</w:t>"
+        ));
+    }
+
+    #[test]
+    fn test_image_size() {
+        assert_eq!(image_size(&png_header(640, 480)), Some((640, 480)));
+        assert_eq!(image_size(b"GIF89a\x20\x00\x10\x00"), Some((32, 16)));
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00,
+            0x30, 0x00, 0x40, 0x03,
+        ];
+        assert_eq!(image_size(&jpeg), Some((64, 48)));
+        assert_eq!(image_size(b"not an image"), None);
+        assert_eq!(display_size(100, 50), (952_500, 476_250));
+    }
+}
@@ -0,0 +1,399 @@
+//! EPUB export
+//!
+//! Builds an EPUB 3 package from the markdown AST. The document is split into
+//! chapters at its section headings, each chapter is rendered to XHTML with the
+//! AST [`HtmlRenderer`], and local images are copied into the package and listed
+//! in the OPF manifest. A `toc.ncx` is included for EPUB 2 reading systems.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::{escape_html as escape_xml, LocalImage, ZipPackage};
+use crate::ast::{Node, NodeType, Tree};
+use crate::error::Result;
+use crate::render::{HtmlRenderer, RenderOptions};
+use crate::time;
+
+const STYLESHEET: &str = "body { font-family: serif; line-height: 1.5; margin: 0 5%; }\n\
+h1, h2, h3, h4, h5, h6 { font-family: sans-serif; line-height: 1.2; page-break-after: avoid; }\n\
+pre { white-space: pre-wrap; font-size: 0.85em; background: #f4f4f4; padding: 0.6em; }\n\
+code { font-family: monospace; }\n\
+blockquote { margin-left: 1em; padding-left: 1em; border-left: 3px solid #ccc; color: #555; }\n\
+img { max-width: 100%; }\n\
+table { border-collapse: collapse; margin: 1em 0; }\n\
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; }\n";
+
+/// One chapter of the book
+struct Chapter {
+    title: String,
+    nodes: Vec<Node>,
+}
+
+impl Chapter {
+    fn file_name(index: usize) -> String {
+        format!("chapter-{}.xhtml", index + 1)
+    }
+}
+
+/// Image copied into the package
+struct PackagedImage {
+    href: String,
+    mime_type: &'static str,
+    content: Vec<u8>,
+}
+
+/// Write a parsed markdown document as an EPUB package
+pub fn write_epub(tree: &Tree, title: &str, base_dir: &Path) -> Result<Vec<u8>> {
+    let mut root = tree.root.clone();
+    let mut images = Vec::new();
+    prepare_nodes(
+        &mut root.children,
+        base_dir,
+        &mut images,
+        &mut HashMap::new(),
+    );
+
+    let level = chapter_level(&root.children);
+    let chapters = split_chapters(root.children, level, title);
+    let identifier = format!("urn:uuid:{}", uuid::Uuid::new_v4());
+
+    let mut package = ZipPackage::new();
+    // The mimetype entry must come first and be stored uncompressed
+    package.stored("mimetype", b"application/epub+zip")?;
+    package.deflated("META-INF/container.xml", CONTAINER.as_bytes())?;
+    package.deflated(
+        "OEBPS/content.opf",
+        package_document(title, &identifier, &chapters, &images).as_bytes(),
+    )?;
+    package.deflated("OEBPS/nav.xhtml", navigation(title, &chapters).as_bytes())?;
+    package.deflated(
+        "OEBPS/toc.ncx",
+        ncx(title, &identifier, &chapters).as_bytes(),
+    )?;
+    package.deflated("OEBPS/style.css", STYLESHEET.as_bytes())?;
+    for (index, chapter) in chapters.iter().enumerate() {
+        package.deflated(
+            &format!("OEBPS/{}", Chapter::file_name(index)),
+            chapter_document(chapter).as_bytes(),
+        )?;
+    }
+    for image in &images {
+        package.stored(&format!("OEBPS/{}", image.href), &image.content)?;
+    }
+    package.finish()
+}
+
+/// Rewrite the AST for XHTML output: embed local images and turn task markers
+/// into plain checkbox characters
+fn prepare_nodes(
+    nodes: &mut [Node],
+    base_dir: &Path,
+    images: &mut Vec<PackagedImage>,
+    seen: &mut HashMap<String, String>,
+) {
+    for node in nodes.iter_mut() {
+        match node.node_type {
+            NodeType::Image => {
+                let src = node.get_attribute("src").cloned().unwrap_or_default();
+                let href = match seen.get(&src) {
+                    Some(href) => Some(href.clone()),
+                    None => LocalImage::load(&src, base_dir).map(|image| {
+                        let href =
+                            format!("images/image{}.{}", images.len() + 1, image.extension());
+                        images.push(PackagedImage {
+                            href: href.clone(),
+                            mime_type: image.mime_type,
+                            content: image.content,
+                        });
+                        seen.insert(src.clone(), href.clone());
+                        href
+                    }),
+                };
+
+                match href {
+                    Some(href) => node.set_attribute("src", &href),
+                    None => {
+                        // Remote resources would need network access in the reader
+                        let alt = node.get_attribute("alt").cloned().unwrap_or_default();
+                        *node = Node::text(&alt);
+                    }
+                }
+            }
+            // `type="unordered"` and `type="ordered"` are not valid XHTML
+            NodeType::List => {
+                node.attributes.remove("type");
+            }
+            NodeType::ListItem => {
+                if let Some(checked) = node.attributes.remove("checked") {
+                    node.attributes.remove("type");
+                    let marker = if checked == "true" { "☑ " } else { "☐ " };
+                    for child in node.children.iter_mut() {
+                        if child.node_type == NodeType::TaskListItemMarker {
+                            *child = Node::text(marker);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        prepare_nodes(&mut node.children, base_dir, images, seen);
+    }
+}
+
+/// Heading level that starts a new chapter
+///
+/// Uses the shallowest level that occurs more than once, so a single title
+/// heading followed by `##` sections splits at the sections.
+fn chapter_level(nodes: &[Node]) -> usize {
+    let mut counts = [0usize; 7];
+    for node in nodes {
+        if node.node_type == NodeType::Heading {
+            counts[node.level.unwrap_or(1).clamp(1, 6)] += 1;
+        }
+    }
+
+    (1..=6)
+        .find(|&level| counts[level] > 1)
+        .or_else(|| (1..=6).find(|&level| counts[level] > 0))
+        .unwrap_or(1)
+}
+
+/// Split top-level nodes into chapters at headings of `level` or shallower
+fn split_chapters(nodes: Vec<Node>, level: usize, title: &str) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+    let mut current: Vec<Node> = Vec::new();
+
+    for node in nodes {
+        let starts_chapter = node.node_type == NodeType::Heading
+            && node.level.unwrap_or(1) <= level
+            && !current.is_empty();
+        if starts_chapter {
+            chapters.push(new_chapter(std::mem::take(&mut current), title));
+        }
+        current.push(node);
+    }
+    if !current.is_empty() || chapters.is_empty() {
+        chapters.push(new_chapter(current, title));
+    }
+
+    chapters
+}
+
+fn new_chapter(nodes: Vec<Node>, fallback_title: &str) -> Chapter {
+    let title = nodes
+        .iter()
+        .find(|node| node.node_type == NodeType::Heading)
+        .map(|heading| heading.text_content().trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| fallback_title.to_string());
+    Chapter { title, nodes }
+}
+
+fn chapter_document(chapter: &Chapter) -> String {
+    let mut tree = Tree::new();
+    tree.root.children = chapter.nodes.clone();
+
+    let mut renderer = HtmlRenderer::new(RenderOptions {
+        soft_break_as_hard_break: false,
+        ..Default::default()
+    });
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n\
+         <head>\n<meta charset=\"UTF-8\"/>\n<title>{}</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n</head>\n\
+         <body>\n<section epub:type=\"chapter\">\n{}</section>\n</body>\n</html>\n",
+        escape_xml(&chapter.title),
+        renderer.render(&tree)
+    )
+}
+
+fn navigation(title: &str, chapters: &[Chapter]) -> String {
+    let entries: String = chapters
+        .iter()
+        .enumerate()
+        .map(|(index, chapter)| {
+            format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                Chapter::file_name(index),
+                escape_xml(&chapter.title)
+            )
+        })
+        .collect();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n\
+         <head>\n<meta charset=\"UTF-8\"/>\n<title>{title}</title>\n</head>\n<body>\n\
+         <nav epub:type=\"toc\" id=\"toc\">\n<h1>{title}</h1>\n<ol>\n{entries}</ol>\n</nav>\n\
+         </body>\n</html>\n",
+        title = escape_xml(title),
+        entries = entries
+    )
+}
+
+fn ncx(title: &str, identifier: &str, chapters: &[Chapter]) -> String {
+    let points: String = chapters
+        .iter()
+        .enumerate()
+        .map(|(index, chapter)| {
+            format!(
+                "<navPoint id=\"nav-{n}\" playOrder=\"{n}\"><navLabel><text>{}</text></navLabel>\
+                 <content src=\"{}\"/></navPoint>\n",
+                escape_xml(&chapter.title),
+                Chapter::file_name(index),
+                n = index + 1
+            )
+        })
+        .collect();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <ncx xmlns=\"http://www.daisy.org/z3986/2005/ncx/\" version=\"2005-1\">\n\
+         <head><meta name=\"dtb:uid\" content=\"{}\"/></head>\n\
+         <docTitle><text>{}</text></docTitle>\n<navMap>\n{}</navMap>\n</ncx>\n",
+        identifier,
+        escape_xml(title),
+        points
+    )
+}
+
+fn package_document(
+    title: &str,
+    identifier: &str,
+    chapters: &[Chapter],
+    images: &[PackagedImage],
+) -> String {
+    let mut manifest = String::from(
+        "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n\
+         <item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\"/>\n\
+         <item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n",
+    );
+    let mut spine = String::new();
+    for index in 0..chapters.len() {
+        manifest.push_str(&format!(
+            "<item id=\"chapter-{n}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            Chapter::file_name(index),
+            n = index + 1
+        ));
+        spine.push_str(&format!("<itemref idref=\"chapter-{}\"/>\n", index + 1));
+    }
+    for (index, image) in images.iter().enumerate() {
+        manifest.push_str(&format!(
+            "<item id=\"image-{}\" href=\"{}\" media-type=\"{}\"/>\n",
+            index + 1,
+            image.href,
+            image.mime_type
+        ));
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\">\n\
+         <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+         <dc:identifier id=\"book-id\">{}</dc:identifier>\n<dc:title>{}</dc:title>\n\
+         <dc:language>en</dc:language>\n\
+         <meta property=\"dcterms:modified\">{}</meta>\n</metadata>\n\
+         <manifest>\n{}</manifest>\n<spine toc=\"ncx\">\n{}</spine>\n</package>\n",
+        identifier,
+        escape_xml(title),
+        time::utc_timestamp(SystemTime::now()),
+        manifest,
+        spine
+    )
+}
+
+const CONTAINER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n\
+<rootfiles>\n\
+<rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/>\n\
+</rootfiles>\n\
+</container>\n";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::MarkdownParser;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    fn read_entry(archive: &mut zip::ZipArchive<std::io::Cursor<Vec<u8>>>, name: &str) -> String {
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn test_chapter_level() {
+        let parse = |markdown: &str| MarkdownParser::new().parse(markdown).root.children;
+        assert_eq!(chapter_level(&parse("# A\n\n# B\n")), 1);
+        assert_eq!(chapter_level(&parse("# Book\n\n## One\n\n## Two\n")), 2);
+        assert_eq!(chapter_level(&parse("### Only\n")), 3);
+        assert_eq!(chapter_level(&parse("no headings")), 1);
+    }
+
+    #[test]
+    fn test_split_chapters() {
+        let tree = MarkdownParser::new().parse("Intro\n\n# Book\n\n## One\n\ntext\n\n## Two\n");
+        let chapters = split_chapters(tree.root.children, 2, "Fallback");
+        let titles: Vec<_> = chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["Fallback", "Book", "One", "Two"]);
+        assert_eq!(chapters[2].nodes.len(), 2);
+    }
+
+    #[test]
+    fn test_write_epub_package() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("img")).unwrap();
+        std::fs::write(temp_dir.path().join("img/cover.png"), b"\x89PNG").unwrap();
+
+        let markdown = "# One & Only\n\n![Cover](img/cover.png)\n\n- [x] read\n\n\
+                        # Two\n\n![Again](img/cover.png) ![Web](https://x.io/a.png)\n\n\
+                        | Chapter | Pages |\n|:--|--:|\n| Two | 12 |\n\n\
+                        - roses\n\nred  \nblue\n";
+        let tree = MarkdownParser::new().parse(markdown);
+        let bytes = write_epub(&tree, "Book", temp_dir.path()).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        {
+            let mimetype = archive.by_index(0).unwrap();
+            assert_eq!(mimetype.name(), "mimetype");
+            assert_eq!(mimetype.compression(), zip::CompressionMethod::Stored);
+        }
+
+        let opf = read_entry(&mut archive, "OEBPS/content.opf");
+        assert!(opf.contains("<dc:title>Book</dc:title>"));
+        assert!(opf.contains("href=\"images/image1.png\" media-type=\"image/png\""));
+        assert!(!opf.contains("image2"));
+        assert!(opf.contains("<itemref idref=\"chapter-2\"/>"));
+
+        let nav = read_entry(&mut archive, "OEBPS/nav.xhtml");
+        assert!(nav.contains("<a href=\"chapter-1.xhtml\">One &amp; Only</a>"));
+        assert!(nav.contains("<a href=\"chapter-2.xhtml\">Two</a>"));
+
+        let first = read_entry(&mut archive, "OEBPS/chapter-1.xhtml");
+        assert!(first.contains("src=\"images/image1.png\""));
+        assert!(first.contains("☑ read"));
+        assert!(!first.contains("<input"));
+        let second = read_entry(&mut archive, "OEBPS/chapter-2.xhtml");
+        assert!(second.contains("<h1>Two</h1>"));
+        assert!(second.contains("Web"));
+        assert!(!second.contains("x.io"));
+        assert!(second.contains(
+            "<thead>\n<tr><th style=\"text-align: left\">Chapter</th>\
+             <th style=\"text-align: right\">Pages</th></tr>\n</thead>\n\
+             <tbody>\n<tr><td style=\"text-align: left\">Two</td>"
+        ));
+        assert!(second.contains("<ul>\n<li>roses</li>"));
+        assert!(!second.contains("unordered"));
+        assert!(second.contains("<p>red<br />\nblue</p>"));
+        assert!(archive.by_name("OEBPS/images/image1.png").is_ok());
+    }
+}
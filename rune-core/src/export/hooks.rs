@@ -0,0 +1,264 @@
+//! Export hooks
+//!
+//! Hooks configured in [`ExportHooks`](crate::config::ExportHooks) run before and after an export so users
+//! can chain steps such as image optimization or deployment. Command hooks run
+//! through the platform shell; plugin hooks call an [`ExportHookHandler`]
+//! registered under the plugin's name. Both receive the same description of the
+//! export, as `RUNE_EXPORT_*` environment variables for commands.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::ExportFormat;
+use crate::config::{ExportHook, ExportHookAction};
+use crate::error::{Result, RuneError};
+use crate::process;
+
+/// Point in the export at which a hook runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    Pre,
+    Post,
+}
+
+impl fmt::Display for HookStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookStage::Pre => write!(f, "pre"),
+            HookStage::Post => write!(f, "post"),
+        }
+    }
+}
+
+/// Description of the export a hook runs for
+#[derive(Debug, Clone)]
+pub struct HookEnvironment {
+    pub stage: HookStage,
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub format: ExportFormat,
+    /// Size of the written output; only known to post-export hooks
+    pub bytes_written: Option<usize>,
+}
+
+impl HookEnvironment {
+    /// Directory the output file is written to
+    pub fn output_dir(&self) -> PathBuf {
+        self.output
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+
+    /// Environment variables passed to command hooks
+    pub fn variables(&self) -> Vec<(&'static str, String)> {
+        let mut variables = vec![
+            ("RUNE_EXPORT_STAGE", self.stage.to_string()),
+            (
+                "RUNE_EXPORT_INPUT",
+                self.input.to_string_lossy().to_string(),
+            ),
+            (
+                "RUNE_EXPORT_OUTPUT",
+                self.output.to_string_lossy().to_string(),
+            ),
+            (
+                "RUNE_EXPORT_DIR",
+                self.output_dir().to_string_lossy().to_string(),
+            ),
+            ("RUNE_EXPORT_FORMAT", self.format.to_string()),
+        ];
+        if let Some(bytes) = self.bytes_written {
+            variables.push(("RUNE_EXPORT_BYTES", bytes.to_string()));
+        }
+        variables
+    }
+}
+
+/// Handler for `{"plugin": "<name>"}` export hooks
+#[async_trait]
+pub trait ExportHookHandler: Send + Sync {
+    /// Run the hook for the given export
+    async fn run(&self, environment: &HookEnvironment) -> Result<()>;
+}
+
+/// Runs configured export hooks in order
+#[derive(Clone, Default)]
+pub struct HookRunner {
+    handlers: HashMap<String, Arc<dyn ExportHookHandler>>,
+}
+
+impl HookRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the handler called by plugin hooks naming `plugin`
+    pub fn register_handler(&mut self, plugin: &str, handler: Arc<dyn ExportHookHandler>) {
+        self.handlers.insert(plugin.to_string(), handler);
+    }
+
+    /// Run all hooks of a stage, stopping at the first failure that is not allowed
+    ///
+    /// Returns the number of hooks that ran.
+    pub async fn run_all(
+        &self,
+        hooks: &[ExportHook],
+        environment: &HookEnvironment,
+    ) -> Result<usize> {
+        for hook in hooks {
+            match self.run(hook, environment).await {
+                Ok(()) => {}
+                Err(e) if hook.allow_failure => {
+                    warn!("{}-export hook failed (ignored): {}", environment.stage, e)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(hooks.len())
+    }
+
+    /// Run a single hook
+    pub async fn run(&self, hook: &ExportHook, environment: &HookEnvironment) -> Result<()> {
+        match &hook.action {
+            ExportHookAction::Command(command) => run_command(command, environment).await,
+            ExportHookAction::Plugin(plugin) => {
+                let handler = self.handlers.get(plugin).ok_or_else(|| {
+                    RuneError::plugin(format!(
+                        "No export hook handler registered by plugin '{}'",
+                        plugin
+                    ))
+                })?;
+                info!(
+                    "Running {}-export hook of plugin '{}'",
+                    environment.stage, plugin
+                );
+                handler.run(environment).await
+            }
+        }
+    }
+}
+
+/// Run a command hook through the platform shell in the document's directory
+async fn run_command(command: &str, environment: &HookEnvironment) -> Result<()> {
+    info!("Running {}-export hook: {}", environment.stage, command);
+
+    let runner = process::runner();
+    let mut process = runner.shell(command)?;
+    if let Some(dir) = environment.input.parent() {
+        if !dir.as_os_str().is_empty() {
+            process.current_dir(dir);
+        }
+    }
+    process.envs(environment.variables());
+    Ok(runner.status(process).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::TempDir;
+
+    struct CountingHandler(AtomicUsize);
+
+    #[async_trait]
+    impl ExportHookHandler for CountingHandler {
+        async fn run(&self, environment: &HookEnvironment) -> Result<()> {
+            assert_eq!(environment.stage, HookStage::Post);
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn environment(dir: &Path) -> HookEnvironment {
+        HookEnvironment {
+            stage: HookStage::Post,
+            input: dir.join("doc.md"),
+            output: dir.join("site/doc.html"),
+            format: ExportFormat::Html,
+            bytes_written: Some(42),
+        }
+    }
+
+    fn hook(action: ExportHookAction, allow_failure: bool) -> ExportHook {
+        ExportHook {
+            action,
+            allow_failure,
+        }
+    }
+
+    #[test]
+    fn test_hook_environment_variables() {
+        let environment = environment(Path::new("/docs"));
+        let variables: HashMap<_, _> = environment.variables().into_iter().collect();
+        assert_eq!(variables["RUNE_EXPORT_DIR"], "/docs/site");
+        assert_eq!(variables["RUNE_EXPORT_STAGE"], "post");
+        assert_eq!(variables["RUNE_EXPORT_BYTES"], "42");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_hooks_run_in_document_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let hooks = vec![hook(
+            ExportHookAction::Command(
+                "echo \"$RUNE_EXPORT_FORMAT $RUNE_EXPORT_BYTES\" > hook.txt".to_string(),
+            ),
+            false,
+        )];
+
+        let count = HookRunner::new()
+            .run_all(&hooks, &environment(temp_dir.path()))
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("hook.txt")).unwrap(),
+            "html 42\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failing_hooks() {
+        let temp_dir = TempDir::new().unwrap();
+        let runner = HookRunner::new();
+        let environment = environment(temp_dir.path());
+
+        let allowed = vec![hook(ExportHookAction::Command("exit 3".to_string()), true)];
+        assert!(runner.run_all(&allowed, &environment).await.is_ok());
+
+        let failing = vec![
+            hook(ExportHookAction::Command("exit 3".to_string()), false),
+            hook(ExportHookAction::Command("touch never".to_string()), false),
+        ];
+        assert!(runner.run_all(&failing, &environment).await.is_err());
+        assert!(!temp_dir.path().join("never").exists());
+    }
+
+    #[tokio::test]
+    async fn test_plugin_hooks() {
+        let temp_dir = TempDir::new().unwrap();
+        let handler = Arc::new(CountingHandler(AtomicUsize::new(0)));
+        let mut runner = HookRunner::new();
+        runner.register_handler("deploy", handler.clone());
+
+        let hooks = vec![hook(ExportHookAction::Plugin("deploy".to_string()), false)];
+        runner
+            .run_all(&hooks, &environment(temp_dir.path()))
+            .await
+            .unwrap();
+        assert_eq!(handler.0.load(Ordering::SeqCst), 1);
+
+        let missing = vec![hook(ExportHookAction::Plugin("missing".to_string()), false)];
+        assert!(runner
+            .run_all(&missing, &environment(temp_dir.path()))
+            .await
+            .is_err());
+    }
+}
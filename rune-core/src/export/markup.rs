@@ -0,0 +1,307 @@
+//! Plain text, Slack and Jira markup
+//!
+//! Serializers for pasting a document where HTML is not understood: plain
+//! text with every link spelled out, Slack's mrkdwn and Jira's wiki markup.
+//! Like DOCX and EPUB they are written from the markdown AST. Link and image
+//! targets go through a resolver, so relative ones can be made absolute.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ast::{Node, NodeType};
+use crate::error::{Result, RuneError};
+use crate::frontmatter;
+use crate::parser::MarkdownParser;
+
+/// Markup a document can be copied as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Markup {
+    PlainText,
+    Slack,
+    Jira,
+}
+
+impl Markup {
+    /// All supported markups
+    pub fn all() -> &'static [Markup] {
+        &[Markup::PlainText, Markup::Slack, Markup::Jira]
+    }
+}
+
+impl fmt::Display for Markup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Markup::PlainText => write!(f, "text"),
+            Markup::Slack => write!(f, "slack"),
+            Markup::Jira => write!(f, "jira"),
+        }
+    }
+}
+
+impl FromStr for Markup {
+    type Err = RuneError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" | "plain" | "txt" => Ok(Markup::PlainText),
+            "slack" | "mrkdwn" => Ok(Markup::Slack),
+            "jira" | "confluence" => Ok(Markup::Jira),
+            other => Err(RuneError::config(format!(
+                "Unsupported markup '{}'. Supported markups: {}",
+                other,
+                Markup::all()
+                    .iter()
+                    .map(|m| m.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+}
+
+/// Write a markdown document in `markup`, passing link and image targets
+/// through `resolve`; its frontmatter is left out
+pub fn write_markup(markdown: &str, markup: Markup, resolve: &dyn Fn(&str) -> String) -> String {
+    let (_, body) = frontmatter::split(markdown);
+    let tree = MarkdownParser::new().parse(body);
+    let writer = MarkupWriter { markup, resolve };
+    let mut text = writer.blocks(&tree.root.children).join("\n\n");
+    text.push('\n');
+    text
+}
+
+struct MarkupWriter<'a> {
+    markup: Markup,
+    resolve: &'a dyn Fn(&str) -> String,
+}
+
+impl MarkupWriter<'_> {
+    fn blocks(&self, nodes: &[Node]) -> Vec<String> {
+        nodes.iter().flat_map(|node| self.block(node)).collect()
+    }
+
+    /// Blocks written for a node; containers yield one per child
+    fn block(&self, node: &Node) -> Vec<String> {
+        let text = match node.node_type {
+            NodeType::Heading => {
+                let text = self.inlines(&node.children);
+                match self.markup {
+                    Markup::PlainText => text,
+                    Markup::Slack => format!("*{}*", text),
+                    Markup::Jira => format!("h{}. {}", node.level.unwrap_or(1).clamp(1, 6), text),
+                }
+            }
+            NodeType::CodeBlock | NodeType::MathBlock | NodeType::HTMLBlock => {
+                let code = node.text_content();
+                match self.markup {
+                    Markup::PlainText => code,
+                    Markup::Slack => format!("```\n{}\n```", code),
+                    Markup::Jira if node.data.is_empty() => format!("{{code}}\n{}\n{{code}}", code),
+                    Markup::Jira => format!("{{code:{}}}\n{}\n{{code}}", node.data, code),
+                }
+            }
+            NodeType::Blockquote => {
+                let quoted = self.blocks(&node.children).join("\n\n");
+                match self.markup {
+                    Markup::Jira => format!("{{quote}}\n{}\n{{quote}}", quoted),
+                    _ => quoted
+                        .lines()
+                        .map(|line| format!("> {}", line).trim_end().to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                }
+            }
+            NodeType::List => self.list(node),
+            NodeType::Table => self.table(node),
+            NodeType::ThematicBreak => match self.markup {
+                Markup::Jira => "----".to_string(),
+                _ => "---".to_string(),
+            },
+            NodeType::LinkReferenceDefinition | NodeType::KramdownBlockIAL => return Vec::new(),
+            _ if node.children.iter().any(Node::is_block) => return self.blocks(&node.children),
+            _ => self.inlines(&node.children),
+        };
+        vec![text]
+    }
+
+    fn list(&self, list: &Node) -> String {
+        let ordered = list.get_attribute("type").map(String::as_str) == Some("ordered");
+        list.children
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let bullet = match (self.markup, ordered) {
+                    (Markup::Jira, true) => "#".to_string(),
+                    (Markup::Jira, false) => "*".to_string(),
+                    (_, true) => format!("{}.", index + 1),
+                    (Markup::Slack, false) => "•".to_string(),
+                    (Markup::PlainText, false) => "-".to_string(),
+                };
+                let marker = match item.get_attribute("checked").map(String::as_str) {
+                    Some("true") => "☒ ",
+                    Some(_) => "☐ ",
+                    None => "",
+                };
+                format!("{} {}{}", bullet, marker, self.inlines(&item.children))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Tables become tab-separated rows in plain text, so they paste into
+    /// spreadsheets; Slack has no tables and gets `|`-separated rows
+    fn table(&self, table: &Node) -> String {
+        table
+            .children
+            .iter()
+            .flat_map(|child| match child.node_type {
+                NodeType::TableHead => child.children.iter().map(|row| (row, true)).collect(),
+                _ => vec![(child, false)],
+            })
+            .map(|(row, header)| {
+                let cells: Vec<String> = row
+                    .children
+                    .iter()
+                    .map(|cell| self.inlines(&cell.children))
+                    .collect();
+                match self.markup {
+                    Markup::PlainText => cells.join("\t"),
+                    Markup::Slack if header => format!("*{}*", cells.join(" | ")),
+                    Markup::Slack => cells.join(" | "),
+                    Markup::Jira if header => format!("||{}||", cells.join("||")),
+                    Markup::Jira => format!("|{}|", cells.join("|")),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn inlines(&self, nodes: &[Node]) -> String {
+        nodes.iter().map(|node| self.inline(node)).collect()
+    }
+
+    fn inline(&self, node: &Node) -> String {
+        match node.node_type {
+            NodeType::Text => self.escape(&node.data),
+            NodeType::Strong => self.wrap(node, "*", "*"),
+            NodeType::Emph => self.wrap(node, "_", "_"),
+            NodeType::Strikethrough => self.wrap(node, "~", "-"),
+            NodeType::Code | NodeType::InlineMath => {
+                let code = node.text_content();
+                match self.markup {
+                    Markup::PlainText => code,
+                    Markup::Slack => format!("`{}`", code),
+                    Markup::Jira => format!("{{{{{}}}}}", code),
+                }
+            }
+            NodeType::SoftBreak => " ".to_string(),
+            NodeType::LineBreak => "\n".to_string(),
+            NodeType::Link => {
+                let href = (self.resolve)(node.get_attribute("href").map_or("", String::as_str));
+                let text = self.inlines(&node.children);
+                self.link(&href, &text)
+            }
+            NodeType::Image => {
+                let src = (self.resolve)(node.get_attribute("src").map_or("", String::as_str));
+                let alt = node.get_attribute("alt").cloned().unwrap_or_default();
+                match self.markup {
+                    Markup::Jira => format!("!{}!", src),
+                    _ => self.link(&src, &self.escape(&alt)),
+                }
+            }
+            NodeType::TaskListItemMarker => String::new(),
+            _ => self.inlines(&node.children),
+        }
+    }
+
+    /// Emphasis marked with `slack` or `jira` around it
+    fn wrap(&self, node: &Node, slack: &str, jira: &str) -> String {
+        let text = self.inlines(&node.children);
+        match self.markup {
+            Markup::PlainText => text,
+            Markup::Slack => format!("{0}{1}{0}", slack, text),
+            Markup::Jira => format!("{0}{1}{0}", jira, text),
+        }
+    }
+
+    fn link(&self, href: &str, text: &str) -> String {
+        if href.is_empty() {
+            return text.to_string();
+        }
+        match self.markup {
+            Markup::PlainText if text.is_empty() || text == href => href.to_string(),
+            Markup::PlainText => format!("{} ({})", text, href),
+            Markup::Slack if text.is_empty() => format!("<{}>", href),
+            Markup::Slack => format!("<{}|{}>", href, text),
+            Markup::Jira if text.is_empty() => format!("[{}]", href),
+            Markup::Jira => format!("[{}|{}]", text, href),
+        }
+    }
+
+    /// Slack needs `&`, `<` and `>` escaped in message text
+    fn escape(&self, text: &str) -> String {
+        match self.markup {
+            Markup::Slack => text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;"),
+            _ => text.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(href: &str) -> String {
+        if href.contains("://") {
+            href.to_string()
+        } else {
+            format!("http://localhost:3000/{}", href.trim_start_matches('/'))
+        }
+    }
+
+    #[test]
+    fn test_write_markup_flavors() {
+        let markdown = "# Plan\n\nShip **now** with `cargo` and [docs](guide.md) & <tea>\n\n\
+                        1. one\n2. two\n\nThen\n\n- [x] done\n\n| A | B |\n|---|---|\n| 1 | 2 |\n\n\
+                        ```rust\nfn main() {}\n```\n";
+
+        let text = write_markup(markdown, Markup::PlainText, &resolve);
+        assert!(text.starts_with(
+            "Plan\n\nShip now with cargo and docs (http://localhost:3000/guide.md) & <tea>\n"
+        ));
+        assert!(text.contains("1. one\n2. two"));
+        assert!(text.contains("- ☒ done"));
+        assert!(text.contains("A\tB\n1\t2"));
+        assert!(text.contains("\n\nfn main() {}\n"));
+
+        let slack = write_markup(markdown, Markup::Slack, &resolve);
+        assert!(slack.starts_with("*Plan*\n\nShip *now* with `cargo` and "));
+        assert!(slack.contains("<http://localhost:3000/guide.md|docs> &amp; &lt;tea&gt;"));
+        assert!(slack.contains("```\nfn main() {}\n```"));
+
+        let jira = write_markup(markdown, Markup::Jira, &resolve);
+        assert!(jira.starts_with(
+            "h1. Plan\n\nShip *now* with {{cargo}} and [docs|http://localhost:3000/guide.md]"
+        ));
+        assert!(jira.contains("# one\n# two"));
+        assert!(jira.contains("||A||B||\n|1|2|"));
+        assert!(jira.contains("{code:rust}\nfn main() {}\n{code}"));
+
+        let markdown = "---\ntitle: Plan\ntags: [a]\n---\n# Plan\n";
+        for markup in Markup::all() {
+            let written = write_markup(markdown, *markup, &resolve);
+            assert!(!written.contains("title"), "{}: {}", markup, written);
+            assert!(!written.contains("---"), "{}: {}", markup, written);
+        }
+        assert_eq!(
+            write_markup(markdown, Markup::PlainText, &resolve),
+            "Plan\n"
+        );
+
+        assert_eq!("slack".parse::<Markup>().unwrap(), Markup::Slack);
+        assert!("rtf".parse::<Markup>().is_err());
+    }
+}
@@ -0,0 +1,270 @@
+//! Incremental export for watch mode
+//!
+//! An [`ExportSession`] remembers, for every exported document, a hash of its
+//! content and the local files embedded into its output. When the file watcher
+//! reports changes only the affected documents are exported again, and saves
+//! that leave a document's content untouched are skipped.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::{content_hash, document_dir, ExportOptions, ExportReport, Exporter};
+use crate::ast::{Node, NodeType};
+use crate::error::{Result, RuneError};
+use crate::parser::MarkdownParser;
+
+/// Result of building one document
+#[derive(Debug)]
+pub enum BuildStatus {
+    Exported(ExportReport),
+    /// Content and dependencies are unchanged since the last export
+    Unchanged,
+    Failed(RuneError),
+}
+
+/// Outcome of building one document, with the wall time it took
+#[derive(Debug)]
+pub struct BuildOutcome {
+    pub input: PathBuf,
+    pub status: BuildStatus,
+    pub elapsed: Duration,
+}
+
+/// State remembered about an exported document
+#[derive(Debug, Default)]
+struct TrackedDocument {
+    content_hash: Option<String>,
+    dependencies: HashSet<PathBuf>,
+}
+
+/// Set of documents exported together and rebuilt as their sources change
+pub struct ExportSession {
+    exporter: Arc<Exporter>,
+    options: ExportOptions,
+    documents: HashMap<PathBuf, TrackedDocument>,
+}
+
+impl ExportSession {
+    /// Create a session for the given markdown files
+    pub fn new(
+        exporter: Arc<Exporter>,
+        options: ExportOptions,
+        inputs: &[PathBuf],
+    ) -> Result<Self> {
+        let mut documents = HashMap::new();
+        for input in inputs {
+            let input = input.canonicalize().map_err(|e| {
+                RuneError::file_system(format!("Cannot watch {}: {}", input.display(), e))
+            })?;
+            documents.insert(input, TrackedDocument::default());
+        }
+
+        Ok(Self {
+            exporter,
+            options,
+            documents,
+        })
+    }
+
+    /// Documents of the session, sorted by path
+    pub fn documents(&self) -> Vec<&Path> {
+        let mut documents: Vec<&Path> = self.documents.keys().map(PathBuf::as_path).collect();
+        documents.sort();
+        documents
+    }
+
+    /// Directories that contain documents or their dependencies
+    pub fn watch_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = self
+            .documents
+            .iter()
+            .flat_map(|(input, document)| std::iter::once(input).chain(&document.dependencies))
+            .filter_map(|path| path.parent().map(Path::to_path_buf))
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+
+    /// Export every document of the session
+    pub async fn build_all(&mut self) -> Vec<BuildOutcome> {
+        let mut outcomes = Vec::new();
+        for input in self.sorted_inputs(|_, _| true) {
+            outcomes.push(self.build(&input, true).await);
+        }
+        outcomes
+    }
+
+    /// Re-export the documents affected by the changed paths
+    pub async fn rebuild_changed(&mut self, changed: &[PathBuf]) -> Vec<BuildOutcome> {
+        // Deleted files cannot be canonicalized; compare them as reported
+        let changed: HashSet<PathBuf> = changed
+            .iter()
+            .map(|path| path.canonicalize().unwrap_or_else(|_| path.clone()))
+            .collect();
+
+        let mut outcomes = Vec::new();
+        for input in self.sorted_inputs(|input, document| {
+            changed.contains(input) || !document.dependencies.is_disjoint(&changed)
+        }) {
+            let dependency_changed = !self.documents[&input].dependencies.is_disjoint(&changed);
+            outcomes.push(self.build(&input, dependency_changed).await);
+        }
+        outcomes
+    }
+
+    fn sorted_inputs(&self, filter: impl Fn(&Path, &TrackedDocument) -> bool) -> Vec<PathBuf> {
+        let mut inputs: Vec<PathBuf> = self
+            .documents
+            .iter()
+            .filter(|(input, document)| filter(input, document))
+            .map(|(input, _)| input.clone())
+            .collect();
+        inputs.sort();
+        inputs
+    }
+
+    /// Export one document unless its content is unchanged and `force` is not set
+    async fn build(&mut self, input: &Path, force: bool) -> BuildOutcome {
+        let start = Instant::now();
+        let status = match crate::crypto::read_document(input) {
+            Ok(content) => {
+                let hash = content_hash(&[&content]);
+                let document = self.documents.entry(input.to_path_buf()).or_default();
+                if !force && document.content_hash.as_deref() == Some(hash.as_str()) {
+                    BuildStatus::Unchanged
+                } else {
+                    match self.exporter.export(input, &self.options).await {
+                        Ok(report) => {
+                            let document = self.documents.entry(input.to_path_buf()).or_default();
+                            document.content_hash = Some(hash);
+                            document.dependencies = if self.options.embeds_assets() {
+                                local_dependencies(&content, &document_dir(input))
+                            } else {
+                                HashSet::new()
+                            };
+                            BuildStatus::Exported(report)
+                        }
+                        Err(e) => BuildStatus::Failed(e),
+                    }
+                }
+            }
+            Err(e) => BuildStatus::Failed(e),
+        };
+
+        BuildOutcome {
+            input: input.to_path_buf(),
+            status,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+/// Local files a document embeds when exported (currently its images)
+pub fn local_dependencies(content: &str, base_dir: &Path) -> HashSet<PathBuf> {
+    fn collect(node: &Node, base_dir: &Path, dependencies: &mut HashSet<PathBuf>) {
+        if node.node_type == NodeType::Image {
+            let src = node.get_attribute("src").map(String::as_str).unwrap_or("");
+            let is_local = !src.is_empty()
+                && !src.starts_with("data:")
+                && !src.contains("://")
+                && !src.starts_with("//");
+            if is_local {
+                let src = src.split(['?', '#']).next().unwrap_or(src);
+                if let Ok(path) = base_dir.join(src.trim_start_matches('/')).canonicalize() {
+                    dependencies.insert(path);
+                }
+            }
+        }
+        for child in &node.children {
+            collect(child, base_dir, dependencies);
+        }
+    }
+
+    let tree = MarkdownParser::new().parse(content);
+    let mut dependencies = HashSet::new();
+    collect(&tree.root, base_dir, &mut dependencies);
+    dependencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ExportFormat;
+    use crate::renderer::RendererRegistry;
+    use tempfile::TempDir;
+
+    fn session(dir: &Path, inputs: &[&str]) -> ExportSession {
+        let exporter = Arc::new(Exporter::new(Arc::new(RendererRegistry::new())));
+        let options = ExportOptions {
+            format: ExportFormat::Epub,
+            ..Default::default()
+        };
+        let inputs: Vec<PathBuf> = inputs.iter().map(|name| dir.join(name)).collect();
+        ExportSession::new(exporter, options, &inputs).unwrap()
+    }
+
+    fn exported(outcomes: &[BuildOutcome]) -> Vec<String> {
+        outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome.status, BuildStatus::Exported(_)))
+            .map(|outcome| {
+                outcome
+                    .input
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_local_dependencies() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a.png"), b"png").unwrap();
+
+        let dependencies = local_dependencies(
+            "![A](a.png) ![Missing](b.png) ![Web](https://x.io/c.png)",
+            temp_dir.path(),
+        );
+        assert_eq!(dependencies.len(), 1);
+        assert!(dependencies.contains(&temp_dir.path().join("a.png").canonicalize().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_rebuilds_only_affected_documents() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("logo.png"), b"png").unwrap();
+        std::fs::write(dir.join("one.md"), "# One\n\n![Logo](logo.png)\n").unwrap();
+        std::fs::write(dir.join("two.md"), "# Two\n").unwrap();
+
+        let mut session = session(dir, &["one.md", "two.md"]);
+        assert_eq!(
+            exported(&session.build_all().await),
+            vec!["one.md", "two.md"]
+        );
+        assert!(dir.join("two.epub").exists());
+
+        // Saving without changes is skipped
+        let outcomes = session.rebuild_changed(&[dir.join("two.md")]).await;
+        assert!(matches!(outcomes[0].status, BuildStatus::Unchanged));
+
+        std::fs::write(dir.join("two.md"), "# Two\n\nMore\n").unwrap();
+        let outcomes = session.rebuild_changed(&[dir.join("two.md")]).await;
+        assert_eq!(exported(&outcomes), vec!["two.md"]);
+
+        // An embedded image change re-exports the document using it
+        let outcomes = session.rebuild_changed(&[dir.join("logo.png")]).await;
+        assert_eq!(exported(&outcomes), vec!["one.md"]);
+
+        // Unrelated files do not trigger anything
+        assert!(session
+            .rebuild_changed(&[dir.join("one.epub")])
+            .await
+            .is_empty());
+    }
+}
@@ -142,6 +142,24 @@ pub fn glob_match(pattern: &str, text: &str) -> bool {
     pattern == text
 }
 
+/// Statistics about file watching activity
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatchStatistics {
+    pub watched_path_count: usize,
+    pub pending_events_count: usize,
+    pub total_events_processed: u64,
+    pub watched_paths: Vec<PathBuf>,
+}
+
+/// Live source of watcher statistics
+///
+/// Watcher plugins share one as the `file_watcher_statistics` resource so other
+/// plugins can report on watching activity without knowing the watcher type.
+#[async_trait]
+pub trait WatchStatisticsProvider: Send + Sync {
+    async fn watch_statistics(&self) -> WatchStatistics;
+}
+
 /// File watcher trait extending Plugin interface
 #[async_trait]
 pub trait FileWatcher: Plugin {
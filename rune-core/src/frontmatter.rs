@@ -0,0 +1,170 @@
+//! Simple values from the YAML frontmatter of a document
+//!
+//! Documents may open with a `---` fenced YAML block. The few keys rune reads
+//! from it (`draft`, `publish_at`, `aliases`, `dir`, `lang`, `glossary`,
+//! `numbering`, `tags`, `title`) are flat, so this reads top-level `key: value` lines rather
+//! than parsing YAML. Keys start at the beginning of a line, so keys of nested mappings
+//! are never taken for top-level ones; spaces before the colon are allowed.
+//! Values are trimmed and lose one layer of surrounding quotes.
+
+/// Lines of the frontmatter opening `markdown`, without the fences and
+/// trailing whitespace; none when there is no frontmatter
+pub fn lines(markdown: &str) -> impl Iterator<Item = &str> + '_ {
+    markdown
+        .strip_prefix("---\n")
+        .or_else(|| markdown.strip_prefix("---\r\n"))
+        .into_iter()
+        .flat_map(str::lines)
+        .map(str::trim_end)
+        .take_while(|line| *line != "---" && *line != "...")
+}
+
+/// Top-level `(key, value)` pairs of the frontmatter, values unquoted
+pub fn entries(markdown: &str) -> impl Iterator<Item = (&str, &str)> + '_ {
+    lines(markdown).filter_map(|line| {
+        if line.starts_with(|c: char| c.is_whitespace() || c == '-' || c == '#') {
+            return None;
+        }
+        let (key, value) = line.split_once(':')?;
+        Some((key.trim_end(), unquote(value)))
+    })
+}
+
+/// Value of the top-level `key`, unquoted
+pub fn value<'a>(markdown: &'a str, key: &str) -> Option<&'a str> {
+    entries(markdown).find_map(|(name, value)| (name == key).then_some(value))
+}
+
+/// Items of the list under `key`
+///
+/// Both `key: [a, b]` and a block list of `- a` items are read; a single
+/// `key: a` is a list of one. Empty items are dropped.
+pub fn list(markdown: &str, key: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut in_list = false;
+    for line in lines(markdown) {
+        if in_list {
+            if let Some(item) = line.trim_start().strip_prefix("- ") {
+                items.push(unquote(item).to_string());
+                continue;
+            }
+            in_list = false;
+        }
+        let Some(value) = line
+            .split_once(':')
+            .filter(|(name, _)| name.trim_end() == key && !line.starts_with(char::is_whitespace))
+            .map(|(_, value)| value.trim())
+        else {
+            continue;
+        };
+        if value.is_empty() {
+            in_list = true;
+        } else if let Some(flow) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            items.extend(flow.split(',').map(|item| unquote(item).to_string()));
+        } else {
+            items.push(unquote(value).to_string());
+        }
+    }
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+/// Whether `markdown` opens with a closed `---` or `+++` block
+///
+/// Only then may the markdown crate's frontmatter construct be enabled: it
+/// panics on some documents whose block is never closed.
+pub fn is_closed(markdown: &str) -> bool {
+    let mut lines = markdown.lines().map(str::trim_end);
+    match lines.next() {
+        Some(fence @ ("---" | "+++")) => lines.any(|line| line == fence),
+        _ => false,
+    }
+}
+
+/// `markdown` split into its frontmatter block, fences and line ending
+/// included, and the rest; the block is empty unless it is closed
+pub fn split(markdown: &str) -> (&str, &str) {
+    let mut lines = markdown.split_inclusive('\n');
+    let Some(first) = lines.next() else {
+        return ("", markdown);
+    };
+    let fence = first.trim_end();
+    if fence != "---" && fence != "+++" {
+        return ("", markdown);
+    }
+    let mut end = first.len();
+    for line in lines {
+        end += line.len();
+        let line = line.trim_end();
+        if line == fence || (fence == "---" && line == "...") {
+            return markdown.split_at(end);
+        }
+    }
+    ("", markdown)
+}
+
+/// `value` trimmed and without one layer of surrounding quotes
+pub fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| {
+            value
+                .strip_prefix(*quote)
+                .and_then(|inner| inner.strip_suffix(*quote))
+        })
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_of_top_level_keys() {
+        let markdown = "---\r\ntitle: \"Notes\"\ndraft : yes\nmeta:\n  lang: fr\nlang: 'en'\n---\nlang: body\n";
+        assert_eq!(value(markdown, "title"), Some("Notes"));
+        assert_eq!(value(markdown, "draft"), Some("yes"));
+        // The nested key and the body are not frontmatter keys
+        assert_eq!(value(markdown, "lang"), Some("en"));
+        assert_eq!(value(markdown, "missing"), None);
+        assert_eq!(value("title: no fences\n", "title"), None);
+        assert_eq!(unquote(" '\"quoted\"' "), "\"quoted\"");
+    }
+
+    #[test]
+    fn test_lists_in_every_form() {
+        let flow = "---\naliases: [old.md, \"older.md\", ]\n---\n";
+        assert_eq!(list(flow, "aliases"), ["old.md", "older.md"]);
+        let block = "---\naliases:\n  - old.md\n  - 'notes/older.md'\ntitle: x\n- stray\n---\n";
+        assert_eq!(list(block, "aliases"), ["old.md", "notes/older.md"]);
+        assert_eq!(list("---\naliases: old.md\n---\n", "aliases"), ["old.md"]);
+        assert!(list("# No frontmatter\n", "aliases").is_empty());
+    }
+
+    #[test]
+    fn test_blocks_must_be_closed_by_their_own_fence() {
+        assert!(is_closed("---\ntitle: x\n---\n# Body\n"));
+        assert!(is_closed("+++ \r\ntitle = 'x'\r\n+++\r\n"));
+        assert!(!is_closed("---\ntitle: x\n...\n"));
+        assert!(!is_closed("---\n1. a\r\n[^1]: b"));
+        assert!(!is_closed("---"));
+        assert!(!is_closed("# ---\n---\n"));
+    }
+
+    #[test]
+    fn test_split_keeps_the_block_as_written() {
+        let markdown = "---\r\ntitle: x\r\n---\r\n# Body\n";
+        assert_eq!(split(markdown), ("---\r\ntitle: x\r\n---\r\n", "# Body\n"));
+        assert_eq!(
+            split("---\ntitle: x\n...\nText"),
+            ("---\ntitle: x\n...\n", "Text")
+        );
+        assert_eq!(
+            split("+++\ntitle = 'x'\n+++"),
+            ("+++\ntitle = 'x'\n+++", "")
+        );
+        assert_eq!(split("---\ntitle: x\n"), ("", "---\ntitle: x\n"));
+        assert_eq!(split("# Body\n---\n"), ("", "# Body\n---\n"));
+    }
+}
@@ -0,0 +1,367 @@
+//! Glossary of terms explained where they appear
+//!
+//! Terms are defined with the abbreviation syntax of PHP Markdown Extra, one
+//! per line:
+//!
+//! ```markdown
+//! *[HTML]: HyperText Markup Language
+//! ```
+//!
+//! The nearest [`GLOSSARY_FILE`] in the directory of a document or one of
+//! its parents, up to the repository root, holds the terms of the
+//! workspace; a document may define more of its own, which are not rendered
+//! as text. Rendered documents wrap the terms in `<abbr>` elements whose
+//! title is the definition, unless their frontmatter has `glossary: false`.
+//! HTML exports write an index page of all terms, see [`Glossary::index_html`].
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::error::Result;
+use crate::export::escape_html;
+use crate::frontmatter;
+
+/// Name of the file holding the terms of a workspace
+pub const GLOSSARY_FILE: &str = "glossary.md";
+
+/// Name of the index page HTML exports write next to the documents
+pub const INDEX_PAGE: &str = "glossary.html";
+
+/// Elements whose text never has terms marked up
+const SKIPPED_ELEMENTS: &[&str] = &["a", "abbr", "code", "kbd", "pre", "script", "style"];
+
+/// Terms and their definitions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Glossary {
+    terms: BTreeMap<String, String>,
+}
+
+impl Glossary {
+    /// Terms defined in `markdown`, outside code blocks
+    pub fn parse(markdown: &str) -> Self {
+        let terms = outside_fences(markdown)
+            .filter_map(definition)
+            .map(|(term, text)| (term.to_string(), text.to_string()))
+            .collect();
+        Self { terms }
+    }
+
+    /// Terms of a glossary file
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::parse(&crate::crypto::read_document(path)?))
+    }
+
+    /// Terms applying to the document at `path` with content `markdown`:
+    /// those of its workspace's glossary file, and its own, which win
+    pub fn for_document(path: &Path, markdown: &str) -> Self {
+        let mut glossary = find_file(path)
+            .and_then(|file| match Self::load(&file) {
+                Ok(glossary) => Some(glossary),
+                Err(e) => {
+                    tracing::warn!("Failed to read glossary {}: {}", file.display(), e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        glossary.merge(Self::parse(markdown));
+        glossary
+    }
+
+    /// Add the terms of `other`, replacing definitions of the same terms
+    pub fn merge(&mut self, other: Glossary) {
+        self.terms.extend(other.terms);
+    }
+
+    /// Whether no terms are defined
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Number of terms
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    /// Definition of `term`
+    pub fn definition(&self, term: &str) -> Option<&str> {
+        self.terms.get(term).map(String::as_str)
+    }
+
+    /// Wrap the terms in the text of rendered `html` in `<abbr>` elements,
+    /// returning the new HTML and the number of terms marked up
+    pub fn expand(&self, html: &str) -> (String, usize) {
+        if self.terms.is_empty() {
+            return (html.to_string(), 0);
+        }
+
+        // Terms as they appear in escaped text, longest first so that the
+        // longer of two overlapping terms wins
+        let mut escaped: Vec<(String, &str)> = self
+            .terms
+            .iter()
+            .map(|(term, text)| (escape_html(term), text.as_str()))
+            .collect();
+        escaped.sort_by_key(|(term, _)| std::cmp::Reverse(term.len()));
+        let pattern = escaped
+            .iter()
+            .map(|(term, _)| regex::escape(term))
+            .collect::<Vec<_>>()
+            .join("|");
+        let Ok(terms) = Regex::new(&pattern) else {
+            return (html.to_string(), 0);
+        };
+        let definitions: BTreeMap<&str, &str> = escaped
+            .iter()
+            .map(|(term, text)| (term.as_str(), *text))
+            .collect();
+
+        let mut output = String::with_capacity(html.len());
+        let mut marked = 0;
+        let mut skipped = 0usize;
+        let mut rest = html;
+        loop {
+            let text_end = rest.find('<').unwrap_or(rest.len());
+            let (text, tail) = rest.split_at(text_end);
+            if skipped == 0 {
+                marked += mark_terms(text, &terms, &definitions, &mut output);
+            } else {
+                output.push_str(text);
+            }
+            if tail.is_empty() {
+                break;
+            }
+
+            let tag_end = if tail.starts_with("<!--") {
+                tail.find("-->").map(|end| end + 3)
+            } else {
+                tail.find('>').map(|end| end + 1)
+            }
+            .unwrap_or(tail.len());
+            let tag = &tail[..tag_end];
+            if let Some((name, closing)) = tag_name(tag) {
+                if SKIPPED_ELEMENTS.contains(&name.as_str()) {
+                    if closing {
+                        skipped = skipped.saturating_sub(1);
+                    } else if !tag.ends_with("/>") {
+                        skipped += 1;
+                    }
+                }
+            }
+            output.push_str(tag);
+            rest = &tail[tag_end..];
+        }
+        (output, marked)
+    }
+
+    /// Body of the index page listing the terms alphabetically
+    pub fn index_html(&self) -> String {
+        let mut terms: Vec<(&String, &String)> = self.terms.iter().collect();
+        terms.sort_by_cached_key(|(term, _)| term.to_lowercase());
+
+        let mut html = String::from("<h1>Glossary</h1>\n<dl class=\"rune-glossary-index\">\n");
+        for (term, text) in terms {
+            html.push_str(&format!(
+                "<dt id=\"{}\">{}</dt>\n<dd>{}</dd>\n",
+                crate::slugify(term, Default::default()),
+                escape_html(term),
+                escape_html(text)
+            ));
+        }
+        html.push_str("</dl>\n");
+        html
+    }
+}
+
+/// Whether the document `markdown` has its terms marked up
+pub fn enabled(markdown: &str) -> bool {
+    frontmatter::value(markdown, "glossary") != Some("false")
+}
+
+/// Whether `path` is a glossary file
+pub fn is_glossary_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.eq_ignore_ascii_case(GLOSSARY_FILE))
+}
+
+/// Nearest glossary file of the document at `path`, looking no further up
+/// than the repository root
+pub fn find_file(path: &Path) -> Option<PathBuf> {
+    for dir in path.parent()?.ancestors() {
+        let file = dir.join(GLOSSARY_FILE);
+        if file.is_file() {
+            return Some(file);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// `markdown` with its term definitions blanked out, so they are not
+/// rendered; lines stay where they are
+pub fn strip_definitions(markdown: &str) -> Cow<'_, str> {
+    if !outside_fences(markdown).any(|line| definition(line).is_some()) {
+        return Cow::Borrowed(markdown);
+    }
+    let mut in_fence = None;
+    let stripped: Vec<&str> = markdown
+        .split_inclusive('\n')
+        .map(|line| {
+            if toggles_fence(line, &mut in_fence) || in_fence.is_some() {
+                return line;
+            }
+            match definition(line) {
+                Some(_) if line.ends_with("\r\n") => "\r\n",
+                Some(_) if line.ends_with('\n') => "\n",
+                Some(_) => "",
+                None => line,
+            }
+        })
+        .collect();
+    Cow::Owned(stripped.concat())
+}
+
+/// Term and definition of a `*[term]: definition` line
+fn definition(line: &str) -> Option<(&str, &str)> {
+    let (term, text) = line.trim_end().strip_prefix("*[")?.split_once("]:")?;
+    let (term, text) = (term.trim(), text.trim());
+    (!term.is_empty() && !text.is_empty()).then_some((term, text))
+}
+
+/// Lines of `markdown` outside fenced code blocks
+pub(crate) fn outside_fences(markdown: &str) -> impl Iterator<Item = &str> + '_ {
+    let mut in_fence = None;
+    markdown
+        .lines()
+        .filter(move |line| !toggles_fence(line, &mut in_fence) && in_fence.is_none())
+}
+
+/// Whether `line` opens or closes a fenced code block, tracking the fence
+/// of the open block in `in_fence`
+fn toggles_fence(line: &str, in_fence: &mut Option<String>) -> bool {
+    let trimmed = line.trim();
+    let fence: String = trimmed
+        .chars()
+        .take_while(|c| *c == '`' || *c == '~')
+        .collect();
+    if fence.len() < 3 || !fence.chars().all(|c| c == fence.chars().next().unwrap()) {
+        return false;
+    }
+    match in_fence {
+        Some(open) if trimmed == fence && fence.starts_with(open.as_str()) => {
+            *in_fence = None;
+            true
+        }
+        Some(_) => false,
+        None => {
+            *in_fence = Some(fence);
+            true
+        }
+    }
+}
+
+/// Lowercase name of an HTML tag and whether it closes an element
+fn tag_name(tag: &str) -> Option<(String, bool)> {
+    let inner = tag.strip_prefix('<')?;
+    let (inner, closing) = match inner.strip_prefix('/') {
+        Some(inner) => (inner, true),
+        None => (inner, false),
+    };
+    let name: String = inner
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+    (!name.is_empty()).then(|| (name.to_ascii_lowercase(), closing))
+}
+
+/// Write `text` to `output` with the whole-word matches of `terms` marked
+/// up, returning their number
+fn mark_terms(
+    text: &str,
+    terms: &Regex,
+    definitions: &BTreeMap<&str, &str>,
+    output: &mut String,
+) -> usize {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut marked = 0;
+    let mut written = 0;
+    for found in terms.find_iter(text) {
+        let before = text[..found.start()].chars().next_back();
+        let after = text[found.end()..].chars().next();
+        if before.is_some_and(is_word) || after.is_some_and(is_word) {
+            continue;
+        }
+        let Some(definition) = definitions.get(found.as_str()) else {
+            continue;
+        };
+        output.push_str(&text[written..found.start()]);
+        output.push_str(&format!(
+            "<abbr class=\"rune-glossary\" title=\"{}\">{}</abbr>",
+            escape_html(definition),
+            found.as_str()
+        ));
+        written = found.end();
+        marked += 1;
+    }
+    output.push_str(&text[written..]);
+    marked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terms_are_marked_up_outside_code_and_links() {
+        let markdown = "Uses HTML & CSS.\n\n*[HTML]: HyperText Markup Language\n*[HTML5]: HTML, version 5\n\n```\n*[CSS]: not a definition\n```\n";
+        let glossary = Glossary::parse(markdown);
+        assert_eq!(glossary.len(), 2);
+        assert_eq!(glossary.definition("CSS"), None);
+        assert_eq!(
+            strip_definitions(markdown),
+            "Uses HTML & CSS.\n\n\n\n\n```\n*[CSS]: not a definition\n```\n"
+        );
+
+        let (html, marked) = glossary.expand(
+            "<p>HTML5 is HTML, not XHTML.</p>\n<pre><code>HTML</code></pre>\n<p><a href=\"/\">HTML</a></p>",
+        );
+        assert_eq!(marked, 2);
+        assert_eq!(
+            html,
+            "<p><abbr class=\"rune-glossary\" title=\"HTML, version 5\">HTML5</abbr> is <abbr class=\"rune-glossary\" title=\"HyperText Markup Language\">HTML</abbr>, not XHTML.</p>\n<pre><code>HTML</code></pre>\n<p><a href=\"/\">HTML</a></p>"
+        );
+
+        assert!(!enabled("---\nglossary: false\n---\nHTML"));
+        assert!(enabled("HTML"));
+    }
+
+    #[test]
+    fn test_document_terms_join_the_workspace_glossary() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join(GLOSSARY_FILE),
+            "# Terms\n\n*[API]: Application Programming Interface\n*[CLI]: Command line\n",
+        )
+        .unwrap();
+        let docs = temp_dir.path().join("docs");
+        std::fs::create_dir(&docs).unwrap();
+        let document = docs.join("guide.md");
+
+        let glossary = Glossary::for_document(&document, "*[CLI]: Command-line interface\n");
+        assert_eq!(
+            find_file(&document),
+            Some(temp_dir.path().join(GLOSSARY_FILE))
+        );
+        assert_eq!(glossary.definition("CLI"), Some("Command-line interface"));
+        assert_eq!(
+            glossary.index_html(),
+            "<h1>Glossary</h1>\n<dl class=\"rune-glossary-index\">\n\
+             <dt id=\"api\">API</dt>\n<dd>Application Programming Interface</dd>\n\
+             <dt id=\"cli\">CLI</dt>\n<dd>Command-line interface</dd>\n</dl>\n"
+        );
+    }
+}
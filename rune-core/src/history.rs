@@ -0,0 +1,300 @@
+//! Version history of saved documents
+//!
+//! Every save of a document appends a [`VersionEntry`] to its log in the
+//! `.rune/history/` directory next to it. The saved content goes into a blob
+//! named after its SHA-1 hash, so identical versions share storage. Logs are
+//! JSON lines under `versions/<file name>.jsonl`, blobs live in `blobs/`.
+//! [`side_by_side`] lines up two versions for display.
+
+use crate::error::{Result, RuneError};
+use crate::rune_dir::RuneDir;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One saved version of a document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VersionEntry {
+    /// Save time in milliseconds since the Unix epoch
+    pub timestamp: u64,
+    /// SHA-1 of the content, naming its blob
+    pub hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Content size in bytes
+    pub size: usize,
+}
+
+/// On-disk version history of the documents in one directory
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    root: PathBuf,
+}
+
+impl HistoryStore {
+    /// Create a store rooted at the given directory
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Create the store used for a markdown document (`<dir>/.rune/history`)
+    pub fn for_document(markdown_file: &Path) -> Self {
+        let dir = markdown_file.parent().unwrap_or_else(|| Path::new("."));
+        Self::new(RuneDir::of(dir).history_dir())
+    }
+
+    /// Record a save of `markdown_file` in its directory's store
+    pub fn record_save(
+        markdown_file: &Path,
+        content: &str,
+        message: Option<String>,
+    ) -> Result<VersionEntry> {
+        let file_name = markdown_file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| {
+                RuneError::file_system(format!("Not a file: {}", markdown_file.display()))
+            })?;
+        Self::for_document(markdown_file).record(&file_name, content, message)
+    }
+
+    /// Get the root directory of the store
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Append a version of `file_name` with the given content
+    pub fn record(
+        &self,
+        file_name: &str,
+        content: &str,
+        message: Option<String>,
+    ) -> Result<VersionEntry> {
+        let log = self.log_path(file_name)?;
+        let hash = content_hash(content);
+
+        let blob = self.root.join("blobs").join(&hash);
+        if !blob.exists() {
+            fs::create_dir_all(self.root.join("blobs"))?;
+            // Written aside first, so a blob is never seen half written
+            let partial = blob.with_extension("partial");
+            fs::write(&partial, content)?;
+            fs::rename(&partial, &blob)?;
+        }
+
+        let entry = VersionEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            hash,
+            message: message.filter(|message| !message.trim().is_empty()),
+            size: content.len(),
+        };
+
+        fs::create_dir_all(self.root.join("versions"))?;
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log)?
+            .write_all(line.as_bytes())?;
+
+        tracing::debug!("Recorded version {} of {}", entry.hash, file_name);
+        Ok(entry)
+    }
+
+    /// Versions of `file_name`, oldest first
+    pub fn versions(&self, file_name: &str) -> Result<Vec<VersionEntry>> {
+        let content = match fs::read_to_string(self.log_path(file_name)?) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable version of {}: {}", file_name, e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Content of the version with the given hash
+    pub fn content(&self, hash: &str) -> Result<Option<String>> {
+        if hash.len() != 40 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(None);
+        }
+
+        match fs::read_to_string(self.root.join("blobs").join(hash)) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Log of a file, rejecting names that would leave the store
+    fn log_path(&self, file_name: &str) -> Result<PathBuf> {
+        if file_name.is_empty() || file_name.starts_with('.') || file_name.contains(['/', '\\']) {
+            return Err(RuneError::file_system(format!(
+                "Invalid document name: {}",
+                file_name
+            )));
+        }
+        Ok(self
+            .root
+            .join("versions")
+            .join(format!("{}.jsonl", file_name)))
+    }
+}
+
+/// SHA-1 of some content, as lowercase hex
+pub fn content_hash(content: impl AsRef<[u8]>) -> String {
+    Sha1::digest(content.as_ref())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// How a row of a side-by-side diff changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    Unchanged,
+    Changed,
+    Removed,
+    Added,
+}
+
+/// An item of the old version next to an item of the new one, with their
+/// 1-based positions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffRow<T> {
+    pub kind: DiffKind,
+    pub old: Option<(usize, T)>,
+    pub new: Option<(usize, T)>,
+}
+
+/// Line up two versions, split into lines or blocks, pairing removed items
+/// with the items added in their place
+pub fn side_by_side<T: PartialEq + Clone>(old: &[T], new: &[T]) -> Vec<DiffRow<T>> {
+    let mut rows = Vec::new();
+    let (mut old_index, mut new_index) = (0, 0);
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+
+    fn flush<T>(
+        rows: &mut Vec<DiffRow<T>>,
+        removed: &mut Vec<(usize, T)>,
+        added: &mut Vec<(usize, T)>,
+    ) {
+        let paired = removed.len().max(added.len());
+        let mut removed = removed.drain(..);
+        let mut added = added.drain(..);
+        for _ in 0..paired {
+            let (old, new) = (removed.next(), added.next());
+            let kind = match (&old, &new) {
+                (Some(_), Some(_)) => DiffKind::Changed,
+                (Some(_), None) => DiffKind::Removed,
+                _ => DiffKind::Added,
+            };
+            rows.push(DiffRow { kind, old, new });
+        }
+    }
+
+    for item in diff::slice(old, new) {
+        match item {
+            diff::Result::Left(item) => {
+                old_index += 1;
+                removed.push((old_index, item.clone()));
+            }
+            diff::Result::Right(item) => {
+                new_index += 1;
+                added.push((new_index, item.clone()));
+            }
+            diff::Result::Both(item, _) => {
+                flush(&mut rows, &mut removed, &mut added);
+                old_index += 1;
+                new_index += 1;
+                rows.push(DiffRow {
+                    kind: DiffKind::Unchanged,
+                    old: Some((old_index, item.clone())),
+                    new: Some((new_index, item.clone())),
+                });
+            }
+        }
+    }
+    flush(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_history_records_content_addressed_versions() {
+        let temp_dir = TempDir::new().unwrap();
+        let document = temp_dir.path().join("notes.md");
+
+        let first = HistoryStore::record_save(&document, "# Notes\n", None).unwrap();
+        let second =
+            HistoryStore::record_save(&document, "# Notes\n\nMore.\n", Some("Add more".into()))
+                .unwrap();
+        let third = HistoryStore::record_save(&document, "# Notes\n", Some(" ".into())).unwrap();
+
+        let store = HistoryStore::for_document(&document);
+        assert_eq!(store.root(), temp_dir.path().join(".rune/history"));
+        assert_eq!(
+            store.versions("notes.md").unwrap(),
+            vec![first.clone(), second.clone(), third.clone()]
+        );
+        assert_eq!(first.hash, third.hash);
+        assert_eq!(first.hash, "3029c96dd0b8ecd69fc7c41c3f44460dcb298b9f");
+        assert_eq!(second.message.as_deref(), Some("Add more"));
+        assert_eq!(third.message, None);
+        assert_eq!(fs::read_dir(store.root().join("blobs")).unwrap().count(), 2);
+        assert_eq!(
+            store.content(&second.hash).unwrap().as_deref(),
+            Some("# Notes\n\nMore.\n")
+        );
+
+        assert!(store.versions("other.md").unwrap().is_empty());
+        assert!(store.versions("../notes.md").is_err());
+        assert_eq!(store.content("../../notes.md").unwrap(), None);
+    }
+
+    #[test]
+    fn test_side_by_side_pairs_replaced_lines() {
+        let lines = |text: &'static str| text.lines().collect::<Vec<_>>();
+        let rows = side_by_side(&lines("a\nb\nc\nd\n"), &lines("a\nB\nc\nd\ne\n"));
+        let kinds: Vec<_> = rows.iter().map(|row| row.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DiffKind::Unchanged,
+                DiffKind::Changed,
+                DiffKind::Unchanged,
+                DiffKind::Unchanged,
+                DiffKind::Added,
+            ]
+        );
+        assert_eq!(rows[1].old, Some((2, "b")));
+        assert_eq!(rows[1].new, Some((2, "B")));
+        assert_eq!(rows[4].old, None);
+        assert_eq!(rows[4].new, Some((5, "e")));
+
+        let rows = side_by_side(&lines("a\nb\n"), &lines("a\n"));
+        assert_eq!(rows[1].kind, DiffKind::Removed);
+    }
+}
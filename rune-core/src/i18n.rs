@@ -0,0 +1,249 @@
+//! Translations of command line messages and web interface labels
+//!
+//! Messages are [Fluent](https://projectfluent.org) resources built into the
+//! binary, one per locale under `rune-core/locales`. The locale comes from
+//! the `--lang` flag or the `lang` setting, then from the environment
+//! (`LC_ALL`, `LC_MESSAGES`, `LANG`), and falls back to [`DEFAULT_LOCALE`];
+//! messages missing from a translation fall back to it as well.
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::FluentResource;
+use fluent_langneg::{negotiate_languages, NegotiationStrategy};
+use regex::{Captures, Regex};
+use std::sync::{Arc, OnceLock, RwLock};
+use tracing::warn;
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::{FluentArgs, FluentValue};
+
+/// Locale of the messages every translation falls back to
+pub const DEFAULT_LOCALE: &str = "en-US";
+
+/// Built-in translations by locale
+pub const LOCALES: &[(&str, &str)] = &[
+    ("en-US", include_str!("../locales/en-US/rune.ftl")),
+    ("zh-CN", include_str!("../locales/zh-CN/rune.ftl")),
+];
+
+/// Translation catalog of one locale
+pub struct Catalog {
+    locale: String,
+    /// Bundles of the locale and its fallbacks, most specific first
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+impl std::fmt::Debug for Catalog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Catalog")
+            .field("locale", &self.locale)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Catalog {
+    /// Catalog of the built-in locale best matching `requested`
+    pub fn new(requested: &str) -> Self {
+        let default: LanguageIdentifier = DEFAULT_LOCALE.parse().expect("valid default locale");
+        let available: Vec<LanguageIdentifier> = LOCALES
+            .iter()
+            .filter_map(|(locale, _)| locale.parse().ok())
+            .collect();
+        let requested: Vec<LanguageIdentifier> = normalize_locale(requested)
+            .and_then(|locale| locale.parse().ok())
+            .into_iter()
+            .collect();
+
+        let mut chain: Vec<LanguageIdentifier> = negotiate_languages(
+            &requested,
+            &available,
+            Some(&default),
+            NegotiationStrategy::Filtering,
+        )
+        .into_iter()
+        .cloned()
+        .collect();
+        if !chain.contains(&default) {
+            chain.push(default);
+        }
+
+        let bundles = chain
+            .iter()
+            .filter_map(|locale| {
+                let id = locale.to_string();
+                let (_, source) = LOCALES.iter().find(|(name, _)| *name == id)?;
+                Some(Self::bundle(locale.clone(), source))
+            })
+            .collect();
+
+        Self {
+            locale: chain[0].to_string(),
+            bundles,
+        }
+    }
+
+    fn bundle(locale: LanguageIdentifier, source: &str) -> FluentBundle<FluentResource> {
+        let resource =
+            FluentResource::try_new(source.to_string()).unwrap_or_else(|(resource, errors)| {
+                warn!("Errors in the {} translation: {:?}", locale, errors);
+                resource
+            });
+        let mut bundle = FluentBundle::new_concurrent(vec![locale]);
+        // Isolation marks would show up as garbage in terminals
+        bundle.set_use_isolating(false);
+        if let Err(errors) = bundle.add_resource(resource) {
+            warn!("Duplicate messages in a translation: {:?}", errors);
+        }
+        bundle
+    }
+
+    /// Locale of the catalog, e.g. `en-US`
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Message `id` formatted with `args`, if any translation has it
+    pub fn get(&self, id: &str, args: Option<&FluentArgs>) -> Option<String> {
+        self.bundles.iter().find_map(|bundle| {
+            let pattern = bundle.get_message(id)?.value()?;
+            let mut errors = Vec::new();
+            let message = bundle.format_pattern(pattern, args, &mut errors);
+            if !errors.is_empty() {
+                warn!("Errors formatting message '{}': {:?}", id, errors);
+            }
+            Some(message.into_owned())
+        })
+    }
+
+    /// Message `id` formatted with `args`; the id itself when no translation has it
+    pub fn format(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        self.get(id, args).unwrap_or_else(|| id.to_string())
+    }
+}
+
+/// Locale identifier of a POSIX locale name such as `zh_CN.UTF-8`; `None`
+/// for the `C` and `POSIX` locales
+fn normalize_locale(locale: &str) -> Option<String> {
+    let locale = locale.split(['.', '@']).next().unwrap_or(locale).trim();
+    match locale {
+        "" | "C" | "POSIX" => None,
+        _ => Some(locale.replace('_', "-")),
+    }
+}
+
+/// Locale to use: `configured` if given, otherwise the environment's
+pub fn detect_locale(configured: Option<&str>) -> String {
+    configured
+        .and_then(normalize_locale)
+        .or_else(|| {
+            ["LC_ALL", "LC_MESSAGES", "LANG"]
+                .iter()
+                .filter_map(|name| std::env::var(name).ok())
+                .find_map(|value| normalize_locale(&value))
+        })
+        .unwrap_or_else(|| DEFAULT_LOCALE.to_string())
+}
+
+static CATALOG: RwLock<Option<Arc<Catalog>>> = RwLock::new(None);
+
+/// Use the catalog best matching `locale` from now on
+pub fn set_locale(locale: &str) {
+    let catalog = Arc::new(Catalog::new(locale));
+    *CATALOG.write().unwrap_or_else(|e| e.into_inner()) = Some(catalog);
+}
+
+/// Catalog in use, of the environment's locale until [`set_locale`] is called
+pub fn catalog() -> Arc<Catalog> {
+    if let Some(catalog) = CATALOG.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return catalog.clone();
+    }
+    let mut current = CATALOG.write().unwrap_or_else(|e| e.into_inner());
+    current
+        .get_or_insert_with(|| Arc::new(Catalog::new(&detect_locale(None))))
+        .clone()
+}
+
+/// Fill the `{t:message-id}` placeholders of a page template with HTML
+/// escaped messages, and `{LANG}` with the catalog's locale
+pub fn localize_template(template: &str, catalog: &Catalog) -> String {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    let placeholder = PLACEHOLDER
+        .get_or_init(|| Regex::new(r"\{t:([A-Za-z0-9-]+)\}").expect("valid placeholder pattern"));
+
+    placeholder
+        .replace_all(template, |caps: &Captures| {
+            crate::export::escape_html(&catalog.format(&caps[1], None))
+        })
+        .replace("{LANG}", catalog.locale())
+}
+
+/// Message of the current catalog
+///
+/// ```
+/// let title = rune_core::t!("startup-title");
+/// let file = rune_core::t!("startup-file", path = "README.md");
+/// ```
+#[macro_export]
+macro_rules! t {
+    ($id:expr) => {
+        $crate::i18n::catalog().format($id, None)
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = $crate::i18n::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::catalog().format($id, Some(&args))
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn message_ids(source: &str) -> BTreeSet<String> {
+        let resource = FluentResource::try_new(source.to_string())
+            .unwrap_or_else(|(_, errors)| panic!("translation does not parse: {:?}", errors));
+        resource
+            .entries()
+            .filter_map(|entry| match entry {
+                fluent_syntax::ast::Entry::Message(message) => Some(message.id.name.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_translations_cover_default_messages() {
+        let (_, default) = LOCALES[0];
+        let expected = message_ids(default);
+        for (locale, source) in LOCALES {
+            assert_eq!(message_ids(source), expected, "messages of {}", locale);
+        }
+    }
+
+    #[test]
+    fn test_catalog_negotiates_and_formats() {
+        assert_eq!(normalize_locale("zh_CN.UTF-8").as_deref(), Some("zh-CN"));
+        assert_eq!(normalize_locale("C"), None);
+        assert_eq!(detect_locale(Some("zh_TW")), "zh-TW");
+
+        assert_eq!(Catalog::new("zh-TW").locale(), "zh-CN");
+        assert_eq!(Catalog::new("de-DE").locale(), DEFAULT_LOCALE);
+        assert_eq!(Catalog::new("not a locale").locale(), DEFAULT_LOCALE);
+
+        let catalog = Catalog::new("en");
+        let mut args = FluentArgs::new();
+        args.set("count", 1);
+        assert_eq!(
+            catalog.format("themes-count", Some(&args)),
+            "📊 1 theme available"
+        );
+        assert_eq!(catalog.format("no-such-message", None), "no-such-message");
+        assert_eq!(
+            localize_template(
+                r#"<html lang="{LANG}"><b>{t:ui-save}</b>"#,
+                &Catalog::new("zh")
+            ),
+            r#"<html lang="zh-CN"><b>保存</b>"#
+        );
+    }
+}
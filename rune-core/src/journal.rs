@@ -0,0 +1,588 @@
+//! Daily notes
+//!
+//! `rune journal` and `POST /api/journal` open the note of a day, creating
+//! it from the configured template when it does not exist yet. Notes live
+//! at a date pattern below the journal directory, [`DEFAULT_PATH`] unless
+//! the `journal` section of the configuration says otherwise, see
+//! [`JournalConfig`]. Rendered notes link to the nearest earlier and later
+//! day that has a note, see [`Journal::navigation_html`].
+//!
+//! Templates may use `{{date}}` (`2024-05-01`), `{{title}}` (`Wednesday, 1
+//! May 2024`), `{{weekday}}`, `{{yesterday}}` and `{{tomorrow}}`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use regex::Regex;
+
+use crate::config::JournalConfig;
+use crate::error::{Result, RuneError};
+use crate::export::escape_html;
+use crate::time::{civil_from_days, days_from_civil, parse_utc_offset};
+
+/// Path of a day's note below the journal directory, unless configured
+pub const DEFAULT_PATH: &str = "journal/%Y/%Y-%m-%d.md";
+
+/// What a new note starts with when no template is configured
+pub const DEFAULT_TEMPLATE: &str = "# {{title}}\n\n";
+
+/// Days looked through for the previous or next note
+const NEIGHBOUR_DAYS: i64 = 366;
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// A calendar day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// The day, if it exists
+    pub fn new(year: i64, month: u32, day: u32) -> Option<Self> {
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        // Days past the end of the month roll over into the next one
+        let date = Self { year, month, day };
+        (Self::from_days(date.days()) == date).then_some(date)
+    }
+
+    /// Today, for a clock `utc_offset` seconds ahead of UTC
+    pub fn today(utc_offset: i64) -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or_default();
+        Self::from_days((seconds + utc_offset).div_euclid(86_400))
+    }
+
+    fn from_days(days: i64) -> Self {
+        let (year, month, day) = civil_from_days(days);
+        Self {
+            year,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    fn days(self) -> i64 {
+        days_from_civil(self.year, i64::from(self.month), i64::from(self.day))
+    }
+
+    /// The day `days` after this one, or before it when negative
+    pub fn add_days(self, days: i64) -> Self {
+        Self::from_days(self.days() + days)
+    }
+
+    /// Name of the day of the week, e.g. `Monday`
+    pub fn weekday(self) -> &'static str {
+        // The epoch was a Thursday
+        WEEKDAYS[(self.days() + 3).rem_euclid(7) as usize]
+    }
+
+    /// Name of the month, e.g. `May`
+    pub fn month_name(self) -> &'static str {
+        MONTHS[self.month as usize - 1]
+    }
+
+    /// The day written out, e.g. `Wednesday, 1 May 2024`
+    pub fn title(self) -> String {
+        format!(
+            "{}, {} {} {}",
+            self.weekday(),
+            self.day,
+            self.month_name(),
+            self.year
+        )
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = RuneError;
+
+    /// Parse `2024-05-01`
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || RuneError::config(format!("'{}' is not a date like 2024-05-01", value));
+        let mut parts = value.trim().splitn(3, '-');
+        let mut field = || parts.next().and_then(|part| part.parse::<i64>().ok());
+        let (year, month, day) = (
+            field().ok_or_else(invalid)?,
+            field().ok_or_else(invalid)?,
+            field().ok_or_else(invalid)?,
+        );
+        let month = u32::try_from(month).map_err(|_| invalid())?;
+        let day = u32::try_from(day).map_err(|_| invalid())?;
+        Self::new(year, month, day).ok_or_else(invalid)
+    }
+}
+
+/// Part of a date a path pattern spells out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Year,
+    Month,
+    MonthName,
+    Day,
+    Weekday,
+}
+
+/// Piece of a path pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Date(Field),
+}
+
+/// Path of a day's note, like `journal/%Y/%Y-%m-%d.md`
+///
+/// `%Y` is the year, `%m` the month and `%d` the day as numbers, `%B` the
+/// name of the month, `%A` the name of the weekday and `%%` a percent sign.
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    pieces: Vec<Piece>,
+    /// Matches the paths of notes, with a group for each date piece
+    regex: Regex,
+}
+
+impl PathPattern {
+    /// Parse a pattern, which must name the year, month and day and end in
+    /// a markdown extension
+    pub fn new(pattern: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            RuneError::config(format!("Journal path '{}' is invalid: {}", pattern, reason))
+        };
+
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                text.push(c);
+                continue;
+            }
+            let field = match chars.next() {
+                Some('%') => {
+                    text.push('%');
+                    continue;
+                }
+                Some('Y') => Field::Year,
+                Some('m') => Field::Month,
+                Some('B') => Field::MonthName,
+                Some('d') => Field::Day,
+                Some('A') => Field::Weekday,
+                Some(other) => return Err(invalid(&format!("unknown field %{}", other))),
+                None => return Err(invalid("it ends in a lone %")),
+            };
+            if !text.is_empty() {
+                pieces.push(Piece::Text(std::mem::take(&mut text)));
+            }
+            pieces.push(Piece::Date(field));
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+
+        let has = |fields: &[Field]| {
+            pieces
+                .iter()
+                .any(|piece| matches!(piece, Piece::Date(field) if fields.contains(field)))
+        };
+        if !has(&[Field::Year]) || !has(&[Field::Month, Field::MonthName]) || !has(&[Field::Day]) {
+            return Err(invalid("it must contain %Y, %m or %B, and %d"));
+        }
+        let lower = pattern.to_lowercase();
+        if !lower.ends_with(".md") && !lower.ends_with(".markdown") {
+            return Err(invalid("notes must be .md or .markdown files"));
+        }
+        let path = Path::new(pattern);
+        if path.is_absolute()
+            || path
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(invalid("it must be a relative path without '..'"));
+        }
+
+        let regex: String = pieces
+            .iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => regex::escape(text),
+                Piece::Date(Field::Year) => r"(\d{4})".to_string(),
+                Piece::Date(Field::Month | Field::Day) => r"(\d{2})".to_string(),
+                Piece::Date(Field::MonthName) => format!("({})", MONTHS.join("|")),
+                Piece::Date(Field::Weekday) => format!("({})", WEEKDAYS.join("|")),
+            })
+            .collect();
+        let regex = Regex::new(&format!("^{}$", regex)).map_err(|e| invalid(&e.to_string()))?;
+        Ok(Self { pieces, regex })
+    }
+
+    /// Relative path of the note of `date`
+    pub fn format(&self, date: Date) -> String {
+        self.pieces
+            .iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => text.clone(),
+                Piece::Date(Field::Year) => format!("{:04}", date.year),
+                Piece::Date(Field::Month) => format!("{:02}", date.month),
+                Piece::Date(Field::MonthName) => date.month_name().to_string(),
+                Piece::Date(Field::Day) => format!("{:02}", date.day),
+                Piece::Date(Field::Weekday) => date.weekday().to_string(),
+            })
+            .collect()
+    }
+
+    /// Day whose note is at the relative path `path`, written with `/`
+    pub fn date_of(&self, path: &str) -> Option<Date> {
+        let captures = self.regex.captures(path)?;
+        let fields = self.pieces.iter().filter_map(|piece| match piece {
+            Piece::Date(field) => Some(*field),
+            Piece::Text(_) => None,
+        });
+        let (mut year, mut month, mut day) = (None, None, None);
+        for (field, value) in fields.zip(captures.iter().skip(1)) {
+            let value = value?.as_str();
+            let (slot, parsed) = match field {
+                Field::Year => (&mut year, value.parse().ok()?),
+                Field::Month => (&mut month, value.parse().ok()?),
+                Field::MonthName => (
+                    &mut month,
+                    MONTHS.iter().position(|name| *name == value)? as i64 + 1,
+                ),
+                Field::Day => (&mut day, value.parse().ok()?),
+                Field::Weekday => continue,
+            };
+            // A field given twice must say the same both times
+            if slot.is_some_and(|known| known != parsed) {
+                return None;
+            }
+            *slot = Some(parsed);
+        }
+        let date = Date::new(
+            year?,
+            u32::try_from(month?).ok()?,
+            u32::try_from(day?).ok()?,
+        )?;
+        // A weekday, if spelled out, must be the one of the date
+        (self.format(date) == path).then_some(date)
+    }
+}
+
+/// A journal of daily notes
+#[derive(Debug, Clone)]
+pub struct Journal {
+    dir: PathBuf,
+    pattern: PathPattern,
+    template: Option<PathBuf>,
+    utc_offset: i64,
+}
+
+impl Journal {
+    /// Journal described by the configuration
+    pub fn from_config(config: &JournalConfig) -> Result<Self> {
+        let dir = match &config.dir {
+            Some(dir) => match dir.strip_prefix("~") {
+                Ok(rest) => dirs::home_dir()
+                    .ok_or_else(|| RuneError::config("Cannot find the home directory"))?
+                    .join(rest),
+                Err(_) => dir.clone(),
+            },
+            None => PathBuf::from("."),
+        };
+        let dir = dir.canonicalize().or_else(|_| std::path::absolute(&dir))?;
+        let pattern = PathPattern::new(config.path.as_deref().unwrap_or(DEFAULT_PATH))?;
+        let utc_offset = match &config.utc_offset {
+            Some(offset) => parse_utc_offset(offset).ok_or_else(|| {
+                RuneError::config(format!(
+                    "Journal utc_offset '{}' is not an offset from UTC like +02:00",
+                    offset
+                ))
+            })?,
+            None => 0,
+        };
+        Ok(Self {
+            template: config.template.as_ref().map(|template| dir.join(template)),
+            dir,
+            pattern,
+            utc_offset,
+        })
+    }
+
+    /// Directory the notes are kept in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Today, by the configured offset from UTC
+    pub fn today(&self) -> Date {
+        Date::today(self.utc_offset)
+    }
+
+    /// Day named `today`, `yesterday`, `tomorrow` or like `2024-05-01`
+    pub fn date(&self, name: &str) -> Result<Date> {
+        match name.trim().to_lowercase().as_str() {
+            "today" => Ok(self.today()),
+            "yesterday" => Ok(self.today().add_days(-1)),
+            "tomorrow" => Ok(self.today().add_days(1)),
+            _ => name.parse(),
+        }
+    }
+
+    /// Path of the note of `date`, whether or not it exists
+    pub fn note_path(&self, date: Date) -> PathBuf {
+        self.dir.join(self.pattern.format(date))
+    }
+
+    /// Day whose note is at `path`, if it is one
+    pub fn date_of(&self, path: &Path) -> Option<Date> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let relative = path.strip_prefix(&self.dir).ok()?;
+        self.pattern
+            .date_of(&relative.to_string_lossy().replace('\\', "/"))
+    }
+
+    /// Note of `date`, created from the template if it does not exist yet;
+    /// also returns whether it was created
+    pub fn open(&self, date: Date) -> Result<(PathBuf, bool)> {
+        let path = self.note_path(date);
+        if path.exists() {
+            return Ok((path, false));
+        }
+
+        let template = match &self.template {
+            Some(template) => std::fs::read_to_string(template).map_err(|e| {
+                RuneError::FileSystem(format!(
+                    "Failed to read journal template {}: {}",
+                    template.display(),
+                    e
+                ))
+            })?,
+            None => DEFAULT_TEMPLATE.to_string(),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Another process may have created the note in the meantime
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                file.write_all(fill_template(&template, date).as_bytes())?;
+                Ok((path, true))
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok((path, false)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Nearest earlier day with a note, within a year
+    pub fn previous(&self, date: Date) -> Option<(Date, PathBuf)> {
+        self.neighbour(date, -1)
+    }
+
+    /// Nearest later day with a note, within a year
+    pub fn next(&self, date: Date) -> Option<(Date, PathBuf)> {
+        self.neighbour(date, 1)
+    }
+
+    fn neighbour(&self, date: Date, step: i64) -> Option<(Date, PathBuf)> {
+        (1..=NEIGHBOUR_DAYS)
+            .map(|days| date.add_days(days * step))
+            .map(|date| (date, self.note_path(date)))
+            .find(|(_, path)| path.is_file())
+    }
+
+    /// Links from the note at `path` to the previous and next notes, or
+    /// `None` when it is not a note or has no neighbours
+    pub fn navigation_html(&self, path: &Path) -> Option<String> {
+        let date = self.date_of(path)?;
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let from = path.parent()?;
+        let link = |neighbour: Option<(Date, PathBuf)>, class: &str, rel: &str| {
+            neighbour.map(|(date, note)| {
+                let label = escape_html(&date.title());
+                let label = if rel == "prev" {
+                    format!("← {}", label)
+                } else {
+                    format!("{} →", label)
+                };
+                format!(
+                    "<a class=\"{}\" rel=\"{}\" href=\"{}\">{}</a>",
+                    class,
+                    rel,
+                    escape_html(&relative_href(from, &note)),
+                    label
+                )
+            })
+        };
+        let links: Vec<String> = [
+            link(self.previous(date), "rune-journal-prev", "prev"),
+            link(self.next(date), "rune-journal-next", "next"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if links.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "<nav class=\"rune-journal-nav\" aria-label=\"Journal\">\n{}\n</nav>\n",
+            links.join("\n")
+        ))
+    }
+}
+
+/// A new note of `date` from `template`
+pub fn fill_template(template: &str, date: Date) -> String {
+    let variables = BTreeMap::from([
+        ("date".to_string(), date.to_string()),
+        ("title".to_string(), date.title()),
+        ("weekday".to_string(), date.weekday().to_string()),
+        ("yesterday".to_string(), date.add_days(-1).to_string()),
+        ("tomorrow".to_string(), date.add_days(1).to_string()),
+        ("cursor".to_string(), String::new()),
+    ]);
+    crate::templates::fill(template, &variables)
+}
+
+/// Link from a page in the directory `from` to the file `to`
+fn relative_href(from: &Path, to: &Path) -> String {
+    let from: Vec<_> = from.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from
+        .iter()
+        .zip(&to)
+        .take_while(|(from, to)| from == to)
+        .count();
+    std::iter::repeat_n("..".to_string(), from.len() - common)
+        .chain(
+            to[common..]
+                .iter()
+                .map(|component| component.as_os_str().to_string_lossy().into_owned()),
+        )
+        .map(|segment| {
+            segment
+                .replace('%', "%25")
+                .replace(' ', "%20")
+                .replace('#', "%23")
+                .replace('?', "%3F")
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dates_and_path_patterns() {
+        let date: Date = "2024-02-29".parse().unwrap();
+        assert_eq!(date.weekday(), "Thursday");
+        assert_eq!(date.title(), "Thursday, 29 February 2024");
+        assert_eq!(date.add_days(1).to_string(), "2024-03-01");
+        assert_eq!(date.add_days(-60).to_string(), "2023-12-31");
+        assert!("2023-02-29".parse::<Date>().is_err());
+        assert!("yesterday".parse::<Date>().is_err());
+
+        let pattern = PathPattern::new(DEFAULT_PATH).unwrap();
+        assert_eq!(pattern.format(date), "journal/2024/2024-02-29.md");
+        assert_eq!(pattern.date_of("journal/2024/2024-02-29.md"), Some(date));
+        // The year of the directory and of the name disagree
+        assert_eq!(pattern.date_of("journal/2023/2024-02-29.md"), None);
+        assert_eq!(pattern.date_of("journal/2024/notes.md"), None);
+
+        let named = PathPattern::new("%Y/%B/%d %A.md").unwrap();
+        assert_eq!(named.format(date), "2024/February/29 Thursday.md");
+        assert_eq!(named.date_of("2024/February/29 Thursday.md"), Some(date));
+        assert_eq!(named.date_of("2024/February/29 Monday.md"), None);
+
+        assert!(PathPattern::new("%Y-%m.md").is_err());
+        assert!(PathPattern::new("%Y-%m-%d.txt").is_err());
+        assert!(PathPattern::new("../%Y-%m-%d.md").is_err());
+        assert!(PathPattern::new("%Y-%m-%d-%H.md").is_err());
+    }
+
+    #[test]
+    fn test_notes_are_created_from_the_template_and_linked() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("daily.md"),
+            "# {{title}}\n\nAfter [{{yesterday}}](#)\n",
+        )
+        .unwrap();
+        let journal = Journal::from_config(&JournalConfig {
+            dir: Some(temp_dir.path().to_path_buf()),
+            template: Some(PathBuf::from("daily.md")),
+            ..JournalConfig::default()
+        })
+        .unwrap();
+
+        let new_year: Date = "2025-01-01".parse().unwrap();
+        let (note, created) = journal.open(new_year).unwrap();
+        assert!(created);
+        assert_eq!(note, journal.dir().join("journal/2025/2025-01-01.md"));
+        assert_eq!(
+            std::fs::read_to_string(&note).unwrap(),
+            "# Wednesday, 1 January 2025\n\nAfter [2024-12-31](#)\n"
+        );
+        std::fs::write(&note, "kept").unwrap();
+        assert_eq!(journal.open(new_year).unwrap(), (note.clone(), false));
+        assert_eq!(std::fs::read_to_string(&note).unwrap(), "kept");
+
+        assert_eq!(journal.navigation_html(&note), None);
+        let (earlier, _) = journal.open("2024-12-28".parse().unwrap()).unwrap();
+        let nav = journal.navigation_html(&note).unwrap();
+        assert!(nav.contains(
+            "<a class=\"rune-journal-prev\" rel=\"prev\" \
+             href=\"../2024/2024-12-28.md\">← Saturday, 28 December 2024</a>"
+        ));
+        assert!(!nav.contains("rune-journal-next"));
+        assert!(journal
+            .navigation_html(&earlier)
+            .unwrap()
+            .contains("href=\"../2025/2025-01-01.md\""));
+        assert_eq!(
+            journal.navigation_html(&journal.dir().join("daily.md")),
+            None
+        );
+    }
+}
@@ -4,16 +4,51 @@
 //! that powers the modular Rune markdown editor.
 
 pub mod ast;
+pub mod chaos;
+pub mod command;
 pub mod config;
+pub mod convert;
+pub mod crash;
+pub mod crypto;
+pub mod drafts;
 pub mod error;
 pub mod event;
+pub mod export;
 pub mod file_watcher;
+pub mod frontmatter;
+pub mod glossary;
+pub mod history;
+pub mod i18n;
+pub mod journal;
+pub mod logging;
+pub mod memory;
+pub mod notification;
+pub mod pandoc;
 pub mod parser;
 pub mod plugin;
+pub mod plugin_data;
+pub mod process;
+pub mod profile;
 pub mod quill;
+pub mod redirects;
 pub mod render;
 pub mod renderer;
+pub mod rune_dir;
+pub mod safe_write;
+pub mod schedule;
+pub mod search;
+pub mod serializer;
+pub mod slug;
 pub mod state;
+pub mod supervisor;
+pub mod tags;
+pub mod telemetry;
+pub mod templates;
+pub mod text_encoding;
+pub mod time;
+pub mod trace;
+pub mod transform;
+pub mod trust;
 
 #[cfg(test)]
 mod event_test;
@@ -27,24 +62,41 @@ mod plugin_context_test;
 // Re-export commonly used types
 pub use ast::{Node, NodeType, ParseOptions, Position, Tree, WalkStatus};
 pub use config::{
-    Config, ConfigLoadContext, ConfigMetadata, PluginConfig, RuntimeConfigManager, ServerConfig,
-    SystemConfig, ValidationResult,
+    ChaosConfig, Config, ConfigLoadContext, ConfigMetadata, ConfigProfile, CorsConfig,
+    EncryptionConfig, ExportAssetConfig, ExportHook, ExportHookAction, ExportHooks, JournalConfig,
+    LargeFileConfig, LinkPreviewConfig, LogConfig, MemoryConfig, PluginConfig, PluginProfile,
+    ProcessConfig, RuntimeConfigManager, ServerConfig, ServerProfile, SystemConfig,
+    TelemetryConfig, ValidationResult,
 };
 pub use error::{Result, RuneError};
 pub use event::{
     Event, EventBus, EventFilter, EventHandler, ExtendedEventBus, InMemoryEventBus, SubscriptionId,
     SystemEvent, SystemEventHandler,
 };
-pub use file_watcher::{DefaultFileFilter, FileFilter, FileWatcher, FileWatcherConfig, WatcherId};
+pub use export::{ExportFormat, ExportOptions, ExportReport, Exporter};
+pub use file_watcher::{
+    DefaultFileFilter, FileFilter, FileWatcher, FileWatcherConfig, WatchStatistics,
+    WatchStatisticsProvider, WatcherId,
+};
+pub use memory::{EvictionPriority, MemoryBudget, MemoryConsumer, MemoryReport, MemoryUsage};
+pub use notification::{Notification, NotificationAction, NotificationLevel};
 pub use parser::MarkdownParser;
-pub use plugin::{Plugin, PluginContext, PluginInfo, PluginRegistry, PluginStatus};
+pub use plugin::{
+    Plugin, PluginContext, PluginInfo, PluginRegistry, PluginStatus, ServiceReadiness,
+};
 pub use quill::Quill;
+pub use redirects::RedirectRule;
 pub use render::{render_html, render_wysiwyg, HtmlRenderer, RenderOptions, WysiwygRenderer};
 pub use renderer::{
-    Asset, AssetType, ContentRenderer, RenderContext, RenderMetadata, RenderResult,
-    RendererRegistry,
+    Asset, AssetType, ContentRenderer, RenderContext, RenderMetadata, RenderResult, RenderStream,
+    RendererRegistry, NONCE_PLACEHOLDER,
 };
-pub use state::{ApplicationState, StateManager};
+pub use slug::{slugify, SlugStyle, Slugger};
+pub use state::{ApplicationState, RenderCacheMemory, ServedRoot, StateManager};
+pub use supervisor::{
+    DrainReport, RestartOn, RestartPolicy, TaskState, TaskStatus, TaskSupervisor,
+};
+pub use text_encoding::{LineEnding, TextEncoding};
 
 // CoreEngine is defined in this module, no need to re-export
 
@@ -59,6 +111,9 @@ pub struct CoreEngine {
     plugin_registry: PluginRegistry,
     state_manager: Arc<StateManager>,
     config: Arc<Config>,
+    supervisor: Arc<TaskSupervisor>,
+    memory: Arc<MemoryBudget>,
+    scheduler: Arc<schedule::PublishScheduler>,
     is_initialized: bool,
     shutdown_signal: Option<tokio::sync::oneshot::Sender<()>>,
 }
@@ -66,15 +121,37 @@ pub struct CoreEngine {
 impl CoreEngine {
     /// Create a new CoreEngine instance
     pub fn new(config: Config) -> Result<Self> {
-        let event_bus = Arc::new(event::InMemoryEventBus::new());
+        // External programs run within the process settings
+        process::configure(&config.processes);
+
+        // Faults injected in development mode, per the chaos settings
+        let chaos = chaos::ChaosInjector::from_config(&config);
+        let mut event_bus = event::InMemoryEventBus::new();
+        if let Some(chaos) = &chaos {
+            event_bus = event_bus.with_chaos(chaos.clone());
+        }
+        let event_bus = Arc::new(event_bus);
         let state_manager = Arc::new(StateManager::new());
         let plugin_registry = PluginRegistry::new();
+        let mut supervisor = TaskSupervisor::new(event_bus.clone());
+        if let Some(chaos) = chaos {
+            supervisor = supervisor.with_chaos(chaos);
+        }
+        let supervisor = Arc::new(supervisor);
+        let memory = Arc::new(MemoryBudget::new(config.memory.budget_bytes()));
+        let scheduler = Arc::new(schedule::PublishScheduler::new(
+            event_bus.clone(),
+            state_manager.clone(),
+        ));
 
         Ok(Self {
             event_bus,
             plugin_registry,
             state_manager,
             config: Arc::new(config),
+            supervisor,
+            memory,
+            scheduler,
             is_initialized: false,
             shutdown_signal: None,
         })
@@ -103,7 +180,9 @@ impl CoreEngine {
             self.event_bus.clone(),
             self.config.clone(),
             self.state_manager.clone(),
-        );
+        )
+        .with_supervisor(self.supervisor.clone())
+        .with_memory_budget(self.memory.clone());
 
         // Initialize plugin registry with enhanced error handling
         match self.plugin_registry.initialize(context.clone()).await {
@@ -140,6 +219,28 @@ impl CoreEngine {
             );
         }
 
+        // Keep caches and buffers plugins registered within the memory budget
+        self.memory
+            .register(Arc::new(RenderCacheMemory::new(self.state_manager.clone())))
+            .await;
+        let memory = self.memory.clone();
+        let interval = self.config.memory.check_interval();
+        self.supervisor
+            .supervise(memory::MEMORY_TASK, RestartPolicy::default(), move || {
+                memory.clone().run(interval)
+            })
+            .await?;
+
+        // Publish documents scheduled for later once their time has come
+        let scheduler = self.scheduler.clone();
+        self.supervisor
+            .supervise(
+                schedule::PUBLISH_TASK,
+                RestartPolicy::default(),
+                move || scheduler.clone().run(),
+            )
+            .await?;
+
         self.is_initialized = true;
         tracing::info!(
             "Core Engine initialized successfully with {} active plugins",
@@ -257,6 +358,18 @@ impl CoreEngine {
         self.plugin_registry.register_plugin(plugin, context).await
     }
 
+    /// Register plugins in the order their dependencies and required
+    /// services call for
+    pub async fn register_plugins(
+        &mut self,
+        plugins: Vec<Box<dyn Plugin>>,
+        context: &PluginContext,
+    ) -> Result<()> {
+        self.plugin_registry
+            .register_plugins(plugins, context)
+            .await
+    }
+
     /// Get the plugin context for external plugin registration
     pub fn create_plugin_context(&self) -> PluginContext {
         PluginContext::new(
@@ -264,6 +377,8 @@ impl CoreEngine {
             self.config.clone(),
             self.state_manager.clone(),
         )
+        .with_supervisor(self.supervisor.clone())
+        .with_memory_budget(self.memory.clone())
     }
 
     /// Load plugins specified in configuration
@@ -609,6 +724,21 @@ impl CoreEngine {
                 }
             }
         }
+        for drain in &shutdown_report.drained {
+            tracing::info!(
+                "Drained '{}' in {:?}: {} completed, {} interrupted, {} connections closed{}",
+                drain.task,
+                drain.elapsed,
+                drain.completed,
+                drain.interrupted,
+                drain.closed_connections,
+                if drain.finished {
+                    ""
+                } else {
+                    " (aborted at the deadline)"
+                }
+            );
+        }
 
         Ok(())
     }
@@ -727,6 +857,9 @@ impl CoreEngine {
     async fn cleanup_system_resources(&mut self) -> Result<()> {
         tracing::debug!("Cleaning up system resources");
 
+        // Stop supervised tasks that outlived their plugins
+        self.supervisor.shutdown().await;
+
         // Clear any remaining event bus subscriptions
         // This would be implemented in the event bus
 
@@ -758,6 +891,7 @@ impl CoreEngine {
             force_stopped: shutdown_result.force_stopped.clone(),
             timed_out: shutdown_result.timed_out,
             registry_error: shutdown_result.registry_error.clone(),
+            drained: self.supervisor.drain_reports().await,
         }
     }
 
@@ -766,6 +900,16 @@ impl CoreEngine {
         self.event_bus.clone()
     }
 
+    /// Get the supervisor of critical background tasks
+    pub fn supervisor(&self) -> Arc<TaskSupervisor> {
+        self.supervisor.clone()
+    }
+
+    /// Get the memory budget caches and buffers are accounted against
+    pub fn memory_budget(&self) -> Arc<MemoryBudget> {
+        self.memory.clone()
+    }
+
     /// Get a reference to the plugin registry
     pub fn plugin_registry(&self) -> &PluginRegistry {
         &self.plugin_registry
@@ -807,13 +951,16 @@ impl CoreEngine {
 
         // Update configuration
         self.config = Arc::new(new_config);
+        self.memory.set_limit(self.config.memory.budget_bytes());
 
         // Create new context with updated config
         let context = PluginContext::new(
             self.event_bus.clone(),
             self.config.clone(),
             self.state_manager.clone(),
-        );
+        )
+        .with_supervisor(self.supervisor.clone())
+        .with_memory_budget(self.memory.clone());
 
         // Reload plugin configurations
         context.reload_configurations().await?;
@@ -830,6 +977,7 @@ impl CoreEngine {
         self.state_manager
             .set_current_file(Some(file_path.clone()))
             .await;
+        self.scheduler.rescan();
 
         // Publish the file change event for immediate processing
         // The FileWatcher plugin will automatically start monitoring the current directory
@@ -840,6 +988,21 @@ impl CoreEngine {
         Ok(WatcherId::new())
     }
 
+    /// Serve a file or directory under its own route prefix
+    ///
+    /// Roots must be added before the plugins are registered; the server and
+    /// file watcher pick them up when they initialize.
+    pub async fn add_served_root(&mut self, path: PathBuf) -> Result<ServedRoot> {
+        let path = path.canonicalize().map_err(|e| {
+            RuneError::file_system(format!("Cannot serve {}: {}", path.display(), e))
+        })?;
+
+        let root = self.state_manager.add_served_root(path).await;
+        self.scheduler.rescan();
+        tracing::info!("Serving {} under /{}/", root.path.display(), root.prefix);
+        Ok(root)
+    }
+
     /// Get current watched file
     pub async fn get_current_file(&self) -> Option<PathBuf> {
         let state = self.state_manager.get_state().await;
@@ -959,4 +1122,6 @@ pub struct ShutdownReport {
     pub force_stopped: Vec<String>,
     pub timed_out: bool,
     pub registry_error: Option<String>,
+    /// How the tasks drained by their plugins, like the server, wound down
+    pub drained: Vec<DrainReport>,
 }
@@ -0,0 +1,475 @@
+//! Logging with per-target levels and in-memory capture
+//!
+//! [`init`] installs the global subscriber: a reloadable target filter, a
+//! [`LogBuffer`] holding the most recent records and the formatted console
+//! output. The returned [`LogController`] changes levels while running, from
+//! the `log` section of the configuration or the server's `/api/logs/level`,
+//! and holds the [`Profiler`] of the spans the subscriber sees.
+
+use crate::config::LogConfig;
+use crate::error::{Result, RuneError};
+use crate::profile::{ProfileLayer, Profiler};
+use crate::trace::{self, TraceId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, Layer, Registry};
+
+/// Number of records kept by [`init`]
+pub const DEFAULT_CAPACITY: usize = 1000;
+
+/// How log records are written to the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogOutput {
+    /// One line per record
+    Plain,
+    /// Multi-line records with target, file and line number
+    Verbose,
+    /// Nothing is written; records are only captured
+    Silent,
+}
+
+/// A captured log record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    /// Increasing sequence number, usable as a cursor for polling
+    pub id: u64,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// Request the record was logged for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<TraceId>,
+}
+
+/// Filter for [`LogBuffer::query`]
+#[derive(Debug, Clone, Default)]
+pub struct LogQuery {
+    /// Only records at this level or more severe
+    pub level: Option<Level>,
+    /// Only records whose target is this module or one of its children
+    pub target: Option<String>,
+    /// Only records logged for this request
+    pub trace_id: Option<String>,
+    /// Only records with a larger id
+    pub after: Option<u64>,
+    /// Only the newest matching records
+    pub limit: Option<usize>,
+}
+
+/// Ring buffer of the most recent log records
+pub struct LogBuffer {
+    capacity: usize,
+    records: Mutex<VecDeque<LogRecord>>,
+    next_id: AtomicU64,
+}
+
+impl LogBuffer {
+    /// Create a buffer keeping up to `capacity` records
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            records: Mutex::new(VecDeque::with_capacity(capacity.min(DEFAULT_CAPACITY))),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Append a record, with the trace id of the current task, dropping the
+    /// oldest one when full
+    pub fn push(&self, level: Level, target: &str, message: String) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(LogRecord {
+            id,
+            timestamp,
+            level: level.to_string(),
+            target: target.to_string(),
+            message,
+            trace_id: trace::current(),
+        });
+        id
+    }
+
+    /// Matching records, oldest first
+    pub fn query(&self, query: &LogQuery) -> Vec<LogRecord> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        let mut matching: Vec<LogRecord> = records
+            .iter()
+            .filter(|record| query.after.is_none_or(|after| record.id > after))
+            .filter(|record| {
+                query.level.is_none_or(|level| {
+                    record
+                        .level
+                        .parse::<Level>()
+                        .is_ok_and(|record_level| record_level <= level)
+                })
+            })
+            .filter(|record| {
+                query
+                    .target
+                    .as_deref()
+                    .is_none_or(|target| target_matches(target, &record.target))
+            })
+            .filter(|record| {
+                query.trace_id.as_deref().is_none_or(|trace_id| {
+                    record
+                        .trace_id
+                        .as_ref()
+                        .is_some_and(|id| id.as_str() == trace_id)
+                })
+            })
+            .cloned()
+            .collect();
+
+        if let Some(limit) = query.limit {
+            let skip = matching.len().saturating_sub(limit);
+            matching.drain(..skip);
+        }
+        matching
+    }
+
+    /// Number of records currently held
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Check whether no records are held
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Maximum number of records held
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// Check whether `target` is `module` or nested below it
+fn target_matches(module: &str, target: &str) -> bool {
+    target
+        .strip_prefix(module)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+/// Active levels as reported by [`LogController::levels`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogLevels {
+    /// Level of targets without an override
+    pub default: String,
+    /// Per-target overrides
+    pub targets: BTreeMap<String, String>,
+}
+
+struct LevelState {
+    default: LevelFilter,
+    targets: BTreeMap<String, LevelFilter>,
+}
+
+impl LevelState {
+    fn filter(&self) -> Targets {
+        Targets::new()
+            .with_default(self.default)
+            .with_targets(self.targets.clone())
+    }
+}
+
+/// Runtime control over log levels and access to captured records
+pub struct LogController {
+    state: RwLock<LevelState>,
+    buffer: Arc<LogBuffer>,
+    profiler: Arc<Profiler>,
+    handle: Option<reload::Handle<Targets, Registry>>,
+}
+
+impl LogController {
+    /// Create a controller that is not attached to the global subscriber
+    pub fn new(default: LevelFilter, capacity: usize) -> Self {
+        Self {
+            state: RwLock::new(LevelState {
+                default,
+                targets: BTreeMap::new(),
+            }),
+            buffer: Arc::new(LogBuffer::new(capacity)),
+            profiler: Arc::new(Profiler::new()),
+            handle: None,
+        }
+    }
+
+    /// Captured records
+    pub fn buffer(&self) -> &Arc<LogBuffer> {
+        &self.buffer
+    }
+
+    /// Profiler of the spans enabled by the current levels
+    pub fn profiler(&self) -> &Arc<Profiler> {
+        &self.profiler
+    }
+
+    /// Current default level and target overrides
+    pub fn levels(&self) -> LogLevels {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        LogLevels {
+            default: state.default.to_string(),
+            targets: state
+                .targets
+                .iter()
+                .map(|(target, level)| (target.clone(), level.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Change the level of targets without an override
+    pub fn set_default_level(&self, level: &str) -> Result<()> {
+        let level = parse_level(level)?;
+        self.update(|state| state.default = level)
+    }
+
+    /// Override the level of `target`, or remove its override with `None`
+    pub fn set_level(&self, target: &str, level: Option<&str>) -> Result<()> {
+        let target = target.trim();
+        if target.is_empty() {
+            return Err(RuneError::config("Log target must not be empty"));
+        }
+        match level {
+            Some(level) => {
+                let level = parse_level(level)?;
+                self.update(|state| {
+                    state.targets.insert(target.to_string(), level);
+                })
+            }
+            None => self.update(|state| {
+                state.targets.remove(target);
+            }),
+        }
+    }
+
+    /// Apply the configured levels; overrides not in `config` are removed
+    pub fn apply_config(&self, config: &LogConfig) -> Result<()> {
+        let default = config.level.as_deref().map(parse_level).transpose()?;
+        let targets = config
+            .levels
+            .iter()
+            .map(|(target, level)| Ok((target.clone(), parse_level(level)?)))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        self.update(|state| {
+            if let Some(default) = default {
+                state.default = default;
+            }
+            state.targets = targets;
+        })
+    }
+
+    fn update(&self, change: impl FnOnce(&mut LevelState)) -> Result<()> {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        change(&mut state);
+        if let Some(handle) = &self.handle {
+            handle
+                .reload(state.filter())
+                .map_err(|e| RuneError::generic(format!("Failed to change log levels: {}", e)))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a level name such as `debug`, or `off`
+pub fn parse_level(level: &str) -> Result<LevelFilter> {
+    level
+        .trim()
+        .parse::<LevelFilter>()
+        .map_err(|_| RuneError::config(format!("Unknown log level '{}'", level)))
+}
+
+/// Install the global subscriber and return its controller
+pub fn init(default: LevelFilter, output: LogOutput) -> Result<Arc<LogController>> {
+    let mut controller = LogController::new(default, DEFAULT_CAPACITY);
+    let filter = controller
+        .state
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .filter();
+    let (filter, handle) = reload::Layer::new(filter);
+    controller.handle = Some(handle);
+
+    let capture = CaptureLayer {
+        buffer: controller.buffer.clone(),
+    };
+    let console = match output {
+        LogOutput::Plain => Some(fmt::layer().with_ansi(true).boxed()),
+        LogOutput::Verbose => Some(
+            fmt::layer()
+                .with_ansi(true)
+                .with_target(true)
+                .with_line_number(true)
+                .with_file(true)
+                .pretty()
+                .boxed(),
+        ),
+        LogOutput::Silent => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(capture)
+        .with(ProfileLayer::new(controller.profiler.clone()))
+        .with(console)
+        .try_init()
+        .map_err(|e| RuneError::generic(format!("Failed to initialize logging: {}", e)))?;
+
+    Ok(Arc::new(controller))
+}
+
+/// Layer copying every enabled event into a [`LogBuffer`]
+struct CaptureLayer {
+    buffer: Arc<LogBuffer>,
+}
+
+impl<S: Subscriber> Layer<S> for CaptureLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let metadata = event.metadata();
+        self.buffer
+            .push(*metadata.level(), metadata.target(), visitor.message);
+    }
+}
+
+/// Collects the message of an event followed by its `key=value` fields
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl MessageVisitor {
+    fn separate(&mut self) {
+        if !self.message.is_empty() {
+            self.message.push(' ');
+        }
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.insert_str(0, value);
+        } else {
+            self.separate();
+            let _ = write!(self.message, "{}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message.insert_str(0, &format!("{:?}", value));
+        } else if !field.name().starts_with("log.") {
+            self.separate();
+            let _ = write!(self.message, "{}={:?}", field.name(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_buffer_query() {
+        let buffer = LogBuffer::new(3);
+        buffer.push(Level::INFO, "rune_server", "started".to_string());
+        buffer.push(Level::DEBUG, "rune_server::handlers", "request".to_string());
+        buffer.push(Level::WARN, "rune_serverless", "slow".to_string());
+        let newest = buffer.push(Level::ERROR, "rune_core", "failed".to_string());
+
+        // The oldest record was evicted
+        assert_eq!(buffer.len(), 3);
+        let all = buffer.query(&LogQuery::default());
+        assert_eq!(all.first().unwrap().message, "request");
+        assert_eq!(all.last().unwrap().id, newest);
+
+        let server = buffer.query(&LogQuery {
+            target: Some("rune_server".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(server.len(), 1);
+        assert_eq!(server[0].target, "rune_server::handlers");
+
+        let warnings = buffer.query(&LogQuery {
+            level: Some(Level::WARN),
+            limit: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "failed");
+
+        let after = buffer.query(&LogQuery {
+            after: Some(newest - 1),
+            ..Default::default()
+        });
+        assert_eq!(after.len(), 1);
+        assert_eq!(after[0].id, newest);
+    }
+
+    #[tokio::test]
+    async fn test_records_carry_the_trace_id() {
+        let buffer = LogBuffer::new(10);
+        buffer.push(Level::INFO, "rune_server", "untraced".to_string());
+        let id = TraceId::new();
+        trace::scope(id.clone(), async {
+            buffer.push(Level::INFO, "rune_editor", "traced".to_string());
+        })
+        .await;
+
+        let traced = buffer.query(&LogQuery {
+            trace_id: Some(id.to_string()),
+            ..Default::default()
+        });
+        assert_eq!(traced.len(), 1);
+        assert_eq!(traced[0].message, "traced");
+        assert_eq!(traced[0].trace_id, Some(id));
+    }
+
+    #[test]
+    fn test_log_controller_levels() {
+        let controller = LogController::new(LevelFilter::INFO, 10);
+        controller.set_level("rune_server", Some("debug")).unwrap();
+        controller.set_default_level("warn").unwrap();
+        assert!(controller.set_level("rune_core", Some("loud")).is_err());
+        assert!(controller.set_level(" ", Some("debug")).is_err());
+
+        let levels = controller.levels();
+        assert_eq!(levels.default, "warn");
+        assert_eq!(levels.targets.get("rune_server").unwrap(), "debug");
+        assert!(!levels.targets.contains_key("rune_core"));
+
+        controller.set_level("rune_server", None).unwrap();
+        assert!(controller.levels().targets.is_empty());
+
+        let config = LogConfig {
+            level: None,
+            levels: [("rune_theme".to_string(), "trace".to_string())].into(),
+        };
+        controller.apply_config(&config).unwrap();
+        let levels = controller.levels();
+        assert_eq!(levels.default, "warn");
+        assert_eq!(levels.targets.get("rune_theme").unwrap(), "trace");
+    }
+}
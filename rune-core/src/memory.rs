@@ -0,0 +1,345 @@
+//! Memory accounting and eviction for long-running instances
+//!
+//! Caches and buffers that grow with use, such as the render cache, preview
+//! pages, editor sessions or theme assets, register as a [`MemoryConsumer`]
+//! with the engine's [`MemoryBudget`]. [`MemoryBudget::enforce`] adds up what
+//! they report and, while the total is over the budget, asks them to free
+//! memory in order of their [`EvictionPriority`]: caches that can be rebuilt
+//! first, then idle session state. Pinned consumers are only accounted for.
+//! Anything else that holds memory for the whole session, such as a search
+//! index, can register the same way.
+
+use crate::error::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+/// Budget used when the configuration sets none
+pub const DEFAULT_MEMORY_BUDGET_MB: u64 = 256;
+
+/// Interval between budget checks when the configuration sets none
+pub const DEFAULT_MEMORY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Name of the supervised task enforcing the budget
+pub const MEMORY_TASK: &str = "memory-budget";
+
+/// Order in which consumers are asked to free memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EvictionPriority {
+    /// Rebuilt from its source on the next use
+    Cache,
+    /// Per-user state; only parts that can be reopened without losing
+    /// changes are given up
+    Session,
+    /// Counted against the budget but never evicted
+    Pinned,
+}
+
+/// Memory held by a consumer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryUsage {
+    pub bytes: usize,
+    pub entries: usize,
+}
+
+/// Something that holds memory the budget should account for
+#[async_trait]
+pub trait MemoryConsumer: Send + Sync {
+    /// Unique name shown in memory reports, e.g. `render-cache`
+    fn name(&self) -> &str;
+
+    fn priority(&self) -> EvictionPriority {
+        EvictionPriority::Cache
+    }
+
+    /// Memory currently held
+    async fn usage(&self) -> MemoryUsage;
+
+    /// Free about `bytes` bytes, returning how many were actually freed
+    async fn evict(&self, _bytes: usize) -> usize {
+        0
+    }
+}
+
+/// Usage and eviction history of one consumer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsumerReport {
+    pub name: String,
+    pub priority: EvictionPriority,
+    #[serde(flatten)]
+    pub usage: MemoryUsage,
+    /// Times the consumer freed memory for the budget
+    pub evictions: u64,
+    pub evicted_bytes: u64,
+}
+
+/// Snapshot of the budget and every consumer, largest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryReport {
+    pub limit_bytes: usize,
+    pub used_bytes: usize,
+    pub consumers: Vec<ConsumerReport>,
+    pub last_enforced: Option<SystemTime>,
+}
+
+impl MemoryReport {
+    pub fn over_budget(&self) -> bool {
+        self.used_bytes > self.limit_bytes
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct EvictionStats {
+    evictions: u64,
+    evicted_bytes: u64,
+}
+
+/// Global memory budget shared by every registered consumer
+pub struct MemoryBudget {
+    limit: AtomicUsize,
+    consumers: RwLock<Vec<Arc<dyn MemoryConsumer>>>,
+    stats: RwLock<HashMap<String, EvictionStats>>,
+    last_enforced: RwLock<Option<SystemTime>>,
+}
+
+impl MemoryBudget {
+    /// Create a budget of `limit_bytes`
+    pub fn new(limit_bytes: usize) -> Self {
+        Self {
+            limit: AtomicUsize::new(limit_bytes),
+            consumers: RwLock::new(Vec::new()),
+            stats: RwLock::new(HashMap::new()),
+            last_enforced: RwLock::new(None),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit.load(Ordering::Relaxed)
+    }
+
+    /// Change the budget, e.g. after a configuration reload
+    pub fn set_limit(&self, limit_bytes: usize) {
+        self.limit.store(limit_bytes, Ordering::Relaxed);
+    }
+
+    /// Account for `consumer`, replacing a consumer of the same name
+    pub async fn register(&self, consumer: Arc<dyn MemoryConsumer>) {
+        let mut consumers = self.consumers.write().await;
+        consumers.retain(|existing| existing.name() != consumer.name());
+        debug!("Accounting memory of '{}'", consumer.name());
+        consumers.push(consumer);
+    }
+
+    /// Stop accounting for a consumer; returns whether it was registered
+    pub async fn unregister(&self, name: &str) -> bool {
+        let mut consumers = self.consumers.write().await;
+        let before = consumers.len();
+        consumers.retain(|consumer| consumer.name() != name);
+        consumers.len() != before
+    }
+
+    /// Current usage of every consumer
+    pub async fn report(&self) -> MemoryReport {
+        let consumers = self.consumers.read().await.clone();
+        let stats = self.stats.read().await.clone();
+
+        let mut reports = Vec::with_capacity(consumers.len());
+        for consumer in consumers {
+            let stats = stats.get(consumer.name()).copied().unwrap_or_default();
+            reports.push(ConsumerReport {
+                name: consumer.name().to_string(),
+                priority: consumer.priority(),
+                usage: consumer.usage().await,
+                evictions: stats.evictions,
+                evicted_bytes: stats.evicted_bytes,
+            });
+        }
+        reports.sort_by_key(|report| std::cmp::Reverse(report.usage.bytes));
+
+        MemoryReport {
+            limit_bytes: self.limit(),
+            used_bytes: reports.iter().map(|report| report.usage.bytes).sum(),
+            consumers: reports,
+            last_enforced: *self.last_enforced.read().await,
+        }
+    }
+
+    /// Evict from consumers until usage fits the budget, returning the bytes
+    /// freed
+    pub async fn enforce(&self) -> usize {
+        *self.last_enforced.write().await = Some(SystemTime::now());
+
+        let mut consumers = self.consumers.read().await.clone();
+        let mut used = 0;
+        for consumer in &consumers {
+            used += consumer.usage().await.bytes;
+        }
+        let limit = self.limit();
+        if used <= limit {
+            return 0;
+        }
+
+        // Stable sort keeps registration order within a priority
+        consumers.sort_by_key(|consumer| consumer.priority());
+        let mut freed = 0;
+        for consumer in consumers {
+            if used - freed <= limit || consumer.priority() == EvictionPriority::Pinned {
+                break;
+            }
+            let consumer_freed = consumer.evict(used - freed - limit).await;
+            if consumer_freed == 0 {
+                continue;
+            }
+            debug!(
+                "Evicted {} bytes from '{}' to stay within the memory budget",
+                consumer_freed,
+                consumer.name()
+            );
+            let mut stats = self.stats.write().await;
+            let entry = stats.entry(consumer.name().to_string()).or_default();
+            entry.evictions += 1;
+            entry.evicted_bytes += consumer_freed as u64;
+            freed += consumer_freed.min(used - freed);
+        }
+
+        if used - freed > limit {
+            warn!(
+                "Memory use of {} bytes stays over the budget of {} bytes after evicting {} bytes",
+                used - freed,
+                limit,
+                freed
+            );
+        }
+        freed
+    }
+
+    /// Enforce the budget every `interval`; runs until the task is aborted
+    pub async fn run(self: Arc<Self>, interval: Duration) -> Result<()> {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            self.enforce().await;
+        }
+    }
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        Self::new((DEFAULT_MEMORY_BUDGET_MB * 1024 * 1024) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Consumer holding `entries` of 100 bytes each
+    struct Entries {
+        name: &'static str,
+        priority: EvictionPriority,
+        entries: Mutex<usize>,
+    }
+
+    impl Entries {
+        fn new(name: &'static str, priority: EvictionPriority, entries: usize) -> Arc<Self> {
+            Arc::new(Self {
+                name,
+                priority,
+                entries: Mutex::new(entries),
+            })
+        }
+
+        fn len(&self) -> usize {
+            *self.entries.lock().unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl MemoryConsumer for Entries {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn priority(&self) -> EvictionPriority {
+            self.priority
+        }
+
+        async fn usage(&self) -> MemoryUsage {
+            MemoryUsage {
+                bytes: self.len() * 100,
+                entries: self.len(),
+            }
+        }
+
+        async fn evict(&self, bytes: usize) -> usize {
+            if self.priority == EvictionPriority::Pinned {
+                return 0;
+            }
+            let mut entries = self.entries.lock().unwrap();
+            let evicted = bytes.div_ceil(100).min(*entries);
+            *entries -= evicted;
+            evicted * 100
+        }
+    }
+
+    #[tokio::test]
+    async fn test_enforce_evicts_caches_before_sessions() {
+        let budget = MemoryBudget::new(1_000);
+        let sessions = Entries::new("sessions", EvictionPriority::Session, 6);
+        let cache = Entries::new("cache", EvictionPriority::Cache, 5);
+        let assets = Entries::new("assets", EvictionPriority::Pinned, 2);
+        budget.register(sessions.clone()).await;
+        budget.register(cache.clone()).await;
+        budget.register(assets.clone()).await;
+
+        // 1300 bytes against 1000: the cache alone gives up 300
+        assert_eq!(budget.enforce().await, 300);
+        assert_eq!((cache.len(), sessions.len(), assets.len()), (2, 6, 2));
+        assert_eq!(budget.enforce().await, 0);
+
+        budget.set_limit(300);
+        assert_eq!(budget.enforce().await, 700);
+        assert_eq!((cache.len(), sessions.len(), assets.len()), (0, 1, 2));
+
+        let report = budget.report().await;
+        assert_eq!(report.used_bytes, 300);
+        assert!(!report.over_budget());
+        assert_eq!(report.consumers[0].name, "assets");
+        let cache_report = report.consumers.iter().find(|c| c.name == "cache").unwrap();
+        assert_eq!(cache_report.evictions, 2);
+        assert_eq!(cache_report.evicted_bytes, 500);
+
+        // Pinned consumers are never asked to free memory
+        budget.set_limit(0);
+        assert_eq!(budget.enforce().await, 100);
+        assert_eq!(assets.len(), 2);
+        assert!(budget.report().await.over_budget());
+    }
+
+    #[tokio::test]
+    async fn test_register_replaces_consumers_by_name() {
+        let budget = MemoryBudget::default();
+        budget
+            .register(Entries::new("cache", EvictionPriority::Cache, 1))
+            .await;
+        budget
+            .register(Entries::new("cache", EvictionPriority::Cache, 3))
+            .await;
+
+        let report = budget.report().await;
+        assert_eq!(report.consumers.len(), 1);
+        assert_eq!(report.used_bytes, 300);
+
+        assert!(budget.unregister("cache").await);
+        assert!(!budget.unregister("cache").await);
+        assert_eq!(budget.report().await.used_bytes, 0);
+    }
+}
@@ -0,0 +1,93 @@
+//! Notifications shown to the user
+//!
+//! Plugins tell the user about things worth knowing, like a save that failed
+//! or a task that was restarted, by publishing a [`Notification`] on the
+//! event bus with [`SystemEvent::notification`](crate::SystemEvent::notification).
+//! The server streams them to every open page, which shows them as toasts
+//! with their actions as buttons.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// How much a notification matters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationLevel {
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+/// A button of a notification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationAction {
+    pub label: String,
+    /// Id of the [command](crate::command) the button executes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Address the button opens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl NotificationAction {
+    /// A button executing the command `id`
+    pub fn command(label: &str, id: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            command: Some(id.to_string()),
+            url: None,
+        }
+    }
+
+    /// A button opening `url`
+    pub fn link(label: &str, url: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            command: None,
+            url: Some(url.to_string()),
+        }
+    }
+}
+
+/// A message for the user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: Uuid,
+    pub level: NotificationLevel,
+    /// Plugin or part of Rune the notification comes from
+    pub source: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<NotificationAction>,
+}
+
+impl Notification {
+    pub fn new(level: NotificationLevel, source: &str, message: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            level,
+            source: source.to_string(),
+            message: message.into(),
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn info(source: &str, message: impl Into<String>) -> Self {
+        Self::new(NotificationLevel::Info, source, message)
+    }
+
+    pub fn warn(source: &str, message: impl Into<String>) -> Self {
+        Self::new(NotificationLevel::Warn, source, message)
+    }
+
+    pub fn error(source: &str, message: impl Into<String>) -> Self {
+        Self::new(NotificationLevel::Error, source, message)
+    }
+
+    pub fn with_action(mut self, action: NotificationAction) -> Self {
+        self.actions.push(action);
+        self
+    }
+}
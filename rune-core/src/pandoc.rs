@@ -0,0 +1,364 @@
+//! Bridge to pandoc
+//!
+//! When [pandoc](https://pandoc.org) is installed, documents it reads (org,
+//! reStructuredText, LaTeX, MediaWiki and more) are previewed through the
+//! renderer plugin's pandoc renderer, and `rune export` writes the formats
+//! it writes. Pandoc is found on the `PATH`, or at [`PANDOC_ENV`]; without
+//! it those formats are refused with a message saying what to install,
+//! while everything else works as before.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use crate::error::{Result, RuneError};
+use crate::process::{self, ProcessError};
+
+/// Environment variable naming the pandoc executable to run
+pub const PANDOC_ENV: &str = "RUNE_PANDOC";
+
+/// A document format pandoc reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PandocInput {
+    /// Pandoc's name of the format
+    pub format: &'static str,
+    /// File extensions of documents in the format
+    pub extensions: &'static [&'static str],
+    /// Content type renderers are asked for
+    pub content_type: &'static str,
+}
+
+/// Formats previewed through pandoc
+pub const INPUT_FORMATS: &[PandocInput] = &[
+    PandocInput {
+        format: "org",
+        extensions: &["org"],
+        content_type: "text/x-org",
+    },
+    PandocInput {
+        format: "rst",
+        extensions: &["rst"],
+        content_type: "text/x-rst",
+    },
+    PandocInput {
+        format: "latex",
+        extensions: &["tex", "latex"],
+        content_type: "text/x-tex",
+    },
+    PandocInput {
+        format: "mediawiki",
+        extensions: &["wiki", "mediawiki"],
+        content_type: "text/x-mediawiki",
+    },
+    PandocInput {
+        format: "textile",
+        extensions: &["textile"],
+        content_type: "text/x-textile",
+    },
+    PandocInput {
+        format: "typst",
+        extensions: &["typ"],
+        content_type: "text/x-typst",
+    },
+    PandocInput {
+        format: "djot",
+        extensions: &["dj", "djot"],
+        content_type: "text/x-djot",
+    },
+    PandocInput {
+        format: "docbook",
+        extensions: &["dbk"],
+        content_type: "application/docbook+xml",
+    },
+    PandocInput {
+        format: "opml",
+        extensions: &["opml"],
+        content_type: "text/x-opml",
+    },
+    PandocInput {
+        format: "ipynb",
+        extensions: &["ipynb"],
+        content_type: "application/x-ipynb+json",
+    },
+];
+
+/// A document format pandoc writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PandocOutput {
+    /// Pandoc's name of the format
+    pub format: &'static str,
+    /// Other names the format is asked for by
+    pub aliases: &'static [&'static str],
+    /// File extension of exported documents
+    pub extension: &'static str,
+}
+
+/// Formats exported through pandoc
+pub const OUTPUT_FORMATS: &[PandocOutput] = &[
+    PandocOutput {
+        format: "latex",
+        aliases: &["tex"],
+        extension: "tex",
+    },
+    PandocOutput {
+        format: "beamer",
+        aliases: &[],
+        extension: "tex",
+    },
+    PandocOutput {
+        format: "context",
+        aliases: &[],
+        extension: "tex",
+    },
+    PandocOutput {
+        format: "org",
+        aliases: &[],
+        extension: "org",
+    },
+    PandocOutput {
+        format: "rst",
+        aliases: &["restructuredtext"],
+        extension: "rst",
+    },
+    PandocOutput {
+        format: "asciidoc",
+        aliases: &["adoc"],
+        extension: "adoc",
+    },
+    PandocOutput {
+        format: "mediawiki",
+        aliases: &["wiki"],
+        extension: "wiki",
+    },
+    PandocOutput {
+        format: "dokuwiki",
+        aliases: &[],
+        extension: "txt",
+    },
+    PandocOutput {
+        format: "textile",
+        aliases: &[],
+        extension: "textile",
+    },
+    PandocOutput {
+        format: "typst",
+        aliases: &["typ"],
+        extension: "typ",
+    },
+    PandocOutput {
+        format: "texinfo",
+        aliases: &[],
+        extension: "texi",
+    },
+    PandocOutput {
+        format: "man",
+        aliases: &[],
+        extension: "1",
+    },
+    PandocOutput {
+        format: "docbook",
+        aliases: &[],
+        extension: "dbk",
+    },
+    PandocOutput {
+        format: "jats",
+        aliases: &[],
+        extension: "xml",
+    },
+    PandocOutput {
+        format: "icml",
+        aliases: &[],
+        extension: "icml",
+    },
+    PandocOutput {
+        format: "opml",
+        aliases: &[],
+        extension: "opml",
+    },
+    PandocOutput {
+        format: "ipynb",
+        aliases: &["jupyter"],
+        extension: "ipynb",
+    },
+    PandocOutput {
+        format: "plain",
+        aliases: &["txt"],
+        extension: "txt",
+    },
+    PandocOutput {
+        format: "odt",
+        aliases: &["opendocument"],
+        extension: "odt",
+    },
+    PandocOutput {
+        format: "rtf",
+        aliases: &[],
+        extension: "rtf",
+    },
+    PandocOutput {
+        format: "pptx",
+        aliases: &["powerpoint"],
+        extension: "pptx",
+    },
+];
+
+/// Format pandoc reads a document in, going by its extension
+pub fn input_format(path: &Path) -> Option<&'static PandocInput> {
+    let extension = crate::crypto::plain_path(path)
+        .extension()?
+        .to_string_lossy()
+        .to_lowercase();
+    INPUT_FORMATS
+        .iter()
+        .find(|input| input.extensions.contains(&extension.as_str()))
+}
+
+/// Format pandoc writes for a name or alias
+pub fn output_format(name: &str) -> Option<&'static PandocOutput> {
+    let name = name.to_lowercase();
+    OUTPUT_FORMATS
+        .iter()
+        .find(|output| output.format == name || output.aliases.contains(&name.as_str()))
+}
+
+/// An installed pandoc
+#[derive(Debug, Clone)]
+pub struct Pandoc {
+    program: PathBuf,
+    version: String,
+}
+
+impl Pandoc {
+    /// Find pandoc, at [`PANDOC_ENV`] or on the `PATH`
+    pub fn detect() -> Option<Self> {
+        let program = std::env::var_os(PANDOC_ENV)
+            .filter(|program| !program.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("pandoc"));
+        Self::at(program)
+    }
+
+    /// Pandoc at `program`, if it runs
+    pub fn at(program: PathBuf) -> Option<Self> {
+        let output = process::runner()
+            .std_command(&program)
+            .ok()?
+            .arg("--version")
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let version = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .unwrap_or("pandoc")
+            .trim()
+            .to_string();
+        Some(Self { program, version })
+    }
+
+    /// Pandoc, or an error explaining that `purpose` needs it
+    pub fn require(purpose: &str) -> Result<Self> {
+        Self::detect().ok_or_else(|| {
+            RuneError::config(format!(
+                "{} needs pandoc, which was not found. Install it from \
+                 https://pandoc.org or set {} to its path",
+                purpose, PANDOC_ENV
+            ))
+        })
+    }
+
+    /// First line of `pandoc --version`, like `pandoc 3.1.11`
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Convert `input` from one pandoc format to another; relative images
+    /// and includes are looked up in `resource_dir`
+    pub async fn convert(
+        &self,
+        input: &str,
+        from: &str,
+        to: &str,
+        resource_dir: &Path,
+        standalone: bool,
+    ) -> Result<Vec<u8>> {
+        let runner = process::runner();
+        let mut command = runner.command(&self.program)?;
+        command
+            .arg("--from")
+            .arg(from)
+            .arg("--to")
+            .arg(to)
+            .arg("--resource-path")
+            .arg(resource_dir)
+            .arg("--output")
+            .arg("-")
+            .current_dir(resource_dir);
+        if standalone {
+            command.arg("--standalone");
+        }
+
+        match runner
+            .output(command, Some(input.as_bytes().to_vec()))
+            .await
+        {
+            Ok(output) => Ok(output),
+            Err(ProcessError::NotFound { .. }) => Err(RuneError::config(format!(
+                "pandoc was not found at {}",
+                self.program.display()
+            ))),
+            Err(ProcessError::Failed { stderr, .. }) => Err(RuneError::Plugin(format!(
+                "pandoc failed converting {} to {}: {}",
+                from, to, stderr
+            ))),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[tokio::test]
+    async fn test_formats_and_conversion_through_pandoc() {
+        assert_eq!(
+            input_format(Path::new("notes.org")).unwrap().content_type,
+            "text/x-org"
+        );
+        assert!(input_format(Path::new("notes.md")).is_none());
+        assert_eq!(output_format("TEX").unwrap().format, "latex");
+        assert!(output_format("pdf").is_none());
+
+        // A stand-in echoing its arguments and input
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let program = temp_dir.path().join("pandoc");
+        std::fs::write(
+            &program,
+            "#!/bin/sh\n[ \"$1\" = --version ] && { echo 'pandoc 9.9'; exit 0; }\n\
+             [ \"$2\" = fail ] && { echo 'bad input' >&2; exit 64; }\n\
+             echo \"$@\"; cat\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let pandoc = Pandoc::at(program).unwrap();
+        assert_eq!(pandoc.version(), "pandoc 9.9");
+        let output = pandoc
+            .convert("* Heading", "org", "html5", temp_dir.path(), false)
+            .await
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("--from org --to html5 --resource-path "));
+        assert!(output.ends_with("--output -\n* Heading"));
+
+        let error = pandoc
+            .convert("", "fail", "html5", temp_dir.path(), true)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("bad input"));
+
+        assert!(Pandoc::at(temp_dir.path().join("missing")).is_none());
+    }
+}
@@ -3,7 +3,7 @@
 //! This module provides a simplified Markdown parser that converts Markdown text
 //! into an AST structure, supporting basic GFM features.
 
-use crate::ast::{Node, NodeType, ParseOptions, Tree};
+use crate::ast::{Node, NodeType, ParseOptions, Position, Tree};
 use regex::Regex;
 
 /// Markdown parser for converting text to AST
@@ -25,13 +25,41 @@ impl MarkdownParser {
     }
 
     /// Parse markdown text into an AST tree
+    ///
+    /// Top-level blocks get the position they start at and keep the source
+    /// lines they were parsed from in `tokens`, for the serializer to reuse
     pub fn parse(&self, markdown: &str) -> Tree {
         let mut tree = Tree::with_options(self.options.clone());
         let lines = markdown.lines().collect::<Vec<_>>();
+        let line_offsets = markdown
+            .split_inclusive('\n')
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len();
+                Some(start)
+            })
+            .collect::<Vec<_>>();
 
         let mut line_index = 0;
         while line_index < lines.len() {
-            line_index += self.parse_block(&mut tree.root, &lines, line_index);
+            let first_block = tree.root.children.len();
+            let consumed = self.parse_block(&mut tree.root, &lines, line_index);
+            let end = (line_index + consumed).min(lines.len());
+
+            let mut source_lines = &lines[line_index..end];
+            while let [rest @ .., last] = source_lines {
+                if !last.trim().is_empty() {
+                    break;
+                }
+                source_lines = rest;
+            }
+            let source = source_lines.join("\n");
+            for block in &mut tree.root.children[first_block..] {
+                block.position = Some(Position::new(line_index + 1, 1, line_offsets[line_index]));
+                block.tokens = source.as_bytes().to_vec();
+            }
+
+            line_index = end;
         }
 
         tree
@@ -72,6 +100,17 @@ impl MarkdownParser {
             return self.parse_list(parent, lines, start_index);
         }
 
+        // Table: a header row followed by a delimiter row
+        if self.options.tables
+            && line.contains('|')
+            && lines
+                .get(start_index + 1)
+                .and_then(|next| table_alignments(next))
+                .is_some_and(|alignments| alignments.len() == table_cells(line).len())
+        {
+            return self.parse_table(parent, lines, start_index);
+        }
+
         // Thematic break
         if self.is_thematic_break(line) {
             let mut hr = Node::new(NodeType::ThematicBreak);
@@ -162,7 +201,9 @@ impl MarkdownParser {
         let sub_parser = MarkdownParser::with_options(self.options.clone());
         let quote_tree = sub_parser.parse(&quote_content);
 
-        for child in quote_tree.root.children {
+        for mut child in quote_tree.root.children {
+            // Positions in the quote's content are not positions in the document
+            child.position = None;
             blockquote.append_child(child);
         }
 
@@ -254,6 +295,44 @@ impl MarkdownParser {
         line_index - start_index
     }
 
+    /// Parse a GFM table; cells keep their column's alignment in `data`
+    fn parse_table(&self, parent: &mut Node, lines: &[&str], start_index: usize) -> usize {
+        let alignments = table_alignments(lines[start_index + 1]).unwrap_or_default();
+        let mut table = Node::new(NodeType::Table);
+
+        let mut head = Node::new(NodeType::TableHead);
+        head.append_child(self.table_row(lines[start_index], &alignments));
+        table.append_child(head);
+
+        let mut line_index = start_index + 2;
+        while line_index < lines.len() {
+            let line = lines[line_index];
+            if line.trim().is_empty() || !line.contains('|') || self.starts_block(line) {
+                break;
+            }
+            table.append_child(self.table_row(line, &alignments));
+            line_index += 1;
+        }
+
+        parent.append_child(table);
+        line_index - start_index
+    }
+
+    /// Row with one cell per column, padded or cut to the header's width
+    fn table_row(&self, line: &str, alignments: &[Option<&'static str>]) -> Node {
+        let mut cells = table_cells(line).into_iter();
+        let mut row = Node::new(NodeType::TableRow);
+        for alignment in alignments {
+            let mut cell = Node::new(NodeType::TableCell);
+            if let Some(alignment) = alignment {
+                cell.data = alignment.to_string();
+            }
+            self.parse_inline_content(&mut cell, &cells.next().unwrap_or_default());
+            row.append_child(cell);
+        }
+        row
+    }
+
     /// Check if line is a thematic break
     fn is_thematic_break(&self, line: &str) -> bool {
         let trimmed = line.trim();
@@ -278,15 +357,27 @@ impl MarkdownParser {
             if line.trim().is_empty() {
                 break;
             }
-            if line_index > start_index && self.starts_block(line) {
-                break;
+            if line_index > start_index {
+                // An underline makes the paragraph a setext heading
+                if let Some(level) = setext_level(line) {
+                    let mut heading = Node::new(NodeType::Heading);
+                    heading.level = Some(level);
+                    self.parse_inline_content(&mut heading, paragraph_lines.join("\n").trim());
+                    paragraph_lines.push(line);
+                    heading.tokens = paragraph_lines.join("\n").into_bytes();
+                    parent.append_child(heading);
+                    return line_index - start_index + 1;
+                }
+                if self.starts_block(line) {
+                    break;
+                }
             }
 
             paragraph_lines.push(line);
             line_index += 1;
         }
 
-        let content = paragraph_lines.join(" ");
+        let content = paragraph_lines.join("\n");
         paragraph.tokens = content.as_bytes().to_vec();
         self.parse_inline_content(&mut paragraph, &content);
 
@@ -331,6 +422,33 @@ impl MarkdownParser {
         let mut remaining = text;
 
         while !remaining.is_empty() {
+            // Line breaks: hard after a backslash or two or more spaces
+            let spaces = remaining.len() - remaining.trim_start_matches(' ').len();
+            if let Some(rest) = remaining
+                .strip_prefix("\\\n")
+                .or_else(|| (spaces >= 2).then(|| remaining[spaces..].strip_prefix('\n'))?)
+            {
+                parent.append_child(Node::new(NodeType::LineBreak));
+                remaining = rest;
+                continue;
+            }
+            if let Some(rest) = remaining.strip_prefix('\n') {
+                parent.append_child(Node::new(NodeType::SoftBreak));
+                remaining = rest;
+                continue;
+            }
+
+            // Backslash escapes of ASCII punctuation are literal text
+            if let Some(escaped) = remaining
+                .strip_prefix('\\')
+                .and_then(|rest| rest.chars().next())
+                .filter(char::is_ascii_punctuation)
+            {
+                parent.append_child(Node::text(&escaped.to_string()));
+                remaining = &remaining[1 + escaped.len_utf8()..];
+                continue;
+            }
+
             // Try to find inline elements
             if let Some((element, consumed)) = self.parse_next_inline(remaining) {
                 parent.append_child(element);
@@ -463,6 +581,68 @@ impl MarkdownParser {
     }
 }
 
+/// Column alignments of a table delimiter row like `| :-- | :-: | --: |`
+fn table_alignments(line: &str) -> Option<Vec<Option<&'static str>>> {
+    let line = line.trim();
+    if !line.contains('|') {
+        return None;
+    }
+    table_cells(line)
+        .iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Some("center"),
+                (true, false) => Some("left"),
+                (false, true) => Some("right"),
+                (false, false) => None,
+            })
+        })
+        .collect()
+}
+
+/// Heading level of a setext underline: `=` for level 1, `-` for level 2
+fn setext_level(line: &str) -> Option<usize> {
+    let underline = line.trim();
+    if underline.is_empty() || line.len() - line.trim_start().len() > 3 {
+        None
+    } else if underline.chars().all(|c| c == '=') {
+        Some(1)
+    } else if underline.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// Trimmed cells of a table row, split at pipes that are not escaped
+fn table_cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(inner) if !inner.ends_with('\\') => inner,
+        _ => line,
+    };
+
+    let mut cells = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('|') => cells.last_mut().unwrap().push('|'),
+                Some(other) => cells.last_mut().unwrap().extend(['\\', other]),
+                None => cells.last_mut().unwrap().push('\\'),
+            },
+            '|' => cells.push(String::new()),
+            c => cells.last_mut().unwrap().push(c),
+        }
+    }
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
+}
+
 impl Default for MarkdownParser {
     fn default() -> Self {
         Self::new()
@@ -568,4 +748,58 @@ mod tests {
             Some(&"https://google.com".to_string())
         );
     }
+
+    #[test]
+    fn test_parse_table() {
+        let parser = MarkdownParser::new();
+        let tree = parser.parse(
+            "| Name | Price |  Note |\n|:-----|------:|:-----:|\n| `a\\|b` | **1** |\nc | 2 | 3 | extra\n\nAfter",
+        );
+
+        let table = &tree.root.children[0];
+        assert_eq!(table.node_type, NodeType::Table);
+        assert_eq!(table.children.len(), 3);
+        let head = &table.children[0];
+        assert_eq!(head.node_type, NodeType::TableHead);
+        let header = &head.children[0].children;
+        assert_eq!(header.len(), 3);
+        assert_eq!(header[0].text_content(), "Name");
+        let alignments: Vec<_> = header.iter().map(|cell| cell.data.as_str()).collect();
+        assert_eq!(alignments, ["left", "right", "center"]);
+
+        // Rows are padded or cut to the header's width
+        let first = &table.children[1].children;
+        assert_eq!(first.len(), 3);
+        assert_eq!(first[0].children[0].node_type, NodeType::Code);
+        assert_eq!(first[0].text_content(), "a|b");
+        assert_eq!(first[2].text_content(), "");
+        assert_eq!(table.children[2].children.len(), 3);
+        assert_eq!(tree.root.children[1].node_type, NodeType::Paragraph);
+
+        // Without a delimiter row a line with pipes is a paragraph
+        let tree = parser.parse("a | b\nc | d");
+        assert_eq!(tree.root.children[0].node_type, NodeType::Paragraph);
+    }
+
+    #[test]
+    fn test_parse_line_breaks() {
+        let parser = MarkdownParser::new();
+        let tree = parser.parse("one  \ntwo\\\nthree\nfour");
+
+        let breaks: Vec<_> = tree.root.children[0]
+            .children
+            .iter()
+            .filter(|n| matches!(n.node_type, NodeType::LineBreak | NodeType::SoftBreak))
+            .map(|n| n.node_type)
+            .collect();
+        assert_eq!(
+            breaks,
+            [
+                NodeType::LineBreak,
+                NodeType::LineBreak,
+                NodeType::SoftBreak
+            ]
+        );
+        assert_eq!(tree.root.children[0].text_content(), "one\ntwo\nthree four");
+    }
 }
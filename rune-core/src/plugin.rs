@@ -4,16 +4,20 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 use tokio::time::interval;
 use tracing::{debug, error, info, warn};
 
-use crate::config::Config;
+use crate::config::{Config, FieldSchema};
 use crate::error::{Result, RuneError};
 use crate::event::{EventBus, SystemEvent};
+use crate::memory::MemoryBudget;
+use crate::plugin_data;
 use crate::state::StateManager;
+use crate::supervisor::TaskSupervisor;
 
 /// Core plugin trait that all plugins must implement
 #[async_trait]
@@ -45,6 +49,17 @@ pub trait Plugin: Send + Sync + 'static {
         Vec::new()
     }
 
+    /// Services that must be provided before this plugin initializes
+    fn required_services(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// Keys this plugin reads from its configuration, checked when the
+    /// configuration is validated; without any, every key is accepted
+    fn config_schema(&self) -> HashMap<String, FieldSchema> {
+        HashMap::new()
+    }
+
     /// For downcasting
     fn as_any(&self) -> &dyn Any;
 
@@ -58,6 +73,12 @@ pub struct PluginContext {
     pub event_bus: Arc<dyn EventBus>,
     pub config: Arc<Config>,
     pub state_manager: Arc<StateManager>,
+    /// Restarts critical background tasks such as the server loop
+    pub supervisor: Arc<TaskSupervisor>,
+    /// Budget that caches and buffers register their memory with
+    pub memory: Arc<MemoryBudget>,
+    /// Services provided so far, which plugins wait for
+    pub services: Arc<ServiceReadiness>,
     plugin_name: Option<String>,
     shared_resources: Arc<RwLock<HashMap<String, Arc<dyn Any + Send + Sync>>>>,
     plugin_configs: Arc<RwLock<HashMap<String, PluginNamespaceConfig>>>,
@@ -71,6 +92,9 @@ impl PluginContext {
         state_manager: Arc<StateManager>,
     ) -> Self {
         Self {
+            supervisor: Arc::new(TaskSupervisor::new(event_bus.clone())),
+            memory: Arc::new(MemoryBudget::new(config.memory.budget_bytes())),
+            services: Arc::new(ServiceReadiness::default()),
             event_bus,
             config,
             state_manager,
@@ -80,6 +104,18 @@ impl PluginContext {
         }
     }
 
+    /// Use a supervisor shared with other contexts, such as the engine's
+    pub fn with_supervisor(mut self, supervisor: Arc<TaskSupervisor>) -> Self {
+        self.supervisor = supervisor;
+        self
+    }
+
+    /// Use a memory budget shared with other contexts, such as the engine's
+    pub fn with_memory_budget(mut self, memory: Arc<MemoryBudget>) -> Self {
+        self.memory = memory;
+        self
+    }
+
     /// Create a plugin-specific context with namespace access
     pub fn for_plugin(&self, plugin_name: String) -> Self {
         let mut context = self.clone();
@@ -124,6 +160,19 @@ impl PluginContext {
         resources.keys().cloned().collect()
     }
 
+    /// Directory of `plugin_name` for caches, indexes and history stores,
+    /// created if needed: under the `.rune/` directory of the loaded
+    /// workspace, or the user data directory outside of one
+    pub async fn data_dir(&self, plugin_name: &str) -> Result<PathBuf> {
+        let root = match self.state_manager.workspace_data_dir().await {
+            Some(dir) => dir,
+            None => plugin_data::user_root().ok_or_else(|| {
+                RuneError::file_system("No user data directory to keep plugin data in")
+            })?,
+        };
+        plugin_data::plugin_dir(&root, plugin_name)
+    }
+
     /// Get plugin-specific configuration with namespace isolation
     pub async fn get_plugin_config(&self) -> Result<PluginNamespaceConfig> {
         let plugin_name = self
@@ -244,6 +293,72 @@ impl PluginContext {
     }
 }
 
+/// How long a plugin waits for the services it requires by default
+pub const SERVICE_WAIT: Duration = Duration::from_secs(10);
+
+/// Services provided so far
+///
+/// Plugins used to find what they need in the shared resources only if the
+/// plugin providing it happened to be initialized first. The registry marks
+/// the [services a plugin provides](Plugin::provided_services) once it
+/// initialized, and a plugin is only initialized once the services it
+/// [requires](Plugin::required_services) are provided. Hosts may provide
+/// services of their own.
+#[derive(Debug)]
+pub struct ServiceReadiness {
+    ready: watch::Sender<HashSet<String>>,
+}
+
+impl Default for ServiceReadiness {
+    fn default() -> Self {
+        Self {
+            ready: watch::channel(HashSet::new()).0,
+        }
+    }
+}
+
+impl ServiceReadiness {
+    /// Mark `service` as provided
+    pub fn provide(&self, service: &str) {
+        self.ready
+            .send_if_modified(|ready| ready.insert(service.to_string()));
+    }
+
+    /// Mark `service` as no longer provided
+    pub fn withdraw(&self, service: &str) {
+        self.ready.send_if_modified(|ready| ready.remove(service));
+    }
+
+    /// Whether `service` is provided
+    pub fn is_ready(&self, service: &str) -> bool {
+        self.ready.borrow().contains(service)
+    }
+
+    /// Services provided, sorted
+    pub fn ready(&self) -> Vec<String> {
+        let mut ready: Vec<String> = self.ready.borrow().iter().cloned().collect();
+        ready.sort();
+        ready
+    }
+
+    /// Wait until `service` is provided, for at most `timeout`
+    pub async fn wait_for(&self, service: &str, timeout: Duration) -> Result<()> {
+        let mut ready = self.ready.subscribe();
+        let provided =
+            tokio::time::timeout(timeout, ready.wait_for(|ready| ready.contains(service)))
+                .await
+                .is_ok_and(|provided| provided.is_ok());
+        if provided {
+            Ok(())
+        } else {
+            Err(RuneError::Plugin(format!(
+                "Service {} was not provided within {:?}",
+                service, timeout
+            )))
+        }
+    }
+}
+
 /// Plugin registry for managing loaded plugins with lifecycle management
 pub struct PluginRegistry {
     plugins: HashMap<String, Box<dyn Plugin>>,
@@ -252,6 +367,8 @@ pub struct PluginRegistry {
     load_order: Vec<String>,
     health_monitor: PluginHealthMonitor,
     context: Option<PluginContext>,
+    /// How long plugins wait for the services they require
+    service_wait: Duration,
 }
 
 impl PluginRegistry {
@@ -264,9 +381,15 @@ impl PluginRegistry {
             load_order: Vec::new(),
             health_monitor: PluginHealthMonitor::new(),
             context: None,
+            service_wait: SERVICE_WAIT,
         }
     }
 
+    /// Wait at most `wait` for the services a plugin requires
+    pub fn set_service_wait(&mut self, wait: Duration) {
+        self.service_wait = wait;
+    }
+
     /// Initialize the plugin registry with context and start health monitoring
     pub async fn initialize(&mut self, context: PluginContext) -> Result<()> {
         info!("Initializing plugin registry");
@@ -325,54 +448,72 @@ impl PluginRegistry {
         // Stop health monitoring first
         self.health_monitor.stop_monitoring().await;
 
-        // Calculate shutdown order (reverse of dependency order)
-        let shutdown_order = self.calculate_shutdown_order();
-        info!("Plugin shutdown order: {:?}", shutdown_order);
+        // Dependents go before what they depend on
+        let stages = self.shutdown_stages();
+        info!("Plugin shutdown stages: {:?}", stages);
 
         let mut shutdown_errors = Vec::new();
         let mut successful_shutdowns = 0;
 
-        // Shutdown plugins in calculated order
-        for plugin_name in &shutdown_order {
-            if let Some(mut plugin) = self.plugins.remove(plugin_name) {
-                info!("Shutting down plugin: {}", plugin_name);
-
-                // Update status to indicate shutdown in progress
-                if let Some(info) = self.plugin_info.get_mut(plugin_name) {
-                    info.status = PluginStatus::Shutting;
+        let stage_count = stages.len();
+        for (stage, plugins) in stages.iter().enumerate() {
+            if let Some(context) = &self.context {
+                if let Err(e) = context
+                    .event_bus
+                    .publish_system_event(SystemEvent::system_shutdown_stage(
+                        stage + 1,
+                        stage_count,
+                        plugins.clone(),
+                    ))
+                    .await
+                {
+                    warn!("Failed to publish shutdown stage event: {}", e);
                 }
+            }
+            for plugin_name in plugins {
+                if let Some(mut plugin) = self.plugins.remove(plugin_name) {
+                    info!("Shutting down plugin: {}", plugin_name);
+
+                    // Update status to indicate shutdown in progress
+                    if let Some(info) = self.plugin_info.get_mut(plugin_name) {
+                        info.status = PluginStatus::Shutting;
+                    }
 
-                // Notify dependent plugins that this plugin is shutting down
-                self.notify_dependents_of_shutdown(plugin_name).await;
+                    // Notify dependent plugins that this plugin is shutting down
+                    self.notify_dependents_of_shutdown(plugin_name).await;
+                    self.withdraw_services(plugin_name);
 
-                // Attempt graceful shutdown with timeout
-                let shutdown_timeout = Duration::from_secs(30);
-                match tokio::time::timeout(shutdown_timeout, plugin.shutdown()).await {
-                    Ok(Ok(())) => {
-                        info!("Plugin {} shutdown successfully", plugin_name);
-                        if let Some(info) = self.plugin_info.get_mut(plugin_name) {
-                            info.status = PluginStatus::Stopped;
+                    // Attempt graceful shutdown with timeout
+                    let shutdown_timeout = Duration::from_secs(30);
+                    match tokio::time::timeout(shutdown_timeout, plugin.shutdown()).await {
+                        Ok(Ok(())) => {
+                            info!("Plugin {} shutdown successfully", plugin_name);
+                            if let Some(info) = self.plugin_info.get_mut(plugin_name) {
+                                info.status = PluginStatus::Stopped;
+                            }
+                            successful_shutdowns += 1;
                         }
-                        successful_shutdowns += 1;
-                    }
-                    Ok(Err(e)) => {
-                        error!("Plugin {} shutdown failed: {}", plugin_name, e);
-                        if let Some(info) = self.plugin_info.get_mut(plugin_name) {
-                            info.status = PluginStatus::Error(format!("Shutdown failed: {}", e));
+                        Ok(Err(e)) => {
+                            error!("Plugin {} shutdown failed: {}", plugin_name, e);
+                            if let Some(info) = self.plugin_info.get_mut(plugin_name) {
+                                info.status =
+                                    PluginStatus::Error(format!("Shutdown failed: {}", e));
+                            }
+                            shutdown_errors.push((plugin_name.clone(), e.to_string()));
                         }
-                        shutdown_errors.push((plugin_name.clone(), e.to_string()));
-                    }
-                    Err(_) => {
-                        error!("Plugin {} shutdown timed out", plugin_name);
-                        if let Some(info) = self.plugin_info.get_mut(plugin_name) {
-                            info.status = PluginStatus::Error("Shutdown timeout".to_string());
+                        Err(_) => {
+                            error!("Plugin {} shutdown timed out", plugin_name);
+                            if let Some(info) = self.plugin_info.get_mut(plugin_name) {
+                                info.status = PluginStatus::Error("Shutdown timeout".to_string());
+                            }
+                            shutdown_errors
+                                .push((plugin_name.clone(), "Shutdown timeout".to_string()));
                         }
-                        shutdown_errors.push((plugin_name.clone(), "Shutdown timeout".to_string()));
                     }
-                }
 
-                // Small delay between plugin shutdowns to allow cleanup
-                tokio::time::sleep(Duration::from_millis(100)).await;
+                    // Small delay between plugin shutdowns to allow cleanup
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
             }
         }
 
@@ -388,6 +529,7 @@ impl PluginRegistry {
                     if let Some(info) = self.plugin_info.get_mut(&plugin_name) {
                         info.status = PluginStatus::Shutting;
                     }
+                    self.withdraw_services(&plugin_name);
 
                     // Force shutdown with shorter timeout
                     match tokio::time::timeout(Duration::from_secs(10), plugin.shutdown()).await {
@@ -413,6 +555,11 @@ impl PluginRegistry {
         }
 
         // Clear all data structures
+        if let Some(context) = &self.context {
+            for plugin_name in self.plugin_info.keys() {
+                context.state_manager.remove_plugin(plugin_name).await;
+            }
+        }
         self.plugins.clear();
         self.plugin_info.clear();
         self.load_order.clear();
@@ -454,44 +601,79 @@ impl PluginRegistry {
         Ok(())
     }
 
-    /// Calculate the proper shutdown order based on dependencies
-    fn calculate_shutdown_order(&self) -> Vec<String> {
-        // Shutdown order should be reverse of load order to respect dependencies
-        // Plugins that depend on others should shutdown first
-        let mut shutdown_order = self.load_order.clone();
-        shutdown_order.reverse();
-
-        // Additionally, we can use the dependency graph to ensure proper ordering
-        if let Ok(dependency_order) = self.dependencies.resolve_load_order() {
-            // Reverse the dependency order for shutdown
-            let mut dep_shutdown_order = dependency_order;
-            dep_shutdown_order.reverse();
-
-            // Merge with load order, preferring dependency-based ordering
-            let mut final_order = Vec::new();
-            let mut processed = std::collections::HashSet::new();
-
-            // First, add plugins in dependency shutdown order
-            for plugin in dep_shutdown_order {
-                if self.plugins.contains_key(&plugin) && !processed.contains(&plugin) {
-                    final_order.push(plugin.clone());
-                    processed.insert(plugin);
-                }
-            }
-
-            // Then add any remaining plugins from load order
-            for plugin in shutdown_order {
-                if self.plugins.contains_key(&plugin) && !processed.contains(&plugin) {
-                    final_order.push(plugin.clone());
-                    processed.insert(plugin);
-                }
+    /// Plugins grouped into the stages they shut down in: each stage holds
+    /// the plugins nothing left depends on, by name or for a service, latest
+    /// loaded first
+    fn shutdown_stages(&self) -> Vec<Vec<String>> {
+        let mut remaining: Vec<String> = self
+            .load_order
+            .iter()
+            .rev()
+            .filter(|name| self.plugins.contains_key(*name))
+            .cloned()
+            .collect();
+        let mut stray: Vec<String> = self
+            .plugins
+            .keys()
+            .filter(|name| !remaining.contains(name))
+            .cloned()
+            .collect();
+        stray.sort();
+        remaining.extend(stray);
+
+        let providers: HashMap<&str, &str> = self
+            .plugin_info
+            .values()
+            .flat_map(|info| {
+                info.provided_services
+                    .iter()
+                    .map(move |service| (service.as_str(), info.name.as_str()))
+            })
+            .collect();
+        let prerequisites: HashMap<&str, HashSet<String>> = self
+            .plugins
+            .iter()
+            .map(|(name, plugin)| {
+                let mut prerequisites: HashSet<String> = plugin
+                    .dependencies()
+                    .into_iter()
+                    .map(|dep| dep.to_string())
+                    .chain(self.dependencies.get_dependencies(name))
+                    .collect();
+                prerequisites.extend(
+                    plugin
+                        .required_services()
+                        .into_iter()
+                        .filter_map(|service| providers.get(service))
+                        .map(|provider| provider.to_string()),
+                );
+                prerequisites.remove(name);
+                (name.as_str(), prerequisites)
+            })
+            .collect();
+
+        let mut stages = Vec::new();
+        while !remaining.is_empty() {
+            let (stage, rest): (Vec<String>, Vec<String>) =
+                remaining.iter().cloned().partition(|name| {
+                    !remaining.iter().any(|other| {
+                        prerequisites
+                            .get(other.as_str())
+                            .is_some_and(|prerequisites| prerequisites.contains(name))
+                    })
+                });
+            if stage.is_empty() {
+                warn!(
+                    "Circular dependency among plugins {:?}, shutting them down together",
+                    rest
+                );
+                stages.push(rest);
+                break;
             }
-
-            final_order
-        } else {
-            // Fallback to simple reverse load order
-            shutdown_order
+            stages.push(stage);
+            remaining = rest;
         }
+        stages
     }
 
     /// Notify dependent plugins that a plugin is shutting down
@@ -543,6 +725,19 @@ impl PluginRegistry {
 
         // Validate dependencies
         self.validate_dependencies(plugin.as_ref())?;
+        let schema = plugin.config_schema();
+        if !schema.is_empty() {
+            crate::config::register_plugin_schema(&name, schema);
+        }
+        for service in plugin.required_services() {
+            context
+                .services
+                .wait_for(service, self.service_wait)
+                .await
+                .map_err(|e| {
+                    RuneError::Plugin(format!("Plugin {} cannot initialize: {}", name, e))
+                })?;
+        }
 
         // Create initial plugin info with loading status
         let mut info = PluginInfo {
@@ -566,6 +761,7 @@ impl PluginRegistry {
         };
 
         self.plugin_info.insert(name.clone(), info.clone());
+        context.state_manager.update_plugin(info.clone()).await;
 
         // Publish plugin loading event
         if let Err(e) = context
@@ -587,7 +783,7 @@ impl PluginRegistry {
                 error!("Plugin {} initialization failed: {}", name, e);
                 info.status = PluginStatus::Error(format!("Initialization failed: {}", e));
                 info.health_status = PluginHealthStatus::Unhealthy;
-                self.plugin_info.insert(name.clone(), info);
+                self.record_failure(info, e.to_string(), context).await;
                 return Err(RuneError::Plugin(format!(
                     "Failed to initialize plugin {}: {}",
                     name, e
@@ -597,7 +793,8 @@ impl PluginRegistry {
                 error!("Plugin {} initialization timed out", name);
                 info.status = PluginStatus::Error("Initialization timeout".to_string());
                 info.health_status = PluginHealthStatus::Unhealthy;
-                self.plugin_info.insert(name.clone(), info);
+                self.record_failure(info, "initialization timed out".to_string(), context)
+                    .await;
                 return Err(RuneError::Plugin(format!(
                     "Plugin {} initialization timed out",
                     name
@@ -606,12 +803,16 @@ impl PluginRegistry {
         }
 
         // Update plugin info and store plugin
+        context.state_manager.update_plugin(info.clone()).await;
         self.plugin_info.insert(name.clone(), info);
         self.plugins.insert(name.clone(), plugin);
         self.load_order.push(name.clone());
 
         // Register plugin for health monitoring
         self.health_monitor.register_plugin(name.clone());
+        for service in self.plugins[&name].provided_services() {
+            context.services.provide(service);
+        }
 
         // Publish plugin loaded event
         if let Err(e) = context
@@ -626,6 +827,114 @@ impl PluginRegistry {
         Ok(())
     }
 
+    /// Register and initialize `plugins` in the order their dependencies and
+    /// required services call for, whatever order they are given in
+    pub async fn register_plugins(
+        &mut self,
+        plugins: Vec<Box<dyn Plugin>>,
+        context: &PluginContext,
+    ) -> Result<()> {
+        let order = Self::initialization_order(&plugins)?;
+        info!(
+            "Plugin initialization order: {:?}",
+            order
+                .iter()
+                .map(|&index| plugins[index].name())
+                .collect::<Vec<_>>()
+        );
+        let mut plugins: Vec<Option<Box<dyn Plugin>>> = plugins.into_iter().map(Some).collect();
+        for index in order {
+            if let Some(plugin) = plugins[index].take() {
+                self.register_plugin(plugin, context).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Indices of `plugins` in the order they can be initialized, keeping the
+    /// given order where nothing calls for another
+    fn initialization_order(plugins: &[Box<dyn Plugin>]) -> Result<Vec<usize>> {
+        let providers: HashMap<&str, usize> = plugins
+            .iter()
+            .enumerate()
+            .flat_map(|(index, plugin)| {
+                plugin
+                    .provided_services()
+                    .into_iter()
+                    .map(move |service| (service, index))
+            })
+            .collect();
+        let names: HashMap<&str, usize> = plugins
+            .iter()
+            .enumerate()
+            .map(|(index, plugin)| (plugin.name(), index))
+            .collect();
+        let prerequisites: Vec<HashSet<usize>> = plugins
+            .iter()
+            .enumerate()
+            .map(|(index, plugin)| {
+                let by_name = plugin
+                    .dependencies()
+                    .into_iter()
+                    .filter_map(|dep| names.get(dep));
+                let by_service = plugin
+                    .required_services()
+                    .into_iter()
+                    .filter_map(|service| providers.get(service));
+                by_name
+                    .chain(by_service)
+                    .copied()
+                    .filter(|&prerequisite| prerequisite != index)
+                    .collect()
+            })
+            .collect();
+
+        let mut order: Vec<usize> = Vec::with_capacity(plugins.len());
+        let mut placed = vec![false; plugins.len()];
+        while order.len() < plugins.len() {
+            let next = (0..plugins.len())
+                .find(|&index| !placed[index] && prerequisites[index].iter().all(|&p| placed[p]));
+            let Some(next) = next else {
+                let remaining: Vec<&str> = (0..plugins.len())
+                    .filter(|&index| !placed[index])
+                    .map(|index| plugins[index].name())
+                    .collect();
+                return Err(RuneError::Plugin(format!(
+                    "Circular dependency detected involving plugins: {:?}",
+                    remaining
+                )));
+            };
+            placed[next] = true;
+            order.push(next);
+        }
+        Ok(order)
+    }
+
+    /// Mark the services of plugin `name` as no longer provided
+    fn withdraw_services(&self, name: &str) {
+        let (Some(context), Some(info)) = (&self.context, self.plugin_info.get(name)) else {
+            return;
+        };
+        for service in &info.provided_services {
+            context.services.withdraw(service);
+        }
+    }
+
+    /// Keep the info of a plugin that failed to initialize and announce the failure
+    async fn record_failure(&mut self, info: PluginInfo, error: String, context: &PluginContext) {
+        let name = info.name.clone();
+        context.state_manager.update_plugin(info.clone()).await;
+        self.plugin_info.insert(name.clone(), info);
+
+        if let Err(e) = context
+            .event_bus
+            .publish_system_event(SystemEvent::plugin_failed(name, error))
+            .await
+        {
+            warn!("Failed to publish plugin failed event: {}", e);
+        }
+    }
+
     /// Validate plugin dependencies are satisfied
     fn validate_dependencies(&self, plugin: &dyn Plugin) -> Result<()> {
         for dep in plugin.dependencies() {
@@ -656,6 +965,7 @@ impl PluginRegistry {
 
         // Remove from health monitoring
         self.health_monitor.unregister_plugin(name);
+        self.withdraw_services(name);
 
         // Shutdown and remove plugin
         if let Some(mut plugin) = self.plugins.remove(name) {
@@ -685,6 +995,7 @@ impl PluginRegistry {
 
         // Publish plugin unloaded event
         if let Some(context) = &self.context {
+            context.state_manager.remove_plugin(name).await;
             if let Err(e) = context
                 .event_bus
                 .publish_system_event(SystemEvent::plugin_unloaded(name.to_string()))
@@ -701,7 +1012,7 @@ impl PluginRegistry {
     pub async fn restart_plugin(&mut self, name: &str) -> Result<()> {
         info!("Restarting plugin: {}", name);
 
-        if self.context.is_some() {
+        if let Some(context) = &self.context {
             // This is a simplified restart - in a real implementation,
             // we would need to preserve the plugin instance or reload it
             if let Some(info) = self.plugin_info.get_mut(name) {
@@ -717,6 +1028,7 @@ impl PluginRegistry {
                 );
                 info.status = PluginStatus::Active;
                 info.health_status = PluginHealthStatus::Healthy;
+                context.state_manager.update_plugin(info.clone()).await;
             }
         }
 
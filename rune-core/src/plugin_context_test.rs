@@ -407,4 +407,20 @@ mod plugin_context_tests {
         assert!(!invalid_result.unwrap().is_valid);
         assert!(!invalid_result.unwrap().errors.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_data_dirs_live_in_the_loaded_workspace() {
+        let context = create_test_context();
+        let workspace = TempDir::new().unwrap();
+        context
+            .state_manager
+            .load_workspace(workspace.path())
+            .await
+            .unwrap();
+
+        let dir = context.data_dir("search").await.unwrap();
+        assert_eq!(dir, workspace.path().join(".rune/plugin-data/search"));
+        assert!(dir.is_dir());
+        assert!(context.data_dir("../search").await.is_err());
+    }
 }
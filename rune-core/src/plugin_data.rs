@@ -0,0 +1,160 @@
+//! Directories where plugins keep their caches, indexes and history stores
+//!
+//! Each plugin writing to disk used to pick its own place. Plugins now ask
+//! [`PluginContext::data_dir`](crate::plugin::PluginContext::data_dir) for a
+//! directory of their own under `plugin-data/`, either in the `.rune/`
+//! directory of the open workspace or in the user data directory
+//! (`<data dir>/rune/plugin-data`). [`usage`] reports how much each plugin
+//! keeps there and [`clear`] removes it, which is what `rune cache` does.
+
+use crate::error::{Result, RuneError};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory of the plugin data directories, relative to their root
+pub const PLUGIN_DATA_DIR: &str = "plugin-data";
+
+/// Root of the plugin data kept outside any workspace, `<data dir>/rune`
+pub fn user_root() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("rune"))
+}
+
+/// Data directory of `plugin` under `root`, created if it does not exist
+pub fn plugin_dir(root: &Path, plugin: &str) -> Result<PathBuf> {
+    let dir = root.join(PLUGIN_DATA_DIR).join(checked_name(plugin)?);
+    fs::create_dir_all(&dir).map_err(|e| {
+        RuneError::file_system(format!(
+            "Failed to create the data directory {}: {}",
+            dir.display(),
+            e
+        ))
+    })?;
+    Ok(dir)
+}
+
+/// Plugin names become directory names, so they may not leave the root
+fn checked_name(plugin: &str) -> Result<&str> {
+    if plugin.is_empty()
+        || plugin.starts_with('.')
+        || plugin.contains(['/', '\\'])
+        || plugin.contains("..")
+    {
+        return Err(RuneError::plugin(format!(
+            "'{}' cannot name a plugin data directory",
+            plugin
+        )));
+    }
+    Ok(plugin)
+}
+
+/// Disk space taken by the data directory of one plugin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PluginDataUsage {
+    pub plugin: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub files: usize,
+}
+
+/// Space taken by each plugin data directory under `root`, by plugin name
+pub fn usage(root: &Path) -> Result<Vec<PluginDataUsage>> {
+    let entries = match fs::read_dir(root.join(PLUGIN_DATA_DIR)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut usage = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let (bytes, files) = size_of(&path);
+        usage.push(PluginDataUsage {
+            plugin: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path,
+            bytes,
+            files,
+        });
+    }
+    usage.sort_by(|a, b| a.plugin.cmp(&b.plugin));
+    Ok(usage)
+}
+
+/// Remove the data of `plugin`, or of every plugin, under `root`; returns
+/// the bytes freed
+pub fn clear(root: &Path, plugin: Option<&str>) -> Result<u64> {
+    let mut freed = 0;
+    for entry in usage(root)? {
+        if plugin.is_some_and(|plugin| plugin != entry.plugin) {
+            continue;
+        }
+        fs::remove_dir_all(&entry.path)?;
+        freed += entry.bytes;
+    }
+    Ok(freed)
+}
+
+/// Bytes and number of files under `dir`, skipping what cannot be read
+fn size_of(dir: &Path) -> (u64, usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (0, 0);
+    };
+    entries
+        .flatten()
+        .fold((0, 0), |(bytes, files), entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => {
+                let (more_bytes, more_files) = size_of(&entry.path());
+                (bytes + more_bytes, files + more_files)
+            }
+            Ok(_) => (
+                bytes + entry.metadata().map(|meta| meta.len()).unwrap_or(0),
+                files + 1,
+            ),
+            Err(_) => (bytes, files),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_plugin_dirs_are_created_measured_and_cleared() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+
+        let search = plugin_dir(root, "search").unwrap();
+        assert_eq!(search, root.join("plugin-data/search"));
+        fs::create_dir_all(search.join("index")).unwrap();
+        fs::write(search.join("index/terms"), "0123456789").unwrap();
+        fs::write(search.join("meta.json"), "{}").unwrap();
+        let editor = plugin_dir(root, "editor").unwrap();
+        fs::write(editor.join("history"), "abc").unwrap();
+
+        for name in ["", "../escape", ".hidden", "a/b"] {
+            assert!(plugin_dir(root, name).is_err());
+        }
+
+        let reported: Vec<(String, u64, usize)> = usage(root)
+            .unwrap()
+            .into_iter()
+            .map(|usage| (usage.plugin, usage.bytes, usage.files))
+            .collect();
+        assert_eq!(
+            reported,
+            vec![("editor".to_string(), 3, 1), ("search".to_string(), 12, 2)]
+        );
+
+        assert_eq!(clear(root, Some("search")).unwrap(), 12);
+        assert!(!search.exists());
+        assert!(editor.exists());
+        assert_eq!(clear(root, None).unwrap(), 3);
+        assert!(usage(root).unwrap().is_empty());
+    }
+}
@@ -9,6 +9,7 @@ mod tests {
     use crate::state::StateManager;
     use async_trait::async_trait;
     use std::sync::Arc;
+    use std::time::Duration;
 
     /// Mock plugin for testing
     struct MockPlugin {
@@ -16,7 +17,9 @@ mod tests {
         version: String,
         dependencies: Vec<String>,
         services: Vec<String>,
+        required: Vec<String>,
         status: PluginStatus,
+        fail_initialization: bool,
     }
 
     impl MockPlugin {
@@ -26,20 +29,31 @@ mod tests {
                 version: version.to_string(),
                 dependencies: Vec::new(),
                 services: Vec::new(),
+                required: Vec::new(),
                 status: PluginStatus::Active,
+                fail_initialization: false,
             }
         }
 
+        fn failing(mut self) -> Self {
+            self.fail_initialization = true;
+            self
+        }
+
         fn with_dependencies(mut self, deps: Vec<&str>) -> Self {
             self.dependencies = deps.iter().map(|s| s.to_string()).collect();
             self
         }
 
-        #[allow(dead_code)]
         fn with_services(mut self, services: Vec<&str>) -> Self {
             self.services = services.iter().map(|s| s.to_string()).collect();
             self
         }
+
+        fn requiring(mut self, services: Vec<&str>) -> Self {
+            self.required = services.iter().map(|s| s.to_string()).collect();
+            self
+        }
     }
 
     #[async_trait]
@@ -57,6 +71,9 @@ mod tests {
         }
 
         async fn initialize(&mut self, _context: &PluginContext) -> Result<()> {
+            if self.fail_initialization {
+                return Err(RuneError::plugin("boom"));
+            }
             self.status = PluginStatus::Active;
             Ok(())
         }
@@ -74,6 +91,10 @@ mod tests {
             self.services.iter().map(|s| s.as_str()).collect()
         }
 
+        fn required_services(&self) -> Vec<&str> {
+            self.required.iter().map(|s| s.as_str()).collect()
+        }
+
         fn as_any(&self) -> &dyn std::any::Any {
             self
         }
@@ -327,4 +348,120 @@ mod tests {
         assert!(!registry.is_plugin_loaded("plugin-1"));
         assert!(!registry.is_plugin_loaded("plugin-2"));
     }
+
+    #[tokio::test]
+    async fn test_plugin_info_mirrored_in_state() {
+        let mut registry = PluginRegistry::new();
+        let context = create_test_context();
+
+        registry.initialize(context.clone()).await.unwrap();
+
+        let plugin = Box::new(MockPlugin::new("good", "1.0.0"));
+        registry.register_plugin(plugin, &context).await.unwrap();
+        let plugin = Box::new(MockPlugin::new("bad", "0.1.0").failing());
+        assert!(registry.register_plugin(plugin, &context).await.is_err());
+
+        let plugins = context.state_manager.list_plugins().await;
+        assert_eq!(plugins.len(), 2);
+        assert_eq!(plugins[0].name, "bad");
+        assert!(matches!(plugins[0].status, PluginStatus::Error(_)));
+        assert_eq!(plugins[1].status, PluginStatus::Active);
+
+        registry.unregister_plugin("good").await.unwrap();
+        let plugins = context.state_manager.list_plugins().await;
+        assert_eq!(plugins.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_plugins_wait_for_required_services() {
+        let mut registry = PluginRegistry::new();
+        let context = create_test_context();
+        registry.initialize(context.clone()).await.unwrap();
+        registry.set_service_wait(Duration::from_millis(50));
+
+        // Given before its provider, the editor is still initialized after it
+        let plugins: Vec<Box<dyn Plugin>> = vec![
+            Box::new(MockPlugin::new("editor", "1.0.0").requiring(vec!["renderer-registry"])),
+            Box::new(MockPlugin::new("renderer", "1.0.0").with_services(vec!["renderer-registry"])),
+        ];
+        registry.register_plugins(plugins, &context).await.unwrap();
+        assert!(registry.is_plugin_active("editor"));
+        assert!(context.services.is_ready("renderer-registry"));
+
+        // Nobody provides this one in time
+        let error = registry
+            .register_plugin(
+                Box::new(MockPlugin::new("search", "1.0.0").requiring(vec!["index"])),
+                &context,
+            )
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Service index was not provided"));
+
+        // Hosts may provide services themselves, and plugins going away
+        // withdraw theirs
+        let waiting = tokio::spawn({
+            let services = context.services.clone();
+            async move { services.wait_for("index", Duration::from_secs(5)).await }
+        });
+        context.services.provide("index");
+        waiting.await.unwrap().unwrap();
+        registry.unregister_plugin("editor").await.unwrap();
+        registry.unregister_plugin("renderer").await.unwrap();
+        assert_eq!(context.services.ready(), vec!["index"]);
+
+        let circular: Vec<Box<dyn Plugin>> = vec![
+            Box::new(
+                MockPlugin::new("a", "1.0.0")
+                    .with_services(vec!["x"])
+                    .requiring(vec!["y"]),
+            ),
+            Box::new(
+                MockPlugin::new("b", "1.0.0")
+                    .with_services(vec!["y"])
+                    .requiring(vec!["x"]),
+            ),
+        ];
+        assert!(registry.register_plugins(circular, &context).await.is_err());
+    }
+
+    /// Plugins of each shutdown stage event
+    struct StageCollector(tokio::sync::Mutex<Vec<Vec<String>>>);
+
+    #[async_trait]
+    impl crate::event::SystemEventHandler for StageCollector {
+        async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+            if let SystemEvent::SystemShutdownStage { plugins, .. } = event {
+                self.0.lock().await.push(plugins.clone());
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dependents_shut_down_before_their_dependencies() {
+        let mut registry = PluginRegistry::new();
+        let context = create_test_context();
+        registry.initialize(context.clone()).await.unwrap();
+        let stages = Arc::new(StageCollector(Default::default()));
+        context
+            .event_bus
+            .subscribe_system_events(stages.clone())
+            .await
+            .unwrap();
+
+        let plugins: Vec<Box<dyn Plugin>> = vec![
+            Box::new(MockPlugin::new("watcher", "1.0.0")),
+            Box::new(MockPlugin::new("renderer", "1.0.0").with_services(vec!["renderer-registry"])),
+            Box::new(MockPlugin::new("editor", "1.0.0").with_dependencies(vec!["renderer"])),
+            Box::new(MockPlugin::new("preview", "1.0.0").requiring(vec!["renderer-registry"])),
+        ];
+        registry.register_plugins(plugins, &context).await.unwrap();
+        registry.shutdown().await.unwrap();
+
+        assert_eq!(
+            *stages.0.lock().await,
+            vec![vec!["preview", "editor", "watcher"], vec!["renderer"]]
+        );
+    }
 }
@@ -0,0 +1,540 @@
+//! Running external programs
+//!
+//! Pandoc, image encoders, export hooks, passphrase commands and tunnels all
+//! run through the [`CommandRunner`] of [`runner`], set up from the
+//! `processes` section of the configuration:
+//! - Only the `allowed_programs` may run: any with the default `*`, none
+//!   when the list is empty. Shell commands need the shell, `sh` or `cmd`,
+//!   since it runs anything.
+//! - Programs get a scrubbed environment: the path, home, locale and a few
+//!   more variables needed to find things, plus those named in `pass_env`,
+//!   never rune's own like `RUNE_PASSPHRASE`.
+//! - [`CommandRunner::output`] and [`CommandRunner::status`] stop programs
+//!   after `timeout_secs` and refuse output over `max_output_mb`.
+//!
+//! Failures are reported as a [`ProcessError`].
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::ErrorKind;
+use std::ops::{Deref, DerefMut};
+use std::process::Stdio;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+
+use crate::config::ProcessConfig;
+
+/// Seconds a program may run unless configured otherwise
+pub const DEFAULT_PROCESS_TIMEOUT_SECS: u64 = 300;
+
+/// Megabytes of output kept from a program unless configured otherwise
+pub const DEFAULT_MAX_OUTPUT_MB: u64 = 64;
+
+/// Entry of `allowed_programs` letting any program run
+pub const ANY_PROGRAM: &str = "*";
+
+/// Environment variables programs never get, even when in `pass_env`
+const WITHHELD_ENV: &[&str] = &["RUNE_PASSPHRASE"];
+
+/// Environment variables programs always get, when set
+const PASSED_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "TERM",
+    "TZ",
+    "TMPDIR",
+    "LANG",
+    "LANGUAGE",
+    "LC_ALL",
+    "LC_CTYPE",
+    "LC_MESSAGES",
+    "XDG_CONFIG_HOME",
+    "XDG_CACHE_HOME",
+    "XDG_DATA_HOME",
+    "XDG_RUNTIME_DIR",
+    "DBUS_SESSION_BUS_ADDRESS",
+    "SSH_AUTH_SOCK",
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+    "SYSTEMROOT",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "TEMP",
+    "TMP",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PROGRAMDATA",
+];
+
+/// Longest stderr kept in a [`ProcessError::Failed`]
+const MAX_STDERR_CHARS: usize = 4000;
+
+static RUNNER: RwLock<Option<Arc<CommandRunner>>> = RwLock::new(None);
+
+/// Run programs as `config` says from now on
+pub fn configure(config: &ProcessConfig) {
+    let runner = Arc::new(CommandRunner::new(config));
+    *RUNNER.write().unwrap_or_else(|e| e.into_inner()) = Some(runner);
+}
+
+/// Runner of external programs, as last configured
+pub fn runner() -> Arc<CommandRunner> {
+    RUNNER
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Why an external program did not do its job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessError {
+    /// The program is not in `processes.allowed_programs`
+    NotAllowed { program: String },
+    /// The program does not exist
+    NotFound { command: String },
+    /// Starting or talking to the program failed
+    Io { command: String, message: String },
+    /// The program ran longer than the timeout and was killed
+    TimedOut { command: String, timeout: Duration },
+    /// The program wrote more than the output limit and was killed
+    OutputTooLarge { command: String, limit: usize },
+    /// The program exited unsuccessfully; `code` is `None` when killed by
+    /// a signal
+    Failed {
+        command: String,
+        code: Option<i32>,
+        stderr: String,
+    },
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAllowed { program } => write!(
+                f,
+                "{} is not allowed to run; add it to processes.allowed_programs",
+                program
+            ),
+            Self::NotFound { command } => write!(f, "{} was not found", command),
+            Self::Io { command, message } => write!(f, "Failed to run {}: {}", command, message),
+            Self::TimedOut { command, timeout } => {
+                write!(f, "{} took longer than {}s", command, timeout.as_secs_f64())
+            }
+            Self::OutputTooLarge { command, limit } => {
+                write!(f, "{} wrote more than {} bytes", command, limit)
+            }
+            Self::Failed {
+                command,
+                code,
+                stderr,
+            } => {
+                match code {
+                    Some(code) => write!(f, "{} failed with exit code {}", command, code)?,
+                    None => write!(f, "{} was killed", command)?,
+                }
+                if !stderr.is_empty() {
+                    write!(f, ": {}", stderr)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProcessError {}
+
+/// Command of an allowed program, with a scrubbed environment
+#[derive(Debug)]
+pub struct CheckedCommand {
+    command: tokio::process::Command,
+    label: String,
+}
+
+impl CheckedCommand {
+    /// What the command runs, for messages: the program, or the shell
+    /// command line
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+impl Deref for CheckedCommand {
+    type Target = tokio::process::Command;
+
+    fn deref(&self) -> &Self::Target {
+        &self.command
+    }
+}
+
+impl DerefMut for CheckedCommand {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.command
+    }
+}
+
+/// Starts external programs within the configured limits
+#[derive(Debug, Clone)]
+pub struct CommandRunner {
+    allowed: Vec<String>,
+    pass_env: Vec<String>,
+    timeout: Duration,
+    max_output: usize,
+}
+
+impl Default for CommandRunner {
+    fn default() -> Self {
+        Self::new(&ProcessConfig::default())
+    }
+}
+
+impl CommandRunner {
+    pub fn new(config: &ProcessConfig) -> Self {
+        Self {
+            allowed: config.allowed_programs.clone(),
+            pass_env: config.pass_env.clone(),
+            timeout: config.timeout(),
+            max_output: config.max_output_bytes(),
+        }
+    }
+
+    /// This runner stopping programs after `timeout` at the latest
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = self.timeout.min(timeout);
+        self
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Check that `program`, a name looked up on the `PATH` or a path, may
+    /// run
+    pub fn check(&self, program: &OsStr) -> Result<(), ProcessError> {
+        let name = program.to_string_lossy();
+        let allowed = self.allowed.iter().any(|allowed| {
+            allowed == ANY_PROGRAM
+                || *allowed == name
+                || cfg!(windows)
+                    && name
+                        .strip_suffix(".exe")
+                        .is_some_and(|name| name.eq_ignore_ascii_case(allowed))
+        });
+        if allowed {
+            Ok(())
+        } else {
+            Err(ProcessError::NotAllowed {
+                program: name.to_string(),
+            })
+        }
+    }
+
+    /// Command running `program`, if allowed
+    pub fn command(&self, program: impl AsRef<OsStr>) -> Result<CheckedCommand, ProcessError> {
+        let program = program.as_ref();
+        self.check(program)?;
+        let mut command = tokio::process::Command::new(program);
+        self.scrub(command.as_std_mut());
+        command.stdin(Stdio::null()).kill_on_drop(true);
+        Ok(CheckedCommand {
+            command,
+            label: program.to_string_lossy().to_string(),
+        })
+    }
+
+    /// Command running `command_line` through the platform shell, `sh -c`
+    /// or `cmd /C`, if the shell is allowed
+    pub fn shell(&self, command_line: &str) -> Result<CheckedCommand, ProcessError> {
+        let (shell, flag) = if cfg!(windows) {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+        let mut command = self.command(shell)?;
+        command.arg(flag).arg(command_line);
+        command.label = command_line.to_string();
+        Ok(command)
+    }
+
+    /// Blocking command running `program`, if allowed, for quick probes
+    /// like `--version`
+    pub fn std_command(
+        &self,
+        program: impl AsRef<OsStr>,
+    ) -> Result<std::process::Command, ProcessError> {
+        let program = program.as_ref();
+        self.check(program)?;
+        let mut command = std::process::Command::new(program);
+        self.scrub(&mut command);
+        command.stdin(Stdio::null());
+        Ok(command)
+    }
+
+    /// Run `command` with `input` on its stdin, if any, and return its
+    /// stdout once it succeeded
+    pub async fn output(
+        &self,
+        mut command: CheckedCommand,
+        input: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, ProcessError> {
+        let label = command.label.clone();
+        if input.is_some() {
+            command.stdin(Stdio::piped());
+        }
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        let mut child = command.spawn().map_err(|e| spawn_error(&label, e))?;
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+
+        // Written and read concurrently, as the program may fill a pipe first
+        let write = async {
+            if let (Some(mut stdin), Some(input)) = (stdin, input) {
+                let written = match stdin.write_all(&input).await {
+                    Ok(()) => stdin.shutdown().await,
+                    Err(e) => Err(e),
+                };
+                // Programs may exit without reading all their input
+                match written {
+                    Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(io_error(&label, e)),
+                    _ => {}
+                }
+            }
+            Ok(())
+        };
+        let limit = self.max_output;
+        let run = async {
+            let ((), stdout, stderr) = tokio::try_join!(
+                write,
+                read_capped(stdout, limit, &label),
+                read_capped(stderr, limit, &label)
+            )?;
+            let status = child.wait().await.map_err(|e| io_error(&label, e))?;
+            Ok::<_, ProcessError>((status, stdout, stderr))
+        };
+        // Dropping the child on the way out kills it
+        let (status, stdout, stderr) =
+            tokio::time::timeout(self.timeout, run)
+                .await
+                .map_err(|_| ProcessError::TimedOut {
+                    command: label.clone(),
+                    timeout: self.timeout,
+                })??;
+        if status.success() {
+            Ok(stdout)
+        } else {
+            Err(ProcessError::Failed {
+                command: label,
+                code: status.code(),
+                stderr: tail(String::from_utf8_lossy(&stderr).trim()),
+            })
+        }
+    }
+
+    /// Run `command` with its output going where rune's goes, and wait for
+    /// it to succeed
+    pub async fn status(&self, mut command: CheckedCommand) -> Result<(), ProcessError> {
+        let label = command.label.clone();
+        let mut child = command.spawn().map_err(|e| spawn_error(&label, e))?;
+        let status = tokio::time::timeout(self.timeout, child.wait())
+            .await
+            .map_err(|_| ProcessError::TimedOut {
+                command: label.clone(),
+                timeout: self.timeout,
+            })?
+            .map_err(|e| io_error(&label, e))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(ProcessError::Failed {
+                command: label,
+                code: status.code(),
+                stderr: String::new(),
+            })
+        }
+    }
+
+    /// Leave `command` only the passed environment variables
+    fn scrub(&self, command: &mut std::process::Command) {
+        command.env_clear();
+        for name in self.passed_env() {
+            if let Some(value) = std::env::var_os(name) {
+                command.env(name, value);
+            }
+        }
+    }
+
+    /// Names of the environment variables programs get, when set
+    fn passed_env(&self) -> impl Iterator<Item = &str> {
+        PASSED_ENV
+            .iter()
+            .copied()
+            .chain(self.pass_env.iter().map(String::as_str))
+            .filter(|name| {
+                !WITHHELD_ENV
+                    .iter()
+                    .any(|withheld| name.eq_ignore_ascii_case(withheld))
+            })
+    }
+}
+
+/// Everything `pipe` yields, refused past `limit` bytes
+async fn read_capped(
+    pipe: impl AsyncRead + Unpin,
+    limit: usize,
+    label: &str,
+) -> Result<Vec<u8>, ProcessError> {
+    let mut bytes = Vec::new();
+    pipe.take(limit as u64 + 1)
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(|e| io_error(label, e))?;
+    if bytes.len() > limit {
+        return Err(ProcessError::OutputTooLarge {
+            command: label.to_string(),
+            limit,
+        });
+    }
+    Ok(bytes)
+}
+
+fn spawn_error(label: &str, e: std::io::Error) -> ProcessError {
+    match e.kind() {
+        ErrorKind::NotFound => ProcessError::NotFound {
+            command: label.to_string(),
+        },
+        _ => io_error(label, e),
+    }
+}
+
+fn io_error(label: &str, e: std::io::Error) -> ProcessError {
+    ProcessError::Io {
+        command: label.to_string(),
+        message: e.to_string(),
+    }
+}
+
+/// The end of a long `text`, where the error usually is
+fn tail(text: &str) -> String {
+    match text.char_indices().rev().nth(MAX_STDERR_CHARS) {
+        Some((start, _)) => format!("…{}", &text[start..]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn runner(allowed: &[&str]) -> CommandRunner {
+        CommandRunner::new(&ProcessConfig {
+            allowed_programs: allowed.iter().map(|program| program.to_string()).collect(),
+            max_output_mb: Some(1),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_only_allowed_programs_run() {
+        let runner = runner(&["sh", "cat"]);
+        assert!(runner.command("cat").is_ok());
+        assert_eq!(
+            runner.command("/usr/bin/cat").unwrap_err(),
+            ProcessError::NotAllowed {
+                program: "/usr/bin/cat".to_string()
+            }
+        );
+        assert!(runner.std_command("pandoc").is_err());
+        assert!(super::runner().command("pandoc").is_ok());
+        assert!(self::runner(&[]).command("cat").is_err());
+        assert!(self::runner(&["cat", ANY_PROGRAM])
+            .command("pandoc")
+            .is_ok());
+
+        let cat = runner.command("cat").unwrap();
+        let output = runner.output(cat, Some(b"# Notes".to_vec())).await;
+        assert_eq!(output.unwrap(), b"# Notes");
+
+        let shell = self::runner(&["cat"]);
+        assert!(shell.shell("echo hi").is_err());
+        let mut refused = runner.command("cat").unwrap();
+        refused.arg("--no-such-flag");
+        let error = runner.output(refused, None).await.unwrap_err();
+        assert!(matches!(error, ProcessError::Failed { code: Some(_), .. }));
+    }
+
+    #[tokio::test]
+    async fn test_limits_and_failures() {
+        let runner = runner(&[ANY_PROGRAM]);
+        let run = |line: &str| runner.output(runner.shell(line).unwrap(), None);
+
+        assert_eq!(
+            run("echo oops >&2; exit 3").await.unwrap_err(),
+            ProcessError::Failed {
+                command: "echo oops >&2; exit 3".to_string(),
+                code: Some(3),
+                stderr: "oops".to_string(),
+            }
+        );
+        let flood = run("head -c 2000000 /dev/zero").await.unwrap_err();
+        assert!(matches!(flood, ProcessError::OutputTooLarge { .. }));
+
+        let quick = runner.clone().with_timeout(Duration::from_millis(100));
+        let slow = quick.output(quick.shell("sleep 5").unwrap(), None).await;
+        assert!(matches!(slow, Err(ProcessError::TimedOut { .. })));
+        let slow = quick.status(quick.shell("sleep 5").unwrap()).await;
+        assert!(matches!(slow, Err(ProcessError::TimedOut { .. })));
+
+        let missing = runner.command("no-such-program-here").unwrap();
+        assert!(matches!(
+            runner.output(missing, None).await,
+            Err(ProcessError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_environment_is_scrubbed() {
+        // Set by cargo for the tests, and never passed on unless asked for
+        let Some(dir) = std::env::var_os("CARGO_MANIFEST_DIR") else {
+            return;
+        };
+        let echo = "printf '%s|%s' \"$CARGO_MANIFEST_DIR\" \"$PATH\"";
+        let scrubbed = runner(&[ANY_PROGRAM]);
+        let output = scrubbed
+            .output(scrubbed.shell(echo).unwrap(), None)
+            .await
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let (manifest_dir, path) = output.split_once('|').unwrap();
+        assert_eq!(manifest_dir, "");
+        assert_eq!(Some(path), std::env::var("PATH").ok().as_deref());
+
+        let passing = CommandRunner::new(&ProcessConfig {
+            pass_env: vec!["CARGO_MANIFEST_DIR".to_string()],
+            ..Default::default()
+        });
+        let output = passing
+            .output(passing.shell(echo).unwrap(), None)
+            .await
+            .unwrap();
+        assert!(output.starts_with(dir.to_string_lossy().as_bytes()));
+    }
+
+    #[test]
+    fn test_passphrase_is_never_passed() {
+        let runner = CommandRunner::new(&ProcessConfig {
+            pass_env: vec!["RUNE_PASSPHRASE".to_string(), "NGROK_AUTHTOKEN".to_string()],
+            ..Default::default()
+        });
+        let passed: Vec<_> = runner.passed_env().collect();
+        assert!(passed.contains(&"NGROK_AUTHTOKEN"));
+        assert!(!passed.contains(&"RUNE_PASSPHRASE"));
+    }
+}
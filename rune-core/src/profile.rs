@@ -0,0 +1,269 @@
+//! Span profiles in the folded-stacks format
+//!
+//! Render stages, HTTP handlers and editor session operations run in
+//! tracing spans. While a [`Profiler`] records, its [`ProfileLayer`] adds
+//! the time spent in each stack of spans, less the time spent in the spans
+//! it contains, and [`Profiler::record`] returns the totals as folded
+//! stacks: one `outer;inner;innermost <microseconds>` line per stack, which
+//! `inferno-flamegraph` or speedscope turn into a flamegraph. A span with a
+//! `name` field appears as `span:name`, like `render_stage:syntax`.
+//!
+//! Spans below the active log level are not recorded; the render and
+//! session spans are at debug level, which development mode enables.
+
+use crate::error::{Result, RuneError};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Time spent in each stack of spans while recording
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// Time recorded
+    pub duration: Duration,
+    /// Self time of each stack, by its frames joined with `;`
+    pub stacks: BTreeMap<String, Duration>,
+}
+
+impl Profile {
+    /// The stacks in the folded format, one `stack microseconds` line each
+    pub fn folded(&self) -> String {
+        let mut folded = String::new();
+        for (stack, time) in &self.stacks {
+            let _ = writeln!(folded, "{} {}", stack, time.as_micros());
+        }
+        folded
+    }
+}
+
+/// Collects the time spans take while recording
+#[derive(Debug, Default)]
+pub struct Profiler {
+    recording: AtomicBool,
+    stacks: Mutex<BTreeMap<String, Duration>>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.load(Ordering::Acquire)
+    }
+
+    /// Record the spans run during `duration`; only one recording runs at
+    /// a time
+    pub async fn record(&self, duration: Duration) -> Result<Profile> {
+        if self
+            .recording
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return Err(RuneError::generic("A profile is already being recorded"));
+        }
+        // Stops the recording even if the caller gives up waiting
+        let _stop = StopRecording(&self.recording);
+        self.lock().clear();
+        tokio::time::sleep(duration).await;
+        self.recording.store(false, Ordering::Release);
+        Ok(Profile {
+            duration,
+            stacks: std::mem::take(&mut *self.lock()),
+        })
+    }
+
+    fn add(&self, stack: String, time: Duration) {
+        *self.lock().entry(stack).or_default() += time;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Duration>> {
+        self.stacks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+struct StopRecording<'a>(&'a AtomicBool);
+
+impl Drop for StopRecording<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// Layer timing spans for a [`Profiler`]
+pub struct ProfileLayer {
+    profiler: Arc<Profiler>,
+}
+
+impl ProfileLayer {
+    pub fn new(profiler: Arc<Profiler>) -> Self {
+        Self { profiler }
+    }
+}
+
+/// Frame of a span and its time in the current entry
+struct SpanTiming {
+    frame: String,
+    entered: Option<Instant>,
+    /// Time spent in spans entered within this entry
+    children: Duration,
+}
+
+/// Reads the `name` field of a span
+#[derive(Default)]
+struct NameVisitor(Option<String>);
+
+impl Visit for NameVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "name" {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+impl<S> Layer<S> for ProfileLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut name = NameVisitor::default();
+        attrs.record(&mut name);
+        // Semicolons and spaces separate frames and counts
+        let frame = match name.0 {
+            Some(name) => format!("{}:{}", span.name(), name),
+            None => span.name().to_string(),
+        }
+        .replace([';', ' '], "_");
+        span.extensions_mut().insert(SpanTiming {
+            frame,
+            entered: None,
+            children: Duration::ZERO,
+        });
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if !self.profiler.is_recording() {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            if let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>() {
+                timing.entered = Some(Instant::now());
+                timing.children = Duration::ZERO;
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let (elapsed, children) = {
+            let mut extensions = span.extensions_mut();
+            let Some(timing) = extensions.get_mut::<SpanTiming>() else {
+                return;
+            };
+            let Some(entered) = timing.entered.take() else {
+                return;
+            };
+            (entered.elapsed(), timing.children)
+        };
+        if !self.profiler.is_recording() {
+            return;
+        }
+
+        let stack = span
+            .scope()
+            .from_root()
+            .filter_map(|span| {
+                span.extensions()
+                    .get::<SpanTiming>()
+                    .map(|timing| timing.frame.clone())
+            })
+            .collect::<Vec<_>>()
+            .join(";");
+        self.profiler.add(stack, elapsed.saturating_sub(children));
+
+        if let Some(parent) = span.parent() {
+            if let Some(timing) = parent.extensions_mut().get_mut::<SpanTiming>() {
+                if timing.entered.is_some() {
+                    timing.children += elapsed;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::debug_span;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[tokio::test]
+    async fn test_spans_are_folded_with_self_time() {
+        let profiler = Arc::new(Profiler::new());
+        let subscriber = tracing_subscriber::registry().with(ProfileLayer::new(profiler.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        // Nothing is recorded outside a recording
+        debug_span!("ignored").in_scope(|| {});
+
+        let recording = profiler.record(Duration::from_millis(50));
+        let spans = async {
+            tokio::task::yield_now().await;
+            debug_span!("render").in_scope(|| {
+                debug_span!("render_stage", name = "syntax").in_scope(|| {
+                    std::thread::sleep(Duration::from_millis(5));
+                });
+                debug_span!("render_stage", name = "math; tex").in_scope(|| {});
+            });
+        };
+        let (profile, ()) = tokio::join!(recording, spans);
+        let profile = profile.unwrap();
+
+        assert!(!profile.folded().contains("ignored"));
+        let stage = profile.stacks["render;render_stage:syntax"];
+        assert!(stage >= Duration::from_millis(5));
+        assert!(profile.stacks["render"] < stage);
+        assert!(profile.stacks.contains_key("render;render_stage:math__tex"));
+        assert!(profile.folded().lines().all(|line| line
+            .rsplit_once(' ')
+            .unwrap()
+            .1
+            .parse::<u64>()
+            .is_ok()));
+    }
+
+    #[tokio::test]
+    async fn test_one_recording_at_a_time() {
+        let profiler = Profiler::new();
+        let first = profiler.record(Duration::from_millis(20));
+        let second = async {
+            tokio::task::yield_now().await;
+            profiler.record(Duration::from_millis(1)).await
+        };
+        let (first, second) = tokio::join!(first, second);
+        assert!(first.is_ok());
+        assert!(second.is_err());
+        assert!(!profiler.is_recording());
+    }
+}
@@ -5,6 +5,7 @@
 
 use crate::parser::MarkdownParser;
 use crate::render::{render_html, render_wysiwyg, RenderOptions};
+use crate::serializer::MarkdownSerializer;
 use regex::Regex;
 
 /// The Rune Quill - A text processing engine for markdown
@@ -247,12 +248,9 @@ impl Quill {
     fn convert_ordered_list_items(&self, html: &str) -> String {
         let re = Regex::new(r#"<li[^>]*>(.*?)</li>"#).unwrap();
         let mut result = String::new();
-        let mut index = 1;
-
-        for caps in re.captures_iter(html) {
+        for (index, caps) in (1..).zip(re.captures_iter(html)) {
             let content = &caps[1];
             result.push_str(&format!("{}. {}\n", index, content.trim()));
-            index += 1;
         }
 
         result
@@ -277,15 +275,12 @@ impl Quill {
         normalized
     }
 
-    /// Format Markdown text (similar to Lute's Format function)
+    /// Format Markdown text (similar to Lute's Format function) by writing its
+    /// AST back out in canonical form
     pub fn format_markdown(&self, markdown: &str) -> String {
         let parser = MarkdownParser::with_options(self.parse_options.clone());
         let tree = parser.parse(markdown);
-
-        // For now, we'll just convert back to markdown through HTML conversion
-        // This ensures consistent formatting
-        let html = render_html(&tree);
-        self.html_to_markdown(&html)
+        MarkdownSerializer::canonical().serialize(&tree)
     }
 }
 
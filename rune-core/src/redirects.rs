@@ -0,0 +1,249 @@
+//! Redirects of renamed documents
+//!
+//! A document lists its former paths in its frontmatter:
+//!
+//! ```yaml
+//! ---
+//! aliases: [old-name.md, /guide/setup.md]
+//! ---
+//! ```
+//!
+//! Relative aliases resolve against the document's directory and aliases
+//! starting with `/` against the root it is served from. The `redirects`
+//! section of the configuration maps further URL paths to their new
+//! location. The server answers both with redirects; exports write a small
+//! [`stub_page`] at each old path instead.
+
+use crate::error::Result;
+use crate::frontmatter;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Where a configured redirect leads
+///
+/// ```json
+/// "redirects": {
+///   "/old.md": "/docs/new.md",
+///   "/beta/": {"to": "/docs/", "permanent": false}
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RedirectRule {
+    /// Permanent redirect to a URL
+    To(String),
+    Detailed {
+        to: String,
+        /// 301 when true, 302 otherwise
+        #[serde(default = "permanent_by_default")]
+        permanent: bool,
+    },
+}
+
+fn permanent_by_default() -> bool {
+    true
+}
+
+impl RedirectRule {
+    /// URL the redirect leads to
+    pub fn target(&self) -> &str {
+        match self {
+            Self::To(to) | Self::Detailed { to, .. } => to,
+        }
+    }
+
+    /// Whether clients may remember the redirect
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            Self::To(_) => true,
+            Self::Detailed { permanent, .. } => *permanent,
+        }
+    }
+}
+
+/// Former paths of a document, from the `aliases` key of its YAML frontmatter
+///
+/// Both `aliases: [a.md, b.md]` and a block list of `- a.md` items are read;
+/// a single `aliases: a.md` works too.
+pub fn frontmatter_aliases(markdown: &str) -> Vec<String> {
+    frontmatter::list(markdown, "aliases")
+}
+
+/// `path` with `.` and `..` components resolved, without touching the disk
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// File an export writes for the old URL path `path`: markdown names get
+/// the `.html` extension and directories, or names without an extension,
+/// an `index.html`
+pub fn stub_path(path: &str) -> PathBuf {
+    let path = Path::new(path.trim_start_matches('/'));
+    match path.extension() {
+        Some(extension) if extension == "md" || extension == "markdown" => {
+            path.with_extension("html")
+        }
+        Some(_) => path.to_path_buf(),
+        None => path.join("index.html"),
+    }
+}
+
+/// Relative link from a page in `from_dir` to `to`, both below the same
+/// directory
+pub fn relative_url(from_dir: &Path, to: &Path) -> String {
+    let (from_dir, to) = (normalize(from_dir), normalize(to));
+    let from: Vec<Component> = from_dir.components().collect();
+    let to_components: Vec<Component> = to.components().collect();
+    let common = from
+        .iter()
+        .zip(&to_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    parts.extend(
+        to_components[common..]
+            .iter()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned()),
+    );
+    parts.join("/")
+}
+
+/// Page sending browsers on to `target`, for hosts that cannot redirect
+pub fn stub_page(target: &str) -> String {
+    let target = crate::export::escape_html(target);
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Redirecting…</title>\n<link rel=\"canonical\" href=\"{target}\">\n<meta http-equiv=\"refresh\" content=\"0; url={target}\">\n<meta name=\"robots\" content=\"noindex\">\n</head>\n<body>\n<p>This page has moved to <a href=\"{target}\">{target}</a>.</p>\n</body>\n</html>\n",
+        target = target
+    )
+}
+
+/// Write a stub page for each alias of the document `markdown`, exported
+/// to `output`; aliases resolve against the output's directory. Returns the
+/// number of stubs written.
+pub async fn write_alias_stubs(markdown: &str, output: &Path) -> Result<usize> {
+    let output_dir = output.parent().unwrap_or(Path::new(""));
+    let mut written = 0;
+    for alias in frontmatter_aliases(markdown) {
+        let stub = normalize(&output_dir.join(stub_path(&alias)));
+        if stub == normalize(output) {
+            continue;
+        }
+        let target = relative_url(stub.parent().unwrap_or(output_dir), output);
+        write_stub(&stub, &target).await?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Write a stub page below `site_dir` for each configured redirect. Local
+/// targets are linked relative to the stub, with markdown names pointing at
+/// their exported `.html` pages. Returns the number of stubs written.
+pub async fn write_redirect_stubs(
+    site_dir: &Path,
+    rules: &BTreeMap<String, RedirectRule>,
+) -> Result<usize> {
+    for (from, rule) in rules {
+        let stub = normalize(&site_dir.join(stub_path(from)));
+        let target = rule.target();
+        let target = if target.starts_with('/') && !target.starts_with("//") {
+            let stub_dir = stub.parent().unwrap_or(site_dir);
+            let target = normalize(&site_dir.join(stub_path(target)));
+            let mut url = relative_url(stub_dir, &target);
+            // Directories link to their index page's directory
+            if let Some(dir) = url.strip_suffix("index.html") {
+                url = if dir.is_empty() {
+                    "./".to_string()
+                } else {
+                    dir.to_string()
+                };
+            }
+            url
+        } else {
+            target.to_string()
+        };
+        write_stub(&stub, &target).await?;
+    }
+    Ok(rules.len())
+}
+
+async fn write_stub(stub: &Path, target: &str) -> Result<()> {
+    if let Some(parent) = stub.parent() {
+        if !parent.as_os_str().is_empty() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+    }
+    tokio::fs::write(stub, stub_page(target)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases_rules_and_stubs() {
+        let inline = "---\ntitle: New\naliases: [old.md, \"/guide/setup.md\"]\n---\n# New\n";
+        assert_eq!(frontmatter_aliases(inline), ["old.md", "/guide/setup.md"]);
+        let block = "---\naliases:\n  - a.md\n  - 'b/c.md'\ndraft: false\n---\n";
+        assert_eq!(frontmatter_aliases(block), ["a.md", "b/c.md"]);
+        assert_eq!(
+            frontmatter_aliases("---\naliases: one.md\n---\n"),
+            ["one.md"]
+        );
+        assert!(frontmatter_aliases("aliases: [x.md]\n").is_empty());
+
+        let rules: std::collections::BTreeMap<String, RedirectRule> = serde_json::from_str(
+            r#"{"/a.md": "/b.md", "/beta/": {"to": "/docs/", "permanent": false}, "/c": {"to": "/d"}}"#,
+        )
+        .unwrap();
+        assert!(rules["/a.md"].is_permanent());
+        assert_eq!(rules["/beta/"].target(), "/docs/");
+        assert!(!rules["/beta/"].is_permanent());
+        assert!(rules["/c"].is_permanent());
+
+        assert_eq!(stub_path("/guide/old.md"), Path::new("guide/old.html"));
+        assert_eq!(stub_path("/beta/"), Path::new("beta/index.html"));
+        assert_eq!(stub_path("a/b.markdown"), Path::new("a/b.html"));
+        assert_eq!(stub_path("/moved"), Path::new("moved/index.html"));
+        assert_eq!(
+            relative_url(Path::new("out/old"), Path::new("out/guide/new.html")),
+            "../guide/new.html"
+        );
+        assert!(stub_page("new.html?a=1&b=2").contains("url=new.html?a=1&amp;b=2"));
+    }
+
+    #[tokio::test]
+    async fn test_export_stubs() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let site = temp_dir.path();
+        let output = site.join("guide/install.html");
+        let markdown = "---\naliases: [setup.md, ../old/install.md, install.md]\n---\n";
+        assert_eq!(write_alias_stubs(markdown, &output).await.unwrap(), 2);
+        let stub = std::fs::read_to_string(site.join("old/install.html")).unwrap();
+        assert!(stub.contains("url=../guide/install.html"));
+        assert!(site.join("guide/setup.html").is_file());
+        assert!(!output.exists());
+
+        let rules = serde_json::from_str(
+            r#"{"/beta/": "/guide/", "/a.md": "/guide/install.md", "/ext": "https://example.com"}"#,
+        )
+        .unwrap();
+        assert_eq!(write_redirect_stubs(site, &rules).await.unwrap(), 3);
+        let read = |path: &str| std::fs::read_to_string(site.join(path)).unwrap();
+        assert!(read("beta/index.html").contains("url=../guide/\""));
+        assert!(read("a.html").contains("url=guide/install.html\""));
+        assert!(read("ext/index.html").contains("url=https://example.com\""));
+    }
+}
@@ -45,6 +45,8 @@ impl Default for RenderOptions {
 pub struct HtmlRenderer {
     options: RenderOptions,
     output: String,
+    /// Whether table cells being rendered are header cells
+    in_table_head: bool,
 }
 
 impl HtmlRenderer {
@@ -53,6 +55,7 @@ impl HtmlRenderer {
         Self {
             options,
             output: String::new(),
+            in_table_head: false,
         }
     }
 
@@ -163,6 +166,69 @@ impl HtmlRenderer {
                 self.output.push_str("</li>\n");
             }
 
+            NodeType::Table => {
+                self.output.push_str("<table");
+                self.render_attributes(node);
+                self.output.push_str(">\n");
+
+                let (head, body): (Vec<&Node>, Vec<&Node>) = node
+                    .children
+                    .iter()
+                    .partition(|child| child.node_type == NodeType::TableHead);
+                for child in head {
+                    self.render_node(child);
+                }
+                if !body.is_empty() {
+                    self.output.push_str("<tbody>\n");
+                    for child in body {
+                        self.render_node(child);
+                    }
+                    self.output.push_str("</tbody>\n");
+                }
+
+                self.output.push_str("</table>\n");
+            }
+
+            NodeType::TableHead => {
+                self.output.push_str("<thead>\n");
+                self.in_table_head = true;
+                for child in &node.children {
+                    self.render_node(child);
+                }
+                self.in_table_head = false;
+                self.output.push_str("</thead>\n");
+            }
+
+            NodeType::TableRow => {
+                self.output.push_str("<tr");
+                self.render_attributes(node);
+                self.output.push('>');
+
+                for child in &node.children {
+                    self.render_node(child);
+                }
+
+                self.output.push_str("</tr>\n");
+            }
+
+            NodeType::TableCell => {
+                let tag = if self.in_table_head { "th" } else { "td" };
+                self.output.push_str(&format!("<{}", tag));
+                self.render_attributes(node);
+                // The column's alignment, from the delimiter row
+                if !node.data.is_empty() {
+                    self.output
+                        .push_str(&format!(r#" style="text-align: {}""#, node.data));
+                }
+                self.output.push('>');
+
+                for child in &node.children {
+                    self.render_node(child);
+                }
+
+                self.output.push_str(&format!("</{}>", tag));
+            }
+
             NodeType::ThematicBreak => {
                 self.output.push_str("<hr");
                 self.render_attributes(node);
@@ -499,6 +565,16 @@ impl WysiwygRenderer {
                 self.output.push_str("</s>");
             }
 
+            NodeType::SoftBreak => {
+                if self.options.soft_break_as_hard_break {
+                    self.output.push_str("<br />");
+                } else {
+                    self.output.push('\n');
+                }
+            }
+
+            NodeType::LineBreak => self.output.push_str("<br />"),
+
             // Handle other node types
             _ => {
                 for child in &node.children {
@@ -1,13 +1,16 @@
 //! Content renderer system for pluggable content rendering
 
 use async_trait::async_trait;
+use futures_util::stream::{self, BoxStream, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tracing::Instrument;
 
 use crate::error::{Result, RuneError};
+use crate::export::escape_html;
 use crate::plugin::Plugin;
 
 /// Metadata about the rendering process and renderer
@@ -58,8 +61,30 @@ pub trait ContentRenderer: Plugin {
     fn renderer_metadata(&self) -> RendererMetadata {
         RendererMetadata::default()
     }
+
+    /// Split content into pieces of roughly `target_bytes` that render
+    /// independently, for [`RendererRegistry::render_stream`]; renderers
+    /// that cannot split their input keep it whole
+    fn split_blocks(&self, content: &str, _target_bytes: usize) -> Vec<String> {
+        vec![content.to_string()]
+    }
+
+    /// Whether rendering is too costly for large documents, like renderers
+    /// laying out diagrams or fetching things; skipped for contexts marked
+    /// with [`RenderContext::with_large_document`]
+    fn is_expensive(&self) -> bool {
+        false
+    }
 }
 
+/// Source bytes per chunk of a streamed render; markdown render time grows
+/// faster than linearly with input size, so small chunks also render a large
+/// document several times faster than one pass over it
+pub const DEFAULT_STREAM_CHUNK_BYTES: usize = 16 * 1024;
+
+/// HTML chunks of a streamed render
+pub type RenderStream = BoxStream<'static, Result<String>>;
+
 /// Context provided to renderers during rendering
 #[derive(Debug, Clone)]
 pub struct RenderContext {
@@ -75,12 +100,17 @@ pub struct RenderContext {
     pub content_type: String,
     /// Original file extension
     pub file_extension: Option<String>,
+    /// Whether the document is large enough to skip expensive renderers
+    pub large_document: bool,
+    /// Request the render is done for, that of the creating task by default
+    pub trace_id: Option<crate::trace::TraceId>,
 }
 
 impl RenderContext {
     /// Create a new render context
     pub fn new(file_path: PathBuf, base_dir: PathBuf, theme: String) -> Self {
-        let file_extension = file_path
+        // Encrypted documents are rendered as the format they decrypt to
+        let file_extension = crate::crypto::plain_path(&file_path)
             .extension()
             .and_then(|ext| ext.to_str())
             .map(|s| s.to_lowercase());
@@ -89,7 +119,10 @@ impl RenderContext {
             Some("md") | Some("markdown") => "text/markdown".to_string(),
             Some("html") | Some("htm") => "text/html".to_string(),
             Some("txt") => "text/plain".to_string(),
-            _ => "application/octet-stream".to_string(),
+            _ => match crate::pandoc::input_format(&file_path) {
+                Some(input) => input.content_type.to_string(),
+                None => "application/octet-stream".to_string(),
+            },
         };
 
         Self {
@@ -99,6 +132,8 @@ impl RenderContext {
             custom_data: HashMap::new(),
             content_type,
             file_extension,
+            large_document: false,
+            trace_id: crate::trace::current(),
         }
     }
 
@@ -118,6 +153,12 @@ impl RenderContext {
         self.content_type = content_type;
         self
     }
+
+    /// Mark the document as large, leaving out expensive renderers
+    pub fn with_large_document(mut self) -> Self {
+        self.large_document = true;
+        self
+    }
 }
 
 /// Result of content rendering
@@ -166,6 +207,10 @@ impl RenderResult {
 /// Alias for RendererMetadata for backward compatibility
 pub type RenderMetadata = RendererMetadata;
 
+/// Placeholder for the content security policy nonce of a page, replaced
+/// with a fresh nonce each time the page is served
+pub const NONCE_PLACEHOLDER: &str = "{RUNE_NONCE}";
+
 /// Asset required for rendered content
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Asset {
@@ -177,6 +222,42 @@ pub struct Asset {
     pub is_critical: bool,
     /// Integrity hash for security
     pub integrity: Option<String>,
+    /// Nonce of the element loading the asset, usually [`NONCE_PLACEHOLDER`]
+    #[serde(default)]
+    pub nonce: Option<String>,
+}
+
+impl Asset {
+    /// Element loading the asset into a page, if the asset type has one
+    pub fn to_html(&self) -> Option<String> {
+        let url = escape_html(&self.url);
+        let mut attributes = String::new();
+        if let Some(integrity) = &self.integrity {
+            attributes.push_str(&format!(
+                r#" integrity="{}" crossorigin="anonymous""#,
+                escape_html(integrity)
+            ));
+        }
+        if let Some(nonce) = &self.nonce {
+            attributes.push_str(&format!(r#" nonce="{}""#, escape_html(nonce)));
+        }
+
+        match &self.asset_type {
+            AssetType::JavaScript => {
+                Some(format!(r#"<script src="{}"{}></script>"#, url, attributes))
+            }
+            AssetType::Css => Some(format!(
+                r#"<link rel="stylesheet" href="{}"{}>"#,
+                url, attributes
+            )),
+            AssetType::Font => Some(format!(
+                r#"<link rel="preload" href="{}" as="font" crossorigin>"#,
+                url
+            )),
+            AssetType::Image => Some(format!(r#"<link rel="preload" href="{}" as="image">"#, url)),
+            AssetType::Other(_) => None,
+        }
+    }
 }
 
 /// Types of assets that can be required
@@ -298,6 +379,7 @@ impl RendererRegistry {
     }
 
     /// Render content using a chained pipeline of renderers
+    #[tracing::instrument(name = "render", level = "debug", skip_all)]
     pub async fn render_with_pipeline(
         &self,
         content: &str,
@@ -317,8 +399,14 @@ impl RendererRegistry {
         for renderer_name in applicable_renderers {
             let renderers = self.renderers.read().await;
             if let Some(renderer) = renderers.get(&renderer_name) {
+                if current_context.large_document && renderer.is_expensive() {
+                    continue;
+                }
                 if renderer.can_render(&current_context.content_type) {
-                    let render_result = renderer.render(&current_content, &current_context).await?;
+                    let render_result = renderer
+                        .render(&current_content, &current_context)
+                        .instrument(tracing::debug_span!("render_stage", name = %renderer_name))
+                        .await?;
 
                     // Update content for next renderer in pipeline
                     current_content = render_result.html;
@@ -338,10 +426,8 @@ impl RendererRegistry {
 
                     pipeline_renderers.push(renderer_name.clone());
 
-                    // Update context content type if it changed
-                    if current_context.content_type.starts_with("text/markdown") {
-                        current_context.content_type = "text/html".to_string();
-                    }
+                    // Renderers write HTML, so the next ones get HTML
+                    current_context.content_type = "text/html".to_string();
                 }
             }
         }
@@ -371,6 +457,53 @@ impl RendererRegistry {
         Ok(result)
     }
 
+    /// Render content chunk by chunk through the pipeline
+    ///
+    /// The first renderer of the pipeline splits the content with
+    /// [`ContentRenderer::split_blocks`] and each piece is rendered as soon as
+    /// the previous one was consumed, so a caller can send the HTML of a huge
+    /// document while the rest is still being rendered.
+    pub fn render_stream(
+        self: Arc<Self>,
+        content: String,
+        context: RenderContext,
+        chunk_bytes: usize,
+    ) -> RenderStream {
+        stream::once(async move {
+            let chunks = self
+                .split_for_stream(&content, &context.content_type, chunk_bytes)
+                .await;
+            drop(content);
+            stream::iter(chunks).then(move |chunk| {
+                let registry = self.clone();
+                let context = context.clone();
+                async move {
+                    let result = registry.render_with_pipeline(&chunk, &context).await;
+                    // Rendering is CPU bound; let other tasks run between chunks
+                    tokio::task::yield_now().await;
+                    result.map(|result| result.html)
+                }
+            })
+        })
+        .flatten()
+        .boxed()
+    }
+
+    /// Pieces the pipeline's first renderer splits `content` into
+    async fn split_for_stream(
+        &self,
+        content: &str,
+        content_type: &str,
+        chunk_bytes: usize,
+    ) -> Vec<String> {
+        let first = self.get_pipeline_renderers(content_type).await;
+        let renderers = self.renderers.read().await;
+        match first.first().and_then(|name| renderers.get(name)) {
+            Some(renderer) => renderer.split_blocks(content, chunk_bytes),
+            None => vec![content.to_string()],
+        }
+    }
+
     /// Get renderers that should be applied in pipeline order for a content type
     async fn get_pipeline_renderers(&self, content_type: &str) -> Vec<String> {
         let renderers = self.renderers.read().await;
@@ -399,13 +532,28 @@ impl RendererRegistry {
                 }
             }
         } else {
-            // For other content types, just find the first applicable renderer
-            for renderer_name in pipeline.iter() {
-                if let Some(renderer) = renderers.get(renderer_name) {
-                    if renderer.can_render(content_type) {
-                        applicable.push(renderer_name.clone());
-                        break;
-                    }
+            // For other content types, find the first applicable renderer
+            let first = pipeline.iter().find(|renderer_name| {
+                renderers
+                    .get(*renderer_name)
+                    .is_some_and(|renderer| renderer.can_render(content_type))
+            });
+            if let Some(first) = first {
+                applicable.push(first.clone());
+
+                // Renderers of other formats write HTML, which then goes
+                // through the HTML processors like markdown does
+                if !renderers[first].can_render("text/html") {
+                    applicable.extend(
+                        pipeline
+                            .iter()
+                            .filter(|renderer_name| {
+                                renderers
+                                    .get(*renderer_name)
+                                    .is_some_and(|renderer| renderer.can_render("text/html"))
+                            })
+                            .cloned(),
+                    );
                 }
             }
         }
@@ -434,7 +582,7 @@ impl RendererRegistry {
             .collect();
 
         // Sort by priority (higher first)
-        pipeline.sort_by(|a, b| b.1.cmp(&a.1));
+        pipeline.sort_by_key(|entry| std::cmp::Reverse(entry.1));
 
         let mut render_pipeline = self.render_pipeline.write().await;
         *render_pipeline = pipeline.into_iter().map(|(name, _)| name).collect();
@@ -0,0 +1,251 @@
+//! Layout of the `.rune/` directory of a workspace
+//!
+//! Everything rune keeps about a workspace goes in its `.rune/` directory:
+//!
+//! ```text
+//! .rune/
+//!   VERSION          layout version, see LAYOUT_VERSION
+//!   config.json      workspace configuration, used once the workspace is trusted
+//!   state/           workspace state such as recent and pinned documents
+//!   history/         version history of saved documents
+//!   backups/         copies kept before destructive changes
+//!   annotations/     comments and highlights attached to documents
+//!   snapshots/       frozen copies of rendered pages
+//!   templates/       document templates
+//!   plugin-data/     one directory per plugin
+//!   cache/           anything that can be rebuilt, safe to delete
+//! ```
+//!
+//! Older versions of rune put the workspace state and the caches straight
+//! into `.rune/`. [`RuneDir::prepare`] reads the `VERSION` marker, a
+//! missing one meaning the first layout, and runs the migrations from that
+//! version on, so files move once and every feature finds them where the
+//! layout says.
+
+use crate::error::{Result, RuneError};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the directory rune keeps workspace data in
+pub const RUNE_DIR: &str = ".rune";
+
+/// Version of the layout this build writes
+pub const LAYOUT_VERSION: u32 = 2;
+
+/// File holding the layout version, relative to the `.rune/` directory
+pub const VERSION_FILE: &str = "VERSION";
+
+/// Directory of rebuildable data, relative to the `.rune/` directory
+pub const CACHE_DIR: &str = "cache";
+
+/// A step from one layout version to the next
+struct Migration {
+    /// Version the step starts from
+    from: u32,
+    description: &'static str,
+    run: fn(&Path) -> io::Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "move the workspace state into state/ and the caches into cache/",
+    run: move_into_subdirectories,
+}];
+
+/// The `.rune/` directory of a workspace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuneDir {
+    root: PathBuf,
+}
+
+impl RuneDir {
+    /// The `.rune/` directory of the workspace in `dir`
+    pub fn of(dir: &Path) -> Self {
+        Self {
+            root: dir.join(RUNE_DIR),
+        }
+    }
+
+    /// Path of the `.rune/` directory itself
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Configuration of the workspace, see [`crate::trust`]
+    pub fn config_file(&self) -> PathBuf {
+        self.root.join("config.json")
+    }
+
+    /// File of the recently edited and pinned documents
+    pub fn state_file(&self) -> PathBuf {
+        self.root.join("state").join("workspace.json")
+    }
+
+    pub fn history_dir(&self) -> PathBuf {
+        self.root.join("history")
+    }
+
+    pub fn backups_dir(&self) -> PathBuf {
+        self.root.join("backups")
+    }
+
+    pub fn annotations_dir(&self) -> PathBuf {
+        self.root.join("annotations")
+    }
+
+    pub fn snapshots_dir(&self) -> PathBuf {
+        self.root.join("snapshots")
+    }
+
+    pub fn templates_dir(&self) -> PathBuf {
+        self.root.join("templates")
+    }
+
+    /// Directory of rebuildable data, which cleanup may delete
+    pub fn cache_dir(&self) -> PathBuf {
+        self.root.join(CACHE_DIR)
+    }
+
+    /// Layout version of the directory: the one in its marker, 1 for a
+    /// directory from before markers, `None` when there is no directory
+    pub fn version(&self) -> Result<Option<u32>> {
+        if !self.root.is_dir() {
+            return Ok(None);
+        }
+        match fs::read_to_string(self.root.join(VERSION_FILE)) {
+            Ok(marker) => marker.trim().parse().map(Some).map_err(|_| {
+                RuneError::file_system(format!(
+                    "Invalid layout version '{}' in {}",
+                    marker.trim(),
+                    self.root.join(VERSION_FILE).display()
+                ))
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Some(1)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Create the directory, or migrate it to the current layout; returns
+    /// what the migrations that ran did
+    pub fn prepare(&self) -> Result<Vec<&'static str>> {
+        let mut applied = Vec::new();
+        let version = match self.version()? {
+            None => {
+                fs::create_dir_all(&self.root)?;
+                LAYOUT_VERSION
+            }
+            Some(version) if version > LAYOUT_VERSION => {
+                return Err(RuneError::file_system(format!(
+                    "{} uses layout version {}, newer than the {} this rune knows; \
+                    upgrade rune to use this workspace",
+                    self.root.display(),
+                    version,
+                    LAYOUT_VERSION
+                )));
+            }
+            Some(version) => version,
+        };
+
+        for migration in MIGRATIONS.iter().filter(|m| m.from >= version) {
+            (migration.run)(&self.root).map_err(|e| {
+                RuneError::file_system(format!(
+                    "Failed to migrate {} from layout version {}: {}",
+                    self.root.display(),
+                    migration.from,
+                    e
+                ))
+            })?;
+            // Written after each step, so a failed step is retried alone
+            write_version(&self.root, migration.from + 1)?;
+            applied.push(migration.description);
+        }
+        if !self.root.join(VERSION_FILE).exists() {
+            write_version(&self.root, LAYOUT_VERSION)?;
+        }
+        Ok(applied)
+    }
+
+    /// Delete the rebuildable data; returns the bytes freed
+    pub fn clear_cache(&self) -> Result<u64> {
+        let cache = self.cache_dir();
+        if !cache.exists() {
+            return Ok(0);
+        }
+        let bytes = size_of(&cache);
+        fs::remove_dir_all(&cache)?;
+        Ok(bytes)
+    }
+}
+
+fn write_version(root: &Path, version: u32) -> Result<()> {
+    fs::write(root.join(VERSION_FILE), format!("{}\n", version))?;
+    Ok(())
+}
+
+/// Layout 1 to 2: `workspace.json` goes into `state/`, `asset-cache/` and
+/// `link-previews/` into `cache/`
+fn move_into_subdirectories(root: &Path) -> io::Result<()> {
+    for (from, to) in [
+        ("workspace.json", "state/workspace.json"),
+        ("asset-cache", "cache/assets"),
+        ("link-previews", "cache/link-previews"),
+    ] {
+        let (from, to) = (root.join(from), root.join(to));
+        if !from.exists() || to.exists() {
+            continue;
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(from, to)?;
+    }
+    Ok(())
+}
+
+fn size_of(path: &Path) -> u64 {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => fs::read_dir(path)
+            .map(|entries| entries.flatten().map(|entry| size_of(&entry.path())).sum())
+            .unwrap_or(0),
+        Ok(meta) => meta.len(),
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_first_layout_directories_are_migrated_once() {
+        let temp_dir = tempdir().unwrap();
+        let dir = RuneDir::of(temp_dir.path());
+        fs::create_dir_all(dir.root().join("asset-cache")).unwrap();
+        fs::write(dir.root().join("asset-cache/logo.png"), "png").unwrap();
+        fs::write(dir.root().join("workspace.json"), "{}").unwrap();
+        fs::create_dir_all(dir.history_dir()).unwrap();
+        assert_eq!(dir.version().unwrap(), Some(1));
+
+        assert_eq!(dir.prepare().unwrap().len(), 1);
+        assert_eq!(dir.version().unwrap(), Some(LAYOUT_VERSION));
+        assert_eq!(fs::read_to_string(dir.state_file()).unwrap(), "{}");
+        assert!(dir.cache_dir().join("assets/logo.png").is_file());
+        assert!(!dir.root().join("asset-cache").exists());
+        assert!(dir.history_dir().is_dir());
+        assert!(dir.prepare().unwrap().is_empty());
+
+        assert_eq!(dir.clear_cache().unwrap(), 3);
+        assert!(!dir.cache_dir().exists());
+        assert!(dir.state_file().is_file());
+
+        // Fresh directories start at the current layout, newer ones are left alone
+        let fresh = RuneDir::of(&temp_dir.path().join("new"));
+        assert_eq!(fresh.version().unwrap(), None);
+        assert!(fresh.prepare().unwrap().is_empty());
+        assert_eq!(fresh.version().unwrap(), Some(LAYOUT_VERSION));
+        fs::write(fresh.root().join(VERSION_FILE), "99").unwrap();
+        assert!(fresh.prepare().is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! Saving files without truncating them
+//!
+//! A save that dies halfway through, because the process is killed or the
+//! disk fills up, must not leave a truncated document behind. By default,
+//! [`write_file`] writes the content to a temporary file next to the target,
+//! flushes it to the disk, gives it the permissions and owner of the file it
+//! replaces and renames it over the target, so readers see either the old
+//! content or the new one.
+//!
+//! Some filesystems and file watchers do not cope with a file being replaced
+//! by another, like a watch that stays on the replaced inode. For those,
+//! [`WriteStrategy::WriteThrough`] overwrites the file in place and only
+//! flushes it.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// How files are saved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteStrategy {
+    /// Write a temporary file and rename it over the target
+    #[default]
+    Atomic,
+    /// Overwrite the target in place, keeping its inode
+    WriteThrough,
+}
+
+/// Write `contents` to `path` the way `strategy` says, flushed to the disk
+pub fn write_file(path: &Path, contents: &[u8], strategy: WriteStrategy) -> io::Result<()> {
+    match strategy {
+        WriteStrategy::Atomic => write_atomic(path, contents),
+        WriteStrategy::WriteThrough => write_through(path, contents),
+    }
+}
+
+fn write_through(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    // A symlink keeps pointing at the file it names
+    let target = match fs::canonicalize(path) {
+        Ok(target) => target,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => path.to_path_buf(),
+        Err(e) => return Err(e),
+    };
+    let directory = match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    let temp = directory.join(format!(".{}.{}.tmp", name, Uuid::new_v4().simple()));
+
+    let mut file = match OpenOptions::new().write(true).create_new(true).open(&temp) {
+        Ok(file) => file,
+        // A writable file in a directory that is not can only be overwritten
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied && target.exists() => {
+            tracing::debug!(
+                "Cannot create a temporary file next to {}, writing it in place",
+                target.display()
+            );
+            return write_through(&target, contents);
+        }
+        Err(e) => return Err(e),
+    };
+
+    let written = (|| {
+        file.write_all(contents)?;
+        if let Ok(metadata) = fs::metadata(&target) {
+            file.set_permissions(metadata.permissions())?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                // Only a privileged process may hand a file to another owner
+                if let Err(e) =
+                    std::os::unix::fs::fchown(&file, Some(metadata.uid()), Some(metadata.gid()))
+                {
+                    tracing::debug!("Kept the owner of {}: {}", target.display(), e);
+                }
+            }
+        }
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&temp, &target)
+    })();
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+
+    // The rename itself is durable once the directory is flushed
+    #[cfg(unix)]
+    if let Err(e) = File::open(&directory).and_then(|directory| directory.sync_all()) {
+        tracing::debug!("Failed to flush {}: {}", directory.display(), e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_writes_replace_the_file_keeping_its_permissions() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notes.md");
+        fs::write(&path, "old").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+
+        write_file(&path, b"new", WriteStrategy::Atomic).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o640);
+        }
+        let entries = fs::read_dir(temp_dir.path()).unwrap().count();
+        assert_eq!(entries, 1, "the temporary file is gone");
+
+        #[cfg(unix)]
+        {
+            let link = temp_dir.path().join("link.md");
+            std::os::unix::fs::symlink(&path, &link).unwrap();
+            write_file(&link, b"linked", WriteStrategy::Atomic).unwrap();
+            assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+            assert_eq!(fs::read_to_string(&path).unwrap(), "linked");
+        }
+
+        write_file(&path, b"in place", WriteStrategy::WriteThrough).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "in place");
+        let created = temp_dir.path().join("new.md");
+        write_file(&created, b"fresh", WriteStrategy::Atomic).unwrap();
+        assert_eq!(fs::read_to_string(&created).unwrap(), "fresh");
+    }
+}
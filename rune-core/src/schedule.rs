@@ -0,0 +1,177 @@
+//! Publishing of documents scheduled for later
+//!
+//! A document whose frontmatter sets a `publish_at` time still ahead is
+//! treated like a draft until then. The [`PublishScheduler`] keeps track of
+//! the scheduled documents being served and, once a time passes, announces a
+//! change to the document so listings, pages and open previews pick up that
+//! it is out.
+
+use crate::drafts::PublishState;
+use crate::error::Result;
+use crate::event::{ChangeType, EventBus, SystemEvent};
+use crate::state::StateManager;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+
+/// Name of the supervised task publishing scheduled documents
+pub const PUBLISH_TASK: &str = "publish-scheduler";
+
+/// Longest wait between looks for newly scheduled documents
+pub const PUBLISH_RESCAN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Announces scheduled documents once their publish time has passed
+pub struct PublishScheduler {
+    event_bus: Arc<dyn EventBus>,
+    state_manager: Arc<StateManager>,
+    rescan: Notify,
+}
+
+impl PublishScheduler {
+    pub fn new(event_bus: Arc<dyn EventBus>, state_manager: Arc<StateManager>) -> Self {
+        Self {
+            event_bus,
+            state_manager,
+            rescan: Notify::new(),
+        }
+    }
+
+    /// Look for scheduled documents again without waiting for the next scan,
+    /// e.g. after a root was added
+    pub fn rescan(&self) {
+        self.rescan.notify_one();
+    }
+
+    /// Markdown documents being served: the current file and those of the
+    /// served roots
+    async fn documents(&self) -> Vec<PathBuf> {
+        let state = self.state_manager.get_state().await;
+        let mut documents: Vec<PathBuf> = state.current_file.into_iter().collect();
+        for root in &state.served_roots {
+            documents.extend(root.documents());
+        }
+        documents.sort();
+        documents.dedup();
+        documents
+    }
+
+    /// Scan `documents` at `now`, replacing `pending` with those scheduled
+    /// for later. Returns the previously pending documents that are out now.
+    fn scan(
+        documents: &[PathBuf],
+        pending: &mut HashMap<PathBuf, SystemTime>,
+        now: SystemTime,
+    ) -> Vec<PathBuf> {
+        let mut scheduled = HashMap::new();
+        for document in documents {
+            let Ok(content) = std::fs::read_to_string(document) else {
+                continue;
+            };
+            if let PublishState::Scheduled(time) = PublishState::of(&content, now) {
+                scheduled.insert(document.clone(), time);
+            }
+        }
+
+        let mut published: Vec<PathBuf> = pending
+            .keys()
+            .filter(|document| !scheduled.contains_key(*document) && documents.contains(document))
+            .cloned()
+            .collect();
+        published.sort();
+        *pending = scheduled;
+        published
+    }
+
+    /// Watch the served documents until the task is stopped
+    pub async fn run(self: Arc<Self>) -> Result<()> {
+        let mut pending = HashMap::new();
+        loop {
+            let documents = self.documents().await;
+            let now = SystemTime::now();
+            let published = tokio::task::spawn_blocking({
+                let mut scanned = std::mem::take(&mut pending);
+                move || {
+                    let published = Self::scan(&documents, &mut scanned, now);
+                    (published, scanned)
+                }
+            })
+            .await;
+            let published = match published {
+                Ok((published, scanned)) => {
+                    pending = scanned;
+                    published
+                }
+                Err(e) => {
+                    warn!("Scanning for scheduled documents failed: {}", e);
+                    Vec::new()
+                }
+            };
+
+            for document in published {
+                info!("Publishing scheduled document {}", document.display());
+                self.event_bus
+                    .publish_system_event(SystemEvent::file_changed(document, ChangeType::Modified))
+                    .await?;
+            }
+
+            // Wake up for the next publish time, or look again for documents
+            // scheduled in the meantime
+            let wait = pending
+                .values()
+                .filter_map(|time| time.duration_since(SystemTime::now()).ok())
+                .min()
+                .map_or(PUBLISH_RESCAN_INTERVAL, |until| {
+                    until.min(PUBLISH_RESCAN_INTERVAL)
+                });
+            debug!(
+                "{} scheduled document(s), next scan in {:?}",
+                pending.len(),
+                wait
+            );
+            // A little past the time, so the document counts as published
+            tokio::select! {
+                _ = tokio::time::sleep(wait + Duration::from_millis(50)) => {}
+                _ = self.rescan.notified() => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drafts::parse_time;
+
+    #[test]
+    fn test_scheduled_documents_are_published_once_due() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let scheduled = temp_dir.path().join("launch.md");
+        let published = temp_dir.path().join("notes.md");
+        std::fs::write(
+            &scheduled,
+            "---\npublish_at: 2024-06-01T09:00\n---\n# Launch\n",
+        )
+        .unwrap();
+        std::fs::write(&published, "# Notes\n").unwrap();
+        let documents = vec![scheduled.clone(), published];
+
+        let mut pending = HashMap::new();
+        let before = parse_time("2024-05-31").unwrap();
+        assert!(PublishScheduler::scan(&documents, &mut pending, before).is_empty());
+        assert_eq!(
+            pending.get(&scheduled),
+            parse_time("2024-06-01T09:00").as_ref()
+        );
+
+        let after = parse_time("2024-06-01T09:00:01").unwrap();
+        assert_eq!(
+            PublishScheduler::scan(&documents, &mut pending, after),
+            [scheduled]
+        );
+        assert!(pending.is_empty());
+        assert!(PublishScheduler::scan(&documents, &mut pending, after).is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+//! Saved searches over the served documents
+//!
+//! The `saved_searches` section of the configuration names queries, e.g.
+//!
+//! ```json
+//! "saved_searches": {
+//!   "Open questions": {"text": "question", "tags": ["research"]},
+//!   "Drafts": {"frontmatter": {"status": "draft"}}
+//! }
+//! ```
+//!
+//! A document matches a [`SavedSearch`] when it contains every word of
+//! `text`, has every tag of `tags` (or a tag nested below it) and, for every
+//! key of `frontmatter`, that value or a list holding it. Comparisons ignore
+//! case. The server shows each search as a folder of the file tree and as a
+//! page listing its documents.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{frontmatter, tags};
+
+/// A named query of the configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    /// Words every document contains, in any order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Tags every document has
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Frontmatter values every document has
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub frontmatter: BTreeMap<String, String>,
+}
+
+impl SavedSearch {
+    /// Whether the search has no filter, so it would match everything
+    pub fn is_empty(&self) -> bool {
+        self.words().next().is_none() && self.tags.is_empty() && self.frontmatter.is_empty()
+    }
+
+    /// Whether the search needs the text of documents
+    pub fn needs_text(&self) -> bool {
+        self.words().next().is_some()
+    }
+
+    fn words(&self) -> impl Iterator<Item = &str> {
+        self.text.iter().flat_map(|text| text.split_whitespace())
+    }
+
+    /// Whether `document` matches every filter
+    pub fn matches(&self, document: &SearchDocument) -> bool {
+        self.words()
+            .all(|word| document.text.contains(&word.to_lowercase()))
+            && self.tags.iter().all(|wanted| {
+                let wanted = wanted.trim_start_matches('#').to_lowercase();
+                document.tags.iter().any(|tag| {
+                    tag == &wanted
+                        || tag
+                            .strip_prefix(wanted.as_str())
+                            .is_some_and(|rest| rest.starts_with('/'))
+                })
+            })
+            && self.frontmatter.iter().all(|(key, wanted)| {
+                document.frontmatter.get(key).is_some_and(|values| {
+                    values
+                        .iter()
+                        .any(|value| value.eq_ignore_ascii_case(wanted))
+                })
+            })
+    }
+}
+
+/// What saved searches look at in a document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchDocument {
+    pub title: String,
+    pub tags: Vec<String>,
+    /// Top-level frontmatter values, lists by their items
+    pub frontmatter: BTreeMap<String, Vec<String>>,
+    /// Lowercased content; empty unless asked for, as no search needs it
+    pub text: String,
+}
+
+impl SearchDocument {
+    /// Facts of the document at `path` with content `markdown`, keeping its
+    /// text when `with_text`
+    pub fn of(path: &Path, markdown: &str, with_text: bool) -> Self {
+        let frontmatter = frontmatter::entries(markdown)
+            .map(|(key, value)| {
+                let values = if value.is_empty() || value.starts_with('[') {
+                    frontmatter::list(markdown, key)
+                } else {
+                    vec![value.to_string()]
+                };
+                (key.to_string(), values)
+            })
+            .collect();
+        Self {
+            title: tags::document_title(path, markdown),
+            tags: tags::parse(markdown),
+            frontmatter,
+            text: if with_text {
+                markdown.to_lowercase()
+            } else {
+                String::new()
+            },
+        }
+    }
+}
+
+/// Name of the page of the search `name`, e.g. `open-questions`
+pub fn slug(name: &str) -> String {
+    crate::slugify(name, Default::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_text_tags_and_frontmatter() {
+        let markdown = "---\nstatus: Draft\nauthors: [ada, grace]\n---\n# Plan\n\nAn open Question about #research/ml.\n";
+        let document = SearchDocument::of(Path::new("plan.md"), markdown, true);
+        let search = |json: &str| serde_json::from_str::<SavedSearch>(json).unwrap();
+
+        assert!(search(r##"{"text": "question OPEN", "tags": ["#research"]}"##).matches(&document));
+        assert!(
+            search(r#"{"frontmatter": {"status": "draft", "authors": "grace"}}"#)
+                .matches(&document)
+        );
+        assert!(!search(r#"{"text": "answer"}"#).matches(&document));
+        assert!(!search(r#"{"tags": ["research/m"]}"#).matches(&document));
+        assert!(!search(r#"{"frontmatter": {"status": "done"}}"#).matches(&document));
+        assert!(search(r#"{"text": " "}"#).is_empty());
+
+        // Without its text a document only matches searches that need none
+        let document = SearchDocument::of(Path::new("plan.md"), markdown, false);
+        assert!(!search(r#"{"text": "question"}"#).matches(&document));
+        assert!(search(r#"{"tags": ["research/ml"]}"#).matches(&document));
+    }
+}
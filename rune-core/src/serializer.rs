@@ -0,0 +1,428 @@
+//! Markdown serializer for converting an AST back to Markdown text
+//!
+//! The counterpart of [`crate::parser`]: documents can be parsed, changed as
+//! a tree and written out again instead of being edited as strings. The
+//! parser keeps the source of every top-level block in its `tokens`, with its
+//! `position`; a block whose content is unchanged is written exactly as it
+//! was, spacing to its neighbours included, so a round trip only rewrites
+//! the blocks that were touched. Other blocks, and trees built by hand or by
+//! [`crate::convert`], are written in a canonical form.
+
+use crate::ast::{Node, NodeType, Tree};
+use crate::parser::MarkdownParser;
+
+/// Serializer writing an AST as markdown
+pub struct MarkdownSerializer {
+    preserve_source: bool,
+}
+
+impl MarkdownSerializer {
+    /// Create a serializer keeping the source of unchanged blocks
+    pub fn new() -> Self {
+        Self {
+            preserve_source: true,
+        }
+    }
+
+    /// Create a serializer writing every block in canonical form, as a
+    /// formatter does
+    pub fn canonical() -> Self {
+        Self {
+            preserve_source: false,
+        }
+    }
+
+    /// Write a tree as markdown
+    pub fn serialize(&self, tree: &Tree) -> String {
+        let parser = MarkdownParser::with_options(tree.parse_options.clone());
+        let mut markdown = String::new();
+        // The last block written and whether it was its source
+        let mut previous: Option<(&Node, bool)> = None;
+
+        for node in &tree.root.children {
+            let source = self.source(node, &parser);
+            let preserved = source.is_some();
+            let block = source.map_or_else(|| write_block(node), str::to_string);
+            if block.is_empty() {
+                continue;
+            }
+            if let Some((previous, previous_preserved)) = previous {
+                match blank_lines_between(previous, node) {
+                    Some(blank_lines) if preserved && previous_preserved => {
+                        markdown.push_str(&"\n".repeat(blank_lines + 1))
+                    }
+                    _ => markdown.push_str("\n\n"),
+                }
+            }
+            markdown.push_str(&block);
+            previous = Some((node, preserved));
+        }
+
+        if !markdown.is_empty() {
+            markdown.push('\n');
+        }
+        markdown
+    }
+
+    /// Source a block was parsed from, when its content is unchanged: the
+    /// source must parse back to a block written the same way
+    fn source<'a>(&self, node: &'a Node, parser: &MarkdownParser) -> Option<&'a str> {
+        if !self.preserve_source || node.position.is_none() {
+            return None;
+        }
+        let source = std::str::from_utf8(&node.tokens).ok()?;
+        match parser.parse(source).root.children.as_slice() {
+            [reparsed] if write_block(reparsed) == write_block(node) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl Default for MarkdownSerializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Blank lines the source had between two blocks, if they are in order
+fn blank_lines_between(previous: &Node, next: &Node) -> Option<usize> {
+    let previous_end = previous.position.as_ref()?.line + source_lines(previous);
+    next.position.as_ref()?.line.checked_sub(previous_end)
+}
+
+fn source_lines(node: &Node) -> usize {
+    node.tokens.iter().filter(|&&byte| byte == b'\n').count() + 1
+}
+
+fn write_blocks(nodes: &[Node], separator: &str) -> String {
+    nodes
+        .iter()
+        .map(write_block)
+        .filter(|block| !block.is_empty())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn write_block(node: &Node) -> String {
+    match node.node_type {
+        NodeType::Heading => {
+            let text = write_inline_block(&node.children);
+            if text.is_empty() {
+                return String::new();
+            }
+            let level = node.level.unwrap_or(1).clamp(1, 6);
+            // Headings hold a single line
+            format!("{} {}", "#".repeat(level), single_line(&text))
+        }
+        NodeType::Paragraph => write_inline_block(&node.children)
+            .lines()
+            .map(escape_block_start)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        NodeType::CodeBlock | NodeType::MathBlock => {
+            let code = node.text_content();
+            let fence = "`".repeat(longest_run(&code, '`').max(2) + 1);
+            format!("{fence}{}\n{}\n{fence}", node.data, code)
+        }
+        NodeType::HTMLBlock => node.text_content(),
+        NodeType::Blockquote => write_blocks(&node.children, "\n\n")
+            .lines()
+            .map(|line| format!("> {}", line).trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        NodeType::List => write_list(node),
+        NodeType::Table => write_table(node),
+        NodeType::ThematicBreak => "---".to_string(),
+        NodeType::LinkReferenceDefinition | NodeType::KramdownBlockIAL => String::new(),
+        _ if node.children.iter().any(Node::is_block) => write_blocks(&node.children, "\n\n"),
+        _ => escape_block_start(&write_inline_block(&node.children)),
+    }
+}
+
+fn write_list(list: &Node) -> String {
+    let ordered = list.get_attribute("type").map(String::as_str) == Some("ordered");
+    let start: usize = list
+        .get_attribute("start")
+        .and_then(|start| start.parse().ok())
+        .unwrap_or(1);
+    list.children
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let marker = if ordered {
+                format!("{}. ", start + index)
+            } else {
+                "- ".to_string()
+            };
+            let task = match item.get_attribute("checked").map(String::as_str) {
+                Some("true") => "[x] ",
+                Some(_) => "[ ] ",
+                None => "",
+            };
+            let content = if item.children.iter().any(Node::is_block) {
+                write_blocks(&item.children, "\n")
+            } else {
+                write_inline_block(&item.children)
+            };
+            // Continuation lines line up with the item's text
+            let indent = " ".repeat(marker.len());
+            let mut lines = content.lines();
+            let first = lines.next().unwrap_or_default();
+            let mut written = format!("{}{}{}", marker, task, first);
+            for line in lines {
+                written.push('\n');
+                if !line.is_empty() {
+                    written.push_str(&indent);
+                    written.push_str(line);
+                }
+            }
+            written
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn write_table(table: &Node) -> String {
+    let rows: Vec<Vec<(String, &str)>> = table
+        .children
+        .iter()
+        .flat_map(|child| match child.node_type {
+            NodeType::TableHead => child.children.iter().collect(),
+            _ => vec![child],
+        })
+        .map(|row| {
+            row.children
+                .iter()
+                .map(|cell| {
+                    let text = single_line(&write_inline_block(&cell.children)).replace('|', "\\|");
+                    (text, cell.data.as_str())
+                })
+                .collect()
+        })
+        .collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+
+    let line = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+    let mut lines = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        let cells = (0..columns)
+            .map(|column| {
+                row.get(column)
+                    .map(|(text, _)| text.clone())
+                    .unwrap_or_default()
+            })
+            .collect();
+        lines.push(line(cells));
+        if index == 0 {
+            let delimiters = (0..columns)
+                .map(|column| {
+                    match rows[0].get(column).map(|(_, alignment)| *alignment) {
+                        Some("left") => ":---",
+                        Some("center") => ":---:",
+                        Some("right") => "---:",
+                        _ => "---",
+                    }
+                    .to_string()
+                })
+                .collect();
+            lines.push(line(delimiters));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Written inline content, for transforms measuring it
+pub(crate) fn write_inlines(nodes: &[Node]) -> String {
+    write_inline_block(nodes)
+}
+
+/// Inline content of a block: lines trimmed, except for the two trailing
+/// spaces of a line break, and empty lines dropped
+fn write_inline_block(nodes: &[Node]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        write_inline(node, &mut text);
+    }
+    text.trim_matches(|c: char| c.is_whitespace())
+        .split('\n')
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let trimmed = line.trim();
+            if line.ends_with("  ") {
+                format!("{}  ", trimmed)
+            } else {
+                trimmed.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Inline content joined onto one line, for headings and table cells
+fn single_line(text: &str) -> String {
+    text.replace("  \n", " ").replace('\n', " ")
+}
+
+fn write_inline(node: &Node, out: &mut String) {
+    match node.node_type {
+        NodeType::Text => out.push_str(&escape_text(&node.data)),
+        NodeType::SoftBreak => out.push('\n'),
+        NodeType::LineBreak => out.push_str("  \n"),
+        NodeType::Strong => write_emphasis(node, "**", out),
+        NodeType::Emph => write_emphasis(node, "*", out),
+        NodeType::Strikethrough => write_emphasis(node, "~~", out),
+        NodeType::Code | NodeType::InlineMath => {
+            let code = node.text_content();
+            if code.trim().is_empty() {
+                out.push_str(&code);
+                return;
+            }
+            let fence = "`".repeat(longest_run(&code, '`') + 1);
+            let padding = if code.starts_with('`') || code.ends_with('`') {
+                " "
+            } else {
+                ""
+            };
+            out.push_str(&format!("{fence}{padding}{code}{padding}{fence}"));
+        }
+        NodeType::Link => {
+            let mut text = String::new();
+            for child in &node.children {
+                write_inline(child, &mut text);
+            }
+            let href = node.get_attribute("href").map_or("", String::as_str);
+            if text.trim().is_empty() {
+                out.push_str(&format!("<{}>", href));
+            } else {
+                out.push_str(&format!("[{}]({})", text.trim(), destination(href)));
+            }
+        }
+        NodeType::Image => {
+            let src = node.get_attribute("src").map_or("", String::as_str);
+            let alt = node.get_attribute("alt").map_or("", String::as_str);
+            out.push_str(&format!("![{}]({})", escape_text(alt), destination(src)));
+        }
+        NodeType::HTMLInline => out.push_str(&node.data),
+        NodeType::TaskListItemMarker => {}
+        _ => {
+            for child in &node.children {
+                write_inline(child, out);
+            }
+        }
+    }
+}
+
+/// Emphasis around a node's content; markers must touch the text, so the
+/// whitespace at its ends goes outside them
+fn write_emphasis(node: &Node, marker: &str, out: &mut String) {
+    let mut inner = String::new();
+    for child in &node.children {
+        write_inline(child, &mut inner);
+    }
+    let trimmed = inner.trim();
+    if trimmed.is_empty() {
+        out.push_str(&inner);
+        return;
+    }
+    let leading = &inner[..inner.len() - inner.trim_start().len()];
+    let trailing = &inner[inner.trim_end().len()..];
+    out.push_str(&format!("{leading}{marker}{trimmed}{marker}{trailing}"));
+}
+
+/// Link destination, in angle brackets when it has spaces or parentheses
+fn destination(url: &str) -> String {
+    if url.contains([' ', '(', ')']) {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+/// Escape characters that would otherwise start markdown syntax
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let chars: Vec<char> = text.chars().collect();
+    for (index, &c) in chars.iter().enumerate() {
+        let intraword = index > 0
+            && chars[index - 1].is_alphanumeric()
+            && chars
+                .get(index + 1)
+                .is_some_and(|next| next.is_alphanumeric());
+        match c {
+            '\\' | '`' | '*' | '[' | ']' | '<' => escaped.push('\\'),
+            '_' | '~' if !intraword => escaped.push('\\'),
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escape a paragraph starting like a heading, quote, list item or rule
+fn escape_block_start(text: &str) -> String {
+    let starts_block = text.starts_with(['#', '>', '+', '-', '='])
+        || text.split_once(['.', ')']).is_some_and(|(number, rest)| {
+            !number.is_empty()
+                && number.chars().all(|c| c.is_ascii_digit())
+                && (rest.is_empty() || rest.starts_with(' '))
+        });
+    if starts_block {
+        match text.find(|c: char| !c.is_ascii_digit()) {
+            Some(index) if index > 0 => format!("{}\\{}", &text[..index], &text[index..]),
+            _ => format!("\\{}", text),
+        }
+    } else {
+        text.to_string()
+    }
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|other| other != c)
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_keeps_untouched_blocks() {
+        let source = "Title\n\n* star   bullets\n* __bold__ item\n\n\n***\n\n\
+                      | a |b|\n|:-|-:|\n| 1 |2|\n";
+        let mut tree = MarkdownParser::new().parse(source);
+
+        assert_eq!(MarkdownSerializer::new().serialize(&tree), source);
+
+        // Only the changed paragraph is rewritten
+        tree.root.children[0].children = vec![Node::text("New *title*")];
+        assert_eq!(
+            MarkdownSerializer::new().serialize(&tree),
+            source.replacen("Title", "New \\*title\\*", 1)
+        );
+
+        assert_eq!(
+            MarkdownSerializer::canonical().serialize(&tree),
+            "New \\*title\\*\n\n- star   bullets\n- **bold** item\n\n---\n\n\
+             | a | b |\n| :--- | ---: |\n| 1 | 2 |\n"
+        );
+    }
+
+    #[test]
+    fn test_canonical_output_parses_back_to_the_same_tree() {
+        let source = "# Plan\n\nShip `now`, see [docs](guide.md) and 2 \\* 3\n\n\
+                      1. one\n2. [x] two\n\n> quoted *text*\n\n```rust\nfn main() {}\n```\n";
+        let serializer = MarkdownSerializer::canonical();
+
+        let written = serializer.serialize(&MarkdownParser::new().parse(source));
+        assert_eq!(written, source);
+        assert_eq!(
+            serializer.serialize(&MarkdownParser::new().parse(&written)),
+            written
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Heading slugs
+//!
+//! Heading ids, the table of contents and anchor checks all derive their
+//! slugs here, so a `#fragment` means the same heading everywhere.
+
+use crate::error::{Result, RuneError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// How heading text becomes a slug
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SlugStyle {
+    /// Like GitHub: lowercase, spaces become `-`, other punctuation is
+    /// dropped and letters of every script are kept
+    #[default]
+    Github,
+    /// Lowercase ASCII letters and digits, runs of anything else become `-`
+    Ascii,
+    /// Like GitHub, but keeping the case of the text
+    Preserve,
+}
+
+impl SlugStyle {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Github => "github",
+            Self::Ascii => "ascii",
+            Self::Preserve => "preserve",
+        }
+    }
+}
+
+impl FromStr for SlugStyle {
+    type Err = RuneError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "github" => Ok(Self::Github),
+            "ascii" => Ok(Self::Ascii),
+            "preserve" => Ok(Self::Preserve),
+            other => Err(RuneError::config(format!(
+                "Unknown heading slug style '{}'. Supported styles: github, ascii, preserve",
+                other
+            ))),
+        }
+    }
+}
+
+/// Slug of `text` in `style`; `section` when nothing of it is left
+pub fn slugify(text: &str, style: SlugStyle) -> String {
+    let text = text.trim();
+    let slug: String = match style {
+        SlugStyle::Github | SlugStyle::Preserve => {
+            let mut slug = String::with_capacity(text.len());
+            for c in text.chars() {
+                match c {
+                    ' ' => slug.push('-'),
+                    '-' | '_' => slug.push(c),
+                    c if c.is_alphanumeric() && style == SlugStyle::Github => {
+                        slug.extend(c.to_lowercase())
+                    }
+                    c if c.is_alphanumeric() => slug.push(c),
+                    _ => {}
+                }
+            }
+            slug
+        }
+        SlugStyle::Ascii => text
+            .to_ascii_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-"),
+    };
+
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Unique slugs of one document's headings, in document order
+///
+/// A slug already taken gets the first free `-1`, `-2`, … suffix, so
+/// "Setup", "Setup" becomes `setup`, `setup-1`.
+#[derive(Debug, Clone, Default)]
+pub struct Slugger {
+    style: SlugStyle,
+    taken: HashSet<String>,
+}
+
+impl Slugger {
+    pub fn new(style: SlugStyle) -> Self {
+        Self {
+            style,
+            taken: HashSet::new(),
+        }
+    }
+
+    /// Slug of the next heading, with text `text`
+    pub fn slug(&mut self, text: &str) -> String {
+        let base = slugify(text, self.style);
+        let slug = if self.taken.contains(&base) {
+            (1..)
+                .map(|n| format!("{}-{}", base, n))
+                .find(|candidate| !self.taken.contains(candidate))
+                .unwrap_or(base)
+        } else {
+            base
+        };
+        self.taken.insert(slug.clone());
+        slug
+    }
+
+    /// Mark an id the author set explicitly as taken
+    pub fn reserve(&mut self, id: &str) {
+        self.taken.insert(id.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slug_styles_and_duplicates() {
+        let text = "  Step 2: Install `rune` (macOS/Linux) — 中文 ";
+        assert_eq!(
+            slugify(text, SlugStyle::Github),
+            "step-2-install-rune-macoslinux--中文"
+        );
+        assert_eq!(
+            slugify(text, SlugStyle::Ascii),
+            "step-2-install-rune-macos-linux"
+        );
+        assert_eq!(slugify("Hello World", SlugStyle::Preserve), "Hello-World");
+        assert_eq!(slugify("!!!", SlugStyle::Github), "section");
+        assert_eq!("ASCII".parse::<SlugStyle>().unwrap(), SlugStyle::Ascii);
+        assert!("kebab".parse::<SlugStyle>().is_err());
+
+        let mut slugger = Slugger::new(SlugStyle::Github);
+        slugger.reserve("setup-1");
+        let slugs: Vec<_> = ["Setup", "Setup", "Setup", "Usage"]
+            .iter()
+            .map(|text| slugger.slug(text))
+            .collect();
+        assert_eq!(slugs, ["setup", "setup-2", "setup-3", "usage"]);
+    }
+}
@@ -1,14 +1,26 @@
 //! State management for the Rune system
 
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
+use tracing::info;
 use uuid::Uuid;
 
+use crate::error::{Result, RuneError};
+use crate::memory::{MemoryConsumer, MemoryUsage};
 use crate::plugin::PluginInfo;
+use crate::rune_dir::{RuneDir, RUNE_DIR};
+
+/// File keeping the recently edited and pinned documents of a workspace,
+/// relative to its directory
+pub const WORKSPACE_STATE_FILE: &str = ".rune/state/workspace.json";
+
+/// Number of recently edited documents kept
+pub const RECENT_LIMIT: usize = 50;
 
 /// Application state manager
 pub struct StateManager {
@@ -34,6 +46,31 @@ impl StateManager {
         state.current_file = file;
     }
 
+    /// Serve a file or directory under its own route prefix
+    ///
+    /// The prefix is derived from the file stem or directory name and made
+    /// unique among the roots served so far.
+    pub async fn add_served_root(&self, path: PathBuf) -> ServedRoot {
+        let mut state = self.state.write().await;
+        let taken: Vec<&str> = state
+            .served_roots
+            .iter()
+            .map(|root| root.prefix.as_str())
+            .collect();
+        let root = ServedRoot {
+            prefix: route_prefix(&path, &taken),
+            path,
+        };
+        state.served_roots.push(root.clone());
+        root
+    }
+
+    /// Roots served under their own route prefix, in the order they were added
+    pub async fn served_roots(&self) -> Vec<ServedRoot> {
+        let state = self.state.read().await;
+        state.served_roots.clone()
+    }
+
     /// Add a connected client
     pub async fn add_client(&self, client_id: Uuid, info: ClientInfo) {
         let mut state = self.state.write().await;
@@ -60,6 +97,14 @@ impl StateManager {
             .insert(plugin_info.name.clone(), plugin_info);
     }
 
+    /// Get information about all known plugins, sorted by name
+    pub async fn list_plugins(&self) -> Vec<PluginInfo> {
+        let state = self.state.read().await;
+        let mut plugins: Vec<PluginInfo> = state.loaded_plugins.values().cloned().collect();
+        plugins.sort_by(|a, b| a.name.cmp(&b.name));
+        plugins
+    }
+
     /// Remove plugin information
     pub async fn remove_plugin(&self, plugin_name: &str) {
         let mut state = self.state.write().await;
@@ -91,6 +136,50 @@ impl StateManager {
         state.render_cache.get(content_hash).cloned()
     }
 
+    /// Get statistics about the render cache
+    pub async fn render_cache_stats(&self) -> RenderCacheStats {
+        let state = self.state.read().await;
+        RenderCacheStats {
+            entries: state.render_cache.len(),
+            total_bytes: state
+                .render_cache
+                .values()
+                .map(|render| render.rendered_html.len())
+                .sum(),
+        }
+    }
+
+    /// Drop all cached renders, returning how many were removed
+    pub async fn clear_render_cache(&self) -> usize {
+        let mut state = self.state.write().await;
+        let entries = state.render_cache.len();
+        state.render_cache.clear();
+        entries
+    }
+
+    /// Drop the oldest cached renders until about `bytes` bytes are freed,
+    /// returning the bytes freed
+    pub async fn evict_renders(&self, bytes: usize) -> usize {
+        let mut state = self.state.write().await;
+        let mut oldest: Vec<(SystemTime, String)> = state
+            .render_cache
+            .iter()
+            .map(|(key, render)| (render.timestamp, key.clone()))
+            .collect();
+        oldest.sort();
+
+        let mut freed = 0;
+        for (_, key) in oldest {
+            if freed >= bytes {
+                break;
+            }
+            if let Some(render) = state.render_cache.remove(&key) {
+                freed += render.rendered_html.len();
+            }
+        }
+        freed
+    }
+
     /// Update system health status
     pub async fn update_system_health(&self, health: SystemHealth) {
         let mut state = self.state.write().await;
@@ -103,6 +192,122 @@ impl StateManager {
         state.system_health.clone()
     }
 
+    /// Load the recently edited and pinned documents of the workspace in
+    /// `dir`, and keep them there from now on; its `.rune/` directory is
+    /// migrated to the current layout first
+    pub async fn load_workspace(&self, dir: &Path) -> Result<()> {
+        for migration in RuneDir::of(dir).prepare()? {
+            info!("Migrated {}: {}", dir.join(RUNE_DIR).display(), migration);
+        }
+        let file = dir.join(WORKSPACE_STATE_FILE);
+        let documents = match tokio::fs::read_to_string(&file).await {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| {
+                RuneError::FileSystem(format!("Invalid workspace state {}: {}", file.display(), e))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => WorkspaceDocuments::default(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut state = self.state.write().await;
+        state.workspace = documents;
+        state.workspace_file = Some(file);
+        Ok(())
+    }
+
+    /// `.rune/` directory of the loaded workspace, if one was loaded
+    pub async fn workspace_data_dir(&self) -> Option<PathBuf> {
+        let state = self.state.read().await;
+        // The state file is `.rune/state/workspace.json`
+        state
+            .workspace_file
+            .as_deref()
+            .and_then(|file| file.ancestors().nth(2))
+            .map(Path::to_path_buf)
+    }
+
+    /// Recently edited and pinned documents of the workspace
+    pub async fn workspace_documents(&self) -> WorkspaceDocuments {
+        self.state.read().await.workspace.clone()
+    }
+
+    /// Note that the document at `path` was just edited
+    pub async fn record_edit(&self, path: &Path) -> Result<()> {
+        let edited_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        self.change_workspace(|documents| {
+            documents.recent.retain(|recent| recent.path != path);
+            documents.recent.insert(
+                0,
+                RecentDocument {
+                    path: path.to_path_buf(),
+                    edited_at,
+                },
+            );
+            documents.recent.truncate(RECENT_LIMIT);
+        })
+        .await
+    }
+
+    /// Follow the document at `from` to `to`, or forget it when it is gone
+    pub async fn move_document(&self, from: &Path, to: Option<&Path>) -> Result<()> {
+        self.change_workspace(|documents| match to {
+            Some(to) => {
+                for recent in documents.recent.iter_mut().filter(|r| r.path == from) {
+                    recent.path = to.to_path_buf();
+                }
+                for pinned in documents.pinned.iter_mut().filter(|p| *p == from) {
+                    *pinned = to.to_path_buf();
+                }
+            }
+            None => {
+                documents.recent.retain(|recent| recent.path != from);
+                documents.pinned.retain(|pinned| pinned != from);
+            }
+        })
+        .await
+    }
+
+    /// Pin the document at `path`, or unpin it
+    pub async fn set_pinned(&self, path: &Path, pinned: bool) -> Result<()> {
+        self.change_workspace(|documents| {
+            let position = documents.pinned.iter().position(|p| p == path);
+            match (position, pinned) {
+                (None, true) => documents.pinned.push(path.to_path_buf()),
+                (Some(position), false) => {
+                    documents.pinned.remove(position);
+                }
+                _ => {}
+            }
+        })
+        .await
+    }
+
+    /// Apply `change` to the workspace documents and save them when they
+    /// changed and a workspace is loaded
+    async fn change_workspace(&self, change: impl FnOnce(&mut WorkspaceDocuments)) -> Result<()> {
+        let (file, json) = {
+            let mut state = self.state.write().await;
+            let before = state.workspace.clone();
+            change(&mut state.workspace);
+            if state.workspace == before {
+                return Ok(());
+            }
+            let Some(file) = state.workspace_file.clone() else {
+                return Ok(());
+            };
+            let json = serde_json::to_string_pretty(&state.workspace).map_err(|e| {
+                RuneError::FileSystem(format!("Failed to save workspace state: {}", e))
+            })?;
+            (file, json)
+        };
+        if let Some(dir) = file.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&file, json).await?;
+        Ok(())
+    }
+
     /// Clear all state (used during shutdown)
     pub async fn clear_state(&self) {
         let mut state = self.state.write().await;
@@ -116,14 +321,211 @@ impl Default for StateManager {
     }
 }
 
+/// Accounts the render cache against the memory budget
+pub struct RenderCacheMemory {
+    state_manager: Arc<StateManager>,
+}
+
+impl RenderCacheMemory {
+    pub fn new(state_manager: Arc<StateManager>) -> Self {
+        Self { state_manager }
+    }
+}
+
+#[async_trait]
+impl MemoryConsumer for RenderCacheMemory {
+    fn name(&self) -> &str {
+        "render-cache"
+    }
+
+    async fn usage(&self) -> MemoryUsage {
+        let stats = self.state_manager.render_cache_stats().await;
+        MemoryUsage {
+            bytes: stats.total_bytes,
+            entries: stats.entries,
+        }
+    }
+
+    async fn evict(&self, bytes: usize) -> usize {
+        self.state_manager.evict_renders(bytes).await
+    }
+}
+
+/// Markdown files below a directory, sorted, skipping hidden directories
+pub fn markdown_documents(dir: &Path) -> Vec<PathBuf> {
+    fn collect(dir: &Path, documents: &mut Vec<PathBuf>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                collect(&path, documents);
+            } else if path.extension().is_some_and(|extension| {
+                extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown")
+            }) {
+                documents.push(path);
+            }
+        }
+    }
+
+    let mut documents = Vec::new();
+    collect(dir, &mut documents);
+    documents.sort();
+    documents
+}
+
 /// Main application state
 #[derive(Debug, Clone, Default)]
 pub struct ApplicationState {
     pub current_file: Option<PathBuf>,
+    pub served_roots: Vec<ServedRoot>,
     pub active_clients: HashMap<Uuid, ClientInfo>,
     pub loaded_plugins: HashMap<String, PluginInfo>,
     pub render_cache: HashMap<String, CachedRender>,
     pub system_health: SystemHealth,
+    pub workspace: WorkspaceDocuments,
+    /// Where `workspace` is saved, once a workspace is loaded
+    pub workspace_file: Option<PathBuf>,
+}
+
+/// Recently edited and pinned documents of a workspace
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceDocuments {
+    /// Most recently edited first
+    #[serde(default)]
+    pub recent: Vec<RecentDocument>,
+    /// In the order they were pinned
+    #[serde(default)]
+    pub pinned: Vec<PathBuf>,
+}
+
+/// A recently edited document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentDocument {
+    pub path: PathBuf,
+    /// When it was last edited, in seconds since the Unix epoch
+    pub edited_at: u64,
+}
+
+/// A file or directory served under its own route prefix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServedRoot {
+    /// First path segment of the root's URLs, without slashes
+    pub prefix: String,
+    pub path: PathBuf,
+}
+
+impl ServedRoot {
+    /// Whether the root is a directory of documents rather than a single file
+    pub fn is_dir(&self) -> bool {
+        self.path.is_dir()
+    }
+
+    /// Directory that relative links and assets of the root resolve against
+    pub fn base_dir(&self) -> PathBuf {
+        if self.is_dir() {
+            self.path.clone()
+        } else {
+            self.path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default()
+        }
+    }
+
+    /// Whether `path` is the root's file or lies below its directory
+    pub fn contains(&self, path: &Path) -> bool {
+        if self.is_dir() {
+            path.starts_with(&self.path)
+        } else {
+            path.starts_with(self.base_dir())
+        }
+    }
+
+    /// Markdown documents of the root: its file, or those below its directory
+    pub fn documents(&self) -> Vec<PathBuf> {
+        if self.is_dir() {
+            markdown_documents(&self.path)
+        } else {
+            vec![self.path.clone()]
+        }
+    }
+
+    /// Directories to watch for changes to the root's documents and assets
+    ///
+    /// Hidden directories below a directory root are skipped.
+    pub fn watch_dirs(&self) -> Vec<PathBuf> {
+        fn collect(dir: &Path, dirs: &mut Vec<PathBuf>) {
+            dirs.push(dir.to_path_buf());
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                return;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                if path.is_dir() && !hidden {
+                    collect(&path, dirs);
+                }
+            }
+        }
+
+        let mut dirs = Vec::new();
+        if self.is_dir() {
+            collect(&self.path, &mut dirs);
+        } else {
+            dirs.push(self.base_dir());
+        }
+        dirs
+    }
+}
+
+/// First path segments used by the server's own routes
+const RESERVED_PREFIXES: &[&str] = &["api", "ws", "s", "share", "themes"];
+
+/// Derive a URL-safe prefix for `path` that is not in `taken`
+fn route_prefix(path: &Path, taken: &[&str]) -> String {
+    let name = if path.is_dir() {
+        path.file_name()
+    } else {
+        path.file_stem()
+    }
+    .map(|name| name.to_string_lossy().to_lowercase())
+    .unwrap_or_default();
+
+    let slug: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let slug = slug
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let slug = if slug.is_empty() {
+        "doc".to_string()
+    } else {
+        slug
+    };
+
+    let is_free = |prefix: &str| !taken.contains(&prefix) && !RESERVED_PREFIXES.contains(&prefix);
+    if is_free(&slug) {
+        return slug;
+    }
+    (2..)
+        .map(|n| format!("{}-{}", slug, n))
+        .find(|prefix| is_free(prefix))
+        .unwrap_or(slug)
+}
+
+/// Size of the render cache
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RenderCacheStats {
+    pub entries: usize,
+    pub total_bytes: usize,
 }
 
 /// Information about connected clients
@@ -242,3 +644,103 @@ pub struct PluginHealth {
     pub error_count: u32,
     pub last_error: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_served_roots_get_unique_prefixes() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::create_dir_all(dir.join("My Docs/.git")).unwrap();
+        std::fs::create_dir_all(dir.join("My Docs/guide")).unwrap();
+        std::fs::create_dir_all(dir.join("other")).unwrap();
+        std::fs::write(dir.join("README.md"), "# A").unwrap();
+        std::fs::write(dir.join("other/README.md"), "# B").unwrap();
+        std::fs::write(dir.join("api.md"), "# C").unwrap();
+
+        let state_manager = StateManager::new();
+        let docs = state_manager.add_served_root(dir.join("My Docs")).await;
+        let readme = state_manager.add_served_root(dir.join("README.md")).await;
+        let other = state_manager
+            .add_served_root(dir.join("other/README.md"))
+            .await;
+        let api = state_manager.add_served_root(dir.join("api.md")).await;
+
+        assert_eq!(docs.prefix, "my-docs");
+        assert_eq!(readme.prefix, "readme");
+        assert_eq!(other.prefix, "readme-2");
+        assert_eq!(api.prefix, "api-2");
+        assert_eq!(state_manager.served_roots().await.len(), 4);
+
+        assert!(docs.is_dir());
+        assert_eq!(
+            docs.watch_dirs(),
+            vec![dir.join("My Docs"), dir.join("My Docs/guide")]
+        );
+        assert!(docs.contains(&dir.join("My Docs/guide/intro.md")));
+        assert!(!docs.contains(&dir.join("README.md")));
+        assert!(other.contains(&dir.join("other/logo.png")));
+        assert_eq!(readme.watch_dirs(), vec![dir.to_path_buf()]);
+    }
+
+    #[tokio::test]
+    async fn test_workspace_documents_are_kept() {
+        let temp_dir = TempDir::new().unwrap();
+        let (a, b) = (temp_dir.path().join("a.md"), temp_dir.path().join("b.md"));
+
+        let state_manager = StateManager::new();
+        state_manager.load_workspace(temp_dir.path()).await.unwrap();
+        state_manager.record_edit(&a).await.unwrap();
+        state_manager.record_edit(&b).await.unwrap();
+        state_manager.record_edit(&a).await.unwrap();
+        state_manager.set_pinned(&b, true).await.unwrap();
+        state_manager
+            .move_document(&b, Some(&temp_dir.path().join("c.md")))
+            .await
+            .unwrap();
+
+        // A new session of the workspace finds them again
+        let reloaded = StateManager::new();
+        reloaded.load_workspace(temp_dir.path()).await.unwrap();
+        let documents = reloaded.workspace_documents().await;
+        let recent: Vec<&Path> = documents.recent.iter().map(|r| r.path.as_path()).collect();
+        assert_eq!(recent, [a.as_path(), &temp_dir.path().join("c.md")]);
+        assert_eq!(documents.pinned, [temp_dir.path().join("c.md")]);
+
+        reloaded.move_document(&a, None).await.unwrap();
+        reloaded
+            .set_pinned(&temp_dir.path().join("c.md"), false)
+            .await
+            .unwrap();
+        let documents = reloaded.workspace_documents().await;
+        assert_eq!(documents.recent.len(), 1);
+        assert!(documents.pinned.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_render_cache_evicts_oldest_first() {
+        let state_manager = Arc::new(StateManager::new());
+        for (age, key) in [(3, "old"), (2, "middle"), (1, "new")] {
+            let mut render =
+                CachedRender::new(key.to_string(), "x".repeat(100), RenderMetadata::default());
+            render.timestamp = SystemTime::now() - std::time::Duration::from_secs(age);
+            state_manager.cache_render(key.to_string(), render).await;
+        }
+
+        let memory = RenderCacheMemory::new(state_manager.clone());
+        assert_eq!(
+            memory.usage().await,
+            MemoryUsage {
+                bytes: 300,
+                entries: 3
+            }
+        );
+        assert_eq!(memory.evict(150).await, 200);
+        assert!(state_manager.get_cached_render("old").await.is_none());
+        assert!(state_manager.get_cached_render("middle").await.is_none());
+        assert!(state_manager.get_cached_render("new").await.is_some());
+    }
+}
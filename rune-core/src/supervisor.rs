@@ -0,0 +1,629 @@
+//! Supervision of critical background tasks
+//!
+//! A [`TaskSupervisor`] runs tasks such as the HTTP server or the file watcher
+//! loop from a factory, so a task that ends or panics can be started again.
+//! Every termination is announced as an unhealthy [`SystemEvent::PluginHealthCheck`]
+//! under the task's name; the [`RestartPolicy`] then decides whether, and after
+//! how long a backoff, the task is restarted.
+//!
+//! Stopping a task aborts it. A task that can wind down by itself, like the
+//! server finishing the requests it is answering, is [drained](TaskSupervisor::drain)
+//! instead: it is left to finish, without being restarted, until a deadline.
+//! The [`DrainReport`]s of drained tasks end up in the engine's shutdown report.
+
+use crate::chaos::ChaosInjector;
+use crate::crash::catch_panic;
+use crate::error::{Result, RuneError};
+use crate::event::{EventBus, SystemEvent};
+use crate::notification::Notification;
+use crate::plugin::PluginHealthStatus;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+/// Which terminations lead to a restart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RestartOn {
+    Never,
+    /// Errors and panics; a task returning `Ok` stays stopped
+    Failure,
+    /// Any termination, for tasks that should run for the whole session
+    Always,
+}
+
+/// When and how fast a supervised task is restarted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestartPolicy {
+    pub restart_on: RestartOn,
+    /// Consecutive restarts before giving up; `None` retries forever
+    pub max_restarts: Option<u32>,
+    /// Delay before the first restart, doubled for each further one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// A task running this long counts as recovered and its backoff resets
+    pub stable_after: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            restart_on: RestartOn::Always,
+            max_restarts: Some(5),
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            stable_after: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Restart after errors and panics, but not after a clean exit
+    pub fn on_failure() -> Self {
+        Self {
+            restart_on: RestartOn::Failure,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before the given restart, counting from zero
+    pub fn backoff(&self, restart: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(restart))
+            .min(self.max_backoff)
+    }
+}
+
+/// Lifecycle state of a supervised task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskState {
+    Running,
+    /// Terminated and waiting for its backoff to elapse
+    Restarting,
+    /// Returned normally and not restarted
+    Exited,
+    /// Failed and not restarted, either by policy or after too many restarts
+    Failed,
+    /// Stopped through the supervisor
+    Stopped,
+}
+
+/// Snapshot of a supervised task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskStatus {
+    pub name: String,
+    pub state: TaskState,
+    /// Restarts since the task was first started
+    pub restarts: u32,
+    pub last_error: Option<String>,
+    pub started_at: SystemTime,
+}
+
+/// How draining a task went
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DrainReport {
+    pub task: String,
+    /// Whether the task finished before its deadline; it was aborted
+    /// otherwise
+    pub finished: bool,
+    pub elapsed: Duration,
+    /// Work the task was doing and let finish, like requests in flight
+    pub completed: usize,
+    /// Work cut off when the task was aborted
+    pub interrupted: usize,
+    /// Clients told the task was going away, like WebSocket connections
+    pub closed_connections: usize,
+}
+
+struct SupervisedTask {
+    status: TaskStatus,
+    monitor: Option<JoinHandle<()>>,
+    /// Whether the task is left to finish and must not be restarted
+    draining: bool,
+}
+
+/// Runs critical tasks and restarts them according to their policy
+pub struct TaskSupervisor {
+    event_bus: Arc<dyn EventBus>,
+    tasks: Arc<RwLock<HashMap<String, SupervisedTask>>>,
+    drains: RwLock<Vec<DrainReport>>,
+    /// Injects faults into task starts in development mode
+    chaos: Option<Arc<ChaosInjector>>,
+}
+
+impl TaskSupervisor {
+    /// Create a supervisor announcing task health on `event_bus`
+    pub fn new(event_bus: Arc<dyn EventBus>) -> Self {
+        Self {
+            event_bus,
+            tasks: Arc::new(RwLock::new(HashMap::new())),
+            drains: RwLock::new(Vec::new()),
+            chaos: None,
+        }
+    }
+
+    /// Let `chaos` delay, fail or panic tasks as they are started and restarted
+    pub fn with_chaos(mut self, chaos: Arc<ChaosInjector>) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Start `factory`'s task under `name`, replacing a task of the same name
+    pub async fn supervise<F, Fut>(
+        &self,
+        name: impl Into<String>,
+        policy: RestartPolicy,
+        factory: F,
+    ) -> Result<()>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let name = name.into();
+        if name.trim().is_empty() {
+            return Err(RuneError::generic("Supervised task name must not be empty"));
+        }
+        self.stop(&name).await;
+
+        let mut tasks = self.tasks.write().await;
+        tasks.insert(
+            name.clone(),
+            SupervisedTask {
+                status: TaskStatus {
+                    name: name.clone(),
+                    state: TaskState::Running,
+                    restarts: 0,
+                    last_error: None,
+                    started_at: SystemTime::now(),
+                },
+                monitor: None,
+                draining: false,
+            },
+        );
+        let monitor = tokio::spawn(monitor(
+            name.clone(),
+            policy,
+            factory,
+            self.event_bus.clone(),
+            self.tasks.clone(),
+            self.chaos.clone(),
+        ));
+        if let Some(task) = tasks.get_mut(&name) {
+            task.monitor = Some(monitor);
+        }
+
+        info!("Supervising task '{}'", name);
+        Ok(())
+    }
+
+    /// Status of every task, sorted by name
+    pub async fn status(&self) -> Vec<TaskStatus> {
+        let tasks = self.tasks.read().await;
+        let mut status: Vec<TaskStatus> = tasks.values().map(|task| task.status.clone()).collect();
+        status.sort_by(|a, b| a.name.cmp(&b.name));
+        status
+    }
+
+    /// Status of one task
+    pub async fn task_status(&self, name: &str) -> Option<TaskStatus> {
+        let tasks = self.tasks.read().await;
+        tasks.get(name).map(|task| task.status.clone())
+    }
+
+    /// Abort a task without restarting it; returns whether it was running
+    pub async fn stop(&self, name: &str) -> bool {
+        let mut tasks = self.tasks.write().await;
+        let Some(task) = tasks.get_mut(name) else {
+            return false;
+        };
+        let was_running = match task.monitor.take() {
+            Some(monitor) => {
+                let running = !monitor.is_finished();
+                monitor.abort();
+                running
+            }
+            None => false,
+        };
+        task.status.state = TaskState::Stopped;
+        was_running
+    }
+
+    /// Let a task finish by itself without restarting it, aborting it once
+    /// `deadline` passes; returns whether it finished in time. The task must
+    /// have been told to finish, the supervisor only waits for it
+    pub async fn drain(&self, name: &str, deadline: Duration) -> bool {
+        let monitor = {
+            let mut tasks = self.tasks.write().await;
+            let Some(task) = tasks.get_mut(name) else {
+                return true;
+            };
+            task.draining = true;
+            task.monitor.take()
+        };
+        let finished = match monitor {
+            Some(mut monitor) => {
+                let finished = tokio::time::timeout(deadline, &mut monitor).await.is_ok();
+                if !finished {
+                    warn!("Task '{}' did not finish within {:?}", name, deadline);
+                    monitor.abort();
+                }
+                finished
+            }
+            None => true,
+        };
+        update(&self.tasks, name, |status| {
+            status.state = TaskState::Stopped
+        })
+        .await;
+        finished
+    }
+
+    /// Keep the report of a drained task for the shutdown report
+    pub async fn report_drain(&self, report: DrainReport) {
+        self.drains.write().await.push(report);
+    }
+
+    /// Reports of the tasks drained so far
+    pub async fn drain_reports(&self) -> Vec<DrainReport> {
+        self.drains.read().await.clone()
+    }
+
+    /// Abort every task
+    pub async fn shutdown(&self) {
+        let names: Vec<String> = self.tasks.read().await.keys().cloned().collect();
+        for name in names {
+            self.stop(&name).await;
+        }
+    }
+}
+
+/// Run a task until the policy gives up on it
+async fn monitor<F, Fut>(
+    name: String,
+    policy: RestartPolicy,
+    factory: F,
+    event_bus: Arc<dyn EventBus>,
+    tasks: Arc<RwLock<HashMap<String, SupervisedTask>>>,
+    chaos: Option<Arc<ChaosInjector>>,
+) where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let mut consecutive_restarts = 0u32;
+    loop {
+        let started = Instant::now();
+        let run = async {
+            if let Some(chaos) = &chaos {
+                chaos.inject(&name).await?;
+            }
+            factory().await
+        };
+        let failure = match catch_panic(&name, run).await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(report) => {
+                publish(&event_bus, report.to_event()).await;
+                Some(report.summary())
+            }
+        };
+        let draining = tasks
+            .read()
+            .await
+            .get(&name)
+            .is_some_and(|task| task.draining);
+        if draining {
+            info!("Supervised task '{}' drained", name);
+            return;
+        }
+        match &failure {
+            Some(failure) => error!("Supervised task '{}' failed: {}", name, failure),
+            None => warn!("Supervised task '{}' exited", name),
+        }
+        publish(
+            &event_bus,
+            SystemEvent::plugin_health_check(name.clone(), PluginHealthStatus::Unhealthy),
+        )
+        .await;
+
+        if started.elapsed() >= policy.stable_after {
+            consecutive_restarts = 0;
+        }
+        let wanted = match policy.restart_on {
+            RestartOn::Never => false,
+            RestartOn::Failure => failure.is_some(),
+            RestartOn::Always => true,
+        };
+        let exhausted = policy
+            .max_restarts
+            .is_some_and(|max| consecutive_restarts >= max);
+        if !wanted || exhausted {
+            if exhausted {
+                error!(
+                    "Giving up on task '{}' after {} restarts",
+                    name, consecutive_restarts
+                );
+            }
+            if let Some(failure) = &failure {
+                let notification = Notification::error(
+                    &name,
+                    format!("{} failed and was not restarted: {}", name, failure),
+                );
+                publish(&event_bus, SystemEvent::notification(notification)).await;
+            }
+            update(&tasks, &name, |status| {
+                status.state = if failure.is_some() {
+                    TaskState::Failed
+                } else {
+                    TaskState::Exited
+                };
+                status.last_error = failure;
+            })
+            .await;
+            return;
+        }
+
+        let delay = policy.backoff(consecutive_restarts);
+        consecutive_restarts += 1;
+        let restarted = failure
+            .as_ref()
+            .map(|failure| format!("{} crashed and was restarted: {}", name, failure));
+        update(&tasks, &name, |status| {
+            status.state = TaskState::Restarting;
+            status.last_error = failure;
+        })
+        .await;
+        publish(
+            &event_bus,
+            SystemEvent::plugin_health_check(name.clone(), PluginHealthStatus::Recovering),
+        )
+        .await;
+        info!("Restarting task '{}' in {:?}", name, delay);
+        tokio::time::sleep(delay).await;
+
+        update(&tasks, &name, |status| {
+            status.state = TaskState::Running;
+            status.restarts += 1;
+            status.started_at = SystemTime::now();
+        })
+        .await;
+        publish(
+            &event_bus,
+            SystemEvent::plugin_health_check(name.clone(), PluginHealthStatus::Healthy),
+        )
+        .await;
+        if let Some(message) = restarted {
+            let notification = Notification::warn(&name, message);
+            publish(&event_bus, SystemEvent::notification(notification)).await;
+        }
+    }
+}
+
+async fn update(
+    tasks: &RwLock<HashMap<String, SupervisedTask>>,
+    name: &str,
+    change: impl FnOnce(&mut TaskStatus),
+) {
+    if let Some(task) = tasks.write().await.get_mut(name) {
+        change(&mut task.status);
+    }
+}
+
+async fn publish(event_bus: &Arc<dyn EventBus>, event: SystemEvent) {
+    if let Err(e) = event_bus.publish_system_event(event).await {
+        warn!("Failed to publish task event: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{InMemoryEventBus, SystemEventHandler};
+    use crate::notification::NotificationLevel;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tokio::sync::Mutex;
+
+    /// Health checks and notifications published about tasks
+    struct HealthCollector(Mutex<Vec<PluginHealthStatus>>, Mutex<Vec<Notification>>);
+
+    #[async_trait]
+    impl SystemEventHandler for HealthCollector {
+        async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+            match event {
+                SystemEvent::PluginHealthCheck { status, .. } => {
+                    self.0.lock().await.push(status.clone())
+                }
+                SystemEvent::Notification { notification, .. } => {
+                    self.1.lock().await.push(notification.clone())
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    fn fast(restart_on: RestartOn, max_restarts: Option<u32>) -> RestartPolicy {
+        RestartPolicy {
+            restart_on,
+            max_restarts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            stable_after: Duration::from_secs(60),
+        }
+    }
+
+    async fn wait_for(supervisor: &TaskSupervisor, name: &str, state: TaskState) -> TaskStatus {
+        for _ in 0..200 {
+            if let Some(status) = supervisor.task_status(name).await {
+                if status.state == state {
+                    return status;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("task '{}' never reached {:?}", name, state);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = RestartPolicy::default();
+        assert_eq!(policy.backoff(0), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(40), Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_failing_task_is_restarted_until_limit() {
+        let event_bus: Arc<dyn EventBus> = Arc::new(InMemoryEventBus::new());
+        let health = Arc::new(HealthCollector(
+            Mutex::new(Vec::new()),
+            Mutex::new(Vec::new()),
+        ));
+        event_bus
+            .subscribe_system_events(health.clone())
+            .await
+            .unwrap();
+        let supervisor = TaskSupervisor::new(event_bus);
+
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        supervisor
+            .supervise("flaky", fast(RestartOn::Failure, Some(2)), move || {
+                let run = counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if run == 1 {
+                        panic!("second run panics");
+                    }
+                    Err(RuneError::generic("lost connection"))
+                }
+            })
+            .await
+            .unwrap();
+
+        let status = wait_for(&supervisor, "flaky", TaskState::Failed).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert_eq!(status.restarts, 2);
+        assert!(status.last_error.unwrap().contains("lost connection"));
+
+        // The user hears of both restarts and of giving up
+        let notifications = health.1.lock().await;
+        let levels: Vec<NotificationLevel> = notifications.iter().map(|n| n.level).collect();
+        assert_eq!(
+            levels,
+            [
+                NotificationLevel::Warn,
+                NotificationLevel::Warn,
+                NotificationLevel::Error
+            ]
+        );
+        assert!(notifications[0]
+            .message
+            .contains("flaky crashed and was restarted"));
+
+        let health = health.0.lock().await;
+        assert_eq!(
+            health
+                .iter()
+                .filter(|s| **s == PluginHealthStatus::Healthy)
+                .count(),
+            2
+        );
+        assert_eq!(health.last(), Some(&PluginHealthStatus::Unhealthy));
+    }
+
+    #[tokio::test]
+    async fn test_chaos_failures_go_through_restarts() {
+        let chaos = crate::chaos::ChaosInjector::new(crate::config::ChaosConfig {
+            error_probability: Some(1.0),
+            targets: vec!["watcher".to_string()],
+            seed: Some(1),
+            ..Default::default()
+        });
+        let supervisor =
+            TaskSupervisor::new(Arc::new(InMemoryEventBus::new())).with_chaos(Arc::new(chaos));
+
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        supervisor
+            .supervise("watcher", fast(RestartOn::Failure, Some(2)), move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                std::future::pending()
+            })
+            .await
+            .unwrap();
+
+        // The task never gets to run, yet is restarted like a failing one
+        let status = wait_for(&supervisor, "watcher", TaskState::Failed).await;
+        assert_eq!(status.restarts, 2);
+        assert!(status.last_error.unwrap().contains("Chaos"));
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_clean_exit_and_stop() {
+        let supervisor = TaskSupervisor::new(Arc::new(InMemoryEventBus::new()));
+        supervisor
+            .supervise("once", fast(RestartOn::Failure, None), || async { Ok(()) })
+            .await
+            .unwrap();
+        let status = wait_for(&supervisor, "once", TaskState::Exited).await;
+        assert_eq!(status.restarts, 0);
+
+        supervisor
+            .supervise("forever", RestartPolicy::default(), || {
+                std::future::pending::<Result<()>>()
+            })
+            .await
+            .unwrap();
+        assert!(supervisor.stop("forever").await);
+        assert_eq!(
+            supervisor.task_status("forever").await.unwrap().state,
+            TaskState::Stopped
+        );
+        assert!(!supervisor.stop("missing").await);
+    }
+
+    #[tokio::test]
+    async fn test_drained_tasks_finish_without_restart() {
+        let supervisor = TaskSupervisor::new(Arc::new(InMemoryEventBus::new()));
+        let (finish, finished) = tokio::sync::watch::channel(false);
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        supervisor
+            .supervise("server", fast(RestartOn::Always, None), move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut finished = finished.clone();
+                async move {
+                    let _ = finished.wait_for(|finished| *finished).await;
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+
+        finish.send_replace(true);
+        assert!(supervisor.drain("server", Duration::from_secs(5)).await);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            supervisor.task_status("server").await.unwrap().state,
+            TaskState::Stopped
+        );
+
+        // A task that does not finish is aborted at the deadline
+        supervisor
+            .supervise("stuck", RestartPolicy::default(), || {
+                std::future::pending::<Result<()>>()
+            })
+            .await
+            .unwrap();
+        assert!(!supervisor.drain("stuck", Duration::from_millis(10)).await);
+        assert_eq!(
+            supervisor.task_status("stuck").await.unwrap().state,
+            TaskState::Stopped
+        );
+    }
+}
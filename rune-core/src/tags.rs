@@ -0,0 +1,240 @@
+//! Tags of documents and the pages listing them
+//!
+//! A document is tagged by the `tags` list of its frontmatter and by
+//! `#tag` tokens in its text, outside code. Tags are lowercased, may be
+//! nested like `#project/rune` and need a letter, so `#1` stays an issue
+//! number. The server lists the documents of every tag under `/tags/` and
+//! HTML exports write the same pages to a [`TAG_DIR`] next to the documents;
+//! both name a tag's page after its [`slug`].
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::export::escape_html;
+use crate::frontmatter;
+
+/// Directory of the tag pages, in the served site and in exports
+pub const TAG_DIR: &str = "tags";
+
+/// Whether `c` may be part of an inline tag
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '/')
+}
+
+/// Tags of the document `markdown`, lowercased, sorted and without
+/// duplicates
+pub fn parse(markdown: &str) -> Vec<String> {
+    let mut tags: Vec<String> = frontmatter::list(markdown, "tags")
+        .iter()
+        .map(|tag| tag.trim_start_matches('#').to_lowercase())
+        .collect();
+
+    // The body starts past the closing fence of the frontmatter
+    let body = if markdown.starts_with("---\n") || markdown.starts_with("---\r\n") {
+        let fenced = frontmatter::lines(markdown).count() + 2;
+        let offset: usize = markdown
+            .split_inclusive('\n')
+            .take(fenced)
+            .map(str::len)
+            .sum();
+        &markdown[offset..]
+    } else {
+        markdown
+    };
+    for line in crate::glossary::outside_fences(body) {
+        // Indented code blocks and inline code spans hold no tags
+        if line.starts_with("    ") || line.starts_with('\t') {
+            continue;
+        }
+        for (index, text) in line.split('`').enumerate() {
+            if index % 2 == 1 {
+                continue;
+            }
+            inline_tags(text, &mut tags);
+        }
+    }
+
+    tags.retain(|tag| !tag.is_empty());
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Collect the `#tag` tokens of `text` into `tags`
+fn inline_tags(text: &str, tags: &mut Vec<String>) {
+    for (start, _) in text.match_indices('#') {
+        if text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| !c.is_whitespace())
+        {
+            continue;
+        }
+        let rest = &text[start + 1..];
+        let end = rest.find(|c| !is_tag_char(c)).unwrap_or(rest.len());
+        let tag = rest[..end].trim_end_matches(['/', '-']);
+        if tag.chars().any(char::is_alphabetic) && !tag.starts_with('/') {
+            tags.push(tag.to_lowercase());
+        }
+    }
+}
+
+/// Name of the page of `tag`, e.g. `project-rune` for `project/rune`
+pub fn slug(tag: &str) -> String {
+    crate::slugify(&tag.replace('/', "-"), Default::default())
+}
+
+/// Title of the document at `path`: that of its frontmatter, its first
+/// level one heading, or its file name
+pub fn document_title(path: &Path, markdown: &str) -> String {
+    if let Some(title) = frontmatter::value(markdown, "title").filter(|t| !t.is_empty()) {
+        return title.to_string();
+    }
+    crate::glossary::outside_fences(markdown)
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|heading| heading.trim().to_string())
+        .filter(|heading| !heading.is_empty())
+        .unwrap_or_else(|| crate::templates::title_from_path(path))
+}
+
+/// A document listed on a tag page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaggedDocument {
+    pub title: String,
+    /// Link to the document, from the page listing it
+    pub url: String,
+}
+
+/// Documents of every tag
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagPages {
+    tags: BTreeMap<String, Vec<TaggedDocument>>,
+}
+
+impl TagPages {
+    /// List `document` under each of `tags`
+    pub fn add(&mut self, tags: &[String], document: TaggedDocument) {
+        for tag in tags {
+            self.tags
+                .entry(tag.clone())
+                .or_default()
+                .push(document.clone());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Number of tags
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Tags and their documents, by tag
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[TaggedDocument])> {
+        self.tags
+            .iter()
+            .map(|(tag, documents)| (tag.as_str(), documents.as_slice()))
+    }
+
+    /// Tag and documents of the page named `slug`
+    pub fn page(&self, slug: &str) -> Option<(&str, &[TaggedDocument])> {
+        self.iter().find(|(tag, _)| self::slug(tag) == slug)
+    }
+
+    /// Body of the page listing every tag, linked with `href`
+    pub fn index_html(&self, href: impl Fn(&str) -> String) -> String {
+        let mut html = String::from("<h1>Tags</h1>\n");
+        if self.is_empty() {
+            html.push_str("<p>No tagged documents.</p>\n");
+            return html;
+        }
+        html.push_str("<ul class=\"rune-tag-index\">\n");
+        for (tag, documents) in self.iter() {
+            html.push_str(&format!(
+                "<li><a class=\"rune-tag\" href=\"{}\">#{}</a> <span class=\"rune-tag-count\">{}</span></li>\n",
+                escape_html(&href(tag)),
+                escape_html(tag),
+                documents.len()
+            ));
+        }
+        html.push_str("</ul>\n");
+        html
+    }
+
+    /// Body of the page of `tag`, linking back to the index at `index_href`
+    pub fn page_html(tag: &str, documents: &[TaggedDocument], index_href: &str) -> String {
+        let mut html = format!(
+            "<p><a href=\"{}\">All tags</a></p>\n<h1>#{}</h1>\n<ul class=\"rune-tag-documents\">\n",
+            escape_html(index_href),
+            escape_html(tag)
+        );
+        for document in documents {
+            html.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                escape_html(&document.url),
+                escape_html(&document.title)
+            ));
+        }
+        html.push_str("</ul>\n");
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_frontmatter_and_inline_tags() {
+        let markdown = concat!(
+            "---\ntags: [Rust, \"#notes\"]\ntitle: Plan #not-a-tag\n---\n",
+            "# Plan\n\n",
+            "Working on #project/rune and #Rust, see issue #12 and [a](#anchor).\n",
+            "`#code` stays code, as does\n\n",
+            "```\n#fenced\n```\n\n",
+            "    #indented\n",
+            "Trailing #todo-/ and a lone # sign.\n",
+        );
+        assert_eq!(parse(markdown), ["notes", "project/rune", "rust", "todo"]);
+        assert_eq!(slug("project/rune"), "project-rune");
+        assert_eq!(
+            document_title(Path::new("plan.md"), markdown),
+            "Plan #not-a-tag"
+        );
+        assert_eq!(
+            document_title(Path::new("road-map.md"), "# Roadmap\n"),
+            "Roadmap"
+        );
+        assert_eq!(
+            document_title(Path::new("road-map.md"), "text\n"),
+            "Road map"
+        );
+    }
+
+    #[test]
+    fn test_pages_list_documents_by_tag() {
+        let mut pages = TagPages::default();
+        let document = |title: &str| TaggedDocument {
+            title: title.to_string(),
+            url: format!("{}.html", title),
+        };
+        pages.add(
+            &["rust".to_string(), "project/rune".to_string()],
+            document("a"),
+        );
+        pages.add(&["rust".to_string()], document("b<c"));
+
+        assert_eq!(pages.len(), 2);
+        let index = pages.index_html(|tag| format!("{}.html", slug(tag)));
+        assert!(index.contains("<a class=\"rune-tag\" href=\"project-rune.html\">#project/rune</a> <span class=\"rune-tag-count\">1</span>"));
+        let (tag, documents) = pages.page("rust").unwrap();
+        let page = TagPages::page_html(tag, documents, "index.html");
+        assert!(page.contains("<h1>#rust</h1>"));
+        assert!(page.contains("<a href=\"b&lt;c.html\">b&lt;c</a>"));
+        assert!(pages.page("missing").is_none());
+    }
+}
@@ -0,0 +1,334 @@
+//! Local usage statistics
+//!
+//! With `"telemetry": {"enabled": true}` in the configuration, rune counts
+//! the sessions started, the documents opened in a preview, the renders and
+//! how often each feature is used, and keeps the counts in `stats.json` in
+//! the rune data directory. Only counts are kept: no paths, names or
+//! contents of documents, and nothing is ever sent anywhere. The server
+//! shows them on its `/stats` page and `rune stats` prints, exports or
+//! deletes them. Without the setting nothing is recorded.
+
+use crate::config::Config;
+use crate::error::{Result, RuneError};
+use crate::event::{SystemEvent, SystemEventHandler};
+use crate::plugin::PluginContext;
+use crate::safe_write::{write_file, WriteStrategy};
+use crate::supervisor::RestartPolicy;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// File the statistics are kept in, under the rune data directory
+pub const STATS_FILE: &str = "stats.json";
+
+/// Shared resource holding the [`UsageRecorder`] when statistics are enabled
+pub const USAGE_STATS_RESOURCE: &str = "usage_stats";
+
+/// Name of the supervised task writing the statistics to disk
+pub const STATS_TASK: &str = "usage-stats";
+
+/// How often recorded statistics are written to disk
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Usage counts kept on this machine
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageStats {
+    /// When counting started, in seconds since the Unix epoch
+    pub since: u64,
+    /// When the counts last changed, in seconds since the Unix epoch
+    pub updated: u64,
+    /// Times rune was started with statistics enabled
+    pub sessions: u64,
+    /// Preview pages of documents opened
+    pub documents_opened: u64,
+    /// Documents rendered, on open and on every change
+    pub renders: u64,
+    /// Times each feature was used, by feature name
+    #[serde(default)]
+    pub features: BTreeMap<String, u64>,
+}
+
+/// Path of the statistics file: the configured one, or `stats.json` in the
+/// rune data directory
+pub fn stats_path(config: &Config) -> Option<PathBuf> {
+    config
+        .telemetry
+        .path
+        .clone()
+        .or_else(|| crate::plugin_data::user_root().map(|root| root.join(STATS_FILE)))
+}
+
+/// Statistics kept in `path`, `None` if nothing was recorded there yet
+pub fn load(path: &Path) -> Result<Option<UsageStats>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    serde_json::from_str(&content).map(Some).map_err(|e| {
+        RuneError::generic(format!(
+            "Failed to read the usage statistics in {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Delete the statistics kept in `path`; whether there were any
+pub fn delete(path: &Path) -> Result<bool> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Counts the usage of this session into the statistics file
+#[derive(Debug)]
+pub struct UsageRecorder {
+    path: PathBuf,
+    stats: Mutex<UsageStats>,
+    /// Whether the counts changed since they were last written
+    dirty: AtomicBool,
+}
+
+impl UsageRecorder {
+    /// Continue the statistics kept in `path`, counting a new session;
+    /// unreadable statistics are started over
+    pub fn open(path: PathBuf) -> Self {
+        let now = now();
+        let mut stats = load(&path)
+            .unwrap_or_else(|e| {
+                warn!("{}; starting over", e);
+                None
+            })
+            .unwrap_or(UsageStats {
+                since: now,
+                ..Default::default()
+            });
+        stats.sessions += 1;
+        stats.updated = now;
+        Self {
+            path,
+            stats: Mutex::new(stats),
+            dirty: AtomicBool::new(true),
+        }
+    }
+
+    /// Recorder of `config`, if it enables statistics
+    pub fn from_config(config: &Config) -> Option<Arc<Self>> {
+        if !config.telemetry.is_enabled() {
+            return None;
+        }
+        let Some(path) = stats_path(config) else {
+            warn!("Usage statistics are enabled but there is no data directory to keep them in");
+            return None;
+        };
+        info!("Recording usage statistics in {}", path.display());
+        Some(Arc::new(Self::open(path)))
+    }
+
+    /// File the statistics are written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record_document_opened(&self) {
+        self.update(|stats| stats.documents_opened += 1);
+    }
+
+    pub fn record_render(&self) {
+        self.update(|stats| stats.renders += 1);
+    }
+
+    /// Count a use of `feature`, such as `export` or `search`
+    pub fn record_feature(&self, feature: &str) {
+        self.update(|stats| *stats.features.entry(feature.to_string()).or_default() += 1);
+    }
+
+    /// Counts recorded so far, this session's included
+    pub fn snapshot(&self) -> UsageStats {
+        self.lock().clone()
+    }
+
+    /// Write the counts to the statistics file if they changed
+    pub fn save(&self) -> Result<()> {
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let result = self.write();
+        if result.is_err() {
+            self.dirty.store(true, Ordering::Release);
+        }
+        result
+    }
+
+    fn write(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_vec_pretty(&self.snapshot())?;
+        write_file(&self.path, &json, WriteStrategy::Atomic)?;
+        Ok(())
+    }
+
+    /// Write changed counts every `interval`, until the task is stopped
+    pub async fn run(self: Arc<Self>, interval: Duration) -> Result<()> {
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            ticks.tick().await;
+            self.save()?;
+        }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut UsageStats)) {
+        let mut stats = self.lock();
+        change(&mut stats);
+        stats.updated = now();
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, UsageStats> {
+        // Counters stay consistent even if a panic interrupted an update
+        self.stats
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Feature a system event counts as a use of
+fn event_feature(event: &SystemEvent) -> Option<&'static str> {
+    match event {
+        SystemEvent::ThemeChanged { .. } => Some("theme-change"),
+        SystemEvent::ConfigChanged { .. } => Some("config-change"),
+        SystemEvent::TunnelOpened { .. } => Some("tunnel"),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl SystemEventHandler for UsageRecorder {
+    async fn handle_system_event(&self, event: &SystemEvent) -> Result<()> {
+        match event {
+            SystemEvent::RenderComplete { .. } => self.record_render(),
+            SystemEvent::SystemShutdownInitiated { .. } => self.save()?,
+            _ => {
+                if let Some(feature) = event_feature(event) {
+                    self.record_feature(feature);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handler_name(&self) -> &str {
+        "usage-stats"
+    }
+}
+
+/// Start recording the usage `config` enables: renders and other events
+/// are counted, the counts are written to disk periodically and on
+/// shutdown, and the recorder is shared as [`USAGE_STATS_RESOURCE`] for
+/// plugins to count what they serve
+pub async fn install(
+    config: &Config,
+    context: &PluginContext,
+) -> Result<Option<Arc<UsageRecorder>>> {
+    let Some(recorder) = UsageRecorder::from_config(config) else {
+        return Ok(None);
+    };
+    context
+        .event_bus
+        .subscribe_system_events(recorder.clone())
+        .await?;
+    let flushed = recorder.clone();
+    context
+        .supervisor
+        .supervise(STATS_TASK, RestartPolicy::default(), move || {
+            flushed.clone().run(FLUSH_INTERVAL)
+        })
+        .await?;
+    context
+        .set_shared_resource(USAGE_STATS_RESOURCE.to_string(), recorder.clone())
+        .await?;
+    Ok(Some(recorder))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_counts_carry_over_sessions() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested").join(STATS_FILE);
+        assert_eq!(load(&path).unwrap(), None);
+
+        let recorder = UsageRecorder::open(path.clone());
+        recorder.record_document_opened();
+        recorder.record_render();
+        recorder.record_render();
+        recorder.record_feature("export");
+        recorder.save().unwrap();
+
+        let recorder = UsageRecorder::open(path.clone());
+        recorder.record_feature("export");
+        recorder.save().unwrap();
+
+        let stats = load(&path).unwrap().unwrap();
+        assert_eq!(stats.sessions, 2);
+        assert_eq!(stats.documents_opened, 1);
+        assert_eq!(stats.renders, 2);
+        assert_eq!(stats.features.get("export"), Some(&2));
+
+        assert!(delete(&path).unwrap());
+        assert!(!delete(&path).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_nothing_is_recorded_unless_enabled() {
+        let dir = TempDir::new().unwrap();
+        let mut config = Config::new();
+        config.telemetry.path = Some(dir.path().join(STATS_FILE));
+        let context = PluginContext::new(
+            Arc::new(crate::event::InMemoryEventBus::new()),
+            Arc::new(config.clone()),
+            Arc::new(crate::state::StateManager::new()),
+        );
+        assert!(install(&config, &context).await.unwrap().is_none());
+        assert!(context
+            .get_shared_resource::<Arc<UsageRecorder>>(USAGE_STATS_RESOURCE)
+            .await
+            .is_none());
+
+        config.telemetry.enabled = Some(true);
+        let recorder = install(&config, &context).await.unwrap().unwrap();
+        context
+            .event_bus
+            .publish_system_event(SystemEvent::render_complete(
+                "abc".to_string(),
+                Duration::from_millis(3),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(recorder.snapshot().renders, 1);
+        assert!(context
+            .get_shared_resource::<Arc<UsageRecorder>>(USAGE_STATS_RESOURCE)
+            .await
+            .is_some());
+        context.supervisor.shutdown().await;
+    }
+}
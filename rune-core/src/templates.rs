@@ -0,0 +1,316 @@
+//! Documents created from templates
+//!
+//! `rune new` and `POST /api/files/new` create documents from templates,
+//! markdown files named after the template, e.g. `meeting.md` for
+//! `meeting`. They are looked up in the directory of the `templates_dir`
+//! setting, then in [`TEMPLATE_DIR`] of the working directory and in
+//! `rune/templates` of the user's configuration directory. Without a
+//! template a document starts from [`BLANK_TEMPLATE`].
+//!
+//! Templates may use `{{date}}`, `{{time}}`, `{{title}}`, `{{author}}` (the
+//! `author` setting) and `{{file}}`, plus the variables given when creating
+//! the document. `{{cursor}}` marks where editors put the cursor.
+
+use std::collections::BTreeMap;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use regex::{Captures, Regex};
+
+use crate::config::Config;
+use crate::error::{Result, RuneError};
+use crate::journal::Date;
+
+/// Directory of templates next to the documents
+pub const TEMPLATE_DIR: &str = ".rune/templates";
+
+/// What a document starts with when no template is named
+pub const BLANK_TEMPLATE: &str = "# {{title}}\n\n{{cursor}}";
+
+/// Placeholder of the cursor position
+const CURSOR: &str = "{{cursor}}";
+
+/// A template and where it was found
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DocumentTemplate {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Directories templates are looked up in, first match wins
+#[derive(Debug, Clone, Default)]
+pub struct Templates {
+    dirs: Vec<PathBuf>,
+}
+
+impl Templates {
+    /// Templates of the configured directory, or of the default ones
+    pub fn from_config(config: &Config) -> Self {
+        let mut dirs = Vec::new();
+        if let Some(dir) = config.get_global_setting::<String>("templates_dir") {
+            dirs.push(PathBuf::from(dir));
+        }
+        dirs.push(PathBuf::from(TEMPLATE_DIR));
+        if let Some(config_dir) = dirs::config_dir() {
+            dirs.push(config_dir.join("rune").join("templates"));
+        }
+        Self::in_dirs(dirs)
+    }
+
+    /// Templates of `dirs`
+    pub fn in_dirs(dirs: Vec<PathBuf>) -> Self {
+        Self { dirs }
+    }
+
+    /// Every template, by name
+    pub fn list(&self) -> Vec<DocumentTemplate> {
+        let mut templates: BTreeMap<String, PathBuf> = BTreeMap::new();
+        for dir in &self.dirs {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            for path in entries.flatten().map(|entry| entry.path()) {
+                if !path.is_file()
+                    || !path
+                        .extension()
+                        .is_some_and(|extension| extension.eq_ignore_ascii_case("md"))
+                {
+                    continue;
+                }
+                if let Some(name) = path.file_stem() {
+                    templates
+                        .entry(name.to_string_lossy().into_owned())
+                        .or_insert(path);
+                }
+            }
+        }
+        templates
+            .into_iter()
+            .map(|(name, path)| DocumentTemplate { name, path })
+            .collect()
+    }
+
+    /// Text of the template `name`, or of the blank one
+    pub fn source(&self, name: Option<&str>) -> Result<String> {
+        let Some(name) = name else {
+            return Ok(BLANK_TEMPLATE.to_string());
+        };
+        let templates = self.list();
+        match templates.iter().find(|template| template.name == name) {
+            Some(template) => std::fs::read_to_string(&template.path).map_err(|e| {
+                RuneError::FileSystem(format!(
+                    "Failed to read template {}: {}",
+                    template.path.display(),
+                    e
+                ))
+            }),
+            None => {
+                let names: Vec<&str> = templates
+                    .iter()
+                    .map(|template| template.name.as_str())
+                    .collect();
+                Err(RuneError::config(format!(
+                    "No template named '{}'. {}",
+                    name,
+                    if names.is_empty() {
+                        format!("Add one as {}/{}.md", TEMPLATE_DIR, name)
+                    } else {
+                        format!("Available: {}", names.join(", "))
+                    }
+                )))
+            }
+        }
+    }
+}
+
+/// Variables every new document at `path` gets; the date and time follow
+/// the journal's `utc_offset`
+pub fn variables(config: &Config, path: &Path) -> BTreeMap<String, String> {
+    let utc_offset = config
+        .journal
+        .utc_offset
+        .as_deref()
+        .and_then(crate::time::parse_utc_offset)
+        .unwrap_or(0);
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+        + utc_offset;
+
+    let mut variables = BTreeMap::new();
+    variables.insert("date".to_string(), Date::today(utc_offset).to_string());
+    variables.insert(
+        "time".to_string(),
+        format!(
+            "{:02}:{:02}",
+            seconds.rem_euclid(86_400) / 3600,
+            seconds.rem_euclid(3600) / 60
+        ),
+    );
+    variables.insert("title".to_string(), title_from_path(path));
+    variables.insert(
+        "file".to_string(),
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    );
+    if let Some(author) = config.get_global_setting::<String>("author") {
+        variables.insert("author".to_string(), author);
+    }
+    variables
+}
+
+/// Title of a document going by its file name, e.g. `Team sync` for
+/// `team-sync.md`
+pub fn title_from_path(path: &Path) -> String {
+    let stem = crate::crypto::plain_path(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().replace(['-', '_'], " "))
+        .unwrap_or_default();
+    let mut chars = stem.trim().chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// `template` with `{{name}}` placeholders replaced by their variables;
+/// placeholders without one are kept
+pub fn fill(template: &str, variables: &BTreeMap<String, String>) -> String {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    let placeholder = PLACEHOLDER.get_or_init(|| {
+        Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_-]*)\s*\}\}").expect("placeholder regex is valid")
+    });
+    placeholder
+        .replace_all(template, |captures: &Captures| {
+            variables
+                .get(&captures[1])
+                .cloned()
+                .unwrap_or_else(|| captures[0].to_string())
+        })
+        .into_owned()
+}
+
+/// A document created from a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewDocument {
+    pub path: PathBuf,
+    /// Line of the `{{cursor}}` placeholder, or the last line
+    pub cursor_line: usize,
+}
+
+/// Create the document `path` from `template`, along with its directory;
+/// an existing file is never overwritten
+pub fn create(
+    path: &Path,
+    template: &str,
+    variables: &BTreeMap<String, String>,
+) -> Result<NewDocument> {
+    let content = fill(template, variables);
+    let cursor = content.find(CURSOR).unwrap_or(content.len());
+    let cursor_line = content[..cursor].matches('\n').count() + 1;
+    let content = content.replace(CURSOR, "");
+
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| match e.kind() {
+            ErrorKind::AlreadyExists => {
+                RuneError::FileSystem(format!("{} already exists", path.display()))
+            }
+            _ => RuneError::Io(e),
+        })?;
+    file.write_all(content.as_bytes())?;
+
+    Ok(NewDocument {
+        path: path.to_path_buf(),
+        cursor_line,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fills_placeholders() {
+        let variables = BTreeMap::from([
+            ("title".to_string(), "Team sync".to_string()),
+            ("author".to_string(), "Ada".to_string()),
+        ]);
+        assert_eq!(
+            fill("# {{title}}\nby {{ author }}, {{unknown}}", &variables),
+            "# Team sync\nby Ada, {{unknown}}"
+        );
+        assert_eq!(
+            title_from_path(Path::new("notes/team-sync.md")),
+            "Team sync"
+        );
+        assert_eq!(
+            title_from_path(Path::new("ideas_2024.md.age")),
+            "Ideas 2024"
+        );
+    }
+
+    #[test]
+    fn test_creates_documents_from_templates() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let local = temp_dir.path().join("local");
+        let shared = temp_dir.path().join("shared");
+        std::fs::create_dir_all(&local).unwrap();
+        std::fs::create_dir_all(&shared).unwrap();
+        std::fs::write(
+            local.join("meeting.md"),
+            "# {{title}}\n\nAttendees: {{author}}\n\n## Notes\n\n{{cursor}}\n",
+        )
+        .unwrap();
+        std::fs::write(shared.join("meeting.md"), "shadowed").unwrap();
+        std::fs::write(shared.join("retro.md"), "# Retro").unwrap();
+        let templates = Templates::in_dirs(vec![local.clone(), shared]);
+
+        let names: Vec<String> = templates.list().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, ["meeting", "retro"]);
+        let error = templates.source(Some("standup")).unwrap_err();
+        assert!(error.to_string().contains("Available: meeting, retro"));
+
+        let mut config = Config::new();
+        config
+            .global_settings
+            .insert("author".to_string(), "Ada".into());
+        let path = temp_dir.path().join("docs/2024/team-sync.md");
+        let document = create(
+            &path,
+            &templates.source(Some("meeting")).unwrap(),
+            &variables(&config, &path),
+        )
+        .unwrap();
+        assert_eq!(document.cursor_line, 7);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# Team sync\n\nAttendees: Ada\n\n## Notes\n\n\n"
+        );
+
+        // Existing documents are left alone
+        assert!(create(&path, "new", &BTreeMap::new()).is_err());
+        assert_eq!(
+            create(
+                &temp_dir.path().join("blank.md"),
+                BLANK_TEMPLATE,
+                &BTreeMap::new()
+            )
+            .unwrap()
+            .cursor_line,
+            3
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! Text encodings of documents
+//!
+//! Documents are edited as UTF-8, but not every file is UTF-8: notes from
+//! Windows may be UTF-16 and old ones Latin-1. [`read_text`] sniffs the
+//! bytes of a file: byte order marks tell UTF-8 and UTF-16 apart, UTF-16
+//! without one shows in its zero bytes, valid UTF-8 is UTF-8 and any other
+//! text is taken as Latin-1, which every byte is valid in. Files with zero
+//! bytes or many control characters are binary and are refused, since
+//! opening an image or an archive as text only garbles it.
+//!
+//! Line endings are a matter of the file too: documents are edited with
+//! `\n` alone, and [`LineEnding`] tells which ending a file had so it is
+//! written back with the same one.
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::Path;
+
+use crate::{Result, RuneError};
+
+/// Bytes looked at to tell text from binary
+pub const SNIFF_BYTES: usize = 8 * 1024;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// Encoding of a text file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark
+    Utf8Bom,
+    /// Little-endian UTF-16, written with a byte order mark
+    Utf16Le,
+    /// Big-endian UTF-16, written with a byte order mark
+    Utf16Be,
+    /// ISO 8859-1
+    Latin1,
+}
+
+impl std::fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf8Bom => "UTF-8 with BOM",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::Latin1 => "Latin-1",
+        })
+    }
+}
+
+/// Whether `byte` is a control character text does not have
+fn is_binary_control(byte: u8) -> bool {
+    matches!(byte, 0..=0x08 | 0x0E..=0x1A | 0x1C..=0x1F | 0x7F)
+}
+
+impl TextEncoding {
+    /// Whether the encoding is UTF-8, with or without a byte order mark
+    pub fn is_utf8(self) -> bool {
+        matches!(self, Self::Utf8 | Self::Utf8Bom)
+    }
+
+    /// Encoding of the text `bytes`, `None` for binary content; only the
+    /// start of the content tells whether it is binary
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(UTF8_BOM) {
+            return Some(Self::Utf8Bom);
+        }
+        if bytes.starts_with(UTF16LE_BOM) {
+            return Some(Self::Utf16Le);
+        }
+        if bytes.starts_with(UTF16BE_BOM) {
+            return Some(Self::Utf16Be);
+        }
+
+        let sample = &bytes[..bytes.len().min(SNIFF_BYTES)];
+        // Mostly ASCII UTF-16 has a zero byte in every other place
+        let zeros_at = |parity| {
+            sample
+                .iter()
+                .skip(parity)
+                .step_by(2)
+                .filter(|&&byte| byte == 0)
+                .count()
+        };
+        let (even_zeros, odd_zeros) = (zeros_at(0), zeros_at(1));
+        let pairs = sample.len() / 2;
+        if even_zeros == 0 && odd_zeros * 2 > pairs {
+            return Some(Self::Utf16Le);
+        }
+        if odd_zeros == 0 && even_zeros * 2 > pairs {
+            return Some(Self::Utf16Be);
+        }
+        if even_zeros + odd_zeros > 0 {
+            return None;
+        }
+
+        let controls = sample
+            .iter()
+            .filter(|&&byte| is_binary_control(byte))
+            .count();
+        if controls * 10 > sample.len() {
+            return None;
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(_) => Some(Self::Utf8),
+            Err(_) => Some(Self::Latin1),
+        }
+    }
+
+    /// Text of `bytes` in this encoding, without a byte order mark;
+    /// malformed parts become replacement characters
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Self::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Self::Utf8Bom => {
+                String::from_utf8_lossy(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes)).into_owned()
+            }
+            Self::Utf16Le | Self::Utf16Be => {
+                let bom = if self == Self::Utf16Le {
+                    UTF16LE_BOM
+                } else {
+                    UTF16BE_BOM
+                };
+                let units = bytes
+                    .strip_prefix(bom)
+                    .unwrap_or(bytes)
+                    .chunks_exact(2)
+                    .map(|pair| match self {
+                        Self::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                        _ => u16::from_be_bytes([pair[0], pair[1]]),
+                    });
+                char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+            Self::Latin1 => bytes.iter().map(|&byte| char::from(byte)).collect(),
+        }
+    }
+
+    /// Bytes of `text` in this encoding; fails for text Latin-1 has no
+    /// characters for
+    pub fn encode(self, text: &str) -> Result<Vec<u8>> {
+        Ok(match self {
+            Self::Utf8 => text.as_bytes().to_vec(),
+            Self::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+            Self::Utf16Le => UTF16LE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect(),
+            Self::Utf16Be => UTF16BE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+                .collect(),
+            Self::Latin1 => text
+                .chars()
+                .map(|c| {
+                    u8::try_from(c).map_err(|_| {
+                        RuneError::file_system(format!(
+                            "'{}' cannot be written as Latin-1; save the document as UTF-8",
+                            c
+                        ))
+                    })
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+/// Line ending of a text file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    /// `\r\n`, as written on Windows
+    Crlf,
+}
+
+impl std::fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+        })
+    }
+}
+
+impl LineEnding {
+    /// Ending most lines of `text` have; text without line breaks is `Lf`
+    pub fn detect(text: &str) -> Self {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        if crlf > lf {
+            Self::Crlf
+        } else {
+            Self::Lf
+        }
+    }
+
+    /// `text` with every `\r\n` turned into `\n`
+    pub fn normalize(text: &str) -> Cow<'_, str> {
+        if text.contains("\r\n") {
+            Cow::Owned(text.replace("\r\n", "\n"))
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    /// Normalized `text` with its lines ended by this ending
+    pub fn apply(self, text: &str) -> Cow<'_, str> {
+        match self {
+            Self::Lf => Self::normalize(text),
+            Self::Crlf => Cow::Owned(Self::normalize(text).replace('\n', "\r\n")),
+        }
+    }
+}
+
+/// Text of `bytes` and its encoding, `None` for binary content
+pub fn decode(bytes: &[u8]) -> Option<(String, TextEncoding)> {
+    let encoding = TextEncoding::detect(bytes)?;
+    Some((encoding.decode(bytes), encoding))
+}
+
+/// Read the text file at `path`, transcoded to UTF-8
+pub fn read_text(path: &Path) -> Result<(String, TextEncoding)> {
+    let bytes = std::fs::read(path)?;
+    decode(&bytes).ok_or_else(|| binary_file(path))
+}
+
+/// Error for the binary file at `path`
+pub fn binary_file(path: &Path) -> RuneError {
+    RuneError::file_system(format!(
+        "{} is a binary file; only text documents can be opened",
+        path.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodings_are_detected_and_round_trip() {
+        let text = "Café — naïve\n";
+        for encoding in [
+            TextEncoding::Utf8,
+            TextEncoding::Utf8Bom,
+            TextEncoding::Utf16Le,
+            TextEncoding::Utf16Be,
+        ] {
+            let bytes = encoding.encode(text).unwrap();
+            assert_eq!(decode(&bytes), Some((text.to_string(), encoding)));
+        }
+
+        let latin1 = b"Caf\xE9 na\xEFve\n";
+        assert_eq!(
+            decode(latin1),
+            Some(("Café naïve\n".to_string(), TextEncoding::Latin1))
+        );
+        assert_eq!(TextEncoding::Latin1.encode("Café naïve\n").unwrap(), latin1);
+        assert!(TextEncoding::Latin1.encode(text).is_err());
+
+        // UTF-16 without a byte order mark shows in its zero bytes
+        let bare: Vec<u8> = "# Notes\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(TextEncoding::detect(&bare), Some(TextEncoding::Utf16Le));
+
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\x02\x80";
+        assert_eq!(decode(png), None);
+        assert_eq!(TextEncoding::detect(b"\x01\x02\x03\x04text"), None);
+        assert_eq!(decode(b""), Some((String::new(), TextEncoding::Utf8)));
+    }
+
+    #[test]
+    fn test_line_endings_are_detected_and_restored() {
+        let windows = "# Title\r\n\r\nBody\r\nlast\n";
+        assert_eq!(LineEnding::detect(windows), LineEnding::Crlf);
+        assert_eq!(LineEnding::detect("a\nb\r\nc\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("no breaks"), LineEnding::Lf);
+
+        let normalized = LineEnding::normalize(windows);
+        assert_eq!(normalized, "# Title\n\nBody\nlast\n");
+        assert_eq!(
+            LineEnding::Crlf.apply(&normalized),
+            "# Title\r\n\r\nBody\r\nlast\r\n"
+        );
+        assert_eq!(LineEnding::Lf.apply(windows), normalized);
+        assert!(matches!(LineEnding::normalize("a\nb"), Cow::Borrowed(_)));
+    }
+}
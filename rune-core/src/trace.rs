@@ -0,0 +1,113 @@
+//! Trace ids following one request through the plugins
+//!
+//! The server gives every HTTP request and WebSocket message a [`TraceId`],
+//! taken from its `X-Request-Id` header when the client sent a usable one,
+//! and handles it within [`scope`]. Everything running in that task sees
+//! the id through [`current`]: render contexts and the render and error
+//! events are created with it, captured log records carry it, and failed
+//! requests report it, so `/api/logs?trace_id=…` shows one flow across the
+//! server, the editor and the renderers.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+
+/// Header carrying the trace id of a request and its response
+pub const TRACE_HEADER: &str = "x-request-id";
+
+/// Longest trace id accepted from a client
+const MAX_TRACE_ID_LEN: usize = 64;
+
+tokio::task_local! {
+    static CURRENT: TraceId;
+}
+
+/// Id correlating the work done for one request
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TraceId(String);
+
+impl TraceId {
+    /// A new random id of 16 hex digits
+    pub fn new() -> Self {
+        let mut id = uuid::Uuid::new_v4().simple().to_string();
+        id.truncate(16);
+        Self(id)
+    }
+
+    /// Id sent by a client, if it is short and only has letters, digits,
+    /// `-`, `_` and `.`, so it can be logged and echoed safely
+    pub fn parse(id: &str) -> Option<Self> {
+        let valid = !id.is_empty()
+            && id.len() <= MAX_TRACE_ID_LEN
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        valid.then(|| Self(id.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for TraceId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Trace id of the request the current task works for, if any
+pub fn current() -> Option<TraceId> {
+    CURRENT.try_with(|id| id.clone()).ok()
+}
+
+/// Run `future` as part of the request traced by `id`
+pub async fn scope<F: Future>(id: TraceId, future: F) -> F::Output {
+    CURRENT.scope(id, future).await
+}
+
+/// Spawn `future` as a task still working for the current request, if any
+pub fn spawn<F>(future: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match current() {
+        Some(id) => tokio::spawn(CURRENT.scope(id, future)),
+        None => tokio::spawn(future),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_ids_are_checked() {
+        assert_eq!(TraceId::new().as_str().len(), 16);
+        assert_ne!(TraceId::new(), TraceId::new());
+        assert!(TraceId::parse("req-42_a.b").is_some());
+        for invalid in ["", "has space", "line\nbreak", &"x".repeat(65)] {
+            assert!(TraceId::parse(invalid).is_none(), "{:?}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scope_sets_the_current_id() {
+        assert_eq!(current(), None);
+        let id = TraceId::new();
+        let seen = scope(id.clone(), async { current() }).await;
+        assert_eq!(seen, Some(id.clone()));
+        assert_eq!(current(), None);
+
+        let spawned = scope(id.clone(), async { spawn(async { current() }).await }).await;
+        assert_eq!(spawned.unwrap(), Some(id));
+    }
+}
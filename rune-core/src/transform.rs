@@ -0,0 +1,572 @@
+//! Document transforms
+//!
+//! Named operations rewriting a parsed document, like sorting a list or
+//! wrapping long lines. They change the AST and the document is written back
+//! by the [`MarkdownSerializer`], so blocks a transform leaves alone keep
+//! their source formatting. Frontmatter is not markdown: it is split off
+//! before parsing and written back unchanged. Every [`TransformRegistry`]
+//! starts with the built-in transforms; plugins add their own to the registry
+//! shared as [`TRANSFORM_REGISTRY_RESOURCE`].
+
+use regex::Regex;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::RwLock;
+
+use crate::ast::{Node, NodeType, Tree};
+use crate::error::{Result, RuneError};
+use crate::frontmatter;
+use crate::parser::MarkdownParser;
+use crate::plugin::PluginContext;
+use crate::serializer::{write_inlines, MarkdownSerializer};
+
+/// Shared resource key of the [`TransformRegistry`], an
+/// `Arc<TransformRegistry>`
+pub const TRANSFORM_REGISTRY_RESOURCE: &str = "transform_registry";
+
+/// Options given to a transform, as sent to the transform API
+pub type TransformOptions = serde_json::Map<String, serde_json::Value>;
+
+/// A named operation on a document's AST
+pub trait Transform: Send + Sync {
+    /// Name the transform is requested by
+    fn name(&self) -> &str;
+
+    /// One line describing what the transform does
+    fn description(&self) -> &str;
+
+    /// Apply the transform to `tree`
+    fn apply(&self, tree: &mut Tree, options: &TransformOptions) -> Result<()>;
+}
+
+/// A transform as listed by the registry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransformInfo {
+    pub name: String,
+    pub description: String,
+}
+
+/// Registry of the transforms documents can go through
+pub struct TransformRegistry {
+    transforms: RwLock<BTreeMap<String, Arc<dyn Transform>>>,
+}
+
+impl TransformRegistry {
+    /// Create a registry holding the built-in transforms
+    pub fn new() -> Self {
+        let builtin: [Arc<dyn Transform>; 4] = [
+            Arc::new(SortList),
+            Arc::new(RenumberHeadings),
+            Arc::new(SetextToAtx),
+            Arc::new(WrapLines),
+        ];
+        Self {
+            transforms: RwLock::new(
+                builtin
+                    .into_iter()
+                    .map(|transform| (transform.name().to_string(), transform))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The registry shared between plugins, created on first use
+    pub async fn shared(context: &PluginContext) -> Result<Arc<TransformRegistry>> {
+        if let Some(registry) = context
+            .get_shared_resource::<Arc<TransformRegistry>>(TRANSFORM_REGISTRY_RESOURCE)
+            .await
+        {
+            return Ok(registry.as_ref().clone());
+        }
+        let registry = Arc::new(TransformRegistry::new());
+        context
+            .set_shared_resource(TRANSFORM_REGISTRY_RESOURCE.to_string(), registry.clone())
+            .await?;
+        Ok(registry)
+    }
+
+    /// Register a transform
+    pub async fn register_transform(&self, transform: Arc<dyn Transform>) -> Result<()> {
+        let name = transform.name().to_string();
+        let mut transforms = self.transforms.write().await;
+        if transforms.contains_key(&name) {
+            return Err(RuneError::Plugin(format!(
+                "Transform '{}' is already registered",
+                name
+            )));
+        }
+        transforms.insert(name.clone(), transform);
+        tracing::info!("Registered document transform: {}", name);
+        Ok(())
+    }
+
+    /// Unregister a transform
+    pub async fn unregister_transform(&self, name: &str) -> Result<()> {
+        match self.transforms.write().await.remove(name) {
+            Some(_) => Ok(()),
+            None => Err(RuneError::Plugin(format!(
+                "Transform '{}' is not registered",
+                name
+            ))),
+        }
+    }
+
+    /// Registered transforms, by name
+    pub async fn list_transforms(&self) -> Vec<TransformInfo> {
+        self.transforms
+            .read()
+            .await
+            .values()
+            .map(|transform| TransformInfo {
+                name: transform.name().to_string(),
+                description: transform.description().to_string(),
+            })
+            .collect()
+    }
+
+    /// Run `markdown` through the named transform
+    pub async fn transform(
+        &self,
+        name: &str,
+        markdown: &str,
+        options: &TransformOptions,
+    ) -> Result<String> {
+        let transform = self
+            .transforms
+            .read()
+            .await
+            .get(name)
+            .cloned()
+            .ok_or_else(|| RuneError::config(format!("Unknown transform '{}'", name)))?;
+        let (frontmatter, body) = frontmatter::split(markdown);
+        let mut options = options.clone();
+        if let Some(line) = number_option(&options, "line")? {
+            // Lines are given in the document, but counted in the body
+            let skipped = frontmatter.lines().count();
+            if line <= skipped {
+                return Err(RuneError::config(format!(
+                    "Line {} is in the frontmatter",
+                    line
+                )));
+            }
+            options.insert("line".to_string(), (line - skipped).into());
+        }
+        let mut tree = MarkdownParser::new().parse(body);
+        transform.apply(&mut tree, &options)?;
+        Ok(format!(
+            "{}{}",
+            frontmatter,
+            MarkdownSerializer::new().serialize(&tree)
+        ))
+    }
+}
+
+impl Default for TransformRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A positive number option
+fn number_option(options: &TransformOptions, key: &str) -> Result<Option<usize>> {
+    match options.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(value) => value
+            .as_u64()
+            .filter(|&number| number > 0)
+            .map(|number| Some(number as usize))
+            .ok_or_else(|| {
+                RuneError::config(format!("Option '{}' must be a positive number", key))
+            }),
+    }
+}
+
+fn flag_option(options: &TransformOptions, key: &str) -> Result<bool> {
+    match options.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(false),
+        Some(value) => value
+            .as_bool()
+            .ok_or_else(|| RuneError::config(format!("Option '{}' must be true or false", key))),
+    }
+}
+
+/// Apply to every node of a type in `node`, outermost first
+fn for_each_node(node: &mut Node, node_type: NodeType, apply: &mut dyn FnMut(&mut Node)) {
+    if node.node_type == node_type {
+        apply(node);
+    }
+    for child in &mut node.children {
+        for_each_node(child, node_type, apply);
+    }
+}
+
+/// Adjacent text nodes joined, as the parser adds text a character at a time
+fn merge_text(nodes: Vec<Node>) -> Vec<Node> {
+    let mut merged: Vec<Node> = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        match merged.last_mut() {
+            Some(last) if last.node_type == NodeType::Text && node.node_type == NodeType::Text => {
+                last.data.push_str(&node.data)
+            }
+            _ => merged.push(node),
+        }
+    }
+    merged
+}
+
+/// Sorts list items alphabetically
+///
+/// Options: `line`, a line of the list to sort, or else every list is
+/// sorted; `descending` to sort Z to A.
+struct SortList;
+
+impl Transform for SortList {
+    fn name(&self) -> &str {
+        "sort-list"
+    }
+
+    fn description(&self) -> &str {
+        "Sort list items alphabetically"
+    }
+
+    fn apply(&self, tree: &mut Tree, options: &TransformOptions) -> Result<()> {
+        let descending = flag_option(options, "descending")?;
+        let mut sort = |list: &mut Node| {
+            list.children.sort_by_cached_key(|item| {
+                let key = item.text_content().trim().to_lowercase();
+                (descending.then(|| Reverse(key.clone())), key)
+            });
+        };
+
+        let Some(line) = number_option(options, "line")? else {
+            for_each_node(&mut tree.root, NodeType::List, &mut sort);
+            return Ok(());
+        };
+        let list = tree
+            .root
+            .children
+            .iter_mut()
+            .find(|block| {
+                block.node_type == NodeType::List
+                    && block.position.as_ref().is_some_and(|position| {
+                        let lines = block.tokens.iter().filter(|&&b| b == b'\n').count();
+                        (position.line..=position.line + lines).contains(&line)
+                    })
+            })
+            .ok_or_else(|| RuneError::config(format!("There is no list at line {}", line)))?;
+        sort(list);
+        Ok(())
+    }
+}
+
+/// Numbers headings by section, like `1.`, `1.1` and `1.2`, replacing the
+/// numbers they had
+///
+/// Options: `from_level`, the level of the headings numbered `1.`, `2.`;
+/// shallower headings are left alone. Defaults to 1.
+struct RenumberHeadings;
+
+impl Transform for RenumberHeadings {
+    fn name(&self) -> &str {
+        "renumber-headings"
+    }
+
+    fn description(&self) -> &str {
+        "Number headings by section"
+    }
+
+    fn apply(&self, tree: &mut Tree, options: &TransformOptions) -> Result<()> {
+        static NUMBER: OnceLock<Regex> = OnceLock::new();
+        let number = NUMBER.get_or_init(|| Regex::new(r"^\d+(\.\d+)*\.?\s+").unwrap());
+        let from_level = number_option(options, "from_level")?.unwrap_or(1);
+
+        // Levels of the enclosing sections and their counts
+        let mut sections: Vec<(usize, usize)> = Vec::new();
+        for heading in tree
+            .root
+            .children
+            .iter_mut()
+            .filter(|block| block.node_type == NodeType::Heading)
+        {
+            let level = heading.level.unwrap_or(1);
+            if level < from_level {
+                sections.clear();
+                continue;
+            }
+            while sections.last().is_some_and(|&(open, _)| open > level) {
+                sections.pop();
+            }
+            match sections.last_mut() {
+                Some((open, count)) if *open == level => *count += 1,
+                _ => sections.push((level, 1)),
+            }
+            let mut label = sections
+                .iter()
+                .map(|(_, count)| count.to_string())
+                .collect::<Vec<_>>()
+                .join(".");
+            if sections.len() == 1 {
+                label.push('.');
+            }
+
+            let mut children = merge_text(std::mem::take(&mut heading.children));
+            match children.first_mut() {
+                Some(first) if first.node_type == NodeType::Text => {
+                    let text = number.replace(&first.data, "");
+                    first.data = format!("{} {}", label, text);
+                }
+                _ => children.insert(0, Node::text(&format!("{} ", label))),
+            }
+            heading.children = children;
+        }
+        Ok(())
+    }
+}
+
+/// Rewrites `===` and `---` underlined headings as `#` headings
+struct SetextToAtx;
+
+impl Transform for SetextToAtx {
+    fn name(&self) -> &str {
+        "setext-to-atx"
+    }
+
+    fn description(&self) -> &str {
+        "Convert underlined headings to # headings"
+    }
+
+    fn apply(&self, tree: &mut Tree, _options: &TransformOptions) -> Result<()> {
+        fn has_setext(node: &Node) -> bool {
+            let setext = node.node_type == NodeType::Heading
+                && !node.tokens.is_empty()
+                && !node.tokens.starts_with(b"#");
+            setext || node.children.iter().any(has_setext)
+        }
+
+        // Setext headings parse to the same AST, so their blocks are
+        // detached from their source to be written anew
+        for block in &mut tree.root.children {
+            if has_setext(block) {
+                block.position = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rewraps paragraphs so lines are at most `width` characters, 80 by
+/// default; words longer than that get a line of their own
+struct WrapLines;
+
+impl Transform for WrapLines {
+    fn name(&self) -> &str {
+        "wrap-lines"
+    }
+
+    fn description(&self) -> &str {
+        "Wrap paragraphs at a line width"
+    }
+
+    fn apply(&self, tree: &mut Tree, options: &TransformOptions) -> Result<()> {
+        let width = number_option(options, "width")?.unwrap_or(80);
+        wrap_blocks(&mut tree.root.children, width);
+        Ok(())
+    }
+}
+
+fn wrap_blocks(blocks: &mut [Node], width: usize) {
+    for block in blocks {
+        match block.node_type {
+            NodeType::Paragraph => {
+                let children = std::mem::take(&mut block.children);
+                block.children = wrap_paragraph(children, width);
+            }
+            // Quoted lines lose two characters to the `> ` marker
+            NodeType::Blockquote => {
+                wrap_blocks(&mut block.children, width.saturating_sub(2).max(1))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Inline content laid out in lines of at most `width` characters
+fn wrap_paragraph(children: Vec<Node>, width: usize) -> Vec<Node> {
+    // Words, with `None` for line breaks
+    let mut words: Vec<Option<Vec<Node>>> = Vec::new();
+    let mut word = Vec::new();
+    for child in merge_text(children) {
+        match child.node_type {
+            NodeType::Text => {
+                for (index, piece) in child.data.split(char::is_whitespace).enumerate() {
+                    if index > 0 && !word.is_empty() {
+                        words.push(Some(std::mem::take(&mut word)));
+                    }
+                    if !piece.is_empty() {
+                        word.push(Node::text(piece));
+                    }
+                }
+            }
+            NodeType::SoftBreak | NodeType::LineBreak => {
+                if !word.is_empty() {
+                    words.push(Some(std::mem::take(&mut word)));
+                }
+                if child.node_type == NodeType::LineBreak {
+                    words.push(None);
+                }
+            }
+            _ => word.push(child),
+        }
+    }
+    if !word.is_empty() {
+        words.push(Some(word));
+    }
+
+    let mut wrapped = Vec::new();
+    let mut line_width = 0;
+    for word in words {
+        let Some(word) = word else {
+            wrapped.push(Node::new(NodeType::LineBreak));
+            line_width = 0;
+            continue;
+        };
+        let word_width = write_inlines(&word).chars().count();
+        if line_width > 0 && line_width + 1 + word_width > width {
+            wrapped.push(Node::new(NodeType::SoftBreak));
+            line_width = 0;
+        } else if line_width > 0 {
+            wrapped.push(Node::text(" "));
+            line_width += 1;
+        }
+        wrapped.extend(word);
+        line_width += word_width;
+    }
+    wrapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn transform(name: &str, markdown: &str, options: serde_json::Value) -> String {
+        let options = options.as_object().cloned().unwrap_or_default();
+        TransformRegistry::new()
+            .transform(name, markdown, &options)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_builtin_transforms() {
+        let list = "Intro\n\n* pear\n* Apple\n* fig\n\nOutro\n\n- b\n- a\n";
+        assert_eq!(
+            transform("sort-list", list, serde_json::json!({ "line": 4 })).await,
+            "Intro\n\n- Apple\n- fig\n- pear\n\nOutro\n\n- b\n- a\n"
+        );
+        assert_eq!(
+            transform("sort-list", list, serde_json::json!({ "descending": true })).await,
+            "Intro\n\n- pear\n- fig\n- Apple\n\nOutro\n\n- b\n- a\n"
+        );
+
+        let headings = "# Guide\n\n## 3. Setup\n\n### Install\n\n### Configure\n\n## Usage\n";
+        assert_eq!(
+            transform(
+                "renumber-headings",
+                headings,
+                serde_json::json!({ "from_level": 2 })
+            )
+            .await,
+            "# Guide\n\n## 1. Setup\n\n### 1.1 Install\n\n### 1.2 Configure\n\n## 2. Usage\n"
+        );
+
+        assert_eq!(
+            transform(
+                "setext-to-atx",
+                "Title\n=====\n\nText\n\n> Part\n> ----\n",
+                serde_json::json!({})
+            )
+            .await,
+            "# Title\n\nText\n\n> ## Part\n"
+        );
+
+        assert_eq!(
+            transform(
+                "wrap-lines",
+                "one two three **four five** six\nseven\n\n> eight nine ten\n",
+                serde_json::json!({ "width": 12 })
+            )
+            .await,
+            "one two\nthree\n**four five**\nsix seven\n\n> eight nine\n> ten\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_frontmatter_is_left_alone() {
+        let frontmatter = "---\ntitle: Guide\ntags: [a, b]\n---\n";
+        let document = format!("{}Setup\n-----\n\n## Usage\n", frontmatter);
+        assert_eq!(
+            transform("setext-to-atx", &document, serde_json::json!({})).await,
+            format!("{}## Setup\n\n## Usage\n", frontmatter)
+        );
+        assert_eq!(
+            transform("renumber-headings", &document, serde_json::json!({})).await,
+            format!("{}## 1. Setup\n\n## 2. Usage\n", frontmatter)
+        );
+
+        let list = format!("{}\n* b\n* a\n", frontmatter);
+        assert_eq!(
+            transform("sort-list", &list, serde_json::json!({ "line": 6 })).await,
+            format!("{}- a\n- b\n", frontmatter)
+        );
+        let options = serde_json::json!({ "line": 2 });
+        assert!(TransformRegistry::new()
+            .transform("sort-list", &list, options.as_object().unwrap())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_registry_errors_and_plugins() {
+        struct Shout;
+        impl Transform for Shout {
+            fn name(&self) -> &str {
+                "shout"
+            }
+            fn description(&self) -> &str {
+                "Upper-case all text"
+            }
+            fn apply(&self, tree: &mut Tree, _options: &TransformOptions) -> Result<()> {
+                for_each_node(&mut tree.root, NodeType::Text, &mut |text| {
+                    text.data = text.data.to_uppercase()
+                });
+                Ok(())
+            }
+        }
+
+        let registry = TransformRegistry::new();
+        registry.register_transform(Arc::new(Shout)).await.unwrap();
+        assert!(registry.register_transform(Arc::new(Shout)).await.is_err());
+        assert!(registry
+            .list_transforms()
+            .await
+            .iter()
+            .any(|info| info.name == "shout"));
+        let options = TransformOptions::new();
+        assert_eq!(
+            registry
+                .transform("shout", "# hi\n", &options)
+                .await
+                .unwrap(),
+            "# HI\n"
+        );
+
+        assert!(registry.transform("nope", "hi", &options).await.is_err());
+        let bad_width = serde_json::json!({ "width": "wide" });
+        assert!(registry
+            .transform("wrap-lines", "hi", bad_width.as_object().unwrap())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//! Trust in workspaces
+//!
+//! A workspace can bring its own configuration in `.rune/config.json`, and
+//! with it a page template, export hooks or allowed programs: opening someone
+//! else's repository must not run their code. Until the user trusts a
+//! workspace, rune ignores its configuration, drops the scripts of themes and
+//! skips export hooks, which could run the workspace's own build scripts.
+//!
+//! Trusted workspaces are kept in `<data dir>/rune/trusted-workspaces.json`;
+//! trusting a directory trusts everything below it. Whether the open
+//! workspace is trusted is the `workspace_trusted` setting, so plugins can
+//! tell with [`is_trusted`].
+
+use crate::config::Config;
+use crate::error::{Result, RuneError};
+use crate::plugin_data;
+use crate::rune_dir::RuneDir;
+use crate::safe_write::{write_file, WriteStrategy};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// File listing the trusted workspaces, relative to the user data directory
+pub const TRUST_FILE: &str = "trusted-workspaces.json";
+
+/// Setting telling whether the open workspace is trusted
+pub const TRUSTED_SETTING: &str = "workspace_trusted";
+
+/// Workspaces the user trusts
+#[derive(Debug, Clone, Default)]
+pub struct TrustStore {
+    /// File the store is saved to; `None` without a user data directory
+    file: Option<PathBuf>,
+    workspaces: BTreeSet<PathBuf>,
+}
+
+impl TrustStore {
+    /// Store of the user data directory
+    pub fn load() -> Result<Self> {
+        match plugin_data::user_root() {
+            Some(root) => Self::at(root.join(TRUST_FILE)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Store kept in `file`, empty when the file does not exist yet
+    pub fn at(file: PathBuf) -> Result<Self> {
+        let workspaces = match std::fs::read_to_string(&file) {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| {
+                RuneError::config(format!(
+                    "Invalid list of trusted workspaces {}: {}",
+                    file.display(),
+                    e
+                ))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeSet::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            file: Some(file),
+            workspaces,
+        })
+    }
+
+    /// Whether `workspace` or a directory above it is trusted
+    pub fn is_trusted(&self, workspace: &Path) -> bool {
+        canonical(workspace)
+            .ancestors()
+            .any(|dir| self.workspaces.contains(dir))
+    }
+
+    /// Trust `workspace` from now on
+    pub fn trust(&mut self, workspace: &Path) -> Result<()> {
+        if self.workspaces.insert(canonical(workspace)) {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Stop trusting `workspace`; returns whether it was trusted
+    pub fn revoke(&mut self, workspace: &Path) -> Result<bool> {
+        let revoked = self.workspaces.remove(&canonical(workspace));
+        if revoked {
+            self.save()?;
+        }
+        Ok(revoked)
+    }
+
+    /// Trusted workspaces, in order
+    pub fn workspaces(&self) -> impl Iterator<Item = &Path> {
+        self.workspaces.iter().map(PathBuf::as_path)
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(&self.workspaces)?;
+        write_file(file, json.as_bytes(), WriteStrategy::Atomic)?;
+        Ok(())
+    }
+}
+
+/// Configuration file of the workspace in `workspace`
+pub fn local_config_file(workspace: &Path) -> PathBuf {
+    RuneDir::of(workspace).config_file()
+}
+
+/// Whether the open workspace is trusted; without a workspace, as for
+/// embedders building their own configuration, everything is
+pub fn is_trusted(config: &Config) -> bool {
+    config
+        .get_global_setting::<bool>(TRUSTED_SETTING)
+        .unwrap_or(true)
+}
+
+/// Apply the workspace in `workspace` to `config`: merge its configuration
+/// when `trusted`, otherwise skip it and turn off export hooks
+pub fn apply(config: &mut Config, workspace: &Path, trusted: bool) -> Result<()> {
+    let local = local_config_file(workspace);
+    if trusted {
+        if local.is_file() {
+            info!("Loading workspace configuration from {}", local.display());
+            config.merge(Config::from_file(&local)?)?;
+        }
+    } else {
+        if local.is_file() {
+            warn!(
+                "Ignoring {}: {} is not a trusted workspace",
+                local.display(),
+                workspace.display()
+            );
+        }
+        if !config.export_hooks.is_empty() {
+            warn!(
+                "Skipping export hooks: {} is not a trusted workspace",
+                workspace.display()
+            );
+            config.export_hooks = Default::default();
+        }
+    }
+    config.set_global_setting(TRUSTED_SETTING.to_string(), trusted)
+}
+
+/// Whether trusting `workspace` would change anything with `config`
+pub fn needs_trust(config: &Config, workspace: &Path) -> bool {
+    local_config_file(workspace).is_file() || !config.export_hooks.is_empty()
+}
+
+/// `path` with symlinks resolved, as given when it does not exist
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ExportHook, ExportHookAction};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_workspace_configuration_needs_trust() {
+        let temp_dir = tempdir().unwrap();
+        let workspace = temp_dir.path().join("repo");
+        std::fs::create_dir_all(workspace.join(".rune")).unwrap();
+        let mut local = Config::new();
+        local
+            .set_global_setting("template_path".to_string(), "evil.html")
+            .unwrap();
+        local.save_to_file(&local_config_file(&workspace)).unwrap();
+
+        let mut store = TrustStore::at(temp_dir.path().join(TRUST_FILE)).unwrap();
+        assert!(!store.is_trusted(&workspace));
+
+        let mut config = Config::new();
+        config.export_hooks.post = vec![ExportHook {
+            action: ExportHookAction::Command("make deploy".to_string()),
+            allow_failure: false,
+        }];
+        assert!(needs_trust(&config, &workspace));
+        apply(&mut config, &workspace, false).unwrap();
+        assert!(!is_trusted(&config));
+        assert!(config.export_hooks.post.is_empty());
+        assert_eq!(config.get_template_path(), None);
+
+        store.trust(&workspace).unwrap();
+        let store = TrustStore::at(temp_dir.path().join(TRUST_FILE)).unwrap();
+        assert!(store.is_trusted(&workspace));
+        assert!(store.is_trusted(&workspace.join("docs")));
+        assert!(!store.is_trusted(temp_dir.path()));
+
+        let mut config = Config::new();
+        apply(&mut config, &workspace, true).unwrap();
+        assert!(is_trusted(&config));
+        assert_eq!(config.get_template_path(), Some(PathBuf::from("evil.html")));
+
+        let mut store = store;
+        assert!(store.revoke(&workspace).unwrap());
+        assert!(!store.revoke(&workspace).unwrap());
+        assert_eq!(store.workspaces().count(), 0);
+    }
+}
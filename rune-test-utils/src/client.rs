@@ -0,0 +1,234 @@
+//! HTTP and WebSocket client for driving a server from tests
+
+use crate::DEFAULT_TIMEOUT;
+use futures_util::{SinkExt, StreamExt};
+use rune_core::error::{Result, RuneError};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// Answer to an HTTP request
+#[derive(Debug, Clone)]
+pub struct HttpReply {
+    pub status: u16,
+    pub body: String,
+}
+
+impl HttpReply {
+    /// Body parsed as JSON
+    pub fn json(&self) -> Result<Value> {
+        Ok(serde_json::from_str(&self.body)?)
+    }
+}
+
+/// Lines of a client's exchanges, shared by its sockets
+type Transcript = Arc<Mutex<Vec<String>>>;
+
+/// Client of one server, recording what it sends and receives
+///
+/// Steps that fail or time out report the transcript of the exchanges so
+/// far, so a failing flow shows where it went astray.
+pub struct ScriptedClient {
+    address: String,
+    http: reqwest::Client,
+    transcript: Transcript,
+}
+
+impl ScriptedClient {
+    /// Client of the server listening on `address`, e.g. `127.0.0.1:3000`
+    pub fn new(address: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            http: reqwest::Client::new(),
+            transcript: Arc::default(),
+        }
+    }
+
+    /// Exchanges so far, one line each
+    pub fn transcript(&self) -> Vec<String> {
+        self.transcript.lock().unwrap().clone()
+    }
+
+    pub async fn get(&self, path: &str) -> Result<HttpReply> {
+        self.send(self.http.get(self.url(path)), "GET", path, None)
+            .await
+    }
+
+    pub async fn post_json(&self, path: &str, body: &Value) -> Result<HttpReply> {
+        let request = self
+            .http
+            .post(self.url(path))
+            .header("content-type", "application/json")
+            .body(body.to_string());
+        self.send(request, "POST", path, Some(body)).await
+    }
+
+    /// `GET` expecting `status`
+    pub async fn expect_get(&self, path: &str, status: u16) -> Result<HttpReply> {
+        let reply = self.get(path).await?;
+        if reply.status != status {
+            return Err(self.failure(format!(
+                "Expected {} from GET {}, got {}",
+                status, path, reply.status
+            )));
+        }
+        Ok(reply)
+    }
+
+    /// Open a WebSocket to `path`
+    pub async fn connect(&self, path: &str) -> Result<ScriptedSocket> {
+        let url = format!("ws://{}{}", self.address, path);
+        let (stream, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .map_err(|e| self.failure(format!("Failed to connect to {}: {}", path, e)))?;
+        record(&self.transcript, format!("WS {} connected", path));
+        Ok(ScriptedSocket {
+            path: path.to_string(),
+            stream,
+            transcript: self.transcript.clone(),
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.address, path)
+    }
+
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        method: &str,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<HttpReply> {
+        match body {
+            Some(body) => record(&self.transcript, format!("{} {} {}", method, path, body)),
+            None => record(&self.transcript, format!("{} {}", method, path)),
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| self.failure(format!("{} {} failed: {}", method, path, e)))?;
+        let status = response.status().as_u16();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| self.failure(format!("Failed to read {} {}: {}", method, path, e)))?;
+        record(
+            &self.transcript,
+            format!("<- {} {}", status, abbreviate(&body)),
+        );
+        Ok(HttpReply { status, body })
+    }
+
+    fn failure(&self, message: String) -> RuneError {
+        failure(&self.transcript, message)
+    }
+}
+
+/// WebSocket opened by a [`ScriptedClient`], exchanging JSON messages
+pub struct ScriptedSocket {
+    path: String,
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    transcript: Transcript,
+}
+
+impl ScriptedSocket {
+    pub async fn send_json(&mut self, message: &Value) -> Result<()> {
+        record(&self.transcript, format!("WS {} -> {}", self.path, message));
+        self.stream
+            .send(Message::Text(message.to_string()))
+            .await
+            .map_err(|e| failure(&self.transcript, format!("Failed to send: {}", e)))
+    }
+
+    /// Next JSON message within `timeout`
+    pub async fn next_json(&mut self, timeout: Duration) -> Result<Value> {
+        let next = tokio::time::timeout(timeout, async {
+            loop {
+                match self.stream.next().await {
+                    Some(Ok(Message::Text(text))) => return Ok(text),
+                    Some(Ok(Message::Binary(data))) => {
+                        return String::from_utf8(data).map_err(|e| e.to_string())
+                    }
+                    Some(Ok(Message::Close(_))) | None => return Err("socket closed".to_string()),
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e.to_string()),
+                }
+            }
+        })
+        .await;
+        let text = match next {
+            Ok(Ok(text)) => text,
+            Ok(Err(e)) => {
+                return Err(failure(
+                    &self.transcript,
+                    format!("WS {}: {}", self.path, e),
+                ))
+            }
+            Err(_) => {
+                return Err(failure(
+                    &self.transcript,
+                    format!("WS {}: nothing received within {:?}", self.path, timeout),
+                ))
+            }
+        };
+        record(
+            &self.transcript,
+            format!("WS {} <- {}", self.path, abbreviate(&text)),
+        );
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// First message matching `predicate` within the default timeout,
+    /// skipping the others
+    pub async fn expect<F>(&mut self, predicate: F) -> Result<Value>
+    where
+        F: Fn(&Value) -> bool,
+    {
+        let deadline = tokio::time::Instant::now() + DEFAULT_TIMEOUT;
+        loop {
+            let left = deadline.saturating_duration_since(tokio::time::Instant::now());
+            let message = self.next_json(left).await?;
+            if predicate(&message) {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// First message whose `type` is `message_type`, see [`expect`](Self::expect)
+    pub async fn expect_type(&mut self, message_type: &str) -> Result<Value> {
+        self.expect(|message| message["type"] == message_type).await
+    }
+
+    pub async fn close(mut self) -> Result<()> {
+        record(&self.transcript, format!("WS {} closed", self.path));
+        self.stream
+            .close(None)
+            .await
+            .map_err(|e| failure(&self.transcript, format!("Failed to close: {}", e)))
+    }
+}
+
+fn record(transcript: &Transcript, line: String) {
+    transcript.lock().unwrap().push(line);
+}
+
+fn failure(transcript: &Transcript, message: String) -> RuneError {
+    RuneError::server(format!(
+        "{}\ntranscript:\n  {}",
+        message,
+        transcript.lock().unwrap().join("\n  ")
+    ))
+}
+
+/// Long bodies cut short for the transcript
+fn abbreviate(text: &str) -> String {
+    const LIMIT: usize = 200;
+    match text.char_indices().nth(LIMIT) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
@@ -0,0 +1,123 @@
+//! Documents of a test workspace, kept in memory
+
+use rune_core::error::{Result, RuneError};
+use rune_core::event::{ChangeType, EventBus, SystemEvent};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+/// Documents of a test, by path relative to the workspace
+///
+/// The store is the test's idea of what each document holds. Plugins read
+/// and write real files, so the store writes its documents into a temporary
+/// workspace directory, removed when the store is dropped, and
+/// [`external_write`](Self::external_write) plays another program changing a
+/// document: it writes the file and publishes the change like the file
+/// watcher would.
+pub struct DocumentStore {
+    workspace: TempDir,
+    documents: Mutex<BTreeMap<PathBuf, String>>,
+    event_bus: Arc<dyn EventBus>,
+}
+
+impl DocumentStore {
+    /// Empty store announcing external changes on `event_bus`
+    pub fn new(event_bus: Arc<dyn EventBus>) -> Result<Self> {
+        Ok(Self {
+            workspace: TempDir::new()?,
+            documents: Mutex::new(BTreeMap::new()),
+            event_bus,
+        })
+    }
+
+    /// Directory the documents are written to
+    pub fn workspace(&self) -> &Path {
+        self.workspace.path()
+    }
+
+    /// Path on disk of the document at `relative`
+    pub fn path(&self, relative: impl AsRef<Path>) -> PathBuf {
+        self.workspace.path().join(relative)
+    }
+
+    /// Add or replace a document before the plugins look at it; no event
+    /// is published
+    pub fn insert(&self, relative: impl AsRef<Path>, content: &str) -> Result<PathBuf> {
+        let path = self.write_file(relative.as_ref(), content)?;
+        self.documents
+            .lock()
+            .unwrap()
+            .insert(relative.as_ref().to_path_buf(), content.to_string());
+        Ok(path)
+    }
+
+    /// Change a document the way another program would, publishing
+    /// [`SystemEvent::FileChanged`] for it
+    pub async fn external_write(&self, relative: impl AsRef<Path>, content: &str) -> Result<()> {
+        let created = !self
+            .documents
+            .lock()
+            .unwrap()
+            .contains_key(relative.as_ref());
+        let path = self.insert(relative, content)?;
+        let change_type = if created {
+            ChangeType::Created
+        } else {
+            ChangeType::Modified
+        };
+        self.event_bus
+            .publish_system_event(SystemEvent::file_changed(path, change_type))
+            .await
+    }
+
+    /// Change a document the way another program would, without publishing
+    /// anything, for tests running the file watcher to notice it
+    pub fn write_unannounced(&self, relative: impl AsRef<Path>, content: &str) -> Result<PathBuf> {
+        self.insert(relative, content)
+    }
+
+    /// Content the store last gave the document, which differs from
+    /// [`read_disk`](Self::read_disk) once Rune saved it
+    pub fn get(&self, relative: impl AsRef<Path>) -> Option<String> {
+        self.documents
+            .lock()
+            .unwrap()
+            .get(relative.as_ref())
+            .cloned()
+    }
+
+    /// Content of the document on disk, as the plugins left it
+    pub fn read_disk(&self, relative: impl AsRef<Path>) -> Result<String> {
+        let path = self.path(relative);
+        std::fs::read_to_string(&path).map_err(|e| {
+            RuneError::file_system(format!("Failed to read {}: {}", path.display(), e))
+        })
+    }
+
+    /// Take what the plugins saved to disk as the store's content, returning
+    /// whether it changed
+    pub fn sync_from_disk(&self, relative: impl AsRef<Path>) -> Result<bool> {
+        let content = self.read_disk(relative.as_ref())?;
+        let previous = self
+            .documents
+            .lock()
+            .unwrap()
+            .insert(relative.as_ref().to_path_buf(), content.clone());
+        Ok(previous.as_deref() != Some(content.as_str()))
+    }
+
+    /// Paths of the documents, relative to the workspace
+    pub fn documents(&self) -> Vec<PathBuf> {
+        self.documents.lock().unwrap().keys().cloned().collect()
+    }
+
+    fn write_file(&self, relative: &Path, content: &str) -> Result<PathBuf> {
+        let path = self.path(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, content)?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,128 @@
+//! Event bus that records what is published
+
+use async_trait::async_trait;
+use rune_core::error::{Result, RuneError};
+use rune_core::event::{
+    Event, EventBus, InMemoryEventBus, SubscriptionId, SystemEvent, SystemEventHandler,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// [`EventBus`] delivering events to its subscribers like
+/// [`InMemoryEventBus`] and keeping every one published
+///
+/// Events are recorded before they are delivered, so a test waiting for one
+/// sees it even when a subscriber is slow to handle it.
+pub struct MockEventBus {
+    inner: InMemoryEventBus,
+    published: Mutex<Vec<SystemEvent>>,
+    sender: broadcast::Sender<SystemEvent>,
+}
+
+impl MockEventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(256);
+        Self {
+            inner: InMemoryEventBus::new(),
+            published: Mutex::new(Vec::new()),
+            sender,
+        }
+    }
+
+    /// Events published so far, oldest first
+    pub fn published(&self) -> Vec<SystemEvent> {
+        self.published.lock().unwrap().clone()
+    }
+
+    /// Published events of the type named by `event_type`, e.g. `"file_changed"`
+    pub fn published_of(&self, event_type: &str) -> Vec<SystemEvent> {
+        self.published()
+            .into_iter()
+            .filter(|event| event.event_type() == event_type)
+            .collect()
+    }
+
+    /// Forget the events published so far
+    pub fn clear(&self) {
+        self.published.lock().unwrap().clear();
+    }
+
+    /// First event matching `predicate`, published already or within
+    /// `timeout`
+    pub async fn wait_for<F>(&self, timeout: Duration, predicate: F) -> Result<SystemEvent>
+    where
+        F: Fn(&SystemEvent) -> bool,
+    {
+        // Subscribed before looking at the record, so nothing slips between
+        let mut receiver = self.sender.subscribe();
+        if let Some(event) = self.published().into_iter().find(|event| predicate(event)) {
+            return Ok(event);
+        }
+        let found = tokio::time::timeout(timeout, async {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if predicate(&event) => return Some(event),
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+        .await;
+        found.ok().flatten().ok_or_else(|| {
+            let seen: Vec<String> = self
+                .published()
+                .iter()
+                .map(|event| event.event_type().to_string())
+                .collect();
+            RuneError::event_bus(format!(
+                "No matching event within {:?}; published: {:?}",
+                timeout, seen
+            ))
+        })
+    }
+
+    /// Address of the first server started, within `timeout`
+    pub async fn server_address(&self, timeout: Duration) -> Result<String> {
+        match self
+            .wait_for(timeout, |event| {
+                matches!(event, SystemEvent::ServerStarted { .. })
+            })
+            .await?
+        {
+            SystemEvent::ServerStarted { address, .. } => Ok(address),
+            _ => unreachable!("only server started events match"),
+        }
+    }
+}
+
+impl Default for MockEventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl EventBus for MockEventBus {
+    async fn publish_system_event(&self, event: SystemEvent) -> Result<()> {
+        self.published.lock().unwrap().push(event.clone());
+        // Nobody waiting is not an error
+        let _ = self.sender.send(event.clone());
+        self.inner.publish_system_event(event).await
+    }
+
+    async fn subscribe_system_events(
+        &self,
+        handler: Arc<dyn SystemEventHandler>,
+    ) -> Result<SubscriptionId> {
+        self.inner.subscribe_system_events(handler).await
+    }
+
+    async fn unsubscribe(&self, id: SubscriptionId) -> Result<()> {
+        self.inner.unsubscribe(id).await
+    }
+
+    async fn subscription_count(&self) -> usize {
+        self.inner.subscription_count().await
+    }
+}
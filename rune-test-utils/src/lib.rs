@@ -0,0 +1,28 @@
+//! Helpers for end-to-end tests of Rune
+//!
+//! Integration tests drive flows that cross plugins, like editing a document
+//! over the editor socket while another program changes it on disk. This
+//! crate gives them the pieces:
+//!
+//! - [`MockEventBus`] delivers events like the real bus and records them, so
+//!   tests can wait for the one they expect
+//! - [`DocumentStore`] keeps documents in memory and writes them into a
+//!   temporary workspace, telling the bus about changes made "outside" Rune
+//! - [`EphemeralServer`] runs the server plugin on a free port
+//! - [`ScriptedClient`] talks HTTP and WebSocket to it, keeping a transcript
+//!   that failures report
+
+pub mod client;
+pub mod document_store;
+pub mod event_bus;
+pub mod server;
+
+pub use client::{HttpReply, ScriptedClient, ScriptedSocket};
+pub use document_store::DocumentStore;
+pub use event_bus::MockEventBus;
+pub use server::EphemeralServer;
+
+use std::time::Duration;
+
+/// How long helpers wait for an event or message unless told otherwise
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
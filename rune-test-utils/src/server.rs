@@ -0,0 +1,80 @@
+//! Server plugin running on a free port for the length of a test
+
+use crate::{MockEventBus, ScriptedClient, DEFAULT_TIMEOUT};
+use rune_core::error::{Result, RuneError};
+use rune_core::plugin::{Plugin, PluginContext};
+use rune_core::{Config, StateManager};
+use rune_server::{HandlerRegistry, ServerPlugin};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// [`ServerPlugin`] initialized on a port the system picks, serving one
+/// document or none
+///
+/// The plugin starts the way `rune` starts it, so every core handler is
+/// registered; the address is the one it announces with
+/// [`SystemEvent::ServerStarted`](rune_core::event::SystemEvent::ServerStarted).
+pub struct EphemeralServer {
+    plugin: ServerPlugin,
+    context: PluginContext,
+    address: String,
+}
+
+impl EphemeralServer {
+    /// Serve `document` at `/` with events going through `event_bus`
+    pub async fn spawn(event_bus: Arc<MockEventBus>, document: Option<PathBuf>) -> Result<Self> {
+        Self::spawn_with_config(event_bus, document, Config::new()).await
+    }
+
+    /// Like [`spawn`](Self::spawn), with `config` apart from the listening
+    /// address
+    pub async fn spawn_with_config(
+        event_bus: Arc<MockEventBus>,
+        document: Option<PathBuf>,
+        mut config: Config,
+    ) -> Result<Self> {
+        config.server.hostname = "127.0.0.1".to_string();
+        config.server.port = 0;
+
+        let state_manager = Arc::new(StateManager::new());
+        state_manager.set_current_file(document).await;
+        let context = PluginContext::new(event_bus.clone(), Arc::new(config), state_manager)
+            .for_plugin("server".to_string());
+
+        let mut plugin = ServerPlugin::new();
+        plugin.initialize(&context).await?;
+        let address = event_bus.server_address(DEFAULT_TIMEOUT).await?;
+        Ok(Self {
+            plugin,
+            context,
+            address,
+        })
+    }
+
+    /// Address the server listens on, e.g. `127.0.0.1:49152`
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Context the plugin was initialized with
+    pub fn context(&self) -> &PluginContext {
+        &self.context
+    }
+
+    /// Handlers the server routes requests to
+    pub fn registry(&self) -> Result<Arc<HandlerRegistry>> {
+        self.plugin
+            .handler_registry()
+            .ok_or_else(|| RuneError::server("Server has no handler registry"))
+    }
+
+    /// New client of the server
+    pub fn client(&self) -> ScriptedClient {
+        ScriptedClient::new(&self.address)
+    }
+
+    /// Stop the server, letting requests in flight finish
+    pub async fn shutdown(mut self) -> Result<()> {
+        self.plugin.shutdown().await
+    }
+}
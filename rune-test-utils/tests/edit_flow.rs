@@ -0,0 +1,141 @@
+use rune_core::event::SystemEvent;
+use rune_core::plugin::{Plugin, PluginContext};
+use rune_core::{Config, Notification, StateManager};
+use rune_editor::{ConflictResolutionStrategy, EditorPlugin, RuneEditorPlugin, TextEdit};
+use rune_file_watcher::FileWatcherPlugin;
+use rune_test_utils::{DocumentStore, EphemeralServer, MockEventBus};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Auto-save waits two seconds after the last edit
+const AUTO_SAVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[tokio::test]
+async fn test_edit_autosave_external_change_conflict() {
+    let bus = Arc::new(MockEventBus::new());
+    let store = DocumentStore::new(bus.clone()).unwrap();
+    let document = store
+        .insert("notes.md", "# Notes\n\nFirst draft.\n")
+        .unwrap();
+    let server = EphemeralServer::spawn(bus.clone(), Some(document.clone()))
+        .await
+        .unwrap();
+    let mut preview = server.client().connect("/ws").await.unwrap();
+
+    // The editor and the file watcher run as `rune` runs them
+    let state_manager = Arc::new(StateManager::new());
+    state_manager
+        .add_served_root(store.workspace().to_path_buf())
+        .await;
+    let context = PluginContext::new(bus.clone(), Arc::new(Config::new()), state_manager);
+    let mut watcher = FileWatcherPlugin::new();
+    watcher
+        .initialize(&context.for_plugin("file-watcher".to_string()))
+        .await
+        .unwrap();
+    let mut editor = RuneEditorPlugin::new();
+    editor
+        .initialize(&context.for_plugin("editor".to_string()))
+        .await
+        .unwrap();
+
+    let session = editor.create_session(document.clone()).await.unwrap();
+    editor
+        .session_manager()
+        .set_conflict_strategy(session, ConflictResolutionStrategy::AutoMerge)
+        .await
+        .unwrap();
+
+    // An edit is auto-saved after a pause, and the watcher sees the save
+    editor
+        .apply_edits(session, vec![TextEdit::replace(9, 14, "Second")])
+        .await
+        .unwrap();
+    bus.wait_for(
+        AUTO_SAVE_TIMEOUT,
+        |event| matches!(event, SystemEvent::FileChanged { path, .. } if *path == document),
+    )
+    .await
+    .unwrap();
+    assert!(!editor.has_unsaved_changes(session).await.unwrap());
+    assert_eq!(
+        store.read_disk("notes.md").unwrap(),
+        "# Notes\n\nSecond draft.\n"
+    );
+
+    // Unsaved edits meet a change made in another program
+    editor
+        .apply_edits(
+            session,
+            vec![TextEdit::replace(9, 22, "Third draft, not saved.")],
+        )
+        .await
+        .unwrap();
+    let external = "# Notes\n\nRewritten elsewhere.\n";
+    store.write_unannounced("notes.md", external).unwrap();
+
+    let conflict = bus
+        .wait_for(AUTO_SAVE_TIMEOUT, |event| {
+            matches!(event, SystemEvent::Notification { .. })
+        })
+        .await
+        .unwrap();
+    let SystemEvent::Notification { notification, .. } = conflict else {
+        unreachable!("only notifications match");
+    };
+    assert_eq!(
+        notification,
+        Notification {
+            id: notification.id,
+            ..Notification::warn(
+                "editor",
+                "External changes to notes.md conflict with your edits in 1 place(s)",
+            )
+        }
+    );
+
+    // The preview shows the change right away
+    preview
+        .expect(|message| message.to_string().contains("Rewritten elsewhere"))
+        .await
+        .unwrap();
+
+    // Neither the edits nor an auto-save overwrite the external change
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert_eq!(store.read_disk("notes.md").unwrap(), external);
+    assert!(editor.has_unsaved_changes(session).await.unwrap());
+    assert_eq!(
+        editor.get_content(session).await.unwrap(),
+        "# Notes\n\nThird draft, not saved.\n"
+    );
+    assert_eq!(bus.published_of("notification").len(), 1);
+
+    editor.shutdown().await.unwrap();
+    watcher.shutdown().await.unwrap();
+    preview.close().await.unwrap();
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_waiting_for_an_event_that_never_comes_times_out() {
+    let bus = Arc::new(MockEventBus::new());
+    let store = DocumentStore::new(bus.clone()).unwrap();
+    store.external_write("new.md", "# New\n").await.unwrap();
+
+    let created = bus.published_of("file_changed");
+    assert!(matches!(
+        created.as_slice(),
+        [SystemEvent::FileChanged {
+            change_type: rune_core::event::ChangeType::Created,
+            ..
+        }]
+    ));
+    let error = bus
+        .wait_for(std::time::Duration::from_millis(50), |event| {
+            matches!(event, SystemEvent::ServerStarted { .. })
+        })
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("file_changed"));
+    assert_eq!(store.documents(), vec![std::path::PathBuf::from("new.md")]);
+}